@@ -0,0 +1,1304 @@
+// =============================================================================
+// Buy-Side Application Callbacks
+// =============================================================================
+// Glue between the FIX engine and the business components:
+//
+//   market data session                      order session
+//   -------------------                      -------------
+//   on_logon  -> 35=c per symbol, 35=x,      on_logon -> enable trading
+//                subscribe symbols
+//   35=d/y    -> instrument store
+//   35=W/X    -> strategy -> risk -> OMS ->  35=D  (send_to_target)
+//                                            35=8  -> OMS -> positions
+//   news feed -> strategy (on_news)
+//
+// Synthetic instruments (--synthetic) are priced from their constituents'
+// quotes and handed to the strategy like any other symbol; orders on them are
+// split into one child order per leg (trading::synthetic).
+//
+// Stops, take-profits and OCO pairs of them wait in a StopBook, watching the
+// quotes or the fills; the plain market or limit order they trigger goes
+// through risk and the OMS like the others (trading::oms::stops).
+//
+// Every fill also goes to the trade blotter, which the REST gateway pages
+// through and exports, and the WebSocket bridge streams (trading::oms::blotter).
+//
+// Limit prices are rounded to the tick the venue defined for the symbol, and
+// the risk checks count its lot size and contract multiplier
+// (trading::instruments).
+//
+// With --fx, orders carry the Currency (15) of their symbol, their notional
+// is checked in the base currency of the rates, and the execution reports
+// in another currency than their order's are flagged
+// (trading::risk::currency).
+//
+// Filled orders are split between accounts with an AllocationInstruction
+// (35=J) on the order session; the 35=P / 35=AS answers update the status of
+// the allocation and of each fill in it (trading::oms::allocations).
+//
+// The QuickFIX callbacks (FixCallbacks) only record monitoring data and push
+// decoded events into a channel, as does the optional news feed thread;
+// everything above runs in the async task BuySideApp::run(), off the engine
+// threads. With --fast-lane, the MsgTypes listed skip ahead of the others,
+// which are batched (trading::session::lanes).
+//
+// Optional webhooks are fired from there too: order_filled, session_down and
+// limit_breach (see trading::gateway::webhook). With --alert risk_breach, a
+// refused order also raises an alert (trading::alerts).
+//
+// In dry run (--dry-run, or per session), orders and cancels go through all
+// of the above but are not sent: the acknowledgement is made up locally and
+// flagged DRY RUN (trading::session::dry_run).
+//
+// In paper trading (--paper), nothing the order session would send is sent:
+// a PaperEngine keeps the orders and fills them against the quotes of the
+// market data session, and its ExecutionReports, flagged PAPER, are applied
+// and published like the venue's (trading::sim::paper).
+//
+// Whatever is sent goes through a RateLimiter first (--rate-limit): over the
+// venue's message rate, orders wait for their turn or are refused with
+// OrderError::RateLimited (trading::session::rate_limit).
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::Instant,
+};
+
+use quickfix::*;
+
+use trading::{
+    alerts::AlertMonitor,
+    bus::EventBus,
+    conformance::profile::{ConformanceProfile, ProfileViolation},
+    gateway::{
+        kafka::KafkaPublisher,
+        metrics::Metrics,
+        webhook::{WebhookEvent, WebhookNotifier},
+        websocket::{pairs_json, Bridge, Event},
+    },
+    news::NewsEvent,
+    oms::{
+        allocations::{AllocShare, Allocation, AllocationBook, AllocationError},
+        blotter::TradeBlotter,
+        ids::ClOrdIdGenerator,
+        positions::PositionBook,
+        stops::{StopBook, Triggered},
+        OrdType, Order, OrderManager, OrderStatus, Side,
+    },
+    instruments::{Instrument, InstrumentStore, InstrumentUpdate},
+    risk::{
+        currency::{CurrencyIssue, CurrencyTracker},
+        margin::MarginReport,
+        RiskChecker, RiskViolation,
+    },
+    session::{
+        dry_run::DryRun,
+        events::{group_field, FixEvent, FixMessage},
+        handover::Handover,
+        lanes::{LaneReceiver, LaneSender},
+        rate_limit::{RateLimited, RateLimiter, RateLimits, SendError},
+        Direction,
+    },
+    sim::paper::PaperEngine,
+    store::{StateStore, StoreError},
+    synthetic::{SyntheticBook, SyntheticError, SyntheticInstrument},
+};
+
+use crate::{
+    config::StackSessions,
+    strategy::{OrderIntent, Quote, Strategy},
+};
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum OrderError {
+    /// Order session is not logged on
+    TradingDisabled,
+
+    /// Handing over to a new version: no new orders
+    Draining,
+
+    /// Pre-trade risk refused the order
+    Risk(RiskViolation),
+
+    /// No working order with this ClOrdID
+    UnknownOrder,
+
+    /// QuickFIX refused to send the message
+    Send(QuickFixError),
+
+    /// Over the outbound rate limits: not sent
+    RateLimited(RateLimited),
+
+    /// An order on a synthetic instrument could not be split into legs
+    Synthetic(SyntheticError),
+
+    /// The venue profile says the venue would reject the message
+    Conformance(Vec<ProfileViolation>),
+
+    /// The fills cannot be split between the accounts as asked
+    Allocation(AllocationError),
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::TradingDisabled => write!(f, "trading disabled: session not logged on"),
+            OrderError::Draining => write!(f, "draining for an upgrade: no new orders"),
+            OrderError::Risk(err) => write!(f, "risk check failed: {err}"),
+            OrderError::UnknownOrder => write!(f, "unknown or inactive order"),
+            OrderError::Send(err) => write!(f, "send failed: {err:?}"),
+            OrderError::RateLimited(err) => write!(f, "not sent: {err}"),
+            OrderError::Synthetic(err) => write!(f, "synthetic order: {err}"),
+            OrderError::Conformance(violations) => {
+                let violations: Vec<_> = violations.iter().map(|x| x.to_string()).collect();
+                write!(f, "refused by the venue profile: {}", violations.join("; "))
+            }
+            OrderError::Allocation(err) => write!(f, "allocation refused: {err}"),
+        }
+    }
+}
+
+impl Error for OrderError {}
+
+impl From<SyntheticError> for OrderError {
+    fn from(err: SyntheticError) -> Self {
+        OrderError::Synthetic(err)
+    }
+}
+
+impl From<AllocationError> for OrderError {
+    fn from(err: AllocationError) -> Self {
+        OrderError::Allocation(err)
+    }
+}
+
+impl From<SendError> for OrderError {
+    fn from(err: SendError) -> Self {
+        match err {
+            SendError::Limited(err) => OrderError::RateLimited(err),
+            SendError::Send(err) => OrderError::Send(err),
+        }
+    }
+}
+
+// =============================================================================
+// BuySideApp
+// =============================================================================
+
+pub struct BuySideApp {
+    sessions: StackSessions,
+    symbols: Vec<String>,
+    pub oms: OrderManager,
+    pub positions: PositionBook,
+
+    /// Stops and take-profits held until their trigger
+    pub stops: StopBook,
+
+    /// Every fill received, for the blotter views
+    pub blotter: TradeBlotter,
+
+    /// Allocation instructions sent, with the broker's answers
+    pub allocations: AllocationBook,
+
+    /// Tick size, lot size and multiplier of each symbol, from the venue
+    pub instruments: InstrumentStore,
+    risk: RiskChecker,
+
+    /// Currency (15) each order was sent in, to check its reports against
+    currencies: Mutex<CurrencyTracker>,
+    strategy: Mutex<Box<dyn Strategy>>,
+
+    /// Baskets priced from their constituents, traded as child orders
+    synthetics: Mutex<SyntheticBook>,
+    pub metrics: Arc<Metrics>,
+    pub bridge: Arc<Bridge>,
+
+    /// Every event decoded for the business (fills, rejects, market data,
+    /// sessions up and down), for modules that subscribe to it
+    pub bus: Arc<EventBus>,
+
+    /// Optional downstream feed of execution reports and order states
+    kafka: Option<KafkaPublisher>,
+
+    /// Optional HTTP callbacks on fills, session losses and risk breaches
+    webhooks: Option<Arc<WebhookNotifier>>,
+
+    /// Paging conditions, with --alert; the bus feeds the others
+    alerts: Option<Arc<AlertMonitor>>,
+
+    /// What the venue accepts, checked before an order or cancel is sent
+    profile: Option<ConformanceProfile>,
+
+    /// Orders are only sent while the order session is logged on
+    trading_enabled: AtomicBool,
+
+    /// Set before a version upgrade: new orders are refused, the reports of
+    /// those already sent are still applied
+    draining: AtomicBool,
+
+    /// Sessions whose orders are acknowledged locally instead of sent
+    pub dry_run: DryRun,
+
+    /// With --paper: orders filled against the market data instead of sent
+    paper: Option<PaperEngine>,
+
+    /// Outbound message rate, per session and across them
+    limiter: RateLimiter,
+}
+
+impl BuySideApp {
+    pub fn new(
+        sessions: StackSessions,
+        symbols: Vec<String>,
+        risk: RiskChecker,
+        strategy: Box<dyn Strategy>,
+    ) -> Self {
+        Self {
+            sessions,
+            symbols,
+            oms: OrderManager::new("BUY"),
+            positions: PositionBook::new(),
+            stops: StopBook::new("STP"),
+            blotter: TradeBlotter::new(),
+            allocations: AllocationBook::new("ALC"),
+            instruments: InstrumentStore::new("SEC"),
+            risk,
+            currencies: Mutex::new(CurrencyTracker::new()),
+            strategy: Mutex::new(strategy),
+            synthetics: Mutex::new(SyntheticBook::default()),
+            metrics: Arc::new(Metrics::new()),
+            bridge: Arc::new(Bridge::new()),
+            bus: Arc::new(EventBus::new()),
+            kafka: None,
+            webhooks: None,
+            alerts: None,
+            profile: None,
+            trading_enabled: AtomicBool::new(false),
+            draining: AtomicBool::new(false),
+            dry_run: DryRun::new(),
+            paper: None,
+            limiter: RateLimiter::unlimited(),
+        }
+    }
+
+    /// Publish every ExecutionReport and order state transition to Kafka
+    pub fn with_kafka(mut self, publisher: KafkaPublisher) -> Self {
+        self.kafka = Some(publisher);
+        self
+    }
+
+    /// Price and trade these synthetic instruments; their constituents are
+    /// subscribed to along with the traded symbols
+    pub fn with_synthetics(mut self, instruments: Vec<SyntheticInstrument>) -> Self {
+        self.synthetics = Mutex::new(SyntheticBook::new(instruments));
+        self
+    }
+
+    /// Take the ClOrdIDs of orders and cancels from this generator, before
+    /// `with_store`
+    pub fn with_id_generator(mut self, ids: Box<dyn ClOrdIdGenerator>) -> Self {
+        self.oms = self.oms.with_id_generator(ids);
+        self
+    }
+
+    /// Restore orders, the ClOrdID sequence, positions and instrument
+    /// definitions from a state store, and keep them there as they change
+    pub fn with_store(mut self, store: Arc<dyn StateStore>) -> Result<Self, StoreError> {
+        self.oms = self.oms.with_store(Arc::clone(&store))?;
+        self.positions = self.positions.with_store(Arc::clone(&store))?;
+        self.instruments = self.instruments.with_store(store)?;
+        Ok(self)
+    }
+
+    /// Fire the configured webhooks
+    pub fn with_webhooks(mut self, notifier: Arc<WebhookNotifier>) -> Self {
+        self.webhooks = Some(notifier);
+        self
+    }
+
+    /// Raise an alert on the orders the risk checks refuse
+    pub fn with_alerts(mut self, alerts: Arc<AlertMonitor>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    pub fn alerts(&self) -> Option<Arc<AlertMonitor>> {
+        self.alerts.clone()
+    }
+
+    /// Check orders and cancels against a venue profile before sending
+    pub fn with_profile(mut self, profile: ConformanceProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Paper trade: keep what the order session would send, and fill the
+    /// orders against the market data
+    pub fn with_paper(mut self) -> Self {
+        self.paper = Some(PaperEngine::new());
+        self
+    }
+
+    pub fn is_paper(&self) -> bool {
+        self.paper.is_some()
+    }
+
+    /// Keep what is sent under these rates, queueing or refusing the rest
+    pub fn with_rate_limits(mut self, limits: RateLimits) -> Self {
+        self.limiter = RateLimiter::new(limits);
+        self
+    }
+
+    fn notify(&self, event: WebhookEvent, fields: &[(&str, String)]) {
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.notify(event, fields);
+        }
+    }
+
+    // =========================================================================
+    // Event Processing
+    // =========================================================================
+
+    /// Business logic task: consume the events pushed by FixCallbacks
+    ///
+    /// Returns once the callbacks are dropped and the backlog is drained, so
+    /// the last execution reports are applied before the program exits.
+    pub async fn run(self: Arc<Self>, mut events: LaneReceiver) {
+        while let Some(event) = events.recv().await {
+            self.bus.publish_fix(&event);
+            match event {
+                FixEvent::Logon { session } => {
+                    if self.sessions.is_market_data(&session) {
+                        self.request_instruments();
+                        self.subscribe_market_data();
+                    } else if self.sessions.is_orders(&session) {
+                        self.trading_enabled.store(true, Ordering::Relaxed);
+                    }
+                }
+                FixEvent::Logout { session } => {
+                    // Never trade blind: stop generating orders when either leg is down
+                    self.trading_enabled.store(false, Ordering::Relaxed);
+                    self.notify(WebhookEvent::SessionDown, &[("session", session)]);
+                }
+                FixEvent::Message(msg) => match msg.msg_type() {
+                    "W" | "X" => {
+                        if let Some(paper) = &self.paper {
+                            self.apply_local_reports(paper.on_market_data(&msg));
+                        }
+                        self.on_market_data(&msg);
+                    }
+                    "8" => self.on_execution_report(&msg),
+                    "P" | "AS" => self.on_allocation_ack(&msg),
+                    "d" | "y" => self.on_instruments(&msg),
+                    _ => {}
+                },
+                FixEvent::Created { .. } => {}
+                FixEvent::News(news) => self.on_news(&news),
+            }
+        }
+    }
+
+    // =========================================================================
+    // Market Data
+    // =========================================================================
+
+    /// The traded symbols, then the constituents of the synthetics
+    fn market_data_symbols(&self) -> Vec<String> {
+        let mut symbols = self.symbols.clone();
+        for symbol in self.synthetics().constituents() {
+            if !symbols.contains(&symbol) {
+                symbols.push(symbol);
+            }
+        }
+        symbols
+    }
+
+    /// Send one MarketDataRequest (35=V) per symbol, top of book, streaming
+    fn subscribe_market_data(&self) {
+        for (index, symbol) in self.market_data_symbols().iter().enumerate() {
+            let result = build_market_data_request(&format!("MD-{index}"), symbol)
+                .map_err(SendError::from)
+                .and_then(|msg| {
+                    self.sessions
+                        .market_data()
+                        .map_err(SendError::from)
+                        .and_then(|session| self.limiter.send(msg, &session))
+                });
+            println!(">> subscribe {symbol}: {result:?}");
+        }
+    }
+
+    /// Ask for the definition of every symbol subscribed to (35=c), and for
+    /// the list of those the venue trades (35=x)
+    fn request_instruments(&self) {
+        let symbols = self.market_data_symbols();
+        let requests = symbols
+            .iter()
+            .map(|symbol| self.instruments.definition_request(symbol).to_message())
+            .chain([self.instruments.list_request(None).to_message()]);
+        for request in requests {
+            let result = request.map_err(SendError::from).and_then(|msg| {
+                self.sessions
+                    .market_data()
+                    .map_err(SendError::from)
+                    .and_then(|session| self.limiter.send(msg, &session))
+            });
+            if let Err(err) = result {
+                println!(">> instrument request not sent: {err}");
+            }
+        }
+    }
+
+    /// Keep the definitions of a 35=d or 35=y
+    fn on_instruments(&self, msg: &FixMessage) {
+        match self.instruments.on_message(msg) {
+            Some(InstrumentUpdate::Defined { instruments, .. }) => {
+                for instrument in instruments {
+                    let tick = instrument.tick_size.map_or("-".to_string(), |x| x.to_string());
+                    let lot = instrument.lot_size.map_or("-".to_string(), |x| x.to_string());
+                    println!(
+                        ">> instrument {} tick={tick} lot={lot} multiplier={} {}",
+                        instrument.symbol, instrument.multiplier, instrument.currency
+                    );
+                }
+            }
+            Some(InstrumentUpdate::Rejected { request_id, reason }) => {
+                println!(">> instrument request {request_id} rejected: {reason}");
+            }
+            None => {}
+        }
+    }
+
+    /// Extract symbol and top of book from a snapshot (W) or incremental (X)
+    fn on_market_data(&self, msg: &FixMessage) {
+        let Some(symbol) = msg.get(55) else {
+            return;
+        };
+
+        // MDEntries (268), one instance per MDEntryType (269)
+        let (mut bid, mut ask) = (None, None);
+        for group in msg.groups(269) {
+            let price = group_field(group, 270).and_then(|x| x.parse::<f64>().ok());
+            match group_field(group, 269) {
+                Some("0") => bid = price,
+                Some("1") => ask = price,
+                _ => {}
+            }
+        }
+
+        if let (Some(bid), Some(ask)) = (bid, ask) {
+            self.on_quote(symbol, Quote { bid, ask });
+        }
+    }
+
+    /// Reprice the synthetics, fire the stops the quote triggers, then run
+    /// the strategy on the symbol (if traded, not only a constituent) and on
+    /// every repriced synthetic
+    fn on_quote(&self, symbol: &str, quote: Quote) {
+        let repriced = self.synthetics().on_quote(symbol, quote.bid, quote.ask);
+        if !self.is_trading() {
+            return;
+        }
+        self.fire_stops(self.stops.on_quote(symbol, quote.bid, quote.ask));
+
+        let synthetic_quotes = repriced
+            .into_iter()
+            .map(|(synthetic, bid, ask)| (synthetic, Quote { bid, ask }));
+        let traded = self.symbols.iter().any(|x| x == symbol);
+        let quotes = traded
+            .then(|| (symbol.to_string(), quote))
+            .into_iter()
+            .chain(synthetic_quotes);
+        for (symbol, quote) in quotes {
+            let position = self.position(&symbol);
+            let intent = self
+                .strategy
+                .lock()
+                .expect("strategy lock poisoned")
+                .on_quote(&symbol, quote, position);
+            if let Some(intent) = intent {
+                self.submit_intent(&intent);
+            }
+        }
+    }
+
+    fn synthetics(&self) -> MutexGuard<'_, SyntheticBook> {
+        self.synthetics.lock().expect("synthetic book lock poisoned")
+    }
+
+    /// Signed position; for a synthetic, the complete baskets held
+    fn position(&self, symbol: &str) -> f64 {
+        match self.synthetics().get(symbol) {
+            Some(instrument) => instrument.position(|leg| self.positions.quantity(leg)),
+            None => self.positions.quantity(symbol),
+        }
+    }
+
+    // =========================================================================
+    // News
+    // =========================================================================
+
+    /// Show a headline, stream it, and let the strategy react for each traded
+    /// symbol it is about
+    fn on_news(&self, news: &NewsEvent) {
+        let sentiment = news.sentiment.map_or("-".to_string(), |x| format!("{x:+.2}"));
+        println!(
+            ">> news [{}] {} {:?} sentiment={sentiment}",
+            news.source, news.headline, news.symbols
+        );
+        if self.bridge.has_clients() {
+            self.bridge.publish(Event {
+                session: None,
+                topic: "news".to_string(),
+                json: format!("{{\"type\":\"news\",\"news\":{}}}", news.to_json()),
+            });
+        }
+
+        if !self.is_trading() {
+            return;
+        }
+        for symbol in self.symbols.iter().filter(|x| news.is_about(x)) {
+            let position = self.positions.quantity(symbol);
+            let intent = self
+                .strategy
+                .lock()
+                .expect("strategy lock poisoned")
+                .on_news(symbol, news, position);
+            if let Some(intent) = intent {
+                self.submit_intent(&intent);
+            }
+        }
+    }
+
+    // =========================================================================
+    // Orders
+    // =========================================================================
+
+    /// Risk-check a new limit order and send it on the order session
+    ///
+    /// Single entry point for every order source (strategy, REST gateway)
+    pub fn submit_order(
+        &self,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+        price: f64,
+    ) -> Result<Order, OrderError> {
+        self.submit(symbol, side, quantity, price, OrdType::Limit)
+    }
+
+    /// Risk-check a new market order, at the price it is expected to trade
+    /// at, and send it on the order session
+    pub fn submit_market_order(
+        &self,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+        price: f64,
+    ) -> Result<Order, OrderError> {
+        self.submit(symbol, side, quantity, price, OrdType::Market)
+    }
+
+    fn submit(
+        &self,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+        price: f64,
+        ord_type: OrdType,
+    ) -> Result<Order, OrderError> {
+        if !self.trading_enabled.load(Ordering::Relaxed) {
+            return Err(OrderError::TradingDisabled);
+        }
+        if self.draining.load(Ordering::Relaxed) {
+            return Err(OrderError::Draining);
+        }
+
+        // On the venue's tick grid, away from the market
+        let instrument = self.instruments.get(symbol);
+        let price = match (&instrument, ord_type) {
+            (Some(instrument), OrdType::Limit) => instrument.round_price(side, price),
+            _ => price,
+        };
+        let currency = self.order_currency(symbol, instrument.as_ref());
+
+        let position = self.positions.quantity(symbol);
+        let working_qty = self.oms.working_qty(symbol, side);
+        let checked = self
+            .risk
+            .check(side, quantity, price, position, working_qty)
+            .and_then(|()| match &instrument {
+                Some(instrument) => self.risk.check_instrument(instrument, quantity, price),
+                None => Ok(()),
+            })
+            .and_then(|()| {
+                let currency = currency.as_deref();
+                self.risk
+                    .check_currency(symbol, instrument.as_ref(), currency, quantity, price)
+            })
+            .and_then(|()| self.check_margin(symbol, side, quantity, price));
+        if let Err(violation) = checked {
+            self.notify(
+                WebhookEvent::LimitBreach,
+                &[
+                    ("symbol", symbol.to_string()),
+                    ("side", side.as_str().to_string()),
+                    ("quantity", quantity.to_string()),
+                    ("price", price.to_string()),
+                    ("position", position.to_string()),
+                    ("reason", violation.to_string()),
+                ],
+            );
+            let reason = violation.to_string();
+            let alert = self.alerts.as_ref().and_then(|x| x.on_risk_breach(symbol, &reason));
+            if let Some(alert) = alert {
+                println!(">> {alert}");
+            }
+            return Err(OrderError::Risk(violation));
+        }
+
+        let order = match ord_type {
+            OrdType::Limit => self.oms.create_order(symbol, side, quantity, price),
+            OrdType::Market => self.oms.create_market_order(symbol, side, quantity, price),
+        };
+        let new_order_single = || -> Result<Message, QuickFixError> {
+            let mut msg = order.to_new_order_single()?;
+            if let Some(currency) = &currency {
+                msg.set_field(15, currency.as_str())?; // Currency
+            }
+            Ok(msg)
+        };
+        self.currencies
+            .lock()
+            .expect("currency tracker lock poisoned")
+            .on_order(&order.cl_ord_id, currency.as_deref());
+        if let Err(err) = self.check_profile(new_order_single) {
+            println!(">> order {order}: {err}");
+            self.on_order_changed(&order, None);
+            if let Some(rejected) = self.oms.reject_locally(&order.cl_ord_id) {
+                self.on_order_changed(&rejected, Some(order.status));
+            }
+            return Err(err);
+        }
+        let result = self.sessions.orders().map_err(SendError::from).and_then(|session| {
+            let started = Instant::now();
+            let result = new_order_single()
+                .map_err(SendError::from)
+                .and_then(|msg| self.send_order_message(msg, &session));
+            self.metrics
+                .observe_send_latency(&session, started.elapsed());
+            result
+        });
+        println!(">> order {order}: {result:?}");
+
+        self.on_order_changed(&order, None);
+        match result {
+            Ok(reports) => {
+                self.apply_local_reports(reports);
+                Ok(order)
+            }
+            Err(err) => {
+                if let Some(rejected) = self.oms.reject_locally(&order.cl_ord_id) {
+                    self.on_order_changed(&rejected, Some(order.status));
+                }
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Currency (15) of an order: the one the --fx file lists for the
+    /// symbol, else the venue's; none without --fx
+    fn order_currency(&self, symbol: &str, instrument: Option<&Instrument>) -> Option<String> {
+        let fx = self.risk.fx()?;
+        let defined = instrument.map(|x| x.currency.as_str()).filter(|x| !x.is_empty());
+        fx.symbol_currency(symbol).or(defined).map(str::to_string)
+    }
+
+    /// Realized P&L of every position in the base currency of --fx, each
+    /// symbol's counted in the currency its orders are sent in
+    ///
+    /// # Returns
+    /// The base currency and the total; None without --fx
+    pub fn normalized_pnl(&self) -> Option<(String, Result<f64, CurrencyIssue>)> {
+        let fx = self.risk.fx()?;
+        let pnl: Vec<_> = self
+            .positions
+            .snapshot()
+            .into_iter()
+            .map(|(symbol, position)| {
+                let instrument = self.instruments.get(&symbol);
+                let currency = self.order_currency(&symbol, instrument.as_ref());
+                (position.realized_pnl, currency.unwrap_or_else(|| fx.base().to_string()))
+            })
+            .collect();
+        let total = fx.sum_to_base(pnl.iter().map(|(pnl, currency)| (*pnl, currency.as_str())));
+        Some((fx.base().to_string(), total))
+    }
+
+    /// Portfolio margin of the positions held, valued at their average
+    /// cost, and the equity of --equity
+    ///
+    /// # Returns
+    /// None without --equity
+    pub fn margin_report(&self) -> Option<(MarginReport, f64)> {
+        let (holdings, avg_costs) = self.holdings();
+        self.risk
+            .margin_report(&holdings, &|x| avg_costs.get(x).copied())
+    }
+
+    /// Portfolio margin once the order fills, other positions valued at
+    /// their average cost (a no-op without --equity)
+    fn check_margin(
+        &self,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+        price: f64,
+    ) -> Result<(), RiskViolation> {
+        let (holdings, avg_costs) = self.holdings();
+        self.risk.check_margin(
+            &holdings,
+            &|x| avg_costs.get(x).copied(),
+            symbol,
+            side,
+            quantity,
+            price,
+        )
+    }
+
+    /// Signed quantity by symbol, and the average cost of those a fill
+    /// has priced
+    fn holdings(&self) -> (Vec<(String, f64)>, HashMap<String, f64>) {
+        let snapshot = self.positions.snapshot();
+        let avg_costs = snapshot
+            .iter()
+            .filter(|(_, position)| position.avg_cost > 0.0)
+            .map(|(symbol, position)| (symbol.clone(), position.avg_cost))
+            .collect();
+        let holdings = snapshot
+            .into_iter()
+            .map(|(symbol, position)| (symbol, position.quantity))
+            .collect();
+        (holdings, avg_costs)
+    }
+
+    /// Send the child orders of triggered stops: a market order at the
+    /// triggering price, or a limit order at the stop's limit
+    fn fire_stops(&self, triggered: Vec<Triggered>) {
+        for Triggered { entry, price } in triggered {
+            let stop = &entry.instruction;
+            if let Some(oco) = &entry.oco {
+                println!(">> stop {oco} cancelled: {} triggered", entry.id);
+            }
+            let result = match stop.limit {
+                Some(limit) => self.submit_order(&stop.symbol, stop.side, stop.quantity, limit),
+                None => self.submit_market_order(&stop.symbol, stop.side, stop.quantity, price),
+            };
+            match result {
+                Ok(order) => {
+                    println!(">> stop {entry} triggered at {price}: {}", order.cl_ord_id)
+                }
+                Err(err) => println!(">> stop {entry} triggered at {price}, not sent: {err}"),
+            }
+        }
+    }
+
+    /// Send what the strategy asked for, splitting synthetic orders
+    fn submit_intent(&self, intent: &OrderIntent) {
+        let result = if self.is_synthetic(&intent.symbol) {
+            self.submit_synthetic(&intent.symbol, intent.side, intent.quantity)
+                .map(|_| ())
+        } else {
+            self.submit_order(&intent.symbol, intent.side, intent.quantity, intent.price)
+                .map(|_| ())
+        };
+        if let Err(err) = result {
+            println!(">> strategy order {intent:?} not sent: {err}");
+        }
+    }
+
+    pub fn is_synthetic(&self, symbol: &str) -> bool {
+        self.synthetics().is_synthetic(symbol)
+    }
+
+    /// Split an order on a synthetic instrument into one limit order per
+    /// leg, each priced at the leg's far touch and risk-checked on its own
+    ///
+    /// All or nothing: if a leg cannot be sent, the legs already sent are
+    /// cancelled, so no half basket is left working.
+    pub fn submit_synthetic(
+        &self,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+    ) -> Result<Vec<Order>, OrderError> {
+        if !self.trading_enabled.load(Ordering::Relaxed) {
+            return Err(OrderError::TradingDisabled);
+        }
+        if self.draining.load(Ordering::Relaxed) {
+            return Err(OrderError::Draining);
+        }
+        let children = self.synthetics().decompose(symbol, side, quantity)?;
+        println!(
+            ">> synthetic {symbol} {} {quantity}: {} leg(s)",
+            side.as_str(),
+            children.len()
+        );
+
+        let mut sent: Vec<Order> = Vec::with_capacity(children.len());
+        for child in children {
+            match self.submit_order(&child.symbol, child.side, child.quantity, child.price) {
+                Ok(order) => sent.push(order),
+                Err(err) => {
+                    for order in &sent {
+                        let _ = self.cancel_order(&order.cl_ord_id);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Send an OrderCancelRequest for a working order
+    pub fn cancel_order(&self, cl_ord_id: &str) -> Result<(), OrderError> {
+        let order = self
+            .oms
+            .working_orders()
+            .into_iter()
+            .find(|x| x.cl_ord_id == cl_ord_id)
+            .ok_or(OrderError::UnknownOrder)?;
+
+        let cancel_id = self.oms.next_cl_ord_id();
+        self.check_profile(|| order.to_cancel_request(&cancel_id))?;
+        let result = order
+            .to_cancel_request(&cancel_id)
+            .map_err(SendError::from)
+            .and_then(|msg| {
+                self.sessions
+                    .orders()
+                    .map_err(SendError::from)
+                    .and_then(|session| self.send_order_message(msg, &session))
+            });
+        println!(">> cancel {}: {result:?}", order.cl_ord_id);
+
+        let reports = result?;
+        if let Some(pending) = self.oms.mark_pending_cancel(&order.cl_ord_id) {
+            self.on_order_changed(&pending, Some(order.status));
+        }
+        self.apply_local_reports(reports);
+        Ok(())
+    }
+
+    /// Check a message about to be sent against the venue profile, if any
+    fn check_profile(
+        &self,
+        msg: impl FnOnce() -> Result<Message, QuickFixError>,
+    ) -> Result<(), OrderError> {
+        let Some(profile) = &self.profile else {
+            return Ok(());
+        };
+        let violations = msg().map(|msg| profile.check(&msg)).unwrap_or_default();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(OrderError::Conformance(violations))
+        }
+    }
+
+    /// Send a message on the order session; in paper trading hand it to the
+    /// paper engine, in dry run count it and make up the counterparty's
+    /// acknowledgement instead
+    ///
+    /// # Returns
+    /// The made-up ExecutionReports, to apply once the OMS knows about the
+    /// request; none when the message was sent
+    fn send_order_message(
+        &self,
+        msg: Message,
+        session: &SessionId,
+    ) -> Result<Vec<FixMessage>, SendError> {
+        let label = self.sessions.orders_label();
+        if let Some(paper) = &self.paper {
+            println!(">> [PAPER] 35={} kept, not sent to {label}", msg_type(&msg));
+            return Ok(paper.on_order_message(label, &msg));
+        }
+        if !self.dry_run.is_active(label) {
+            return self.limiter.send(msg, session).map(|()| Vec::new());
+        }
+
+        let msg_type = msg_type(&msg);
+        self.metrics.on_dry_run(label, &msg_type);
+        println!(">> [DRY RUN] 35={msg_type} not sent to {label}");
+        Ok(self.dry_run.acknowledge(label, &msg).into_iter().collect())
+    }
+
+    /// Apply ExecutionReports made up in paper trading or dry run, and
+    /// publish them on the bus as if received
+    fn apply_local_reports(&self, reports: Vec<FixMessage>) {
+        for report in reports {
+            self.bus.publish_fix(&FixEvent::Message(report.clone()));
+            if report.msg_type() == "8" {
+                self.on_execution_report(&report);
+            }
+        }
+    }
+
+    /// Whether the strategy may send orders
+    fn is_trading(&self) -> bool {
+        self.trading_enabled.load(Ordering::Relaxed) && !self.draining.load(Ordering::Relaxed)
+    }
+
+    // =========================================================================
+    // Allocations
+    // =========================================================================
+
+    /// Split the fills of these orders between accounts: send an
+    /// AllocationInstruction on the order session and track its status
+    pub fn allocate(
+        &self,
+        cl_ord_ids: &[String],
+        allocs: Vec<AllocShare>,
+    ) -> Result<Allocation, OrderError> {
+        let fills: Vec<_> = self
+            .positions
+            .fills()
+            .into_iter()
+            .filter(|x| cl_ord_ids.contains(&x.cl_ord_id))
+            .collect();
+        let instruction = self.allocations.prepare(&fills, allocs)?;
+        let result = instruction
+            .to_message()
+            .map_err(SendError::from)
+            .and_then(|msg| {
+                self.sessions
+                    .orders()
+                    .map_err(SendError::from)
+                    .and_then(|session| self.send_order_message(msg, &session))
+            });
+        println!(">> allocation {instruction}: {result:?}");
+        result?;
+
+        let alloc_id = instruction.alloc_id.clone();
+        self.allocations.track(instruction);
+        Ok(self.allocations.get(&alloc_id)?)
+    }
+
+    /// Apply the broker's answer to an allocation (35=P or 35=AS)
+    fn on_allocation_ack(&self, msg: &FixMessage) {
+        if let Some(allocation) = self.allocations.on_message(msg) {
+            println!(">> allocation {allocation}");
+        }
+    }
+
+    // =========================================================================
+    // Version Upgrade
+    // =========================================================================
+
+    /// Refuse new orders from now on, to hand over to a new version
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::Relaxed);
+    }
+
+    /// Orders sent and not acknowledged yet: new ones and pending cancels
+    pub fn in_flight(&self) -> usize {
+        self.oms
+            .working_orders()
+            .iter()
+            .filter(|x| matches!(x.status, OrderStatus::PendingNew | OrderStatus::PendingCancel))
+            .count()
+    }
+
+    /// The orders, ClOrdID sequence and fills to hand over, without the
+    /// sequence numbers (read once the sessions are stopped)
+    pub fn handover(&self) -> Handover {
+        let mut handover = Handover::new(self.oms.id_prefix());
+        handover.orders = self.oms.orders();
+        handover.next_cl_ord_id = self.oms.sequence();
+        handover.fills = self.positions.fills();
+        handover
+    }
+
+    /// Take over the orders and fills of the version this one replaces
+    pub fn take_over(&self, handover: Handover) {
+        self.oms.restore(handover.orders, handover.next_cl_ord_id);
+        self.positions.restore(handover.fills);
+    }
+
+    /// Send a cancel for every working order (used on shutdown)
+    pub fn cancel_all(&self) {
+        for order in self.oms.working_orders() {
+            // Failures are already reported by cancel_order
+            let _ = self.cancel_order(&order.cl_ord_id);
+        }
+    }
+
+    fn on_execution_report(&self, msg: &FixMessage) {
+        let issue = self
+            .currencies
+            .lock()
+            .expect("currency tracker lock poisoned")
+            .on_message(msg);
+        if let Some(issue) = issue {
+            println!(">> report {}: {issue}", msg.get(11).unwrap_or("-"));
+        }
+        if let Some(trade) = self.blotter.on_execution_report(msg) {
+            self.bridge.publish_trade(&trade);
+        }
+
+        let Some(update) = self.oms.on_execution_report(msg) else {
+            return;
+        };
+
+        // Keyed by the original ClOrdID so cancels share the order's partition
+        if let Some(kafka) = &self.kafka {
+            kafka.publish(
+                &update.order.cl_ord_id,
+                format!(
+                    "{{\"type\":\"execution_report\",\"session\":\"{}\",\"fields\":{}}}",
+                    msg.session,
+                    pairs_json(msg.fields.iter().map(|(tag, value)| (tag, value)))
+                ),
+            );
+        }
+        self.on_order_changed(&update.order, Some(update.previous_status));
+
+        if let Some(fill) = update.fill {
+            let position = self.positions.apply_fill(&fill);
+            println!(
+                ">> fill {} {} {:?} {}@{} position={}",
+                fill.cl_ord_id,
+                fill.symbol,
+                fill.side,
+                fill.quantity,
+                fill.price,
+                position.quantity
+            );
+            if update.order.status == OrderStatus::Filled {
+                let order = &update.order;
+                self.notify(
+                    WebhookEvent::OrderFilled,
+                    &[
+                        ("session", msg.session.clone()),
+                        ("cl_ord_id", order.cl_ord_id.clone()),
+                        ("symbol", order.symbol.clone()),
+                        ("side", order.side.as_str().to_string()),
+                        ("quantity", order.cum_qty.to_string()),
+                        ("price", order.avg_px.to_string()),
+                        ("position", position.quantity.to_string()),
+                    ],
+                );
+            }
+            if self.bridge.has_clients() {
+                self.bridge.publish(Event {
+                    session: None,
+                    topic: "position".to_string(),
+                    json: format!(
+                        "{{\"type\":\"position\",\"position\":{}}}",
+                        position.to_json(&fill.symbol)
+                    ),
+                });
+            }
+            if self.is_trading() {
+                self.fire_stops(self.stops.on_trade(&fill.symbol, fill.price));
+            }
+        }
+
+        if let Some(correction) = update.correction {
+            let position = self.positions.apply_correction(&correction);
+            let original = &correction.original;
+            match &correction.corrected {
+                None => println!(
+                    ">> bust {} of {} {} {:?} {}@{} position={}",
+                    correction.exec_ref_id,
+                    original.cl_ord_id,
+                    original.symbol,
+                    original.side,
+                    original.quantity,
+                    original.price,
+                    position.quantity
+                ),
+                Some(corrected) => println!(
+                    ">> correct {} -> {} of {} {} {:?} {}@{} -> {}@{} position={}",
+                    correction.exec_ref_id,
+                    correction.exec_id,
+                    original.cl_ord_id,
+                    original.symbol,
+                    original.side,
+                    original.quantity,
+                    original.price,
+                    corrected.quantity,
+                    corrected.price,
+                    position.quantity
+                ),
+            }
+            if self.bridge.has_clients() {
+                self.bridge.publish(Event {
+                    session: None,
+                    topic: "position".to_string(),
+                    json: format!(
+                        "{{\"type\":\"position\",\"position\":{},\"correction\":{}}}",
+                        position.to_json(&original.symbol),
+                        correction.to_json()
+                    ),
+                });
+            }
+        }
+    }
+
+    // =========================================================================
+    // Monitoring
+    // =========================================================================
+
+    /// Count a message and stream it to WebSocket clients
+    ///
+    /// # Returns
+    /// The MsgType (tag 35) of the message
+    fn record_message(&self, session: &SessionId, direction: Direction, msg: &Message) -> String {
+        let msg_type = msg_type(msg);
+        self.metrics.on_message(session, direction, &msg_type);
+        self.bridge.publish_fix(session, direction, msg);
+        msg_type
+    }
+
+    /// Fan out an order update: WebSocket clients see every update, Kafka
+    /// only real state transitions
+    ///
+    /// # Arguments
+    /// * `previous` - Status before the change, None for a new order
+    fn on_order_changed(&self, order: &Order, previous: Option<OrderStatus>) {
+        if self.bridge.has_clients() {
+            self.bridge.publish(Event {
+                session: Some(self.sessions.orders_label().to_string()),
+                topic: "order".to_string(),
+                json: format!("{{\"type\":\"order\",\"order\":{}}}", order.to_json()),
+            });
+        }
+
+        if let Some(kafka) = &self.kafka {
+            if previous != Some(order.status) {
+                let from = previous.map_or("null".to_string(), |x| format!("\"{x:?}\""));
+                kafka.publish(
+                    &order.cl_ord_id,
+                    format!(
+                        "{{\"type\":\"order_state\",\"from\":{from},\"to\":\"{:?}\",\"order\":{}}}",
+                        order.status,
+                        order.to_json()
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// MsgType (tag 35) of a message, empty if missing
+fn msg_type(msg: &Message) -> String {
+    msg.with_header(|h| h.get_field(35)).unwrap_or_default()
+}
+
+/// Build a streaming top-of-book MarketDataRequest (35=V)
+fn build_market_data_request(md_req_id: &str, symbol: &str) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "V"))?;
+    msg.set_field(262, md_req_id)?; // MDReqID
+    msg.set_field(263, "1")?; // SubscriptionRequestType: snapshot + updates
+    msg.set_field(264, "1")?; // MarketDepth: top of book
+
+    // NoMDEntryTypes (267): bid and offer
+    for entry_type in ["0", "1"] {
+        let mut group = Group::try_new(267, 269)?;
+        group.set_field(269, entry_type)?;
+        msg.add_group(&group)?;
+    }
+
+    // NoRelatedSym (146)
+    let mut group = Group::try_new(146, 55)?;
+    group.set_field(55, symbol)?;
+    msg.add_group(&group)?;
+
+    Ok(msg)
+}
+
+// =============================================================================
+// ApplicationCallback Implementation
+// =============================================================================
+// Runs on the QuickFIX engine threads: record monitoring data, then hand the
+// events the business logic cares about over to BuySideApp::run()
+// =============================================================================
+
+pub struct FixCallbacks {
+    app: Arc<BuySideApp>,
+    events: LaneSender,
+}
+
+impl FixCallbacks {
+    pub fn new(app: Arc<BuySideApp>, events: LaneSender) -> Self {
+        Self { app, events }
+    }
+
+    /// Never blocks (unbounded channel); fails only once the task is gone
+    fn push(&self, event: FixEvent) {
+        let _ = self.events.send(event);
+    }
+}
+
+impl ApplicationCallback for FixCallbacks {
+    fn on_create(&self, session: &SessionId) {
+        println!(">> session created: {session:?}");
+    }
+
+    fn on_logon(&self, session: &SessionId) {
+        println!(">> logon: {session:?}");
+        self.app.metrics.on_logon(session);
+        self.push(FixEvent::logon(session));
+    }
+
+    fn on_logout(&self, session: &SessionId) {
+        println!(">> logout: {session:?}");
+        self.app.metrics.on_logout(session);
+        self.push(FixEvent::logout(session));
+    }
+
+    fn on_msg_to_admin(&self, msg: &mut Message, session: &SessionId) {
+        self.app.record_message(session, Direction::Outbound, msg);
+    }
+
+    fn on_msg_to_app(&self, msg: &mut Message, session: &SessionId) -> Result<(), MsgToAppError> {
+        self.app.record_message(session, Direction::Outbound, msg);
+        Ok(())
+    }
+
+    fn on_msg_from_admin(
+        &self,
+        msg: &Message,
+        session: &SessionId,
+    ) -> Result<(), MsgFromAdminError> {
+        self.app.record_message(session, Direction::Inbound, msg);
+        Ok(())
+    }
+
+    fn on_msg_from_app(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        let msg_type = self.app.record_message(session, Direction::Inbound, msg);
+        if matches!(msg_type.as_str(), "W" | "X" | "8" | "P" | "AS" | "d" | "y") {
+            self.push(FixEvent::Message(FixMessage::decode(
+                session,
+                Direction::Inbound,
+                false,
+                msg,
+            )));
+        }
+        Ok(())
+    }
+}
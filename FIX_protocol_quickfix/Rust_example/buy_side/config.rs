@@ -0,0 +1,131 @@
+// =============================================================================
+// Buy-Side Stack Configuration Builder
+// =============================================================================
+// Builds the initiator configuration programmatically (same approach as
+// demo_config.rs) with two sessions against the simulator acceptor:
+//
+// - A market data session, used only for subscriptions and price updates
+// - An order entry session, used for NewOrderSingle / cancels / fills
+//
+// Splitting market data and order flow over separate sessions is the usual
+// production layout: a burst of quotes never delays an order or a fill.
+// =============================================================================
+
+use quickfix::{dictionary_item::*, Dictionary, QuickFixError, SessionId, SessionSettings};
+
+use trading::session::session_label;
+
+/// FIX version used by both sessions
+pub const BEGIN_STRING: &str = "FIX.4.4";
+
+/// QuickFIX message store (FileStorePath): messages and sequence numbers
+pub const STORE_DIR: &str = "buy_side_store";
+
+/// CompID of the simulator acceptor we connect to
+pub const SIMULATOR_COMP_ID: &str = "SIMULATOR";
+
+/// Our CompID on the market data session
+pub const MD_SENDER_COMP_ID: &str = "BUYSIDE_MD";
+
+/// Our CompID on the order entry session
+pub const ORDER_SENDER_COMP_ID: &str = "BUYSIDE_ORD";
+
+// =============================================================================
+// StackSessions: The Two Session Identifiers
+// =============================================================================
+// SessionId wraps a C++ handle that is neither Send nor Sync. Only session
+// labels are kept here (the form carried by decoded FixEvents) and identifiers
+// are rebuilt when a message is sent, so the application can be shared with
+// the REST gateway thread and the async event task.
+// =============================================================================
+
+#[derive(Debug)]
+pub struct StackSessions {
+    /// Label of the market data session (MarketDataRequest / snapshots)
+    market_data: String,
+
+    /// Label of the order entry session (orders, execution reports)
+    orders: String,
+}
+
+impl StackSessions {
+    pub fn try_new() -> Result<Self, QuickFixError> {
+        Ok(Self {
+            market_data: session_label(&market_data_session()?),
+            orders: session_label(&order_session()?),
+        })
+    }
+
+    /// Session used for MarketDataRequest / snapshots / incremental refresh
+    pub fn market_data(&self) -> Result<SessionId, QuickFixError> {
+        market_data_session()
+    }
+
+    /// Session used for order entry and execution reports
+    pub fn orders(&self) -> Result<SessionId, QuickFixError> {
+        order_session()
+    }
+
+    pub fn orders_label(&self) -> &str {
+        &self.orders
+    }
+
+    /// Whether the session label is the market data session
+    pub fn is_market_data(&self, label: &str) -> bool {
+        label == self.market_data
+    }
+
+    /// Whether the session label is the order entry session
+    pub fn is_orders(&self, label: &str) -> bool {
+        label == self.orders
+    }
+}
+
+fn market_data_session() -> Result<SessionId, QuickFixError> {
+    SessionId::try_new(BEGIN_STRING, MD_SENDER_COMP_ID, SIMULATOR_COMP_ID, "")
+}
+
+fn order_session() -> Result<SessionId, QuickFixError> {
+    SessionId::try_new(BEGIN_STRING, ORDER_SENDER_COMP_ID, SIMULATOR_COMP_ID, "")
+}
+
+// =============================================================================
+// Settings Builder
+// =============================================================================
+
+/// Build initiator settings for both sessions
+///
+/// # Arguments
+/// * `sessions` - Session identifiers to configure
+/// * `host` - Simulator acceptor host
+/// * `port` - Simulator acceptor port
+pub fn build_settings(
+    sessions: &StackSessions,
+    host: &str,
+    port: u16,
+) -> Result<SessionSettings, QuickFixError> {
+    let mut settings = SessionSettings::new();
+
+    // Defaults shared by both sessions
+    settings.set(
+        None,
+        Dictionary::try_from_items(&[
+            &ConnectionType::Initiator,
+            &ReconnectInterval(5),
+            &FileStorePath(STORE_DIR),
+            &StartTime("00:00:00"),
+            &EndTime("00:00:00"),
+            &HeartBtInt(30),
+            &SocketConnectHost(host),
+            &SocketConnectPort(port),
+            &DataDictionary("spec/FIX44.xml"),
+        ])?,
+    )?;
+
+    // One [SESSION] block per session; everything else is inherited
+    for session in [sessions.market_data()?, sessions.orders()?] {
+        settings.set(Some(&session), Dictionary::new())?;
+    }
+
+    Ok(settings)
+}
@@ -0,0 +1,382 @@
+// =============================================================================
+// gRPC Order Entry Gateway
+// =============================================================================
+// Lets microservices in any language trade through the FIX sessions, this
+// process acting as their FIX gateway. The service is trading.gateway.v1.
+// FixGateway of gateway.proto (next to this file):
+//
+//   SubmitOrder        35=D, through the same risk -> OMS -> wire path as
+//                      the REST gateway (one 35=D per leg of a synthetic)
+//   CancelOrder        35=F
+//   StreamExecutions   acknowledgements, fills, busts / corrects and
+//                      rejects from the order session, off the event bus
+//   StreamMarketData   35=W / 35=X from the market data session, off the
+//                      event bus
+//
+// A stream subscribes to the bus when the call starts and ends when the
+// client cancels it; nothing is replayed from before the call. SubmitOrder
+// and CancelOrder are recorded in the operator audit log, with the
+// x-operator metadata (or the client address) as the actor.
+// =============================================================================
+
+use std::{
+    io,
+    sync::{
+        mpsc::{Receiver, RecvTimeoutError},
+        Arc,
+    },
+    time::Duration,
+};
+
+use trading::{
+    audit::{AuditEntry, AuditLog, AuditSource},
+    bus::{AppEvent, EventKind, MarketDataUpdate, RejectOf},
+    gateway::grpc::{self, Call, Code, ProtoMessage, ProtoWriter, ServerStream, Status},
+    oms::{blotter::Trade, Order, Side},
+};
+
+use crate::app::{BuySideApp, OrderError};
+
+/// Service name of gateway.proto
+const SERVICE: &str = "trading.gateway.v1.FixGateway";
+
+/// How often an idle stream checks that its client is still there
+const STREAM_POLL: Duration = Duration::from_secs(1);
+
+// Execution.Type of gateway.proto
+const ACCEPTED: u64 = 1;
+const FILL: u64 = 2;
+const BUSTED: u64 = 3;
+const CORRECTED: u64 = 4;
+const ORDER_REJECTED: u64 = 5;
+const CANCEL_REJECTED: u64 = 6;
+
+/// Start the gateway on `0.0.0.0:<port>` in a background thread
+pub fn spawn_server(port: u16, app: Arc<BuySideApp>, audit: Arc<AuditLog>) -> io::Result<()> {
+    grpc::spawn_server("grpc", port, move |call, stream| {
+        handle(&app, &audit, call, stream)
+    })?;
+    println!(">> gRPC gateway available on 0.0.0.0:{port} ({SERVICE})");
+    Ok(())
+}
+
+fn handle(
+    app: &BuySideApp,
+    audit: &AuditLog,
+    call: &Call,
+    stream: &mut ServerStream,
+) -> Result<(), Status> {
+    if call.service() != SERVICE {
+        return Err(Status::unimplemented(format!(
+            "unknown service {}",
+            call.service()
+        )));
+    }
+    let request = ProtoMessage::parse(&call.message)?;
+    match call.method() {
+        "SubmitOrder" => {
+            let result = submit_order(app, &request);
+            record(audit, call, &request, &result);
+            send(stream, result?)
+        }
+        "CancelOrder" => {
+            let result = cancel_order(app, &request);
+            record(audit, call, &request, &result);
+            send(stream, result?)
+        }
+        "StreamExecutions" => stream_executions(app, &request, stream),
+        "StreamMarketData" => stream_market_data(app, &request, stream),
+        method => Err(Status::unimplemented(format!("unknown method {method}"))),
+    }
+}
+
+fn send(stream: &mut ServerStream, reply: ProtoWriter) -> Result<(), Status> {
+    stream
+        .send(&reply.finish())
+        .map_err(|err| Status::new(Code::Unavailable, err.to_string()))
+}
+
+/// Append a state-changing call to the audit log
+fn record(
+    audit: &AuditLog,
+    call: &Call,
+    request: &ProtoMessage,
+    result: &Result<ProtoWriter, Status>,
+) {
+    let command = match call.method() {
+        "SubmitOrder" => format!(
+            "SubmitOrder {} {} {} @ {}",
+            request.string(1).unwrap_or_default(),
+            side_name(request.uint(2)),
+            request.double(3),
+            request.double(4)
+        ),
+        method => format!("{method} {}", request.string(1).unwrap_or_default()),
+    };
+    let outcome = match result {
+        Ok(_) => "OK".to_string(),
+        Err(status) => status.to_string(),
+    };
+    let entry = AuditEntry::new(
+        AuditSource::Grpc,
+        &call.operator(),
+        &command,
+        result.is_ok(),
+        &outcome,
+    );
+    if let Err(err) = audit.record(&entry) {
+        eprintln!("Cannot write to {}: {err}", audit.path().display());
+    }
+}
+
+// =============================================================================
+// Orders
+// =============================================================================
+
+/// SubmitOrderRequest -> SubmitOrderReply
+fn submit_order(app: &BuySideApp, request: &ProtoMessage) -> Result<ProtoWriter, Status> {
+    let symbol = request.string(1)?;
+    if symbol.is_empty() {
+        return Err(Status::invalid_argument("missing symbol"));
+    }
+    let side = match request.uint(2) {
+        1 => Side::Buy,
+        2 => Side::Sell,
+        _ => return Err(Status::invalid_argument("side must be BUY or SELL")),
+    };
+    // Written so that a NaN double, for which every comparison is false, fails
+    let quantity = request.double(3);
+    if !(quantity.is_finite() && quantity > 0.0) {
+        return Err(Status::invalid_argument("missing or invalid quantity"));
+    }
+
+    let orders = if app.is_synthetic(symbol) {
+        let orders = app
+            .submit_synthetic(symbol, side, quantity)
+            .map_err(order_status)?;
+        println!(">> gRPC synthetic order {symbol}: {} leg(s)", orders.len());
+        orders
+    } else {
+        let price = request.double(4);
+        if !(price.is_finite() && price > 0.0) {
+            return Err(Status::invalid_argument("missing or invalid price"));
+        }
+        let order = app
+            .submit_order(symbol, side, quantity, price)
+            .map_err(order_status)?;
+        println!(">> gRPC order {}", order.cl_ord_id);
+        vec![order]
+    };
+    Ok(orders.iter().fold(ProtoWriter::new(), |reply, order| {
+        reply.message(1, order_message(order))
+    }))
+}
+
+/// CancelOrderRequest -> CancelOrderReply
+fn cancel_order(app: &BuySideApp, request: &ProtoMessage) -> Result<ProtoWriter, Status> {
+    let cl_ord_id = request.string(1)?;
+    if cl_ord_id.is_empty() {
+        return Err(Status::invalid_argument("missing cl_ord_id"));
+    }
+    app.cancel_order(cl_ord_id).map_err(order_status)?;
+    println!(">> gRPC cancel {cl_ord_id}");
+    Ok(ProtoWriter::new().string(1, cl_ord_id))
+}
+
+fn order_message(order: &Order) -> ProtoWriter {
+    ProtoWriter::new()
+        .string(1, &order.cl_ord_id)
+        .string(2, &order.symbol)
+        .uint(3, side_number(order.side))
+        .double(4, order.quantity)
+        .double(5, order.price)
+        .string(6, order.ord_type.as_str())
+        .double(7, order.cum_qty)
+        .double(8, order.avg_px)
+        .double(9, order.leaves_qty())
+        .string(10, &format!("{:?}", order.status))
+}
+
+/// The status code of a refused order, as the REST gateway's HTTP status
+fn order_status(err: OrderError) -> Status {
+    let code = match err {
+        OrderError::Risk(_)
+        | OrderError::Synthetic(_)
+        | OrderError::Conformance(_)
+        | OrderError::Allocation(_) => Code::FailedPrecondition,
+        OrderError::UnknownOrder => Code::NotFound,
+        OrderError::RateLimited(_) => Code::ResourceExhausted,
+        OrderError::TradingDisabled | OrderError::Draining | OrderError::Send(_) => {
+            Code::Unavailable
+        }
+    };
+    Status::new(code, err.to_string())
+}
+
+fn side_number(side: Side) -> u64 {
+    match side {
+        Side::Buy => 1,
+        Side::Sell => 2,
+    }
+}
+
+fn side_name(side: u64) -> &'static str {
+    match side {
+        1 => "buy",
+        2 => "sell",
+        _ => "?",
+    }
+}
+
+// =============================================================================
+// Streams
+// =============================================================================
+
+/// StreamExecutionsRequest -> stream of Execution
+fn stream_executions(
+    app: &BuySideApp,
+    request: &ProtoMessage,
+    stream: &mut ServerStream,
+) -> Result<(), Status> {
+    let symbols = request.strings(1)?;
+    let events = app
+        .bus
+        .subscribe(&[EventKind::OrderAccepted, EventKind::Fill, EventKind::Reject]);
+    forward(stream, &events, |event| {
+        let (message, symbol) = execution_message(app, &event)?;
+        let wanted = symbols.is_empty() || symbol.is_empty() || symbols.contains(&symbol.as_str());
+        wanted.then_some(message)
+    })
+}
+
+/// StreamMarketDataRequest -> stream of MarketData
+fn stream_market_data(
+    app: &BuySideApp,
+    request: &ProtoMessage,
+    stream: &mut ServerStream,
+) -> Result<(), Status> {
+    let symbols = request.strings(1)?;
+    let events = app.bus.subscribe(&[EventKind::MarketData]);
+    forward(stream, &events, |event| match event {
+        AppEvent::MarketDataUpdate(update)
+            if symbols.is_empty() || symbols.contains(&update.symbol.as_str()) =>
+        {
+            Some(market_data_message(&update))
+        }
+        _ => None,
+    })
+}
+
+/// Send what `convert` makes of each event until the client goes away
+fn forward(
+    stream: &mut ServerStream,
+    events: &Receiver<AppEvent>,
+    convert: impl Fn(AppEvent) -> Option<ProtoWriter>,
+) -> Result<(), Status> {
+    while !stream.is_closed() {
+        match events.recv_timeout(STREAM_POLL) {
+            Ok(event) => {
+                if let Some(message) = convert(event) {
+                    if stream.send(&message.finish()).is_err() {
+                        break;
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(Status::new(Code::Unavailable, "shutting down"));
+            }
+        }
+    }
+    // Cancelled by the client: the status goes nowhere
+    Ok(())
+}
+
+/// The Execution an event makes, and its symbol
+fn execution_message(app: &BuySideApp, event: &AppEvent) -> Option<(ProtoWriter, String)> {
+    let execution = |kind| ProtoWriter::new().uint(1, kind);
+    match event {
+        AppEvent::OrderAccepted {
+            session,
+            cl_ord_id,
+            order_id,
+            symbol,
+        } => Some((
+            execution(ACCEPTED)
+                .string(2, session)
+                .string(3, cl_ord_id)
+                .string(4, order_id)
+                .string(7, symbol),
+            symbol.clone(),
+        )),
+        AppEvent::Fill(trade) => {
+            Some((trade_message(execution(FILL), trade), trade.symbol.clone()))
+        }
+        AppEvent::FillAmended {
+            session,
+            exec_ref_id,
+            corrected: Some(trade),
+        } => Some((
+            trade_message(execution(CORRECTED), trade)
+                .string(2, session)
+                .string(6, exec_ref_id),
+            trade.symbol.clone(),
+        )),
+        // Symbol unknown: streamed whatever the symbols asked for
+        AppEvent::FillAmended {
+            session,
+            exec_ref_id,
+            corrected: None,
+        } => Some((
+            execution(BUSTED).string(2, session).string(6, exec_ref_id),
+            String::new(),
+        )),
+        AppEvent::Reject(reject) => {
+            let kind = match reject.of {
+                RejectOf::Order => ORDER_REJECTED,
+                RejectOf::Cancel => CANCEL_REJECTED,
+                _ => return None,
+            };
+            let symbol = app
+                .oms
+                .orders()
+                .into_iter()
+                .find(|x| x.cl_ord_id == reject.reference)
+                .map(|x| x.symbol)
+                .unwrap_or_default();
+            let message = execution(kind)
+                .string(2, &reject.session)
+                .string(3, &reject.reference)
+                .string(7, &symbol)
+                .string(12, reject.text.as_deref().unwrap_or_default());
+            Some((message, symbol))
+        }
+        _ => None,
+    }
+}
+
+fn trade_message(execution: ProtoWriter, trade: &Trade) -> ProtoWriter {
+    execution
+        .string(2, &trade.session)
+        .string(3, &trade.cl_ord_id)
+        .string(5, &trade.exec_id)
+        .string(7, &trade.symbol)
+        .uint(8, side_number(trade.side))
+        .double(9, trade.quantity)
+        .double(10, trade.price)
+        .int(11, trade.time)
+}
+
+fn market_data_message(update: &MarketDataUpdate) -> ProtoWriter {
+    let message = ProtoWriter::new()
+        .string(1, &update.session)
+        .string(2, &update.symbol)
+        .bool(3, update.snapshot);
+    update.entries.iter().fold(message, |message, entry| {
+        let entry = ProtoWriter::new()
+            .string(1, &entry.entry_type)
+            .double(2, entry.price.unwrap_or_default())
+            .double(3, entry.size.unwrap_or_default());
+        message.message(4, entry)
+    })
+}
@@ -0,0 +1,401 @@
+// =============================================================================
+// REST Order Entry Gateway
+// =============================================================================
+// Lets web frontends and scripts trade through the FIX order session without
+// speaking FIX. Every request goes through the same risk -> OMS -> wire path
+// as the strategy:
+//
+//   POST   /orders            35=D  body: {"symbol","side","quantity","price"}
+//                                   (no price for a synthetic: one 35=D per
+//                                   leg, the list of orders in return)
+//   DELETE /orders/{clordid}  35=F
+//   GET    /orders            OMS snapshot (?symbol= to filter)
+//   POST   /stops             stop and / or take-profit held locally, an OCO
+//                             pair when both are given; body: {"symbol",
+//                             "side","quantity","stop","stop_limit",
+//                             "take_profit","take_profit_limit","trigger"}
+//   DELETE /stops/{id}        drop a stop (both legs of an OCO pair)
+//   GET    /stops             stops waiting for their trigger
+//   GET    /positions         position book snapshot
+//   GET    /blotter           fills, newest first, a page at a time
+//                             (?account= &symbol= &side= &min_qty= to
+//                             filter, &offset= &limit= to page, default 50;
+//                             &format=csv: every match, oldest first, as CSV)
+//   GET    /corrections       trade busts / corrects received (?exec_id= for
+//                             the amendment chain of one fill)
+//   POST   /allocations       35=J splitting the fills of orders between
+//                             accounts; body: {"cl_ord_ids":"BUY-1,BUY-2",
+//                             "allocs":"FUND_A=120,FUND_B=80"}
+//   GET    /allocations       allocations sent and their status (?exec_id=
+//                             for those of one fill)
+//   GET    /allocations/{id}  one allocation
+//   GET    /instruments       definitions received from the venue: tick
+//                             size, lot size, multiplier, currency
+//   GET    /instruments/{sym} one of them
+//   GET    /dry-run           dry run state: global and per session
+//   PUT    /dry-run           body: {"enabled":true|false[,"session":LABEL]}
+//
+// POST, DELETE and PUT requests are recorded in the operator audit log, with
+// the X-Operator header (or the client address) as the actor.
+// =============================================================================
+
+use std::{io, sync::Arc};
+
+use trading::{
+    audit::{AuditLog, AuditSource},
+    gateway::http::{self, json_escape, parse_json_object, Request, Response},
+    instruments::Instrument,
+    oms::{
+        allocations::{self, Allocation},
+        blotter::{self, BlotterFilter, DEFAULT_PAGE_SIZE},
+        stops::{PriceSource, StopEntry, StopError, StopInstruction},
+        Correction, Order, Side,
+    },
+};
+
+use crate::app::{BuySideApp, OrderError};
+
+/// Start the gateway on `0.0.0.0:<port>` in a background thread
+pub fn spawn_server(port: u16, app: Arc<BuySideApp>, audit: Arc<AuditLog>) -> io::Result<()> {
+    let handler = http::audited(audit, AuditSource::Rest, move |request| handle(&app, request));
+    http::spawn_server("rest-http", port, handler)?;
+    println!(">> REST gateway available on http://0.0.0.0:{port}/orders");
+    Ok(())
+}
+
+fn handle(app: &BuySideApp, request: &Request) -> Response {
+    match (request.method.as_str(), request.segments().as_slice()) {
+        ("POST", ["orders"]) => new_order(app, &request.body),
+        ("DELETE", ["orders", cl_ord_id]) => match app.cancel_order(cl_ord_id) {
+            Ok(()) => Response::json(format!(
+                "{{\"cancel_requested\":\"{}\"}}",
+                json_escape(cl_ord_id)
+            )),
+            Err(err) => error_response(&err),
+        },
+        ("GET", ["orders"]) => {
+            let mut orders = app.oms.orders();
+            if let Some(symbol) = request.query("symbol") {
+                orders.retain(|x| x.symbol == symbol);
+            }
+            orders.sort_by(|a, b| a.cl_ord_id.cmp(&b.cl_ord_id));
+            let orders: Vec<_> = orders.iter().map(Order::to_json).collect();
+            Response::json(format!("[{}]", orders.join(",")))
+        }
+        ("POST", ["stops"]) => new_stop(app, &request.body),
+        ("DELETE", ["stops", id]) => match app.stops.cancel(id) {
+            Ok(cancelled) => {
+                println!(">> REST stop {id} cancelled");
+                Response::json(stops_json(&cancelled))
+            }
+            Err(err) => stop_error_response(&err),
+        },
+        ("GET", ["stops"]) => Response::json(stops_json(&app.stops.entries())),
+        ("GET", ["positions"]) => positions(app),
+        ("GET", ["blotter"]) => trade_blotter(app, request),
+        ("GET", ["corrections"]) => {
+            let corrections = match request.query("exec_id") {
+                Some(exec_id) => app.oms.correction_chain(exec_id),
+                None => app.oms.corrections(),
+            };
+            let corrections: Vec<_> = corrections.iter().map(Correction::to_json).collect();
+            Response::json(format!("[{}]", corrections.join(",")))
+        }
+        ("POST", ["allocations"]) => new_allocation(app, &request.body),
+        ("GET", ["allocations"]) => {
+            let mut allocations = app.allocations.allocations();
+            if let Some(exec_id) = request.query("exec_id") {
+                allocations.retain(|x| x.instruction.execs.iter().any(|e| e.exec_id == exec_id));
+            }
+            let allocations: Vec<_> = allocations.iter().map(Allocation::to_json).collect();
+            Response::json(format!("[{}]", allocations.join(",")))
+        }
+        ("GET", ["allocations", id]) => match app.allocations.get(id) {
+            Ok(allocation) => Response::json(allocation.to_json()),
+            Err(err) => Response::error("404 Not Found", &err.to_string()),
+        },
+        ("GET", ["instruments"]) => {
+            let instruments: Vec<_> =
+                app.instruments.instruments().iter().map(Instrument::to_json).collect();
+            Response::json(format!("[{}]", instruments.join(",")))
+        }
+        ("GET", ["instruments", symbol]) => match app.instruments.get(symbol) {
+            Some(instrument) => Response::json(instrument.to_json()),
+            None => Response::not_found(),
+        },
+        ("GET", ["dry-run"]) => dry_run_state(app),
+        ("PUT", ["dry-run"]) => set_dry_run(app, &request.body),
+        _ => Response::not_found(),
+    }
+}
+
+fn dry_run_state(app: &BuySideApp) -> Response {
+    let sessions: Vec<_> = app
+        .dry_run
+        .sessions()
+        .iter()
+        .map(|x| format!("\"{}\"", json_escape(x)))
+        .collect();
+    Response::json(format!(
+        "{{\"global\":{},\"sessions\":[{}]}}",
+        app.dry_run.is_global(),
+        sessions.join(",")
+    ))
+}
+
+/// Switch dry run globally, or for one session with "session"
+fn set_dry_run(app: &BuySideApp, body: &str) -> Response {
+    let Some(fields) = parse_json_object(body) else {
+        return Response::error("400 Bad Request", "body must be a flat JSON object");
+    };
+    let enabled = match fields.get("enabled").map(String::as_str) {
+        Some("true") => true,
+        Some("false") => false,
+        _ => return Response::error("400 Bad Request", "enabled must be true or false"),
+    };
+    match fields.get("session") {
+        Some(label) => app.dry_run.set_session(label, enabled),
+        None => app.dry_run.set_global(enabled),
+    }
+    println!(">> REST dry run {}", if enabled { "ON" } else { "OFF" });
+    dry_run_state(app)
+}
+
+fn new_order(app: &BuySideApp, body: &str) -> Response {
+    let Some(fields) = parse_json_object(body) else {
+        return Response::error("400 Bad Request", "body must be a flat JSON object");
+    };
+
+    let Some(symbol) = fields.get("symbol").filter(|x| !x.is_empty()) else {
+        return Response::error("400 Bad Request", "missing symbol");
+    };
+    let side = match fields
+        .get("side")
+        .map(|x| x.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("buy") => Side::Buy,
+        Some("sell") => Side::Sell,
+        _ => return Response::error("400 Bad Request", "side must be buy or sell"),
+    };
+    let Some(quantity) = fields.get("quantity").and_then(|x| positive(x)) else {
+        return Response::error("400 Bad Request", "missing or invalid quantity");
+    };
+    if app.is_synthetic(symbol) {
+        return match app.submit_synthetic(symbol, side, quantity) {
+            Ok(orders) => {
+                let orders: Vec<_> = orders.iter().map(Order::to_json).collect();
+                println!(">> REST synthetic order {symbol}: {} leg(s)", orders.len());
+                Response {
+                    status: "201 Created",
+                    ..Response::json(format!("[{}]", orders.join(",")))
+                }
+            }
+            Err(err) => error_response(&err),
+        };
+    }
+    let Some(price) = fields.get("price").and_then(|x| positive(x)) else {
+        return Response::error("400 Bad Request", "missing or invalid price");
+    };
+
+    match app.submit_order(symbol, side, quantity, price) {
+        Ok(order) => {
+            println!(">> REST order {}", order.cl_ord_id);
+            Response {
+                status: "201 Created",
+                ..Response::json(order.to_json())
+            }
+        }
+        Err(err) => error_response(&err),
+    }
+}
+
+/// A stop, a take-profit, or both as an OCO pair; their child orders are
+/// market orders unless a `*_limit` price is given, except the
+/// take-profit's, a limit at the target by default
+fn new_stop(app: &BuySideApp, body: &str) -> Response {
+    let Some(fields) = parse_json_object(body) else {
+        return Response::error("400 Bad Request", "body must be a flat JSON object");
+    };
+    let Some(symbol) = fields.get("symbol").filter(|x| !x.is_empty()) else {
+        return Response::error("400 Bad Request", "missing symbol");
+    };
+    let Some(side) = fields
+        .get("side")
+        .and_then(|x| Side::from_name(&x.to_ascii_lowercase()))
+    else {
+        return Response::error("400 Bad Request", "side must be buy or sell");
+    };
+    let Some(quantity) = fields.get("quantity").and_then(|x| positive(x)) else {
+        return Response::error("400 Bad Request", "missing or invalid quantity");
+    };
+    let source = match fields.get("trigger") {
+        Some(name) => match PriceSource::from_name(name) {
+            Some(source) => source,
+            None => return Response::error("400 Bad Request", "trigger must be quote or trade"),
+        },
+        None => PriceSource::Quote,
+    };
+    let mut prices = [None; 4];
+    let keys = ["stop", "stop_limit", "take_profit", "take_profit_limit"];
+    for (price, key) in prices.iter_mut().zip(keys) {
+        if let Some(value) = fields.get(key) {
+            match positive(value) {
+                Some(value) => *price = Some(value),
+                None => {
+                    return Response::error("400 Bad Request", &format!("invalid {key}"));
+                }
+            }
+        }
+    }
+    let [stop, stop_limit, take_profit, take_profit_limit] = prices;
+
+    let stop = stop.map(|trigger| {
+        StopInstruction::stop(symbol, side, quantity, trigger)
+            .with_limit(stop_limit)
+            .with_source(source)
+    });
+    let take_profit = take_profit.map(|target| {
+        let instruction = StopInstruction::take_profit(symbol, side, quantity, target);
+        let limit = take_profit_limit.or(instruction.limit);
+        instruction.with_limit(limit).with_source(source)
+    });
+    let ids = match (stop, take_profit) {
+        (Some(stop), Some(take_profit)) => app
+            .stops
+            .add_oco(stop, take_profit)
+            .map(|(first, second)| vec![first, second]),
+        (Some(instruction), None) | (None, Some(instruction)) => {
+            app.stops.add(instruction).map(|id| vec![id])
+        }
+        (None, None) => {
+            return Response::error("400 Bad Request", "missing stop or take_profit");
+        }
+    };
+    let ids = match ids {
+        Ok(ids) => ids,
+        Err(err) => return stop_error_response(&err),
+    };
+
+    let mut added = app.stops.entries();
+    added.retain(|x| ids.contains(&x.id));
+    for entry in &added {
+        println!(">> REST stop {entry}");
+    }
+    Response {
+        status: "201 Created",
+        ..Response::json(stops_json(&added))
+    }
+}
+
+fn stops_json(entries: &[StopEntry]) -> String {
+    let entries: Vec<_> = entries.iter().map(StopEntry::to_json).collect();
+    format!("[{}]", entries.join(","))
+}
+
+fn stop_error_response(err: &StopError) -> Response {
+    let status = match err {
+        StopError::UnknownStop(_) => "404 Not Found",
+        _ => "422 Unprocessable Entity",
+    };
+    Response::error(status, &err.to_string())
+}
+
+fn positions(app: &BuySideApp) -> Response {
+    let positions: Vec<_> = app
+        .positions
+        .snapshot()
+        .iter()
+        .map(|(symbol, position)| position.to_json(symbol))
+        .collect();
+    Response::json(format!("[{}]", positions.join(",")))
+}
+
+/// Split the fills of the orders listed between the accounts
+fn new_allocation(app: &BuySideApp, body: &str) -> Response {
+    let Some(fields) = parse_json_object(body) else {
+        return Response::error("400 Bad Request", "body must be a flat JSON object");
+    };
+    let Some(cl_ord_ids) = fields.get("cl_ord_ids").filter(|x| !x.is_empty()) else {
+        return Response::error("400 Bad Request", "missing cl_ord_ids");
+    };
+    let cl_ord_ids: Vec<_> = cl_ord_ids.split(',').map(|x| x.trim().to_string()).collect();
+    let allocs = match fields
+        .get("allocs")
+        .map(String::as_str)
+        .map(allocations::parse_shares)
+    {
+        Some(Ok(allocs)) => allocs,
+        Some(Err(err)) => return Response::error("400 Bad Request", &err.to_string()),
+        None => return Response::error("400 Bad Request", "missing allocs"),
+    };
+
+    match app.allocate(&cl_ord_ids, allocs) {
+        Ok(allocation) => {
+            println!(">> REST allocation {}", allocation.instruction.alloc_id);
+            Response {
+                status: "201 Created",
+                ..Response::json(allocation.to_json())
+            }
+        }
+        Err(err) => error_response(&err),
+    }
+}
+
+/// Largest page of the blotter
+const MAX_PAGE_SIZE: usize = 1000;
+
+fn trade_blotter(app: &BuySideApp, request: &Request) -> Response {
+    let mut filter = BlotterFilter::default();
+    for name in ["account", "symbol", "side", "min_qty"] {
+        if let Some(value) = request.query(name) {
+            if let Err(err) = filter.set(name, value) {
+                return Response::error("400 Bad Request", &err.to_string());
+            }
+        }
+    }
+
+    if request.query("format") == Some("csv") {
+        return Response {
+            status: "200 OK",
+            content_type: "text/csv; charset=utf-8",
+            body: blotter::to_csv(&app.blotter.matching(&filter)),
+        };
+    }
+
+    let number = |name, default| match request.query(name) {
+        Some(value) => value.parse::<usize>().ok(),
+        None => Some(default),
+    };
+    let (Some(offset), Some(limit)) = (number("offset", 0), number("limit", DEFAULT_PAGE_SIZE))
+    else {
+        return Response::error("400 Bad Request", "offset and limit must be whole numbers");
+    };
+    let page = app.blotter.page(&filter, offset, limit.min(MAX_PAGE_SIZE));
+    Response::json(page.to_json())
+}
+
+/// A quantity or a price: finite and above zero, so "NaN" and "inf", which
+/// parse as f64, are refused with the rest
+fn positive(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|x| x.is_finite() && *x > 0.0)
+}
+
+fn error_response(err: &OrderError) -> Response {
+    let status = match err {
+        OrderError::Risk(_)
+        | OrderError::Synthetic(_)
+        | OrderError::Conformance(_)
+        | OrderError::Allocation(_) => "422 Unprocessable Entity",
+        OrderError::UnknownOrder => "404 Not Found",
+        OrderError::RateLimited(_) => "429 Too Many Requests",
+        OrderError::TradingDisabled | OrderError::Draining | OrderError::Send(_) => {
+            "503 Service Unavailable"
+        }
+    };
+    Response::error(status, &err.to_string())
+}
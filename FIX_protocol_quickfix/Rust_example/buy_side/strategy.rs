@@ -0,0 +1,125 @@
+// =============================================================================
+// Sample Strategy
+// =============================================================================
+// A deliberately small momentum strategy to show where trading logic plugs
+// into the stack. It watches the top of book for each symbol, keeps a moving
+// average of the mid price and emits order intents:
+//
+// - Mid breaks above the average by `threshold` and we are flat -> buy
+// - Mid breaks below the average by `threshold` and we are long -> sell
+//
+// The strategy never talks to FIX directly: it returns intents which are
+// risk-checked and turned into orders by the application layer.
+//
+// Event-driven strategies also get the headlines of the news feeds
+// (--news), once per symbol they are tagged with, in order with the quotes.
+// =============================================================================
+
+use std::collections::{HashMap, VecDeque};
+
+use trading::{news::NewsEvent, oms::Side};
+
+/// Top of book for one symbol
+#[derive(Debug, Clone, Copy)]
+pub struct Quote {
+    pub bid: f64,
+    pub ask: f64,
+}
+
+impl Quote {
+    pub fn mid(&self) -> f64 {
+        (self.bid + self.ask) / 2.0
+    }
+}
+
+/// What the strategy wants to do; the caller decides if it is allowed
+#[derive(Debug, Clone)]
+pub struct OrderIntent {
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    pub price: f64,
+}
+
+// =============================================================================
+// Strategy Trait
+// =============================================================================
+
+pub trait Strategy: Send {
+    /// Called on every top-of-book update
+    ///
+    /// # Arguments
+    /// * `symbol` - Instrument that changed
+    /// * `quote` - New top of book
+    /// * `position` - Current signed position in the symbol
+    fn on_quote(&mut self, symbol: &str, quote: Quote, position: f64) -> Option<OrderIntent>;
+
+    /// Called for every headline about a traded symbol; ignored by default
+    ///
+    /// # Arguments
+    /// * `symbol` - One of the symbols the headline is tagged with
+    /// * `news` - The headline, with its sentiment if the feed gives one
+    /// * `position` - Current signed position in the symbol
+    fn on_news(&mut self, symbol: &str, news: &NewsEvent, position: f64) -> Option<OrderIntent> {
+        let _ = (symbol, news, position);
+        None
+    }
+}
+
+// =============================================================================
+// MomentumStrategy
+// =============================================================================
+
+pub struct MomentumStrategy {
+    window: usize,
+    threshold: f64,
+    clip_size: f64,
+    history: HashMap<String, VecDeque<f64>>,
+}
+
+impl MomentumStrategy {
+    /// # Arguments
+    /// * `window` - Number of mids in the moving average
+    /// * `threshold` - Relative breakout, e.g. 0.001 = 10bp
+    /// * `clip_size` - Quantity of each order
+    pub fn new(window: usize, threshold: f64, clip_size: f64) -> Self {
+        Self {
+            window,
+            threshold,
+            clip_size,
+            history: HashMap::new(),
+        }
+    }
+}
+
+impl Strategy for MomentumStrategy {
+    fn on_quote(&mut self, symbol: &str, quote: Quote, position: f64) -> Option<OrderIntent> {
+        let history = self.history.entry(symbol.to_string()).or_default();
+        let mid = quote.mid();
+
+        // Not enough history yet: just record
+        if history.len() < self.window {
+            history.push_back(mid);
+            return None;
+        }
+
+        let average = history.iter().sum::<f64>() / history.len() as f64;
+        history.pop_front();
+        history.push_back(mid);
+
+        let (side, price) = if mid > average * (1.0 + self.threshold) && position <= 0.0 {
+            (Side::Buy, quote.ask)
+        } else if mid < average * (1.0 - self.threshold) && position > 0.0 {
+            (Side::Sell, quote.bid)
+        } else {
+            return None;
+        };
+
+        Some(OrderIntent {
+            symbol: symbol.to_string(),
+            side,
+            quantity: self.clip_size,
+            price,
+        })
+    }
+}
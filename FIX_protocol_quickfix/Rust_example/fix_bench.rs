@@ -0,0 +1,171 @@
+// =============================================================================
+// QuickFIX Rust Example: fix_bench - Throughput Benchmark
+// =============================================================================
+// Puts numbers on the threading model choice the other examples make in
+// comments ("SingleThreaded: simpler, MultiThreaded: better performance"). An
+// acceptor and an initiator run in-process; the initiator pumps
+// NewOrderSingles, the acceptor answers each with an ExecutionReport, and
+// every round trip is timed (trading::bench). By default the same load runs
+// once per socket kind and the two are compared:
+//
+//   sockets          round trips/s   msgs/s   p50   p99   p99.9   max
+//   single-threaded  ...
+//   multi-threaded   ...
+//
+// The multi-threaded server gives each session its own thread; with the one
+// session measured here, that is mostly extra thread hand-offs, and its gain
+// with many sessions is not modelled. Run on the target hardware, with the
+// store used in production.
+//
+// Key Learning Points:
+// 1. SingleThreaded vs MultiThreaded socket servers, measured
+// 2. What a file message store costs per message compared to memory
+// 3. Throughput vs latency: a deeper in-flight window raises the first and
+//    the second with it
+// =============================================================================
+
+use std::{env, process::exit, time::Duration};
+
+use trading::bench::{run_throughput, Load, StoreKind, ThroughputOptions, ThroughputReport};
+
+/// Round trips measured when neither a count nor --duration is given
+const DEFAULT_ROUND_TRIPS: u64 = 100_000;
+
+/// Round trips sent before measuring, when --warmup is not given
+const DEFAULT_WARMUP: u64 = 1_000;
+
+const USAGE: &str = "usage: fix_bench [ROUND_TRIPS] [--duration SECS] [--store memory|file] \
+                     [--sockets single|multi|both] [--in-flight N] [--warmup N]";
+
+// =============================================================================
+// Main Entry Point
+// =============================================================================
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut options = ThroughputOptions {
+        load: Load::RoundTrips(DEFAULT_ROUND_TRIPS),
+        warmup: DEFAULT_WARMUP,
+        ..ThroughputOptions::default()
+    };
+    let mut kinds = vec![false, true];
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("{arg} requires a value")))
+        };
+        match arg.as_str() {
+            "--duration" => {
+                let seconds = parse_count(&arg, &value());
+                options.load = Load::Duration(Duration::from_secs(seconds));
+            }
+            "--store" => {
+                let store = value();
+                options.store = StoreKind::from_name(&store)
+                    .unwrap_or_else(|| fail(&format!("--store must be memory or file: {store}")));
+            }
+            "--sockets" => {
+                kinds = match value().as_str() {
+                    "single" => vec![false],
+                    "multi" => vec![true],
+                    "both" => vec![false, true],
+                    other => fail(&format!("--sockets must be single, multi or both: {other}")),
+                };
+            }
+            "--in-flight" => options.max_in_flight = parse_count(&arg, &value()),
+            "--warmup" => {
+                let warmup = value();
+                options.warmup = warmup
+                    .parse()
+                    .unwrap_or_else(|_| fail(&format!("invalid --warmup value: {warmup}")));
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            flag if flag.starts_with('-') => fail(&format!("unknown option {flag}")),
+            count => options.load = Load::RoundTrips(parse_count("ROUND_TRIPS", count)),
+        }
+    }
+
+    let mut reports = Vec::new();
+    for multi_threaded in kinds {
+        let options = ThroughputOptions {
+            multi_threaded,
+            ..options.clone()
+        };
+        println!(
+            ">> {} sockets, {:?} store, {}...",
+            options.sockets(),
+            options.store,
+            options.load
+        );
+        match run_throughput(&options) {
+            Ok(report) => {
+                report.print();
+                reports.push(report);
+            }
+            Err(err) => {
+                eprintln!("Benchmark failed ({} sockets): {err}", options.sockets());
+                exit(1);
+            }
+        }
+    }
+
+    if reports.len() > 1 {
+        print_comparison(&reports);
+    }
+}
+
+/// One line per run, side by side
+fn print_comparison(reports: &[ThroughputReport]) {
+    println!();
+    println!(
+        "{:<16} {:>14} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "sockets", "round trips/s", "msgs/s", "p50", "p99", "p99.9", "max"
+    );
+    for report in reports {
+        let rate = report.round_trips_per_sec();
+        println!(
+            "{:<16} {:>14.0} {:>10.0} {:>10} {:>10} {:>10} {:>10}",
+            report.options.sockets(),
+            rate,
+            rate * 2.0,
+            format!("{:?}", report.percentile(0.5)),
+            format!("{:?}", report.percentile(0.99)),
+            format!("{:?}", report.percentile(0.999)),
+            format!("{:?}", report.percentile(1.0)),
+        );
+    }
+}
+
+/// A positive integer, or exit with the usage
+fn parse_count(name: &str, value: &str) -> u64 {
+    value
+        .parse()
+        .ok()
+        .filter(|x| *x > 0)
+        .unwrap_or_else(|| fail(&format!("{name} must be a positive integer: {value}")))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    eprintln!("{USAGE}");
+    exit(2);
+}
+
+// =============================================================================
+// Usage Examples
+// =============================================================================
+//
+// 100,000 round trips, single then multi-threaded sockets, memory store:
+//   cargo run --release --example fix_bench
+//
+// Ten seconds each, against the file store:
+//   cargo run --release --example fix_bench -- --duration 10 --store file
+//
+// Latency with one order at a time, multi-threaded sockets only:
+//   cargo run --release --example fix_bench -- 20000 --in-flight 1 --sockets multi
+//
+// =============================================================================
@@ -0,0 +1,88 @@
+// =============================================================================
+// Session Blocks From the Configuration File
+// =============================================================================
+// SessionSettings loads the file for the engine, but the quickfix crate does
+// not list the sessions it found. The router needs that list to register one
+// handler per counterparty, so this module reads the [SESSION] blocks itself.
+//
+// Every block inherits the [DEFAULT] values, like QuickFIX does. Keys the
+// engine does not know (MaxOrderQty, MaxNotional) are ignored by QuickFIX and
+// carry the per-counterparty risk limits:
+//
+//   [SESSION]
+//   BeginString=FIX.4.4
+//   SenderCompID=ACCEPTOR
+//   TargetCompID=FUND_A
+//   MaxOrderQty=10000
+//   MaxNotional=1000000
+// =============================================================================
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use quickfix::{QuickFixError, SessionId};
+
+/// One [SESSION] block, [DEFAULT] values included
+#[derive(Debug, Clone)]
+pub struct SessionBlock {
+    values: HashMap<String, String>,
+}
+
+impl SessionBlock {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Counterparty CompID, used to name the route
+    pub fn target_comp_id(&self) -> &str {
+        self.get("TargetCompID").unwrap_or_default()
+    }
+
+    /// Identifier of the session this block configures
+    pub fn session_id(&self) -> Result<SessionId, QuickFixError> {
+        SessionId::try_new(
+            self.get("BeginString").unwrap_or_default(),
+            self.get("SenderCompID").unwrap_or_default(),
+            self.target_comp_id(),
+            self.get("SessionQualifier").unwrap_or_default(),
+        )
+    }
+}
+
+/// Read every [SESSION] block of a QuickFIX configuration file
+pub fn session_blocks<P: AsRef<Path>>(path: P) -> io::Result<Vec<SessionBlock>> {
+    let text = fs::read_to_string(path)?;
+
+    let mut defaults = HashMap::new();
+    let mut sessions: Vec<HashMap<String, String>> = Vec::new();
+    let mut in_session = false;
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') {
+            in_session = line.eq_ignore_ascii_case("[SESSION]");
+            if in_session {
+                sessions.push(HashMap::new());
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_string(), value.trim().to_string());
+        match sessions.last_mut() {
+            Some(session) if in_session => session.insert(key, value),
+            _ => defaults.insert(key, value),
+        };
+    }
+
+    Ok(sessions
+        .into_iter()
+        .map(|session| {
+            let mut values = defaults.clone();
+            values.extend(session);
+            SessionBlock { values }
+        })
+        .collect())
+}
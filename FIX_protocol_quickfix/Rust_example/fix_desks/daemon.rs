@@ -0,0 +1,224 @@
+// =============================================================================
+// Daemon Mode
+// =============================================================================
+// With --daemon the acceptor runs without a terminal, as a service:
+//
+//   fix_desks acceptor.cfg --daemon --pid-file run/acceptor.pid \
+//       --log-file logs/acceptor.log --log-keep 7 --log-max-mb 100
+//
+// The process started from the shell (or by systemd) only launches a copy of
+// itself and exits: the copy runs in a process group of its own, so the
+// terminal's CTRL-C and hangup do not reach it, with stdin at /dev/null and
+// stdout and stderr appended to the log file, the engine's output included.
+// The launcher writes the copy's PID to the PID file before exiting, which
+// is what a systemd unit of Type=forking waits for:
+//
+//   [Service]
+//   Type=forking
+//   WorkingDirectory=/srv/fix
+//   PIDFile=/srv/fix/run/acceptor.pid
+//   ExecStart=/srv/fix/fix_desks acceptor.cfg --daemon \
+//       --pid-file /srv/fix/run/acceptor.pid --log-file /srv/fix/logs/acceptor.log
+//   ExecReload=/bin/kill -HUP $MAINPID
+//
+// The daemon stops cleanly (logouts sent, stores flushed) on SIGTERM or
+// SIGINT and removes its PID file. SIGHUP rotates the log: LOG becomes
+// LOG.1, LOG.1 becomes LOG.2 and so on up to --log-keep files (default 5),
+// the oldest dropped; with --log-max-mb the log is also rotated once it
+// grows past that size. The process keeps writing through the descriptors
+// it was started with, so rotating is copy-then-truncate: a line written in
+// between can be lost. The working directory is kept, for the relative
+// paths of the config file.
+// =============================================================================
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use trading::session::runtime::shutdown_signal;
+
+/// Set in the environment of the detached copy
+const DETACHED_ENV: &str = "FIX_DESKS_DETACHED";
+
+/// Defaults of --pid-file, --log-file and --log-keep
+const DEFAULT_PID_FILE: &str = "fix_desks.pid";
+const DEFAULT_LOG_FILE: &str = "fix_desks.log";
+const DEFAULT_LOG_KEEP: usize = 5;
+
+/// How often the size of the log is checked, with --log-max-mb
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Where the daemon writes, from the command line
+#[derive(Debug, Clone)]
+pub struct DaemonOptions {
+    pub pid_file: PathBuf,
+    pub log_file: PathBuf,
+
+    /// Rotated logs kept next to the current one
+    pub log_keep: usize,
+
+    /// Size past which the log is rotated, if any
+    pub log_max_bytes: Option<u64>,
+}
+
+impl DaemonOptions {
+    /// The options of --daemon, or None without it
+    ///
+    /// # Returns
+    /// A message for the user when a value is missing or invalid
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        if !args.iter().any(|x| x == "--daemon") {
+            return Ok(None);
+        }
+        if cfg!(not(unix)) {
+            return Err("--daemon is only available on Unix".to_string());
+        }
+        let value = |flag: &str| match args.iter().position(|x| x == flag) {
+            Some(index) => match args.get(index + 1) {
+                Some(value) => Ok(Some(value.as_str())),
+                None => Err(format!("{flag} requires a value")),
+            },
+            None => Ok(None),
+        };
+
+        let log_keep = match value("--log-keep")? {
+            Some(keep) => keep
+                .parse()
+                .map_err(|_| "--log-keep requires a number of files".to_string())?,
+            None => DEFAULT_LOG_KEEP,
+        };
+        let log_max_bytes = match value("--log-max-mb")? {
+            Some(size) => match size.parse::<u64>() {
+                Ok(megabytes) if megabytes > 0 => Some(megabytes * 1024 * 1024),
+                _ => return Err("--log-max-mb requires a positive size".to_string()),
+            },
+            None => None,
+        };
+        Ok(Some(Self {
+            pid_file: PathBuf::from(value("--pid-file")?.unwrap_or(DEFAULT_PID_FILE)),
+            log_file: PathBuf::from(value("--log-file")?.unwrap_or(DEFAULT_LOG_FILE)),
+            log_keep,
+            log_max_bytes,
+        }))
+    }
+
+    /// Remove the PID file on the way out; it may be gone already
+    pub fn remove_pid_file(&self) {
+        if let Err(err) = fs::remove_file(&self.pid_file) {
+            if err.kind() != io::ErrorKind::NotFound {
+                eprintln!("Cannot remove {}: {err}", self.pid_file.display());
+            }
+        }
+    }
+}
+
+/// Whether this process is the detached copy
+pub fn is_detached() -> bool {
+    env::var_os(DETACHED_ENV).is_some()
+}
+
+/// Start the detached copy of this process, with the same arguments, and
+/// write its PID to the PID file
+///
+/// # Returns
+/// The PID of the copy
+pub fn detach(options: &DaemonOptions) -> io::Result<u32> {
+    let log = open_log(&options.log_file)?;
+    create_parent(&options.pid_file)?;
+    let mut command = Command::new(env::current_exe()?);
+    command
+        .args(env::args_os().skip(1))
+        .env(DETACHED_ENV, "1")
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let child = command.spawn()?;
+    fs::write(&options.pid_file, format!("{}\n", child.id()))?;
+    Ok(child.id())
+}
+
+/// Block until SIGTERM or SIGINT, rotating the log on SIGHUP and when it
+/// outgrows --log-max-mb
+pub fn wait_for_signals(options: &DaemonOptions) -> io::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(async {
+        #[cfg(unix)]
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+        let mut size_check = tokio::time::interval(SIZE_CHECK_INTERVAL);
+        let shutdown = shutdown_signal();
+        tokio::pin!(shutdown);
+
+        loop {
+            #[cfg(unix)]
+            let rotate = tokio::select! {
+                () = &mut shutdown => return Ok(()),
+                _ = hangup.recv() => true,
+                _ = size_check.tick() => outgrown(options),
+            };
+            #[cfg(not(unix))]
+            let rotate = tokio::select! {
+                () = &mut shutdown => return Ok(()),
+                _ = size_check.tick() => outgrown(options),
+            };
+            if rotate {
+                match rotate_log(&options.log_file, options.log_keep) {
+                    Ok(()) => println!(">> Log rotated"),
+                    Err(err) => eprintln!("Cannot rotate {}: {err}", options.log_file.display()),
+                }
+            }
+        }
+    })
+}
+
+/// The log file, created if needed, opened for appending
+fn open_log(path: &Path) -> io::Result<File> {
+    create_parent(path)?;
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// The directory of a file, if it has one and it is missing
+fn create_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent),
+        _ => Ok(()),
+    }
+}
+
+fn outgrown(options: &DaemonOptions) -> bool {
+    options.log_max_bytes.is_some_and(|max| {
+        fs::metadata(&options.log_file).is_ok_and(|metadata| metadata.len() > max)
+    })
+}
+
+/// Shift LOG.1.. up by one, the oldest dropped, copy LOG to LOG.1 and
+/// empty LOG; without any file kept, LOG is only emptied
+fn rotate_log(path: &Path, keep: usize) -> io::Result<()> {
+    let numbered = |index: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{index}"));
+        PathBuf::from(name)
+    };
+    if keep > 0 {
+        for index in (1..keep).rev() {
+            match fs::rename(numbered(index), numbered(index + 1)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        fs::copy(path, numbered(1))?;
+    }
+    // Writers opened in append mode go on at the new end of the file
+    OpenOptions::new().write(true).open(path)?.set_len(0)
+}
@@ -0,0 +1,233 @@
+// =============================================================================
+// Desk: SessionHandler of One Counterparty
+// =============================================================================
+// A deliberately small order handler, one instance per counterparty:
+//
+//   35=D NewOrderSingle     -> risk check -> book it, ack (ExecutionReport New)
+//                                        \-> ExecutionReport Rejected + Text
+//   35=F OrderCancelRequest -> remove from book, ExecutionReport Canceled
+//
+// Nothing matches: the point is that limits and books are per counterparty,
+// so FUND_A hitting its limit has no effect on FUND_B.
+// =============================================================================
+
+use std::collections::BTreeMap;
+
+use quickfix::{send_to_target, FieldMap, Message, MsgFromAppError, QuickFixError, SessionId};
+
+use crate::{config::SessionBlock, router::SessionHandler};
+
+// =============================================================================
+// Risk Limits
+// =============================================================================
+
+#[derive(Debug, Clone, Copy)]
+pub struct RiskLimits {
+    /// Largest OrderQty (38) accepted
+    pub max_order_qty: f64,
+
+    /// Largest OrderQty x Price accepted
+    pub max_notional: f64,
+}
+
+impl RiskLimits {
+    /// Limits of a [SESSION] block (MaxOrderQty, MaxNotional), unlimited if unset
+    pub fn from_block(block: &SessionBlock) -> Self {
+        let limit = |key| {
+            block
+                .get(key)
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(f64::INFINITY)
+        };
+        Self {
+            max_order_qty: limit("MaxOrderQty"),
+            max_notional: limit("MaxNotional"),
+        }
+    }
+
+    /// Reason for refusing an order, if any
+    fn check(&self, quantity: f64, price: f64) -> Option<String> {
+        if quantity <= 0.0 {
+            Some("quantity must be positive".to_string())
+        } else if quantity > self.max_order_qty {
+            Some(format!(
+                "quantity {quantity} above limit {}",
+                self.max_order_qty
+            ))
+        } else if quantity * price > self.max_notional {
+            Some(format!(
+                "notional {} above limit {}",
+                quantity * price,
+                self.max_notional
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+// =============================================================================
+// Desk
+// =============================================================================
+
+#[derive(Debug, Clone)]
+struct BookOrder {
+    order_id: String,
+    symbol: String,
+    side: String,
+    quantity: f64,
+}
+
+pub struct Desk {
+    name: String,
+    limits: RiskLimits,
+
+    /// Resting orders by ClOrdID
+    book: BTreeMap<String, BookOrder>,
+
+    /// Sequence for OrderID / ExecID
+    next_id: u64,
+}
+
+impl Desk {
+    pub fn new(name: &str, limits: RiskLimits) -> Self {
+        Self {
+            name: name.to_string(),
+            limits,
+            book: BTreeMap::new(),
+            next_id: 1,
+        }
+    }
+
+    fn next_id(&mut self, prefix: &str) -> String {
+        let id = self.next_id;
+        self.next_id += 1;
+        format!("{}-{prefix}{id}", self.name)
+    }
+
+    fn on_new_order(&mut self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        let cl_ord_id = msg.get_field(11).ok_or(MsgFromAppError::FieldNotFound)?;
+        let symbol = msg.get_field(55).ok_or(MsgFromAppError::FieldNotFound)?;
+        let side = msg.get_field(54).ok_or(MsgFromAppError::FieldNotFound)?;
+        let quantity: f64 = msg
+            .get_field(38)
+            .ok_or(MsgFromAppError::FieldNotFound)?
+            .parse()
+            .map_err(|_| MsgFromAppError::IncorrectDataFormat)?;
+        // Market orders have no price: only the quantity limit applies
+        let price: f64 = msg
+            .get_field(44)
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(0.0);
+
+        let order = BookOrder {
+            order_id: self.next_id("O"),
+            symbol,
+            side,
+            quantity,
+        };
+
+        let exec_id = self.next_id("E");
+        let report = match self.limits.check(quantity, price) {
+            Some(reason) => {
+                println!(">> [{}] reject {cl_ord_id}: {reason}", self.name);
+                execution_report(&order, &cl_ord_id, &exec_id, "8", Some(&reason))
+            }
+            None => {
+                println!(
+                    ">> [{}] accept {cl_ord_id} {} {quantity}@{price}",
+                    self.name, order.symbol
+                );
+                let report = execution_report(&order, &cl_ord_id, &exec_id, "0", None);
+                self.book.insert(cl_ord_id, order);
+                report
+            }
+        };
+        send(report, session);
+        Ok(())
+    }
+
+    fn on_cancel(&mut self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        let cl_ord_id = msg.get_field(11).ok_or(MsgFromAppError::FieldNotFound)?;
+        let orig_cl_ord_id = msg.get_field(41).ok_or(MsgFromAppError::FieldNotFound)?;
+
+        let Some(order) = self.book.remove(&orig_cl_ord_id) else {
+            // Unknown or already gone: a production desk would answer with
+            // an OrderCancelReject (35=9)
+            println!(
+                ">> [{}] cancel of unknown order {orig_cl_ord_id}",
+                self.name
+            );
+            return Ok(());
+        };
+
+        println!(">> [{}] cancel {orig_cl_ord_id}", self.name);
+        let exec_id = self.next_id("E");
+        let report = execution_report(&order, &cl_ord_id, &exec_id, "4", None).and_then(|mut x| {
+            x.set_field(41, orig_cl_ord_id.as_str())?; // OrigClOrdID
+            Ok(x)
+        });
+        send(report, session);
+        Ok(())
+    }
+}
+
+impl SessionHandler for Desk {
+    fn on_logout(&mut self, _session: &SessionId) {
+        // Orders stay booked across reconnects, as on most venues
+        println!(">> [{}] {} resting order(s)", self.name, self.book.len());
+    }
+
+    fn on_message(&mut self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        let msg_type = msg
+            .with_header(|h| h.get_field(35))
+            .ok_or(MsgFromAppError::FieldNotFound)?;
+        match msg_type.as_str() {
+            "D" => self.on_new_order(msg, session),
+            "F" => self.on_cancel(msg, session),
+            _ => Err(MsgFromAppError::UnsupportedMessageType),
+        }
+    }
+}
+
+// =============================================================================
+// Outgoing Messages
+// =============================================================================
+
+/// Build an ExecutionReport (35=8) with ExecType = OrdStatus = `status`
+///
+/// `status` is one of 0 (New), 4 (Canceled) or 8 (Rejected); none of them
+/// carries a fill, so LeavesQty is the whole order only while it is New.
+fn execution_report(
+    order: &BookOrder,
+    cl_ord_id: &str,
+    exec_id: &str,
+    status: &str,
+    text: Option<&str>,
+) -> Result<Message, QuickFixError> {
+    let leaves_qty = if status == "0" { order.quantity } else { 0.0 };
+
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "8"))?;
+    msg.set_field(37, order.order_id.as_str())?; // OrderID
+    msg.set_field(11, cl_ord_id)?; // ClOrdID
+    msg.set_field(17, exec_id)?; // ExecID
+    msg.set_field(150, status)?; // ExecType
+    msg.set_field(39, status)?; // OrdStatus
+    msg.set_field(55, order.symbol.as_str())?;
+    msg.set_field(54, order.side.as_str())?;
+    msg.set_field(38, order.quantity.to_string().as_str())?;
+    msg.set_field(151, leaves_qty.to_string().as_str())?; // LeavesQty
+    msg.set_field(14, "0")?; // CumQty
+    msg.set_field(6, "0")?; // AvgPx
+    if let Some(text) = text {
+        msg.set_field(58, text)?; // Text
+    }
+    Ok(msg)
+}
+
+fn send(report: Result<Message, QuickFixError>, session: &SessionId) {
+    if let Err(err) = report.and_then(|x| send_to_target(x, session)) {
+        eprintln!("Cannot send execution report: {err:?}");
+    }
+}
@@ -0,0 +1,352 @@
+// =============================================================================
+// QuickFIX Rust Example: fix_desks - Per-Counterparty Acceptor
+// =============================================================================
+// One acceptor serves every [SESSION] block of a configuration file, as
+// fix_getting_started does, but callbacks go through a router that hands
+// each session's messages to its own handler (risk limits, order book),
+// instead of one global callback doing everything.
+//
+// Key Learning Points:
+// 1. Routing callbacks per counterparty (SessionHandler, router.rs)
+// 2. Per-counterparty settings read from the [SESSION] blocks (config.rs)
+// 3. Running as a service (--daemon): detached, with a PID file and a log
+//    file rotated on SIGHUP (daemon.rs)
+// 4. Printing off the engine's threads (--log-callbacks): every callback
+//    copied into a ring per session and written by a thread of its own,
+//    dropped and counted when a burst fills the ring (--log-ring <records>)
+// =============================================================================
+
+use std::{
+    env,      // For reading command-line arguments
+    io::{stdin, stdout, Read}, // For user input handling
+    process::exit,     // For exiting with error codes
+};
+
+// Import all QuickFIX types - wildcard import for convenience
+// In production code, you might prefer explicit imports
+use quickfix::*;
+use trading::session::callback_log::{CallbackLog, DEFAULT_CAPACITY};
+
+use crate::{
+    config::session_blocks, // [SESSION] blocks of the configuration file
+    daemon::DaemonOptions,  // --daemon, --pid-file, --log-file
+    desk::{Desk, RiskLimits}, // Per-counterparty order handler
+    router::SessionRouter,  // Dispatches callbacks by session
+};
+
+mod config; // Reads the [SESSION] blocks
+mod daemon; // Detached run, PID file and log rotation
+mod desk;   // SessionHandler with its own risk limits and book
+mod router; // SessionHandler trait and SessionRouter
+
+// =============================================================================
+// Application Implementation
+// =============================================================================
+// The application callback is a SessionRouter (router.rs): it owns one
+// SessionHandler per counterparty and forwards on_logon, on_logout and
+// on_msg_from_app to the handler of the session they concern.
+//
+// In a real trading system each handler would hold that counterparty's
+// business logic: risk limits, order book, reference data, and so on.
+// =============================================================================
+
+// =============================================================================
+// Main Entry Point
+// =============================================================================
+// Demonstrates loading configuration from a file and running an acceptor
+// =============================================================================
+
+fn main() -> Result<(), QuickFixError> {
+    // =========================================================================
+    // Step 1: Parse Command-Line Arguments
+    // =========================================================================
+    // This acceptor requires a configuration file path as an argument
+    // =========================================================================
+    
+    let args: Vec<_> = env::args().collect();
+    
+    // Check if config file was provided
+    // Pattern matching with Some/None for safe unwrapping
+    let Some(config_file) = args.get(1) else {
+        // If no config file provided, print usage and exit with error code
+        eprintln!("Bad program usage: {} <config_file> [--daemon [--pid-file <file>] [--log-file <file>] [--log-keep <n>] [--log-max-mb <mb>]] [--log-callbacks [--log-ring <records>]]", args[0]);
+        exit(1);
+    };
+
+    // With --daemon, the process started from the shell launches a detached
+    // copy of itself and exits; the copy goes on below, writing to the log
+    let daemon = DaemonOptions::from_args(&args).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        exit(1);
+    });
+    if let Some(options) = daemon.as_ref().filter(|_| !daemon::is_detached()) {
+        match daemon::detach(options) {
+            Ok(pid) => {
+                println!(
+                    ">> Daemon started, pid {pid}, logging to {}",
+                    options.log_file.display()
+                );
+                return Ok(());
+            }
+            Err(err) => {
+                eprintln!("Cannot start the daemon: {err}");
+                exit(1);
+            }
+        }
+    }
+
+    // =========================================================================
+    // Step 2: Create FIX Engine Components
+    // =========================================================================
+    // Build all the components needed to run a FIX acceptor
+    // =========================================================================
+    
+    println!(">> Creating resources");
+    
+    // Load session settings from the configuration file
+    // The file should be in INI format with [DEFAULT] and [SESSION] sections
+    // Example config file format:
+    //   [DEFAULT]
+    //   ConnectionType=acceptor
+    //   FileStorePath=/var/log/fix
+    //   
+    //   [SESSION]
+    //   BeginString=FIX.4.4
+    //   SenderCompID=SERVER
+    //   TargetCompID=CLIENT
+    //   SocketAcceptPort=5000
+    let settings = SessionSettings::try_from_path(config_file)?;
+
+    // The crate does not list the sessions it loaded, so read the [SESSION]
+    // blocks again to register one handler per counterparty
+    let blocks = session_blocks(config_file).unwrap_or_else(|err| {
+        eprintln!("Cannot read {config_file}: {err}");
+        exit(1);
+    });
+    
+    // Create a file-based message store
+    // This persists:
+    // - All sent and received messages (for regulatory compliance)
+    // - Sequence numbers (for recovery after restart)
+    // - Session state
+    // 
+    // Critical for production: ensures exactly-once message delivery
+    // even after crashes or restarts
+    let store_factory = FileMessageStoreFactory::try_new(&settings)?;
+    
+    // Create a logger that outputs to stdout
+    // In production, you would typically log to files with rotation
+    // Alternatives:
+    // - StdLogger::Stderr for error output
+    // - Custom logger implementation for structured logging
+    let log_factory = LogFactory::try_new(&StdLogger::Stdout)?;
+    
+    // One Desk per [SESSION] block, each with the limits of its block
+    let mut callbacks = SessionRouter::new();
+    for block in &blocks {
+        let name = block.target_comp_id();
+        let limits = RiskLimits::from_block(block);
+        println!(
+            ">> Route {name}: max qty {}, max notional {}",
+            limits.max_order_qty, limits.max_notional
+        );
+        callbacks.add(&block.session_id()?, name, Box::new(Desk::new(name, limits)));
+    }
+    println!(">> {} session(s) routed", callbacks.len());
+
+    // Callbacks written by a thread of their own instead of printed by the
+    // engine's (trading::session::callback_log)
+    let callback_log = callback_log(&args);
+    if let Some(log) = &callback_log {
+        callbacks.with_log(log);
+    }
+    
+    // Wrap our callbacks in a QuickFIX Application object
+    // This bridges our Rust code with the underlying C++ QuickFIX engine
+    let app = Application::try_new(&callbacks)?;
+
+    // =========================================================================
+    // Step 3: Create and Configure the Acceptor
+    // =========================================================================
+    // The acceptor is the core component that manages FIX sessions
+    // =========================================================================
+    
+    let mut acceptor = Acceptor::try_new(
+        &settings,      // All session configuration loaded from file
+        &app,           // Our application callback handlers
+        &store_factory, // Persistent message storage
+        &log_factory,   // Logging destination
+        FixSocketServerKind::SingleThreaded, // Threading model
+        // SingleThreaded: Simpler, easier to debug
+        // MultiThreaded: Better performance, handles multiple sessions concurrently
+    )?;
+
+    // =========================================================================
+    // Step 4: Start the Acceptor
+    // =========================================================================
+    // Begin listening for incoming connections and processing messages
+    // =========================================================================
+    
+    println!(">> connection handler START");
+    acceptor.start()?;
+    // At this point:
+    // - TCP socket is listening on the configured port
+    // - Ready to accept incoming FIX connections
+    // - Will validate logon messages against configured sessions
+    // - Heartbeat monitoring is active
+
+    // =========================================================================
+    // Step 5: Run Until User Quits
+    // =========================================================================
+    // Keep the acceptor running in a simple input loop
+    // As a daemon, there is no user: run until SIGTERM, rotating the log on
+    // SIGHUP
+    // =========================================================================
+    
+    if let Some(options) = &daemon {
+        println!(">> App running as a daemon, SIGTERM to stop, SIGHUP to rotate the log");
+        if let Err(err) = daemon::wait_for_signals(options) {
+            eprintln!("Cannot wait for signals: {err}");
+        }
+    } else {
+        println!(">> App running, press 'q' to quit");
+        
+        let mut stdin = stdin().lock();
+        let mut stdin_buf = [0];
+        
+        loop {
+            // Blocking read - wait for user input
+            let _ = stdin.read_exact(&mut stdin_buf);
+            
+            // Check if user wants to quit
+            if stdin_buf[0] == b'q' {
+                break;
+            }
+            // Any other key is ignored - acceptor keeps running
+        }
+    }
+
+    // =========================================================================
+    // Step 6: Graceful Shutdown
+    // =========================================================================
+    // Properly close all sessions before exiting
+    // =========================================================================
+    
+    println!(">> connection handler STOP");
+    acceptor.stop()?;
+    // This will:
+    // - Send logout messages to all connected counterparties
+    // - Flush all pending messages to disk
+    // - Close TCP connections
+    // - Save sequence numbers for recovery
+
+    // Written to the end: the sessions are closed, nothing pushes anymore
+    if let Some(log) = callback_log {
+        match log.stop() {
+            Ok(0) => {}
+            Ok(dropped) => println!(">> {dropped} callback record(s) dropped"),
+            Err(err) => eprintln!("Cannot write the callback log: {err}"),
+        }
+    }
+
+    if let Some(options) = &daemon {
+        options.remove_pid_file();
+    }
+    println!(">> All cleared. Bye !");
+    Ok(())
+}
+
+/// The callback log asked for with --log-callbacks, writing to stdout (the
+/// log file as a daemon)
+fn callback_log(args: &[String]) -> Option<CallbackLog> {
+    if !args.iter().any(|x| x == "--log-callbacks") {
+        return None;
+    }
+    let capacity = match args.iter().position(|x| x == "--log-ring") {
+        Some(index) => match args.get(index + 1).and_then(|x| x.parse().ok()) {
+            Some(records) if records > 0 => records,
+            _ => {
+                eprintln!("--log-ring requires a positive number of records");
+                exit(1);
+            }
+        },
+        None => DEFAULT_CAPACITY,
+    };
+    match CallbackLog::with_capacity(stdout(), capacity) {
+        Ok(log) => Some(log),
+        Err(err) => {
+            eprintln!("Cannot start the callback log: {err}");
+            exit(1);
+        }
+    }
+}
+
+// =============================================================================
+// Example Configuration File
+// =============================================================================
+// Save this as 'acceptor.cfg' and run:
+//   cargo run --example fix_desks -- acceptor.cfg
+//
+// Or as a daemon, then rotate its log and stop it:
+//   cargo run --example fix_desks -- acceptor.cfg --daemon --log-file logs/acceptor.log
+//   kill -HUP $(cat fix_desks.pid)
+//   kill $(cat fix_desks.pid)
+//
+// [DEFAULT]
+// ConnectionType=acceptor
+// ReconnectInterval=60
+// FileStorePath=./fixdata
+// FileLogPath=./fixlog
+// StartTime=00:00:00
+// EndTime=00:00:00
+// UseDataDictionary=Y
+// ValidateFieldsOutOfOrder=N
+// 
+// [SESSION]
+// BeginString=FIX.4.4
+// SenderCompID=ACCEPTOR
+// TargetCompID=FUND_A
+// HeartBtInt=30
+// SocketAcceptPort=5001
+// DataDictionary=spec/FIX44.xml
+// MaxOrderQty=10000
+// MaxNotional=1000000
+// 
+// [SESSION]
+// BeginString=FIX.4.4
+// SenderCompID=ACCEPTOR
+// TargetCompID=FUND_B
+// HeartBtInt=30
+// SocketAcceptPort=5001
+// DataDictionary=spec/FIX44.xml
+// MaxOrderQty=500
+// 
+// =============================================================================
+// Configuration Parameters Explained
+// =============================================================================
+// 
+// [DEFAULT] Section - Applies to all sessions:
+// 
+// ConnectionType: acceptor (server) or initiator (client)
+// ReconnectInterval: Seconds to wait between reconnection attempts
+// FileStorePath: Directory for message store files
+// FileLogPath: Directory for log files
+// StartTime/EndTime: Session hours (00:00:00 = 24/7 operation)
+// UseDataDictionary: Enable message validation
+// ValidateFieldsOutOfOrder: Strict field ordering validation
+// 
+// [SESSION] Section - Specific to each trading counterparty:
+// 
+// BeginString: FIX protocol version (FIX.4.0, FIX.4.2, FIX.4.4, FIXT.1.1)
+// SenderCompID: Our identifier (must be unique)
+// TargetCompID: Counterparty identifier
+// HeartBtInt: Heartbeat interval in seconds
+// SocketAcceptPort: TCP port to listen on
+// DataDictionary: Path to FIX dictionary XML file
+// MaxOrderQty / MaxNotional: Risk limits of this counterparty (read by this
+//   example, ignored by QuickFIX); no limit when absent
+// 
+// You can have multiple [SESSION] sections for different counterparties, on
+// the same port: the acceptor tells them apart by CompIDs at logon, and the
+// router sends each one's messages to its own handler
+// =============================================================================
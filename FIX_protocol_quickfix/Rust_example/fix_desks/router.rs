@@ -0,0 +1,183 @@
+// =============================================================================
+// Per-Session Routing
+// =============================================================================
+// One acceptor serves every [SESSION] block of the configuration file, and
+// QuickFIX calls a single ApplicationCallback for all of them. Instead of one
+// global callback doing everything, SessionRouter dispatches each callback to
+// the SessionHandler registered for that session:
+//
+//   QuickFIX --on_msg_from_app(msg, session)--> SessionRouter
+//                                                 |-- FUND_A -> Desk (limits A, book A)
+//                                                 |-- FUND_B -> Desk (limits B, book B)
+//                                                 \-- unknown -> rejected
+//
+// Each handler owns its state (risk limits, order book) and never sees the
+// traffic of other counterparties.
+//
+// With a callback log (--log-callbacks), every callback of a route, messages
+// included, goes to its ring in the log (trading::session::callback_log)
+// instead of being printed on the engine's thread.
+// =============================================================================
+
+use std::{collections::HashMap, sync::Mutex};
+
+use quickfix::{
+    ApplicationCallback, Message, MsgFromAdminError, MsgFromAppError, MsgToAppError, SessionId,
+};
+use trading::session::callback_log::{Callback, CallbackLog, Producer, Record};
+
+// =============================================================================
+// SessionHandler: Business Logic of One Counterparty
+// =============================================================================
+// Handlers get &mut self: the router serializes the calls of each session, so
+// a handler needs no locking of its own. Send because QuickFIX may call from
+// any of its threads.
+// =============================================================================
+
+#[allow(unused_variables)]
+pub trait SessionHandler: Send {
+    /// Counterparty logged on
+    fn on_logon(&mut self, session: &SessionId) {}
+
+    /// Counterparty logged out (or the connection dropped)
+    fn on_logout(&mut self, session: &SessionId) {}
+
+    /// Application message received from the counterparty
+    fn on_message(&mut self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError>;
+}
+
+// =============================================================================
+// SessionRouter
+// =============================================================================
+// Routes are keyed by the session's string form: SessionId has no Eq / Hash.
+// They are all registered before the engine starts, so the map itself is
+// never modified while callbacks run; only each handler is locked.
+// =============================================================================
+
+#[derive(Default)]
+pub struct SessionRouter {
+    routes: HashMap<String, Route>,
+}
+
+struct Route {
+    /// Counterparty CompID, for log lines
+    name: String,
+    handler: Mutex<Box<dyn SessionHandler>>,
+
+    /// This session's ring in the callback log; a session's callbacks come
+    /// from one thread at a time, so the lock is never contended
+    log: Option<Mutex<Producer>>,
+}
+
+impl SessionRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the handler of a session
+    ///
+    /// A later registration for the same session replaces the earlier one.
+    pub fn add(&mut self, session: &SessionId, name: &str, handler: Box<dyn SessionHandler>) {
+        self.routes.insert(
+            session.to_repr(),
+            Route {
+                name: name.to_string(),
+                handler: Mutex::new(handler),
+                log: None,
+            },
+        );
+    }
+
+    /// Write the callbacks of the routes registered so far to `log`, a ring
+    /// each, instead of printing them
+    pub fn with_log(&mut self, log: &CallbackLog) {
+        for route in self.routes.values_mut() {
+            route.log = Some(Mutex::new(log.producer(&route.name)));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// Run `f` on the handler of `session`, if one is registered
+    fn with_handler<T>(
+        &self,
+        session: &SessionId,
+        f: impl FnOnce(&str, &mut dyn SessionHandler) -> T,
+    ) -> Option<T> {
+        let route = self.routes.get(&session.to_repr())?;
+        let mut handler = route.handler.lock().expect("session handler lock poisoned");
+        Some(f(&route.name, handler.as_mut()))
+    }
+
+    /// Push a record to the session's ring; false without a callback log
+    fn log(&self, session: &SessionId, record: impl FnOnce() -> Record) -> bool {
+        let Some(log) = self
+            .routes
+            .get(&session.to_repr())
+            .and_then(|route| route.log.as_ref())
+        else {
+            return false;
+        };
+        // Dropped and counted by the log when its ring is full
+        log.lock()
+            .expect("callback log lock poisoned")
+            .push(&record());
+        true
+    }
+}
+
+impl ApplicationCallback for SessionRouter {
+    fn on_create(&self, session: &SessionId) {
+        if self.with_handler(session, |_, _| ()).is_none() {
+            println!(">> WARNING: no handler for session {}", session.to_repr());
+        }
+    }
+
+    fn on_logon(&self, session: &SessionId) {
+        let logged = self.log(session, || Record::new(Callback::Logon));
+        self.with_handler(session, |name, handler| {
+            if !logged {
+                println!(">> [{name}] logon");
+            }
+            handler.on_logon(session);
+        });
+    }
+
+    fn on_logout(&self, session: &SessionId) {
+        let logged = self.log(session, || Record::new(Callback::Logout));
+        self.with_handler(session, |name, handler| {
+            if !logged {
+                println!(">> [{name}] logout");
+            }
+            handler.on_logout(session);
+        });
+    }
+
+    fn on_msg_to_admin(&self, msg: &mut Message, session: &SessionId) {
+        self.log(session, || Record::from_message(Callback::ToAdmin, msg));
+    }
+
+    fn on_msg_to_app(&self, msg: &mut Message, session: &SessionId) -> Result<(), MsgToAppError> {
+        self.log(session, || Record::from_message(Callback::ToApp, msg));
+        Ok(())
+    }
+
+    fn on_msg_from_admin(
+        &self,
+        msg: &Message,
+        session: &SessionId,
+    ) -> Result<(), MsgFromAdminError> {
+        self.log(session, || Record::from_message(Callback::FromAdmin, msg));
+        Ok(())
+    }
+
+    fn on_msg_from_app(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        self.log(session, || Record::from_message(Callback::FromApp, msg));
+        // A session without a handler has no business logic behind it: the
+        // engine answers with a BusinessMessageReject
+        self.with_handler(session, |_, handler| handler.on_message(msg, session))
+            .unwrap_or(Err(MsgFromAppError::UnsupportedMessageType))
+    }
+}
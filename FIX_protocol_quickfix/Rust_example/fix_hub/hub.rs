@@ -0,0 +1,218 @@
+// =============================================================================
+// Hub Callbacks
+// =============================================================================
+// One ApplicationCallback serves the acceptor and the initiator alike: every
+// application message received, on either side, is handed to the Router
+// (trading::session::routing) and sent on where it says, rewritten.
+//
+//   on_msg_from_app --decode--> Router::decide --Forward--> send_to_target
+//                                              \-Unroutable-> 35=j by QuickFIX
+//
+// A route whose target is logged off is answered with a
+// BusinessMessageReject (35=j, 380=4 application not available) rather than
+// stored: the client learns at once that nothing reached the venue. Replies
+// going back to a client logged off are sent all the same; QuickFIX keeps
+// them in the store and resends them when the client asks at its next logon.
+//
+// With transform rules (trading::session::transform), a message is
+// rewritten as received before the router sees it, and as sent in
+// on_msg_to_app, so that each venue gets its quirks and each client its own.
+// A symbology (trading::symbology) gives the venues their symbols the same
+// way: mapped to the internal ones as received, after the transform rules,
+// and back as sent, before them.
+// =============================================================================
+
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+use quickfix::{
+    send_to_target, ApplicationCallback, FieldMap, Message, MsgFromAppError, MsgToAppError,
+    QuickFixError, SessionId,
+};
+use trading::{
+    session::{
+        events::FixMessage,
+        parse_session_label,
+        routing::{Decision, Forward, Router},
+        session_label,
+        transform::Transformer,
+        Direction,
+    },
+    symbology::Symbology,
+};
+
+/// BusinessRejectReason (380): application not available
+const APPLICATION_NOT_AVAILABLE: &str = "4";
+
+pub struct Hub {
+    router: Router,
+    transforms: Transformer,
+    symbology: Symbology,
+
+    /// Labels of the sessions logged on
+    logged_on: Mutex<HashSet<String>>,
+    forwarded: AtomicU64,
+    replies: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl Hub {
+    pub fn new(router: Router, transforms: Transformer) -> Self {
+        Self {
+            router,
+            transforms,
+            symbology: Symbology::default(),
+            logged_on: Mutex::new(HashSet::new()),
+            forwarded: AtomicU64::new(0),
+            replies: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Map symbols between the clients and the venues
+    pub fn with_symbology(mut self, symbology: Symbology) -> Self {
+        self.symbology = symbology;
+        self
+    }
+
+    /// One line of counts, for the console and the shutdown
+    pub fn summary(&self) -> String {
+        format!(
+            "{} forwarded, {} replies, {} rejected, {} order(s) mapped",
+            self.forwarded.load(Ordering::Relaxed),
+            self.replies.load(Ordering::Relaxed),
+            self.rejected.load(Ordering::Relaxed),
+            self.router.routed_count()
+        )
+    }
+
+    fn is_logged_on(&self, label: &str) -> bool {
+        self.lock().contains(label)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashSet<String>> {
+        self.logged_on.lock().expect("hub lock poisoned")
+    }
+
+    /// Send a message on where the router says
+    fn forward(
+        &self,
+        forward: &Forward,
+        msg: &Message,
+        fix: &FixMessage,
+    ) -> Result<(), QuickFixError> {
+        let target = parse_session_label(&forward.target).ok_or_else(|| {
+            QuickFixError::InvalidArgument(format!("invalid session {}", forward.target))
+        })??;
+        send_to_target(forward.apply(msg)?, &target)?;
+
+        let route = forward.route.as_deref().unwrap_or("reply");
+        println!(
+            ">> [{route}] {} -> {}: 35={} 11={}",
+            fix.session,
+            forward.target,
+            fix.msg_type(),
+            fix.get(11).unwrap_or("-")
+        );
+        let counter = if forward.route.is_some() {
+            &self.forwarded
+        } else {
+            &self.replies
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// BusinessMessageReject (35=j) back to the session a message came from
+    fn reject(&self, fix: &FixMessage, session: &SessionId, text: &str) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        println!(">> {}: 35={} rejected: {text}", fix.session, fix.msg_type());
+        let reject = (|| {
+            let mut msg = Message::new();
+            msg.with_header_mut(|h| h.set_field(35, "j"))?;
+            msg.set_field(45, fix.seq_num())?; // RefSeqNum
+            msg.set_field(372, fix.msg_type())?; // RefMsgType
+            if let Some(cl_ord_id) = fix.get(11) {
+                msg.set_field(379, cl_ord_id)?; // BusinessRejectRefID
+            }
+            msg.set_field(380, APPLICATION_NOT_AVAILABLE)?; // BusinessRejectReason
+            msg.set_field(58, text)?; // Text
+            send_to_target(msg, session)
+        })();
+        if let Err(err) = reject {
+            eprintln!(">> {}: cannot send the reject: {err:?}", fix.session);
+        }
+    }
+}
+
+impl ApplicationCallback for Hub {
+    fn on_logon(&self, session: &SessionId) {
+        let label = session_label(session);
+        println!(">> {label} logon");
+        self.lock().insert(label);
+    }
+
+    fn on_logout(&self, session: &SessionId) {
+        let label = session_label(session);
+        println!(">> {label} logout");
+        self.lock().remove(&label);
+    }
+
+    fn on_msg_to_app(&self, msg: &mut Message, session: &SessionId) -> Result<(), MsgToAppError> {
+        let result = self
+            .symbology
+            .outbound(msg, session)
+            .and_then(|_| self.transforms.outbound(msg, session));
+        if let Err(err) = result {
+            eprintln!(
+                ">> {}: transform failed, not sent: {err:?}",
+                session_label(session)
+            );
+            return Err(MsgToAppError::DoNotSend);
+        }
+        Ok(())
+    }
+
+    fn on_msg_from_app(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        let transformed = self.transforms.inbound(msg, session).unwrap_or_else(|err| {
+            eprintln!(">> {}: transform failed: {err:?}", session_label(session));
+            None
+        });
+        let msg = transformed.as_ref().unwrap_or(msg);
+        let mapped = self.symbology.inbound(msg, session).unwrap_or_else(|err| {
+            eprintln!(
+                ">> {}: symbol mapping failed: {err:?}",
+                session_label(session)
+            );
+            None
+        });
+        let msg = mapped.as_ref().unwrap_or(msg);
+        let fix = FixMessage::decode(session, Direction::Inbound, false, msg);
+        let forward = match self.router.decide(&fix) {
+            Decision::Forward(forward) => forward,
+            // The engine answers with a BusinessMessageReject
+            Decision::Unroutable => {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                println!(">> {}: 35={} matches no route", fix.session, fix.msg_type());
+                return Err(MsgFromAppError::UnsupportedMessageType);
+            }
+        };
+
+        if forward.route.is_some() && !self.is_logged_on(&forward.target) {
+            let text = format!("{} is not logged on", forward.target);
+            self.reject(&fix, session, &text);
+            return Ok(());
+        }
+        if let Err(err) = self.forward(&forward, msg, &fix) {
+            let text = format!("cannot forward to {}", forward.target);
+            eprintln!(">> {}: {text}: {err:?}", fix.session);
+            self.reject(&fix, session, &text);
+        }
+        Ok(())
+    }
+}
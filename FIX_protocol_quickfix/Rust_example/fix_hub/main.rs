@@ -0,0 +1,284 @@
+// =============================================================================
+// QuickFIX Rust Example: fix_hub - FIX-to-FIX Routing Hub
+// =============================================================================
+// A minimal FIX router: an acceptor for the clients and initiators to the
+// venues run in one process, and the application messages received on any
+// session go on to another, where the rules of a routes file send them
+// (trading::session::routing):
+//
+//   buy_side ==FIX==> [acceptor  fix_hub  initiator] ==FIX==> sell_side
+//            <==FIX==                                <==FIX==
+//
+// The sessions of both sides come from one configuration file: those with
+// ConnectionType=acceptor are served by the acceptor, those with
+// ConnectionType=initiator by the initiator. ${VAR} values are filled from
+// the environment, as fix_repl and fix_doctor do.
+//
+// ClOrdIDs are replaced by the hub's own on the way to a venue and put back
+// on the way to the client; the execution reports of an order go back to
+// the session it came from whatever the rules say. See fix_hub/hub.cfg and
+// fix_hub/routes.txt for a hub between buy_side and sell_side.
+//
+// With --transforms FILE, the messages are rewritten by rules on the way in
+// and out as well (trading::session::transform): what one venue wants in
+// its orders, and what its execution reports carry that clients do not.
+// With --symbology FILE, the clients' symbols are sent as each venue's
+// (trading::symbology): rows for the venue sessions only, as a row for
+// every session would give the clients the venue's symbols too.
+//
+// Key Learning Points:
+// 1. An acceptor and an initiator sharing one application and one store
+// 2. Routing on message content with rules kept out of the code
+// 3. Rewriting ClOrdIDs so that clients never collide at the venue, and
+//    mapping the replies back
+// 4. Venue quirks handled by transform rules, and venue symbols by a
+//    symbology, per session
+// =============================================================================
+
+use std::{
+    env,
+    io::{stdin, Read},
+    path::Path,
+    process::exit,
+};
+
+use quickfix::{
+    Acceptor, Application, ConnectionHandler, FileMessageStoreFactory, Initiator, LogFactory,
+    QuickFixError, StdLogger,
+};
+use trading::{
+    session::{
+        dictionary::Dictionary,
+        routing::{self, Router},
+        session_label,
+        settings::{FileSecrets, SettingsLoader},
+        socket_server_kind,
+        transform::{self, Transformer},
+    },
+    symbology::Symbology,
+};
+
+use crate::hub::Hub;
+
+mod hub; // ApplicationCallback handing every message to the router
+
+const USAGE: &str = "usage: fix_hub <config_file> <routes_file> [--secrets-dir DIR] \
+                     [--transforms FILE] [--symbology FILE] [--multi-threaded]";
+
+// =============================================================================
+// Main Entry Point
+// =============================================================================
+
+fn main() -> Result<(), QuickFixError> {
+    // =========================================================================
+    // Step 1: Parse Command-Line Arguments
+    // =========================================================================
+
+    let mut args = env::args().skip(1);
+    let mut files = Vec::new();
+    let mut loader = SettingsLoader::new();
+    let mut transforms = None;
+    let mut symbology = Symbology::default();
+    let mut multi_threaded = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--secrets-dir" => {
+                let dir = args
+                    .next()
+                    .unwrap_or_else(|| fail("--secrets-dir requires a directory"));
+                loader = loader.with_provider(FileSecrets::new(Path::new(&dir)));
+            }
+            "--transforms" => {
+                let path = args
+                    .next()
+                    .unwrap_or_else(|| fail("--transforms requires a file"));
+                transforms = Some(path);
+            }
+            "--symbology" => {
+                let path = args
+                    .next()
+                    .unwrap_or_else(|| fail("--symbology requires a file"));
+                symbology = Symbology::load(Path::new(&path)).unwrap_or_else(|err| {
+                    eprintln!("Cannot read {path}: {err}");
+                    exit(1);
+                });
+                println!(">> {} symbol(s) in {path}", symbology.len());
+            }
+            "--multi-threaded" => multi_threaded = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            flag if flag.starts_with('-') => fail(&format!("unknown option {flag}")),
+            path => files.push(path.to_string()),
+        }
+    }
+    let [config_file, routes_file] = files.as_slice() else {
+        fail(USAGE);
+    };
+
+    // =========================================================================
+    // Step 2: Sessions and Routes
+    // =========================================================================
+    // Each route must send to a session of the file; a route to one of the
+    // acceptor's sessions is fine (market data fanned out to clients)
+    // =========================================================================
+
+    let config = loader.read(Path::new(config_file)).unwrap_or_else(|err| {
+        eprintln!("Cannot read {config_file}: {err}");
+        exit(1);
+    });
+    let (mut acceptors, mut initiators) = (Vec::new(), Vec::new());
+    for block in config.sessions() {
+        let label = session_label(&block.session_id()?);
+        match block.get("ConnectionType") {
+            Some("acceptor") => acceptors.push(label),
+            Some("initiator") => initiators.push(label),
+            other => fail(&format!(
+                "{label}: ConnectionType must be acceptor or initiator, not {}",
+                other.unwrap_or("missing")
+            )),
+        }
+    }
+
+    let routes = routing::load_routes(Path::new(routes_file), &Dictionary::standard())
+        .unwrap_or_else(|err| {
+            eprintln!("Cannot read {routes_file}: {err}");
+            exit(1);
+        });
+    if routes.is_empty() {
+        fail(&format!("{routes_file} has no route"));
+    }
+    for route in &routes {
+        if !acceptors.contains(&route.target) && !initiators.contains(&route.target) {
+            fail(&format!(
+                "route {}: no session {} in {config_file}",
+                route.rule.name, route.target
+            ));
+        }
+        println!(">> route {route}");
+    }
+    let transforms = match transforms {
+        Some(path) => {
+            let rules = transform::load_transforms(Path::new(&path), &Dictionary::standard())
+                .unwrap_or_else(|err| {
+                    eprintln!("Cannot read {path}: {err}");
+                    exit(1);
+                });
+            for rule in &rules {
+                println!(">> transform {rule}");
+            }
+            Transformer::new(rules)
+        }
+        None => Transformer::default(),
+    };
+    println!(
+        ">> {} client session(s), {} venue session(s)",
+        acceptors.len(),
+        initiators.len()
+    );
+
+    // =========================================================================
+    // Step 3: Create the Acceptor and the Initiator
+    // =========================================================================
+    // Both read the same settings, each taking the sessions of its
+    // ConnectionType, and share the store factory and the application
+    // =========================================================================
+
+    let settings = config.to_settings().unwrap_or_else(|err| {
+        eprintln!("Cannot read {config_file}: {err}");
+        exit(1);
+    });
+    let store_factory = FileMessageStoreFactory::try_new(&settings)?;
+    let log_factory = LogFactory::try_new(&StdLogger::Stdout)?;
+    let hub = Hub::new(Router::new(routes), transforms).with_symbology(symbology);
+    let app = Application::try_new(&hub)?;
+
+    let mut acceptor = if acceptors.is_empty() {
+        None
+    } else {
+        Some(Acceptor::try_new(
+            &settings,
+            &app,
+            &store_factory,
+            &log_factory,
+            socket_server_kind(&hub, multi_threaded),
+        )?)
+    };
+    let mut initiator = if initiators.is_empty() {
+        None
+    } else {
+        Some(Initiator::try_new(
+            &settings,
+            &app,
+            &store_factory,
+            &log_factory,
+            socket_server_kind(&hub, multi_threaded),
+        )?)
+    };
+
+    // =========================================================================
+    // Step 4: Run Until User Quits
+    // =========================================================================
+
+    println!(">> connection handlers START");
+    if let Some(acceptor) = acceptor.as_mut() {
+        acceptor.start()?;
+    }
+    if let Some(initiator) = initiator.as_mut() {
+        initiator.start()?;
+    }
+
+    println!(">> Hub running, press 's' for counts, 'q' to quit");
+    let mut stdin = stdin().lock();
+    let mut stdin_buf = [0];
+    while stdin.read_exact(&mut stdin_buf).is_ok() && stdin_buf[0] != b'q' {
+        if stdin_buf[0] == b's' {
+            println!(">> {}", hub.summary());
+        }
+    }
+
+    // =========================================================================
+    // Step 5: Graceful Shutdown
+    // =========================================================================
+    // Clients first, so that nothing new comes in while the venues log out
+    // =========================================================================
+
+    println!(">> connection handlers STOP");
+    if let Some(acceptor) = acceptor.as_mut() {
+        acceptor.stop()?;
+    }
+    if let Some(initiator) = initiator.as_mut() {
+        initiator.stop()?;
+    }
+    println!(">> {}", hub.summary());
+    Ok(())
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    exit(1);
+}
+
+// =============================================================================
+// Usage Examples
+// =============================================================================
+//
+// Between buy_side and sell_side (FIX.4.4, CompIDs of fix_hub/hub.cfg):
+//   cargo run --example sell_side -- 5001 --comp-ids HUB,HUB_MD
+//   cargo run --example fix_hub -- fix_hub/hub.cfg fix_hub/routes.txt
+//   cargo run --example buy_side -- 127.0.0.1 5101
+//
+// A routes file, first match wins:
+//   big_aapl = symbol == "AAPL" && qty >= 10000 => FIX.4.4:HUB->VENUE_B
+//   client_a = msg.49 == "CLIENT_A" => FIX.4.4:HUB->VENUE_A
+//
+// VENUE_B's orders with an Account and good till cancel (transforms.txt):
+//   venue_b = session == "FIX.4.4:HUB->VENUE_B" && direction == "out" => set Account HUB-B; map TimeInForce Day=GoodTillCancel
+//   cargo run --example fix_hub -- hub.cfg routes.txt --transforms transforms.txt
+//
+// VENUE_B's tickers and ISINs for the clients' symbols (symbology.csv):
+//   internal,session,symbol,security_id_source,isin
+//   VOD,FIX.4.4:HUB->VENUE_B,VOD.L,isin,GB00BH4HKS39
+//   cargo run --example fix_hub -- hub.cfg routes.txt --symbology symbology.csv
+// =============================================================================
@@ -0,0 +1,54 @@
+// =============================================================================
+// Message Archive Task
+// =============================================================================
+// With --archive-dir DIR, every message the callbacks see is also added to
+// a Parquet archive (trading::session::archive), one file per batch in
+// DIR/date=YYYY-MM-DD/session_id=SESSION/. A batch is written once it has
+// --archive-rows rows (default 10000) or is --archive-interval seconds old
+// (default 300), and whatever is left on the way out:
+//
+//   duckdb -c "SELECT msg_type, count(*) FROM read_parquet('archive/*/*/*.parquet',
+//              hive_partitioning = true) WHERE date = '2026-10-15' GROUP BY 1"
+//
+// Writing runs on the blocking pool: gzip over ten thousand rows is not for
+// the thread of the shell.
+// =============================================================================
+
+use std::{sync::Arc, time::Duration};
+
+use tracing::{debug, warn};
+use trading::session::archive::MessageArchive;
+
+/// How often the batches are looked at
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Write the batches that are due, for the life of the process
+pub async fn archive_task(archive: Arc<MessageArchive>) {
+    let mut ticks = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        ticks.tick().await;
+        let archive = Arc::clone(&archive);
+        match tokio::task::spawn_blocking(move || archive.flush_due()).await {
+            Ok(Ok(files)) => {
+                for file in files {
+                    debug!(file = %file.display(), "messages archived");
+                }
+            }
+            Ok(Err(err)) => warn!(%err, "cannot archive the messages, kept for the next try"),
+            Err(err) => warn!(%err, "archive task failed"),
+        }
+    }
+}
+
+/// Write what is left, on the way out
+pub fn flush(archive: &MessageArchive) {
+    match archive.flush() {
+        Ok(files) => debug!(files = files.len(), "messages archived"),
+        Err(err) => warn!(
+            %err,
+            pending = archive.pending(),
+            dir = %archive.dir().display(),
+            "cannot archive the last messages"
+        ),
+    }
+}
@@ -0,0 +1,311 @@
+// =============================================================================
+// Order Blotter (terminal UI)
+// =============================================================================
+// `--tui` turns the REPL into a blotter: four live panes on top, the usual
+// command line at the bottom.
+//
+//   ┌ Open orders ─────────────────┬ Positions ───────────────────┐
+//   │ age  session  ClOrdID  ...   │ symbol  net  avg buy  ...    │
+//   ├ Executions ──────────────────┼ Sessions ────────────────────┤
+//   │ ago  ClOrdID  exec  ...      │ # session  on  in  out  ...  │
+//   └──────────────────────────────┴──────────────────────────────┘
+//   FIX> send tmpl new_limit_order symbol=AAPL qty=100 px=150
+//   >> send tmpl OK 85µs
+//
+// The panes are repainted every REPAINT_INTERVAL from the state the event
+// task keeps (orders.rs, watch.rs). Plain ANSI escapes do the drawing:
+//
+// - the bottom rows are a scroll region (DECSTBM), where the prompt, command
+//   output and help scroll as in the plain REPL, without touching the panes
+// - each repaint saves and restores the cursor, so a half-typed command
+//   stays where it is
+//
+// Input stays line-based (the terminal is not put in raw mode). The size is
+// read once with `stty size`, or from LINES / COLUMNS; after resizing the
+// terminal, `redraw` lays the screen out again. Logs go to a file in this
+// mode (see main.rs), since lines written to the terminal by another thread
+// would land in the panes.
+// =============================================================================
+
+use std::{
+    env,
+    fmt::Write as _,
+    fs::File,
+    io::{stdout, Write},
+    process::Command,
+    time::Duration,
+};
+
+use crate::{
+    orders::{OrderTracker, Side, TrackedOrder},
+    watch::LiveState,
+};
+
+/// How often the panes are repainted
+pub const REPAINT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Rows kept for the prompt and command output
+const COMMAND_ROWS: u16 = 8;
+
+/// Smallest usable terminal
+const MIN_ROWS: u16 = COMMAND_ROWS + 12;
+const MIN_COLS: u16 = 80;
+
+pub struct Blotter {
+    rows: u16,
+    cols: u16,
+}
+
+impl Blotter {
+    /// Measure the terminal; None if it is too small for the panes
+    pub fn open() -> Option<Self> {
+        let (rows, cols) = terminal_size().unwrap_or((24, 80));
+        (rows >= MIN_ROWS && cols >= MIN_COLS).then_some(Self { rows, cols })
+    }
+
+    fn pane_rows(&self) -> u16 {
+        self.rows - COMMAND_ROWS
+    }
+
+    /// Clear the screen, draw the frame and put the cursor in the command
+    /// area; also after `watch`, which uses the whole screen
+    pub fn enter(&mut self) {
+        if let Some((rows, cols)) = terminal_size() {
+            if rows >= MIN_ROWS && cols >= MIN_COLS {
+                self.rows = rows;
+                self.cols = cols;
+            }
+        }
+
+        let mut out = stdout().lock();
+        let _ = write!(
+            out,
+            "\x1b[r\x1b[2J\x1b[{};{}r\x1b[{};1H",
+            self.pane_rows() + 1,
+            self.rows,
+            self.rows
+        );
+        let _ = out.flush();
+    }
+
+    /// Give the whole screen back
+    pub fn leave(&self) {
+        let mut out = stdout().lock();
+        let _ = write!(out, "\x1b[r\x1b[2J\x1b[H");
+        let _ = out.flush();
+    }
+
+    /// Repaint the panes, leaving the cursor where it was
+    pub fn paint(&self, orders: &OrderTracker, live: &LiveState) {
+        let pane_rows = usize::from(self.pane_rows());
+        let top_rows = (pane_rows - 3) / 2;
+        let bottom_rows = pane_rows - 3 - top_rows;
+        let left = usize::from(self.cols) / 2 - 1;
+        let right = usize::from(self.cols) - left - 3;
+
+        let open_orders = orders.open_orders();
+        let orders_pane = orders_lines(&open_orders);
+        let positions_pane = positions_lines(live);
+        let executions_pane = executions_lines(live, bottom_rows);
+        let sessions_pane = sessions_lines(live);
+
+        let mut lines = Vec::with_capacity(pane_rows);
+        lines.push(border(
+            ('┌', '┬', '┐'),
+            &format!("Open orders ({})", open_orders.len()),
+            "Positions",
+            left,
+            right,
+        ));
+        lines.extend(rows(&orders_pane, &positions_pane, top_rows, left, right));
+        lines.push(border(('├', '┼', '┤'), "Executions", "Sessions", left, right));
+        lines.extend(rows(&executions_pane, &sessions_pane, bottom_rows, left, right));
+        lines.push(border(('└', '┴', '┘'), "", "", left, right));
+
+        let mut out = String::from("\x1b7");
+        for (index, line) in lines.iter().enumerate() {
+            let _ = write!(out, "\x1b[{};1H{line}\x1b[K", index + 1);
+        }
+        out.push_str("\x1b8");
+
+        let mut stdout = stdout().lock();
+        let _ = stdout.write_all(out.as_bytes());
+        let _ = stdout.flush();
+    }
+}
+
+// =============================================================================
+// Panes
+// =============================================================================
+// Each pane is a header line and rows, cut to the pane width when drawn
+// =============================================================================
+
+fn side_name(side: Option<Side>) -> &'static str {
+    match side {
+        Some(Side::Buy) => "buy",
+        Some(Side::Sell) => "sell",
+        None => "?",
+    }
+}
+
+/// Counterparty CompID of a session label (`FIX.4.4:CLIENT->EXCHANGE`)
+fn counterparty(label: &str) -> &str {
+    label.rsplit_once("->").map_or(label, |(_, x)| x)
+}
+
+fn orders_lines(orders: &[TrackedOrder]) -> Vec<String> {
+    let mut out = vec![format!(
+        "{:>6} {:<10} {:<16} {:<4} {:>8} {:<8} {}",
+        "age", "to", "ClOrdID", "side", "qty", "symbol", "state"
+    )];
+    for order in orders {
+        out.push(format!(
+            "{:>5}s {:<10} {:<16} {:<4} {:>8} {:<8} {:?}",
+            order.age().as_secs(),
+            counterparty(&order.session),
+            order.cl_ord_id,
+            side_name(order.side),
+            order.quantity,
+            order.symbol,
+            order.state
+        ));
+    }
+    out
+}
+
+fn positions_lines(live: &LiveState) -> Vec<String> {
+    let price = |x: Option<f64>| x.map_or("-".to_string(), |x| format!("{x:.4}"));
+    let mut out = vec![format!(
+        "{:<8} {:>10} {:>12} {:>12} {:>6}",
+        "symbol", "net", "avg buy", "avg sell", "fills"
+    )];
+    for position in live.positions() {
+        out.push(format!(
+            "{:<8} {:>10} {:>12} {:>12} {:>6}",
+            position.symbol,
+            position.net,
+            price(position.avg_buy),
+            price(position.avg_sell),
+            position.fills
+        ));
+    }
+    out
+}
+
+fn executions_lines(live: &LiveState, count: usize) -> Vec<String> {
+    let mut out = vec![format!(
+        "{:>6} {:<16} {:<8} {:<4} {:>4} {:>4} {:>8} {:>10}",
+        "ago", "ClOrdID", "symbol", "side", "exec", "ord", "last qty", "last px"
+    )];
+    for execution in live.recent_executions(count) {
+        let side = match execution.side.as_str() {
+            "1" => "buy",
+            "2" => "sell",
+            other => other,
+        };
+        out.push(format!(
+            "{:>5}s {:<16} {:<8} {:<4} {:>4} {:>4} {:>8} {:>10}",
+            execution.received_at.elapsed().as_secs(),
+            execution.cl_ord_id,
+            execution.symbol,
+            side,
+            execution.exec_type,
+            execution.ord_status,
+            execution.last_qty,
+            execution.last_px
+        ));
+    }
+    out
+}
+
+fn sessions_lines(live: &LiveState) -> Vec<String> {
+    let mut out = vec![format!(
+        "{:>2} {:<32} {:<3} {:>7} {:>7} {:>6}",
+        "#", "session", "on", "in", "out", "idle"
+    )];
+    for (index, session) in live.sessions().iter().enumerate() {
+        let idle = session
+            .last_message
+            .map_or("-".to_string(), |x| format!("{}s", x.elapsed().as_secs()));
+        out.push(format!(
+            "{:>2} {:<32} {:<3} {:>7} {:>7} {:>6}",
+            index + 1,
+            session.label,
+            if session.logged_on { "yes" } else { "no" },
+            session.received,
+            session.sent,
+            idle
+        ));
+    }
+    out
+}
+
+// =============================================================================
+// Drawing
+// =============================================================================
+
+/// `┌ Title ────┬ Title ────┐`, titles in bold
+fn border(
+    corners: (char, char, char),
+    left_title: &str,
+    right_title: &str,
+    left: usize,
+    right: usize,
+) -> String {
+    let segment = |title: &str, width: usize| {
+        if title.is_empty() {
+            return "─".repeat(width);
+        }
+        let title = fit(title, width.saturating_sub(3));
+        let used = title.chars().count() + 2;
+        format!(" \x1b[1m{title}\x1b[0m {}", "─".repeat(width.saturating_sub(used)))
+    };
+    format!(
+        "{}{}{}{}{}",
+        corners.0,
+        segment(left_title, left),
+        corners.1,
+        segment(right_title, right),
+        corners.2
+    )
+}
+
+/// `height` rows of two panes side by side; longer panes are cut
+fn rows(
+    left_pane: &[String],
+    right_pane: &[String],
+    height: usize,
+    left: usize,
+    right: usize,
+) -> Vec<String> {
+    (0..height)
+        .map(|index| {
+            let cell = |pane: &[String], width| {
+                let text = pane.get(index).map_or("", String::as_str);
+                let text = fit(text, width);
+                let padding = width - text.chars().count();
+                format!("{text}{}", " ".repeat(padding))
+            };
+            format!("│{}│{}│", cell(left_pane, left), cell(right_pane, right))
+        })
+        .collect()
+}
+
+/// Cut to `width` characters
+fn fit(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Rows and columns of the controlling terminal
+fn terminal_size() -> Option<(u16, u16)> {
+    let from_stty = File::open("/dev/tty").ok().and_then(|tty| {
+        let output = Command::new("stty").arg("size").stdin(tty).output().ok()?;
+        let text = String::from_utf8(output.stdout).ok()?;
+        let mut numbers = text.split_whitespace().map(|x| x.parse::<u16>().ok());
+        Some((numbers.next()??, numbers.next()??))
+    });
+    from_stty.or_else(|| {
+        let number = |name| env::var(name).ok()?.parse::<u16>().ok();
+        Some((number("LINES")?, number("COLUMNS")?))
+    })
+}
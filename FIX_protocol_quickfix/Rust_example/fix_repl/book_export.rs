@@ -0,0 +1,336 @@
+// =============================================================================
+// Order Book Snapshots and Export
+// =============================================================================
+// After an incident the question is often what the book looked like, and
+// where our orders sat in it. `book` draws the market data book kept for
+// `watch book` (watch.rs), with the orders working at each level marked
+// (orders.rs):
+//
+//   FIX> book AAPL 3
+//   AAPL  2026-10-15 14:03:07.250 UTC  (1234 update(s))
+//                  ours   bid size        bid | offer      offer size ours
+//         ######   *100        300     150.25 | 150.26     200               ####
+//   ############               600     150.24 | 150.27     500        *50    ##########
+//              #                50      150.2 |
+//
+// The bars scale with the size of each level, and `*QTY` is the quantity of
+// our orders at that price (their order quantity, fills not deducted).
+// Orders at a price outside the levels shown are not marked.
+//
+// With --book-export DIR the books are also written out every --book-interval
+// seconds (default 1), --book-depth levels deep (default 5), to be attached to
+// a postmortem. A book is only written when market data changed it, one JSON
+// object per line, in DIR/books-YYYYMMDD.jsonl:
+//
+//   {"t":1792072987250,"s":"AAPL","u":1234,"b":[[150.25,300,100],[150.24,600]],
+//    "a":[[150.26,200]]}
+//
+// t is Unix milliseconds, u the market data updates applied so far, b and a
+// the bids and offers best first: [price, size], or [price, size, ours].
+// `book SYMBOL --from FILE [--at TIME]` draws the last snapshot of the symbol
+// in such a file, at or before TIME (UTCTimestamp or ISO 8601) if given.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use tracing::warn;
+use trading::{
+    json::{json_escape, JsonValue},
+    time::{time_of_day, Date},
+};
+
+use crate::{
+    orders::{OrderTracker, Side, TrackedOrder},
+    watch::{BookDepth, LiveState},
+};
+
+/// Time between two exports when --book-interval is not given
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Width of the size bars, in characters
+const BAR_WIDTH: usize = 12;
+
+// =============================================================================
+// Snapshots
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookLevel {
+    pub price: f64,
+    pub size: f64,
+
+    /// Quantity of our open orders at this price; 0 for none
+    pub ours: f64,
+}
+
+/// One book at one time
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookSnapshot {
+    /// Unix milliseconds
+    pub time: i64,
+    pub symbol: String,
+
+    /// Market data messages applied since the first
+    pub updates: u64,
+
+    /// Best first
+    pub bids: Vec<BookLevel>,
+    pub offers: Vec<BookLevel>,
+}
+
+impl BookSnapshot {
+    /// The book now, with `orders` marked at their price
+    pub fn capture(symbol: &str, depth: &BookDepth, orders: &[TrackedOrder]) -> Self {
+        let levels = |levels: &[(f64, f64)], side: Side| {
+            levels
+                .iter()
+                .map(|&(price, size)| BookLevel {
+                    price,
+                    size,
+                    ours: orders
+                        .iter()
+                        .filter(|x| x.symbol == symbol && x.side == Some(side))
+                        .filter(|x| x.price == Some(price))
+                        .filter_map(|x| x.quantity.parse::<f64>().ok())
+                        .sum(),
+                })
+                .collect()
+        };
+        Self {
+            time: unix_millis(),
+            symbol: symbol.to_string(),
+            updates: depth.updates,
+            bids: levels(&depth.bids, Side::Buy),
+            offers: levels(&depth.offers, Side::Sell),
+        }
+    }
+
+    /// Keep the top `depth` levels of each side
+    pub fn truncate(&mut self, depth: usize) {
+        self.bids.truncate(depth);
+        self.offers.truncate(depth);
+    }
+
+    /// One line of an export file
+    pub fn to_json(&self) -> String {
+        let levels = |levels: &[BookLevel]| {
+            let levels: Vec<_> = levels
+                .iter()
+                .map(|x| {
+                    if x.ours > 0.0 {
+                        format!("[{},{},{}]", x.price, x.size, x.ours)
+                    } else {
+                        format!("[{},{}]", x.price, x.size)
+                    }
+                })
+                .collect();
+            levels.join(",")
+        };
+        format!(
+            "{{\"t\":{},\"s\":\"{}\",\"u\":{},\"b\":[{}],\"a\":[{}]}}",
+            self.time,
+            json_escape(&self.symbol),
+            self.updates,
+            levels(&self.bids),
+            levels(&self.offers)
+        )
+    }
+
+    /// Parse a line of an export file; None if it is not a snapshot
+    pub fn from_json(line: &str) -> Option<Self> {
+        let value = JsonValue::parse(line)?;
+        let levels = |key: &str| -> Option<Vec<BookLevel>> {
+            value
+                .get(key)?
+                .as_array()?
+                .iter()
+                .map(|level| match level.as_array()? {
+                    [price, size, rest @ ..] => Some(BookLevel {
+                        price: price.as_f64()?,
+                        size: size.as_f64()?,
+                        ours: rest.first().and_then(JsonValue::as_f64).unwrap_or(0.0),
+                    }),
+                    _ => None,
+                })
+                .collect()
+        };
+        Some(Self {
+            time: value.get("t")?.as_f64()? as i64,
+            symbol: value.get("s")?.as_str()?.to_string(),
+            updates: value.get("u")?.as_f64()? as u64,
+            bids: levels("b")?,
+            offers: levels("a")?,
+        })
+    }
+
+    /// The book as text: bids on the left, offers on the right, sizes as
+    /// bars
+    pub fn render(&self) -> String {
+        let largest = self
+            .bids
+            .iter()
+            .chain(&self.offers)
+            .map(|x| x.size)
+            .fold(0.0, f64::max);
+        let bar = |size: f64| {
+            let width = if largest > 0.0 {
+                (size / largest * BAR_WIDTH as f64).ceil() as usize
+            } else {
+                0
+            };
+            "#".repeat(width.min(BAR_WIDTH))
+        };
+        let ours = |x: &BookLevel| {
+            if x.ours > 0.0 {
+                format!("*{}", x.ours)
+            } else {
+                String::new()
+            }
+        };
+
+        let mut out = format!(
+            "{}  {}  ({} update(s))\n",
+            self.symbol,
+            format_millis(self.time),
+            self.updates
+        );
+        let header = format!(
+            "{:>w$} {:>6} {:>10} {:>10} | {:<10} {:<10} {:<6}",
+            "",
+            "ours",
+            "bid size",
+            "bid",
+            "offer",
+            "offer size",
+            "ours",
+            w = BAR_WIDTH
+        );
+        let _ = writeln!(out, "{}", header.trim_end());
+        for level in 0..self.bids.len().max(self.offers.len()) {
+            let bid = self.bids.get(level);
+            let offer = self.offers.get(level);
+            let cell = |x: Option<&BookLevel>, text: &dyn Fn(&BookLevel) -> String| {
+                x.map_or(String::new(), text)
+            };
+            let line = format!(
+                "{:>w$} {:>6} {:>10} {:>10} | {:<10} {:<10} {:<6} {}",
+                cell(bid, &|x| bar(x.size)),
+                cell(bid, &ours),
+                cell(bid, &|x| x.size.to_string()),
+                cell(bid, &|x| x.price.to_string()),
+                cell(offer, &|x| x.price.to_string()),
+                cell(offer, &|x| x.size.to_string()),
+                cell(offer, &ours),
+                cell(offer, &|x| bar(x.size)),
+                w = BAR_WIDTH
+            );
+            let _ = writeln!(out, "{}", line.trim_end());
+        }
+        if self.bids.is_empty() && self.offers.is_empty() {
+            out.push_str("(empty book)\n");
+        }
+        out
+    }
+}
+
+/// Milliseconds since the Unix epoch, now
+fn unix_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_millis() as i64)
+}
+
+/// `YYYY-MM-DD HH:MM:SS.mmm UTC`
+fn format_millis(millis: i64) -> String {
+    let seconds = millis.div_euclid(1_000);
+    format!(
+        "{} {}.{:03} UTC",
+        Date::from_unix(seconds).to_iso(),
+        time_of_day(seconds),
+        millis.rem_euclid(1_000)
+    )
+}
+
+/// The last snapshot of `symbol` in an export file, at or before `at` (Unix
+/// milliseconds) if given
+pub fn read_snapshot(
+    path: &Path,
+    symbol: &str,
+    at: Option<i64>,
+) -> io::Result<Option<BookSnapshot>> {
+    let text = fs::read_to_string(path)?;
+    Ok(text
+        .lines()
+        .filter_map(BookSnapshot::from_json)
+        .filter(|x| x.symbol == symbol && at.is_none_or(|at| x.time <= at))
+        .max_by_key(|x| x.time))
+}
+
+// =============================================================================
+// Export Task
+// =============================================================================
+
+/// Where and how often books are written (--book-export)
+#[derive(Debug, Clone)]
+pub struct BookExport {
+    pub dir: PathBuf,
+    pub interval: Duration,
+    pub depth: usize,
+}
+
+impl BookExport {
+    /// Append snapshots to the file of the day
+    fn write(&self, snapshots: &[BookSnapshot]) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let date = Date::from_unix(unix_millis().div_euclid(1_000));
+        let path = self.dir.join(format!("books-{}.jsonl", date.to_fix()));
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let mut lines = String::new();
+        for snapshot in snapshots {
+            lines.push_str(&snapshot.to_json());
+            lines.push('\n');
+        }
+        file.write_all(lines.as_bytes())?;
+        Ok(path)
+    }
+}
+
+/// Write the books that changed every interval, for the life of the process
+pub async fn export_task(export: BookExport, live: Arc<LiveState>, orders: Arc<OrderTracker>) {
+    let mut ticks = tokio::time::interval(export.interval);
+    // Update count of each book when it was last written
+    let mut written: HashMap<String, u64> = HashMap::new();
+
+    loop {
+        ticks.tick().await;
+        let working = orders.open_orders();
+        let snapshots: Vec<_> = live
+            .book_symbols()
+            .into_iter()
+            .filter_map(|symbol| {
+                let depth = live.book(&symbol, export.depth)?;
+                (written.get(&symbol) != Some(&depth.updates))
+                    .then(|| BookSnapshot::capture(&symbol, &depth, &working))
+            })
+            .collect();
+        if snapshots.is_empty() {
+            continue;
+        }
+        match export.write(&snapshots) {
+            Ok(_) => {
+                for snapshot in snapshots {
+                    written.insert(snapshot.symbol, snapshot.updates);
+                }
+            }
+            Err(err) => warn!(dir = %export.dir.display(), %err, "cannot export books"),
+        }
+    }
+}
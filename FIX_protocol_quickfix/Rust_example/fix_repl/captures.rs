@@ -0,0 +1,137 @@
+// =============================================================================
+// Trade Capture Reconciliation
+// =============================================================================
+// The venue's own record of our trades (trading::oms::captures), set against
+// the fills of the blotter (trading::oms::blotter) by ExecID:
+//
+//   FIX> trades request 1                 35=AD for today's trades, session 1
+//                                         (as for watch session)
+//   FIX> trades request 1 --date 20240115 --symbol AAPL
+//   FIX> trades                           every report kept, and the requests
+//   FIX> trades --date 20240115 --unmatched
+//                                         active reports without a fill of ours
+//   FIX> trades --csv venue.csv           the reports listed, as CSV
+//
+// A report is matched when the blotter holds a fill with its ExecID. The
+// last line also counts the other way round: fills of the sessions, day and
+// symbol listed that no active report covers. The blotter is kept in memory
+// only, so after a restart the reports (kept with --state URL) are left to
+// compare with nothing.
+// =============================================================================
+
+use std::{collections::HashSet, fmt::Write as _};
+
+use trading::{
+    oms::{
+        blotter::Trade,
+        captures::{CaptureFilter, CaptureRequest, CaptureStatus, CapturedTrade},
+    },
+    time::{time_of_day, Date},
+};
+
+/// Active reports without a fill in the blotter
+pub fn unmatched(trades: Vec<CapturedTrade>, fills: &[Trade]) -> Vec<CapturedTrade> {
+    let fills: HashSet<&str> = fills.iter().map(|x| x.exec_id.as_str()).collect();
+    trades
+        .into_iter()
+        .filter(|x| x.status == CaptureStatus::Active && !fills.contains(x.report.exec_id.as_str()))
+        .collect()
+}
+
+/// Fills in the scope of the filter that no active report covers
+fn unreported<'a>(
+    reports: &[CapturedTrade],
+    fills: &'a [Trade],
+    filter: &CaptureFilter,
+) -> Vec<&'a Trade> {
+    let reported: HashSet<&str> = reports
+        .iter()
+        .filter(|x| x.status == CaptureStatus::Active)
+        .map(|x| x.report.exec_id.as_str())
+        .collect();
+    fills
+        .iter()
+        .filter(|x| filter.session.as_ref().is_none_or(|session| x.session == *session))
+        .filter(|x| filter.symbol.as_ref().is_none_or(|symbol| x.symbol == *symbol))
+        .filter(|x| {
+            let date = Date::from_unix(x.time.div_euclid(1_000));
+            filter.trade_date.is_none_or(|trade_date| trade_date == date)
+        })
+        .filter(|x| !reported.contains(x.exec_id.as_str()))
+        .collect()
+}
+
+/// The requests, the reports listed with whether a fill matches them, and
+/// what does not reconcile
+///
+/// # Arguments
+/// * `listed` - Reports to show
+/// * `reports` - Every report of the filter, to find the fills unreported
+/// * `fills` - The blotter
+pub fn render(
+    requests: &[CaptureRequest],
+    listed: &[CapturedTrade],
+    reports: &[CapturedTrade],
+    fills: &[Trade],
+    filter: &CaptureFilter,
+) -> String {
+    let mut out = String::new();
+    for request in requests {
+        let _ = writeln!(out, "request {request} on {}", request.session);
+    }
+    if listed.is_empty() {
+        out.push_str("no trade capture reports\n");
+    } else {
+        let _ = writeln!(
+            out,
+            "{:<10} {:<8} {:<16} {:<9} {:<10} {:<4} {:>10} {:>12}  {:<14} {:<10} fill",
+            "date", "time", "report", "status", "symbol", "side", "quantity", "price", "ExecID",
+            "account"
+        );
+    }
+    let matched: HashSet<&str> = fills.iter().map(|x| x.exec_id.as_str()).collect();
+    for trade in listed {
+        let report = &trade.report;
+        let _ = writeln!(
+            out,
+            "{:<10} {:<8} {:<16} {:<9} {:<10} {:<4} {:>10} {:>12}  {:<14} {:<10} {}",
+            report.trade_date.to_iso(),
+            time_of_day(report.time.div_euclid(1_000)),
+            report.trade_report_id,
+            trade.status.as_str(),
+            report.symbol,
+            report.side.as_str(),
+            report.quantity,
+            report.price,
+            report.exec_id,
+            report.account,
+            if matched.contains(report.exec_id.as_str()) { "yes" } else { "no" }
+        );
+    }
+
+    let active = reports
+        .iter()
+        .filter(|x| x.status == CaptureStatus::Active)
+        .count();
+    let missing = unmatched(reports.to_vec(), fills).len();
+    let unreported = unreported(reports, fills, filter);
+    let _ = writeln!(
+        out,
+        "{} report(s), {active} active, {missing} without a fill; {} fill(s) without a report",
+        reports.len(),
+        unreported.len()
+    );
+    for fill in unreported {
+        let _ = writeln!(
+            out,
+            "  unreported {} {} {} {} @ {} on {}",
+            fill.exec_id,
+            fill.symbol,
+            fill.side.as_str(),
+            fill.quantity,
+            fill.price,
+            fill.session
+        );
+    }
+    out
+}
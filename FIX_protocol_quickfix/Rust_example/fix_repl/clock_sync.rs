@@ -0,0 +1,107 @@
+// =============================================================================
+// Clock Sync Evidence Task
+// =============================================================================
+// With --ntp HOST[:PORT], the local clock is checked against that server every
+// SAMPLE_INTERVAL (trading::clock), and every REPORT_INTERVAL the period is
+// closed into a report saved with the audit trail:
+//
+//   audit/clock-sync-20240115-140000.json     offset samples, max divergence,
+//                                             source, tolerance, breaches
+//
+// The directory is --audit-dir (default: audit); the tolerance
+// --clock-tolerance-us (default: 1000, MiFID II for algorithmic trading).
+// A sample out of tolerance, or a failed query, logs a warning at once.
+//
+//   FIX> clock                        the period so far
+//   FIX> clock --report               close the period now and save it
+// =============================================================================
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use tracing::{info, warn};
+use trading::clock::{ClockSyncMonitor, ClockSyncReport};
+
+/// How often the time server is queried
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a reporting period lasts
+const REPORT_INTERVAL: Duration = Duration::from_secs(3_600);
+
+/// How long a query waits for the reply
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The monitor and where its reports are stored, shared with the shell
+pub struct ClockSync {
+    pub monitor: Arc<ClockSyncMonitor>,
+    pub audit_dir: PathBuf,
+}
+
+impl ClockSync {
+    /// Close the current period and save its report
+    pub fn save_report(&self) -> ClockSyncReport {
+        let report = self.monitor.take_report();
+        save(&report, &self.audit_dir);
+        report
+    }
+}
+
+/// Log where a report went, or why it could not be written
+fn save(report: &ClockSyncReport, dir: &Path) {
+    match report.save(dir) {
+        Ok(path) => info!(
+            source = %report.source,
+            samples = report.samples.len(),
+            max_divergence = ?report.max_divergence(),
+            compliant = report.compliant(),
+            path = %path.display(),
+            "clock sync report saved"
+        ),
+        Err(err) => warn!(dir = %dir.display(), ?err, "cannot save clock sync report"),
+    }
+}
+
+/// Sample the clock and save a report every period, for the life of the
+/// process
+///
+/// Queries block, so they run on tokio's blocking pool.
+pub async fn sync_task(clock: Arc<ClockSync>) {
+    let mut samples = tokio::time::interval(SAMPLE_INTERVAL);
+    let mut reports = tokio::time::interval(REPORT_INTERVAL);
+    // Both fire at once: the first report would be empty
+    reports.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = samples.tick() => {
+                let monitor = Arc::clone(&clock.monitor);
+                let result =
+                    tokio::task::spawn_blocking(move || monitor.sample(QUERY_TIMEOUT)).await;
+                match result {
+                    Ok(Ok(sample)) => {
+                        let bound = sample.divergence_bound();
+                        if bound > clock.monitor.tolerance() {
+                            warn!(
+                                source = clock.monitor.source(),
+                                offset_ns = sample.offset,
+                                rtt = ?sample.round_trip,
+                                "clock out of tolerance"
+                            );
+                        }
+                    }
+                    Ok(Err(err)) => {
+                        warn!(source = clock.monitor.source(), ?err, "clock sync query failed");
+                    }
+                    Err(err) => warn!("clock sync task failed: {err}"),
+                }
+            }
+            _ = reports.tick() => {
+                let report = clock.monitor.take_report();
+                save(&report, &clock.audit_dir);
+            }
+        }
+    }
+}
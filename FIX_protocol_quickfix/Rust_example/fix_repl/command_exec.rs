@@ -9,13 +9,213 @@
 // - Generic programming with ConnectionHandler trait
 // - Command execution and result display
 // - Proper I/O buffering for responsive terminal interaction
+// - Async input: the shell waits on stdin and on the shutdown signal at the
+//   same time, so CTRL-C exits as cleanly as 'quit'
+// - Every command is timed and ends with a result code (see history.rs);
+//   those that change something also go to the operator audit log, with
+//   the user's login (trading::audit)
+// - Bulk cancel of the working orders seen by the event task (see orders.rs)
+// - Watch expressions: live views repainted until Enter (see watch.rs)
+// - Book snapshots: a book drawn with our orders marked, live or from the
+//   files written by --book-export (book_export.rs)
+// - Trade blotter: the fills received, filtered by account, symbol, side and
+//   size, a page at a time or exported as CSV (trading::oms::blotter)
+// - CSV export of the orders tracked, the fills and the positions, columns
+//   picked by name (export.rs)
+// - Trade capture reports: the venue's record of the trades, asked for and
+//   reconciled with the blotter by ExecID (captures.rs)
+// - Order mass status: the venue's open orders, asked for and set against
+//   the orders tracked (trading::oms::mass_status)
+// - Session provisioning: add_session registers a session, then the REPL
+//   hands back to main() to rebuild the connection handler (trading::session::provisioning)
+// - Counterparty onboarding: session add asks for the settings one by one
+//   (onboarding.rs)
+// - Throughput self-test on a blocking thread, so the event task keeps
+//   running meanwhile (trading::bench)
+// - Heartbeat health per session (health.rs)
+// - Messages sent and received per session and MsgType, counted by the
+//   callbacks without a global lock (trading::session::stats)
+// - TestRequest and ResendRequest on demand, to exercise heartbeat and
+//   recovery handling of the counterparty
+// - Conformance scenarios played against a session on a blocking thread,
+//   fed by the callbacks' tap (trading::conformance)
+// - Session hours (--schedule): the handler started and stopped around the
+//   session windows, weekly sequence resets (trading::session::schedule)
+// - Certification self-check: the standard battery as conformance scenarios,
+//   HeartBtInt taken from the session's health (trading::conformance::certify)
+// - Message templates: send tmpl fills a named message (templates.rs)
+// - Blotter mode (--tui): live panes above the prompt (blotter.rs)
+// - Clock sync evidence (--ntp): the current period, or its report saved on
+//   demand (clock_sync.rs)
+// - Fault injection: outbound messages dropped, corrupted, delayed or
+//   renumbered by the callbacks, switched from here (trading::session::faults)
+// - Rejects (35=3, 35=j) received, with the message each one rejects
+//   (trading::session::rejects)
+// - Counterparty scorecards: uptime, rejects, ack latency, resends and fill
+//   quality per session and day, with their trend (scorecard.rs)
+// - Garbled message diagnostics: messages the engine discarded, taken apart
+//   by the logger (trading::session::garbled)
+// - Tag dictionary: messages checked against it before they are sent, its
+//   field and value names taken by send_to, and its fields looked up by tag
+//   or name (trading::session::dictionary)
+// - Venue profiles: messages to a venue checked against what it accepts
+//   before they are sent (trading::conformance::profile)
+// - Message log filter: mute takes Heartbeats and the like out of the log
+//   of the event task, summarized (mute.rs)
+// - Command roles: read-only, trader or admin, switched with login up to
+//   the grant of the user; commands above the role are DENIED (roles.rs)
+// - Remote console (--admin-socket, --admin-listen): the lines of
+//   authenticated clients run here one at a time, with their own login and
+//   role, their output sent back to them (remote.rs)
+// - Pipe mode (--pipe): lines from another process, shell commands or JSON
+//   orders, no prompt, a JSON result per command on stdout (pipe.rs)
+// - Errors: the steps of a send return a ShellError (parse, template,
+//   validation, session, send), reported once with a hint of what to try;
+//   no input stops the shell (error.rs)
+// - Outbound rate limits (--rate-limit, --rate-limit-global): what the shell
+//   sends is queued or refused over the venue's msgs/sec caps, so scripts
+//   and cancel-all stay under them (trading::session::rate_limit)
+// - Duplicate orders: ClOrdIDs generated when left out, reused ones and
+//   the same order typed twice within --duplicate-window-ms refused or
+//   warned about (trading::oms::duplicates)
+// - Send queue: what is sent to a session logged off waits for its next
+//   logon, dropped after --queue-ttl; `queue` shows what waits
+//   (trading::session::send_queue)
+// - Cancel on disconnect (--cancel-on-disconnect): `disconnects` shows what
+//   the logons did with the orders of the sessions that dropped, and the
+//   orders left for review (trading::oms::disconnect)
+// - Gateway failover (--failover): sessions down for FailoverAfter connect
+//   attempts move to their backup gateway, and back; the handler is rebuilt
+//   as after add_session. `failover` shows the gateways and forces a switch
+//   (trading::session::failover)
+// - End-of-day tasks (--eod): flatten, fills exported, store archived,
+//   ClOrdIDs reset and the day's summary, at times set per session from its
+//   close; `eod` lists them and runs one by hand (eod.rs)
+// - Daily report: orders, fills, cancel ratio, rejects, notional and
+//   slippage against the arrival price per session and symbol, as text,
+//   HTML or JSON; `report` on demand, the summary task at the close
+//   (trading::oms::report)
+//
+// Output is split in two. What a command shows (the prompt, the help text,
+// tables, listings, reports, "nothing to show" lines, progress of a long
+// command) is the shell's user interface and stays on stdout, through the
+// print! / println! of remote.rs, which copy it to a remote client. What a
+// command does (a handler started or stopped, a message sent, queued or
+// refused, an action that failed) is a structured tracing event, filtered
+// and formatted with the rest of the log (see logging.rs). New commands
+// follow the same split.
 // =============================================================================
 
-use std::io::{self, stdin, stdout, BufRead, StdinLock, Write};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    future::Future,
+    io::{self, stdout, Write},
+    mem,
+    path::{Path, PathBuf},
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use quickfix::{
+    ConnectionHandler, FieldMap, Message, QuickFixError, SessionId, SessionSettings,
+};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::{error, info, info_span, warn, Instrument};
+use trading::{
+    audit::{local_operator, AuditEntry, AuditLog, AuditQuery, AuditSource},
+    bench::{run_throughput, ThroughputOptions},
+    conformance::{
+        self, certify::CertificationSettings, profile::ConformanceProfile, Scenario,
+        SessionCounterparty, Tap,
+    },
+    gateway::metrics::Metrics,
+    session::{
+        dictionary::{Dictionary, FieldDef},
+        eod::{EodAction, EodPlan, EodRun, EodScheduler},
+        failover::{Failover, Switch},
+        file_store::FileStore,
+        faults::{Fault, FaultInjector},
+        garbled::GarbledMonitor,
+        rejects::RejectLog,
+        scorecard::{trends, Scorecard},
+        parse_session_label,
+        provisioning::{add_session, SessionSpec},
+        rate_limit::{RateLimiter, SendError},
+        runtime::{shutdown_signal, stdin_lines},
+        schedule::{reset_store, ScheduleAction, SessionScheduler},
+        send_queue::{SendQueue, Sent},
+        session_label,
+        stats::SessionStats,
+        version::FIXT_1_1,
+    },
+    oms::{
+        blotter::{self as trade_blotter, BlotterFilter, DEFAULT_PAGE_SIZE},
+        captures::{self as trade_captures, CaptureFilter, TradeCaptureBook},
+        disconnect::DisconnectGuard,
+        duplicates::DuplicateGuard,
+        mass_status::MassStatusBook,
+        report::{DailyReport, ReportFormat},
+    },
+    time::{time_of_day, unix_now, Date},
+};
+
+use crate::{
+    blotter::{Blotter, REPAINT_INTERVAL},
+    book_export::{self, BookSnapshot},
+    captures,
+    clock_sync::ClockSync,
+    command_parser::{BadCommand, SendTarget, ShellCommand},
+    eod,
+    error::{SessionProblem, ShellError},
+    export::{self, ExportKind},
+    health::HealthMonitor,
+    latency::LatencyMonitor,
+    history::{History, ResultCode},
+    logging::{label_span, session_span},
+    mute::{MessageFilter, MuteMatch, MuteRule, MuteTarget},
+    onboarding,
+    orders::{CancelFilter, OrderTracker},
+    pipe::{self, PipeResult},
+    remote::{self, print, println, RemoteClient, RemoteConsole},
+    roles::{Role, RoleGrants},
+    scorecard::{self, render_days, render_trends},
+    templates::TemplateLibrary,
+    watch::{render_trades, LiveState, WatchTarget},
+};
+
+/// BeginString of send_to when no session between the CompIDs is known yet
+const DEFAULT_BEGIN_STRING: &str = "FIX.4.4";
+
+/// How long test_request waits for the Heartbeat
+const TEST_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Discarded messages garbled shows
+const GARBLED_SHOWN: usize = 10;
+
+/// How often the session schedule is checked, with --schedule
+const SCHEDULE_TICK: Duration = Duration::from_secs(1);
+
+/// How often the sessions down are counted against FailoverAfter, with
+/// --failover
+const FAILOVER_TICK: Duration = Duration::from_secs(1);
 
-use quickfix::{send_to_target, ConnectionHandler};
+// =============================================================================
+// Why the REPL Returned
+// =============================================================================
 
-use crate::command_parser::ShellCommand;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellExit {
+    /// 'quit', CTRL-D, CTRL-C or SIGTERM: shut down
+    Quit,
+
+    /// Sessions were added or switched to another gateway: rebuild the
+    /// connection handler, then call repl() again
+    Reload,
+}
 
 // =============================================================================
 // FixShell: Interactive FIX Command Shell
@@ -24,29 +224,394 @@ use crate::command_parser::ShellCommand;
 // for sending commands to the FIX engine.
 // =============================================================================
 
-pub struct FixShell<'a> {
-    /// Locked stdin handle for efficient reading
-    /// Locking prevents multiple locks per read, improving performance
-    stdin: StdinLock<'a>,
-    
-    /// Buffer to store the last command entered by the user
-    /// Pre-allocated with reasonable capacity to avoid frequent reallocations
-    last_command: String,
+/// What the shell shares with the FIX callbacks and the event task
+pub struct ShellState {
+    /// Registry where send latencies are recorded
+    pub metrics: Arc<Metrics>,
+
+    /// Working orders, queried by cancel-all
+    pub orders: Arc<OrderTracker>,
+
+    /// State rendered by watch
+    pub live: Arc<LiveState>,
+
+    /// Heartbeat monitor shown by health
+    pub health: Arc<HealthMonitor>,
+
+    /// Inbound latencies shown by latency
+    pub latency: Arc<LatencyMonitor>,
+
+    /// Tap the conformance runs read replies from
+    pub conformance: Arc<Tap>,
+
+    /// Fault injector switched by fault
+    pub faults: Arc<FaultInjector>,
+
+    /// Counterparty statistics shown by scorecard
+    pub scorecard: Arc<Scorecard>,
+
+    /// Rejects listed by rejects
+    pub rejects: Arc<RejectLog>,
+
+    /// Trade capture reports, requested and listed by trades
+    pub captures: Arc<TradeCaptureBook>,
+
+    /// Order status reports, requested and listed by mass_status
+    pub mass_status: Arc<MassStatusBook>,
+
+    /// Garbled message diagnostics, switched by garbled
+    pub garbled: Arc<GarbledMonitor>,
+
+    /// Operator audit log, appended to and queried by audit
+    pub audit: Arc<AuditLog>,
+
+    /// Tag dictionary, checked before sending and shown by dict
+    pub dictionary: Arc<Dictionary>,
+
+    /// Message log filter, set by mute and unmute
+    pub mutes: Arc<MessageFilter>,
+}
+
+pub struct FixShell {
+    /// Lines typed by the user, read by a dedicated thread (see session::runtime)
+    /// The channel closes when stdin reaches EOF
+    /// (those of a remote client while its command runs, those of the pipe
+    /// with --pipe)
+    lines: UnboundedReceiver<String>,
+
+    /// Stdin reached EOF with the remote console on: the shell goes on
+    stdin_closed: bool,
+
+    /// The lines come from another process (--pipe): no prompt, a JSON
+    /// result on stdout per command
+    pipe: bool,
+
+    /// Lines read from the pipe, for the `seq` of their results
+    piped: u64,
+
+    /// Metrics registry, used to record send latencies
+    metrics: Arc<Metrics>,
+
+    /// Commands run so far, with their result code and time
+    history: History,
+
+    /// Working orders, maintained by the event task
+    orders: Arc<OrderTracker>,
+
+    /// Positions, books and sessions for `watch`, maintained by the event task
+    live: Arc<LiveState>,
+
+    /// Heartbeat round trips and silences for `health`, fed by the callbacks
+    health: Arc<HealthMonitor>,
+
+    /// SendingTime / TransactTime latencies for `latency`, fed by the callbacks
+    latency: Arc<LatencyMonitor>,
+
+    /// Inbound messages for `conformance`, copied by the callbacks
+    conformance: Arc<Tap>,
+
+    /// Outbound faults for `fault`, applied by the callbacks
+    faults: Arc<FaultInjector>,
+
+    /// Day-by-day statistics of each counterparty for `scorecard`, fed by
+    /// the callbacks
+    scorecard: Arc<Scorecard>,
+
+    /// Rejects for `rejects`, fed by the event task
+    rejects: Arc<RejectLog>,
+
+    /// Trade capture reports for `trades`, fed by the event task
+    captures: Arc<TradeCaptureBook>,
+
+    /// Order status reports for `mass_status`, fed by the event task
+    mass_status: Arc<MassStatusBook>,
+
+    /// Messages the engine discarded for `garbled`, fed by the logger
+    garbled: Arc<GarbledMonitor>,
+
+    /// State-changing commands, appended after they run; queried by `audit`
+    audit: Arc<AuditLog>,
+
+    /// Login recorded as the actor of every command
+    operator: String,
+
+    /// What the commands may do now, switched by login
+    role: Role,
+
+    /// The highest role of each login, from --roles
+    grants: RoleGrants,
+
+    /// Interface of the commands in the audit log: the REPL or its pipe, or
+    /// the remote console while a command of its client runs
+    source: AuditSource,
+
+    /// Clients of --admin-socket and --admin-listen
+    remote: Option<RemoteConsole>,
+
+    /// Field names and types, venue tags included: what send_to and send
+    /// tmpl are checked against, what `dict` looks up
+    dictionary: Arc<Dictionary>,
+
+    /// Mute rules of the message log, applied by the event task
+    mutes: Arc<MessageFilter>,
+
+    /// What each venue accepts, with --venue-profile: send_to and send tmpl
+    /// refuse what the profile of the target says it would reject
+    profiles: Vec<ConformanceProfile>,
+
+    /// Application messages to sessions logged off, held until they log
+    /// on (the event task flushes them), then through the msgs/sec caps of
+    /// --rate-limit like every message the shell sends
+    queue: Arc<SendQueue>,
+
+    /// ClOrdIDs of send_to and send tmpl: generated when left out, checked
+    /// for reuse, and orders checked for repeats
+    duplicates: DuplicateGuard,
+
+    /// Orders of the sessions that dropped, canceled or left for review by
+    /// the event task at the next logon; None without --cancel-on-disconnect
+    disconnects: Option<Arc<DisconnectGuard>>,
+
+    /// CTRL-C / SIGTERM, created once so the handlers stay installed for the
+    /// whole session; awaited both at the prompt and during `watch`
+    shutdown: Pin<Box<dyn Future<Output = ()>>>,
+
+    /// The shutdown signal fired (it must not be polled again)
+    shutting_down: bool,
+
+    /// Settings the connection handler is built from; add_session adds to
+    /// them, a failover rebuilds them. Shared with main(), which rebuilds
+    /// the handler.
+    settings: Rc<RefCell<SessionSettings>>,
+
+    /// Sessions added since the start, added again when a failover
+    /// rebuilds the settings from the config file
+    added: Vec<SessionSpec>,
+
+    /// A session was added or switched since the handler was built
+    reload: bool,
+
+    /// Config file the settings were read from; `session add` can append
+    /// to it
+    config_path: PathBuf,
+
+    /// Named messages for `send tmpl`, loaded at startup
+    templates: TemplateLibrary,
+
+    /// Live panes above the prompt, with --tui
+    blotter: Option<Blotter>,
+
+    /// Clock sync monitor and audit directory, with --ntp
+    clock: Option<Arc<ClockSync>>,
+
+    /// Session windows the connection handler follows, with --schedule
+    scheduler: Option<SessionScheduler>,
+
+    /// Gateways of the sessions with a backup, with --failover
+    failover: Option<Failover>,
+
+    /// End-of-day tasks of the sessions that set some, with --eod
+    eod: Option<EodScheduler>,
 }
 
-impl FixShell<'_> {
+impl FixShell {
     /// Create a new interactive shell instance
     /// 
+    /// # Arguments
+    /// * `state` - What the shell shares with the callbacks and the event task
+    /// * `settings` - Session settings, extended by add_session
+    /// * `config_path` - File the settings come from
+    /// * `templates` - Message templates for send tmpl
+    /// 
     /// # Returns
     /// A new FixShell ready to accept user input
-    pub fn new() -> Self {
+    pub fn new(
+        state: ShellState,
+        settings: Rc<RefCell<SessionSettings>>,
+        config_path: PathBuf,
+        templates: TemplateLibrary,
+    ) -> Self {
+        let ShellState {
+            metrics,
+            orders,
+            live,
+            health,
+            latency,
+            conformance,
+            faults,
+            scorecard,
+            rejects,
+            captures,
+            mass_status,
+            garbled,
+            audit,
+            dictionary,
+            mutes,
+        } = state;
         Self {
-            // Lock stdin once for the lifetime of the shell
-            stdin: stdin().lock(),
-            
-            // Pre-allocate 1KB for command buffer
-            // This is more than enough for typical commands
-            last_command: String::with_capacity(1024),
+            // Start reading stdin in the background
+            lines: stdin_lines(),
+            stdin_closed: false,
+            pipe: false,
+            piped: 0,
+
+            metrics,
+            history: History::new(),
+            orders,
+            live,
+            health,
+            latency,
+            conformance,
+            faults,
+            scorecard,
+            rejects,
+            captures,
+            mass_status,
+            garbled,
+            audit,
+            operator: local_operator(),
+            role: Role::Admin,
+            grants: RoleGrants::default(),
+            source: AuditSource::Repl,
+            remote: None,
+            dictionary,
+            mutes,
+            profiles: Vec::new(),
+            queue: Arc::new(SendQueue::new(RateLimiter::unlimited())),
+            duplicates: DuplicateGuard::new(&format!("REPL{}", unix_now())),
+            disconnects: None,
+            shutdown: Box::pin(shutdown_signal()),
+            shutting_down: false,
+            settings,
+            added: Vec::new(),
+            reload: false,
+            config_path,
+            templates,
+            blotter: None,
+            clock: None,
+            scheduler: None,
+            failover: None,
+            eod: None,
+        }
+    }
+
+    /// Show the blotter panes above the prompt (--tui)
+    pub fn with_blotter(mut self, blotter: Blotter) -> Self {
+        self.blotter = Some(blotter);
+        self
+    }
+
+    /// Answer `clock` from this monitor (--ntp)
+    pub fn with_clock(mut self, clock: Arc<ClockSync>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Start and stop the connection handler around the session windows,
+    /// and reset sequence numbers at the weekly reset points (--schedule)
+    pub fn with_scheduler(mut self, scheduler: SessionScheduler) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Switch the sessions that stay down to their other gateway (--failover)
+    pub fn with_failover(mut self, failover: Failover) -> Self {
+        self.failover = Some(failover);
+        self
+    }
+
+    /// Run the end-of-day tasks of these plans at their time (--eod)
+    pub fn with_eod(mut self, eod: EodScheduler) -> Self {
+        self.eod = Some(eod);
+        self
+    }
+
+    /// Also run the lines of the remote console's clients; stdin reaching
+    /// EOF then no longer ends the shell
+    pub fn with_remote(mut self, console: RemoteConsole) -> Self {
+        self.remote = Some(console);
+        self
+    }
+
+    /// Take the lines from another process instead of the user (--pipe):
+    /// those of a named pipe or a file, or stdin's with None (see pipe.rs).
+    /// stdout then carries a JSON result per command, and what the shell
+    /// prints goes to stderr.
+    pub fn with_pipe(mut self, lines: Option<UnboundedReceiver<String>>) -> Self {
+        if let Some(lines) = lines {
+            self.lines = lines;
+        }
+        self.pipe = true;
+        self.source = AuditSource::Pipe;
+        remote::reserve_stdout();
+        self
+    }
+
+    /// Whether the handler should run now: always without a schedule
+    pub fn in_session_hours(&self) -> bool {
+        self.scheduler.as_ref().is_none_or(|x| x.is_open(unix_now()))
+    }
+
+    /// Take the grants of --roles, and start with `role`, or the grant of
+    /// the user without one
+    ///
+    /// # Returns
+    /// The role refused when it is above the grant
+    pub fn with_roles(mut self, grants: RoleGrants, role: Option<Role>) -> Result<Self, Role> {
+        let granted = grants.grant(&self.operator);
+        let role = role.unwrap_or(granted);
+        if role > granted {
+            return Err(role);
+        }
+        self.role = role;
+        self.grants = grants;
+        Ok(self)
+    }
+
+    /// Login of the user and the role it is granted, for the startup log
+    pub fn role(&self) -> (&str, Role) {
+        (&self.operator, self.role)
+    }
+
+    /// Check outbound messages against these venue profiles
+    pub fn with_profiles(mut self, profiles: Vec<ConformanceProfile>) -> Self {
+        self.profiles = profiles;
+        self
+    }
+
+    /// Check orders for duplicates with this guard (--duplicates,
+    /// --duplicate-window-ms)
+    pub fn with_duplicate_guard(mut self, guard: DuplicateGuard) -> Self {
+        self.duplicates = guard;
+        self
+    }
+
+    /// Show the reports of this guard, shared with the event task that
+    /// fills them (--cancel-on-disconnect)
+    pub fn with_disconnect_guard(mut self, guard: Arc<DisconnectGuard>) -> Self {
+        self.disconnects = Some(guard);
+        self
+    }
+
+    /// Send through this queue, shared with the event task that flushes it
+    /// at logon, and through its rate limits
+    pub fn with_send_queue(mut self, queue: Arc<SendQueue>) -> Self {
+        self.queue = queue;
+        self
+    }
+
+    /// Repaint the blotter panes, if shown
+    fn paint_blotter(&self) {
+        if let Some(blotter) = &self.blotter {
+            blotter.paint(&self.orders, &self.live);
+        }
+    }
+
+    /// Lay the blotter out from scratch, if shown
+    fn enter_blotter(&mut self) {
+        if let Some(blotter) = &mut self.blotter {
+            blotter.enter();
+            blotter.paint(&self.orders, &self.live);
         }
     }
 
@@ -54,34 +619,42 @@ impl FixShell<'_> {
     // User Input Handling
     // =========================================================================
     
-    /// Read one line of input from the user
-    /// 
-    /// This function:
-    /// 1. Displays the prompt "FIX> "
-    /// 2. Waits for user input (blocking)
-    /// 3. Stores the input in last_command buffer
+    /// Display the prompt "FIX> "
     /// 
     /// # Returns
     /// Ok(()) on success, Err on I/O error
-    fn read_user_input(&mut self) -> io::Result<()> {
-        // Print prompt
+    fn prompt() -> io::Result<()> {
         let mut stdout = stdout().lock();
         write!(stdout, "FIX> ")?;
         
         // Flush immediately so prompt appears before waiting for input
         // Without flush, the prompt might not appear until after user types
-        stdout.flush()?;
-        
-        // Drop stdout lock to allow command output
-        drop(stdout);
+        stdout.flush()
+    }
 
-        // Clear previous command from buffer (reuse allocation)
-        self.last_command.clear();
-        
-        // Read one line from stdin (blocks until Enter is pressed)
-        self.stdin.read_line(&mut self.last_command)?;
+    /// The prompt, unless stdin is closed or piped; a terminal gone is not
+    /// a reason to stop serving the remote console
+    fn show_prompt(&self) {
+        if self.stdin_closed || self.pipe {
+            return;
+        }
+        if let Err(err) = Self::prompt() {
+            warn!("cannot show the prompt: {err}");
+        }
+    }
 
-        Ok(())
+    /// Ask a yes/no question and wait for the answer
+    /// 
+    /// Anything but `y` / `yes` (including CTRL-D) means no
+    async fn confirm(&mut self, question: &str) -> bool {
+        print!("{question} [y/N] ");
+        if stdout().flush().is_err() {
+            return false;
+        }
+        matches!(
+            self.lines.recv().await.as_deref().map(str::trim),
+            Some("y" | "yes")
+        )
     }
 
     // =========================================================================
@@ -97,11 +670,14 @@ impl FixShell<'_> {
     /// # Arguments
     /// * `command` - The parsed command to execute
     /// * `connection_handler` - The FIX connection handler (Acceptor or Initiator)
-    fn exec_command<C: ConnectionHandler>(
+    /// 
+    /// # Returns
+    /// The result code shown after the command and kept in the history
+    async fn exec_command<C: ConnectionHandler>(
         &mut self,
         command: ShellCommand,
         connection_handler: &mut C,
-    ) {
+    ) -> ResultCode {
         match command {
             // -----------------------------------------------------------------
             // Help Command
@@ -110,12 +686,82 @@ impl FixShell<'_> {
             // -----------------------------------------------------------------
             ShellCommand::Help => {
                 println!("Available commands:");
-                println!("- status : Print connection handler status");
+                println!("- status : Print connection handler status, and the next open / close of each session with --schedule");
+                println!("- health : Heartbeat round trips, missed heartbeats and clock skew per session");
+                println!("- latency [--reset] : Inbound latency vs SendingTime (52) / TransactTime (60)");
+                println!("    : p50/p99/p99.9 per session and MsgType; --reset starts over");
+                println!("- clock [--report] : Offsets against the --ntp server this period, max divergence");
+                println!("    : --report saves the period to the audit directory and starts a new one");
+                println!("- stats [SESSION] : Messages in / out per session, last message, largest inbound gap");
+                println!("    : with SESSION (as for watch session), by MsgType");
                 println!("- start  : Start connection handler");
                 println!("- block  : Block connection handler");
                 println!("- poll   : Poll connection handler");
                 println!("- stop   : Stop connection handler");
-                println!("- send_to K1=V1|K2=V2|… sender target : Create new FIX message");
+                println!("- send_to K1=V1|K2=V2|… sender target [--version V] : Create new FIX message");
+                println!("    (Kn: tag or field name, Vn: value or value name, as dict shows them)");
+                println!("    (V: FIX.4.0 to FIX.4.4, FIX.5.0, FIX.5.0SP1, FIX.5.0SP2; default: the session's)");
+                println!("- send tmpl NAME [KEY=VALUE…] : Send a message from a template");
+                println!("- templates : List the loaded templates and their placeholders");
+                println!("- history [--stats] : Last commands / per-command timings");
+                println!("- cancel-all [--symbol S] [--side buy|sell] [--session N] [--older-than 5m] [--mass]");
+                println!("    : Cancel working orders (one 35=F each, or one 35=q per session with --mass)");
+                println!("- watch positions [S] | book S [DEPTH] | session N | quotes [S] | blotter [FILTERS] [--interval MS]");
+                println!("    : Live view, repainted every MS (default 1000) until Enter");
+                println!("- book S [DEPTH] [--from FILE [--at TIME]] : Draw a book, our orders marked *QTY");
+                println!("    : live, or the last snapshot in a --book-export file (at TIME: UTCTimestamp or ISO 8601)");
+                println!("- blotter [--account A] [--symbol S] [--side buy|sell] [--min-qty N] [--page N] [--csv FILE]");
+                println!("    : Fills received, newest first, {DEFAULT_PAGE_SIZE} a page; --csv writes every match, oldest first");
+                println!("- export orders|fills|positions FILE [--columns C1,C2,…] : Orders tracked, fills or positions as CSV");
+                println!("    : orders: sent_ms,age_secs,session,cl_ord_id,symbol,side,quantity,price,state");
+                println!("    : fills: time_ms,session,exec_id,cl_ord_id,account,symbol,side,quantity,price");
+                println!("    : positions: symbol,bought,sold,net,avg_buy,avg_sell,fills");
+                println!("- trades request N [--date YYYYMMDD] [--symbol S] : Ask session N for the venue's trade capture reports (35=AD) of a day, today by default");
+                println!("- trades [--session N] [--date YYYYMMDD] [--symbol S] [--unmatched] [--csv FILE] : Trade capture reports received, matched with the blotter by ExecID");
+                println!("- report [N] [--date YYYYMMDD] [--format text|html|json] [--out FILE] : Daily trading report: orders, fills, cancel ratio, rejects, notional and slippage per symbol");
+                println!("- mass_status N [--symbol S] : Ask session N for the status of its orders (35=AF), set against ours when all are in");
+                println!("- mass_status : Requests sent, with the orders only the venue or only we hold open");
+                println!("- redraw : Lay the blotter out again, e.g. after resizing the terminal (--tui)");
+                println!("- add_session BEGIN SENDER TARGET [port=N] [KEY=VALUE…]");
+                println!("    : Add a session; the connection handler restarts to pick it up");
+                println!("- session add : Same, question by question, and print the counterparty's config");
+                println!("- selftest throughput [SECONDS] [--store memory|file] [--multi-threaded]");
+                println!("    : Loop orders through an in-process pair, report msgs/s and latency");
+                println!("- test_request N : Send a TestRequest (35=1), report the Heartbeat round trip");
+                println!("- resend N BEGIN END : Send a ResendRequest (35=2), END 0 = up to the last");
+                println!("- conformance FILE N [--report PATH] : Run the scenarios of FILE against N");
+                println!("    : pass/fail per step; PATH.json gets the JSON report, others the text");
+                println!("- certify N [--symbol S] [--qty Q] [--price P] [--report PATH] : Standard checks after a new connection:");
+                println!("    : heartbeat, test request, resend, session reject, order round trip (default 100 ZVZZT at 1.00)");
+                println!("    (N: session index, label or CompID, as for watch session)");
+                println!("- fault [off | drop N | corrupt N | jitter MS | skip-seq N | heartbeat S]");
+                println!("    : Break outbound traffic on purpose to test the counterparty's recovery; 0 = off");
+                println!("    : drop / corrupt every Nth app message, skip a MsgSeqNum every Nth message,");
+                println!("    : delay app messages up to MS, hold heartbeats S seconds; alone: what is on");
+                println!("- rejects [N] : Last N (default 20) Rejects / BusinessMessageRejects received");
+                println!("- queue [clear [N]] : Messages sent while their session was logged off, waiting for its logon");
+                println!("    : flushed in order at the logon, dropped after --queue-ttl; clear drops them now");
+                println!("- disconnects [resolve [ClOrdID]] : Orders open when a session dropped, canceled at its logon or left for review");
+                println!("    : (--cancel-on-disconnect); resolve takes one, or all, off the review list");
+                println!("- failover [all | N] : Gateway of each session with a backup (--failover), switches made");
+                println!("    : all / N switches them to their other gateway now; the handler restarts");
+                println!("- eod [run TASK [N]] : End-of-day tasks of each session (--eod) and when they run next");
+                println!("    : run runs one now for the last close (flatten, export_fills, archive_store,");
+                println!("    : reset_order_ids, summary), for session N or every session that has it");
+                println!("- scorecard [SESSION] [--days N] [--csv FILE] : Uptime, rejects, ack latency, resends and fill quality per counterparty (day by day for one session)");
+                println!("    : with the rejected message's type, ClOrdID, tag, reason and text");
+                println!("- garbled [on | off] : Messages the engine discarded (bad BodyLength, CheckSum, header)");
+                println!("    : each with its bytes field by field and what is wrong; on / off switches the diagnostics");
+                println!("- audit [N] [--since AGE] [--source S] [--actor A] [--grep TEXT] [--failed]");
+                println!("    : Last N (default 20) state-changing commands of the audit log: who, when, what, result");
+                println!("    (S: repl, remote, pipe, console, rest or admin; AGE: 30s, 5m, 2h)");
+                println!("- dict [TAG | NAME] : A field of the tag dictionary, with its type and values");
+                println!("    : alone, the venue fields loaded with --dictionary");
+                println!("- mute [admin | TAG=VALUE] [--session N] [--drop] : Take messages out of the log");
+                println!("    : counted in a summary line every minute, or not at all with --drop; alone, the rules");
+                println!("- unmute all | admin | TAG=VALUE [--session N] : Log them again");
+                println!("- login [read-only | trader | admin] : Switch role, up to the one granted; alone, the role");
+                println!("    : read-only looks, trader sends orders, admin controls the sessions");
                 println!();
                 println!("Examples:");
                 println!("  send_to 35=D|55=AAPL|54=1|38=100 CLIENT EXCHANGE");
@@ -123,6 +769,22 @@ impl FixShell<'_> {
                 println!();
                 println!("  send_to 35=V|262=REQ1|263=1|55=MSFT CLIENT EXCHANGE");
                 println!("    (Subscribe to market data for MSFT)");
+                println!();
+                println!("  send_to MsgType=D|Symbol=AAPL|Side=Buy|OrderQty=100|OrdType=Market CLIENT EXCHANGE");
+                println!("    (Market buy order for 100 AAPL, with names from the tag dictionary)");
+                println!();
+                println!("  send_to 35=D|55=AAPL|54=1|38=100|40=1 CLIENT EXCHANGE --version FIX.5.0SP2");
+                println!("    (Over a FIXT.1.1 session, with ApplVerID 9)");
+                println!();
+                println!("  send tmpl new_limit_order symbol=AAPL qty=100 px=150");
+                println!("    (Fill the new_limit_order template and send it)");
+                println!();
+                println!("  cancel-all --symbol AAPL --older-than 5m");
+                println!("    (Cancel AAPL orders sent more than 5 minutes ago)");
+                println!();
+                println!("  mute MsgType=Heartbeat --session 2");
+                println!("    (Only count the Heartbeats of session 2)");
+                ResultCode::Ok
             }
             
             // -----------------------------------------------------------------
@@ -133,11 +795,13 @@ impl FixShell<'_> {
             // - For Initiator: Begin attempting to connect to configured host
             // -----------------------------------------------------------------
             ShellCommand::Start => {
-                println!("RESULT: {:?}", connection_handler.start());
+                let result = connection_handler.start();
+                info!(command = "start", ?result);
                 // Possible results:
                 // - Ok(()) - Successfully started
                 // - Err(AlreadyRunning) - Already started
                 // - Err(ConfigurationError) - Invalid configuration
+                engine_code(&result)
             }
             
             // -----------------------------------------------------------------
@@ -149,10 +813,12 @@ impl FixShell<'_> {
             // - Flushes message stores
             // -----------------------------------------------------------------
             ShellCommand::Stop => {
-                println!("RESULT: {:?}", connection_handler.stop());
+                let result = connection_handler.stop();
+                info!(command = "stop", ?result);
                 // Possible results:
                 // - Ok(()) - Successfully stopped
                 // - Err(NotRunning) - Already stopped
+                engine_code(&result)
             }
             
             // -----------------------------------------------------------------
@@ -162,13 +828,87 @@ impl FixShell<'_> {
             // Useful for debugging connectivity issues
             // -----------------------------------------------------------------
             ShellCommand::Status => {
-                println!(
-                    "Connection handler status: logged_on={:?}, stopped={:?}",
-                    connection_handler.is_logged_on(),
-                    connection_handler.is_stopped(),
-                );
+                let logged_on = connection_handler.is_logged_on();
+                let stopped = connection_handler.is_stopped();
+                info!(command = "status", ?logged_on, ?stopped);
+                if let Some(scheduler) = &self.scheduler {
+                    let now = unix_now();
+                    for session in scheduler.sessions() {
+                        let schedule = session.schedule.status(now);
+                        info!(command = "status", session = %session.session, %schedule);
+                    }
+                }
                 // logged_on=true means at least one session is active
                 // stopped=true means the handler is not running
+                if logged_on.is_ok() && stopped.is_ok() {
+                    ResultCode::Ok
+                } else {
+                    ResultCode::EngineError
+                }
+            }
+            
+            // -----------------------------------------------------------------
+            // Health Command
+            // -----------------------------------------------------------------
+            // Heartbeat view of every session: time since the last inbound
+            // message, TestRequest round trips, heartbeats missed
+            // -----------------------------------------------------------------
+            ShellCommand::Health => {
+                self.health.print();
+                ResultCode::Ok
+            }
+            
+            // -----------------------------------------------------------------
+            // Latency Command
+            // -----------------------------------------------------------------
+            // Age of inbound messages against their SendingTime (52) and
+            // TransactTime (60), per session and MsgType (latency.rs)
+            // -----------------------------------------------------------------
+            ShellCommand::Latency { reset } => {
+                self.latency.print();
+                if reset {
+                    self.latency.reset();
+                }
+                ResultCode::Ok
+            }
+            
+            // -----------------------------------------------------------------
+            // Stats Command
+            // -----------------------------------------------------------------
+            // Messages each way per session, or by MsgType for one session
+            // (trading::session::stats)
+            // -----------------------------------------------------------------
+            ShellCommand::Stats { session } => self.stats(session.as_deref()),
+            
+            // -----------------------------------------------------------------
+            // Clock Command
+            // -----------------------------------------------------------------
+            // Offsets against the --ntp server this period; --report files
+            // the evidence now instead of at the end of the hour
+            // -----------------------------------------------------------------
+            ShellCommand::Clock { report } => {
+                let Some(clock) = &self.clock else {
+                    warn!(command = "clock", "no time server, start with --ntp HOST[:PORT]");
+                    return ResultCode::BadCommand;
+                };
+                let report = if report {
+                    clock.save_report()
+                } else {
+                    clock.monitor.report()
+                };
+                print!("{}", report.to_text());
+                ResultCode::Ok
+            }
+            
+            // -----------------------------------------------------------------
+            // Redraw Command
+            // -----------------------------------------------------------------
+            // Measure the terminal again and repaint the blotter (no-op
+            // without --tui)
+            // -----------------------------------------------------------------
+            ShellCommand::Redraw => {
+                self.enter_blotter();
+                ResultCode::Ok
             }
             
             // -----------------------------------------------------------------
@@ -179,9 +919,10 @@ impl FixShell<'_> {
             // message arrives or timeout occurs
             // -----------------------------------------------------------------
             ShellCommand::Block => {
-                println!("RESULT: {:?}", connection_handler.block());
-                println!("(Blocked until message received)");
+                let result = connection_handler.block();
+                info!(command = "block", ?result, "blocked until message received");
                 // Use this to test message receiving without polling
+                engine_code(&result)
             }
             
             // -----------------------------------------------------------------
@@ -192,10 +933,12 @@ impl FixShell<'_> {
             // Returns immediately whether or not messages were found
             // -----------------------------------------------------------------
             ShellCommand::Poll => {
-                println!("RESULT: {:?}", connection_handler.poll());
+                let result = connection_handler.poll();
+                info!(command = "poll", ?result);
                 // Ok(true) - Messages were processed
                 // Ok(false) - No messages pending
                 // Err(...) - Error occurred
+                engine_code(&result)
             }
             
             // -----------------------------------------------------------------
@@ -204,127 +947,2351 @@ impl FixShell<'_> {
             // Send a FIX message to a specific session
             // This is the most powerful command - allows sending any FIX message
             // -----------------------------------------------------------------
-            ShellCommand::SendMessage(msg, session_id) => {
-                println!("Sending {msg:?} to {session_id:?}");
-                
-                // send_to_target is the main function for sending FIX messages
-                // It will:
-                // 1. Call on_msg_to_app() callback (your last chance to modify)
-                // 2. Add standard header fields (sequence number, timestamp, etc.)
-                // 3. Calculate checksum
-                // 4. Send over the network
-                // 5. Store in message log
-                println!("SEND_RESULT: {:?}", send_to_target(msg, &session_id));
-                
-                // Possible results:
-                // - Ok(()) - Message queued for sending
-                // - Err(SessionNotFound) - No session with that ID
-                // - Err(NotLoggedOn) - Session exists but not logged on
-                // - Err(ValidationError) - Message failed validation
+            ShellCommand::SendMessage { fields, target } => {
+                let result = self
+                    .build_message(&fields)
+                    .and_then(|msg| self.send_message("send_to", msg, &target));
+                outcome("send_to", result)
             }
             
             // -----------------------------------------------------------------
-            // No Operation / Quit
+            // Send Template Command
             // -----------------------------------------------------------------
-            // Do nothing - user pressed Enter or typed quit
+            // Fill a named template with the given values, then send the
+            // message as send_to would
             // -----------------------------------------------------------------
-            ShellCommand::NoOperation | ShellCommand::Quit => {}
-        }
-    }
-
-    // =========================================================================
-    // Main REPL Loop
-    // =========================================================================
-    
-    /// Run the interactive Read-Eval-Print Loop
-    /// 
-    /// This is the main entry point for the interactive shell. It:
-    /// 1. Displays a welcome message
-    /// 2. Prompts for user input
-    /// 3. Parses the input into a command
-    /// 4. Executes the command
-    /// 5. Repeats until user quits
-    /// 
-    /// # Arguments
-    /// * `connection_handler` - The FIX connection handler to control
-    /// 
-    /// # Termination
-    /// The loop terminates when:
-    /// - User types "quit" or "q"
-    /// - User presses CTRL-D (EOF)
-    pub fn repl<C: ConnectionHandler>(&mut self, connection_handler: &mut C) {
-        // Display welcome message
-        println!(">> Type 'help' or '?' for more information, 'quit' or 'q' to exit.");
-
-        // Main loop - runs until user quits
-        loop {
-            // ================================================================
-            // Step 1: Read user input
-            // ================================================================
-            // This blocks waiting for the user to type something and press Enter
-            // ================================================================
+            ShellCommand::SendTemplate { name, values } => {
+                let result = self
+                    .templates
+                    .get(&name)
+                    .and_then(|template| template.expand(&values))
+                    .map_err(ShellError::from)
+                    .and_then(|(msg, target)| self.send_message("send tmpl", msg, &target));
+                outcome("send tmpl", result)
+            }
             
-            self.read_user_input().expect("I/O error");
-
-            // ================================================================
-            // Step 2: Handle EOF (CTRL-D)
-            // ================================================================
-            // If stdin reaches EOF (user pressed CTRL-D), read_line returns
-            // an empty string. This is a common way to exit interactive programs.
-            // ================================================================
+            // -----------------------------------------------------------------
+            // Templates Command
+            // -----------------------------------------------------------------
+            ShellCommand::Templates => {
+                self.templates.print();
+                ResultCode::Ok
+            }
             
-            if self.last_command.is_empty() {
-                println!("CTRL-D");
-                break;
+            // -----------------------------------------------------------------
+            // History Command
+            // -----------------------------------------------------------------
+            // Show the last commands, or per-command timings with --stats
+            // The history command itself is recorded after it prints
+            // -----------------------------------------------------------------
+            ShellCommand::History { stats } => {
+                if stats {
+                    self.history.print_stats();
+                } else {
+                    self.history.print();
+                }
+                ResultCode::Ok
             }
             
-            // ================================================================
-            // Step 3: Parse and execute the command
-            // ================================================================
-            // Try to parse the input string into a ShellCommand
-            // If successful, execute it. If parsing fails, show error.
-            // ================================================================
+            // -----------------------------------------------------------------
+            // Cancel-All Command
+            // -----------------------------------------------------------------
+            // Cancel every working order matching the filters, after the
+            // user confirmed the list
+            // -----------------------------------------------------------------
+            ShellCommand::CancelAll(filter) => self.cancel_all(filter).await,
+            
+            // -----------------------------------------------------------------
+            // Watch Command
+            // -----------------------------------------------------------------
+            // Repaint a live view until the user presses Enter
+            // -----------------------------------------------------------------
+            ShellCommand::Watch { target, interval } => {
+                self.watch(&target, interval).await;
+                // watch took the whole screen
+                self.enter_blotter();
+                ResultCode::Ok
+            }
             
-            match self.last_command.parse::<ShellCommand>() {
-                // User wants to quit
-                Ok(ShellCommand::Quit) => break,
-                
-                // Execute the parsed command
-                Ok(cmd) => self.exec_command(cmd, connection_handler),
-                
-                // Parsing failed - show error message
-                Err(err) => eprintln!("Error when running command: {err}"),
+            // -----------------------------------------------------------------
+            // Book Command
+            // -----------------------------------------------------------------
+            // Draw a book once, for the terminal or a postmortem
+            // -----------------------------------------------------------------
+            ShellCommand::Book { symbol, depth, from, at } => {
+                self.book(&symbol, depth, from.as_deref(), at)
+            }
+            
+            // -----------------------------------------------------------------
+            // Blotter Command
+            // -----------------------------------------------------------------
+            // A page of the fills received, or all of them to a CSV file
+            // -----------------------------------------------------------------
+            ShellCommand::Blotter { filter, page, csv } => {
+                self.blotter(&filter, page, csv.as_deref())
+            }
+            
+            // -----------------------------------------------------------------
+            // Export Command
+            // -----------------------------------------------------------------
+            // Orders, fills or positions to a CSV file, for a spreadsheet
+            // -----------------------------------------------------------------
+            ShellCommand::Export { kind, path, columns } => {
+                self.export(kind, &path, &columns)
+            }
+            
+            // -----------------------------------------------------------------
+            // Trades Commands
+            // -----------------------------------------------------------------
+            // Ask the venue for its trade capture reports, then set them
+            // against the fills received
+            // -----------------------------------------------------------------
+            ShellCommand::RequestTrades { session, date, symbol } => outcome(
+                "trades request",
+                self.request_trades(&session, date, symbol.as_deref()),
+            ),
+            ShellCommand::Trades { filter, unmatched, csv } => {
+                self.trades(filter, unmatched, csv.as_deref())
             }
-        }
-    }
-}
 
-// =============================================================================
-// Usage Pattern
-// =============================================================================
-// This shell demonstrates several important Rust patterns:
-//
-// 1. Generic Programming:
-//    - Works with any type implementing ConnectionHandler
-//    - No code duplication for Acceptor vs Initiator
-//
-// 2. Resource Management:
-//    - StdinLock held for shell lifetime (efficient)
-//    - String buffer reused across iterations (no allocation overhead)
-//    - Explicit stdout flushing for responsive UI
-//
-// 3. Error Handling:
-//    - Uses Result types throughout
-//    - Graceful error messages for users
-//    - Panics only on truly unexpected errors (I/O failure)
-//
-// 4. Interactive UX:
-//    - Clear prompts and help text
-//    - Immediate feedback on command execution
-//    - Support for CTRL-D (standard Unix convention)
-//
-// =============================================================================
-// Testing Workflow Example
+            // -----------------------------------------------------------------
+            // Report Command
+            // -----------------------------------------------------------------
+            // The day's trading per session and symbol, printed or written
+            // -----------------------------------------------------------------
+            ShellCommand::Report { session, date, format, out } => {
+                self.report(session.as_deref(), date, format, out.as_deref())
+            }
+            
+            // -----------------------------------------------------------------
+            // Mass Status Command
+            // -----------------------------------------------------------------
+            // Ask the venue for the status of every order it holds; the event
+            // task reconciles once the last report is in
+            // -----------------------------------------------------------------
+            ShellCommand::MassStatus { session: Some(session), symbol } => outcome(
+                "mass_status",
+                self.request_mass_status(&session, symbol.as_deref()),
+            ),
+            ShellCommand::MassStatus { session: None, .. } => self.show_mass_status(),
+            
+            // -----------------------------------------------------------------
+            // Add Session Command
+            // -----------------------------------------------------------------
+            // Register the session now; the REPL returns right after so that
+            // main() rebuilds the connection handler with it
+            // -----------------------------------------------------------------
+            ShellCommand::AddSession(spec) => self.add_session(&spec),
+            
+            // -----------------------------------------------------------------
+            // Session Add Command
+            // -----------------------------------------------------------------
+            // Same, with the settings asked one by one and checked, plus the
+            // config the counterparty needs
+            // -----------------------------------------------------------------
+            ShellCommand::OnboardSession => self.onboard_session().await,
+            
+            // -----------------------------------------------------------------
+            // Self-Test Command
+            // -----------------------------------------------------------------
+            // Measure what the engine sustains with the chosen store and
+            // threading model, independently of the configured sessions
+            // -----------------------------------------------------------------
+            ShellCommand::SelfTest(options) => self.selftest(options).await,
+            
+            // -----------------------------------------------------------------
+            // TestRequest Command
+            // -----------------------------------------------------------------
+            // Ask the counterparty for a Heartbeat and time it; the round
+            // trip also shows up in `health`
+            // -----------------------------------------------------------------
+            ShellCommand::TestRequest(selector) => self.test_request(&selector).await,
+            
+            // -----------------------------------------------------------------
+            // Resend Command
+            // -----------------------------------------------------------------
+            // Ask the counterparty to replay a range; what comes back
+            // (PossDup messages, SequenceReset-GapFill) is in the logs and in
+            // `watch session`
+            // -----------------------------------------------------------------
+            ShellCommand::Resend {
+                session,
+                begin,
+                end,
+            } => outcome("resend", self.resend(&session, begin, end)),
+            
+            // -----------------------------------------------------------------
+            // Conformance Command
+            // -----------------------------------------------------------------
+            // Certification dry run: scripted messages, expected replies,
+            // a report to hand over to the counterparty
+            // -----------------------------------------------------------------
+            ShellCommand::Conformance {
+                path,
+                session,
+                report,
+            } => self.conformance(&path, &session, report.as_deref()).await,
+            ShellCommand::Certify {
+                session,
+                settings,
+                report,
+            } => self.certify(settings, &session, report.as_deref()).await,
+            
+            // -----------------------------------------------------------------
+            // Fault Command
+            // -----------------------------------------------------------------
+            // Break our side of the sessions on purpose, then watch the
+            // counterparty recover (`watch session`, the logs)
+            // -----------------------------------------------------------------
+            ShellCommand::Faults { setting, clear } => self.faults(setting, clear),
+            
+            // -----------------------------------------------------------------
+            // Rejects Command
+            // -----------------------------------------------------------------
+            ShellCommand::Rejects { limit } => self.rejects(limit),
+            
+            // -----------------------------------------------------------------
+            // Queue Command
+            // -----------------------------------------------------------------
+            // Messages sent to sessions logged off, waiting for the logon
+            // that flushes them, or dropped by hand before it
+            // -----------------------------------------------------------------
+            ShellCommand::Queue { clear, session } => self.send_queue(clear, session.as_deref()),
+            ShellCommand::Disconnects { resolve, cl_ord_id } => {
+                self.disconnects(resolve, cl_ord_id.as_deref())
+            }
+            ShellCommand::Failover { switch, session } => {
+                self.failover(switch, session.as_deref(), connection_handler)
+            }
+            
+            // -----------------------------------------------------------------
+            // End-of-Day Command
+            // -----------------------------------------------------------------
+            // The tasks the sessions set around their close (--eod), or one
+            // run now, as when its time comes
+            // -----------------------------------------------------------------
+            ShellCommand::Eod { run: None } => self.eod_plans(),
+            ShellCommand::Eod { run: Some((action, session)) } => {
+                self.eod_run(action, session.as_deref(), connection_handler).await
+            }
+            
+            // -----------------------------------------------------------------
+            // Scorecard Command
+            // -----------------------------------------------------------------
+            ShellCommand::Scorecard { session, days, csv } => {
+                self.scorecard(session.as_deref(), days, csv.as_deref())
+            }
+            
+            // -----------------------------------------------------------------
+            // Garbled Command
+            // -----------------------------------------------------------------
+            ShellCommand::Garbled { enabled } => self.garbled(enabled),
+            
+            // -----------------------------------------------------------------
+            // Audit Command
+            // -----------------------------------------------------------------
+            ShellCommand::Audit(query) => self.audit_query(&query),
+            
+            // -----------------------------------------------------------------
+            // Dictionary Command
+            // -----------------------------------------------------------------
+            ShellCommand::Dictionary { field } => self.dictionary(field.as_deref()),
+            
+            // -----------------------------------------------------------------
+            // Mute / Unmute Commands
+            // -----------------------------------------------------------------
+            ShellCommand::Mute { target: None, .. } => self.mute_rules(),
+            ShellCommand::Mute { target: Some(target), session, drop } => {
+                self.mute(&target, session.as_deref(), drop)
+            }
+            ShellCommand::Unmute { target, session } => {
+                self.unmute(target.as_ref(), session.as_deref())
+            }
+            
+            // -----------------------------------------------------------------
+            // Command Roles
+            // -----------------------------------------------------------------
+            ShellCommand::Login(role) => self.login(role),
+            
+            // -----------------------------------------------------------------
+            // No Operation / Quit
+            // -----------------------------------------------------------------
+            // Do nothing - user pressed Enter or typed quit
+            // -----------------------------------------------------------------
+            ShellCommand::NoOperation | ShellCommand::Quit => ResultCode::Ok,
+        }
+    }
+
+    // =========================================================================
+    // Bulk Cancel
+    // =========================================================================
+    
+    /// Cancel the working orders matching `filter`
+    /// 
+    /// Lists the orders and asks for confirmation first. Cancels are sent
+    /// one by one (35=F), or as one OrderMassCancelRequest (35=q) per session
+    /// with --mass. The venue confirms them asynchronously: the summary counts
+    /// what was sent, the tracker follows the ExecutionReports.
+    async fn cancel_all(&mut self, filter: CancelFilter) -> ResultCode {
+        let orders: Vec<_> = self
+            .orders
+            .working_orders()
+            .into_iter()
+            .filter(|x| filter.matches(x))
+            .collect();
+        if orders.is_empty() {
+            info!(command = "cancel-all", "no working order matches");
+            return ResultCode::Ok;
+        }
+
+        // One representative order per session, for --mass
+        let sessions: BTreeMap<_, _> =
+            orders.iter().map(|x| (x.session.as_str(), x)).collect();
+
+        for order in &orders {
+            println!("  {order}");
+        }
+        let question = if filter.mass {
+            format!(
+                "Send an OrderMassCancelRequest to {} session(s), covering {} order(s)?",
+                sessions.len(),
+                orders.len()
+            )
+        } else {
+            format!("Cancel {} order(s)?", orders.len())
+        };
+        if !self.confirm(&question).await {
+            return ResultCode::Aborted;
+        }
+
+        let (mut sent, mut queued, mut failed) = (0, 0, 0);
+        if filter.mass {
+            for (label, order) in &sessions {
+                let _span = label_span(label).entered();
+                let result = order.session_id().map_err(SendError::from).and_then(|session_id| {
+                    let msg = filter.to_mass_cancel_request(&self.orders.next_cancel_id())?;
+                    self.queue.send(msg, &session_id)
+                });
+                match result {
+                    Ok(Sent::Now) => sent += 1,
+                    Ok(Sent::Queued { .. }) => queued += 1,
+                    Err(err) => {
+                        failed += 1;
+                        warn!(command = "cancel-all", ?err, "mass cancel failed");
+                    }
+                }
+            }
+        } else {
+            for order in &orders {
+                let _span = label_span(&order.session).entered();
+                let result = order.session_id().map_err(SendError::from).and_then(|session_id| {
+                    let msg = order.to_cancel_request(&self.orders.next_cancel_id())?;
+                    self.queue.send(msg, &session_id)
+                });
+                match result {
+                    Ok(Sent::Now) => sent += 1,
+                    Ok(Sent::Queued { .. }) => queued += 1,
+                    Err(err) => {
+                        failed += 1;
+                        warn!(
+                            command = "cancel-all",
+                            cl_ord_id = %order.cl_ord_id,
+                            ?err,
+                            "cancel failed"
+                        );
+                    }
+                }
+            }
+        }
+
+        info!(
+            command = "cancel-all",
+            mass = filter.mass,
+            matched = orders.len(),
+            sent,
+            queued,
+            failed
+        );
+        if failed == 0 {
+            ResultCode::Ok
+        } else {
+            ResultCode::SendFailed
+        }
+    }
+
+    // =========================================================================
+    // Session Provisioning
+    // =========================================================================
+    
+    /// Register a session with the settings and ask for a handler rebuild
+    fn add_session(&mut self, spec: &SessionSpec) -> ResultCode {
+        let result = add_session(&mut self.settings.borrow_mut(), spec);
+        match result {
+            Ok(_) => {
+                info!(command = "add_session", session = %spec, "session registered");
+                self.added.push(spec.clone());
+                self.reload = true;
+                ResultCode::Ok
+            }
+            Err(err) => {
+                warn!(command = "add_session", session = %spec, "cannot add session: {err}");
+                ResultCode::EngineError
+            }
+        }
+    }
+
+    /// Run the onboarding wizard, then register, save and print the result
+    async fn onboard_session(&mut self) -> ResultCode {
+        let connection_type = self
+            .settings
+            .borrow()
+            .with_dictionary(None, |x| x.get::<String>("ConnectionType").ok())
+            .flatten();
+        let Some(connection_type) = connection_type else {
+            warn!(command = "session add", "ConnectionType missing from the [DEFAULT] settings");
+            return ResultCode::EngineError;
+        };
+
+        let Some(onboarding) = onboarding::run(&mut self.lines, &connection_type).await else {
+            return ResultCode::Aborted;
+        };
+        println!();
+        print!("{}", onboarding.our_section());
+        if !self.confirm("Add this session?").await {
+            return ResultCode::Aborted;
+        }
+
+        let spec = onboarding.spec();
+        let code = self.add_session(&spec);
+        if !code.is_ok() {
+            return code;
+        }
+
+        let question = format!("Append it to {}?", self.config_path.display());
+        if self.confirm(&question).await {
+            let result = OpenOptions::new()
+                .append(true)
+                .open(&self.config_path)
+                .and_then(|mut file| {
+                    write!(
+                        file,
+                        "\n# Added by `session add` on {}\n{}",
+                        Date::today().to_iso(),
+                        onboarding.our_section()
+                    )
+                });
+            match result {
+                Ok(()) => info!(command = "session add", session = %spec, "saved to the config"),
+                Err(err) => warn!(command = "session add", %err, "cannot save to the config"),
+            }
+        }
+
+        println!();
+        println!("Config for the counterparty ({}):", onboarding.target_comp_id);
+        println!();
+        print!("{}", onboarding.counterparty_config());
+        code
+    }
+
+    // =========================================================================
+    // Sending
+    // =========================================================================
+    
+    /// The message of a send_to, its tags and values by number or by name
+    /// in the tag dictionary
+    fn build_message(&self, fields: &[(String, String)]) -> Result<Message, ShellError> {
+        let mut msg = Message::new();
+        for (key, raw) in fields {
+            let Some(tag) = self.dictionary.resolve(key) else {
+                return Err(ShellError::Validation(vec![format!(
+                    "{key}: unknown tag or field name"
+                )]));
+            };
+            let value = self.dictionary.resolve_value(tag, raw);
+            let result = if tag == 35 {
+                msg.with_header_mut(|h| h.set_field(35, value))
+            } else {
+                msg.set_field(tag, value)
+            };
+            if let Err(err) = result {
+                return Err(ShellError::Validation(vec![format!("{tag}={value}: {err:?}")]));
+            }
+        }
+        Ok(msg)
+    }
+
+    /// Send a message built by send_to or a template
+    fn send_message(
+        &self,
+        command: &str,
+        mut msg: Message,
+        target: &SendTarget,
+    ) -> Result<(), ShellError> {
+        self.check_fields(&msg)?;
+        let session_id = self.resolve_send_target(target, &mut msg)?;
+        let _span = session_span(&session_id).entered();
+        let checked = self.duplicates.check(&mut msg, Instant::now())?;
+        if let Some(cl_ord_id) = &checked.assigned {
+            info!(command, %cl_ord_id, "ClOrdID generated");
+        }
+        for duplicate in &checked.warnings {
+            warn!(command, %duplicate, "duplicate order, sent anyway (--duplicates warn)");
+        }
+        self.check_profiles(&session_id, &msg)?;
+        
+        // send_to_target is the main function for sending FIX messages,
+        // here behind the send queue (held while the session is logged
+        // off) and the rate limits (queued or refused over them)
+        // It will:
+        // 1. Call on_msg_to_app() callback (your last chance to modify)
+        // 2. Add standard header fields (sequence number, timestamp, etc.)
+        // 3. Calculate checksum
+        // 4. Send over the network
+        // 5. Store in message log
+        let started = Instant::now();
+        let result = self.queue.send(msg, &session_id);
+        self.metrics.observe_send_latency(&session_id, started.elapsed());
+        
+        // Possible results:
+        // - Ok(()) - Message queued for sending
+        // - Err(SessionNotFound) - No session with that ID
+        // - Err(NotLoggedOn) - Session exists but not logged on
+        // - Err(ValidationError) - Message failed validation
+        // - Err(Limited) - Over --rate-limit, not sent
+        // - Ok(Queued) - Session logged off, sent at its next logon
+        match result? {
+            Sent::Now => info!(command, "message sent"),
+            Sent::Queued { waiting } => {
+                let ttl = self.queue.ttl();
+                warn!(command, waiting, ?ttl, "session logged off, message queued until logon");
+            }
+        }
+        Ok(())
+    }
+
+    // =========================================================================
+    // FIX Versions
+    // =========================================================================
+    
+    /// SessionId for send_to and templates, with ApplVerID set when it goes over FIXT.1.1
+    /// 
+    /// Without --version, the one session known between the two CompIDs is
+    /// used. With it, the session of that version, or else a FIXT.1.1 one
+    /// carrying it as ApplVerID (1128); a FIXT message without ApplVerID is
+    /// in the DefaultApplVerID agreed at logon.
+    fn resolve_send_target(
+        &self,
+        target: &SendTarget,
+        msg: &mut Message,
+    ) -> Result<SessionId, ShellError> {
+        let known = self.live.begin_strings(&target.sender, &target.target);
+        let begin_string = match (target.version, known.as_slice()) {
+            (None, []) => DEFAULT_BEGIN_STRING.to_string(),
+            (None, [begin_string]) => begin_string.clone(),
+            (None, _) => return Err(SessionProblem::Ambiguous(known.clone()).into()),
+            (Some(version), _) => known
+                .iter()
+                .find(|x| *x == version.as_str())
+                .or_else(|| known.iter().find(|x| *x == FIXT_1_1))
+                .cloned()
+                .unwrap_or_else(|| version.begin_string().to_string()),
+        };
+
+        let session_id = SessionId::try_new(&begin_string, &target.sender, &target.target, "")
+            .map_err(|err| {
+                let label = format!("{begin_string}:{}->{}", target.sender, target.target);
+                SessionProblem::Invalid(label, err)
+            })?;
+        if let (FIXT_1_1, Some(version)) = (begin_string.as_str(), target.version) {
+            msg.with_header_mut(|h| h.set_field(1128, version.appl_ver_id()))?; // ApplVerID
+        }
+        Ok(session_id)
+    }
+
+    // =========================================================================
+    // Session Schedule
+    // =========================================================================
+    
+    /// Carry out what the schedule asks for at this tick (--schedule)
+    ///
+    /// A reset needs the handler stopped: a running one is stopped around
+    /// it and started again. Checked at the prompt only, so a long `watch`
+    /// delays the change until it returns.
+    fn follow_schedule<C: ConnectionHandler>(&mut self, connection_handler: &mut C) {
+        let Some(scheduler) = &mut self.scheduler else {
+            return;
+        };
+        let mut running = connection_handler.is_stopped().is_ok_and(|x| !x);
+        let mut start = false;
+        for action in scheduler.tick(unix_now()) {
+            match action {
+                ScheduleAction::Reset { session, store_dir, store_prefix } => {
+                    if running {
+                        info!(schedule = "reset", "connection handler STOP");
+                        if let Err(err) = connection_handler.stop() {
+                            warn!(%session, ?err, "cannot stop for the weekly reset");
+                            continue;
+                        }
+                        running = false;
+                        start = true;
+                    }
+                    match reset_store(&store_dir, &store_prefix) {
+                        Ok(()) => info!(%session, "weekly reset: MsgSeqNum back to 1"),
+                        Err(err) => warn!(%session, "cannot reset the message store: {err}"),
+                    }
+                }
+                ScheduleAction::Start => start = true,
+                ScheduleAction::Stop => {
+                    start = false;
+                    if running {
+                        info!(schedule = "close", "connection handler STOP");
+                        if let Err(err) = connection_handler.stop() {
+                            warn!(?err, "cannot stop at the end of the session hours");
+                        }
+                        running = false;
+                    }
+                }
+            }
+        }
+        if start && !running {
+            info!(schedule = "open", "connection handler START");
+            if let Err(err) = connection_handler.start() {
+                warn!(?err, "cannot start at the session open");
+            }
+        }
+    }
+
+    /// Carry out the end-of-day tasks due at this tick (--eod), checked at
+    /// the prompt like the schedule
+    fn follow_eod<C: ConnectionHandler>(&mut self, connection_handler: &mut C) {
+        let Some(eod) = &mut self.eod else {
+            return;
+        };
+        for run in eod.tick(unix_now()) {
+            self.run_eod_task(&run, connection_handler);
+        }
+    }
+
+    /// Switch the sessions down for too long to their other gateway
+    fn follow_failover<C: ConnectionHandler>(&mut self, connection_handler: &mut C) {
+        let Some(failover) = &mut self.failover else {
+            return;
+        };
+        let running = connection_handler.is_stopped().is_ok_and(|x| !x);
+        let logged_on: Vec<_> = self
+            .live
+            .sessions()
+            .into_iter()
+            .filter(|x| x.logged_on)
+            .map(|x| x.label)
+            .collect();
+        let switches = failover.tick(unix_now(), running, |session| {
+            logged_on.iter().any(|x| x == session)
+        });
+        if !switches.is_empty() {
+            self.switch_gateways(&switches, connection_handler);
+        }
+    }
+
+    /// Point the switched sessions at their new gateway: stop the handler
+    /// to empty the stores of those resetting their sequence numbers, then
+    /// rebuild the settings and hand back to main() to rebuild the handler
+    fn switch_gateways<C: ConnectionHandler>(
+        &mut self,
+        switches: &[Switch],
+        connection_handler: &mut C,
+    ) -> ResultCode {
+        let Some(failover) = &self.failover else {
+            return ResultCode::Ok;
+        };
+        for switch in switches {
+            warn!(
+                session = %switch.session,
+                gateway = switch.to.as_str(),
+                endpoint = %switch.endpoint,
+                reason = %switch.reason,
+                "failover"
+            );
+            self.metrics.on_failover(&switch.session, switch.to.as_str());
+        }
+
+        let running = connection_handler.is_stopped().is_ok_and(|x| !x);
+        if running && switches.iter().any(|x| x.reset_store.is_some()) {
+            info!(failover = "reset", "connection handler STOP");
+            if let Err(err) = connection_handler.stop() {
+                warn!(?err, "cannot stop to reset the sequence numbers");
+            }
+        }
+        for switch in switches {
+            if let Some(store_dir) = &switch.reset_store {
+                let session = &switch.session;
+                match reset_store(store_dir, &switch.store_prefix) {
+                    Ok(()) => info!(%session, "failover: MsgSeqNum back to 1"),
+                    Err(err) => warn!(%session, "cannot reset the message store: {err}"),
+                }
+            }
+        }
+
+        let mut settings = match failover.settings() {
+            Ok(settings) => settings,
+            Err(err) => {
+                warn!(command = "failover", %err, "cannot rebuild the settings");
+                return ResultCode::EngineError;
+            }
+        };
+        for spec in &self.added {
+            if let Err(err) = add_session(&mut settings, spec) {
+                warn!(command = "failover", session = %spec, "cannot add session: {err}");
+            }
+        }
+        *self.settings.borrow_mut() = settings;
+        self.reload = true;
+        ResultCode::Ok
+    }
+
+    // =========================================================================
+    // Session-Level Messages
+    // =========================================================================
+    
+    /// SessionId for a session selector, as accepted by `watch session`
+    ///
+    /// A full label is accepted even before the session produced an event.
+    fn resolve_session(&self, selector: &str) -> Result<SessionId, SessionProblem> {
+        let label = self
+            .live
+            .resolve_session(selector)
+            .unwrap_or_else(|| selector.to_string());
+        match parse_session_label(&label) {
+            Some(Ok(session_id)) => Ok(session_id),
+            Some(Err(err)) => Err(SessionProblem::Invalid(label, err)),
+            None => Err(SessionProblem::Unknown(selector.to_string())),
+        }
+    }
+
+    /// Send a TestRequest and wait for the Heartbeat carrying its TestReqID
+    async fn test_request(&mut self, selector: &str) -> ResultCode {
+        let session_id = match self.resolve_session(selector) {
+            Ok(session_id) => session_id,
+            Err(problem) => return outcome("test_request", Err(problem.into())),
+        };
+        let span = session_span(&session_id);
+
+        // Wait registered first: the answer may beat send_to_target back
+        let test_req_id = self.health.next_test_req_id();
+        let answer = self.health.await_heartbeat(&session_id, &test_req_id);
+        let result = test_request_message(&test_req_id)
+            .map_err(SendError::from)
+            .and_then(|msg| self.queue.limiter().send(msg, &session_id));
+        if let Err(err) = result {
+            self.health.forget_test_request(&session_id, &test_req_id);
+            let _span = span.entered();
+            return outcome("test_request", Err(err.into()));
+        }
+
+        // The span is entered again once the wait is over, not across it
+        let answer = tokio::time::timeout(TEST_REQUEST_TIMEOUT, answer);
+        let result = tokio::select! {
+            result = answer => result,
+            () = &mut self.shutdown => {
+                self.shutting_down = true;
+                return ResultCode::Aborted;
+            }
+        };
+
+        let _span = span.entered();
+        match result {
+            Ok(Ok(rtt)) => {
+                info!(command = "test_request", test_req_id, ?rtt, "heartbeat received");
+                ResultCode::Ok
+            }
+            Ok(Err(_)) => {
+                warn!(command = "test_request", test_req_id, "session logged out");
+                ResultCode::SendFailed
+            }
+            Err(_) => {
+                warn!(
+                    command = "test_request",
+                    test_req_id,
+                    timeout = ?TEST_REQUEST_TIMEOUT,
+                    "no heartbeat"
+                );
+                ResultCode::Timeout
+            }
+        }
+    }
+
+    /// Send a ResendRequest for `begin..=end` (0: no end)
+    fn resend(&mut self, selector: &str, begin: u64, end: u64) -> Result<(), ShellError> {
+        let session_id = self.resolve_session(selector)?;
+        let _span = session_span(&session_id).entered();
+
+        let msg = resend_request_message(begin, end)?;
+        self.queue.limiter().send(msg, &session_id)?;
+        info!(command = "resend", begin, end, "resend requested");
+        Ok(())
+    }
+
+    // =========================================================================
+    // Conformance
+    // =========================================================================
+    
+    /// Run the scenarios of `path` against a session, print the report and
+    /// save it to `report` if given
+    async fn conformance(
+        &mut self,
+        path: &Path,
+        selector: &str,
+        report: Option<&Path>,
+    ) -> ResultCode {
+        let scenarios = match conformance::load(path) {
+            Ok(scenarios) => scenarios,
+            Err(err) => {
+                warn!(command = "conformance", "{err}");
+                return ResultCode::BadCommand;
+            }
+        };
+        self.run_scenarios("conformance", scenarios, selector, report).await
+    }
+
+    /// Run the certification battery against a session: heartbeat, test
+    /// request, resend, session reject and an order round trip
+    /// 
+    /// The Heartbeat is waited for over the HeartBtInt of the session's
+    /// Logon, when the health monitor has seen one.
+    async fn certify(
+        &mut self,
+        settings: CertificationSettings,
+        selector: &str,
+        report: Option<&Path>,
+    ) -> ResultCode {
+        let label = self
+            .live
+            .resolve_session(selector)
+            .unwrap_or_else(|| selector.to_string());
+        let interval = self
+            .health
+            .snapshot()
+            .into_iter()
+            .find(|x| x.session == label)
+            .and_then(|x| x.heartbeat_interval);
+        let settings = match interval {
+            Some(interval) => settings.with_heartbeat_interval(interval),
+            None => settings,
+        };
+        let scenarios = match settings.scenarios() {
+            Ok(scenarios) => scenarios,
+            Err(err) => {
+                warn!(command = "certify", "{err}");
+                return ResultCode::BadCommand;
+            }
+        };
+        self.run_scenarios("certify", scenarios, selector, report).await
+    }
+
+    /// Play scenarios against a session, print the report and save it to
+    /// `report` if given
+    /// 
+    /// The runner waits on replies, so it goes to tokio's blocking pool like
+    /// the self-test; the callbacks feed it through the tap meanwhile.
+    async fn run_scenarios(
+        &mut self,
+        command: &'static str,
+        scenarios: Vec<Scenario>,
+        selector: &str,
+        report: Option<&Path>,
+    ) -> ResultCode {
+        // Checked here, but the SessionId itself is made again on the
+        // blocking thread: it cannot be moved there
+        let session_id = match self.resolve_session(selector) {
+            Ok(session_id) => session_id,
+            Err(problem) => return outcome(command, Err(problem.into())),
+        };
+        let label = session_label(&session_id);
+
+        println!("Running {} scenario(s) against {label}...", scenarios.len());
+        let inbox = self.conformance.attach(&label);
+        let result = tokio::task::spawn_blocking(move || {
+            let mut counterparty = SessionCounterparty::new(&label, inbox);
+            conformance::run(&scenarios, &mut counterparty, &label)
+        })
+        .await;
+        self.conformance.detach();
+
+        let result = match result {
+            Ok(result) => result,
+            Err(err) => {
+                error!(command, "conformance task failed: {err}");
+                return ResultCode::EngineError;
+            }
+        };
+        print!("{}", result.to_text());
+
+        if let Some(report) = report {
+            let text = if report.extension().is_some_and(|x| x == "json") {
+                result.to_json()
+            } else {
+                result.to_text()
+            };
+            match fs::write(report, text) {
+                Ok(()) => info!(command, report = %report.display(), "saved"),
+                Err(err) => warn!(command, report = %report.display(), ?err),
+            }
+        }
+
+        let passed = result.passed_count();
+        let total = result.scenarios.len();
+        if result.passed() {
+            info!(command, passed, total, "all scenarios passed");
+            ResultCode::Ok
+        } else {
+            warn!(command, passed, total, "scenarios failed");
+            ResultCode::EngineError
+        }
+    }
+
+    // =========================================================================
+    // Fault Injection
+    // =========================================================================
+
+    /// Switch one fault, or every fault off, then show what is on, what was
+    /// injected so far and the sequence numbers skipped per session
+    fn faults(&self, setting: Option<(Fault, u64)>, clear: bool) -> ResultCode {
+        if clear {
+            self.faults.clear();
+            info!(command = "fault", "every fault off");
+        }
+        if let Some((fault, value)) = setting {
+            self.faults.set(fault, value);
+            if value == 0 {
+                info!(command = "fault", %fault, "off");
+            } else {
+                warn!(command = "fault", %fault, value, "on: {}", fault.describe(value));
+            }
+        }
+
+        let settings = self.faults.settings();
+        if settings.is_empty() {
+            println!("No fault on");
+        }
+        for (fault, value) in settings {
+            println!("{:<10} {}", fault.as_str(), fault.describe(value));
+        }
+
+        let injected = self.faults.injected();
+        if !injected.is_empty() {
+            let counts: Vec<_> = injected
+                .iter()
+                .map(|(fault, count)| format!("{fault}={count}"))
+                .collect();
+            println!("Injected: {}", counts.join(" "));
+        }
+        for (label, offset) in self.faults.seq_offsets() {
+            println!("{label}: {offset} MsgSeqNum skipped, until a Logon with 141=Y");
+        }
+        ResultCode::Ok
+    }
+
+    // =========================================================================
+    // Rejects
+    // =========================================================================
+
+    /// Print the last `limit` rejects, oldest first
+    fn rejects(&self, limit: usize) -> ResultCode {
+        let rejects = self.rejects.recent(limit);
+        if rejects.is_empty() {
+            println!("No rejects");
+        }
+        for reject in rejects {
+            println!("{reject}");
+        }
+        ResultCode::Ok
+    }
+
+    // =========================================================================
+    // Send Queue
+    // =========================================================================
+
+    /// Print what waits for a logon per session, or drop it
+    fn send_queue(&self, clear: bool, selector: Option<&str>) -> ResultCode {
+        let label = selector.map(|x| self.live.resolve_session(x).unwrap_or_else(|| x.to_string()));
+        if clear {
+            let dropped = self.queue.clear(label.as_deref());
+            info!(command = "queue", session = ?label, dropped, "queued messages dropped");
+            return ResultCode::Ok;
+        }
+
+        let stats = self.queue.stats();
+        if stats.is_empty() {
+            println!("No message queued");
+        }
+        for x in stats {
+            let state = if x.logged_on { "logged on" } else { "logged off" };
+            let oldest = x.oldest.map_or("-".to_string(), |x| format!("{}s", x.as_secs()));
+            println!(
+                "{}: {state}, {} queued (oldest {oldest}), {} flushed, {} expired",
+                x.session, x.queued, x.flushed, x.expired
+            );
+        }
+        ResultCode::Ok
+    }
+
+    // =========================================================================
+    // Cancel on Disconnect
+    // =========================================================================
+
+    /// Print the sessions down, the reports of the logons and the orders
+    /// left for review, or take orders off the review list
+    fn disconnects(&self, resolve: bool, cl_ord_id: Option<&str>) -> ResultCode {
+        let Some(guard) = &self.disconnects else {
+            println!("Cancel on disconnect is off (--cancel-on-disconnect cancel|review)");
+            return ResultCode::Ok;
+        };
+        if resolve {
+            let resolved = guard.resolve(cl_ord_id);
+            info!(command = "disconnects", ?cl_ord_id, resolved, "orders reviewed");
+            return ResultCode::Ok;
+        }
+
+        for (session, open) in guard.down() {
+            println!("{session}: down, {open} order(s) open");
+        }
+        for report in guard.reports() {
+            println!("{report}");
+            for action in &report.actions {
+                println!("  {action}");
+            }
+        }
+        let review = guard.review();
+        if review.is_empty() {
+            println!("No order left for review");
+        }
+        for x in review {
+            let order = &x.order;
+            println!(
+                "review: {} {} {} {} {} (down at {})",
+                x.session,
+                order.cl_ord_id,
+                order.side.map_or("?", |x| x.as_str()),
+                order.quantity,
+                order.symbol,
+                time_of_day(x.down_at)
+            );
+        }
+        ResultCode::Ok
+    }
+
+    // =========================================================================
+    // Gateway Failover
+    // =========================================================================
+
+    /// Print the gateway of each session with a backup and the switches
+    /// made, or switch one session or all of them now
+    fn failover<C: ConnectionHandler>(
+        &mut self,
+        switch: bool,
+        selector: Option<&str>,
+        connection_handler: &mut C,
+    ) -> ResultCode {
+        let label = selector.map(|x| self.live.resolve_session(x).unwrap_or_else(|| x.to_string()));
+        let Some(failover) = &mut self.failover else {
+            println!("Failover is off (--failover, FailoverConnectHost in the config)");
+            return ResultCode::Ok;
+        };
+        let now = unix_now();
+        if switch {
+            return match failover.force(label.as_deref(), now) {
+                Ok(switches) => self.switch_gateways(&switches, connection_handler),
+                Err(err) => {
+                    warn!(command = "failover", %err);
+                    ResultCode::BadCommand
+                }
+            };
+        }
+
+        for route in failover.routes(now) {
+            let state = match route.attempts {
+                Some(attempts) => format!("down, {attempts} of {} attempts", route.after),
+                None => "up or stopped".to_string(),
+            };
+            println!(
+                "{}: {} {}, {state}, {} switch(es)",
+                route.session,
+                route.active.as_str(),
+                route.endpoint,
+                route.switches
+            );
+        }
+        for switch in failover.history() {
+            println!("{} {switch}", time_of_day(switch.at));
+        }
+        ResultCode::Ok
+    }
+
+    // =========================================================================
+    // End-of-Day Tasks
+    // =========================================================================
+
+    /// Print the tasks of each session and when they run next
+    fn eod_plans(&self) -> ResultCode {
+        let Some(eod) = &self.eod else {
+            println!("No end-of-day task (--eod, EndOfDay* keys in the config)");
+            return ResultCode::Ok;
+        };
+        let now = unix_now();
+        let when = |x: i64| format!("{} {}", Date::from_unix(x).to_iso(), time_of_day(x));
+        for plan in eod.plans() {
+            let close = plan.next_close(now).map_or("-".to_string(), when);
+            println!("{}: next close {close}, files in {}", plan.session, plan.dir.display());
+            for task in &plan.tasks {
+                let next = plan.next_run(task, now).map_or("-".to_string(), |(at, _)| when(at));
+                println!("  {:<28} next {next}", task.to_string());
+            }
+        }
+        ResultCode::Ok
+    }
+
+    /// Run one task now for the last close, on one session or on every
+    /// session that has it; flatten asks first
+    async fn eod_run<C: ConnectionHandler>(
+        &mut self,
+        action: EodAction,
+        selector: Option<&str>,
+        connection_handler: &mut C,
+    ) -> ResultCode {
+        let Some(eod) = &self.eod else {
+            warn!(command = "eod", "no end-of-day plan, start with --eod");
+            return ResultCode::BadCommand;
+        };
+        let now = unix_now();
+        let plans: Vec<&EodPlan> = match selector {
+            Some(selector) => {
+                let label = self
+                    .live
+                    .resolve_session(selector)
+                    .unwrap_or_else(|| selector.to_string());
+                let Some(plan) = eod.plan(&label) else {
+                    warn!(command = "eod", session = %label, "no end-of-day plan");
+                    return ResultCode::BadCommand;
+                };
+                vec![plan]
+            }
+            None => eod
+                .plans()
+                .iter()
+                .filter(|x| x.tasks.iter().any(|x| x.action == action))
+                .collect(),
+        };
+        let mut runs = Vec::new();
+        for plan in plans {
+            match plan.last_close(now) {
+                Some(close) => runs.push(EodRun { session: plan.session.clone(), action, close }),
+                None => {
+                    warn!(command = "eod", session = %plan.session, "no close in the past week");
+                }
+            }
+        }
+        if runs.is_empty() {
+            return ResultCode::BadCommand;
+        }
+
+        if action == EodAction::Flatten {
+            let question = format!("Flatten {} session(s)?", runs.len());
+            if !self.confirm(&question).await {
+                return ResultCode::Aborted;
+            }
+        }
+        let mut code = ResultCode::Ok;
+        for run in &runs {
+            let result = self.run_eod_task(run, connection_handler);
+            if !result.is_ok() {
+                code = result;
+            }
+        }
+        code
+    }
+
+    /// Carry out one end-of-day task
+    fn run_eod_task<C: ConnectionHandler>(
+        &mut self,
+        run: &EodRun,
+        connection_handler: &mut C,
+    ) -> ResultCode {
+        let Some(plan) = self.eod.as_ref().and_then(|x| x.plan(&run.session)).cloned() else {
+            return ResultCode::BadCommand;
+        };
+        let _span = label_span(&run.session).entered();
+        info!(eod = run.action.as_str(), close = %time_of_day(run.close), "end-of-day task");
+        match run.action {
+            EodAction::Flatten => self.eod_flatten(&run.session, run.close),
+            EodAction::ExportFills => self.eod_export_fills(&plan, run.close),
+            EodAction::ArchiveStore => self.eod_archive_store(&plan, run.close, connection_handler),
+            EodAction::ResetOrderIds => {
+                // One generator for every session: the first reset of the
+                // day starts them all over
+                self.duplicates.reset();
+                info!(eod = "reset_order_ids", "ClOrdIDs start over at 1");
+                ResultCode::Ok
+            }
+            EodAction::Summary => self.eod_summary(&plan, run.close),
+        }
+    }
+
+    /// Cancel the working orders of a session, then close the net position
+    /// of each symbol its fills of the day left with a market order; a
+    /// cancel filled meanwhile leaves what it fills open
+    fn eod_flatten(&self, session: &str, close: i64) -> ResultCode {
+        let (mut sent, mut failed) = (0, 0);
+        let orders: Vec<_> = self
+            .orders
+            .working_orders()
+            .into_iter()
+            .filter(|x| x.session == session)
+            .collect();
+        for order in &orders {
+            let result = order.session_id().map_err(SendError::from).and_then(|session_id| {
+                let msg = order.to_cancel_request(&self.orders.next_cancel_id())?;
+                self.queue.send(msg, &session_id)
+            });
+            match result {
+                Ok(_) => sent += 1,
+                Err(err) => {
+                    failed += 1;
+                    warn!(eod = "flatten", cl_ord_id = %order.cl_ord_id, ?err, "cancel failed");
+                }
+            }
+        }
+
+        let trades = self.live.trades().matching(&BlotterFilter::default());
+        let positions = eod::net_positions(&eod::day_fills(&trades, session, close));
+        for (symbol, net) in &positions {
+            let result = self.resolve_session(session).map_err(ShellError::from).and_then(
+                |session_id| {
+                    let mut msg = eod::flatten_order(symbol, *net)?;
+                    self.duplicates.check(&mut msg, Instant::now())?;
+                    Ok(self.queue.send(msg, &session_id)?)
+                },
+            );
+            match result {
+                Ok(_) => {
+                    sent += 1;
+                    info!(eod = "flatten", %symbol, net, "closing order sent");
+                }
+                Err(err) => {
+                    failed += 1;
+                    warn!(eod = "flatten", %symbol, net, hint = err.hint(), "{err}");
+                }
+            }
+        }
+
+        info!(eod = "flatten", cancels = orders.len(), positions = positions.len(), sent, failed);
+        if failed == 0 {
+            ResultCode::Ok
+        } else {
+            ResultCode::SendFailed
+        }
+    }
+
+    /// Write the fills of the day to DIR/fills-SESSION-YYYYMMDD.csv
+    fn eod_export_fills(&self, plan: &EodPlan, close: i64) -> ResultCode {
+        let trades = self.live.trades().matching(&BlotterFilter::default());
+        let fills = eod::day_fills(&trades, &plan.session, close);
+        let columns: Vec<String> =
+            ExportKind::Fills.columns().iter().map(|x| x.to_string()).collect();
+        let path = plan.path("fills", close, "csv");
+        let result = fs::create_dir_all(&plan.dir)
+            .and_then(|()| fs::write(&path, export::fills_csv(&fills, &columns)));
+        match result {
+            Ok(()) => {
+                info!(eod = "export_fills", path = %path.display(), rows = fills.len(), "exported");
+                ResultCode::Ok
+            }
+            Err(err) => {
+                warn!(eod = "export_fills", path = %path.display(), %err, "cannot export");
+                ResultCode::EngineError
+            }
+        }
+    }
+
+    /// Compact the session's file store up to the close into DIR/store;
+    /// a running handler is stopped around it, as for a weekly reset
+    fn eod_archive_store<C: ConnectionHandler>(
+        &self,
+        plan: &EodPlan,
+        close: i64,
+        connection_handler: &mut C,
+    ) -> ResultCode {
+        let Some(store_dir) = &plan.store_dir else {
+            warn!(eod = "archive_store", "no FileStorePath, nothing to archive");
+            return ResultCode::BadCommand;
+        };
+        let running = connection_handler.is_stopped().is_ok_and(|x| !x);
+        if running {
+            info!(eod = "archive_store", "connection handler STOP");
+            if let Err(err) = connection_handler.stop() {
+                warn!(?err, "cannot stop to archive the message store");
+                return ResultCode::EngineError;
+            }
+        }
+        let store = FileStore::new(store_dir, &plan.store_prefix);
+        let code = match store.compact(close, &plan.archive_dir()) {
+            Ok(compaction) => {
+                let archive = compaction.archive.as_ref().map(|x| x.display().to_string());
+                info!(
+                    eod = "archive_store",
+                    archived = compaction.archived,
+                    kept = compaction.kept,
+                    archive = archive.as_deref().unwrap_or("-"),
+                    "message store compacted"
+                );
+                ResultCode::Ok
+            }
+            Err(err) => {
+                warn!(eod = "archive_store", %err, "cannot compact the message store");
+                ResultCode::EngineError
+            }
+        };
+        if running && self.in_session_hours() {
+            info!(eod = "archive_store", "connection handler START");
+            if let Err(err) = connection_handler.start() {
+                warn!(?err, "cannot start after archiving the message store");
+                return ResultCode::EngineError;
+            }
+        }
+        code
+    }
+
+    /// Write the report of the day to DIR/summary-SESSION-YYYYMMDD.EXT, in
+    /// the plan's format, and log it as text
+    fn eod_summary(&self, plan: &EodPlan, close: i64) -> ResultCode {
+        let report = self.daily_report(close - 86_400, close, Some(&plan.session));
+        for line in report.to_text().lines() {
+            info!(eod = "summary", "{line}");
+        }
+        let path = plan.path("summary", close, plan.report_format.extension());
+        let result = fs::create_dir_all(&plan.dir)
+            .and_then(|()| fs::write(&path, report.render(plan.report_format)));
+        match result {
+            Ok(()) => {
+                info!(eod = "summary", path = %path.display(), "written");
+                ResultCode::Ok
+            }
+            Err(err) => {
+                warn!(eod = "summary", path = %path.display(), %err, "cannot write");
+                ResultCode::EngineError
+            }
+        }
+    }
+
+    // =========================================================================
+    // Message Statistics
+    // =========================================================================
+
+    /// Print the message counts of every session, or of one by MsgType
+    fn stats(&self, selector: Option<&str>) -> ResultCode {
+        let show = |x: Option<Duration>| x.map_or("-".to_string(), |x| format!("{x:.1?}"));
+        let print_header = || {
+            println!(
+                "{:<32} {:>10} {:>10} {:>9} {:>9} {:>9}",
+                "session", "in", "out", "last in", "last out", "max gap"
+            );
+        };
+        let print_row = |x: &SessionStats| {
+            println!(
+                "{:<32} {:>10} {:>10} {:>9} {:>9} {:>9}",
+                x.session,
+                x.inbound,
+                x.outbound,
+                show(x.since_inbound),
+                show(x.since_outbound),
+                format!("{:.1?}", x.max_inbound_gap),
+            );
+        };
+
+        let Some(selector) = selector else {
+            let sessions = self.metrics.stats().snapshot();
+            if sessions.is_empty() {
+                println!("no message yet");
+                return ResultCode::Ok;
+            }
+            print_header();
+            sessions.iter().for_each(print_row);
+            return ResultCode::Ok;
+        };
+
+        let label = self
+            .live
+            .resolve_session(selector)
+            .unwrap_or_else(|| selector.to_string());
+        let Some(session) = self.metrics.stats().session(&label) else {
+            let problem = SessionProblem::Unknown(selector.to_string());
+            return outcome("stats", Err(problem.into()));
+        };
+        print_header();
+        print_row(&session);
+        println!();
+        println!("{:<4} {:<24} {:>10} {:>10}", "type", "name", "in", "out");
+        for x in &session.by_type {
+            println!(
+                "{:<4} {:<24} {:>10} {:>10}",
+                x.msg_type,
+                self.dictionary.value_name(35, &x.msg_type).unwrap_or("-"),
+                x.inbound,
+                x.outbound
+            );
+        }
+        ResultCode::Ok
+    }
+
+    // =========================================================================
+    // Counterparty Scorecards
+    // =========================================================================
+
+    /// Print the sessions over the window, or one session day by day, or
+    /// write the days to a CSV file
+    fn scorecard(&self, selector: Option<&str>, days: u32, csv: Option<&Path>) -> ResultCode {
+        let mut scores = match self.scorecard.history(days) {
+            Ok(scores) => scores,
+            Err(err) => {
+                warn!(command = "scorecard", %err, "cannot read the scorecards");
+                return ResultCode::EngineError;
+            }
+        };
+        if let Some(selector) = selector {
+            let label = self
+                .live
+                .resolve_session(selector)
+                .unwrap_or_else(|| selector.to_string());
+            scores.retain(|x| x.session == label);
+        }
+
+        let Some(path) = csv else {
+            match selector {
+                Some(_) => print!("{}", render_days(&scores)),
+                None => print!("{}", render_trends(&trends(&scores), days)),
+            }
+            return ResultCode::Ok;
+        };
+        match fs::write(path, scorecard::to_csv(&scores)) {
+            Ok(()) => {
+                let rows = scores.len();
+                info!(command = "scorecard", path = %path.display(), rows, "exported");
+                ResultCode::Ok
+            }
+            Err(err) => {
+                warn!(command = "scorecard", path = %path.display(), %err, "cannot export");
+                ResultCode::EngineError
+            }
+        }
+    }
+
+    // =========================================================================
+    // Garbled Messages
+    // =========================================================================
+
+    /// Switch the diagnostics, or print the last messages discarded, oldest
+    /// first
+    fn garbled(&self, enabled: Option<bool>) -> ResultCode {
+        if let Some(enabled) = enabled {
+            self.garbled.set_enabled(enabled);
+            info!(command = "garbled", enabled, "garbled message diagnostics");
+            return ResultCode::Ok;
+        }
+
+        if !self.garbled.is_enabled() {
+            println!("Garbled message diagnostics off (garbled on to switch them on)");
+        }
+        let reports = self.garbled.recent(GARBLED_SHOWN);
+        if reports.is_empty() {
+            println!("No message discarded");
+        }
+        for report in reports {
+            print!("{report}");
+        }
+        ResultCode::Ok
+    }
+
+    // =========================================================================
+    // Audit Log
+    // =========================================================================
+
+    /// Append a command that changes something to the audit log
+    fn audit(&self, line: &str, code: ResultCode) {
+        let entry =
+            AuditEntry::new(self.source, &self.operator, line, code.is_ok(), code.as_str());
+        if let Err(err) = self.audit.record(&entry) {
+            error!(path = %self.audit.path().display(), "cannot write to the audit log: {err}");
+        }
+    }
+
+    /// Switch to a role up to the grant of the user, or show both
+    fn login(&mut self, role: Option<Role>) -> ResultCode {
+        let granted = self.grants.grant(&self.operator);
+        let Some(role) = role else {
+            println!("{} as {} (granted {granted})", self.operator, self.role);
+            return ResultCode::Ok;
+        };
+        if role > granted {
+            warn!(
+                command = "login",
+                operator = %self.operator,
+                %role,
+                %granted,
+                "role not granted"
+            );
+            return ResultCode::Denied;
+        }
+        info!(
+            command = "login",
+            operator = %self.operator,
+            from = %self.role,
+            to = %role,
+            "role switched"
+        );
+        self.role = role;
+        ResultCode::Ok
+    }
+
+    /// Print the entries of the audit log matching a query, oldest first
+    fn audit_query(&self, query: &AuditQuery) -> ResultCode {
+        let entries = match self.audit.query(query) {
+            Ok(entries) => entries,
+            Err(err) => {
+                error!(path = %self.audit.path().display(), "cannot read the audit log: {err}");
+                return ResultCode::EngineError;
+            }
+        };
+        if entries.is_empty() {
+            println!("No entry in {}", self.audit.path().display());
+        }
+        for entry in entries {
+            println!("{}", entry.to_text());
+        }
+        ResultCode::Ok
+    }
+
+    // =========================================================================
+    // Tag Dictionary
+    // =========================================================================
+
+    /// Print a field by tag or name, or every venue field
+    fn dictionary(&self, field: Option<&str>) -> ResultCode {
+        let Some(field) = field else {
+            let mut custom = self.dictionary.custom_fields().peekable();
+            if custom.peek().is_none() {
+                println!("No venue field (load them with --dictionary FILE)");
+            }
+            custom.for_each(print_field);
+            return ResultCode::Ok;
+        };
+
+        match self.dictionary.resolve(field).and_then(|x| self.dictionary.field(x)) {
+            Some(def) => {
+                print_field(def);
+                ResultCode::Ok
+            }
+            None => {
+                warn!(command = "dict", field, "not in the tag dictionary");
+                ResultCode::BadCommand
+            }
+        }
+    }
+
+    /// Check every field of a message to send against the dictionary
+    ///
+    /// The error lists each field it rejects; tags it does not know pass.
+    fn check_fields(&self, msg: &Message) -> Result<(), ShellError> {
+        let text = msg.to_fix_string().unwrap_or_default();
+        let mut problems = Vec::new();
+        for (tag, value) in text.split('\x01').filter_map(|x| x.split_once('=')) {
+            let Ok(tag) = tag.parse() else {
+                continue;
+            };
+            if let Err(err) = self.dictionary.validate(tag, value) {
+                match self.dictionary.name(tag) {
+                    Some(field) => problems.push(format!("{field} ({tag})={value}: {err}")),
+                    None => problems.push(format!("{tag}={value}: {err}")),
+                }
+            }
+        }
+        validated(problems)
+    }
+
+    /// Check a message to send against the profiles of its target
+    ///
+    /// The error lists each violation, with the profile that finds it.
+    fn check_profiles(&self, session_id: &SessionId, msg: &Message) -> Result<(), ShellError> {
+        let target = session_id.get_target_comp_id().unwrap_or_default();
+        let mut problems = Vec::new();
+        for profile in self.profiles.iter().filter(|x| x.applies_to(&target)) {
+            for violation in profile.check(msg) {
+                problems.push(format!("venue would reject ({}): {violation}", profile.name));
+            }
+        }
+        validated(problems)
+    }
+
+    // =========================================================================
+    // Message Log Filter
+    // =========================================================================
+
+    /// What a mute target matches; field names go through the dictionary
+    fn mute_match(&self, command: &str, target: &MuteTarget) -> Option<MuteMatch> {
+        match target {
+            MuteTarget::Admin => Some(MuteMatch::Admin),
+            MuteTarget::Field { tag, value } => {
+                let Some(number) = self.dictionary.resolve(tag) else {
+                    warn!(command, field = %tag, "not in the tag dictionary");
+                    return None;
+                };
+                let value = self.dictionary.resolve_value(number, value);
+                Some(MuteMatch::Field(number, value.to_string()))
+            }
+        }
+    }
+
+    /// Label of the session a selector names, as the event task sees it
+    fn mute_session(&self, selector: Option<&str>) -> Option<String> {
+        selector.map(|x| self.live.resolve_session(x).unwrap_or_else(|| x.to_string()))
+    }
+
+    fn mute(&self, target: &MuteTarget, session: Option<&str>, drop: bool) -> ResultCode {
+        let Some(matcher) = self.mute_match("mute", target) else {
+            return ResultCode::BadCommand;
+        };
+        let session = self.mute_session(session);
+        let rule = MuteRule { matcher, session, drop };
+        let added = self.mutes.mute(rule.clone());
+        info!(
+            command = "mute",
+            rule = %rule.matcher,
+            session = rule.session.as_deref().unwrap_or("all"),
+            drop,
+            added,
+            "muted"
+        );
+        ResultCode::Ok
+    }
+
+    fn unmute(&self, target: Option<&MuteTarget>, session: Option<&str>) -> ResultCode {
+        let matcher = match target {
+            Some(target) => match self.mute_match("unmute", target) {
+                Some(matcher) => Some(matcher),
+                None => return ResultCode::BadCommand,
+            },
+            None => None,
+        };
+        let session = self.mute_session(session);
+        let removed = self.mutes.unmute(matcher.as_ref(), session.as_deref());
+        if removed == 0 {
+            warn!(command = "unmute", "no such mute rule");
+            return ResultCode::BadCommand;
+        }
+        info!(command = "unmute", removed, "unmuted");
+        ResultCode::Ok
+    }
+
+    /// Print the mute rules, with the messages each one muted
+    fn mute_rules(&self) -> ResultCode {
+        let rules = self.mutes.rules();
+        if rules.is_empty() {
+            println!("Nothing muted");
+            return ResultCode::Ok;
+        }
+        println!("{:<24} {:<32} {:>10}  SUMMARY", "RULE", "SESSION", "MUTED");
+        for (rule, count) in rules {
+            println!(
+                "{:<24} {:<32} {:>10}  {}",
+                rule.matcher.to_string(),
+                rule.session.as_deref().unwrap_or("all"),
+                count,
+                if rule.drop { "no" } else { "yes" }
+            );
+        }
+        ResultCode::Ok
+    }
+
+    // =========================================================================
+    // Self-Test
+    // =========================================================================
+    
+    /// Run the throughput self-test and print its report
+    /// 
+    /// The test blocks for its whole duration, so it runs on tokio's
+    /// blocking pool; the prompt comes back when it is over.
+    async fn selftest(&mut self, options: ThroughputOptions) -> ResultCode {
+        println!(
+            "Running for {}, with a {:?} store...",
+            options.load, options.store
+        );
+        let result = tokio::task::spawn_blocking(move || run_throughput(&options)).await;
+        match result {
+            Ok(Ok(report)) => {
+                report.print();
+                info!(
+                    command = "selftest",
+                    round_trips = report.received,
+                    rate = format!("{:.0}/s", report.round_trips_per_sec()),
+                    p99 = ?report.percentile(0.99)
+                );
+                ResultCode::Ok
+            }
+            Ok(Err(err)) => {
+                warn!(command = "selftest", "self-test failed: {err}");
+                ResultCode::EngineError
+            }
+            Err(err) => {
+                error!(command = "selftest", "self-test task failed: {err}");
+                ResultCode::EngineError
+            }
+        }
+    }
+
+    // =========================================================================
+    // Watch
+    // =========================================================================
+    
+    /// Repaint `target` every `interval` until Enter (or CTRL-D) is pressed
+    /// 
+    /// The screen is cleared before each repaint. A shutdown signal also ends
+    /// the watch, and then the REPL.
+    async fn watch(&mut self, target: &WatchTarget, interval: Duration) {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            // Clear the screen and move the cursor home, then repaint
+            print!("\x1b[2J\x1b[H");
+            println!("watch {target:?} every {interval:?}, press Enter to stop\n");
+            print!("{}", self.live.render(target));
+            let _ = stdout().flush();
+
+            tokio::select! {
+                _ = ticks.tick() => {}
+                _ = self.lines.recv() => break,
+                () = &mut self.shutdown => {
+                    self.shutting_down = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    // =========================================================================
+    // Book
+    // =========================================================================
+
+    /// Print a book with our open orders marked, or its last snapshot in an
+    /// export file, at or before `at` if given
+    fn book(&self, symbol: &str, depth: usize, from: Option<&Path>, at: Option<i64>) -> ResultCode {
+        let snapshot = match from {
+            Some(path) => match book_export::read_snapshot(path, symbol, at) {
+                Ok(snapshot) => snapshot.map(|mut x| {
+                    x.truncate(depth);
+                    x
+                }),
+                Err(err) => {
+                    warn!(command = "book", path = %path.display(), %err, "cannot read the export");
+                    return ResultCode::BadCommand;
+                }
+            },
+            None => self
+                .live
+                .book(symbol, depth)
+                .map(|x| BookSnapshot::capture(symbol, &x, &self.orders.open_orders())),
+        };
+        match snapshot {
+            Some(snapshot) => print!("{}", snapshot.render()),
+            None if from.is_some() => println!("No snapshot of {symbol}"),
+            None => println!("No market data for {symbol}"),
+        }
+        ResultCode::Ok
+    }
+
+    fn blotter(&self, filter: &BlotterFilter, page: usize, csv: Option<&Path>) -> ResultCode {
+        let trades = self.live.trades();
+        let Some(path) = csv else {
+            let page = trades.page(filter, (page - 1) * DEFAULT_PAGE_SIZE, DEFAULT_PAGE_SIZE);
+            print!("{}", render_trades(&page, filter));
+            return ResultCode::Ok;
+        };
+
+        let matching = trades.matching(filter);
+        match fs::write(path, trade_blotter::to_csv(&matching)) {
+            Ok(()) => {
+                let trades = matching.len();
+                info!(command = "blotter", path = %path.display(), trades, "exported");
+                ResultCode::Ok
+            }
+            Err(err) => {
+                warn!(command = "blotter", path = %path.display(), %err, "cannot export");
+                ResultCode::EngineError
+            }
+        }
+    }
+
+    /// Write the orders tracked, the fills or the positions to a CSV file
+    fn export(&self, kind: ExportKind, path: &Path, columns: &[String]) -> ResultCode {
+        let (csv, rows) = match kind {
+            ExportKind::Orders => {
+                let orders = self.orders.orders();
+                (export::orders_csv(&orders, columns), orders.len())
+            }
+            ExportKind::Fills => {
+                let fills = self.live.trades().matching(&BlotterFilter::default());
+                (export::fills_csv(&fills, columns), fills.len())
+            }
+            ExportKind::Positions => {
+                let positions = self.live.positions();
+                (export::positions_csv(&positions, columns), positions.len())
+            }
+        };
+        match fs::write(path, csv) {
+            Ok(()) => {
+                info!(command = "export", %kind, path = %path.display(), rows, "exported");
+                ResultCode::Ok
+            }
+            Err(err) => {
+                warn!(command = "export", %kind, path = %path.display(), %err, "cannot export");
+                ResultCode::EngineError
+            }
+        }
+    }
+
+    /// Send a TradeCaptureReportRequest; the ack and the reports come back
+    /// through the event task
+    fn request_trades(
+        &self,
+        selector: &str,
+        date: Date,
+        symbol: Option<&str>,
+    ) -> Result<(), ShellError> {
+        let session_id = self.resolve_session(selector)?;
+        let _span = session_span(&session_id).entered();
+
+        // Tracked first: the ack may beat send_to_target back
+        let request = self.captures.prepare(symbol, Some(date));
+        self.captures.track(&session_label(&session_id), request.clone());
+        let command = "trades request";
+        match self.queue.send(request.to_message()?, &session_id)? {
+            Sent::Now => info!(command, %request, "trade capture requested"),
+            Sent::Queued { .. } => info!(command, %request, "queued until logon"),
+        }
+        Ok(())
+    }
+
+    /// Print the requests and the reports matching the filter, set against
+    /// the blotter, or write the reports to a CSV file
+    fn trades(&self, mut filter: CaptureFilter, unmatched: bool, csv: Option<&Path>) -> ResultCode {
+        if let Some(selector) = &filter.session {
+            filter.session = Some(
+                self.live
+                    .resolve_session(selector)
+                    .unwrap_or_else(|| selector.clone()),
+            );
+        }
+        let reports = self.captures.trades(&filter);
+        let fills = self.live.trades().matching(&BlotterFilter::default());
+        let listed = if unmatched {
+            captures::unmatched(reports.clone(), &fills)
+        } else {
+            reports.clone()
+        };
+
+        let Some(path) = csv else {
+            let requests = self.captures.requests();
+            print!("{}", captures::render(&requests, &listed, &reports, &fills, &filter));
+            return ResultCode::Ok;
+        };
+        match fs::write(path, trade_captures::to_csv(&listed)) {
+            Ok(()) => {
+                let reports = listed.len();
+                info!(command = "trades", path = %path.display(), reports, "exported");
+                ResultCode::Ok
+            }
+            Err(err) => {
+                warn!(command = "trades", path = %path.display(), %err, "cannot export");
+                ResultCode::EngineError
+            }
+        }
+    }
+
+    /// Print the trading report of a UTC day, or write it to a file
+    fn report(
+        &self,
+        selector: Option<&str>,
+        date: Date,
+        format: ReportFormat,
+        out: Option<&Path>,
+    ) -> ResultCode {
+        let session = selector.map(|x| {
+            self.live
+                .resolve_session(x)
+                .unwrap_or_else(|| x.to_string())
+        });
+        let from = date.to_unix();
+        let report = self.daily_report(from, from + 86_400, session.as_deref());
+
+        let Some(path) = out else {
+            print!("{}", report.render(format));
+            return ResultCode::Ok;
+        };
+        match fs::write(path, report.render(format)) {
+            Ok(()) => {
+                info!(command = "report", path = %path.display(), %format, "written");
+                ResultCode::Ok
+            }
+            Err(err) => {
+                warn!(command = "report", path = %path.display(), %err, "cannot write");
+                ResultCode::EngineError
+            }
+        }
+    }
+
+    /// The orders tracked, fills and rejects received over [from, to)
+    fn daily_report(&self, from: i64, to: i64, session: Option<&str>) -> DailyReport {
+        let orders: Vec<_> = self.orders.orders().iter().filter_map(|x| x.to_report()).collect();
+        let fills = self.live.trades().matching(&BlotterFilter::default());
+        let rejects = self.rejects.recent(usize::MAX);
+        DailyReport::build(from, to, session, &orders, &fills, &rejects)
+    }
+
+    /// Send an OrderMassStatusRequest; the reports come back through the
+    /// event task
+    fn request_mass_status(&self, selector: &str, symbol: Option<&str>) -> Result<(), ShellError> {
+        let session_id = self.resolve_session(selector)?;
+        let _span = session_span(&session_id).entered();
+
+        // Tracked first: the reports may beat send_to_target back
+        let request = self.mass_status.prepare(symbol);
+        self.mass_status.track(&session_label(&session_id), request.clone());
+        match self.queue.send(request.to_message()?, &session_id)? {
+            Sent::Now => info!(command = "mass_status", %request, "order mass status requested"),
+            Sent::Queued { .. } => info!(command = "mass_status", %request, "queued until logon"),
+        }
+        Ok(())
+    }
+
+    /// Print every request with, once complete, what does not reconcile
+    /// with the orders open now
+    fn show_mass_status(&self) -> ResultCode {
+        let runs = self.mass_status.runs();
+        if runs.is_empty() {
+            println!("no mass status requests");
+        }
+        let open = self.orders.open_orders();
+        for run in runs {
+            println!("request {run} on {}", run.session);
+            if !run.complete {
+                continue;
+            }
+            let local: Vec<_> = open
+                .iter()
+                .filter(|x| x.session == run.session)
+                .map(|x| x.to_local())
+                .collect();
+            let discrepancies = run.reconcile(&local);
+            if discrepancies.is_empty() {
+                println!("  {} open order(s), in agreement", local.len());
+            }
+            for discrepancy in discrepancies {
+                println!("  {discrepancy}");
+            }
+        }
+        ResultCode::Ok
+    }
+
+    /// Execute one line of input, then show and record its timing
+    /// 
+    /// Empty lines are neither shown nor recorded. The time of cancel-all
+    /// includes waiting for the confirmation. Commands above the role are
+    /// not run (DENIED). Commands that change something are also appended
+    /// to the audit log, denied or not.
+    /// 
+    /// # Returns
+    /// The name of the command, its result code and time; None for an
+    /// empty line
+    async fn run_line<C: ConnectionHandler>(
+        &mut self,
+        line: &str,
+        command: Result<ShellCommand, BadCommand>,
+        connection_handler: &mut C,
+    ) -> Option<(&'static str, ResultCode, Duration)> {
+        let started = Instant::now();
+        let (name, code, audited) = match command {
+            Ok(ShellCommand::NoOperation) => return None,
+            Ok(cmd) => {
+                let audited = cmd.changes_state();
+                let required = cmd.required_role();
+                if required > self.role {
+                    warn!(
+                        command = cmd.name(),
+                        role = %self.role,
+                        %required,
+                        "not allowed to this role, see login"
+                    );
+                    (cmd.name(), ResultCode::Denied, audited)
+                } else {
+                    (cmd.name(), self.exec_command(cmd, connection_handler).await, audited)
+                }
+            }
+            Err(err) => {
+                let err = ShellError::from(err);
+                error!(input = line.trim(), hint = err.hint(), "{err}");
+                ("?", err.code(), false)
+            }
+        };
+        let elapsed = started.elapsed();
+
+        // A piped command has its outcome in its result
+        if self.source != AuditSource::Pipe {
+            info!(command = name, code = code.as_str(), ?elapsed);
+        }
+        // Logs go to a file with the blotter, show the outcome at the prompt;
+        // a remote client reads it as the end of the output
+        if self.blotter.is_some() || remote::capturing() {
+            println!(">> {name} {code} {elapsed:?}");
+        }
+        self.history.record(name, line, code, elapsed);
+        if audited {
+            self.audit(line, code);
+        }
+        Some((name, code, elapsed))
+    }
+
+    /// Run a line of the pipe, then write its result to stdout (--pipe)
+    /// 
+    /// What the command prints and logs meanwhile is the output of the
+    /// result. A JSON line that makes no command is BAD_COMMAND, with the
+    /// reason.
+    /// 
+    /// # Returns
+    /// Whether the line was `quit`
+    async fn run_piped<C: ConnectionHandler>(
+        &mut self,
+        line: &str,
+        connection_handler: &mut C,
+    ) -> bool {
+        self.piped += 1;
+        let piped = match pipe::read_line(line) {
+            Ok(piped) => piped,
+            Err(err) => {
+                warn!(input = line.trim(), "{err}");
+                let result = PipeResult {
+                    seq: self.piped,
+                    id: err.id.as_deref(),
+                    input: line,
+                    command: "?",
+                    code: ResultCode::BadCommand,
+                    elapsed: Duration::ZERO,
+                    error: Some(&err.message),
+                    output: "",
+                };
+                result.emit();
+                return false;
+            }
+        };
+        let command = piped.command.parse::<ShellCommand>();
+        if matches!(command, Ok(ShellCommand::Quit)) {
+            return true;
+        }
+
+        let transcript = remote::transcribe();
+        let outcome = self
+            .run_line(&piped.command, command, connection_handler)
+            .await;
+        let output = transcript.finish();
+        if let Some((name, code, elapsed)) = outcome {
+            let result = PipeResult {
+                seq: self.piped,
+                id: piped.id.as_deref(),
+                input: &piped.command,
+                command: name,
+                code,
+                elapsed,
+                error: None,
+                output: &output,
+            };
+            result.emit();
+        }
+        false
+    }
+
+    /// Run the next line of a remote client, as the client
+    /// 
+    /// Its login, role and lines take the place of the user's until the
+    /// command ends, and what it prints goes to the client too. `quit`
+    /// closes the connection.
+    async fn run_remote<C: ConnectionHandler>(
+        &mut self,
+        client: &RemoteClient,
+        connection_handler: &mut C,
+    ) {
+        // A line the command before it read as an answer is already gone
+        let Some(line) = client.next_line() else {
+            return;
+        };
+        let command = line.parse::<ShellCommand>();
+        if matches!(command, Ok(ShellCommand::Quit)) {
+            client.close();
+            return;
+        }
+
+        client.swap_lines(&mut self.lines);
+        let operator = mem::replace(&mut self.operator, client.login.clone());
+        let role = mem::replace(&mut self.role, client.role());
+        let source = mem::replace(&mut self.source, AuditSource::Remote);
+        {
+            let _capture = client.capture();
+            let span = info_span!("remote", login = %client.login, peer = %client.peer);
+            self.run_line(&line, command, connection_handler)
+                .instrument(span)
+                .await;
+        }
+        client.set_role(mem::replace(&mut self.role, role));
+        self.operator = operator;
+        self.source = source;
+        client.swap_lines(&mut self.lines);
+    }
+
+    // =========================================================================
+    // Main REPL Loop
+    // =========================================================================
+    
+    /// Run the interactive Read-Eval-Print Loop
+    /// 
+    /// This is the main entry point for the interactive shell. It:
+    /// 1. Displays a welcome message
+    /// 2. Prompts for user input
+    /// 3. Parses the input into a command
+    /// 4. Executes the command
+    /// 5. Repeats until user quits
+    /// 
+    /// # Arguments
+    /// * `connection_handler` - The FIX connection handler to control
+    /// 
+    /// # Termination
+    /// The loop terminates when:
+    /// - User types "quit" or "q"
+    /// - User presses CTRL-D (EOF)
+    /// - The process receives CTRL-C or SIGTERM
+    /// - A session was added or failed over (ShellExit::Reload): the caller
+    ///   rebuilds the connection handler and calls repl() again
+    pub async fn repl<C: ConnectionHandler>(&mut self, connection_handler: &mut C) -> ShellExit {
+        // Display welcome message (once, not after each reload)
+        self.enter_blotter();
+        if !self.reload && !self.pipe {
+            println!(">> Type 'help' or '?' for more information, 'quit' or 'q' to exit.");
+            if self.role != Role::Admin {
+                println!(">> Role: {}, 'login' to switch", self.role);
+            }
+        }
+        self.reload = false;
+        let mut repaint = tokio::time::interval(REPAINT_INTERVAL);
+        let mut schedule = tokio::time::interval(SCHEDULE_TICK);
+        let mut failover = tokio::time::interval(FAILOVER_TICK);
+        let mut exit = ShellExit::Quit;
+
+        // Main loop - runs until user quits
+        loop {
+            // ================================================================
+            // Step 1: Wait for user input or a shutdown signal
+            // ================================================================
+            // Other tasks (the FIX event task) keep running while we wait
+            // ================================================================
+            
+            // The signal may have fired while a command (watch) was running
+            if self.shutting_down {
+                info!("shutdown signal received");
+                break;
+            }
+
+            self.show_prompt();
+
+            // The blotter is repainted, and the remote console served, while
+            // waiting
+            let line = loop {
+                tokio::select! {
+                    line = self.lines.recv(), if !self.stdin_closed => break line,
+                    Some(client) = remote::next_client(self.remote.as_mut()) => {
+                        self.run_remote(&client, connection_handler).await;
+                        if self.reload || self.shutting_down {
+                            break None;
+                        }
+                        self.show_prompt();
+                    }
+                    () = &mut self.shutdown => {
+                        self.shutting_down = true;
+                        break None;
+                    }
+                    _ = repaint.tick(), if self.blotter.is_some() => self.paint_blotter(),
+                    _ = schedule.tick(), if self.scheduler.is_some() || self.eod.is_some() => {
+                        self.follow_schedule(connection_handler);
+                        self.follow_eod(connection_handler);
+                    }
+                    _ = failover.tick(), if self.failover.is_some() => {
+                        self.follow_failover(connection_handler);
+                        if self.reload {
+                            break None;
+                        }
+                    }
+                }
+            };
+            if self.shutting_down {
+                println!();
+                info!("shutdown signal received");
+                break;
+            }
+            // A remote client added a session, or a session failed over
+            if self.reload {
+                exit = ShellExit::Reload;
+                break;
+            }
+
+            // ================================================================
+            // Step 2: Handle EOF (CTRL-D)
+            // ================================================================
+            // If stdin reaches EOF (user pressed CTRL-D), the reader thread
+            // closes the channel. This is a common way to exit interactive
+            // programs. With the remote console, the shell goes on: a
+            // service runs with stdin at /dev/null.
+            // ================================================================
+            
+            let Some(line) = line else {
+                if self.remote.is_some() {
+                    info!("stdin closed, the remote console goes on");
+                    self.stdin_closed = true;
+                    continue;
+                }
+                if self.pipe {
+                    info!("end of the piped lines");
+                } else {
+                    info!("CTRL-D");
+                }
+                break;
+            };
+            
+            // ================================================================
+            // Step 3: Parse and execute the command
+            // ================================================================
+            // Try to parse the input string into a ShellCommand
+            // If successful, execute it. If parsing fails, show error.
+            // ================================================================
+            
+            // A line of the pipe may be a JSON object, and has a result
+            if self.pipe {
+                if self.run_piped(&line, connection_handler).await {
+                    break;
+                }
+            } else {
+                match line.parse::<ShellCommand>() {
+                    // User wants to quit
+                    Ok(ShellCommand::Quit) => break,
+
+                    // Execute the command (or report the parse error), timed
+                    command => {
+                        self.run_line(&line, command, connection_handler).await;
+                    }
+                }
+            }
+
+            // ================================================================
+            // Step 4: Hand back to main() if a session was added or switched
+            // ================================================================
+            
+            if self.reload {
+                exit = ShellExit::Reload;
+                break;
+            }
+        }
+
+        if let Some(blotter) = &self.blotter {
+            blotter.leave();
+        }
+        exit
+    }
+}
+
+/// TestRequest (35=1); the engine fills the header
+fn test_request_message(test_req_id: &str) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "1"))?;
+    msg.set_field(112, test_req_id)?; // TestReqID
+    Ok(msg)
+}
+
+/// ResendRequest (35=2)
+fn resend_request_message(begin: u64, end: u64) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "2"))?;
+    msg.set_field(7, begin.to_string().as_str())?; // BeginSeqNo
+    msg.set_field(16, end.to_string().as_str())?; // EndSeqNo
+    Ok(msg)
+}
+
+/// Result code of a command step, its error reported with a hint
+fn outcome(command: &str, result: Result<(), ShellError>) -> ResultCode {
+    match result {
+        Ok(()) => ResultCode::Ok,
+        Err(err) => {
+            warn!(command, hint = err.hint(), "{err}");
+            err.code()
+        }
+    }
+}
+
+/// Validation error listing the problems found, if any
+fn validated(problems: Vec<String>) -> Result<(), ShellError> {
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ShellError::Validation(problems))
+    }
+}
+
+/// Result code of a connection handler call
+fn engine_code<T, E>(result: &Result<T, E>) -> ResultCode {
+    match result {
+        Ok(_) => ResultCode::Ok,
+        Err(_) => ResultCode::EngineError,
+    }
+}
+
+/// A field of the tag dictionary: tag, name, type, then its values
+fn print_field(def: &FieldDef) {
+    let origin = if def.custom { "venue" } else { "standard" };
+    println!("{:<6} {:<24} {:<20} {origin}", def.tag, def.name, def.field_type.as_str());
+    for (value, name) in &def.values {
+        println!("{:<6} {value:<24} {name}", "");
+    }
+    if def.closed {
+        println!("{:<6} (no other value accepted)", "");
+    }
+}
+
+// =============================================================================
+// Usage Pattern
+// =============================================================================
+// This shell demonstrates several important Rust patterns:
+//
+// 1. Generic Programming:
+//    - Works with any type implementing ConnectionHandler
+//    - No code duplication for Acceptor vs Initiator
+//
+// 2. Resource Management:
+//    - stdin read by one background thread for the shell lifetime
+//    - Signal handlers installed once, not per prompt or per watch
+//    - Explicit stdout flushing for responsive UI
+//
+// 3. Error Handling:
+//    - Uses Result types throughout
+//    - Graceful error messages for users
+//    - Panics only on truly unexpected errors (I/O failure)
+//
+// 4. Interactive UX:
+//    - Clear prompts and help text
+//    - Immediate feedback on command execution
+//    - Support for CTRL-D and CTRL-C (standard Unix conventions)
+//
+// =============================================================================
+// Testing Workflow Example
 // =============================================================================
 //
 // 1. Start two terminals
@@ -335,7 +3302,8 @@ impl FixShell<'_> {
 //    >> connection handler START
 //    >> Type 'help' or '?' for more information
 //    FIX> status
-//    Connection handler status: logged_on=false, stopped=false
+//    INFO command="status" logged_on=Ok(false) stopped=Ok(false)
+//    INFO command="status" code="OK" elapsed=9µs
 //
 // 3. Terminal 2 - Start initiator:
 //    $ cargo run --example fix_repl -- initiator initiator.cfg
@@ -343,12 +3311,19 @@ impl FixShell<'_> {
 //    >> connection handler START
 //    >> Type 'help' or '?' for more information
 //    FIX> status
-//    Connection handler status: logged_on=true, stopped=false
+//    INFO command="status" logged_on=Ok(true) stopped=Ok(false)
+//    INFO command="status" code="OK" elapsed=8µs
 //
 // 4. Terminal 2 - Send a test order:
 //    FIX> send_to 35=D|55=AAPL|54=1|38=100|40=2|44=150.00 CLIENT EXCHANGE
-//    Sending Message(...) to SessionId(...)
-//    SEND_RESULT: Ok(())
+//    INFO session{id=FIX.4.4:CLIENT->EXCHANGE}: command="send_to" message sent
+//    INFO command="send_to" code="OK" elapsed=61µs
+//
+//    Per-command timings so far:
+//    FIX> history --stats
+//    command      count  failed          min          avg          max
+//    send_to          1       0       61µs         61µs         61µs
+//    status           1       0        8µs          8µs          8µs
 //
 // 5. Terminal 1 - Should receive and log the order in on_msg_from_app callback
 //
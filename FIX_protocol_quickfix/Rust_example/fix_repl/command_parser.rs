@@ -5,15 +5,40 @@
 // It demonstrates:
 // - Custom error types with the Error trait
 // - FromStr trait implementation for string parsing
-// - FIX message construction from text format
+// - FIX message fields from text format
 // - SessionId creation
 //
 // The parser supports a simple command syntax for interacting with FIX sessions.
 // =============================================================================
 
-use std::{error::Error, fmt, str::FromStr};
+use std::{error::Error, fmt, path::PathBuf, str::FromStr, time::Duration};
 
-use quickfix::{FieldMap, Message, SessionId};
+use trading::{
+    audit::{AuditQuery, AuditSource},
+    bench::{Load, StoreKind, ThroughputOptions},
+    conformance::certify::CertificationSettings,
+    oms::{
+        blotter::{BlotterError, BlotterFilter},
+        captures::CaptureFilter,
+        report::ReportFormat,
+    },
+    session::{eod::EodAction, faults::Fault, provisioning::SessionSpec, version::FixVersion},
+    time::{parse_iso8601, parse_utc_timestamp, unix_now, Date},
+};
+
+use crate::{
+    export::ExportKind,
+    mute::MuteTarget,
+    orders::{parse_age, CancelFilter, Side},
+    roles::Role,
+    watch::{WatchTarget, DEFAULT_DEPTH},
+};
+
+/// Repaint period of `watch` when --interval is not given
+const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Entries shown by `audit` when no count is given
+const DEFAULT_AUDIT_ENTRIES: usize = 20;
 
 // =============================================================================
 // Error Types
@@ -60,6 +85,22 @@ impl fmt::Display for BadCommand {
 // Implement Error trait to make this a standard Rust error
 impl Error for BadCommand {}
 
+// =============================================================================
+// Send Target
+// =============================================================================
+// send_to names the two CompIDs; the BeginString comes from the session
+// configured between them, or from --version (see command_exec.rs)
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct SendTarget {
+    pub sender: String,
+    pub target: String,
+
+    /// `--version`: FIX.4.0 to FIX.5.0SP2
+    pub version: Option<FixVersion>,
+}
+
 // =============================================================================
 // Command Enumeration
 // =============================================================================
@@ -91,13 +132,354 @@ pub enum ShellCommand {
     Poll,
     
     /// Send a FIX message to a specific session
-    /// Parameters: (message, session_id)
-    SendMessage(Message, SessionId),
+    /// Parameters: TAG=VALUE fields in command order, tags and values as
+    /// typed (numbers or names, resolved through the tag dictionary when the
+    /// command runs), then sender/target/version
+    SendMessage {
+        fields: Vec<(String, String)>,
+        target: SendTarget,
+    },
+    
+    /// Send a message built from a template (see templates.rs)
+    /// Parameters: template name, NAME=VALUE placeholders in command order
+    SendTemplate { name: String, values: Vec<(String, String)> },
+    
+    /// List the loaded templates and their placeholders
+    Templates,
+    
+    /// Show the last commands, or per-command timings with `--stats`
+    History { stats: bool },
+    
+    /// Cancel every working order matching the filter, after confirmation
+    CancelAll(CancelFilter),
+    
+    /// Repaint a live view every `interval` until Enter is pressed
+    Watch { target: WatchTarget, interval: Duration },
+    
+    /// Draw a book with our orders marked, live or from a --book-export
+    /// file at a time (Unix milliseconds; see book_export.rs)
+    Book { symbol: String, depth: usize, from: Option<PathBuf>, at: Option<i64> },
+    
+    /// Show a page (1-based) of the fills matching the filter, newest
+    /// first, or write all of them to a CSV file (trading::oms::blotter)
+    Blotter { filter: BlotterFilter, page: usize, csv: Option<PathBuf> },
+    
+    /// Write the orders tracked, the fills or the positions to a CSV file,
+    /// with the columns given in their order (see export.rs)
+    Export { kind: ExportKind, path: PathBuf, columns: Vec<String> },
+    
+    /// Send a TradeCaptureReportRequest (35=AD) for the trades of a day on
+    /// a session (a selector as for watch session)
+    RequestTrades { session: String, date: Date, symbol: Option<String> },
+    
+    /// Show the trade capture reports received, matched with the blotter
+    /// by ExecID, or write them to a CSV file (see captures.rs)
+    Trades { filter: CaptureFilter, unmatched: bool, csv: Option<PathBuf> },
+    
+    /// Show the trading report of a UTC day (today by default), for one
+    /// session (a selector as for watch session) or all, or write it to a
+    /// file (trading::oms::report)
+    Report {
+        session: Option<String>,
+        date: Date,
+        format: ReportFormat,
+        out: Option<PathBuf>,
+    },
+    
+    /// Send an OrderMassStatusRequest (35=AF) on a session (a selector as
+    /// for watch session), or list the requests sent when None
+    MassStatus { session: Option<String>, symbol: Option<String> },
+    
+    /// Register a new session and rebuild the connection handler with it
+    AddSession(SessionSpec),
+    
+    /// Ask for a new counterparty session interactively (onboarding.rs),
+    /// then register it as AddSession does
+    OnboardSession,
+    
+    /// Measure engine throughput with an in-process acceptor/initiator pair
+    SelfTest(ThroughputOptions),
+    
+    /// Show heartbeat round trips and missed heartbeats per session
+    Health,
+    
+    /// Show inbound latencies per session and MsgType, then clear them
+    /// with `--reset`
+    Latency { reset: bool },
+    
+    /// Show messages sent and received per session, or by MsgType for one
+    /// session, a selector as for watch session (trading::session::stats)
+    Stats { session: Option<String> },
+    
+    /// Show clock sync over the current period, or with `--report` save
+    /// it to the audit directory and start a new one (--ntp)
+    Clock { report: bool },
+    
+    /// Lay the blotter out again, e.g. after resizing the terminal
+    Redraw,
+    
+    /// Send a TestRequest (35=1) and wait for the Heartbeat answering it
+    /// Parameter: session selector (index, label or CompID, as for watch)
+    TestRequest(String),
+    
+    /// Send a ResendRequest (35=2) for BeginSeqNo..=EndSeqNo (0 = no end)
+    Resend { session: String, begin: u64, end: u64 },
+    
+    /// Run the scenarios of a file against a session and report each step
+    /// (trading::conformance), optionally saving the report
+    Conformance { path: PathBuf, session: String, report: Option<PathBuf> },
+    
+    /// Run the built-in certification battery against a session
+    /// (trading::conformance::certify), optionally saving the report
+    Certify { session: String, settings: CertificationSettings, report: Option<PathBuf> },
+    
+    /// Switch an outbound fault on with its value or off with 0
+    /// (trading::session::faults), every fault off with `clear`; with
+    /// neither, show them
+    Faults { setting: Option<(Fault, u64)>, clear: bool },
+    
+    /// Show the last `limit` Rejects and BusinessMessageRejects received,
+    /// with the message each one rejects (trading::session::rejects)
+    Rejects { limit: usize },
+    
+    /// Show the messages waiting for their session to log on; with `clear`,
+    /// drop them, for one session (a selector as for watch session) or all
+    /// (trading::session::send_queue)
+    Queue { clear: bool, session: Option<String> },
+    
+    /// Show the sessions down with orders open, what the logons did with
+    /// them and the orders left for review; with `resolve`, take one
+    /// (ClOrdID) or all off the review list (trading::oms::disconnect)
+    Disconnects { resolve: bool, cl_ord_id: Option<String> },
+    
+    /// Show the gateway each session with a backup connects to; with
+    /// `switch`, move one session (a selector as for watch session) or all
+    /// to their other gateway (trading::session::failover)
+    Failover { switch: bool, session: Option<String> },
+    
+    /// Show the end-of-day plans and the next run of each task; with `run`,
+    /// run one task now for the last close, on one session (a selector as
+    /// for watch session) or every session that has it (see eod.rs)
+    Eod { run: Option<(EodAction, Option<String>)> },
+    
+    /// Show the counterparty scorecards over the last `days` days, merged
+    /// per session, or day by day for one session (a selector as for watch
+    /// session), or write the days to a CSV file (see scorecard.rs)
+    Scorecard { session: Option<String>, days: u32, csv: Option<PathBuf> },
+    
+    /// Switch the garbled message diagnostics on or off; with neither, show
+    /// the messages discarded so far (trading::session::garbled)
+    Garbled { enabled: Option<bool> },
+    
+    /// Query the operator audit log (trading::audit)
+    Audit(AuditQuery),
+    
+    /// Show a field of the tag dictionary by tag or name; without one, the
+    /// venue fields (trading::session::dictionary)
+    Dictionary { field: Option<String> },
+    
+    /// Take messages out of the message log, on one session (a selector as
+    /// for watch session) or all, summarized unless `drop`; without a
+    /// target, show the rules (see mute.rs)
+    Mute { target: Option<MuteTarget>, session: Option<String>, drop: bool },
+    
+    /// Remove mute rules; every rule without a target
+    Unmute { target: Option<MuteTarget>, session: Option<String> },
+    
+    /// Switch to another role, up to the one granted to the login; without
+    /// one, show the role and the grant (see roles.rs)
+    Login(Option<Role>),
     
     /// Empty command (user just pressed Enter)
     NoOperation,
 }
 
+impl ShellCommand {
+    /// Command name, as typed by the user
+    /// Used to group timings in `history --stats`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::Help => "help",
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Status => "status",
+            Self::Block => "block",
+            Self::Poll => "poll",
+            Self::SendMessage { .. } => "send_to",
+            Self::SendTemplate { .. } => "send tmpl",
+            Self::Templates => "templates",
+            Self::History { .. } => "history",
+            Self::CancelAll(_) => "cancel-all",
+            Self::Watch { .. } => "watch",
+            Self::Book { .. } => "book",
+            Self::Blotter { .. } => "blotter",
+            Self::Export { .. } => "export",
+            Self::RequestTrades { .. } => "trades request",
+            Self::Trades { .. } => "trades",
+            Self::Report { .. } => "report",
+            Self::MassStatus { .. } => "mass_status",
+            Self::AddSession(_) => "add_session",
+            Self::OnboardSession => "session add",
+            Self::SelfTest(_) => "selftest",
+            Self::Health => "health",
+            Self::Latency { .. } => "latency",
+            Self::Stats { .. } => "stats",
+            Self::Clock { .. } => "clock",
+            Self::Redraw => "redraw",
+            Self::TestRequest(_) => "test_request",
+            Self::Resend { .. } => "resend",
+            Self::Conformance { .. } => "conformance",
+            Self::Certify { .. } => "certify",
+            Self::Faults { .. } => "fault",
+            Self::Rejects { .. } => "rejects",
+            Self::Queue { .. } => "queue",
+            Self::Disconnects { .. } => "disconnects",
+            Self::Failover { .. } => "failover",
+            Self::Eod { .. } => "eod",
+            Self::Scorecard { .. } => "scorecard",
+            Self::Garbled { .. } => "garbled",
+            Self::Audit(_) => "audit",
+            Self::Dictionary { .. } => "dict",
+            Self::Mute { .. } => "mute",
+            Self::Unmute { .. } => "unmute",
+            Self::Login(_) => "login",
+            Self::NoOperation => "",
+        }
+    }
+
+    /// Whether the command changes something (sessions, orders, settings,
+    /// diagnostics), which puts it in the audit log
+    pub fn changes_state(&self) -> bool {
+        match self {
+            Self::Start
+            | Self::Stop
+            | Self::SendMessage { .. }
+            | Self::SendTemplate { .. }
+            | Self::CancelAll(_)
+            | Self::AddSession(_)
+            | Self::OnboardSession
+            | Self::TestRequest(_)
+            | Self::Resend { .. }
+            | Self::RequestTrades { .. }
+            | Self::Conformance { .. }
+            | Self::Certify { .. }
+            | Self::Unmute { .. } => true,
+            Self::Latency { reset } => *reset,
+            Self::Clock { report } => *report,
+            Self::Faults { setting, clear } => setting.is_some() || *clear,
+            Self::Garbled { enabled } => enabled.is_some(),
+            Self::Queue { clear, .. } => *clear,
+            Self::Disconnects { resolve, .. } => *resolve,
+            Self::Failover { switch, .. } => *switch,
+            Self::Eod { run } => run.is_some(),
+            Self::Mute { target, .. } => target.is_some(),
+            Self::MassStatus { session, .. } => session.is_some(),
+            Self::Login(role) => role.is_some(),
+            Self::Quit
+            | Self::Help
+            | Self::Status
+            | Self::Block
+            | Self::Poll
+            | Self::Templates
+            | Self::History { .. }
+            | Self::Watch { .. }
+            | Self::Book { .. }
+            | Self::Blotter { .. }
+            | Self::Export { .. }
+            | Self::Trades { .. }
+            | Self::Report { .. }
+            | Self::SelfTest(_)
+            | Self::Health
+            | Self::Stats { .. }
+            | Self::Redraw
+            | Self::Rejects { .. }
+            | Self::Scorecard { .. }
+            | Self::Audit(_)
+            | Self::Dictionary { .. }
+            | Self::NoOperation => false,
+        }
+    }
+
+    /// The role the command needs to run (see roles.rs)
+    pub fn required_role(&self) -> Role {
+        match self {
+            Self::Start
+            | Self::Stop
+            | Self::Block
+            | Self::Poll
+            | Self::AddSession(_)
+            | Self::OnboardSession
+            | Self::Resend { .. }
+            | Self::Conformance { .. }
+            | Self::Certify { .. }
+            | Self::SelfTest(_)
+            | Self::Failover { switch: true, .. }
+            | Self::Eod { run: Some((EodAction::ArchiveStore, _)) } => Role::Admin,
+            Self::Faults { setting, clear } if setting.is_some() || *clear => Role::Admin,
+            Self::Garbled { enabled: Some(_) } => Role::Admin,
+            Self::SendMessage { fields, .. } if is_admin_message(fields) => Role::Admin,
+            Self::SendMessage { .. }
+            | Self::SendTemplate { .. }
+            | Self::CancelAll(_)
+            | Self::RequestTrades { .. }
+            | Self::TestRequest(_)
+            | Self::Unmute { .. } => Role::Trader,
+            Self::MassStatus { session: Some(_), .. }
+            | Self::Queue { clear: true, .. }
+            | Self::Disconnects { resolve: true, .. }
+            | Self::Mute { target: Some(_), .. }
+            | Self::Latency { reset: true }
+            | Self::Clock { report: true }
+            | Self::Eod { run: Some(_) } => Role::Trader,
+            Self::Quit
+            | Self::Help
+            | Self::Status
+            | Self::Templates
+            | Self::History { .. }
+            | Self::Watch { .. }
+            | Self::Book { .. }
+            | Self::Blotter { .. }
+            | Self::Export { .. }
+            | Self::Trades { .. }
+            | Self::Report { .. }
+            | Self::MassStatus { .. }
+            | Self::Health
+            | Self::Latency { .. }
+            | Self::Stats { .. }
+            | Self::Clock { .. }
+            | Self::Redraw
+            | Self::Faults { .. }
+            | Self::Rejects { .. }
+            | Self::Queue { .. }
+            | Self::Disconnects { .. }
+            | Self::Failover { .. }
+            | Self::Eod { .. }
+            | Self::Scorecard { .. }
+            | Self::Garbled { .. }
+            | Self::Audit(_)
+            | Self::Dictionary { .. }
+            | Self::Mute { .. }
+            | Self::Login(_)
+            | Self::NoOperation => Role::ReadOnly,
+        }
+    }
+}
+
+/// Whether send_to fields make a session-level message (Heartbeat to
+/// Logout, Logon), by MsgType number or name
+fn is_admin_message(fields: &[(String, String)]) -> bool {
+    fields.iter().any(|(tag, value)| {
+        matches!(tag.as_str(), "35" | "MsgType")
+            && matches!(
+                value.as_str(),
+                "0" | "1" | "2" | "3" | "4" | "5" | "A"
+                    | "Heartbeat" | "TestRequest" | "ResendRequest" | "Reject"
+                    | "SequenceReset" | "Logout" | "Logon"
+            )
+    })
+}
+
 // =============================================================================
 // Command Parser Implementation
 // =============================================================================
@@ -116,9 +498,60 @@ impl FromStr for ShellCommand {
     /// - `start` - Start connection handler
     /// - `stop` - Stop connection handler
     /// - `status` - Show connection status
+    /// - `health` - Show heartbeat health per session
+    /// - `latency [--reset]` - Show SendingTime / TransactTime latencies
+    /// - `stats [SESSION]` - Show message counts per session / MsgType
+    /// - `clock [--report]` - Show / save clock sync against the --ntp server
+    /// - `redraw` - Lay the blotter out again (--tui)
     /// - `block` - Block for messages
     /// - `poll` - Poll for messages
-    /// - `send_to MSG SENDER TARGET` - Send FIX message
+    /// - `send_to MSG SENDER TARGET [--version V]` - Send FIX message (tags
+    ///   and values by number or dictionary name)
+    /// - `send tmpl NAME [KEY=VALUE...]` - Send a message from a template
+    /// - `templates` - List the loaded templates
+    /// - `history [--stats]` - Show command history / timings
+    /// - `cancel-all [--symbol S] [--side buy|sell] [--session N]
+    ///   [--older-than 5m] [--mass]` - Cancel working orders
+    /// - `watch positions [S] | book S [DEPTH] | session N | quotes [S] |
+    ///   blotter [FILTERS] [--interval MS]` - Live view until Enter
+    /// - `book S [DEPTH] [--from FILE [--at TIME]]` - Draw a book, our orders
+    ///   marked, live or as exported
+    /// - `blotter [--account A] [--symbol S] [--side buy|sell] [--min-qty N]
+    ///   [--page N] [--csv FILE]` - Fills received, a page or all as CSV
+    /// - `export orders|fills|positions FILE [--columns C1,C2,...]` - Orders,
+    ///   fills or positions as CSV
+    /// - `trades request N [--date YYYYMMDD] [--symbol S]` - Ask the venue
+    ///   for its trade capture reports
+    /// - `trades [--session N] [--date YYYYMMDD] [--symbol S] [--unmatched]
+    ///   [--csv FILE]` - Reports received, matched with the blotter
+    /// - `report [N] [--date YYYYMMDD] [--format text|html|json] [--out FILE]`
+    ///   - Trading report of a day
+    /// - `mass_status [N [--symbol S]]` - Ask the venue for the status of our
+    ///   orders, or list the requests and discrepancies
+    /// - `add_session BEGIN SENDER TARGET [port=N] [KEY=VALUE...]` - Add a session
+    /// - `session add` - Add a counterparty session, question by question
+    /// - `selftest throughput [SECONDS] [--store memory|file] [--multi-threaded]`
+    ///   - Measure throughput and latency in-process
+    /// - `test_request N` - Send 35=1, report the Heartbeat round trip
+    /// - `resend N BEGIN END` - Send 35=2 (END 0 = up to the last message)
+    /// - `conformance FILE N [--report PATH]` - Run certification scenarios
+    /// - `certify N [--symbol S] [--qty Q] [--price P] [--report PATH]` - Run
+    ///   the standard certification battery
+    /// - `fault [off | drop N | corrupt N | jitter MS | skip-seq N | heartbeat S]`
+    ///   - Show / switch outbound faults (0 = off)
+    /// - `rejects [N]` - Last N rejects received (default 20)
+    /// - `queue [clear [N]]` - Messages waiting for a logon / drop them
+    /// - `eod [run TASK [N]]` - End-of-day plans / run a task now
+    /// - `scorecard [N] [--days D] [--csv FILE]` - Counterparty statistics,
+    ///   per session or day by day for one
+    /// - `garbled [on | off]` - Discarded messages / switch the diagnostics
+    /// - `audit [N] [--since AGE] [--source S] [--actor A] [--grep TEXT] [--failed]`
+    ///   - Query the operator audit log
+    /// - `dict [TAG | NAME]` - A field of the tag dictionary / the venue fields
+    /// - `mute [admin | TAG=VALUE] [--session N] [--drop]` - Take messages out
+    ///   of the log / show the rules
+    /// - `unmute all | admin | TAG=VALUE [--session N]` - Log them again
+    /// - `login [read-only | trader | admin]` - Switch role / show it
     /// - (empty) - No operation
     fn from_str(source: &str) -> Result<Self, Self::Err> {
         // Trim whitespace and match against known commands
@@ -133,18 +566,142 @@ impl FromStr for ShellCommand {
             "start" => Ok(Self::Start),
             "stop" => Ok(Self::Stop),
             "status" => Ok(Self::Status),
+            "health" => Ok(Self::Health),
+            "latency" => Ok(Self::Latency { reset: false }),
+            "latency --reset" => Ok(Self::Latency { reset: true }),
+            "clock" => Ok(Self::Clock { report: false }),
+            "clock --report" => Ok(Self::Clock { report: true }),
+            "redraw" => Ok(Self::Redraw),
+            "templates" => Ok(Self::Templates),
+            
+            // Message counters
+            cmd if cmd == "stats" || cmd.starts_with("stats ") => {
+                match cmd.split_whitespace().skip(1).collect::<Vec<_>>()[..] {
+                    [] => Ok(Self::Stats { session: None }),
+                    [session] => Ok(Self::Stats { session: Some(session.to_string()) }),
+                    _ => Err(BadCommand::InvalidArgument("expected: stats [SESSION]")),
+                }
+            }
             
             // Message processing modes
             "block" => Ok(Self::Block),
             "poll" => Ok(Self::Poll),
             
+            // Command history
+            "history" => Ok(Self::History { stats: false }),
+            cmd if cmd.starts_with("history ") => parse_history(cmd),
+            
+            // Bulk cancel
+            "cancel-all" => Ok(Self::CancelAll(CancelFilter::default())),
+            cmd if cmd.starts_with("cancel-all ") => parse_cancel_all(cmd).map(Self::CancelAll),
+            
+            // Live views
+            cmd if cmd == "watch" || cmd.starts_with("watch ") => parse_watch(cmd),
+            cmd if cmd == "book" || cmd.starts_with("book ") => parse_book(cmd),
+            cmd if cmd == "blotter" || cmd.starts_with("blotter ") => parse_blotter(cmd),
+            
+            // CSV export
+            cmd if cmd == "export" || cmd.starts_with("export ") => parse_export(cmd),
+            
+            // Trade capture reports
+            cmd if cmd == "trades" || cmd.starts_with("trades ") => parse_trades(cmd),
+            
+            // Daily trading report
+            cmd if cmd == "report" || cmd.starts_with("report ") => parse_report(cmd),
+            
+            // Order mass status
+            cmd if cmd == "mass_status" || cmd.starts_with("mass_status ") => {
+                parse_mass_status(cmd)
+            }
+            
+            // Session provisioning
+            "session add" => Ok(Self::OnboardSession),
+            cmd if cmd == "add_session" || cmd.starts_with("add_session ") => {
+                parse_add_session(cmd).map(Self::AddSession)
+            }
+            
+            // Engine self-test
+            cmd if cmd == "selftest" || cmd.starts_with("selftest ") => {
+                parse_selftest(cmd).map(Self::SelfTest)
+            }
+            
+            // Session-level messages
+            cmd if cmd == "test_request" || cmd.starts_with("test_request ") => {
+                parse_test_request(cmd)
+            }
+            cmd if cmd == "resend" || cmd.starts_with("resend ") => parse_resend(cmd),
+            
+            // Counterparty certification
+            cmd if cmd == "conformance" || cmd.starts_with("conformance ") => {
+                parse_conformance(cmd)
+            }
+            cmd if cmd == "certify" || cmd.starts_with("certify ") => parse_certify(cmd),
+            
+            // Fault injection
+            cmd if cmd == "fault" || cmd.starts_with("fault ") => parse_fault(cmd),
+            
+            // Rejects received
+            cmd if cmd == "rejects" || cmd.starts_with("rejects ") => parse_rejects(cmd),
+            
+            // Send queue
+            cmd if cmd == "queue" || cmd.starts_with("queue ") => parse_queue(cmd),
+            
+            // Cancel on disconnect
+            cmd if cmd == "disconnects" || cmd.starts_with("disconnects ") => {
+                parse_disconnects(cmd)
+            }
+            
+            // Gateway failover
+            cmd if cmd == "failover" || cmd.starts_with("failover ") => parse_failover(cmd),
+            
+            // End-of-day tasks
+            cmd if cmd == "eod" || cmd.starts_with("eod ") => parse_eod(cmd),
+            
+            // Counterparty scorecards
+            cmd if cmd == "scorecard" || cmd.starts_with("scorecard ") => parse_scorecard(cmd),
+            
+            // Garbled message diagnostics
+            "garbled" => Ok(Self::Garbled { enabled: None }),
+            "garbled on" => Ok(Self::Garbled { enabled: Some(true) }),
+            "garbled off" => Ok(Self::Garbled { enabled: Some(false) }),
+            
+            // Operator audit log
+            cmd if cmd == "audit" || cmd.starts_with("audit ") => {
+                parse_audit(cmd).map(Self::Audit)
+            }
+            
+            // Tag dictionary lookup
+            cmd if cmd == "dict" || cmd.starts_with("dict ") => {
+                match cmd.split_whitespace().skip(1).collect::<Vec<_>>()[..] {
+                    [] => Ok(Self::Dictionary { field: None }),
+                    [field] => Ok(Self::Dictionary { field: Some(field.to_string()) }),
+                    _ => Err(BadCommand::InvalidArgument("expected: dict [TAG | NAME]")),
+                }
+            }
+            
+            // Message log filter
+            cmd if cmd == "mute" || cmd.starts_with("mute ") => parse_mute(cmd),
+            cmd if cmd == "unmute" || cmd.starts_with("unmute ") => parse_mute(cmd),
+            
+            // Command roles
+            cmd if cmd == "login" || cmd.starts_with("login ") => {
+                match cmd.split_whitespace().skip(1).collect::<Vec<_>>()[..] {
+                    [] => Ok(Self::Login(None)),
+                    [role] => Role::from_name(role)
+                        .map(|x| Self::Login(Some(x)))
+                        .ok_or(BadCommand::InvalidArgument("expected read-only, trader or admin")),
+                    _ => Err(BadCommand::InvalidArgument("expected: login [ROLE]")),
+                }
+            }
+            
             // Empty input
             "" => Ok(Self::NoOperation),
             
             // Send message command - more complex parsing required
             cmd if cmd.starts_with("send_to ") => {
-                parse_send_to(cmd).map(|x| Self::SendMessage(x.0, x.1))
+                parse_send_to(cmd).map(|(fields, target)| Self::SendMessage { fields, target })
             }
+            cmd if cmd == "send" || cmd.starts_with("send ") => parse_send_template(cmd),
             
             // Unknown command
             cmd => Err(BadCommand::Unknown(cmd.to_string())),
@@ -152,11 +709,926 @@ impl FromStr for ShellCommand {
     }
 }
 
+// =============================================================================
+// History Parser
+// =============================================================================
+// Only one option for now:
+//   history --stats
+// =============================================================================
+
+fn parse_history(source: &str) -> Result<ShellCommand, BadCommand> {
+    let args: Vec<_> = source.split_whitespace().skip(1).collect();
+    match args.as_slice() {
+        [] => Ok(ShellCommand::History { stats: false }),
+        ["--stats"] => Ok(ShellCommand::History { stats: true }),
+        [_] => Err(BadCommand::InvalidArgument("expected --stats")),
+        _ => Err(BadCommand::InvalidArgumentCount {
+            current: args.len(),
+            expected: 1,
+        }),
+    }
+}
+
+// =============================================================================
+// Cancel-All Parser
+// =============================================================================
+// Options come in any order, each at most once:
+//   cancel-all --symbol AAPL --side buy --session EXCHANGE --older-than 5m
+//   cancel-all --symbol AAPL --mass
+// =============================================================================
+
+fn parse_cancel_all(source: &str) -> Result<CancelFilter, BadCommand> {
+    let mut filter = CancelFilter::default();
+    let mut tokens = source.split_whitespace().skip(1);
+
+    while let Some(option) = tokens.next() {
+        if option == "--mass" {
+            filter.mass = true;
+            continue;
+        }
+
+        let value = tokens
+            .next()
+            .ok_or(BadCommand::InvalidArgument("option without a value"))?;
+        match option {
+            "--symbol" => filter.symbol = Some(value.to_string()),
+            "--side" => {
+                filter.side = Some(
+                    Side::from_name(value)
+                        .ok_or(BadCommand::InvalidArgument("side must be buy or sell"))?,
+                );
+            }
+            "--session" => filter.session = Some(value.to_string()),
+            "--older-than" => {
+                filter.older_than = Some(
+                    parse_age(value)
+                        .ok_or(BadCommand::InvalidArgument("age must look like 30s, 5m or 2h"))?,
+                );
+            }
+            _ => return Err(BadCommand::InvalidArgument("unknown cancel-all option")),
+        }
+    }
+
+    // A mass cancel is evaluated by the venue, which knows nothing of the
+    // time we sent each order
+    if filter.mass && filter.older_than.is_some() {
+        return Err(BadCommand::InvalidArgument("--older-than cannot be used with --mass"));
+    }
+
+    Ok(filter)
+}
+
+// =============================================================================
+// Watch Parser
+// =============================================================================
+//   watch positions [SYMBOL]
+//   watch book SYMBOL [DEPTH]
+//   watch session N
+//   watch quotes [SYMBOL]
+//   watch blotter [--account A] [--symbol S] [--side buy|sell] [--min-qty N]
+// each optionally followed by --interval <milliseconds>
+// =============================================================================
+
+fn parse_watch(source: &str) -> Result<ShellCommand, BadCommand> {
+    let mut args: Vec<_> = source.split_whitespace().skip(1).collect();
+
+    let mut interval = DEFAULT_WATCH_INTERVAL;
+    if let Some(index) = args.iter().position(|x| *x == "--interval") {
+        let millis: u64 = args
+            .get(index + 1)
+            .and_then(|x| x.parse().ok())
+            .filter(|x| *x > 0)
+            .ok_or(BadCommand::InvalidArgument("interval must be a positive number of ms"))?;
+        interval = Duration::from_millis(millis);
+        args.drain(index..index + 2);
+    }
+
+    let target = match args.as_slice() {
+        ["positions"] => WatchTarget::Positions(None),
+        ["positions", symbol] => WatchTarget::Positions(Some(symbol.to_string())),
+        ["book", symbol] => WatchTarget::Book {
+            symbol: symbol.to_string(),
+            depth: DEFAULT_DEPTH,
+        },
+        ["book", symbol, depth] => WatchTarget::Book {
+            symbol: symbol.to_string(),
+            depth: depth
+                .parse()
+                .ok()
+                .filter(|x| *x > 0)
+                .ok_or(BadCommand::InvalidArgument("depth must be a positive number"))?,
+        },
+        ["session", session] => WatchTarget::Session(session.to_string()),
+        ["quotes"] => WatchTarget::Quotes(None),
+        ["quotes", symbol] => WatchTarget::Quotes(Some(symbol.to_string())),
+        ["blotter", options @ ..] => {
+            let mut filter = BlotterFilter::default();
+            for pair in options.chunks(2) {
+                let [option, value] = pair else {
+                    return Err(BadCommand::InvalidArgument("option without a value"));
+                };
+                set_blotter_option(&mut filter, option, value)?;
+            }
+            WatchTarget::Blotter(filter)
+        }
+        _ => {
+            return Err(BadCommand::InvalidArgument(
+                "expected: positions [S] | book S [DEPTH] | session N | quotes [S] | blotter",
+            ))
+        }
+    };
+
+    Ok(ShellCommand::Watch { target, interval })
+}
+
+// =============================================================================
+// Book Parser
+// =============================================================================
+//   book AAPL
+//   book AAPL 10
+//   book AAPL --from books/books-20240115.jsonl --at 20240115-14:03:07
+//   book AAPL --from books/books-20240115.jsonl --at 2024-01-15T14:03:07Z
+// =============================================================================
+
+fn parse_book(source: &str) -> Result<ShellCommand, BadCommand> {
+    let mut args: Vec<_> = source.split_whitespace().skip(1).collect();
+
+    let mut option = |flag: &str| -> Result<Option<String>, BadCommand> {
+        let Some(index) = args.iter().position(|x| *x == flag) else {
+            return Ok(None);
+        };
+        let value = args
+            .get(index + 1)
+            .ok_or(BadCommand::InvalidArgument("--from and --at need a value"))?
+            .to_string();
+        args.drain(index..index + 2);
+        Ok(Some(value))
+    };
+    let from = option("--from")?.map(PathBuf::from);
+    let at = match option("--at")? {
+        Some(time) => Some(
+            parse_utc_timestamp(&time)
+                .or_else(|| parse_iso8601(&time))
+                .ok_or(BadCommand::InvalidArgument(
+                    "--at must be a UTCTimestamp or an ISO 8601 time",
+                ))?
+                / 1_000_000,
+        ),
+        None => None,
+    };
+    if at.is_some() && from.is_none() {
+        return Err(BadCommand::InvalidArgument("--at needs --from"));
+    }
+
+    let (symbol, depth) = match args.as_slice() {
+        [symbol] => (symbol, DEFAULT_DEPTH),
+        [symbol, depth] => (
+            symbol,
+            depth
+                .parse()
+                .ok()
+                .filter(|x| *x > 0)
+                .ok_or(BadCommand::InvalidArgument("depth must be a positive number"))?,
+        ),
+        _ => {
+            return Err(BadCommand::InvalidArgument(
+                "expected: book SYMBOL [DEPTH] [--from FILE [--at TIME]]",
+            ))
+        }
+    };
+
+    Ok(ShellCommand::Book { symbol: symbol.to_string(), depth, from, at })
+}
+
+// =============================================================================
+// Blotter Parser
+// =============================================================================
+// Options come in any order, each at most once:
+//   blotter --account ACC1 --side buy --min-qty 100 --page 2
+//   blotter --symbol AAPL --csv aapl.csv
+// The filter options are the same for watch blotter
+// =============================================================================
+
+fn parse_blotter(source: &str) -> Result<ShellCommand, BadCommand> {
+    let mut filter = BlotterFilter::default();
+    let mut page = 1;
+    let mut csv = None;
+    let mut tokens = source.split_whitespace().skip(1);
+
+    while let Some(option) = tokens.next() {
+        let value = tokens
+            .next()
+            .ok_or(BadCommand::InvalidArgument("option without a value"))?;
+        match option {
+            "--page" => {
+                page = value
+                    .parse()
+                    .ok()
+                    .filter(|x| *x > 0)
+                    .ok_or(BadCommand::InvalidArgument("page must be a positive number"))?;
+            }
+            "--csv" => csv = Some(PathBuf::from(value)),
+            _ => set_blotter_option(&mut filter, option, value)?,
+        }
+    }
+
+    Ok(ShellCommand::Blotter { filter, page, csv })
+}
+
+/// One of --account, --symbol, --side and --min-qty
+fn set_blotter_option(
+    filter: &mut BlotterFilter,
+    option: &str,
+    value: &str,
+) -> Result<(), BadCommand> {
+    let name = match option {
+        "--account" => "account",
+        "--symbol" => "symbol",
+        "--side" => "side",
+        "--min-qty" => "min_qty",
+        _ => return Err(BadCommand::InvalidArgument("unknown blotter option")),
+    };
+    filter.set(name, value).map_err(|err| match err {
+        BlotterError::InvalidSide(_) => BadCommand::InvalidArgument("side must be buy or sell"),
+        _ => BadCommand::InvalidArgument("--min-qty must be a number, 0 or more"),
+    })
+}
+
+// =============================================================================
+// Export Parser
+// =============================================================================
+//   export orders orders.csv
+//   export fills fills.csv --columns time_ms,symbol,side,quantity,price
+// Every column of the kind unless --columns names some (export.rs)
+// =============================================================================
+
+fn parse_export(source: &str) -> Result<ShellCommand, BadCommand> {
+    const USAGE: &str = "expected: export orders|fills|positions FILE [--columns C1,C2,...]";
+    let mut tokens = source.split_whitespace().skip(1);
+    let kind = tokens
+        .next()
+        .and_then(ExportKind::from_name)
+        .ok_or(BadCommand::InvalidArgument(USAGE))?;
+    let path = tokens.next().ok_or(BadCommand::InvalidArgument(USAGE))?;
+    let mut columns: Vec<String> = kind.columns().iter().map(|x| x.to_string()).collect();
+
+    match (tokens.next(), tokens.next(), tokens.next()) {
+        (None, _, _) => {}
+        (Some("--columns"), Some(list), None) => {
+            columns = list
+                .split(',')
+                .filter(|x| !x.is_empty())
+                .map(str::to_string)
+                .collect();
+            if columns.is_empty() {
+                return Err(BadCommand::InvalidArgument("--columns requires a column"));
+            }
+            if columns.iter().any(|x| !kind.columns().contains(&x.as_str())) {
+                return Err(BadCommand::InvalidArgument("unknown column (help lists them)"));
+            }
+        }
+        _ => return Err(BadCommand::InvalidArgument(USAGE)),
+    }
+
+    Ok(ShellCommand::Export { kind, path: PathBuf::from(path), columns })
+}
+
+// =============================================================================
+// Trades Parser
+// =============================================================================
+//   trades request 1
+//   trades request 1 --date 20240115 --symbol AAPL
+//   trades --session 1 --date 20240115 --symbol AAPL --unmatched
+//   trades --csv venue.csv
+// A request is for today's trades unless --date says otherwise
+// =============================================================================
+
+fn parse_trades(source: &str) -> Result<ShellCommand, BadCommand> {
+    let mut tokens = source.split_whitespace().skip(1).peekable();
+    let session = match tokens.peek() {
+        Some(&"request") => {
+            tokens.next();
+            let session = tokens.next().ok_or(BadCommand::InvalidArgument(
+                "expected: trades request SESSION [--date YYYYMMDD] [--symbol S]",
+            ))?;
+            Some(session.to_string())
+        }
+        _ => None,
+    };
+    let listing = session.is_none();
+
+    let mut filter = CaptureFilter::default();
+    let mut unmatched = false;
+    let mut csv = None;
+    while let Some(option) = tokens.next() {
+        if option == "--unmatched" && listing {
+            unmatched = true;
+            continue;
+        }
+        let value = tokens
+            .next()
+            .ok_or(BadCommand::InvalidArgument("option without a value"))?;
+        match option {
+            "--date" => {
+                let date = Date::from_fix(value)
+                    .ok_or(BadCommand::InvalidArgument("--date must be YYYYMMDD"))?;
+                filter.trade_date = Some(date);
+            }
+            "--symbol" => filter.symbol = Some(value.to_string()),
+            "--session" if listing => filter.session = Some(value.to_string()),
+            "--csv" if listing => csv = Some(PathBuf::from(value)),
+            _ => return Err(BadCommand::InvalidArgument("unknown trades option")),
+        }
+    }
+
+    Ok(match session {
+        Some(session) => ShellCommand::RequestTrades {
+            session,
+            date: filter.trade_date.unwrap_or_else(Date::today),
+            symbol: filter.symbol,
+        },
+        None => ShellCommand::Trades { filter, unmatched, csv },
+    })
+}
+
+// =============================================================================
+// Report Parser
+// =============================================================================
+//   report                                     today, every session, as text
+//   report 1 --date 20261015                   one session, another day
+//   report --format html --out report.html     written to a file
+// =============================================================================
+
+fn parse_report(source: &str) -> Result<ShellCommand, BadCommand> {
+    let mut tokens = source.split_whitespace().skip(1).peekable();
+    let session = tokens
+        .next_if(|x| !x.starts_with("--"))
+        .map(str::to_string);
+    let mut date = Date::today();
+    let mut format = None;
+    let mut out: Option<PathBuf> = None;
+    while let Some(option) = tokens.next() {
+        let value = tokens
+            .next()
+            .ok_or(BadCommand::InvalidArgument("option without a value"))?;
+        match option {
+            "--date" => {
+                date = Date::from_fix(value)
+                    .ok_or(BadCommand::InvalidArgument("--date must be YYYYMMDD"))?;
+            }
+            "--format" => {
+                let value = value
+                    .parse()
+                    .map_err(|_| BadCommand::InvalidArgument("--format is text, html or json"))?;
+                format = Some(value);
+            }
+            "--out" => out = Some(PathBuf::from(value)),
+            _ => return Err(BadCommand::InvalidArgument("unknown report option")),
+        }
+    }
+
+    // A file of an unnamed format takes it from its extension
+    let format = format.unwrap_or_else(|| {
+        out.as_ref()
+            .and_then(|x| x.extension())
+            .and_then(|x| x.to_str())
+            .and_then(|x| x.parse().ok())
+            .unwrap_or_default()
+    });
+    Ok(ShellCommand::Report { session, date, format, out })
+}
+
+// =============================================================================
+// Mass Status Parser
+// =============================================================================
+//   mass_status
+//   mass_status 1
+//   mass_status 1 --symbol AAPL
+// =============================================================================
+
+fn parse_mass_status(source: &str) -> Result<ShellCommand, BadCommand> {
+    let mut tokens = source.split_whitespace().skip(1);
+    let session = tokens.next().map(str::to_string);
+    let symbol = match (tokens.next(), tokens.next()) {
+        (None, _) => None,
+        (Some("--symbol"), Some(symbol)) => Some(symbol.to_string()),
+        _ => {
+            return Err(BadCommand::InvalidArgument(
+                "expected: mass_status [SESSION [--symbol S]]",
+            ))
+        }
+    };
+    if tokens.next().is_some() {
+        return Err(BadCommand::InvalidArgument("expected: mass_status [SESSION [--symbol S]]"));
+    }
+    Ok(ShellCommand::MassStatus { session, symbol })
+}
+
+// =============================================================================
+// Scorecard Parser
+// =============================================================================
+//   scorecard                        today, every session
+//   scorecard --days 30              30 days merged per session
+//   scorecard 1 --days 30            session 1, day by day
+//   scorecard --days 7 --csv week.csv
+// =============================================================================
+
+fn parse_scorecard(source: &str) -> Result<ShellCommand, BadCommand> {
+    let mut session = None;
+    let mut days = 1;
+    let mut csv = None;
+    let mut tokens = source.split_whitespace().skip(1);
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "--days" => {
+                days = tokens
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|x| *x > 0)
+                    .ok_or(BadCommand::InvalidArgument("--days must be a positive number"))?;
+            }
+            "--csv" => {
+                let path = tokens
+                    .next()
+                    .ok_or(BadCommand::InvalidArgument("--csv requires a file"))?;
+                csv = Some(PathBuf::from(path));
+            }
+            selector if session.is_none() && !selector.starts_with("--") => {
+                session = Some(selector.to_string());
+            }
+            _ => {
+                return Err(BadCommand::InvalidArgument(
+                    "expected: scorecard [SESSION] [--days N] [--csv FILE]",
+                ))
+            }
+        }
+    }
+
+    Ok(ShellCommand::Scorecard { session, days, csv })
+}
+
+// =============================================================================
+// Add Session Parser
+// =============================================================================
+//   add_session FIX.4.4 EXCHANGE CLIENT2 port=5002 HeartBtInt=30
+// The three identifiers are positional, settings follow as KEY=VALUE
+// =============================================================================
+
+fn parse_add_session(source: &str) -> Result<SessionSpec, BadCommand> {
+    let mut tokens = source.split_whitespace().skip(1);
+
+    let mut next_id = |current| {
+        tokens
+            .next()
+            .map(str::to_string)
+            .ok_or(BadCommand::InvalidArgumentCount { current, expected: 3 })
+    };
+    let begin_string = next_id(0)?;
+    let sender_comp_id = next_id(1)?;
+    let target_comp_id = next_id(2)?;
+
+    let params = tokens
+        .map(|x| {
+            x.split_once('=')
+                .filter(|(key, _)| !key.is_empty())
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .ok_or(BadCommand::InvalidArgument("settings must look like KEY=VALUE"))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(SessionSpec {
+        begin_string,
+        sender_comp_id,
+        target_comp_id,
+        params,
+    })
+}
+
+// =============================================================================
+// Self-Test Parser
+// =============================================================================
+//   selftest throughput
+//   selftest throughput 30 --store file --multi-threaded
+// The duration is in seconds; options come in any order
+// =============================================================================
+
+fn parse_selftest(source: &str) -> Result<ThroughputOptions, BadCommand> {
+    let mut tokens = source.split_whitespace().skip(1);
+    if tokens.next() != Some("throughput") {
+        return Err(BadCommand::InvalidArgument("expected: selftest throughput"));
+    }
+
+    let mut options = ThroughputOptions::default();
+    while let Some(token) = tokens.next() {
+        match token {
+            "--multi-threaded" => options.multi_threaded = true,
+            "--store" => {
+                options.store = tokens
+                    .next()
+                    .and_then(StoreKind::from_name)
+                    .ok_or(BadCommand::InvalidArgument("store must be memory or file"))?;
+            }
+            seconds => {
+                options.load = seconds
+                    .parse()
+                    .ok()
+                    .filter(|x| *x > 0)
+                    .map(|x| Load::Duration(Duration::from_secs(x)))
+                    .ok_or(BadCommand::InvalidArgument("duration must be a positive number of seconds"))?;
+            }
+        }
+    }
+
+    Ok(options)
+}
+
+// =============================================================================
+// Session-Level Message Parsers
+// =============================================================================
+//   test_request EXCHANGE
+//   resend 1 100 120
+//   resend FIX.4.4:CLIENT->EXCHANGE 100 0
+// The session is selected as for `watch session`; EndSeqNo 0 asks for
+// everything from BeginSeqNo on
+// =============================================================================
+
+fn parse_test_request(source: &str) -> Result<ShellCommand, BadCommand> {
+    let args: Vec<_> = source.split_whitespace().skip(1).collect();
+    match args.as_slice() {
+        [session] => Ok(ShellCommand::TestRequest(session.to_string())),
+        _ => Err(BadCommand::InvalidArgumentCount {
+            current: args.len(),
+            expected: 1,
+        }),
+    }
+}
+
+fn parse_resend(source: &str) -> Result<ShellCommand, BadCommand> {
+    let args: Vec<_> = source.split_whitespace().skip(1).collect();
+    let [session, begin, end] = args.as_slice() else {
+        return Err(BadCommand::InvalidArgumentCount {
+            current: args.len(),
+            expected: 3,
+        });
+    };
+
+    let begin: u64 = begin
+        .parse()
+        .ok()
+        .filter(|x| *x > 0)
+        .ok_or(BadCommand::InvalidArgument("BeginSeqNo must be a positive number"))?;
+    let end: u64 = end
+        .parse()
+        .ok()
+        .ok_or(BadCommand::InvalidArgument("EndSeqNo must be a number (0 for no end)"))?;
+    if end != 0 && end < begin {
+        return Err(BadCommand::InvalidArgument("EndSeqNo is before BeginSeqNo"));
+    }
+
+    Ok(ShellCommand::Resend {
+        session: session.to_string(),
+        begin,
+        end,
+    })
+}
+
+// =============================================================================
+// Conformance Parser
+// =============================================================================
+//   conformance scenarios/venue.txt EXCHANGE
+//   conformance scenarios/venue.txt 1 --report cert.json
+// The session is selected as for `watch session`; a report path ending in
+// .json gets the JSON report, any other the text one
+// =============================================================================
+
+fn parse_conformance(source: &str) -> Result<ShellCommand, BadCommand> {
+    let args: Vec<_> = source.split_whitespace().skip(1).collect();
+    let (path, session, report) = match args.as_slice() {
+        [path, session] => (path, session, None),
+        [path, session, "--report", report] => (path, session, Some(PathBuf::from(report))),
+        [_, _, _, _] => return Err(BadCommand::InvalidArgument("expected --report PATH")),
+        _ => {
+            return Err(BadCommand::InvalidArgumentCount {
+                current: args.len(),
+                expected: 2,
+            })
+        }
+    };
+
+    Ok(ShellCommand::Conformance {
+        path: PathBuf::from(path),
+        session: session.to_string(),
+        report,
+    })
+}
+
+// =============================================================================
+// Certify Parser
+// =============================================================================
+//   certify EXCHANGE
+//   certify 1 --symbol AAPL --qty 1 --price 0.50 --report broker.json
+// The round-trip order is 100 ZVZZT at 1.00 unless the options say otherwise
+// =============================================================================
+
+fn parse_certify(source: &str) -> Result<ShellCommand, BadCommand> {
+    let mut tokens = source.split_whitespace().skip(1);
+    let session = tokens.next().ok_or(BadCommand::InvalidArgument(
+        "expected: certify SESSION [--symbol S] [--qty Q] [--price P] [--report PATH]",
+    ))?;
+
+    let mut settings = CertificationSettings::default();
+    let mut report = None;
+    while let Some(option) = tokens.next() {
+        let value = tokens
+            .next()
+            .ok_or(BadCommand::InvalidArgument("option without a value"))?;
+        let number = || {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|x| *x > 0.0)
+                .ok_or(BadCommand::InvalidArgument("--qty and --price must be positive numbers"))
+        };
+        settings = match option {
+            "--symbol" => settings.with_symbol(value),
+            "--qty" => settings.with_quantity(number()?),
+            "--price" => settings.with_price(number()?),
+            "--report" => {
+                report = Some(PathBuf::from(value));
+                settings
+            }
+            _ => return Err(BadCommand::InvalidArgument("unknown certify option")),
+        };
+    }
+
+    Ok(ShellCommand::Certify {
+        session: session.to_string(),
+        settings,
+        report,
+    })
+}
+
+// =============================================================================
+// Fault Parser
+// =============================================================================
+//   fault                  what is on, and what was injected
+//   fault skip-seq 20      skip a sequence number every 20th message
+//   fault jitter 0         switch jitter off
+//   fault off              switch every fault off
+// =============================================================================
+
+fn parse_fault(source: &str) -> Result<ShellCommand, BadCommand> {
+    let args: Vec<_> = source.split_whitespace().skip(1).collect();
+    match args.as_slice() {
+        [] => Ok(ShellCommand::Faults { setting: None, clear: false }),
+        ["off"] => Ok(ShellCommand::Faults { setting: None, clear: true }),
+        [fault, value] => {
+            let fault: Fault = fault.parse().map_err(|_| {
+                BadCommand::InvalidArgument(
+                    "expected drop, corrupt, jitter, skip-seq or heartbeat",
+                )
+            })?;
+            let value: u64 = value
+                .parse()
+                .map_err(|_| BadCommand::InvalidArgument("value must be a number (0 for off)"))?;
+            Ok(ShellCommand::Faults {
+                setting: Some((fault, value)),
+                clear: false,
+            })
+        }
+        [_] => Err(BadCommand::InvalidArgument("expected off, or a fault and its value")),
+        _ => Err(BadCommand::InvalidArgumentCount {
+            current: args.len(),
+            expected: 2,
+        }),
+    }
+}
+
+// =============================================================================
+// Rejects Parser
+// =============================================================================
+//   rejects                the last DEFAULT_REJECTS rejects
+//   rejects 100            the last 100
+// =============================================================================
+
+/// Rejects shown when no count is given
+const DEFAULT_REJECTS: usize = 20;
+
+fn parse_rejects(source: &str) -> Result<ShellCommand, BadCommand> {
+    let args: Vec<_> = source.split_whitespace().skip(1).collect();
+    match args.as_slice() {
+        [] => Ok(ShellCommand::Rejects { limit: DEFAULT_REJECTS }),
+        [limit] => {
+            let limit = limit
+                .parse()
+                .map_err(|_| BadCommand::InvalidArgument("count must be a number"))?;
+            Ok(ShellCommand::Rejects { limit })
+        }
+        _ => Err(BadCommand::InvalidArgumentCount {
+            current: args.len(),
+            expected: 1,
+        }),
+    }
+}
+
+// =============================================================================
+// Queue Parser
+// =============================================================================
+//   queue                  what waits for a logon, per session
+//   queue clear            drop all of it
+//   queue clear 1          drop what waits for session 1
+// =============================================================================
+
+fn parse_queue(source: &str) -> Result<ShellCommand, BadCommand> {
+    let args: Vec<_> = source.split_whitespace().skip(1).collect();
+    match args.as_slice() {
+        [] => Ok(ShellCommand::Queue { clear: false, session: None }),
+        ["clear"] => Ok(ShellCommand::Queue { clear: true, session: None }),
+        ["clear", session] => Ok(ShellCommand::Queue {
+            clear: true,
+            session: Some(session.to_string()),
+        }),
+        _ => Err(BadCommand::InvalidArgument("expected: queue [clear [N]]")),
+    }
+}
+
+// =============================================================================
+// Disconnects Parser
+// =============================================================================
+//   disconnects                    sessions down, reports, orders for review
+//   disconnects resolve            take every order off the review list
+//   disconnects resolve BUY-12     take one
+// =============================================================================
+
+fn parse_disconnects(source: &str) -> Result<ShellCommand, BadCommand> {
+    let args: Vec<_> = source.split_whitespace().skip(1).collect();
+    match args.as_slice() {
+        [] => Ok(ShellCommand::Disconnects { resolve: false, cl_ord_id: None }),
+        ["resolve"] => Ok(ShellCommand::Disconnects { resolve: true, cl_ord_id: None }),
+        ["resolve", cl_ord_id] => Ok(ShellCommand::Disconnects {
+            resolve: true,
+            cl_ord_id: Some(cl_ord_id.to_string()),
+        }),
+        _ => Err(BadCommand::InvalidArgument("expected: disconnects [resolve [ClOrdID]]")),
+    }
+}
+
+// =============================================================================
+// Failover Parser
+// =============================================================================
+//   failover                       gateway of each session, switches made
+//   failover all                   switch every session to its other gateway
+//   failover 1                     switch one session
+// =============================================================================
+
+fn parse_failover(source: &str) -> Result<ShellCommand, BadCommand> {
+    let args: Vec<_> = source.split_whitespace().skip(1).collect();
+    match args.as_slice() {
+        [] => Ok(ShellCommand::Failover { switch: false, session: None }),
+        ["all"] => Ok(ShellCommand::Failover { switch: true, session: None }),
+        [session] => Ok(ShellCommand::Failover {
+            switch: true,
+            session: Some(session.to_string()),
+        }),
+        _ => Err(BadCommand::InvalidArgument("expected: failover [all | N]")),
+    }
+}
+
+// =============================================================================
+// End-of-Day Parser
+// =============================================================================
+//   eod                            plans, next run of each task
+//   eod run export_fills           run one task for every session having it
+//   eod run summary 1              for one session
+// =============================================================================
+
+fn parse_eod(source: &str) -> Result<ShellCommand, BadCommand> {
+    let args: Vec<_> = source.split_whitespace().skip(1).collect();
+    let run = |action: &str, session: Option<&str>| -> Result<ShellCommand, BadCommand> {
+        let action = action.parse().map_err(|_| {
+            BadCommand::InvalidArgument(
+                "expected flatten, export_fills, archive_store, reset_order_ids or summary",
+            )
+        })?;
+        Ok(ShellCommand::Eod { run: Some((action, session.map(str::to_string))) })
+    };
+    match args.as_slice() {
+        [] => Ok(ShellCommand::Eod { run: None }),
+        ["run", action] => run(action, None),
+        ["run", action, session] => run(action, Some(session)),
+        _ => Err(BadCommand::InvalidArgument("expected: eod [run TASK [N]]")),
+    }
+}
+
+// =============================================================================
+// Audit Parser
+// =============================================================================
+// A count and options, in any order, each at most once:
+//   audit                           the last DEFAULT_AUDIT_ENTRIES entries
+//   audit 100 --since 2h --failed   failures of the last two hours
+//   audit --source rest --actor alice --grep /orders
+// =============================================================================
+
+fn parse_audit(source: &str) -> Result<AuditQuery, BadCommand> {
+    let mut query = AuditQuery::new().with_limit(DEFAULT_AUDIT_ENTRIES);
+    let mut tokens = source.split_whitespace().skip(1);
+
+    while let Some(token) = tokens.next() {
+        if token == "--failed" {
+            query = query.failed_only();
+            continue;
+        }
+        if let Ok(limit) = token.parse() {
+            query = query.with_limit(limit);
+            continue;
+        }
+
+        let value = tokens
+            .next()
+            .ok_or(BadCommand::InvalidArgument("option without a value"))?;
+        query = match token {
+            "--since" => {
+                let age = parse_age(value)
+                    .ok_or(BadCommand::InvalidArgument("age must look like 30s, 5m or 2h"))?;
+                query.with_since(unix_now() - age.as_secs() as i64)
+            }
+            "--source" => query.with_source(AuditSource::from_name(value).ok_or(
+                BadCommand::InvalidArgument(
+                    "source must be repl, remote, pipe, console, rest or admin",
+                ),
+            )?),
+            "--actor" => query.with_actor(value),
+            "--grep" => query.with_contains(value),
+            _ => return Err(BadCommand::InvalidArgument("unknown audit option")),
+        };
+    }
+
+    Ok(query)
+}
+
+// =============================================================================
+// Mute Parser
+// =============================================================================
+//   mute                              the rules
+//   mute admin [--session N] [--drop]
+//   mute 35=0 | mute MsgType=Heartbeat [--session N] [--drop]
+//   unmute all | unmute admin | unmute 35=0 [--session N]
+// =============================================================================
+
+fn parse_mute(source: &str) -> Result<ShellCommand, BadCommand> {
+    let mut tokens = source.split_whitespace();
+    let unmute = tokens.next() == Some("unmute");
+    let mut target = None;
+    let mut session = None;
+    let mut drop = false;
+    let mut all = false;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "--session" => {
+                let selector = tokens
+                    .next()
+                    .ok_or(BadCommand::InvalidArgument("--session without a value"))?;
+                session = Some(selector.to_string());
+            }
+            "--drop" if !unmute => drop = true,
+            "all" if unmute && target.is_none() => all = true,
+            "admin" if target.is_none() => target = Some(MuteTarget::Admin),
+            field if target.is_none() => {
+                let (tag, value) = field
+                    .split_once('=')
+                    .filter(|(tag, _)| !tag.is_empty())
+                    .ok_or(BadCommand::InvalidArgument("expected admin or TAG=VALUE"))?;
+                target = Some(MuteTarget::Field {
+                    tag: tag.to_string(),
+                    value: value.to_string(),
+                });
+            }
+            _ => return Err(BadCommand::InvalidArgument("one of admin or TAG=VALUE only")),
+        }
+    }
+
+    if !unmute {
+        if target.is_none() && (session.is_some() || drop) {
+            return Err(BadCommand::InvalidArgument("expected: mute admin | TAG=VALUE"));
+        }
+        return Ok(ShellCommand::Mute { target, session, drop });
+    }
+    match (all, target.is_some(), session.is_some()) {
+        (true, false, false) => Ok(ShellCommand::Unmute { target: None, session: None }),
+        (false, true, _) => Ok(ShellCommand::Unmute { target, session }),
+        _ => Err(BadCommand::InvalidArgument(
+            "expected: unmute all | admin | TAG=VALUE [--session N]",
+        )),
+    }
+}
+
 // =============================================================================
 // Send Message Parser
 // =============================================================================
 // Parses the "send_to" command which has a complex syntax:
-//   send_to TAG=VALUE|TAG=VALUE|... sender_id target_id
+//   send_to TAG=VALUE|TAG=VALUE|... sender_id target_id [--version V]
 //
 // Example:
 //   send_to 35=D|55=AAPL|54=1|38=100|40=2|44=150.50 CLIENT EXCHANGE
@@ -167,9 +1639,18 @@ impl FromStr for ShellCommand {
 // - 38=100 (Quantity)
 // - 40=2 (Order Type: Limit)
 // - 44=150.50 (Price)
+//
+// Field names and value names of the tag dictionary work as well, mixed
+// with tag numbers as you like; the shell resolves them when it runs the
+// command (see trading::session::dictionary):
+//   send_to MsgType=D|Symbol=AAPL|Side=Buy|OrderQty=100|OrdType=Limit|44=150.50 CLIENT EXCHANGE
+//
+// --version picks the FIX version, for counterparties reached over several
+// sessions or over FIXT.1.1:
+//   send_to 35=D|55=AAPL|54=1|38=100|40=1 CLIENT EXCHANGE --version FIX.5.0SP2
 // =============================================================================
 
-fn parse_send_to(source: &str) -> Result<(Message, SessionId), BadCommand> {
+fn parse_send_to(source: &str) -> Result<(Vec<(String, String)>, SendTarget), BadCommand> {
     // =========================================================================
     // Step 1: Tokenize the command
     // =========================================================================
@@ -179,7 +1660,7 @@ fn parse_send_to(source: &str) -> Result<(Message, SessionId), BadCommand> {
     let mut tokens = source.split_whitespace();
     
     // Skip the "send_to" part (we already validated this)
-    debug_assert_eq!(tokens.next().as_deref(), Some("send_to"));
+    debug_assert_eq!(tokens.next(), Some("send_to"));
 
     // Extract the three required arguments
     let text_msg = tokens.next().ok_or(BadCommand::InvalidArgumentCount {
@@ -198,58 +1679,96 @@ fn parse_send_to(source: &str) -> Result<(Message, SessionId), BadCommand> {
     })?;
 
     // =========================================================================
-    // Step 2: Parse the FIX message from TAG=VALUE format
+    // Step 2: Split the FIX message into TAG=VALUE fields
     // =========================================================================
     // FIX messages are represented as tag-value pairs
     // Tag 35 is MsgType (defines what kind of message this is)
+    // The tags stay text here: a name needs the tag dictionary to become a
+    // number, and the dictionary lives in the shell
     // =========================================================================
     
-    let mut msg = Message::new();
+    let mut fields = Vec::new();
     
     // Split on pipe character to get individual fields
     for field in text_msg.split('|') {
-        // Split each field into tag and value
-        let mut field_tokens = field.splitn(2, '=');
-
-        // Parse the tag number (integer)
-        let tag = field_tokens
-            .next()
-            .ok_or(BadCommand::InvalidArgument("Invalid tag"))?
-            .parse()
-            .ok()
-            .ok_or(BadCommand::InvalidArgument("Invalid tag number"))?;
-
-        // Get the value (string)
-        let value = field_tokens
-            .next()
+        // Split each field into tag (number or name) and value
+        let (tag, value) = field
+            .split_once('=')
             .ok_or(BadCommand::InvalidArgument("Invalid value"))?;
-
-        // Set the field in the message
-        // Tags are integers defined in the FIX specification
-        msg.set_field(tag, value)
-            .ok()
-            .expect("Fail to set msg tag=value");
+        if tag.is_empty() {
+            return Err(BadCommand::InvalidArgument("Invalid tag"));
+        }
+        fields.push((tag.to_string(), value.to_string()));
     }
 
     // =========================================================================
-    // Step 3: Create SessionId
+    // Step 3: Optional FIX version
     // =========================================================================
     // A FIX session is uniquely identified by:
-    // - BeginString (FIX version, e.g., "FIX.4.4")
+    // - BeginString (FIX version, e.g., "FIX.4.4", or "FIXT.1.1" for 5.0+)
     // - SenderCompID (who is sending)
     // - TargetCompID (who is receiving)
     // - Optional qualifier (for multiple sessions between same parties)
+    // The shell resolves the BeginString when it runs the command, from the
+    // sessions it knows and the version asked for here
     // =========================================================================
     
-    let session_id = SessionId::try_new(
-        "FIX.4.4",    // FIX version - hardcoded for this example
-        text_sender,   // Our identifier
-        text_target,   // Counterparty identifier
-        ""            // Empty qualifier
-    )
-    .expect("Fail to allocate new session ID");
+    let version = match (tokens.next(), tokens.next(), tokens.next()) {
+        (None, ..) => None,
+        (Some("--version"), Some(version), None) => Some(
+            version
+                .parse()
+                .ok()
+                .ok_or(BadCommand::InvalidArgument("version must be FIX.4.0 to FIX.5.0SP2"))?,
+        ),
+        (Some("--version"), None, _) => {
+            return Err(BadCommand::InvalidArgument("--version without a value"))
+        }
+        _ => {
+            return Err(BadCommand::InvalidArgument(
+                "expected: send_to MSG SENDER TARGET [--version V]",
+            ))
+        }
+    };
 
-    Ok((msg, session_id))
+    Ok((
+        fields,
+        SendTarget {
+            sender: text_sender.to_string(),
+            target: text_target.to_string(),
+            version,
+        },
+    ))
+}
+
+// =============================================================================
+// Send Template Parser
+// =============================================================================
+//   send tmpl new_limit_order symbol=AAPL qty=100 px=150
+// Values are matched against the template's placeholders when the command
+// runs, since the templates live in the shell (see templates.rs)
+// =============================================================================
+
+fn parse_send_template(source: &str) -> Result<ShellCommand, BadCommand> {
+    let mut tokens = source.split_whitespace().skip(1);
+    if tokens.next() != Some("tmpl") {
+        return Err(BadCommand::InvalidArgument("expected: send tmpl NAME [KEY=VALUE...]"));
+    }
+    let name = tokens
+        .next()
+        .ok_or(BadCommand::InvalidArgumentCount { current: 0, expected: 1 })?
+        .to_string();
+
+    let values = tokens
+        .map(|x| {
+            x.split_once('=')
+                .filter(|(key, _)| !key.is_empty())
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .ok_or(BadCommand::InvalidArgument("values must look like KEY=VALUE"))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(ShellCommand::SendTemplate { name, values })
 }
 
 // =============================================================================
@@ -306,4 +1825,7 @@ fn parse_send_to(source: &str) -> Result<(Message, SessionId), BadCommand> {
 // Request market data for TSLA:
 //   send_to 35=V|262=MD001|263=1|55=TSLA CLIENT EXCHANGE
 //
+// The same, by name:
+//   send_to MsgType=V|MDReqID=MD001|263=SnapshotPlusUpdates|Symbol=TSLA CLIENT EXCHANGE
+//
 // =============================================================================
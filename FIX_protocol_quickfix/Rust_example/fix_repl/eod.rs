@@ -0,0 +1,73 @@
+// =============================================================================
+// End-of-Day Tasks
+// =============================================================================
+// With --eod, the tasks set per session with EndOfDay* keys
+// (trading::session::eod) run at their time from the session's close; the
+// shell carries them out, as it holds the orders, fills and stores:
+//
+//   flatten          working orders of the session canceled, then the net
+//                    position of each symbol closed by a market order
+//   export_fills     the day's fills, DIR/fills-SESSION-YYYYMMDD.csv
+//   archive_store    the file store compacted up to the close, the messages
+//                    before it gzipped into DIR/store (handler stopped)
+//   reset_order_ids  ClOrdIDs generated from 1 again, duplicates forgotten
+//   summary          the trading report of the day (trading::oms::report),
+//                    DIR/summary-SESSION-YYYYMMDD.EXT in EndOfDayReportFormat,
+//                    and the log as text
+//
+// `eod` lists the plans and the next run of each task; `eod run TASK [N]`
+// runs one now, for the last close. The day of a close is the 24 hours up to
+// it; fills and rejects are in memory only, so a restart during the day
+// leaves out what came before it.
+// =============================================================================
+
+use std::collections::BTreeMap;
+
+use quickfix::{FieldMap, Message, QuickFixError};
+use trading::{
+    oms::{blotter::Trade, Side},
+    time::transact_time,
+};
+
+/// Seconds in the day a close ends
+const DAY: i64 = 86_400;
+
+/// The fills of a session in the day ending at `close`, oldest first
+pub fn day_fills(trades: &[Trade], session: &str, close: i64) -> Vec<Trade> {
+    let (from, to) = ((close - DAY) * 1_000, close * 1_000);
+    trades
+        .iter()
+        .filter(|x| x.session == session && x.time >= from && x.time < to)
+        .cloned()
+        .collect()
+}
+
+/// Net quantity per symbol, bought less sold; flat symbols left out
+pub fn net_positions(trades: &[Trade]) -> BTreeMap<String, f64> {
+    let mut net = BTreeMap::new();
+    for trade in trades {
+        let quantity = match trade.side {
+            Side::Buy => trade.quantity,
+            Side::Sell => -trade.quantity,
+        };
+        *net.entry(trade.symbol.clone()).or_insert(0.0) += quantity;
+    }
+    net.retain(|_, x: &mut f64| x.abs() > f64::EPSILON);
+    net
+}
+
+/// The market order (35=D) bringing a net position back to zero; no
+/// ClOrdID, the duplicate guard assigns one
+pub fn flatten_order(symbol: &str, net: f64) -> Result<Message, QuickFixError> {
+    let side = if net > 0.0 { Side::Sell } else { Side::Buy };
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "D"))?;
+    msg.set_field(21, "1")?; // HandlInst: automated, no intervention
+    msg.set_field(55, symbol)?; // Symbol
+    msg.set_field(54, side.as_fix())?; // Side
+    msg.set_field(38, net.abs().to_string().as_str())?; // OrderQty
+    msg.set_field(40, "1")?; // OrdType: Market
+    msg.set_field(59, "0")?; // TimeInForce: Day
+    msg.set_field(60, transact_time().as_str())?; // TransactTime
+    Ok(msg)
+}
@@ -0,0 +1,179 @@
+// =============================================================================
+// Shell Errors
+// =============================================================================
+// What can go wrong between the line typed and the message on the wire, in
+// the order it is found:
+//
+//   parse        the line is not a command (BadCommand)
+//   template     send tmpl names no template, or leaves a placeholder out
+//   validation   the message is one the tag dictionary, a venue profile or
+//                a template refuses
+//   session      the session named is unknown or ambiguous
+//   duplicate    a ClOrdID used before, or the same order again within the
+//                window (trading::oms::duplicates)
+//   rate limit   over --rate-limit, with the reject policy or waiting too
+//                long (trading::session::rate_limit)
+//   send         QuickFIX refused the message (not logged on, ...)
+//
+// The steps of a command return these; the command reports the error once,
+// as a warning with a hint of what to try, and ends with its result code
+// (history.rs). Nothing the user types stops the shell.
+// =============================================================================
+
+use std::{error::Error, fmt};
+
+use quickfix::QuickFixError;
+use trading::{
+    oms::duplicates::Duplicate,
+    session::rate_limit::{RateLimited, SendError},
+};
+
+use crate::{command_parser::BadCommand, history::ResultCode, templates::TemplateError};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ShellError {
+    /// The line is not a command, or not a valid one
+    Parse(BadCommand),
+
+    /// The template or the values of a send tmpl
+    Template(TemplateError),
+
+    /// The message would be refused: each problem found, for the user
+    Validation(Vec<String>),
+
+    /// No session to send to
+    Session(SessionProblem),
+
+    /// Refused as a duplicate order
+    Duplicate(Duplicate),
+
+    /// Over the outbound rate limits: not sent
+    RateLimited(RateLimited),
+
+    /// QuickFIX refused to build or send the message
+    Send(QuickFixError),
+}
+
+/// Why a session selector or a pair of CompIDs gives no session
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SessionProblem {
+    /// Nothing known by that name
+    Unknown(String),
+
+    /// Several sessions between the CompIDs, one per BeginString
+    Ambiguous(Vec<String>),
+
+    /// Not a valid SessionId
+    Invalid(String, QuickFixError),
+}
+
+impl ShellError {
+    /// The result code the command ends with
+    pub fn code(&self) -> ResultCode {
+        match self {
+            ShellError::Parse(_) | ShellError::Template(_) => ResultCode::BadCommand,
+            ShellError::Validation(_)
+            | ShellError::Session(_)
+            | ShellError::Duplicate(_)
+            | ShellError::RateLimited(_)
+            | ShellError::Send(_) => ResultCode::SendFailed,
+        }
+    }
+
+    /// What to try next
+    pub fn hint(&self) -> &'static str {
+        match self {
+            ShellError::Parse(_) => "type 'help' for the commands and their arguments",
+            ShellError::Template(_) => "'templates' lists them with their placeholders",
+            ShellError::Validation(_) => "'dict TAG' shows what a field accepts",
+            ShellError::Session(SessionProblem::Ambiguous(_)) => "choose one with --version",
+            ShellError::Session(_) => "'health' lists the sessions",
+            ShellError::Duplicate(_) => "omit 11 for a new ClOrdID; --duplicates warn sends it",
+            ShellError::RateLimited(_) => "send slower; --rate-limit-policy queue:MS waits longer",
+            ShellError::Send(_) => "is the session logged on? see 'health'",
+        }
+    }
+}
+
+impl fmt::Display for ShellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShellError::Parse(err) => write!(f, "{err}"),
+            ShellError::Template(err) => write!(f, "{err}"),
+            ShellError::Validation(problems) => {
+                write!(f, "message refused: {}", problems.join("; "))
+            }
+            ShellError::Session(problem) => write!(f, "{problem}"),
+            ShellError::Duplicate(duplicate) => write!(f, "duplicate refused: {duplicate}"),
+            ShellError::RateLimited(err) => write!(f, "not sent: {err}"),
+            ShellError::Send(err) => write!(f, "send failed: {err:?}"),
+        }
+    }
+}
+
+impl fmt::Display for SessionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionProblem::Unknown(selector) => write!(f, "no such session: {selector}"),
+            SessionProblem::Ambiguous(begin_strings) => write!(
+                f,
+                "several sessions between these CompIDs: {}",
+                begin_strings.join(", ")
+            ),
+            SessionProblem::Invalid(label, err) => write!(f, "invalid session {label}: {err:?}"),
+        }
+    }
+}
+
+impl Error for ShellError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ShellError::Parse(err) => Some(err),
+            ShellError::Template(err) => Some(err),
+            ShellError::Duplicate(err) => Some(err),
+            ShellError::RateLimited(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<BadCommand> for ShellError {
+    fn from(err: BadCommand) -> Self {
+        ShellError::Parse(err)
+    }
+}
+
+impl From<TemplateError> for ShellError {
+    fn from(err: TemplateError) -> Self {
+        ShellError::Template(err)
+    }
+}
+
+impl From<QuickFixError> for ShellError {
+    fn from(err: QuickFixError) -> Self {
+        ShellError::Send(err)
+    }
+}
+
+impl From<SendError> for ShellError {
+    fn from(err: SendError) -> Self {
+        match err {
+            SendError::Limited(err) => ShellError::RateLimited(err),
+            SendError::Send(err) => ShellError::Send(err),
+        }
+    }
+}
+
+impl From<Duplicate> for ShellError {
+    fn from(duplicate: Duplicate) -> Self {
+        ShellError::Duplicate(duplicate)
+    }
+}
+
+impl From<SessionProblem> for ShellError {
+    fn from(problem: SessionProblem) -> Self {
+        ShellError::Session(problem)
+    }
+}
@@ -0,0 +1,183 @@
+// =============================================================================
+// CSV Export
+// =============================================================================
+// What the REPL knows of orders, fills and positions, written to a CSV file
+// for a spreadsheet, the quickest reconciliation there is:
+//
+//   FIX> export orders orders.csv         every order tracked (orders.rs)
+//   FIX> export fills fills.csv           every fill of the trade blotter
+//   FIX> export positions positions.csv   per symbol, from the fills
+//   FIX> export fills fills.csv --columns time_ms,symbol,side,quantity,price
+//
+// Every column by default, in the order below; --columns picks some and
+// orders them as given:
+//
+//   orders     sent_ms, age_secs, session, cl_ord_id, symbol, side,
+//              quantity, price, state
+//   fills      time_ms, session, exec_id, cl_ord_id, account, symbol, side,
+//              quantity, price (as blotter --csv)
+//   positions  symbol, bought, sold, net, avg_buy, avg_sell, fills
+//
+// Times are Unix milliseconds; a value the REPL does not have (no price on
+// a market order, no sells for an average) is left empty. Orders and fills
+// are oldest first, positions by symbol.
+// =============================================================================
+
+use std::{
+    fmt::{self, Write as _},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use trading::{csv::csv_field, oms::blotter::Trade};
+
+use crate::{
+    orders::{OrderState, Side, TrackedOrder},
+    watch::PositionSummary,
+};
+
+/// What `export` writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportKind {
+    Orders,
+    Fills,
+    Positions,
+}
+
+impl ExportKind {
+    /// Parse `orders` / `fills` / `positions` as typed in the shell
+    pub fn from_name(value: &str) -> Option<Self> {
+        match value {
+            "orders" => Some(Self::Orders),
+            "fills" => Some(Self::Fills),
+            "positions" => Some(Self::Positions),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Orders => "orders",
+            Self::Fills => "fills",
+            Self::Positions => "positions",
+        }
+    }
+
+    /// Every column, in the default order
+    pub fn columns(self) -> &'static [&'static str] {
+        match self {
+            Self::Orders => &[
+                "sent_ms",
+                "age_secs",
+                "session",
+                "cl_ord_id",
+                "symbol",
+                "side",
+                "quantity",
+                "price",
+                "state",
+            ],
+            Self::Fills => &[
+                "time_ms",
+                "session",
+                "exec_id",
+                "cl_ord_id",
+                "account",
+                "symbol",
+                "side",
+                "quantity",
+                "price",
+            ],
+            Self::Positions => &[
+                "symbol", "bought", "sold", "net", "avg_buy", "avg_sell", "fills",
+            ],
+        }
+    }
+}
+
+impl fmt::Display for ExportKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// =============================================================================
+// Rows
+// =============================================================================
+// A value per column name; the parser only lets through the names of
+// ExportKind::columns
+// =============================================================================
+
+/// The orders, one line each
+pub fn orders_csv(orders: &[TrackedOrder], columns: &[String]) -> String {
+    let now = unix_millis();
+    to_csv(orders, columns, |order, column| match column {
+        "sent_ms" => (now - order.age().as_millis() as i64).to_string(),
+        "age_secs" => order.age().as_secs().to_string(),
+        "session" => csv_field(&order.session),
+        "cl_ord_id" => csv_field(&order.cl_ord_id),
+        "symbol" => csv_field(&order.symbol),
+        "side" => match order.side {
+            Some(Side::Buy) => "buy".to_string(),
+            Some(Side::Sell) => "sell".to_string(),
+            None => String::new(),
+        },
+        "quantity" => csv_field(&order.quantity),
+        "price" => order.price.map_or(String::new(), |x| x.to_string()),
+        "state" => match order.state {
+            OrderState::Working => "working",
+            OrderState::PendingCancel => "pending_cancel",
+            OrderState::Done => "done",
+            OrderState::Rejected => "rejected",
+        }
+        .to_string(),
+        _ => String::new(),
+    })
+}
+
+/// The fills, one line each
+pub fn fills_csv(trades: &[Trade], columns: &[String]) -> String {
+    to_csv(trades, columns, |trade, column| match column {
+        "time_ms" => trade.time.to_string(),
+        "session" => csv_field(&trade.session),
+        "exec_id" => csv_field(&trade.exec_id),
+        "cl_ord_id" => csv_field(&trade.cl_ord_id),
+        "account" => csv_field(&trade.account),
+        "symbol" => csv_field(&trade.symbol),
+        "side" => trade.side.as_str().to_string(),
+        "quantity" => trade.quantity.to_string(),
+        "price" => trade.price.to_string(),
+        _ => String::new(),
+    })
+}
+
+/// The positions, one line per symbol
+pub fn positions_csv(positions: &[PositionSummary], columns: &[String]) -> String {
+    let price = |x: Option<f64>| x.map_or(String::new(), |x| x.to_string());
+    to_csv(positions, columns, |position, column| match column {
+        "symbol" => csv_field(&position.symbol),
+        "bought" => position.bought.to_string(),
+        "sold" => position.sold.to_string(),
+        "net" => position.net.to_string(),
+        "avg_buy" => price(position.avg_buy),
+        "avg_sell" => price(position.avg_sell),
+        "fills" => position.fills.to_string(),
+        _ => String::new(),
+    })
+}
+
+/// Header, then a line per row
+fn to_csv<T>(rows: &[T], columns: &[String], value: impl Fn(&T, &str) -> String) -> String {
+    let mut out = format!("{}\n", columns.join(","));
+    for row in rows {
+        let values: Vec<_> = columns.iter().map(|x| value(row, x)).collect();
+        let _ = writeln!(out, "{}", values.join(","));
+    }
+    out
+}
+
+/// Milliseconds since the Unix epoch, now
+fn unix_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_millis() as i64)
+}
@@ -8,65 +8,307 @@
 // - Monitoring system behavior
 // - Learning how FIX sessions work
 //
-// In production, you would replace the logging with actual business logic.
+// The callbacks run on QuickFIX engine threads and stay thin: they update the
+// metrics, then decode each event into an owned FixEvent and push it into a
+// tokio channel (see trading::session::events). The logging itself happens in an async task,
+// process_events(), which is where business logic would go in production.
 // =============================================================================
 
-use std::{
-    io::{stdout, Write}, // For writing to console
-    sync::atomic::{AtomicU32, Ordering}, // Thread-safe counter
-};
+use std::sync::Arc; // Shared ownership of the metrics registry
 
 use quickfix::*; // Import all QuickFIX types
+use tracing::{debug, info, warn}; // Structured logging facade (see logging.rs)
+
+use trading::{
+    alerts::AlertMonitor, // Paging conditions (--alert)
+    conformance::Tap, // Inbound messages for a running conformance scenario
+    gateway::{metrics::Metrics, websocket::Bridge}, // Prometheus counters, live JSON stream
+    oms::{
+        captures::{CaptureUpdate, RequestStatus, TradeCaptureBook}, // 35=AQ / 35=AE kept
+        disconnect::DisconnectGuard, // Orders of sessions that dropped
+        mass_status::{MassStatusBook, MassStatusUpdate}, // Order status reports (35=AF)
+    },
+    session::{
+        archive::MessageArchive, // Every message, batched into Parquet files
+        dictionary::Dictionary, // Field types and values, venue tags included
+        events::{EventReceiver, EventSender, FixEvent, FixMessage}, // Callback -> task channel
+        faults::{Fault, FaultInjector}, // Outbound faults, switched from the shell
+        rejects::RejectLog,             // 35=3 / 35=j matched with what they reject
+        scorecard::Scorecard,         // Day-by-day statistics of each counterparty
+        rate_limit::SendError,        // Why a cancel was not sent
+        send_queue::SendQueue,        // Messages held while their session is logged off
+        transform::Transformer,       // Venue quirks fixed by rules (--transforms)
+        parse_session_label,
+        session_label,
+        Direction,
+    },
+    symbology::Symbology,
+    time::unix_now,
+};
+
+use crate::{
+    health::{HealthMonitor, HealthThresholds}, // Heartbeat round trips and silences
+    latency::LatencyMonitor,       // SendingTime / TransactTime latencies
+    logging::label_span,           // Per-session spans
+    mute::{MessageFilter, Verdict}, // Mute rules of the message log
+    orders::OrderTracker,          // Working orders, for cancel-all
+    watch::LiveState,              // Positions, books and sessions, for watch
+};
 
 // =============================================================================
 // MyApplication: FIX Callback Handler with Message Tracking
 // =============================================================================
-// This application tracks and displays all FIX session events and messages
+// This application forwards all FIX session events and messages to the
+// event task
 // =============================================================================
 
-#[derive(Default)]
 pub struct MyApplication {
-    // Thread-safe counter for numbering messages
-    // AtomicU32 allows multiple threads to safely increment the counter
-    // This is important if using MultiThreaded socket server
-    message_index: AtomicU32,
+    // Sending half of the event channel; the task ends once it is dropped
+    events: EventSender,
+
+    // Metrics registry, shared with the shell and the HTTP exporter
+    metrics: Arc<Metrics>,
+
+    // WebSocket bridge, fed with every message (idle until a client connects)
+    bridge: Arc<Bridge>,
+
+    // Heartbeat monitor, shared with the shell and the watchdog task
+    health: Arc<HealthMonitor>,
+
+    // Inbound latencies, shared with the shell
+    latency: Arc<LatencyMonitor>,
+
+    // Inbound messages of the session under a conformance run, if any
+    conformance: Arc<Tap>,
+
+    // Faults applied to outbound messages, shared with the shell
+    faults: Arc<FaultInjector>,
+
+    // Counterparty statistics, fed with every event before it is pushed
+    scorecard: Arc<Scorecard>,
+
+    // Parquet archive of every message, with --archive-dir
+    archive: Option<Arc<MessageArchive>>,
+
+    // Transform rules of application messages, with --transforms
+    transforms: Arc<Transformer>,
+
+    // Internal symbols and each venue's, with --symbology
+    symbology: Arc<Symbology>,
 }
 
 impl MyApplication {
     /// Create a new application instance
-    pub fn new() -> Self {
-        Self::default()
+    ///
+    /// # Arguments
+    /// * `events` - Channel consumed by process_events()
+    /// * `thresholds` - When the heartbeat monitor logs warnings
+    pub fn new(events: EventSender, thresholds: HealthThresholds) -> Self {
+        let metrics = Arc::new(Metrics::new());
+        Self {
+            events,
+            health: Arc::new(HealthMonitor::new(Arc::clone(&metrics), thresholds)),
+            metrics,
+            bridge: Arc::default(),
+            latency: Arc::new(LatencyMonitor::new()),
+            conformance: Arc::default(),
+            faults: Arc::default(),
+            scorecard: Arc::default(),
+            archive: None,
+            transforms: Arc::default(),
+            symbology: Arc::default(),
+        }
     }
 
-    /// Increment and return the message counter
-    /// Uses Relaxed ordering since we only need atomicity, not ordering guarantees
-    fn inc_message_index(&self) {
-        self.message_index.fetch_add(1, Ordering::Relaxed);
+    /// Feed this scorecard instead of a fresh one (kept in the state store)
+    pub fn with_scorecard(mut self, scorecard: Arc<Scorecard>) -> Self {
+        self.scorecard = scorecard;
+        self
     }
 
-    /// Helper function to print callback information in a consistent format
-    /// 
-    /// # Arguments
-    /// * `callback_name` - Name of the callback being invoked
-    /// * `session` - Session ID where the event occurred
-    /// * `msg` - Optional FIX message (if applicable)
-    fn print_callback(&self, callback_name: &str, session: &SessionId, msg: Option<&Message>) {
-        let msg_count = self.message_index.load(Ordering::Relaxed);
-
-        // Lock stdout for atomic write (prevents interleaved output in multithreaded scenarios)
-        let mut stdout = stdout().lock();
-        
-        // Print callback information
-        let _ = write!(stdout, "{callback_name}");
-        let _ = write!(stdout, "(id={msg_count}) ");
-        let _ = write!(stdout, "session={session:?}");
-        
-        // If there's a message, print it
-        if let Some(msg) = msg {
-            let _ = write!(stdout, " msg={msg:?}");
+    /// Also add every message to this archive
+    pub fn with_archive(mut self, archive: Arc<MessageArchive>) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Rewrite application messages with these rules, outbound before
+    /// they are recorded and inbound before anything else sees them
+    pub fn with_transforms(mut self, transforms: Arc<Transformer>) -> Self {
+        self.transforms = transforms;
+        self
+    }
+
+    /// Give application messages the venue's symbols on the way out and
+    /// the internal ones on the way in; the transform rules see the
+    /// venue's
+    pub fn with_symbology(mut self, symbology: Arc<Symbology>) -> Self {
+        self.symbology = symbology;
+        self
+    }
+
+    /// Watch these alert conditions with the heartbeats; before the
+    /// monitor is shared
+    pub fn with_alerts(mut self, alerts: Arc<AlertMonitor>) -> Self {
+        let thresholds = self.health.thresholds();
+        let health = HealthMonitor::new(Arc::clone(&self.metrics), thresholds).with_alerts(alerts);
+        self.health = Arc::new(health);
+        self
+    }
+
+    /// Shared handle on the metrics registry fed by the callbacks
+    pub fn metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    /// Shared handle on the WebSocket bridge fed by the callbacks
+    pub fn bridge(&self) -> Arc<Bridge> {
+        Arc::clone(&self.bridge)
+    }
+
+    /// Shared handle on the heartbeat monitor fed by the callbacks
+    pub fn health(&self) -> Arc<HealthMonitor> {
+        Arc::clone(&self.health)
+    }
+
+    /// Shared handle on the inbound latencies measured by the callbacks
+    pub fn latency(&self) -> Arc<LatencyMonitor> {
+        Arc::clone(&self.latency)
+    }
+
+    /// Shared handle on the tap conformance runs read the session from
+    pub fn conformance(&self) -> Arc<Tap> {
+        Arc::clone(&self.conformance)
+    }
+
+    /// Shared handle on the fault injector the outbound callbacks apply
+    pub fn faults(&self) -> Arc<FaultInjector> {
+        Arc::clone(&self.faults)
+    }
+
+    /// Shared handle on the counterparty scorecard fed by the callbacks
+    pub fn scorecard(&self) -> Arc<Scorecard> {
+        Arc::clone(&self.scorecard)
+    }
+
+    /// Count a message in the metrics registry, keyed by its MsgType (tag 35),
+    /// stream it to WebSocket clients, time heartbeats and measure inbound
+    /// latency (first, so the receive time is as early as possible)
+    fn record_message(&self, session: &SessionId, direction: Direction, msg: &Message) {
+        if direction == Direction::Inbound {
+            self.latency.on_inbound(session, msg);
         }
-        
-        let _ = writeln!(stdout);
+        let msg_type = msg.with_header(|h| h.get_field(35)).unwrap_or_default();
+        self.metrics.on_message(session, direction, &msg_type);
+        self.health.on_message(session, direction, msg);
+        self.bridge.publish_fix(session, direction, msg);
+    }
+
+    /// Score an event, then hand it over to the event task
+    ///
+    /// Never blocks: the channel is unbounded. A send only fails once the
+    /// task is gone (shutdown), and then there is nobody left to tell.
+    fn push(&self, event: FixEvent) {
+        self.scorecard.on_event(&event);
+        let _ = self.events.send(event);
+    }
+
+    /// Apply the fault injector to an outbound message, before it is
+    /// recorded so that what is recorded is what goes out
+    ///
+    /// # Returns
+    /// Whether the message must be dropped
+    fn inject_faults(&self, session: &SessionId, admin: bool, msg: &mut Message) -> bool {
+        if !self.faults.is_armed() {
+            return false;
+        }
+        let label = session_label(session);
+        let injected = if admin {
+            self.faults.on_admin(&label, msg)
+        } else {
+            self.faults.on_app(&label, msg)
+        };
+        for fault in &injected {
+            warn!(session = %label, %fault, "fault injected");
+        }
+        injected.contains(&Fault::Drop)
+    }
+
+    /// Apply the transform rules to an outbound application message
+    ///
+    /// # Returns
+    /// Whether the message must be dropped: a rule could not be applied,
+    /// and a message half transformed must not reach the venue
+    fn transform_outbound(&self, session: &SessionId, msg: &mut Message) -> bool {
+        if self.transforms.is_empty() {
+            return false;
+        }
+        let label = session_label(session);
+        match self.transforms.outbound(msg, session) {
+            Ok(applied) => {
+                if !applied.is_empty() {
+                    debug!(session = %label, rules = ?applied, "message transformed");
+                }
+                false
+            }
+            Err(err) => {
+                warn!(session = %label, ?err, "transform failed, message dropped");
+                true
+            }
+        }
+    }
+
+    /// A transformed copy of an inbound application message, None when no
+    /// rule matches it (or a rule failed: the message is processed as
+    /// received)
+    fn transform_inbound(&self, session: &SessionId, msg: &Message) -> Option<Message> {
+        self.transforms.inbound(msg, session).unwrap_or_else(|err| {
+            let label = session_label(session);
+            warn!(session = %label, ?err, "transform failed, message kept as received");
+            None
+        })
+    }
+
+    /// Give an outbound application message the venue's symbol
+    ///
+    /// # Returns
+    /// Whether the message must be dropped, as for transform_outbound
+    fn map_symbols_outbound(&self, session: &SessionId, msg: &mut Message) -> bool {
+        if self.symbology.is_empty() {
+            return false;
+        }
+        match self.symbology.outbound(msg, session) {
+            Ok(_) => false,
+            Err(err) => {
+                let label = session_label(session);
+                warn!(session = %label, ?err, "symbol mapping failed, message dropped");
+                true
+            }
+        }
+    }
+
+    /// A copy of an inbound application message with the internal symbol,
+    /// None when it needs none (or the mapping failed)
+    fn map_symbols_inbound(&self, session: &SessionId, msg: &Message) -> Option<Message> {
+        if self.symbology.is_empty() {
+            return None;
+        }
+        self.symbology.inbound(msg, session).unwrap_or_else(|err| {
+            let label = session_label(session);
+            warn!(session = %label, ?err, "symbol mapping failed, message kept as received");
+            None
+        })
+    }
+
+    /// Decode a message and hand it over to the event task, to the
+    /// conformance run watching the session and to the archive
+    fn push_message(&self, session: &SessionId, direction: Direction, admin: bool, msg: &Message) {
+        let decoded = FixMessage::decode(session, direction, admin, msg);
+        self.conformance.offer(&decoded);
+        if let Some(archive) = &self.archive {
+            archive.on_message(&decoded);
+        }
+        self.push(FixEvent::Message(decoded));
     }
 }
 
@@ -93,7 +335,8 @@ impl ApplicationCallback for MyApplication {
     // This is called once per session during application startup.
     // =========================================================================
     fn on_create(&self, session: &SessionId) {
-        self.print_callback("on_create", session, None);
+        self.push(FixEvent::created(session));
+        self.health.on_create(session);
         
         // In production, you might do:
         // - Initialize a HashMap for this session's orders
@@ -119,7 +362,9 @@ impl ApplicationCallback for MyApplication {
     // due to disconnections and reconnections).
     // =========================================================================
     fn on_logon(&self, session: &SessionId) {
-        self.print_callback("on_logon", session, None);
+        self.push(FixEvent::logon(session));
+        self.metrics.on_logon(session);
+        self.health.on_logon(session);
         
         // In production, you might do:
         // - Send NewOrderSingle messages
@@ -145,7 +390,9 @@ impl ApplicationCallback for MyApplication {
     // the next on_logon.
     // =========================================================================
     fn on_logout(&self, session: &SessionId) {
-        self.print_callback("on_logout", session, None);
+        self.push(FixEvent::logout(session));
+        self.metrics.on_logout(session);
+        self.health.on_logout(session);
         
         // In production, you might do:
         // - Cancel working orders
@@ -171,8 +418,9 @@ impl ApplicationCallback for MyApplication {
     // Note: The message parameter is mutable, so you can modify it.
     // =========================================================================
     fn on_msg_to_admin(&self, msg: &mut Message, session: &SessionId) {
-        self.inc_message_index();
-        self.print_callback("to_admin", session, Some(msg));
+        self.inject_faults(session, true, msg);
+        self.record_message(session, Direction::Outbound, msg);
+        self.push_message(session, Direction::Outbound, true, msg);
         
         // In production, you might do:
         // if msg.msg_type() == "A" {  // Logon message
@@ -198,8 +446,14 @@ impl ApplicationCallback for MyApplication {
     // Return Err to prevent the message from being sent.
     // =========================================================================
     fn on_msg_to_app(&self, msg: &mut Message, session: &SessionId) -> Result<(), MsgToAppError> {
-        self.inc_message_index();
-        self.print_callback("to_app", session, Some(msg));
+        if self.map_symbols_outbound(session, msg)
+            || self.transform_outbound(session, msg)
+            || self.inject_faults(session, false, msg)
+        {
+            return Err(MsgToAppError::DoNotSend);
+        }
+        self.record_message(session, Direction::Outbound, msg);
+        self.push_message(session, Direction::Outbound, false, msg);
         
         // In production, you might do:
         // if msg.msg_type() == "D" {  // NewOrderSingle
@@ -234,8 +488,8 @@ impl ApplicationCallback for MyApplication {
         msg: &Message,
         session: &SessionId,
     ) -> Result<(), MsgFromAdminError> {
-        self.inc_message_index();
-        self.print_callback("from_admin", session, Some(msg));
+        self.record_message(session, Direction::Inbound, msg);
+        self.push_message(session, Direction::Inbound, true, msg);
         
         // In production, you might do:
         // if msg.msg_type() == "A" {  // Logon
@@ -278,8 +532,12 @@ impl ApplicationCallback for MyApplication {
     // Return Err to trigger a business reject message.
     // =========================================================================
     fn on_msg_from_app(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
-        self.inc_message_index();
-        self.print_callback("from_app", session, Some(msg));
+        let transformed = self.transform_inbound(session, msg);
+        let msg = transformed.as_ref().unwrap_or(msg);
+        let mapped = self.map_symbols_inbound(session, msg);
+        let msg = mapped.as_ref().unwrap_or(msg);
+        self.record_message(session, Direction::Inbound, msg);
+        self.push_message(session, Direction::Inbound, false, msg);
         
         // In production, you might do:
         //
@@ -313,6 +571,255 @@ impl ApplicationCallback for MyApplication {
     }
 }
 
+// =============================================================================
+// Event Task
+// =============================================================================
+// Consumes the events pushed by the callbacks, in order, on the tokio runtime.
+// Every event is logged inside the session's span with structured fields, so
+// it can be filtered by level (RUST_LOG) and grepped by field. Admin messages
+// (heartbeats, logons, ...) are logged at DEBUG to keep the default INFO
+// output focused on business traffic.
+//
+// Every event also updates the state the shell reads: the order tracker for
+// cancel-all, the live state (positions, books, sessions) for watch, the
+// reject log for rejects, the trade capture reports for trades, the order
+// status reports for mass_status. A Reject (35=3) or BusinessMessageReject
+// (35=j) is logged as a warning with the message it rejects, whose order it
+// moves on. Once a mass status request is answered in full, its reports are
+// set against the tracker's open orders of the session and every
+// discrepancy is logged as a warning.
+// Inbound fields that the tag dictionary rejects (a bad format, a venue
+// value not listed) are logged as warnings too; the message is still
+// processed.
+//
+// Messages the shell muted (mute.rs) are left out of the log, and only
+// there: they are counted instead, and the counts logged per session at
+// most once a minute, when the next event comes in.
+//
+// A Logon flushes what the shell queued for the session while it was logged
+// off (trading::session::send_queue), a Logout starts queueing again.
+//
+// With --cancel-on-disconnect, a Logout hands the orders open on the
+// session to the guard, and the next Logon cancels them, right after the
+// queue is flushed, or leaves them for review (trading::oms::disconnect).
+//
+// Returns once every sender is dropped and the backlog is drained, which is
+// what lets main() shut down without losing the last messages.
+// =============================================================================
+
+/// What process_events keeps up to date, shared with the shell
+pub struct EventState {
+    pub orders: Arc<OrderTracker>,
+    pub live: Arc<LiveState>,
+    pub rejects: Arc<RejectLog>,
+    pub captures: Arc<TradeCaptureBook>,
+    pub mass_status: Arc<MassStatusBook>,
+    pub dictionary: Arc<Dictionary>,
+    pub mutes: Arc<MessageFilter>,
+    pub queue: Arc<SendQueue>,
+    pub disconnects: Option<Arc<DisconnectGuard>>,
+}
+
+pub async fn process_events(mut events: EventReceiver, state: EventState) {
+    let EventState {
+        orders,
+        live,
+        rejects,
+        captures,
+        mass_status,
+        dictionary,
+        mutes,
+        queue,
+        disconnects,
+    } = state;
+
+    // Numbering messages needs no atomics: this task is the only consumer
+    let mut message_index: u32 = 0;
+
+    while let Some(event) = events.recv().await {
+        for (session, counts) in mutes.take_summary() {
+            let _span = label_span(&session).entered();
+            info!(muted = %counts, "muted messages");
+        }
+
+        let (callback, session) = match &event {
+            FixEvent::Created { session } => ("on_create", session),
+            FixEvent::Logon { session } => ("on_logon", session),
+            FixEvent::Logout { session } => ("on_logout", session),
+            FixEvent::Message(msg) => {
+                message_index += 1;
+                let callback = match (msg.direction, msg.admin) {
+                    (Direction::Outbound, true) => "to_admin",
+                    (Direction::Outbound, false) => "to_app",
+                    (Direction::Inbound, true) => "from_admin",
+                    (Direction::Inbound, false) => "from_app",
+                };
+                (callback, &msg.session)
+            }
+            // Not fed by the REPL; logged should another source share the bus
+            FixEvent::News(news) => {
+                info!(source = %news.source, symbols = ?news.symbols, headline = %news.headline);
+                continue;
+            }
+        };
+
+        let _span = label_span(session).entered();
+        live.on_event(&event);
+        follow_logon(&queue, &event);
+        if let Some(guard) = &disconnects {
+            follow_disconnect(guard, &orders, &queue, &event);
+        }
+
+        let FixEvent::Message(msg) = &event else {
+            info!(callback, id = message_index);
+            continue;
+        };
+
+        if mutes.check(msg) == Verdict::Log {
+            let (msg_type, seq_num, text) = (msg.msg_type(), msg.seq_num(), msg.to_text());
+            if msg.admin {
+                debug!(callback, id = message_index, %msg_type, %seq_num, msg = %text);
+            } else {
+                info!(callback, id = message_index, %msg_type, %seq_num, msg = %text);
+            }
+        }
+
+        if msg.direction == Direction::Inbound {
+            for (tag, value) in &msg.fields {
+                if let Err(err) = dictionary.validate(*tag, value) {
+                    let field = dictionary.name(*tag).unwrap_or_default();
+                    warn!(id = message_index, tag, field, %value, %err, "unexpected value");
+                }
+            }
+        }
+
+        // Business logic: keep track of working orders, and of the market
+        // they arrived in for the daily report
+        orders.on_message(msg);
+        if msg.direction == Direction::Outbound && msg.msg_type() == "D" {
+            let mid = msg.get(55).and_then(|symbol| live.mid(symbol));
+            if let (Some(cl_ord_id), Some(mid)) = (msg.get(11), mid) {
+                orders.set_arrival(&msg.session, cl_ord_id, mid);
+            }
+        }
+        if let Some(reject) = rejects.on_message(msg) {
+            warn!(
+                id = message_index,
+                ref_seq_num = ?reject.ref_seq_num,
+                ref_msg_type = ?reject.rejected_msg_type(),
+                cl_ord_id = ?reject.cl_ord_id(),
+                reason = ?reject.reason_name(),
+                text = ?reject.text,
+                "rejected"
+            );
+            orders.on_reject(&reject);
+        }
+
+        // The venue's record of our trades, kept for reconciliation
+        match captures.on_message(msg) {
+            Some(CaptureUpdate::Ack(request)) if request.status == RequestStatus::Rejected => {
+                warn!(id = message_index, %request, "trade capture request rejected");
+            }
+            Some(CaptureUpdate::Ack(request)) => {
+                info!(id = message_index, %request, "trade capture request");
+            }
+            Some(CaptureUpdate::Report(trade)) if trade.report.last_requested => {
+                let request_id = &trade.report.request_id;
+                info!(id = message_index, %request_id, "trade capture reports received");
+            }
+            _ => {}
+        }
+
+        // The venue's open orders, set against ours once all are in
+        if let Some(MassStatusUpdate::Completed(run)) = mass_status.on_message(msg) {
+            let local: Vec<_> = orders
+                .open_orders()
+                .iter()
+                .filter(|x| x.session == run.session)
+                .map(|x| x.to_local())
+                .collect();
+            let discrepancies = run.reconcile(&local);
+            for discrepancy in &discrepancies {
+                let request_id = &run.request.request_id;
+                warn!(id = message_index, %request_id, %discrepancy, "mass status discrepancy");
+            }
+            let (open, discrepancies) = (local.len(), discrepancies.len());
+            info!(id = message_index, %run, open, discrepancies, "mass status reconciled");
+        }
+    }
+}
+
+/// Flush the send queue of a session logging on; queue for one logging off
+fn follow_logon(queue: &SendQueue, event: &FixEvent) {
+    let (FixEvent::Logon { session } | FixEvent::Logout { session }) = event else {
+        return;
+    };
+    let Some(Ok(session_id)) = parse_session_label(session) else {
+        return;
+    };
+    if let FixEvent::Logout { .. } = event {
+        queue.on_logout(&session_id);
+        return;
+    }
+
+    let flush = queue.on_logon(&session_id);
+    if flush.sent > 0 || flush.expired > 0 {
+        info!(sent = flush.sent, expired = flush.expired, "send queue flushed");
+    }
+    if let Some(err) = flush.failed {
+        warn!(%err, "send queue flush stopped, the rest stays queued");
+    }
+}
+
+/// Hand the orders open on a session going down to the guard; cancel them,
+/// or leave them for review, once it is back
+fn follow_disconnect(
+    guard: &DisconnectGuard,
+    orders: &OrderTracker,
+    queue: &SendQueue,
+    event: &FixEvent,
+) {
+    match event {
+        FixEvent::Logout { session } => {
+            let open: Vec<_> = orders
+                .open_orders()
+                .iter()
+                .filter(|x| x.session == *session)
+                .map(|x| x.to_local())
+                .collect();
+            if !open.is_empty() {
+                let policy = guard.policy().as_str();
+                warn!(open = open.len(), policy, "session down with orders open");
+            }
+            guard.on_logout(session, open, unix_now());
+        }
+        FixEvent::Logon { session } => {
+            let report = guard.on_logon(session, unix_now(), |order| {
+                let Some(tracked) = orders.order(session, &order.cl_ord_id) else {
+                    return Err("order no longer tracked".to_string());
+                };
+                let cancel_id = orders.next_cancel_id();
+                let result = tracked.session_id().map_err(SendError::from).and_then(|id| {
+                    queue.send(tracked.to_cancel_request(&cancel_id)?, &id)
+                });
+                result.map(|_| cancel_id).map_err(|err| err.to_string())
+            });
+            let Some(report) = report else {
+                return;
+            };
+            for action in &report.actions {
+                info!(%action, "cancel on disconnect");
+            }
+            if report.left_for_review() > 0 {
+                warn!(%report, "orders left for review, see 'disconnects'");
+            } else {
+                info!(%report, "cancel on disconnect");
+            }
+        }
+        _ => {}
+    }
+}
+
 // =============================================================================
 // Message Flow Summary
 // =============================================================================
@@ -328,7 +835,7 @@ impl ApplicationCallback for MyApplication {
 // 2. Sequence number is validated
 // 3. Message is parsed and validated against data dictionary
 // 4. on_msg_from_app() or on_msg_from_admin() is called
-// 5. Your application processes the message
+// 5. The callback pushes it to process_events(), which processes it
 // 6. If you return Ok(()), sequence number is incremented
 // 7. If you return Err(()), appropriate reject/logout is sent
 //
@@ -0,0 +1,524 @@
+// =============================================================================
+// Heartbeat and Latency Monitor
+// =============================================================================
+// Watches the session-level traffic of every session to answer "is the
+// counterparty alive, and how far away is it?":
+//
+// - TestRequest (35=1) out -> Heartbeat (35=0) in with the same TestReqID
+//   (112) gives a round-trip time
+// - HeartBtInt (108) is taken from the Logon; a session silent for longer
+//   than that has missed heartbeats
+// - SendingTime (52) of the inbound messages against our clock gives the
+//   counterparty's clock skew (trading::session::skew), which breaks
+//   session-time validation once it grows past MaxLatency
+//
+// The callbacks feed it directly (fix_app.rs) so timestamps are taken on the
+// engine threads, not after the event channel. A watchdog task re-checks the
+// silence every second. Results show up in:
+//
+//   FIX> health                       one line per session
+//   FIX> test_request N               one round trip, measured now
+//   /metrics                          fix_heartbeat_rtt_seconds,
+//                                     fix_missed_heartbeats_total
+//
+// and a warning is logged when a threshold is crossed (--max-rtt-ms,
+// --max-missed-heartbeats, --max-skew-ms), the skew's return under its
+// threshold logged too.
+//
+// With --alert conditions, the same feed goes to an AlertMonitor
+// (trading::alerts): sessions down, rejects received (35=3, 35=j, 35=9 and
+// rejected orders) and round trips are checked against them, the watchdog
+// times the outages, and what fires goes to the notifiers (--alert-webhook,
+// --alert-slack, --alert-mail) and the log.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use quickfix::{FieldMap, Message, SessionId};
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+use trading::{
+    alerts::{Alert, AlertMonitor},
+    gateway::metrics::Metrics,
+    session::{
+        session_label,
+        skew::{SkewAlert, SkewMonitor, DEFAULT_MAX_SKEW},
+        Direction,
+    },
+};
+
+use crate::{logging::label_span, remote::println};
+
+/// How often the watchdog looks for silent sessions
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// =============================================================================
+// Thresholds
+// =============================================================================
+
+/// Alert thresholds; crossing one logs a warning
+#[derive(Debug, Clone, Copy)]
+pub struct HealthThresholds {
+    /// TestRequest -> Heartbeat round trip
+    pub max_rtt: Duration,
+
+    /// Consecutive heartbeat intervals without any inbound message
+    pub max_missed_heartbeats: u32,
+
+    /// Counterparty clock skew, either way
+    pub max_skew: Duration,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            max_rtt: Duration::from_millis(500),
+            max_missed_heartbeats: 1,
+            max_skew: DEFAULT_MAX_SKEW,
+        }
+    }
+}
+
+// =============================================================================
+// Per-Session State
+// =============================================================================
+
+#[derive(Debug, Default)]
+struct SessionHealth {
+    logged_on: bool,
+
+    /// HeartBtInt agreed at logon
+    heartbeat_interval: Option<Duration>,
+
+    last_inbound: Option<Instant>,
+    last_heartbeat_in: Option<Instant>,
+    last_heartbeat_out: Option<Instant>,
+
+    /// TestRequests sent and not answered yet, by TestReqID
+    pending: HashMap<String, Instant>,
+
+    /// Shell commands waiting for the answer to their TestRequest
+    waiters: HashMap<String, oneshot::Sender<Duration>>,
+
+    rtt_last: Option<Duration>,
+    rtt_min: Option<Duration>,
+    rtt_max: Duration,
+    rtt_total: Duration,
+    rtt_count: u32,
+
+    /// Heartbeat intervals elapsed in the current silence
+    missed_now: u32,
+
+    /// Heartbeat intervals missed since start
+    missed_total: u64,
+}
+
+impl SessionHealth {
+    fn record_rtt(&mut self, rtt: Duration) {
+        self.rtt_last = Some(rtt);
+        self.rtt_min = Some(self.rtt_min.map_or(rtt, |min| min.min(rtt)));
+        self.rtt_max = self.rtt_max.max(rtt);
+        self.rtt_total += rtt;
+        self.rtt_count += 1;
+    }
+
+    fn rtt_average(&self) -> Option<Duration> {
+        (self.rtt_count > 0).then(|| self.rtt_total / self.rtt_count)
+    }
+}
+
+/// Read-only copy of one session's health, for the shell
+#[derive(Debug, Clone)]
+pub struct HealthSnapshot {
+    pub session: String,
+    pub logged_on: bool,
+    pub heartbeat_interval: Option<Duration>,
+    pub since_inbound: Option<Duration>,
+    pub since_heartbeat_in: Option<Duration>,
+    pub since_heartbeat_out: Option<Duration>,
+    pub pending_test_requests: usize,
+    pub rtt_last: Option<Duration>,
+    pub rtt_min: Option<Duration>,
+    pub rtt_avg: Option<Duration>,
+    pub rtt_max: Option<Duration>,
+    pub missed_now: u32,
+    pub missed_total: u64,
+
+    /// Clock skew estimate in nanoseconds, positive when the counterparty is
+    /// behind, and whether it is beyond the threshold
+    pub skew: Option<(i64, bool)>,
+}
+
+// =============================================================================
+// HealthMonitor
+// =============================================================================
+
+pub struct HealthMonitor {
+    sessions: Mutex<HashMap<String, SessionHealth>>,
+    thresholds: HealthThresholds,
+
+    /// RTTs and missed heartbeats are exported with the other metrics
+    metrics: Arc<Metrics>,
+
+    /// Counterparty clocks, from the SendingTime of inbound messages
+    skew: SkewMonitor,
+
+    /// Paging conditions, with --alert
+    alerts: Option<Arc<AlertMonitor>>,
+
+    next_test_req_id: AtomicU64,
+
+    /// Start time in seconds, so TestReqIDs do not repeat across runs
+    run_id: u64,
+}
+
+impl HealthMonitor {
+    pub fn new(metrics: Arc<Metrics>, thresholds: HealthThresholds) -> Self {
+        Self {
+            sessions: Mutex::default(),
+            thresholds,
+            metrics,
+            skew: SkewMonitor::new(thresholds.max_skew),
+            alerts: None,
+            next_test_req_id: AtomicU64::new(1),
+            run_id: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |x| x.as_secs()),
+        }
+    }
+
+    /// Feed these alert conditions too
+    pub fn with_alerts(mut self, alerts: Arc<AlertMonitor>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    pub fn thresholds(&self) -> HealthThresholds {
+        self.thresholds
+    }
+
+    /// Allocate a TestReqID (112) for a TestRequest sent by the shell
+    pub fn next_test_req_id(&self) -> String {
+        let id = self.next_test_req_id.fetch_add(1, Ordering::Relaxed);
+        format!("TEST-{}-{id}", self.run_id)
+    }
+
+    /// Be told the round trip of the TestRequest `test_req_id`
+    ///
+    /// Register before sending, so the Heartbeat cannot come back first. The
+    /// sender is dropped, without a value, if the session logs out.
+    pub fn await_heartbeat(
+        &self,
+        session: &SessionId,
+        test_req_id: &str,
+    ) -> oneshot::Receiver<Duration> {
+        let (tx, rx) = oneshot::channel();
+        let mut sessions = self.lock();
+        let health = sessions.entry(session_label(session)).or_default();
+        health.waiters.insert(test_req_id.to_string(), tx);
+        rx
+    }
+
+    /// Stop waiting for `test_req_id`, e.g. when sending it failed
+    pub fn forget_test_request(&self, session: &SessionId, test_req_id: &str) {
+        if let Some(health) = self.lock().get_mut(&session_label(session)) {
+            health.waiters.remove(test_req_id);
+            health.pending.remove(test_req_id);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, SessionHealth>> {
+        self.sessions.lock().expect("health lock poisoned")
+    }
+
+    /// A session down until its first logon, as far as alerts go
+    pub fn on_create(&self, session: &SessionId) {
+        if let Some(alerts) = &self.alerts {
+            alerts.on_create(&session_label(session));
+        }
+    }
+
+    pub fn on_logon(&self, session: &SessionId) {
+        let label = session_label(session);
+        let mut sessions = self.lock();
+        let health = sessions.entry(label.clone()).or_default();
+        health.logged_on = true;
+        health.last_inbound = Some(Instant::now());
+        health.missed_now = 0;
+        drop(sessions);
+        self.skew.reset(session);
+        if let Some(alert) = self.alerts.as_ref().and_then(|x| x.on_logon(&label)) {
+            raised(&alert);
+        }
+    }
+
+    pub fn on_logout(&self, session: &SessionId) {
+        let label = session_label(session);
+        let mut sessions = self.lock();
+        let health = sessions.entry(label.clone()).or_default();
+        health.logged_on = false;
+        // Answers to these will never come
+        health.pending.clear();
+        health.waiters.clear();
+        drop(sessions);
+        if let Some(alerts) = &self.alerts {
+            alerts.on_logout(&label);
+        }
+    }
+
+    /// Look at one message; call from the to_/from_ admin/app callbacks
+    pub fn on_message(&self, session: &SessionId, direction: Direction, msg: &Message) {
+        let now = Instant::now();
+        if direction == Direction::Inbound {
+            if let Some(alert) = self.skew.on_inbound(session, msg) {
+                self.skew_alert(alert);
+            }
+        }
+        let label = session_label(session);
+        let msg_type = msg.with_header(|h| h.get_field(35)).unwrap_or_default();
+        if direction == Direction::Inbound && is_reject(&msg_type, msg) {
+            if let Some(alert) = self.alerts.as_ref().and_then(|x| x.on_reject(&label)) {
+                raised(&alert);
+            }
+        }
+
+        let mut sessions = self.lock();
+        let health = sessions.entry(label.clone()).or_default();
+
+        if msg_type == "A" {
+            if let Some(seconds) = msg.get_field(108).and_then(|x| x.parse().ok()) {
+                health.heartbeat_interval = Some(Duration::from_secs(seconds));
+            }
+        }
+
+        match direction {
+            Direction::Outbound => match msg_type.as_str() {
+                "0" => health.last_heartbeat_out = Some(now),
+                "1" => {
+                    if let Some(test_req_id) = msg.get_field(112) {
+                        health.pending.insert(test_req_id, now);
+                    }
+                }
+                _ => {}
+            },
+            Direction::Inbound => {
+                health.last_inbound = Some(now);
+                health.missed_now = 0;
+                if msg_type != "0" {
+                    return;
+                }
+                health.last_heartbeat_in = Some(now);
+
+                // A Heartbeat with a TestReqID answers one of our TestRequests
+                let Some(test_req_id) = msg.get_field(112) else {
+                    return;
+                };
+                let Some(sent) = health.pending.remove(&test_req_id) else {
+                    return;
+                };
+                let rtt = now - sent;
+                health.record_rtt(rtt);
+                let waiter = health.waiters.remove(&test_req_id);
+                drop(sessions);
+
+                if let Some(waiter) = waiter {
+                    let _ = waiter.send(rtt);
+                }
+                self.metrics.observe_heartbeat_rtt(session, rtt);
+                if rtt > self.thresholds.max_rtt {
+                    let _span = label_span(&label).entered();
+                    warn!(
+                        ?rtt,
+                        threshold = ?self.thresholds.max_rtt,
+                        "heartbeat round trip above threshold"
+                    );
+                }
+                let alert = self.alerts.as_ref().and_then(|x| x.on_heartbeat_rtt(&label, rtt));
+                if let Some(alert) = alert {
+                    raised(&alert);
+                }
+            }
+        }
+    }
+
+    fn skew_alert(&self, alert: SkewAlert) {
+        let threshold = self.thresholds.max_skew;
+        match alert {
+            SkewAlert::Exceeded { session, skew } => {
+                let _span = label_span(&session).entered();
+                warn!(
+                    skew = %signed(skew),
+                    ?threshold,
+                    "counterparty clock skew above threshold"
+                );
+            }
+            SkewAlert::Recovered { session, skew } => {
+                let _span = label_span(&session).entered();
+                info!(
+                    skew = %signed(skew),
+                    ?threshold,
+                    "counterparty clock skew back under threshold"
+                );
+            }
+        }
+    }
+
+    /// Count the heartbeat intervals elapsed without inbound traffic
+    ///
+    /// Called by the watchdog; a warning is logged once per silence, when it
+    /// reaches the threshold.
+    pub fn check(&self) {
+        let now = Instant::now();
+        let mut alerts = Vec::new();
+
+        for (label, health) in self.lock().iter_mut() {
+            let (true, Some(interval), Some(last)) =
+                (health.logged_on, health.heartbeat_interval, health.last_inbound)
+            else {
+                continue;
+            };
+            if interval.is_zero() {
+                continue;
+            }
+
+            let missed = u32::try_from((now - last).as_nanos() / interval.as_nanos())
+                .unwrap_or(u32::MAX);
+            if missed <= health.missed_now {
+                continue;
+            }
+
+            let newly_missed = missed - health.missed_now;
+            health.missed_total += u64::from(newly_missed);
+            let threshold = self.thresholds.max_missed_heartbeats;
+            if health.missed_now < threshold && missed >= threshold {
+                alerts.push((label.clone(), missed, now - last));
+            }
+            health.missed_now = missed;
+            self.metrics.on_missed_heartbeats(label, newly_missed);
+        }
+
+        for (label, missed, silence) in alerts {
+            let _span = label_span(&label).entered();
+            warn!(missed, ?silence, "missed heartbeats");
+        }
+        for alert in self.alerts.as_ref().map(|x| x.check()).unwrap_or_default() {
+            raised(&alert);
+        }
+    }
+
+    /// Health of every session, sorted by label
+    pub fn snapshot(&self) -> Vec<HealthSnapshot> {
+        let now = Instant::now();
+        let since = |x: Option<Instant>| x.map(|x| now - x);
+        let skews = self.skew.snapshot();
+
+        let mut snapshot: Vec<_> = self
+            .lock()
+            .iter()
+            .map(|(label, health)| HealthSnapshot {
+                session: label.clone(),
+                logged_on: health.logged_on,
+                heartbeat_interval: health.heartbeat_interval,
+                since_inbound: since(health.last_inbound),
+                since_heartbeat_in: since(health.last_heartbeat_in),
+                since_heartbeat_out: since(health.last_heartbeat_out),
+                pending_test_requests: health.pending.len(),
+                rtt_last: health.rtt_last,
+                rtt_min: health.rtt_min,
+                rtt_avg: health.rtt_average(),
+                rtt_max: health.rtt_min.map(|_| health.rtt_max),
+                missed_now: health.missed_now,
+                missed_total: health.missed_total,
+                skew: skews
+                    .iter()
+                    .find(|x| &x.session == label)
+                    .map(|x| (x.skew, x.exceeded)),
+            })
+            .collect();
+        snapshot.sort_by(|a, b| a.session.cmp(&b.session));
+        snapshot
+    }
+
+    /// Print one line per session
+    pub fn print(&self) {
+        let snapshot = self.snapshot();
+        if snapshot.is_empty() {
+            println!("no session yet");
+            return;
+        }
+
+        let show = |x: Option<Duration>| x.map_or("-".to_string(), |x| format!("{x:.1?}"));
+        println!(
+            "{:<32} {:>4} {:>4} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>7} {:>7} {:>10}",
+            "session", "up", "hb", "last in", "hb in", "hb out", "rtt", "min", "avg", "max",
+            "pending", "missed", "skew"
+        );
+        for x in snapshot {
+            // A skew beyond the threshold is flagged with a `!`
+            let skew = x.skew.map_or("-".to_string(), |(skew, exceeded)| {
+                format!("{}{}", signed(skew), if exceeded { "!" } else { "" })
+            });
+            println!(
+                "{:<32} {:>4} {:>4} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>7} {:>7} {:>10}",
+                x.session,
+                if x.logged_on { "yes" } else { "no" },
+                x.heartbeat_interval.map_or("-".to_string(), |x| format!("{}s", x.as_secs())),
+                show(x.since_inbound),
+                show(x.since_heartbeat_in),
+                show(x.since_heartbeat_out),
+                show(x.rtt_last),
+                show(x.rtt_min),
+                show(x.rtt_avg),
+                show(x.rtt_max),
+                x.pending_test_requests,
+                format!("{}/{}", x.missed_now, x.missed_total),
+                skew,
+            );
+        }
+    }
+}
+
+/// A Reject, BusinessMessageReject, OrderCancelReject or rejected order
+fn is_reject(msg_type: &str, msg: &Message) -> bool {
+    match msg_type {
+        "3" | "j" | "9" => true,
+        "8" => msg.get_field(150).as_deref() == Some("8"),
+        _ => false,
+    }
+}
+
+/// Log an alert handed to the notifiers
+fn raised(alert: &Alert) {
+    let _span = label_span(&alert.subject).entered();
+    if alert.resolved {
+        info!(alert = %alert.kind, "{}", alert.summary);
+    } else {
+        warn!(alert = %alert.kind, "{}", alert.summary);
+    }
+}
+
+/// `+1.2ms` / `-350.0ms` of nanoseconds
+fn signed(nanos: i64) -> String {
+    let sign = if nanos < 0 { '-' } else { '+' };
+    format!("{sign}{:.1?}", Duration::from_nanos(nanos.unsigned_abs()))
+}
+
+// =============================================================================
+// Watchdog Task
+// =============================================================================
+
+/// Check for silent sessions every second, for the life of the process
+pub async fn watchdog(health: Arc<HealthMonitor>) {
+    let mut ticks = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        ticks.tick().await;
+        health.check();
+    }
+}
@@ -0,0 +1,194 @@
+// =============================================================================
+// Command History and Timing
+// =============================================================================
+// Every command typed in the shell is timed and ends with a result code, so
+// the latency of sends and control operations (start, stop, poll, ...) is
+// visible from the prompt itself:
+//
+//   FIX> send_to 35=D|55=AAPL|54=1|38=100 CLIENT EXCHANGE
+//   INFO command="send_to" code="OK" elapsed=61µs
+//
+// `history` lists the last commands, `history --stats` aggregates them per
+// command (count, failures, min / avg / max time).
+// =============================================================================
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    time::Duration,
+};
+
+use crate::remote::println;
+
+/// Commands kept for `history`; older ones are dropped (stats are not)
+const HISTORY_CAPACITY: usize = 1000;
+
+/// Commands listed by a plain `history`
+const HISTORY_SHOWN: usize = 20;
+
+// =============================================================================
+// Result Codes
+// =============================================================================
+
+/// Outcome of one shell command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultCode {
+    /// Command ran and the engine accepted it
+    Ok,
+
+    /// Input could not be parsed into a command
+    BadCommand,
+
+    /// The connection handler returned an error (start, stop, block, poll)
+    EngineError,
+
+    /// send_to_target refused the message (unknown session, not logged on,
+    /// validation failure)
+    SendFailed,
+
+    /// The user declined the confirmation prompt
+    Aborted,
+
+    /// No answer came back in time (test_request)
+    Timeout,
+
+    /// The role of the shell does not allow the command (see roles.rs)
+    Denied,
+}
+
+impl ResultCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResultCode::Ok => "OK",
+            ResultCode::BadCommand => "BAD_COMMAND",
+            ResultCode::EngineError => "ENGINE_ERROR",
+            ResultCode::SendFailed => "SEND_FAILED",
+            ResultCode::Aborted => "ABORTED",
+            ResultCode::Timeout => "TIMEOUT",
+            ResultCode::Denied => "DENIED",
+        }
+    }
+
+    pub fn is_ok(self) -> bool {
+        self == ResultCode::Ok
+    }
+}
+
+impl fmt::Display for ResultCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// =============================================================================
+// History
+// =============================================================================
+
+/// One executed command
+#[derive(Debug)]
+struct Entry {
+    input: String,
+    code: ResultCode,
+    elapsed: Duration,
+}
+
+/// Aggregated timings of one command
+#[derive(Debug, Default)]
+struct CommandStats {
+    count: u64,
+    failed: u64,
+    total: Duration,
+    min: Option<Duration>,
+    max: Duration,
+}
+
+impl CommandStats {
+    fn record(&mut self, code: ResultCode, elapsed: Duration) {
+        self.count += 1;
+        if !code.is_ok() {
+            self.failed += 1;
+        }
+        self.total += elapsed;
+        self.min = Some(self.min.map_or(elapsed, |min| min.min(elapsed)));
+        self.max = self.max.max(elapsed);
+    }
+
+    fn average(&self) -> Duration {
+        // count is at least 1 once the entry exists
+        self.total / u32::try_from(self.count).unwrap_or(u32::MAX)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    /// Most recent commands, oldest first
+    entries: VecDeque<Entry>,
+
+    /// Commands recorded so far; the last one has this 1-based index
+    recorded: u64,
+
+    /// Per command name, over the whole shell session
+    stats: BTreeMap<&'static str, CommandStats>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an executed command under its name (`send_to`, `poll`, ...)
+    pub fn record(
+        &mut self,
+        command: &'static str,
+        input: &str,
+        code: ResultCode,
+        elapsed: Duration,
+    ) {
+        self.stats.entry(command).or_default().record(code, elapsed);
+
+        if self.entries.len() == HISTORY_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Entry {
+            input: input.trim().to_string(),
+            code,
+            elapsed,
+        });
+        self.recorded += 1;
+    }
+
+    /// Print the last commands, with their result code and time
+    pub fn print(&self) {
+        let shown = self.entries.len().min(HISTORY_SHOWN);
+        let first_index = self.recorded - shown as u64 + 1;
+        for (index, entry) in
+            (first_index..).zip(self.entries.iter().skip(self.entries.len() - shown))
+        {
+            println!(
+                "{index:>5}  {:<12} {:>12}  {}",
+                entry.code.as_str(),
+                format!("{:?}", entry.elapsed),
+                entry.input
+            );
+        }
+    }
+
+    /// Print per-command counts and timings
+    pub fn print_stats(&self) {
+        println!(
+            "{:<10} {:>7} {:>7} {:>12} {:>12} {:>12}",
+            "command", "count", "failed", "min", "avg", "max"
+        );
+        for (command, stats) in &self.stats {
+            println!(
+                "{:<10} {:>7} {:>7} {:>12} {:>12} {:>12}",
+                command,
+                stats.count,
+                stats.failed,
+                format!("{:?}", stats.min.unwrap_or_default()),
+                format!("{:?}", stats.average()),
+                format!("{:?}", stats.max)
+            );
+        }
+    }
+}
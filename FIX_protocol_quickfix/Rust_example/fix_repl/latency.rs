@@ -0,0 +1,235 @@
+// =============================================================================
+// Inbound Latency (SendingTime / TransactTime)
+// =============================================================================
+// How old is a message when it reaches us? Every inbound message carries the
+// counterparty's SendingTime (52), and executions and orders usually a
+// TransactTime (60). Both are compared to the local clock when the callback
+// sees the message:
+//
+//   latency = receive time - SendingTime     wire + counterparty send path
+//   latency = receive time - TransactTime    + time from the event to the send
+//
+// Results are kept per session and MsgType, as histograms, and shown with:
+//
+//   FIX> latency                      count, min, p50, p99, p99.9, max
+//   FIX> latency --reset              same, then start over
+//
+// Both clocks take part, so the numbers are only as good as their
+// synchronisation: a message stamped ahead of our clock gives a negative
+// latency. Those are counted ("ahead") and show in min, but weigh as zero in
+// the percentiles. SendingTime usually has millisecond precision, which
+// bounds the precision here too.
+// =============================================================================
+
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use quickfix::{FieldMap, Message, SessionId};
+use trading::{session::session_label, time::parse_utc_timestamp};
+
+use crate::remote::println;
+
+// =============================================================================
+// Histogram
+// =============================================================================
+// Log-linear buckets over microseconds: exact below 2^SUB_BITS, then
+// 2^SUB_BITS buckets per power of two, so any value is known within ~6% in
+// constant memory, however many messages come in
+// =============================================================================
+
+const SUB_BITS: u32 = 4;
+const SUB_BUCKETS: usize = 1 << SUB_BITS;
+
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    buckets: Vec<u64>,
+    count: u64,
+
+    /// Samples below zero (stamped ahead of our clock)
+    ahead: u64,
+
+    /// Microseconds; min can be negative
+    min: i64,
+    max: i64,
+}
+
+impl Histogram {
+    fn bucket(micros: u64) -> usize {
+        if micros < SUB_BUCKETS as u64 {
+            return micros as usize;
+        }
+        let exponent = 63 - micros.leading_zeros();
+        let sub = (micros >> (exponent - SUB_BITS)) as usize & (SUB_BUCKETS - 1);
+        (exponent - SUB_BITS + 1) as usize * SUB_BUCKETS + sub
+    }
+
+    /// Largest value falling in a bucket
+    fn upper_bound(bucket: usize) -> u64 {
+        if bucket < SUB_BUCKETS {
+            return bucket as u64;
+        }
+        let exponent = (bucket / SUB_BUCKETS) as u32 + SUB_BITS - 1;
+        let sub = (bucket % SUB_BUCKETS) as u64;
+        ((SUB_BUCKETS as u64 + sub + 1) << (exponent - SUB_BITS)) - 1
+    }
+
+    pub fn record(&mut self, micros: i64) {
+        if self.count == 0 {
+            self.min = micros;
+            self.max = micros;
+        } else {
+            self.min = self.min.min(micros);
+            self.max = self.max.max(micros);
+        }
+        self.count += 1;
+        if micros < 0 {
+            self.ahead += 1;
+        }
+
+        let bucket = Self::bucket(micros.max(0) as u64);
+        if bucket >= self.buckets.len() {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Microseconds below which `quantile` (0.0 ..= 1.0) of the samples fall
+    pub fn percentile(&self, quantile: f64) -> Option<i64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((self.count as f64 * quantile).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let value = i64::try_from(Self::upper_bound(bucket)).unwrap_or(i64::MAX);
+                return Some(value.min(self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+// =============================================================================
+// LatencyMonitor
+// =============================================================================
+
+/// The two measures kept for a session and MsgType
+#[derive(Debug, Clone, Default)]
+pub struct MessageLatency {
+    /// Against SendingTime (52)
+    pub sending: Histogram,
+
+    /// Against TransactTime (60), for messages that have one
+    pub transact: Histogram,
+}
+
+/// Latencies by session label and MsgType
+#[derive(Default)]
+pub struct LatencyMonitor {
+    latencies: Mutex<BTreeMap<(String, String), MessageLatency>>,
+}
+
+impl LatencyMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<(String, String), MessageLatency>> {
+        self.latencies.lock().expect("latency lock poisoned")
+    }
+
+    /// Measure one inbound message; call from the from_admin / from_app
+    /// callbacks, as early as possible
+    pub fn on_inbound(&self, session: &SessionId, msg: &Message) {
+        let received = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_nanos() as i64);
+        let (msg_type, sending_time) =
+            msg.with_header(|h| (h.get_field(35), h.get_field(52)));
+        let Some(msg_type) = msg_type else {
+            return;
+        };
+        let since = |stamp: Option<String>| {
+            parse_utc_timestamp(&stamp?).map(|x| (received - x) / 1_000)
+        };
+        let sending = since(sending_time);
+        let transact = since(msg.get_field(60));
+        if sending.is_none() && transact.is_none() {
+            return;
+        }
+
+        let mut latencies = self.lock();
+        let entry = latencies
+            .entry((session_label(session), msg_type))
+            .or_default();
+        if let Some(micros) = sending {
+            entry.sending.record(micros);
+        }
+        if let Some(micros) = transact {
+            entry.transact.record(micros);
+        }
+    }
+
+    /// Copy of every histogram, by session label and MsgType
+    pub fn snapshot(&self) -> BTreeMap<(String, String), MessageLatency> {
+        self.lock().clone()
+    }
+
+    pub fn reset(&self) {
+        self.lock().clear();
+    }
+
+    /// Print one line per session, MsgType and measure
+    pub fn print(&self) {
+        let snapshot = self.snapshot();
+        if snapshot.is_empty() {
+            println!("no inbound message with a SendingTime yet");
+            return;
+        }
+
+        println!(
+            "{:<32} {:<4} {:<8} {:>8} {:>6} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            "session", "type", "vs", "count", "ahead", "min", "p50", "p99", "p99.9", "max"
+        );
+        for ((session, msg_type), latency) in &snapshot {
+            let measures = [("52 sent", &latency.sending), ("60 trans", &latency.transact)];
+            for (name, histogram) in measures {
+                if histogram.count() == 0 {
+                    continue;
+                }
+                let show = |x: Option<i64>| x.map_or("-".to_string(), format_micros);
+                println!(
+                    "{:<32} {:<4} {:<8} {:>8} {:>6} {:>9} {:>9} {:>9} {:>9} {:>9}",
+                    session,
+                    msg_type,
+                    name,
+                    histogram.count(),
+                    histogram.ahead,
+                    format_micros(histogram.min),
+                    show(histogram.percentile(0.5)),
+                    show(histogram.percentile(0.99)),
+                    show(histogram.percentile(0.999)),
+                    format_micros(histogram.max),
+                );
+            }
+        }
+    }
+}
+
+/// `850µs`, `12.3ms`, `4.07s`
+fn format_micros(micros: i64) -> String {
+    match micros.unsigned_abs() {
+        0..=999 => format!("{micros}µs"),
+        1_000..=999_999 => format!("{:.1}ms", micros as f64 / 1e3),
+        _ => format!("{:.2}s", micros as f64 / 1e6),
+    }
+}
@@ -0,0 +1,143 @@
+// =============================================================================
+// Logging Facade (tracing)
+// =============================================================================
+// All diagnostic output of the REPL goes through the `tracing` facade instead
+// of println!, so it can be filtered and formatted like any Rust service
+// (what the shell displays on demand, tables and reports, stays on stdout;
+// see command_exec.rs):
+//
+//   RUST_LOG=info                         default: lifecycle + app messages
+//   RUST_LOG=debug                        + admin messages (heartbeats, ...)
+//   RUST_LOG=info,quickfix=trace          + raw engine traffic and events
+//   RUST_LOG=warn,fix_repl=debug          per-module levels
+//
+// Two sources feed it:
+// - The event task (fix_app.rs) and the shell (command_exec.rs), with one
+//   `session` span per FIX session and structured fields (msg_type, seq, ...)
+// - The QuickFIX engine itself, through TracingLogger: a LogCallback that can
+//   be handed to LogFactory in place of StdLogger, so engine logs keep flowing
+//   through the usual quickfix API and end up in the same subscriber
+//
+// The engine's log is also the only place a garbled message shows up: with
+// the garbled diagnostics on, TracingLogger hands the raw traffic and events
+// to a GarbledMonitor (trading::session::garbled), and every message the
+// engine discards is logged as a warning with its annotated breakdown.
+// With --journal, the raw traffic is also recorded, byte for byte, into a
+// binary journal (trading::session::journal).
+// =============================================================================
+
+use std::{
+    fs::OpenOptions,
+    io,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use quickfix::{LogCallback, SessionId};
+use tracing::{debug, trace, warn, Span};
+use tracing_subscriber::{fmt::writer::MakeWriterExt, EnvFilter};
+
+use trading::session::{garbled::GarbledMonitor, journal::Journal, session_label};
+
+use crate::remote;
+
+/// Filter used when RUST_LOG is not set
+const DEFAULT_FILTER: &str = "info";
+
+/// Install the global subscriber: human readable lines on stderr, filtered
+/// by RUST_LOG
+///
+/// Logs go to stderr so they can be redirected apart from the shell prompt
+/// and help text on stdout. With `file`, they are appended to it instead
+/// (the blotter owns the whole terminal). While a command of the remote
+/// console runs, they also go to its client (remote.rs).
+pub fn init(file: Option<&Path>) -> io::Result<()> {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            let writer = Mutex::new(file).and(remote::log_writer);
+            builder.with_ansi(false).with_writer(writer).init();
+        }
+        None => builder.with_writer(std::io::stderr.and(remote::log_writer)).init(),
+    }
+    Ok(())
+}
+
+/// Span grouping every event of one FIX session
+pub fn session_span(session: &SessionId) -> Span {
+    label_span(&session_label(session))
+}
+
+/// Same span from a session label, for code that only has decoded events
+pub fn label_span(label: &str) -> Span {
+    tracing::info_span!("session", id = %label)
+}
+
+/// Raw FIX text with SOH replaced by '|' for readable log lines
+pub fn printable(raw: &str) -> String {
+    raw.replace('\x01', "|")
+}
+
+// =============================================================================
+// TracingLogger: QuickFIX LogFactory -> tracing
+// =============================================================================
+
+pub struct TracingLogger {
+    /// Garbled message diagnostics, switched by --diagnose-garbled and the
+    /// shell's garbled command
+    garbled: Arc<GarbledMonitor>,
+
+    /// Wire journal of --journal
+    journal: Option<Arc<Journal>>,
+}
+
+impl TracingLogger {
+    pub fn new(garbled: Arc<GarbledMonitor>) -> Self {
+        Self {
+            garbled,
+            journal: None,
+        }
+    }
+
+    /// Record the raw traffic into a journal as well
+    pub fn with_journal(mut self, journal: Arc<Journal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+}
+
+impl LogCallback for TracingLogger {
+    fn on_incoming(&self, session_id: Option<&SessionId>, msg: &str) {
+        let session = session_id.map(session_label).unwrap_or_default();
+        trace!(target: "quickfix::incoming", %session, msg = %printable(msg));
+        self.garbled.on_incoming(&session, msg);
+        if let Some(journal) = &self.journal {
+            journal.on_incoming(session_id, msg);
+        }
+    }
+
+    fn on_outgoing(&self, session_id: Option<&SessionId>, msg: &str) {
+        let session = session_id.map(session_label).unwrap_or_default();
+        trace!(target: "quickfix::outgoing", %session, msg = %printable(msg));
+        if let Some(journal) = &self.journal {
+            journal.on_outgoing(session_id, msg);
+        }
+    }
+
+    fn on_event(&self, session_id: Option<&SessionId>, msg: &str) {
+        let session = session_id.map(session_label).unwrap_or_default();
+        debug!(target: "quickfix::event", %session, "{msg}");
+        if let Some(report) = self.garbled.on_event(&session, msg) {
+            warn!(
+                target: "quickfix::garbled",
+                %session,
+                reason = %report.reason,
+                "message discarded\n{}",
+                report.diagnosis.breakdown()
+            );
+        }
+    }
+}
@@ -10,132 +10,1139 @@
 // 2. Interactive command-line interface for FIX operations
 // 3. Generic programming with ConnectionHandler trait
 // 4. Real-time message sending and connection management
+// 5. Async runtime: callbacks feed a channel, the shell and the event
+//    processing run as tokio tasks, CTRL-C / SIGTERM shut down cleanly
+// 6. Sessions added at runtime (add_session, session add) by rebuilding the
+//    handler
+// 7. Optional terminal UI (--tui): blotter panes above the command line
+// 8. Clock sync evidence (--ntp): offsets against a time server, reported
+//    periodically into the audit directory
+// 9. Garbled message diagnostics (--diagnose-garbled): what the engine
+//    discards for a bad frame, taken apart in the logs
+// 10. Operator audit log: every command that changes something, appended
+//    to the audit directory and queried with `audit`
+// 11. Venue tag dictionaries (--dictionary FILE): custom fields named and
+//    checked, on the way out and on the way in
+// 12. State store (--state URL): message templates, scorecards and trade
+//    capture reports kept across runs, encrypted with --state-key FILE
+// 13. Message log filter: Heartbeats and other noise muted, or summarized,
+//    with `mute` / `unmute`
+// 14. Venue profiles (--venue-profile FILE): what a venue would reject,
+//    refused before it is sent
+// 15. Order book snapshots: `book` draws a book with our orders marked, and
+//    --book-export DIR writes them out periodically for postmortems
+// 16. Trade blotter: the fills received, filtered by account, symbol, side
+//    and size, paged with `blotter`, followed with `watch blotter`, exported
+//    with `blotter --csv FILE`; orders, fills and positions exported to
+//    CSV with `export`, for reconciliation in a spreadsheet
+// 17. Counterparty scorecards: uptime, rejects, ack latency, resends and
+//    fill quality of each session, day by day, with `scorecard`, kept in
+//    the state store for trends over weeks
+// 18. Trade capture reports: `trades request` asks the venue for its
+//    record of the day (35=AD), `trades` sets the reports against the
+//    blotter
+// 19. Order mass status: `mass_status` asks the venue for the status of
+//    every order it holds (35=AF) and reports the orders only one side
+//    knows about
+// 20. Certification self-check: `certify` runs the standard battery
+//    (heartbeat, test request, resend, reject, order round trip) against a
+//    session and prints a pass/fail summary
+// 21. Session hours (--schedule): the connection handler started and stopped
+//    around the StartTime / EndTime / StartDay / EndDay windows of the
+//    config file, sequence numbers reset at the weekly reset point, and the
+//    next open / close shown by `status`
+// 22. Config variables: ${VAR} values filled from the environment or from
+//    secret files (--secrets-dir), so credentials are not committed
+// 23. Message archive (--archive-dir): every message sent and received,
+//    batched into Parquet files by day and session for pandas / DuckDB
+// 24. Command roles (--role, --roles FILE): read-only, trader or admin,
+//    switched with `login`; what the role does not allow is refused
+// 25. Remote console (--admin-socket PATH, --admin-listen HOST:PORT): the
+//    same commands from clients authenticated with --admin-tokens FILE, for
+//    an acceptor run as a service without a terminal
+// 26. Outbound rate limits (--rate-limit, --rate-limit-global): token
+//    buckets per session and across all of them, the messages over them
+//    queued or refused (--rate-limit-policy)
+// 27. Send queue (--queue-ttl): messages to a session logged off held until
+//    it logs on again, then sent in order; `queue` lists them
+// 28. Duplicate orders (--duplicates, --duplicate-window-ms): ClOrdIDs
+//    generated when send_to leaves them out, reused ones and orders typed
+//    twice refused, or sent with a warning
+// 29. ClOrdID formats (--cl-ord-id): the ClOrdIDs generated for send_to
+//    dated, random UUIDs, or within a venue's length limit
+// 30. Cancel on disconnect (--cancel-on-disconnect cancel|review): the
+//    orders open when a session dropped canceled as soon as it logs on
+//    again, or left for review; `disconnects` reports what was done
+// 31. Gateway failover (--failover): initiator sessions with a backup
+//    gateway in the config (FailoverConnectHost) switch to it after
+//    FailoverAfter connect attempts without a logon, and back; `failover`
+//    forces a switch
+// 32. Multi-threaded sockets (--multi-threaded): a thread per session
+//    instead of one for all, the callbacks sharing their state (metrics,
+//    monitors, scorecards) behind locks and atomics
+// 33. Wire journal (--journal FILE): the raw messages from the engine's log,
+//    byte for byte with nanosecond timestamps, appended to a binary journal
+//    that fixtail --journal reads back; encrypted at rest with the key of
+//    --journal-key FILE, or of TRADING_STORE_KEY
+// 34. Clock skew (--max-skew-ms): the counterparty's clock against ours,
+//    from the SendingTime of its messages, shown by `health` and warned
+//    about before session-time validation starts rejecting
+// 35. End-of-day tasks (--eod): positions flattened, fills exported, the
+//    store archived, ClOrdIDs reset and a summary written, each at a time
+//    from the session's close set with EndOfDay* keys; `eod` lists them
+//    and `report` prints or writes the day's trading report (text, HTML or
+//    JSON): orders, fills, cancel ratio, rejects, notional and slippage
+// 36. Alerts (--alert): a session down too long, a burst of rejects or a
+//    slow heartbeat round trip posted to webhooks (--alert-webhook,
+//    --alert-slack) or mailed (--alert-mail), to page a human
+// 37. Pipe mode (--pipe - | PATH): commands or JSON orders from stdin or a
+//    named pipe, no prompt, a JSON result per command on stdout, so other
+//    processes can drive the order flow
+// 38. Transform rules (--transforms FILE): fields added, stripped or renamed
+//    and values remapped per counterparty, in or out, so that venue quirks
+//    are configuration rather than code
+// 39. Symbology (--symbology FILE): internal symbols sent as each venue's
+//    Symbol / SecurityID, and received back as internal ones, from CSV
 // =============================================================================
 
-use std::{env, process::exit};
+use std::{
+    cell::RefCell,
+    env,
+    path::{Path, PathBuf},
+    process::exit,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
 
 use quickfix::{
     Acceptor,          // FIX server (accepts connections)
     Application,       // Wrapper for callbacks
     ConnectionHandler, // Common trait for Acceptor and Initiator
     FileMessageStoreFactory, // Persistent message storage
-    FixSocketServerKind,     // Threading model
     Initiator,               // FIX client (initiates connections)
     LogFactory,              // Logging factory
     QuickFixError,           // Error type
-    SessionSettings,         // Configuration container
-    StdLogger,               // Standard output logger
+};
+use tracing::{info, warn}; // Structured logging facade
+use trading::{
+    alerts::{AlertCondition, AlertKind, AlertMonitor}, // Conditions worth paging for
+    audit::{AuditLog, DEFAULT_AUDIT_DIR}, // Operator audit log
+    clock::{ClockSyncMonitor, MIFID_ALGO_TOLERANCE}, // Offsets against an SNTP server
+    conformance::profile::ConformanceProfile, // What each venue accepts
+    gateway::{
+        metrics,
+        smtp::{MailNotifier, SmtpSink},
+        webhook::{WebhookConfig, WebhookEvent, WebhookNotifier},
+        websocket,
+    }, // Prometheus exporter, alert notifiers, WebSocket bridge
+    oms::{
+        captures::TradeCaptureBook, // Trade capture reports, for trades
+        disconnect::{DisconnectGuard, DisconnectPolicy}, // Orders of sessions that dropped
+        duplicates::{self, DuplicateGuard, DuplicatePolicy}, // ClOrdIDs and repeats of send_to
+        ids::IdFormat, // Shape of the ClOrdIDs generated
+        mass_status::MassStatusBook, // Order status reports, for mass_status
+    },
+    session::{
+        archive::MessageArchive, // Parquet files of the traffic
+        dictionary::Dictionary, // Field names and types, venue tags included
+        eod::{read_eod_plans, EodScheduler}, // Tasks around each session's close
+        events,
+        failover::Failover, // Backup gateways of the initiator sessions
+        garbled::GarbledMonitor,
+        journal::Journal, // Raw traffic, byte for byte (--journal)
+        rate_limit::{Rate, RateLimiter, RateLimits},
+        rejects::RejectLog,
+        schedule::{read_schedules, SessionScheduler},
+        scorecard::Scorecard,
+        send_queue::{self, SendQueue},
+        settings::{FileSecrets, SettingsLoader},
+        socket_server_kind, // Threading model, checked against the callbacks
+        transform::{self, Transformer}, // Venue quirks fixed by rules (--transforms)
+    }, // Events, diagnostics, counterparty statistics, session hours, config, sending
+    store::{
+        self,
+        encrypted::{EncryptedStore, EncryptionKey},
+    }, // State kept across runs, encrypted or not
+    symbology::Symbology, // Venue symbols and identifiers (--symbology)
+    time::{time_of_day, unix_now},
 };
 
 // Import our custom modules
 use crate::{
-    command_exec::FixShell,  // Interactive shell implementation
-    fix_app::MyApplication,  // FIX callback handlers
+    archive::archive_task, // Writes the archive batches that are due
+    blotter::Blotter, // Live panes for --tui
+    book_export::{BookExport, DEFAULT_INTERVAL}, // Book snapshots for --book-export
+    clock_sync::ClockSync,   // Clock sync evidence for --ntp
+    command_exec::{FixShell, ShellExit, ShellState}, // Interactive shell implementation
+    fix_app::{process_events, EventState, MyApplication}, // FIX callbacks and event task
+    health::HealthThresholds, // Heartbeat alert thresholds
+    logging::TracingLogger,  // Routes QuickFIX engine logs into tracing
+    mute::MessageFilter,     // Mute rules of the message log
+    orders::OrderTracker,    // Working orders seen on the wire
+    pipe::pipe_lines,        // Lines from another process (--pipe)
+    remote::{AdminTokens, RemoteConsole}, // Commands from other terminals
+    roles::{Role, RoleGrants}, // What each login may do
+    templates::TemplateLibrary, // Named messages for send tmpl
+    watch::{LiveState, DEFAULT_DEPTH}, // State behind the watch views
 };
 
 // Module declarations - these files must exist in the same directory
+// (events, metrics, runtime, provisioning and the throughput benchmark come
+// from the trading library)
+mod archive;         // Parquet message archive (--archive-dir)
+mod blotter;         // Terminal UI panes (--tui)
+mod book_export;     // Order book snapshots (book, --book-export)
+mod captures;        // Trade capture reconciliation (trades)
+mod clock_sync;      // Periodic clock sync reports (--ntp)
 mod command_exec;    // Shell execution logic
 mod command_parser;  // Command parsing logic
+mod eod;             // End-of-day tasks carried out (--eod, eod)
+mod error;           // Shell errors, reported with a hint
+mod export;          // CSV export of orders, fills and positions (export)
 mod fix_app;         // FIX application callbacks
+mod health;          // Heartbeat and latency monitor
+mod history;         // Command timings and result codes
+mod latency;         // SendingTime / TransactTime latency histograms
+mod logging;         // tracing subscriber setup and QuickFIX log bridge
+mod mute;            // Message log filter (mute / unmute)
+mod onboarding;      // session add wizard
+mod orders;          // Order tracker for bulk cancels
+mod pipe;            // Non-interactive mode (--pipe)
+mod remote;          // Remote console (--admin-socket, --admin-listen)
+mod roles;           // Command roles (--role, --roles, login)
+mod scorecard;       // Counterparty scorecards (scorecard)
+mod templates;       // Message templates with placeholders
+mod watch;           // Live state for watch expressions
+
+/// Where logs go with --tui
+const TUI_LOG_FILE: &str = "fix_repl.log";
+
+/// Body of --alert-slack posts, for Slack-style incoming webhooks
+const SLACK_TEMPLATE: &str = r#"{"text":"{{text}}"}"#;
+
+/// Relay of --alert-mail without --smtp
+const DEFAULT_SMTP_RELAY: &str = "127.0.0.1:25";
 
 // =============================================================================
 // Main Entry Point
 // =============================================================================
 // Parses arguments and launches either an acceptor or initiator with an
 // interactive shell for sending commands
+//
+// A current-thread runtime is enough: QuickFIX runs its own threads, and the
+// connection handler (not Send) stays on the main task with the shell.
 // =============================================================================
 
-fn main() -> Result<(), QuickFixError> {
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), QuickFixError> {
     // =========================================================================
     // Step 1: Parse Command-Line Arguments
     // =========================================================================
     // Required args: [acceptor|initiator] <config_file>
+    // Optional args: --metrics-port <port> --ws-port <port>
+    //                --max-rtt-ms <ms> --max-missed-heartbeats <n> --max-skew-ms <ms>
+    //                --templates <file|dir> --tui
+    //                --ntp <host[:port]> --clock-tolerance-us <us>
+    //                --audit-dir <dir> --diagnose-garbled
+    //                --dictionary <file> (repeatable) --state <url> --state-key <file>
+    //                --venue-profile <file> (repeatable)
+    //                --book-export <dir> --book-interval <s> --book-depth <n>
+    //                --schedule --secrets-dir <dir>
+    //                --archive-dir <dir> --archive-rows <n> --archive-interval <s>
+    //                --role <read-only|trader|admin> --roles <file>
+    //                --admin-socket <path> --admin-listen <host:port>
+    //                --admin-tokens <file>
+    //                --rate-limit <rate[/burst]> --rate-limit-global <rate[/burst]>
+    //                --rate-limit-session <label=rate[/burst]> (repeatable)
+    //                --rate-limit-policy <queue[:ms]|reject> --queue-ttl <s>
+    //                --duplicates <reject|warn> --duplicate-window-ms <ms>
+    //                --cl-ord-id <sequence|dated|uuid|venue:len>
+    //                --cancel-on-disconnect <cancel|review> --failover
+    //                --multi-threaded --journal <file> --eod
+    //                --alert <condition> (repeatable) --alert-webhook <url>
+    //                --alert-slack <url> --alert-mail <address> --smtp <host:port>
+    //                --pipe <-|path>
     // =========================================================================
     
     let args: Vec<_> = env::args().collect();
+
+    // Log filtering is controlled with RUST_LOG (see logging.rs); the
+    // blotter needs the terminal for itself, so its logs go to a file
+    let tui = args.iter().any(|x| x == "--tui");
+    let log_file = tui.then(|| Path::new(TUI_LOG_FILE));
+    if let Err(err) = logging::init(log_file) {
+        eprintln!("Cannot open {TUI_LOG_FILE}: {err}");
+        exit(1);
+    }
     
     // Use pattern matching to destructure and validate arguments
     let (Some(connect_mode), Some(config_file)) = (args.get(1), args.get(2)) else {
         eprintln!(
-            "Bad program usage: {} [acceptor|initiator] <config_file>",
+            "Bad program usage: {} [acceptor|initiator] <config_file> [--metrics-port <port>] [--ws-port <port>] [--max-rtt-ms <ms>] [--max-missed-heartbeats <n>] [--max-skew-ms <ms>] [--templates <file|dir>] [--tui] [--ntp <host[:port]>] [--clock-tolerance-us <us>] [--audit-dir <dir>] [--diagnose-garbled] [--dictionary <file>]... [--state <url>] [--state-key <file>] [--venue-profile <file>]... [--book-export <dir>] [--book-interval <s>] [--book-depth <n>] [--schedule] [--secrets-dir <dir>] [--archive-dir <dir>] [--archive-rows <n>] [--archive-interval <s>] [--role <read-only|trader|admin>] [--roles <file>] [--admin-socket <path>] [--admin-listen <host:port>] [--admin-tokens <file>] [--rate-limit <rate[/burst]>] [--rate-limit-global <rate[/burst]>] [--rate-limit-session <label=rate[/burst]>]... [--rate-limit-policy <queue[:ms]|reject>] [--queue-ttl <s>] [--duplicates <reject|warn>] [--duplicate-window-ms <ms>] [--cl-ord-id <sequence|dated|uuid|venue:len>] [--cancel-on-disconnect <cancel|review>] [--failover] [--multi-threaded] [--journal <file>] [--journal-key <file>] [--eod] [--alert <condition>]... [--alert-webhook <url>]... [--alert-slack <url>]... [--alert-mail <address>]... [--smtp <host:port>] [--pipe <-|path>] [--transforms <file>] [--symbology <file>]",
             args[0]
         );
         exit(1);
     };
 
+    // Prometheus exporter and WebSocket bridge are opt-in: only listen when
+    // a port is given
+    let port_flag = |flag: &str| {
+        args.iter()
+            .position(|x| x == flag)
+            .map(|index| args.get(index + 1).and_then(|x| x.parse::<u16>().ok()))
+    };
+    let metrics_port = port_flag("--metrics-port");
+    let ws_port = port_flag("--ws-port");
+
+    // Heartbeat alert thresholds; a missing or invalid value keeps the default
+    let number_flag = |flag: &str| {
+        args.iter()
+            .position(|x| x == flag)
+            .and_then(|index| args.get(index + 1))
+            .and_then(|x| x.parse::<u32>().ok())
+    };
+    let mut thresholds = HealthThresholds::default();
+    if let Some(millis) = number_flag("--max-rtt-ms") {
+        thresholds.max_rtt = Duration::from_millis(u64::from(millis));
+    }
+    if let Some(count) = number_flag("--max-missed-heartbeats") {
+        thresholds.max_missed_heartbeats = count;
+    }
+    if let Some(millis) = number_flag("--max-skew-ms") {
+        thresholds.max_skew = Duration::from_millis(u64::from(millis));
+    }
+
+    // State store (memory, file:DIR, sqlite:PATH, redis://...): where the
+    // templates loaded are kept for the next run; encrypted with the key of
+    // --state-key FILE, or of TRADING_STORE_KEY
+    let state = match args.iter().position(|x| x == "--state") {
+        Some(index) => {
+            let Some(url) = args.get(index + 1) else {
+                eprintln!("--state requires a store URL");
+                exit(1);
+            };
+            let key_file = args
+                .iter()
+                .position(|x| x == "--state-key")
+                .and_then(|index| args.get(index + 1))
+                .map(PathBuf::from);
+            let state = EncryptionKey::load(key_file.as_deref())
+                .and_then(|key| Ok(EncryptedStore::wrap(store::open(url)?, key.as_ref())));
+            match state {
+                Ok(state) => Some(state),
+                Err(err) => {
+                    eprintln!("Cannot open the state store: {err}");
+                    exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    // Message templates for `send tmpl`; a broken file stops the start-up
+    // rather than failing at the first send
+    let templates = match args.iter().position(|x| x == "--templates") {
+        Some(index) => {
+            let Some(path) = args.get(index + 1) else {
+                eprintln!("--templates requires a file or directory");
+                exit(1);
+            };
+            match TemplateLibrary::load(Path::new(path)) {
+                Ok(library) => {
+                    info!(count = library.len(), path = %path, "templates loaded");
+                    if let Some(state) = &state {
+                        if let Err(err) = library.save(state.as_ref()) {
+                            warn!(store = %state.url(), "cannot keep the templates: {err}");
+                        }
+                    }
+                    library
+                }
+                Err(err) => {
+                    eprintln!("Cannot load templates: {err}");
+                    exit(1);
+                }
+            }
+        }
+        None => match &state {
+            Some(state) => match TemplateLibrary::load_store(state.as_ref()) {
+                Ok(library) => {
+                    info!(count = library.len(), store = %state.url(), "templates loaded");
+                    library
+                }
+                Err(err) => {
+                    eprintln!("Cannot load templates: {err}");
+                    exit(1);
+                }
+            },
+            None => TemplateLibrary::new(),
+        },
+    };
+
+    // Venue tag dictionaries, merged over the standard fields in the order
+    // given; a broken file stops the start-up as well
+    let dictionary_files: Vec<_> = args
+        .windows(2)
+        .filter(|x| x[0] == "--dictionary")
+        .map(|x| PathBuf::from(&x[1]))
+        .collect();
+    let dictionary = match Dictionary::with_files(&dictionary_files) {
+        Ok(dictionary) => {
+            if !dictionary_files.is_empty() {
+                let custom = dictionary.custom_fields().count();
+                info!(custom, files = ?dictionary_files, "tag dictionaries loaded");
+            }
+            Arc::new(dictionary)
+        }
+        Err(err) => {
+            eprintln!("Cannot load the tag dictionary: {err}");
+            exit(1);
+        }
+    };
+
+    // Venue profiles, their values and tags named through the dictionary
+    let profiles: Vec<_> = args
+        .windows(2)
+        .filter(|x| x[0] == "--venue-profile")
+        .map(|x| match ConformanceProfile::load(Path::new(&x[1]), &dictionary) {
+            Ok(profile) => {
+                let target = profile.target.as_deref().unwrap_or("every session");
+                info!(profile = profile.name.as_str(), target, "venue profile loaded");
+                profile
+            }
+            Err(err) => {
+                eprintln!("Cannot load the venue profile: {err}");
+                exit(1);
+            }
+        })
+        .collect();
+
+    // Clock sync evidence is opt-in: only sampled against a given server
+    let value_flag = |flag: &str| {
+        args.iter()
+            .position(|x| x == flag)
+            .and_then(|index| args.get(index + 1))
+    };
+    let audit_dir =
+        PathBuf::from(value_flag("--audit-dir").map_or(DEFAULT_AUDIT_DIR, String::as_str));
+    let clock = value_flag("--ntp").map(|server| {
+        let tolerance = number_flag("--clock-tolerance-us")
+            .map_or(MIFID_ALGO_TOLERANCE, |x| Duration::from_micros(u64::from(x)));
+        Arc::new(ClockSync {
+            monitor: Arc::new(ClockSyncMonitor::new(server, tolerance)),
+            audit_dir: audit_dir.clone(),
+        })
+    });
+
+    // Book snapshots are opt-in as well: only written to a given directory
+    let book_export = value_flag("--book-export").map(|dir| BookExport {
+        dir: PathBuf::from(dir),
+        interval: number_flag("--book-interval")
+            .filter(|x| *x > 0)
+            .map_or(DEFAULT_INTERVAL, |x| Duration::from_secs(u64::from(x))),
+        depth: number_flag("--book-depth")
+            .filter(|x| *x > 0)
+            .map_or(DEFAULT_DEPTH, |x| x as usize),
+    });
+
+    // So is the message archive: only kept in a given directory
+    let archive = value_flag("--archive-dir").map(|dir| {
+        let mut archive = MessageArchive::new(Path::new(dir));
+        if let Some(rows) = number_flag("--archive-rows").filter(|x| *x > 0) {
+            archive = archive.with_batch_rows(rows as usize);
+        }
+        if let Some(seconds) = number_flag("--archive-interval").filter(|x| *x > 0) {
+            archive = archive.with_max_age(Duration::from_secs(u64::from(seconds)));
+        }
+        Arc::new(archive)
+    });
+
+    // Outbound rate limits, per session (all of them, or one by label) and
+    // across the sessions; without them, messages are sent as they come
+    let rate_flag = |flag: &str| {
+        value_flag(flag).map(|x| match x.parse::<Rate>() {
+            Ok(rate) => rate,
+            Err(err) => {
+                eprintln!("Invalid {flag}: {err}");
+                exit(1);
+            }
+        })
+    };
+    let mut rate_limits = RateLimits::new();
+    if let Some(rate) = rate_flag("--rate-limit") {
+        rate_limits = rate_limits.with_session(rate);
+    }
+    if let Some(rate) = rate_flag("--rate-limit-global") {
+        rate_limits = rate_limits.with_global(rate);
+    }
+    for pair in args.windows(2).filter(|x| x[0] == "--rate-limit-session") {
+        let rate = pair[1]
+            .split_once('=')
+            .ok_or_else(|| format!("not LABEL=RATE: {}", pair[1]))
+            .and_then(|(label, rate)| rate.parse::<Rate>().map(|rate| (label, rate)));
+        match rate {
+            Ok((label, rate)) => rate_limits = rate_limits.with_session_rate(label, rate),
+            Err(err) => {
+                eprintln!("Invalid --rate-limit-session: {err}");
+                exit(1);
+            }
+        }
+    }
+    if let Some(policy) = value_flag("--rate-limit-policy") {
+        match policy.parse() {
+            Ok(policy) => rate_limits = rate_limits.with_policy(policy),
+            Err(err) => {
+                eprintln!("Invalid --rate-limit-policy: {err}");
+                exit(1);
+            }
+        }
+    }
+    if rate_limits.is_limited() {
+        let limits = &rate_limits;
+        info!(session = ?limits.session, sessions = ?limits.sessions, global = ?limits.global,
+            policy = ?limits.policy, "outbound rate limits");
+    }
+
+    // Messages to a session logged off wait for its next logon, up to
+    // --queue-ttl seconds (0: not queued, the send fails at once); the
+    // event task flushes them, the shell queues them
+    let queue_ttl = number_flag("--queue-ttl")
+        .map_or(send_queue::DEFAULT_TTL, |x| Duration::from_secs(u64::from(x)));
+    let send_queue = Arc::new(SendQueue::new(RateLimiter::new(rate_limits)).with_ttl(queue_ttl));
+
+    // Orders of send_to and send tmpl: a ClOrdID reused, or the same order
+    // within the window (0: not compared), is refused unless --duplicates warn
+    let policy = match value_flag("--duplicates").map(|x| x.parse::<DuplicatePolicy>()) {
+        Some(Ok(policy)) => policy,
+        Some(Err(err)) => {
+            eprintln!("Invalid --duplicates: {err}");
+            exit(1);
+        }
+        None => DuplicatePolicy::Reject,
+    };
+    let window = number_flag("--duplicate-window-ms")
+        .map_or(duplicates::DEFAULT_WINDOW, |x| Duration::from_millis(u64::from(x)));
+    let id_prefix = format!("REPL{}", unix_now());
+    let mut duplicate_guard = DuplicateGuard::new(&id_prefix)
+        .with_policy(policy)
+        .with_window(window);
+
+    // The ClOrdIDs generated: REPL<start time>-N unless --cl-ord-id says
+    // otherwise
+    if let Some(value) = value_flag("--cl-ord-id") {
+        match value.parse::<IdFormat>().and_then(|x| x.generator(&id_prefix)) {
+            Ok(ids) => duplicate_guard = duplicate_guard.with_ids(ids),
+            Err(err) => {
+                eprintln!("Invalid --cl-ord-id: {err}");
+                exit(1);
+            }
+        }
+    }
+
+    // The orders open on a session that drops are canceled at its next
+    // logon, or left for review; without the flag, left working
+    let disconnects = value_flag("--cancel-on-disconnect").map(|value| {
+        match value.parse::<DisconnectPolicy>() {
+            Ok(policy) => {
+                info!(policy = policy.as_str(), "cancel on disconnect");
+                Arc::new(DisconnectGuard::new(policy))
+            }
+            Err(err) => {
+                eprintln!("Invalid --cancel-on-disconnect: {err}");
+                exit(1);
+            }
+        }
+    });
+
+    // Commands that change something are appended to the operator audit
+    // log; a log that cannot be written stops the start-up
+    let audit = match AuditLog::open(&audit_dir) {
+        Ok(log) => Arc::new(log),
+        Err(err) => {
+            eprintln!("Cannot open the audit log in {}: {err}", audit_dir.display());
+            exit(1);
+        }
+    };
+
     // =========================================================================
     // Step 2: Initialize FIX Engine Components
     // =========================================================================
     // These components are shared between acceptor and initiator modes
     // =========================================================================
     
-    println!(">> Creating resources");
+    info!("Creating resources");
     
     // Load configuration from file
     // Supports both acceptor and initiator configurations
-    let settings = SessionSettings::try_from_path(config_file)?;
-    
-    // Use file-based message store for persistence
-    // Critical for maintaining sequence numbers across restarts
-    let store_factory = FileMessageStoreFactory::try_new(&settings)?;
+    // ${VAR} values come from the environment, then from --secrets-dir
+    // (one file per secret), so passwords and hosts stay out of the file
+    let loader = match value_flag("--secrets-dir") {
+        Some(dir) => SettingsLoader::new().with_provider(FileSecrets::new(Path::new(dir))),
+        None => SettingsLoader::new(),
+    };
+    let config = match loader.read(Path::new(config_file)) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Cannot read {config_file}: {err}");
+            exit(1);
+        }
+    };
+    // Shared with the shell, whose add_session command extends it
+    let settings = match config.to_settings() {
+        Ok(settings) => Rc::new(RefCell::new(settings)),
+        Err(err) => {
+            eprintln!("Invalid settings in {config_file}: {err}");
+            exit(1);
+        }
+    };
     
-    // Log to stdout for visibility during testing
-    let log_factory = LogFactory::try_new(&StdLogger::Stdout)?;
+    // Engine logs go through tracing as well (RUST_LOG=quickfix=trace to see
+    // them), watched for garbled messages once the diagnostics are on
+    let garbled = Arc::new(GarbledMonitor::new());
+    garbled.set_enabled(args.iter().any(|x| x == "--diagnose-garbled"));
+    let logger = TracingLogger::new(Arc::clone(&garbled));
+    // and, with --journal, recorded byte for byte into a binary journal,
+    // encrypted when there is a key
+    let logger = match value_flag("--journal") {
+        Some(path) => match open_journal(path, value_flag("--journal-key")) {
+            Ok(journal) => {
+                info!(path = %path, "journaling the wire traffic");
+                logger.with_journal(Arc::new(journal))
+            }
+            Err(err) => {
+                eprintln!("Cannot open the journal {path}: {err}");
+                exit(1);
+            }
+        },
+        None => logger,
+    };
+    let log_factory = LogFactory::try_new(&logger)?;
     
+    // Events decoded by the callbacks are logged by a separate task, which
+    // also keeps the working orders and live views for the shell and skips
+    // what the shell muted
+    let mutes = Arc::new(MessageFilter::new());
+    let orders = Arc::new(OrderTracker::new());
+    let live = Arc::new(LiveState::new());
+    let rejects = Arc::new(RejectLog::new());
+
+    // Trade capture reports, kept in the state store; TradeRequestIDs carry
+    // the start time so that they are not reused by the next run
+    let captures = TradeCaptureBook::new(&format!("TCR{}", unix_now()));
+    let captures = match &state {
+        Some(state) => match captures.with_store(Arc::clone(state)) {
+            Ok(captures) => captures,
+            Err(err) => {
+                eprintln!("Cannot load the trade capture reports: {err}");
+                exit(1);
+            }
+        },
+        None => captures,
+    };
+    let captures = Arc::new(captures);
+    let mass_status = Arc::new(MassStatusBook::new(&format!("MS{}", unix_now())));
+    let (events_sender, events_receiver) = events::channel();
+    let event_state = EventState {
+        orders: Arc::clone(&orders),
+        live: Arc::clone(&live),
+        rejects: Arc::clone(&rejects),
+        captures: Arc::clone(&captures),
+        mass_status: Arc::clone(&mass_status),
+        dictionary: Arc::clone(&dictionary),
+        mutes: Arc::clone(&mutes),
+        queue: Arc::clone(&send_queue),
+        disconnects: disconnects.clone(),
+    };
+    let event_task = tokio::spawn(process_events(events_receiver, event_state));
+
+    // Score each counterparty, carrying on from the days in the state store
+    let scorecard = match &state {
+        Some(state) => match Scorecard::new().with_store(Arc::clone(state)) {
+            Ok(scorecard) => scorecard,
+            Err(err) => {
+                eprintln!("Cannot load the scorecards: {err}");
+                exit(1);
+            }
+        },
+        None => Scorecard::new(),
+    };
+    let scorecard = Arc::new(scorecard);
+    if state.is_some() {
+        tokio::spawn(scorecard::flush_task(Arc::clone(&scorecard)));
+    }
+
     // Create our custom application with full callback logging
-    let callbacks = MyApplication::new();
+    let mut callbacks =
+        MyApplication::new(events_sender, thresholds).with_scorecard(Arc::clone(&scorecard));
+    if let Some(archive) = &archive {
+        info!(dir = %archive.dir().display(), "message archive");
+        callbacks = callbacks.with_archive(Arc::clone(archive));
+        tokio::spawn(archive_task(Arc::clone(archive)));
+    }
+
+    // Transform rules, their fields named through the dictionary: the
+    // application messages rewritten on the way out and in
+    if let Some(path) = value_flag("--transforms") {
+        match transform::load_transforms(Path::new(path), &dictionary) {
+            Ok(rules) => {
+                info!(count = rules.len(), path = %path, "transform rules loaded");
+                callbacks = callbacks.with_transforms(Arc::new(Transformer::new(rules)));
+            }
+            Err(err) => {
+                eprintln!("Cannot load the transform rules {path}: {err}");
+                exit(1);
+            }
+        }
+    }
+
+    // Symbology: internal symbols in, the venue's Symbol / SecurityID out
+    if let Some(path) = value_flag("--symbology") {
+        match Symbology::load(Path::new(path)) {
+            Ok(symbology) => {
+                info!(symbols = symbology.len(), path = %path, "symbology loaded");
+                callbacks = callbacks.with_symbology(Arc::new(symbology));
+            }
+            Err(err) => {
+                eprintln!("Cannot load the symbology {path}: {err}");
+                exit(1);
+            }
+        }
+    }
+
+    // Paging: the --alert conditions are watched with the heartbeats, and
+    // what fires goes to the webhooks and mailboxes given, and to the log
+    let conditions: Vec<AlertCondition> = args
+        .windows(2)
+        .filter(|x| x[0] == "--alert")
+        .map(|x| {
+            x[1].parse().unwrap_or_else(|err| {
+                eprintln!("Invalid --alert: {err}");
+                exit(1);
+            })
+        })
+        .collect();
+    let mut webhooks = Vec::new();
+    let mut mailboxes = Vec::new();
+    for pair in args.windows(2) {
+        match pair[0].as_str() {
+            "--alert-webhook" => webhooks.push(WebhookConfig::new(&pair[1])),
+            "--alert-slack" => webhooks.push(
+                WebhookConfig::new(&pair[1]).with_template(WebhookEvent::Alert, SLACK_TEMPLATE),
+            ),
+            "--alert-mail" => mailboxes.push(pair[1].clone()),
+            _ => {}
+        }
+    }
+    if conditions.is_empty() && !(webhooks.is_empty() && mailboxes.is_empty()) {
+        eprintln!("--alert-webhook, --alert-slack and --alert-mail need --alert <condition>");
+        exit(1);
+    }
+    if !conditions.is_empty() {
+        if conditions.iter().any(|x| x.kind() == AlertKind::RiskBreach) {
+            warn!("--alert risk_breach: fix_repl makes no risk checks, only buy_side raises it");
+        }
+        let list: Vec<_> = conditions.iter().map(ToString::to_string).collect();
+        let webhook_count = webhooks.len();
+        info!(conditions = %list.join(", "), webhooks = webhook_count, mail = ?mailboxes, "alerts");
+        let mut alerts = AlertMonitor::new(conditions);
+        if !webhooks.is_empty() {
+            match WebhookNotifier::start(webhooks, callbacks.metrics()) {
+                Ok(notifier) => alerts = alerts.with_notifier(Arc::new(notifier)),
+                Err(err) => {
+                    eprintln!("Cannot start the alert webhooks: {err}");
+                    exit(1);
+                }
+            }
+        }
+        if !mailboxes.is_empty() {
+            let relay = args
+                .iter()
+                .position(|x| x == "--smtp")
+                .and_then(|index| args.get(index + 1))
+                .map_or(DEFAULT_SMTP_RELAY, String::as_str);
+            let sink = SmtpSink::new(relay, "fix_repl@localhost");
+            alerts = alerts.with_notifier(Arc::new(MailNotifier::new(sink, mailboxes)));
+        }
+        callbacks = callbacks.with_alerts(Arc::new(alerts));
+    }
+
+    // Look for silent sessions in the background
+    tokio::spawn(health::watchdog(callbacks.health()));
+
+    // Sample the clock and file a report every period
+    if let Some(clock) = &clock {
+        let monitor = &clock.monitor;
+        info!(source = monitor.source(), tolerance = ?monitor.tolerance(), "clock sync");
+        tokio::spawn(clock_sync::sync_task(Arc::clone(clock)));
+    }
+
+    // Write the books that changed every interval
+    if let Some(export) = book_export {
+        info!(dir = %export.dir.display(), interval = ?export.interval, "book export");
+        tokio::spawn(book_export::export_task(
+            export,
+            Arc::clone(&live),
+            Arc::clone(&orders),
+        ));
+    }
     
     // Wrap callbacks for the QuickFIX engine
     let app = Application::try_new(&callbacks)?;
 
+    // Expose the metrics collected by the callbacks over HTTP
+    match metrics_port {
+        Some(Some(port)) => {
+            if let Err(err) = metrics::spawn_server(port, callbacks.metrics()) {
+                eprintln!("Cannot start metrics exporter: {err}");
+                exit(1);
+            }
+        }
+        Some(None) => {
+            eprintln!("Invalid --metrics-port value");
+            exit(1);
+        }
+        None => {}
+    }
+
+    // Stream decoded messages to dashboards over WebSocket
+    match ws_port {
+        Some(Some(port)) => {
+            if let Err(err) = websocket::spawn_server(port, callbacks.bridge()) {
+                eprintln!("Cannot start WebSocket bridge: {err}");
+                exit(1);
+            }
+        }
+        Some(None) => {
+            eprintln!("Invalid --ws-port value");
+            exit(1);
+        }
+        None => {}
+    }
+
     // =========================================================================
     // Step 3: Create Connection Handler Based on Mode
     // =========================================================================
     // Branch based on command-line argument to create acceptor or initiator
     // Both implement the ConnectionHandler trait, allowing generic handling
+    //
+    // The shell outlives the handler: when add_session registers a session,
+    // the handler is stopped and rebuilt from the updated settings, and the
+    // same shell (history included) carries on
     // =========================================================================
     
-    match connect_mode.as_str() {
-        // ---------------------------------------------------------------------
-        // Initiator Mode: Connect to a remote FIX acceptor
-        // ---------------------------------------------------------------------
-        // Use case: Trading client connecting to an exchange or broker
-        // The initiator will attempt to connect to the configured host:port
-        // and maintain the connection with automatic reconnection
-        // ---------------------------------------------------------------------
-        "initiator" => server_loop(Initiator::try_new(
-            &settings,      // Contains SocketConnectHost and SocketConnectPort
-            &app,           // Our callback handlers
-            &store_factory, // Message persistence
-            &log_factory,   // Logging
-            FixSocketServerKind::SingleThreaded, // Threading model
-        )?),
-        
-        // ---------------------------------------------------------------------
-        // Acceptor Mode: Listen for incoming FIX connections
-        // ---------------------------------------------------------------------
-        // Use case: Exchange or broker accepting client connections
-        // The acceptor will listen on the configured port for incoming
-        // connections from multiple trading counterparties
-        // ---------------------------------------------------------------------
-        "acceptor" => server_loop(Acceptor::try_new(
-            &settings,      // Contains SocketAcceptPort
-            &app,           // Our callback handlers
-            &store_factory, // Message persistence
-            &log_factory,   // Logging
-            FixSocketServerKind::SingleThreaded, // Threading model
-        )?),
-        
-        // ---------------------------------------------------------------------
-        // Invalid Mode
-        // ---------------------------------------------------------------------
-        _ => {
-            eprintln!("Invalid connection mode");
+    // Command roles: the grants of --roles (every login admin without it),
+    // the shell starting with --role or the grant of the user
+    let grants = match args.iter().position(|x| x == "--roles") {
+        Some(index) => {
+            let Some(path) = args.get(index + 1) else {
+                eprintln!("--roles requires a file");
+                exit(1);
+            };
+            match RoleGrants::load(Path::new(path)) {
+                Ok(grants) => grants,
+                Err(err) => {
+                    eprintln!("Cannot read the roles: {err}");
+                    exit(1);
+                }
+            }
+        }
+        None => RoleGrants::default(),
+    };
+
+    // Remote console: opt-in, and only with the tokens its clients log in
+    // with
+    let flag_value = |flag: &str| {
+        args.iter()
+            .position(|x| x == flag)
+            .map(|index| args.get(index + 1).cloned())
+    };
+    let socket = flag_value("--admin-socket");
+    let listen = flag_value("--admin-listen");
+    let remote = if socket.is_some() || listen.is_some() {
+        let Some(Some(tokens)) = flag_value("--admin-tokens") else {
+            eprintln!("--admin-socket and --admin-listen require --admin-tokens <file>");
             exit(1);
+        };
+        let tokens = match AdminTokens::load(Path::new(&tokens)) {
+            Ok(tokens) => tokens,
+            Err(err) => {
+                eprintln!("Cannot read the admin tokens: {err}");
+                exit(1);
+            }
+        };
+        let mut console = Ok(RemoteConsole::new(tokens, grants.clone()));
+        match socket {
+            #[cfg(unix)]
+            Some(Some(path)) => console = console.and_then(|x| x.listen_unix(Path::new(&path))),
+            #[cfg(not(unix))]
+            Some(Some(_)) => {
+                eprintln!("--admin-socket needs Unix domain sockets, use --admin-listen");
+                exit(1);
+            }
+            Some(None) => {
+                eprintln!("--admin-socket requires a path");
+                exit(1);
+            }
+            None => {}
+        }
+        match listen {
+            Some(Some(address)) => console = console.and_then(|x| x.listen_tcp(&address)),
+            Some(None) => {
+                eprintln!("--admin-listen requires <host:port>");
+                exit(1);
+            }
+            None => {}
+        }
+        match console {
+            Ok(console) => Some(console),
+            Err(err) => {
+                eprintln!("Cannot start the remote console: {err}");
+                exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    let role = match args.iter().position(|x| x == "--role") {
+        Some(index) => match args.get(index + 1).and_then(|x| Role::from_name(x)) {
+            Some(role) => Some(role),
+            None => {
+                eprintln!("--role requires read-only, trader or admin");
+                exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let shell_state = ShellState {
+        metrics: callbacks.metrics(),
+        orders,
+        live,
+        health: callbacks.health(),
+        latency: callbacks.latency(),
+        conformance: callbacks.conformance(),
+        faults: callbacks.faults(),
+        scorecard: callbacks.scorecard(),
+        rejects,
+        captures,
+        mass_status,
+        garbled,
+        audit,
+        dictionary,
+        mutes,
+    };
+    let shell = FixShell::new(
+        shell_state,
+        Rc::clone(&settings),
+        PathBuf::from(config_file),
+        templates,
+    )
+    .with_profiles(profiles)
+    .with_roles(grants, role);
+    let mut shell = match shell {
+        Ok(shell) => shell,
+        Err(role) => {
+            eprintln!("The role {role} is not granted to this login (see --roles)");
+            exit(1);
+        }
+    };
+    let (operator, role) = shell.role();
+    info!(operator, %role, "command role");
+    if let Some(remote) = remote {
+        shell = shell.with_remote(remote);
+    }
+
+    // Pipe mode: another process feeds the shell, stdout carries a JSON
+    // result per command
+    if let Some(path) = flag_value("--pipe") {
+        let Some(path) = path else {
+            eprintln!("--pipe requires - (stdin) or a path");
+            exit(1);
+        };
+        if tui {
+            eprintln!("--pipe and --tui cannot be used together");
+            exit(1);
+        }
+        let lines = if path == pipe::STDIN {
+            None
+        } else {
+            match pipe_lines(Path::new(&path)) {
+                Ok(lines) => Some(lines),
+                Err(err) => {
+                    eprintln!("Cannot read {path}: {err}");
+                    exit(1);
+                }
+            }
+        };
+        info!(pipe = %path, "commands from a pipe, results on stdout");
+        shell = shell.with_pipe(lines);
+    }
+    if tui {
+        let Some(blotter) = Blotter::open() else {
+            eprintln!("The terminal is too small for --tui (20 rows, 80 columns at least)");
+            exit(1);
+        };
+        shell = shell.with_blotter(blotter);
+    }
+    if let Some(clock) = clock {
+        shell = shell.with_clock(clock);
+    }
+    shell = shell
+        .with_send_queue(send_queue)
+        .with_duplicate_guard(duplicate_guard);
+    if let Some(guard) = disconnects {
+        shell = shell.with_disconnect_guard(guard);
+    }
+
+    // Session hours are opt-in: without --schedule the handler runs until
+    // stopped by hand. Sessions added later follow no schedule.
+    if args.iter().any(|x| x == "--schedule") {
+        let sessions = match read_schedules(&config) {
+            Ok(sessions) => sessions,
+            Err(err) => {
+                eprintln!("Cannot read the session schedules: {err}");
+                exit(1);
+            }
+        };
+        let now = unix_now();
+        for session in &sessions {
+            let schedule = session.schedule.status(now);
+            info!(session = %session.session, %schedule, "session hours");
+        }
+        shell = shell.with_scheduler(SessionScheduler::new(sessions));
+    }
+
+    // Failover is opt-in too: without --failover an initiator retries its
+    // primary gateway forever, as QuickFIX does
+    if args.iter().any(|x| x == "--failover") {
+        let failover = match Failover::new(&config) {
+            Ok(failover) if !failover.is_empty() => failover,
+            Ok(_) => {
+                eprintln!("--failover: no session has a FailoverConnectHost");
+                exit(1);
+            }
+            Err(err) => {
+                eprintln!("Cannot read the backup gateways: {err}");
+                exit(1);
+            }
+        };
+        for route in failover.routes(unix_now()) {
+            let (session, endpoint) = (&route.session, &route.endpoint);
+            info!(%session, %endpoint, after = route.after, "failover");
+        }
+        shell = shell.with_failover(failover);
+    }
+
+    // End-of-day tasks run only with --eod, for the sessions whose
+    // settings have EndOfDay* keys
+    if args.iter().any(|x| x == "--eod") {
+        let plans = match read_eod_plans(&config) {
+            Ok(plans) if !plans.is_empty() => plans,
+            Ok(_) => {
+                eprintln!("--eod: no session has an EndOfDay* task");
+                exit(1);
+            }
+            Err(err) => {
+                eprintln!("Cannot read the end-of-day tasks: {err}");
+                exit(1);
+            }
+        };
+        let now = unix_now();
+        for plan in &plans {
+            for task in &plan.tasks {
+                let next = plan.next_run(task, now).map(|(at, _)| time_of_day(at));
+                info!(session = %plan.session, %task, next = next.as_deref(), "end of day");
+            }
         }
-    }?;
+        shell = shell.with_eod(EodScheduler::new(plans));
+    }
+
+    // One thread for every session, or one each
+    let multi_threaded = args.iter().any(|x| x == "--multi-threaded");
+    if multi_threaded {
+        info!("multi-threaded sockets: a thread per session");
+    }
+
+    loop {
+        // Use file-based message store for persistence
+        // Critical for maintaining sequence numbers across restarts
+        // (rebuilt with the handler: it looks up FileStorePath per session)
+        let store_factory = FileMessageStoreFactory::try_new(&settings.borrow())?;
+
+        let exit = match connect_mode.as_str() {
+            // -----------------------------------------------------------------
+            // Initiator Mode: Connect to a remote FIX acceptor
+            // -----------------------------------------------------------------
+            // Use case: Trading client connecting to an exchange or broker
+            // The initiator will attempt to connect to the configured host:port
+            // and maintain the connection with automatic reconnection
+            // -----------------------------------------------------------------
+            "initiator" => {
+                let initiator = Initiator::try_new(
+                    &settings.borrow(), // Contains SocketConnectHost and SocketConnectPort
+                    &app,               // Our callback handlers
+                    &store_factory,     // Message persistence
+                    &log_factory,       // Logging
+                    socket_server_kind(&callbacks, multi_threaded), // Threading model
+                )?;
+                server_loop(initiator, &mut shell).await
+            }
+            
+            // -----------------------------------------------------------------
+            // Acceptor Mode: Listen for incoming FIX connections
+            // -----------------------------------------------------------------
+            // Use case: Exchange or broker accepting client connections
+            // The acceptor will listen on the configured port for incoming
+            // connections from multiple trading counterparties
+            // -----------------------------------------------------------------
+            "acceptor" => {
+                let acceptor = Acceptor::try_new(
+                    &settings.borrow(), // Contains SocketAcceptPort
+                    &app,               // Our callback handlers
+                    &store_factory,     // Message persistence
+                    &log_factory,       // Logging
+                    socket_server_kind(&callbacks, multi_threaded), // Threading model
+                )?;
+                server_loop(acceptor, &mut shell).await
+            }
+            
+            // -----------------------------------------------------------------
+            // Invalid Mode
+            // -----------------------------------------------------------------
+            _ => {
+                eprintln!("Invalid connection mode");
+                exit(1);
+            }
+        }?;
 
-    println!(">> All cleared. Bye !");
+        if exit == ShellExit::Quit {
+            break;
+        }
+        info!("rebuilding connection handler with the new settings");
+    }
+
+    // =========================================================================
+    // Step 4: Drain Pending Events
+    // =========================================================================
+    // Dropping the callbacks closes the channel; the event task then logs
+    // what is left (e.g. the final Logout) and returns
+    // =========================================================================
+
+    drop(app);
+    drop(callbacks);
+    if let Err(err) = event_task.await {
+        warn!("event task failed: {err}");
+    }
+    if state.is_some() {
+        if let Err(err) = scorecard.flush() {
+            warn!("cannot keep the scorecards: {err}");
+        }
+    }
+    if let Some(archive) = &archive {
+        archive::flush(archive);
+    }
+
+    info!("All cleared. Bye !");
     Ok(())
 }
 
@@ -152,7 +1159,10 @@ fn main() -> Result<(), QuickFixError> {
 // - block() / poll() - Message processing control
 // =============================================================================
 
-fn server_loop<C: ConnectionHandler>(mut connection_handler: C) -> Result<(), QuickFixError> {
+async fn server_loop<C: ConnectionHandler>(
+    mut connection_handler: C,
+    shell: &mut FixShell,
+) -> Result<ShellExit, QuickFixError> {
     // =========================================================================
     // Start the Connection Handler
     // =========================================================================
@@ -160,8 +1170,13 @@ fn server_loop<C: ConnectionHandler>(mut connection_handler: C) -> Result<(), Qu
     // For Acceptor: Begins listening on configured port
     // =========================================================================
     
-    println!(">> connection handler START");
-    connection_handler.start()?;
+    // With --schedule, outside the session hours the schedule starts it
+    if shell.in_session_hours() {
+        info!("connection handler START");
+        connection_handler.start()?;
+    } else {
+        info!("outside the session hours, connection handler waits for the open");
+    }
 
     // =========================================================================
     // Launch Interactive Shell
@@ -172,9 +1187,9 @@ fn server_loop<C: ConnectionHandler>(mut connection_handler: C) -> Result<(), Qu
     // - Control the connection (start/stop/block/poll)
     // =========================================================================
     
-    let mut shell = FixShell::new();
-    shell.repl(&mut connection_handler);
-    // The REPL blocks here until the user quits (types 'quit' or presses CTRL-D)
+    let exit = shell.repl(&mut connection_handler).await;
+    // The REPL runs here until the user quits ('quit', CTRL-D or CTRL-C)
+    // or adds a session (the caller then rebuilds the handler)
 
     // =========================================================================
     // Stop the Connection Handler
@@ -186,10 +1201,24 @@ fn server_loop<C: ConnectionHandler>(mut connection_handler: C) -> Result<(), Qu
     // - Saves sequence numbers
     // =========================================================================
     
-    println!(">> connection handler STOP");
-    connection_handler.stop()?;
+    // Unless already stopped, by hand or by the schedule
+    if !connection_handler.is_stopped()? {
+        info!("connection handler STOP");
+        connection_handler.stop()?;
+    }
 
-    Ok(())
+    Ok(exit)
+}
+
+/// The wire journal of --journal, encrypted with the key of `key_file`, or
+/// of TRADING_STORE_KEY, if there is one
+fn open_journal(path: &str, key_file: Option<&String>) -> Result<Journal, String> {
+    let key = EncryptionKey::load(key_file.map(Path::new)).map_err(|err| err.to_string())?;
+    let journal = match key {
+        Some(key) => Journal::open_encrypted(path, &key),
+        None => Journal::open(path),
+    };
+    journal.map_err(|err| err.to_string())
 }
 
 // =============================================================================
@@ -202,6 +1231,194 @@ fn server_loop<C: ConnectionHandler>(mut connection_handler: C) -> Result<(), Qu
 // Run as Initiator (Client):
 //   cargo run --example fix_repl -- initiator initiator.cfg
 //
+// Run with the Prometheus exporter on port 9100:
+//   cargo run --example fix_repl -- initiator initiator.cfg --metrics-port 9100
+//   curl http://localhost:9100/metrics
+//
+// Show admin messages and raw engine traffic as well:
+//   RUST_LOG=debug,quickfix=trace cargo run --example fix_repl -- initiator initiator.cfg
+//
+// Warn when a TestRequest takes more than 200 ms to be answered, or when a
+// session misses 2 heartbeats in a row:
+//   cargo run --example fix_repl -- initiator initiator.cfg --max-rtt-ms 200 --max-missed-heartbeats 2
+//
+// Warn when the counterparty's clock drifts more than 250 ms from ours:
+//   cargo run --example fix_repl -- initiator initiator.cfg --max-skew-ms 250
+//   FIX> health
+//
+// Run as a blotter: open orders, executions, positions and sessions above
+// the command line (logs go to fix_repl.log):
+//   cargo run --example fix_repl -- initiator initiator.cfg --tui
+//
+// Load message templates (a file, or every .toml file of a directory):
+//   cargo run --example fix_repl -- initiator initiator.cfg --templates templates/
+//
+// Stream messages as JSON over WebSocket on port 9200 (only D and 8 here):
+//   cargo run --example fix_repl -- initiator initiator.cfg --ws-port 9200
+//   websocat 'ws://localhost:9200/?types=D,8'
+//
+// Keep clock sync evidence against a time server, HFT tolerance (100us),
+// hourly reports in /var/audit/fix:
+//   cargo run --example fix_repl -- initiator initiator.cfg --ntp time.example.com \
+//       --clock-tolerance-us 100 --audit-dir /var/audit/fix
+//
+// Name and check the venue's own tags (see venue_tags.toml):
+//   cargo run --example fix_repl -- initiator initiator.cfg --dictionary venue_tags.toml
+//
+// Refuse what the venue would reject before sending it (see
+// trading/conformance/profile.rs for the file):
+//   cargo run --example fix_repl -- initiator initiator.cfg --venue-profile ecn.toml
+//
+// Keep 10 levels of every book, every 5 s, in books/books-YYYYMMDD.jsonl
+// (see book_export.rs), then draw one as it was:
+//   cargo run --example fix_repl -- initiator initiator.cfg --book-export books \
+//       --book-interval 5 --book-depth 10
+//   FIX> book AAPL --from books/books-20240115.jsonl --at 20240115-14:03:07
+//
+// Keep the templates in a state store, then start without --templates:
+//   cargo run --example fix_repl -- initiator initiator.cfg --templates templates/ --state file:state
+//   cargo run --example fix_repl -- initiator initiator.cfg --state file:state
+//
+// ... encrypted, the key in the environment rather than a file:
+//   TRADING_STORE_KEY=$(head -c 32 /dev/urandom | xxd -p -c 64) \
+//       cargo run --example fix_repl -- initiator initiator.cfg --state file:state
+//
+// Keep credentials and hosts out of the config file: Password=${file:broker}
+// reads secrets/broker, SocketConnectHost=${FIX_HOST} the environment:
+//   FIX_HOST=fix.broker.example cargo run --example fix_repl -- initiator initiator.cfg \
+//       --secrets-dir secrets
+//
+// Follow the session hours of the config file, e.g. a venue open Sunday
+// 22:00 to Friday 21:00 UTC, with StartDay=Sunday EndDay=Friday
+// StartTime=22:00:00 EndTime=21:00:00: connected only then, sequence numbers
+// reset each Sunday at 22:00, `status` shows the next open and close:
+//   cargo run --example fix_repl -- initiator initiator.cfg --schedule
+//   FIX> status
+//
+// Archive the traffic as Parquet, a file per session every minute at most,
+// then query it with DuckDB (see archive.rs):
+//   cargo run --example fix_repl -- initiator initiator.cfg --archive-dir archive \
+//       --archive-interval 60
+//   duckdb -c "SELECT symbol, sum(last_qty) FROM 'archive/*/*/*.parquet'
+//              WHERE exec_type = 'F' GROUP BY 1"
+//
+// Review the brokers over the last month (the days are kept in the state
+// store), then one of them day by day:
+//   cargo run --example fix_repl -- initiator initiator.cfg --state sqlite:state.db
+//   FIX> scorecard --days 30
+//   FIX> scorecard 1 --days 30 --csv broker.csv
+//
+// Reconcile the day with the venue (sell_side answers 35=AD), keeping the
+// reports in the state store, then export what does not match:
+//   cargo run --example fix_repl -- initiator initiator.cfg --state file:state
+//   FIX> trades request 1
+//   FIX> trades --unmatched --csv breaks.csv
+//
+// End of day in a spreadsheet: the orders with their final state, the
+// fills and the positions, the fills cut to the columns of the broker's
+// statement:
+//   FIX> export orders orders.csv
+//   FIX> export fills fills.csv --columns time_ms,exec_id,symbol,side,quantity,price
+//   FIX> export positions positions.csv
+//
+// After a reconnect, check that the venue and the REPL agree on what is
+// open (sell_side answers 35=AF), then list the requests and discrepancies:
+//   FIX> mass_status 1
+//   FIX> mass_status
+//
+// Check a new broker connection with the standard battery, the round-trip
+// order on a symbol the broker allows for tests:
+//   FIX> certify 1 --symbol AAPL --report cert.json
+//
+// A desk screen that cannot send by accident, the traders and admins of
+// roles.txt switching up when they need to:
+//   cargo run --example fix_repl -- initiator initiator.cfg --roles roles.txt --role read-only
+//   FIX> login trader
+//
+// Run the acceptor as a service, controlled from another terminal through
+// a socket only its owner can open (admin_tokens.txt: alice = <token>):
+//   cargo run --example fix_repl -- acceptor acceptor.cfg --admin-socket fix_repl.sock \
+//       --admin-tokens admin_tokens.txt < /dev/null
+//   socat - UNIX-CONNECT:fix_repl.sock
+//   auth alice <token>
+//   status
+//
+// Stay under the venue's message rate: 50 msgs/sec a session in bursts of
+// 10, 200 across the sessions, refusing at once what goes over:
+//   cargo run --example fix_repl -- initiator initiator.cfg --rate-limit 50/10 \
+//       --rate-limit-global 200 --rate-limit-policy reject
+//
+// Orders typed while the venue is reconnecting go out at its logon, unless
+// they waited more than 10 seconds:
+//   cargo run --example fix_repl -- initiator initiator.cfg --queue-ttl 10
+//   FIX> queue
+//
+// Send the same order twice on purpose, e.g. to test the venue's own
+// checks: warned about, not refused (ClOrdIDs left out are generated):
+//   cargo run --example fix_repl -- initiator initiator.cfg --duplicates warn
+//
+// Generate the ClOrdIDs send_to leaves out as UUIDs:
+//   cargo run --example fix_repl -- initiator initiator.cfg --cl-ord-id uuid
+//
+// Cancel what was working when a session dropped, as soon as it is back:
+//   cargo run --example fix_repl -- initiator initiator.cfg --cancel-on-disconnect cancel
+//   FIX> disconnects
+//
+// Move to the backup gateways of the config (FailoverConnectHost) when the
+// primary ones stay down, or by hand:
+//   cargo run --example fix_repl -- initiator initiator.cfg --failover
+//   FIX> failover 1
+//
+// Flatten, export the fills, archive the store and write a summary around
+// each session's close (EndOfDay* keys below):
+//   cargo run --example fix_repl -- initiator initiator.cfg --eod
+//   FIX> eod
+//   FIX> eod run summary 1
+//   FIX> report --format html --out report.html
+//
+// Page whoever is on call when a session stays down, rejects pile up or
+// heartbeats slow down:
+//   cargo run --example fix_repl -- initiator initiator.cfg \
+//       --alert session_down:60s --alert reject_rate:5/60s \
+//       --alert heartbeat_latency:500ms \
+//       --alert-slack http://hooks-proxy:8080/services/T000/B000/XXXX \
+//       --alert-mail oncall@example.com --smtp 127.0.0.1:25
+//
+// An acceptor for many counterparties, each session on a thread of its own
+// so that a slow one does not hold the others up:
+//   cargo run --example fix_repl -- acceptor acceptor.cfg --multi-threaded
+//
+// Keep every message exactly as it went over the wire, and read it back:
+//   cargo run --example fix_repl -- initiator initiator.cfg --journal wire.jrnl
+//   cargo run --example fixtail -- --journal wire.jrnl
+//
+// The same journal encrypted at rest, read back with its key:
+//   head -c 32 /dev/urandom > wire.key && chmod 600 wire.key
+//   cargo run --example fix_repl -- initiator initiator.cfg --journal wire.jrnl --journal-key wire.key
+//   cargo run --example fixtail -- --journal wire.jrnl --journal-key wire.key
+//
+// Let another process drive the order flow through a named pipe, a JSON
+// result per command on stdout:
+//   mkfifo orders.fifo
+//   cargo run --example fix_repl -- initiator initiator.cfg --pipe orders.fifo > results.jsonl
+//   echo 'start' > orders.fifo
+//   echo '{"id":"o-1","sender":"CLIENT","target":"EXCHANGE","fields":{"MsgType":"D",
+//         "Symbol":"AAPL","Side":"Buy","OrderQty":100,"OrdType":"Market"}}' > orders.fifo
+//   ./strategy | cargo run --example fix_repl -- initiator initiator.cfg --pipe -
+//
+// A venue that wants an Account on every order and GTC rather than Day,
+// and sends its fill price in a tag of its own (transforms.txt):
+//   account = direction == "out" && msg.35 == "D" => set Account ACCT-7
+//   tif     = direction == "out" => map TimeInForce Day=GoodTillCancel
+//   fill_px = direction == "in" && msg.35 == "8" => rename 5044 LastPx
+//   cargo run --example fix_repl -- initiator initiator.cfg --transforms transforms.txt
+//
+// Trade internal symbols at a venue that wants its own tickers, or ISINs
+// in SecurityID (symbology.csv, see trading/symbology.rs):
+//   internal,session,symbol,security_id_source,isin
+//   VOD,FIX.4.4:CLIENT->EXCHANGE,VOD.L,isin,GB00BH4HKS39
+//   cargo run --example fix_repl -- initiator initiator.cfg --symbology symbology.csv
+//
 // =============================================================================
 // Example Acceptor Configuration (acceptor.cfg)
 // =============================================================================
@@ -239,6 +1456,31 @@ fn server_loop<C: ConnectionHandler>(mut connection_handler: C) -> Result<(), Qu
 // SocketConnectHost=127.0.0.1
 // SocketConnectPort=5001
 // DataDictionary=spec/FIX44.xml
+// # With --failover: the backup gateway, after 3 attempts without a logon
+// FailoverConnectHost=127.0.0.2
+// FailoverAfter=3
+// # With --eod: tasks from the close (EndTime, or EndOfDayTime when the
+// # session never closes), files in EndOfDayDir (./eod)
+// EndOfDayFlatten=-00:15:00
+// EndOfDayExportFills=00:01:00
+// EndOfDaySummary=00:01:00
+// EndOfDayReportFormat=html
+//
+// A FIX 5.0 session runs over FIXT.1.1: the transport and the application
+// have a dictionary each, and DefaultApplVerID is sent in the Logon
+//
+// [SESSION]
+// BeginString=FIXT.1.1
+// DefaultApplVerID=FIX.5.0SP2
+// SenderCompID=CLIENT
+// TargetCompID=EXCHANGE50
+// HeartBtInt=30
+// SocketConnectHost=127.0.0.1
+// SocketConnectPort=5002
+// TransportDataDictionary=spec/FIXT11.xml
+// AppDataDictionary=spec/FIX50SP2.xml
+//
+// `watch session` shows the DefaultApplVerID of both sides once logged on.
 //
 // =============================================================================
 // Interactive Commands
@@ -246,14 +1488,106 @@ fn server_loop<C: ConnectionHandler>(mut connection_handler: C) -> Result<(), Qu
 // Once running, you can use these commands:
 //
 // help      - Show available commands
-// status    - Display connection status (logged on, stopped)
+// redraw    - Lay the blotter out again after a terminal resize (--tui)
+// status    - Display connection status (logged on, stopped), and with
+//             --schedule when each session opens and closes next
+// health    - Heartbeat round trips (TestRequest -> Heartbeat), time since
+//             the last inbound message, missed heartbeats, per session
+// latency   - Age of inbound messages against their SendingTime (52) and
+//             TransactTime (60): p50/p99/p99.9 per session and MsgType
+//             Format: latency [--reset]
+// test_request - Send a TestRequest (35=1) and time the Heartbeat answer
+//             Format: test_request N (index, label or CompID)
+// resend    - Send a ResendRequest (35=2)
+//             Format: resend N BEGIN END (END 0 = up to the last message)
+// conformance - Play certification scenarios against a session and report
+//             pass/fail per step (see conformance.txt)
+//             Format: conformance FILE N [--report PATH]
+// certify   - Standard battery against a session: heartbeat, test request,
+//             resend, session reject, order round trip on a test symbol
+//             Format: certify N [--symbol S] [--qty Q] [--price P]
+//             [--report PATH]
+// fault     - Break outbound traffic on purpose, to see the counterparty's
+//             resend and recovery at work (trading::session::faults)
+//             Format: fault [off | drop N | corrupt N | jitter MS |
+//             skip-seq N | heartbeat S], 0 switching one fault off
+//             Example: fault skip-seq 10, then watch session 1
+// rejects   - Last Rejects (35=3) and BusinessMessageRejects (35=j) received,
+//             with the message each one rejects; its order goes Rejected
+//             Format: rejects [N] (default 20)
+// queue     - Messages sent to sessions logged off, waiting for their logon
+//             (trading::session::send_queue); clear drops them
+//             Format: queue [clear [N]]
+// failover  - Gateway of each session with a backup (--failover) and the
+//             switches made; all / N switches them now, rebuilding the handler
+//             Format: failover [all | N]
+// eod       - End-of-day tasks of each session (--eod) and their next run;
+//             run runs one now for the last close (flatten asks first)
+//             Format: eod [run TASK [N]]
+// report    - Trading report of a UTC day per session and symbol: orders,
+//             fills, cancel ratio, rejects, notional, slippage against the
+//             arrival price (trading::oms::report)
+//             Format: report [N] [--date YYYYMMDD] [--format text|html|json]
+//             [--out FILE] (format from FILE's extension when left out)
+// garbled   - Messages the engine discarded for a bad frame, each with an
+//             annotated breakdown of its bytes (trading::session::garbled)
+//             Format: garbled [on | off] (on from the start: --diagnose-garbled)
+// audit     - Query the operator audit log: every command that changed
+//             something, from here and the other tools sharing --audit-dir
+//             Format: audit [N] [--since AGE] [--source S] [--actor A]
+//             [--grep TEXT] [--failed]
+// dict      - A field of the tag dictionary by tag or name, with its type and
+//             values; alone, the fields the --dictionary files define
+//             Format: dict [TAG | NAME]
 // start     - Start the connection handler
 // stop      - Stop the connection handler
 // block     - Block waiting for messages (for testing)
 // poll      - Poll for messages without blocking
 // send_to   - Send a custom FIX message
-//             Format: send_to TAG=VALUE|TAG=VALUE sender target
+//             Format: send_to TAG=VALUE|TAG=VALUE sender target [--version V]
 //             Example: send_to 35=D|54=1|55=AAPL|38=100 CLIENT EXCHANGE
-// quit      - Exit the program
+//             The session's version is used unless --version picks one
+// send tmpl - Send a message from a template (see templates.rs)
+//             Format: send tmpl NAME [KEY=VALUE...]
+//             Example: send tmpl new_limit_order symbol=AAPL qty=100 px=150
+// templates - List the loaded templates and their placeholders
+// history   - Last commands with result code and time (--stats: per command)
+// cancel-all - Cancel working orders, after confirmation
+//             Filters: --symbol S --side buy|sell --session N --older-than 5m
+//             --mass sends one OrderMassCancelRequest per session instead
+// watch     - Live view repainted until Enter: positions [S], book S [DEPTH],
+//             session N (index, label or CompID), quotes [S] (live 35=S either
+//             way), blotter [FILTERS] (latest fills); --interval MS
+//             (default 1000)
+// book      - A book drawn once, our open orders marked *QTY at their price
+//             Format: book S [DEPTH] [--from FILE [--at TIME]]
+//             --from: the last snapshot in a --book-export file, at or before
+//             TIME (UTCTimestamp or ISO 8601) if given
+// blotter   - Fills received, newest first, a page at a time
+//             Format: blotter [--account A] [--symbol S] [--side buy|sell]
+//             [--min-qty N] [--page N] [--csv FILE]
+//             --csv: every matching fill, oldest first, to FILE
+// export    - Orders tracked, fills or positions to a CSV file, every column
+//             or those listed, in that order (see export.rs)
+//             Format: export orders|fills|positions FILE [--columns C1,C2,...]
+//             Example: export fills fills.csv --columns time_ms,symbol,side,price
+// add_session - Add a session without restarting the process
+//             Format: add_session BEGIN SENDER TARGET [port=N] [KEY=VALUE...]
+//             Example: add_session FIX.4.4 EXCHANGE CLIENT2 port=5002
+//             The connection handler is rebuilt to pick it up
+// session add - Same, asking for CompIDs, version, port/host, dictionary,
+//             hours and risk profile one by one; can append the session to
+//             the config file and prints the counterparty's config
+// selftest  - Measure throughput and latency with an in-process pair
+//             Format: selftest throughput [SECONDS] [--store memory|file]
+//             [--multi-threaded]
+// clock     - Clock sync against the --ntp server over the current period:
+//             samples, mean offset, max divergence, breaches of the tolerance
+//             Format: clock [--report] (--report saves it and starts over)
+// login     - Switch role, up to the one granted by --roles; alone, the
+//             current role and the grant (see roles.rs)
+//             Format: login [read-only | trader | admin]
+// quit      - Exit the program (CTRL-D, CTRL-C and SIGTERM do the same);
+//             from the remote console, close the connection (see remote.rs)
 //
 // =============================================================================
@@ -0,0 +1,228 @@
+// =============================================================================
+// Message Log Filter (mute / unmute)
+// =============================================================================
+// A long-running session fills the console with Heartbeats and TestRequests,
+// one pair per HeartBtInt per session, for hours. `mute` takes messages out
+// of the message log of the event task (fix_app.rs), everywhere or on one
+// session:
+//
+//   FIX> mute admin                 every session-level message
+//   FIX> mute 35=0                  Heartbeats: any TAG=VALUE of the message
+//   FIX> mute MsgType=TestRequest   by name, through the tag dictionary
+//   FIX> mute 35=0 --session 2      on one session (as for watch session)
+//   FIX> mute 35=0 --drop           not even summarized
+//   FIX> mute                       the rules, with how much each muted
+//   FIX> unmute 35=0                a rule (--session for a per-session one)
+//   FIX> unmute all
+//
+// A muted message is summarized instead of logged: the event task counts it
+// by session and MsgType, and at most every SUMMARY_INTERVAL logs one line per
+// session with the counts (`muted: 0 x120, 1 x2`). --drop leaves it out of
+// the summary as well.
+//
+// Only the message log is filtered. The order tracker, the live views, the
+// reject log and the dictionary checks still see every message, and their
+// warnings are never muted; so do the metrics, the WebSocket bridge and the
+// engine's own log (RUST_LOG=quickfix=trace).
+// =============================================================================
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use trading::session::events::FixMessage;
+
+/// How often the muted counts are logged, at most
+pub const SUMMARY_INTERVAL: Duration = Duration::from_secs(60);
+
+// =============================================================================
+// Rules
+// =============================================================================
+
+/// What a mute / unmute command names, as typed (field names are resolved
+/// by the shell, which has the tag dictionary)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MuteTarget {
+    Admin,
+    Field { tag: String, value: String },
+}
+
+/// Which messages a rule mutes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MuteMatch {
+    /// Session-level messages (Logon, Heartbeat, TestRequest...)
+    Admin,
+
+    /// Messages with this value in this tag
+    Field(i32, String),
+}
+
+impl MuteMatch {
+    fn matches(&self, msg: &FixMessage) -> bool {
+        match self {
+            MuteMatch::Admin => msg.admin,
+            MuteMatch::Field(tag, value) => msg.get(*tag) == Some(value.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for MuteMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MuteMatch::Admin => f.write_str("admin"),
+            MuteMatch::Field(tag, value) => write!(f, "{tag}={value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MuteRule {
+    pub matcher: MuteMatch,
+
+    /// Label of the one session it applies to; None for all
+    pub session: Option<String>,
+
+    /// Not counted in the summary either
+    pub drop: bool,
+}
+
+impl MuteRule {
+    fn matches(&self, msg: &FixMessage) -> bool {
+        self.session.as_ref().is_none_or(|x| *x == msg.session) && self.matcher.matches(msg)
+    }
+}
+
+/// What the event task does with a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Log,
+
+    /// Counted for the next summary
+    Summarize,
+    Drop,
+}
+
+// =============================================================================
+// MessageFilter
+// =============================================================================
+
+/// Mute rules, set by the shell and applied by the event task
+#[derive(Debug)]
+pub struct MessageFilter {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    /// Rules in the order they were added, with the messages each muted
+    rules: Vec<(MuteRule, u64)>,
+
+    /// Muted since the last summary: count by MsgType, by session
+    pending: BTreeMap<String, BTreeMap<String, u64>>,
+    last_summary: Instant,
+}
+
+impl Default for MessageFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MessageFilter {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                rules: Vec::new(),
+                pending: BTreeMap::new(),
+                last_summary: Instant::now(),
+            }),
+        }
+    }
+
+    /// Add a rule; one for the same messages and session is replaced
+    ///
+    /// # Returns
+    /// Whether it is new
+    pub fn mute(&self, rule: MuteRule) -> bool {
+        let mut inner = self.lock();
+        let same = |x: &MuteRule| x.matcher == rule.matcher && x.session == rule.session;
+        match inner.rules.iter_mut().find(|(x, _)| same(x)) {
+            Some((known, _)) => {
+                known.drop = rule.drop;
+                false
+            }
+            None => {
+                inner.rules.push((rule, 0));
+                true
+            }
+        }
+    }
+
+    /// Remove the rules for these messages and session; every rule with
+    /// `matcher` None
+    ///
+    /// # Returns
+    /// How many were removed
+    pub fn unmute(&self, matcher: Option<&MuteMatch>, session: Option<&str>) -> usize {
+        let mut inner = self.lock();
+        let before = inner.rules.len();
+        match matcher {
+            Some(matcher) => inner
+                .rules
+                .retain(|(x, _)| x.matcher != *matcher || x.session.as_deref() != session),
+            None => inner.rules.clear(),
+        }
+        before - inner.rules.len()
+    }
+
+    /// The rules, with how many messages each muted
+    pub fn rules(&self) -> Vec<(MuteRule, u64)> {
+        self.lock().rules.clone()
+    }
+
+    /// Whether to log a message; counts it against the first rule muting it
+    pub fn check(&self, msg: &FixMessage) -> Verdict {
+        let mut inner = self.lock();
+        let Some((rule, count)) = inner.rules.iter_mut().find(|(x, _)| x.matches(msg)) else {
+            return Verdict::Log;
+        };
+        *count += 1;
+        if rule.drop {
+            return Verdict::Drop;
+        }
+        *inner
+            .pending
+            .entry(msg.session.clone())
+            .or_default()
+            .entry(msg.msg_type().to_string())
+            .or_default() += 1;
+        Verdict::Summarize
+    }
+
+    /// Counts muted since the last summary, by session, once
+    /// SUMMARY_INTERVAL has passed since then (`0 x120, 1 x2`)
+    pub fn take_summary(&self) -> Vec<(String, String)> {
+        let mut inner = self.lock();
+        if inner.pending.is_empty() || inner.last_summary.elapsed() < SUMMARY_INTERVAL {
+            return Vec::new();
+        }
+        inner.last_summary = Instant::now();
+        std::mem::take(&mut inner.pending)
+            .into_iter()
+            .map(|(session, counts)| {
+                let counts: Vec<_> = counts
+                    .iter()
+                    .map(|(msg_type, count)| format!("{msg_type} x{count}"))
+                    .collect();
+                (session, counts.join(", "))
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().expect("message filter lock poisoned")
+    }
+}
@@ -0,0 +1,381 @@
+// =============================================================================
+// Counterparty Onboarding Wizard
+// =============================================================================
+// `session add` walks through what a new counterparty session needs, one
+// question at a time, instead of a long add_session line:
+//
+//   CompIDs -> FIX version -> port (and host) -> data dictionary -> session
+//   hours and heartbeat -> risk profile
+//
+// Every answer is checked as it is typed and asked again when invalid; an
+// empty answer takes the default shown in brackets, CTRL-D gives up. The
+// result is:
+//
+// - the session, registered like add_session does (the connection handler is
+//   rebuilt to pick it up) and, on request, appended to the config file
+// - the config the counterparty needs on their side: the same session seen
+//   from the other end (CompIDs swapped, acceptor <-> initiator)
+//
+// The risk profile is recorded with the session (RiskProfile and the
+// RiskMax* limits, see trading::risk::RiskLimits); fix_repl itself does not
+// apply it.
+// =============================================================================
+
+use std::{fmt::Write as _, io::Write, path::Path};
+
+use tokio::sync::mpsc::UnboundedReceiver;
+use trading::{
+    risk::RiskLimits,
+    session::{
+        provisioning::SessionSpec,
+        version::{FixVersion, FIXT_1_1},
+    },
+};
+
+use crate::remote::{print, println};
+
+/// Defaults offered by the wizard
+const DEFAULT_VERSION: FixVersion = FixVersion::Fix44;
+const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_HEARTBEAT: u32 = 30;
+const DEFAULT_TIME: &str = "00:00:00";
+const DEFAULT_RISK_PROFILE: &str = "standard";
+
+// =============================================================================
+// Answers
+// =============================================================================
+
+/// Where the data dictionaries are, by FIX version family
+#[derive(Debug, Clone)]
+pub enum Dictionaries {
+    /// No validation against a dictionary
+    None,
+
+    /// FIX.4.x: DataDictionary
+    Fix4(String),
+
+    /// FIXT.1.1: TransportDataDictionary and AppDataDictionary
+    Fixt { transport: String, app: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct Onboarding {
+    /// `acceptor` or `initiator`: our side; the counterparty has the other
+    pub connection_type: String,
+    pub sender_comp_id: String,
+    pub target_comp_id: String,
+    pub version: FixVersion,
+    pub port: u16,
+
+    /// Initiator: host we connect to. Acceptor: host the counterparty
+    /// connects to (only for their config)
+    pub host: String,
+    pub dictionaries: Dictionaries,
+    pub heart_bt_int: u32,
+    pub start_time: String,
+    pub end_time: String,
+
+    /// Name and limits, or None for no limits
+    pub risk: Option<(String, RiskLimits)>,
+}
+
+impl Onboarding {
+    fn is_acceptor(&self) -> bool {
+        self.connection_type == "acceptor"
+    }
+
+    fn begin_string(&self) -> &'static str {
+        self.version.begin_string()
+    }
+
+    /// Settings shared by both ends
+    fn common_settings(&self) -> Vec<(&'static str, String)> {
+        let mut out = Vec::new();
+        if self.begin_string() == FIXT_1_1 {
+            out.push(("DefaultApplVerID", self.version.as_str().to_string()));
+        }
+        out.push(("HeartBtInt", self.heart_bt_int.to_string()));
+        out.push(("StartTime", self.start_time.clone()));
+        out.push(("EndTime", self.end_time.clone()));
+        match &self.dictionaries {
+            Dictionaries::None => out.push(("UseDataDictionary", "N".to_string())),
+            Dictionaries::Fix4(path) => {
+                out.push(("UseDataDictionary", "Y".to_string()));
+                out.push(("DataDictionary", path.clone()));
+            }
+            Dictionaries::Fixt { transport, app } => {
+                out.push(("UseDataDictionary", "Y".to_string()));
+                out.push(("TransportDataDictionary", transport.clone()));
+                out.push(("AppDataDictionary", app.clone()));
+            }
+        }
+        out
+    }
+
+    /// Our session's settings, apart from the identifiers
+    fn our_settings(&self) -> Vec<(&'static str, String)> {
+        let mut out = if self.is_acceptor() {
+            vec![("SocketAcceptPort", self.port.to_string())]
+        } else {
+            vec![
+                ("SocketConnectHost", self.host.clone()),
+                ("SocketConnectPort", self.port.to_string()),
+            ]
+        };
+        out.extend(self.common_settings());
+        if let Some((name, limits)) = &self.risk {
+            out.push(("RiskProfile", name.clone()));
+            out.extend(limits.to_settings());
+        }
+        out
+    }
+
+    /// The session to register, as add_session would
+    pub fn spec(&self) -> SessionSpec {
+        SessionSpec {
+            begin_string: self.begin_string().to_string(),
+            sender_comp_id: self.sender_comp_id.clone(),
+            target_comp_id: self.target_comp_id.clone(),
+            params: self
+                .our_settings()
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        }
+    }
+
+    /// Our [SESSION] block, as appended to the config file
+    pub fn our_section(&self) -> String {
+        section(
+            self.begin_string(),
+            &self.sender_comp_id,
+            &self.target_comp_id,
+            &self.our_settings(),
+        )
+    }
+
+    /// The config file the counterparty needs, from their point of view
+    pub fn counterparty_config(&self) -> String {
+        let (connection_type, mut settings) = if self.is_acceptor() {
+            (
+                "initiator",
+                vec![
+                    ("SocketConnectHost", self.host.clone()),
+                    ("SocketConnectPort", self.port.to_string()),
+                ],
+            )
+        } else {
+            ("acceptor", vec![("SocketAcceptPort", self.port.to_string())])
+        };
+        settings.extend(self.common_settings());
+
+        let mut out = String::new();
+        let _ = writeln!(out, "[DEFAULT]");
+        let _ = writeln!(out, "ConnectionType={connection_type}");
+        if connection_type == "initiator" {
+            let _ = writeln!(out, "ReconnectInterval=5");
+        }
+        let _ = writeln!(out, "FileStorePath=./fix_store");
+        let _ = writeln!(out, "FileLogPath=./fix_log");
+        out.push('\n');
+        out.push_str(&section(
+            self.begin_string(),
+            &self.target_comp_id,
+            &self.sender_comp_id,
+            &settings,
+        ));
+        out
+    }
+}
+
+fn section(
+    begin_string: &str,
+    sender: &str,
+    target: &str,
+    settings: &[(&str, String)],
+) -> String {
+    let mut out = String::from("[SESSION]\n");
+    let _ = writeln!(out, "BeginString={begin_string}");
+    let _ = writeln!(out, "SenderCompID={sender}");
+    let _ = writeln!(out, "TargetCompID={target}");
+    for (key, value) in settings {
+        let _ = writeln!(out, "{key}={value}");
+    }
+    out
+}
+
+// =============================================================================
+// Questions
+// =============================================================================
+
+/// Ask every question; None if stdin closed (CTRL-D) on the way
+///
+/// # Arguments
+/// * `lines` - The shell's stdin lines
+/// * `connection_type` - Our side, from the [DEFAULT] settings
+pub async fn run(
+    lines: &mut UnboundedReceiver<String>,
+    connection_type: &str,
+) -> Option<Onboarding> {
+    let acceptor = connection_type == "acceptor";
+    println!("New counterparty session ({connection_type} side); CTRL-D to give up");
+
+    let sender_comp_id = ask(lines, "Our CompID (SenderCompID)", None, parse_comp_id).await?;
+    let target_comp_id = ask(lines, "Their CompID (TargetCompID)", None, |x| {
+        let comp_id = parse_comp_id(x)?;
+        if comp_id == sender_comp_id {
+            return Err("must differ from our CompID".to_string());
+        }
+        Ok(comp_id)
+    })
+    .await?;
+    let version = ask(lines, "FIX version", Some(DEFAULT_VERSION.as_str()), |x| {
+        x.parse::<FixVersion>().map_err(|err| err.to_string())
+    })
+    .await?;
+
+    let port_question = if acceptor {
+        "Port we listen on"
+    } else {
+        "Port we connect to"
+    };
+    let port = ask(lines, port_question, None, parse_port).await?;
+    let host_question = if acceptor {
+        "Host the counterparty connects to"
+    } else {
+        "Host we connect to"
+    };
+    let host = ask(lines, host_question, Some(DEFAULT_HOST), parse_host).await?;
+
+    let no_validation = Some("");
+    let dictionaries = if version.needs_fixt() {
+        let question = "Transport dictionary (empty: no validation)";
+        match ask(lines, question, no_validation, parse_dictionary).await? {
+            None => Dictionaries::None,
+            Some(transport) => {
+                let app = ask(lines, "Application dictionary", None, |x| {
+                    parse_dictionary(x)?.ok_or_else(|| "required with a transport one".to_string())
+                })
+                .await?;
+                Dictionaries::Fixt { transport, app }
+            }
+        }
+    } else {
+        let question = "Data dictionary (empty: no validation)";
+        match ask(lines, question, no_validation, parse_dictionary).await? {
+            Some(path) => Dictionaries::Fix4(path),
+            None => Dictionaries::None,
+        }
+    };
+
+    let heartbeat = DEFAULT_HEARTBEAT.to_string();
+    let heart_bt_int = ask(lines, "Heartbeat interval, seconds", Some(&heartbeat), |x| {
+        x.parse::<u32>()
+            .ok()
+            .filter(|x| *x > 0)
+            .ok_or_else(|| "expected a positive number of seconds".to_string())
+    })
+    .await?;
+    let start_time = ask(lines, "Session start, UTC", Some(DEFAULT_TIME), parse_time).await?;
+    let end_time = ask(lines, "Session end, UTC", Some(DEFAULT_TIME), parse_time).await?;
+
+    let profiles = format!("Risk profile ({} or none)", RiskLimits::PROFILES.join(", "));
+    let risk = ask(lines, &profiles, Some(DEFAULT_RISK_PROFILE), |x| match x {
+        "none" => Ok(None),
+        name => RiskLimits::by_profile(name)
+            .map(|limits| Some((name.to_string(), limits)))
+            .ok_or_else(|| format!("unknown profile {name}")),
+    })
+    .await?;
+
+    Some(Onboarding {
+        connection_type: connection_type.to_string(),
+        sender_comp_id,
+        target_comp_id,
+        version,
+        port,
+        host,
+        dictionaries,
+        heart_bt_int,
+        start_time,
+        end_time,
+        risk,
+    })
+}
+
+/// Ask until the answer parses; an empty answer takes the default
+async fn ask<T>(
+    lines: &mut UnboundedReceiver<String>,
+    question: &str,
+    default: Option<&str>,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Option<T> {
+    loop {
+        match default {
+            Some(default) if !default.is_empty() => print!("{question} [{default}]: "),
+            _ => print!("{question}: "),
+        }
+        let _ = std::io::stdout().flush();
+
+        let line = lines.recv().await?;
+        let answer = match (line.trim(), default) {
+            ("", Some(default)) => default,
+            (answer, _) => answer,
+        };
+        match parse(answer) {
+            Ok(value) => return Some(value),
+            Err(reason) => println!("  {reason}"),
+        }
+    }
+}
+
+// =============================================================================
+// Validation
+// =============================================================================
+
+/// Letters, digits, `_`, `-` and `.`: safe in config files and labels
+fn parse_comp_id(value: &str) -> Result<String, String> {
+    if value.is_empty() {
+        return Err("required".to_string());
+    }
+    if !value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err("letters, digits, '_', '-' and '.' only".to_string());
+    }
+    Ok(value.to_string())
+}
+
+fn parse_port(value: &str) -> Result<u16, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|x| *x > 0)
+        .ok_or_else(|| "expected a port number, 1 to 65535".to_string())
+}
+
+fn parse_host(value: &str) -> Result<String, String> {
+    if value.is_empty() || value.contains(char::is_whitespace) {
+        return Err("expected a host name or IP address".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// Empty for none, else a file that exists
+fn parse_dictionary(value: &str) -> Result<Option<String>, String> {
+    match value {
+        "" => Ok(None),
+        path if Path::new(path).is_file() => Ok(Some(path.to_string())),
+        path => Err(format!("no such file: {path}")),
+    }
+}
+
+/// `HH:MM:SS`, as QuickFIX expects StartTime / EndTime
+fn parse_time(value: &str) -> Result<String, String> {
+    let parts: Vec<u32> = value.split(':').filter_map(|x| x.parse().ok()).collect();
+    match parts.as_slice() {
+        [h, m, s] if value.len() == 8 && *h < 24 && *m < 60 && *s < 60 => Ok(value.to_string()),
+        _ => Err("expected HH:MM:SS".to_string()),
+    }
+}
@@ -0,0 +1,493 @@
+// =============================================================================
+// Order Tracker for the REPL
+// =============================================================================
+// The REPL has no order entry logic of its own: orders are whatever the user
+// typed with send_to. To cancel them in bulk it still needs to know which are
+// working, so the event task (fix_app.rs) feeds every order message it sees
+// into this tracker:
+//
+//   outbound 35=D  -> order is working (session, symbol, side, qty, price, time)
+//   outbound 35=F  -> pending cancel, cancels counted
+//   inbound  35=8  -> OrdStatus (39) moves it on; Replaced (150=5) re-keys it
+//   inbound  35=9  -> cancel rejected, working again
+//   inbound  35=3 / 35=j referring to a D -> rejected; to an F or G -> the
+//                    request failed, working again (trading::session::rejects)
+//
+// The shell queries it for `cancel-all` (command_exec.rs), and `book` marks
+// the orders working at each price level (book_export.rs). `mass_status`
+// sets its open orders against the venue's (trading::oms::mass_status).
+// `report` reads every order, with the price of the book when it was sent
+// (trading::oms::report).
+// The event task hands those of a session that drops to the cancel on
+// disconnect guard, and cancels them from here when it is back
+// (trading::oms::disconnect).
+//
+// Bulk cancels are single OrderCancelRequests (35=F) by default. With --mass
+// one OrderMassCancelRequest (35=q) goes to each session instead, for venues
+// that support it; the age filter cannot be expressed there.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use quickfix::{FieldMap, Message, QuickFixError, SessionId};
+
+use trading::{
+    oms::{self, mass_status::LocalOrder, report::ReportOrder},
+    session::{events::FixMessage, rejects::Reject, Direction},
+    time::transact_time,
+};
+
+// =============================================================================
+// Order Model
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    /// Parse `buy` / `sell` as typed in the shell
+    pub fn from_name(value: &str) -> Option<Self> {
+        match value {
+            "buy" => Some(Side::Buy),
+            "sell" => Some(Side::Sell),
+            _ => None,
+        }
+    }
+
+    /// Map FIX Side (tag 54)
+    pub fn from_fix(value: &str) -> Option<Self> {
+        match value {
+            "1" => Some(Side::Buy),
+            "2" => Some(Side::Sell),
+            _ => None,
+        }
+    }
+
+    /// FIX Side (tag 54) value
+    pub fn as_fix(self) -> &'static str {
+        match self {
+            Side::Buy => "1",
+            Side::Sell => "2",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderState {
+    /// Can still trade (new, partially filled, pending new / replace)
+    Working,
+    /// Cancel sent, not yet confirmed
+    PendingCancel,
+    /// Filled, canceled or expired
+    Done,
+    /// Rejected by the venue (39=8), or by the counterparty's session or
+    /// application layer (35=3 / 35=j)
+    Rejected,
+}
+
+impl OrderState {
+    /// Map FIX OrdStatus (tag 39)
+    fn from_fix(value: &str) -> Self {
+        match value {
+            "2" | "3" | "4" | "C" => OrderState::Done,
+            "8" => OrderState::Rejected,
+            "6" => OrderState::PendingCancel,
+            _ => OrderState::Working,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrackedOrder {
+    /// Label of the session the order was sent on
+    pub session: String,
+
+    /// Header of that session, to rebuild its SessionId for cancels
+    begin_string: String,
+    sender_comp_id: String,
+    target_comp_id: String,
+
+    pub cl_ord_id: String,
+    pub symbol: String,
+    pub side: Option<Side>,
+    pub quantity: String,
+
+    /// Limit price (44); None for a market order
+    pub price: Option<f64>,
+    pub sent_at: Instant,
+    pub state: OrderState,
+
+    /// Mid of the book when it was sent, if there was one
+    pub arrival: Option<f64>,
+
+    /// Cancel requests sent for it
+    pub cancels: u32,
+}
+
+impl TrackedOrder {
+    pub fn age(&self) -> Duration {
+        self.sent_at.elapsed()
+    }
+
+    /// Whether `session` names this order's session: its label
+    /// (`FIX.4.4:CLIENT->EXCHANGE`) or the counterparty CompID
+    pub fn is_on_session(&self, session: &str) -> bool {
+        self.session == session || self.target_comp_id == session
+    }
+
+    pub fn session_id(&self) -> Result<SessionId, QuickFixError> {
+        SessionId::try_new(
+            &self.begin_string,
+            &self.sender_comp_id,
+            &self.target_comp_id,
+            "",
+        )
+    }
+
+    /// Build the OrderCancelRequest (35=F) for this order
+    pub fn to_cancel_request(&self, cancel_cl_ord_id: &str) -> Result<Message, QuickFixError> {
+        let mut msg = Message::new();
+        msg.with_header_mut(|h| h.set_field(35, "F"))?;
+        msg.set_field(11, cancel_cl_ord_id)?; // ClOrdID of the cancel itself
+        msg.set_field(41, self.cl_ord_id.as_str())?; // OrigClOrdID
+        msg.set_field(55, self.symbol.as_str())?; // Symbol
+        if let Some(side) = self.side {
+            msg.set_field(54, side.as_fix())?; // Side
+        }
+        msg.set_field(38, self.quantity.as_str())?; // OrderQty
+        msg.set_field(60, transact_time().as_str())?; // TransactTime
+        Ok(msg)
+    }
+
+    /// The order as set against a mass status request's reports
+    pub fn to_local(&self) -> LocalOrder {
+        LocalOrder {
+            cl_ord_id: self.cl_ord_id.clone(),
+            symbol: self.symbol.clone(),
+            side: self.side.map(|side| match side {
+                Side::Buy => oms::Side::Buy,
+                Side::Sell => oms::Side::Sell,
+            }),
+            quantity: self.quantity.parse().unwrap_or_default(),
+        }
+    }
+
+    /// The order as the daily report counts it; None without a side
+    pub fn to_report(&self) -> Option<ReportOrder> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_millis() as i64);
+        Some(ReportOrder {
+            session: self.session.clone(),
+            cl_ord_id: self.cl_ord_id.clone(),
+            symbol: self.symbol.clone(),
+            side: match self.side? {
+                Side::Buy => oms::Side::Buy,
+                Side::Sell => oms::Side::Sell,
+            },
+            sent: now - self.age().as_millis() as i64,
+            arrival: self.arrival,
+            cancels: self.cancels,
+            rejected: self.state == OrderState::Rejected,
+        })
+    }
+}
+
+impl fmt::Display for TrackedOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} qty={} age={}s {:?}",
+            self.session,
+            self.cl_ord_id,
+            self.side.map_or("?", |side| match side {
+                Side::Buy => "buy",
+                Side::Sell => "sell",
+            }),
+            self.symbol,
+            self.quantity,
+            self.age().as_secs(),
+            self.state
+        )
+    }
+}
+
+// =============================================================================
+// Bulk Cancel Filter
+// =============================================================================
+
+/// Which working orders `cancel-all` applies to; unset fields match anything
+#[derive(Debug, Default)]
+pub struct CancelFilter {
+    pub symbol: Option<String>,
+    pub side: Option<Side>,
+
+    /// Session label or counterparty CompID
+    pub session: Option<String>,
+
+    /// Only orders sent at least this long ago
+    pub older_than: Option<Duration>,
+
+    /// Send one OrderMassCancelRequest (35=q) per session
+    pub mass: bool,
+}
+
+impl CancelFilter {
+    pub fn matches(&self, order: &TrackedOrder) -> bool {
+        // Option::iter().all() is true for None: an unset filter matches
+        self.symbol.iter().all(|x| *x == order.symbol)
+            && self.side.iter().all(|x| Some(*x) == order.side)
+            && self.session.iter().all(|x| order.is_on_session(x))
+            && self.older_than.iter().all(|x| order.age() >= *x)
+    }
+
+    /// Build the OrderMassCancelRequest (35=q) for one session
+    ///
+    /// MassCancelRequestType (530) is 1 (by security) with --symbol, 7 (all
+    /// orders) otherwise; the side, if any, narrows it further.
+    pub fn to_mass_cancel_request(&self, cl_ord_id: &str) -> Result<Message, QuickFixError> {
+        let mut msg = Message::new();
+        msg.with_header_mut(|h| h.set_field(35, "q"))?;
+        msg.set_field(11, cl_ord_id)?; // ClOrdID
+        match &self.symbol {
+            Some(symbol) => {
+                msg.set_field(530, "1")?; // MassCancelRequestType: security
+                msg.set_field(55, symbol.as_str())?; // Symbol
+            }
+            None => msg.set_field(530, "7")?, // MassCancelRequestType: all
+        }
+        if let Some(side) = self.side {
+            msg.set_field(54, side.as_fix())?; // Side
+        }
+        msg.set_field(60, transact_time().as_str())?; // TransactTime
+        Ok(msg)
+    }
+}
+
+/// Parse a duration typed in the shell: `30s`, `5m`, `2h`
+///
+/// # Returns
+/// None for anything else, or an age too large to count in seconds
+pub fn parse_age(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let amount: u64 = value[..value.len() - unit.len_utf8()].parse().ok()?;
+    let seconds = match unit {
+        's' => amount,
+        'm' => amount.checked_mul(60)?,
+        'h' => amount.checked_mul(3600)?,
+        _ => return None,
+    };
+    Some(Duration::from_secs(seconds))
+}
+
+// =============================================================================
+// OrderTracker
+// =============================================================================
+// Written by the event task and read by the shell, hence the Mutex. Orders
+// are keyed by (session label, ClOrdID): two sessions may reuse an id.
+// =============================================================================
+
+pub struct OrderTracker {
+    orders: Mutex<HashMap<(String, String), TrackedOrder>>,
+    next_cancel_id: AtomicU64,
+
+    /// Start time in seconds, so cancel ids do not repeat across runs
+    run_id: u64,
+}
+
+impl OrderTracker {
+    pub fn new() -> Self {
+        Self {
+            orders: Mutex::new(HashMap::new()),
+            next_cancel_id: AtomicU64::new(1),
+            run_id: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |x| x.as_secs()),
+        }
+    }
+
+    /// Allocate a ClOrdID for a cancel sent by the shell
+    pub fn next_cancel_id(&self) -> String {
+        let id = self.next_cancel_id.fetch_add(1, Ordering::Relaxed);
+        format!("CXL-{}-{id}", self.run_id)
+    }
+
+    /// Apply an order-related message seen by the event task
+    pub fn on_message(&self, msg: &FixMessage) {
+        if msg.admin {
+            return;
+        }
+        match (msg.direction, msg.msg_type()) {
+            (Direction::Outbound, "D") => self.on_new_order(msg),
+            (Direction::Outbound, "F") => {
+                if let Some(orig) = msg.get(41) {
+                    let key = (msg.session.clone(), orig.to_string());
+                    if let Some(order) = self.lock().get_mut(&key) {
+                        order.state = OrderState::PendingCancel;
+                        order.cancels += 1;
+                    }
+                }
+            }
+            (Direction::Inbound, "8") => self.on_execution_report(msg),
+            (Direction::Inbound, "9") => {
+                if let Some(orig) = msg.get(41) {
+                    self.set_state(&msg.session, orig, OrderState::Working);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Apply a Reject (35=3) or BusinessMessageReject (35=j) to the order
+    /// it refers to, found through the rejected message
+    pub fn on_reject(&self, reject: &Reject) {
+        let Some(original) = &reject.original else {
+            return;
+        };
+        match original.msg_type() {
+            "D" => {
+                if let Some(cl_ord_id) = original.get(11) {
+                    self.set_state(&reject.session, cl_ord_id, OrderState::Rejected);
+                }
+            }
+            "F" | "G" => {
+                if let Some(orig) = original.get(41) {
+                    self.set_state(&reject.session, orig, OrderState::Working);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_new_order(&self, msg: &FixMessage) {
+        let Some(cl_ord_id) = msg.get(11) else {
+            return;
+        };
+        let order = TrackedOrder {
+            session: msg.session.clone(),
+            begin_string: msg.get(8).unwrap_or_default().to_string(),
+            sender_comp_id: msg.get(49).unwrap_or_default().to_string(),
+            target_comp_id: msg.get(56).unwrap_or_default().to_string(),
+            cl_ord_id: cl_ord_id.to_string(),
+            symbol: msg.get(55).unwrap_or_default().to_string(),
+            side: msg.get(54).and_then(Side::from_fix),
+            quantity: msg.get(38).unwrap_or_default().to_string(),
+            price: msg.get(44).and_then(|x| x.parse().ok()),
+            sent_at: Instant::now(),
+            state: OrderState::Working,
+            arrival: None,
+            cancels: 0,
+        };
+        self.lock()
+            .insert((order.session.clone(), order.cl_ord_id.clone()), order);
+    }
+
+    fn on_execution_report(&self, msg: &FixMessage) {
+        let mut orders = self.lock();
+        let session = msg.session.clone();
+
+        // A replace moves the order from OrigClOrdID (41) to its new ClOrdID
+        if let (Some("5"), Some(orig), Some(new)) = (msg.get(150), msg.get(41), msg.get(11)) {
+            if let Some(mut order) = orders.remove(&(session.clone(), orig.to_string())) {
+                order.cl_ord_id = new.to_string();
+                if let Some(quantity) = msg.get(38) {
+                    order.quantity = quantity.to_string();
+                }
+                if let Some(price) = msg.get(44).and_then(|x| x.parse().ok()) {
+                    order.price = Some(price);
+                }
+                orders.insert((session.clone(), new.to_string()), order);
+            }
+        }
+
+        // Cancels are reported with the cancel's ClOrdID and OrigClOrdID (41)
+        // pointing at the original order
+        let order = [msg.get(11), msg.get(41)]
+            .into_iter()
+            .flatten()
+            .find(|id| orders.contains_key(&(session.clone(), id.to_string())));
+        let Some(cl_ord_id) = order else {
+            return;
+        };
+        if let (Some(order), Some(status)) = (
+            orders.get_mut(&(session, cl_ord_id.to_string())),
+            msg.get(39),
+        ) {
+            order.state = OrderState::from_fix(status);
+        }
+    }
+
+    fn set_state(&self, session: &str, cl_ord_id: &str, state: OrderState) {
+        if let Some(order) = self
+            .lock()
+            .get_mut(&(session.to_string(), cl_ord_id.to_string()))
+        {
+            order.state = state;
+        }
+    }
+
+    /// Record the market price when an order was sent
+    pub fn set_arrival(&self, session: &str, cl_ord_id: &str, price: f64) {
+        if let Some(order) = self
+            .lock()
+            .get_mut(&(session.to_string(), cl_ord_id.to_string()))
+        {
+            order.arrival = Some(price);
+        }
+    }
+
+    /// An order of a session, whatever its state
+    pub fn order(&self, session: &str, cl_ord_id: &str) -> Option<TrackedOrder> {
+        self.lock()
+            .get(&(session.to_string(), cl_ord_id.to_string()))
+            .cloned()
+    }
+
+    /// Snapshot of orders that can still trade, oldest first
+    pub fn working_orders(&self) -> Vec<TrackedOrder> {
+        let mut orders: Vec<_> = self
+            .lock()
+            .values()
+            .filter(|x| x.state == OrderState::Working)
+            .cloned()
+            .collect();
+        orders.sort_by_key(|x| x.sent_at);
+        orders
+    }
+
+    /// Snapshot of every order seen, done and rejected included, oldest
+    /// first (export.rs)
+    pub fn orders(&self) -> Vec<TrackedOrder> {
+        let mut orders: Vec<_> = self.lock().values().cloned().collect();
+        orders.sort_by_key(|x| x.sent_at);
+        orders
+    }
+
+    /// Snapshot of orders not done yet, cancels pending included, oldest
+    /// first (blotter.rs)
+    pub fn open_orders(&self) -> Vec<TrackedOrder> {
+        let mut orders: Vec<_> = self
+            .lock()
+            .values()
+            .filter(|x| !matches!(x.state, OrderState::Done | OrderState::Rejected))
+            .cloned()
+            .collect();
+        orders.sort_by_key(|x| x.sent_at);
+        orders
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<(String, String), TrackedOrder>> {
+        self.orders.lock().expect("order tracker lock poisoned")
+    }
+}
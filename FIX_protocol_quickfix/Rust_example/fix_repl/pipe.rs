@@ -0,0 +1,327 @@
+// =============================================================================
+// Pipe Mode
+// =============================================================================
+// With --pipe, another process drives the shell instead of a person: the
+// lines come from stdin (`--pipe -`), from a named pipe (`--pipe PATH`, made
+// with mkfifo) or from a file, no prompt is shown, and each command ends
+// with one JSON object on stdout:
+//
+//   $ mkfifo orders.fifo
+//   $ fix_repl initiator initiator.cfg --pipe orders.fifo > results.jsonl &
+//   $ echo '{"id":"o-1","sender":"CLIENT","target":"EXCHANGE","fields":
+//       {"MsgType":"D","Symbol":"AAPL","Side":"Buy","OrderQty":100,
+//        "OrdType":"Limit","Price":150.5}}' > orders.fifo
+//
+//   {"seq":1,"id":"o-1","input":"send_to MsgType=D|Symbol=AAPL|... CLIENT
+//    EXCHANGE","command":"send_to","code":"OK","ok":true,"elapsed_us":61,
+//    "output":["... INFO fix_repl::command_exec: message sent ..."]}
+//
+// A line is a shell command as typed at the prompt, or a JSON object:
+// - an order: `sender`, `target` and `fields` (tags or dictionary names,
+//   values as strings or numbers), `version` optionally; sent as send_to
+// - a template: `template` and its `values`; sent as send tmpl
+// - a command: `command`, a shell line
+// and `id` optionally, a string or a number copied into the result so the
+// producer can match them up.
+//
+// stdout carries the results only: what the command prints, and the logs
+// meanwhile, go to stderr as usual and into the `output` lines of its
+// result. The answers a command waits for (cancel-all's confirmation, the
+// session add questions, the end of a watch) are the next lines. A line
+// that is not valid JSON, or not one of the objects above, has a result
+// with code BAD_COMMAND and an `error`. Blank lines have none.
+//
+// stdin or a file reaching its end ends the shell, as CTRL-D would; a named
+// pipe is opened again when its last writer closes it, so producers can
+// come and go. The commands are audited with the source `pipe`.
+// =============================================================================
+
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+use std::{
+    fmt::{self, Write as _},
+    fs::{self, File},
+    io::{self, stdout, BufRead, BufReader, Write},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tracing::warn;
+use trading::json::{json_escape, JsonValue};
+
+use crate::history::ResultCode;
+
+/// `--pipe` value that reads stdin
+pub const STDIN: &str = "-";
+
+// =============================================================================
+// Input
+// =============================================================================
+
+/// Lines of a named pipe or a file, without the trailing newline
+///
+/// A named pipe is opened again each time its writers are all gone, the
+/// channel closing only if it cannot be; a file closes it at its end.
+pub fn pipe_lines(path: &Path) -> io::Result<UnboundedReceiver<String>> {
+    let metadata = fs::metadata(path)?;
+    if metadata.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "is a directory",
+        ));
+    }
+    #[cfg(unix)]
+    let reopen = metadata.file_type().is_fifo();
+    #[cfg(not(unix))]
+    let reopen = false;
+
+    let (sender, receiver) = unbounded_channel();
+    let path = path.to_path_buf();
+    thread::Builder::new()
+        .name("pipe".to_string())
+        .spawn(move || loop {
+            // Blocks until a writer opens the pipe
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(err) => {
+                    warn!(path = %path.display(), "cannot open the pipe: {err}");
+                    return;
+                }
+            };
+            for line in BufReader::new(file).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    return;
+                }
+            }
+            if !reopen || sender.is_closed() {
+                return;
+            }
+        })?;
+    Ok(receiver)
+}
+
+/// A line of the pipe made into a shell line
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipeLine {
+    /// `id` of a JSON object, for its result
+    pub id: Option<String>,
+
+    /// What runs in the shell: the line itself, or the command the JSON
+    /// object stands for
+    pub command: String,
+}
+
+/// A JSON line that does not make a command
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipeLineError {
+    pub id: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for PipeLineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for PipeLineError {}
+
+/// Read a line of the pipe: a JSON object when it starts with `{`, a shell
+/// line otherwise
+pub fn read_line(line: &str) -> Result<PipeLine, PipeLineError> {
+    if !line.trim_start().starts_with('{') {
+        return Ok(PipeLine {
+            id: None,
+            command: line.to_string(),
+        });
+    }
+    let error = |id: Option<&String>, message: String| PipeLineError {
+        id: id.cloned(),
+        message,
+    };
+    let value = JsonValue::parse(line).ok_or_else(|| error(None, "not valid JSON".into()))?;
+    let id = match value.get("id") {
+        Some(id) => {
+            Some(scalar(id).ok_or_else(|| error(None, "id must be a string or a number".into()))?)
+        }
+        None => None,
+    };
+    let command = json_command(&value).map_err(|message| error(id.as_ref(), message))?;
+    Ok(PipeLine { id, command })
+}
+
+/// The shell line of an order, template or command object
+fn json_command(value: &JsonValue) -> Result<String, String> {
+    if let Some(command) = value.get("command") {
+        return command
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| "command must be a string".to_string());
+    }
+
+    if let Some(name) = value.get("template") {
+        let name = word(name, "template")?;
+        let mut line = format!("send tmpl {name}");
+        for (key, value) in members(value.get("values"), "values")? {
+            let _ = write!(line, " {key}={value}");
+        }
+        return Ok(line);
+    }
+
+    if value.get("fields").is_some() {
+        let fields = members(value.get("fields"), "fields")?;
+        if fields.is_empty() {
+            return Err("fields must not be empty".into());
+        }
+        let fields = fields
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<_>>()
+            .join("|");
+        let sender = word(
+            value.get("sender").ok_or("an order needs a sender")?,
+            "sender",
+        )?;
+        let target = word(
+            value.get("target").ok_or("an order needs a target")?,
+            "target",
+        )?;
+        let mut line = format!("send_to {fields} {sender} {target}");
+        if let Some(version) = value.get("version") {
+            let _ = write!(line, " --version {}", word(version, "version")?);
+        }
+        return Ok(line);
+    }
+
+    Err("expected an order (sender, target, fields), a template or a command".into())
+}
+
+/// A string or a number, as text
+fn scalar(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::String(x) => Some(x.clone()),
+        JsonValue::Number(x) => Some(x.to_string()),
+        _ => None,
+    }
+}
+
+/// A string or a number fit for a shell line: not empty, no space, no `|`
+fn word(value: &JsonValue, name: &str) -> Result<String, String> {
+    match scalar(value) {
+        Some(x) if !x.is_empty() && !x.contains(|c: char| c.is_whitespace() || c == '|') => Ok(x),
+        _ => Err(format!(
+            "{name} must be a string or a number, not empty, without spaces or '|'"
+        )),
+    }
+}
+
+/// The members of an object, values made words; none when absent
+fn members(value: Option<&JsonValue>, name: &str) -> Result<Vec<(String, String)>, String> {
+    let members = match value {
+        None => return Ok(Vec::new()),
+        Some(JsonValue::Object(members)) => members,
+        Some(_) => return Err(format!("{name} must be an object")),
+    };
+    members
+        .iter()
+        .map(|(key, value)| {
+            let key_ok = !key.is_empty()
+                && !key.contains(|c: char| c.is_whitespace() || c == '|' || c == '=');
+            if !key_ok {
+                return Err(format!("{name}: bad key {key:?}"));
+            }
+            Ok((key.clone(), word(value, &format!("{name}.{key}"))?))
+        })
+        .collect()
+}
+
+// =============================================================================
+// Results
+// =============================================================================
+
+/// The outcome of one line, written to stdout as a JSON line
+#[derive(Debug)]
+pub struct PipeResult<'a> {
+    /// Lines read, counted from 1, blank ones included
+    pub seq: u64,
+    pub id: Option<&'a str>,
+
+    /// The shell line that ran
+    pub input: &'a str,
+
+    /// Name of the command, `?` when it did not parse
+    pub command: &'a str,
+    pub code: ResultCode,
+    pub elapsed: Duration,
+
+    /// Why a JSON line made no command
+    pub error: Option<&'a str>,
+
+    /// What the command printed and logged
+    pub output: &'a str,
+}
+
+impl PipeResult<'_> {
+    pub fn to_json(&self) -> String {
+        let mut json = format!("{{\"seq\":{}", self.seq);
+        if let Some(id) = self.id {
+            let _ = write!(json, ",\"id\":\"{}\"", json_escape(id));
+        }
+        let _ = write!(
+            json,
+            ",\"input\":\"{}\",\"command\":\"{}\",\"code\":\"{}\",\"ok\":{},\"elapsed_us\":{}",
+            json_escape(self.input.trim()),
+            json_escape(self.command),
+            self.code,
+            self.code.is_ok(),
+            self.elapsed.as_micros()
+        );
+        if let Some(error) = self.error {
+            let _ = write!(json, ",\"error\":\"{}\"", json_escape(error));
+        }
+        let output = self
+            .output
+            .lines()
+            .map(plain)
+            .filter(|x| !x.trim().is_empty())
+            .map(|x| format!("\"{}\"", json_escape(x.trim_end())))
+            .collect::<Vec<_>>();
+        let _ = write!(json, ",\"output\":[{}]}}", output.join(","));
+        json
+    }
+
+    /// Write the result to stdout, flushed so the producer sees it at once
+    pub fn emit(&self) {
+        let mut stdout = stdout().lock();
+        let written = writeln!(stdout, "{}", self.to_json()).and_then(|()| stdout.flush());
+        if let Err(err) = written {
+            warn!("cannot write the result of a piped command: {err}");
+        }
+    }
+}
+
+/// A line of output without its terminal colors
+fn plain(line: &str) -> String {
+    let mut plain = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequence: ESC [ parameters, up to a final letter
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        plain.push(c);
+    }
+    plain
+}
@@ -0,0 +1,581 @@
+// =============================================================================
+// Remote Console
+// =============================================================================
+// A headless fix_repl (an acceptor run as a service, stdin at /dev/null) can
+// still be inspected and controlled: with --admin-socket PATH (a Unix domain
+// socket) or --admin-listen HOST:PORT (TCP), the shell also takes commands
+// from clients authenticated with a token of --admin-tokens FILE:
+//
+//   # login = token (16 characters at least)
+//   alice = 3b1f0c9e5d2a47a8b6e4
+//
+//   $ socat - UNIX-CONNECT:fix_repl.sock
+//   auth alice 3b1f0c9e5d2a47a8b6e4
+//   >> alice as admin, 'quit' to disconnect
+//   status
+//   INFO command="status" logged_on=Ok(true) stopped=Ok(false)
+//   >> status OK 12µs
+//
+// A line of a client runs in the shell like a line typed at the prompt, one
+// command at a time whoever sent it. The login of the client is the actor of
+// the audit log (source `remote`), with a role of its own that starts at the
+// grant of --roles and is switched by `login`. What the command prints, and
+// the logs meanwhile, go to the client as well as to the process's output;
+// the answers it waits for (cancel-all's confirmation, the session add
+// questions, Enter to end a watch) are the next lines of the client. Each
+// command ends with a `>> NAME CODE ELAPSED` line; `quit` closes the
+// connection, not the process.
+//
+// The token crosses a TCP connection in clear: keep --admin-listen on
+// 127.0.0.1, behind an SSH tunnel or a TLS terminator. The socket file is
+// made readable and writable by its owner only.
+// =============================================================================
+
+#[cfg(unix)]
+use std::os::unix::{
+    fs::PermissionsExt,
+    net::{UnixListener, UnixStream},
+};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::{self, Write as _},
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    mem,
+    net::{Shutdown, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::Duration,
+};
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tracing::{info, warn};
+
+use crate::roles::{Role, RoleGrants};
+
+/// Shortest token the tokens file accepts
+pub const MIN_TOKEN_LEN: usize = 16;
+
+/// Time a client has to send `auth LOGIN TOKEN`
+const AUTH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest `auth LOGIN TOKEN` line read, its newline included: a client
+/// cannot make the connection thread buffer more before it authenticates
+const MAX_AUTH_LINE: u64 = 1024;
+
+/// Pause before answering a failed authentication, to slow down guessing
+const AUTH_FAILURE_DELAY: Duration = Duration::from_secs(1);
+
+// =============================================================================
+// Output
+// =============================================================================
+// The shell prints with the print! / println! of this module, imported in
+// place of the std ones: they write to stdout, and to the client whose
+// command is running. Logs reach it through log_writer (see logging.rs).
+// With --pipe, stdout carries the results (pipe.rs): what the shell prints
+// goes to stderr, and into the transcript of the piped command running.
+// =============================================================================
+
+type Output = Arc<Mutex<Box<dyn Connection>>>;
+
+/// The connection of the client whose command is running
+static CAPTURE: Mutex<Option<Output>> = Mutex::new(None);
+
+/// stdout is kept for the results of --pipe
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// What the piped command running printed and logged
+static TRANSCRIPT: Mutex<Option<String>> = Mutex::new(None);
+
+/// print! to stdout and to the remote client of the command running
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::remote::write_output(format_args!($($arg)*), false)
+    };
+}
+
+/// println! to stdout and to the remote client of the command running
+macro_rules! println {
+    () => {
+        $crate::remote::write_output(format_args!(""), true)
+    };
+    ($($arg:tt)*) => {
+        $crate::remote::write_output(format_args!($($arg)*), true)
+    };
+}
+
+pub(crate) use {print, println};
+
+/// Behind print! and println!; a client gone away is not an error
+pub fn write_output(args: fmt::Arguments<'_>, newline: bool) {
+    match (STDOUT_RESERVED.load(Ordering::Relaxed), newline) {
+        (false, true) => std::println!("{args}"),
+        (false, false) => std::print!("{args}"),
+        (true, true) => eprintln!("{args}"),
+        (true, false) => eprint!("{args}"),
+    }
+    if let Some(transcript) = lock_transcript().as_mut() {
+        let _ = transcript.write_fmt(args);
+        if newline {
+            transcript.push('\n');
+        }
+    }
+    if let Some(output) = captured() {
+        let mut output = lock_output(&output);
+        let _ = output.write_fmt(args);
+        if newline {
+            let _ = output.write_all(b"\n");
+        }
+    }
+}
+
+/// Whether a remote client's command is running
+pub fn capturing() -> bool {
+    captured().is_some()
+}
+
+/// Writer of the log lines for the client whose command is running, next
+/// to stderr or the log file (see logging.rs)
+pub fn log_writer() -> LogWriter {
+    LogWriter(captured())
+}
+
+pub struct LogWriter(Option<Output>);
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(output) = &self.0 {
+            let _ = lock_output(output).write_all(buf);
+        }
+        if let Some(transcript) = lock_transcript().as_mut() {
+            transcript.push_str(&String::from_utf8_lossy(buf));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Output sent to a client until dropped
+pub struct Capture(());
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        *CAPTURE.lock().expect("capture lock poisoned") = None;
+    }
+}
+
+/// From now on, print! and println! write to stderr: stdout carries the
+/// results of --pipe
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+/// Keep what is printed and logged, until the Transcript is finished
+pub fn transcribe() -> Transcript {
+    *lock_transcript() = Some(String::new());
+    Transcript(())
+}
+
+/// Output of a piped command, kept until finished or dropped
+pub struct Transcript(());
+
+impl Transcript {
+    /// What was printed and logged since transcribe()
+    pub fn finish(self) -> String {
+        lock_transcript().take().unwrap_or_default()
+    }
+}
+
+impl Drop for Transcript {
+    fn drop(&mut self) {
+        *lock_transcript() = None;
+    }
+}
+
+fn lock_transcript() -> MutexGuard<'static, Option<String>> {
+    TRANSCRIPT.lock().expect("transcript lock poisoned")
+}
+
+fn captured() -> Option<Output> {
+    CAPTURE.lock().expect("capture lock poisoned").clone()
+}
+
+fn lock_output(output: &Output) -> MutexGuard<'_, Box<dyn Connection>> {
+    output.lock().expect("remote output lock poisoned")
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+pub enum RemoteError {
+    Io(PathBuf, io::Error),
+
+    /// A line of the tokens file other than `login = token`, a comment or a
+    /// blank
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: &'static str,
+    },
+
+    /// The socket or address cannot be listened on
+    Listen(String, io::Error),
+}
+
+impl fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoteError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            RemoteError::Syntax {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+            RemoteError::Listen(address, err) => write!(f, "cannot listen on {address}: {err}"),
+        }
+    }
+}
+
+impl Error for RemoteError {}
+
+// =============================================================================
+// Tokens
+// =============================================================================
+
+/// The token of each login allowed on the console; not Debug, so that no
+/// log line shows them
+pub struct AdminTokens {
+    tokens: BTreeMap<String, String>,
+}
+
+impl AdminTokens {
+    /// Read a tokens file: `login = token` lines, one login at least
+    pub fn load(path: &Path) -> Result<Self, RemoteError> {
+        let text = fs::read_to_string(path).map_err(|err| RemoteError::Io(path.into(), err))?;
+        let syntax = |line: usize, message| RemoteError::Syntax {
+            path: path.into(),
+            line,
+            message,
+        };
+
+        let mut tokens = BTreeMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((login, token)) = line.split_once('=') else {
+                return Err(syntax(index + 1, "expected LOGIN = TOKEN"));
+            };
+            let (login, token) = (login.trim(), token.trim());
+            if login.is_empty() || login.contains(char::is_whitespace) {
+                return Err(syntax(index + 1, "expected LOGIN = TOKEN"));
+            }
+            if token.len() < MIN_TOKEN_LEN || token.contains(char::is_whitespace) {
+                return Err(syntax(
+                    index + 1,
+                    "tokens are 16 characters at least, no space",
+                ));
+            }
+            tokens.insert(login.to_string(), token.to_string());
+        }
+        if tokens.is_empty() {
+            return Err(syntax(0, "no login"));
+        }
+        Ok(Self { tokens })
+    }
+
+    /// Whether `token` is the one of `login`, compared in constant time
+    pub fn check(&self, login: &str, token: &str) -> bool {
+        let Some(expected) = self.tokens.get(login) else {
+            return false;
+        };
+        expected.len() == token.len()
+            && expected
+                .bytes()
+                .zip(token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+// =============================================================================
+// Clients
+// =============================================================================
+
+/// A connection, Unix or TCP
+trait Connection: Read + Write + Send {
+    fn try_clone_boxed(&self) -> io::Result<Box<dyn Connection>>;
+    fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+    fn close(&self);
+}
+
+impl Connection for TcpStream {
+    fn try_clone_boxed(&self) -> io::Result<Box<dyn Connection>> {
+        Ok(Box::new(self.try_clone()?))
+    }
+
+    fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.set_read_timeout(timeout)
+    }
+
+    fn close(&self) {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixStream {
+    fn try_clone_boxed(&self) -> io::Result<Box<dyn Connection>> {
+        Ok(Box::new(self.try_clone()?))
+    }
+
+    fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.set_read_timeout(timeout)
+    }
+
+    fn close(&self) {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+}
+
+/// A client authenticated with its token
+pub struct RemoteClient {
+    pub login: String,
+
+    /// Address of the client, for the logs
+    pub peer: String,
+
+    /// What its commands may do, switched by login
+    role: Mutex<Role>,
+
+    output: Output,
+
+    /// Lines received and not run yet; taken by the shell while one of
+    /// them runs, for the answers it waits for
+    lines: Mutex<UnboundedReceiver<String>>,
+}
+
+impl RemoteClient {
+    pub fn role(&self) -> Role {
+        *self.role.lock().expect("remote role lock poisoned")
+    }
+
+    pub fn set_role(&self, role: Role) {
+        *self.role.lock().expect("remote role lock poisoned") = role;
+    }
+
+    /// The next line to run, if the command before it did not read it
+    pub fn next_line(&self) -> Option<String> {
+        self.lines
+            .lock()
+            .expect("remote lines lock poisoned")
+            .try_recv()
+            .ok()
+    }
+
+    /// Exchange the lines of the client with those of the shell: before a
+    /// command of the client runs, and after
+    pub fn swap_lines(&self, lines: &mut UnboundedReceiver<String>) {
+        mem::swap(
+            &mut *self.lines.lock().expect("remote lines lock poisoned"),
+            lines,
+        );
+    }
+
+    /// Send the output to the client until the Capture is dropped
+    pub fn capture(&self) -> Capture {
+        *CAPTURE.lock().expect("capture lock poisoned") = Some(Arc::clone(&self.output));
+        Capture(())
+    }
+
+    /// Close the connection; its thread sees the end and stops
+    pub fn close(&self) {
+        lock_output(&self.output).close();
+    }
+}
+
+// =============================================================================
+// Console
+// =============================================================================
+
+/// What the connection threads share
+struct Shared {
+    tokens: AdminTokens,
+    grants: RoleGrants,
+
+    /// A client has a line to run (once per line)
+    ready: UnboundedSender<Arc<RemoteClient>>,
+}
+
+/// The listening sockets, and the clients with a line to run
+pub struct RemoteConsole {
+    shared: Arc<Shared>,
+    ready: UnboundedReceiver<Arc<RemoteClient>>,
+
+    /// Socket file, removed on drop
+    socket: Option<PathBuf>,
+}
+
+impl RemoteConsole {
+    /// A console accepting the logins of `tokens`, each with its grant
+    pub fn new(tokens: AdminTokens, grants: RoleGrants) -> Self {
+        let (sender, ready) = unbounded_channel();
+        Self {
+            shared: Arc::new(Shared {
+                tokens,
+                grants,
+                ready: sender,
+            }),
+            ready,
+            socket: None,
+        }
+    }
+
+    /// Accept clients on a Unix domain socket, replacing a stale socket file
+    #[cfg(unix)]
+    pub fn listen_unix(mut self, path: &Path) -> Result<Self, RemoteError> {
+        let listen_error = |err| RemoteError::Listen(path.display().to_string(), err);
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(listen_error(io::ErrorKind::AddrInUse.into()));
+            }
+            fs::remove_file(path).map_err(listen_error)?;
+        }
+        let listener = UnixListener::bind(path).map_err(listen_error)?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(listen_error)?;
+        self.socket = Some(path.to_path_buf());
+
+        let shared = Arc::clone(&self.shared);
+        let name = path.display().to_string();
+        spawn_accept(&name, move || {
+            for stream in listener.incoming().flatten() {
+                serve(Box::new(stream), "local".to_string(), &shared);
+            }
+        })
+        .map_err(listen_error)?;
+        info!(socket = %name, "remote console listening");
+        Ok(self)
+    }
+
+    /// Accept clients on a TCP address (HOST:PORT)
+    pub fn listen_tcp(self, address: &str) -> Result<Self, RemoteError> {
+        let listen_error = |err| RemoteError::Listen(address.to_string(), err);
+        let listener = TcpListener::bind(address).map_err(listen_error)?;
+
+        let shared = Arc::clone(&self.shared);
+        spawn_accept(address, move || {
+            for stream in listener.incoming().flatten() {
+                let peer = stream
+                    .peer_addr()
+                    .map_or_else(|_| "unknown".to_string(), |addr| addr.to_string());
+                serve(Box::new(stream), peer, &shared);
+            }
+        })
+        .map_err(listen_error)?;
+        info!(%address, "remote console listening");
+        Ok(self)
+    }
+
+    /// The next client with a line to run
+    pub async fn next(&mut self) -> Option<Arc<RemoteClient>> {
+        self.ready.recv().await
+    }
+}
+
+impl Drop for RemoteConsole {
+    fn drop(&mut self) {
+        if let Some(path) = &self.socket {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// The next client with a line to run, or never without a console
+pub async fn next_client(console: Option<&mut RemoteConsole>) -> Option<Arc<RemoteClient>> {
+    match console {
+        Some(console) => console.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn spawn_accept<F>(name: &str, accept: F) -> io::Result<()>
+where
+    F: FnOnce() + Send + 'static,
+{
+    thread::Builder::new()
+        .name(format!("remote {name}"))
+        .spawn(accept)
+        .map(drop)
+}
+
+/// Start a thread for a new connection: it authenticates the client, then
+/// hands each of its lines to the shell
+fn serve(stream: Box<dyn Connection>, peer: String, shared: &Arc<Shared>) {
+    let shared = Arc::clone(shared);
+    let spawned = thread::Builder::new()
+        .name(format!("remote {peer}"))
+        .spawn(move || {
+            if let Err(err) = read_client(stream, &peer, &shared) {
+                warn!(%peer, "remote console: {err}");
+            }
+        });
+    if let Err(err) = spawned {
+        warn!("remote console: cannot start a connection thread: {err}");
+    }
+}
+
+fn read_client(mut stream: Box<dyn Connection>, peer: &str, shared: &Shared) -> io::Result<()> {
+    stream.set_timeout(Some(AUTH_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone_boxed()?);
+
+    let mut auth = String::new();
+    let read = (&mut reader).take(MAX_AUTH_LINE).read_line(&mut auth)?;
+    if read as u64 == MAX_AUTH_LINE && !auth.ends_with('\n') {
+        warn!(%peer, "remote console: authentication line longer than {MAX_AUTH_LINE} bytes");
+        auth.clear();
+    }
+    let login = match auth.split_whitespace().collect::<Vec<_>>()[..] {
+        ["auth", login, token] if shared.tokens.check(login, token) => login.to_string(),
+        _ => {
+            warn!(%peer, "remote console: authentication failed");
+            thread::sleep(AUTH_FAILURE_DELAY);
+            writeln!(stream, ">> authentication failed")?;
+            return Ok(());
+        }
+    };
+    stream.set_timeout(None)?;
+
+    let role = shared.grants.grant(&login);
+    writeln!(stream, ">> {login} as {role}, 'quit' to disconnect")?;
+    info!(%login, %peer, %role, "remote console connected");
+
+    let (sender, lines) = unbounded_channel();
+    let client = Arc::new(RemoteClient {
+        login,
+        peer: peer.to_string(),
+        role: Mutex::new(role),
+        output: Arc::new(Mutex::new(stream)),
+        lines: Mutex::new(lines),
+    });
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if sender.send(line).is_err() || shared.ready.send(Arc::clone(&client)).is_err() {
+            break;
+        }
+    }
+    info!(login = %client.login, %peer, "remote console disconnected");
+    Ok(())
+}
@@ -0,0 +1,173 @@
+// =============================================================================
+// Command Roles
+// =============================================================================
+// The REPL of a production session is shared by a support desk; not everyone
+// at it should be one typo away from an order or a sequence reset. Every
+// command needs a role, each role allowing what the one before it does:
+//
+//   read-only   status, history, health, latency, clock, watch, book,
+//               blotter, export, trades, scorecard, rejects, audit, dict,
+//               templates, and fault / garbled / mute / mass_status shown
+//   trader      orders: send_to, send tmpl, cancel-all, trades request,
+//               mass_status N, test_request, mute, unmute, latency --reset,
+//               clock --report
+//   admin       sessions: start, stop, block, poll, resend, add_session,
+//               session add, conformance, certify, selftest, fault and
+//               garbled switched, send_to of a session-level message (35=0
+//               to 5, A)
+//
+// The shell starts with --role, by default the highest role granted to the
+// login; `login ROLE` switches, down at will, up to that grant at most. The
+// grants are read from --roles FILE, one login per line:
+//
+//   # login = role
+//   alice = admin
+//   bob = trader
+//   * = read-only       every other login (read-only without this line)
+//
+// Without --roles, every login is granted admin: `--role read-only` then
+// keeps an order from being sent by accident, not by someone who types
+// `login trader`. A command above the role is not run: it ends DENIED, and
+// goes to the audit log if it would have. The login is the one of the audit
+// log ($USER): the roles guard against mistakes, they do not stand against
+// someone who can set the environment of the process.
+// =============================================================================
+
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// What a command needs, in increasing order of power
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    ReadOnly,
+    Trader,
+    Admin,
+}
+
+impl Role {
+    /// Parse `read-only` / `trader` / `admin` as typed in the shell or the
+    /// roles file
+    pub fn from_name(value: &str) -> Option<Self> {
+        match value {
+            "read-only" | "readonly" => Some(Self::ReadOnly),
+            "trader" => Some(Self::Trader),
+            "admin" => Some(Self::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ReadOnly => "read-only",
+            Self::Trader => "trader",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+pub enum RoleError {
+    Io(PathBuf, io::Error),
+
+    /// A line other than `login = role`, a comment or a blank
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: &'static str,
+    },
+}
+
+impl fmt::Display for RoleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoleError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            RoleError::Syntax {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+        }
+    }
+}
+
+impl Error for RoleError {}
+
+// =============================================================================
+// Grants
+// =============================================================================
+
+/// The highest role of each login
+#[derive(Debug, Clone)]
+pub struct RoleGrants {
+    logins: BTreeMap<String, Role>,
+
+    /// Logins not listed, from the `*` line
+    others: Role,
+}
+
+impl Default for RoleGrants {
+    /// Every login granted admin, as without --roles
+    fn default() -> Self {
+        Self {
+            logins: BTreeMap::new(),
+            others: Role::Admin,
+        }
+    }
+}
+
+impl RoleGrants {
+    /// Read a roles file; logins it does not list are read-only unless it
+    /// has a `*` line
+    pub fn load(path: &Path) -> Result<Self, RoleError> {
+        let text = fs::read_to_string(path).map_err(|err| RoleError::Io(path.into(), err))?;
+        let syntax = |line: usize, message| RoleError::Syntax {
+            path: path.into(),
+            line,
+            message,
+        };
+
+        let mut grants = Self {
+            logins: BTreeMap::new(),
+            others: Role::ReadOnly,
+        };
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((login, role)) = line.split_once('=') else {
+                return Err(syntax(index + 1, "expected LOGIN = ROLE"));
+            };
+            let Some(role) = Role::from_name(role.trim()) else {
+                return Err(syntax(index + 1, "expected read-only, trader or admin"));
+            };
+            match login.trim() {
+                "" => return Err(syntax(index + 1, "expected LOGIN = ROLE")),
+                "*" => grants.others = role,
+                login => {
+                    grants.logins.insert(login.to_string(), role);
+                }
+            }
+        }
+        Ok(grants)
+    }
+
+    /// The highest role a login may take
+    pub fn grant(&self, login: &str) -> Role {
+        self.logins.get(login).copied().unwrap_or(self.others)
+    }
+}
@@ -0,0 +1,189 @@
+// =============================================================================
+// Counterparty Scorecard
+// =============================================================================
+// Statistics of every session, day by day (trading::session::scorecard), fed
+// by the callbacks so that broker reviews have numbers from this stack:
+//
+//   FIX> scorecard                    today, one line per session
+//   FIX> scorecard --days 30          the last 30 days merged per session,
+//                                     with the trend of the last day
+//   FIX> scorecard 1 --days 30        one session (as for watch session),
+//                                     one line per day
+//   FIX> scorecard --days 30 --csv review.csv
+//                                     every day of every session, as CSV
+//
+// The trend sets the last day against the days before it in the window:
+// mean ack latency in percent, reject rate and uptime in points.
+//
+// Days before today come from the state store (--state URL), where the
+// scores are flushed every minute and on the way out; without one, there
+// is only today since start-up.
+// =============================================================================
+
+use std::{fmt::Write as _, sync::Arc, time::Duration};
+
+use tracing::warn;
+use trading::session::scorecard::{DayScore, ScoreTrend, Scorecard};
+
+/// How often the scores are written to the state store
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+// =============================================================================
+// Rendering
+// =============================================================================
+
+const HEADER: [&str; 10] = [
+    "up", "orders", "rej", "sess rej", "ack avg", "ack max", "resend/h", "fill", "impr bps",
+    "trend",
+];
+
+fn percent(x: Option<f64>) -> String {
+    x.map_or("-".to_string(), |x| format!("{:.1}%", x * 100.0))
+}
+
+fn duration(x: Option<Duration>) -> String {
+    x.map_or("-".to_string(), |x| format!("{x:.1?}"))
+}
+
+fn decimal(x: Option<f64>) -> String {
+    x.map_or("-".to_string(), |x| format!("{x:.2}"))
+}
+
+/// One line of figures, after the first column
+fn figures(x: &DayScore, trend: &str) -> String {
+    format!(
+        "{:>6} {:>7} {:>6} {:>8} {:>9} {:>9} {:>8} {:>6} {:>8} {}",
+        percent(x.uptime()),
+        x.orders,
+        percent(x.reject_rate()),
+        x.session_rejects,
+        duration(x.mean_ack()),
+        duration(x.max_ack()),
+        decimal(x.resends_per_hour()),
+        percent(x.fill_rate()),
+        decimal(x.improvement_bps()),
+        trend
+    )
+    .trim_end()
+    .to_string()
+}
+
+fn header(first: &str) -> String {
+    format!(
+        "{first:<32} {:>6} {:>7} {:>6} {:>8} {:>9} {:>9} {:>8} {:>6} {:>8} {}",
+        HEADER[0],
+        HEADER[1],
+        HEADER[2],
+        HEADER[3],
+        HEADER[4],
+        HEADER[5],
+        HEADER[6],
+        HEADER[7],
+        HEADER[8],
+        HEADER[9]
+    )
+}
+
+/// Changes of the last day from the days before it
+fn trend_of(trend: &ScoreTrend) -> String {
+    let Some(before) = &trend.before else {
+        return String::new();
+    };
+    let last = &trend.last;
+    let mut out = Vec::new();
+    if let (Some(now), Some(then)) = (last.mean_ack(), before.mean_ack()) {
+        let change = (now.as_secs_f64() / then.as_secs_f64() - 1.0) * 100.0;
+        out.push(format!("ack {change:+.0}%"));
+    }
+    if let (Some(now), Some(then)) = (last.reject_rate(), before.reject_rate()) {
+        out.push(format!("rej {:+.1}pt", (now - then) * 100.0));
+    }
+    if let (Some(now), Some(then)) = (last.uptime(), before.uptime()) {
+        out.push(format!("up {:+.1}pt", (now - then) * 100.0));
+    }
+    out.join(" ")
+}
+
+/// One line per session, over the window
+pub fn render_trends(trends: &[ScoreTrend], days: u32) -> String {
+    if trends.is_empty() {
+        return "no session yet\n".to_string();
+    }
+    let mut out = format!("last {days} day(s), UTC\n{}\n", header("session"));
+    for trend in trends {
+        let _ = writeln!(
+            out,
+            "{:<32} {}",
+            trend.window.session,
+            figures(&trend.window, &trend_of(trend))
+        );
+    }
+    out
+}
+
+/// One line per day, of one session
+pub fn render_days(scores: &[DayScore]) -> String {
+    let Some(first) = scores.first() else {
+        return "no scores for this session\n".to_string();
+    };
+    let mut out = format!("{}\n{}\n", first.session, header("date"));
+    for score in scores {
+        let _ = writeln!(out, "{:<32} {}", score.date.to_iso(), figures(score, ""));
+    }
+    out
+}
+
+/// Every day of every session, with the raw counts
+pub fn to_csv(scores: &[DayScore]) -> String {
+    let mut out = String::from(
+        "date,session,observed_secs,uptime_secs,logons,logouts,orders,ordered_qty,acks,\
+         ack_mean_us,ack_max_us,order_rejects,session_rejects,resends_in,resends_out,\
+         gap_fills,poss_dups,fills,filled_qty,fill_rate,improvement_bps\n",
+    );
+    for x in scores {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            x.date.to_iso(),
+            x.session,
+            x.observed_secs,
+            x.uptime_secs,
+            x.logons,
+            x.logouts,
+            x.orders,
+            x.ordered_qty,
+            x.acks,
+            x.mean_ack()
+                .map_or(String::new(), |x| x.as_micros().to_string()),
+            x.ack_max_us,
+            x.order_rejects,
+            x.session_rejects,
+            x.resends_in,
+            x.resends_out,
+            x.gap_fills,
+            x.poss_dups,
+            x.fills,
+            x.filled_qty,
+            x.fill_rate().map_or(String::new(), |x| format!("{x:.4}")),
+            x.improvement_bps()
+                .map_or(String::new(), |x| format!("{x:.2}"))
+        );
+    }
+    out
+}
+
+// =============================================================================
+// Flush Task
+// =============================================================================
+
+/// Write the scores to the state store every minute, for the life of the
+/// process
+pub async fn flush_task(scorecard: Arc<Scorecard>) {
+    let mut ticks = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        ticks.tick().await;
+        if let Err(err) = scorecard.flush() {
+            warn!(%err, "cannot keep the scorecards");
+        }
+    }
+}
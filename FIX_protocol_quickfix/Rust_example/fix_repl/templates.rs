@@ -0,0 +1,493 @@
+// =============================================================================
+// Message Templates
+// =============================================================================
+// Named messages with placeholders, so common workflows don't need raw tags
+// typed at the prompt:
+//
+//   FIX> send tmpl new_limit_order symbol=AAPL qty=100 px=150
+//
+// Templates are read at startup (--templates FILE|DIR) from TOML files, one
+// table per template. Keys are tag numbers, plus `sender`, `target` and the
+// optional `version` and `description`; values are strings (or bare numbers):
+//
+//   [new_limit_order]
+//   description = "Limit order, day"
+//   sender = "${sender:CLIENT}"
+//   target = "${target:EXCHANGE}"
+//   35 = "D"
+//   11 = "${id}"
+//   55 = "${symbol}"
+//   54 = "${side:1}"
+//   38 = "${qty}"
+//   40 = "2"
+//   44 = "${px}"
+//   59 = "0"
+//
+// `${name}` must be given on the command line, `${name:default}` may be.
+// `${id}`, when not given, is a fresh identifier (for ClOrdID, MDReqID...).
+// Fields are set in file order; repeating groups are not supported. Only this
+// subset of TOML is read: tables, `key = value` lines and `#` comments.
+//
+// With a state store (--state URL) the files loaded are kept in it under
+// "templates", by file name, and a start without --templates loads them
+// from there.
+// =============================================================================
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use quickfix::{FieldMap, Message};
+use trading::{
+    session::version::FixVersion,
+    store::{StateStore, StoreError},
+};
+
+use crate::{command_parser::SendTarget, remote::println};
+
+/// Placeholder filled with a fresh identifier when not given
+const ID_PLACEHOLDER: &str = "id";
+
+/// Store namespace of the template files, keyed by file name
+pub const TEMPLATES_NAMESPACE: &str = "templates";
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+pub enum TemplateError {
+    Io(PathBuf, io::Error),
+
+    /// A line of a template file could not be read
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: &'static str,
+    },
+
+    /// Two templates have the same name
+    Duplicate(String),
+
+    UnknownTemplate(String),
+
+    /// A placeholder without a default was not given
+    MissingValue { template: String, name: String },
+
+    /// A value was given that the template does not use (likely a typo)
+    UnusedValue { template: String, name: String },
+
+    /// The expanded message is not valid
+    Invalid { template: String, message: String },
+
+    /// The state store could not be read or written
+    Store(StoreError),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            TemplateError::Syntax {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+            TemplateError::Duplicate(name) => write!(f, "template {name} defined twice"),
+            TemplateError::UnknownTemplate(name) => write!(f, "no template named {name}"),
+            TemplateError::MissingValue { template, name } => {
+                write!(f, "template {template} needs {name}=VALUE")
+            }
+            TemplateError::UnusedValue { template, name } => {
+                write!(f, "template {template} has no placeholder {name}")
+            }
+            TemplateError::Invalid { template, message } => {
+                write!(f, "template {template}: {message}")
+            }
+            TemplateError::Store(err) => write!(f, "state store: {err}"),
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+// =============================================================================
+// Templates
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub description: Option<String>,
+    sender: Option<String>,
+    target: Option<String>,
+    version: Option<String>,
+
+    /// Tag and raw value, in file order
+    fields: Vec<(i32, String)>,
+}
+
+impl Template {
+    /// Placeholder names, with their default if any
+    pub fn placeholders(&self) -> BTreeMap<String, Option<String>> {
+        let mut out = BTreeMap::new();
+        for raw in self.raw_values() {
+            for (name, default) in placeholders(raw) {
+                out.entry(name.to_string())
+                    .or_insert_with(|| default.map(str::to_string));
+            }
+        }
+        out
+    }
+
+    fn raw_values(&self) -> impl Iterator<Item = &str> {
+        [&self.sender, &self.target, &self.version]
+            .into_iter()
+            .flatten()
+            .chain(self.fields.iter().map(|(_, value)| value))
+            .map(String::as_str)
+    }
+
+    /// Build the message and its destination from the given values
+    pub fn expand(
+        &self,
+        values: &[(String, String)],
+    ) -> Result<(Message, SendTarget), TemplateError> {
+        let placeholders = self.placeholders();
+        if let Some((name, _)) = values.iter().find(|(x, _)| !placeholders.contains_key(x)) {
+            return Err(TemplateError::UnusedValue {
+                template: self.name.clone(),
+                name: name.clone(),
+            });
+        }
+
+        // Resolve every placeholder once, so ${id} is the same everywhere
+        let mut resolved = BTreeMap::new();
+        for (name, default) in placeholders {
+            let given = values.iter().rev().find(|(x, _)| *x == name);
+            let value = match (given, default) {
+                (Some((_, value)), _) => value.clone(),
+                (None, Some(default)) => default,
+                (None, None) if name == ID_PLACEHOLDER => next_id(),
+                (None, None) => {
+                    return Err(TemplateError::MissingValue {
+                        template: self.name.clone(),
+                        name,
+                    })
+                }
+            };
+            resolved.insert(name, value);
+        }
+        let fill = |raw: &str| substitute(raw, &resolved);
+
+        let invalid = |message: String| TemplateError::Invalid {
+            template: self.name.clone(),
+            message,
+        };
+        let (Some(sender), Some(target)) = (&self.sender, &self.target) else {
+            return Err(invalid("sender and target are required".to_string()));
+        };
+        let version = match &self.version {
+            Some(raw) => Some(
+                fill(raw)
+                    .parse::<FixVersion>()
+                    .map_err(|err| invalid(err.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let mut msg = Message::new();
+        for (tag, raw) in &self.fields {
+            let value = fill(raw);
+            let result = if *tag == 35 {
+                msg.with_header_mut(|h| h.set_field(35, value.as_str()))
+            } else {
+                msg.set_field(*tag, value.as_str())
+            };
+            result.map_err(|err| invalid(format!("{tag}={value}: {err:?}")))?;
+        }
+
+        Ok((
+            msg,
+            SendTarget {
+                sender: fill(sender),
+                target: fill(target),
+                version,
+            },
+        ))
+    }
+}
+
+/// `(name, default)` of every `${...}` in a raw value
+fn placeholders(raw: &str) -> Vec<(&str, Option<&str>)> {
+    let mut out = Vec::new();
+    let mut rest = raw;
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let inner = &rest[start + 2..start + end];
+        out.push(match inner.split_once(':') {
+            Some((name, default)) => (name, Some(default)),
+            None => (inner, None),
+        });
+        rest = &rest[start + end + 1..];
+    }
+    out
+}
+
+fn substitute(raw: &str, values: &BTreeMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut rest = raw;
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let inner = &rest[start + 2..start + end];
+        let name = inner.split_once(':').map_or(inner, |(name, _)| name);
+        out.push_str(values.get(name).map_or("", String::as_str));
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Unique for the process and across restarts
+fn next_id() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs());
+    format!("T{started}-{}", NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+// =============================================================================
+// Library
+// =============================================================================
+
+#[derive(Debug, Default)]
+pub struct TemplateLibrary {
+    templates: BTreeMap<String, Template>,
+
+    /// Source of each file loaded, by file name, for the state store
+    sources: BTreeMap<String, String>,
+}
+
+impl TemplateLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a template file, or every `.toml` file of a directory
+    pub fn load(path: &Path) -> Result<Self, TemplateError> {
+        let mut library = Self::new();
+        let io_error = |err| TemplateError::Io(path.to_path_buf(), err);
+        if path.is_dir() {
+            let mut files: Vec<_> = fs::read_dir(path)
+                .map_err(io_error)?
+                .filter_map(|x| x.ok().map(|x| x.path()))
+                .filter(|x| x.extension().is_some_and(|x| x == "toml"))
+                .collect();
+            files.sort();
+            for file in files {
+                library.load_file(&file)?;
+            }
+        } else {
+            library.load_file(path)?;
+        }
+        Ok(library)
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<(), TemplateError> {
+        let source =
+            fs::read_to_string(path).map_err(|err| TemplateError::Io(path.to_path_buf(), err))?;
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |x| x.to_string_lossy().into_owned(),
+        );
+        self.add_source(path, name, source)
+    }
+
+    fn add_source(
+        &mut self,
+        path: &Path,
+        name: String,
+        source: String,
+    ) -> Result<(), TemplateError> {
+        for template in parse(path, &source)? {
+            if self.templates.contains_key(&template.name) {
+                return Err(TemplateError::Duplicate(template.name));
+            }
+            self.templates.insert(template.name.clone(), template);
+        }
+        self.sources.insert(name, source);
+        Ok(())
+    }
+
+    /// Load the template files kept in a state store
+    pub fn load_store(store: &dyn StateStore) -> Result<Self, TemplateError> {
+        let mut library = Self::new();
+        let sources = store
+            .scan(TEMPLATES_NAMESPACE)
+            .map_err(TemplateError::Store)?;
+        for (name, source) in sources {
+            // Errors point at the file as the store names it
+            let path = PathBuf::from(format!("{}/{TEMPLATES_NAMESPACE}/{name}", store.url()));
+            library.add_source(&path, name, source)?;
+        }
+        Ok(library)
+    }
+
+    /// Replace the template files kept in a state store with those loaded
+    pub fn save(&self, store: &dyn StateStore) -> Result<(), StoreError> {
+        for (name, _) in store.scan(TEMPLATES_NAMESPACE)? {
+            if !self.sources.contains_key(&name) {
+                store.delete(TEMPLATES_NAMESPACE, &name)?;
+            }
+        }
+        for (name, source) in &self.sources {
+            store.put(TEMPLATES_NAMESPACE, name, source)?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Template, TemplateError> {
+        self.templates
+            .get(name)
+            .ok_or_else(|| TemplateError::UnknownTemplate(name.to_string()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    /// One line per template: name, placeholders, description
+    pub fn print(&self) {
+        if self.templates.is_empty() {
+            println!("No templates loaded (start with --templates FILE|DIR or --state URL)");
+            return;
+        }
+        for template in self.templates.values() {
+            let params: Vec<_> = template
+                .placeholders()
+                .into_iter()
+                .map(|(name, default)| match default {
+                    Some(default) => format!("[{name}={default}]"),
+                    None if name == ID_PLACEHOLDER => format!("[{name}]"),
+                    None => format!("{name}=…"),
+                })
+                .collect();
+            println!("{:<20} {}", template.name, params.join(" "));
+            if let Some(description) = &template.description {
+                println!("{:<20} {description}", "");
+            }
+        }
+    }
+}
+
+// =============================================================================
+// File Parser
+// =============================================================================
+
+fn parse(path: &Path, source: &str) -> Result<Vec<Template>, TemplateError> {
+    let syntax = |line, message| TemplateError::Syntax {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+
+    let mut templates: Vec<Template> = Vec::new();
+    let mut seen_tags = BTreeSet::new();
+    for (index, line) in source.lines().enumerate() {
+        let line_no = index + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .map(str::trim)
+                .filter(|x| !x.is_empty() && !x.contains(char::is_whitespace))
+                .ok_or_else(|| syntax(line_no, "expected [template_name]"))?;
+            seen_tags.clear();
+            templates.push(Template {
+                name: name.to_string(),
+                description: None,
+                sender: None,
+                target: None,
+                version: None,
+                fields: Vec::new(),
+            });
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| syntax(line_no, "expected key = value"))?;
+        let key = key.trim().trim_matches('"');
+        let value = parse_value(value.trim())
+            .ok_or_else(|| syntax(line_no, "invalid string (unterminated or trailing text)"))?;
+        let template = templates
+            .last_mut()
+            .ok_or_else(|| syntax(line_no, "key outside of a [template] table"))?;
+
+        match key {
+            "description" => template.description = Some(value),
+            "sender" => template.sender = Some(value),
+            "target" => template.target = Some(value),
+            "version" => template.version = Some(value),
+            tag => {
+                let tag: i32 = tag.parse().ok().filter(|x| *x > 0).ok_or_else(|| {
+                    syntax(line_no, "key must be a tag, sender, target, version or description")
+                })?;
+                if !seen_tags.insert(tag) {
+                    return Err(syntax(line_no, "tag set twice (no repeating groups)"));
+                }
+                template.fields.push((tag, value));
+            }
+        }
+    }
+    Ok(templates)
+}
+
+/// A `#` outside of a string starts a comment
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// A quoted string (with \" and \\ escapes) or a bare value
+fn parse_value(raw: &str) -> Option<String> {
+    let Some(quoted) = raw.strip_prefix('"') else {
+        return Some(raw.to_string());
+    };
+
+    let mut out = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return chars.as_str().trim().is_empty().then_some(out),
+            '\\' => out.push(chars.next()?),
+            c => out.push(c),
+        }
+    }
+    None
+}
@@ -0,0 +1,785 @@
+// =============================================================================
+// Live State for Watch Expressions
+// =============================================================================
+// `watch` repaints a small view every N ms until Enter is pressed:
+//
+//   watch positions [SYMBOL]      net position per symbol, from fills
+//   watch book SYMBOL [DEPTH]     top of the market data book
+//   watch session N               state of one session (index, label or CompID)
+//   watch quotes [SYMBOL]         quotes live (35=S), sent or received
+//   watch blotter [FILTERS]       latest fills, filtered (trading::oms::blotter)
+//
+// The views are built from the event bus: the event task (fix_app.rs) hands
+// every FixEvent to LiveState, which keeps just enough state to render them.
+// The shell only reads it (command_exec.rs), and so does the blotter
+// (blotter.rs), which also shows the last ExecutionReports kept here, and
+// the book export (book_export.rs). Fills also go to a trade blotter, which
+// `blotter` pages through and exports.
+// =============================================================================
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write as _,
+    sync::{Mutex, MutexGuard},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use trading::{
+    oms::blotter::{BlotterFilter, BlotterPage, TradeBlotter},
+    session::{
+        events::{group_field, FixEvent, FixMessage},
+        version::{FixVersion, FIXT_1_1},
+        Direction,
+    },
+    time::{parse_utc_timestamp, time_of_day},
+};
+
+/// Levels shown by `watch book`, `book` and --book-export when no depth is
+/// given
+pub const DEFAULT_DEPTH: usize = 5;
+
+/// Inbound ExecutionReports kept for the blotter
+const RECENT_EXECUTIONS: usize = 100;
+
+/// Fills shown by `watch blotter`
+const RECENT_TRADES: usize = 20;
+
+/// DefaultApplVerID as a version name when known (`9` -> `FIX.5.0SP2`)
+fn appl_ver_name(value: &str) -> String {
+    FixVersion::from_appl_ver_id(value).map_or(value.to_string(), |x| x.to_string())
+}
+
+// =============================================================================
+// Watch Targets
+// =============================================================================
+
+/// What a `watch` command displays
+#[derive(Debug, Clone)]
+pub enum WatchTarget {
+    /// All symbols, or one
+    Positions(Option<String>),
+    Book {
+        symbol: String,
+        depth: usize,
+    },
+    /// 1-based index in creation order, session label or counterparty CompID
+    Session(String),
+    /// All symbols, or one
+    Quotes(Option<String>),
+    /// Latest fills matching a filter
+    Blotter(BlotterFilter),
+}
+
+// =============================================================================
+// Tracked State
+// =============================================================================
+
+#[derive(Debug, Default)]
+struct Position {
+    bought: f64,
+    sold: f64,
+    buy_value: f64,
+    sell_value: f64,
+    fills: u64,
+}
+
+impl Position {
+    fn net(&self) -> f64 {
+        self.bought - self.sold
+    }
+}
+
+/// Price levels of one side, best first
+#[derive(Debug, Default)]
+struct BookSide {
+    levels: Vec<(f64, f64)>,
+}
+
+impl BookSide {
+    /// Set the size at a price (0 removes the level)
+    fn set(&mut self, price: f64, size: f64, descending: bool) {
+        self.levels.retain(|(x, _)| *x != price);
+        if size > 0.0 {
+            self.levels.push((price, size));
+        }
+        self.levels.sort_by(|a, b| {
+            let order = a.0.total_cmp(&b.0);
+            if descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+    }
+}
+
+#[derive(Debug, Default)]
+struct Book {
+    bids: BookSide,
+    offers: BookSide,
+    updates: u64,
+}
+
+impl Book {
+    /// Apply one MDEntry; `delete` is MDUpdateAction (279) = 2
+    fn apply(&mut self, entry: &[(i32, String)], delete: bool) {
+        let number = |tag| group_field(entry, tag).and_then(|x| x.parse::<f64>().ok());
+        let Some(price) = number(270) else {
+            return;
+        };
+        let size = if delete {
+            0.0
+        } else {
+            number(271).unwrap_or(0.0)
+        };
+        match group_field(entry, 269) {
+            Some("0") => self.bids.set(price, size, true),
+            Some("1") => self.offers.set(price, size, false),
+            _ => {}
+        }
+    }
+}
+
+/// A Quote (35=S) seen on a session, either way
+///
+/// A newer quote on the same session for the same symbol replaces it, as
+/// does a QuoteCancel (35=Z) or a QuoteStatusReport (35=AI) saying it is
+/// gone; past its ValidUntilTime (62) it is no longer shown.
+#[derive(Debug)]
+struct Quote {
+    session: String,
+    direction: Direction,
+    quote_id: String,
+    symbol: String,
+    bid: (String, String),
+    offer: (String, String),
+
+    /// Unix nanoseconds
+    valid_until: Option<i64>,
+}
+
+impl Quote {
+    fn is_live(&self, now: i64) -> bool {
+        self.valid_until.is_none_or(|x| x > now)
+    }
+}
+
+#[derive(Debug)]
+struct SessionState {
+    label: String,
+    logged_on: bool,
+    received: u64,
+    sent: u64,
+    last_seq_in: String,
+    last_seq_out: String,
+    last_msg_type: String,
+    last_message: Option<Instant>,
+
+    /// DefaultApplVerID (1137) of the Logons, FIXT.1.1 sessions only
+    appl_ver_ours: String,
+    appl_ver_theirs: String,
+}
+
+impl SessionState {
+    fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            logged_on: false,
+            received: 0,
+            sent: 0,
+            last_seq_in: String::new(),
+            last_seq_out: String::new(),
+            last_msg_type: String::new(),
+            last_message: None,
+            appl_ver_ours: String::new(),
+            appl_ver_theirs: String::new(),
+        }
+    }
+}
+
+/// One inbound ExecutionReport, as listed by the blotter
+#[derive(Debug, Clone)]
+pub struct Execution {
+    pub received_at: Instant,
+    pub cl_ord_id: String,
+    pub symbol: String,
+    pub side: String,
+    pub exec_type: String,
+    pub ord_status: String,
+    pub last_qty: String,
+    pub last_px: String,
+}
+
+/// Net position of one symbol, for the blotter and `export positions`
+#[derive(Debug, Clone)]
+pub struct PositionSummary {
+    pub symbol: String,
+    pub bought: f64,
+    pub sold: f64,
+    pub net: f64,
+    pub avg_buy: Option<f64>,
+    pub avg_sell: Option<f64>,
+    pub fills: u64,
+}
+
+/// One line of session state, for the blotter
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub label: String,
+    pub logged_on: bool,
+    pub received: u64,
+    pub sent: u64,
+    pub last_message: Option<Instant>,
+}
+
+/// Top levels of one book, for `book` and the book export (book_export.rs)
+#[derive(Debug, Clone)]
+pub struct BookDepth {
+    /// (price, size), best first
+    pub bids: Vec<(f64, f64)>,
+    pub offers: Vec<(f64, f64)>,
+
+    /// Market data messages applied since the first
+    pub updates: u64,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    positions: HashMap<String, Position>,
+    books: HashMap<String, Book>,
+    /// In creation order, so `watch session 1` is stable
+    sessions: Vec<SessionState>,
+    /// Newest last
+    executions: VecDeque<Execution>,
+    /// In arrival order
+    quotes: Vec<Quote>,
+}
+
+impl Inner {
+    fn session(&mut self, label: &str) -> &mut SessionState {
+        let index = match self.sessions.iter().position(|x| x.label == label) {
+            Some(index) => index,
+            None => {
+                self.sessions.push(SessionState::new(label));
+                self.sessions.len() - 1
+            }
+        };
+        &mut self.sessions[index]
+    }
+}
+
+// =============================================================================
+// LiveState
+// =============================================================================
+// Written by the event task and read by the shell, hence the Mutex.
+// =============================================================================
+
+#[derive(Default)]
+pub struct LiveState {
+    inner: Mutex<Inner>,
+
+    /// Every fill received; locked on its own
+    trades: TradeBlotter,
+}
+
+impl LiveState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update the state with one event from the bus
+    pub fn on_event(&self, event: &FixEvent) {
+        let mut inner = self.lock();
+        match event {
+            FixEvent::Created { session } => {
+                inner.session(session);
+            }
+            FixEvent::Logon { session } => inner.session(session).logged_on = true,
+            FixEvent::Logout { session } => inner.session(session).logged_on = false,
+            FixEvent::Message(msg) => {
+                let state = inner.session(&msg.session);
+                state.last_msg_type = msg.msg_type().to_string();
+                state.last_message = Some(Instant::now());
+                let appl_ver = msg.get(1137).filter(|_| msg.msg_type() == "A");
+                match msg.direction {
+                    Direction::Inbound => {
+                        state.received += 1;
+                        state.last_seq_in = msg.seq_num().to_string();
+                        if let Some(appl_ver) = appl_ver {
+                            state.appl_ver_theirs = appl_ver_name(appl_ver);
+                        }
+                    }
+                    Direction::Outbound => {
+                        state.sent += 1;
+                        state.last_seq_out = msg.seq_num().to_string();
+                        if let Some(appl_ver) = appl_ver {
+                            state.appl_ver_ours = appl_ver_name(appl_ver);
+                        }
+                    }
+                }
+
+                // Quotes are tracked both ways: the REPL may be quoting
+                if !msg.admin {
+                    match msg.msg_type() {
+                        "S" => inner.on_quote(msg),
+                        "Z" => inner.on_quote_cancel(msg),
+                        "AI" => inner.on_quote_status(msg),
+                        _ => {}
+                    }
+                }
+                if msg.direction == Direction::Inbound && !msg.admin {
+                    match msg.msg_type() {
+                        "8" => {
+                            inner.on_execution(msg);
+                            inner.on_fill(msg);
+                            self.trades.on_execution_report(msg);
+                        }
+                        "W" => inner.on_snapshot(msg),
+                        "X" => inner.on_incremental(msg),
+                        _ => {}
+                    }
+                }
+            }
+            FixEvent::News(_) => {}
+        }
+    }
+
+    // =========================================================================
+    // Views
+    // =========================================================================
+
+    /// Text of a watch view, repainted by the shell
+    pub fn render(&self, target: &WatchTarget) -> String {
+        let inner = self.lock();
+        match target {
+            WatchTarget::Positions(symbol) => inner.render_positions(symbol.as_deref()),
+            WatchTarget::Book { symbol, depth } => inner.render_book(symbol, *depth),
+            WatchTarget::Session(selector) => inner.render_session(selector),
+            WatchTarget::Quotes(symbol) => inner.render_quotes(symbol.as_deref()),
+            WatchTarget::Blotter(filter) => {
+                render_trades(&self.trades.page(filter, 0, RECENT_TRADES), filter)
+            }
+        }
+    }
+
+    /// Positions of every symbol traded, by symbol
+    pub fn positions(&self) -> Vec<PositionSummary> {
+        let average = |value: f64, quantity: f64| (quantity > 0.0).then(|| value / quantity);
+        let mut out: Vec<_> = self
+            .lock()
+            .positions
+            .iter()
+            .map(|(symbol, x)| PositionSummary {
+                symbol: symbol.clone(),
+                bought: x.bought,
+                sold: x.sold,
+                net: x.net(),
+                avg_buy: average(x.buy_value, x.bought),
+                avg_sell: average(x.sell_value, x.sold),
+                fills: x.fills,
+            })
+            .collect();
+        out.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        out
+    }
+
+    /// Every fill received, for `blotter`
+    pub fn trades(&self) -> &TradeBlotter {
+        &self.trades
+    }
+
+    /// The last `count` ExecutionReports received, newest first
+    pub fn recent_executions(&self, count: usize) -> Vec<Execution> {
+        self.lock().executions.iter().rev().take(count).cloned().collect()
+    }
+
+    /// Every session seen, in creation order
+    pub fn sessions(&self) -> Vec<SessionSummary> {
+        self.lock()
+            .sessions
+            .iter()
+            .map(|x| SessionSummary {
+                label: x.label.clone(),
+                logged_on: x.logged_on,
+                received: x.received,
+                sent: x.sent,
+                last_message: x.last_message,
+            })
+            .collect()
+    }
+
+    /// The top `depth` levels of a symbol's book, if market data was seen
+    pub fn book(&self, symbol: &str, depth: usize) -> Option<BookDepth> {
+        let inner = self.lock();
+        let book = inner.books.get(symbol)?;
+        let top = |side: &BookSide| side.levels.iter().take(depth).copied().collect();
+        Some(BookDepth {
+            bids: top(&book.bids),
+            offers: top(&book.offers),
+            updates: book.updates,
+        })
+    }
+
+    /// Mid of a symbol's book, or its one side quoted
+    pub fn mid(&self, symbol: &str) -> Option<f64> {
+        let book = self.book(symbol, 1)?;
+        match (book.bids.first(), book.offers.first()) {
+            (Some(bid), Some(offer)) => Some((bid.0 + offer.0) / 2.0),
+            (Some(best), None) | (None, Some(best)) => Some(best.0),
+            (None, None) => None,
+        }
+    }
+
+    /// Symbols with a book, sorted
+    pub fn book_symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<_> = self.lock().books.keys().cloned().collect();
+        symbols.sort();
+        symbols
+    }
+
+    /// BeginStrings of the sessions between two CompIDs, as seen so far
+    pub fn begin_strings(&self, sender: &str, target: &str) -> Vec<String> {
+        let comp_ids = format!(":{sender}->{target}");
+        self.lock()
+            .sessions
+            .iter()
+            .filter_map(|x| x.label.strip_suffix(&comp_ids))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Label of the session a `watch session` selector designates
+    ///
+    /// Also used by the commands that take a session (test_request, resend).
+    pub fn resolve_session(&self, selector: &str) -> Option<String> {
+        self.lock().find_session(selector).map(|x| x.label.clone())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().expect("live state lock poisoned")
+    }
+}
+
+impl Inner {
+    fn on_execution(&mut self, msg: &FixMessage) {
+        let field = |tag| msg.get(tag).unwrap_or_default().to_string();
+        if self.executions.len() == RECENT_EXECUTIONS {
+            self.executions.pop_front();
+        }
+        self.executions.push_back(Execution {
+            received_at: Instant::now(),
+            cl_ord_id: field(11),
+            symbol: field(55),
+            side: field(54),
+            exec_type: field(150),
+            ord_status: field(39),
+            last_qty: field(32),
+            last_px: field(31),
+        });
+    }
+
+    /// Fills are ExecutionReports with LastQty (32) > 0
+    fn on_fill(&mut self, msg: &FixMessage) {
+        let number = |tag| msg.get(tag).and_then(|x| x.parse::<f64>().ok());
+        let (Some(symbol), Some(quantity)) = (msg.get(55), number(32)) else {
+            return;
+        };
+        if quantity <= 0.0 {
+            return;
+        }
+        let value = quantity * number(31).unwrap_or(0.0);
+
+        let position = self.positions.entry(symbol.to_string()).or_default();
+        position.fills += 1;
+        match msg.get(54) {
+            Some("1") => {
+                position.bought += quantity;
+                position.buy_value += value;
+            }
+            Some("2") => {
+                position.sold += quantity;
+                position.sell_value += value;
+            }
+            _ => {}
+        }
+    }
+
+    /// 35=W replaces the whole book of its symbol
+    fn on_snapshot(&mut self, msg: &FixMessage) {
+        let Some(symbol) = msg.get(55) else {
+            return;
+        };
+        let updates = self.books.get(symbol).map_or(0, |x| x.updates);
+        let mut book = Book {
+            updates: updates + 1,
+            ..Book::default()
+        };
+        for entry in msg.groups(269) {
+            book.apply(entry, false);
+        }
+        self.books.insert(symbol.to_string(), book);
+    }
+
+    /// 35=X carries the symbol and the action in each entry
+    fn on_incremental(&mut self, msg: &FixMessage) {
+        for entry in msg.groups(279) {
+            let Some(symbol) = group_field(entry, 55).or_else(|| msg.get(55)) else {
+                continue;
+            };
+            let book = self.books.entry(symbol.to_string()).or_default();
+            book.apply(entry, group_field(entry, 279) == Some("2"));
+            book.updates += 1;
+        }
+    }
+
+    /// 35=S replaces the quote of its session for the symbol
+    fn on_quote(&mut self, msg: &FixMessage) {
+        let (Some(quote_id), Some(symbol)) = (msg.get(117), msg.get(55)) else {
+            return;
+        };
+        let field = |tag| msg.get(tag).unwrap_or_default().to_string();
+        self.quotes
+            .retain(|x| x.session != msg.session || x.symbol != symbol);
+        self.quotes.push(Quote {
+            session: msg.session.clone(),
+            direction: msg.direction,
+            quote_id: quote_id.to_string(),
+            symbol: symbol.to_string(),
+            bid: (field(132), field(134)),
+            offer: (field(133), field(135)),
+            valid_until: msg.get(62).and_then(parse_utc_timestamp),
+        });
+    }
+
+    /// 35=Z by QuoteCancelType (298): 1 a symbol, 4 all, 5 one QuoteID
+    fn on_quote_cancel(&mut self, msg: &FixMessage) {
+        let session = &msg.session;
+        match (msg.get(298), msg.get(55), msg.get(117)) {
+            (Some("1"), Some(symbol), _) => self
+                .quotes
+                .retain(|x| x.session != *session || x.symbol != symbol),
+            (Some("4"), ..) => self.quotes.retain(|x| x.session != *session),
+            (Some("5"), _, Some(quote_id)) => self
+                .quotes
+                .retain(|x| x.session != *session || x.quote_id != quote_id),
+            _ => {}
+        }
+    }
+
+    /// 35=AI with a QuoteStatus (297) other than accepted (0): gone
+    fn on_quote_status(&mut self, msg: &FixMessage) {
+        if let (Some(status), Some(quote_id)) = (msg.get(297), msg.get(117)) {
+            if status != "0" {
+                self.quotes
+                    .retain(|x| x.session != msg.session || x.quote_id != quote_id);
+            }
+        }
+    }
+
+    fn render_quotes(&self, symbol: Option<&str>) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_nanos() as i64);
+        let mut quotes: Vec<_> = self
+            .quotes
+            .iter()
+            .filter(|x| x.is_live(now) && symbol.is_none_or(|s| x.symbol == s))
+            .collect();
+        quotes.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        if quotes.is_empty() {
+            return "no live quotes\n".to_string();
+        }
+
+        let mut out = format!(
+            "{:<10} {:<12} {:>10} {:>12} | {:<12} {:<10} {:>7}  {}\n",
+            "symbol", "quote id", "bid size", "bid", "offer", "offer size", "valid", "session"
+        );
+        for quote in quotes {
+            let valid = quote.valid_until.map_or("-".to_string(), |x| {
+                format!("{}s", (x - now) / 1_000_000_000)
+            });
+            let direction = match quote.direction {
+                Direction::Inbound => "from",
+                Direction::Outbound => "to",
+            };
+            let _ = writeln!(
+                out,
+                "{:<10} {:<12} {:>10} {:>12} | {:<12} {:<10} {:>7}  {direction} {}",
+                quote.symbol,
+                quote.quote_id,
+                quote.bid.1,
+                quote.bid.0,
+                quote.offer.0,
+                quote.offer.1,
+                valid,
+                quote.session
+            );
+        }
+        out
+    }
+
+    fn render_positions(&self, symbol: Option<&str>) -> String {
+        let mut symbols: Vec<_> = self
+            .positions
+            .iter()
+            .filter(|(x, _)| symbol.is_none() || symbol == Some(x.as_str()))
+            .collect();
+        symbols.sort_by(|a, b| a.0.cmp(b.0));
+        if symbols.is_empty() {
+            return "no fills yet\n".to_string();
+        }
+
+        let average = |value: f64, quantity: f64| {
+            if quantity > 0.0 {
+                format!("{:.4}", value / quantity)
+            } else {
+                "-".to_string()
+            }
+        };
+        let mut out = format!(
+            "{:<10} {:>12} {:>12} {:>12} {:>12} {:>12} {:>6}\n",
+            "symbol", "net", "bought", "avg buy", "sold", "avg sell", "fills"
+        );
+        for (symbol, position) in symbols {
+            let _ = writeln!(
+                out,
+                "{:<10} {:>12} {:>12} {:>12} {:>12} {:>12} {:>6}",
+                symbol,
+                position.net(),
+                position.bought,
+                average(position.buy_value, position.bought),
+                position.sold,
+                average(position.sell_value, position.sold),
+                position.fills
+            );
+        }
+        out
+    }
+
+    fn render_book(&self, symbol: &str, depth: usize) -> String {
+        let Some(book) = self.books.get(symbol) else {
+            return format!("no market data for {symbol}\n");
+        };
+        let mut out = format!("{symbol}  ({} update(s))\n", book.updates);
+        let _ = writeln!(
+            out,
+            "{:>12} {:>12} | {:<12} {:<12}",
+            "bid size", "bid", "offer", "offer size"
+        );
+        for level in 0..depth {
+            let bid = book.bids.levels.get(level);
+            let offer = book.offers.levels.get(level);
+            if bid.is_none() && offer.is_none() {
+                break;
+            }
+            let cell = |x: Option<&(f64, f64)>, price: bool| {
+                x.map_or(String::new(), |(px, size)| {
+                    if price {
+                        px.to_string()
+                    } else {
+                        size.to_string()
+                    }
+                })
+            };
+            let _ = writeln!(
+                out,
+                "{:>12} {:>12} | {:<12} {:<12}",
+                cell(bid, false),
+                cell(bid, true),
+                cell(offer, true),
+                cell(offer, false)
+            );
+        }
+        out
+    }
+
+    /// Session by 1-based index, label or counterparty CompID
+    fn find_session(&self, selector: &str) -> Option<&SessionState> {
+        match selector.parse::<usize>() {
+            Ok(index) => index.checked_sub(1).and_then(|x| self.sessions.get(x)),
+            Err(_) => self
+                .sessions
+                .iter()
+                .find(|x| x.label == selector || x.label.ends_with(&format!("->{selector}"))),
+        }
+    }
+
+    fn render_session(&self, selector: &str) -> String {
+        let Some(session) = self.find_session(selector) else {
+            let mut out = format!("no session {selector}; known sessions:\n");
+            for (index, session) in self.sessions.iter().enumerate() {
+                let _ = writeln!(out, "  {} {}", index + 1, session.label);
+            }
+            return out;
+        };
+
+        let last_message = session
+            .last_message
+            .map_or("-".to_string(), |x| format!("{:?} ago", x.elapsed()));
+        let mut out = format!(
+            "session       {}\n\
+             logged on     {}\n\
+             received      {} (last seq {})\n\
+             sent          {} (last seq {})\n\
+             last message  {} {}\n",
+            session.label,
+            session.logged_on,
+            session.received,
+            session.last_seq_in,
+            session.sent,
+            session.last_seq_out,
+            session.last_msg_type,
+            last_message
+        );
+        if session.label.starts_with(FIXT_1_1) {
+            let show = |x: &str| if x.is_empty() { "-".to_string() } else { x.to_string() };
+            let _ = writeln!(
+                out,
+                "appl version  ours {}, theirs {}",
+                show(&session.appl_ver_ours),
+                show(&session.appl_ver_theirs)
+            );
+        }
+        out
+    }
+}
+
+// =============================================================================
+// Trade Blotter
+// =============================================================================
+
+/// A page of the trade blotter as a table, for `watch blotter` and `blotter`
+pub fn render_trades(page: &BlotterPage, filter: &BlotterFilter) -> String {
+    if page.trades.is_empty() {
+        return format!("no trades ({filter}, {} in all)\n", page.total);
+    }
+
+    let mut out = format!(
+        "trades {}-{} of {}, newest first ({filter})\n",
+        page.offset + 1,
+        page.offset + page.trades.len(),
+        page.total
+    );
+    let _ = writeln!(
+        out,
+        "{:<12} {:<10} {:<10} {:<4} {:>10} {:>12}  {:<14} {:<14} session",
+        "time", "account", "symbol", "side", "quantity", "price", "ClOrdID", "ExecID"
+    );
+    for trade in &page.trades {
+        let _ = writeln!(
+            out,
+            "{}.{:03} {:<10} {:<10} {:<4} {:>10} {:>12}  {:<14} {:<14} {}",
+            time_of_day(trade.time.div_euclid(1_000)),
+            trade.time.rem_euclid(1_000),
+            trade.account,
+            trade.symbol,
+            trade.side.as_str(),
+            trade.quantity,
+            trade.price,
+            trade.cl_ord_id,
+            trade.exec_id,
+            trade.session
+        );
+    }
+    out
+}
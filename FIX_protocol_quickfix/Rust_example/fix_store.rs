@@ -0,0 +1,226 @@
+// =============================================================================
+// QuickFIX Rust Example: fix_store - File Store Maintenance
+// =============================================================================
+// The QuickFIX file store of each [SESSION] of a configuration file
+// (FileStorePath), looked after with the engine stopped, rather than with
+// shell scripts around dd and sed (trading::session::file_store):
+//
+//   verify    seqnums, header and body checked against each other
+//   compact   messages sent more than N days ago moved to a gzip archive,
+//             PREFIX.FIRST-LAST.gz, that zcat and zgrep read
+//   migrate   sessions and messages copied to the QuickFIX SQL store tables
+//             of a SQLite database (built with the `sqlite` feature)
+//
+//   >> FIX.4.4:CLIENT->EXCHANGE: 5230 message(s), next sender 5231, next target 4977
+//   >> FIX.4.4:CLIENT->EXCHANGE: archived 5100 to store/archive/FIX.4.4-CLIENT-EXCHANGE.1-5100.gz,
+//      kept 130 (1182310 -> 29844 bytes)
+//
+// compact and migrate leave a store verify finds a problem with as it is.
+// Exits with 1 when a store has a problem or an operation failed.
+//
+// Key Learning Points:
+// 1. What the QuickFIX file store keeps, file by file
+// 2. Why resends of archived messages turn into gap fills
+// 3. Moving from the file store to a SQL store without losing the sequence
+// =============================================================================
+
+use std::{
+    collections::HashSet,
+    env,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use trading::{
+    session::{
+        file_store::FileStore,
+        session_label,
+        settings::{FileSecrets, SettingsLoader},
+    },
+    time::unix_now,
+};
+
+const USAGE: &str = "usage: fix_store <verify|compact|migrate> <config_file> [--older-than DAYS] [--archive DIR] [--to DATABASE] [--secrets-dir DIR]";
+
+enum Operation {
+    Verify,
+
+    /// Cutoff, and archive directory (FileStorePath/archive when none)
+    Compact(i64, Option<PathBuf>),
+
+    /// SQLite database
+    Migrate(PathBuf),
+}
+
+// =============================================================================
+// Main Entry Point
+// =============================================================================
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut positional = Vec::new();
+    let mut loader = SettingsLoader::new();
+    let mut older_than = None;
+    let mut archive = None;
+    let mut database = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("{name} requires a value")))
+        };
+        match arg.as_str() {
+            "--older-than" => {
+                let days = value("--older-than");
+                match days.parse::<u32>() {
+                    Ok(days) if days > 0 => older_than = Some(days),
+                    _ => fail(&format!("--older-than: {days} is not a number of days")),
+                }
+            }
+            "--archive" => archive = Some(PathBuf::from(value("--archive"))),
+            "--to" => database = Some(PathBuf::from(value("--to"))),
+            "--secrets-dir" => {
+                let dir = value("--secrets-dir");
+                loader = loader.with_provider(FileSecrets::new(Path::new(&dir)));
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            flag if flag.starts_with('-') => fail(&format!("unknown option {flag}")),
+            _ => positional.push(arg),
+        }
+    }
+    let [command, config_file] = &positional[..] else {
+        fail("a command and a config file are required");
+    };
+    let operation = match command.as_str() {
+        "verify" => Operation::Verify,
+        "compact" => {
+            let days = older_than.unwrap_or_else(|| fail("compact requires --older-than DAYS"));
+            Operation::Compact(unix_now() - i64::from(days) * 86_400, archive)
+        }
+        "migrate" => Operation::Migrate(database.unwrap_or_else(|| fail("migrate requires --to"))),
+        other => fail(&format!("unknown command {other}")),
+    };
+
+    let config = loader.read(Path::new(config_file)).unwrap_or_else(|err| {
+        eprintln!("{config_file}: {err}");
+        exit(1);
+    });
+
+    let mut seen = HashSet::new();
+    let mut ok = true;
+    for block in config.sessions() {
+        let Ok(session_id) = block.session_id() else {
+            continue;
+        };
+        let label = session_label(&session_id);
+        let Some(dir) = block.get("FileStorePath") else {
+            println!(">> {label}: no FileStorePath, skipped");
+            continue;
+        };
+        let store = FileStore::for_session(Path::new(dir), &session_id);
+        if !seen.insert(store.path("seqnums")) {
+            continue;
+        }
+        if !store.exists() {
+            println!(">> {label}: no store in {dir}");
+            continue;
+        }
+        ok &= match &operation {
+            Operation::Verify => verify(&label, &store),
+            Operation::Compact(before, archive) => {
+                let archive = archive
+                    .clone()
+                    .unwrap_or_else(|| Path::new(dir).join("archive"));
+                compact(&label, &store, *before, &archive)
+            }
+            Operation::Migrate(database) => match store.migrate_to_sqlite(&session_id, database) {
+                Ok(count) => {
+                    println!(
+                        ">> {label}: {count} message(s) copied to {}",
+                        database.display()
+                    );
+                    true
+                }
+                Err(err) => {
+                    println!(">> {label}: {err}");
+                    false
+                }
+            },
+        };
+    }
+    if !ok {
+        exit(1);
+    }
+}
+
+fn verify(label: &str, store: &FileStore) -> bool {
+    match store.verify() {
+        Ok(verification) => {
+            println!(
+                ">> {label}: {} message(s), next sender {}, next target {}",
+                verification.messages.len(),
+                verification.next_sender,
+                verification.next_target
+            );
+            for issue in &verification.issues {
+                println!(">>   {issue}");
+            }
+            verification.is_ok()
+        }
+        Err(err) => {
+            println!(">> {label}: {err}");
+            false
+        }
+    }
+}
+
+fn compact(label: &str, store: &FileStore, before: i64, archive: &Path) -> bool {
+    match store.compact(before, archive) {
+        Ok(compaction) => {
+            match &compaction.archive {
+                Some(path) => println!(
+                    ">> {label}: archived {} to {}, kept {} ({} -> {} bytes)",
+                    compaction.archived,
+                    path.display(),
+                    compaction.kept,
+                    compaction.body_before,
+                    compaction.body_after
+                ),
+                None => println!(">> {label}: nothing to archive, kept {}", compaction.kept),
+            }
+            true
+        }
+        Err(err) => {
+            println!(">> {label}: {err}");
+            false
+        }
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    eprintln!("{USAGE}");
+    exit(2);
+}
+
+// =============================================================================
+// Usage Examples
+// =============================================================================
+//
+// Check the stores of a configuration, engine stopped:
+//   cargo run --example fix_store -- verify initiator.cfg
+//
+// Archive what was sent more than 30 days ago, next to the store:
+//   cargo run --example fix_store -- compact initiator.cfg --older-than 30
+//
+// Read an archive back:
+//   zcat store/archive/FIX.4.4-CLIENT-EXCHANGE.1-5100.gz > old.log
+//   cargo run --example fixtail -- old.log --from-start --no-follow
+//
+// Move to a SQL store:
+//   cargo run --example fix_store --features sqlite -- migrate initiator.cfg --to fix.db
+//
+// =============================================================================
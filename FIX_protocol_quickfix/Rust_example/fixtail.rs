@@ -0,0 +1,599 @@
+// =============================================================================
+// QuickFIX Rust Example: fixtail - Live FIX Log Viewer
+// =============================================================================
+// A tcpdump-style viewer for QuickFIX message logs. It follows a
+// `messages.log` file in real time (like `tail -f`), splits every message on
+// the SOH delimiter, decodes tag numbers to their field names and prints one
+// colorized line per message. Venue-defined tags and values are named too
+// when their dictionary is given (--dictionary FILE), and values that do not
+// match the dictionary are flagged.
+//
+// With --pcap it reads a packet capture instead (trading::pcap): the TCP
+// streams are put back in order and the FIX messages cut out of them, so
+// what went over the wire can be set against what the engine logged. `-`
+// reads the capture from stdin, live from tcpdump; --port keeps the
+// connections of the FIX ports.
+//
+// With --journal it reads a wire journal (trading::session::journal, written
+// by fix_repl --journal): each message with its receive or send time to the
+// nanosecond, its session and direction, and for inbound ones the time since
+// their SendingTime (52). An encrypted journal is decrypted with the key of
+// --journal-key FILE, or of TRADING_STORE_KEY.
+//
+// Key Learning Points:
+// 1. Anatomy of a raw FIX message on the wire (TAG=VALUE<SOH>...)
+// 2. Admin (session-level) vs application (business) message types
+// 3. Following a growing file with plain std I/O (no external crates)
+// 4. Simple predicate filtering on decoded fields
+// 5. TCP reassembly: a message split across segments, sent twice, or
+//    behind a lost one
+// =============================================================================
+
+use std::{
+    env,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    process::exit,
+    thread,
+    time::Duration,
+};
+
+#[cfg(feature = "encryption")]
+use trading::store::encrypted::EncryptionKey;
+use trading::{
+    pcap::{tcp_segment, FixStreams, PcapError, PcapReader},
+    session::{
+        dictionary::Dictionary,
+        journal::{JournalError, JournalReader},
+        Direction,
+    },
+    time::{time_of_day, Date},
+};
+
+/// FIX field delimiter (Start Of Header, ASCII 0x01)
+const SOH: char = '\x01';
+
+/// How long to sleep when the log has no new data
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// ANSI escape sequences used for colorized output
+const COLOR_ADMIN: &str = "\x1b[2;36m"; // dim cyan for session-level traffic
+const COLOR_APP: &str = "\x1b[1;32m"; // bold green for business messages
+const COLOR_TAG: &str = "\x1b[33m"; // yellow for field names
+const COLOR_BAD: &str = "\x1b[1;31m"; // bold red for values the dictionary rejects
+const COLOR_RESET: &str = "\x1b[0m";
+
+// =============================================================================
+// Message Types
+// =============================================================================
+// Tag numbers are named by the dictionary (trading::session::dictionary):
+// the built-in fields, plus the venue fields of the --dictionary files.
+// Unknown tags are printed as numbers.
+// =============================================================================
+
+/// Human readable name for the most common MsgType (tag 35) values
+fn msg_type_name(msg_type: &str) -> &'static str {
+    match msg_type {
+        "0" => "Heartbeat",
+        "1" => "TestRequest",
+        "2" => "ResendRequest",
+        "3" => "Reject",
+        "4" => "SequenceReset",
+        "5" => "Logout",
+        "A" => "Logon",
+        "8" => "ExecutionReport",
+        "9" => "OrderCancelReject",
+        "D" => "NewOrderSingle",
+        "F" => "OrderCancelRequest",
+        "G" => "OrderCancelReplaceRequest",
+        "H" => "OrderStatusRequest",
+        "V" => "MarketDataRequest",
+        "W" => "MarketDataSnapshotFullRefresh",
+        "X" => "MarketDataIncrementalRefresh",
+        "Y" => "MarketDataRequestReject",
+        "j" => "BusinessMessageReject",
+        _ => "Unknown",
+    }
+}
+
+/// Admin (session-level) messages are the ones handled by the engine itself
+/// and routed through `on_msg_to_admin` / `on_msg_from_admin`.
+fn is_admin(msg_type: &str) -> bool {
+    matches!(msg_type, "0" | "1" | "2" | "3" | "4" | "5" | "A")
+}
+
+// =============================================================================
+// Filters
+// =============================================================================
+// Predicates are given as command-line arguments:
+//   --type D,8        keep only NewOrderSingle and ExecutionReport
+//   --where 55=AAPL   tag 55 must equal AAPL
+//   --where 39!=8     tag 39 must not be 8 (OrdStatus=Rejected)
+//   --where 11        tag 11 must be present
+// All predicates must match for a message to be printed. A tag can be given
+// by its name in the dictionary (--where Symbol=AAPL).
+// =============================================================================
+
+#[derive(Debug)]
+enum TagPredicate {
+    /// Tag is present, whatever its value
+    Present(i32),
+    /// Tag is present and equals the value
+    Equals(i32, String),
+    /// Tag is absent or differs from the value
+    NotEquals(i32, String),
+}
+
+impl TagPredicate {
+    fn parse(source: &str, dictionary: &Dictionary) -> Option<Self> {
+        if let Some((tag, value)) = source.split_once("!=") {
+            return Some(Self::NotEquals(dictionary.resolve(tag)?, value.to_string()));
+        }
+        if let Some((tag, value)) = source.split_once('=') {
+            return Some(Self::Equals(dictionary.resolve(tag)?, value.to_string()));
+        }
+        Some(Self::Present(dictionary.resolve(source)?))
+    }
+
+    fn matches(&self, fields: &[(i32, &str)]) -> bool {
+        let lookup = |tag: i32| fields.iter().find(|(t, _)| *t == tag).map(|(_, v)| *v);
+        match self {
+            Self::Present(tag) => lookup(*tag).is_some(),
+            Self::Equals(tag, value) => lookup(*tag) == Some(value.as_str()),
+            Self::NotEquals(tag, value) => lookup(*tag) != Some(value.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    path: String,
+    msg_types: Vec<String>,
+    /// As given: tag names can only be resolved once the dictionary is loaded
+    predicates: Vec<String>,
+    dictionaries: Vec<PathBuf>,
+    /// Capture to read instead of a log, `-` for stdin
+    pcap: Option<String>,
+    /// TCP ports of the FIX connections in the capture; every one when empty
+    ports: Vec<u16>,
+    /// Wire journal to read instead of a log
+    journal: Option<String>,
+    /// Key file of an encrypted journal
+    journal_key: Option<PathBuf>,
+    from_start: bool,
+    follow: bool,
+    color: bool,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options {
+            follow: true,
+            color: true,
+            ..Default::default()
+        };
+        let mut args = args.iter().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--type" | "-t" => {
+                    let value = args.next().ok_or("--type requires a value")?;
+                    options
+                        .msg_types
+                        .extend(value.split(',').map(|x| x.trim().to_string()));
+                }
+                "--where" | "-w" => {
+                    let value = args.next().ok_or("--where requires a value")?;
+                    options.predicates.push(value.to_string());
+                }
+                "--dictionary" | "-d" => {
+                    let value = args.next().ok_or("--dictionary requires a file")?;
+                    options.dictionaries.push(PathBuf::from(value));
+                }
+                "--pcap" => {
+                    let value = args.next().ok_or("--pcap requires a file or -")?;
+                    options.pcap = Some(value.to_string());
+                }
+                "--journal" => {
+                    let value = args.next().ok_or("--journal requires a file")?;
+                    options.journal = Some(value.to_string());
+                }
+                "--journal-key" => {
+                    let value = args.next().ok_or("--journal-key requires a file")?;
+                    options.journal_key = Some(PathBuf::from(value));
+                }
+                "--port" | "-p" => {
+                    let value = args.next().ok_or("--port requires a value")?;
+                    for port in value.split(',') {
+                        let port = port
+                            .trim()
+                            .parse()
+                            .map_err(|_| format!("invalid port: {port}"))?;
+                        options.ports.push(port);
+                    }
+                }
+                "--from-start" => options.from_start = true,
+                "--no-follow" => options.follow = false,
+                "--no-color" => options.color = false,
+                path if !path.starts_with('-') && options.path.is_empty() => {
+                    options.path = path.to_string();
+                }
+                other => return Err(format!("unknown argument: {other}")),
+            }
+        }
+
+        let sources = [
+            !options.path.is_empty(),
+            options.pcap.is_some(),
+            options.journal.is_some(),
+        ];
+        match sources.iter().filter(|x| **x).count() {
+            0 => return Err("missing log file".to_string()),
+            1 => {}
+            _ => return Err("a log file, --pcap or --journal, one of them".to_string()),
+        }
+        Ok(options)
+    }
+
+    /// Predicates, with the tag names of the dictionary resolved
+    fn predicates(&self, dictionary: &Dictionary) -> Result<Vec<TagPredicate>, String> {
+        self.predicates
+            .iter()
+            .map(|x| {
+                TagPredicate::parse(x, dictionary).ok_or_else(|| format!("invalid predicate: {x}"))
+            })
+            .collect()
+    }
+
+    fn accepts(&self, predicates: &[TagPredicate], fields: &[(i32, &str)]) -> bool {
+        let msg_type = fields.iter().find(|(t, _)| *t == 35).map(|(_, v)| *v);
+        let type_ok = self.msg_types.is_empty()
+            || msg_type.is_some_and(|x| self.msg_types.iter().any(|t| t == x));
+        type_ok && predicates.iter().all(|p| p.matches(fields))
+    }
+}
+
+// =============================================================================
+// Decoding
+// =============================================================================
+
+/// Split a log line into (prefix, fields)
+///
+/// QuickFIX `FileLog` writes one message per line, optionally preceded by a
+/// timestamp (`20240102-10:00:00.000 : 8=FIX.4.4<SOH>9=...`). Everything
+/// before `8=` is returned as the prefix.
+fn decode_line(line: &str) -> Option<(&str, Vec<(i32, &str)>)> {
+    let start = line.find("8=FIX")?;
+    let (prefix, raw) = line.split_at(start);
+
+    let fields = raw
+        .trim_end_matches(['\r', '\n'])
+        .split(SOH)
+        .filter(|x| !x.is_empty())
+        .filter_map(|field| {
+            let (tag, value) = field.split_once('=')?;
+            Some((tag.parse().ok()?, value))
+        })
+        .collect();
+
+    Some((prefix.trim_end_matches([' ', ':']), fields))
+}
+
+/// One line: prefix, message type and every field by name
+///
+/// Values the dictionary names are followed by their name, `Side=1(Buy)`;
+/// values it rejects are shown in red, or followed by `!` without color.
+fn print_message(
+    out: &mut impl Write,
+    dictionary: &Dictionary,
+    prefix: &str,
+    fields: &[(i32, &str)],
+    color: bool,
+) -> io::Result<()> {
+    let msg_type = fields
+        .iter()
+        .find(|(t, _)| *t == 35)
+        .map(|(_, v)| *v)
+        .unwrap_or("?");
+
+    let (head_color, tag_color, reset) = match (color, is_admin(msg_type)) {
+        (false, _) => ("", "", ""),
+        (true, true) => (COLOR_ADMIN, COLOR_TAG, COLOR_RESET),
+        (true, false) => (COLOR_APP, COLOR_TAG, COLOR_RESET),
+    };
+
+    if !prefix.is_empty() {
+        write!(out, "{prefix} ")?;
+    }
+    write!(out, "{head_color}[{}]{reset}", msg_type_name(msg_type))?;
+
+    for &(tag, value) in fields {
+        match dictionary.name(tag) {
+            Some(name) => write!(out, " {tag_color}{name}{reset}=")?,
+            None => write!(out, " {tag_color}{tag}{reset}=")?,
+        }
+        match (dictionary.validate(tag, value), color) {
+            (Ok(()), _) => write!(out, "{value}")?,
+            (Err(_), true) => write!(out, "{COLOR_BAD}{value}{COLOR_RESET}")?,
+            (Err(_), false) => write!(out, "{value}!")?,
+        }
+        if let Some(name) = dictionary.value_name(tag, value) {
+            write!(out, "({name})")?;
+        }
+    }
+    writeln!(out)
+}
+
+// =============================================================================
+// Packet Captures
+// =============================================================================
+
+/// `YYYYMMDD-HH:MM:SS.ffffff` of Unix nanoseconds, as in the message logs
+fn capture_time(nanos: i64) -> String {
+    let seconds = nanos.div_euclid(1_000_000_000);
+    format!(
+        "{}-{}.{:06}",
+        Date::from_unix(seconds).to_fix(),
+        time_of_day(seconds),
+        nanos.rem_euclid(1_000_000_000) / 1_000
+    )
+}
+
+/// Print the FIX messages of a capture, prefixed with their time and
+/// connection, until its end (or that of tcpdump's output)
+fn read_capture(
+    options: &Options,
+    dictionary: &Dictionary,
+    predicates: &[TagPredicate],
+    path: &str,
+) -> Result<(), PcapError> {
+    let input: Box<dyn Read> = match path {
+        "-" => Box::new(io::stdin().lock()),
+        path => Box::new(BufReader::new(File::open(path)?)),
+    };
+    let mut capture = PcapReader::new(input)?;
+    let mut streams = FixStreams::new().with_ports(&options.ports);
+    let mut stdout = io::stdout().lock();
+
+    while let Some(frame) = capture.next_frame()? {
+        let Some(segment) = tcp_segment(&frame) else {
+            continue;
+        };
+        for message in streams.on_segment(&segment) {
+            let line = String::from_utf8_lossy(&message.raw);
+            let Some((_, fields)) = decode_line(&line) else {
+                continue;
+            };
+            if options.accepts(predicates, &fields) {
+                let prefix = format!(
+                    "{} {} > {}",
+                    capture_time(message.time),
+                    message.src,
+                    message.dst
+                );
+                print_message(&mut stdout, dictionary, &prefix, &fields, options.color)?;
+            }
+        }
+    }
+
+    if streams.gaps() > 0 {
+        eprintln!(
+            "TCP stream gaps: {}, messages lost with the segments missing from the capture",
+            streams.gaps()
+        );
+    }
+    Ok(())
+}
+
+// =============================================================================
+// Wire Journals
+// =============================================================================
+
+/// `+1.234ms` from nanoseconds
+fn lag(nanos: i64) -> String {
+    format!("{:+.3}ms", nanos as f64 / 1_000_000.0)
+}
+
+/// A journal, to be decrypted with the key of --journal-key or
+/// TRADING_STORE_KEY if encrypted
+#[cfg(feature = "encryption")]
+fn open_journal(options: &Options, path: &str) -> Result<JournalReader<BufReader<File>>, String> {
+    let key = EncryptionKey::load(options.journal_key.as_deref()).map_err(|err| err.to_string())?;
+    let journal = match key {
+        Some(key) => JournalReader::open_with_key(path, &key),
+        None => JournalReader::open(path),
+    };
+    journal.map_err(|err| err.to_string())
+}
+
+/// A journal in clear: without the `encryption` feature, there is no key
+#[cfg(not(feature = "encryption"))]
+fn open_journal(_options: &Options, path: &str) -> Result<JournalReader<BufReader<File>>, String> {
+    JournalReader::open(path).map_err(|err| err.to_string())
+}
+
+/// Print the messages of a journal, prefixed with their time, session and
+/// direction, and the lag behind SendingTime of the inbound ones
+fn read_journal(
+    options: &Options,
+    dictionary: &Dictionary,
+    predicates: &[TagPredicate],
+    journal: JournalReader<BufReader<File>>,
+) -> Result<(), JournalError> {
+    let mut stdout = io::stdout().lock();
+
+    for record in journal {
+        let record = record?;
+        let line = record.text();
+        let Some((_, fields)) = decode_line(&line) else {
+            continue;
+        };
+        if !options.accepts(predicates, &fields) {
+            continue;
+        }
+        let mut prefix = format!(
+            "{} {} {}",
+            capture_time(record.at),
+            record.session,
+            record.direction.as_label()
+        );
+        if record.direction == Direction::Inbound {
+            if let Some(nanos) = record.sending_lag() {
+                prefix = format!("{prefix} {}", lag(nanos));
+            }
+        }
+        print_message(&mut stdout, dictionary, &prefix, &fields, options.color)?;
+    }
+    Ok(())
+}
+
+// =============================================================================
+// Main Entry Point
+// =============================================================================
+
+fn main() -> io::Result<()> {
+    let args: Vec<_> = env::args().collect();
+
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("Bad program usage: {err}");
+            eprintln!(
+                "Usage: {} <log-file | --pcap FILE|- [--port P1,P2] | --journal FILE [--journal-key FILE]> \
+                 [--type T1,T2] \
+                 [--where TAG[=|!=]VALUE] [--dictionary FILE] [--from-start] [--no-follow] \
+                 [--no-color]",
+                args[0]
+            );
+            exit(1);
+        }
+    };
+
+    // Venue fields, merged over the built-in ones in the order given
+    let dictionary = match Dictionary::with_files(&options.dictionaries) {
+        Ok(dictionary) => dictionary,
+        Err(err) => {
+            eprintln!("Cannot load the dictionary: {err}");
+            exit(1);
+        }
+    };
+    let predicates = match options.predicates(&dictionary) {
+        Ok(predicates) => predicates,
+        Err(err) => {
+            eprintln!("Bad program usage: {err}");
+            exit(1);
+        }
+    };
+
+    if let Some(path) = &options.pcap {
+        if let Err(err) = read_capture(&options, &dictionary, &predicates, path) {
+            eprintln!("Cannot read the capture: {err}");
+            exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = &options.journal {
+        let journal = match open_journal(&options, path) {
+            Ok(journal) => journal,
+            Err(err) => {
+                eprintln!("Cannot open the journal {path}: {err}");
+                exit(1);
+            }
+        };
+        if let Err(err) = read_journal(&options, &dictionary, &predicates, journal) {
+            eprintln!("Cannot read the journal: {err}");
+            exit(1);
+        }
+        return Ok(());
+    }
+
+    let mut file = File::open(&options.path)?;
+
+    // Like tail -f: skip existing content unless asked to replay it
+    if !options.from_start && options.follow {
+        file.seek(SeekFrom::End(0))?;
+    }
+
+    let mut reader = BufReader::new(file);
+    // Raw bytes: venue payloads are not always UTF-8, and one bad byte must
+    // not stop the tail
+    let mut buf = Vec::with_capacity(4096);
+    let mut stdout = io::stdout().lock();
+
+    loop {
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf)?;
+
+        if read == 0 {
+            if !options.follow {
+                break;
+            }
+
+            // Handle log rotation / truncation: restart from the beginning
+            // when the file became shorter than our current position
+            let position = reader.stream_position()?;
+            let length = reader.get_ref().metadata()?.len();
+            if length < position {
+                reader.seek(SeekFrom::Start(0))?;
+            }
+
+            stdout.flush()?;
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+
+        // A partially written line: wait for the rest of it
+        if !buf.ends_with(b"\n") && options.follow {
+            let position = reader.stream_position()? - read as u64;
+            reader.seek(SeekFrom::Start(position))?;
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+
+        let line = String::from_utf8_lossy(&buf);
+        let Some((prefix, fields)) = decode_line(&line) else {
+            continue;
+        };
+
+        if options.accepts(&predicates, &fields) {
+            print_message(&mut stdout, &dictionary, prefix, &fields, options.color)?;
+        }
+    }
+
+    Ok(())
+}
+
+// =============================================================================
+// Usage Examples
+// =============================================================================
+//
+// Follow a live session log:
+//   cargo run --example fixtail -- ./fixlog/FIX.4.4-CLIENT-EXCHANGE.messages.log
+//
+// Replay the whole file and only show orders and executions:
+//   cargo run --example fixtail -- messages.log --from-start --no-follow --type D,8
+//
+// Only show AAPL fills:
+//   cargo run --example fixtail -- messages.log --type 8 --where 55=AAPL --where 150=2
+//
+// With a venue's own tags, filtering on one of them by name:
+//   cargo run --example fixtail -- messages.log --dictionary fix_repl/venue_tags.toml \
+//     --where VenueOrderClass=P
+//
+// What went over the wire, from a capture or live from tcpdump:
+//   cargo run --example fixtail -- --pcap session.pcap --port 5001 --type D,8
+//   tcpdump -i eth0 -w - 'tcp port 5001' | cargo run --example fixtail -- --pcap - --port 5001
+//
+// The wire journal of fix_repl --journal, inbound messages with their lag:
+//   cargo run --example fixtail -- --journal wire.jrnl --type 8
+//
+// An encrypted one (fix_repl --journal-key), with its key:
+//   cargo run --example fixtail -- --journal wire.jrnl --journal-key wire.key
+//
+// Sample output:
+//   20240102-10:00:00.000 [Logon] BeginString=FIX.4.4 BodyLength=65 MsgType=A ...
+//   20240102-10:00:05.123 [NewOrderSingle] ... Symbol=AAPL Side=1 OrderQty=100 ...
+//
+// =============================================================================
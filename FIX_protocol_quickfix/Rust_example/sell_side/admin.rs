@@ -0,0 +1,270 @@
+// =============================================================================
+// Venue Admin API
+// =============================================================================
+// Operations endpoints served over HTTP (JSON responses):
+//
+//   GET  /status          logged-on sessions, venue profile, subscriptions
+//   GET  /books           top of book for every symbol
+//   GET  /quotes          quotes live, answering QuoteRequests (35=R)
+//   GET  /alerts          surveillance alerts
+//   GET  /throttle        inbound rate limits and each session's last window
+//   GET  /metrics         Prometheus metrics (inbound throttling)
+//   POST /halt/{symbol}   stop accepting new orders for a symbol
+//   POST /resume/{symbol} resume trading
+//   POST /eod             write (and mail) today's trade confirmations and
+//                         reports, deliver them; returns the receipts
+//   GET  /trades/{exec_id}    a trade and its busts / corrections, oldest first
+//   POST /bust/{exec_id}      bust a trade (both sides get 35=8 150=H)
+//   POST /correct/{exec_id}   correct it, body {"quantity":..,"price":..}
+//                             (both sides get 35=8 150=G)
+//
+// Every POST is recorded in the operator audit log, with the X-Operator
+// header (or the client address) as the actor.
+// =============================================================================
+
+use std::{fmt::Write as _, io, sync::Arc};
+
+use trading::{
+    audit::{AuditLog, AuditSource},
+    gateway::http::{self, json_escape, parse_json_object, Request, Response},
+};
+
+use crate::{
+    app::SellSideApp,
+    ledger::{AmendError, Trade},
+};
+
+/// Start the admin API on `0.0.0.0:<port>` in a background thread
+pub fn spawn_server(port: u16, app: Arc<SellSideApp>, audit: Arc<AuditLog>) -> io::Result<()> {
+    let handler = http::audited(audit, AuditSource::Admin, move |request| handle(&app, request));
+    http::spawn_server("admin-http", port, handler)?;
+    println!(">> admin API available on http://0.0.0.0:{port}/status");
+    Ok(())
+}
+
+fn handle(app: &SellSideApp, request: &Request) -> Response {
+    match (request.method.as_str(), request.segments().as_slice()) {
+        ("GET", ["status"]) => status(app),
+        ("GET", ["books"]) => books(app),
+        ("GET", ["quotes"]) => quotes(app),
+        ("GET", ["alerts"]) => alerts(app),
+        ("GET", ["throttle"]) => throttle(app),
+        ("GET", ["metrics"]) => Response::text(app.metrics.render()),
+        ("POST", ["halt", symbol]) => {
+            app.engine
+                .lock()
+                .expect("engine lock poisoned")
+                .halt(symbol);
+            println!(">> ADMIN halt {symbol}");
+            Response::json(format!("{{\"halted\":\"{}\"}}", json_escape(symbol)))
+        }
+        ("POST", ["resume", symbol]) => {
+            app.engine
+                .lock()
+                .expect("engine lock poisoned")
+                .resume(symbol);
+            println!(">> ADMIN resume {symbol}");
+            Response::json(format!("{{\"resumed\":\"{}\"}}", json_escape(symbol)))
+        }
+        ("POST", ["eod"]) => eod(app),
+        ("GET", ["trades", exec_id]) => match chain(app, exec_id) {
+            Some(chain) => Response::json(chain),
+            None => Response::not_found(),
+        },
+        ("POST", ["bust", exec_id]) => amend_trade(app, exec_id, None),
+        ("POST", ["correct", exec_id]) => {
+            let fields = parse_json_object(&request.body).unwrap_or_default();
+            let quantity = fields.get("quantity").and_then(|x| x.parse::<u64>().ok());
+            let price = fields.get("price").and_then(|x| x.parse::<f64>().ok());
+            match quantity.zip(price) {
+                Some(correction) => amend_trade(app, exec_id, Some(correction)),
+                None => Response::error(
+                    "400 Bad Request",
+                    "body must be {\"quantity\":<integer>,\"price\":<number>}",
+                ),
+            }
+        }
+        _ => Response::not_found(),
+    }
+}
+
+/// Bust or correct both sides of a trade and return their chains
+fn amend_trade(app: &SellSideApp, exec_id: &str, correction: Option<(u64, f64)>) -> Response {
+    let sides = match app.amend_trade(exec_id, correction) {
+        Ok(x) => x,
+        Err(err) => {
+            let status = match err {
+                AmendError::UnknownTrade(_) => "404 Not Found",
+                AmendError::AlreadyAmended(_) => "409 Conflict",
+                AmendError::InvalidCorrection => "400 Bad Request",
+            };
+            return Response::error(status, &err.to_string());
+        }
+    };
+
+    match correction {
+        Some((quantity, price)) => println!(">> ADMIN correct {exec_id} -> {quantity}@{price}"),
+        None => println!(">> ADMIN bust {exec_id}"),
+    }
+    let chains: Vec<_> = sides
+        .iter()
+        .filter_map(|x| chain(app, &x.exec_id))
+        .collect();
+    Response::json(format!("[{}]", chains.join(",")))
+}
+
+/// JSON array of the ledger chain of a trade; None if it is unknown
+fn chain(app: &SellSideApp, exec_id: &str) -> Option<String> {
+    let chain: Vec<_> = app.ledger.chain(exec_id).iter().map(Trade::to_json).collect();
+    (!chain.is_empty()).then(|| format!("[{}]", chain.join(",")))
+}
+
+fn status(app: &SellSideApp) -> Response {
+    let sessions: Vec<_> = app
+        .logged_on_sessions()
+        .iter()
+        .map(|x| format!("\"{}\"", json_escape(x)))
+        .collect();
+    let profile = app
+        .engine
+        .lock()
+        .expect("engine lock poisoned")
+        .profile()
+        .name;
+    let subscriptions = app
+        .market_data
+        .lock()
+        .expect("market data lock poisoned")
+        .subscription_count();
+
+    Response::json(format!(
+        "{{\"venue_profile\":\"{profile}\",\"logged_on\":[{}],\"md_subscriptions\":{subscriptions}}}",
+        sessions.join(",")
+    ))
+}
+
+fn books(app: &SellSideApp) -> Response {
+    let engine = app.engine.lock().expect("engine lock poisoned");
+    let profile = engine.profile();
+
+    let mut out = String::from("[");
+    for (index, symbol) in engine.symbols().iter().enumerate() {
+        let Some(book) = engine.book(symbol) else {
+            continue;
+        };
+        let level = |x: Option<(i64, u64)>| match x {
+            Some((px, qty)) => format!("{{\"px\":{},\"qty\":{qty}}}", profile.to_price(px)),
+            None => "null".to_string(),
+        };
+        let last = book
+            .last_trade()
+            .map_or("null".to_string(), |x| profile.to_price(x).to_string());
+
+        if index > 0 {
+            out.push(',');
+        }
+        let _ = write!(
+            out,
+            "{{\"symbol\":\"{}\",\"halted\":{},\"bid\":{},\"ask\":{},\"last\":{last}}}",
+            json_escape(symbol),
+            engine.is_halted(symbol),
+            level(book.best_bid()),
+            level(book.best_ask()),
+        );
+    }
+    out.push(']');
+    Response::json(out)
+}
+
+fn quotes(app: &SellSideApp) -> Response {
+    let quotes: Vec<_> = app
+        .quotes
+        .lock()
+        .expect("quote engine lock poisoned")
+        .live_quotes()
+        .iter()
+        .map(|x| x.to_json())
+        .collect();
+    Response::json(format!("[{}]", quotes.join(",")))
+}
+
+fn alerts(app: &SellSideApp) -> Response {
+    let surveillance = app.surveillance.lock().expect("surveillance lock poisoned");
+    let alerts: Vec<_> = surveillance
+        .alerts()
+        .iter()
+        .map(|x| {
+            format!(
+                "{{\"timestamp\":{},\"session\":\"{}\",\"description\":\"{}\"}}",
+                x.timestamp,
+                json_escape(&x.session),
+                json_escape(&x.description)
+            )
+        })
+        .collect();
+    Response::json(format!("[{}]", alerts.join(",")))
+}
+
+fn throttle(app: &SellSideApp) -> Response {
+    let policy = app.throttle.policy();
+    let sessions: Vec<_> = app
+        .throttle
+        .snapshot()
+        .iter()
+        .map(|(label, count, level)| {
+            format!(
+                "{{\"session\":\"{}\",\"messages\":{count},\"level\":\"{}\"}}",
+                json_escape(label),
+                level.as_str()
+            )
+        })
+        .collect();
+    Response::json(format!(
+        "{{\"window_ms\":{},\"warn_at\":{},\"throttle_at\":{},\"disconnect_at\":{},\
+         \"delay_ms\":{},\"sessions\":[{}]}}",
+        policy.window.as_millis(),
+        policy.warn_at,
+        policy.throttle_at,
+        policy.disconnect_at,
+        policy.delay.as_millis(),
+        sessions.join(",")
+    ))
+}
+
+fn eod(app: &SellSideApp) -> Response {
+    let run = match app.run_eod() {
+        Ok(x) => x,
+        Err(err) => return Response::error("500 Internal Server Error", &err.to_string()),
+    };
+    let confirmations: Vec<_> = run
+        .confirmations
+        .iter()
+        .map(|x| {
+            let mailed_to: Vec<_> = x
+                .mailed_to
+                .iter()
+                .map(|x| format!("\"{}\"", json_escape(x)))
+                .collect();
+            format!(
+                "{{\"account\":\"{}\",\"trades\":{},\"csv\":\"{}\",\"html\":\"{}\",\"mailed_to\":[{}]}}",
+                json_escape(&x.account),
+                x.trades,
+                json_escape(&x.csv.display().to_string()),
+                json_escape(&x.html.display().to_string()),
+                mailed_to.join(",")
+            )
+        })
+        .collect();
+    let reports: Vec<_> = run
+        .reports
+        .iter()
+        .map(|x| format!("\"{}\"", json_escape(&x.display().to_string())))
+        .collect();
+    let receipts: Vec<_> = run.receipts.iter().map(|x| x.to_json()).collect();
+    Response::json(format!(
+        "{{\"confirmations\":[{}],\"reports\":[{}],\"receipts\":[{}]}}",
+        confirmations.join(","),
+        reports.join(","),
+        receipts.join(",")
+    ))
+}
@@ -0,0 +1,929 @@
+// =============================================================================
+// Sell-Side Application Callbacks
+// =============================================================================
+// Routes inbound messages to the venue components and sends the results back:
+//
+//   35=A  -> auth policy (accept / reject logon)
+//   35=D  -> matching engine -> 35=8 to owner (+ drop copy) -> 35=W publish
+//   35=F  -> matching engine -> 35=8 or 35=9 (cancel reject)
+//   35=V  -> market data publisher -> initial 35=W snapshot
+//   35=R  -> quote engine -> 35=S around the book's mid (or 35=AG)
+//   35=Z  -> quote engine -> 35=AI per quote withdrawn
+//   35=a  -> quote engine -> 35=AI per quote asked about
+//   35=J  -> allocation adds up? -> 35=P accepted or rejected as a block
+//   35=AD -> trades ledger -> 35=AQ, then 35=AE per trade of the
+//            counterparty on the day asked for (today without TradeDate)
+//   35=AF -> matching engine -> 35=8 (ExecType I) per order of the
+//            counterparty resting in the books, the last one flagged
+//   35=c  -> venue profile -> 35=d, the symbol's tick size, lot, multiplier
+//   35=x  -> matching engine -> 35=y, every symbol with a book (or the one
+//            asked for)
+//
+// Every inbound message is first counted against the rate limits of the
+// venue profile (sim::throttle): past them a session is warned about in the
+// log, then slowed down, then logged out. Application messages are then
+// checked against the operator's alert rules, if any (trading::expr).
+//
+// Trades are busted or corrected from the admin API: both sides of the match
+// get a 35=8 with ExecType H (trade cancel) or G (trade correct) and
+// ExecRefID (19) pointing at their fill, and the ledger keeps the chain.
+//
+// All state sits behind Mutexes since QuickFIX callbacks only get &self. Locks
+// are released before anything is sent: send_to_target re-enters the engine.
+// =============================================================================
+
+use std::{
+    collections::HashSet,
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use quickfix::*;
+
+use trading::{
+    gateway::metrics::Metrics,
+    md::MarketDataPublisher,
+    oms::{
+        self,
+        allocations::{build_allocation_ack, AllocationInstruction},
+        captures::{build_capture_ack, ReportTransType, TradeCaptureReport, TradeCaptureRequest},
+        mass_status::{build_no_orders, MassStatusRequest, OrderStatusReport},
+    },
+    instruments::{
+        build_security_definition, build_security_list, SecurityDefinitionRequest,
+        SecurityListRequest,
+    },
+    quotes::{
+        build_quote, build_quote_request_reject, build_quote_status_report, QuoteCancelScope,
+        QuoteEngine, QuoteRequest, QuoteSettings, QuoteStatusQuery, QuoteStatusReport,
+    },
+    expr::Rule,
+    session::{events::FixMessage, session_label, view::MessageView, Direction},
+    sim::{
+        matching::{BookOrder, ExecEvent, ExecKind, MatchingEngine, NewOrder, Side},
+        throttle::{InboundThrottle, ThrottleLevel},
+    },
+    store::{StateStore, StoreError},
+    time::Date,
+};
+
+use crate::{
+    auth::{AuthDecision, AuthPolicy},
+    config::{counterparty_comp_id, counterparty_session},
+    confirmations::{self, ConfirmationSettings},
+    drop_copy::DropCopy,
+    eod::{self, EodRun, EodSettings},
+    ledger::{AmendError, Trade, TradeLedger},
+    surveillance::Surveillance,
+};
+
+pub struct SellSideApp {
+    auth: AuthPolicy,
+    pub engine: Mutex<MatchingEngine>,
+    pub market_data: Mutex<MarketDataPublisher>,
+
+    /// Quotes given in answer to QuoteRequests
+    pub quotes: Mutex<QuoteEngine>,
+    pub surveillance: Mutex<Surveillance>,
+    pub ledger: TradeLedger,
+    drop_copy: DropCopy,
+    confirmations: ConfirmationSettings,
+    eod: EodSettings,
+
+    /// Inbound message rates per session, limits from the venue profile
+    pub throttle: InboundThrottle,
+
+    /// Served by the admin API on GET /metrics
+    pub metrics: Metrics,
+
+    /// Sessions currently logged on, by label
+    logged_on: Mutex<HashSet<String>>,
+    next_exec_id: AtomicU64,
+
+    /// In front of every ExecID, so that shards do not give the same ones
+    exec_id_prefix: String,
+}
+
+impl SellSideApp {
+    pub fn new(auth: AuthPolicy, engine: MatchingEngine, drop_copy: DropCopy) -> Self {
+        Self {
+            auth,
+            throttle: InboundThrottle::new(engine.profile().throttle.clone()),
+            quotes: Mutex::new(QuoteEngine::new(QuoteSettings::for_venue(engine.profile()))),
+            engine: Mutex::new(engine),
+            market_data: Mutex::new(MarketDataPublisher::new()),
+            surveillance: Mutex::new(Surveillance::new()),
+            ledger: TradeLedger::new(),
+            drop_copy,
+            confirmations: ConfirmationSettings::default(),
+            eod: EodSettings::default(),
+            metrics: Metrics::new(),
+            logged_on: Mutex::new(HashSet::new()),
+            next_exec_id: AtomicU64::new(1),
+            exec_id_prefix: String::new(),
+        }
+    }
+
+    /// Restore the market data subscriptions from a state store, and keep
+    /// them there as they change
+    pub fn with_store(mut self, store: Arc<dyn StateStore>) -> Result<Self, StoreError> {
+        let market_data = self.market_data.get_mut().expect("market data lock poisoned");
+        *market_data = std::mem::take(market_data).with_store(store)?;
+        Ok(self)
+    }
+
+    /// Spread, size and life of the quotes, reference prices of symbols
+    /// without a book
+    pub fn with_quotes(mut self, quotes: QuoteEngine) -> Self {
+        self.quotes = Mutex::new(quotes);
+        self
+    }
+
+    /// ExecIDs of `<prefix>E1`, `<prefix>E2`...: unique across the shards
+    /// of a coordinator
+    pub fn with_exec_id_prefix(mut self, prefix: &str) -> Self {
+        self.exec_id_prefix = prefix.to_string();
+        self
+    }
+
+    /// Where and to whom the end-of-day confirmations go
+    pub fn with_confirmations(mut self, settings: ConfirmationSettings) -> Self {
+        self.confirmations = settings;
+        self
+    }
+
+    /// Venue fee of the EOD fees report, and where the EOD files are
+    /// delivered
+    pub fn with_eod(mut self, settings: EodSettings) -> Self {
+        self.eod = settings;
+        self
+    }
+
+    /// Raise a surveillance alert for every inbound message a rule matches
+    pub fn with_alert_rules(mut self, rules: Vec<Rule>) -> Self {
+        let surveillance = self.surveillance.get_mut().expect("surveillance lock poisoned");
+        *surveillance = std::mem::take(surveillance).with_rules(rules);
+        self
+    }
+
+    /// Confirm today's trades to every account that traded, write the
+    /// day's reports and deliver them all
+    pub fn run_eod(&self) -> io::Result<EodRun> {
+        let today = Date::today();
+        let trades = self.ledger.trades_on(today);
+        let confirmations = confirmations::run_eod(&trades, today, &self.confirmations)?;
+        for x in &confirmations {
+            println!(
+                ">> EOD {}: {} trade(s) -> {}{}",
+                x.account,
+                x.trades,
+                x.csv.display(),
+                if x.mailed_to.is_empty() {
+                    String::new()
+                } else {
+                    format!(", mailed to {}", x.mailed_to.join(", "))
+                }
+            );
+        }
+
+        let mut run = EodRun {
+            reports: eod::write_reports(&trades, today, &self.confirmations.dir, &self.eod)?,
+            confirmations,
+            receipts: Vec::new(),
+        };
+        if !self.eod.delivery.is_empty() {
+            run.receipts = self.eod.delivery.deliver(&run.files())?;
+        }
+        for x in &run.receipts {
+            println!(
+                ">> EOD {} -> {}: {}{}",
+                x.file,
+                x.destination,
+                x.status.as_str(),
+                x.error.as_ref().map_or(String::new(), |x| format!(" ({x})"))
+            );
+        }
+        Ok(run)
+    }
+
+    /// Labels of the sessions currently logged on
+    pub fn logged_on_sessions(&self) -> Vec<String> {
+        let mut sessions: Vec<_> = self
+            .logged_on
+            .lock()
+            .expect("session lock poisoned")
+            .iter()
+            .cloned()
+            .collect();
+        sessions.sort();
+        sessions
+    }
+
+    // =========================================================================
+    // Flood Protection
+    // =========================================================================
+
+    /// Count an inbound message against the rate limits, act on the level
+    /// it reaches and return it
+    ///
+    /// Warn is logged, Throttle sleeps before the message is processed (on
+    /// the session's own thread: the others go on), Disconnect sends a
+    /// Logout. From then on the caller drops the session's messages.
+    fn throttle_inbound(&self, session: &SessionId) -> ThrottleLevel {
+        let label = session_label(session);
+        let verdict = self.throttle.on_message(&label, Instant::now());
+        if verdict.level == ThrottleLevel::Normal {
+            return verdict.level;
+        }
+        self.metrics.on_throttle(&label, verdict.level.as_str());
+
+        let policy = self.throttle.policy();
+        if verdict.escalated {
+            println!(
+                ">> THROTTLE {label}: {} messages in {:?}, {}",
+                verdict.count,
+                policy.window,
+                verdict.level.as_str()
+            );
+        }
+        match verdict.level {
+            ThrottleLevel::Throttle => thread::sleep(policy.delay),
+            ThrottleLevel::Disconnect if verdict.escalated => {
+                let result = build_logout("message rate limit exceeded")
+                    .and_then(|logout| send_to_target(logout, session));
+                if let Err(err) = result {
+                    eprintln!("cannot send logout: {err:?}");
+                }
+            }
+            _ => {}
+        }
+        verdict.level
+    }
+
+    /// Check an inbound application message against the alert rules
+    fn inspect_inbound(&self, msg: &Message, session: &SessionId) {
+        let mut surveillance = self.surveillance.lock().expect("surveillance lock poisoned");
+        if surveillance.has_rules() {
+            let decoded = FixMessage::decode(session, Direction::Inbound, false, msg);
+            surveillance.inspect(&counterparty_comp_id(session), &decoded);
+        }
+    }
+
+    // =========================================================================
+    // Order Handling
+    // =========================================================================
+
+    fn on_new_order(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        let view = MessageView::new(msg);
+        let request = NewOrder {
+            cl_ord_id: view.get_str(11)?,
+            owner: counterparty_comp_id(session),
+            symbol: view.get_str(55)?,
+            side: view.get_with(54, Side::from_fix)?,
+            price: view.get_price(44)?,
+            quantity: view.get_qty(38)?,
+        };
+
+        let symbol = request.symbol.clone();
+        let events = self
+            .engine
+            .lock()
+            .expect("engine lock poisoned")
+            .submit(request);
+        self.dispatch(&events);
+        self.publish_market_data(&symbol);
+        Ok(())
+    }
+
+    fn on_cancel_request(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        let view = MessageView::new(msg);
+        let cl_ord_id = view.get_str(11)?;
+        let orig_cl_ord_id = view.get_str(41)?;
+        let symbol = view.get_str(55)?;
+
+        let event = self.engine.lock().expect("engine lock poisoned").cancel(
+            &symbol,
+            &orig_cl_ord_id,
+            &cl_ord_id,
+            &counterparty_comp_id(session),
+        );
+
+        match event {
+            Some(event) => {
+                self.dispatch(&[event]);
+                self.publish_market_data(&symbol);
+            }
+            None => {
+                let result = build_cancel_reject(&cl_ord_id, &orig_cl_ord_id)
+                    .and_then(|reject| send_to_target(reject, session));
+                if let Err(err) = result {
+                    eprintln!("cannot send cancel reject: {err:?}");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the ExecutionReports for a batch of engine events
+    fn dispatch(&self, events: &[ExecEvent]) {
+        self.surveillance
+            .lock()
+            .expect("surveillance lock poisoned")
+            .observe(events, |event| event.order.owner.clone());
+
+        for event in events {
+            let exec_id = self.next_exec_id();
+            self.ledger.record(event, &exec_id);
+            self.send_report(build_execution_report(event, &exec_id), &event.order.owner);
+        }
+    }
+
+    /// Bust (`correction` None) or correct a trade, on both sides of the match
+    ///
+    /// # Arguments
+    /// * `exec_id` - ExecID of either side's fill
+    /// * `correction` - Corrected quantity and price
+    ///
+    /// # Returns
+    /// The sides of the match as they were before
+    pub fn amend_trade(
+        &self,
+        exec_id: &str,
+        correction: Option<(u64, f64)>,
+    ) -> Result<Vec<Trade>, AmendError> {
+        if correction
+            .is_some_and(|(quantity, price)| quantity == 0 || !(price.is_finite() && price > 0.0))
+        {
+            return Err(AmendError::InvalidCorrection);
+        }
+
+        let sides = self.ledger.match_of(exec_id)?;
+        for trade in &sides {
+            let amend_exec_id = self.next_exec_id();
+            self.ledger.amend(&trade.exec_id, &amend_exec_id, correction)?;
+            let resting = self.engine.lock().expect("engine lock poisoned").amend_fill(
+                &trade.symbol,
+                &trade.order_id,
+                (trade.quantity, trade.price),
+                correction,
+            );
+            let totals = self.ledger.order_totals(&trade.order_id);
+            let report =
+                build_trade_amendment(trade, &amend_exec_id, correction, resting.as_ref(), totals);
+            self.send_report(report, &trade.account);
+        }
+        Ok(sides)
+    }
+
+    fn next_exec_id(&self) -> String {
+        let next = self.next_exec_id.fetch_add(1, Ordering::Relaxed);
+        format!("{}E{next}", self.exec_id_prefix)
+    }
+
+    /// Send an ExecutionReport to its owner, and a copy to the drop copy
+    fn send_report(&self, report: Result<Message, QuickFixError>, owner: &str) {
+        let report = match report {
+            Ok(report) => report,
+            Err(err) => {
+                eprintln!("cannot build execution report: {err:?}");
+                return;
+            }
+        };
+
+        if let Err(err) = self.drop_copy.forward(&report, owner) {
+            eprintln!("cannot send drop copy: {err:?}");
+        }
+        let result =
+            counterparty_session(owner).and_then(|session| send_to_target(report, &session));
+        if let Err(err) = result {
+            eprintln!("cannot send execution report: {err:?}");
+        }
+    }
+
+    // =========================================================================
+    // Market Data
+    // =========================================================================
+
+    fn publish_market_data(&self, symbol: &str) {
+        let snapshots = {
+            let engine = self.engine.lock().expect("engine lock poisoned");
+            self.market_data
+                .lock()
+                .expect("market data lock poisoned")
+                .snapshots(symbol, engine.book(symbol), engine.profile())
+        };
+
+        for (snapshot, comp_id) in snapshots {
+            let result = counterparty_session(&comp_id)
+                .and_then(|session| send_to_target(snapshot, &session));
+            if let Err(err) = result {
+                eprintln!("cannot publish market data: {err:?}");
+            }
+        }
+    }
+
+    fn on_market_data_request(&self, msg: &Message, session: &SessionId) {
+        let symbols = self
+            .market_data
+            .lock()
+            .expect("market data lock poisoned")
+            .on_request(msg, &counterparty_comp_id(session));
+
+        // Initial snapshot for the new subscriptions
+        for symbol in symbols {
+            self.publish_market_data(&symbol);
+        }
+    }
+
+    // =========================================================================
+    // Quotes
+    // =========================================================================
+
+    /// Answer a QuoteRequest with a Quote around the mid of the book, or the
+    /// last trade; without either, the engine's own reference, if any
+    fn on_quote_request(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        let request = QuoteRequest::from_message(msg).ok_or(MsgFromAppError::FieldNotFound)?;
+        let reference = {
+            let engine = self.engine.lock().expect("engine lock poisoned");
+            let profile = engine.profile();
+            engine
+                .book(&request.symbol)
+                .and_then(|book| match (book.best_bid(), book.best_ask()) {
+                    (Some((bid, _)), Some((ask, _))) => {
+                        Some((profile.to_price(bid) + profile.to_price(ask)) / 2.0)
+                    }
+                    _ => book.last_trade().map(|x| profile.to_price(x)),
+                })
+        };
+
+        let owner = counterparty_comp_id(session);
+        let result = self
+            .quotes
+            .lock()
+            .expect("quote engine lock poisoned")
+            .quote(&request, &owner, reference);
+        let reply = match &result {
+            Ok(quote) => {
+                println!(">> QUOTE {quote}");
+                build_quote(quote)
+            }
+            Err(err) => {
+                println!(">> QUOTE REQUEST {} rejected: {err}", request.quote_req_id);
+                build_quote_request_reject(&request, err)
+            }
+        };
+        if let Err(err) = reply.and_then(|reply| send_to_target(reply, session)) {
+            eprintln!("cannot send quote: {err:?}");
+        }
+        Ok(())
+    }
+
+    /// Withdraw the counterparty's quotes a QuoteCancel selects
+    fn on_quote_cancel(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        let scope = QuoteCancelScope::from_message(msg).ok_or(MsgFromAppError::IncorrectTagValue)?;
+        let reports = self
+            .quotes
+            .lock()
+            .expect("quote engine lock poisoned")
+            .cancel(&counterparty_comp_id(session), &scope);
+        self.send_quote_status(&reports, session);
+        Ok(())
+    }
+
+    fn on_quote_status_request(&self, msg: &Message, session: &SessionId) {
+        let reports = self
+            .quotes
+            .lock()
+            .expect("quote engine lock poisoned")
+            .status(&counterparty_comp_id(session), &QuoteStatusQuery::from_message(msg));
+        self.send_quote_status(&reports, session);
+    }
+
+    fn send_quote_status(&self, reports: &[QuoteStatusReport], session: &SessionId) {
+        for report in reports {
+            let result = build_quote_status_report(report)
+                .and_then(|report| send_to_target(report, session));
+            if let Err(err) = result {
+                eprintln!("cannot send quote status report: {err:?}");
+            }
+        }
+    }
+
+    // =========================================================================
+    // Allocations
+    // =========================================================================
+
+    /// Accept an AllocationInstruction whose accounts and fills add up,
+    /// reject it as a block otherwise
+    fn on_allocation_instruction(
+        &self,
+        msg: &Message,
+        session: &SessionId,
+    ) -> Result<(), MsgFromAppError> {
+        let instruction =
+            AllocationInstruction::from_message(msg).ok_or(MsgFromAppError::FieldNotFound)?;
+        let result = instruction.check();
+        match &result {
+            Ok(()) => println!(">> ALLOCATION {instruction} accepted"),
+            Err(err) => println!(">> ALLOCATION {instruction} rejected: {err}"),
+        }
+        let ack = build_allocation_ack(&instruction.alloc_id, &result)
+            .and_then(|ack| send_to_target(ack, session));
+        if let Err(err) = ack {
+            eprintln!("cannot send allocation ack: {err:?}");
+        }
+        Ok(())
+    }
+
+    // =========================================================================
+    // Trade Capture
+    // =========================================================================
+
+    /// Answer a TradeCaptureReportRequest with the counterparty's trades
+    /// still standing on the day asked for, the last one flagged
+    fn on_trade_capture_request(
+        &self,
+        msg: &Message,
+        session: &SessionId,
+    ) -> Result<(), MsgFromAppError> {
+        let request =
+            TradeCaptureRequest::from_message(msg).ok_or(MsgFromAppError::IncorrectTagValue)?;
+        let owner = counterparty_comp_id(session);
+        let date = request.trade_date.unwrap_or_else(Date::today);
+        let trades: Vec<Trade> = self
+            .ledger
+            .trades_on(date)
+            .into_iter()
+            .filter(|x| x.account == owner)
+            .filter(|x| request.symbol.as_ref().is_none_or(|symbol| x.symbol == *symbol))
+            .collect();
+        println!(">> TRADE CAPTURE {request} for {owner}: {} trade(s)", trades.len());
+
+        let mut replies = vec![build_capture_ack(&request, Ok(trades.len()))];
+        for (index, trade) in trades.iter().enumerate() {
+            let last = index + 1 == trades.len();
+            replies.push(capture_report(trade, &request.request_id, last).to_message());
+        }
+        for reply in replies {
+            if let Err(err) = reply.and_then(|reply| send_to_target(reply, session)) {
+                eprintln!("cannot send trade capture report: {err:?}");
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // =========================================================================
+    // Order Mass Status
+    // =========================================================================
+
+    /// Answer an OrderMassStatusRequest with a status report per order of
+    /// the counterparty resting in the books, or a single one saying none
+    fn on_mass_status_request(
+        &self,
+        msg: &Message,
+        session: &SessionId,
+    ) -> Result<(), MsgFromAppError> {
+        let request =
+            MassStatusRequest::from_message(msg).ok_or(MsgFromAppError::IncorrectTagValue)?;
+        let owner = counterparty_comp_id(session);
+        let orders: Vec<(BookOrder, f64)> = {
+            let engine = self.engine.lock().expect("engine lock poisoned");
+            engine
+                .open_orders(&owner, request.symbol.as_deref())
+                .into_iter()
+                .map(|x| {
+                    let price = engine.profile().to_price(x.price_ticks);
+                    (x, price)
+                })
+                .collect()
+        };
+        println!(">> MASS STATUS {request} for {owner}: {} order(s)", orders.len());
+
+        let replies = if orders.is_empty() {
+            vec![build_no_orders(&request, &self.next_exec_id())]
+        } else {
+            orders
+                .iter()
+                .enumerate()
+                .map(|(index, (order, price))| {
+                    let report = OrderStatusReport {
+                        request_id: request.request_id.clone(),
+                        total: Some(orders.len() as u64),
+                        last_requested: index + 1 == orders.len(),
+                        cl_ord_id: order.cl_ord_id.clone(),
+                        order_id: order.order_id.clone(),
+                        ord_status: if order.cum_qty > 0 { "1" } else { "0" }.to_string(),
+                        symbol: order.symbol.clone(),
+                        side: match order.side {
+                            Side::Buy => oms::Side::Buy,
+                            Side::Sell => oms::Side::Sell,
+                        },
+                        quantity: order.quantity as f64,
+                        price: Some(*price),
+                        leaves_qty: order.leaves_qty() as f64,
+                        cum_qty: order.cum_qty as f64,
+                        avg_px: order.avg_px(),
+                    };
+                    report.to_message(&self.next_exec_id())
+                })
+                .collect()
+        };
+        for reply in replies {
+            if let Err(err) = reply.and_then(|reply| send_to_target(reply, session)) {
+                eprintln!("cannot send order status report: {err:?}");
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // =========================================================================
+    // Reference Data
+    // =========================================================================
+
+    /// Answer a SecurityDefinitionRequest: every symbol trades under the
+    /// rules of the venue profile
+    fn on_security_definition_request(
+        &self,
+        msg: &Message,
+        session: &SessionId,
+    ) -> Result<(), MsgFromAppError> {
+        let request =
+            SecurityDefinitionRequest::from_message(msg).ok_or(MsgFromAppError::IncorrectTagValue)?;
+        let instrument = self
+            .engine
+            .lock()
+            .expect("engine lock poisoned")
+            .profile()
+            .instrument(&request.symbol);
+        println!(">> SECURITY DEFINITION {} {}", request.request_id, request.symbol);
+
+        let reply = build_security_definition(&request, Ok(&instrument))
+            .and_then(|reply| send_to_target(reply, session));
+        if let Err(err) = reply {
+            eprintln!("cannot send security definition: {err:?}");
+        }
+        Ok(())
+    }
+
+    /// Answer a SecurityListRequest with the symbols that have a book, or
+    /// the one asked for
+    fn on_security_list_request(
+        &self,
+        msg: &Message,
+        session: &SessionId,
+    ) -> Result<(), MsgFromAppError> {
+        let request =
+            SecurityListRequest::from_message(msg).ok_or(MsgFromAppError::IncorrectTagValue)?;
+        let instruments: Vec<_> = {
+            let engine = self.engine.lock().expect("engine lock poisoned");
+            let symbols = match &request.symbol {
+                Some(symbol) => vec![symbol.clone()],
+                None => engine.symbols(),
+            };
+            symbols.iter().map(|x| engine.profile().instrument(x)).collect()
+        };
+        println!(">> SECURITY LIST {}: {} symbol(s)", request.request_id, instruments.len());
+
+        let reply = build_security_list(&request, &instruments)
+            .and_then(|reply| send_to_target(reply, session));
+        if let Err(err) = reply {
+            eprintln!("cannot send security list: {err:?}");
+        }
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Message Builders
+// =============================================================================
+
+/// TradeCaptureReport of one side of a trade, answering a request
+fn capture_report(trade: &Trade, request_id: &str, last: bool) -> TradeCaptureReport {
+    TradeCaptureReport {
+        trade_report_id: format!("TR-{}", trade.exec_id),
+        trans_type: ReportTransType::New,
+        ref_id: String::new(),
+        request_id: request_id.to_string(),
+        last_requested: last,
+        exec_id: trade.exec_id.clone(),
+        match_id: trade.match_id.clone(),
+        symbol: trade.symbol.clone(),
+        side: match trade.side {
+            Side::Buy => oms::Side::Buy,
+            Side::Sell => oms::Side::Sell,
+        },
+        quantity: trade.quantity as f64,
+        price: trade.price,
+        trade_date: trade.trade_date(),
+        time: trade.time * 1_000,
+        order_id: trade.order_id.clone(),
+        cl_ord_id: trade.cl_ord_id.clone(),
+        account: trade.account.clone(),
+    }
+}
+
+fn build_execution_report(event: &ExecEvent, exec_id: &str) -> Result<Message, QuickFixError> {
+    let order = &event.order;
+    let (exec_type, ord_status) = match event.kind {
+        ExecKind::New => ("0", "0"),
+        ExecKind::Trade if order.leaves_qty() == 0 => ("F", "2"),
+        ExecKind::Trade => ("F", "1"),
+        ExecKind::Canceled => ("4", "4"),
+        ExecKind::Rejected => ("8", "8"),
+    };
+    let leaves_qty = match event.kind {
+        ExecKind::Canceled | ExecKind::Rejected => 0,
+        _ => order.leaves_qty(),
+    };
+
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "8"))?;
+    msg.set_field(37, order.order_id.as_str())?; // OrderID
+    msg.set_field(17, exec_id)?; // ExecID
+    msg.set_field(150, exec_type)?; // ExecType
+    msg.set_field(39, ord_status)?; // OrdStatus
+    msg.set_field(55, order.symbol.as_str())?;
+    msg.set_field(54, order.side.as_fix())?;
+    msg.set_field(38, order.quantity.to_string().as_str())?;
+    msg.set_field(32, event.last_qty.to_string().as_str())?; // LastQty
+    msg.set_field(31, event.last_px.to_string().as_str())?; // LastPx
+    msg.set_field(151, leaves_qty.to_string().as_str())?; // LeavesQty
+    msg.set_field(14, order.cum_qty.to_string().as_str())?; // CumQty
+    msg.set_field(6, order.avg_px().to_string().as_str())?; // AvgPx
+
+    match &event.cancel_cl_ord_id {
+        Some(cancel_id) => {
+            msg.set_field(11, cancel_id.as_str())?;
+            msg.set_field(41, order.cl_ord_id.as_str())?;
+        }
+        None => msg.set_field(11, order.cl_ord_id.as_str())?,
+    }
+    if let Some(text) = &event.text {
+        msg.set_field(58, text.as_str())?;
+    }
+
+    Ok(msg)
+}
+
+/// ExecutionReport busting (150=H) or correcting (150=G) one side of a trade
+///
+/// # Arguments
+/// * `trade` - The fill as it was
+/// * `resting` - The order, if it still rests in the book
+/// * `totals` - CumQty and AvgPx of the order after the change
+fn build_trade_amendment(
+    trade: &Trade,
+    exec_id: &str,
+    correction: Option<(u64, f64)>,
+    resting: Option<&BookOrder>,
+    (cum_qty, avg_px): (u64, f64),
+) -> Result<Message, QuickFixError> {
+    let (exec_type, text) = match correction {
+        Some(_) => ("G", "trade corrected by venue"),
+        None => ("H", "trade busted by venue"),
+    };
+    // LastQty / LastPx: the corrected fill, or the one taken back
+    let (last_qty, last_px) = correction.unwrap_or((trade.quantity, trade.price));
+    // The order is done unless it still rests; done with nothing left
+    // executed, it reads as canceled
+    let leaves_qty = resting.map_or(0, BookOrder::leaves_qty);
+    let ord_status = match (leaves_qty, cum_qty) {
+        (0, 0) => "4",
+        (0, _) => "2",
+        (_, 0) => "0",
+        _ => "1",
+    };
+
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "8"))?;
+    msg.set_field(37, trade.order_id.as_str())?; // OrderID
+    msg.set_field(11, trade.cl_ord_id.as_str())?;
+    msg.set_field(17, exec_id)?; // ExecID
+    msg.set_field(19, trade.exec_id.as_str())?; // ExecRefID
+    msg.set_field(150, exec_type)?; // ExecType
+    msg.set_field(39, ord_status)?; // OrdStatus
+    msg.set_field(55, trade.symbol.as_str())?;
+    msg.set_field(54, trade.side.as_fix())?;
+    msg.set_field(38, (cum_qty + leaves_qty).to_string().as_str())?; // OrderQty
+    msg.set_field(32, last_qty.to_string().as_str())?; // LastQty
+    msg.set_field(31, last_px.to_string().as_str())?; // LastPx
+    msg.set_field(151, leaves_qty.to_string().as_str())?; // LeavesQty
+    msg.set_field(14, cum_qty.to_string().as_str())?; // CumQty
+    msg.set_field(6, avg_px.to_string().as_str())?; // AvgPx
+    msg.set_field(58, text)?;
+    Ok(msg)
+}
+
+fn build_logout(text: &str) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "5"))?;
+    msg.set_field(58, text)?; // Text
+    Ok(msg)
+}
+
+fn build_cancel_reject(cl_ord_id: &str, orig_cl_ord_id: &str) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "9"))?;
+    msg.set_field(37, "NONE")?; // OrderID unknown
+    msg.set_field(11, cl_ord_id)?;
+    msg.set_field(41, orig_cl_ord_id)?;
+    msg.set_field(39, "8")?; // OrdStatus: Rejected
+    msg.set_field(434, "1")?; // CxlRejResponseTo: OrderCancelRequest
+    msg.set_field(102, "1")?; // CxlRejReason: Unknown order
+    Ok(msg)
+}
+
+// =============================================================================
+// ApplicationCallback Implementation
+// =============================================================================
+
+impl ApplicationCallback for SellSideApp {
+    fn on_create(&self, session: &SessionId) {
+        println!(">> session created: {session:?}");
+    }
+
+    fn on_logon(&self, session: &SessionId) {
+        println!(">> logon: {session:?}");
+        if self.drop_copy.is_session(session) {
+            self.drop_copy.set_logged_on(true);
+        }
+        self.logged_on
+            .lock()
+            .expect("session lock poisoned")
+            .insert(counterparty_comp_id(session));
+    }
+
+    fn on_logout(&self, session: &SessionId) {
+        println!(">> logout: {session:?}");
+        let comp_id = counterparty_comp_id(session);
+        if self.drop_copy.is_session(session) {
+            self.drop_copy.set_logged_on(false);
+        }
+        self.market_data
+            .lock()
+            .expect("market data lock poisoned")
+            .remove_session(&comp_id);
+        self.quotes
+            .lock()
+            .expect("quote engine lock poisoned")
+            .remove_session(&comp_id);
+        self.logged_on
+            .lock()
+            .expect("session lock poisoned")
+            .remove(&comp_id);
+        self.throttle.reset(&session_label(session));
+    }
+
+    fn on_msg_from_admin(
+        &self,
+        msg: &Message,
+        session: &SessionId,
+    ) -> Result<(), MsgFromAdminError> {
+        // Only an error from here makes the engine drop the connection
+        if self.throttle_inbound(session) == ThrottleLevel::Disconnect {
+            return Err(MsgFromAdminError::RejectLogon);
+        }
+        if msg.with_header(|h| h.get_field(35)).as_deref() == Some("A") {
+            if let AuthDecision::Reject(reason) = self.auth.check_logon(msg) {
+                println!(">> logon rejected for {session:?}: {reason}");
+                return Err(MsgFromAdminError::RejectLogon);
+            }
+        }
+        Ok(())
+    }
+
+    fn on_msg_from_app(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        // Logged out: dropped until the next admin message ends the session
+        if self.throttle_inbound(session) == ThrottleLevel::Disconnect {
+            return Ok(());
+        }
+        self.inspect_inbound(msg, session);
+        match msg.with_header(|h| h.get_field(35)).as_deref() {
+            Some("D") => self.on_new_order(msg, session),
+            Some("F") => self.on_cancel_request(msg, session),
+            Some("V") => {
+                self.on_market_data_request(msg, session);
+                Ok(())
+            }
+            Some("R") => self.on_quote_request(msg, session),
+            Some("Z") => self.on_quote_cancel(msg, session),
+            Some("a") => {
+                self.on_quote_status_request(msg, session);
+                Ok(())
+            }
+            Some("J") => self.on_allocation_instruction(msg, session),
+            Some("AD") => self.on_trade_capture_request(msg, session),
+            Some("AF") => self.on_mass_status_request(msg, session),
+            Some("c") => self.on_security_definition_request(msg, session),
+            Some("x") => self.on_security_list_request(msg, session),
+            _ => Err(MsgFromAppError::UnsupportedMessageType),
+        }
+    }
+}
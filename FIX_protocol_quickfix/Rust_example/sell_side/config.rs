@@ -0,0 +1,82 @@
+// =============================================================================
+// Sell-Side Acceptor Configuration Builder
+// =============================================================================
+// One acceptor, one listening port, several counterparties. Each allowed
+// counterparty gets its own [SESSION] block; QuickFIX routes each inbound
+// connection to the right session from the CompIDs in its Logon.
+//
+// The default session set mirrors the buy_side example:
+//   SIMULATOR <- BUYSIDE_MD   market data
+//   SIMULATOR <- BUYSIDE_ORD  order entry
+//   SIMULATOR <- DROPCOPY     drop copy of all execution reports
+//
+// --comp-ids replaces the trading counterparties. A shard worker of
+// coordinator mode (coordinator.rs) gets its share of them only, the drop
+// copy session, and a message store of its own.
+// =============================================================================
+
+use quickfix::{dictionary_item::*, Dictionary, QuickFixError, SessionId, SessionSettings};
+
+pub const BEGIN_STRING: &str = "FIX.4.4";
+
+/// Our CompID
+pub const VENUE_COMP_ID: &str = "SIMULATOR";
+
+/// Counterparties trading on the venue
+pub const TRADING_COMP_IDS: [&str; 2] = ["BUYSIDE_MD", "BUYSIDE_ORD"];
+
+/// Counterparty receiving the drop copy
+pub const DROP_COPY_COMP_ID: &str = "DROPCOPY";
+
+/// FileStorePath of the sessions; shard workers use a directory below it
+pub const STORE_DIR: &str = "sell_side_store";
+
+/// Session identifier of a counterparty, seen from the venue
+pub fn counterparty_session(comp_id: &str) -> Result<SessionId, QuickFixError> {
+    SessionId::try_new(BEGIN_STRING, VENUE_COMP_ID, comp_id, "")
+}
+
+/// CompID of the counterparty on the other end of a venue session
+///
+/// Venue state is keyed by CompID rather than SessionId: SessionId wraps a
+/// C++ handle that cannot be shared with the admin API thread.
+pub fn counterparty_comp_id(session: &SessionId) -> String {
+    session.get_target_comp_id().unwrap_or_default()
+}
+
+/// Build acceptor settings for the trading counterparties and the drop copy
+///
+/// # Arguments
+/// * `port` - TCP port shared by every session
+/// * `comp_ids` - Trading counterparties
+/// * `store_path` - FileStorePath, one per process
+pub fn build_settings(
+    port: u16,
+    comp_ids: &[String],
+    store_path: &str,
+) -> Result<SessionSettings, QuickFixError> {
+    let mut settings = SessionSettings::new();
+
+    settings.set(
+        None,
+        Dictionary::try_from_items(&[
+            &ConnectionType::Acceptor,
+            &FileStorePath(store_path),
+            &StartTime("00:00:00"),
+            &EndTime("00:00:00"),
+            &HeartBtInt(30),
+            &SocketAcceptPort(port),
+            &DataDictionary("spec/FIX44.xml"),
+        ])?,
+    )?;
+
+    for comp_id in comp_ids
+        .iter()
+        .map(String::as_str)
+        .chain([DROP_COPY_COMP_ID])
+    {
+        settings.set(Some(&counterparty_session(comp_id)?), Dictionary::new())?;
+    }
+
+    Ok(settings)
+}
@@ -0,0 +1,258 @@
+// =============================================================================
+// End-of-Day Trade Confirmations
+// =============================================================================
+// At end of day the venue, acting as broker, confirms each account's trades
+// of the day from the trades ledger (ledger.rs):
+//
+//   <dir>/<YYYYMMDD>/<ACCOUNT>.csv    one line per fill, for reconciliation
+//   <dir>/<YYYYMMDD>/<ACCOUNT>.html   printable confirmation with a summary
+//                                     per symbol (print to PDF from a browser)
+//
+// When a mail relay and recipients are configured, each account's two files
+// are also mailed to its recipients (trading::gateway::smtp). Accounts without
+// trades get nothing. Running EOD again for the same day rewrites the files.
+//
+// Triggered by `eod` on the console or POST /eod on the admin API.
+// =============================================================================
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use trading::{
+    csv::csv_field,
+    gateway::smtp::{Attachment, Email, SmtpSink},
+    sim::matching::Side,
+    time::{time_of_day, Date},
+};
+
+use crate::{config::VENUE_COMP_ID, ledger::Trade};
+
+/// Where confirmations go
+#[derive(Debug, Clone)]
+pub struct ConfirmationSettings {
+    pub dir: PathBuf,
+
+    /// Relay to mail them through; files only without one
+    pub smtp: Option<SmtpSink>,
+
+    /// Recipients by account
+    pub recipients: HashMap<String, Vec<String>>,
+}
+
+impl Default for ConfirmationSettings {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("confirmations"),
+            smtp: None,
+            recipients: HashMap::new(),
+        }
+    }
+}
+
+/// Files written for one account
+#[derive(Debug, Clone)]
+pub struct Confirmation {
+    pub account: String,
+    pub trades: usize,
+    pub csv: PathBuf,
+    pub html: PathBuf,
+
+    /// Mailed to these addresses
+    pub mailed_to: Vec<String>,
+}
+
+/// Write (and mail) the confirmations of `date`
+///
+/// A mail failure is reported and skipped: the files are the record.
+pub fn run_eod(
+    trades: &[Trade],
+    date: Date,
+    settings: &ConfirmationSettings,
+) -> io::Result<Vec<Confirmation>> {
+    if trades.is_empty() {
+        return Ok(Vec::new());
+    }
+    let dir = settings.dir.join(date.to_fix());
+    fs::create_dir_all(&dir)?;
+
+    let mut by_account: BTreeMap<&str, Vec<&Trade>> = BTreeMap::new();
+    for trade in trades {
+        by_account.entry(&trade.account).or_default().push(trade);
+    }
+
+    let mut confirmations = Vec::new();
+    for (account, trades) in by_account {
+        let csv = render_csv(&trades, date);
+        let html = render_html(account, &trades, date);
+        let csv_path = dir.join(format!("{account}.csv"));
+        let html_path = dir.join(format!("{account}.html"));
+        fs::write(&csv_path, &csv)?;
+        fs::write(&html_path, &html)?;
+
+        let mut mailed_to = Vec::new();
+        if let (Some(smtp), Some(to)) = (&settings.smtp, settings.recipients.get(account)) {
+            let email = Email {
+                to: to.clone(),
+                subject: format!("Trade confirmation {account} {}", date.to_iso()),
+                body: format!(
+                    "{} trade(s) for account {account} on {}, details attached.\n\n{VENUE_COMP_ID}\n",
+                    trades.len(),
+                    date.to_iso()
+                ),
+                attachments: vec![
+                    attachment(&csv_path, "text/csv", csv.into_bytes()),
+                    attachment(&html_path, "text/html", html.into_bytes()),
+                ],
+            };
+            match smtp.send(&email) {
+                Ok(()) => mailed_to = to.clone(),
+                Err(err) => eprintln!("cannot mail confirmation of {account}: {err}"),
+            }
+        }
+
+        confirmations.push(Confirmation {
+            account: account.to_string(),
+            trades: trades.len(),
+            csv: csv_path,
+            html: html_path,
+            mailed_to,
+        });
+    }
+    Ok(confirmations)
+}
+
+fn attachment(path: &Path, content_type: &str, data: Vec<u8>) -> Attachment {
+    Attachment {
+        file_name: path
+            .file_name()
+            .map_or(String::new(), |x| x.to_string_lossy().into_owned()),
+        content_type: content_type.to_string(),
+        data,
+    }
+}
+
+pub fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "BUY",
+        Side::Sell => "SELL",
+    }
+}
+
+// =============================================================================
+// CSV
+// =============================================================================
+
+fn render_csv(trades: &[&Trade], date: Date) -> String {
+    let mut out = String::from(
+        "trade_date,time_utc,exec_id,order_id,cl_ord_id,symbol,side,quantity,price,notional\n",
+    );
+    for trade in trades {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{:.2}",
+            date.to_iso(),
+            time_of_day(trade.time),
+            csv_field(&trade.exec_id),
+            csv_field(&trade.order_id),
+            csv_field(&trade.cl_ord_id),
+            csv_field(&trade.symbol),
+            side_name(trade.side),
+            trade.quantity,
+            trade.price,
+            trade.notional()
+        );
+    }
+    out
+}
+
+// =============================================================================
+// HTML
+// =============================================================================
+
+#[derive(Default)]
+struct SymbolSummary {
+    bought: u64,
+    sold: u64,
+    buy_notional: f64,
+    sell_notional: f64,
+}
+
+fn render_html(account: &str, trades: &[&Trade], date: Date) -> String {
+    let mut summary: BTreeMap<&str, SymbolSummary> = BTreeMap::new();
+    for trade in trades {
+        let entry = summary.entry(&trade.symbol).or_default();
+        match trade.side {
+            Side::Buy => {
+                entry.bought += trade.quantity;
+                entry.buy_notional += trade.notional();
+            }
+            Side::Sell => {
+                entry.sold += trade.quantity;
+                entry.sell_notional += trade.notional();
+            }
+        }
+    }
+
+    let title = format!("Trade confirmation - {} - {}", html(account), date.to_iso());
+    let mut out = format!(
+        "<!DOCTYPE html>\n\
+         <html><head><meta charset=\"utf-8\"><title>{title}</title>\n\
+         <style>body{{font-family:sans-serif}}table{{border-collapse:collapse}}\
+         td,th{{border:1px solid #999;padding:2px 8px}}td.n{{text-align:right}}</style>\n\
+         </head><body>\n\
+         <h1>{title}</h1>\n\
+         <p>Executing broker: {VENUE_COMP_ID}. Times are UTC.</p>\n\
+         <h2>Trades</h2>\n\
+         <table><tr><th>Time</th><th>Exec ID</th><th>Order ID</th><th>ClOrdID</th>\
+         <th>Symbol</th><th>Side</th><th>Quantity</th><th>Price</th><th>Notional</th></tr>\n"
+    );
+    for trade in trades {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+             <td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{:.2}</td></tr>",
+            time_of_day(trade.time),
+            html(&trade.exec_id),
+            html(&trade.order_id),
+            html(&trade.cl_ord_id),
+            html(&trade.symbol),
+            side_name(trade.side),
+            trade.quantity,
+            trade.price,
+            trade.notional()
+        );
+    }
+    out.push_str(
+        "</table>\n<h2>Summary</h2>\n\
+         <table><tr><th>Symbol</th><th>Bought</th><th>Sold</th><th>Net</th>\
+         <th>Buy notional</th><th>Sell notional</th></tr>\n",
+    );
+    for (symbol, x) in summary {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td>\
+             <td class=\"n\">{:.2}</td><td class=\"n\">{:.2}</td></tr>",
+            html(symbol),
+            x.bought,
+            x.sold,
+            x.bought as i64 - x.sold as i64,
+            x.buy_notional,
+            x.sell_notional
+        );
+    }
+    out.push_str("</table>\n</body></html>\n");
+    out
+}
+
+/// Escape text for HTML content
+fn html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
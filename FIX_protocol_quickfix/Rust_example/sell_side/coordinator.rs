@@ -0,0 +1,287 @@
+// =============================================================================
+// Coordinator Mode
+// =============================================================================
+// `sell_side coordinator N` runs no acceptor of its own: it starts N copies
+// of this binary, `--shard I/N` each, and puts one admin API in front of
+// them (trading::gateway::shards):
+//
+//   worker I      FIX on PORT+I, admin API on ADMIN_PORT+1+I; the
+//                 counterparties of --comp-ids whose CompID hashes to I,
+//                 and DROPCOPY; ExecIDs S<I>-E1, S<I>-E2...; its message
+//                 store in sell_side_store/shard-I, its confirmations and
+//                 reports in <confirms-dir>/shard-I, its --state keys in
+//                 namespaces of its own (shard-I-subscriptions)
+//   coordinator   admin API on ADMIN_PORT, the endpoints of the workers
+//
+// GET /status and /throttle list the answer of each worker; /books, /quotes
+// and /alerts join theirs, each entry with its "shard"; /metrics labels
+// every series shard="I" and adds fix_shard_up. POST /halt, /resume and
+// /eod go to every worker, /trades, /bust and /correct to the one whose
+// ExecID it is. GET /shards lists the workers, their ports and CompIDs.
+//
+// A worker matches the orders of its own counterparties only: two
+// counterparties trade with each other when they are on the same shard.
+// The drop copy client connects to the port of every worker.
+// =============================================================================
+
+use std::{
+    env,
+    path::PathBuf,
+    process::{exit, Command},
+    sync::Arc,
+    time::Duration,
+};
+
+use trading::{
+    gateway::{
+        http::{self, json_escape, Request, Response},
+        shards::{
+            assign, merge_arrays, merge_metrics, merge_objects, Coordinator, ShardReply, ShardSpec,
+        },
+    },
+    session::runtime::{shutdown_signal, stdin_lines},
+    sim::venue::VenueProfile,
+};
+
+use crate::take_comp_ids;
+
+/// How long the workers have to shut down once told to
+const STOP_GRACE: Duration = Duration::from_secs(10);
+
+/// How often the coordinator looks for workers that exited
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What the coordinator knows of a shard besides its process
+struct ShardInfo {
+    fix_port: u16,
+    comp_ids: Vec<String>,
+}
+
+/// Run the coordinator until 'q', CTRL-C or SIGTERM, then stop the workers
+///
+/// Arguments: `coordinator N [port] [profile] [admin_port] [--comp-ids
+/// LIST] [-- WORKER OPTIONS]`, the worker options being those of a venue
+/// (--fee-bps, --state...).
+pub async fn run(mut args: Vec<String>) {
+    let forwarded = match args.iter().position(|x| x == "--") {
+        Some(at) => {
+            let forwarded = args.split_off(at + 1);
+            args.pop();
+            forwarded
+        }
+        None => Vec::new(),
+    };
+    let comp_ids = take_comp_ids(&mut args);
+
+    let Some(count) = args
+        .get(2)
+        .and_then(|x| x.parse::<u16>().ok())
+        .filter(|x| *x > 0)
+    else {
+        eprintln!("Usage: sell_side coordinator N [port] [profile] [admin_port] [--comp-ids LIST] [-- OPTIONS]");
+        exit(1);
+    };
+    let port: u16 = args.get(3).and_then(|x| x.parse().ok()).unwrap_or(5001);
+    let profile = args.get(4).map_or("equities", String::as_str);
+    let admin_port: u16 = args.get(5).and_then(|x| x.parse().ok()).unwrap_or(8081);
+    if VenueProfile::by_name(profile).is_none() {
+        eprintln!("Unknown venue profile: {profile} (expected equities, futures or fx)");
+        exit(1);
+    }
+    if port.checked_add(count).is_none() || admin_port.checked_add(count + 1).is_none() {
+        eprintln!("Not enough ports above {port} and {admin_port} for {count} workers");
+        exit(1);
+    }
+    let program = match env::current_exe() {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("Cannot find this program to start the workers: {err}");
+            exit(1);
+        }
+    };
+
+    // =========================================================================
+    // Start the Workers
+    // =========================================================================
+
+    println!(
+        ">> Coordinator: {count} workers, {} counterparties",
+        comp_ids.len()
+    );
+    let coordinator = Arc::new(Coordinator::new());
+    let mut shards = Vec::new();
+    for (index, shard_comp_ids) in assign(comp_ids.iter().map(String::as_str), count.into())
+        .into_iter()
+        .enumerate()
+    {
+        let spec = ShardSpec::new(index, count.into()).expect("index below count");
+        let offset = index as u16;
+        let fix_port = port + offset;
+        let worker_admin_port = admin_port + 1 + offset;
+
+        let mut command = Command::new(&program);
+        command
+            .arg(fix_port.to_string())
+            .arg(profile)
+            .arg(worker_admin_port.to_string())
+            .args([
+                "--shard",
+                &spec.to_string(),
+                "--comp-ids",
+                &comp_ids.join(","),
+            ])
+            .args(worker_flags(&forwarded, index));
+        match coordinator.spawn(index, worker_admin_port, command) {
+            Ok(pid) => println!(
+                ">> shard {spec}: pid {pid}, FIX port {fix_port}, admin port {worker_admin_port}"
+            ),
+            Err(err) => {
+                eprintln!("{err}");
+                coordinator.stop(STOP_GRACE);
+                exit(1);
+            }
+        }
+        shards.push(ShardInfo {
+            fix_port,
+            comp_ids: shard_comp_ids,
+        });
+    }
+
+    let handler = {
+        let coordinator = Arc::clone(&coordinator);
+        move |request: &Request| handle(&coordinator, &shards, request)
+    };
+    if let Err(err) = http::spawn_server("coordinator-http", admin_port, handler) {
+        eprintln!("Cannot start admin API: {err}");
+        coordinator.stop(STOP_GRACE);
+        exit(1);
+    }
+    println!(">> admin API of every shard on http://0.0.0.0:{admin_port}/status");
+
+    // =========================================================================
+    // Run Until User Quits
+    // =========================================================================
+
+    println!(">> Coordinator running, 'eod' goes to every worker, 'q' to quit (or CTRL-C)");
+    let mut lines = stdin_lines();
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    let mut watch = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        tokio::select! {
+            line = lines.recv() => match line {
+                Some(line) if line.trim() == "q" => break,
+                Some(line) if line.trim() == "eod" => coordinator.send_line("eod"),
+                Some(_) => {}
+                None => break,
+            },
+            _ = watch.tick() => {
+                for worker in coordinator.exited() {
+                    let exit = worker.exit.map_or(String::new(), |x| x.to_string());
+                    eprintln!("Shard {} (pid {}) exited: {exit}", worker.shard, worker.pid);
+                }
+            }
+            () = &mut shutdown => {
+                println!(">> Shutdown signal received");
+                break;
+            }
+        }
+    }
+
+    println!(">> Stopping the workers");
+    coordinator.stop(STOP_GRACE);
+    println!(">> All cleared. Bye !");
+}
+
+/// The options of a worker: those given after `--`, the confirmations
+/// directory made its own
+fn worker_flags(forwarded: &[String], index: usize) -> Vec<String> {
+    let shard_dir = |dir: &str| {
+        PathBuf::from(dir)
+            .join(format!("shard-{index}"))
+            .display()
+            .to_string()
+    };
+    let mut flags = forwarded.to_vec();
+    match flags.iter().position(|x| x == "--confirms-dir") {
+        Some(at) if at + 1 < flags.len() => flags[at + 1] = shard_dir(&flags[at + 1]),
+        _ => flags.extend(["--confirms-dir".to_string(), shard_dir("confirmations")]),
+    }
+    flags
+}
+
+// =============================================================================
+// Admin API
+// =============================================================================
+
+fn handle(coordinator: &Coordinator, shards: &[ShardInfo], request: &Request) -> Response {
+    let operator = request.operator();
+    let (method, path) = (request.method.as_str(), request.path.as_str());
+    let every_worker = || coordinator.request(method, path, &operator, &request.body);
+    match (method, request.segments().as_slice()) {
+        ("GET", ["shards"]) => shard_list(coordinator, shards),
+        ("GET", ["status" | "throttle"]) => Response::json(merge_objects(&every_worker())),
+        ("GET", ["books" | "quotes" | "alerts"]) => Response::json(merge_arrays(&every_worker())),
+        ("GET", ["metrics"]) => Response::text(merge_metrics(&every_worker())),
+        ("POST", ["halt" | "resume", _] | ["eod"]) => {
+            println!(">> COORDINATOR {method} {path}");
+            Response::json(merge_objects(&every_worker()))
+        }
+        ("GET", ["trades", exec_id]) | ("POST", ["bust" | "correct", exec_id]) => {
+            match owner(exec_id) {
+                Some(shard) if shard < shards.len() => {
+                    forward(coordinator.request_one(shard, method, path, &operator, &request.body))
+                }
+                _ => Response::not_found(),
+            }
+        }
+        _ => Response::not_found(),
+    }
+}
+
+/// The shard of an ExecID: I of S<I>-E12
+fn owner(exec_id: &str) -> Option<usize> {
+    exec_id.strip_prefix('S')?.split_once('-')?.0.parse().ok()
+}
+
+/// A worker's answer as it is
+fn forward(reply: ShardReply) -> Response {
+    match reply.result {
+        Ok((status, body)) => Response {
+            status: match status {
+                200 => "200 OK",
+                400 => "400 Bad Request",
+                404 => "404 Not Found",
+                409 => "409 Conflict",
+                500 => "500 Internal Server Error",
+                _ => "502 Bad Gateway",
+            },
+            content_type: "application/json",
+            body,
+        },
+        Err(err) => Response::error("502 Bad Gateway", &err.to_string()),
+    }
+}
+
+fn shard_list(coordinator: &Coordinator, shards: &[ShardInfo]) -> Response {
+    let workers: Vec<_> = coordinator
+        .workers()
+        .iter()
+        .map(|worker| {
+            let info = &shards[worker.shard];
+            let comp_ids: Vec<_> = info
+                .comp_ids
+                .iter()
+                .map(|x| format!("\"{}\"", json_escape(x)))
+                .collect();
+            let status = worker.to_json();
+            format!(
+                "{},\"fix_port\":{},\"comp_ids\":[{}]}}",
+                status.strip_suffix('}').unwrap_or(&status),
+                info.fix_port,
+                comp_ids.join(",")
+            )
+        })
+        .collect();
+    Response::json(format!("[{}]", workers.join(",")))
+}
@@ -0,0 +1,420 @@
+// =============================================================================
+// Two-Node Demo / Smoke Test
+// =============================================================================
+// `sell_side demo` runs the venue acceptor and a scripted initiator in the same
+// process, on an ephemeral port with in-memory message stores, then plays a
+// canned scenario against it:
+//
+//   logon      both BUYSIDE_MD and BUYSIDE_ORD log on
+//   subscribe  35=V -> initial 35=W snapshot
+//   trade      resting sell + crossing buy -> both filled (39=2)
+//   bust       the venue busts that trade -> both sides get 150=H
+//   cancel     resting buy -> 35=F -> canceled (39=4)
+//   eod        initiator logs out -> venue sees no session left
+//
+// Each step prints PASS/FAIL with its duration; the process exit code tells
+// whether the whole scenario passed, so it can gate CI.
+// =============================================================================
+
+use std::{
+    collections::HashSet,
+    net::TcpListener,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use quickfix::{dictionary_item::*, *};
+
+use trading::{
+    sim::{
+        matching::{MatchingEngine, Side},
+        venue::VenueProfile,
+    },
+    time::transact_time,
+};
+
+use crate::{
+    app::SellSideApp,
+    auth::AuthPolicy,
+    config::{
+        build_settings, BEGIN_STRING, DROP_COPY_COMP_ID, STORE_DIR, TRADING_COMP_IDS, VENUE_COMP_ID,
+    },
+    drop_copy::DropCopy,
+};
+
+/// How long each step may take before it fails
+const STEP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often pending conditions are re-checked
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+const SYMBOL: &str = "DEMO";
+
+// =============================================================================
+// Scripted Client
+// =============================================================================
+// Records logons and every application message received so that the scenario
+// can wait for the responses it expects.
+// =============================================================================
+
+#[derive(Default)]
+struct DemoClient {
+    logged_on: Mutex<HashSet<String>>,
+    inbox: Mutex<Vec<Message>>,
+}
+
+impl DemoClient {
+    fn logged_on_count(&self) -> usize {
+        self.logged_on.lock().expect("session lock poisoned").len()
+    }
+
+    /// Wait for (and consume) the first received message matching `predicate`
+    fn wait_for<P>(&self, predicate: P) -> Option<Message>
+    where
+        P: Fn(&Message) -> bool,
+    {
+        let started = Instant::now();
+        loop {
+            {
+                let mut inbox = self.inbox.lock().expect("inbox lock poisoned");
+                if let Some(index) = inbox.iter().position(&predicate) {
+                    return Some(inbox.remove(index));
+                }
+            }
+            if started.elapsed() > STEP_TIMEOUT {
+                return None;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl ApplicationCallback for DemoClient {
+    fn on_logon(&self, session: &SessionId) {
+        self.logged_on
+            .lock()
+            .expect("session lock poisoned")
+            .insert(session.get_sender_comp_id().unwrap_or_default());
+    }
+
+    fn on_logout(&self, session: &SessionId) {
+        self.logged_on
+            .lock()
+            .expect("session lock poisoned")
+            .remove(&session.get_sender_comp_id().unwrap_or_default());
+    }
+
+    fn on_msg_from_app(&self, msg: &Message, _session: &SessionId) -> Result<(), MsgFromAppError> {
+        self.inbox
+            .lock()
+            .expect("inbox lock poisoned")
+            .push(msg.clone());
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Scenario
+// =============================================================================
+
+/// Outcome of one scenario step
+struct StepResult {
+    name: &'static str,
+    passed: bool,
+    elapsed: Duration,
+    detail: String,
+}
+
+/// Run the demo scenario and print a summary
+///
+/// # Returns
+/// true if every step passed
+pub fn run() -> Result<bool, QuickFixError> {
+    let port = ephemeral_port().map_err(|err| QuickFixError::InvalidArgument(err.to_string()))?;
+    println!(">> demo: venue and client on 127.0.0.1:{port}");
+
+    // -------------------------------------------------------------------------
+    // Venue (acceptor)
+    // -------------------------------------------------------------------------
+    let venue_settings = build_settings(port, &TRADING_COMP_IDS.map(String::from), STORE_DIR)?;
+    let venue = SellSideApp::new(
+        AuthPolicy::new()
+            .allow(TRADING_COMP_IDS[0])
+            .allow(TRADING_COMP_IDS[1])
+            .allow(DROP_COPY_COMP_ID),
+        MatchingEngine::new(VenueProfile::equities()),
+        DropCopy::new(DROP_COPY_COMP_ID),
+    );
+    let venue_app = Application::try_new(&venue)?;
+    let venue_store = MemoryMessageStoreFactory::new();
+    let venue_log = LogFactory::try_new(&NullLogger)?;
+    let mut acceptor = Acceptor::try_new(
+        &venue_settings,
+        &venue_app,
+        &venue_store,
+        &venue_log,
+        FixSocketServerKind::SingleThreaded,
+    )?;
+
+    // -------------------------------------------------------------------------
+    // Scripted client (initiator)
+    // -------------------------------------------------------------------------
+    let md_session = client_session(TRADING_COMP_IDS[0])?;
+    let order_session = client_session(TRADING_COMP_IDS[1])?;
+    let client_settings = build_client_settings(&[&md_session, &order_session], port)?;
+    let client = DemoClient::default();
+    let client_app = Application::try_new(&client)?;
+    let client_store = MemoryMessageStoreFactory::new();
+    let client_log = LogFactory::try_new(&NullLogger)?;
+    let mut initiator = Initiator::try_new(
+        &client_settings,
+        &client_app,
+        &client_store,
+        &client_log,
+        FixSocketServerKind::SingleThreaded,
+    )?;
+
+    acceptor.start()?;
+    initiator.start()?;
+
+    let mut results = Vec::new();
+
+    results.push(step("logon", || {
+        wait_until(|| client.logged_on_count() == 2)
+            .then_some(())
+            .ok_or_else(|| format!("{} of 2 sessions logged on", client.logged_on_count()))
+    }));
+
+    results.push(step("subscribe", || {
+        send(build_market_data_request("DEMO-MD"), &md_session)?;
+        client
+            .wait_for(|x| msg_type(x) == "W" && x.get_field(55).as_deref() == Some(SYMBOL))
+            .map(|_| ())
+            .ok_or_else(|| "no market data snapshot".to_string())
+    }));
+
+    let mut trade_exec_id = String::new();
+    results.push(step("trade", || {
+        send(
+            build_new_order("DEMO-1", Side::Sell, 100, 10.0),
+            &order_session,
+        )?;
+        send(
+            build_new_order("DEMO-2", Side::Buy, 100, 10.0),
+            &order_session,
+        )?;
+        for cl_ord_id in ["DEMO-1", "DEMO-2"] {
+            let report = client
+                .wait_for(|x| is_report(x, cl_ord_id, "2"))
+                .ok_or_else(|| format!("{cl_ord_id} not filled"))?;
+            trade_exec_id = report.get_field(17).unwrap_or_default();
+        }
+        Ok(())
+    }));
+
+    results.push(step("bust", || {
+        venue
+            .amend_trade(&trade_exec_id, None)
+            .map_err(|err| err.to_string())?;
+        for cl_ord_id in ["DEMO-1", "DEMO-2"] {
+            client
+                .wait_for(|x| {
+                    is_report(x, cl_ord_id, "4") && x.get_field(150).as_deref() == Some("H")
+                })
+                .ok_or_else(|| format!("{cl_ord_id} not busted"))?;
+        }
+        Ok(())
+    }));
+
+    results.push(step("cancel", || {
+        send(
+            build_new_order("DEMO-3", Side::Buy, 100, 9.5),
+            &order_session,
+        )?;
+        client
+            .wait_for(|x| is_report(x, "DEMO-3", "0"))
+            .ok_or_else(|| "DEMO-3 not acknowledged".to_string())?;
+
+        send(build_cancel("DEMO-4", "DEMO-3"), &order_session)?;
+        client
+            .wait_for(|x| is_report(x, "DEMO-4", "4"))
+            .map(|_| ())
+            .ok_or_else(|| "DEMO-3 not canceled".to_string())
+    }));
+
+    results.push(step("eod", || {
+        initiator.stop().map_err(|err| format!("{err:?}"))?;
+        wait_until(|| venue.logged_on_sessions().is_empty())
+            .then_some(())
+            .ok_or_else(|| format!("still logged on: {:?}", venue.logged_on_sessions()))
+    }));
+
+    // Harmless if the eod step already stopped it
+    let _ = initiator.stop();
+    acceptor.stop()?;
+
+    Ok(print_summary(&results))
+}
+
+/// Time one step, converting its error into a failed result
+fn step<F>(name: &'static str, body: F) -> StepResult
+where
+    F: FnOnce() -> Result<(), String>,
+{
+    let started = Instant::now();
+    let outcome = body();
+    let result = StepResult {
+        name,
+        passed: outcome.is_ok(),
+        elapsed: started.elapsed(),
+        detail: outcome.err().unwrap_or_default(),
+    };
+    println!(
+        ">> demo step {:<10} {}",
+        result.name,
+        if result.passed { "ok" } else { "FAILED" }
+    );
+    result
+}
+
+fn print_summary(results: &[StepResult]) -> bool {
+    println!();
+    println!("Demo summary");
+    println!("------------");
+    for result in results {
+        println!(
+            "  [{}] {:<10} {:>6} ms  {}",
+            if result.passed { "PASS" } else { "FAIL" },
+            result.name,
+            result.elapsed.as_millis(),
+            result.detail
+        );
+    }
+
+    let passed = results.iter().filter(|x| x.passed).count();
+    let all_passed = passed == results.len();
+    println!(
+        "{} ({passed}/{} steps)",
+        if all_passed { "PASSED" } else { "FAILED" },
+        results.len()
+    );
+    all_passed
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Ask the OS for a free TCP port
+fn ephemeral_port() -> std::io::Result<u16> {
+    // The listener is dropped right away; the acceptor binds the port next
+    Ok(TcpListener::bind(("127.0.0.1", 0))?.local_addr()?.port())
+}
+
+/// Poll a condition until it holds or the step timeout expires
+fn wait_until<F: Fn() -> bool>(condition: F) -> bool {
+    let started = Instant::now();
+    while !condition() {
+        if started.elapsed() > STEP_TIMEOUT {
+            return false;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    true
+}
+
+fn client_session(comp_id: &str) -> Result<SessionId, QuickFixError> {
+    SessionId::try_new(BEGIN_STRING, comp_id, VENUE_COMP_ID, "")
+}
+
+fn build_client_settings(
+    sessions: &[&SessionId],
+    port: u16,
+) -> Result<SessionSettings, QuickFixError> {
+    let mut settings = SessionSettings::new();
+    settings.set(
+        None,
+        Dictionary::try_from_items(&[
+            &ConnectionType::Initiator,
+            &ReconnectInterval(1),
+            &StartTime("00:00:00"),
+            &EndTime("00:00:00"),
+            &HeartBtInt(30),
+            &SocketConnectHost("127.0.0.1"),
+            &SocketConnectPort(port),
+            &DataDictionary("spec/FIX44.xml"),
+        ])?,
+    )?;
+    for session in sessions {
+        settings.set(Some(session), Dictionary::new())?;
+    }
+    Ok(settings)
+}
+
+/// Send a freshly built message, flattening both errors into a step failure
+fn send(msg: Result<Message, QuickFixError>, session: &SessionId) -> Result<(), String> {
+    msg.and_then(|msg| send_to_target(msg, session))
+        .map_err(|err| format!("send failed: {err:?}"))
+}
+
+fn msg_type(msg: &Message) -> String {
+    msg.with_header(|h| h.get_field(35)).unwrap_or_default()
+}
+
+/// ExecutionReport for `cl_ord_id` with the given OrdStatus
+fn is_report(msg: &Message, cl_ord_id: &str, ord_status: &str) -> bool {
+    msg_type(msg) == "8"
+        && msg.get_field(11).as_deref() == Some(cl_ord_id)
+        && msg.get_field(39).as_deref() == Some(ord_status)
+}
+
+fn build_market_data_request(md_req_id: &str) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "V"))?;
+    msg.set_field(262, md_req_id)?; // MDReqID
+    msg.set_field(263, "1")?; // SubscriptionRequestType: snapshot + updates
+    msg.set_field(264, "1")?; // MarketDepth: top of book
+
+    for entry_type in ["0", "1"] {
+        let mut group = Group::try_new(267, 269)?;
+        group.set_field(269, entry_type)?;
+        msg.add_group(&group)?;
+    }
+
+    let mut group = Group::try_new(146, 55)?;
+    group.set_field(55, SYMBOL)?;
+    msg.add_group(&group)?;
+
+    Ok(msg)
+}
+
+fn build_new_order(
+    cl_ord_id: &str,
+    side: Side,
+    quantity: u64,
+    price: f64,
+) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "D"))?;
+    msg.set_field(11, cl_ord_id)?;
+    msg.set_field(21, "1")?; // HandlInst: automated
+    msg.set_field(55, SYMBOL)?;
+    msg.set_field(54, side.as_fix())?;
+    msg.set_field(38, quantity.to_string().as_str())?;
+    msg.set_field(40, "2")?; // OrdType: Limit
+    msg.set_field(44, price.to_string().as_str())?;
+    msg.set_field(60, transact_time().as_str())?; // TransactTime
+    Ok(msg)
+}
+
+fn build_cancel(cl_ord_id: &str, orig_cl_ord_id: &str) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "F"))?;
+    msg.set_field(11, cl_ord_id)?;
+    msg.set_field(41, orig_cl_ord_id)?; // OrigClOrdID
+    msg.set_field(55, SYMBOL)?;
+    msg.set_field(54, Side::Buy.as_fix())?;
+    msg.set_field(60, transact_time().as_str())?; // TransactTime
+    Ok(msg)
+}
@@ -0,0 +1,54 @@
+// =============================================================================
+// Drop Copy
+// =============================================================================
+// A drop copy session receives a copy of every ExecutionReport the venue
+// sends, whatever the originating session. Clearing firms, risk desks and
+// back offices use it to follow activity they do not own.
+//
+// Copies carry the originating CompID in Account (tag 1) so the receiver can
+// attribute each report.
+// =============================================================================
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use quickfix::{send_to_target, FieldMap, Message, QuickFixError, SessionId};
+
+use crate::config::{counterparty_comp_id, counterparty_session};
+
+pub struct DropCopy {
+    comp_id: String,
+    logged_on: AtomicBool,
+}
+
+impl DropCopy {
+    pub fn new(comp_id: &str) -> Self {
+        Self {
+            comp_id: comp_id.to_string(),
+            logged_on: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether `session` is the drop copy session
+    pub fn is_session(&self, session: &SessionId) -> bool {
+        counterparty_comp_id(session) == self.comp_id
+    }
+
+    pub fn set_logged_on(&self, logged_on: bool) {
+        self.logged_on.store(logged_on, Ordering::Relaxed);
+    }
+
+    /// Forward a copy of an ExecutionReport sent to the `owner` CompID
+    ///
+    /// Copies are dropped while the drop copy session is down: QuickFIX would
+    /// refuse to send them anyway, and the receiver is expected to resync with
+    /// an OrderMassStatusRequest after logon.
+    pub fn forward(&self, report: &Message, owner: &str) -> Result<(), QuickFixError> {
+        if !self.logged_on.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let mut copy = report.clone();
+        copy.set_field(1, owner)?;
+        send_to_target(copy, &counterparty_session(&self.comp_id)?)
+    }
+}
@@ -0,0 +1,188 @@
+// =============================================================================
+// End-of-Day Reports and Delivery
+// =============================================================================
+// Besides the confirmations of each account (confirmations.rs), EOD writes
+// the venue's own files of the day, from the trades ledger (ledger.rs):
+//
+//   <dir>/<YYYYMMDD>/trades.csv      every trade still standing
+//   <dir>/<YYYYMMDD>/positions.csv   bought, sold and net per account / symbol
+//   <dir>/<YYYYMMDD>/fees.csv        venue fees per account, --fee-bps of the
+//                                    notional traded
+//
+// These three are written on quiet days too, with their header alone, so a
+// counterparty can tell a day without trades from a file that never came.
+//
+// With --eod-deliver destinations, the files and the confirmations are then
+// pushed to each of them, a drop directory or an SFTP server, with checksum
+// sidecars and a receipt per file (trading::gateway::delivery). Receipts go
+// to --eod-receipts, default <dir>/deliveries.jsonl.
+// =============================================================================
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use trading::{
+    csv::csv_field,
+    gateway::delivery::{DeliveryJob, Receipt},
+    sim::matching::Side,
+    time::{time_of_day, Date},
+};
+
+use crate::{
+    confirmations::{side_name, Confirmation},
+    ledger::Trade,
+};
+
+/// Fees and delivery of the EOD files
+#[derive(Debug, Clone, Default)]
+pub struct EodSettings {
+    /// Venue fee, in basis points of the notional
+    pub fee_bps: f64,
+
+    /// No destination: files are written, not delivered
+    pub delivery: DeliveryJob,
+}
+
+/// What an EOD run wrote and delivered
+#[derive(Debug, Clone, Default)]
+pub struct EodRun {
+    pub confirmations: Vec<Confirmation>,
+
+    /// trades.csv, positions.csv and fees.csv
+    pub reports: Vec<PathBuf>,
+    pub receipts: Vec<Receipt>,
+}
+
+impl EodRun {
+    /// Reports and confirmation files, in delivery order
+    pub fn files(&self) -> Vec<PathBuf> {
+        let confirmations = self
+            .confirmations
+            .iter()
+            .flat_map(|x| [x.csv.clone(), x.html.clone()]);
+        self.reports.iter().cloned().chain(confirmations).collect()
+    }
+}
+
+/// Write the reports of `date` in `<dir>/<YYYYMMDD>/`
+pub fn write_reports(
+    trades: &[Trade],
+    date: Date,
+    dir: &Path,
+    settings: &EodSettings,
+) -> io::Result<Vec<PathBuf>> {
+    let dir = dir.join(date.to_fix());
+    fs::create_dir_all(&dir)?;
+    let reports = [
+        ("trades.csv", render_trades(trades, date)),
+        ("positions.csv", render_positions(trades, date)),
+        ("fees.csv", render_fees(trades, date, settings.fee_bps)),
+    ];
+    let mut paths = Vec::new();
+    for (name, content) in reports {
+        let path = dir.join(name);
+        fs::write(&path, content)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+// =============================================================================
+// Reports
+// =============================================================================
+
+fn render_trades(trades: &[Trade], date: Date) -> String {
+    let mut out = String::from(
+        "trade_date,time_utc,exec_id,match_id,account,order_id,cl_ord_id,symbol,side,\
+         quantity,price,notional\n",
+    );
+    for trade in trades {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{},{:.2}",
+            date.to_iso(),
+            time_of_day(trade.time),
+            csv_field(&trade.exec_id),
+            csv_field(&trade.match_id),
+            csv_field(&trade.account),
+            csv_field(&trade.order_id),
+            csv_field(&trade.cl_ord_id),
+            csv_field(&trade.symbol),
+            side_name(trade.side),
+            trade.quantity,
+            trade.price,
+            trade.notional()
+        );
+    }
+    out
+}
+
+#[derive(Default)]
+struct Position {
+    bought: u64,
+    sold: u64,
+    buy_notional: f64,
+    sell_notional: f64,
+}
+
+fn render_positions(trades: &[Trade], date: Date) -> String {
+    let mut positions: BTreeMap<(&str, &str), Position> = BTreeMap::new();
+    for trade in trades {
+        let entry = positions
+            .entry((&trade.account, &trade.symbol))
+            .or_default();
+        match trade.side {
+            Side::Buy => {
+                entry.bought += trade.quantity;
+                entry.buy_notional += trade.notional();
+            }
+            Side::Sell => {
+                entry.sold += trade.quantity;
+                entry.sell_notional += trade.notional();
+            }
+        }
+    }
+
+    let mut out =
+        String::from("trade_date,account,symbol,bought,sold,net,buy_notional,sell_notional\n");
+    for ((account, symbol), x) in positions {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{:.2},{:.2}",
+            date.to_iso(),
+            csv_field(account),
+            csv_field(symbol),
+            x.bought,
+            x.sold,
+            x.bought as i64 - x.sold as i64,
+            x.buy_notional,
+            x.sell_notional
+        );
+    }
+    out
+}
+
+fn render_fees(trades: &[Trade], date: Date, fee_bps: f64) -> String {
+    let mut by_account: BTreeMap<&str, (usize, f64)> = BTreeMap::new();
+    for trade in trades {
+        let entry = by_account.entry(&trade.account).or_default();
+        entry.0 += 1;
+        entry.1 += trade.notional();
+    }
+
+    let mut out = String::from("trade_date,account,trades,notional,fee_bps,fees\n");
+    for (account, (count, notional)) in by_account {
+        let _ = writeln!(
+            out,
+            "{},{},{count},{notional:.2},{fee_bps},{:.2}",
+            date.to_iso(),
+            csv_field(account),
+            notional * fee_bps / 10_000.0
+        );
+    }
+    out
+}
@@ -0,0 +1,299 @@
+// =============================================================================
+// Trades Ledger
+// =============================================================================
+// Every fill the venue reports, one entry per side and execution, in the
+// order they happened. It is the books of record for the back office: the
+// end-of-day confirmations (confirmations.rs) are generated from it.
+//
+// The account of a trade is the CompID of the session that sent the order,
+// as in the Account (1) of the drop copy.
+//
+// Entries are never removed. A bust marks the trade Busted; a correction
+// marks it Corrected and appends the corrected trade, which points back at
+// it (`exec_ref_id`). Each amended entry names the ExecID that amended it,
+// so the whole chain can be walked from any of its ExecIDs. Only Active
+// trades are confirmed.
+// =============================================================================
+
+use std::{error::Error, fmt, sync::Mutex};
+
+use trading::{
+    json::json_escape,
+    sim::matching::{ExecEvent, ExecKind, Side},
+    time::{unix_now, Date},
+};
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum AmendError {
+    /// No trade with this ExecID
+    UnknownTrade(String),
+
+    /// The trade was already busted or corrected
+    AlreadyAmended(String),
+
+    /// A corrected quantity of zero (bust instead) or a price that is not
+    /// positive
+    InvalidCorrection,
+}
+
+impl fmt::Display for AmendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmendError::UnknownTrade(x) => write!(f, "no trade with ExecID {x}"),
+            AmendError::AlreadyAmended(x) => write!(f, "trade {x} was already busted or corrected"),
+            AmendError::InvalidCorrection => {
+                write!(f, "corrected quantity and price must be positive")
+            }
+        }
+    }
+}
+
+impl Error for AmendError {}
+
+// =============================================================================
+// Trades
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeStatus {
+    Active,
+    Busted,
+    Corrected,
+}
+
+impl TradeStatus {
+    /// Lower-case name used in JSON
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TradeStatus::Active => "active",
+            TradeStatus::Busted => "busted",
+            TradeStatus::Corrected => "corrected",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub exec_id: String,
+
+    /// Shared with the other side of the same match
+    pub match_id: String,
+    pub account: String,
+    pub order_id: String,
+    pub cl_ord_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: u64,
+    pub price: f64,
+
+    /// Unix seconds
+    pub time: i64,
+
+    pub status: TradeStatus,
+
+    /// ExecID of the trade this one corrects
+    pub exec_ref_id: Option<String>,
+
+    /// ExecID of the bust or correct report that amended this trade
+    pub amended_by: Option<String>,
+}
+
+impl Trade {
+    pub fn notional(&self) -> f64 {
+        self.quantity as f64 * self.price
+    }
+
+    pub fn trade_date(&self) -> Date {
+        Date::from_unix(self.time)
+    }
+
+    /// JSON object for the admin API
+    pub fn to_json(&self) -> String {
+        let optional = |x: &Option<String>| {
+            x.as_ref()
+                .map_or("null".to_string(), |x| format!("\"{}\"", json_escape(x)))
+        };
+        format!(
+            "{{\"exec_id\":\"{}\",\"match_id\":\"{}\",\"account\":\"{}\",\"order_id\":\"{}\",\
+             \"cl_ord_id\":\"{}\",\"symbol\":\"{}\",\"side\":\"{}\",\"quantity\":{},\"price\":{},\
+             \"time\":{},\"status\":\"{}\",\"exec_ref_id\":{},\"amended_by\":{}}}",
+            json_escape(&self.exec_id),
+            json_escape(&self.match_id),
+            json_escape(&self.account),
+            json_escape(&self.order_id),
+            json_escape(&self.cl_ord_id),
+            json_escape(&self.symbol),
+            match self.side {
+                Side::Buy => "buy",
+                Side::Sell => "sell",
+            },
+            self.quantity,
+            self.price,
+            self.time,
+            self.status.as_str(),
+            optional(&self.exec_ref_id),
+            optional(&self.amended_by)
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct TradeLedger {
+    trades: Mutex<Vec<Trade>>,
+}
+
+impl TradeLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the event if it is a fill; others are ignored
+    pub fn record(&self, event: &ExecEvent, exec_id: &str) {
+        if event.kind != ExecKind::Trade {
+            return;
+        }
+        let order = &event.order;
+        self.trades.lock().expect("ledger lock poisoned").push(Trade {
+            exec_id: exec_id.to_string(),
+            match_id: event.match_id.clone().unwrap_or_default(),
+            account: order.owner.clone(),
+            order_id: order.order_id.clone(),
+            cl_ord_id: order.cl_ord_id.clone(),
+            symbol: order.symbol.clone(),
+            side: order.side,
+            quantity: event.last_qty,
+            price: event.last_px,
+            time: unix_now(),
+            status: TradeStatus::Active,
+            exec_ref_id: None,
+            amended_by: None,
+        });
+    }
+
+    /// Trades of one day still standing, in execution order
+    pub fn trades_on(&self, date: Date) -> Vec<Trade> {
+        self.trades
+            .lock()
+            .expect("ledger lock poisoned")
+            .iter()
+            .filter(|x| x.status == TradeStatus::Active && x.trade_date() == date)
+            .cloned()
+            .collect()
+    }
+
+    /// Both sides of the match `exec_id` belongs to, that side first
+    ///
+    /// # Errors
+    /// If the trade is unknown or was already amended
+    pub fn match_of(&self, exec_id: &str) -> Result<Vec<Trade>, AmendError> {
+        let trades = self.trades.lock().expect("ledger lock poisoned");
+        let trade = trades
+            .iter()
+            .find(|x| x.exec_id == exec_id)
+            .ok_or_else(|| AmendError::UnknownTrade(exec_id.to_string()))?;
+
+        if trade.status != TradeStatus::Active {
+            return Err(AmendError::AlreadyAmended(exec_id.to_string()));
+        }
+
+        // Earlier versions of a corrected match share its id: skip them
+        let mut sides = vec![trade.clone()];
+        sides.extend(
+            trades
+                .iter()
+                .filter(|x| !x.match_id.is_empty() && x.match_id == trade.match_id)
+                .filter(|x| x.exec_id != trade.exec_id && x.status == TradeStatus::Active)
+                .cloned(),
+        );
+        Ok(sides)
+    }
+
+    /// Bust (`correction` None) or correct an active trade
+    ///
+    /// # Arguments
+    /// * `exec_id` - The trade
+    /// * `amend_exec_id` - ExecID of the bust / correct report, and of the
+    ///   corrected trade
+    /// * `correction` - Corrected quantity and price
+    ///
+    /// # Returns
+    /// The corrected trade, None for a bust
+    pub fn amend(
+        &self,
+        exec_id: &str,
+        amend_exec_id: &str,
+        correction: Option<(u64, f64)>,
+    ) -> Result<Option<Trade>, AmendError> {
+        let mut trades = self.trades.lock().expect("ledger lock poisoned");
+        let trade = trades
+            .iter_mut()
+            .find(|x| x.exec_id == exec_id)
+            .ok_or_else(|| AmendError::UnknownTrade(exec_id.to_string()))?;
+        if trade.status != TradeStatus::Active {
+            return Err(AmendError::AlreadyAmended(exec_id.to_string()));
+        }
+
+        trade.amended_by = Some(amend_exec_id.to_string());
+        let Some((quantity, price)) = correction else {
+            trade.status = TradeStatus::Busted;
+            return Ok(None);
+        };
+        trade.status = TradeStatus::Corrected;
+
+        // Same trade date as the original: a correction does not move it
+        let corrected = Trade {
+            exec_id: amend_exec_id.to_string(),
+            quantity,
+            price,
+            status: TradeStatus::Active,
+            exec_ref_id: Some(exec_id.to_string()),
+            amended_by: None,
+            ..trade.clone()
+        };
+        trades.push(corrected.clone());
+        Ok(Some(corrected))
+    }
+
+    /// The trade `exec_id` belongs to and all its amendments, oldest first
+    pub fn chain(&self, exec_id: &str) -> Vec<Trade> {
+        let trades = self.trades.lock().expect("ledger lock poisoned");
+        let find = |id: &str| trades.iter().find(|x| x.exec_id == id);
+
+        let Some(mut first) = find(exec_id) else {
+            return Vec::new();
+        };
+        while let Some(previous) = first.exec_ref_id.as_deref().and_then(find) {
+            first = previous;
+        }
+
+        let mut chain = vec![first.clone()];
+        let mut current = first;
+        while let Some(next) = current.amended_by.as_deref().and_then(find) {
+            chain.push(next.clone());
+            current = next;
+        }
+        chain
+    }
+
+    /// CumQty and AvgPx of an order over its active trades
+    pub fn order_totals(&self, order_id: &str) -> (u64, f64) {
+        let trades = self.trades.lock().expect("ledger lock poisoned");
+        let (quantity, notional) = trades
+            .iter()
+            .filter(|x| x.order_id == order_id && x.status == TradeStatus::Active)
+            .fold((0, 0.0), |(quantity, notional), x| {
+                (quantity + x.quantity, notional + x.notional())
+            });
+        let avg_px = if quantity == 0 {
+            0.0
+        } else {
+            notional / quantity as f64
+        };
+        (quantity, avg_px)
+    }
+}
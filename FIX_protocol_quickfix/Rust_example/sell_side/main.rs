@@ -0,0 +1,641 @@
+// =============================================================================
+// QuickFIX Rust Example: Full Sell-Side / Exchange Stack
+// =============================================================================
+// The counterpart of the buy_side example: a small simulated venue that the
+// buy-side stack, the REPL and integration tests can all connect to.
+//
+//   multi-session acceptor (one port, several CompIDs, a thread each)
+//     -> inbound throttle (venue profile rate limits)
+//     -> auth policy on Logon
+//     -> matching engine (price-time priority, venue profile rules)
+//     -> ExecutionReports to the owner + drop copy session
+//     -> market data publisher (35=W snapshots to subscribers)
+//     -> quote engine (35=R answered with 35=S around the book's mid)
+//     -> allocation instructions (35=J checked, answered with 35=P)
+//     -> reference data (35=c / 35=x answered from the venue profile)
+//     -> surveillance alerts
+//     -> admin HTTP API (status, books, alerts, halt/resume, eod)
+//     -> operator audit log (admin API and console commands)
+//     -> trades ledger -> end-of-day confirmations (CSV/HTML, optional mail)
+//     -> end-of-day reports (trades, positions, fees) delivered to drop
+//        directories or SFTP, with checksums and receipts
+//
+// For more counterparties than one process serves well, 'coordinator N'
+// spreads them over N worker processes of this binary, one admin API in
+// front of them all (see coordinator.rs).
+//
+// Key Learning Points:
+// 1. Serving several counterparties from a single acceptor
+// 2. Rejecting logons from the application layer
+// 3. Turning matching results into FIX ExecutionReports
+// 4. Fan-out of the same event to owner, drop copy and market data
+// 5. Sharding sessions over processes behind one admin API
+//
+// The console runs on a tokio runtime so CTRL-C / SIGTERM stop the venue as
+// cleanly as typing 'q'. Typing 'eod' writes the day's trade confirmations
+// and reports, which are written once more on the way out. With
+// --eod-deliver, they are then pushed to each destination given.
+//
+// Admin API requests that change something (every POST) and console
+// commands are appended to commands.jsonl in --audit-dir (default: audit).
+//
+// QuoteRequests are priced --quote-spread-bps apart (default 10) around the
+// mid of the book, else the last trade, else a --quote-ref SYMBOL=PX; quotes
+// are --quote-size (default 100 or a lot) and live --quote-valid-secs
+// (default 30).
+//
+// With --state, market data subscriptions are kept in a state store
+// (file:DIR, sqlite:PATH, redis://HOST) and restored on start; with
+// --state-key FILE, or the key in TRADING_STORE_KEY, encrypted.
+//
+// Alert rules are expressions over inbound messages (trading::expr), given
+// with --alert-rule NAME=EXPR or a file of them with --alert-rules: every
+// message one matches raises a surveillance alert, on GET /alerts.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    process::exit,
+    sync::Arc,
+    time::Duration,
+};
+
+use quickfix::{
+    Acceptor, Application, ConnectionHandler, FileMessageStoreFactory, LogFactory, QuickFixError,
+    StdLogger,
+};
+
+use trading::{
+    audit::{local_operator, AuditEntry, AuditLog, AuditSource, DEFAULT_AUDIT_DIR},
+    expr::{self, Rule},
+    gateway::{
+        delivery::{DeliveryJob, Destination},
+        shards::ShardSpec,
+        smtp::SmtpSink,
+    },
+    quotes::{QuoteEngine, QuoteSettings},
+    session::{
+        dictionary::Dictionary,
+        runtime::{shutdown_signal, stdin_lines},
+        socket_server_kind,
+    },
+    sim::{matching::MatchingEngine, venue::VenueProfile},
+    store::{
+        self,
+        encrypted::{EncryptedStore, EncryptionKey},
+        ScopedStore, StateStore,
+    },
+};
+
+use crate::{
+    app::SellSideApp,
+    auth::AuthPolicy,
+    config::{build_settings, DROP_COPY_COMP_ID, STORE_DIR, TRADING_COMP_IDS},
+    confirmations::ConfirmationSettings,
+    drop_copy::DropCopy,
+    eod::EodSettings,
+};
+
+// Matching, venue rules and market data come from the trading library
+mod admin; // Admin HTTP API
+mod app; // FIX callbacks and component wiring
+mod auth; // Logon authorization
+mod config; // Programmatic session settings
+mod confirmations; // End-of-day trade confirmations
+mod coordinator; // Worker processes behind one admin API
+mod demo; // Two-node smoke test scenario
+mod drop_copy; // Drop copy forwarding
+mod eod; // End-of-day reports and delivery
+mod ledger; // Trades ledger
+mod surveillance; // Surveillance alerts
+
+// =============================================================================
+// Main Entry Point
+// =============================================================================
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), QuickFixError> {
+    // =========================================================================
+    // Step 1: Parse Command-Line Arguments
+    // =========================================================================
+    // Optional args: [port] [equities|futures|fx] [admin_port]
+    //           or: demo   (self-contained smoke test, see demo.rs)
+    //           or: coordinator N [port] [profile] [admin_port]
+    //               [--comp-ids LIST] [-- OPTIONS]   (see coordinator.rs)
+    //
+    // Counterparties:
+    //   --comp-ids A,B,...          trading CompIDs (default: BUYSIDE_MD,BUYSIDE_ORD)
+    //   --shard I/N                 serve the CompIDs of shard I only (coordinator
+    //                               workers)
+    //
+    // Confirmation options, anywhere on the line:
+    //   --confirms-dir DIR          where the files go (default: confirmations)
+    //   --smtp HOST:PORT            mail relay, files only without one
+    //   --smtp-from ADDR            sender (default: confirms@localhost)
+    //   --confirm-to ACCOUNT=ADDR   recipient, repeatable
+    //
+    // End-of-day reports and delivery:
+    //   --fee-bps BPS               venue fee in fees.csv (default: 0)
+    //   --eod-deliver DEST          drop directory or sftp://[USER@]HOST[:PORT]/PATH,
+    //                               repeatable
+    //   --sftp-identity FILE        private key for the SFTP destinations
+    //   --eod-receipts FILE         default: <confirms-dir>/deliveries.jsonl
+    //
+    //   --audit-dir DIR             operator audit log (default: audit)
+    //   --state URL                 state store (default: none)
+    //   --state-key FILE            encrypt its values with this key
+    //                               (default: TRADING_STORE_KEY, if set)
+    //
+    // Surveillance:
+    //   --alert-rule NAME=EXPR      alert on the inbound messages matching EXPR,
+    //                               repeatable
+    //   --alert-rules FILE          one NAME = EXPR per line
+    //
+    // Quote options:
+    //   --quote-spread-bps BPS      offer minus bid (default: 10)
+    //   --quote-size QTY            size when the request has none
+    //   --quote-valid-secs S        life of a quote (default: 30)
+    //   --quote-ref SYMBOL=PX       reference without a book, repeatable
+    // =========================================================================
+
+    let mut args: Vec<_> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("coordinator") {
+        coordinator::run(args).await;
+        return Ok(());
+    }
+
+    let confirmations = take_confirmation_flags(&mut args);
+    let eod = take_eod_flags(&mut args, &confirmations.dir);
+    let audit_dir =
+        PathBuf::from(take_flag(&mut args, "--audit-dir").unwrap_or(DEFAULT_AUDIT_DIR.into()));
+    let state_url = take_flag(&mut args, "--state");
+    let state_key = take_flag(&mut args, "--state-key").map(PathBuf::from);
+    let quote_flags = take_quote_flags(&mut args);
+    let alert_rules = take_alert_rules(&mut args);
+    let shard = take_flag(&mut args, "--shard").map(|x| match x.parse::<ShardSpec>() {
+        Ok(shard) => shard,
+        Err(err) => {
+            eprintln!("--shard: {err}");
+            exit(1);
+        }
+    });
+    let mut comp_ids = take_comp_ids(&mut args);
+    if let Some(shard) = shard {
+        comp_ids.retain(|x| shard.owns(x));
+    }
+
+    if args.get(1).map(String::as_str) == Some("demo") {
+        let passed = demo::run()?;
+        exit(if passed { 0 } else { 1 });
+    }
+
+    let port = args.get(1).and_then(|x| x.parse().ok()).unwrap_or(5001);
+    let profile_name = args.get(2).map_or("equities", String::as_str);
+    let admin_port = args.get(3).and_then(|x| x.parse().ok()).unwrap_or(8081);
+
+    let Some(profile) = VenueProfile::by_name(profile_name) else {
+        eprintln!("Unknown venue profile: {profile_name} (expected equities, futures or fx)");
+        exit(1);
+    };
+
+    // =========================================================================
+    // Step 2: Build Configuration and Components
+    // =========================================================================
+
+    println!(
+        ">> Configuring sell-side stack: port={port} profile={}",
+        profile.name
+    );
+    let store_path = match shard {
+        Some(shard) => {
+            println!(">> shard {shard}: {} and {DROP_COPY_COMP_ID}", comp_ids.join(" "));
+            format!("{STORE_DIR}/shard-{}", shard.index)
+        }
+        None => STORE_DIR.to_string(),
+    };
+    let settings = build_settings(port, &comp_ids, &store_path)?;
+
+    let store_factory = FileMessageStoreFactory::try_new(&settings)?;
+    let log_factory = LogFactory::try_new(&StdLogger::Stdout)?;
+
+    // Passwords are optional, read from FIX_PASSWORD_<COMPID> variables
+    let auth = comp_ids
+        .iter()
+        .map(String::as_str)
+        .chain([DROP_COPY_COMP_ID])
+        .fold(AuthPolicy::new(), |policy, comp_id| {
+            match env::var(format!("FIX_PASSWORD_{comp_id}")) {
+                Ok(password) => policy.allow_with_password(comp_id, &password),
+                Err(_) => policy.allow(comp_id),
+            }
+        });
+
+    let quotes = quote_flags.engine(&profile);
+    let mut sell_side = SellSideApp::new(
+        auth,
+        MatchingEngine::new(profile),
+        DropCopy::new(DROP_COPY_COMP_ID),
+    )
+    .with_confirmations(confirmations)
+    .with_eod(eod)
+    .with_quotes(quotes)
+    .with_alert_rules(alert_rules);
+    if let Some(shard) = shard {
+        sell_side = sell_side.with_exec_id_prefix(&format!("S{}-", shard.index));
+    }
+    if let Some(url) = state_url {
+        // The shards of a coordinator share the URL, each in its own namespaces
+        let state = store::open(&url).and_then(|state| match shard {
+            Some(shard) => ScopedStore::new(state, &format!("shard-{}", shard.index))
+                .map(|x| Arc::new(x) as Arc<dyn StateStore>),
+            None => Ok(state),
+        });
+        let state = state.and_then(|state| {
+            let key = EncryptionKey::load(state_key.as_deref())?;
+            Ok(EncryptedStore::wrap(state, key.as_ref()))
+        });
+        match state.and_then(|state| sell_side.with_store(state)) {
+            Ok(restored) => {
+                sell_side = restored;
+                println!(">> state in {url}");
+            }
+            Err(err) => {
+                eprintln!("Cannot restore state from {url}: {err}");
+                exit(1);
+            }
+        }
+    }
+    let callbacks = Arc::new(sell_side);
+    let app = Application::try_new(callbacks.as_ref())?;
+
+    let audit = match AuditLog::open(&audit_dir) {
+        Ok(log) => Arc::new(log),
+        Err(err) => {
+            eprintln!("Cannot open the audit log in {}: {err}", audit_dir.display());
+            exit(1);
+        }
+    };
+    if let Err(err) = admin::spawn_server(admin_port, Arc::clone(&callbacks), Arc::clone(&audit)) {
+        eprintln!("Cannot start admin API: {err}");
+        exit(1);
+    }
+
+    // A thread per session: a session slowed down by the throttle does not
+    // hold the others up (the matching engine, books and OMS are shared
+    // behind locks, which socket_server_kind checks)
+    let mut acceptor = Acceptor::try_new(
+        &settings,
+        &app,
+        &store_factory,
+        &log_factory,
+        socket_server_kind(callbacks.as_ref(), true),
+    )?;
+
+    // =========================================================================
+    // Step 3: Run Until User Quits
+    // =========================================================================
+
+    println!(">> connection handler START");
+    acceptor.start()?;
+
+    println!(">> Venue running, type 'eod' for confirmations and reports, 'q' to quit (or CTRL-C)");
+    let mut lines = stdin_lines();
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            line = lines.recv() => match line {
+                Some(line) if line.trim() == "q" => {
+                    audit_console(&audit, "q", Ok(()));
+                    break;
+                }
+                Some(line) if line.trim() == "eod" => {
+                    let result = callbacks.run_eod().map(|_| ()).map_err(|x| x.to_string());
+                    if let Err(err) = &result {
+                        eprintln!("EOD failed: {err}");
+                    }
+                    audit_console(&audit, "eod", result);
+                }
+                Some(_) => {}
+                None => break,
+            },
+            () = &mut shutdown => {
+                println!(">> Shutdown signal received");
+                break;
+            }
+        }
+    }
+
+    // =========================================================================
+    // Step 4: Graceful Shutdown
+    // =========================================================================
+
+    println!(">> connection handler STOP");
+    acceptor.stop()?;
+
+    if let Err(err) = callbacks.run_eod() {
+        eprintln!("EOD failed: {err}");
+    }
+
+    println!(">> All cleared. Bye !");
+    Ok(())
+}
+
+// =============================================================================
+// Usage Examples
+// =============================================================================
+//
+// Run an equities venue on the default ports (FIX 5001, admin 8081):
+//   cargo run --example sell_side
+//
+// Run a futures venue on custom ports:
+//   cargo run --example sell_side -- 6001 futures 9081
+//
+// Require a password from the order entry session:
+//   FIX_PASSWORD_BUYSIDE_ORD=secret cargo run --example sell_side
+//
+// Connect the buy-side stack to it:
+//   cargo run --example buy_side -- 127.0.0.1 5001
+//
+// Run the self-contained smoke test (venue + scripted client, one process):
+//   cargo run --example sell_side -- demo
+//
+// Query the admin API:
+//   curl http://localhost:8081/status
+//   curl http://localhost:8081/books
+//   curl -X POST http://localhost:8081/halt/AAPL
+//
+// Check the inbound rate limits of the profile and who is near them:
+//   curl http://localhost:8081/throttle
+//   curl http://localhost:8081/metrics
+//
+// Bust or correct a trade (ExecIDs are in the ExecutionReports and the
+// confirmations), then audit it:
+//   curl -X POST http://localhost:8081/bust/E12
+//   curl -X POST http://localhost:8081/correct/E12 -d '{"quantity":50,"price":150.25}'
+//   curl http://localhost:8081/trades/E12
+//
+// Mail the end-of-day confirmations through a local relay (e.g. MailHog):
+//   cargo run --example sell_side -- --smtp 127.0.0.1:1025 \
+//       --confirm-to BUYSIDE_ORD=ops@example.com
+//   curl -X POST http://localhost:8081/eod
+//
+// Deliver the EOD files to a drop directory and a counterparty's SFTP server
+// (checksum sidecars next to each file, receipts in deliveries.jsonl):
+//   cargo run --example sell_side -- --fee-bps 0.5 --eod-deliver /srv/drop/acme \
+//       --eod-deliver sftp://eod@files.acme.com/inbound --sftp-identity ~/.ssh/eod_key
+//   curl -X POST http://localhost:8081/eod
+//   tail confirmations/deliveries.jsonl
+//
+// Name the operator of an admin request in the audit log, then read it:
+//   curl -X POST -H 'X-Operator: alice' http://localhost:8081/halt/AAPL
+//   tail audit/commands.jsonl
+//
+// Keep market data subscriptions across restarts of the venue:
+//   cargo run --example sell_side -- --state file:venue_state
+//   cargo run --example sell_side -- --state file:venue_state --state-key venue.key
+//
+// Spread 200 counterparties over 4 workers (FIX 5001-5004, admin 8082-8085),
+// one admin API on 8081 for them all, a 0.5 bps fee on every worker:
+//   cargo run --example sell_side -- coordinator 4 5001 equities 8081 \
+//       --comp-ids "$(paste -sd, counterparties.txt)" -- --fee-bps 0.5
+//   curl http://localhost:8081/shards
+//   curl http://localhost:8081/metrics
+//
+// Alert on large orders, and on executions rejected for a limit:
+//   cargo run --example sell_side -- \
+//       --alert-rule 'large_order=msg.35 == "D" && qty > 10000' \
+//       --alert-rule 'limit_reject=msg.35 == "8" && msg.ExecType == "8" && msg.58 contains "limit"'
+//   curl http://localhost:8081/alerts
+//
+// Quote 5 bps wide, 500 at a time, EURUSD around 1.085 until it trades:
+//   cargo run --example sell_side -- --quote-spread-bps 5 --quote-size 500 \
+//       --quote-ref EURUSD=1.085
+//   curl http://localhost:8081/quotes
+//
+// =============================================================================
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Append a console command to the audit log, with the local user as the
+/// actor
+fn audit_console(audit: &AuditLog, command: &str, result: Result<(), String>) {
+    let entry = AuditEntry::new(
+        AuditSource::Console,
+        &local_operator(),
+        command,
+        result.is_ok(),
+        result.as_ref().err().map_or("OK", String::as_str),
+    );
+    if let Err(err) = audit.record(&entry) {
+        eprintln!("Cannot write to {}: {err}", audit.path().display());
+    }
+}
+
+/// Remove `flag <value>` from the arguments, returning the value
+fn take_flag(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let index = args.iter().position(|x| x == flag)?;
+    if index + 1 >= args.len() {
+        eprintln!("{flag} requires a value");
+        exit(1);
+    }
+
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Some(value)
+}
+
+/// Remove --comp-ids A,B,...: the trading counterparties, BUYSIDE_MD and
+/// BUYSIDE_ORD without it
+fn take_comp_ids(args: &mut Vec<String>) -> Vec<String> {
+    let Some(list) = take_flag(args, "--comp-ids") else {
+        return TRADING_COMP_IDS.map(String::from).to_vec();
+    };
+    let comp_ids: Vec<String> = list
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(String::from)
+        .collect();
+    if comp_ids.is_empty() || comp_ids.iter().any(|x| x == DROP_COPY_COMP_ID) {
+        eprintln!("--comp-ids expects a comma separated list, without {DROP_COPY_COMP_ID}: {list}");
+        exit(1);
+    }
+    comp_ids
+}
+
+/// Remove --alert-rule NAME=EXPR (repeatable) and --alert-rules FILE
+fn take_alert_rules(args: &mut Vec<String>) -> Vec<Rule> {
+    let dictionary = Dictionary::standard();
+    let mut rules = match take_flag(args, "--alert-rules") {
+        Some(path) => match expr::load_rules(Path::new(&path), &dictionary) {
+            Ok(rules) => rules,
+            Err(err) => {
+                eprintln!("--alert-rules: {err}");
+                exit(1);
+            }
+        },
+        None => Vec::new(),
+    };
+    while let Some(rule) = take_flag(args, "--alert-rule") {
+        match Rule::parse_with(&rule, &dictionary) {
+            Ok(rule) if rules.iter().any(|x| x.name == rule.name) => {
+                eprintln!("--alert-rule: rule {} defined twice", rule.name);
+                exit(1);
+            }
+            Ok(rule) => rules.push(rule),
+            Err(err) => {
+                eprintln!("--alert-rule {rule}: {err}");
+                exit(1);
+            }
+        }
+    }
+    for rule in &rules {
+        println!(">> alert rule {rule}");
+    }
+    rules
+}
+
+/// Quote engine options, applied over the venue's defaults
+struct QuoteFlags {
+    spread_bps: Option<f64>,
+    size: Option<u64>,
+    valid_for: Option<Duration>,
+    references: Vec<(String, f64)>,
+}
+
+impl QuoteFlags {
+    fn engine(self, profile: &VenueProfile) -> QuoteEngine {
+        let mut settings = QuoteSettings::for_venue(profile);
+        if let Some(spread_bps) = self.spread_bps {
+            settings = settings.with_spread_bps(spread_bps);
+        }
+        if let Some(size) = self.size {
+            settings = settings.with_default_size(size);
+        }
+        if let Some(valid_for) = self.valid_for {
+            settings = settings.with_valid_for(valid_for);
+        }
+        self.references
+            .iter()
+            .fold(QuoteEngine::new(settings), |engine, (symbol, price)| {
+                engine.with_reference(symbol, *price)
+            })
+    }
+}
+
+/// Remove the quote flags
+///
+/// --quote-ref may be repeated, once per symbol.
+fn take_quote_flags(args: &mut Vec<String>) -> QuoteFlags {
+    fn number<T: std::str::FromStr>(args: &mut Vec<String>, flag: &str) -> Option<T> {
+        let value = take_flag(args, flag)?;
+        match value.parse() {
+            Ok(x) => Some(x),
+            Err(_) => {
+                eprintln!("{flag} expects a number: {value}");
+                exit(1);
+            }
+        }
+    }
+
+    let spread_bps = number::<f64>(args, "--quote-spread-bps").filter(|x| *x >= 0.0);
+    let size = number(args, "--quote-size").filter(|x| *x > 0);
+    let valid_for = number(args, "--quote-valid-secs").map(Duration::from_secs);
+    let mut references = Vec::new();
+    while let Some(reference) = take_flag(args, "--quote-ref") {
+        let parsed = reference
+            .split_once('=')
+            .and_then(|(symbol, price)| Some((symbol.to_string(), price.parse::<f64>().ok()?)))
+            .filter(|(_, price)| *price > 0.0);
+        let Some(parsed) = parsed else {
+            eprintln!("--quote-ref expects <symbol>=<price>: {reference}");
+            exit(1);
+        };
+        references.push(parsed);
+    }
+    QuoteFlags {
+        spread_bps,
+        size,
+        valid_for,
+        references,
+    }
+}
+
+/// Remove the confirmation flags and build the settings
+///
+/// --confirm-to may be repeated, once per recipient.
+fn take_confirmation_flags(args: &mut Vec<String>) -> ConfirmationSettings {
+    let mut settings = ConfirmationSettings::default();
+    if let Some(dir) = take_flag(args, "--confirms-dir") {
+        settings.dir = PathBuf::from(dir);
+    }
+
+    let server = take_flag(args, "--smtp");
+    let from = take_flag(args, "--smtp-from");
+    let mut recipients: HashMap<String, Vec<String>> = HashMap::new();
+    while let Some(recipient) = take_flag(args, "--confirm-to") {
+        let Some((account, address)) = recipient.split_once('=') else {
+            eprintln!("--confirm-to expects <account>=<address>: {recipient}");
+            exit(1);
+        };
+        recipients
+            .entry(account.to_string())
+            .or_default()
+            .push(address.to_string());
+    }
+
+    match server {
+        Some(server) => {
+            let from = from.as_deref().unwrap_or("confirms@localhost");
+            settings.smtp = Some(SmtpSink::new(&server, from));
+        }
+        None if from.is_some() || !recipients.is_empty() => {
+            eprintln!("--smtp-from and --confirm-to require --smtp <host:port>");
+            exit(1);
+        }
+        None => {}
+    }
+    settings.recipients = recipients;
+    settings
+}
+
+/// Remove the EOD report and delivery flags and build the settings
+///
+/// --eod-deliver may be repeated, once per destination.
+fn take_eod_flags(args: &mut Vec<String>, confirms_dir: &Path) -> EodSettings {
+    let mut settings = EodSettings::default();
+    if let Some(bps) = take_flag(args, "--fee-bps") {
+        match bps.parse::<f64>() {
+            Ok(bps) if bps >= 0.0 => settings.fee_bps = bps,
+            _ => {
+                eprintln!("--fee-bps expects a number of basis points: {bps}");
+                exit(1);
+            }
+        }
+    }
+
+    let identity = take_flag(args, "--sftp-identity").map(PathBuf::from);
+    let receipts = take_flag(args, "--eod-receipts")
+        .map_or_else(|| confirms_dir.join("deliveries.jsonl"), PathBuf::from);
+    let mut delivery = DeliveryJob::new().with_receipts(&receipts);
+    while let Some(destination) = take_flag(args, "--eod-deliver") {
+        let destination = match destination.parse::<Destination>() {
+            Ok(x) => x,
+            Err(err) => {
+                eprintln!("--eod-deliver: {err}");
+                exit(1);
+            }
+        };
+        delivery = delivery.with_destination(match &identity {
+            Some(key) => destination.with_identity(key),
+            None => destination,
+        });
+    }
+    settings.delivery = delivery;
+    settings
+}
@@ -0,0 +1,155 @@
+// =============================================================================
+// Market Surveillance
+// =============================================================================
+// Post-trade / in-flight checks a venue runs to detect abusive or broken
+// client behaviour. Alerts are only recorded (and exposed through the admin
+// API); nothing here blocks order flow.
+//
+// - Self trade: both sides of a trade belong to the same session
+// - Order-to-trade ratio: too many orders for too few trades
+// - Reject storm: a session keeps sending invalid orders
+// - Alert rules: any inbound message an operator's expression matches
+//   (--alert-rule, --alert-rules FILE; see trading::expr)
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use trading::{
+    expr::{self, Rule},
+    session::events::FixMessage,
+    sim::matching::{ExecEvent, ExecKind, Side},
+};
+
+/// Minimum number of orders before the order-to-trade ratio is evaluated
+const OTR_MIN_ORDERS: u64 = 50;
+
+/// Orders per trade above which a session is flagged
+const OTR_LIMIT: f64 = 20.0;
+
+/// Rejects per session above which a session is flagged
+const REJECT_LIMIT: u64 = 10;
+
+#[derive(Debug, Clone)]
+pub struct Alert {
+    /// Seconds since the UNIX epoch
+    pub timestamp: u64,
+    pub session: String,
+    pub description: String,
+}
+
+#[derive(Debug, Default)]
+struct SessionActivity {
+    orders: u64,
+    trades: u64,
+    rejects: u64,
+    otr_flagged: bool,
+    rejects_flagged: bool,
+}
+
+#[derive(Default)]
+pub struct Surveillance {
+    activity: HashMap<String, SessionActivity>,
+    alerts: Vec<Alert>,
+
+    /// Checked against every inbound application message
+    rules: Vec<Rule>,
+}
+
+impl Surveillance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Raise an alert for every inbound message one of these rules matches
+    pub fn with_rules(mut self, rules: Vec<Rule>) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn has_rules(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    /// Check an inbound message against the alert rules
+    pub fn inspect(&mut self, session: &str, msg: &FixMessage) {
+        let matched: Vec<String> = expr::matching(&self.rules, msg)
+            .into_iter()
+            .map(String::from)
+            .collect();
+        for name in matched {
+            let mut description = format!("rule {name}: 35={}", msg.msg_type());
+            for (tag, value) in [(11, msg.get(11)), (55, msg.get(55)), (38, msg.get(38))] {
+                if let Some(value) = value {
+                    description.push_str(&format!(" {tag}={value}"));
+                }
+            }
+            self.raise(session, description);
+        }
+    }
+
+    /// Inspect the events produced by one order submission or cancel
+    pub fn observe(&mut self, events: &[ExecEvent], session_label: impl Fn(&ExecEvent) -> String) {
+        for event in events {
+            let session = session_label(event);
+            let activity = self.activity.entry(session.clone()).or_default();
+
+            match event.kind {
+                ExecKind::New => activity.orders += 1,
+                ExecKind::Rejected => {
+                    activity.orders += 1;
+                    activity.rejects += 1;
+                }
+                ExecKind::Trade => activity.trades += 1,
+                ExecKind::Canceled => {}
+            }
+
+            let mut raised = Vec::new();
+
+            // Each trade produces one event per side: report it once, on the buy
+            if event.kind == ExecKind::Trade
+                && event.order.side == Side::Buy
+                && event.counterparty.as_ref() == Some(&event.order.owner)
+            {
+                raised.push(format!(
+                    "self trade on {} {}@{} (order {})",
+                    event.order.symbol, event.last_qty, event.last_px, event.order.order_id
+                ));
+            }
+
+            if !activity.otr_flagged && activity.orders >= OTR_MIN_ORDERS {
+                let ratio = activity.orders as f64 / activity.trades.max(1) as f64;
+                if ratio > OTR_LIMIT {
+                    activity.otr_flagged = true;
+                    raised.push(format!("order-to-trade ratio {ratio:.1} above {OTR_LIMIT}"));
+                }
+            }
+
+            if !activity.rejects_flagged && activity.rejects > REJECT_LIMIT {
+                activity.rejects_flagged = true;
+                raised.push(format!("{} rejected orders", activity.rejects));
+            }
+
+            for description in raised {
+                self.raise(&session, description);
+            }
+        }
+    }
+
+    fn raise(&mut self, session: &str, description: String) {
+        println!(">> SURVEILLANCE [{session}] {description}");
+        self.alerts.push(Alert {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |x| x.as_secs()),
+            session: session.to_string(),
+            description,
+        });
+    }
+
+    pub fn alerts(&self) -> &[Alert] {
+        &self.alerts
+    }
+}
@@ -0,0 +1,83 @@
+// =============================================================================
+// Latency Budgets
+// =============================================================================
+// check_budget (trading::testing::budget) against the budget of the
+// repository (latency_budget.toml) and one built in code, each asserted with
+// assert_within so that a slower build fails the test suite; and a budget no
+// build can meet, to show that a breach is an error naming every limit broken.
+//
+// For figures worth comparing run them alone and in release:
+//   cargo test --release --test budget -- --test-threads=1
+// =============================================================================
+
+use std::{path::Path, time::Duration};
+
+use trading::{
+    bench::Load,
+    testing::budget::{check_budget, BudgetError, LatencyBudget},
+};
+
+#[test]
+fn latency_within_budget() -> Result<(), BudgetError> {
+    let budget = LatencyBudget::load(Path::new("latency_budget.toml"))?.scaled_by_env();
+    let report = check_budget(&budget)?;
+    report.print();
+    report.assert_within()
+}
+
+#[test]
+fn budget_built_in_code_is_met() -> Result<(), BudgetError> {
+    let budget = LatencyBudget::default()
+        .with_load(Load::RoundTrips(500))
+        .with_ack_p99(Duration::from_millis(50))
+        .with_ack_max(Duration::from_millis(500))
+        .with_min_round_trips_per_sec(50.0)
+        .with_matching(10_000, 5_000.0)
+        .scaled_by_env();
+    let report = check_budget(&budget)?;
+    assert_eq!(report.throughput.received, report.throughput.sent);
+    assert!(report.matching.as_ref().is_some_and(|x| x.trades > 0));
+    report.assert_within()
+}
+
+#[test]
+fn impossible_budget_is_breached() -> Result<(), BudgetError> {
+    let budget = LatencyBudget::default()
+        .with_load(Load::RoundTrips(100))
+        .with_ack_p50(Duration::from_nanos(1))
+        .with_matching(1_000, 1e15);
+    let report = check_budget(&budget)?;
+    assert!(!report.passed());
+
+    match report.assert_within() {
+        Err(BudgetError::Breached(checks)) => {
+            let names: Vec<_> = checks.iter().map(|x| x.name).collect();
+            assert_eq!(names, ["order ack p50", "matching orders/s"]);
+            Ok(())
+        }
+        other => panic!("expected a breach, got {other:?}"),
+    }
+}
+
+#[test]
+fn budget_file_subset_is_read() -> Result<(), BudgetError> {
+    let source = "\
+        # comment\n\
+        round_trips = 300   # trailing comment\n\
+        ack_p99 = \"2ms\"\n\
+        ack_max = 1.5s\n\
+        matching_orders = 0\n";
+    let budget = LatencyBudget::parse(Path::new("inline.toml"), source)?;
+    assert!(matches!(budget.throughput.load, Load::RoundTrips(300)));
+    assert_eq!(budget.ack_p50, None);
+    assert_eq!(budget.ack_p99, Some(Duration::from_millis(2)));
+    assert_eq!(budget.ack_max, Some(Duration::from_millis(1500)));
+    assert_eq!(budget.matching_orders, 0);
+
+    let scaled = budget.scaled(3.0);
+    assert_eq!(scaled.ack_p99, Some(Duration::from_millis(6)));
+
+    let error = LatencyBudget::parse(Path::new("inline.toml"), "ack_p50 = fast\n");
+    assert!(matches!(error, Err(BudgetError::Syntax { line: 1, .. })));
+    Ok(())
+}
@@ -0,0 +1,119 @@
+// =============================================================================
+// End-of-Day File Delivery
+// =============================================================================
+// Destinations (trading::gateway::delivery) parsed from their text, and the
+// names an sftp batch script cannot carry: a newline or carriage return in a
+// destination, a remote directory or a file name would end the command it
+// is part of and start another, so they are refused before sftp is run.
+// =============================================================================
+
+use std::{fs, path::PathBuf};
+
+use trading::gateway::delivery::{DeliveryJob, Destination, ReceiptStatus};
+
+/// A directory of its own per test, emptied of an earlier run
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("delivery-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("test directory");
+    dir
+}
+
+fn sftp(path: &str) -> Destination {
+    Destination::Sftp {
+        user: Some("eod".to_string()),
+        host: "files.acme.com".to_string(),
+        port: None,
+        path: path.to_string(),
+        identity: None,
+    }
+}
+
+#[test]
+fn destinations_are_parsed() {
+    assert_eq!(
+        "sftp://eod@files.acme.com/inbound"
+            .parse::<Destination>()
+            .ok(),
+        Some(sftp("inbound"))
+    );
+    assert_eq!(
+        "/srv/drop/acme".parse::<Destination>().ok(),
+        Some(Destination::Dir(PathBuf::from("/srv/drop/acme")))
+    );
+    for invalid in ["", "ftp://files.acme.com", "sftp://-oProxyCommand=x/in"] {
+        assert!(invalid.parse::<Destination>().is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn destinations_with_control_characters_are_refused() {
+    for invalid in [
+        "sftp://eod@files.acme.com/inbound\n!touch /tmp/pwned",
+        "sftp://eod@files.acme.com/in\rbound",
+        "sftp://eod@files.acme.com\n/inbound",
+        "/srv/drop/acme\n",
+        "/srv/drop/\x00acme",
+    ] {
+        assert!(invalid.parse::<Destination>().is_err(), "{invalid:?}");
+    }
+}
+
+#[test]
+fn file_names_with_control_characters_are_not_sent_by_sftp() {
+    let dir = dir("names");
+    let file = dir.join("trades.csv\n!touch pwned");
+    fs::write(&file, "ClOrdID,Symbol\n").expect("test file");
+
+    let receipts = DeliveryJob::new()
+        .with_destination(sftp("inbound"))
+        .deliver(std::slice::from_ref(&file))
+        .expect("receipts");
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].status, ReceiptStatus::Failed);
+    let error = receipts[0].error.as_deref().unwrap_or_default();
+    assert!(error.contains("control character"), "{error}");
+
+    // Refused before anything was written: no sidecar next to the file
+    let mut sidecar = file.into_os_string();
+    sidecar.push(".sha256");
+    assert!(!PathBuf::from(sidecar).exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn remote_directory_with_control_characters_is_not_sent_by_sftp() {
+    let dir = dir("remote");
+    let file = dir.join("trades.csv");
+    fs::write(&file, "ClOrdID,Symbol\n").expect("test file");
+
+    // Built directly, not parsed: the quoting must refuse it as well
+    let receipts = DeliveryJob::new()
+        .with_destination(sftp("inbound\r\n!rm -rf ."))
+        .deliver(&[file])
+        .expect("receipts");
+    let error = receipts[0].error.as_deref().unwrap_or_default();
+    assert!(error.contains("control character"), "{error}");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn files_are_dropped_in_a_directory_with_their_sidecar() {
+    let (source, drop) = (dir("source"), dir("drop"));
+    let file = source.join("positions.csv");
+    fs::write(&file, "Symbol,Qty\nAAPL,100\n").expect("test file");
+
+    let receipts = DeliveryJob::new()
+        .with_destination(Destination::Dir(drop.clone()))
+        .deliver(&[file])
+        .expect("receipts");
+    assert_eq!(receipts[0].status, ReceiptStatus::Verified);
+    assert_eq!(
+        fs::read_to_string(drop.join("positions.csv")).expect("delivered"),
+        "Symbol,Qty\nAAPL,100\n"
+    );
+    let sidecar = fs::read_to_string(drop.join("positions.csv.sha256")).expect("sidecar");
+    assert_eq!(sidecar, format!("{}  positions.csv\n", receipts[0].sha256));
+    let _ = fs::remove_dir_all(&source);
+    let _ = fs::remove_dir_all(&drop);
+}
@@ -0,0 +1,229 @@
+// =============================================================================
+// Encrypted State Store
+// =============================================================================
+// The cipher of EncryptedStore (trading::store::encrypted) against the
+// AES-256 test cases of the GCM specification (McGrew & Viega, the test
+// vectors of NIST SP 800-38D: cases 13 to 16), then the store itself: values
+// unreadable in the backend, bound to their key, refused under another key
+// or once tampered with.
+// =============================================================================
+
+use std::sync::Arc;
+
+use trading::store::{
+    encrypted::{Cipher, EncryptedStore, EncryptionKey, NONCE_LEN, TAG_LEN},
+    MemoryStore, StateStore, StoreError,
+};
+
+fn hex(value: &str) -> Vec<u8> {
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).expect("hex test vector"))
+        .collect()
+}
+
+fn key(value: &str) -> EncryptionKey {
+    EncryptionKey::from_hex(value).expect("test key")
+}
+
+// =============================================================================
+// Known answers
+// =============================================================================
+
+struct Vector {
+    name: &'static str,
+    key: &'static str,
+    nonce: &'static str,
+    plaintext: &'static str,
+    aad: &'static str,
+    ciphertext: &'static str,
+    tag: &'static str,
+}
+
+const ZERO_KEY: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+const KEY: &str = "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308";
+
+const VECTORS: [Vector; 4] = [
+    Vector {
+        name: "test case 13",
+        key: ZERO_KEY,
+        nonce: "000000000000000000000000",
+        plaintext: "",
+        aad: "",
+        ciphertext: "",
+        tag: "530f8afbc74536b9a963b4f1c4cb738b",
+    },
+    Vector {
+        name: "test case 14",
+        key: ZERO_KEY,
+        nonce: "000000000000000000000000",
+        plaintext: "00000000000000000000000000000000",
+        aad: "",
+        ciphertext: "cea7403d4d606b6e074ec5d3baf39d18",
+        tag: "d0d1c8a799996bf0265b98b5d48ab919",
+    },
+    Vector {
+        name: "test case 15",
+        key: KEY,
+        nonce: "cafebabefacedbaddecaf888",
+        plaintext: "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                    1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255",
+        aad: "",
+        ciphertext: "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+                     8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662898015ad",
+        tag: "b094dac5d93471bdec1a502270e3cc6c",
+    },
+    Vector {
+        name: "test case 16",
+        key: KEY,
+        nonce: "cafebabefacedbaddecaf888",
+        plaintext: "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                    1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+        aad: "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+        ciphertext: "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+                     8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662",
+        tag: "76fc6ece0f4e1768cddf8853bb2d551b",
+    },
+];
+
+#[test]
+fn cipher_matches_the_gcm_test_vectors() -> Result<(), StoreError> {
+    for vector in &VECTORS {
+        let cipher = Cipher::new(&key(vector.key));
+        let nonce: [u8; NONCE_LEN] = hex(vector.nonce).try_into().expect("96-bit nonce");
+        let (plaintext, aad) = (hex(vector.plaintext), hex(vector.aad));
+        let mut expected = hex(vector.ciphertext);
+        expected.extend(hex(vector.tag));
+
+        let sealed = cipher.seal(&nonce, &aad, &plaintext)?;
+        assert_eq!(sealed, expected, "{}: sealed", vector.name);
+        let opened = cipher.open(&nonce, &aad, &sealed);
+        assert_eq!(opened, Some(plaintext), "{}: opened", vector.name);
+    }
+    Ok(())
+}
+
+#[test]
+fn cipher_refuses_a_changed_ciphertext_tag_or_aad() {
+    let vector = &VECTORS[3];
+    let cipher = Cipher::new(&key(vector.key));
+    let nonce: [u8; NONCE_LEN] = hex(vector.nonce).try_into().expect("96-bit nonce");
+    let aad = hex(vector.aad);
+    let mut sealed = hex(vector.ciphertext);
+    sealed.extend(hex(vector.tag));
+
+    for index in [0, sealed.len() - TAG_LEN, sealed.len() - 1] {
+        let mut changed = sealed.clone();
+        changed[index] ^= 1;
+        assert_eq!(cipher.open(&nonce, &aad, &changed), None, "byte {index}");
+    }
+    assert_eq!(cipher.open(&nonce, b"", &sealed), None);
+    assert_eq!(cipher.open(&[0; NONCE_LEN], &aad, &sealed), None);
+    assert_eq!(cipher.open(&nonce, &aad, &sealed[..TAG_LEN - 1]), None);
+}
+
+// =============================================================================
+// Store
+// =============================================================================
+
+const STORE_KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+const OTHER_KEY: &str = "1f1e1d1c1b1a191817161514131211100f0e0d0c0b0a09080706050403020100";
+
+#[test]
+fn values_are_encrypted_in_the_backend() -> Result<(), StoreError> {
+    let inner = Arc::new(MemoryStore::new());
+    let store = EncryptedStore::new(inner.clone(), &key(STORE_KEY));
+    let order = r#"{"cl_ord_id":"ORD-1","price":150.25,"account":"ACC-7"}"#;
+    store.put("orders", "ORD-1", order)?;
+    store.put("orders", "ORD-2", order)?;
+
+    assert_eq!(store.get("orders", "ORD-1")?.as_deref(), Some(order));
+    assert_eq!(store.scan("orders")?.len(), 2);
+
+    let stored = inner.get("orders", "ORD-1")?.expect("stored value");
+    assert!(stored.starts_with("aes256gcm:"), "{stored}");
+    assert!(!stored.contains("ACC-7"));
+
+    // A fresh nonce each write: the same value is never stored twice alike
+    let other = inner.get("orders", "ORD-2")?.expect("stored value");
+    assert_ne!(stored, other);
+    Ok(())
+}
+
+#[test]
+fn values_decrypt_only_with_their_key_and_under_their_name() -> Result<(), StoreError> {
+    let inner = Arc::new(MemoryStore::new());
+    let store = EncryptedStore::new(inner.clone(), &key(STORE_KEY));
+    store.put("positions", "AAPL", "100")?;
+    let stored = inner.get("positions", "AAPL")?.expect("stored value");
+
+    let other = EncryptedStore::new(inner.clone(), &key(OTHER_KEY));
+    assert!(matches!(
+        other.get("positions", "AAPL"),
+        Err(StoreError::Decrypt { .. })
+    ));
+
+    // Copied under another key
+    inner.put("positions", "MSFT", &stored)?;
+    assert!(matches!(
+        store.get("positions", "MSFT"),
+        Err(StoreError::Decrypt { .. })
+    ));
+
+    // Tampered with: the last hex digit of the tag
+    let mut tampered = stored.clone();
+    let last = if tampered.ends_with('0') { "1" } else { "0" };
+    tampered.replace_range(tampered.len() - 1.., last);
+    inner.put("positions", "AAPL", &tampered)?;
+    assert!(matches!(
+        store.get("positions", "AAPL"),
+        Err(StoreError::Decrypt { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn values_decrypt_only_in_their_namespace() -> Result<(), StoreError> {
+    let inner = Arc::new(MemoryStore::new());
+    let store = EncryptedStore::new(inner.clone(), &key(STORE_KEY));
+    store.put("orders", "ORD-1", "100@150.25")?;
+    let stored = inner.get("orders", "ORD-1")?.expect("stored value");
+
+    // Same key, another namespace
+    inner.put("fills", "ORD-1", &stored)?;
+    assert!(matches!(
+        store.get("fills", "ORD-1"),
+        Err(StoreError::Decrypt { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn values_in_clear_are_read_and_encrypted_only_when_asked() -> Result<(), StoreError> {
+    let inner = Arc::new(MemoryStore::new());
+    inner.put("counters", "next_cl_ord_id", "42")?;
+
+    let store = EncryptedStore::new(inner.clone(), &key(STORE_KEY));
+    assert!(matches!(
+        store.get("counters", "next_cl_ord_id"),
+        Err(StoreError::Decrypt { .. })
+    ));
+
+    let store = store.with_plaintext_reads();
+    assert_eq!(
+        store.get("counters", "next_cl_ord_id")?.as_deref(),
+        Some("42")
+    );
+    assert_eq!(store.encrypt_existing("counters")?, 1);
+    assert_eq!(store.encrypt_existing("counters")?, 0);
+
+    let stored = inner
+        .get("counters", "next_cl_ord_id")?
+        .expect("stored value");
+    assert!(stored.starts_with("aes256gcm:"));
+    assert_eq!(
+        store.get("counters", "next_cl_ord_id")?.as_deref(),
+        Some("42")
+    );
+    Ok(())
+}
@@ -0,0 +1,207 @@
+// =============================================================================
+// Message Expressions
+// =============================================================================
+// Expr (trading::expr) parsed and evaluated against a message: operator
+// precedence, `in`, null, fields compared as numbers or as text, the
+// positions syntax errors point at, and nesting past the depth limit,
+// refused rather than overflowing the stack.
+// =============================================================================
+
+use trading::{
+    expr::{Expr, ExprError, Value},
+    session::{events::FixMessage, Direction},
+};
+
+/// A new order: 35=D, 55=IBM, 54=1, 38=100.0, 44=25
+fn order() -> FixMessage {
+    let fields = [(35, "D"), (55, "IBM"), (54, "1"), (38, "100.0"), (44, "25")];
+    FixMessage {
+        session: "FIX.4.4:BUY->SELL".to_string(),
+        direction: Direction::Outbound,
+        admin: false,
+        fields: fields
+            .into_iter()
+            .map(|(tag, value)| (tag, value.to_string()))
+            .collect(),
+    }
+}
+
+fn eval(text: &str) -> Value {
+    Expr::parse(text)
+        .unwrap_or_else(|err| panic!("{text}: {err}"))
+        .eval(&order())
+}
+
+fn is_true(text: &str) -> bool {
+    match eval(text) {
+        Value::Bool(x) => x,
+        other => panic!("{text}: {other}, not a boolean"),
+    }
+}
+
+/// Position and message of a syntax error
+fn syntax_error(text: &str) -> (usize, String) {
+    match Expr::parse(text) {
+        Err(ExprError::Syntax { position, message }) => (position, message),
+        other => panic!("{text}: {other:?}, not a syntax error"),
+    }
+}
+
+// =============================================================================
+// Precedence
+// =============================================================================
+
+#[test]
+fn products_before_sums_before_comparisons() {
+    assert_eq!(eval("1 + 2 * 3"), Value::Number(7.0));
+    assert_eq!(eval("(1 + 2) * 3"), Value::Number(9.0));
+    assert_eq!(eval("-2 * 3"), Value::Number(-6.0));
+    assert!(is_true("1 + 2 * 3 == 7"));
+}
+
+#[test]
+fn operators_of_a_level_associate_to_the_left() {
+    assert_eq!(eval("10 - 4 - 3"), Value::Number(3.0));
+    assert_eq!(eval("12 / 3 / 2"), Value::Number(2.0));
+}
+
+#[test]
+fn and_before_or_not_looser_than_comparisons() {
+    assert!(is_true("true || false && false"));
+    assert!(!is_true("(true || false) && false"));
+    assert!(is_true("!true || true"));
+    // !(1 == 2), not (!1) == 2
+    assert!(is_true("!1 == 2"));
+    assert!(is_true("msg.35 == \"D\" && qty > 50 || false"));
+}
+
+// =============================================================================
+// Operands
+// =============================================================================
+
+#[test]
+fn in_compares_with_each_element() {
+    assert!(is_true("msg.35 in [\"D\", \"G\"]"));
+    assert!(!is_true("msg.MsgType in [\"F\", \"G\"]"));
+    assert!(is_true("msg.38 in [50, 100]"), "numerically");
+    assert!(is_true("msg.58 in [null]"));
+    assert!(!is_true("msg.35 in []"));
+    assert!(is_true("side in ['sell', 'buy'] && !(symbol in ['MSFT'])"));
+}
+
+#[test]
+fn long_in_list_is_not_nesting() {
+    let list: Vec<String> = (0..10_000).map(|x| x.to_string()).collect();
+    assert!(is_true(&format!("msg.38 in [{}]", list.join(", "))));
+}
+
+#[test]
+fn null_equals_only_null_and_compares_false() {
+    assert!(is_true("msg.58 == null"));
+    assert!(!is_true("msg.58 != null"));
+    assert!(is_true("msg.35 != null"));
+    assert!(!is_true("msg.58 > 1"));
+    assert!(!is_true("msg.58 <= 1"));
+    assert!(!is_true("msg.58 == \"\""));
+    assert_eq!(eval("msg.58 + 1"), Value::Null);
+    assert_eq!(eval("-msg.58"), Value::Null);
+    assert_eq!(eval("1 / 0"), Value::Null);
+}
+
+#[test]
+fn field_against_a_number_compares_as_a_number() {
+    // 38=100.0
+    assert!(is_true("msg.38 == 100"));
+    assert!(is_true("msg.38 > 20"));
+    assert!(is_true("qty == 100 && notional == 2500"));
+    assert!(!is_true("msg.55 == 0"));
+    assert!(!is_true("msg.55 > 0"));
+}
+
+#[test]
+fn field_against_text_compares_as_text_unless_both_are_numbers() {
+    assert!(!is_true("msg.38 == \"100\""), "equality is textual");
+    assert!(is_true("msg.38 > \"20\""), "numeric texts order as numbers");
+    assert!(is_true("msg.55 < \"MSFT\""));
+    assert!(is_true("msg.55 contains \"B\""));
+    assert!(!is_true("msg.38 contains 100"));
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[test]
+fn syntax_errors_point_at_the_offending_token() {
+    assert_eq!(syntax_error("qty >> 3"), (5, "unexpected '>'".to_string()));
+    assert_eq!(syntax_error("1 2"), (2, "unexpected 2".to_string()));
+    assert_eq!(
+        syntax_error("(qty > 3"),
+        (8, "expected ')', found the end".to_string())
+    );
+    assert_eq!(
+        syntax_error("msg.35 == "),
+        (10, "unexpected end of expression".to_string())
+    );
+    assert_eq!(
+        syntax_error("msg.35 in [\"D\" \"G\"]"),
+        (15, "expected ',', found \"G\"".to_string())
+    );
+    assert_eq!(
+        syntax_error("msg.58 == 'limit"),
+        (10, "unterminated string".to_string())
+    );
+    assert_eq!(
+        syntax_error("qty # 3"),
+        (4, "unexpected character '#'".to_string())
+    );
+}
+
+#[test]
+fn unknown_names_are_refused() {
+    assert!(matches!(
+        Expr::parse("msg.NoSuchField == 1"),
+        Err(ExprError::UnknownField(x)) if x == "NoSuchField"
+    ));
+    assert!(matches!(
+        Expr::parse("quantity > 1"),
+        Err(ExprError::UnknownVariable(x)) if x == "quantity"
+    ));
+}
+
+// =============================================================================
+// Depth limit
+// =============================================================================
+
+fn too_deep(text: &str) -> bool {
+    matches!(
+        Expr::parse(text),
+        Err(ExprError::Syntax { message, .. }) if message.contains("nested deeper")
+    )
+}
+
+#[test]
+fn deep_parentheses_are_refused_not_recursed_into() {
+    assert!(too_deep(&("(".repeat(200_000) + "1")));
+    assert!(too_deep(&format!("{}1{}", "(".repeat(65), ")".repeat(65))));
+    assert_eq!(
+        eval(&format!("{}1{}", "(".repeat(64), ")".repeat(64))),
+        Value::Number(1.0)
+    );
+}
+
+#[test]
+fn deep_unary_operators_are_refused() {
+    assert!(too_deep(&("!".repeat(200_000) + "true")));
+    assert!(too_deep(&("-".repeat(200_000) + "1")));
+    assert_eq!(eval(&("-".repeat(62) + "1")), Value::Number(1.0));
+}
+
+#[test]
+fn long_chains_of_operators_are_refused() {
+    // Left-associative: each operator one level deeper than the last
+    assert!(too_deep(&["1"; 200_000].join(" + ")));
+    assert!(too_deep(&["true"; 200_000].join(" || ")));
+    assert!(!is_true(&["msg.35 == \"G\""; 60].join(" || ")));
+    assert!(is_true(&["true"; 60].join(" && ")));
+}
@@ -0,0 +1,173 @@
+// =============================================================================
+// Protobuf Wire Format
+// =============================================================================
+// ProtoWriter and ProtoMessage (trading::gateway::grpc) against the
+// encodings of the protobuf documentation ("Encoding", protobuf.dev), then
+// in round trips of every field type, and against messages they must
+// refuse: truncated, of a wire type we do not read, with field number 0.
+// =============================================================================
+
+use trading::gateway::grpc::{Code, ProtoMessage, ProtoWriter};
+
+fn parse(bytes: &[u8]) -> ProtoMessage<'_> {
+    ProtoMessage::parse(bytes).expect("well-formed message")
+}
+
+// =============================================================================
+// Known encodings
+// =============================================================================
+
+#[test]
+fn varint_field_as_documented() {
+    // Field 1 = 150
+    let bytes = ProtoWriter::new().uint(1, 150).finish();
+    assert_eq!(bytes, [0x08, 0x96, 0x01]);
+    assert_eq!(parse(&bytes).uint(1), 150);
+}
+
+#[test]
+fn string_field_as_documented() {
+    // Field 2 = "testing"
+    let bytes = ProtoWriter::new().string(2, "testing").finish();
+    assert_eq!(bytes, b"\x12\x07testing");
+    assert_eq!(parse(&bytes).string(2), Ok("testing"));
+}
+
+#[test]
+fn negative_int_takes_ten_bytes() {
+    let bytes = ProtoWriter::new().int(1, -2).finish();
+    assert_eq!(
+        bytes,
+        [0x08, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+    );
+    assert_eq!(parse(&bytes).int(1), -2);
+}
+
+#[test]
+fn double_is_fixed64_little_endian() {
+    let bytes = ProtoWriter::new().double(4, 1.5).finish();
+    assert_eq!(bytes, [0x21, 0, 0, 0, 0, 0, 0, 0xf8, 0x3f]);
+    assert_eq!(parse(&bytes).double(4), 1.5);
+}
+
+#[test]
+fn defaults_are_left_out() {
+    let bytes = ProtoWriter::new()
+        .uint(1, 0)
+        .bool(2, false)
+        .double(3, 0.0)
+        .string(4, "")
+        .bytes(5, &[])
+        .finish();
+    assert!(bytes.is_empty());
+}
+
+// =============================================================================
+// Round trips
+// =============================================================================
+
+#[test]
+fn every_field_type_round_trips() {
+    let bytes = ProtoWriter::new()
+        .uint(1, u64::MAX)
+        .int(2, i64::MIN)
+        .bool(3, true)
+        .double(4, -150.25)
+        .string(5, "AAPL")
+        .string(5, "MSFT")
+        .bytes(6, &[0, 1, 2, 255])
+        .message(7, ProtoWriter::new().string(1, "first").uint(2, 1))
+        .message(7, ProtoWriter::new().string(1, "second").uint(2, 2))
+        .finish();
+
+    let message = parse(&bytes);
+    assert_eq!(message.uint(1), u64::MAX);
+    assert_eq!(message.int(2), i64::MIN);
+    assert!(message.bool(3));
+    assert_eq!(message.double(4), -150.25);
+    assert_eq!(message.strings(5), Ok(vec!["AAPL", "MSFT"]));
+    assert_eq!(message.bytes(6), [&[0, 1, 2, 255][..]]);
+
+    let nested = message.messages(7).expect("nested messages");
+    assert_eq!(nested.len(), 2);
+    assert_eq!(nested[0].string(1), Ok("first"));
+    assert_eq!(nested[1].string(1), Ok("second"));
+    assert_eq!(nested[1].uint(2), 2);
+}
+
+#[test]
+fn last_value_of_a_repeated_field_wins() {
+    let bytes = ProtoWriter::new().uint(1, 7).uint(1, 9).finish();
+    let message = parse(&bytes);
+    assert_eq!(message.uint(1), 9);
+    assert_eq!(message.string(3), Ok(""), "absent field");
+}
+
+#[test]
+fn field_of_another_wire_type_reads_as_the_default() {
+    let bytes = ProtoWriter::new().string(1, "150").uint(2, 150).finish();
+    let message = parse(&bytes);
+    assert_eq!(message.uint(1), 0);
+    assert_eq!(message.double(2), 0.0);
+    assert!(message.bytes(2).is_empty());
+}
+
+#[test]
+fn float_is_fixed32() {
+    // Field 1, wire type 5, 1.5f32
+    let bytes = [0x0d, 0, 0, 0xc0, 0x3f];
+    assert_eq!(parse(&bytes).float(1), 1.5);
+}
+
+// =============================================================================
+// Malformed messages
+// =============================================================================
+
+fn refused(bytes: &[u8]) -> bool {
+    ProtoMessage::parse(bytes).is_err_and(|x| x.code == Code::InvalidArgument)
+}
+
+#[test]
+fn truncated_messages_are_refused() {
+    assert!(refused(&[0x08]), "key without its varint");
+    assert!(refused(&[0x08, 0x96]), "varint cut short");
+    assert!(refused(&[0x12, 0x07, b't', b'e']), "length past the end");
+    assert!(refused(&[0x21, 0, 0, 0]), "fixed64 cut short");
+    assert!(refused(&[0x0d, 0, 0]), "fixed32 cut short");
+}
+
+#[test]
+fn varint_longer_than_ten_bytes_is_refused() {
+    let mut bytes = vec![0x08];
+    bytes.extend([0xff; 10]);
+    bytes.push(0x01);
+    assert!(refused(&bytes));
+}
+
+#[test]
+fn groups_and_unknown_wire_types_are_refused() {
+    for wire_type in [3, 4, 6, 7] {
+        assert!(refused(&[0x08 | wire_type, 0x00]), "wire type {wire_type}");
+    }
+}
+
+#[test]
+fn field_number_zero_is_refused() {
+    assert!(refused(&[0x00, 0x01]));
+}
+
+#[test]
+fn string_not_in_utf8_is_refused() {
+    let bytes = ProtoWriter::new().bytes(1, &[0xff, 0xfe]).finish();
+    let message = parse(&bytes);
+    assert!(message
+        .string(1)
+        .is_err_and(|x| x.code == Code::InvalidArgument));
+    assert_eq!(message.bytes(1), [&[0xff, 0xfe][..]]);
+}
+
+#[test]
+fn malformed_nested_message_is_refused() {
+    let bytes = ProtoWriter::new().bytes(1, &[0x08]).finish();
+    assert!(parse(&bytes).messages(1).is_err());
+}
@@ -0,0 +1,225 @@
+// =============================================================================
+// Gzip Files
+// =============================================================================
+// crc32, deflate and gzip (trading::gzip) against the CRC-32 check value and
+// outputs known from zlib, then read back by a fixed-code inflater of RFC
+// 1951 written here: FIX logs, runs longer than a match, matches as far back
+// as the window reaches, and bytes that do not repeat.
+// =============================================================================
+
+use trading::gzip::{crc32, deflate, gzip};
+
+fn hex(value: &str) -> Vec<u8> {
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).expect("hex test vector"))
+        .collect()
+}
+
+// =============================================================================
+// Inflater (fixed Huffman codes only)
+// =============================================================================
+
+/// Bits of a DEFLATE stream, least significant first in each byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> u32 {
+        let byte = self.data[self.position / 8];
+        let bit = (byte >> (self.position % 8)) & 1;
+        self.position += 1;
+        u32::from(bit)
+    }
+
+    /// A number of `count` bits, least significant first
+    fn bits(&mut self, count: u8) -> u32 {
+        (0..count).fold(0, |value, x| value | self.bit() << x)
+    }
+
+    /// A Huffman code of `count` bits, most significant first
+    fn code(&mut self, count: u8) -> u32 {
+        (0..count).fold(0, |value, _| value << 1 | self.bit())
+    }
+
+    /// A literal / length symbol in its fixed code (RFC 1951 3.2.6)
+    fn symbol(&mut self) -> u32 {
+        let code = self.code(7);
+        if code <= 0b001_0111 {
+            return 256 + code;
+        }
+        let code = code << 1 | self.bit();
+        match code {
+            0x30..=0xbf => code - 0x30,
+            0xc0..=0xc7 => 280 + code - 0xc0,
+            _ => 144 + (code << 1 | self.bit()) - 0x190,
+        }
+    }
+}
+
+const LENGTH_BASE: [usize; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const DISTANCE_BASE: [usize; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+fn length_extra(index: usize) -> u8 {
+    if index < 8 || index == 28 {
+        0
+    } else {
+        (index / 4 - 1) as u8
+    }
+}
+
+fn distance_extra(index: usize) -> u8 {
+    if index < 4 {
+        0
+    } else {
+        (index / 2 - 1) as u8
+    }
+}
+
+/// One final block of the fixed codes, as deflate writes it
+fn inflate(data: &[u8]) -> Vec<u8> {
+    let mut bits = BitReader { data, position: 0 };
+    assert_eq!(bits.bits(1), 1, "BFINAL");
+    assert_eq!(bits.bits(2), 1, "BTYPE 01");
+
+    let mut out = Vec::new();
+    loop {
+        let symbol = bits.symbol() as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => break,
+            _ => {
+                let index = symbol - 257;
+                let length = LENGTH_BASE[index] + bits.bits(length_extra(index)) as usize;
+                let index = bits.code(5) as usize;
+                let distance = DISTANCE_BASE[index] + bits.bits(distance_extra(index)) as usize;
+                assert!(distance <= out.len(), "distance before the start");
+                assert!(distance <= 32 * 1024, "distance past the window");
+                for _ in 0..length {
+                    out.push(out[out.len() - distance]);
+                }
+            }
+        }
+    }
+    assert_eq!(
+        bits.position.div_ceil(8),
+        data.len(),
+        "bytes after the block"
+    );
+    out
+}
+
+/// The data of a gzip member, its trailer checked
+fn gunzip(file: &[u8]) -> Vec<u8> {
+    assert_eq!(file[..4], [0x1f, 0x8b, 8, 0], "magic, deflate, no flags");
+    let (body, trailer) = file[10..].split_at(file.len() - 18);
+    let data = inflate(body);
+    assert_eq!(trailer[..4], crc32(&data).to_le_bytes(), "CRC-32");
+    assert_eq!(trailer[4..], (data.len() as u32).to_le_bytes(), "ISIZE");
+    data
+}
+
+fn round_trip(data: &[u8]) -> usize {
+    let file = gzip(data);
+    assert_eq!(gunzip(&file), data);
+    file.len()
+}
+
+// =============================================================================
+// Known values
+// =============================================================================
+
+#[test]
+fn crc32_check_value() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(
+        crc32(b"The quick brown fox jumps over the lazy dog"),
+        0x414F_A339
+    );
+}
+
+#[test]
+fn empty_input_is_an_empty_block_in_a_member() {
+    assert_eq!(gzip(b""), hex("1f8b08000000000000ff03000000000000000000"));
+}
+
+#[test]
+fn deflate_without_matches_as_zlib_writes_it() {
+    // zlib at level 9 with the fixed codes (Z_FIXED), raw
+    assert_eq!(deflate(b"8=FIX.4.4"), hex("b3b075f38cd033d1330100"));
+}
+
+#[test]
+fn archive_is_the_same_for_the_same_input() {
+    assert_eq!(gzip(b"35=D"), gzip(b"35=D"));
+}
+
+// =============================================================================
+// Round trips
+// =============================================================================
+
+#[test]
+fn fix_log_round_trips_smaller() {
+    let mut log = Vec::new();
+    for seq in 1..=2_000 {
+        log.extend(
+            format!(
+                "8=FIX.4.4\x019=120\x0135=8\x0134={seq}\x0149=EXCHANGE\x0156=CLIENT\x01\
+                 52=20261016-09:30:00.{:03}\x0111=ORD-{seq}\x0155=AAPL\x0154=1\x01\
+                 38=100\x0131=150.25\x0139=2\x0110=000\x01\n",
+                seq % 1000
+            )
+            .bytes(),
+        );
+    }
+    assert!(round_trip(&log) * 3 < log.len());
+}
+
+#[test]
+fn runs_longer_than_a_match_round_trip() {
+    assert!(round_trip(&[b'A'; 10_000]) < 200);
+    round_trip(b"AA");
+    round_trip(b"AAA");
+    round_trip(&[0; 259]);
+}
+
+#[test]
+fn matches_across_the_whole_window_round_trip() {
+    // A block of bytes that do not repeat within it, again 32 KiB later
+    let block: Vec<u8> = pseudo_random(1_000, 7);
+    let mut data = block.clone();
+    data.extend(pseudo_random(32 * 1024 - block.len(), 11));
+    data.extend(&block);
+    data.extend(pseudo_random(100, 13));
+    data.extend(&block);
+    round_trip(&data);
+}
+
+#[test]
+fn bytes_that_do_not_repeat_round_trip() {
+    round_trip(&pseudo_random(100_000, 1));
+    round_trip(&(0..=255).collect::<Vec<u8>>());
+    round_trip(b"x");
+}
+
+/// xorshift bytes, the same for the same seed
+fn pseudo_random(len: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 32) as u8
+        })
+        .collect()
+}
@@ -0,0 +1,250 @@
+// =============================================================================
+// Concurrent Callbacks
+// =============================================================================
+// hammer (trading::testing::hammer) against what an application shares
+// between sessions under FixSocketServerKind::MultiThreaded: the recorded
+// callbacks, the OMS and its positions, the message statistics. Each test
+// checks that every message of every session arrived once: none lost, none
+// counted twice, each session's in its order.
+// The last test is on one thread: a logon restarts the inbound gap, not
+// the time since the last message.
+// =============================================================================
+
+use std::{
+    collections::{HashMap, HashSet},
+    thread,
+    time::Duration,
+};
+
+use quickfix::{ApplicationCallback, FieldMap, Message, MsgFromAppError, SessionId};
+use trading::{
+    oms::{positions::PositionBook, OrderManager, OrderStatus, Side},
+    session::{events::FixMessage, session_label, stats::MessageStats, Direction},
+    testing::{
+        hammer::{hammer, HammerOptions},
+        message, CallbackKind, HarnessError, NoApp, Recording,
+    },
+};
+
+const SESSIONS: usize = 8;
+const MESSAGES: usize = 500;
+
+fn options() -> HammerOptions {
+    HammerOptions::default()
+        .with_sessions(SESSIONS)
+        .with_messages(MESSAGES)
+}
+
+/// ExecID of the n-th report of a session
+fn exec_id(session: usize, n: usize) -> String {
+    format!("S{session}-{n}")
+}
+
+// =============================================================================
+// Recorded application
+// =============================================================================
+
+#[test]
+fn recording_keeps_every_callback_of_every_session() -> Result<(), HarnessError> {
+    let app = Recording::new(NoApp);
+    let report = hammer(&app, &options(), |session, n| {
+        message("8", &[(17, &exec_id(session, n)), (150, "0")])
+    })?;
+    assert_eq!(report.sessions, SESSIONS);
+    assert_eq!(report.accepted, SESSIONS * MESSAGES);
+    assert_eq!(report.rejected, 0);
+
+    let events = app.events();
+    let count = |kind| events.iter().filter(|x| x.kind == kind).count();
+    assert_eq!(count(CallbackKind::Create), SESSIONS);
+    assert_eq!(count(CallbackKind::Logon), SESSIONS);
+    assert_eq!(count(CallbackKind::Logout), SESSIONS);
+    assert_eq!(count(CallbackKind::FromApp), SESSIONS * MESSAGES);
+
+    // Each session's reports in the order it sent them, none twice
+    let mut by_session: HashMap<&str, Vec<&str>> = HashMap::new();
+    for event in events.iter().filter(|x| x.kind == CallbackKind::FromApp) {
+        let id = event.get(17).expect("recorded report without ExecID");
+        by_session.entry(&event.session).or_default().push(id);
+    }
+    assert_eq!(by_session.len(), SESSIONS);
+    for (label, ids) in by_session {
+        let index: usize = label
+            .split_once(":CLIENT")
+            .and_then(|(_, x)| x.split_once("->"))
+            .and_then(|(x, _)| x.parse().ok())
+            .expect("hammer session label");
+        let expected: Vec<_> = (0..MESSAGES).map(|n| exec_id(index - 1, n)).collect();
+        assert_eq!(ids, expected, "reports of {label}");
+    }
+    Ok(())
+}
+
+// =============================================================================
+// OMS and positions
+// =============================================================================
+
+/// Execution reports applied to the OMS, their fills to the positions
+struct Desk {
+    oms: OrderManager,
+    positions: PositionBook,
+}
+
+impl ApplicationCallback for Desk {
+    fn on_msg_from_app(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        let report = FixMessage::decode(session, Direction::Inbound, false, msg);
+        let update = self
+            .oms
+            .on_execution_report(&report)
+            .ok_or(MsgFromAppError::IncorrectTagValue)?;
+        if let Some(fill) = &update.fill {
+            self.positions.apply_fill(fill);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn oms_and_positions_count_every_fill_once() -> Result<(), HarnessError> {
+    let desk = Desk {
+        oms: OrderManager::new("HAMMER"),
+        positions: PositionBook::new(),
+    };
+
+    // One order a session, filled one lot per report: buys of AAPL on the
+    // even sessions, sells of MSFT on the odd ones
+    let orders: Vec<_> = (0..SESSIONS)
+        .map(|session| {
+            let (symbol, side) = match session % 2 {
+                0 => ("AAPL", Side::Buy),
+                _ => ("MSFT", Side::Sell),
+            };
+            desk.oms
+                .create_order(symbol, side, MESSAGES as f64, 100.0)
+                .cl_ord_id
+        })
+        .collect();
+
+    let report = hammer(&desk, &options(), |session, n| {
+        let filled = n + 1 == MESSAGES;
+        message(
+            "8",
+            &[
+                (11, &orders[session]),
+                (17, &exec_id(session, n)),
+                (150, "F"),
+                (39, if filled { "2" } else { "1" }),
+                (14, &(n + 1).to_string()),
+                (6, "100"),
+                (32, "1"),
+                (31, "100"),
+            ],
+        )
+    })?;
+    assert_eq!(report.accepted, SESSIONS * MESSAGES);
+    assert_eq!(report.rejected, 0);
+
+    for order in desk.oms.orders() {
+        assert_eq!(order.cum_qty, MESSAGES as f64, "{}", order.cl_ord_id);
+        assert_eq!(order.status, OrderStatus::Filled, "{}", order.cl_ord_id);
+    }
+
+    let per_symbol = (SESSIONS / 2 * MESSAGES) as f64;
+    assert_eq!(desk.positions.quantity("AAPL"), per_symbol);
+    assert_eq!(desk.positions.quantity("MSFT"), -per_symbol);
+
+    let fills = desk.positions.fills();
+    let exec_ids: HashSet<_> = fills.iter().map(|x| x.exec_id.as_str()).collect();
+    assert_eq!(fills.len(), SESSIONS * MESSAGES);
+    assert_eq!(exec_ids.len(), fills.len(), "a fill was applied twice");
+    Ok(())
+}
+
+#[test]
+fn reports_of_unknown_orders_are_rejected_not_applied() -> Result<(), HarnessError> {
+    let desk = Desk {
+        oms: OrderManager::new("HAMMER"),
+        positions: PositionBook::new(),
+    };
+    let report = hammer(&desk, &options(), |session, n| {
+        message(
+            "8",
+            &[
+                (11, "NOT-OURS"),
+                (17, &exec_id(session, n)),
+                (150, "F"),
+                (32, "1"),
+                (31, "100"),
+            ],
+        )
+    })?;
+    assert_eq!(report.accepted, 0);
+    assert_eq!(report.rejected, SESSIONS * MESSAGES);
+    assert!(desk.positions.fills().is_empty());
+    Ok(())
+}
+
+// =============================================================================
+// Message statistics
+// =============================================================================
+
+/// Every inbound application message counted, as the REPL does
+#[derive(Default)]
+struct Counted {
+    stats: MessageStats,
+}
+
+impl ApplicationCallback for Counted {
+    fn on_logon(&self, session: &SessionId) {
+        self.stats.on_logon(&session_label(session));
+    }
+
+    fn on_msg_from_app(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        let msg_type = msg.with_header(|h| h.get_field(35)).unwrap_or_default();
+        self.stats
+            .record(&session_label(session), Direction::Inbound, &msg_type);
+        Ok(())
+    }
+}
+
+#[test]
+fn message_stats_count_every_message_once() -> Result<(), HarnessError> {
+    let app = Counted::default();
+    let report = hammer(&app, &options(), |_, n| {
+        let msg_type = if n % 2 == 0 { "D" } else { "F" };
+        message(msg_type, &[(11, &n.to_string())])
+    })?;
+    assert_eq!(report.accepted, SESSIONS * MESSAGES);
+
+    let sessions = app.stats.snapshot();
+    assert_eq!(sessions.len(), SESSIONS);
+    for session in &sessions {
+        assert_eq!(session.inbound, MESSAGES as u64, "{}", session.session);
+        assert_eq!(session.outbound, 0);
+        assert_eq!(session.count("D"), ((MESSAGES / 2) as u64, 0));
+        assert_eq!(session.count("F"), ((MESSAGES / 2) as u64, 0));
+    }
+    let total: u64 = sessions.iter().map(|x| x.inbound).sum();
+    assert_eq!(total, (SESSIONS * MESSAGES) as u64);
+    Ok(())
+}
+
+#[test]
+fn message_stats_logon_resets_the_gap_not_the_last_message() {
+    let stats = MessageStats::new();
+    let session = "FIX.4.4:CLIENT->VENUE";
+    let silence = Duration::from_millis(50);
+
+    stats.record(session, Direction::Inbound, "A");
+    thread::sleep(silence);
+    stats.on_logon(session);
+    let after_logon = stats.session(session).expect("session counted");
+    assert!(after_logon.since_inbound.is_some_and(|x| x >= silence));
+
+    // The silence before the logon is not a gap of the new session
+    stats.record(session, Direction::Inbound, "A");
+    stats.record(session, Direction::Inbound, "0");
+    let read = stats.session(session).expect("session counted");
+    assert_eq!(read.inbound, 3);
+    assert!(read.max_inbound_gap < silence, "{:?}", read.max_inbound_gap);
+}
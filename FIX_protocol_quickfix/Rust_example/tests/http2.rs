@@ -0,0 +1,365 @@
+// =============================================================================
+// HTTP/2 Server
+// =============================================================================
+// The minimal HTTP/2 server (trading::gateway::http2) over loopback, driven
+// frame by frame: the request header blocks of RFC 7541 Appendix C.3 and
+// C.4 (the dynamic table carried from one request to the next, plain and
+// Huffman-coded), a body sent in several DATA frames and echoed back, then
+// frames and header blocks the server must answer with GOAWAY.
+// =============================================================================
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, Mutex},
+    time::Duration,
+};
+
+use trading::gateway::http2::{self, Request};
+
+// Frame types and flags (RFC 9113 §6)
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const SETTINGS: u8 = 0x4;
+const GOAWAY: u8 = 0x7;
+const END_STREAM: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+
+// Error codes
+const PROTOCOL_ERROR: u32 = 0x1;
+const COMPRESSION_ERROR: u32 = 0x9;
+
+fn hex(value: &str) -> Vec<u8> {
+    let value: String = value.split_whitespace().collect();
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).expect("hex test vector"))
+        .collect()
+}
+
+/// A port nothing listens on, for a server of its own per test
+fn free_port() -> u16 {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).expect("bind a free port");
+    listener.local_addr().expect("local address").port()
+}
+
+/// A server answering each request with `x-method` and `x-path` headers and
+/// its body echoed back, that hands the requests it got to the test
+fn echo_server() -> (u16, mpsc::Receiver<Request>) {
+    let port = free_port();
+    let (sender, requests) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    http2::spawn_server(
+        "http2 test",
+        port,
+        move |request: Request, mut responder| {
+            let headers = [
+                ("X-Method", request.method.as_str()),
+                ("X-Path", request.path.as_str()),
+            ];
+            let _ = responder.headers(200, &headers, false);
+            let _ = responder.data(&request.body, true);
+            let _ = sender.lock().expect("test sender").send(request);
+        },
+    )
+    .expect("start the server");
+    (port, requests)
+}
+
+/// A client connection past the preface and the SETTINGS exchange
+fn connect(port: u16) -> TcpStream {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("connect");
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("read timeout");
+    stream
+        .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+        .expect("preface");
+    write_frame(&mut stream, SETTINGS, 0, 0, &[]);
+    stream
+}
+
+fn write_frame(stream: &mut TcpStream, kind: u8, flags: u8, id: u32, payload: &[u8]) {
+    let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+    frame.extend([kind, flags]);
+    frame.extend(id.to_be_bytes());
+    frame.extend(payload);
+    stream.write_all(&frame).expect("write a frame");
+}
+
+/// Type, flags, stream and payload of the next frame; None once the server
+/// closed the connection
+fn read_frame(stream: &mut TcpStream) -> Option<(u8, u8, u32, Vec<u8>)> {
+    let mut header = [0; 9];
+    stream.read_exact(&mut header).ok()?;
+    let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+    let id = u32::from_be_bytes([header[5], header[6], header[7], header[8]]) & 0x7fff_ffff;
+    let mut payload = vec![0; length];
+    stream.read_exact(&mut payload).ok()?;
+    Some((header[3], header[4], id, payload))
+}
+
+/// The header block and body of the response on a stream, read until the
+/// stream ends
+fn response(stream: &mut TcpStream, id: u32) -> (Vec<u8>, Vec<u8>) {
+    let (mut block, mut body) = (Vec::new(), Vec::new());
+    loop {
+        let (kind, flags, on, payload) = read_frame(stream).expect("response frames");
+        match kind {
+            HEADERS if on == id => block.extend(payload),
+            DATA if on == id => body.extend(payload),
+            _ => continue,
+        }
+        if flags & END_STREAM != 0 {
+            return (block, body);
+        }
+    }
+}
+
+/// The error code of the GOAWAY the server answers with
+fn goaway(stream: &mut TcpStream) -> u32 {
+    while let Some((kind, _, _, payload)) = read_frame(stream) {
+        if kind == GOAWAY {
+            return u32::from_be_bytes([payload[4], payload[5], payload[6], payload[7]]);
+        }
+    }
+    panic!("connection closed without GOAWAY");
+}
+
+/// The fields of a response header block: what the server sends is `:status
+/// 200` indexed, then literals without indexing, never Huffman-coded
+fn response_fields(block: &[u8]) -> Vec<(String, String)> {
+    assert_eq!(block.first(), Some(&0x88), ":status 200, indexed");
+    let mut fields = Vec::new();
+    let mut position = 1;
+    let string = |position: &mut usize| {
+        let length = usize::from(block[*position]);
+        assert!(length < 0x7f, "short literals only");
+        let value = String::from_utf8(block[*position + 1..*position + 1 + length].to_vec());
+        *position += 1 + length;
+        value.expect("UTF-8 literal")
+    };
+    while position < block.len() {
+        assert_eq!(block[position], 0x00, "literal without indexing, new name");
+        position += 1;
+        let name = string(&mut position);
+        fields.push((name, string(&mut position)));
+    }
+    fields
+}
+
+fn header(name: &str, value: &str) -> (String, String) {
+    (name.to_string(), value.to_string())
+}
+
+// =============================================================================
+// RFC 7541 Appendix C
+// =============================================================================
+
+/// The three requests of one connection, each header block read against the
+/// dynamic table the previous ones built
+fn requests_of_appendix_c(blocks: [&str; 3]) {
+    let (port, requests) = echo_server();
+    let mut stream = connect(port);
+    let expected = [
+        ("/", vec![]),
+        ("/", vec![header("cache-control", "no-cache")]),
+        ("/index.html", vec![header("custom-key", "custom-value")]),
+    ];
+    for (n, (block, (path, headers))) in blocks.iter().zip(expected).enumerate() {
+        let id = 2 * n as u32 + 1;
+        write_frame(
+            &mut stream,
+            HEADERS,
+            END_HEADERS | END_STREAM,
+            id,
+            &hex(block),
+        );
+        let request = requests
+            .recv_timeout(Duration::from_secs(5))
+            .expect("request handed to the handler");
+        assert_eq!(request.method, "GET", "request {id}");
+        assert_eq!(request.path, path, "request {id}");
+        assert_eq!(request.headers, headers, "request {id}");
+        assert!(request.body.is_empty());
+
+        let (block, body) = response(&mut stream, id);
+        assert_eq!(
+            response_fields(&block),
+            [header("x-method", "GET"), header("x-path", path)]
+        );
+        assert!(body.is_empty());
+    }
+}
+
+#[test]
+fn request_examples_without_huffman_coding() {
+    // C.3.1 to C.3.3
+    requests_of_appendix_c([
+        "8286 8441 0f77 7777 2e65 7861 6d70 6c65 2e63 6f6d",
+        "8286 84be 5808 6e6f 2d63 6163 6865",
+        "8287 85bf 400a 6375 7374 6f6d 2d6b 6579 0c63 7573 746f 6d2d 7661 6c75 65",
+    ]);
+}
+
+#[test]
+fn request_examples_with_huffman_coding() {
+    // C.4.1 to C.4.3
+    requests_of_appendix_c([
+        "8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff",
+        "8286 84be 5886 a8eb 1064 9cbf",
+        "8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf",
+    ]);
+}
+
+// =============================================================================
+// Bodies
+// =============================================================================
+
+#[test]
+fn body_in_several_data_frames_is_echoed_back() {
+    let (port, requests) = echo_server();
+    let mut stream = connect(port);
+
+    // :method POST, :scheme http, :path / (indexed), content-type literal
+    let mut block = hex("8386 84");
+    block.extend([0x00, 12]);
+    block.extend(b"content-type");
+    block.push(16);
+    block.extend(b"application/grpc");
+    write_frame(&mut stream, HEADERS, END_HEADERS, 1, &block);
+    write_frame(&mut stream, DATA, 0, 1, b"hello, ");
+    // Padded: a length byte, the data, then three bytes of padding
+    write_frame(
+        &mut stream,
+        DATA,
+        PADDED | END_STREAM,
+        1,
+        b"\x03world\0\0\0",
+    );
+
+    let request = requests
+        .recv_timeout(Duration::from_secs(5))
+        .expect("request handed to the handler");
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.header("Content-Type"), Some("application/grpc"));
+    assert_eq!(request.body, b"hello, world");
+
+    let (block, body) = response(&mut stream, 1);
+    assert_eq!(
+        response_fields(&block),
+        [header("x-method", "POST"), header("x-path", "/")]
+    );
+    assert_eq!(body, b"hello, world");
+}
+
+// =============================================================================
+// Malformed Input
+// =============================================================================
+
+#[test]
+fn frame_above_the_maximum_size_is_a_protocol_error() {
+    let (port, _requests) = echo_server();
+    let mut stream = connect(port);
+    // The header only: 16 385 bytes announced, one more than the default
+    stream
+        .write_all(&[0x00, 0x40, 0x01, DATA, 0, 0, 0, 0, 1])
+        .expect("frame header");
+    assert_eq!(goaway(&mut stream), PROTOCOL_ERROR);
+}
+
+#[test]
+fn settings_not_a_multiple_of_six_bytes_is_a_protocol_error() {
+    let (port, _requests) = echo_server();
+    let mut stream = connect(port);
+    write_frame(&mut stream, SETTINGS, 0, 0, &[0, 4, 0, 0, 1]);
+    assert_eq!(goaway(&mut stream), PROTOCOL_ERROR);
+}
+
+#[test]
+fn padding_longer_than_the_frame_is_a_protocol_error() {
+    let (port, _requests) = echo_server();
+    let mut stream = connect(port);
+    write_frame(
+        &mut stream,
+        HEADERS,
+        END_HEADERS | PADDED,
+        1,
+        &[10, 0x82, 0x84],
+    );
+    assert_eq!(goaway(&mut stream), PROTOCOL_ERROR);
+}
+
+#[test]
+fn even_stream_identifier_is_a_protocol_error() {
+    let (port, _requests) = echo_server();
+    let mut stream = connect(port);
+    write_frame(
+        &mut stream,
+        HEADERS,
+        END_HEADERS | END_STREAM,
+        2,
+        &hex("8286 84"),
+    );
+    assert_eq!(goaway(&mut stream), PROTOCOL_ERROR);
+}
+
+#[test]
+fn index_past_the_tables_is_a_compression_error() {
+    let (port, _requests) = echo_server();
+    let mut stream = connect(port);
+    // Index 62: the first dynamic entry, of a table still empty
+    write_frame(
+        &mut stream,
+        HEADERS,
+        END_HEADERS | END_STREAM,
+        1,
+        &hex("8286 84be"),
+    );
+    assert_eq!(goaway(&mut stream), COMPRESSION_ERROR);
+}
+
+#[test]
+fn truncated_header_block_is_a_compression_error() {
+    let (port, _requests) = echo_server();
+    let mut stream = connect(port);
+    // The literal of C.3.1 cut in the middle of its value
+    write_frame(
+        &mut stream,
+        HEADERS,
+        END_HEADERS | END_STREAM,
+        1,
+        &hex("8286 8441 0f77 7777"),
+    );
+    assert_eq!(goaway(&mut stream), COMPRESSION_ERROR);
+}
+
+#[test]
+fn huffman_padding_of_zeros_is_a_compression_error() {
+    let (port, _requests) = echo_server();
+    let mut stream = connect(port);
+    // A literal whose Huffman value is one byte of zeros: '0' (00000), then
+    // three bits of padding that are not the ones of EOS
+    write_frame(
+        &mut stream,
+        HEADERS,
+        END_HEADERS | END_STREAM,
+        1,
+        &hex("8286 8404 8100"),
+    );
+    assert_eq!(goaway(&mut stream), COMPRESSION_ERROR);
+}
+
+#[test]
+fn connection_without_the_preface_is_closed() {
+    let (port, _requests) = echo_server();
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("connect");
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("read timeout");
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .expect("request");
+    assert!(read_frame(&mut stream).is_none());
+}
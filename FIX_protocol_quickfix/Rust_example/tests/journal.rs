@@ -0,0 +1,179 @@
+// =============================================================================
+// Wire Journal
+// =============================================================================
+// Journal and JournalReader (trading::session::journal) in clear and
+// encrypted: records read back as written, a record torn at the end cut off
+// when the journal is opened again, the messages of an encrypted journal
+// unreadable in the file and decrypted only with their key and in their
+// record, and journals of one kind refused where the other is expected.
+// =============================================================================
+
+use std::{fs, path::PathBuf};
+
+use trading::{
+    session::{
+        journal::{Journal, JournalError, JournalReader, MAGIC, MAGIC_ENCRYPTED},
+        Direction,
+    },
+    store::encrypted::EncryptionKey,
+};
+
+const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+const OTHER_KEY: &str = "1f1e1d1c1b1a191817161514131211100f0e0d0c0b0a09080706050403020100";
+
+const ORDER: &[u8] = b"8=FIX.4.4\x0135=D\x0111=ORD-1\x0155=AAPL\x011=ACC-7\x0144=150.25\x01";
+const REPORT: &[u8] = b"8=FIX.4.4\x0135=8\x0111=ORD-1\x0139=2\x0131=150.25\x01";
+
+fn key(value: &str) -> EncryptionKey {
+    EncryptionKey::from_hex(value).expect("test key")
+}
+
+/// A journal path of its own per test, removed if left by an earlier run
+fn path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("journal-{}-{name}.jrnl", std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+/// The two messages of every test, at fixed times
+fn write(journal: &Journal) {
+    journal
+        .record_at(1_000, "CLIENT->EXCHANGE", Direction::Outbound, ORDER)
+        .expect("record");
+    journal
+        .record_at(2_000, "CLIENT->EXCHANGE", Direction::Inbound, REPORT)
+        .expect("record");
+    journal.flush().expect("flush");
+}
+
+/// At, direction and payload of each record
+fn read(reader: JournalReader<impl std::io::Read>) -> Vec<(i64, Direction, Vec<u8>)> {
+    reader
+        .map(|x| x.expect("record"))
+        .map(|x| (x.at, x.direction, x.payload))
+        .collect()
+}
+
+fn written() -> Vec<(i64, Direction, Vec<u8>)> {
+    vec![
+        (1_000, Direction::Outbound, ORDER.to_vec()),
+        (2_000, Direction::Inbound, REPORT.to_vec()),
+    ]
+}
+
+// =============================================================================
+// In clear
+// =============================================================================
+
+#[test]
+fn records_are_read_back_as_written() {
+    let path = path("clear");
+    write(&Journal::open(&path).expect("open"));
+
+    assert!(fs::read(&path).expect("journal").starts_with(MAGIC));
+    assert_eq!(read(JournalReader::open(&path).expect("reader")), written());
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn torn_record_is_cut_off_when_opened_again() {
+    let path = path("torn");
+    write(&Journal::open(&path).expect("open"));
+    let whole = fs::metadata(&path).expect("journal").len();
+
+    // A writer killed in the middle of a third record
+    let mut bytes = fs::read(&path).expect("journal");
+    bytes.extend([40, 0, 0, 0, 1, 2, 3]);
+    fs::write(&path, &bytes).expect("tear");
+    assert!(matches!(
+        JournalReader::open(&path).expect("reader").last(),
+        Some(Err(JournalError::Truncated(offset))) if offset == whole
+    ));
+
+    drop(Journal::open(&path).expect("open again"));
+    assert_eq!(fs::metadata(&path).expect("journal").len(), whole);
+    assert_eq!(read(JournalReader::open(&path).expect("reader")), written());
+    let _ = fs::remove_file(&path);
+}
+
+// =============================================================================
+// Encrypted
+// =============================================================================
+
+#[test]
+fn encrypted_records_are_read_back_with_the_key() {
+    let path = path("encrypted");
+    write(&Journal::open_encrypted(&path, &key(KEY)).expect("open"));
+
+    let bytes = fs::read(&path).expect("journal");
+    assert!(bytes.starts_with(MAGIC_ENCRYPTED));
+    for clear in [&b"ACC-7"[..], b"150.25", b"35=D"] {
+        assert!(!bytes.windows(clear.len()).any(|x| x == clear));
+    }
+    // Sessions stay in clear, as store keys do
+    assert!(bytes.windows(6).any(|x| x == b"CLIENT"));
+
+    let reader = JournalReader::open_with_key(&path, &key(KEY)).expect("reader");
+    assert_eq!(read(reader), written());
+
+    // Appended to after a reopen, under the same key
+    write(&Journal::open_encrypted(&path, &key(KEY)).expect("open again"));
+    let reader = JournalReader::open_with_key(&path, &key(KEY)).expect("reader");
+    assert_eq!(read(reader).len(), 4);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn encrypted_records_do_not_decrypt_with_another_key() {
+    let path = path("other-key");
+    write(&Journal::open_encrypted(&path, &key(KEY)).expect("open"));
+
+    let mut reader = JournalReader::open_with_key(&path, &key(OTHER_KEY)).expect("reader");
+    assert!(matches!(
+        reader.next(),
+        Some(Err(JournalError::Decrypt(offset))) if offset == MAGIC.len() as u64
+    ));
+    assert!(reader.next().is_none(), "stops at the first error");
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn encrypted_payload_is_bound_to_its_record() {
+    let path = path("bound");
+    write(&Journal::open_encrypted(&path, &key(KEY)).expect("open"));
+
+    // The timestamp of the first record, right after the header and the
+    // record length, changed: the payload no longer decrypts
+    let mut bytes = fs::read(&path).expect("journal");
+    bytes[MAGIC.len() + 4] ^= 1;
+    fs::write(&path, &bytes).expect("tamper");
+    let mut reader = JournalReader::open_with_key(&path, &key(KEY)).expect("reader");
+    assert!(matches!(reader.next(), Some(Err(JournalError::Decrypt(_)))));
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn journals_of_the_other_kind_are_refused() {
+    let (clear, encrypted) = (path("kind-clear"), path("kind-encrypted"));
+    write(&Journal::open(&clear).expect("open"));
+    write(&Journal::open_encrypted(&encrypted, &key(KEY)).expect("open"));
+
+    assert!(matches!(
+        JournalReader::open(&encrypted),
+        Err(JournalError::Encrypted)
+    ));
+    assert!(matches!(
+        Journal::open(&encrypted),
+        Err(JournalError::Encrypted)
+    ));
+    assert!(matches!(
+        Journal::open_encrypted(&clear, &key(KEY)),
+        Err(JournalError::NotEncrypted)
+    ));
+
+    // A key does not stop a journal in clear from being read
+    let reader = JournalReader::open_with_key(&clear, &key(KEY)).expect("reader");
+    assert_eq!(read(reader), written());
+    let _ = fs::remove_file(&clear);
+    let _ = fs::remove_file(&encrypted);
+}
@@ -0,0 +1,192 @@
+// =============================================================================
+// Kafka Producer
+// =============================================================================
+// The checksum and the partitioner of the producer (trading::gateway::kafka):
+// CRC-32C against its check value and the RFC 3720 vectors, murmur2 against
+// the values of the Java client's own tests (UtilsTest.testMurmur2), then a
+// record published to a one-broker fake cluster: sent to the partition the
+// Java client picks, in a RecordBatch whose CRC checks.
+// =============================================================================
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+use trading::gateway::kafka::{crc32c, murmur2, partition, KafkaConfig, KafkaPublisher};
+
+// =============================================================================
+// CRC-32C
+// =============================================================================
+
+#[test]
+fn crc32c_check_value() {
+    assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+    assert_eq!(crc32c(b""), 0);
+}
+
+#[test]
+fn crc32c_matches_the_rfc_3720_vectors() {
+    let ascending: Vec<u8> = (0..32).collect();
+    let descending: Vec<u8> = (0..32).rev().collect();
+    assert_eq!(crc32c(&[0; 32]), 0x8A91_36AA);
+    assert_eq!(crc32c(&[0xff; 32]), 0x62A8_AB43);
+    assert_eq!(crc32c(&ascending), 0x46DD_794E);
+    assert_eq!(crc32c(&descending), 0x113F_DB5C);
+}
+
+// =============================================================================
+// Partitioner
+// =============================================================================
+
+#[test]
+fn murmur2_matches_the_java_client() {
+    // Utils.murmur2 of the Java client, masked positive by Utils.toPositive
+    let cases: [(&str, i32); 6] = [
+        ("21", -973_932_308),
+        ("foobar", -790_332_482),
+        ("a-little-bit-long-string", -985_981_536),
+        ("a-little-bit-longer-string", -1_486_304_829),
+        (
+            "lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8",
+            -58_897_971,
+        ),
+        ("abc", 479_470_107),
+    ];
+    for (key, java) in cases {
+        assert_eq!(murmur2(key.as_bytes()), java as u32 & 0x7fff_ffff, "{key}");
+    }
+}
+
+#[test]
+fn partition_is_the_positive_hash_modulo_the_partitions() {
+    // toPositive(murmur2("foobar")) = 1357151166
+    assert_eq!(partition(b"foobar", 1), 0);
+    assert_eq!(partition(b"foobar", 12), 6);
+    assert_eq!(partition(b"foobar", 100), 66);
+    assert_eq!(partition(b"a-little-bit-long-string", 3), 2);
+    assert_eq!(partition(b"abc", 6), 3);
+}
+
+// =============================================================================
+// Produce
+// =============================================================================
+
+const TOPIC: &str = "executions";
+const PARTITIONS: i32 = 12;
+
+fn put_i16(buf: &mut Vec<u8>, value: i16) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_i32(buf: &mut Vec<u8>, value: i32) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_string(buf: &mut Vec<u8>, value: &str) {
+    put_i16(buf, value.len() as i16);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+/// One size-prefixed request
+fn read_request(stream: &mut TcpStream) -> Vec<u8> {
+    let mut size = [0; 4];
+    stream.read_exact(&mut size).expect("request size");
+    let mut request = vec![0; i32::from_be_bytes(size) as usize];
+    stream.read_exact(&mut request).expect("request");
+    request
+}
+
+fn write_response(stream: &mut TcpStream, request: &[u8], body: &[u8]) {
+    // Correlation id, after the api key and version
+    let mut response = request[4..8].to_vec();
+    response.extend_from_slice(body);
+    stream
+        .write_all(&(response.len() as i32).to_be_bytes())
+        .and_then(|()| stream.write_all(&response))
+        .expect("response");
+}
+
+/// Metadata v1: this broker as node 1, leading every partition of TOPIC
+fn metadata(port: u16) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_i32(&mut body, 1);
+    put_i32(&mut body, 1);
+    put_string(&mut body, "127.0.0.1");
+    put_i32(&mut body, i32::from(port));
+    put_i16(&mut body, -1); // rack
+    put_i32(&mut body, 1); // controller
+    put_i32(&mut body, 1);
+    put_i16(&mut body, 0);
+    put_string(&mut body, TOPIC);
+    body.push(0); // is_internal
+    put_i32(&mut body, PARTITIONS);
+    for partition in 0..PARTITIONS {
+        put_i16(&mut body, 0);
+        put_i32(&mut body, partition);
+        put_i32(&mut body, 1); // leader
+        put_i32(&mut body, 0); // replicas
+        put_i32(&mut body, 0); // isr
+    }
+    body
+}
+
+/// Partition and RecordBatch of a Produce v3 request of one topic and
+/// partition, answered with success
+fn produced(stream: &mut TcpStream) -> (i32, Vec<u8>) {
+    let request = read_request(stream);
+    assert_eq!(request[..4], [0, 0, 0, 3], "Produce v3");
+    let client_id = i16::from_be_bytes([request[8], request[9]]) as usize;
+    let mut at = 10 + client_id;
+    at += 2 + 2 + 4; // transactional id, acks, timeout
+    assert_eq!(request[at..at + 4], [0, 0, 0, 1], "one topic");
+    at += 4 + 2 + TOPIC.len();
+    assert_eq!(request[at..at + 4], [0, 0, 0, 1], "one partition");
+    let number = |at: usize| i32::from_be_bytes(request[at..at + 4].try_into().unwrap());
+    let partition = number(at + 4);
+    let size = number(at + 8) as usize;
+    let batch = request[at + 12..at + 12 + size].to_vec();
+
+    let mut body = Vec::new();
+    put_i32(&mut body, 1);
+    put_string(&mut body, TOPIC);
+    put_i32(&mut body, 1);
+    put_i32(&mut body, partition);
+    put_i16(&mut body, 0);
+    body.extend_from_slice(&0i64.to_be_bytes()); // base offset
+    body.extend_from_slice(&(-1i64).to_be_bytes()); // log append time
+    put_i32(&mut body, 0); // throttle time
+    write_response(stream, &request, &body);
+    (partition, batch)
+}
+
+#[test]
+fn record_goes_to_its_partition_in_a_checked_batch() {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).expect("bind a free port");
+    let port = listener.local_addr().expect("local address").port();
+    let broker = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().expect("metadata connection");
+        let request = read_request(&mut stream);
+        assert_eq!(request[..4], [0, 3, 0, 1], "Metadata v1");
+        write_response(&mut stream, &request, &metadata(port));
+
+        let (mut stream, _) = listener.accept().expect("produce connection");
+        produced(&mut stream)
+    });
+
+    let config = KafkaConfig::new(&format!("127.0.0.1:{port}"), TOPIC, "kafka-test");
+    let publisher = KafkaPublisher::start(config).expect("publisher");
+    publisher.publish("foobar", "{\"ExecID\":\"E-1\"}".to_string());
+    let (partition, batch) = broker.join().expect("broker");
+
+    assert_eq!(partition, 6, "as the Java client partitions \"foobar\"");
+    assert_eq!(batch[16], 2, "magic");
+    let crc = u32::from_be_bytes(batch[17..21].try_into().unwrap());
+    assert_eq!(crc, crc32c(&batch[21..]), "CRC of attributes .. records");
+    let value = b"{\"ExecID\":\"E-1\"}";
+    assert!(
+        batch.ends_with(&[&value[..], &[0]].concat()),
+        "value, no headers"
+    );
+}
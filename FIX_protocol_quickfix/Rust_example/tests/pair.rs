@@ -0,0 +1,129 @@
+// =============================================================================
+// Session Logic Through a Pair
+// =============================================================================
+// run_pair (trading::testing) against a small venue answering every
+// NewOrderSingle with an acknowledgement: both ends log on, an order makes a
+// D -> 8 round trip, a Logout ends the session on both sides.
+// =============================================================================
+
+use quickfix::{
+    send_to_target, ApplicationCallback, FieldMap, Message, MsgFromAppError, QuickFixError,
+    SessionId,
+};
+use trading::{
+    testing::{message, run_pair, CallbackKind, HarnessError, NoApp, PairConfig, DEFAULT_TIMEOUT},
+    time::transact_time,
+};
+
+/// Acknowledges every NewOrderSingle (ExecType / OrdStatus New)
+#[derive(Default)]
+struct AckVenue;
+
+impl ApplicationCallback for AckVenue {
+    fn on_msg_from_app(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        if msg.with_header(|h| h.get_field(35)).as_deref() != Some("D") {
+            return Ok(());
+        }
+        let field = |tag| msg.get_field(tag).ok_or(MsgFromAppError::FieldNotFound);
+        let (cl_ord_id, symbol, side) = (field(11)?, field(55)?, field(54)?);
+        let quantity = field(38)?;
+        let report = message(
+            "8",
+            &[
+                (37, &format!("V-{cl_ord_id}")),
+                (11, &cl_ord_id),
+                (17, &format!("E-{cl_ord_id}")),
+                (150, "0"),
+                (39, "0"),
+                (55, &symbol),
+                (54, &side),
+                (151, &quantity),
+                (14, "0"),
+                (6, "0"),
+                (60, &transact_time()),
+            ],
+        );
+        // Fails only if the client is gone, which the test sees as a timeout
+        let _ = report.and_then(|x| send_to_target(x, session));
+        Ok(())
+    }
+}
+
+fn new_order_single(cl_ord_id: &str) -> Result<Message, QuickFixError> {
+    message(
+        "D",
+        &[
+            (11, cl_ord_id),
+            (55, "AAPL"),
+            (54, "1"),
+            (38, "100"),
+            (40, "2"),
+            (44, "150.25"),
+            (60, &transact_time()),
+        ],
+    )
+}
+
+#[test]
+fn both_ends_log_on() -> Result<(), HarnessError> {
+    let config = PairConfig::default();
+    run_pair(&config, NoApp, NoApp, |pair| {
+        let logon = pair.venue().expect_admin("A", DEFAULT_TIMEOUT)?;
+        assert_eq!(logon.get(49), Some("CLIENT"));
+        assert_eq!(logon.get(56), Some("VENUE"));
+        let logon = pair.client().expect_admin("A", DEFAULT_TIMEOUT)?;
+        assert_eq!(logon.get(49), Some("VENUE"));
+
+        for end in [pair.venue().events(), pair.client().events()] {
+            let logons = end.iter().filter(|x| x.kind == CallbackKind::Logon);
+            assert_eq!(logons.count(), 1);
+        }
+        Ok(())
+    })?
+}
+
+#[test]
+fn new_order_single_is_acknowledged() -> Result<(), HarnessError> {
+    let config = PairConfig::default();
+    run_pair(&config, AckVenue, NoApp, |pair| {
+        pair.send_from_client(new_order_single("ORD-1")?)?;
+
+        let order = pair.venue().expect_app("D", DEFAULT_TIMEOUT)?;
+        assert_eq!(order.get(11), Some("ORD-1"));
+        assert_eq!(order.get(55), Some("AAPL"));
+
+        let report = pair.client().expect_app("8", DEFAULT_TIMEOUT)?;
+        assert_eq!(report.get(11), Some("ORD-1"));
+        assert_eq!(report.get(150), Some("0"));
+        assert_eq!(report.get(39), Some("0"));
+        assert_eq!(report.get(151), Some("100"));
+        assert!(report.get(60).is_some(), "report without TransactTime");
+
+        // One order, one report
+        pair.client().expect_none(DEFAULT_TIMEOUT / 5, |x| {
+            x.kind == CallbackKind::FromApp && x.msg_type() == Some("8")
+        })
+    })?
+}
+
+#[test]
+fn logout_ends_the_session_on_both_ends() -> Result<(), HarnessError> {
+    let config = PairConfig::default();
+    run_pair(&config, NoApp, NoApp, |pair| {
+        pair.send_from_client(message("5", &[(58, "end of test")])?)?;
+
+        let logout = pair.venue().expect_admin("5", DEFAULT_TIMEOUT)?;
+        assert_eq!(logout.get(58), Some("end of test"));
+        pair.venue().expect("venue logout", DEFAULT_TIMEOUT, |x| {
+            x.kind == CallbackKind::Logout
+        })?;
+
+        // The venue answers with a Logout of its own
+        pair.client().expect_admin("5", DEFAULT_TIMEOUT)?;
+        pair.client()
+            .expect("client logout", DEFAULT_TIMEOUT, |x| {
+                x.kind == CallbackKind::Logout
+            })?;
+        Ok(())
+    })?
+}
@@ -0,0 +1,346 @@
+// =============================================================================
+// Parquet Files
+// =============================================================================
+// Files written by trading::parquet read back with a Thrift compact reader
+// written here: the magic at both ends, the metadata length before the last
+// one, the schema, the row group and its column chunks, then the pages the
+// chunks point at, their values decoded when not compressed.
+// =============================================================================
+
+use trading::parquet::{Codec, Column, ColumnType, ParquetError, Table, Value};
+
+// =============================================================================
+// Thrift Compact Reader
+// =============================================================================
+
+/// A Thrift value, of the types the format uses
+#[derive(Debug, Clone, PartialEq)]
+enum Thrift {
+    Integer(i64),
+    Binary(Vec<u8>),
+    List(Vec<Thrift>),
+    Struct(Vec<(i16, Thrift)>),
+}
+
+impl Thrift {
+    fn field(&self, id: i16) -> &Thrift {
+        match self {
+            Thrift::Struct(fields) => fields
+                .iter()
+                .find(|x| x.0 == id)
+                .map(|x| &x.1)
+                .unwrap_or_else(|| panic!("no field {id} in {fields:?}")),
+            _ => panic!("not a struct: {self:?}"),
+        }
+    }
+
+    fn has(&self, id: i16) -> bool {
+        matches!(self, Thrift::Struct(fields) if fields.iter().any(|x| x.0 == id))
+    }
+
+    fn integer(&self, id: i16) -> i64 {
+        match self.field(id) {
+            Thrift::Integer(x) => *x,
+            other => panic!("field {id} not an integer: {other:?}"),
+        }
+    }
+
+    fn text(&self, id: i16) -> String {
+        match self.field(id) {
+            Thrift::Binary(x) => String::from_utf8(x.clone()).expect("UTF-8"),
+            other => panic!("field {id} not binary: {other:?}"),
+        }
+    }
+
+    fn list(&self, id: i16) -> &[Thrift] {
+        match self.field(id) {
+            Thrift::List(x) => x,
+            other => panic!("field {id} not a list: {other:?}"),
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> u8 {
+        let byte = self.data[self.position];
+        self.position += 1;
+        byte
+    }
+
+    fn varint(&mut self) -> u64 {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte();
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+        }
+        panic!("varint longer than 10 bytes");
+    }
+
+    fn zigzag(&mut self) -> i64 {
+        let value = self.varint();
+        (value >> 1) as i64 ^ -((value & 1) as i64)
+    }
+
+    fn value(&mut self, kind: u8) -> Thrift {
+        match kind {
+            5 | 6 => Thrift::Integer(self.zigzag()),
+            8 => {
+                let len = self.varint() as usize;
+                let value = self.data[self.position..self.position + len].to_vec();
+                self.position += len;
+                Thrift::Binary(value)
+            }
+            9 => {
+                let header = self.byte();
+                let size = match header >> 4 {
+                    15 => self.varint() as usize,
+                    x => usize::from(x),
+                };
+                Thrift::List((0..size).map(|_| self.value(header & 0x0f)).collect())
+            }
+            12 => self.structure(),
+            _ => panic!("type {kind} not written by the format"),
+        }
+    }
+
+    fn structure(&mut self) -> Thrift {
+        let mut fields = Vec::new();
+        let mut last = 0;
+        loop {
+            let header = self.byte();
+            if header == 0 {
+                return Thrift::Struct(fields);
+            }
+            let id = match header >> 4 {
+                0 => self.zigzag() as i16,
+                delta => last + i16::from(delta),
+            };
+            assert!(id > last, "field {id} after {last}");
+            fields.push((id, self.value(header & 0x0f)));
+            last = id;
+        }
+    }
+}
+
+/// A struct at the start of `data`, and its length
+fn read_struct(data: &[u8]) -> (Thrift, usize) {
+    let mut reader = Reader { data, position: 0 };
+    let value = reader.structure();
+    (value, reader.position)
+}
+
+// =============================================================================
+// Files
+// =============================================================================
+
+fn columns() -> Vec<Column> {
+    vec![
+        Column::new("symbol", ColumnType::Text),
+        Column::new("qty", ColumnType::Int64),
+        Column::new("price", ColumnType::Double),
+        Column::new("transact_time", ColumnType::Timestamp),
+        Column::new("is_buy", ColumnType::Boolean),
+    ]
+}
+
+/// Three fills, the second without a price
+fn fills() -> Table {
+    let mut table = Table::new(columns());
+    let rows = [
+        ("AAPL", 100, Some(150.25), true),
+        ("MSFT", -200, None, false),
+        ("AAPL", 300, Some(150.5), true),
+    ];
+    for (i, (symbol, qty, price, is_buy)) in rows.into_iter().enumerate() {
+        table
+            .push(vec![
+                Value::Text(symbol.to_string()),
+                Value::Int64(qty),
+                price.map_or(Value::Null, Value::Double),
+                Value::Int64(1_792_143_000_000_000 + i as i64),
+                Value::Boolean(is_buy),
+            ])
+            .expect("row of the schema");
+    }
+    table
+}
+
+/// The FileMetaData of `file`, the magic and its length checked
+fn metadata(file: &[u8]) -> Thrift {
+    assert!(file.len() >= 12, "{} bytes", file.len());
+    assert_eq!(file[..4], *b"PAR1", "leading magic");
+    assert_eq!(file[file.len() - 4..], *b"PAR1", "trailing magic");
+    let footer = file.len() - 8;
+    let len = u32::from_le_bytes(file[footer..footer + 4].try_into().unwrap()) as usize;
+    assert!(4 + len <= footer, "metadata of {len} bytes in the file");
+
+    let (meta, read) = read_struct(&file[footer - len..footer]);
+    assert_eq!(read, len, "metadata length");
+    meta
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[test]
+fn file_starts_and_ends_with_the_magic() {
+    for codec in [Codec::Uncompressed, Codec::Gzip] {
+        let file = fills().to_parquet(codec);
+        metadata(&file);
+    }
+}
+
+#[test]
+fn schema_is_a_root_and_one_optional_element_per_column() {
+    let meta = metadata(&fills().to_parquet(Codec::default()));
+    assert_eq!(meta.integer(1), 1, "version");
+    assert_eq!(meta.text(6), "trading", "created_by");
+
+    let schema = meta.list(2);
+    assert_eq!(schema.len(), 6);
+    assert_eq!(schema[0].text(4), "schema");
+    assert_eq!(schema[0].integer(5), 5, "num_children");
+
+    // name, physical type, converted type
+    let expected = [
+        ("symbol", 6, Some(0)),
+        ("qty", 2, None),
+        ("price", 5, None),
+        ("transact_time", 2, Some(10)),
+        ("is_buy", 0, None),
+    ];
+    for (element, (name, physical, converted)) in schema[1..].iter().zip(expected) {
+        assert_eq!(element.text(4), name);
+        assert_eq!(element.integer(1), physical, "{name}");
+        assert_eq!(element.integer(3), 1, "{name} OPTIONAL");
+        assert_eq!(
+            element.has(6).then(|| element.integer(6)),
+            converted,
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn one_row_group_of_every_row_and_column() {
+    for codec in [Codec::Uncompressed, Codec::Gzip] {
+        let file = fills().to_parquet(codec);
+        let meta = metadata(&file);
+        assert_eq!(meta.integer(3), 3, "num_rows");
+
+        let groups = meta.list(4);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].integer(3), 3, "row group num_rows");
+        let chunks = groups[0].list(1);
+        assert_eq!(chunks.len(), 5);
+
+        let mut end = 4;
+        let mut uncompressed = 0;
+        for (chunk, column) in chunks.iter().zip(columns()) {
+            let info = chunk.field(3);
+            assert_eq!(info.list(3), [Thrift::Binary(column.name.into_bytes())]);
+            assert_eq!(info.integer(4), if codec == Codec::Gzip { 2 } else { 0 });
+            assert_eq!(info.integer(5), 3, "num_values");
+
+            // Chunks one after the other, from the leading magic on
+            let offset = info.integer(9);
+            assert_eq!(chunk.integer(2), offset, "file_offset");
+            assert_eq!(offset, end);
+            end += info.integer(7);
+            uncompressed += info.integer(6);
+        }
+        let footer = file.len() - 8;
+        let len = u32::from_le_bytes(file[footer..footer + 4].try_into().unwrap());
+        assert_eq!(
+            end as usize,
+            footer - len as usize,
+            "metadata after the chunks"
+        );
+        assert_eq!(groups[0].integer(2), uncompressed, "total_byte_size");
+    }
+}
+
+#[test]
+fn pages_hold_the_levels_and_values_written() {
+    let file = fills().to_parquet(Codec::Uncompressed);
+    let meta = metadata(&file);
+    let mut pages = Vec::new();
+    for chunk in meta.list(4)[0].list(1) {
+        let offset = chunk.field(3).integer(9) as usize;
+        let (header, len) = read_struct(&file[offset..]);
+        assert_eq!(header.integer(1), 0, "DATA_PAGE");
+        assert_eq!(header.integer(2), header.integer(3), "not compressed");
+        assert_eq!(header.field(5).integer(1), 3, "num_values");
+        let start = offset + len;
+        pages.push(file[start..start + header.integer(3) as usize].to_vec());
+    }
+
+    // Definition levels: one bit-packed run of a byte, then the values
+    let values = |page: &[u8], defined: u8| {
+        assert_eq!(page[..6], [2, 0, 0, 0, 0b11, defined]);
+        page[6..].to_vec()
+    };
+    let text = values(&pages[0], 0b111);
+    assert_eq!(text, b"\x04\0\0\0AAPL\x04\0\0\0MSFT\x04\0\0\0AAPL");
+    let qty = values(&pages[1], 0b111);
+    assert_eq!(qty, [100i64, -200, 300].map(i64::to_le_bytes).concat());
+    let price = values(&pages[2], 0b101);
+    assert_eq!(price, [150.25f64, 150.5].map(f64::to_le_bytes).concat());
+    let time = values(&pages[3], 0b111);
+    assert_eq!(time[..8], 1_792_143_000_000_000i64.to_le_bytes());
+    assert_eq!(values(&pages[4], 0b111), [0b101]);
+}
+
+#[test]
+fn gzip_pages_are_gzip_members() {
+    let file = fills().to_parquet(Codec::Gzip);
+    let meta = metadata(&file);
+    for chunk in meta.list(4)[0].list(1) {
+        let offset = chunk.field(3).integer(9) as usize;
+        let (header, len) = read_struct(&file[offset..]);
+        let page = &file[offset + len..offset + len + header.integer(3) as usize];
+        assert_eq!(page[..3], [0x1f, 0x8b, 8], "gzip, deflate");
+        let size = u32::from_le_bytes(page[page.len() - 4..].try_into().unwrap());
+        assert_eq!(i64::from(size), header.integer(2), "ISIZE of the page");
+    }
+}
+
+#[test]
+fn empty_table_is_a_file_of_no_rows() {
+    let table = Table::new(columns());
+    assert!(table.is_empty());
+    let meta = metadata(&table.to_parquet(Codec::Uncompressed));
+    assert_eq!(meta.integer(3), 0);
+    assert_eq!(meta.list(4)[0].integer(3), 0);
+}
+
+#[test]
+fn rows_not_of_the_schema_are_refused() {
+    let mut table = Table::new(columns());
+    assert_eq!(
+        table.push(vec![Value::Null; 4]),
+        Err(ParquetError::Width(4, 5))
+    );
+    assert_eq!(
+        table.push(vec![
+            Value::Text("AAPL".to_string()),
+            Value::Double(100.0),
+            Value::Null,
+            Value::Null,
+            Value::Null,
+        ]),
+        Err(ParquetError::Type("qty".to_string()))
+    );
+    assert!(table.is_empty());
+    assert_eq!(table.push(vec![Value::Null; 5]), Ok(()));
+    assert_eq!(table.len(), 1);
+}
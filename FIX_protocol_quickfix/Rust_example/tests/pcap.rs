@@ -0,0 +1,516 @@
+// =============================================================================
+// Packet Captures
+// =============================================================================
+// PcapReader, tcp_segment and FixStreams (trading::pcap) on captures built in
+// memory: pcap of either byte order and precision and pcapng with its
+// interfaces, cut short at every kind of boundary; frames of the link types
+// taken in; and streams whose segments arrive split, out of order, twice or
+// never, for which the stream waits and then jumps the hole.
+// =============================================================================
+
+use std::net::SocketAddr;
+
+use trading::pcap::{tcp_segment, FixStreams, Frame, PcapError, PcapReader, MAX_PENDING};
+
+const CLIENT: &str = "10.0.0.1:50000";
+const SERVER: &str = "10.0.0.2:5001";
+
+/// Seconds and microseconds of the first frame
+const SECONDS: u32 = 1_792_000_000;
+const MICROS: u32 = 123_456;
+
+const PSH_ACK: u8 = 0x18;
+const SYN: u8 = 0x02;
+
+// =============================================================================
+// Fixtures
+// =============================================================================
+
+/// A FIX message with its BodyLength and CheckSum
+fn fix(body: &str) -> Vec<u8> {
+    let head = format!("8=FIX.4.4\x019={}\x01{body}", body.len());
+    let sum = head.bytes().map(u32::from).sum::<u32>() % 256;
+    format!("{head}10={sum:03}\x01").into_bytes()
+}
+
+fn heartbeat(seq: u32) -> Vec<u8> {
+    fix(&format!("35=0\x0134={seq}\x0149=CLIENT\x0156=SERVER\x01"))
+}
+
+fn new_order() -> Vec<u8> {
+    fix("35=D\x0134=2\x0149=CLIENT\x0156=SERVER\x0111=ORD-1\x0155=AAPL\x0154=1\x0138=100\x01")
+}
+
+/// IPv4 and TCP headers, then the payload
+fn ipv4_tcp(src: &str, dst: &str, seq: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+    let (src, dst): (SocketAddr, SocketAddr) = (src.parse().unwrap(), dst.parse().unwrap());
+    let (SocketAddr::V4(src), SocketAddr::V4(dst)) = (src, dst) else {
+        panic!("IPv4 addresses only");
+    };
+    let mut ip = vec![0x45, 0];
+    ip.extend(((40 + payload.len()) as u16).to_be_bytes());
+    ip.extend([0, 0, 0x40, 0, 64, 6, 0, 0]); // id, DF, TTL, TCP, checksum
+    ip.extend(src.ip().octets());
+    ip.extend(dst.ip().octets());
+    ip.extend(src.port().to_be_bytes());
+    ip.extend(dst.port().to_be_bytes());
+    ip.extend(seq.to_be_bytes());
+    ip.extend([0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+    ip.extend(payload);
+    ip
+}
+
+/// An Ethernet frame of an IPv4 packet
+fn ethernet(ip: &[u8]) -> Vec<u8> {
+    let mut frame = vec![2, 0, 0, 0, 0, 2, 2, 0, 0, 0, 0, 1, 0x08, 0x00];
+    frame.extend(ip);
+    frame
+}
+
+/// The SYN of a client to server connection whose data starts at `seq`
+fn syn(seq: u32) -> Vec<u8> {
+    ethernet(&ipv4_tcp(CLIENT, SERVER, seq.wrapping_sub(1), SYN, b""))
+}
+
+/// A client to server segment in an Ethernet frame
+fn segment(seq: u32, payload: &[u8]) -> Vec<u8> {
+    ethernet(&ipv4_tcp(CLIENT, SERVER, seq, PSH_ACK, payload))
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16, big_endian: bool) {
+    out.extend(if big_endian {
+        value.to_be_bytes()
+    } else {
+        value.to_le_bytes()
+    });
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32, big_endian: bool) {
+    out.extend(if big_endian {
+        value.to_be_bytes()
+    } else {
+        value.to_le_bytes()
+    });
+}
+
+/// A pcap file of Ethernet frames, one a millisecond from SECONDS.MICROS
+fn pcap(frames: &[Vec<u8>], big_endian: bool, nanos: bool) -> Vec<u8> {
+    let mut out = Vec::new();
+    put_u32(
+        &mut out,
+        if nanos { 0xA1B2_3C4D } else { 0xA1B2_C3D4 },
+        big_endian,
+    );
+    put_u16(&mut out, 2, big_endian);
+    put_u16(&mut out, 4, big_endian);
+    put_u32(&mut out, 0, big_endian); // thiszone
+    put_u32(&mut out, 0, big_endian); // sigfigs
+    put_u32(&mut out, 65_535, big_endian);
+    put_u32(&mut out, 1, big_endian); // Ethernet
+    for (index, frame) in frames.iter().enumerate() {
+        let micros = MICROS + 1_000 * index as u32;
+        put_u32(&mut out, SECONDS, big_endian);
+        put_u32(
+            &mut out,
+            if nanos { micros * 1_000 } else { micros },
+            big_endian,
+        );
+        put_u32(&mut out, frame.len() as u32, big_endian);
+        put_u32(&mut out, frame.len() as u32, big_endian);
+        out.extend(frame);
+    }
+    out
+}
+
+/// A pcapng block: type, length, body padded to 32 bits, length again
+fn block(kind: u32, body: &[u8], big_endian: bool) -> Vec<u8> {
+    let padded = body.len().next_multiple_of(4);
+    let length = (12 + padded) as u32;
+    let mut out = Vec::new();
+    put_u32(&mut out, kind, big_endian);
+    put_u32(&mut out, length, big_endian);
+    out.extend(body);
+    out.resize(8 + padded, 0);
+    put_u32(&mut out, length, big_endian);
+    out
+}
+
+fn section(big_endian: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_u32(&mut body, 0x1A2B_3C4D, big_endian);
+    put_u16(&mut body, 1, big_endian);
+    put_u16(&mut body, 0, big_endian);
+    body.extend((-1i64).to_le_bytes()); // section length unknown
+    block(0x0A0D_0D0A, &body, big_endian)
+}
+
+/// An Ethernet interface, `resolution` its if_tsresol if any
+fn interface(resolution: Option<u8>, big_endian: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_u16(&mut body, 1, big_endian);
+    put_u16(&mut body, 0, big_endian);
+    put_u32(&mut body, 65_535, big_endian);
+    if let Some(resolution) = resolution {
+        put_u16(&mut body, 9, big_endian);
+        put_u16(&mut body, 1, big_endian);
+        body.extend([resolution, 0, 0, 0]);
+        body.extend([0; 4]); // opt_endofopt
+    }
+    block(1, &body, big_endian)
+}
+
+fn enhanced_packet(interface: u32, stamp: u64, frame: &[u8], big_endian: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_u32(&mut body, interface, big_endian);
+    put_u32(&mut body, (stamp >> 32) as u32, big_endian);
+    put_u32(&mut body, stamp as u32, big_endian);
+    put_u32(&mut body, frame.len() as u32, big_endian);
+    put_u32(&mut body, frame.len() as u32, big_endian);
+    body.extend(frame);
+    block(6, &body, big_endian)
+}
+
+fn simple_packet(frame: &[u8], big_endian: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_u32(&mut body, frame.len() as u32, big_endian);
+    body.extend(frame);
+    block(3, &body, big_endian)
+}
+
+fn frames(capture: &[u8]) -> Result<Vec<Frame>, PcapError> {
+    let mut reader = PcapReader::new(capture)?;
+    let mut frames = Vec::new();
+    while let Some(frame) = reader.next_frame()? {
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+/// The payloads of the messages a capture holds, in the order completed
+fn wire_messages(capture: &[u8]) -> (Vec<Vec<u8>>, FixStreams) {
+    let mut streams = FixStreams::new().with_ports(&[5001]);
+    let mut messages = Vec::new();
+    for frame in frames(capture).expect("capture") {
+        let segment = tcp_segment(&frame).expect("TCP segment");
+        messages.extend(streams.on_segment(&segment).into_iter().map(|x| x.raw));
+    }
+    (messages, streams)
+}
+
+// =============================================================================
+// pcap
+// =============================================================================
+
+#[test]
+fn pcap_frames_in_either_byte_order_and_precision() {
+    let data = [segment(1, b"x"), segment(2, b"yz")];
+    let time = i64::from(SECONDS) * 1_000_000_000 + i64::from(MICROS) * 1_000;
+    for big_endian in [false, true] {
+        for nanos in [false, true] {
+            let frames = frames(&pcap(&data, big_endian, nanos)).expect("capture");
+            assert_eq!(frames.len(), 2);
+            assert_eq!(frames[0].time, time);
+            assert_eq!(frames[1].time, time + 1_000_000);
+            assert_eq!(frames[0].link_type, 1);
+            assert_eq!(frames[1].data, data[1]);
+        }
+    }
+}
+
+#[test]
+fn pcap_cut_short_is_truncated() {
+    let capture = pcap(&[segment(1, b"35=0")], false, false);
+    // Within the file header, within a record header, within a frame
+    for length in [10, 24 + 7, capture.len() - 1] {
+        assert!(
+            matches!(frames(&capture[..length]), Err(PcapError::Truncated)),
+            "cut at {length}"
+        );
+    }
+    // At a record boundary: a clean end
+    assert_eq!(frames(&capture[..24]).expect("header only").len(), 0);
+}
+
+#[test]
+fn pcap_record_of_impossible_length_is_corrupt() {
+    let mut capture = pcap(&[segment(1, b"35=0")], false, false);
+    capture[24 + 8..24 + 12].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(frames(&capture), Err(PcapError::Corrupt(_))));
+}
+
+#[test]
+fn other_files_are_not_pcap() {
+    assert!(matches!(
+        frames(b"8=FIX.4.4\x019=5\x0135=0\x01"),
+        Err(PcapError::NotPcap)
+    ));
+}
+
+// =============================================================================
+// pcapng
+// =============================================================================
+
+#[test]
+fn pcapng_packets_with_the_resolution_of_their_interface() {
+    for big_endian in [false, true] {
+        let mut capture = section(big_endian);
+        capture.extend(interface(None, big_endian));
+        capture.extend(interface(Some(9), big_endian));
+        capture.extend(enhanced_packet(0, 1_500_000, &segment(1, b"a"), big_endian));
+        capture.extend(enhanced_packet(
+            1,
+            1_500_000_000,
+            &segment(2, b"bc"),
+            big_endian,
+        ));
+        capture.extend(simple_packet(&segment(4, b"def"), big_endian));
+
+        let frames = frames(&capture).expect("capture");
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].time, 1_500_000_000, "microseconds by default");
+        assert_eq!(frames[1].time, 1_500_000_000, "if_tsresol 9: nanoseconds");
+        assert_eq!(frames[1].data, segment(2, b"bc"), "padding left out");
+        assert_eq!(frames[2].data, segment(4, b"def"));
+        assert_eq!(frames[2].link_type, 1);
+    }
+}
+
+#[test]
+fn pcapng_cut_short_is_truncated() {
+    let mut capture = section(false);
+    capture.extend(interface(None, false));
+    let packets = capture.len();
+    capture.extend(enhanced_packet(0, 0, &segment(1, b"35=0"), false));
+    for length in [12, packets + 6, capture.len() - 1] {
+        assert!(
+            matches!(frames(&capture[..length]), Err(PcapError::Truncated)),
+            "cut at {length}"
+        );
+    }
+}
+
+#[test]
+fn pcapng_block_of_impossible_length_is_corrupt() {
+    let mut capture = section(false);
+    capture.extend(interface(None, false));
+    let at = capture.len();
+    capture.extend(enhanced_packet(0, 0, &segment(1, b"35=0"), false));
+    capture[at + 4..at + 8].copy_from_slice(&13u32.to_le_bytes());
+    assert!(matches!(frames(&capture), Err(PcapError::Corrupt(_))));
+}
+
+#[test]
+fn pcapng_packet_of_an_undescribed_interface_is_corrupt() {
+    let mut capture = section(false);
+    capture.extend(enhanced_packet(0, 0, &segment(1, b"35=0"), false));
+    assert!(matches!(frames(&capture), Err(PcapError::Corrupt(_))));
+}
+
+// =============================================================================
+// TCP Segments
+// =============================================================================
+
+fn frame(link_type: u32, data: Vec<u8>) -> Frame {
+    Frame {
+        time: 0,
+        link_type,
+        data,
+    }
+}
+
+#[test]
+fn segments_of_each_link_type() {
+    let ip = ipv4_tcp(CLIENT, SERVER, 7, PSH_ACK, b"8=FIX");
+    let mut vlan = ethernet(&ip);
+    vlan.splice(12..12, [0x81, 0x00, 0x00, 0x64]);
+    let mut cooked = vec![0; 14];
+    cooked.extend([0x08, 0x00]);
+    cooked.extend(&ip);
+    let mut loopback = vec![2, 0, 0, 0];
+    loopback.extend(&ip);
+
+    for (link_type, data) in [
+        (1, ethernet(&ip)),
+        (1, vlan),
+        (113, cooked),
+        (0, loopback),
+        (101, ip.clone()),
+    ] {
+        let segment = tcp_segment(&frame(link_type, data)).expect("TCP segment");
+        assert_eq!(
+            segment.src,
+            CLIENT.parse().unwrap(),
+            "link type {link_type}"
+        );
+        assert_eq!(segment.dst, SERVER.parse().unwrap());
+        assert_eq!(segment.seq, 7);
+        assert_eq!(segment.payload, b"8=FIX");
+        assert!(!segment.syn && !segment.fin && !segment.rst);
+    }
+}
+
+#[test]
+fn segment_ends_where_the_ip_packet_does() {
+    // Ethernet pads short frames: the padding is not payload
+    let mut data = segment(1, b"35=0");
+    data.extend([0; 6]);
+    let segment = tcp_segment(&frame(1, data)).expect("TCP segment");
+    assert_eq!(segment.payload, b"35=0");
+}
+
+#[test]
+fn other_packets_are_not_segments() {
+    let mut udp = ipv4_tcp(CLIENT, SERVER, 1, 0, b"");
+    udp[9] = 17;
+    let mut fragment = ipv4_tcp(CLIENT, SERVER, 1, 0, b"");
+    fragment[6..8].copy_from_slice(&[0x00, 0x10]);
+    let mut arp = ethernet(&[0; 28]);
+    arp[12..14].copy_from_slice(&[0x08, 0x06]);
+
+    assert!(tcp_segment(&frame(101, udp)).is_none());
+    assert!(tcp_segment(&frame(101, fragment)).is_none());
+    assert!(tcp_segment(&frame(1, arp)).is_none());
+    assert!(tcp_segment(&frame(1, vec![0; 10])).is_none(), "runt");
+    assert!(tcp_segment(&frame(147, segment(1, b"x"))).is_none());
+}
+
+// =============================================================================
+// Streams
+// =============================================================================
+
+#[test]
+fn message_split_across_segments_is_put_back_together() {
+    let order = new_order();
+    let (a, rest) = order.split_at(5);
+    let (b, c) = rest.split_at(30);
+    let capture = pcap(
+        &[
+            syn(1_000),
+            segment(1_000, a),
+            segment(1_005, b),
+            segment(1_035, c),
+        ],
+        false,
+        false,
+    );
+    let (messages, streams) = wire_messages(&capture);
+    assert_eq!(messages, [order]);
+    assert_eq!((streams.gaps(), streams.garbled()), (0, 0));
+}
+
+#[test]
+fn segments_out_of_order_are_put_back_in_order() {
+    let (first, second) = (heartbeat(1), new_order());
+    let mut bytes = first.clone();
+    bytes.extend(&second);
+    let (a, rest) = bytes.split_at(20);
+    let (b, c) = rest.split_at(40);
+    let (b_at, c_at) = (a.len() as u32, (a.len() + b.len()) as u32);
+
+    // From the SYN on, so the stream knows where it starts
+    let capture = pcap(
+        &[syn(0), segment(c_at, c), segment(0, a), segment(b_at, b)],
+        false,
+        false,
+    );
+    let (messages, streams) = wire_messages(&capture);
+    assert_eq!(messages, [first, second]);
+    assert_eq!(streams.gaps(), 0);
+}
+
+#[test]
+fn retransmitted_segments_are_taken_once() {
+    let (first, second) = (heartbeat(1), heartbeat(2));
+    let at = first.len() as u32;
+    let mut both = first.clone();
+    both.extend(&second);
+
+    // The first again, then both together overlapping it
+    let capture = pcap(
+        &[segment(0, &first), segment(0, &first), segment(0, &both)],
+        false,
+        false,
+    );
+    let (messages, _) = wire_messages(&capture);
+    assert_eq!(messages, [first, second.clone()]);
+
+    let capture = pcap(&[segment(0, &both), segment(at, &second)], false, false);
+    assert_eq!(wire_messages(&capture).0.len(), 2);
+}
+
+#[test]
+fn stream_jumps_a_hole_that_is_never_filled() {
+    let order = new_order();
+    let mut frames = vec![segment(0, &order[..10])];
+    // The rest of the order is never seen: heartbeats pile up behind it
+    let mut seq = order.len() as u32;
+    for number in 0..=MAX_PENDING as u32 {
+        let message = heartbeat(number);
+        frames.push(segment(seq, &message));
+        seq += message.len() as u32;
+    }
+
+    let (messages, streams) = wire_messages(&pcap(&frames, false, false));
+    assert_eq!(streams.gaps(), 1);
+    assert_eq!(messages.len(), MAX_PENDING + 1, "every heartbeat, no order");
+    assert_eq!(messages[0], heartbeat(0));
+    assert_eq!(messages[MAX_PENDING], heartbeat(MAX_PENDING as u32));
+}
+
+#[test]
+fn hole_filled_in_time_is_not_a_gap() {
+    let (first, second, third) = (heartbeat(1), heartbeat(2), heartbeat(3));
+    let second_at = first.len() as u32;
+    let third_at = second_at + second.len() as u32;
+    let capture = pcap(
+        &[
+            segment(0, &first),
+            segment(third_at, &third),
+            segment(second_at, &second),
+        ],
+        false,
+        false,
+    );
+    let (messages, streams) = wire_messages(&capture);
+    assert_eq!(messages, [first, second, third]);
+    assert_eq!(streams.gaps(), 0);
+}
+
+#[test]
+fn bytes_between_messages_are_skipped() {
+    let mut bytes = heartbeat(1);
+    // A BeginString without a BodyLength
+    bytes.extend(b"8=FIX.4.4\x0135=0\x01");
+    bytes.extend(heartbeat(2));
+    let (messages, streams) = wire_messages(&pcap(&[segment(0, &bytes)], false, false));
+    assert_eq!(messages, [heartbeat(1), heartbeat(2)]);
+    assert!(streams.garbled() > 0);
+}
+
+#[test]
+fn connections_on_other_ports_are_left_out() {
+    let capture = pcap(
+        &[ethernet(&ipv4_tcp(
+            "10.0.0.1:50000",
+            "10.0.0.2:443",
+            0,
+            PSH_ACK,
+            &heartbeat(1),
+        ))],
+        false,
+        false,
+    );
+    assert!(wire_messages(&capture).0.is_empty());
+}
+
+#[test]
+fn capture_in_pcapng_gives_the_same_messages() {
+    let order = new_order();
+    let mut capture = section(false);
+    capture.extend(interface(Some(9), false));
+    capture.extend(enhanced_packet(0, 1, &syn(0), false));
+    capture.extend(enhanced_packet(0, 2, &segment(20, &order[20..]), false));
+    capture.extend(enhanced_packet(0, 3, &segment(0, &order[..10]), false));
+    capture.extend(enhanced_packet(0, 4, &segment(10, &order[10..20]), false));
+    assert_eq!(wire_messages(&capture).0, [order]);
+}
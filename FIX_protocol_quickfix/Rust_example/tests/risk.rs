@@ -0,0 +1,75 @@
+// =============================================================================
+// Pre-Trade Risk Checks
+// =============================================================================
+// RiskChecker::check (trading::risk) against orders that must not get
+// through: quantities and prices that are zero, negative, infinite or not a
+// number, for which a plain `>` comparison against a limit is false. Then
+// the portfolio margin report, as the callers print it.
+// =============================================================================
+
+use trading::{
+    oms::Side,
+    risk::{margin::MarginModel, RiskChecker, RiskLimits, RiskViolation},
+};
+
+fn check(quantity: f64, price: f64) -> Result<(), RiskViolation> {
+    RiskChecker::new(RiskLimits::default()).check(Side::Buy, quantity, price, 0.0, 0.0)
+}
+
+#[test]
+fn orders_within_the_limits_pass() {
+    assert!(check(100.0, 150.25).is_ok());
+}
+
+#[test]
+fn quantities_that_are_not_finite_and_positive_are_refused() {
+    for quantity in [0.0, -100.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        assert!(
+            matches!(check(quantity, 150.25), Err(RiskViolation::OrderQty { .. })),
+            "quantity {quantity}"
+        );
+    }
+}
+
+#[test]
+fn prices_that_are_not_finite_and_positive_are_refused() {
+    for price in [0.0, -150.25, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        assert!(
+            matches!(check(100.0, price), Err(RiskViolation::OrderPrice { .. })),
+            "price {price}"
+        );
+    }
+}
+
+// =============================================================================
+// Margin Report
+// =============================================================================
+
+#[test]
+fn margin_report_lists_the_positions_and_the_totals() {
+    let positions = [
+        ("AAPL".to_string(), 100.0),
+        ("MSFT".to_string(), -50.0),
+        ("TSLA".to_string(), 10.0),
+    ];
+    let price = |x: &str| match x {
+        "AAPL" => Some(150.0),
+        "MSFT" => Some(400.0),
+        _ => None,
+    };
+    let checker = RiskChecker::new(RiskLimits::default());
+    assert!(checker.margin_report(&positions, &price).is_none());
+
+    let checker = checker.with_margin(MarginModel::standard(), 100_000.0);
+    let (report, equity) = checker.margin_report(&positions, &price).expect("margin");
+    assert_eq!(equity, 100_000.0);
+    assert_eq!(report.buying_power(equity), 90_250.0);
+
+    let lines: Vec<String> = report.to_string().lines().map(str::to_string).collect();
+    assert_eq!(lines.len(), 5, "{lines:?}");
+    assert!(lines[0].starts_with("SYMBOL"));
+    assert!(lines[1].starts_with("AAPL") && lines[1].ends_with("3750.00"));
+    assert!(lines[2].starts_with("MSFT") && lines[2].ends_with("6000.00"));
+    assert_eq!(lines[3], "  no price: TSLA");
+    assert_eq!(lines[4], "gross 9750.00  net 9750.00");
+}
@@ -0,0 +1,442 @@
+// =============================================================================
+// SBE Market Data
+// =============================================================================
+// decode_packet and SbeBook (trading::sbe) on MDP 3.0 packets encoded here
+// field by field: book and trade entries decoded at their offsets, the 35=W
+// built from them, messages of other templates, schemas and later versions
+// stepped over, packets cut short or with a size below the message header
+// refused, and packets and entries already seen or out of sequence.
+// =============================================================================
+
+use trading::sbe::{
+    decode_packet, BookEntry, Packet, SbeBook, SbeError, SbeMessage, TradeEntry, UpdateAction,
+    MDP3_SCHEMA_ID,
+};
+
+/// 2026-10-14 17:46:40 UTC, in nanoseconds
+const SENDING_TIME: u64 = 1_792_000_000_000_000_000;
+const TRANSACT_TIME: u64 = SENDING_TIME - 250_000;
+
+const ES: i32 = 1234;
+const NQ: i32 = 5678;
+
+// =============================================================================
+// Encoding
+// =============================================================================
+
+/// Prices in PRICE9, the null of the nullable fields
+const SCALE: f64 = 1e9;
+const NULL_INT32: i32 = i32::MAX;
+
+struct Entry {
+    price: f64,
+    size: i32,
+    security_id: i32,
+    rpt_seq: u32,
+    orders: i32,
+    level: u8,
+    action: u8,
+    entry_type: u8,
+}
+
+fn bid(level: u8, price: f64, size: i32, rpt_seq: u32) -> Entry {
+    Entry {
+        price,
+        size,
+        security_id: ES,
+        rpt_seq,
+        orders: NULL_INT32,
+        level,
+        action: 0,
+        entry_type: b'0',
+    }
+}
+
+fn offer(level: u8, price: f64, size: i32, rpt_seq: u32) -> Entry {
+    Entry {
+        entry_type: b'1',
+        ..bid(level, price, size, rpt_seq)
+    }
+}
+
+/// Header (size, block length, template, schema, version), TransactTime and
+/// the rest of the root block, then one group of `entry_length` entries
+fn message(
+    template: u16,
+    version: u16,
+    root: usize,
+    entry_length: usize,
+    entries: &[Entry],
+) -> Vec<u8> {
+    let mut out = vec![0; 2];
+    out.extend((root as u16).to_le_bytes());
+    out.extend(template.to_le_bytes());
+    out.extend(MDP3_SCHEMA_ID.to_le_bytes());
+    out.extend(version.to_le_bytes());
+    out.extend(TRANSACT_TIME.to_le_bytes());
+    out.resize(10 + root, 0); // MatchEventIndicator, padding, later fields
+    out.extend((entry_length as u16).to_le_bytes());
+    out.push(entries.len() as u8);
+    for entry in entries {
+        let start = out.len();
+        out.extend(((entry.price * SCALE).round() as i64).to_le_bytes());
+        out.extend(entry.size.to_le_bytes());
+        out.extend(entry.security_id.to_le_bytes());
+        out.extend(entry.rpt_seq.to_le_bytes());
+        out.extend(entry.orders.to_le_bytes());
+        out.extend([entry.level, entry.action, entry.entry_type]);
+        out.resize(start + entry_length, 0);
+    }
+    let size = out.len() as u16;
+    out[..2].copy_from_slice(&size.to_le_bytes());
+    out
+}
+
+/// MDIncrementalRefreshBook46 of schema version 9
+fn book(entries: &[Entry]) -> Vec<u8> {
+    message(46, 9, 11, 32, entries)
+}
+
+/// MDIncrementalRefreshTradeSummary48, price, size and RptSeq of ES trades
+fn trades(trades: &[(f64, i32, u32)]) -> Vec<u8> {
+    let entries: Vec<Entry> = trades
+        .iter()
+        .map(|&(price, size, rpt_seq)| Entry {
+            orders: 2,
+            level: 1, // AggressorSide: buy
+            action: 0,
+            ..bid(0, price, size, rpt_seq)
+        })
+        .collect();
+    message(48, 9, 11, 32, &entries)
+}
+
+fn packet(seq_num: u32, messages: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = seq_num.to_le_bytes().to_vec();
+    out.extend(SENDING_TIME.to_le_bytes());
+    for message in messages {
+        out.extend(message);
+    }
+    out
+}
+
+fn decoded(seq_num: u32, messages: &[Vec<u8>]) -> Packet {
+    decode_packet(&packet(seq_num, messages)).expect("well-formed packet")
+}
+
+/// The (269, 270, 271) of each entry of a 35=W
+fn entries(fields: &[(i32, String)]) -> Vec<(String, String, String)> {
+    let value = |at: usize, tag: i32| {
+        fields
+            .get(at)
+            .filter(|x| x.0 == tag)
+            .map(|x| x.1.clone())
+            .unwrap_or_default()
+    };
+    fields
+        .iter()
+        .enumerate()
+        .filter(|(_, (tag, _))| *tag == 269)
+        .map(|(at, (_, entry_type))| (entry_type.clone(), value(at + 1, 270), value(at + 2, 271)))
+        .collect()
+}
+
+fn level(entry_type: &str, price: &str, size: &str) -> (String, String, String) {
+    (entry_type.to_string(), price.to_string(), size.to_string())
+}
+
+// =============================================================================
+// Decoding
+// =============================================================================
+
+#[test]
+fn book_and_trade_entries_are_decoded_at_their_offsets() {
+    let mut change = offer(2, 6000.25, 7, 12);
+    change.security_id = NQ;
+    change.orders = 3;
+    change.action = 1;
+    let packet = decoded(
+        41,
+        &[
+            book(&[bid(1, 5999.75, 12, 11), change]),
+            trades(&[(6000.0, 5, 13)]),
+        ],
+    );
+
+    assert_eq!(packet.seq_num, 41);
+    assert_eq!(packet.sending_time, SENDING_TIME);
+    assert_eq!(
+        packet.messages[0],
+        SbeMessage::Book {
+            transact_time: TRANSACT_TIME,
+            entries: vec![
+                BookEntry {
+                    security_id: ES,
+                    rpt_seq: 11,
+                    price: Some(5999.75),
+                    size: Some(12),
+                    orders: None,
+                    level: 1,
+                    action: UpdateAction::New,
+                    entry_type: '0',
+                },
+                BookEntry {
+                    security_id: NQ,
+                    rpt_seq: 12,
+                    price: Some(6000.25),
+                    size: Some(7),
+                    orders: Some(3),
+                    level: 2,
+                    action: UpdateAction::Change,
+                    entry_type: '1',
+                },
+            ],
+        }
+    );
+    assert_eq!(
+        packet.messages[1],
+        SbeMessage::Trades {
+            transact_time: TRANSACT_TIME,
+            entries: vec![TradeEntry {
+                security_id: ES,
+                rpt_seq: 13,
+                price: 6000.0,
+                size: 5,
+            }],
+        }
+    );
+}
+
+#[test]
+fn null_price_of_a_delete_reads_as_none() {
+    let mut delete = bid(1, 0.0, NULL_INT32, 1);
+    delete.action = 2;
+    let mut message = book(&[delete]);
+    // PRICENULL9 of the entry, after the header, root block and group size
+    let at = 10 + 11 + 3;
+    message[at..at + 8].copy_from_slice(&i64::MAX.to_le_bytes());
+    let SbeMessage::Book { entries, .. } = &decoded(1, &[message]).messages[0] else {
+        panic!("book message");
+    };
+    assert_eq!((entries[0].price, entries[0].size), (None, None));
+    assert_eq!(entries[0].action, UpdateAction::Delete);
+}
+
+#[test]
+fn other_templates_and_schemas_are_stepped_over() {
+    // A SecurityStatus30, a root block without a group, and a message of
+    // another schema, then a book
+    let mut status = vec![40, 0, 30, 0, 30, 0, 1, 0, 9, 0];
+    status.resize(40, 0);
+    let mut foreign = book(&[bid(1, 1.0, 1, 1)]);
+    foreign[6..8].copy_from_slice(&2u16.to_le_bytes());
+    let packet = decoded(1, &[status, foreign, book(&[bid(1, 5999.75, 12, 1)])]);
+
+    assert_eq!(
+        packet.messages[..2],
+        [
+            SbeMessage::Other {
+                schema_id: MDP3_SCHEMA_ID,
+                template_id: 30
+            },
+            SbeMessage::Other {
+                schema_id: 2,
+                template_id: 46
+            },
+        ]
+    );
+    assert!(matches!(&packet.messages[2], SbeMessage::Book { entries, .. } if entries.len() == 1));
+}
+
+#[test]
+fn later_versions_with_longer_blocks_decode() {
+    // Fields appended to the root block and to each entry
+    let packet = decoded(
+        1,
+        &[message(
+            46,
+            13,
+            24,
+            48,
+            &[bid(1, 5999.75, 12, 1), offer(1, 6000.0, 3, 2)],
+        )],
+    );
+    let SbeMessage::Book { entries, .. } = &packet.messages[0] else {
+        panic!("book message");
+    };
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[1].price, Some(6000.0));
+    assert_eq!(entries[1].entry_type, '1');
+}
+
+// =============================================================================
+// Malformed packets
+// =============================================================================
+
+#[test]
+fn packet_shorter_than_its_header_is_truncated() {
+    assert_eq!(decode_packet(&[1, 0, 0, 0, 0]), Err(SbeError::Truncated(0)));
+    // A header alone is an empty packet, a heartbeat
+    assert!(decoded(7, &[]).messages.is_empty());
+}
+
+#[test]
+fn message_cut_short_is_truncated_at_its_offset() {
+    let data = packet(1, &[book(&[bid(1, 1.0, 1, 1)]), book(&[bid(1, 2.0, 1, 2)])]);
+    let second = 12 + book(&[bid(1, 1.0, 1, 1)]).len();
+    assert_eq!(
+        decode_packet(&data[..data.len() - 1]),
+        Err(SbeError::Truncated(second))
+    );
+    // Only the size of the next message
+    assert_eq!(
+        decode_packet(&data[..second + 1]),
+        Err(SbeError::Truncated(second))
+    );
+}
+
+#[test]
+fn group_past_the_message_size_is_truncated() {
+    // The size of the message says one entry, its group says two
+    let mut message = book(&[bid(1, 1.0, 1, 1)]);
+    message[10 + 11 + 2] = 2;
+    assert_eq!(
+        decode_packet(&packet(1, &[message])),
+        Err(SbeError::Truncated(12))
+    );
+}
+
+#[test]
+fn size_below_the_message_header_is_invalid() {
+    for size in [0u16, 1, 9] {
+        let mut data = packet(1, &[book(&[bid(1, 1.0, 1, 1)])]);
+        data[12..14].copy_from_slice(&size.to_le_bytes());
+        assert_eq!(
+            decode_packet(&data),
+            Err(SbeError::InvalidSize(12)),
+            "size {size}"
+        );
+    }
+}
+
+// =============================================================================
+// Books
+// =============================================================================
+
+#[test]
+fn snapshot_carries_the_levels_and_the_last_trade() {
+    let mut feed = SbeBook::new()
+        .with_session("SBE:CME-310")
+        .with_symbol(ES, "ESZ6");
+    let packet = decoded(
+        1,
+        &[
+            book(&[
+                bid(1, 5999.75, 12, 1),
+                bid(2, 5999.5, 30, 2),
+                offer(1, 6000.0, 9, 3),
+            ]),
+            trades(&[(6000.0, 5, 4), (6000.25, 1, 5)]),
+        ],
+    );
+    let messages = feed.on_packet(&packet);
+    assert_eq!(messages.len(), 1, "one instrument touched");
+    let message = &messages[0];
+
+    assert_eq!(message.session, "SBE:CME-310");
+    assert_eq!(message.get(35), Some("W"));
+    assert_eq!(message.get(34), Some("1"));
+    assert_eq!(message.get(55), Some("ESZ6"));
+    assert_eq!(message.get(48), Some("1234"));
+    assert_eq!(message.get(52), Some("20261014-17:46:40.000000000"));
+    assert_eq!(message.get(60), Some("20261014-17:46:39.999750000"));
+    assert_eq!(message.get(268), Some("4"));
+    assert_eq!(
+        entries(&message.fields),
+        [
+            level("0", "5999.75", "12"),
+            level("0", "5999.5", "30"),
+            level("1", "6000", "9"),
+            level("2", "6000.25", "1"),
+        ]
+    );
+}
+
+#[test]
+fn updates_change_delete_and_clear_levels() {
+    let mut feed = SbeBook::new().with_depth(2);
+    feed.on_packet(&decoded(
+        1,
+        &[book(&[
+            bid(1, 100.0, 1, 1),
+            bid(2, 99.0, 2, 2),
+            offer(1, 101.0, 3, 3),
+        ])],
+    ));
+
+    // A new best bid pushes the others down, past the depth
+    let messages = feed.on_packet(&decoded(2, &[book(&[bid(1, 100.5, 4, 4)])]));
+    assert_eq!(messages[0].get(55), Some("1234"), "no symbol given");
+    assert_eq!(
+        entries(&messages[0].fields),
+        [
+            level("0", "100.5", "4"),
+            level("0", "100", "1"),
+            level("1", "101", "3"),
+        ]
+    );
+
+    let mut change = bid(2, 100.0, 9, 5);
+    change.action = 1;
+    let mut delete = offer(1, 0.0, 0, 6);
+    delete.action = 2;
+    let messages = feed.on_packet(&decoded(3, &[book(&[change, delete])]));
+    assert_eq!(
+        entries(&messages[0].fields),
+        [level("0", "100.5", "4"), level("0", "100", "9")]
+    );
+
+    let mut thru = bid(1, 0.0, 0, 7);
+    thru.action = 3;
+    let messages = feed.on_packet(&decoded(4, &[book(&[thru])]));
+    assert_eq!(messages[0].get(268), Some("0"));
+}
+
+#[test]
+fn packet_seen_twice_is_dropped_and_a_jump_counted() {
+    let mut feed = SbeBook::new();
+    let first = decoded(1, &[book(&[bid(1, 100.0, 1, 1)])]);
+    assert_eq!(feed.on_packet(&first).len(), 1);
+    // The same packet from the B feed
+    assert!(feed.on_packet(&first).is_empty());
+
+    // 2 and 3 lost
+    let fourth = decoded(4, &[book(&[bid(1, 100.5, 1, 4)])]);
+    assert_eq!(feed.on_packet(&fourth).len(), 1);
+    assert_eq!(feed.gaps(), 1);
+    assert!(feed
+        .on_packet(&decoded(3, &[book(&[bid(1, 1.0, 1, 3)])]))
+        .is_empty());
+    assert_eq!(feed.gaps(), 1);
+}
+
+#[test]
+fn entries_at_or_below_the_last_rpt_seq_are_skipped() {
+    let mut feed = SbeBook::new();
+    feed.on_packet(&decoded(1, &[book(&[bid(1, 100.0, 1, 10)])]));
+
+    // RptSeq 10 again and 9, in a new packet: nothing changes
+    let stale = decoded(2, &[book(&[bid(1, 50.0, 1, 10), bid(1, 60.0, 1, 9)])]);
+    assert!(feed.on_packet(&stale).is_empty());
+
+    // A jump in RptSeq is taken, another instrument keeps its own
+    let mut other = bid(1, 20_000.0, 1, 1);
+    other.security_id = NQ;
+    let messages = feed.on_packet(&decoded(3, &[book(&[bid(1, 100.25, 2, 15), other])]));
+    assert_eq!(messages.len(), 2);
+    assert_eq!(
+        entries(&messages[0].fields),
+        [level("0", "100.25", "2"), level("0", "100", "1")]
+    );
+    assert_eq!(messages[1].get(48), Some("5678"));
+}
@@ -0,0 +1,193 @@
+// =============================================================================
+// Webhook Signatures
+// =============================================================================
+// sha256 and hmac_sha256 (trading::gateway::webhook) against the FIPS 180-4
+// examples and the RFC 4231 test cases, including the keys and messages
+// longer than a block and the lengths on either side of the padding
+// boundary; then a signed request as a receiver gets it.
+// =============================================================================
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::Arc,
+};
+
+use trading::gateway::{
+    metrics::Metrics,
+    webhook::{hex, hmac_sha256, sha256, WebhookConfig, WebhookEvent, WebhookNotifier},
+};
+
+// =============================================================================
+// SHA-256
+// =============================================================================
+
+#[test]
+fn sha256_matches_the_fips_180_4_examples() {
+    let cases: [(&[u8], &str); 3] = [
+        (
+            b"",
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        ),
+        (
+            b"abc",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+        (
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        ),
+    ];
+    for (message, digest) in cases {
+        assert_eq!(hex(&sha256(message)), digest, "{message:?}");
+    }
+}
+
+#[test]
+fn sha256_of_a_million_a() {
+    assert_eq!(
+        hex(&sha256(&vec![b'a'; 1_000_000])),
+        "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+    );
+}
+
+#[test]
+fn sha256_around_the_padding_boundary() {
+    // 55 bytes leave room for the length in the last block, 56 do not
+    let cases = [
+        (
+            55,
+            "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318",
+        ),
+        (
+            56,
+            "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a",
+        ),
+        (
+            64,
+            "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb",
+        ),
+    ];
+    for (len, digest) in cases {
+        assert_eq!(hex(&sha256(&vec![b'a'; len])), digest, "{len} bytes");
+    }
+}
+
+// =============================================================================
+// HMAC-SHA256 (RFC 4231)
+// =============================================================================
+
+#[test]
+fn hmac_sha256_matches_the_rfc_4231_test_cases() {
+    let key_25: Vec<u8> = (1..=25).collect();
+    let cases: [(&[u8], &[u8], &str); 4] = [
+        (
+            &[0x0b; 20],
+            b"Hi There",
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+        ),
+        (
+            b"Jefe",
+            b"what do ya want for nothing?",
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        ),
+        (
+            &[0xaa; 20],
+            &[0xdd; 50],
+            "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+        ),
+        (
+            &key_25,
+            &[0xcd; 50],
+            "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+        ),
+    ];
+    for (number, (key, message, mac)) in cases.into_iter().enumerate() {
+        assert_eq!(
+            hex(&hmac_sha256(key, message)),
+            mac,
+            "test case {}",
+            number + 1
+        );
+    }
+}
+
+#[test]
+fn hmac_sha256_truncated_as_in_rfc_4231_case_5() {
+    let mac = hmac_sha256(&[0x0c; 20], b"Test With Truncation");
+    assert_eq!(hex(&mac[..16]), "a3b6167473100ee06e0c796c2955552b");
+}
+
+#[test]
+fn hmac_sha256_hashes_keys_longer_than_a_block() {
+    // RFC 4231 cases 6 and 7: a 131-byte key
+    let key = [0xaa; 131];
+    assert_eq!(
+        hex(&hmac_sha256(
+            &key,
+            b"Test Using Larger Than Block-Size Key - Hash Key First"
+        )),
+        "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+    );
+    assert_eq!(
+        hex(&hmac_sha256(
+            &key,
+            b"This is a test using a larger than block-size key and a larger than block-size \
+              data. The key needs to be hashed before being used by the HMAC algorithm."
+        )),
+        "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2"
+    );
+}
+
+// =============================================================================
+// Signed requests
+// =============================================================================
+
+#[test]
+fn request_is_signed_over_timestamp_and_body() {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).expect("bind a free port");
+    let port = listener.local_addr().expect("local address").port();
+
+    let config = WebhookConfig::new(&format!("http://127.0.0.1:{port}/hooks"))
+        .with_template(
+            WebhookEvent::OrderFilled,
+            "{\"text\":\"{{symbol}} filled\"}",
+        )
+        .with_secret("whsec-test");
+    let notifier =
+        WebhookNotifier::start(vec![config], Arc::new(Metrics::new())).expect("notifier");
+    notifier.notify(WebhookEvent::OrderFilled, &[("symbol", "AAPL".to_string())]);
+
+    let (mut stream, _) = listener.accept().expect("webhook request");
+    let mut reader = BufReader::new(stream.try_clone().expect("stream"));
+    let (mut timestamp, mut signature, mut length) = (String::new(), String::new(), 0);
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).expect("header");
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(": ") {
+            match name {
+                "X-Webhook-Timestamp" => timestamp = value.to_string(),
+                "X-Webhook-Signature" => signature = value.to_string(),
+                "Content-Length" => length = value.parse().expect("length"),
+                _ => {}
+            }
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).expect("body");
+    stream
+        .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+        .expect("response");
+
+    assert_eq!(body, b"{\"text\":\"AAPL filled\"}");
+    let mut signed = format!("{timestamp}.").into_bytes();
+    signed.extend(&body);
+    assert_eq!(
+        signature,
+        format!("sha256={}", hex(&hmac_sha256(b"whsec-test", &signed)))
+    );
+}
@@ -0,0 +1,119 @@
+// =============================================================================
+// WebSocket Handshake
+// =============================================================================
+// The opening handshake of the streaming bridge (trading::gateway::websocket):
+// SHA-1 against the FIPS 180-4 examples, base64 against RFC 4648, the
+// Sec-WebSocket-Accept of the RFC 6455 example, and a server answering an
+// upgrade request with it.
+// =============================================================================
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    time::Duration,
+};
+
+use trading::gateway::websocket::{accept_key, base64, sha1, spawn_server, Bridge};
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+// =============================================================================
+// SHA-1 and base64
+// =============================================================================
+
+#[test]
+fn sha1_matches_the_fips_180_4_examples() {
+    let cases: [(&[u8], &str); 3] = [
+        (b"", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+        (b"abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+        (
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+        ),
+    ];
+    for (message, digest) in cases {
+        assert_eq!(hex(&sha1(message)), digest, "{message:?}");
+    }
+    assert_eq!(
+        hex(&sha1(&vec![b'a'; 1_000_000])),
+        "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+    );
+}
+
+#[test]
+fn base64_matches_the_rfc_4648_vectors() {
+    let cases = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+    for (data, encoded) in cases {
+        assert_eq!(base64(data.as_bytes()), encoded, "{data:?}");
+    }
+    assert_eq!(base64(&[0xfb, 0xff, 0xbf]), "+/+/");
+}
+
+// =============================================================================
+// Handshake
+// =============================================================================
+
+/// The example of RFC 6455 section 1.3
+const KEY: &str = "dGhlIHNhbXBsZSBub25jZQ==";
+const ACCEPT: &str = "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=";
+
+#[test]
+fn accept_key_of_the_rfc_6455_example() {
+    assert_eq!(accept_key(KEY), ACCEPT);
+}
+
+/// Send `request` to a bridge of its own, and read the response head back
+fn exchange(request: &str) -> Vec<String> {
+    let port = TcpListener::bind(("127.0.0.1", 0))
+        .and_then(|x| x.local_addr())
+        .expect("free port")
+        .port();
+    spawn_server(port, Arc::new(Bridge::new())).expect("server");
+
+    // Bound before spawn_server returns
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("connect");
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("timeout");
+    stream.write_all(request.as_bytes()).expect("request");
+
+    let mut reader = BufReader::new(stream);
+    let mut head = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).expect("response") == 0 || line == "\r\n" {
+            return head;
+        }
+        head.push(line.trim_end().to_string());
+    }
+}
+
+#[test]
+fn server_answers_the_upgrade_with_the_accept_key() {
+    let head = exchange(&format!(
+        "GET /?types=trade HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+         Connection: Upgrade\r\nSec-WebSocket-Key: {KEY}\r\nSec-WebSocket-Version: 13\r\n\r\n"
+    ));
+    assert_eq!(head[0], "HTTP/1.1 101 Switching Protocols");
+    assert!(
+        head.contains(&format!("Sec-WebSocket-Accept: {ACCEPT}")),
+        "{head:?}"
+    );
+}
+
+#[test]
+fn upgrade_without_a_key_is_refused() {
+    let head = exchange("GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\r\n");
+    assert_eq!(head[0], "HTTP/1.1 400 Bad Request");
+}
@@ -0,0 +1,449 @@
+// =============================================================================
+// Alerts
+// =============================================================================
+// Conditions worth paging a human for, watched over the events an
+// application already sees, and handed to notifiers when they are met:
+//
+//   session_down:30s          a session logged off (or never logged on) for
+//                             longer than 30 seconds; resolved at its logon
+//   reject_rate:5/60s         5 rejects from a session within 60 seconds
+//   risk_breach               pre-trade risk refused an order
+//   heartbeat_latency:500ms   a TestRequest -> Heartbeat round trip above it
+//
+// Durations take ms, s or m. The application feeds the monitor (on_logon,
+// on_reject, on_heartbeat_rtt...) and calls check() every second or so, for
+// the outages that outlast their limit with nothing else happening.
+//
+// A Notifier delivers an Alert: the webhook gateway posts it as the `alert`
+// event (a chat webhook with a {"text":"{{text}}"} template), the SMTP
+// gateway mails it (trading::gateway). Notifiers must not block the caller,
+// which may be a FIX callback.
+//
+// The same condition on the same session fires at most once per cooldown
+// (5 minutes by default), so a flapping link or a burst of rejects pages
+// once; a session down fires once per outage.
+// =============================================================================
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::time::unix_now;
+
+/// Default time before the same condition fires again for a session
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(300);
+
+// =============================================================================
+// Conditions
+// =============================================================================
+
+/// What an alert is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AlertKind {
+    SessionDown,
+    RejectRate,
+    RiskBreach,
+    HeartbeatLatency,
+}
+
+impl AlertKind {
+    pub const ALL: [AlertKind; 4] = [
+        AlertKind::SessionDown,
+        AlertKind::RejectRate,
+        AlertKind::RiskBreach,
+        AlertKind::HeartbeatLatency,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlertKind::SessionDown => "session_down",
+            AlertKind::RejectRate => "reject_rate",
+            AlertKind::RiskBreach => "risk_breach",
+            AlertKind::HeartbeatLatency => "heartbeat_latency",
+        }
+    }
+}
+
+impl fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A condition with its threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertCondition {
+    /// Logged off for longer than this
+    SessionDown(Duration),
+
+    /// This many rejects within the window
+    RejectRate { count: u32, window: Duration },
+
+    /// Any order refused by the risk checks
+    RiskBreach,
+
+    /// Heartbeat round trip above this
+    HeartbeatLatency(Duration),
+}
+
+impl AlertCondition {
+    pub fn kind(&self) -> AlertKind {
+        match self {
+            AlertCondition::SessionDown(_) => AlertKind::SessionDown,
+            AlertCondition::RejectRate { .. } => AlertKind::RejectRate,
+            AlertCondition::RiskBreach => AlertKind::RiskBreach,
+            AlertCondition::HeartbeatLatency(_) => AlertKind::HeartbeatLatency,
+        }
+    }
+}
+
+impl fmt::Display for AlertCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertCondition::SessionDown(after) => write!(f, "session_down:{after:?}"),
+            AlertCondition::RejectRate { count, window } => {
+                write!(f, "reject_rate:{count}/{window:?}")
+            }
+            AlertCondition::RiskBreach => f.write_str("risk_breach"),
+            AlertCondition::HeartbeatLatency(max) => write!(f, "heartbeat_latency:{max:?}"),
+        }
+    }
+}
+
+impl FromStr for AlertCondition {
+    type Err = String;
+
+    /// `session_down:30s`, `reject_rate:5/60s`, `risk_breach`,
+    /// `heartbeat_latency:500ms`
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let (name, value) = match source.split_once(':') {
+            Some((name, value)) => (name, Some(value)),
+            None => (source, None),
+        };
+        let duration = |value: Option<&str>| {
+            value
+                .and_then(parse_duration)
+                .ok_or_else(|| format!("{name}: expected a duration (500ms, 30s, 5m): {source}"))
+        };
+        match name {
+            "session_down" => Ok(AlertCondition::SessionDown(duration(value)?)),
+            "reject_rate" => {
+                let (count, window) = value.and_then(|x| x.split_once('/')).ok_or_else(|| {
+                    format!("reject_rate: expected COUNT/WINDOW (5/60s): {source}")
+                })?;
+                let count = count
+                    .parse()
+                    .ok()
+                    .filter(|x| *x > 0)
+                    .ok_or_else(|| format!("reject_rate: invalid count: {source}"))?;
+                Ok(AlertCondition::RejectRate {
+                    count,
+                    window: duration(Some(window))?,
+                })
+            }
+            "risk_breach" if value.is_none() => Ok(AlertCondition::RiskBreach),
+            "heartbeat_latency" => Ok(AlertCondition::HeartbeatLatency(duration(value)?)),
+            _ => Err(format!(
+                "unknown alert condition {source} (expected session_down:DURATION, \
+                 reject_rate:COUNT/DURATION, risk_breach or heartbeat_latency:DURATION)"
+            )),
+        }
+    }
+}
+
+/// `500ms`, `30s`, `5m`; None as well for minutes past u64 seconds
+fn parse_duration(value: &str) -> Option<Duration> {
+    let split = value.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    match unit {
+        "ms" => Some(Duration::from_millis(number)),
+        "s" => Some(Duration::from_secs(number)),
+        "m" => number.checked_mul(60).map(Duration::from_secs),
+        _ => None,
+    }
+}
+
+// =============================================================================
+// Alerts
+// =============================================================================
+
+/// A condition met, or a session down back up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    pub kind: AlertKind,
+
+    /// Session label, or the symbol of a risk breach without one
+    pub subject: String,
+
+    /// One line for a human: what happened, against which threshold
+    pub summary: String,
+
+    /// The condition no longer holds (a session back up)
+    pub resolved: bool,
+
+    /// Unix seconds
+    pub time: i64,
+}
+
+impl Alert {
+    fn new(kind: AlertKind, subject: &str, summary: String) -> Self {
+        Self {
+            kind,
+            subject: subject.to_string(),
+            summary,
+            resolved: false,
+            time: unix_now(),
+        }
+    }
+
+    /// Named values for templates and JSON bodies: kind, subject, summary,
+    /// resolved, and text (the whole alert as one line)
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("kind", self.kind.as_str().to_string()),
+            ("subject", self.subject.clone()),
+            ("summary", self.summary.clone()),
+            ("resolved", self.resolved.to_string()),
+            ("text", self.to_string()),
+        ]
+    }
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.resolved { "RESOLVED" } else { "ALERT" };
+        write!(
+            f,
+            "{state} {} {}: {}",
+            self.kind, self.subject, self.summary
+        )
+    }
+}
+
+// =============================================================================
+// Notifiers
+// =============================================================================
+
+/// Where alerts go; called on the thread that raised the alert, so an
+/// implementation queues or spawns rather than blocks
+pub trait Notifier: Send + Sync {
+    fn alert(&self, alert: &Alert);
+}
+
+impl<T: Notifier + ?Sized> Notifier for Arc<T> {
+    fn alert(&self, alert: &Alert) {
+        (**self).alert(alert);
+    }
+}
+
+// =============================================================================
+// Monitor
+// =============================================================================
+
+#[derive(Debug, Default)]
+struct SessionAlerts {
+    /// Start of the current outage, if down
+    down_since: Option<Instant>,
+
+    /// The current outage was alerted on
+    down_alerted: bool,
+
+    /// Rejects within the window
+    rejects: VecDeque<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    sessions: HashMap<String, SessionAlerts>,
+
+    /// Last time each condition fired, per subject
+    fired: HashMap<(AlertKind, String), Instant>,
+}
+
+/// The conditions watched, fed by the application
+pub struct AlertMonitor {
+    conditions: Vec<AlertCondition>,
+    notifiers: Vec<Arc<dyn Notifier>>,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+impl AlertMonitor {
+    pub fn new(conditions: Vec<AlertCondition>) -> Self {
+        Self {
+            conditions,
+            notifiers: Vec::new(),
+            cooldown: DEFAULT_COOLDOWN,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Hand every alert to this notifier too
+    pub fn with_notifier(mut self, notifier: Arc<dyn Notifier>) -> Self {
+        self.notifiers.push(notifier);
+        self
+    }
+
+    /// Time before the same condition fires again for a subject
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn conditions(&self) -> &[AlertCondition] {
+        &self.conditions
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("alerts lock poisoned")
+    }
+
+    fn condition(&self, kind: AlertKind) -> Option<AlertCondition> {
+        self.conditions.iter().copied().find(|x| x.kind() == kind)
+    }
+
+    /// Whether `kind` may fire for `subject` now, recording it if so
+    fn cooled_down(&self, state: &mut State, kind: AlertKind, subject: &str) -> bool {
+        let now = Instant::now();
+        let key = (kind, subject.to_string());
+        if state
+            .fired
+            .get(&key)
+            .is_some_and(|x| now - *x < self.cooldown)
+        {
+            return false;
+        }
+        state.fired.insert(key, now);
+        true
+    }
+
+    fn raise(&self, alert: Alert) -> Alert {
+        for notifier in &self.notifiers {
+            notifier.alert(&alert);
+        }
+        alert
+    }
+
+    /// A session that exists from now on; down until its first logon
+    pub fn on_create(&self, session: &str) {
+        let mut state = self.lock();
+        let health = state.sessions.entry(session.to_string()).or_default();
+        health.down_since.get_or_insert_with(Instant::now);
+    }
+
+    /// A session up; resolves the alert of its outage, if one fired
+    pub fn on_logon(&self, session: &str) -> Option<Alert> {
+        let mut state = self.lock();
+        let health = state.sessions.entry(session.to_string()).or_default();
+        let down = health.down_since.take().map(|x| x.elapsed());
+        let alerted = std::mem::take(&mut health.down_alerted);
+        drop(state);
+
+        let down = down.filter(|_| alerted)?;
+        let mut alert = Alert::new(
+            AlertKind::SessionDown,
+            session,
+            format!("logged on after {:?} down", round(down)),
+        );
+        alert.resolved = true;
+        Some(self.raise(alert))
+    }
+
+    /// A session down; alerted by check() once down for long enough
+    pub fn on_logout(&self, session: &str) {
+        let mut state = self.lock();
+        let health = state.sessions.entry(session.to_string()).or_default();
+        if health.down_since.is_none() {
+            health.down_since = Some(Instant::now());
+            health.down_alerted = false;
+        }
+    }
+
+    /// A reject received from the session: of an order, a cancel, or a
+    /// session or business reject
+    pub fn on_reject(&self, session: &str) -> Option<Alert> {
+        let AlertCondition::RejectRate { count, window } = self.condition(AlertKind::RejectRate)?
+        else {
+            return None;
+        };
+        let now = Instant::now();
+        let mut state = self.lock();
+        let health = state.sessions.entry(session.to_string()).or_default();
+        health.rejects.push_back(now);
+        while health.rejects.front().is_some_and(|x| now - *x > window) {
+            health.rejects.pop_front();
+        }
+        if health.rejects.len() < count as usize {
+            return None;
+        }
+        let rejects = health.rejects.len();
+        health.rejects.clear();
+        if !self.cooled_down(&mut state, AlertKind::RejectRate, session) {
+            return None;
+        }
+        drop(state);
+
+        let summary = format!("{rejects} rejects within {window:?} (limit {count})");
+        Some(self.raise(Alert::new(AlertKind::RejectRate, session, summary)))
+    }
+
+    /// An order refused by the risk checks; `subject` is its session, or
+    /// its symbol
+    pub fn on_risk_breach(&self, subject: &str, reason: &str) -> Option<Alert> {
+        self.condition(AlertKind::RiskBreach)?;
+        if !self.cooled_down(&mut self.lock(), AlertKind::RiskBreach, subject) {
+            return None;
+        }
+        let summary = format!("order refused: {reason}");
+        Some(self.raise(Alert::new(AlertKind::RiskBreach, subject, summary)))
+    }
+
+    /// A TestRequest -> Heartbeat round trip measured
+    pub fn on_heartbeat_rtt(&self, session: &str, rtt: Duration) -> Option<Alert> {
+        let AlertCondition::HeartbeatLatency(max) = self.condition(AlertKind::HeartbeatLatency)?
+        else {
+            return None;
+        };
+        if rtt <= max || !self.cooled_down(&mut self.lock(), AlertKind::HeartbeatLatency, session) {
+            return None;
+        }
+        let summary = format!("heartbeat round trip {rtt:.1?} (limit {max:?})");
+        Some(self.raise(Alert::new(AlertKind::HeartbeatLatency, session, summary)))
+    }
+
+    /// Alert on the sessions down for longer than the limit, once per
+    /// outage; call every second or so
+    pub fn check(&self) -> Vec<Alert> {
+        let Some(AlertCondition::SessionDown(after)) = self.condition(AlertKind::SessionDown)
+        else {
+            return Vec::new();
+        };
+        let mut due = Vec::new();
+        for (label, health) in self.lock().sessions.iter_mut() {
+            let Some(since) = health.down_since else {
+                continue;
+            };
+            if !health.down_alerted && since.elapsed() > after {
+                health.down_alerted = true;
+                due.push((label.clone(), since.elapsed()));
+            }
+        }
+        due.sort();
+        due.into_iter()
+            .map(|(label, down)| {
+                let summary = format!("down for {:?} (limit {after:?})", round(down));
+                self.raise(Alert::new(AlertKind::SessionDown, &label, summary))
+            })
+            .collect()
+    }
+}
+
+/// To the second, for outages
+fn round(duration: Duration) -> Duration {
+    Duration::from_secs(duration.as_secs())
+}
@@ -0,0 +1,292 @@
+// =============================================================================
+// Operator Audit Log
+// =============================================================================
+// Who changed what, when, and how it went. Every state-changing command of
+// the operator interfaces (the REPL, its remote console and its pipe, the
+// consoles, the buy side's REST and gRPC gateways, the venue admin API) is
+// appended to one file as a JSON line:
+//
+//   {"time":1705327200,"source":"repl","actor":"alice",
+//    "command":"fault skip-seq 10","ok":true,"result":"OK"}
+//
+// The file, commands.jsonl in the audit directory, is only ever appended
+// to: each entry is one write in append mode, synced before the command
+// returns, and nothing rewrites, truncates or rotates it. Processes sharing
+// a directory interleave their entries line by line. Commands that only
+// read (status, GET) are not recorded.
+//
+// The actor is whoever the interface knows: the login of the REPL's user or
+// of a remote console client, the X-Operator header (x-operator metadata of
+// a gRPC call) or else the client address. Nothing here authenticates it; the remote console
+// does, with its tokens.
+// =============================================================================
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{
+    json::{json_escape, JsonValue},
+    time::{time_of_day, unix_now, Date},
+};
+
+/// Audit directory when none is given
+pub const DEFAULT_AUDIT_DIR: &str = "audit";
+
+/// Name of the log in the audit directory
+pub const AUDIT_FILE: &str = "commands.jsonl";
+
+/// Actor recorded when nobody is known
+pub const UNKNOWN_ACTOR: &str = "unknown";
+
+/// The login of the local user ($USER, or %USERNAME% on Windows)
+pub fn local_operator() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| UNKNOWN_ACTOR.to_string())
+}
+
+/// Interface a command came through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditSource {
+    /// The interactive shell (fix_repl)
+    Repl,
+
+    /// The console of buy_side or sell_side
+    Console,
+
+    /// The buy side's REST order entry gateway
+    Rest,
+
+    /// The venue's admin API (sell_side)
+    Admin,
+
+    /// The remote console of the interactive shell (fix_repl --admin-socket
+    /// or --admin-listen)
+    Remote,
+
+    /// Lines another process feeds the interactive shell (fix_repl --pipe)
+    Pipe,
+
+    /// The buy side's gRPC order entry gateway
+    Grpc,
+}
+
+impl AuditSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditSource::Repl => "repl",
+            AuditSource::Console => "console",
+            AuditSource::Rest => "rest",
+            AuditSource::Admin => "admin",
+            AuditSource::Remote => "remote",
+            AuditSource::Pipe => "pipe",
+            AuditSource::Grpc => "grpc",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "repl" => Some(AuditSource::Repl),
+            "console" => Some(AuditSource::Console),
+            "rest" => Some(AuditSource::Rest),
+            "admin" => Some(AuditSource::Admin),
+            "remote" => Some(AuditSource::Remote),
+            "pipe" => Some(AuditSource::Pipe),
+            "grpc" => Some(AuditSource::Grpc),
+            _ => None,
+        }
+    }
+}
+
+/// One command, as recorded
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// Unix seconds, when it completed
+    pub time: i64,
+    pub source: AuditSource,
+    pub actor: String,
+
+    /// As typed or requested: a shell line, "METHOD /path body"
+    pub command: String,
+
+    /// Whether it succeeded
+    pub ok: bool,
+
+    /// Result code or HTTP status
+    pub result: String,
+}
+
+impl AuditEntry {
+    /// A command that just completed
+    pub fn new(source: AuditSource, actor: &str, command: &str, ok: bool, result: &str) -> Self {
+        Self {
+            time: unix_now(),
+            source,
+            actor: actor.to_string(),
+            command: command.trim().to_string(),
+            ok,
+            result: result.to_string(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"time\":{},\"source\":\"{}\",\"actor\":\"{}\",\"command\":\"{}\",\
+             \"ok\":{},\"result\":\"{}\"}}",
+            self.time,
+            self.source.as_str(),
+            json_escape(&self.actor),
+            json_escape(&self.command),
+            self.ok,
+            json_escape(&self.result),
+        )
+    }
+
+    /// Read back a line of the log; None if it is not an entry
+    pub fn from_json(line: &str) -> Option<Self> {
+        let value = JsonValue::parse(line)?;
+        let text = |key| value.get(key).and_then(JsonValue::as_str);
+        Some(Self {
+            time: value.get("time")?.as_f64()? as i64,
+            source: AuditSource::from_name(text("source")?)?,
+            actor: text("actor")?.to_string(),
+            command: text("command")?.to_string(),
+            ok: matches!(value.get("ok")?, JsonValue::Bool(true)),
+            result: text("result")?.to_string(),
+        })
+    }
+
+    /// One line: date and time (UTC), source, actor, result, command
+    pub fn to_text(&self) -> String {
+        format!(
+            "{} {} {:<7} {:<12} {:<14} {}",
+            Date::from_unix(self.time).to_iso(),
+            time_of_day(self.time),
+            self.source.as_str(),
+            self.actor,
+            self.result,
+            self.command,
+        )
+    }
+}
+
+/// Which entries to read back; every entry by default
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    /// Unix seconds, entries from then on
+    pub since: Option<i64>,
+    pub source: Option<AuditSource>,
+    pub actor: Option<String>,
+
+    /// Text the command contains
+    pub contains: Option<String>,
+
+    /// Failed commands only
+    pub failed: bool,
+
+    /// The newest entries only
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_since(mut self, since: i64) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    pub fn with_source(mut self, source: AuditSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn with_actor(mut self, actor: &str) -> Self {
+        self.actor = Some(actor.to_string());
+        self
+    }
+
+    pub fn with_contains(mut self, text: &str) -> Self {
+        self.contains = Some(text.to_string());
+        self
+    }
+
+    pub fn failed_only(mut self) -> Self {
+        self.failed = true;
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.since.is_none_or(|x| entry.time >= x)
+            && self.source.is_none_or(|x| entry.source == x)
+            && self.actor.as_ref().is_none_or(|x| entry.actor == *x)
+            && self
+                .contains
+                .as_ref()
+                .is_none_or(|x| entry.command.contains(x.as_str()))
+            && !(self.failed && entry.ok)
+    }
+}
+
+/// The append-only log of one audit directory
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl AuditLog {
+    /// Open the log of a directory, created if needed, for appending
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(AUDIT_FILE);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an entry and sync it to disk
+    pub fn record(&self, entry: &AuditEntry) -> io::Result<()> {
+        let line = entry.to_json() + "\n";
+        let mut file = self.file.lock().expect("audit log lock poisoned");
+        file.write_all(line.as_bytes())?;
+        file.sync_data()
+    }
+
+    /// Entries matching a query, oldest first
+    ///
+    /// Lines that are not entries (a partial line after a crash) are
+    /// skipped.
+    pub fn query(&self, query: &AuditQuery) -> io::Result<Vec<AuditEntry>> {
+        let reader = BufReader::new(File::open(&self.path)?);
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            if let Some(entry) = AuditEntry::from_json(&line?) {
+                if query.matches(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+        if let Some(limit) = query.limit {
+            entries.drain(..entries.len().saturating_sub(limit));
+        }
+        Ok(entries)
+    }
+}
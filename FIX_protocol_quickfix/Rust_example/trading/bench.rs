@@ -0,0 +1,581 @@
+// =============================================================================
+// Throughput Benchmark
+// =============================================================================
+// Measures what the FIX engine sustains on this machine, before any real
+// counterparty is involved. Used by `selftest throughput` in fix_repl and by
+// the fix_bench binary:
+//
+// 1. An acceptor and an initiator are started in-process, on an ephemeral
+//    port, with the chosen message store and socket threading model
+// 2. The initiator sends NewOrderSingles (35=D), for a duration or a number
+//    of round trips, keeping at most `max_in_flight` unanswered; the first
+//    `warmup` round trips are not measured
+// 3. The acceptor answers each one with an ExecutionReport (35=8)
+// 4. The round trip of every order is measured; the report gives messages per
+//    second and latency percentiles
+//
+// Comparing the memory and file stores, and single with multi-threaded
+// sockets, shows what each choice costs before production. Messages are not
+// validated against a data dictionary: the figures cover the engine, the
+// sockets and the store.
+// =============================================================================
+
+use std::{
+    env,
+    error::Error,
+    fmt, fs, io, mem,
+    net::TcpListener,
+    path::Path,
+    process,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use quickfix::{dictionary_item::*, *};
+
+use crate::time::transact_time;
+
+/// Duration of the measurement when none is given
+pub const DEFAULT_DURATION: Duration = Duration::from_secs(10);
+
+/// Orders sent but not answered yet, when not given; the sender waits above
+/// this
+pub const DEFAULT_MAX_IN_FLIGHT: u64 = 256;
+
+/// How long the pair may take to log on
+const LOGON_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the last answers may take once sending stopped
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+const BEGIN_STRING: &str = "FIX.4.4";
+const CLIENT_COMP_ID: &str = "BENCH_CLIENT";
+const VENUE_COMP_ID: &str = "BENCH_VENUE";
+
+// =============================================================================
+// Options
+// =============================================================================
+
+/// Message store used by both ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreKind {
+    Memory,
+    File,
+}
+
+impl StoreKind {
+    pub fn from_name(value: &str) -> Option<Self> {
+        match value {
+            "memory" => Some(StoreKind::Memory),
+            "file" => Some(StoreKind::File),
+            _ => None,
+        }
+    }
+}
+
+/// How much to send
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Load {
+    /// Send for this long
+    Duration(Duration),
+
+    /// Send this many orders
+    RoundTrips(u64),
+}
+
+impl fmt::Display for Load {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Load::Duration(duration) => write!(f, "{duration:?}"),
+            Load::RoundTrips(count) => write!(f, "{count} round trips"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ThroughputOptions {
+    pub load: Load,
+    pub store: StoreKind,
+    pub multi_threaded: bool,
+
+    /// Orders sent but not answered yet; the sender waits above this
+    pub max_in_flight: u64,
+
+    /// Round trips sent first and left out of the figures (connection and
+    /// store warm-up)
+    pub warmup: u64,
+}
+
+impl Default for ThroughputOptions {
+    fn default() -> Self {
+        Self {
+            load: Load::Duration(DEFAULT_DURATION),
+            store: StoreKind::Memory,
+            multi_threaded: false,
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            warmup: 0,
+        }
+    }
+}
+
+impl ThroughputOptions {
+    /// `single-threaded` or `multi-threaded`
+    pub fn sockets(&self) -> &'static str {
+        if self.multi_threaded {
+            "multi-threaded"
+        } else {
+            "single-threaded"
+        }
+    }
+
+    fn server_kind(&self) -> FixSocketServerKind {
+        if self.multi_threaded {
+            FixSocketServerKind::MultiThreaded
+        } else {
+            FixSocketServerKind::SingleThreaded
+        }
+    }
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+pub enum BenchError {
+    /// No free port, or the file store directory cannot be created
+    Io(io::Error),
+
+    /// The acceptor / initiator pair could not be built or did not run
+    QuickFix(QuickFixError),
+
+    /// The initiator did not log on within LOGON_TIMEOUT
+    LogonTimeout,
+}
+
+impl fmt::Display for BenchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchError::Io(err) => write!(f, "I/O: {err}"),
+            BenchError::QuickFix(err) => write!(f, "quickfix: {err:?}"),
+            BenchError::LogonTimeout => {
+                write!(f, "no logon within {}s", LOGON_TIMEOUT.as_secs())
+            }
+        }
+    }
+}
+
+impl Error for BenchError {}
+
+impl From<io::Error> for BenchError {
+    fn from(err: io::Error) -> Self {
+        BenchError::Io(err)
+    }
+}
+
+impl From<QuickFixError> for BenchError {
+    fn from(err: QuickFixError) -> Self {
+        BenchError::QuickFix(err)
+    }
+}
+
+// =============================================================================
+// Venue: answers every order
+// =============================================================================
+
+struct EchoVenue;
+
+impl ApplicationCallback for EchoVenue {
+    fn on_msg_from_app(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        // The send fails only if the client is gone, which ends the test
+        if let Some(cl_ord_id) = msg.get_field(11) {
+            let _ = build_report(&cl_ord_id).and_then(|x| send_to_target(x, session));
+        }
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Client: measures round trips
+// =============================================================================
+// Each ClOrdID carries the send time, as nanoseconds since `epoch`, so the
+// answer can be timed without a lookup table shared with the sender.
+// =============================================================================
+
+struct Client {
+    epoch: Instant,
+    logged_on: AtomicBool,
+    received: AtomicU64,
+    latencies: Mutex<Vec<Duration>>,
+}
+
+impl Client {
+    fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+            logged_on: AtomicBool::new(false),
+            received: AtomicU64::new(0),
+            latencies: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn next_cl_ord_id(&self, sequence: u64) -> String {
+        format!("{sequence}:{}", self.epoch.elapsed().as_nanos())
+    }
+}
+
+impl ApplicationCallback for Client {
+    fn on_logon(&self, _session: &SessionId) {
+        self.logged_on.store(true, Ordering::Release);
+    }
+
+    fn on_logout(&self, _session: &SessionId) {
+        self.logged_on.store(false, Ordering::Release);
+    }
+
+    fn on_msg_from_app(&self, msg: &Message, _session: &SessionId) -> Result<(), MsgFromAppError> {
+        let sent_nanos = msg
+            .get_field(11)
+            .and_then(|x| x.split_once(':').and_then(|(_, nanos)| nanos.parse::<u64>().ok()));
+        if let Some(sent_nanos) = sent_nanos {
+            let latency = self
+                .epoch
+                .elapsed()
+                .saturating_sub(Duration::from_nanos(sent_nanos));
+            self.latencies
+                .lock()
+                .expect("latency lock poisoned")
+                .push(latency);
+            self.received.fetch_add(1, Ordering::AcqRel);
+        }
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Report
+// =============================================================================
+
+#[derive(Debug)]
+pub struct ThroughputReport {
+    pub options: ThroughputOptions,
+    pub sent: u64,
+    pub received: u64,
+    pub elapsed: Duration,
+    /// Round trips, sorted
+    latencies: Vec<Duration>,
+}
+
+impl ThroughputReport {
+    /// Round trips per second (each is two messages on the wire)
+    pub fn round_trips_per_sec(&self) -> f64 {
+        self.received as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Latency below which `quantile` (0.0 ..= 1.0) of the round trips fall
+    pub fn percentile(&self, quantile: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let index = ((self.latencies.len() - 1) as f64 * quantile).round() as usize;
+        self.latencies[index]
+    }
+
+    pub fn mean(&self) -> Duration {
+        let total: Duration = self.latencies.iter().sum();
+        total / u32::try_from(self.latencies.len().max(1)).unwrap_or(u32::MAX)
+    }
+
+    pub fn print(&self) {
+        let rate = self.round_trips_per_sec();
+        println!(
+            "throughput: store={:?} sockets={} load={} in-flight={} warmup={}",
+            self.options.store,
+            self.options.sockets(),
+            self.options.load,
+            self.options.max_in_flight,
+            self.options.warmup
+        );
+        println!(
+            "  orders sent {:>10}   answered {:>10}   lost {:>6}",
+            self.sent,
+            self.received,
+            self.sent.saturating_sub(self.received)
+        );
+        println!("  round trips/s {rate:>12.0}   msgs/s {:>12.0}", rate * 2.0);
+        println!(
+            "  {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "min", "mean", "p50", "p90", "p99", "p99.9", "max"
+        );
+        println!(
+            "  {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            format!("{:?}", self.percentile(0.0)),
+            format!("{:?}", self.mean()),
+            format!("{:?}", self.percentile(0.5)),
+            format!("{:?}", self.percentile(0.9)),
+            format!("{:?}", self.percentile(0.99)),
+            format!("{:?}", self.percentile(0.999)),
+            format!("{:?}", self.percentile(1.0)),
+        );
+    }
+}
+
+// =============================================================================
+// Run
+// =============================================================================
+
+/// Run the throughput test; blocks the calling thread for the whole duration
+pub fn run_throughput(options: &ThroughputOptions) -> Result<ThroughputReport, BenchError> {
+    let port = ephemeral_port()?;
+
+    // The file store gets a scratch directory, removed afterwards, so each
+    // run starts from sequence number 1
+    let store_path = env::temp_dir().join(format!("trading_bench_{}", process::id()));
+    if options.store == StoreKind::File {
+        fs::create_dir_all(&store_path)?;
+    }
+    let result = run_pair(options, port, &store_path);
+    if options.store == StoreKind::File {
+        let _ = fs::remove_dir_all(&store_path);
+    }
+    result
+}
+
+fn run_pair(
+    options: &ThroughputOptions,
+    port: u16,
+    store_path: &Path,
+) -> Result<ThroughputReport, BenchError> {
+    let venue_session = SessionId::try_new(BEGIN_STRING, VENUE_COMP_ID, CLIENT_COMP_ID, "")?;
+    let client_session = SessionId::try_new(BEGIN_STRING, CLIENT_COMP_ID, VENUE_COMP_ID, "")?;
+
+    let venue_settings = build_settings(
+        &venue_session,
+        &[&ConnectionType::Acceptor, &SocketAcceptPort(port)],
+        options,
+        &store_path.join("venue"),
+    )?;
+    let client_settings = build_settings(
+        &client_session,
+        &[
+            &ConnectionType::Initiator,
+            &ReconnectInterval(1),
+            &HeartBtInt(30),
+            &SocketConnectHost("127.0.0.1"),
+            &SocketConnectPort(port),
+        ],
+        options,
+        &store_path.join("client"),
+    )?;
+
+    let venue = EchoVenue;
+    let venue_app = Application::try_new(&venue)?;
+    let venue_log = LogFactory::try_new(&NullLogger)?;
+
+    let client = Client::new();
+    let client_app = Application::try_new(&client)?;
+    let client_log = LogFactory::try_new(&NullLogger)?;
+
+    // Both ends use the same kind of store; the factories differ in type,
+    // hence the two branches
+    match options.store {
+        StoreKind::Memory => {
+            let venue_store = MemoryMessageStoreFactory::new();
+            let client_store = MemoryMessageStoreFactory::new();
+            let mut acceptor = Acceptor::try_new(
+                &venue_settings,
+                &venue_app,
+                &venue_store,
+                &venue_log,
+                options.server_kind(),
+            )?;
+            let mut initiator = Initiator::try_new(
+                &client_settings,
+                &client_app,
+                &client_store,
+                &client_log,
+                options.server_kind(),
+            )?;
+            measure(options, &client, &client_session, &mut acceptor, &mut initiator)
+        }
+        StoreKind::File => {
+            let venue_store = FileMessageStoreFactory::try_new(&venue_settings)?;
+            let client_store = FileMessageStoreFactory::try_new(&client_settings)?;
+            let mut acceptor = Acceptor::try_new(
+                &venue_settings,
+                &venue_app,
+                &venue_store,
+                &venue_log,
+                options.server_kind(),
+            )?;
+            let mut initiator = Initiator::try_new(
+                &client_settings,
+                &client_app,
+                &client_store,
+                &client_log,
+                options.server_kind(),
+            )?;
+            measure(options, &client, &client_session, &mut acceptor, &mut initiator)
+        }
+    }
+}
+
+/// Start the pair, send the orders, stop the pair
+fn measure<A: ConnectionHandler, I: ConnectionHandler>(
+    options: &ThroughputOptions,
+    client: &Client,
+    session: &SessionId,
+    acceptor: &mut A,
+    initiator: &mut I,
+) -> Result<ThroughputReport, BenchError> {
+    acceptor.start()?;
+    initiator.start()?;
+
+    let outcome = send_orders(options, client, session);
+
+    // Harmless if the pair never logged on
+    let _ = initiator.stop();
+    let _ = acceptor.stop();
+
+    let (sent, elapsed) = outcome?;
+    let mut latencies = mem::take(&mut *client.latencies.lock().expect("latency lock poisoned"));
+    latencies.sort_unstable();
+
+    Ok(ThroughputReport {
+        options: options.clone(),
+        sent,
+        received: client.received.load(Ordering::Acquire),
+        elapsed,
+        latencies,
+    })
+}
+
+/// Send the warm-up orders, forget them, then send orders until the load is
+/// reached and wait for the last answers
+///
+/// # Returns
+/// The number of measured orders sent and the time from the first send to
+/// the last answer (or the drain timeout)
+fn send_orders(
+    options: &ThroughputOptions,
+    client: &Client,
+    session: &SessionId,
+) -> Result<(u64, Duration), BenchError> {
+    if !wait_until(LOGON_TIMEOUT, || client.logged_on.load(Ordering::Acquire)) {
+        return Err(BenchError::LogonTimeout);
+    }
+
+    // ClOrdIDs stay unique across the warm-up and the measure
+    let mut sequence = 0;
+    if options.warmup > 0 {
+        send_batch(options, client, session, &mut sequence, |sent, _| {
+            sent < options.warmup
+        })?;
+        client.received.store(0, Ordering::Release);
+        client.latencies.lock().expect("latency lock poisoned").clear();
+    }
+
+    let started = Instant::now();
+    let sent = send_batch(options, client, session, &mut sequence, |sent, started| {
+        match options.load {
+            Load::Duration(duration) => started.elapsed() < duration,
+            Load::RoundTrips(count) => sent < count,
+        }
+    })?;
+    Ok((sent, started.elapsed()))
+}
+
+/// Send while `more(sent, started)` holds, then wait for the answers
+fn send_batch<F: Fn(u64, Instant) -> bool>(
+    options: &ThroughputOptions,
+    client: &Client,
+    session: &SessionId,
+    sequence: &mut u64,
+    more: F,
+) -> Result<u64, BenchError> {
+    let started = Instant::now();
+    let mut sent = 0;
+    while more(sent, started) {
+        let answered = client.received.load(Ordering::Acquire);
+        if sent.saturating_sub(answered) >= options.max_in_flight.max(1) {
+            thread::yield_now();
+            continue;
+        }
+        let msg = build_order(&client.next_cl_ord_id(*sequence))?;
+        send_to_target(msg, session)?;
+        sent += 1;
+        *sequence += 1;
+    }
+
+    wait_until(DRAIN_TIMEOUT, || client.received.load(Ordering::Acquire) >= sent);
+    Ok(sent)
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Ask the OS for a free TCP port
+fn ephemeral_port() -> io::Result<u16> {
+    // The listener is dropped right away; the acceptor binds the port next
+    Ok(TcpListener::bind(("127.0.0.1", 0))?.local_addr()?.port())
+}
+
+/// Poll a condition until it holds or `timeout` expires
+fn wait_until<F: Fn() -> bool>(timeout: Duration, condition: F) -> bool {
+    let started = Instant::now();
+    while !condition() {
+        if started.elapsed() > timeout {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    true
+}
+
+/// Settings for one end: `[DEFAULT]` from `items`, plus the one session
+fn build_settings(
+    session: &SessionId,
+    items: &[&dyn DictionaryItem],
+    options: &ThroughputOptions,
+    store_path: &Path,
+) -> Result<SessionSettings, QuickFixError> {
+    let mut defaults = Dictionary::try_from_items(items)?;
+    defaults.set("StartTime", "00:00:00")?;
+    defaults.set("EndTime", "00:00:00")?;
+    defaults.set("UseDataDictionary", "N")?;
+    if options.store == StoreKind::File {
+        defaults.set("FileStorePath", store_path.to_string_lossy().as_ref())?;
+    }
+
+    let mut settings = SessionSettings::new();
+    settings.set(None, defaults)?;
+    settings.set(Some(session), Dictionary::new())?;
+    Ok(settings)
+}
+
+fn build_order(cl_ord_id: &str) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "D"))?;
+    msg.set_field(11, cl_ord_id)?;
+    msg.set_field(55, "BENCH")?;
+    msg.set_field(54, "1")?; // Side: Buy
+    msg.set_field(38, "100")?; // OrderQty
+    msg.set_field(40, "2")?; // OrdType: Limit
+    msg.set_field(44, "10")?; // Price
+    msg.set_field(60, transact_time().as_str())?; // TransactTime
+    Ok(msg)
+}
+
+fn build_report(cl_ord_id: &str) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "8"))?;
+    msg.set_field(11, cl_ord_id)?;
+    msg.set_field(150, "0")?; // ExecType: New
+    msg.set_field(39, "0")?; // OrdStatus: New
+    Ok(msg)
+}
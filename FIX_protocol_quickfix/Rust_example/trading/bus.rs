@@ -0,0 +1,301 @@
+// =============================================================================
+// Application Event Bus
+// =============================================================================
+// The event task of each example matches on MsgType and calls every module
+// that cares in turn: the OMS, the metrics, the WebSocket bridge, the
+// strategy. The bus turns the decoded FixEvents into what they mean to the
+// business instead, once, and hands each one to every subscriber:
+//
+//   FixEvent --decode--> AppEvent --publish--> subscriber channels
+//
+//   SessionUp / SessionDown   Logon / Logout
+//   OrderAccepted             35=8 ExecType (150) New
+//   Fill                      35=8 with LastQty (32), as a blotter Trade
+//   FillAmended               35=8 trade bust / correct (150=H/G, 20=1/2)
+//   Reject                    35=8 ExecType Rejected, 35=9, 35=3, 35=j
+//   MarketDataUpdate          35=W / 35=X, one symbol and its entries
+//
+// A subscriber gets a std mpsc Receiver of the kinds it asked for (every
+// kind with subscribe_all) and reads it from its own thread or task; the
+// channel is unbounded, as the event channel is, so publishing never
+// blocks the event task. A subscriber that drops its Receiver is forgotten
+// at the next event published to it. Outbound messages are not published.
+// =============================================================================
+
+use std::sync::{
+    mpsc::{self, Receiver, Sender},
+    Mutex, MutexGuard,
+};
+
+use crate::{
+    oms::blotter::Trade,
+    session::{
+        events::{group_field, FixEvent, FixMessage},
+        Direction,
+    },
+};
+
+// =============================================================================
+// Events
+// =============================================================================
+
+/// What a FIX event means to the business
+///
+/// Sessions are identified by their label (`FIX.4.4:SENDER->TARGET`).
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum AppEvent {
+    SessionUp {
+        session: String,
+    },
+    SessionDown {
+        session: String,
+    },
+
+    /// The venue acknowledged an order
+    OrderAccepted {
+        session: String,
+        cl_ord_id: String,
+
+        /// OrderID (37); empty if the venue sent none
+        order_id: String,
+        symbol: String,
+    },
+
+    Fill(Trade),
+
+    /// A fill taken back (`corrected` None) or replaced
+    FillAmended {
+        session: String,
+
+        /// ExecRefID (19), the ExecID of the fill amended
+        exec_ref_id: String,
+        corrected: Option<Trade>,
+    },
+
+    Reject(Reject),
+    MarketDataUpdate(MarketDataUpdate),
+}
+
+/// The kinds a subscriber chooses from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Session,
+    OrderAccepted,
+    Fill,
+    Reject,
+    MarketData,
+}
+
+impl AppEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            AppEvent::SessionUp { .. } | AppEvent::SessionDown { .. } => EventKind::Session,
+            AppEvent::OrderAccepted { .. } => EventKind::OrderAccepted,
+            AppEvent::Fill(_) | AppEvent::FillAmended { .. } => EventKind::Fill,
+            AppEvent::Reject(_) => EventKind::Reject,
+            AppEvent::MarketDataUpdate(_) => EventKind::MarketData,
+        }
+    }
+
+    /// The event a FixEvent carries, if it means anything to the business
+    pub fn decode(event: &FixEvent) -> Option<Self> {
+        match event {
+            FixEvent::Logon { session } => Some(AppEvent::SessionUp {
+                session: session.clone(),
+            }),
+            FixEvent::Logout { session } => Some(AppEvent::SessionDown {
+                session: session.clone(),
+            }),
+            FixEvent::Message(msg) if msg.direction == Direction::Inbound => {
+                Self::decode_message(msg)
+            }
+            _ => None,
+        }
+    }
+
+    fn decode_message(msg: &FixMessage) -> Option<Self> {
+        let field = |tag| msg.get(tag).unwrap_or_default().to_string();
+        let bust = msg.get(150) == Some("H") || msg.get(20) == Some("1");
+        let correct = msg.get(150) == Some("G") || msg.get(20) == Some("2");
+        match msg.msg_type() {
+            "8" if bust || correct => Some(AppEvent::FillAmended {
+                session: msg.session.clone(),
+                exec_ref_id: field(19),
+                corrected: Trade::from_execution_report(msg).filter(|_| correct),
+            }),
+            "8" => match msg.get(150) {
+                Some("0") => Some(AppEvent::OrderAccepted {
+                    session: msg.session.clone(),
+                    cl_ord_id: field(11),
+                    order_id: field(37),
+                    symbol: field(55),
+                }),
+                Some("8") => Some(AppEvent::Reject(Reject::new(msg, RejectOf::Order, 11))),
+                _ => Trade::from_execution_report(msg).map(AppEvent::Fill),
+            },
+            "9" => Some(AppEvent::Reject(Reject::new(msg, RejectOf::Cancel, 11))),
+            "3" => Some(AppEvent::Reject(Reject::new(msg, RejectOf::Session, 45))),
+            "j" => Some(AppEvent::Reject(Reject::new(msg, RejectOf::Business, 379))),
+            "W" | "X" => Some(AppEvent::MarketDataUpdate(MarketDataUpdate::new(msg))),
+            _ => None,
+        }
+    }
+}
+
+/// What was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectOf {
+    /// An order (35=8, ExecType 8)
+    Order,
+
+    /// A cancel or replace (35=9)
+    Cancel,
+
+    /// A message breaking the session rules (35=3)
+    Session,
+
+    /// An application message that could not be handled (35=j)
+    Business,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reject {
+    pub session: String,
+    pub of: RejectOf,
+
+    /// ClOrdID (11) of an order or cancel, RefSeqNum (45) of a session
+    /// reject, BusinessRejectRefID (379) of a business reject; empty if
+    /// missing
+    pub reference: String,
+
+    /// Text (58), if any
+    pub text: Option<String>,
+}
+
+impl Reject {
+    fn new(msg: &FixMessage, of: RejectOf, reference: i32) -> Self {
+        Self {
+            session: msg.session.clone(),
+            of,
+            reference: msg.get(reference).unwrap_or_default().to_string(),
+            text: msg.get(58).map(str::to_string),
+        }
+    }
+}
+
+/// A market data snapshot (35=W) or incremental refresh (35=X)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketDataUpdate {
+    pub session: String,
+
+    /// Symbol (55) of the message, or else of its first entry
+    pub symbol: String,
+
+    /// A full snapshot rather than an incremental refresh
+    pub snapshot: bool,
+    pub entries: Vec<MarketDataEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketDataEntry {
+    /// MDEntryType (269): `0` bid, `1` offer, `2` trade, ...
+    pub entry_type: String,
+    pub price: Option<f64>,
+    pub size: Option<f64>,
+}
+
+impl MarketDataUpdate {
+    fn new(msg: &FixMessage) -> Self {
+        let groups = msg.groups(269);
+        let symbol = msg
+            .get(55)
+            .or_else(|| groups.first().and_then(|x| group_field(x, 55)))
+            .unwrap_or_default()
+            .to_string();
+        let number = |group, tag| group_field(group, tag).and_then(|x| x.parse().ok());
+        let entries = groups
+            .iter()
+            .map(|group| MarketDataEntry {
+                entry_type: group_field(group, 269).unwrap_or_default().to_string(),
+                price: number(group, 270),
+                size: number(group, 271),
+            })
+            .collect();
+        Self {
+            session: msg.session.clone(),
+            symbol,
+            snapshot: msg.msg_type() == "W",
+            entries,
+        }
+    }
+}
+
+// =============================================================================
+// Bus
+// =============================================================================
+
+struct Subscriber {
+    /// Empty for every kind
+    kinds: Vec<EventKind>,
+    sender: Sender<AppEvent>,
+}
+
+/// Decoded events handed to every subscriber
+#[derive(Default)]
+pub struct EventBus {
+    // Changed by subscribe, and by publish, which drops the subscribers
+    // whose receiver is gone: hence the Mutex
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A channel of the events of these kinds
+    pub fn subscribe(&self, kinds: &[EventKind]) -> Receiver<AppEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.lock().push(Subscriber {
+            kinds: kinds.to_vec(),
+            sender,
+        });
+        receiver
+    }
+
+    /// A channel of every event
+    pub fn subscribe_all(&self) -> Receiver<AppEvent> {
+        self.subscribe(&[])
+    }
+
+    /// Whether anyone subscribed, so an idle bus costs no decoding
+    pub fn has_subscribers(&self) -> bool {
+        !self.lock().is_empty()
+    }
+
+    /// Hand an event to the subscribers of its kind
+    pub fn publish(&self, event: AppEvent) {
+        let kind = event.kind();
+        self.lock().retain(|subscriber| {
+            if !subscriber.kinds.is_empty() && !subscriber.kinds.contains(&kind) {
+                return true;
+            }
+            subscriber.sender.send(event.clone()).is_ok()
+        });
+    }
+
+    /// Decode a FixEvent and publish what it means, if anything
+    pub fn publish_fix(&self, event: &FixEvent) {
+        if !self.has_subscribers() {
+            return;
+        }
+        if let Some(event) = AppEvent::decode(event) {
+            self.publish(event);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Subscriber>> {
+        self.subscribers.lock().expect("event bus lock poisoned")
+    }
+}
@@ -0,0 +1,412 @@
+// =============================================================================
+// Clock Synchronisation Evidence
+// =============================================================================
+// Regulators want more than "the host runs NTP": MiFID II (RTS 25) asks
+// trading venues and firms to show that business clocks stay within a
+// maximum divergence from UTC, and CAT asks the same of US reporters. The
+// ClockSyncMonitor collects that evidence: it queries a time server (SNTP,
+// RFC 4330) at regular intervals, keeps every offset measured, and closes
+// periods into ClockSyncReports that are stored with the audit trail.
+//
+// One query gives four timestamps, T1 / T4 local (request sent, reply
+// received), T2 / T3 on the server (request received, reply sent):
+//
+//   offset     = ((T2 - T1) + (T3 - T4)) / 2   positive: local clock behind
+//   round trip = (T4 - T1) - (T3 - T2)
+//
+// The offset is only known within half the round trip, so a sample breaches
+// the tolerance when |offset| + round trip / 2 exceeds it: the report never
+// claims more accuracy than was measured.
+//
+// Usual tolerances (maximum divergence from UTC):
+//
+//   MIFID_HFT_TOLERANCE     100 us   high-frequency algorithmic trading
+//   MIFID_ALGO_TOLERANCE      1 ms   other algorithmic trading
+//   CAT_TOLERANCE            50 ms   CAT industry members
+// =============================================================================
+
+use std::{
+    fmt::Write as _,
+    fs, io,
+    net::{ToSocketAddrs, UdpSocket},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    json::json_escape,
+    time::{time_of_day, Date},
+};
+
+pub const MIFID_HFT_TOLERANCE: Duration = Duration::from_micros(100);
+pub const MIFID_ALGO_TOLERANCE: Duration = Duration::from_millis(1);
+pub const CAT_TOLERANCE: Duration = Duration::from_millis(50);
+
+/// Port of a server given without one
+pub const NTP_PORT: u16 = 123;
+
+/// Seconds from 1900 (NTP era 0) to 1970
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+/// Nanoseconds since the Unix epoch, now
+fn now_nanos() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_nanos() as i64)
+}
+
+/// 64-bit NTP timestamp (seconds since 1900, 32-bit fraction) of Unix nanos
+fn to_ntp(nanos: i64) -> u64 {
+    let seconds = (nanos.div_euclid(1_000_000_000) + NTP_UNIX_OFFSET) as u64;
+    let fraction = ((nanos.rem_euclid(1_000_000_000) as u64) << 32) / 1_000_000_000;
+    (seconds << 32) | fraction
+}
+
+fn from_ntp(timestamp: u64) -> i64 {
+    let seconds = (timestamp >> 32) as i64 - NTP_UNIX_OFFSET;
+    let nanos = ((timestamp & 0xffff_ffff) * 1_000_000_000) >> 32;
+    seconds * 1_000_000_000 + nanos as i64
+}
+
+// =============================================================================
+// Samples
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSample {
+    /// Local time of the reply, Unix nanoseconds
+    pub time: i64,
+
+    /// Server minus local clock, nanoseconds
+    pub offset: i64,
+
+    pub round_trip: Duration,
+}
+
+impl ClockSample {
+    /// Largest distance from the server clock consistent with the sample
+    pub fn divergence_bound(&self) -> Duration {
+        Duration::from_nanos(self.offset.unsigned_abs()) + self.round_trip / 2
+    }
+}
+
+/// Query an SNTP server once
+///
+/// # Arguments
+/// * `server` - `HOST` or `HOST:PORT` (default port 123)
+/// * `timeout` - How long to wait for the reply
+///
+/// # Errors
+/// If the server cannot be reached, does not answer in time, or answers
+/// with something other than a valid server reply to this request (a
+/// kiss-o'-death packet included)
+pub fn query_sntp(server: &str, timeout: Duration) -> io::Result<ClockSample> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+    let target = match server.to_socket_addrs() {
+        Ok(mut addresses) => addresses.next(),
+        Err(_) => (server, NTP_PORT).to_socket_addrs()?.next(),
+    }
+    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no address for {server}")))?;
+    let local = if target.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect(target)?;
+
+    // LI 0, version 4, mode 3 (client); the transmit timestamp comes back
+    // as the originate timestamp of the reply
+    let mut request = [0u8; 48];
+    request[0] = 0x23;
+    let t1 = now_nanos();
+    let originate = to_ntp(t1);
+    request[40..48].copy_from_slice(&originate.to_be_bytes());
+    socket.send(&request)?;
+
+    let mut reply = [0u8; 48];
+    let length = socket.recv(&mut reply)?;
+    let t4 = now_nanos();
+
+    let timestamp = |at: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&reply[at..at + 8]);
+        u64::from_be_bytes(bytes)
+    };
+    if length < 48 || reply[0] & 0x07 != 4 {
+        return Err(invalid("not an SNTP server reply"));
+    }
+    if reply[1] == 0 {
+        return Err(invalid("kiss-o'-death from the server"));
+    }
+    if timestamp(24) != originate {
+        return Err(invalid("reply to another request"));
+    }
+
+    let t2 = from_ntp(timestamp(32));
+    let t3 = from_ntp(timestamp(40));
+    let round_trip = (t4 - t1) - (t3 - t2);
+    Ok(ClockSample {
+        time: t4,
+        offset: ((t2 - t1) + (t3 - t4)) / 2,
+        round_trip: Duration::from_nanos(round_trip.max(0) as u64),
+    })
+}
+
+// =============================================================================
+// ClockSyncMonitor
+// =============================================================================
+
+#[derive(Debug, Default)]
+struct Period {
+    /// Unix nanoseconds
+    started: i64,
+    samples: Vec<ClockSample>,
+    failures: u64,
+}
+
+/// Offsets measured against one source, by reporting period
+#[derive(Debug)]
+pub struct ClockSyncMonitor {
+    source: String,
+    tolerance: Duration,
+    period: Mutex<Period>,
+}
+
+impl ClockSyncMonitor {
+    /// # Arguments
+    /// * `source` - SNTP server, `HOST[:PORT]`
+    /// * `tolerance` - Maximum divergence allowed, e.g. MIFID_ALGO_TOLERANCE
+    pub fn new(source: &str, tolerance: Duration) -> Self {
+        Self {
+            source: source.to_string(),
+            tolerance,
+            period: Mutex::new(Period {
+                started: now_nanos(),
+                ..Period::default()
+            }),
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn tolerance(&self) -> Duration {
+        self.tolerance
+    }
+
+    /// Query the source and record the outcome; blocks up to `timeout`
+    pub fn sample(&self, timeout: Duration) -> io::Result<ClockSample> {
+        let result = query_sntp(&self.source, timeout);
+        match &result {
+            Ok(sample) => self.record(*sample),
+            Err(_) => self.period.lock().expect("clock lock poisoned").failures += 1,
+        }
+        result
+    }
+
+    pub fn record(&self, sample: ClockSample) {
+        let mut period = self.period.lock().expect("clock lock poisoned");
+        period.samples.push(sample);
+    }
+
+    /// Report on the current period, which goes on
+    pub fn report(&self) -> ClockSyncReport {
+        let period = self.period.lock().expect("clock lock poisoned");
+        self.make_report(&period, now_nanos())
+    }
+
+    /// Close the current period and report on it; the next one starts now
+    pub fn take_report(&self) -> ClockSyncReport {
+        let mut period = self.period.lock().expect("clock lock poisoned");
+        let now = now_nanos();
+        let report = self.make_report(&period, now);
+        *period = Period {
+            started: now,
+            ..Period::default()
+        };
+        report
+    }
+
+    fn make_report(&self, period: &Period, ended: i64) -> ClockSyncReport {
+        ClockSyncReport {
+            source: self.source.clone(),
+            tolerance: self.tolerance,
+            from: period.started,
+            to: ended,
+            samples: period.samples.clone(),
+            failures: period.failures,
+        }
+    }
+}
+
+// =============================================================================
+// ClockSyncReport
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct ClockSyncReport {
+    pub source: String,
+    pub tolerance: Duration,
+
+    /// Period covered, Unix nanoseconds
+    pub from: i64,
+    pub to: i64,
+
+    pub samples: Vec<ClockSample>,
+
+    /// Queries that got no valid reply
+    pub failures: u64,
+}
+
+impl ClockSyncReport {
+    /// Largest |offset| measured
+    pub fn max_divergence(&self) -> Option<Duration> {
+        self.samples
+            .iter()
+            .map(|x| Duration::from_nanos(x.offset.unsigned_abs()))
+            .max()
+    }
+
+    /// Largest |offset| + round trip / 2, what the tolerance is checked against
+    pub fn max_divergence_bound(&self) -> Option<Duration> {
+        self.samples.iter().map(ClockSample::divergence_bound).max()
+    }
+
+    /// Mean offset, nanoseconds
+    pub fn mean_offset(&self) -> Option<i64> {
+        let sum: i128 = self.samples.iter().map(|x| i128::from(x.offset)).sum();
+        (!self.samples.is_empty()).then(|| (sum / self.samples.len() as i128) as i64)
+    }
+
+    pub fn max_round_trip(&self) -> Option<Duration> {
+        self.samples.iter().map(|x| x.round_trip).max()
+    }
+
+    /// Samples whose divergence bound exceeds the tolerance
+    pub fn breaches(&self) -> Vec<&ClockSample> {
+        self.samples
+            .iter()
+            .filter(|x| x.divergence_bound() > self.tolerance)
+            .collect()
+    }
+
+    /// At least one sample, and none out of tolerance
+    pub fn compliant(&self) -> bool {
+        !self.samples.is_empty() && self.breaches().is_empty()
+    }
+
+    /// For the terminal
+    pub fn to_text(&self) -> String {
+        let stamp = |nanos: i64| {
+            let seconds = nanos.div_euclid(1_000_000_000);
+            format!(
+                "{} {}",
+                Date::from_unix(seconds).to_iso(),
+                time_of_day(seconds)
+            )
+        };
+        let duration = |x: Option<Duration>| x.map_or("-".to_string(), |x| format!("{x:?}"));
+
+        let mut out = String::new();
+        let _ = writeln!(out, "Clock sync against {}", self.source);
+        let _ = writeln!(
+            out,
+            "  period        {} - {} UTC",
+            stamp(self.from),
+            stamp(self.to)
+        );
+        let _ = writeln!(
+            out,
+            "  samples       {} ({} failed queries)",
+            self.samples.len(),
+            self.failures
+        );
+        let _ = writeln!(
+            out,
+            "  mean offset   {}",
+            self.mean_offset()
+                .map_or("-".to_string(), |x| format!("{x} ns"))
+        );
+        let _ = writeln!(out, "  max |offset|  {}", duration(self.max_divergence()));
+        let _ = writeln!(
+            out,
+            "  max bound     {}",
+            duration(self.max_divergence_bound())
+        );
+        let _ = writeln!(out, "  max rtt       {}", duration(self.max_round_trip()));
+        let _ = writeln!(
+            out,
+            "  tolerance     {:?}: {} ({} breaches)",
+            self.tolerance,
+            if self.compliant() {
+                "within"
+            } else {
+                "NOT DEMONSTRATED"
+            },
+            self.breaches().len()
+        );
+        out
+    }
+
+    /// Evidence file: the summary and every sample
+    pub fn to_json(&self) -> String {
+        let nanos =
+            |x: Option<Duration>| x.map_or("null".to_string(), |x| x.as_nanos().to_string());
+        let samples: Vec<String> = self
+            .samples
+            .iter()
+            .map(|x| {
+                format!(
+                    "{{\"time\":{},\"offset_ns\":{},\"round_trip_ns\":{},\"within\":{}}}",
+                    x.time,
+                    x.offset,
+                    x.round_trip.as_nanos(),
+                    x.divergence_bound() <= self.tolerance
+                )
+            })
+            .collect();
+        format!(
+            "{{\"source\":\"{}\",\"from\":{},\"to\":{},\"tolerance_ns\":{},\"samples_count\":{},\
+             \"failures\":{},\"mean_offset_ns\":{},\"max_divergence_ns\":{},\
+             \"max_divergence_bound_ns\":{},\"max_round_trip_ns\":{},\"breaches\":{},\
+             \"compliant\":{},\"samples\":[{}]}}",
+            json_escape(&self.source),
+            self.from,
+            self.to,
+            self.tolerance.as_nanos(),
+            self.samples.len(),
+            self.failures,
+            self.mean_offset()
+                .map_or("null".to_string(), |x| x.to_string()),
+            nanos(self.max_divergence()),
+            nanos(self.max_divergence_bound()),
+            nanos(self.max_round_trip()),
+            self.breaches().len(),
+            self.compliant(),
+            samples.join(",")
+        )
+    }
+
+    /// Write the JSON report to `dir` (created if needed) as
+    /// `clock-sync-YYYYMMDD-HHMMSS.json`, named after the end of the period
+    ///
+    /// # Returns
+    /// The path written
+    pub fn save(&self, dir: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let seconds = self.to.div_euclid(1_000_000_000);
+        let name = format!(
+            "clock-sync-{}-{}.json",
+            Date::from_unix(seconds).to_fix(),
+            time_of_day(seconds).replace(':', "")
+        );
+        let path = dir.join(name);
+        fs::write(&path, self.to_json())?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,912 @@
+// =============================================================================
+// Counterparty Conformance Scenarios
+// =============================================================================
+// Exchanges and brokers certify a new connection by walking it through a list
+// of cases: send this, expect that reply within so long, then send the next
+// message. Scenario files describe those cases; `run` plays them against a
+// live session and produces a pass/fail ConformanceReport.
+//
+//   # Orders are acknowledged, then canceled
+//   timeout 2000
+//
+//   scenario new order, then cancel
+//     send D 11={id} 21=1 55=AAPL 54=1 38=100 40=2 44=1.00 59=0 60={now}
+//     expect 8 11={id} 150=0 39=0 37->order_id
+//     send F 11={id}-C 41={id} 37={order_id} 55=AAPL 54=1 38=100 60={now}
+//     expect 8 within 5000 11={id}-C 150=4 39=4
+//     expect none 3 within 500
+//
+// Lines:
+//
+//   scenario NAME                     starts a case; the steps follow
+//   send MSGTYPE TAG=VALUE...         body fields in order (header tags like
+//                                     PossDupFlag go to the header)
+//   expect MSGTYPE [within MS] PRED.. next message of that type matching
+//                                     every predicate, within MS
+//   expect none MSGTYPE [within MS] PRED..
+//                                     no such message for MS
+//   wait MS                           pause
+//   timeout MS                        default of `within` from here on
+//                                     (5000 at first)
+//
+// Predicates: TAG=V, TAG!=V, TAG<N, TAG>N (numeric), TAG~TEXT (contains),
+// TAG? (present), !TAG (absent); comparisons fail on a missing field.
+// TAG->NAME saves the value as {NAME} for the next steps. Values may be
+// quoted ("two words") and use {id} (fresh per scenario), {now}
+// (UTCTimestamp) and saved names.
+//
+// A scenario stops at its first failing step; the next one starts from a
+// clean inbox. Messages that do not match an `expect` stay in the inbox for
+// the following ones, so heartbeats and unsolicited reports in between do not
+// get in the way.
+//
+// Scenarios find out what a venue rejects by sending; a profile (profile.rs)
+// writes it down, so outbound messages are checked before they are sent.
+// The checks made after every new broker connection come as a built-in
+// battery (certify.rs).
+// =============================================================================
+
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    fmt::{self, Write as _},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{mpsc, Mutex},
+    time::{Duration, Instant},
+};
+
+use quickfix::{send_to_target, FieldMap, Message, QuickFixError};
+
+use crate::{
+    json::json_escape,
+    session::{events::FixMessage, parse_session_label, Direction},
+    time::{time_of_day, unix_now, Date},
+};
+
+pub mod certify;
+pub mod profile;
+
+/// `within` of an expect when the file sets no timeout
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(5_000);
+
+/// Tags that `send` puts in the header rather than the body
+const HEADER_TAGS: [i32; 8] = [43, 50, 57, 97, 115, 116, 122, 128];
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConformanceError {
+    Io(PathBuf, io::Error),
+
+    /// A line of a scenario file could not be read
+    Syntax {
+        line: usize,
+        message: String,
+    },
+
+    /// The file has no scenario
+    Empty,
+}
+
+impl fmt::Display for ConformanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConformanceError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            ConformanceError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+            ConformanceError::Empty => write!(f, "no scenario defined"),
+        }
+    }
+}
+
+impl Error for ConformanceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConformanceError::Io(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// =============================================================================
+// Scenarios
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Contains,
+    Present,
+    Absent,
+
+    /// Always true; saves the value under the predicate's value as name
+    Capture,
+}
+
+/// One condition on a field of a received message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    pub tag: i32,
+    pub op: Op,
+
+    /// Compared value (may hold placeholders), or the name to save under
+    pub value: String,
+}
+
+impl Predicate {
+    /// Whether `actual` (the field, None if missing) satisfies the predicate
+    fn holds(&self, actual: Option<&str>, value: &str) -> bool {
+        let number = |x: &str| x.parse::<f64>().ok();
+        match (self.op, actual) {
+            (Op::Absent, actual) => actual.is_none(),
+            (Op::Capture | Op::Present, actual) => actual.is_some(),
+            (_, None) => false,
+            (Op::Eq, Some(actual)) => actual == value,
+            (Op::Ne, Some(actual)) => actual != value,
+            (Op::Contains, Some(actual)) => actual.contains(value),
+            (Op::Lt, Some(actual)) => number(actual)
+                .zip(number(value))
+                .is_some_and(|(a, b)| a < b),
+            (Op::Gt, Some(actual)) => number(actual)
+                .zip(number(value))
+                .is_some_and(|(a, b)| a > b),
+        }
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tag = self.tag;
+        let value = &self.value;
+        match self.op {
+            Op::Eq => write!(f, "{tag}={value}"),
+            Op::Ne => write!(f, "{tag}!={value}"),
+            Op::Lt => write!(f, "{tag}<{value}"),
+            Op::Gt => write!(f, "{tag}>{value}"),
+            Op::Contains => write!(f, "{tag}~{value}"),
+            Op::Present => write!(f, "{tag}?"),
+            Op::Absent => write!(f, "!{tag}"),
+            Op::Capture => write!(f, "{tag}->{value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expectation {
+    pub msg_type: String,
+    pub within: Duration,
+    pub predicates: Vec<Predicate>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Fields of the message, placeholders not yet filled
+    Send {
+        msg_type: String,
+        fields: Vec<(i32, String)>,
+    },
+    Expect(Expectation),
+    ExpectNone(Expectation),
+    Wait(Duration),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// Line of the scenario file, and its text, for the report
+    pub line: usize,
+    pub text: String,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    pub name: String,
+    pub steps: Vec<Step>,
+}
+
+/// Read a scenario file
+pub fn load(path: &Path) -> Result<Vec<Scenario>, ConformanceError> {
+    let text =
+        fs::read_to_string(path).map_err(|err| ConformanceError::Io(path.to_path_buf(), err))?;
+    parse(&text)
+}
+
+/// Parse scenarios from the text of a scenario file
+pub fn parse(text: &str) -> Result<Vec<Scenario>, ConformanceError> {
+    let mut scenarios: Vec<Scenario> = Vec::new();
+    let mut timeout = DEFAULT_TIMEOUT;
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let syntax = |message: &str| ConformanceError::Syntax {
+            line,
+            message: message.to_string(),
+        };
+        let text = strip_comment(raw).trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        let (keyword, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let rest = rest.trim();
+        let action = match keyword {
+            "scenario" => {
+                if rest.is_empty() {
+                    return Err(syntax("scenario needs a name"));
+                }
+                scenarios.push(Scenario {
+                    name: rest.to_string(),
+                    steps: Vec::new(),
+                });
+                continue;
+            }
+            "timeout" => {
+                timeout = parse_millis(rest).ok_or_else(|| syntax("timeout needs milliseconds"))?;
+                continue;
+            }
+            "wait" => {
+                Action::Wait(parse_millis(rest).ok_or_else(|| syntax("wait needs milliseconds"))?)
+            }
+            "send" => {
+                let tokens = tokenize(rest).map_err(syntax)?;
+                let (msg_type, fields) = tokens
+                    .split_first()
+                    .ok_or_else(|| syntax("send needs a MsgType"))?;
+                let fields = fields
+                    .iter()
+                    .map(|x| {
+                        let (tag, value) = x.split_once('=')?;
+                        Some((tag.parse().ok().filter(|x| *x > 0)?, value.to_string()))
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| syntax("send fields are TAG=VALUE"))?;
+                Action::Send {
+                    msg_type: msg_type.clone(),
+                    fields,
+                }
+            }
+            "expect" => {
+                let mut tokens = tokenize(rest).map_err(syntax)?;
+                let none = tokens.first().is_some_and(|x| x == "none");
+                if none {
+                    tokens.remove(0);
+                }
+                let expectation = parse_expectation(&tokens, timeout).map_err(syntax)?;
+                if none {
+                    Action::ExpectNone(expectation)
+                } else {
+                    Action::Expect(expectation)
+                }
+            }
+            _ => return Err(syntax("expected scenario, send, expect, wait or timeout")),
+        };
+
+        let scenario = scenarios
+            .last_mut()
+            .ok_or_else(|| syntax("step outside of a scenario"))?;
+        scenario.steps.push(Step {
+            line,
+            text: text.to_string(),
+            action,
+        });
+    }
+
+    if scenarios.is_empty() {
+        return Err(ConformanceError::Empty);
+    }
+    Ok(scenarios)
+}
+
+fn parse_expectation(tokens: &[String], timeout: Duration) -> Result<Expectation, &'static str> {
+    let (msg_type, mut rest) = tokens.split_first().ok_or("expect needs a MsgType")?;
+    let mut within = timeout;
+    if rest.first().is_some_and(|x| x == "within") {
+        within = rest
+            .get(1)
+            .and_then(|x| parse_millis(x))
+            .ok_or("within needs milliseconds")?;
+        rest = &rest[2..];
+    }
+
+    let predicates = rest
+        .iter()
+        .map(|x| parse_predicate(x))
+        .collect::<Option<Vec<_>>>()
+        .ok_or("predicates are TAG=V, TAG!=V, TAG<N, TAG>N, TAG~V, TAG?, !TAG or TAG->NAME")?;
+    Ok(Expectation {
+        msg_type: msg_type.clone(),
+        within,
+        predicates,
+    })
+}
+
+fn parse_predicate(token: &str) -> Option<Predicate> {
+    let tag_of = |x: &str| x.parse::<i32>().ok().filter(|x| *x > 0);
+    if let Some(tag) = token.strip_prefix('!') {
+        return Some(Predicate {
+            tag: tag_of(tag)?,
+            op: Op::Absent,
+            value: String::new(),
+        });
+    }
+
+    let digits = token.find(|c: char| !c.is_ascii_digit())?;
+    let (tag, rest) = token.split_at(digits);
+    let (op, value) = [
+        ("->", Op::Capture),
+        ("!=", Op::Ne),
+        ("=", Op::Eq),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+        ("~", Op::Contains),
+        ("?", Op::Present),
+    ]
+    .into_iter()
+    .find_map(|(prefix, op)| Some((op, rest.strip_prefix(prefix)?)))?;
+
+    let valid = match op {
+        Op::Present => value.is_empty(),
+        Op::Capture => !value.is_empty() && value.chars().all(|c| c.is_alphanumeric() || c == '_'),
+        _ => true,
+    };
+    if !valid {
+        return None;
+    }
+    Some(Predicate {
+        tag: tag_of(tag)?,
+        op,
+        value: value.to_string(),
+    })
+}
+
+fn parse_millis(value: &str) -> Option<Duration> {
+    value.trim().parse().ok().map(Duration::from_millis)
+}
+
+/// The line up to a `#` outside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Whitespace separated tokens; double quotes group words and are dropped
+fn tokenize(text: &str) -> Result<Vec<String>, &'static str> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    tokens.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if quoted {
+        return Err("unterminated quote");
+    }
+    if started {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+// =============================================================================
+// Counterparty
+// =============================================================================
+
+/// The session under test, as seen by the runner
+pub trait Counterparty {
+    fn send(&mut self, msg: Message) -> Result<(), QuickFixError>;
+
+    /// Next message received on the session (application or admin), waiting
+    /// up to `timeout`
+    fn receive(&mut self, timeout: Duration) -> Option<FixMessage>;
+}
+
+/// Copies the inbound messages of one session to a runner
+///
+/// Sits in an ApplicationCallback, which offers it every decoded inbound
+/// message; it keeps those of the attached session while a run is going.
+#[derive(Default)]
+pub struct Tap {
+    target: Mutex<Option<(String, mpsc::Sender<FixMessage>)>>,
+}
+
+impl Tap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start copying the messages of `session` (a label); replaces any
+    /// earlier attachment
+    pub fn attach(&self, session: &str) -> mpsc::Receiver<FixMessage> {
+        let (sender, receiver) = mpsc::channel();
+        *self.target.lock().expect("tap lock poisoned") = Some((session.to_string(), sender));
+        receiver
+    }
+
+    pub fn detach(&self) {
+        *self.target.lock().expect("tap lock poisoned") = None;
+    }
+
+    /// Offer a decoded message; cheap when nothing is attached
+    pub fn offer(&self, msg: &FixMessage) {
+        if msg.direction != Direction::Inbound {
+            return;
+        }
+        let target = self.target.lock().expect("tap lock poisoned");
+        if let Some((session, sender)) = target.as_ref() {
+            if *session == msg.session {
+                let _ = sender.send(msg.clone());
+            }
+        }
+    }
+}
+
+/// A live QuickFIX session, fed by a Tap
+pub struct SessionCounterparty {
+    label: String,
+    inbox: mpsc::Receiver<FixMessage>,
+}
+
+impl SessionCounterparty {
+    /// # Arguments
+    /// * `label` - Session under test (`FIX.4.4:SENDER->TARGET`)
+    /// * `inbox` - From `Tap::attach` for the same session
+    pub fn new(label: &str, inbox: mpsc::Receiver<FixMessage>) -> Self {
+        Self {
+            label: label.to_string(),
+            inbox,
+        }
+    }
+}
+
+impl Counterparty for SessionCounterparty {
+    /// The SessionId is made here, on the calling thread: it cannot be moved
+    /// across threads
+    fn send(&mut self, msg: Message) -> Result<(), QuickFixError> {
+        let session = parse_session_label(&self.label).ok_or_else(|| {
+            QuickFixError::InvalidArgument(format!("invalid session {}", self.label))
+        })??;
+        send_to_target(msg, &session)
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Option<FixMessage> {
+        self.inbox.recv_timeout(timeout).ok()
+    }
+}
+
+// =============================================================================
+// Report
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Passed,
+    Failed(String),
+
+    /// An earlier step failed
+    Skipped,
+}
+
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    pub line: usize,
+    pub text: String,
+    pub outcome: Outcome,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScenarioResult {
+    pub name: String,
+    pub steps: Vec<StepResult>,
+    pub elapsed: Duration,
+}
+
+impl ScenarioResult {
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|x| x.outcome == Outcome::Passed)
+    }
+
+    /// The step that failed, if any
+    pub fn failure(&self) -> Option<&StepResult> {
+        self.steps
+            .iter()
+            .find(|x| matches!(x.outcome, Outcome::Failed(_)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConformanceReport {
+    /// Session label of the counterparty
+    pub counterparty: String,
+
+    /// Unix seconds
+    pub started: i64,
+    pub scenarios: Vec<ScenarioResult>,
+}
+
+impl ConformanceReport {
+    pub fn passed(&self) -> bool {
+        self.scenarios.iter().all(ScenarioResult::passed)
+    }
+
+    pub fn passed_count(&self) -> usize {
+        self.scenarios.iter().filter(|x| x.passed()).count()
+    }
+
+    /// Human-readable report: one line per scenario, then the steps of the
+    /// failed ones
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "Conformance report: {}, {} {} UTC",
+            self.counterparty,
+            Date::from_unix(self.started).to_iso(),
+            time_of_day(self.started)
+        );
+        let _ = writeln!(out);
+        for scenario in &self.scenarios {
+            let _ = writeln!(
+                out,
+                "  [{}] {:<40} {:>3} step(s) {:>7} ms",
+                if scenario.passed() { "PASS" } else { "FAIL" },
+                scenario.name,
+                scenario.steps.len(),
+                scenario.elapsed.as_millis()
+            );
+            if let Some(failure) = scenario.failure() {
+                if let Outcome::Failed(reason) = &failure.outcome {
+                    let _ = writeln!(out, "         line {}: {}", failure.line, failure.text);
+                    let _ = writeln!(out, "         {reason}");
+                }
+            }
+        }
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "{} ({}/{} scenarios)",
+            if self.passed() { "PASSED" } else { "FAILED" },
+            self.passed_count(),
+            self.scenarios.len()
+        );
+        out
+    }
+
+    /// `{"counterparty":..,"started":..,"passed":..,"scenarios":[{"name":..,
+    /// "passed":..,"elapsed_ms":..,"steps":[{"line":..,"step":..,
+    /// "outcome":"passed|failed|skipped","reason":..,"elapsed_ms":..}]}]}`
+    pub fn to_json(&self) -> String {
+        let mut scenarios = Vec::new();
+        for scenario in &self.scenarios {
+            let steps: Vec<_> = scenario
+                .steps
+                .iter()
+                .map(|x| {
+                    let (outcome, reason) = match &x.outcome {
+                        Outcome::Passed => ("passed", "null".to_string()),
+                        Outcome::Failed(reason) => {
+                            ("failed", format!("\"{}\"", json_escape(reason)))
+                        }
+                        Outcome::Skipped => ("skipped", "null".to_string()),
+                    };
+                    format!(
+                        "{{\"line\":{},\"step\":\"{}\",\"outcome\":\"{outcome}\",\
+                         \"reason\":{reason},\"elapsed_ms\":{}}}",
+                        x.line,
+                        json_escape(&x.text),
+                        x.elapsed.as_millis()
+                    )
+                })
+                .collect();
+            scenarios.push(format!(
+                "{{\"name\":\"{}\",\"passed\":{},\"elapsed_ms\":{},\"steps\":[{}]}}",
+                json_escape(&scenario.name),
+                scenario.passed(),
+                scenario.elapsed.as_millis(),
+                steps.join(",")
+            ));
+        }
+        format!(
+            "{{\"counterparty\":\"{}\",\"started\":{},\"passed\":{},\"scenarios\":[{}]}}",
+            json_escape(&self.counterparty),
+            self.started,
+            self.passed(),
+            scenarios.join(",")
+        )
+    }
+}
+
+// =============================================================================
+// Runner
+// =============================================================================
+
+/// Play every scenario against the counterparty, in order
+///
+/// # Arguments
+/// * `counterparty` - Session label, for the report
+pub fn run(
+    scenarios: &[Scenario],
+    session: &mut impl Counterparty,
+    counterparty: &str,
+) -> ConformanceReport {
+    let started = unix_now();
+    let scenarios = scenarios
+        .iter()
+        .enumerate()
+        .map(|(index, scenario)| {
+            let id = format!("CT{started}-{}", index + 1);
+            run_scenario(scenario, session, &id)
+        })
+        .collect();
+    ConformanceReport {
+        counterparty: counterparty.to_string(),
+        started,
+        scenarios,
+    }
+}
+
+fn run_scenario(scenario: &Scenario, session: &mut impl Counterparty, id: &str) -> ScenarioResult {
+    let started = Instant::now();
+    let mut variables = HashMap::from([("id".to_string(), id.to_string())]);
+
+    // Leftovers of the previous scenario must not satisfy this one
+    while session.receive(Duration::ZERO).is_some() {}
+    let mut inbox = VecDeque::new();
+
+    let mut failed = false;
+    let mut steps = Vec::with_capacity(scenario.steps.len());
+    for step in &scenario.steps {
+        let step_started = Instant::now();
+        let outcome = if failed {
+            Outcome::Skipped
+        } else {
+            match run_step(&step.action, session, &mut inbox, &mut variables) {
+                Ok(()) => Outcome::Passed,
+                Err(reason) => {
+                    failed = true;
+                    Outcome::Failed(reason)
+                }
+            }
+        };
+        steps.push(StepResult {
+            line: step.line,
+            text: step.text.clone(),
+            outcome,
+            elapsed: step_started.elapsed(),
+        });
+    }
+
+    ScenarioResult {
+        name: scenario.name.clone(),
+        steps,
+        elapsed: started.elapsed(),
+    }
+}
+
+fn run_step(
+    action: &Action,
+    session: &mut impl Counterparty,
+    inbox: &mut VecDeque<FixMessage>,
+    variables: &mut HashMap<String, String>,
+) -> Result<(), String> {
+    match action {
+        Action::Send { msg_type, fields } => {
+            let mut filled = Vec::with_capacity(fields.len());
+            for (tag, value) in fields {
+                filled.push((*tag, substitute(value, variables)?));
+            }
+            let msg =
+                build_message(msg_type, &filled).map_err(|err| format!("cannot build: {err:?}"))?;
+            session
+                .send(msg)
+                .map_err(|err| format!("send failed: {err:?}"))
+        }
+        Action::Expect(expectation) => {
+            let predicates = fill_predicates(expectation, variables)?;
+            let deadline = Instant::now() + expectation.within;
+            loop {
+                let found = inbox
+                    .iter()
+                    .position(|x| matches(x, &expectation.msg_type, &predicates));
+                if let Some(index) = found {
+                    let msg = inbox.remove(index).expect("index in range");
+                    for (predicate, _) in predicates.iter().filter(|(x, _)| x.op == Op::Capture) {
+                        let value = msg.get(predicate.tag).unwrap_or_default();
+                        variables.insert(predicate.value.clone(), value.to_string());
+                    }
+                    return Ok(());
+                }
+
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(timeout_reason(expectation, &predicates, inbox));
+                }
+                if let Some(msg) = session.receive(deadline - now) {
+                    inbox.push_back(msg);
+                }
+            }
+        }
+        Action::ExpectNone(expectation) => {
+            let predicates = fill_predicates(expectation, variables)?;
+            let deadline = Instant::now() + expectation.within;
+            loop {
+                if let Some(msg) = inbox
+                    .iter()
+                    .find(|x| matches(x, &expectation.msg_type, &predicates))
+                {
+                    return Err(format!("unexpected {}", describe(msg)));
+                }
+
+                let now = Instant::now();
+                if now >= deadline {
+                    return Ok(());
+                }
+                if let Some(msg) = session.receive(deadline - now) {
+                    inbox.push_back(msg);
+                }
+            }
+        }
+        Action::Wait(duration) => {
+            let deadline = Instant::now() + *duration;
+            while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+                match session.receive(left) {
+                    Some(msg) => inbox.push_back(msg),
+                    None => break,
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Predicates with their placeholders filled
+fn fill_predicates(
+    expectation: &Expectation,
+    variables: &HashMap<String, String>,
+) -> Result<Vec<(Predicate, String)>, String> {
+    expectation
+        .predicates
+        .iter()
+        .map(|x| {
+            let value = match x.op {
+                Op::Capture => x.value.clone(),
+                _ => substitute(&x.value, variables)?,
+            };
+            Ok((x.clone(), value))
+        })
+        .collect()
+}
+
+fn matches(msg: &FixMessage, msg_type: &str, predicates: &[(Predicate, String)]) -> bool {
+    msg.msg_type() == msg_type
+        && predicates
+            .iter()
+            .all(|(predicate, value)| predicate.holds(msg.get(predicate.tag), value))
+}
+
+/// Why an expect failed: what was expected, and how the last message of
+/// that type differed
+fn timeout_reason(
+    expectation: &Expectation,
+    predicates: &[(Predicate, String)],
+    inbox: &VecDeque<FixMessage>,
+) -> String {
+    let mut reason = format!(
+        "no {} matching the predicates within {} ms",
+        expectation.msg_type,
+        expectation.within.as_millis()
+    );
+    let last = inbox
+        .iter()
+        .rev()
+        .find(|x| x.msg_type() == expectation.msg_type);
+    if let Some(last) = last {
+        let differences: Vec<_> = predicates
+            .iter()
+            .filter(|(predicate, value)| !predicate.holds(last.get(predicate.tag), value))
+            .map(|(predicate, value)| {
+                let actual = last.get(predicate.tag).unwrap_or("<missing>");
+                let expected = Predicate {
+                    value: value.clone(),
+                    ..predicate.clone()
+                };
+                format!("{expected} (got {actual})")
+            })
+            .collect();
+        let _ = write!(
+            reason,
+            "; last {} failed {}",
+            expectation.msg_type,
+            differences.join(", ")
+        );
+    }
+    if let Some(reject) = inbox
+        .iter()
+        .rev()
+        .find(|x| matches!(x.msg_type(), "3" | "j"))
+    {
+        let _ = write!(reason, "; received {}", describe(reject));
+    }
+    reason
+}
+
+/// `35=3 RefSeqNum=.. Text=..`, briefly
+fn describe(msg: &FixMessage) -> String {
+    let mut out = format!("35={}", msg.msg_type());
+    for tag in [11, 39, 150, 45, 371, 373, 380, 58] {
+        if let Some(value) = msg.get(tag) {
+            let _ = write!(out, " {tag}={value}");
+        }
+    }
+    out
+}
+
+/// Fill `{name}` placeholders; `{now}` is the current UTCTimestamp
+fn substitute(value: &str, variables: &HashMap<String, String>) -> Result<String, String> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed placeholder in {value}"))?;
+        let name = &rest[start + 1..start + end];
+        match (name, variables.get(name)) {
+            (_, Some(x)) => out.push_str(x),
+            ("now", None) => {
+                let now = unix_now();
+                let _ = write!(
+                    out,
+                    "{}-{}",
+                    Date::from_unix(now).to_fix(),
+                    time_of_day(now)
+                );
+            }
+            _ => return Err(format!("unknown placeholder {{{name}}}")),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn build_message(msg_type: &str, fields: &[(i32, String)]) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, msg_type))?;
+    for (tag, value) in fields {
+        if HEADER_TAGS.contains(tag) {
+            msg.with_header_mut(|h| h.set_field(*tag, value.as_str()))?;
+        } else {
+            msg.set_field(*tag, value.as_str())?;
+        }
+    }
+    Ok(msg)
+}
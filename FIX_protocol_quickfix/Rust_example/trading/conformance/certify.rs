@@ -0,0 +1,120 @@
+// =============================================================================
+// Session Certification Self-Check
+// =============================================================================
+// The checks run by hand after every new broker connection, as a standard
+// battery of scenarios for the conformance runner:
+//
+//   heartbeat           a Heartbeat (35=0) of the counterparty's own within
+//                       HeartBtInt plus a margin
+//   test request        a TestRequest (35=1) answered by a Heartbeat with
+//                       its TestReqID
+//   resend request      a ResendRequest (35=2) for MsgSeqNum 1, the Logon,
+//                       answered by a SequenceReset-GapFill (35=4) to 2
+//   session reject      a NewOrderSingle without Side refused with a
+//                       Reject (35=3) naming the message, and no 35=8
+//   order round trip    a limit order on the test symbol acknowledged
+//                       (150=0), then canceled (150=4)
+//
+// The order is a buy far from the market (1.00 by default) on a test
+// symbol (ZVZZT, the NASDAQ test security, by default), so that it rests and
+// trades with nothing. Every step is built with the same text as a scenario
+// file (see conformance.rs), so a report of `certify` reads like one of
+// `conformance FILE`.
+// =============================================================================
+
+use std::time::Duration;
+
+use super::{parse, ConformanceError, Scenario};
+
+/// Symbol of the round-trip order when none is given
+pub const DEFAULT_TEST_SYMBOL: &str = "ZVZZT";
+
+/// Wait beyond HeartBtInt for the counterparty's Heartbeat
+const HEARTBEAT_MARGIN: Duration = Duration::from_secs(2);
+
+/// What the battery sends, and how long it waits for a Heartbeat
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertificationSettings {
+    pub symbol: String,
+    pub quantity: f64,
+
+    /// Limit price of the round-trip order
+    pub price: f64,
+
+    /// HeartBtInt agreed at logon
+    pub heartbeat_interval: Duration,
+}
+
+impl Default for CertificationSettings {
+    fn default() -> Self {
+        Self {
+            symbol: DEFAULT_TEST_SYMBOL.to_string(),
+            quantity: 100.0,
+            price: 1.0,
+            heartbeat_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl CertificationSettings {
+    pub fn with_symbol(mut self, symbol: &str) -> Self {
+        self.symbol = symbol.to_string();
+        self
+    }
+
+    pub fn with_quantity(mut self, quantity: f64) -> Self {
+        self.quantity = quantity;
+        self
+    }
+
+    pub fn with_price(mut self, price: f64) -> Self {
+        self.price = price;
+        self
+    }
+
+    pub fn with_heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// The battery as scenario file text
+    pub fn to_text(&self) -> String {
+        let heartbeat = (self.heartbeat_interval + HEARTBEAT_MARGIN).as_millis();
+        let order = format!(
+            "55={} 38={} 40=2 44={} 59=0 60={{now}}",
+            self.symbol, self.quantity, self.price
+        );
+        format!(
+            "timeout 5000\n\
+             \n\
+             scenario heartbeat\n\
+             \x20 expect 0 within {heartbeat} !112\n\
+             \n\
+             scenario test request\n\
+             \x20 send 1 112={{id}}\n\
+             \x20 expect 0 112={{id}}\n\
+             \n\
+             scenario resend request\n\
+             \x20 send 2 7=1 16=1\n\
+             \x20 expect 4 123=Y 36=2\n\
+             \n\
+             scenario session reject\n\
+             \x20 send D 11={{id}} 21=1 {order}\n\
+             \x20 expect 3 45? 373?\n\
+             \x20 expect none 8 within 500 11={{id}}\n\
+             \n\
+             scenario order round trip\n\
+             \x20 send D 11={{id}} 21=1 54=1 {order}\n\
+             \x20 expect 8 11={{id}} 150=0 39=0 37->order_id\n\
+             \x20 send F 11={{id}}-C 41={{id}} 37={{order_id}} 55={} 54=1 38={} 60={{now}}\n\
+             \x20 expect 8 11={{id}}-C 41={{id}} 150=4 39=4\n",
+            self.symbol, self.quantity
+        )
+    }
+
+    /// The battery, ready for `conformance::run`; a syntax error if the
+    /// symbol holds a quote or a `#`
+    pub fn scenarios(&self) -> Result<Vec<Scenario>, ConformanceError> {
+        parse(&self.to_text())
+    }
+}
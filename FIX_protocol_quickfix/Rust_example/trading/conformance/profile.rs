@@ -0,0 +1,417 @@
+// =============================================================================
+// Venue Conformance Profiles
+// =============================================================================
+// What a venue accepts, written down so an order it would reject is caught
+// before it is sent, with a message saying why, rather than coming back as
+// a 35=3 or 35=8 150=8 "unsupported TimeInForce" from the other side:
+//
+//   # Example ECN: limit and market orders, day and IOC only
+//   name = "Example ECN"
+//   target = "ECN"                  # TargetCompID; all sessions if absent
+//
+//   [orders]
+//   ord_types = "Market, Limit"     # 40, values or names
+//   time_in_force = "Day, Immediate or cancel"
+//   max_order_qty = 100000          # 38
+//   price_decimals = 2              # 44 and 99
+//   qty_decimals = 0                # 38
+//
+//   # Tags each MsgType must carry, by number or name
+//   [required]
+//   D = "ClOrdID, HandlInst, Symbol, Side, OrderQty, OrdType"
+//   F = "11, 41, 55, 54, 38"
+//
+// Values, MsgTypes and tags are looked up in the tag dictionary, so a venue
+// dictionary file makes its own values and fields usable here. A rule the
+// profile leaves out is not checked. An order without TimeInForce is a Day
+// order, and is checked as one.
+//
+// The check runs on the message as the application builds it: the header
+// fields the engine fills in on the way out (MsgSeqNum, SendingTime,
+// CompIDs) are not there yet and are never required. Only this subset of
+// TOML is read: tables, `key = value` lines and `#` comments.
+// =============================================================================
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use quickfix::Message;
+
+use crate::session::dictionary::Dictionary;
+
+/// Tags holding a price, for price_decimals
+const PRICE_TAGS: [i32; 2] = [44, 99];
+
+/// Tags holding a quantity, for qty_decimals
+const QTY_TAGS: [i32; 1] = [38];
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+/// A profile file that could not be loaded
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ProfileError {
+    Io(PathBuf, io::Error),
+
+    /// A line of a profile file could not be read
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            ProfileError::Syntax {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+        }
+    }
+}
+
+impl Error for ProfileError {}
+
+/// Something in an outbound message the venue does not accept
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ProfileViolation {
+    /// An OrdType (40) the venue does not take
+    OrdType {
+        value: String,
+        accepted: Vec<String>,
+    },
+
+    /// A TimeInForce (59) the venue does not take; "0" when absent
+    TimeInForce {
+        value: String,
+        accepted: Vec<String>,
+    },
+
+    /// OrderQty (38) over the venue's maximum
+    MaxOrderQty { quantity: f64, max: f64 },
+
+    /// A price or quantity with more decimals than the venue takes
+    Precision {
+        tag: i32,
+        value: String,
+        decimals: u32,
+    },
+
+    /// A tag the venue requires on this MsgType
+    MissingTag { msg_type: String, tag: i32 },
+}
+
+impl fmt::Display for ProfileViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileViolation::OrdType { value, accepted } => write!(
+                f,
+                "OrdType 40={value} is not accepted (only {})",
+                accepted.join(", ")
+            ),
+            ProfileViolation::TimeInForce { value, accepted } => write!(
+                f,
+                "TimeInForce 59={value} is not accepted (only {})",
+                accepted.join(", ")
+            ),
+            ProfileViolation::MaxOrderQty { quantity, max } => {
+                write!(f, "OrderQty 38={quantity} is over the maximum of {max}")
+            }
+            ProfileViolation::Precision {
+                tag,
+                value,
+                decimals,
+            } => write!(f, "{tag}={value} has more than {decimals} decimals"),
+            ProfileViolation::MissingTag { msg_type, tag } => {
+                write!(f, "tag {tag} is required on 35={msg_type}")
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Profile
+// =============================================================================
+
+/// The rules of one venue; a rule left None is not checked
+#[derive(Debug, Clone, Default)]
+pub struct ConformanceProfile {
+    pub name: String,
+
+    /// TargetCompID of the sessions it applies to; None for all
+    pub target: Option<String>,
+
+    /// OrdType values accepted
+    pub ord_types: Option<Vec<String>>,
+
+    /// TimeInForce values accepted
+    pub time_in_force: Option<Vec<String>>,
+    pub max_order_qty: Option<f64>,
+    pub price_decimals: Option<u32>,
+    pub qty_decimals: Option<u32>,
+
+    /// Tags required, by MsgType
+    pub required: Vec<(String, Vec<i32>)>,
+}
+
+impl ConformanceProfile {
+    /// A profile checking nothing yet
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Read a profile file, its values and tags looked up in `dictionary`
+    pub fn load(path: &Path, dictionary: &Dictionary) -> Result<Self, ProfileError> {
+        let source =
+            fs::read_to_string(path).map_err(|err| ProfileError::Io(path.to_path_buf(), err))?;
+        Self::parse(path, &source, dictionary)
+    }
+
+    /// Read the source of a profile file (`path` is for errors, and the
+    /// name when the file sets none)
+    pub fn parse(path: &Path, source: &str, dictionary: &Dictionary) -> Result<Self, ProfileError> {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut profile = Self::new(&name);
+        let mut table = None;
+        for (index, line) in source.lines().enumerate() {
+            let syntax = |message: String| ProfileError::Syntax {
+                path: path.to_path_buf(),
+                line: index + 1,
+                message,
+            };
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                table = match header.strip_suffix(']').map(str::trim) {
+                    Some(name @ ("orders" | "required")) => Some(name),
+                    _ => return Err(syntax("expected [orders] or [required]".to_string())),
+                };
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| syntax("expected key = value".to_string()))?;
+            let (key, value) = (key.trim().trim_matches('"'), value.trim().trim_matches('"'));
+            let values = |tag: i32| {
+                list(value)
+                    .map(|x| {
+                        let resolved = dictionary.resolve_value(tag, x);
+                        match dictionary.validate(tag, resolved) {
+                            Ok(()) => Ok(resolved.to_string()),
+                            Err(err) => Err(syntax(format!("{x}: {err}"))),
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            let decimals = || {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| syntax(format!("{key} must be a number of decimals")))
+            };
+
+            match (table, key) {
+                (None, "name") => profile.name = value.to_string(),
+                (None, "target") => profile.target = Some(value.to_string()),
+                (Some("orders"), "ord_types") => profile.ord_types = Some(values(40)?),
+                (Some("orders"), "time_in_force") => profile.time_in_force = Some(values(59)?),
+                (Some("orders"), "max_order_qty") => {
+                    let max = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|x| *x > 0.0)
+                        .ok_or_else(|| syntax(format!("invalid max_order_qty {value}")))?;
+                    profile.max_order_qty = Some(max);
+                }
+                (Some("orders"), "price_decimals") => profile.price_decimals = Some(decimals()?),
+                (Some("orders"), "qty_decimals") => profile.qty_decimals = Some(decimals()?),
+                (Some("required"), _) => {
+                    let msg_type = dictionary.resolve_value(35, key).to_string();
+                    if msg_type.len() > 2 {
+                        return Err(syntax(format!("unknown MsgType {key}")));
+                    }
+                    let tags = list(value)
+                        .map(|x| {
+                            dictionary
+                                .resolve(x)
+                                .ok_or_else(|| syntax(format!("unknown tag or field name {x}")))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    profile = profile.with_required(&msg_type, &tags);
+                }
+                _ => return Err(syntax(format!("unknown key {key}"))),
+            }
+        }
+        Ok(profile)
+    }
+
+    pub fn with_target(mut self, target_comp_id: &str) -> Self {
+        self.target = Some(target_comp_id.to_string());
+        self
+    }
+
+    pub fn with_ord_types(mut self, values: &[&str]) -> Self {
+        self.ord_types = Some(values.iter().map(|x| x.to_string()).collect());
+        self
+    }
+
+    pub fn with_time_in_force(mut self, values: &[&str]) -> Self {
+        self.time_in_force = Some(values.iter().map(|x| x.to_string()).collect());
+        self
+    }
+
+    pub fn with_max_order_qty(mut self, max: f64) -> Self {
+        self.max_order_qty = Some(max);
+        self
+    }
+
+    pub fn with_price_decimals(mut self, decimals: u32) -> Self {
+        self.price_decimals = Some(decimals);
+        self
+    }
+
+    pub fn with_qty_decimals(mut self, decimals: u32) -> Self {
+        self.qty_decimals = Some(decimals);
+        self
+    }
+
+    /// Require tags on a MsgType, on top of those already required
+    pub fn with_required(mut self, msg_type: &str, tags: &[i32]) -> Self {
+        match self.required.iter_mut().find(|(x, _)| x == msg_type) {
+            Some((_, required)) => {
+                for tag in tags {
+                    if !required.contains(tag) {
+                        required.push(*tag);
+                    }
+                }
+            }
+            None => self.required.push((msg_type.to_string(), tags.to_vec())),
+        }
+        self
+    }
+
+    /// Whether the profile applies to the sessions with this TargetCompID
+    pub fn applies_to(&self, target_comp_id: &str) -> bool {
+        self.target.as_ref().is_none_or(|x| x == target_comp_id)
+    }
+
+    /// What a message about to be sent breaks of the profile
+    pub fn check(&self, msg: &Message) -> Vec<ProfileViolation> {
+        let text = msg.to_fix_string().unwrap_or_default();
+        let fields: Vec<(i32, &str)> = text
+            .split('\x01')
+            .filter_map(|x| x.split_once('='))
+            .filter_map(|(tag, value)| Some((tag.parse().ok()?, value)))
+            .collect();
+        self.check_fields(&fields)
+    }
+
+    /// What the fields of a message, header included, break of the profile
+    pub fn check_fields(&self, fields: &[(i32, &str)]) -> Vec<ProfileViolation> {
+        let get = |tag: i32| {
+            fields
+                .iter()
+                .find(|(x, _)| *x == tag)
+                .map(|(_, value)| *value)
+        };
+        let mut violations = Vec::new();
+
+        if let (Some(accepted), Some(value)) = (&self.ord_types, get(40)) {
+            if !accepted.iter().any(|x| x == value) {
+                violations.push(ProfileViolation::OrdType {
+                    value: value.to_string(),
+                    accepted: accepted.clone(),
+                });
+            }
+        }
+        // Day when absent, for an order
+        let time_in_force = get(59).or(get(40).map(|_| "0"));
+        if let (Some(accepted), Some(value)) = (&self.time_in_force, time_in_force) {
+            if !accepted.iter().any(|x| x == value) {
+                violations.push(ProfileViolation::TimeInForce {
+                    value: value.to_string(),
+                    accepted: accepted.clone(),
+                });
+            }
+        }
+        let quantity = get(38).and_then(|x| x.parse::<f64>().ok());
+        if let (Some(max), Some(quantity)) = (self.max_order_qty, quantity) {
+            if quantity > max {
+                violations.push(ProfileViolation::MaxOrderQty { quantity, max });
+            }
+        }
+
+        let precision = [
+            (&PRICE_TAGS[..], self.price_decimals),
+            (&QTY_TAGS[..], self.qty_decimals),
+        ];
+        for (tags, decimals) in precision {
+            let Some(decimals) = decimals else {
+                continue;
+            };
+            for &(tag, value) in fields.iter().filter(|(x, _)| tags.contains(x)) {
+                if decimal_places(value) > decimals {
+                    violations.push(ProfileViolation::Precision {
+                        tag,
+                        value: value.to_string(),
+                        decimals,
+                    });
+                }
+            }
+        }
+
+        if let Some(msg_type) = get(35) {
+            let required = self.required.iter().filter(|(x, _)| x == msg_type);
+            for &tag in required.flat_map(|(_, tags)| tags) {
+                if get(tag).is_none() {
+                    violations.push(ProfileViolation::MissingTag {
+                        msg_type: msg_type.to_string(),
+                        tag,
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// Digits after the decimal point, trailing zeros aside
+fn decimal_places(value: &str) -> u32 {
+    value
+        .split_once('.')
+        .map_or(0, |(_, frac)| frac.trim_end_matches('0').len() as u32)
+}
+
+/// Items of a comma-separated list, trimmed
+fn list(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').map(str::trim).filter(|x| !x.is_empty())
+}
+
+/// A line without its `#` comment (outside quotes)
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
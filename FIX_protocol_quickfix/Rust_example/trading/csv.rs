@@ -0,0 +1,16 @@
+// =============================================================================
+// CSV Helpers
+// =============================================================================
+// The blotter, the trade capture reports, the end-of-day files and the REPL
+// exports are written as CSV. The one helper they share lives in the core,
+// next to json_escape, so the OMS builds without the gateway feature.
+// =============================================================================
+
+/// Quote a field holding a comma, a quote or a line break
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,813 @@
+// =============================================================================
+// Message Expressions
+// =============================================================================
+// Conditions on a FIX message written as text, so alert, routing and
+// transform rules can live in configuration rather than in code:
+//
+//   msg.35 == "8" && msg.150 == "8" && qty > 10000
+//   msg.MsgType in ["D", "G"] && notional >= 1e6
+//   msg.58 contains "throttle" || !(side == "buy")
+//
+// Operands
+//   msg.TAG, msg.Name   a field of the message, by tag or dictionary name;
+//                       null when absent
+//   session, direction  the session label, "in" or "out"
+//   msg_type, symbol    35 and 55
+//   side                "buy" / "sell" (54), as sent otherwise
+//   qty, price          OrderQty (38) else LastQty (32), Price (44) else
+//                       LastPx (31)
+//   notional            qty * price
+//   "text", 'text', 12.5, true, false, null
+//
+// Operators, loosest first: ||, &&, !, comparisons (== != < <= > >=, in
+// [..], contains), + -, * /, unary -. A field compared with a number is
+// read as one: msg.38 > 100 is a numeric comparison. A comparison with null
+// is false, but == null and != null; arithmetic on null is null.
+// Expressions nest at most 64 deep, parentheses and chains of operators
+// alike: `a || b || ...` stops short of 64 terms, `in` takes any number.
+//
+// A rules file names its expressions, one per line, `#` for comments:
+//
+//   large_order = msg.35 == "D" && qty > 10000
+//   reject_text = msg.35 == "8" && msg.150 == "8" && msg.58 contains "limit"
+// =============================================================================
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::session::{dictionary::Dictionary, events::FixMessage, Direction};
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+/// An expression or a rules file that could not be read
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ExprError {
+    /// What was expected, at a character position of the expression
+    Syntax {
+        position: usize,
+        message: String,
+    },
+
+    /// `msg.X` with X neither a tag nor a field name of the dictionary
+    UnknownField(String),
+
+    /// A name that is not one of VARIABLES
+    UnknownVariable(String),
+
+    Io(PathBuf, io::Error),
+
+    /// A line of a rules file
+    Rule {
+        line: usize,
+        source: Box<ExprError>,
+    },
+
+    /// Two rules of a file with the same name
+    DuplicateRule(String),
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::Syntax { position, message } => {
+                write!(f, "{message} at position {position}")
+            }
+            ExprError::UnknownField(name) => write!(f, "unknown field msg.{name}"),
+            ExprError::UnknownVariable(name) => write!(
+                f,
+                "unknown name {name} (expected msg.TAG or one of {})",
+                VARIABLES.join(", ")
+            ),
+            ExprError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            ExprError::Rule { line, source } => write!(f, "line {line}: {source}"),
+            ExprError::DuplicateRule(name) => write!(f, "rule {name} defined twice"),
+        }
+    }
+}
+
+impl Error for ExprError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExprError::Io(_, err) => Some(err),
+            ExprError::Rule { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+// =============================================================================
+// Values
+// =============================================================================
+
+/// What an expression evaluates to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl Value {
+    /// Whether a rule with this result matches: true, a number other than
+    /// 0, a non-empty text
+    pub fn is_true(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(x) => *x,
+            Value::Number(x) => *x != 0.0,
+            Value::Text(x) => !x.is_empty(),
+        }
+    }
+
+    /// A number, or a text that reads as one
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(x) => Some(*x),
+            Value::Text(x) => x.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Null, _) | (_, Value::Null) => false,
+            (Value::Text(a), Value::Text(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(_), _) | (_, Value::Number(_)) => {
+                match (self.as_number(), other.as_number()) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Numbers (or a number and a numeric text) by value, texts in
+    /// lexicographic order; None for anything else
+    fn compare(&self, other: &Value) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (Value::Text(a), Value::Text(b)) => match (self.as_number(), other.as_number()) {
+                (Some(a), Some(b)) => a.partial_cmp(&b),
+                _ => Some(a.cmp(b)),
+            },
+            _ => self.as_number()?.partial_cmp(&other.as_number()?),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(x) => write!(f, "{x}"),
+            Value::Number(x) => write!(f, "{x}"),
+            Value::Text(x) => write!(f, "{x:?}"),
+        }
+    }
+}
+
+// =============================================================================
+// Syntax Tree
+// =============================================================================
+
+/// Names an expression can use besides msg.TAG
+pub const VARIABLES: [&str; 8] = [
+    "session",
+    "direction",
+    "msg_type",
+    "symbol",
+    "side",
+    "qty",
+    "price",
+    "notional",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variable {
+    Session,
+    Direction,
+    MsgType,
+    Symbol,
+    Side,
+    Qty,
+    Price,
+    Notional,
+}
+
+impl Variable {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "session" => Some(Variable::Session),
+            "direction" => Some(Variable::Direction),
+            "msg_type" => Some(Variable::MsgType),
+            "symbol" => Some(Variable::Symbol),
+            "side" => Some(Variable::Side),
+            "qty" => Some(Variable::Qty),
+            "price" => Some(Variable::Price),
+            "notional" => Some(Variable::Notional),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Literal(Value),
+    Field(i32),
+    Variable(Variable),
+    Not(Box<Node>),
+    Negate(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    In(Box<Node>, Vec<Node>),
+}
+
+// =============================================================================
+// Expr
+// =============================================================================
+
+/// A parsed expression, evaluated against decoded messages
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    text: String,
+    root: Node,
+}
+
+impl Expr {
+    /// Parse an expression, field names from the standard dictionary
+    pub fn parse(text: &str) -> Result<Self, ExprError> {
+        Self::parse_with(text, &Dictionary::standard())
+    }
+
+    /// Parse an expression, field names from this dictionary (venue fields
+    /// included)
+    pub fn parse_with(text: &str, dictionary: &Dictionary) -> Result<Self, ExprError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            index: 0,
+            end: text.chars().count(),
+            dictionary,
+            nesting: 0,
+        };
+        let (root, _) = parser.or()?;
+        if let Some((position, token)) = parser.tokens.get(parser.index) {
+            return Err(ExprError::Syntax {
+                position: *position,
+                message: format!("unexpected {token}"),
+            });
+        }
+        Ok(Self {
+            text: text.trim().to_string(),
+            root,
+        })
+    }
+
+    /// The value of the expression for a message
+    pub fn eval(&self, msg: &FixMessage) -> Value {
+        eval(&self.root, msg)
+    }
+
+    /// Whether the expression is true for a message
+    pub fn matches(&self, msg: &FixMessage) -> bool {
+        self.eval(msg).is_true()
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+fn eval(node: &Node, msg: &FixMessage) -> Value {
+    let number = |x: Option<f64>| x.map_or(Value::Null, Value::Number);
+    let field_number = |tag| msg.get(tag).and_then(|x| x.trim().parse::<f64>().ok());
+    let qty = || field_number(38).or_else(|| field_number(32));
+    let price = || field_number(44).or_else(|| field_number(31));
+    match node {
+        Node::Literal(value) => value.clone(),
+        Node::Field(tag) => msg
+            .get(*tag)
+            .map_or(Value::Null, |x| Value::Text(x.to_string())),
+        Node::Variable(variable) => match variable {
+            Variable::Session => Value::Text(msg.session.clone()),
+            Variable::Direction => Value::Text(
+                match msg.direction {
+                    Direction::Inbound => "in",
+                    Direction::Outbound => "out",
+                }
+                .to_string(),
+            ),
+            Variable::MsgType => Value::Text(msg.msg_type().to_string()),
+            Variable::Symbol => msg
+                .get(55)
+                .map_or(Value::Null, |x| Value::Text(x.to_string())),
+            Variable::Side => match msg.get(54) {
+                Some("1") => Value::Text("buy".to_string()),
+                Some("2") => Value::Text("sell".to_string()),
+                Some(other) => Value::Text(other.to_string()),
+                None => Value::Null,
+            },
+            Variable::Qty => number(qty()),
+            Variable::Price => number(price()),
+            Variable::Notional => number(qty().zip(price()).map(|(q, p)| q * p)),
+        },
+        Node::Not(inner) => Value::Bool(!eval(inner, msg).is_true()),
+        Node::Negate(inner) => number(eval(inner, msg).as_number().map(|x| -x)),
+        Node::Binary(BinaryOp::Or, left, right) => {
+            Value::Bool(eval(left, msg).is_true() || eval(right, msg).is_true())
+        }
+        Node::Binary(BinaryOp::And, left, right) => {
+            Value::Bool(eval(left, msg).is_true() && eval(right, msg).is_true())
+        }
+        Node::Binary(op, left, right) => {
+            let (left, right) = (eval(left, msg), eval(right, msg));
+            let arithmetic = |f: fn(f64, f64) -> f64| {
+                number(
+                    left.as_number()
+                        .zip(right.as_number())
+                        .map(|(a, b)| f(a, b)),
+                )
+            };
+            let ordered = |f: fn(std::cmp::Ordering) -> bool| {
+                Value::Bool(left.compare(&right).is_some_and(f))
+            };
+            match op {
+                BinaryOp::Eq => Value::Bool(left.equals(&right)),
+                BinaryOp::Ne => Value::Bool(!left.equals(&right)),
+                BinaryOp::Lt => ordered(|x| x.is_lt()),
+                BinaryOp::Le => ordered(|x| x.is_le()),
+                BinaryOp::Gt => ordered(|x| x.is_gt()),
+                BinaryOp::Ge => ordered(|x| x.is_ge()),
+                BinaryOp::Contains => Value::Bool(match (&left, &right) {
+                    (Value::Text(a), Value::Text(b)) => a.contains(b.as_str()),
+                    _ => false,
+                }),
+                BinaryOp::Add => arithmetic(|a, b| a + b),
+                BinaryOp::Sub => arithmetic(|a, b| a - b),
+                BinaryOp::Mul => arithmetic(|a, b| a * b),
+                BinaryOp::Div => number(
+                    left.as_number()
+                        .zip(right.as_number())
+                        .and_then(|(a, b)| (b != 0.0).then(|| a / b)),
+                ),
+                BinaryOp::Or | BinaryOp::And => unreachable!("short-circuited above"),
+            }
+        }
+        Node::In(left, list) => {
+            let left = eval(left, msg);
+            Value::Bool(list.iter().any(|x| left.equals(&eval(x, msg))))
+        }
+    }
+}
+
+// =============================================================================
+// Tokenizer
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Name(String),
+    /// `msg.` and what follows the dot
+    Field(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(x) => write!(f, "{x}"),
+            Token::Text(x) => write!(f, "{x:?}"),
+            Token::Name(x) => write!(f, "{x}"),
+            Token::Field(x) => write!(f, "msg.{x}"),
+            Token::Symbol(x) => write!(f, "'{x}'"),
+        }
+    }
+}
+
+/// Longest first, so `<=` is not read as `<`
+const SYMBOLS: [&str; 18] = [
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "(", ")", "[", "]", ",", "+", "-", "*", "/",
+];
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+    let chars: Vec<char> = text.chars().collect();
+    let syntax = |position, message: &str| ExprError::Syntax {
+        position,
+        message: message.to_string(),
+    };
+    let word_end = |from: usize| {
+        (from..chars.len())
+            .find(|&i| !(chars[i].is_ascii_alphanumeric() || chars[i] == '_'))
+            .unwrap_or(chars.len())
+    };
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '"' || c == '\'' {
+            let start = i;
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(syntax(start, "unterminated string")),
+                    Some('\\') if i + 1 < chars.len() => {
+                        value.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(&x) if x == c => {
+                        i += 1;
+                        break;
+                    }
+                    Some(&x) => {
+                        value.push(x);
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push((start, Token::Text(value)));
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                // An exponent may carry a sign: 1e-6
+                if matches!(chars[i], 'e' | 'E') && matches!(chars.get(i + 1), Some('-' | '+')) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            let literal: String = chars[start..i].iter().collect();
+            let number = literal
+                .parse()
+                .map_err(|_| syntax(start, &format!("invalid number {literal}")))?;
+            tokens.push((start, Token::Number(number)));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            i = word_end(i);
+            let word: String = chars[start..i].iter().collect();
+            if word == "msg" && chars.get(i) == Some(&'.') {
+                let end = word_end(i + 1);
+                if end == i + 1 {
+                    return Err(syntax(i + 1, "expected a tag or field name after msg."));
+                }
+                tokens.push((start, Token::Field(chars[i + 1..end].iter().collect())));
+                i = end;
+            } else {
+                tokens.push((start, Token::Name(word)));
+            }
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = SYMBOLS
+                .into_iter()
+                .find(|x| rest.starts_with(x))
+                .ok_or_else(|| syntax(i, &format!("unexpected character {c:?}")))?;
+            tokens.push((i, Token::Symbol(symbol)));
+            i += symbol.len();
+        }
+    }
+    Ok(tokens)
+}
+
+// =============================================================================
+// Parser
+// =============================================================================
+
+/// Nesting deeper than this is refused rather than recursed into, when
+/// parsing and when evaluating
+const MAX_DEPTH: usize = 64;
+
+/// A node and the depth of its tree
+type Parsed = Result<(Node, usize), ExprError>;
+
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+
+    /// Position reported when the expression ends too early
+    end: usize,
+    dictionary: &'a Dictionary,
+
+    /// `(`, `!` and unary `-` being parsed
+    nesting: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.index).map_or(self.end, |(x, _)| *x)
+    }
+
+    /// Consume the next token if it is this symbol or keyword
+    fn eat(&mut self, expected: &str) -> bool {
+        let found = match self.peek() {
+            Some(Token::Symbol(x)) => *x == expected,
+            Some(Token::Name(x)) => x == expected,
+            _ => false,
+        };
+        if found {
+            self.index += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), ExprError> {
+        if self.eat(expected) {
+            return Ok(());
+        }
+        let found = self.peek().map_or("the end".to_string(), |x| x.to_string());
+        Err(ExprError::Syntax {
+            position: self.position(),
+            message: format!("expected '{expected}', found {found}"),
+        })
+    }
+
+    fn too_deep(&self) -> ExprError {
+        ExprError::Syntax {
+            position: self.position(),
+            message: format!("expression nested deeper than {MAX_DEPTH}"),
+        }
+    }
+
+    /// A node over children of this depth, refused past MAX_DEPTH
+    fn node(&self, node: Node, children: usize) -> Parsed {
+        if children >= MAX_DEPTH {
+            return Err(self.too_deep());
+        }
+        Ok((node, children + 1))
+    }
+
+    fn binary(&self, op: BinaryOp, (left, l): (Node, usize), (right, r): (Node, usize)) -> Parsed {
+        self.node(Node::Binary(op, Box::new(left), Box::new(right)), l.max(r))
+    }
+
+    /// What a `(`, `!` or unary `-` opens, the recursion bounded as the
+    /// tree is
+    fn nested(&mut self, parse: fn(&mut Self) -> Parsed) -> Parsed {
+        if self.nesting >= MAX_DEPTH {
+            return Err(self.too_deep());
+        }
+        self.nesting += 1;
+        let parsed = parse(self);
+        self.nesting -= 1;
+        parsed
+    }
+
+    fn or(&mut self) -> Parsed {
+        let mut node = self.and()?;
+        while self.eat("||") {
+            let right = self.and()?;
+            node = self.binary(BinaryOp::Or, node, right)?;
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Parsed {
+        let mut node = self.not()?;
+        while self.eat("&&") {
+            let right = self.not()?;
+            node = self.binary(BinaryOp::And, node, right)?;
+        }
+        Ok(node)
+    }
+
+    fn not(&mut self) -> Parsed {
+        if self.eat("!") {
+            let (inner, depth) = self.nested(Self::not)?;
+            return self.node(Node::Not(Box::new(inner)), depth);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Parsed {
+        let left = self.sum()?;
+        if self.eat("in") {
+            self.expect("[")?;
+            let (left, mut depth) = left;
+            let mut list = Vec::new();
+            if !self.eat("]") {
+                loop {
+                    let (item, item_depth) = self.sum()?;
+                    list.push(item);
+                    depth = depth.max(item_depth);
+                    if self.eat("]") {
+                        break;
+                    }
+                    self.expect(",")?;
+                }
+            }
+            return self.node(Node::In(Box::new(left), list), depth);
+        }
+
+        let op = [
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+            ("<=", BinaryOp::Le),
+            (">=", BinaryOp::Ge),
+            ("<", BinaryOp::Lt),
+            (">", BinaryOp::Gt),
+            ("contains", BinaryOp::Contains),
+        ]
+        .into_iter()
+        .find(|(symbol, _)| self.eat(symbol));
+        match op {
+            Some((_, op)) => {
+                let right = self.sum()?;
+                self.binary(op, left, right)
+            }
+            None => Ok(left),
+        }
+    }
+
+    fn sum(&mut self) -> Parsed {
+        let mut node = self.product()?;
+        loop {
+            let op = if self.eat("+") {
+                BinaryOp::Add
+            } else if self.eat("-") {
+                BinaryOp::Sub
+            } else {
+                return Ok(node);
+            };
+            let right = self.product()?;
+            node = self.binary(op, node, right)?;
+        }
+    }
+
+    fn product(&mut self) -> Parsed {
+        let mut node = self.unary()?;
+        loop {
+            let op = if self.eat("*") {
+                BinaryOp::Mul
+            } else if self.eat("/") {
+                BinaryOp::Div
+            } else {
+                return Ok(node);
+            };
+            let right = self.unary()?;
+            node = self.binary(op, node, right)?;
+        }
+    }
+
+    fn unary(&mut self) -> Parsed {
+        if self.eat("-") {
+            let (inner, depth) = self.nested(Self::unary)?;
+            return self.node(Node::Negate(Box::new(inner)), depth);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Parsed {
+        let position = self.position();
+        let Some(token) = self.peek().cloned() else {
+            return Err(ExprError::Syntax {
+                position,
+                message: "unexpected end of expression".to_string(),
+            });
+        };
+        self.index += 1;
+        let node = match token {
+            Token::Number(x) => Ok(Node::Literal(Value::Number(x))),
+            Token::Text(x) => Ok(Node::Literal(Value::Text(x))),
+            Token::Field(name) => self
+                .dictionary
+                .resolve(&name)
+                .map(Node::Field)
+                .ok_or(ExprError::UnknownField(name)),
+            Token::Name(name) => match name.as_str() {
+                "true" => Ok(Node::Literal(Value::Bool(true))),
+                "false" => Ok(Node::Literal(Value::Bool(false))),
+                "null" => Ok(Node::Literal(Value::Null)),
+                _ => Variable::from_name(&name)
+                    .map(Node::Variable)
+                    .ok_or(ExprError::UnknownVariable(name)),
+            },
+            Token::Symbol("(") => {
+                let parsed = self.nested(Self::or)?;
+                self.expect(")")?;
+                return Ok(parsed);
+            }
+            Token::Symbol(symbol) => Err(ExprError::Syntax {
+                position,
+                message: format!("unexpected '{symbol}'"),
+            }),
+        };
+        Ok((node?, 1))
+    }
+}
+
+// =============================================================================
+// Rules
+// =============================================================================
+
+/// An expression with a name, as alerts and logs show it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    pub expr: Expr,
+}
+
+impl Rule {
+    /// Parse `NAME = EXPRESSION`; the name is letters, digits, `_` and `-`
+    pub fn parse_with(line: &str, dictionary: &Dictionary) -> Result<Self, ExprError> {
+        let (name, expr) = line
+            .split_once('=')
+            .map(|(name, expr)| (name.trim(), expr))
+            .filter(|(name, expr)| {
+                !name.is_empty()
+                    && !expr.starts_with('=')
+                    && name
+                        .chars()
+                        .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-')
+            })
+            .ok_or_else(|| ExprError::Syntax {
+                position: 0,
+                message: "expected NAME = EXPRESSION".to_string(),
+            })?;
+        Ok(Self {
+            name: name.to_string(),
+            expr: Expr::parse_with(expr, dictionary)?,
+        })
+    }
+
+    pub fn parse(line: &str) -> Result<Self, ExprError> {
+        Self::parse_with(line, &Dictionary::standard())
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.name, self.expr)
+    }
+}
+
+/// Parse the rules of a file's text: one per line, blank lines and lines
+/// starting with `#` skipped
+pub fn parse_rules(text: &str, dictionary: &Dictionary) -> Result<Vec<Rule>, ExprError> {
+    let mut rules: Vec<Rule> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let rule = Rule::parse_with(line, dictionary).map_err(|err| ExprError::Rule {
+            line: index + 1,
+            source: Box::new(err),
+        })?;
+        if rules.iter().any(|x| x.name == rule.name) {
+            return Err(ExprError::DuplicateRule(rule.name));
+        }
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// Read and parse a rules file
+pub fn load_rules(path: &Path, dictionary: &Dictionary) -> Result<Vec<Rule>, ExprError> {
+    let text = fs::read_to_string(path).map_err(|err| ExprError::Io(path.to_path_buf(), err))?;
+    parse_rules(&text, dictionary)
+}
+
+/// The names of the rules a message matches, in order
+pub fn matching<'a>(rules: &'a [Rule], msg: &FixMessage) -> Vec<&'a str> {
+    rules
+        .iter()
+        .filter(|x| x.expr.matches(msg))
+        .map(|x| x.name.as_str())
+        .collect()
+}
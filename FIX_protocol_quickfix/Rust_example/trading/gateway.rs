@@ -0,0 +1,47 @@
+// =============================================================================
+// Gateways
+// =============================================================================
+// Ways in and out of a FIX application other than FIX itself, all over std
+// networking:
+//
+// - http: minimal embedded HTTP server behind the REST and admin APIs
+// - grpc: gRPC services (unary and server streaming) over http2, a minimal
+//   HTTP/2 server
+// - metrics: Prometheus registry and exporter
+// - websocket: JSON event stream for dashboards
+// - kafka: fire-and-forget producer for execution reports
+// - webhook: signed HTTP callbacks on fills, session losses and risk breaches
+// - smtp: mail with attachments through a relay (EOD confirmations)
+// - delivery: EOD files to drop directories or SFTP, checksums and receipts
+// - news: news / sentiment feeds in, over WebSocket or polled REST
+// - sbe: binary market data in, over UDP multicast (trading::sbe)
+// - shards: worker processes behind a coordinator, their admin APIs merged
+//
+// All but kafka come with the `gateway` feature, kafka with `kafka`. Each
+// starts its own listener or connection thread when used.
+// =============================================================================
+
+#[cfg(feature = "gateway")]
+pub mod delivery;
+#[cfg(feature = "gateway")]
+pub mod grpc;
+#[cfg(feature = "gateway")]
+pub mod http;
+#[cfg(feature = "gateway")]
+pub mod http2;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "gateway")]
+pub mod metrics;
+#[cfg(feature = "gateway")]
+pub mod news;
+#[cfg(feature = "gateway")]
+pub mod sbe;
+#[cfg(feature = "gateway")]
+pub mod shards;
+#[cfg(feature = "gateway")]
+pub mod smtp;
+#[cfg(feature = "gateway")]
+pub mod webhook;
+#[cfg(feature = "gateway")]
+pub mod websocket;
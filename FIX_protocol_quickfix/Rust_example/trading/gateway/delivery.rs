@@ -0,0 +1,507 @@
+// =============================================================================
+// End-of-Day File Delivery
+// =============================================================================
+// Counterparties exchange their end-of-day files (trades, positions, fees,
+// confirmations) by dropping them in an agreed place: a directory shared
+// with them, or an SFTP server. A DeliveryJob pushes a set of files to each
+// of its destinations:
+//
+//   /srv/drop/acme                            local drop directory
+//   sftp://eod@files.acme.com:2222/inbound    SFTP server
+//
+// SFTP goes through the system `sftp` client in batch mode, so only key
+// authentication works (an agent, ~/.ssh or `with_identity`). The path of an
+// sftp:// URL is relative to the login directory; `//` makes it absolute.
+// A destination, file or directory name with a control character is
+// refused: a newline would end the batch command it is part of.
+//
+// Each file is written as `<name>.part` and renamed once complete, then
+// followed by a `<name>.sha256` sidecar in sha256sum format: a receiver can
+// wait for the sidecar and check the file with `sha256sum -c`. A copy to a
+// directory is also read back and its checksum compared.
+//
+// Every file delivered to a destination, or not, leaves a receipt, appended
+// to the receipts file as one JSON object per line:
+//
+//   {"file":"trades.csv","destination":"sftp://eod@files.acme.com/inbound",
+//    "bytes":2048,"sha256":"9f86d0...","time":1792072987,"status":"delivered"}
+//
+// status is delivered, verified (read back and matched) or failed, with an
+// "error" then. Delivery blocks until done: keep it off latency-sensitive
+// threads.
+// =============================================================================
+
+use std::{
+    error::Error,
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+use crate::{
+    gateway::webhook::{hex, sha256},
+    json::json_escape,
+    time::unix_now,
+};
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DeliveryError {
+    Io(io::Error),
+
+    /// Neither a directory nor an sftp:// URL
+    InvalidDestination(String),
+
+    /// The sftp client failed, with what it said
+    Sftp(String),
+
+    /// A path with a control character, that an sftp batch command cannot
+    /// carry
+    ControlCharacter(String),
+
+    /// The copy read back is not what was sent
+    ChecksumMismatch {
+        file: String,
+        found: String,
+    },
+}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryError::Io(err) => write!(f, "delivery: {err}"),
+            DeliveryError::InvalidDestination(x) => write!(f, "invalid destination '{x}'"),
+            DeliveryError::Sftp(x) => write!(f, "sftp: {x}"),
+            DeliveryError::ControlCharacter(x) => {
+                write!(f, "control character in the path {x:?}")
+            }
+            DeliveryError::ChecksumMismatch { file, found } => {
+                write!(f, "{file} read back with checksum {found}")
+            }
+        }
+    }
+}
+
+impl Error for DeliveryError {}
+
+impl From<io::Error> for DeliveryError {
+    fn from(err: io::Error) -> Self {
+        DeliveryError::Io(err)
+    }
+}
+
+// =============================================================================
+// Destinations
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Destination {
+    /// Local (or mounted) drop directory, created if missing
+    Dir(PathBuf),
+
+    Sftp {
+        user: Option<String>,
+        host: String,
+        port: Option<u16>,
+
+        /// Remote directory, created if missing
+        path: String,
+
+        /// Private key file; the client's defaults without one
+        identity: Option<PathBuf>,
+    },
+}
+
+impl Destination {
+    /// Log in to an SFTP destination with this private key; directories
+    /// are left as they are
+    pub fn with_identity(mut self, key: &Path) -> Self {
+        if let Destination::Sftp { identity, .. } = &mut self {
+            *identity = Some(key.to_path_buf());
+        }
+        self
+    }
+}
+
+/// The directory, or the sftp:// URL (without the key)
+impl fmt::Display for Destination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Destination::Dir(dir) => write!(f, "{}", dir.display()),
+            Destination::Sftp {
+                user,
+                host,
+                port,
+                path,
+                ..
+            } => {
+                write!(f, "sftp://")?;
+                if let Some(user) = user {
+                    write!(f, "{user}@")?;
+                }
+                write!(f, "{host}")?;
+                if let Some(port) = port {
+                    write!(f, ":{port}")?;
+                }
+                write!(f, "/{path}")
+            }
+        }
+    }
+}
+
+/// `sftp://[USER@]HOST[:PORT]/PATH`, else a directory
+impl FromStr for Destination {
+    type Err = DeliveryError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || DeliveryError::InvalidDestination(text.to_string());
+        if text.chars().any(char::is_control) {
+            return Err(invalid());
+        }
+        let Some(rest) = text.strip_prefix("sftp://") else {
+            if text.is_empty() || text.contains("://") {
+                return Err(invalid());
+            }
+            return Ok(Destination::Dir(PathBuf::from(text)));
+        };
+
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let (user, address) = match authority.rsplit_once('@') {
+            Some((user, address)) if !user.is_empty() => (Some(user.to_string()), address),
+            Some(_) => return Err(invalid()),
+            None => (None, authority),
+        };
+        let (host, port) = match address.split_once(':') {
+            Some((host, port)) => (host, Some(port.parse().map_err(|_| invalid())?)),
+            None => (address, None),
+        };
+        // Either would be the first character of sftp's host argument: one
+        // starting with '-' would be read as an option (-oProxyCommand=...)
+        let option = |x: &str| x.starts_with('-');
+        if host.is_empty() || option(host) || user.as_deref().is_some_and(option) {
+            return Err(invalid());
+        }
+        Ok(Destination::Sftp {
+            user,
+            host: host.to_string(),
+            port,
+            path: if path.is_empty() { "." } else { path }.to_string(),
+            identity: None,
+        })
+    }
+}
+
+// =============================================================================
+// Receipts
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptStatus {
+    /// Sent, not read back
+    Delivered,
+
+    /// Sent, read back and checksum matched
+    Verified,
+    Failed,
+}
+
+impl ReceiptStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReceiptStatus::Delivered => "delivered",
+            ReceiptStatus::Verified => "verified",
+            ReceiptStatus::Failed => "failed",
+        }
+    }
+}
+
+/// One file at one destination
+#[derive(Debug, Clone)]
+pub struct Receipt {
+    /// File name, as delivered
+    pub file: String,
+    pub destination: String,
+    pub bytes: u64,
+
+    /// Hex SHA-256 of the file; empty if it could not be read
+    pub sha256: String,
+
+    /// Unix seconds
+    pub time: i64,
+    pub status: ReceiptStatus,
+    pub error: Option<String>,
+}
+
+impl Receipt {
+    /// One line of the receipts file
+    pub fn to_json(&self) -> String {
+        let error = self.error.as_ref().map_or(String::new(), |x| {
+            format!(",\"error\":\"{}\"", json_escape(x))
+        });
+        format!(
+            "{{\"file\":\"{}\",\"destination\":\"{}\",\"bytes\":{},\"sha256\":\"{}\",\
+             \"time\":{},\"status\":\"{}\"{error}}}",
+            json_escape(&self.file),
+            json_escape(&self.destination),
+            self.bytes,
+            self.sha256,
+            self.time,
+            self.status.as_str()
+        )
+    }
+}
+
+// =============================================================================
+// Job
+// =============================================================================
+
+/// A file to deliver, read and checksummed
+struct Outgoing<'a> {
+    path: &'a Path,
+    name: String,
+    data: Vec<u8>,
+    sha256: String,
+}
+
+impl Outgoing<'_> {
+    /// The sidecar's content: `<hex>  <name>`
+    fn sidecar(&self) -> String {
+        format!("{}  {}\n", self.sha256, self.name)
+    }
+}
+
+/// Where the files go, and where the receipts are kept
+#[derive(Debug, Clone, Default)]
+pub struct DeliveryJob {
+    destinations: Vec<Destination>,
+    receipts: Option<PathBuf>,
+}
+
+impl DeliveryJob {
+    /// No destination: delivers nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_destination(mut self, destination: Destination) -> Self {
+        self.destinations.push(destination);
+        self
+    }
+
+    /// Append receipts to this file (JSON lines); not kept without one
+    pub fn with_receipts(mut self, path: &Path) -> Self {
+        self.receipts = Some(path.to_path_buf());
+        self
+    }
+
+    pub fn destinations(&self) -> &[Destination] {
+        &self.destinations
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.destinations.is_empty()
+    }
+
+    /// Deliver `files` to every destination, a receipt per file and
+    /// destination
+    ///
+    /// A file or destination that fails is in the receipts, not an error:
+    /// the error is the receipts file that cannot be written.
+    pub fn deliver(&self, files: &[PathBuf]) -> io::Result<Vec<Receipt>> {
+        let mut outgoing = Vec::new();
+        let mut unreadable = Vec::new();
+        for path in files {
+            let name = path
+                .file_name()
+                .map_or(String::new(), |x| x.to_string_lossy().into_owned());
+            match fs::read(path) {
+                Ok(data) => outgoing.push(Outgoing {
+                    path,
+                    name,
+                    sha256: hex(&sha256(&data)),
+                    data,
+                }),
+                Err(err) => unreadable.push((name, err.to_string())),
+            }
+        }
+
+        let mut receipts = Vec::new();
+        for destination in &self.destinations {
+            let label = destination.to_string();
+            let receipt = |file: &Outgoing, result: Result<ReceiptStatus, String>| {
+                let (status, error) = match result {
+                    Ok(status) => (status, None),
+                    Err(err) => (ReceiptStatus::Failed, Some(err)),
+                };
+                Receipt {
+                    file: file.name.clone(),
+                    destination: label.clone(),
+                    bytes: file.data.len() as u64,
+                    sha256: file.sha256.clone(),
+                    time: unix_now(),
+                    status,
+                    error,
+                }
+            };
+            match destination {
+                Destination::Dir(dir) => {
+                    for file in &outgoing {
+                        let result = deliver_to_dir(dir, file).map_err(|x| x.to_string());
+                        receipts.push(receipt(file, result));
+                    }
+                }
+                Destination::Sftp { .. } => {
+                    let result = deliver_by_sftp(destination, &outgoing)
+                        .map(|()| ReceiptStatus::Delivered)
+                        .map_err(|x| x.to_string());
+                    for file in &outgoing {
+                        receipts.push(receipt(file, result.clone()));
+                    }
+                }
+            }
+            receipts.extend(unreadable.iter().map(|(name, err)| Receipt {
+                file: name.clone(),
+                destination: label.clone(),
+                bytes: 0,
+                sha256: String::new(),
+                time: unix_now(),
+                status: ReceiptStatus::Failed,
+                error: Some(err.clone()),
+            }));
+        }
+
+        if let Some(path) = &self.receipts {
+            append_receipts(path, &receipts)?;
+        }
+        Ok(receipts)
+    }
+}
+
+fn deliver_to_dir(dir: &Path, file: &Outgoing) -> Result<ReceiptStatus, DeliveryError> {
+    fs::create_dir_all(dir)?;
+    let target = dir.join(&file.name);
+    let part = dir.join(format!("{}.part", file.name));
+    fs::write(&part, &file.data)?;
+    fs::rename(&part, &target)?;
+
+    let found = hex(&sha256(&fs::read(&target)?));
+    if found != file.sha256 {
+        return Err(DeliveryError::ChecksumMismatch {
+            file: target.display().to_string(),
+            found,
+        });
+    }
+    fs::write(dir.join(format!("{}.sha256", file.name)), file.sidecar())?;
+    Ok(ReceiptStatus::Verified)
+}
+
+/// All files in one sftp session; a sidecar is written next to each source
+/// to be uploaded from there
+fn deliver_by_sftp(destination: &Destination, files: &[Outgoing]) -> Result<(), DeliveryError> {
+    let Destination::Sftp {
+        user,
+        host,
+        port,
+        path,
+        identity,
+    } = destination
+    else {
+        return Err(DeliveryError::InvalidDestination(destination.to_string()));
+    };
+
+    // Commands prefixed with '-' may fail: the directory may exist, the
+    // file may not
+    let mut script = format!("-mkdir {}\n", quote(path)?);
+    for file in files {
+        let sidecar = sidecar_path(file.path);
+        let remote = format!("{path}/{}", file.name);
+        // Quoted before its sidecar is written
+        let local = quote(&file.path.display().to_string())?;
+        let local_sidecar = quote(&sidecar.display().to_string())?;
+        let part = quote(&format!("{remote}.part"))?;
+        let remote_sidecar = quote(&format!("{remote}.sha256"))?;
+        let remote = quote(&remote)?;
+        script.push_str(&format!(
+            "put {local} {part}\n-rm {remote}\nrename {part} {remote}\n\
+             put {local_sidecar} {remote_sidecar}\n"
+        ));
+        fs::write(&sidecar, file.sidecar())?;
+    }
+    script.push_str("bye\n");
+
+    let mut command = Command::new("sftp");
+    command.args(["-b", "-", "-o", "BatchMode=yes"]);
+    if let Some(port) = port {
+        command.arg("-P").arg(port.to_string());
+    }
+    if let Some(identity) = identity {
+        command.arg("-i").arg(identity);
+    }
+    // Whatever follows is the destination, never an option
+    command.arg("--");
+    command.arg(match user {
+        Some(user) => format!("{user}@{host}"),
+        None => host.clone(),
+    });
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(script.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(DeliveryError::Sftp(format!(
+        "{}: {}",
+        output.status,
+        stderr.trim()
+    )))
+}
+
+/// `<file>.sha256`, next to the file
+fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".sha256");
+    PathBuf::from(name)
+}
+
+/// A path as one argument of an sftp batch command; one with a control
+/// character is refused, a newline ending the command
+fn quote(path: &str) -> Result<String, DeliveryError> {
+    if path.chars().any(char::is_control) {
+        return Err(DeliveryError::ControlCharacter(path.to_string()));
+    }
+    Ok(format!(
+        "\"{}\"",
+        path.replace('\\', "\\\\").replace('"', "\\\"")
+    ))
+}
+
+fn append_receipts(path: &Path, receipts: &[Receipt]) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|x| !x.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut lines = String::new();
+    for receipt in receipts {
+        lines.push_str(&receipt.to_json());
+        lines.push('\n');
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(lines.as_bytes())
+}
@@ -0,0 +1,502 @@
+// =============================================================================
+// Minimal gRPC Server
+// =============================================================================
+// Just enough gRPC for services written against std alone, over the HTTP/2
+// server of http2.rs:
+//
+// - unary and server streaming calls: one request message in, any number
+//   of response messages out, then the status in the trailers
+// - messages in the protobuf wire format, read with ProtoMessage and
+//   written with ProtoWriter; the .proto of a service is the contract with
+//   its clients, the code maps its field numbers by hand
+// - identity encoding only: a compressed request is answered UNIMPLEMENTED
+// - no TLS (h2c): gRPC clients dial a plaintext target ("insecure"
+//   channel credentials)
+//
+// A handler gets the call and a ServerStream. It sends the reply of a unary
+// call, or the messages of a stream until it is done or the client goes
+// away, and returns Ok or the Status the call ends with.
+// =============================================================================
+
+use std::{fmt, io, net::SocketAddr};
+
+use super::http2::{self, Responder, MAX_BODY};
+use crate::audit::UNKNOWN_ACTOR;
+
+// =============================================================================
+// Status
+// =============================================================================
+
+/// gRPC status codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    Ok = 0,
+    Cancelled = 1,
+    Unknown = 2,
+    InvalidArgument = 3,
+    DeadlineExceeded = 4,
+    NotFound = 5,
+    AlreadyExists = 6,
+    PermissionDenied = 7,
+    ResourceExhausted = 8,
+    FailedPrecondition = 9,
+    Aborted = 10,
+    OutOfRange = 11,
+    Unimplemented = 12,
+    Internal = 13,
+    Unavailable = 14,
+    DataLoss = 15,
+    Unauthenticated = 16,
+}
+
+/// How a call ended, when not well
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Status {
+    pub code: Code,
+    pub message: String,
+}
+
+impl Status {
+    pub fn new(code: Code, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new(Code::InvalidArgument, message)
+    }
+
+    pub fn unimplemented(message: impl Into<String>) -> Self {
+        Self::new(Code::Unimplemented, message)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for Status {}
+
+// =============================================================================
+// Calls
+// =============================================================================
+
+/// A call, its request message read
+#[derive(Debug)]
+pub struct Call {
+    /// `/<package>.<Service>/<Method>`
+    pub path: String,
+
+    /// The request message, in the protobuf wire format
+    pub message: Vec<u8>,
+
+    /// Custom metadata and the other headers, names in lower case
+    pub metadata: Vec<(String, String)>,
+
+    /// Client address, when the socket knows it
+    pub peer: Option<SocketAddr>,
+}
+
+impl Call {
+    /// `Method` of `/<package>.<Service>/<Method>`
+    pub fn method(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or_default()
+    }
+
+    /// `<package>.<Service>` of `/<package>.<Service>/<Method>`
+    pub fn service(&self) -> &str {
+        self.path
+            .trim_start_matches('/')
+            .split('/')
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// Value of a metadata entry
+    pub fn metadata(&self, name: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Who made the call, for the audit log: the x-operator metadata, else
+    /// the client's IP address
+    pub fn operator(&self) -> String {
+        match (self.metadata("x-operator"), self.peer) {
+            (Some(operator), _) => operator.to_string(),
+            (None, Some(peer)) => peer.ip().to_string(),
+            (None, None) => UNKNOWN_ACTOR.to_string(),
+        }
+    }
+}
+
+/// The response messages of a call
+pub struct ServerStream {
+    responder: Responder,
+
+    /// The response headers went out with the first message
+    started: bool,
+}
+
+impl ServerStream {
+    /// Send one response message: the reply of a unary call, or the next
+    /// message of a stream
+    ///
+    /// Fails once the client cancelled the call or went away.
+    pub fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        if !self.started {
+            self.responder
+                .headers(200, &[("content-type", "application/grpc")], false)?;
+            self.started = true;
+        }
+        let mut frame = Vec::with_capacity(5 + message.len());
+        frame.push(0); // not compressed
+        frame.extend((message.len() as u32).to_be_bytes());
+        frame.extend(message);
+        self.responder.data(&frame, false)
+    }
+
+    /// Whether the client cancelled the call or went away
+    pub fn is_closed(&self) -> bool {
+        self.responder.is_closed()
+    }
+
+    /// End the call with its status: trailers after the messages, or a
+    /// trailers-only response when there were none
+    fn finish(mut self, status: Result<(), Status>) -> io::Result<()> {
+        let (code, message) = match &status {
+            Ok(()) => (Code::Ok, String::new()),
+            Err(status) => (status.code, percent_encode(&status.message)),
+        };
+        let code = (code as u8).to_string();
+        let mut trailers = vec![("grpc-status", code.as_str())];
+        if !message.is_empty() {
+            trailers.push(("grpc-message", message.as_str()));
+        }
+        if self.started {
+            self.responder.trailers(&trailers)
+        } else {
+            trailers.insert(0, ("content-type", "application/grpc"));
+            self.responder.headers(200, &trailers, true)
+        }
+    }
+}
+
+/// Start serving on `0.0.0.0:<port>` in a background thread
+///
+/// # Arguments
+/// * `name` - Thread name, also used in log lines
+/// * `port` - TCP port to listen on
+/// * `handler` - Called once per call, on a thread of the call's own: sends
+///   the response messages, returns the status
+pub fn spawn_server<H>(name: &str, port: u16, handler: H) -> io::Result<()>
+where
+    H: Fn(&Call, &mut ServerStream) -> Result<(), Status> + Send + Sync + 'static,
+{
+    let log_name = name.to_string();
+    http2::spawn_server(name, port, move |request, responder| {
+        let mut stream = ServerStream {
+            responder,
+            started: false,
+        };
+        let status = read_call(request).and_then(|call| handler(&call, &mut stream));
+        // Cancelled by the client, or the connection gone: nobody to tell
+        if stream.is_closed() {
+            return;
+        }
+        if let Err(err) = stream.finish(status) {
+            eprintln!("{log_name}: {err}");
+        }
+    })
+}
+
+/// The call a request makes, its single message unframed
+fn read_call(request: http2::Request) -> Result<Call, Status> {
+    if request.method != "POST" {
+        return Err(Status::unimplemented("gRPC calls are POST requests"));
+    }
+    let content_type = request.header("content-type").unwrap_or_default();
+    if !content_type.starts_with("application/grpc") {
+        return Err(Status::unimplemented(format!(
+            "content-type {content_type}, not application/grpc"
+        )));
+    }
+
+    let body = &request.body;
+    let Some(prefix) = body.get(..5) else {
+        return Err(Status::invalid_argument("no request message"));
+    };
+    if prefix[0] != 0 {
+        return Err(Status::unimplemented(
+            "compressed messages are not supported",
+        ));
+    }
+    let length = u32::from_be_bytes([prefix[1], prefix[2], prefix[3], prefix[4]]) as usize;
+    if length > MAX_BODY || body.len() != 5 + length {
+        return Err(Status::invalid_argument(
+            "expected exactly one request message",
+        ));
+    }
+
+    Ok(Call {
+        message: body[5..].to_vec(),
+        path: request.path,
+        metadata: request.headers,
+        peer: request.peer,
+    })
+}
+
+/// grpc-message: printable ASCII but '%' as is, the rest percent-encoded
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if (0x20..=0x7e).contains(&byte) && byte != b'%' {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+// =============================================================================
+// Protobuf Wire Format
+// =============================================================================
+
+// Wire types
+const VARINT: u64 = 0;
+const FIXED64: u64 = 1;
+const LEN: u64 = 2;
+const FIXED32: u64 = 5;
+
+/// A message built field by field, in the protobuf wire format
+///
+/// As proto3 does, fields holding their default (0, false, "") are left out.
+#[derive(Debug, Clone, Default)]
+pub struct ProtoWriter {
+    bytes: Vec<u8>,
+}
+
+impl ProtoWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// uint32, uint64, int64 (non-negative), enum, bool
+    pub fn uint(mut self, number: u32, value: u64) -> Self {
+        if value != 0 {
+            self.key(number, VARINT);
+            write_varint(&mut self.bytes, value);
+        }
+        self
+    }
+
+    /// int64, negative values included (ten bytes each)
+    pub fn int(self, number: u32, value: i64) -> Self {
+        self.uint(number, value as u64)
+    }
+
+    pub fn bool(self, number: u32, value: bool) -> Self {
+        self.uint(number, u64::from(value))
+    }
+
+    pub fn double(mut self, number: u32, value: f64) -> Self {
+        if value != 0.0 {
+            self.key(number, FIXED64);
+            self.bytes.extend(value.to_le_bytes());
+        }
+        self
+    }
+
+    pub fn string(self, number: u32, value: &str) -> Self {
+        self.bytes(number, value.as_bytes())
+    }
+
+    pub fn bytes(mut self, number: u32, value: &[u8]) -> Self {
+        if !value.is_empty() {
+            self.len_field(number, value);
+        }
+        self
+    }
+
+    /// A nested message; once per element of a repeated field
+    pub fn message(mut self, number: u32, message: ProtoWriter) -> Self {
+        self.len_field(number, &message.bytes);
+        self
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    fn len_field(&mut self, number: u32, value: &[u8]) {
+        self.key(number, LEN);
+        write_varint(&mut self.bytes, value.len() as u64);
+        self.bytes.extend(value);
+    }
+
+    fn key(&mut self, number: u32, wire_type: u64) {
+        write_varint(&mut self.bytes, u64::from(number) << 3 | wire_type);
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// The value of one field as read, before its .proto type is applied
+#[derive(Debug, Clone, PartialEq)]
+enum Value<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Len(&'a [u8]),
+    Fixed32(u32),
+}
+
+/// A message read from the protobuf wire format
+///
+/// Getters take the field number and answer the proto3 default for a
+/// field absent, or of a wire type its .proto type cannot have; the last
+/// value wins when a field is repeated.
+#[derive(Debug, Clone)]
+pub struct ProtoMessage<'a> {
+    fields: Vec<(u32, Value<'a>)>,
+}
+
+impl<'a> ProtoMessage<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, Status> {
+        let malformed = || Status::invalid_argument("malformed protobuf message");
+        let mut fields = Vec::new();
+        let mut position = 0;
+        while position < bytes.len() {
+            let key = read_varint(bytes, &mut position).ok_or_else(malformed)?;
+            let number = u32::try_from(key >> 3)
+                .ok()
+                .filter(|x| *x != 0)
+                .ok_or_else(malformed)?;
+            let value = match key & 0x7 {
+                VARINT => read_varint(bytes, &mut position).map(Value::Varint),
+                FIXED64 => take(bytes, &mut position, 8)
+                    .map(|x| Value::Fixed64(u64::from_le_bytes(x.try_into().unwrap_or_default()))),
+                LEN => read_varint(bytes, &mut position)
+                    .and_then(|length| usize::try_from(length).ok())
+                    .and_then(|length| take(bytes, &mut position, length))
+                    .map(Value::Len),
+                FIXED32 => take(bytes, &mut position, 4)
+                    .map(|x| Value::Fixed32(u32::from_le_bytes(x.try_into().unwrap_or_default()))),
+                _ => None,
+            };
+            fields.push((number, value.ok_or_else(malformed)?));
+        }
+        Ok(Self { fields })
+    }
+
+    /// uint32, uint64, int64, enum
+    pub fn uint(&self, number: u32) -> u64 {
+        self.values(number)
+            .filter_map(|x| match x {
+                Value::Varint(value) => Some(*value),
+                _ => None,
+            })
+            .last()
+            .unwrap_or_default()
+    }
+
+    pub fn int(&self, number: u32) -> i64 {
+        self.uint(number) as i64
+    }
+
+    pub fn bool(&self, number: u32) -> bool {
+        self.uint(number) != 0
+    }
+
+    pub fn double(&self, number: u32) -> f64 {
+        self.values(number)
+            .filter_map(|x| match x {
+                Value::Fixed64(bits) => Some(f64::from_bits(*bits)),
+                _ => None,
+            })
+            .last()
+            .unwrap_or_default()
+    }
+
+    pub fn float(&self, number: u32) -> f32 {
+        self.values(number)
+            .filter_map(|x| match x {
+                Value::Fixed32(bits) => Some(f32::from_bits(*bits)),
+                _ => None,
+            })
+            .last()
+            .unwrap_or_default()
+    }
+
+    /// A string field; an error if it is not UTF-8
+    pub fn string(&self, number: u32) -> Result<&'a str, Status> {
+        Ok(self.strings(number)?.pop().unwrap_or_default())
+    }
+
+    /// Every element of a repeated string field
+    pub fn strings(&self, number: u32) -> Result<Vec<&'a str>, Status> {
+        self.bytes(number)
+            .into_iter()
+            .map(|x| {
+                std::str::from_utf8(x)
+                    .map_err(|_| Status::invalid_argument(format!("field {number} is not UTF-8")))
+            })
+            .collect()
+    }
+
+    /// Every element of a repeated bytes, string or message field
+    pub fn bytes(&self, number: u32) -> Vec<&'a [u8]> {
+        self.values(number)
+            .filter_map(|x| match x {
+                Value::Len(bytes) => Some(*bytes),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every element of a repeated message field
+    pub fn messages(&self, number: u32) -> Result<Vec<ProtoMessage<'a>>, Status> {
+        self.bytes(number)
+            .into_iter()
+            .map(ProtoMessage::parse)
+            .collect()
+    }
+
+    fn values(&self, number: u32) -> impl Iterator<Item = &Value<'a>> {
+        self.fields
+            .iter()
+            .filter(move |(n, _)| *n == number)
+            .map(|(_, value)| value)
+    }
+}
+
+/// The next `length` bytes
+fn take<'a>(bytes: &'a [u8], position: &mut usize, length: usize) -> Option<&'a [u8]> {
+    let value = bytes.get(*position..position.checked_add(length)?)?;
+    *position += length;
+    Some(value)
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*position)?;
+        *position += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
@@ -0,0 +1,309 @@
+// =============================================================================
+// Minimal Embedded HTTP Server
+// =============================================================================
+// Just enough HTTP/1.1 for the admin and monitoring endpoints of the examples:
+// one request per connection, request line + headers + optional body
+// (Content-Length), handled on a single background thread. Only std is used
+// so the examples keep the quickfix crate as their only dependency.
+//
+// A handler wrapped with `audited` appends every request that is not a GET
+// to the operator audit log (audit.rs).
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    iter::Peekable,
+    net::{SocketAddr, TcpListener, TcpStream},
+    str::Chars,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use crate::audit::{AuditEntry, AuditLog, AuditSource, UNKNOWN_ACTOR};
+
+// Part of the core (the OMS renders JSON without the gateways); kept here
+// under its original path as well
+pub use crate::json::json_escape;
+
+/// Largest request body we accept
+const MAX_BODY: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+
+    /// Name and value of every header, names as sent
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+
+    /// Client address, when the socket knows it
+    pub peer: Option<SocketAddr>,
+}
+
+impl Request {
+    /// Path split on '/', without empty segments: "/orders/42" -> ["orders", "42"]
+    pub fn segments(&self) -> Vec<&str> {
+        self.path
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .filter(|x| !x.is_empty())
+            .collect()
+    }
+
+    /// Value of a query string parameter: "/orders?symbol=AAPL"
+    pub fn query(&self, key: &str) -> Option<&str> {
+        self.path
+            .split_once('?')?
+            .1
+            .split('&')
+            .filter_map(|x| x.split_once('='))
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Value of a header, whatever the case of its name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Who sent the request, for the audit log: the X-Operator header, else
+    /// the client's IP address
+    pub fn operator(&self) -> String {
+        match (self.header("x-operator"), self.peer) {
+            (Some(operator), _) => operator.to_string(),
+            (None, Some(peer)) => peer.ip().to_string(),
+            (None, None) => UNKNOWN_ACTOR.to_string(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn text(body: String) -> Self {
+        Self {
+            status: "200 OK",
+            content_type: "text/plain; charset=utf-8",
+            body,
+        }
+    }
+
+    pub fn json(body: String) -> Self {
+        Self {
+            status: "200 OK",
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    pub fn error(status: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: format!("{{\"error\":\"{}\"}}", json_escape(message)),
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self::error("404 Not Found", "not found")
+    }
+}
+
+
+/// Parse a flat JSON object of string / number / bool values
+///
+/// Enough for small request bodies like `{"symbol":"AAPL","quantity":100}`.
+/// Values are returned as their raw text (strings unescaped). Nested objects
+/// and arrays are not supported.
+pub fn parse_json_object(body: &str) -> Option<HashMap<String, String>> {
+    let mut chars = body.trim().chars().peekable();
+    let mut fields = HashMap::new();
+
+    if chars.next()? != '{' {
+        return None;
+    }
+    skip_ws(&mut chars);
+    if chars.next_if_eq(&'}').is_some() {
+        return Some(fields);
+    }
+
+    loop {
+        skip_ws(&mut chars);
+        let key = parse_string(&mut chars)?;
+        skip_ws(&mut chars);
+        if chars.next()? != ':' {
+            return None;
+        }
+        skip_ws(&mut chars);
+
+        let value = if chars.peek() == Some(&'"') {
+            parse_string(&mut chars)?
+        } else {
+            let mut raw = String::new();
+            while let Some(c) = chars.next_if(|c| !matches!(c, ',' | '}') && !c.is_whitespace()) {
+                raw.push(c);
+            }
+            if raw.is_empty() {
+                return None;
+            }
+            raw
+        };
+        fields.insert(key, value);
+
+        skip_ws(&mut chars);
+        match chars.next()? {
+            ',' => continue,
+            '}' => break,
+            _ => return None,
+        }
+    }
+
+    skip_ws(&mut chars);
+    chars.next().is_none().then_some(fields)
+}
+
+fn skip_ws(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Option<String> {
+    if chars.next()? != '"' {
+        return None;
+    }
+    let mut out = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(out),
+            '\\' => match chars.next()? {
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                c => out.push(c),
+            },
+            c => out.push(c),
+        }
+    }
+}
+
+/// Start serving on `0.0.0.0:<port>` in a background thread
+///
+/// # Arguments
+/// * `name` - Thread name, also used in log lines
+/// * `port` - TCP port to listen on
+/// * `handler` - Called once per request
+pub fn spawn_server<H>(name: &str, port: u16, handler: H) -> io::Result<()>
+where
+    H: Fn(&Request) -> Response + Send + 'static,
+{
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let thread_name = name.to_string();
+
+    thread::Builder::new()
+        .name(thread_name.clone())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(err) = handle_connection(stream, &handler) {
+                    eprintln!("{thread_name}: {err}");
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// Wrap a handler so that every request but GET and HEAD is recorded in an
+/// audit log, with its operator, method, path, body and status
+///
+/// A failure to record is reported on stderr; the response goes out anyway.
+pub fn audited<H>(
+    log: Arc<AuditLog>,
+    source: AuditSource,
+    handler: H,
+) -> impl Fn(&Request) -> Response + Send + 'static
+where
+    H: Fn(&Request) -> Response + Send + 'static,
+{
+    move |request| {
+        let response = handler(request);
+        if !matches!(request.method.as_str(), "GET" | "HEAD") {
+            let command = format!("{} {} {}", request.method, request.path, request.body.trim());
+            let ok = response.status.starts_with('2');
+            let entry = AuditEntry::new(source, &request.operator(), &command, ok, response.status);
+            if let Err(err) = log.record(&entry) {
+                eprintln!("Cannot write to {}: {err}", log.path().display());
+            }
+        }
+        response
+    }
+}
+
+fn handle_connection<H>(stream: TcpStream, handler: &H) -> io::Result<()>
+where
+    H: Fn(&Request) -> Response,
+{
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or("/").to_string();
+
+    // Headers: kept for the handler; Content-Length sizes the body
+    let mut content_length = 0;
+    let mut headers = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? <= 2 {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let (name, value) = (name.trim(), value.trim());
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().unwrap_or(0);
+            }
+            headers.push((name.to_string(), value.to_string()));
+        }
+    }
+
+    let mut body = vec![0; content_length.min(MAX_BODY)];
+    reader.read_exact(&mut body)?;
+
+    let request = Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+        peer: stream.peer_addr().ok(),
+    };
+    let response = handler(&request);
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
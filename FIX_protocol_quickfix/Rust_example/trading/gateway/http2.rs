@@ -0,0 +1,1269 @@
+// =============================================================================
+// Minimal HTTP/2 Server
+// =============================================================================
+// Just enough HTTP/2 (RFC 9113) for the gRPC gateway (grpc.rs), over std TCP:
+//
+// - cleartext with prior knowledge (h2c): clients open with the HTTP/2
+//   preface, as gRPC clients do for plaintext targets; no TLS, no upgrade
+//   from HTTP/1.1
+// - HPACK (RFC 7541) header blocks: the static and dynamic tables and
+//   Huffman-coded strings are read; what the server sends is literal, not
+//   indexed and not Huffman-coded
+// - flow control: DATA goes out within the windows the client grants,
+//   waiting for its WINDOW_UPDATEs; what the client sends is granted back
+//   as soon as it is read
+// - no server push; priorities are ignored
+//
+// One thread reads each connection. A request, once the client ends its
+// stream, is handed to the handler on a thread of its own with a Responder,
+// so that a response streamed for hours (a gRPC server stream) does not
+// hold up the other streams of the connection. The frames of all these
+// threads go out whole, one at a time.
+// =============================================================================
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, BufReader, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// What a client sends first
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+// Frame types
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PUSH_PROMISE: u8 = 0x5;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const WINDOW_UPDATE: u8 = 0x8;
+const CONTINUATION: u8 = 0x9;
+
+// Frame flags
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY: u8 = 0x20;
+
+// Settings
+const SETTINGS_MAX_CONCURRENT_STREAMS: u16 = 0x3;
+const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+const SETTINGS_MAX_FRAME_SIZE: u16 = 0x5;
+
+// Error codes
+const PROTOCOL_ERROR: u32 = 0x1;
+const INTERNAL_ERROR: u32 = 0x2;
+const FLOW_CONTROL_ERROR: u32 = 0x3;
+const FRAME_SIZE_ERROR: u32 = 0x6;
+const REFUSED_STREAM: u32 = 0x7;
+const COMPRESSION_ERROR: u32 = 0x9;
+
+/// Window of a connection and of its streams until the client says otherwise
+const DEFAULT_WINDOW: i64 = 65_535;
+
+/// Largest frame either side may send until told otherwise; we never raise
+/// ours
+const DEFAULT_MAX_FRAME: usize = 16_384;
+
+/// Largest window RFC 9113 allows
+const MAX_WINDOW: i64 = (1 << 31) - 1;
+
+/// Streams of a connection open at once
+const MAX_STREAMS: usize = 100;
+
+/// Largest request body accepted, as gRPC's default message limit
+pub const MAX_BODY: usize = 4 * 1024 * 1024;
+
+/// Largest header block accepted
+const MAX_HEADER_BLOCK: usize = 64 * 1024;
+
+/// Size of the HPACK dynamic table the client may use (the default)
+const HEADER_TABLE_SIZE: usize = 4096;
+
+/// How long DATA waits for the client to grant a window
+const WINDOW_TIMEOUT: Duration = Duration::from_secs(30);
+
+// =============================================================================
+// Requests and Responses
+// =============================================================================
+
+#[derive(Debug)]
+pub struct Request {
+    /// `:method`
+    pub method: String,
+
+    /// `:path`
+    pub path: String,
+
+    /// Every other header, names in lower case as HTTP/2 sends them
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+
+    /// Client address, when the socket knows it
+    pub peer: Option<SocketAddr>,
+}
+
+impl Request {
+    /// Value of a header
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// The response side of a stream, handed to the handler with its request
+///
+/// Headers first, then DATA, then trailers or a DATA ending the stream. A
+/// Responder dropped before the stream ends resets it.
+pub struct Responder {
+    stream: u32,
+    connection: Arc<Connection>,
+    ended: bool,
+}
+
+impl Responder {
+    /// Send the response headers; `end` ends the stream with them (a
+    /// response without body, a gRPC trailers-only response)
+    pub fn headers(&mut self, status: u16, headers: &[(&str, &str)], end: bool) -> io::Result<()> {
+        let block = encode_headers(Some(status), headers);
+        self.connection.send_headers(self.stream, &block, end)?;
+        self.ended |= end;
+        Ok(())
+    }
+
+    /// Send body bytes, within the flow control windows: waits for the
+    /// client to grant more when they are used up
+    pub fn data(&mut self, data: &[u8], end: bool) -> io::Result<()> {
+        self.connection.send_data(self.stream, data, end)?;
+        self.ended |= end;
+        Ok(())
+    }
+
+    /// Send trailers, ending the stream
+    pub fn trailers(&mut self, headers: &[(&str, &str)]) -> io::Result<()> {
+        let block = encode_headers(None, headers);
+        self.connection.send_headers(self.stream, &block, true)?;
+        self.ended = true;
+        Ok(())
+    }
+
+    /// Whether the client reset the stream, or the connection is gone
+    pub fn is_closed(&self) -> bool {
+        self.connection.is_closed(self.stream)
+    }
+}
+
+impl Drop for Responder {
+    fn drop(&mut self) {
+        if !self.ended {
+            let _ = self.connection.reset(self.stream, INTERNAL_ERROR);
+        }
+        self.connection.forget(self.stream);
+    }
+}
+
+// =============================================================================
+// Server
+// =============================================================================
+
+/// Start serving on `0.0.0.0:<port>` in a background thread
+///
+/// # Arguments
+/// * `name` - Thread name, also used in log lines
+/// * `port` - TCP port to listen on
+/// * `handler` - Called once per request, on a thread of the request's own
+pub fn spawn_server<H>(name: &str, port: u16, handler: H) -> io::Result<()>
+where
+    H: Fn(Request, Responder) + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let handler = Arc::new(handler);
+    let name = name.to_string();
+
+    thread::Builder::new().name(name.clone()).spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let handler = Arc::clone(&handler);
+            let thread_name = name.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("{name} connection"))
+                .spawn(move || {
+                    if let Err(err) = serve(stream, &handler) {
+                        eprintln!("{thread_name}: {err}");
+                    }
+                });
+            if let Err(err) = spawned {
+                eprintln!("{name}: cannot start a connection thread: {err}");
+            }
+        }
+    })?;
+    Ok(())
+}
+
+/// Read the frames of a connection until it closes, handing each request
+/// to the handler once complete
+fn serve<H>(stream: TcpStream, handler: &Arc<H>) -> io::Result<()>
+where
+    H: Fn(Request, Responder) + Send + Sync + 'static,
+{
+    let peer = stream.peer_addr().ok();
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut preface = [0; PREFACE.len()];
+    reader.read_exact(&mut preface)?;
+    if preface != PREFACE {
+        return Err(invalid_data("not an HTTP/2 connection (prior knowledge)"));
+    }
+
+    let connection = Arc::new(Connection::new(stream));
+    let mut settings = SETTINGS_MAX_CONCURRENT_STREAMS.to_be_bytes().to_vec();
+    settings.extend((MAX_STREAMS as u32).to_be_bytes());
+    connection.send_frame(SETTINGS, 0, 0, &settings)?;
+
+    let mut reading = Reading {
+        connection: Arc::clone(&connection),
+        decoder: Decoder::new(),
+        incoming: HashMap::new(),
+        last_stream: 0,
+        peer,
+    };
+    let result = reading.frames(&mut reader, handler);
+    if let Err(err) = &result {
+        let code = match err.kind() {
+            io::ErrorKind::InvalidData => PROTOCOL_ERROR,
+            _ => INTERNAL_ERROR,
+        };
+        let _ = connection.goaway(reading.last_stream, code);
+    }
+    connection.close();
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(()),
+        result => result,
+    }
+}
+
+/// What the reading thread of a connection keeps
+struct Reading {
+    connection: Arc<Connection>,
+    decoder: Decoder,
+
+    /// Requests whose body is still coming
+    incoming: HashMap<u32, Request>,
+
+    /// Highest stream the client opened
+    last_stream: u32,
+    peer: Option<SocketAddr>,
+}
+
+impl Reading {
+    fn frames<H>(&mut self, reader: &mut impl Read, handler: &Arc<H>) -> io::Result<()>
+    where
+        H: Fn(Request, Responder) + Send + Sync + 'static,
+    {
+        // A header block continued in CONTINUATION frames: stream, END_STREAM
+        // and the fragments so far
+        let mut continued: Option<(u32, bool, Vec<u8>)> = None;
+        loop {
+            let (kind, flags, stream, payload) = read_frame(reader)?;
+            if let Some((id, end_stream, mut block)) = continued.take() {
+                if kind != CONTINUATION || stream != id {
+                    return Err(invalid_data("header block interrupted"));
+                }
+                block.extend(&payload);
+                if block.len() > MAX_HEADER_BLOCK {
+                    return Err(invalid_data("header block too large"));
+                }
+                if flags & END_HEADERS != 0 {
+                    self.header_block(id, end_stream, &block, handler)?;
+                } else {
+                    continued = Some((id, end_stream, block));
+                }
+                continue;
+            }
+
+            match kind {
+                SETTINGS if flags & ACK == 0 => {
+                    self.connection.apply_settings(&payload)?;
+                    self.connection.send_frame(SETTINGS, ACK, 0, &[])?;
+                }
+                PING if flags & ACK == 0 => self.connection.send_frame(PING, ACK, 0, &payload)?,
+                WINDOW_UPDATE => {
+                    let increment = read_u32(&payload, 0)? & 0x7fff_ffff;
+                    self.connection
+                        .window_update(stream, i64::from(increment))?;
+                }
+                RST_STREAM => {
+                    self.incoming.remove(&stream);
+                    self.connection.forget(stream);
+                }
+                GOAWAY => return Ok(()),
+                PUSH_PROMISE => return Err(invalid_data("clients cannot push")),
+                HEADERS => {
+                    let block = unpad(flags, &payload)?;
+                    let block = if flags & PRIORITY != 0 {
+                        block
+                            .get(5..)
+                            .ok_or_else(|| invalid_data("short HEADERS"))?
+                    } else {
+                        block
+                    };
+                    let end_stream = flags & END_STREAM != 0;
+                    if flags & END_HEADERS != 0 {
+                        self.header_block(stream, end_stream, block, handler)?;
+                    } else {
+                        continued = Some((stream, end_stream, block.to_vec()));
+                    }
+                }
+                DATA => {
+                    // Granted back at once: the body limit is what bounds
+                    // a request
+                    let size = payload.len() as u32;
+                    if size > 0 {
+                        self.connection
+                            .send_frame(WINDOW_UPDATE, 0, 0, &size.to_be_bytes())?;
+                    }
+                    let data = unpad(flags, &payload)?;
+                    let end_stream = flags & END_STREAM != 0;
+                    let Some(incoming) = self.incoming.get_mut(&stream) else {
+                        continue;
+                    };
+                    if size > 0 && !end_stream {
+                        self.connection.send_frame(
+                            WINDOW_UPDATE,
+                            0,
+                            stream,
+                            &size.to_be_bytes(),
+                        )?;
+                    }
+                    incoming.body.extend(data);
+                    if incoming.body.len() > MAX_BODY {
+                        self.incoming.remove(&stream);
+                        self.connection.reset(stream, REFUSED_STREAM)?;
+                        self.connection.forget(stream);
+                        continue;
+                    }
+                    if end_stream {
+                        self.dispatch(stream, handler)?;
+                    }
+                }
+                // SETTINGS and PING acknowledgements, PRIORITY, unknown
+                // frame types
+                _ => {}
+            }
+        }
+    }
+
+    /// A complete header block: a new request, or the trailers of one
+    fn header_block<H>(
+        &mut self,
+        stream: u32,
+        end_stream: bool,
+        block: &[u8],
+        handler: &Arc<H>,
+    ) -> io::Result<()>
+    where
+        H: Fn(Request, Responder) + Send + Sync + 'static,
+    {
+        // Decoded whatever becomes of the stream: the table must follow
+        let headers = self.decoder.decode(block).inspect_err(|_| {
+            let _ = self.connection.goaway(self.last_stream, COMPRESSION_ERROR);
+        })?;
+
+        if self.incoming.contains_key(&stream) {
+            if end_stream {
+                self.dispatch(stream, handler)?;
+            }
+            return Ok(());
+        }
+        if stream.is_multiple_of(2) || stream <= self.last_stream {
+            return Err(invalid_data("bad stream identifier"));
+        }
+        self.last_stream = stream;
+        if self.incoming.len() >= MAX_STREAMS {
+            return self.connection.reset(stream, REFUSED_STREAM);
+        }
+
+        let mut request = Request {
+            method: String::new(),
+            path: String::new(),
+            headers: Vec::new(),
+            body: Vec::new(),
+            peer: self.peer,
+        };
+        for (name, value) in headers {
+            match name.as_str() {
+                ":method" => request.method = value,
+                ":path" => request.path = value,
+                _ if name.starts_with(':') => {}
+                _ => request.headers.push((name, value)),
+            }
+        }
+        self.connection.open(stream);
+        self.incoming.insert(stream, request);
+        if end_stream {
+            self.dispatch(stream, handler)?;
+        }
+        Ok(())
+    }
+
+    /// Hand a complete request to the handler, on a thread of its own
+    fn dispatch<H>(&mut self, stream: u32, handler: &Arc<H>) -> io::Result<()>
+    where
+        H: Fn(Request, Responder) + Send + Sync + 'static,
+    {
+        let Some(request) = self.incoming.remove(&stream) else {
+            return Ok(());
+        };
+        let responder = Responder {
+            stream,
+            connection: Arc::clone(&self.connection),
+            ended: false,
+        };
+        let handler = Arc::clone(handler);
+        // Should the thread not start, the Responder goes down with the
+        // closure and the stream is reset
+        let _ = thread::Builder::new()
+            .name(format!("http2 stream {stream}"))
+            .spawn(move || handler(request, responder));
+        Ok(())
+    }
+}
+
+/// One frame: type, flags, stream and payload
+fn read_frame(reader: &mut impl Read) -> io::Result<(u8, u8, u32, Vec<u8>)> {
+    let mut header = [0; 9];
+    reader.read_exact(&mut header)?;
+    let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+    if length > DEFAULT_MAX_FRAME {
+        return Err(invalid_data("frame larger than SETTINGS_MAX_FRAME_SIZE"));
+    }
+    let stream = read_u32(&header, 5)? & 0x7fff_ffff;
+    let mut payload = vec![0; length];
+    reader.read_exact(&mut payload)?;
+    Ok((header[3], header[4], stream, payload))
+}
+
+/// The payload of a DATA or HEADERS frame without its padding
+fn unpad(flags: u8, payload: &[u8]) -> io::Result<&[u8]> {
+    if flags & PADDED == 0 {
+        return Ok(payload);
+    }
+    let padding = *payload.first().ok_or_else(|| invalid_data("short frame"))? as usize;
+    payload
+        .get(1..payload.len().saturating_sub(padding))
+        .filter(|_| padding < payload.len())
+        .ok_or_else(|| invalid_data("padding longer than the frame"))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> io::Result<u32> {
+    bytes
+        .get(at..at + 4)
+        .map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]]))
+        .ok_or_else(|| invalid_data("short frame"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// =============================================================================
+// Connection
+// =============================================================================
+
+/// The writing side of a connection, shared by its threads
+struct Connection {
+    state: Mutex<State>,
+
+    /// A window grew, a stream was reset or the connection closed
+    changed: Condvar,
+}
+
+struct State {
+    writer: TcpStream,
+
+    /// What the client lets us send on the connection
+    window: i64,
+
+    /// Window of new streams, SETTINGS_INITIAL_WINDOW_SIZE of the client
+    initial_window: i64,
+
+    /// SETTINGS_MAX_FRAME_SIZE of the client
+    max_frame: usize,
+
+    /// Window of each stream we may still send on
+    streams: HashMap<u32, i64>,
+    closed: bool,
+}
+
+impl Connection {
+    fn new(writer: TcpStream) -> Self {
+        Self {
+            state: Mutex::new(State {
+                writer,
+                window: DEFAULT_WINDOW,
+                initial_window: DEFAULT_WINDOW,
+                max_frame: DEFAULT_MAX_FRAME,
+                streams: HashMap::new(),
+                closed: false,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("http2 connection lock poisoned")
+    }
+
+    fn send_frame(&self, kind: u8, flags: u8, stream: u32, payload: &[u8]) -> io::Result<()> {
+        write_frame(&mut self.lock(), kind, flags, stream, payload)
+    }
+
+    /// A header block, split into CONTINUATION frames past the frame size
+    fn send_headers(&self, stream: u32, block: &[u8], end_stream: bool) -> io::Result<()> {
+        let mut state = self.lock();
+        if state.closed || !state.streams.contains_key(&stream) {
+            return Err(io::ErrorKind::ConnectionAborted.into());
+        }
+        let max_frame = state.max_frame;
+        let mut chunks = block.chunks(max_frame).peekable();
+        let mut kind = HEADERS;
+        let mut flags = if end_stream { END_STREAM } else { 0 };
+        loop {
+            let chunk = chunks.next().unwrap_or_default();
+            let last = chunks.peek().is_none();
+            if last {
+                flags |= END_HEADERS;
+            }
+            write_frame(&mut state, kind, flags, stream, chunk)?;
+            if last {
+                break;
+            }
+            (kind, flags) = (CONTINUATION, 0);
+        }
+        if end_stream {
+            state.streams.remove(&stream);
+        }
+        Ok(())
+    }
+
+    /// DATA within the windows, waiting for them to grow
+    fn send_data(&self, stream: u32, mut data: &[u8], end_stream: bool) -> io::Result<()> {
+        let mut state = self.lock();
+        loop {
+            let deadline = Instant::now() + WINDOW_TIMEOUT;
+            let available = loop {
+                if state.closed {
+                    return Err(io::ErrorKind::ConnectionAborted.into());
+                }
+                let Some(&window) = state.streams.get(&stream) else {
+                    return Err(io::ErrorKind::ConnectionReset.into());
+                };
+                let available = window.min(state.window).max(0) as usize;
+                if available > 0 || data.is_empty() {
+                    break available;
+                }
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(io::ErrorKind::TimedOut.into());
+                }
+                state = self
+                    .changed
+                    .wait_timeout(state, deadline - now)
+                    .expect("http2 connection lock poisoned")
+                    .0;
+            };
+
+            let size = data.len().min(available).min(state.max_frame);
+            let (chunk, rest) = data.split_at(size);
+            let last = rest.is_empty();
+            let flags = if last && end_stream { END_STREAM } else { 0 };
+            write_frame(&mut state, DATA, flags, stream, chunk)?;
+            state.window -= size as i64;
+            if let Some(window) = state.streams.get_mut(&stream) {
+                *window -= size as i64;
+            }
+            data = rest;
+            if last {
+                if end_stream {
+                    state.streams.remove(&stream);
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    /// A stream the client opened, that we may answer on
+    fn open(&self, stream: u32) {
+        let mut state = self.lock();
+        let window = state.initial_window;
+        state.streams.insert(stream, window);
+    }
+
+    /// A stream over: reset, or ended by both sides
+    fn forget(&self, stream: u32) {
+        self.lock().streams.remove(&stream);
+        self.changed.notify_all();
+    }
+
+    fn is_closed(&self, stream: u32) -> bool {
+        let state = self.lock();
+        state.closed || !state.streams.contains_key(&stream)
+    }
+
+    fn reset(&self, stream: u32, code: u32) -> io::Result<()> {
+        self.send_frame(RST_STREAM, 0, stream, &code.to_be_bytes())
+    }
+
+    fn goaway(&self, last_stream: u32, code: u32) -> io::Result<()> {
+        let mut payload = last_stream.to_be_bytes().to_vec();
+        payload.extend(code.to_be_bytes());
+        self.send_frame(GOAWAY, 0, 0, &payload)
+    }
+
+    fn window_update(&self, stream: u32, increment: i64) -> io::Result<()> {
+        let mut state = self.lock();
+        let window = if stream == 0 {
+            Some(&mut state.window)
+        } else {
+            state.streams.get_mut(&stream)
+        };
+        if let Some(window) = window {
+            *window += increment;
+            if *window > MAX_WINDOW {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("flow control window overflow (error {FLOW_CONTROL_ERROR})"),
+                ));
+            }
+        }
+        drop(state);
+        self.changed.notify_all();
+        Ok(())
+    }
+
+    fn apply_settings(&self, payload: &[u8]) -> io::Result<()> {
+        if !payload.len().is_multiple_of(6) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "SETTINGS of {} bytes (error {FRAME_SIZE_ERROR})",
+                    payload.len()
+                ),
+            ));
+        }
+        let mut state = self.lock();
+        for setting in payload.chunks(6) {
+            let id = u16::from_be_bytes([setting[0], setting[1]]);
+            let value = read_u32(setting, 2)?;
+            match id {
+                SETTINGS_INITIAL_WINDOW_SIZE => {
+                    let value = i64::from(value);
+                    if value > MAX_WINDOW {
+                        return Err(invalid_data("SETTINGS_INITIAL_WINDOW_SIZE too large"));
+                    }
+                    let delta = value - state.initial_window;
+                    state.initial_window = value;
+                    for window in state.streams.values_mut() {
+                        *window += delta;
+                    }
+                }
+                SETTINGS_MAX_FRAME_SIZE => {
+                    if !(DEFAULT_MAX_FRAME..=0xff_ffff).contains(&(value as usize)) {
+                        return Err(invalid_data("SETTINGS_MAX_FRAME_SIZE out of range"));
+                    }
+                    state.max_frame = value as usize;
+                }
+                // The header table size only bounds an encoder that
+                // indexes, ours does not
+                _ => {}
+            }
+        }
+        drop(state);
+        self.changed.notify_all();
+        Ok(())
+    }
+
+    fn close(&self) {
+        let mut state = self.lock();
+        state.closed = true;
+        let _ = state.writer.shutdown(Shutdown::Both);
+        drop(state);
+        self.changed.notify_all();
+    }
+}
+
+fn write_frame(
+    state: &mut State,
+    kind: u8,
+    flags: u8,
+    stream: u32,
+    payload: &[u8],
+) -> io::Result<()> {
+    if state.closed {
+        return Err(io::ErrorKind::ConnectionAborted.into());
+    }
+    let mut frame = Vec::with_capacity(9 + payload.len());
+    frame.extend(&(payload.len() as u32).to_be_bytes()[1..]);
+    frame.push(kind);
+    frame.push(flags);
+    frame.extend(stream.to_be_bytes());
+    frame.extend(payload);
+    state.writer.write_all(&frame)
+}
+
+// =============================================================================
+// HPACK
+// =============================================================================
+
+/// The header block of a response or of its trailers: every field a
+/// literal without indexing, names in lower case
+fn encode_headers(status: Option<u16>, headers: &[(&str, &str)]) -> Vec<u8> {
+    let mut block = Vec::new();
+    match status {
+        // Indexed: `:status: 200` is entry 8 of the static table
+        Some(200) => block.push(0x88),
+        Some(status) => {
+            encode_integer(&mut block, 8, 4, 0x00);
+            encode_string(&mut block, &status.to_string());
+        }
+        None => {}
+    }
+    for (name, value) in headers {
+        block.push(0x00);
+        encode_string(&mut block, &name.to_ascii_lowercase());
+        encode_string(&mut block, value);
+    }
+    block
+}
+
+fn encode_integer(out: &mut Vec<u8>, value: usize, prefix_bits: u8, flags: u8) {
+    let max = (1 << prefix_bits) - 1;
+    if value < max {
+        out.push(flags | value as u8);
+        return;
+    }
+    out.push(flags | max as u8);
+    let mut rest = value - max;
+    while rest >= 0x80 {
+        out.push((rest & 0x7f) as u8 | 0x80);
+        rest >>= 7;
+    }
+    out.push(rest as u8);
+}
+
+fn encode_string(out: &mut Vec<u8>, value: &str) {
+    encode_integer(out, value.len(), 7, 0x00);
+    out.extend(value.as_bytes());
+}
+
+/// The header blocks of one connection, read in order: the dynamic table
+/// they build is the connection's
+struct Decoder {
+    /// Newest first
+    table: VecDeque<(String, String)>,
+    size: usize,
+    max_size: usize,
+}
+
+impl Decoder {
+    fn new() -> Self {
+        Self {
+            table: VecDeque::new(),
+            size: 0,
+            max_size: HEADER_TABLE_SIZE,
+        }
+    }
+
+    fn decode(&mut self, block: &[u8]) -> io::Result<Vec<(String, String)>> {
+        let mut headers = Vec::new();
+        let mut position = 0;
+        while let Some(&byte) = block.get(position) {
+            if byte & 0x80 != 0 {
+                // Indexed field
+                let index = decode_integer(block, &mut position, 7)?;
+                headers.push(self.entry(index)?);
+            } else if byte & 0x40 != 0 {
+                // Literal added to the table
+                let field = self.literal(block, &mut position, 6)?;
+                self.insert(field.clone());
+                headers.push(field);
+            } else if byte & 0x20 != 0 {
+                // Dynamic table size update
+                let size = decode_integer(block, &mut position, 5)?;
+                if size > HEADER_TABLE_SIZE {
+                    return Err(invalid_data("HPACK table size above the setting"));
+                }
+                self.max_size = size;
+                self.evict(0);
+            } else {
+                // Literal without indexing, or never indexed
+                headers.push(self.literal(block, &mut position, 4)?);
+            }
+        }
+        Ok(headers)
+    }
+
+    fn literal(
+        &self,
+        block: &[u8],
+        position: &mut usize,
+        prefix_bits: u8,
+    ) -> io::Result<(String, String)> {
+        let index = decode_integer(block, position, prefix_bits)?;
+        let name = match index {
+            0 => decode_string(block, position)?,
+            index => self.entry(index)?.0,
+        };
+        Ok((name, decode_string(block, position)?))
+    }
+
+    fn entry(&self, index: usize) -> io::Result<(String, String)> {
+        let field = match index {
+            0 => None,
+            1..=61 => STATIC_TABLE
+                .get(index - 1)
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+            _ => self.table.get(index - 62).cloned(),
+        };
+        field.ok_or_else(|| invalid_data("HPACK index out of the tables"))
+    }
+
+    fn insert(&mut self, field: (String, String)) {
+        let size = field.0.len() + field.1.len() + 32;
+        self.evict(size);
+        if size <= self.max_size {
+            self.size += size;
+            self.table.push_front(field);
+        }
+    }
+
+    /// Drop the oldest entries until `room` more fits
+    fn evict(&mut self, room: usize) {
+        while self.size + room > self.max_size {
+            let Some((name, value)) = self.table.pop_back() else {
+                break;
+            };
+            self.size -= name.len() + value.len() + 32;
+        }
+    }
+}
+
+fn decode_integer(block: &[u8], position: &mut usize, prefix_bits: u8) -> io::Result<usize> {
+    let truncated = || invalid_data("truncated HPACK integer");
+    let max = (1usize << prefix_bits) - 1;
+    let first = *block.get(*position).ok_or_else(truncated)? as usize & max;
+    *position += 1;
+    if first < max {
+        return Ok(first);
+    }
+    let mut value = max;
+    for shift in (0..28).step_by(7) {
+        let byte = *block.get(*position).ok_or_else(truncated)?;
+        *position += 1;
+        value += usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("HPACK integer too large"))
+}
+
+fn decode_string(block: &[u8], position: &mut usize) -> io::Result<String> {
+    let huffman = block.get(*position).is_some_and(|x| x & 0x80 != 0);
+    let length = decode_integer(block, position, 7)?;
+    let bytes = block
+        .get(*position..*position + length)
+        .ok_or_else(|| invalid_data("truncated HPACK string"))?;
+    *position += length;
+    let bytes = if huffman {
+        huffman_decode(bytes)?
+    } else {
+        bytes.to_vec()
+    };
+    String::from_utf8(bytes).map_err(|_| invalid_data("header not in UTF-8"))
+}
+
+/// The Huffman code as a binary tree: two children per node, an index of
+/// the tree when positive, a symbol (-1 - symbol) when negative, none at 0
+fn huffman_tree() -> &'static [[i32; 2]] {
+    static TREE: OnceLock<Vec<[i32; 2]>> = OnceLock::new();
+    TREE.get_or_init(|| {
+        let mut tree = vec![[0, 0]];
+        for (symbol, &(code, bits)) in HUFFMAN_CODES.iter().enumerate() {
+            let mut node = 0;
+            for bit in (0..bits).rev() {
+                let side = ((code >> bit) & 1) as usize;
+                if bit == 0 {
+                    tree[node][side] = -1 - symbol as i32;
+                } else {
+                    if tree[node][side] == 0 {
+                        tree.push([0, 0]);
+                        tree[node][side] = tree.len() as i32 - 1;
+                    }
+                    node = tree[node][side] as usize;
+                }
+            }
+        }
+        tree
+    })
+}
+
+fn huffman_decode(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let tree = huffman_tree();
+    let mut decoded = Vec::with_capacity(bytes.len() * 8 / 5);
+    let (mut node, mut pending, mut ones) = (0, 0, true);
+    for byte in bytes {
+        for bit in (0..8).rev() {
+            let side = usize::from((byte >> bit) & 1);
+            pending += 1;
+            ones &= side == 1;
+            match tree[node][side] {
+                0 => return Err(invalid_data("bad Huffman code")),
+                next if next > 0 => node = next as usize,
+                leaf => {
+                    let symbol = -1 - leaf;
+                    let symbol = u8::try_from(symbol).map_err(|_| invalid_data("Huffman EOS"))?;
+                    decoded.push(symbol);
+                    (node, pending, ones) = (0, 0, true);
+                }
+            }
+        }
+    }
+    // Padding: the first bits of EOS, all ones, shorter than a byte
+    if pending > 7 || !ones {
+        return Err(invalid_data("bad Huffman padding"));
+    }
+    Ok(decoded)
+}
+
+/// RFC 7541 Appendix A
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// RFC 7541 Appendix B: code and length in bits of each byte, then EOS
+const HUFFMAN_CODES: [(u32, u8); 257] = [
+    (0x1ff8, 13),
+    (0x7fffd8, 23),
+    (0xfffffe2, 28),
+    (0xfffffe3, 28),
+    (0xfffffe4, 28),
+    (0xfffffe5, 28),
+    (0xfffffe6, 28),
+    (0xfffffe7, 28),
+    (0xfffffe8, 28),
+    (0xffffea, 24),
+    (0x3ffffffc, 30),
+    (0xfffffe9, 28),
+    (0xfffffea, 28),
+    (0x3ffffffd, 30),
+    (0xfffffeb, 28),
+    (0xfffffec, 28),
+    (0xfffffed, 28),
+    (0xfffffee, 28),
+    (0xfffffef, 28),
+    (0xffffff0, 28),
+    (0xffffff1, 28),
+    (0xffffff2, 28),
+    (0x3ffffffe, 30),
+    (0xffffff3, 28),
+    (0xffffff4, 28),
+    (0xffffff5, 28),
+    (0xffffff6, 28),
+    (0xffffff7, 28),
+    (0xffffff8, 28),
+    (0xffffff9, 28),
+    (0xffffffa, 28),
+    (0xffffffb, 28),
+    (0x14, 6),
+    (0x3f8, 10),
+    (0x3f9, 10),
+    (0xffa, 12),
+    (0x1ff9, 13),
+    (0x15, 6),
+    (0xf8, 8),
+    (0x7fa, 11),
+    (0x3fa, 10),
+    (0x3fb, 10),
+    (0xf9, 8),
+    (0x7fb, 11),
+    (0xfa, 8),
+    (0x16, 6),
+    (0x17, 6),
+    (0x18, 6),
+    (0x0, 5),
+    (0x1, 5),
+    (0x2, 5),
+    (0x19, 6),
+    (0x1a, 6),
+    (0x1b, 6),
+    (0x1c, 6),
+    (0x1d, 6),
+    (0x1e, 6),
+    (0x1f, 6),
+    (0x5c, 7),
+    (0xfb, 8),
+    (0x7ffc, 15),
+    (0x20, 6),
+    (0xffb, 12),
+    (0x3fc, 10),
+    (0x1ffa, 13),
+    (0x21, 6),
+    (0x5d, 7),
+    (0x5e, 7),
+    (0x5f, 7),
+    (0x60, 7),
+    (0x61, 7),
+    (0x62, 7),
+    (0x63, 7),
+    (0x64, 7),
+    (0x65, 7),
+    (0x66, 7),
+    (0x67, 7),
+    (0x68, 7),
+    (0x69, 7),
+    (0x6a, 7),
+    (0x6b, 7),
+    (0x6c, 7),
+    (0x6d, 7),
+    (0x6e, 7),
+    (0x6f, 7),
+    (0x70, 7),
+    (0x71, 7),
+    (0x72, 7),
+    (0xfc, 8),
+    (0x73, 7),
+    (0xfd, 8),
+    (0x1ffb, 13),
+    (0x7fff0, 19),
+    (0x1ffc, 13),
+    (0x3ffc, 14),
+    (0x22, 6),
+    (0x7ffd, 15),
+    (0x3, 5),
+    (0x23, 6),
+    (0x4, 5),
+    (0x24, 6),
+    (0x5, 5),
+    (0x25, 6),
+    (0x26, 6),
+    (0x27, 6),
+    (0x6, 5),
+    (0x74, 7),
+    (0x75, 7),
+    (0x28, 6),
+    (0x29, 6),
+    (0x2a, 6),
+    (0x7, 5),
+    (0x2b, 6),
+    (0x76, 7),
+    (0x2c, 6),
+    (0x8, 5),
+    (0x9, 5),
+    (0x2d, 6),
+    (0x77, 7),
+    (0x78, 7),
+    (0x79, 7),
+    (0x7a, 7),
+    (0x7b, 7),
+    (0x7ffe, 15),
+    (0x7fc, 11),
+    (0x3ffd, 14),
+    (0x1ffd, 13),
+    (0xffffffc, 28),
+    (0xfffe6, 20),
+    (0x3fffd2, 22),
+    (0xfffe7, 20),
+    (0xfffe8, 20),
+    (0x3fffd3, 22),
+    (0x3fffd4, 22),
+    (0x3fffd5, 22),
+    (0x7fffd9, 23),
+    (0x3fffd6, 22),
+    (0x7fffda, 23),
+    (0x7fffdb, 23),
+    (0x7fffdc, 23),
+    (0x7fffdd, 23),
+    (0x7fffde, 23),
+    (0xffffeb, 24),
+    (0x7fffdf, 23),
+    (0xffffec, 24),
+    (0xffffed, 24),
+    (0x3fffd7, 22),
+    (0x7fffe0, 23),
+    (0xffffee, 24),
+    (0x7fffe1, 23),
+    (0x7fffe2, 23),
+    (0x7fffe3, 23),
+    (0x7fffe4, 23),
+    (0x1fffdc, 21),
+    (0x3fffd8, 22),
+    (0x7fffe5, 23),
+    (0x3fffd9, 22),
+    (0x7fffe6, 23),
+    (0x7fffe7, 23),
+    (0xffffef, 24),
+    (0x3fffda, 22),
+    (0x1fffdd, 21),
+    (0xfffe9, 20),
+    (0x3fffdb, 22),
+    (0x3fffdc, 22),
+    (0x7fffe8, 23),
+    (0x7fffe9, 23),
+    (0x1fffde, 21),
+    (0x7fffea, 23),
+    (0x3fffdd, 22),
+    (0x3fffde, 22),
+    (0xfffff0, 24),
+    (0x1fffdf, 21),
+    (0x3fffdf, 22),
+    (0x7fffeb, 23),
+    (0x7fffec, 23),
+    (0x1fffe0, 21),
+    (0x1fffe1, 21),
+    (0x3fffe0, 22),
+    (0x1fffe2, 21),
+    (0x7fffed, 23),
+    (0x3fffe1, 22),
+    (0x7fffee, 23),
+    (0x7fffef, 23),
+    (0xfffea, 20),
+    (0x3fffe2, 22),
+    (0x3fffe3, 22),
+    (0x3fffe4, 22),
+    (0x7ffff0, 23),
+    (0x3fffe5, 22),
+    (0x3fffe6, 22),
+    (0x7ffff1, 23),
+    (0x3ffffe0, 26),
+    (0x3ffffe1, 26),
+    (0xfffeb, 20),
+    (0x7fff1, 19),
+    (0x3fffe7, 22),
+    (0x7ffff2, 23),
+    (0x3fffe8, 22),
+    (0x1ffffec, 25),
+    (0x3ffffe2, 26),
+    (0x3ffffe3, 26),
+    (0x3ffffe4, 26),
+    (0x7ffffde, 27),
+    (0x7ffffdf, 27),
+    (0x3ffffe5, 26),
+    (0xfffff1, 24),
+    (0x1ffffed, 25),
+    (0x7fff2, 19),
+    (0x1fffe3, 21),
+    (0x3ffffe6, 26),
+    (0x7ffffe0, 27),
+    (0x7ffffe1, 27),
+    (0x3ffffe7, 26),
+    (0x7ffffe2, 27),
+    (0xfffff2, 24),
+    (0x1fffe4, 21),
+    (0x1fffe5, 21),
+    (0x3ffffe8, 26),
+    (0x3ffffe9, 26),
+    (0xffffffd, 28),
+    (0x7ffffe3, 27),
+    (0x7ffffe4, 27),
+    (0x7ffffe5, 27),
+    (0xfffec, 20),
+    (0xfffff3, 24),
+    (0xfffed, 20),
+    (0x1fffe6, 21),
+    (0x3fffe9, 22),
+    (0x1fffe7, 21),
+    (0x1fffe8, 21),
+    (0x7ffff3, 23),
+    (0x3fffea, 22),
+    (0x3fffeb, 22),
+    (0x1ffffee, 25),
+    (0x1ffffef, 25),
+    (0xfffff4, 24),
+    (0xfffff5, 24),
+    (0x3ffffea, 26),
+    (0x7ffff4, 23),
+    (0x3ffffeb, 26),
+    (0x7ffffe6, 27),
+    (0x3ffffec, 26),
+    (0x3ffffed, 26),
+    (0x7ffffe7, 27),
+    (0x7ffffe8, 27),
+    (0x7ffffe9, 27),
+    (0x7ffffea, 27),
+    (0x7ffffeb, 27),
+    (0xffffffe, 28),
+    (0x7ffffec, 27),
+    (0x7ffffed, 27),
+    (0x7ffffee, 27),
+    (0x7ffffef, 27),
+    (0x7fffff0, 27),
+    (0x3ffffee, 26),
+    (0x3fffffff, 30),
+];
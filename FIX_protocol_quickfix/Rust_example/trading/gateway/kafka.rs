@@ -0,0 +1,556 @@
+// =============================================================================
+// Minimal Kafka Producer
+// =============================================================================
+// Publishes keyed records to one Kafka topic so downstream systems (settlement,
+// analytics) receive trades without opening a second FIX session.
+//
+// Only what a fire-and-forget producer needs is implemented, over std TCP:
+//
+//   Metadata v1  -> brokers and partition leaders of the topic
+//   Produce  v3  -> one RecordBatch (magic 2) per partition, acks=1
+//
+// Records are partitioned like the Java client's default partitioner
+// (murmur2 of the key), so every event of one ClOrdID lands on the same
+// partition, in order. Publishing only queues the record: a background thread
+// batches, sends, and on failure refreshes metadata and retries once before
+// dropping the batch with an error line.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    net::TcpStream,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const API_PRODUCE: i16 = 0;
+const API_METADATA: i16 = 3;
+
+/// Most records sent in one Produce request
+const MAX_BATCH: usize = 500;
+
+/// Broker side timeout for acks=1
+const PRODUCE_TIMEOUT_MS: i32 = 5_000;
+
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct KafkaConfig {
+    /// Bootstrap brokers, `host:port`
+    pub brokers: Vec<String>,
+    pub topic: String,
+    pub client_id: String,
+}
+
+impl KafkaConfig {
+    /// Build from a comma separated broker list: `"k1:9092,k2:9092"`
+    pub fn new(brokers: &str, topic: &str, client_id: &str) -> Self {
+        Self {
+            brokers: brokers
+                .split(',')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(str::to_string)
+                .collect(),
+            topic: topic.to_string(),
+            client_id: client_id.to_string(),
+        }
+    }
+}
+
+struct Record {
+    key: String,
+    value: String,
+    timestamp_ms: i64,
+}
+
+// =============================================================================
+// Publisher
+// =============================================================================
+
+pub struct KafkaPublisher {
+    records: Sender<Record>,
+}
+
+impl KafkaPublisher {
+    /// Fetch the topic metadata, then start the sender thread
+    ///
+    /// Fails early when no bootstrap broker answers or the topic is unknown.
+    pub fn start(config: KafkaConfig) -> io::Result<Self> {
+        let mut producer = Producer::new(config);
+        producer.refresh_metadata()?;
+        println!(
+            ">> Kafka: topic {} has {} partition(s)",
+            producer.config.topic,
+            producer.leaders.len()
+        );
+
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("kafka-producer".to_string())
+            .spawn(move || producer.run(receiver))?;
+
+        Ok(Self {
+            records: sender,
+        })
+    }
+
+    /// Queue a record, never blocks on the network
+    pub fn publish(&self, key: &str, value: String) {
+        let record = Record {
+            key: key.to_string(),
+            value,
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |x| x.as_millis() as i64),
+        };
+        let _ = self.records.send(record);
+    }
+}
+
+// =============================================================================
+// Producer (sender thread)
+// =============================================================================
+
+struct Producer {
+    config: KafkaConfig,
+
+    /// node id -> "host:port"
+    brokers: HashMap<i32, String>,
+
+    /// Leader node id of each partition, indexed by partition
+    leaders: Vec<i32>,
+
+    /// Open connections by node id
+    connections: HashMap<i32, TcpStream>,
+    correlation_id: i32,
+}
+
+impl Producer {
+    fn new(config: KafkaConfig) -> Self {
+        Self {
+            config,
+            brokers: HashMap::new(),
+            leaders: Vec::new(),
+            connections: HashMap::new(),
+            correlation_id: 0,
+        }
+    }
+
+    fn run(mut self, receiver: Receiver<Record>) {
+        while let Ok(first) = receiver.recv() {
+            let mut batch = vec![first];
+            while batch.len() < MAX_BATCH {
+                match receiver.try_recv() {
+                    Ok(record) => batch.push(record),
+                    Err(_) => break,
+                }
+            }
+
+            if let Err(err) = self.send(&batch) {
+                // Leadership may have moved: reconnect and try once more
+                self.connections.clear();
+                let retry = self.refresh_metadata().and_then(|()| self.send(&batch));
+                if let Err(retry_err) = retry {
+                    eprintln!(
+                        "kafka: dropped {} record(s): {err} / retry: {retry_err}",
+                        batch.len()
+                    );
+                }
+            }
+        }
+    }
+
+    /// Send a batch, one Produce request per leader
+    fn send(&mut self, records: &[Record]) -> io::Result<()> {
+        if self.leaders.is_empty() {
+            return Err(io_error("no partition metadata"));
+        }
+
+        let mut by_partition: HashMap<i32, Vec<&Record>> = HashMap::new();
+        for record in records {
+            let partition = partition(record.key.as_bytes(), self.leaders.len());
+            by_partition
+                .entry(partition as i32)
+                .or_default()
+                .push(record);
+        }
+
+        let mut by_leader: HashMap<i32, Vec<(i32, Vec<&Record>)>> = HashMap::new();
+        for (partition, records) in by_partition {
+            let leader = self.leaders[partition as usize];
+            by_leader
+                .entry(leader)
+                .or_default()
+                .push((partition, records));
+        }
+
+        for (leader, partitions) in by_leader {
+            let mut body = Vec::new();
+            put_i16(&mut body, -1); // transactional_id: null
+            put_i16(&mut body, 1); // acks
+            put_i32(&mut body, PRODUCE_TIMEOUT_MS);
+            put_i32(&mut body, 1); // one topic
+            put_string(&mut body, &self.config.topic);
+            put_i32(&mut body, partitions.len() as i32);
+            for (partition, records) in &partitions {
+                put_i32(&mut body, *partition);
+                let batch = record_batch(records);
+                put_i32(&mut body, batch.len() as i32);
+                body.extend_from_slice(&batch);
+            }
+
+            let response = self.request(leader, API_PRODUCE, 3, &body)?;
+            check_produce_response(&response)?;
+        }
+        Ok(())
+    }
+
+    /// Ask any bootstrap broker for the brokers and partition leaders
+    fn refresh_metadata(&mut self) -> io::Result<()> {
+        let mut body = Vec::new();
+        put_i32(&mut body, 1);
+        put_string(&mut body, &self.config.topic);
+
+        let mut last_error = io_error("no bootstrap broker configured");
+        for address in self.config.brokers.clone() {
+            let result = connect(&address).and_then(|mut stream| {
+                self.correlation_id += 1;
+                exchange(
+                    &mut stream,
+                    API_METADATA,
+                    1,
+                    self.correlation_id,
+                    &self.config.client_id,
+                    &body,
+                )
+            });
+            match result.and_then(|response| self.apply_metadata(&response)) {
+                Ok(()) => return Ok(()),
+                Err(err) => last_error = err,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn apply_metadata(&mut self, response: &[u8]) -> io::Result<()> {
+        let mut reader = Reader::new(response);
+        reader.i32()?; // correlation id
+
+        let mut brokers = HashMap::new();
+        for _ in 0..reader.i32()? {
+            let node_id = reader.i32()?;
+            let host = reader.string()?;
+            let port = reader.i32()?;
+            reader.string()?; // rack
+            brokers.insert(node_id, format!("{host}:{port}"));
+        }
+        reader.i32()?; // controller id
+
+        let mut leaders = Vec::new();
+        for _ in 0..reader.i32()? {
+            let error_code = reader.i16()?;
+            let name = reader.string()?;
+            reader.i8()?; // is_internal
+            let mut partitions = Vec::new();
+            for _ in 0..reader.i32()? {
+                reader.i16()?; // partition error code
+                let partition = reader.i32()?;
+                let leader = reader.i32()?;
+                for _ in 0..2 {
+                    // replicas, isr
+                    for _ in 0..reader.i32()? {
+                        reader.i32()?;
+                    }
+                }
+                partitions.push((partition, leader));
+            }
+
+            if name == self.config.topic {
+                if error_code != 0 {
+                    return Err(io_error(&format!("topic {name}: error code {error_code}")));
+                }
+                partitions.sort();
+                leaders = partitions.into_iter().map(|(_, leader)| leader).collect();
+            }
+        }
+
+        if leaders.is_empty() {
+            return Err(io_error(&format!("topic {} not found", self.config.topic)));
+        }
+        self.brokers = brokers;
+        self.leaders = leaders;
+        Ok(())
+    }
+
+    fn request(
+        &mut self,
+        node_id: i32,
+        api_key: i16,
+        version: i16,
+        body: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        if !self.connections.contains_key(&node_id) {
+            let address = self
+                .brokers
+                .get(&node_id)
+                .ok_or_else(|| io_error(&format!("unknown broker {node_id}")))?;
+            self.connections.insert(node_id, connect(address)?);
+        }
+        let stream = self
+            .connections
+            .get_mut(&node_id)
+            .expect("connection just inserted");
+
+        self.correlation_id += 1;
+        let result = exchange(
+            stream,
+            api_key,
+            version,
+            self.correlation_id,
+            &self.config.client_id,
+            body,
+        );
+        if result.is_err() {
+            self.connections.remove(&node_id);
+        }
+        result
+    }
+}
+
+fn connect(address: &str) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(SOCKET_TIMEOUT))?;
+    stream.set_write_timeout(Some(SOCKET_TIMEOUT))?;
+    Ok(stream)
+}
+
+/// Write one size-prefixed request and read the size-prefixed response
+fn exchange(
+    stream: &mut TcpStream,
+    api_key: i16,
+    version: i16,
+    correlation_id: i32,
+    client_id: &str,
+    body: &[u8],
+) -> io::Result<Vec<u8>> {
+    let mut request = Vec::with_capacity(body.len() + 32);
+    put_i16(&mut request, api_key);
+    put_i16(&mut request, version);
+    put_i32(&mut request, correlation_id);
+    put_string(&mut request, client_id);
+    request.extend_from_slice(body);
+
+    stream.write_all(&(request.len() as i32).to_be_bytes())?;
+    stream.write_all(&request)?;
+
+    let mut size = [0; 4];
+    stream.read_exact(&mut size)?;
+    let mut response = vec![0; i32::from_be_bytes(size).max(0) as usize];
+    stream.read_exact(&mut response)?;
+    Ok(response)
+}
+
+fn check_produce_response(response: &[u8]) -> io::Result<()> {
+    let mut reader = Reader::new(response);
+    reader.i32()?; // correlation id
+    for _ in 0..reader.i32()? {
+        let topic = reader.string()?;
+        for _ in 0..reader.i32()? {
+            let partition = reader.i32()?;
+            let error_code = reader.i16()?;
+            reader.i64()?; // base offset
+            reader.i64()?; // log append time
+            if error_code != 0 {
+                return Err(io_error(&format!(
+                    "produce to {topic}[{partition}]: error code {error_code}"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn io_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// =============================================================================
+// RecordBatch (magic 2) Encoding
+// =============================================================================
+
+fn record_batch(records: &[&Record]) -> Vec<u8> {
+    let first_timestamp = records.first().map_or(0, |x| x.timestamp_ms);
+    let max_timestamp = records.iter().map(|x| x.timestamp_ms).max().unwrap_or(0);
+
+    // Everything covered by the CRC: attributes .. records
+    let mut tail = Vec::new();
+    put_i16(&mut tail, 0); // attributes: no compression
+    put_i32(&mut tail, records.len() as i32 - 1); // last offset delta
+    put_i64(&mut tail, first_timestamp);
+    put_i64(&mut tail, max_timestamp);
+    put_i64(&mut tail, -1); // producer id
+    put_i16(&mut tail, -1); // producer epoch
+    put_i32(&mut tail, -1); // base sequence
+    put_i32(&mut tail, records.len() as i32);
+
+    for (offset, record) in records.iter().enumerate() {
+        let mut body = Vec::new();
+        body.push(0); // attributes
+        put_varint(&mut body, record.timestamp_ms - first_timestamp);
+        put_varint(&mut body, offset as i64);
+        put_varint(&mut body, record.key.len() as i64);
+        body.extend_from_slice(record.key.as_bytes());
+        put_varint(&mut body, record.value.len() as i64);
+        body.extend_from_slice(record.value.as_bytes());
+        put_varint(&mut body, 0); // headers
+
+        put_varint(&mut tail, body.len() as i64);
+        tail.extend_from_slice(&body);
+    }
+
+    let mut batch = Vec::with_capacity(tail.len() + 21);
+    put_i64(&mut batch, 0); // base offset
+    put_i32(&mut batch, (tail.len() + 9) as i32); // length after this field
+    put_i32(&mut batch, -1); // partition leader epoch
+    batch.push(2); // magic
+    batch.extend_from_slice(&crc32c(&tail).to_be_bytes());
+    batch.extend_from_slice(&tail);
+    batch
+}
+
+fn put_i16(buf: &mut Vec<u8>, value: i16) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_i32(buf: &mut Vec<u8>, value: i32) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_i64(buf: &mut Vec<u8>, value: i64) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_string(buf: &mut Vec<u8>, value: &str) {
+    put_i16(buf, value.len() as i16);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+/// Zigzag varint, as used inside records
+fn put_varint(buf: &mut Vec<u8>, value: i64) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        buf.push((zigzag as u8) | 0x80);
+        zigzag >>= 7;
+    }
+    buf.push(zigzag as u8);
+}
+
+/// CRC-32C (Castagnoli), bitwise: the checksum of a RecordBatch
+pub fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0x82F6_3B78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Partition of a record keyed `key` in a topic of `partitions` (not 0), the
+/// one the Java client's default partitioner picks
+pub fn partition(key: &[u8], partitions: usize) -> usize {
+    murmur2(key) as usize % partitions
+}
+
+/// Kafka's murmur2, masked positive like `Utils.toPositive`
+pub fn murmur2(data: &[u8]) -> u32 {
+    const SEED: u32 = 0x9747_b28c;
+    const M: u32 = 0x5bd1_e995;
+    const R: u32 = 24;
+
+    let mut h = SEED ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (index, byte) in tail.iter().enumerate().rev() {
+            h ^= u32::from(*byte) << (8 * index);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h & 0x7fff_ffff
+}
+
+// =============================================================================
+// Response Reader
+// =============================================================================
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(io_error("truncated response"));
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn i8(&mut self) -> io::Result<i8> {
+        Ok(self.take(1)?[0] as i8)
+    }
+
+    fn i16(&mut self) -> io::Result<i16> {
+        Ok(i16::from_be_bytes(
+            self.take(2)?.try_into().expect("2 bytes"),
+        ))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_be_bytes(
+            self.take(4)?.try_into().expect("4 bytes"),
+        ))
+    }
+
+    fn i64(&mut self) -> io::Result<i64> {
+        Ok(i64::from_be_bytes(
+            self.take(8)?.try_into().expect("8 bytes"),
+        ))
+    }
+
+    /// Nullable string: length -1 reads as empty
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.i16()?;
+        if len < 0 {
+            return Ok(String::new());
+        }
+        Ok(String::from_utf8_lossy(self.take(len as usize)?).into_owned())
+    }
+}
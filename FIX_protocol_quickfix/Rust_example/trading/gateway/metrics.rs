@@ -0,0 +1,624 @@
+// =============================================================================
+// Prometheus Metrics Exporter
+// =============================================================================
+// Collects FIX session metrics and serves them over HTTP in the Prometheus
+// text exposition format, so the example applications can be scraped and
+// graphed like a production gateway.
+//
+// Exported series:
+// - fix_messages_total{session,direction,msg_type}   counter
+// - fix_rejects_total{session,msg_type}               counter (3, 9, j)
+// - fix_dry_run_messages_total{session,msg_type}      counter (not sent)
+// - fix_inbound_throttled_total{session,level}        counter (warn,
+//                                                     throttle, disconnect)
+// - fix_logged_on{session}                            gauge (0/1)
+// - fix_logons_total{session}                         counter
+// - fix_inbound_gap_max_seconds{session}              gauge
+// - fix_seconds_since_last_inbound{session}           gauge
+// - fix_send_latency_seconds{session}                 histogram
+// - fix_heartbeat_rtt_seconds{session}                gauge (last TestRequest round trip)
+// - fix_missed_heartbeats_total{session}              counter
+// - fix_failovers_total{session,gateway}              counter (switches to
+//                                                     primary, backup)
+// - fix_on_backup_gateway{session}                    gauge (0/1)
+// - fix_webhook_deliveries_total{event,outcome}       counter (delivered,
+//                                                     retried, failed)
+// - fix_lane_events_total{lane}                       counter (fast, batch)
+// - fix_lane_wait_seconds{lane}                       histogram (time queued)
+// - fix_lane_batches_total                            counter
+//
+// The message counters and inbound gaps come from the lock-free registry
+// (session::stats), the rest from a Mutex the callbacks take for rarer
+// events. Served by the embedded HTTP server (gateway/http.rs) on GET /metrics.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use quickfix::SessionId;
+
+#[cfg(feature = "runtime")]
+use crate::session::lanes::{Lane, LaneObserver};
+use crate::{
+    gateway::http::{self, Response},
+    session::{
+        session_label,
+        stats::{MessageStats, SessionStats},
+        Direction,
+    },
+};
+
+/// Upper bounds (in seconds) of the send latency histogram buckets
+const LATENCY_BUCKETS: [f64; 9] = [
+    0.000_05, 0.000_1, 0.000_25, 0.000_5, 0.001, 0.005, 0.01, 0.05, 0.1,
+];
+
+// =============================================================================
+// Per-Session State
+// =============================================================================
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if value <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+#[derive(Default)]
+struct SessionMetrics {
+    rejects: HashMap<String, u64>,
+    dry_run: HashMap<String, u64>,
+    throttled: HashMap<String, u64>,
+    logged_on: bool,
+    logons: u64,
+    send_latency: Histogram,
+    heartbeat_rtt: Option<Duration>,
+    missed_heartbeats: u64,
+
+    /// Switches by the gateway switched to (session::failover)
+    failovers: HashMap<String, u64>,
+
+    /// None for a session without a backup gateway
+    on_backup: Option<bool>,
+}
+
+// =============================================================================
+// Metrics Registry
+// =============================================================================
+// Shared between the FIX callbacks (writers) and the HTTP thread (reader).
+// Every message goes to the sharded counters of session::stats; a single
+// Mutex keeps the rest simple, taken for logons, rejects and the like.
+// =============================================================================
+
+#[derive(Default)]
+pub struct Metrics {
+    /// Messages by session, MsgType and direction
+    stats: Arc<MessageStats>,
+
+    sessions: Mutex<HashMap<String, SessionMetrics>>,
+
+    /// Webhook attempts by (event, outcome); not per session
+    webhooks: Mutex<HashMap<(String, String), u64>>,
+
+    /// Time events queued, by lane (session::lanes); not per session
+    lanes: Mutex<HashMap<String, Histogram>>,
+    lane_batches: Mutex<u64>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The message counters, for the REPL `stats` command and the like
+    pub fn stats(&self) -> &Arc<MessageStats> {
+        &self.stats
+    }
+
+    fn with_session<T>(&self, session: &SessionId, f: impl FnOnce(&mut SessionMetrics) -> T) -> T {
+        self.with_label(&session_label(session), f)
+    }
+
+    fn with_label<T>(&self, label: &str, f: impl FnOnce(&mut SessionMetrics) -> T) -> T {
+        let mut sessions = self.sessions.lock().expect("metrics lock poisoned");
+        f(sessions.entry(label.to_string()).or_default())
+    }
+
+    /// Count one message; call from the to_/from_ admin/app callbacks
+    pub fn on_message(&self, session: &SessionId, direction: Direction, msg_type: &str) {
+        let label = session_label(session);
+        self.stats.record(&label, direction, msg_type);
+
+        if matches!(msg_type, "3" | "9" | "j") {
+            self.with_label(&label, |metrics| {
+                *metrics.rejects.entry(msg_type.to_string()).or_default() += 1
+            });
+        }
+    }
+
+    pub fn on_logon(&self, session: &SessionId) {
+        let label = session_label(session);
+        // Gaps across a reconnection are not heartbeat gaps
+        self.stats.on_logon(&label);
+        self.with_label(&label, |metrics| {
+            metrics.logged_on = true;
+            metrics.logons += 1;
+        });
+    }
+
+    pub fn on_logout(&self, session: &SessionId) {
+        self.with_session(session, |metrics| metrics.logged_on = false);
+    }
+
+    /// Count one message dropped instead of sent (session::dry_run)
+    pub fn on_dry_run(&self, label: &str, msg_type: &str) {
+        self.with_label(label, |metrics| {
+            *metrics.dry_run.entry(msg_type.to_string()).or_default() += 1
+        });
+    }
+
+    /// Count one inbound message over a rate limit, by the level it reached
+    /// (sim::throttle)
+    pub fn on_throttle(&self, label: &str, level: &str) {
+        self.with_label(label, |metrics| {
+            *metrics.throttled.entry(level.to_string()).or_default() += 1
+        });
+    }
+
+    /// Record how long a `send_to_target` call took
+    pub fn observe_send_latency(&self, session: &SessionId, latency: Duration) {
+        self.with_session(session, |metrics| {
+            metrics.send_latency.observe(latency.as_secs_f64())
+        });
+    }
+
+    /// Record the round trip of a TestRequest answered by a Heartbeat
+    pub fn observe_heartbeat_rtt(&self, session: &SessionId, rtt: Duration) {
+        self.with_session(session, |metrics| metrics.heartbeat_rtt = Some(rtt));
+    }
+
+    /// Count heartbeat intervals that went by without inbound traffic
+    pub fn on_missed_heartbeats(&self, label: &str, count: u32) {
+        self.with_label(label, |metrics| {
+            metrics.missed_heartbeats += u64::from(count)
+        });
+    }
+
+    /// Count one switch of a session to its `primary` or `backup` gateway
+    /// (session::failover)
+    pub fn on_failover(&self, label: &str, gateway: &str) {
+        self.with_label(label, |metrics| {
+            *metrics.failovers.entry(gateway.to_string()).or_default() += 1;
+            metrics.on_backup = Some(gateway == "backup");
+        });
+    }
+
+    /// Count one webhook attempt: `delivered`, `retried` or `failed`
+    pub fn on_webhook(&self, event: &str, outcome: &str) {
+        let mut webhooks = self.webhooks.lock().expect("metrics lock poisoned");
+        *webhooks
+            .entry((event.to_string(), outcome.to_string()))
+            .or_default() += 1;
+    }
+
+    /// Record an event handed out by the event queues after `waited` in
+    /// `lane` (`fast` or `batch`)
+    pub fn on_lane_event(&self, lane: &str, waited: Duration) {
+        let mut lanes = self.lanes.lock().expect("metrics lock poisoned");
+        lanes
+            .entry(lane.to_string())
+            .or_default()
+            .observe(waited.as_secs_f64());
+    }
+
+    /// Count one batch released by the batch lane
+    pub fn on_lane_batch(&self) {
+        *self.lane_batches.lock().expect("metrics lock poisoned") += 1;
+    }
+
+    // =========================================================================
+    // Prometheus Text Format
+    // =========================================================================
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let stats = self.stats.snapshot();
+        let sessions = self.sessions.lock().expect("metrics lock poisoned");
+        let mut labels: Vec<_> = sessions.keys().collect();
+        labels.sort();
+
+        let mut out = String::with_capacity(4096);
+
+        header(
+            &mut out,
+            "fix_messages_total",
+            "counter",
+            "FIX messages by session, direction and type",
+        );
+        for session in &stats {
+            for direction in [Direction::Inbound, Direction::Outbound] {
+                for (msg_type, count) in counts(session, direction) {
+                    let _ = writeln!(
+                        out,
+                        "fix_messages_total{{session=\"{}\",direction=\"{}\",msg_type=\"{}\"}} {count}",
+                        escape(&session.session),
+                        direction.as_label(),
+                        escape(msg_type),
+                    );
+                }
+            }
+        }
+
+        header(
+            &mut out,
+            "fix_rejects_total",
+            "counter",
+            "Reject, OrderCancelReject and BusinessMessageReject received or sent",
+        );
+        for label in &labels {
+            let mut rejects: Vec<_> = sessions[*label].rejects.iter().collect();
+            rejects.sort();
+            for (msg_type, count) in rejects {
+                let _ = writeln!(
+                    out,
+                    "fix_rejects_total{{session=\"{}\",msg_type=\"{}\"}} {count}",
+                    escape(label),
+                    escape(msg_type),
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "fix_dry_run_messages_total",
+            "counter",
+            "Messages dropped instead of sent, dry run being on",
+        );
+        for label in &labels {
+            let mut dry_run: Vec<_> = sessions[*label].dry_run.iter().collect();
+            dry_run.sort();
+            for (msg_type, count) in dry_run {
+                let _ = writeln!(
+                    out,
+                    "fix_dry_run_messages_total{{session=\"{}\",msg_type=\"{}\"}} {count}",
+                    escape(label),
+                    escape(msg_type),
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "fix_inbound_throttled_total",
+            "counter",
+            "Inbound messages over a venue rate limit, by escalation level",
+        );
+        for label in &labels {
+            let mut throttled: Vec<_> = sessions[*label].throttled.iter().collect();
+            throttled.sort();
+            for (level, count) in throttled {
+                let _ = writeln!(
+                    out,
+                    "fix_inbound_throttled_total{{session=\"{}\",level=\"{}\"}} {count}",
+                    escape(label),
+                    escape(level),
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "fix_logged_on",
+            "gauge",
+            "1 when the session is logged on",
+        );
+        for label in &labels {
+            let value = u8::from(sessions[*label].logged_on);
+            let _ = writeln!(
+                out,
+                "fix_logged_on{{session=\"{}\"}} {value}",
+                escape(label)
+            );
+        }
+
+        header(
+            &mut out,
+            "fix_logons_total",
+            "counter",
+            "Successful logons since start",
+        );
+        for label in &labels {
+            let value = sessions[*label].logons;
+            let _ = writeln!(
+                out,
+                "fix_logons_total{{session=\"{}\"}} {value}",
+                escape(label)
+            );
+        }
+
+        header(
+            &mut out,
+            "fix_inbound_gap_max_seconds",
+            "gauge",
+            "Largest gap between two inbound messages",
+        );
+        for session in &stats {
+            let value = session.max_inbound_gap.as_secs_f64();
+            let _ = writeln!(
+                out,
+                "fix_inbound_gap_max_seconds{{session=\"{}\"}} {value}",
+                escape(&session.session)
+            );
+        }
+
+        header(
+            &mut out,
+            "fix_seconds_since_last_inbound",
+            "gauge",
+            "Time since the last inbound message",
+        );
+        for session in &stats {
+            if let Some(since) = session.since_inbound {
+                let value = since.as_secs_f64();
+                let _ = writeln!(
+                    out,
+                    "fix_seconds_since_last_inbound{{session=\"{}\"}} {value}",
+                    escape(&session.session)
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "fix_heartbeat_rtt_seconds",
+            "gauge",
+            "Round trip of the last TestRequest answered by a Heartbeat",
+        );
+        for label in &labels {
+            if let Some(rtt) = sessions[*label].heartbeat_rtt {
+                let _ = writeln!(
+                    out,
+                    "fix_heartbeat_rtt_seconds{{session=\"{}\"}} {}",
+                    escape(label),
+                    rtt.as_secs_f64()
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "fix_missed_heartbeats_total",
+            "counter",
+            "Heartbeat intervals without any inbound message",
+        );
+        for label in &labels {
+            let value = sessions[*label].missed_heartbeats;
+            let _ = writeln!(
+                out,
+                "fix_missed_heartbeats_total{{session=\"{}\"}} {value}",
+                escape(label)
+            );
+        }
+
+        header(
+            &mut out,
+            "fix_failovers_total",
+            "counter",
+            "Switches of a session to its primary or backup gateway",
+        );
+        for label in &labels {
+            let mut failovers: Vec<_> = sessions[*label].failovers.iter().collect();
+            failovers.sort();
+            for (gateway, count) in failovers {
+                let _ = writeln!(
+                    out,
+                    "fix_failovers_total{{session=\"{}\",gateway=\"{}\"}} {count}",
+                    escape(label),
+                    escape(gateway),
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "fix_on_backup_gateway",
+            "gauge",
+            "1 when the session connects to its backup gateway",
+        );
+        for label in &labels {
+            if let Some(on_backup) = sessions[*label].on_backup {
+                let _ = writeln!(
+                    out,
+                    "fix_on_backup_gateway{{session=\"{}\"}} {}",
+                    escape(label),
+                    u8::from(on_backup)
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "fix_send_latency_seconds",
+            "histogram",
+            "Duration of send_to_target calls",
+        );
+        for label in &labels {
+            let histogram = &sessions[*label].send_latency;
+            let label = escape(label);
+            for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+                let _ = writeln!(
+                    out,
+                    "fix_send_latency_seconds_bucket{{session=\"{label}\",le=\"{bound}\"}} {count}"
+                );
+            }
+            let count = histogram.count;
+            let _ = writeln!(
+                out,
+                "fix_send_latency_seconds_bucket{{session=\"{label}\",le=\"+Inf\"}} {count}"
+            );
+            let _ = writeln!(
+                out,
+                "fix_send_latency_seconds_sum{{session=\"{label}\"}} {}",
+                histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "fix_send_latency_seconds_count{{session=\"{label}\"}} {count}"
+            );
+        }
+
+        let webhooks = self.webhooks.lock().expect("metrics lock poisoned");
+        if !webhooks.is_empty() {
+            header(
+                &mut out,
+                "fix_webhook_deliveries_total",
+                "counter",
+                "Webhook attempts by event and outcome",
+            );
+            let mut webhooks: Vec<_> = webhooks.iter().collect();
+            webhooks.sort();
+            for ((event, outcome), count) in webhooks {
+                let _ = writeln!(
+                    out,
+                    "fix_webhook_deliveries_total{{event=\"{}\",outcome=\"{}\"}} {count}",
+                    escape(event),
+                    escape(outcome),
+                );
+            }
+        }
+
+        let lanes = self.lanes.lock().expect("metrics lock poisoned");
+        if !lanes.is_empty() {
+            header(
+                &mut out,
+                "fix_lane_events_total",
+                "counter",
+                "Events handed out by the event queues, by lane",
+            );
+            let mut lanes: Vec<_> = lanes.iter().collect();
+            lanes.sort_by(|a, b| a.0.cmp(b.0));
+            for (lane, histogram) in &lanes {
+                let _ = writeln!(
+                    out,
+                    "fix_lane_events_total{{lane=\"{}\"}} {}",
+                    escape(lane),
+                    histogram.count
+                );
+            }
+
+            header(
+                &mut out,
+                "fix_lane_wait_seconds",
+                "histogram",
+                "Time events spent queued, by lane",
+            );
+            for (lane, histogram) in &lanes {
+                let lane = escape(lane);
+                for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+                    let _ = writeln!(
+                        out,
+                        "fix_lane_wait_seconds_bucket{{lane=\"{lane}\",le=\"{bound}\"}} {count}"
+                    );
+                }
+                let count = histogram.count;
+                let _ = writeln!(
+                    out,
+                    "fix_lane_wait_seconds_bucket{{lane=\"{lane}\",le=\"+Inf\"}} {count}"
+                );
+                let _ = writeln!(
+                    out,
+                    "fix_lane_wait_seconds_sum{{lane=\"{lane}\"}} {}",
+                    histogram.sum
+                );
+                let _ = writeln!(
+                    out,
+                    "fix_lane_wait_seconds_count{{lane=\"{lane}\"}} {count}"
+                );
+            }
+
+            header(
+                &mut out,
+                "fix_lane_batches_total",
+                "counter",
+                "Batches released by the batch lane",
+            );
+            let batches = *self.lane_batches.lock().expect("metrics lock poisoned");
+            let _ = writeln!(out, "fix_lane_batches_total {batches}");
+        }
+
+        out
+    }
+}
+
+/// The event queues report to the registry
+#[cfg(feature = "runtime")]
+impl LaneObserver for Metrics {
+    fn on_lane_event(&self, lane: Lane, waited: Duration) {
+        Metrics::on_lane_event(self, lane.as_str(), waited);
+    }
+
+    fn on_lane_batch(&self, _len: usize) {
+        Metrics::on_lane_batch(self);
+    }
+}
+
+/// Non-zero message counts of a session one way, by MsgType
+fn counts(session: &SessionStats, direction: Direction) -> impl Iterator<Item = (&str, u64)> {
+    session.by_type.iter().filter_map(move |x| {
+        let count = match direction {
+            Direction::Inbound => x.inbound,
+            Direction::Outbound => x.outbound,
+        };
+        (count > 0).then_some((x.msg_type.as_str(), count))
+    })
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Escape a label value (backslash, double quote and newline)
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// =============================================================================
+// HTTP Endpoint
+// =============================================================================
+
+/// Start serving metrics on `0.0.0.0:<port>/metrics` in a background thread
+pub fn spawn_server(port: u16, metrics: Arc<Metrics>) -> io::Result<()> {
+    http::spawn_server("metrics-http", port, move |request| {
+        match request.segments().as_slice() {
+            ["metrics"] => Response {
+                status: "200 OK",
+                content_type: "text/plain; version=0.0.4",
+                body: metrics.render(),
+            },
+            _ => Response::not_found(),
+        }
+    })?;
+    println!(">> metrics available on http://0.0.0.0:{port}/metrics");
+    Ok(())
+}
@@ -0,0 +1,616 @@
+// =============================================================================
+// News Feed Ingestion
+// =============================================================================
+// Reads an external news / sentiment feed and hands every new headline,
+// normalized (trading::news), to a sink; usually the event channel, so
+// strategies get FixEvent::News next to the market data:
+//
+//   ws://host:port/path     WebSocket feed: one document per text message,
+//                           an optional subscribe message sent on connect
+//   http://host:port/path   REST endpoint polled every poll_interval
+//
+// Feeds repeat themselves (a poll returns the latest N articles, a WebSocket
+// feed may replay on reconnect), so articles are deduplicated by id over the
+// last MAX_SEEN ones. A lost connection or failed poll is logged and retried
+// with exponential backoff, up to MAX_BACKOFF.
+//
+// Each feed runs on its own thread until stopped, over plain std TCP like
+// the webhooks (put a TLS-terminating proxy in front of wss:// and https://
+// feeds). A silent
+// WebSocket is pinged every PING_INTERVAL and dropped when the ping goes
+// unanswered.
+// =============================================================================
+
+use std::{
+    collections::{hash_map::RandomState, HashSet, VecDeque},
+    error::Error,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    gateway::websocket::{accept_key, base64},
+    news::{normalize, NewsEvent, SymbolTagger},
+};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A REST response slower than this fails the poll
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// WebSocket silence before a ping, and before giving up after it
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Largest response body or WebSocket message accepted
+const MAX_DOCUMENT: usize = 16 * 1024 * 1024;
+
+/// Article ids remembered for deduplication
+const MAX_SEEN: usize = 10_000;
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum NewsError {
+    /// Not `ws://host[:port][/path]` or `http://host[:port][/path]`
+    InvalidUrl(String),
+
+    /// The feed thread could not be started
+    Io(io::Error),
+}
+
+impl fmt::Display for NewsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NewsError::InvalidUrl(url) => write!(
+                f,
+                "invalid news feed URL (expected ws://host[:port]/path or http://...): {url}"
+            ),
+            NewsError::Io(err) => write!(f, "news feed thread: {err}"),
+        }
+    }
+}
+
+impl Error for NewsError {}
+
+impl From<io::Error> for NewsError {
+    fn from(err: io::Error) -> Self {
+        NewsError::Io(err)
+    }
+}
+
+// =============================================================================
+// Configuration
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct NewsFeedConfig {
+    /// Source of articles that do not name one, and log prefix
+    pub name: String,
+
+    /// `ws://` for a WebSocket feed, `http://` for a polled REST endpoint
+    pub url: String,
+
+    /// REST only
+    pub poll_interval: Duration,
+
+    /// WebSocket only: text message sent after each connect, for feeds that
+    /// need a subscription
+    pub subscribe: Option<String>,
+}
+
+impl NewsFeedConfig {
+    pub fn new(url: &str) -> Self {
+        Self {
+            name: "news".to_string(),
+            url: url.to_string(),
+            poll_interval: Duration::from_secs(30),
+            subscribe: None,
+        }
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn with_subscribe(mut self, message: &str) -> Self {
+        self.subscribe = Some(message.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transport {
+    WebSocket,
+    Rest,
+}
+
+/// Host, port and path of a ws:// or http:// URL
+#[derive(Debug, Clone)]
+struct Endpoint {
+    transport: Transport,
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> Result<Self, NewsError> {
+        let invalid = || NewsError::InvalidUrl(url.to_string());
+        let (transport, rest) = if let Some(rest) = url.strip_prefix("ws://") {
+            (Transport::WebSocket, rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (Transport::Rest, rest)
+        } else {
+            return Err(invalid());
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            transport,
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let address = (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address"))?;
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        stream.set_write_timeout(Some(CONNECT_TIMEOUT))?;
+        Ok(stream)
+    }
+
+    /// `Host` header value
+    fn authority(&self) -> String {
+        if self.port == 80 {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+// =============================================================================
+// Feed
+// =============================================================================
+
+/// Receives the articles, on the feed thread
+type Sink = Box<dyn Fn(NewsEvent) + Send>;
+
+/// Handle on a running feed
+pub struct NewsFeed {
+    sink: Arc<Mutex<Option<Sink>>>,
+}
+
+impl NewsFeed {
+    /// Check the URL, then read the feed on a background thread until
+    /// stopped, calling `sink` once per new article
+    ///
+    /// # Arguments
+    /// * `config` - Where the feed is and how to read it
+    /// * `tagger` - Symbols to look for in headlines, on top of the feed's tags
+    /// * `sink` - Called on the feed thread, e.g. pushes FixEvent::News
+    pub fn start<F>(
+        config: NewsFeedConfig,
+        tagger: SymbolTagger,
+        sink: F,
+    ) -> Result<Self, NewsError>
+    where
+        F: Fn(NewsEvent) + Send + 'static,
+    {
+        let endpoint = Endpoint::parse(&config.url)?;
+        let sink: Arc<Mutex<Option<Sink>>> = Arc::new(Mutex::new(Some(Box::new(sink))));
+        let mut feed = Feed {
+            config,
+            endpoint,
+            tagger,
+            seen: Seen::default(),
+            sink: Arc::clone(&sink),
+        };
+
+        thread::Builder::new()
+            .name(format!("news-{}", feed.config.name))
+            .spawn(move || feed.run())?;
+        Ok(Self { sink })
+    }
+
+    /// Deliver nothing more and drop the sink now (and with it, say, the
+    /// event channel sender a consumer waits on); the thread ends on its next
+    /// wakeup
+    pub fn stop(&self) {
+        self.sink.lock().expect("news sink lock poisoned").take();
+    }
+}
+
+struct Feed {
+    config: NewsFeedConfig,
+    endpoint: Endpoint,
+    tagger: SymbolTagger,
+    seen: Seen,
+
+    /// Shared with NewsFeed; None once stopped
+    sink: Arc<Mutex<Option<Sink>>>,
+}
+
+impl Feed {
+    fn stopped(&self) -> bool {
+        self.sink.lock().expect("news sink lock poisoned").is_none()
+    }
+
+    fn run(&mut self) {
+        println!(">> news feed {} from {}", self.config.name, self.config.url);
+        let mut backoff = FIRST_BACKOFF;
+        while !self.stopped() {
+            let started = Instant::now();
+            let result = match self.endpoint.transport {
+                Transport::WebSocket => self.stream(),
+                Transport::Rest => self.poll(),
+            };
+
+            // A poll that worked, or a connection that lasted, starts the
+            // backoff over
+            if result.is_ok() || started.elapsed() > MAX_BACKOFF {
+                backoff = FIRST_BACKOFF;
+            }
+            match result {
+                Ok(()) if self.endpoint.transport == Transport::Rest => {
+                    thread::sleep(self.config.poll_interval);
+                }
+                // Closed by the server (or stopped): reconnect, without
+                // hammering it
+                Ok(()) => thread::sleep(FIRST_BACKOFF),
+                Err(_) if self.stopped() => {}
+                Err(err) => {
+                    eprintln!("news {}: {err}, retrying in {backoff:?}", self.config.name);
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+        }
+    }
+
+    /// Normalize a document and pass its new articles on
+    fn deliver(&mut self, document: &str) {
+        let Some(events) = normalize(document, &self.config.name, &self.tagger) else {
+            eprintln!("news {}: ignored a message that is not JSON", self.config.name);
+            return;
+        };
+        let sink = self.sink.lock().expect("news sink lock poisoned");
+        let Some(sink) = sink.as_ref() else {
+            return;
+        };
+        for event in events {
+            if self.seen.insert(&event.id) {
+                sink(event);
+            }
+        }
+    }
+
+    // =========================================================================
+    // REST
+    // =========================================================================
+
+    fn poll(&mut self) -> io::Result<()> {
+        let endpoint = &self.endpoint;
+        let mut stream = endpoint.connect()?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: trading-news\r\n\
+             Accept: application/json\r\nConnection: close\r\n\r\n",
+            endpoint.path,
+            endpoint.authority()
+        );
+        stream.write_all(request.as_bytes())?;
+
+        let mut reader = BufReader::new(stream);
+        let (status, headers) = read_head(&mut reader)?;
+        if !(200..300).contains(&status) {
+            return Err(invalid_data(format!("HTTP {status}")));
+        }
+        let chunked = header(&headers, "transfer-encoding")
+            .is_some_and(|x| x.eq_ignore_ascii_case("chunked"));
+        let body = if chunked {
+            read_chunked(&mut reader)?
+        } else if let Some(length) = header(&headers, "content-length") {
+            let length: usize = length
+                .parse()
+                .map_err(|_| invalid_data("bad Content-Length".to_string()))?;
+            if length > MAX_DOCUMENT {
+                return Err(invalid_data(format!("response of {length} bytes")));
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            body
+        } else {
+            let mut body = Vec::new();
+            reader.take(MAX_DOCUMENT as u64).read_to_end(&mut body)?;
+            body
+        };
+
+        let body = String::from_utf8(body).map_err(|_| invalid_data("body not UTF-8".into()))?;
+        self.deliver(&body);
+        Ok(())
+    }
+
+    // =========================================================================
+    // WebSocket
+    // =========================================================================
+
+    /// One connection, until the server closes it (Ok) or it fails
+    fn stream(&mut self) -> io::Result<()> {
+        let endpoint = &self.endpoint;
+        let stream = endpoint.connect()?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        let key = base64(&random_bytes::<16>());
+        let mut writer = stream.try_clone()?;
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: trading-news\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            endpoint.path,
+            endpoint.authority()
+        );
+        writer.write_all(request.as_bytes())?;
+        let mut reader = BufReader::new(stream);
+        let (status, headers) = read_head(&mut reader)?;
+        if status != 101 {
+            return Err(invalid_data(format!("handshake refused: HTTP {status}")));
+        }
+        if header(&headers, "sec-websocket-accept") != Some(accept_key(&key).as_str()) {
+            return Err(invalid_data("bad Sec-WebSocket-Accept".to_string()));
+        }
+        if let Some(message) = &self.config.subscribe {
+            writer.write_all(&client_frame(OPCODE_TEXT, message.as_bytes()))?;
+        }
+        reader.get_ref().set_read_timeout(Some(PING_INTERVAL))?;
+
+        let mut message = Vec::new();
+        let mut pinged = false;
+        while !self.stopped() {
+            let mut first = [0u8; 1];
+            match reader.read(&mut first) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) => pinged = false,
+                Err(err) if is_timeout(&err) && !pinged => {
+                    writer.write_all(&client_frame(OPCODE_PING, b""))?;
+                    pinged = true;
+                    continue;
+                }
+                Err(err) if is_timeout(&err) => {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "ping unanswered"));
+                }
+                Err(err) => return Err(err),
+            }
+
+            let (fin, opcode, payload) = read_frame(&mut reader, first[0])?;
+            match opcode {
+                OPCODE_CONTINUATION | OPCODE_TEXT | OPCODE_BINARY => {
+                    if message.len() + payload.len() > MAX_DOCUMENT {
+                        return Err(invalid_data("message too large".to_string()));
+                    }
+                    if opcode != OPCODE_CONTINUATION {
+                        message.clear();
+                    }
+                    message.extend_from_slice(&payload);
+                    if fin {
+                        match std::str::from_utf8(&message) {
+                            Ok(text) => self.deliver(text),
+                            Err(_) => eprintln!("news {}: non-UTF-8 message", self.config.name),
+                        }
+                        message.clear();
+                    }
+                }
+                OPCODE_CLOSE => {
+                    let _ = writer.write_all(&client_frame(OPCODE_CLOSE, &payload));
+                    return Ok(());
+                }
+                OPCODE_PING => writer.write_all(&client_frame(OPCODE_PONG, &payload))?,
+                _ => {}
+            }
+        }
+        let _ = writer.write_all(&client_frame(OPCODE_CLOSE, b""));
+        Ok(())
+    }
+}
+
+/// Ids of the last MAX_SEEN articles
+#[derive(Default)]
+struct Seen {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl Seen {
+    /// False if already seen
+    fn insert(&mut self, id: &str) -> bool {
+        if !self.ids.insert(id.to_string()) {
+            return false;
+        }
+        self.order.push_back(id.to_string());
+        if self.order.len() > MAX_SEEN {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+// =============================================================================
+// HTTP Helpers
+// =============================================================================
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn is_timeout(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+/// Status code and headers (names lowercase) of a response
+fn read_head(reader: &mut impl BufRead) -> io::Result<(u16, Vec<(String, String)>)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(|| invalid_data("malformed HTTP response".to_string()))?;
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok((status, headers));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(x, _)| x == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Body in `Transfer-Encoding: chunked`
+fn read_chunked(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let size = line.trim().split(';').next().unwrap_or_default();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| invalid_data("bad chunk size".to_string()))?;
+        if size == 0 {
+            return Ok(body);
+        }
+        if body.len() + size > MAX_DOCUMENT {
+            return Err(invalid_data("response too large".to_string()));
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        // CRLF after the chunk
+        line.clear();
+        reader.read_line(&mut line)?;
+    }
+}
+
+// =============================================================================
+// WebSocket Frames (client side, RFC 6455)
+// =============================================================================
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// Rest of a frame whose first byte was read: (FIN, opcode, payload)
+fn read_frame(reader: &mut impl Read, first: u8) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut second = [0u8; 1];
+    reader.read_exact(&mut second)?;
+    let masked = second[0] & 0x80 != 0;
+    let length = match second[0] & 0x7F {
+        126 => {
+            let mut bytes = [0u8; 2];
+            reader.read_exact(&mut bytes)?;
+            u64::from(u16::from_be_bytes(bytes))
+        }
+        127 => {
+            let mut bytes = [0u8; 8];
+            reader.read_exact(&mut bytes)?;
+            u64::from_be_bytes(bytes)
+        }
+        length => u64::from(length),
+    };
+    if length > MAX_DOCUMENT as u64 {
+        return Err(invalid_data(format!("frame of {length} bytes")));
+    }
+
+    // Servers do not mask, but nothing forbids reading it
+    let mut mask = [0u8; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0; length as usize];
+    reader.read_exact(&mut payload)?;
+    if masked {
+        for (index, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[index % 4];
+        }
+    }
+    Ok((first & 0x80 != 0, first & 0x0F, payload))
+}
+
+/// Masked, unfragmented frame (client to server)
+fn client_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len if len < 126 => frame.push(0x80 | len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    let mask = random_bytes::<4>();
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(index, byte)| byte ^ mask[index % 4]));
+    frame
+}
+
+/// Unpredictable bytes for handshake keys and masks (std's randomly seeded
+/// hasher; not for cryptography)
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut out = [0u8; N];
+    for chunk in out.chunks_mut(8) {
+        let value = RandomState::new().build_hasher().finish().to_be_bytes();
+        chunk.copy_from_slice(&value[..chunk.len()]);
+    }
+    out
+}
@@ -0,0 +1,624 @@
+// =============================================================================
+// Session Sharding Across Worker Processes
+// =============================================================================
+// One acceptor serves its sessions from one process, a thread each at best
+// (FixSocketServerKind::MultiThreaded). For very large session counts the
+// sessions are spread over worker processes instead, each running its own
+// Acceptor or Initiator, with a coordinator in front of them:
+//
+//   shard_of / assign  which worker a CompID belongs to: FNV-1a of the
+//                      CompID modulo the worker count, the same in every
+//                      process, build and platform
+//   ShardSpec          the `I/N` a worker is started with
+//   Coordinator        starts the workers, notices those that exit, asks
+//                      them all the same admin request
+//   merge_*            one answer out of theirs: arrays concatenated with a
+//                      "shard" field on each object, objects listed per
+//                      shard, Prometheus series labelled shard="I"
+//
+// Workers and coordinator share nothing but the workers' own admin HTTP API
+// (one request per connection, as gateway::http serves it): a worker that
+// dies takes its own sessions down and no other. Sessions are not moved
+// between workers; counterparties connect to the port of their shard.
+// =============================================================================
+
+use std::{
+    error::Error,
+    fmt::{self, Write as _},
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream},
+    process::{Child, ChildStdin, Command, ExitStatus, Stdio},
+    str::FromStr,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::json::json_escape;
+
+/// How long a worker has to answer an admin request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest answer read from a worker
+const MAX_REPLY: u64 = 16 * 1024 * 1024;
+
+/// How often `Coordinator::stop` checks whether the workers are gone
+const STOP_POLL: Duration = Duration::from_millis(50);
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ShardError {
+    /// Not `I/N` with I below N
+    InvalidSpec(String),
+
+    /// A worker process could not be started
+    Spawn { shard: usize, source: io::Error },
+
+    /// A worker did not answer, or not in HTTP
+    Unreachable { shard: usize, reason: String },
+}
+
+impl fmt::Display for ShardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSpec(spec) => {
+                write!(f, "invalid shard {spec}: expected I/N, I below N")
+            }
+            Self::Spawn { shard, source } => write!(f, "cannot start shard {shard}: {source}"),
+            Self::Unreachable { shard, reason } => {
+                write!(f, "shard {shard} did not answer: {reason}")
+            }
+        }
+    }
+}
+
+impl Error for ShardError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Spawn { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// =============================================================================
+// Shard Plan
+// =============================================================================
+
+/// FNV-1a, 64 bits: unlike std's hashers, the same everywhere and forever
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// The shard of a CompID, among `count`
+pub fn shard_of(comp_id: &str, count: usize) -> usize {
+    if count <= 1 {
+        return 0;
+    }
+    (fnv1a(comp_id.as_bytes()) % count as u64) as usize
+}
+
+/// The CompIDs of each of `count` shards, in the order given
+pub fn assign<'a>(comp_ids: impl IntoIterator<Item = &'a str>, count: usize) -> Vec<Vec<String>> {
+    let mut shards = vec![Vec::new(); count.max(1)];
+    for comp_id in comp_ids {
+        shards[shard_of(comp_id, count)].push(comp_id.to_string());
+    }
+    shards
+}
+
+/// Which shard a worker is, out of how many: `1/4`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardSpec {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl ShardSpec {
+    pub fn new(index: usize, count: usize) -> Result<Self, ShardError> {
+        if index >= count {
+            return Err(ShardError::InvalidSpec(format!("{index}/{count}")));
+        }
+        Ok(Self { index, count })
+    }
+
+    /// Whether the sessions of a counterparty belong to this shard
+    pub fn owns(&self, comp_id: &str) -> bool {
+        shard_of(comp_id, self.count) == self.index
+    }
+}
+
+impl FromStr for ShardSpec {
+    type Err = ShardError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = || ShardError::InvalidSpec(spec.to_string());
+        let (index, count) = spec.split_once('/').ok_or_else(invalid)?;
+        let index = index.trim().parse().map_err(|_| invalid())?;
+        let count = count.trim().parse().map_err(|_| invalid())?;
+        Self::new(index, count).map_err(|_| invalid())
+    }
+}
+
+impl fmt::Display for ShardSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+// =============================================================================
+// Worker Replies
+// =============================================================================
+
+/// One worker's answer to an admin request
+#[derive(Debug)]
+pub struct ShardReply {
+    pub shard: usize,
+
+    /// HTTP status code and body
+    pub result: Result<(u16, String), ShardError>,
+}
+
+impl ShardReply {
+    /// The HTTP status code, if the worker answered
+    pub fn status(&self) -> Option<u16> {
+        self.result.as_ref().ok().map(|(status, _)| *status)
+    }
+
+    /// The body of a 2xx answer
+    pub fn body(&self) -> Option<&str> {
+        match &self.result {
+            Ok((200..=299, body)) => Some(body),
+            _ => None,
+        }
+    }
+}
+
+/// One request to a worker's admin API on this host
+fn fetch(
+    port: u16,
+    method: &str,
+    path: &str,
+    operator: &str,
+    body: &str,
+) -> io::Result<(u16, String)> {
+    let address = SocketAddr::from(([127, 0, 0, 1], port));
+    let mut stream = TcpStream::connect_timeout(&address, REQUEST_TIMEOUT)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\nX-Operator: {operator}\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()?;
+
+    let mut reply = String::new();
+    stream.take(MAX_REPLY).read_to_string(&mut reply)?;
+    let malformed = || io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response");
+    let (head, body) = reply.split_once("\r\n\r\n").ok_or_else(malformed)?;
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(malformed)?;
+    Ok((status, body.to_string()))
+}
+
+// =============================================================================
+// Coordinator
+// =============================================================================
+
+struct Worker {
+    shard: usize,
+    admin_port: u16,
+    child: Child,
+
+    /// The worker's console, to type lines on
+    stdin: Option<ChildStdin>,
+    exit: Option<ExitStatus>,
+
+    /// Whether `exited` returned it already
+    reported: bool,
+}
+
+impl Worker {
+    /// Look whether it exited, and forget its console if it did
+    fn poll(&mut self) {
+        if self.exit.is_none() {
+            self.exit = self.child.try_wait().ok().flatten();
+        }
+        if self.exit.is_some() {
+            self.stdin = None;
+        }
+    }
+
+    fn status(&self) -> WorkerStatus {
+        WorkerStatus {
+            shard: self.shard,
+            pid: self.child.id(),
+            admin_port: self.admin_port,
+            exit: self.exit,
+        }
+    }
+}
+
+/// A worker process as the coordinator last saw it
+#[derive(Debug, Clone)]
+pub struct WorkerStatus {
+    pub shard: usize,
+    pub pid: u32,
+    pub admin_port: u16,
+
+    /// None while it runs
+    pub exit: Option<ExitStatus>,
+}
+
+impl WorkerStatus {
+    pub fn to_json(&self) -> String {
+        let exit = self.exit.map_or("null".to_string(), |x| {
+            format!("\"{}\"", json_escape(&x.to_string()))
+        });
+        format!(
+            "{{\"shard\":{},\"pid\":{},\"admin_port\":{},\"running\":{},\"exit\":{exit}}}",
+            self.shard,
+            self.pid,
+            self.admin_port,
+            self.exit.is_none()
+        )
+    }
+}
+
+/// The worker processes of one coordinator
+///
+/// Shared between the console and the admin API thread.
+#[derive(Default)]
+pub struct Coordinator {
+    workers: Mutex<Vec<Worker>>,
+}
+
+impl Coordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a worker whose admin API listens on `admin_port`
+    ///
+    /// Its stdin is kept, for `send_line` and `stop`; its stdout and stderr
+    /// are the coordinator's. Returns its process id.
+    pub fn spawn(
+        &self,
+        shard: usize,
+        admin_port: u16,
+        mut command: Command,
+    ) -> Result<u32, ShardError> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|source| ShardError::Spawn { shard, source })?;
+        let pid = child.id();
+        let stdin = child.stdin.take();
+        let mut workers = self.workers.lock().expect("workers lock poisoned");
+        workers.push(Worker {
+            shard,
+            admin_port,
+            child,
+            stdin,
+            exit: None,
+            reported: false,
+        });
+        Ok(pid)
+    }
+
+    /// Every worker, by shard, exit status updated
+    pub fn workers(&self) -> Vec<WorkerStatus> {
+        let mut workers = self.workers.lock().expect("workers lock poisoned");
+        let mut out: Vec<_> = workers
+            .iter_mut()
+            .map(|x| {
+                x.poll();
+                x.status()
+            })
+            .collect();
+        out.sort_by_key(|x| x.shard);
+        out
+    }
+
+    /// The workers that exited since the last call
+    pub fn exited(&self) -> Vec<WorkerStatus> {
+        let mut workers = self.workers.lock().expect("workers lock poisoned");
+        let mut out = Vec::new();
+        for worker in workers.iter_mut().filter(|x| !x.reported) {
+            worker.poll();
+            if worker.exit.is_some() {
+                worker.reported = true;
+                out.push(worker.status());
+            }
+        }
+        out
+    }
+
+    /// Type a line on the console of every worker running
+    pub fn send_line(&self, line: &str) {
+        let mut workers = self.workers.lock().expect("workers lock poisoned");
+        for worker in workers.iter_mut() {
+            worker.poll();
+            let Some(stdin) = &mut worker.stdin else {
+                continue;
+            };
+            if let Err(err) = writeln!(stdin, "{line}").and_then(|()| stdin.flush()) {
+                eprintln!("Shard {}: cannot write to its console: {err}", worker.shard);
+                worker.stdin = None;
+            }
+        }
+    }
+
+    /// Ask every worker the same request, all at once; the replies come by
+    /// shard
+    ///
+    /// # Arguments
+    /// * `operator` - Sent as X-Operator, for the workers' audit logs
+    pub fn request(&self, method: &str, path: &str, operator: &str, body: &str) -> Vec<ShardReply> {
+        let targets: Vec<_> = self
+            .workers()
+            .iter()
+            .map(|x| (x.shard, x.admin_port))
+            .collect();
+        thread::scope(|scope| {
+            let pending: Vec<_> = targets
+                .iter()
+                .map(|(shard, port)| {
+                    let handle = scope.spawn(move || fetch(*port, method, path, operator, body));
+                    (*shard, handle)
+                })
+                .collect();
+            pending
+                .into_iter()
+                .map(|(shard, handle)| {
+                    let result = match handle.join() {
+                        Ok(result) => result.map_err(|err| ShardError::Unreachable {
+                            shard,
+                            reason: err.to_string(),
+                        }),
+                        Err(_) => Err(ShardError::Unreachable {
+                            shard,
+                            reason: "request thread panicked".to_string(),
+                        }),
+                    };
+                    ShardReply { shard, result }
+                })
+                .collect()
+        })
+    }
+
+    /// Ask one worker
+    pub fn request_one(
+        &self,
+        shard: usize,
+        method: &str,
+        path: &str,
+        operator: &str,
+        body: &str,
+    ) -> ShardReply {
+        let port = self
+            .workers()
+            .iter()
+            .find(|x| x.shard == shard)
+            .map(|x| x.admin_port);
+        let result = match port {
+            Some(port) => {
+                fetch(port, method, path, operator, body).map_err(|err| ShardError::Unreachable {
+                    shard,
+                    reason: err.to_string(),
+                })
+            }
+            None => Err(ShardError::Unreachable {
+                shard,
+                reason: "no such shard".to_string(),
+            }),
+        };
+        ShardReply { shard, result }
+    }
+
+    /// Type `q` on every worker's console and close it, wait up to `grace`
+    /// for them to exit, kill those still running
+    pub fn stop(&self, grace: Duration) {
+        self.send_line("q");
+        let mut workers = self.workers.lock().expect("workers lock poisoned");
+        for worker in workers.iter_mut() {
+            worker.stdin = None;
+        }
+
+        let deadline = Instant::now() + grace;
+        loop {
+            let mut running = 0;
+            for worker in workers.iter_mut() {
+                worker.poll();
+                running += usize::from(worker.exit.is_none());
+            }
+            if running == 0 || Instant::now() >= deadline {
+                break;
+            }
+            thread::sleep(STOP_POLL);
+        }
+
+        for worker in workers.iter_mut().filter(|x| x.exit.is_none()) {
+            eprintln!(
+                "Shard {} did not stop within {grace:?}, killing it",
+                worker.shard
+            );
+            let _ = worker.child.kill();
+            worker.exit = worker.child.wait().ok();
+        }
+    }
+}
+
+// =============================================================================
+// Merging Replies
+// =============================================================================
+
+/// Top-level elements of a JSON array, as text; None if it is not one
+fn array_elements(text: &str) -> Option<Vec<&str>> {
+    let inner = text.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let mut out = Vec::new();
+    let (mut depth, mut in_string, mut escaped, mut start) = (0usize, false, false, 0);
+    for (index, c) in inner.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth = depth.checked_sub(1)?,
+            ',' if depth == 0 => {
+                out.push(inner[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = inner[start..].trim();
+    if !last.is_empty() {
+        out.push(last);
+    }
+    Some(out)
+}
+
+/// A JSON object with the shard it came from as its first field; anything
+/// else as it is
+fn tagged(object: &str, shard: usize) -> String {
+    match object.strip_prefix('{') {
+        Some(rest) if rest.trim_start().starts_with('}') => format!("{{\"shard\":{shard}}}"),
+        Some(rest) => format!("{{\"shard\":{shard},{rest}"),
+        None => object.to_string(),
+    }
+}
+
+/// The JSON arrays of the workers as one, each object tagged with its
+/// shard; workers that failed, or did not answer an array, are left out
+pub fn merge_arrays(replies: &[ShardReply]) -> String {
+    let elements: Vec<_> = replies
+        .iter()
+        .filter_map(|reply| Some((reply.shard, array_elements(reply.body()?)?)))
+        .flat_map(|(shard, elements)| elements.into_iter().map(move |x| tagged(x, shard)))
+        .collect();
+    format!("[{}]", elements.join(","))
+}
+
+/// The JSON answer of each worker, tagged with its shard, in one array; a
+/// worker that failed is `{"shard":I,"error":"..."}` there
+pub fn merge_objects(replies: &[ShardReply]) -> String {
+    let objects: Vec<_> = replies
+        .iter()
+        .map(|reply| match &reply.result {
+            Ok((200..=299, body)) if body.trim_start().starts_with('{') => {
+                tagged(body.trim(), reply.shard)
+            }
+            Ok((status, body)) => format!(
+                "{{\"shard\":{},\"error\":\"HTTP {status}\",\"body\":\"{}\"}}",
+                reply.shard,
+                json_escape(body.trim())
+            ),
+            Err(err) => format!(
+                "{{\"shard\":{},\"error\":\"{}\"}}",
+                reply.shard,
+                json_escape(&err.to_string())
+            ),
+        })
+        .collect();
+    format!("[{}]", objects.join(","))
+}
+
+/// A sample line with `shard="I"` as its first label
+fn labelled(sample: &str, shard: usize) -> String {
+    match sample.find(['{', ' ']) {
+        Some(at) if sample[at..].starts_with("{}") => {
+            format!(
+                "{}{{shard=\"{shard}\"}}{}",
+                &sample[..at],
+                &sample[at + 2..]
+            )
+        }
+        Some(at) if sample[at..].starts_with('{') => {
+            format!("{}{{shard=\"{shard}\",{}", &sample[..at], &sample[at + 1..])
+        }
+        Some(at) => format!("{}{{shard=\"{shard}\"}}{}", &sample[..at], &sample[at..]),
+        None => sample.to_string(),
+    }
+}
+
+/// The Prometheus metrics of the workers as one exposition
+///
+/// Each family comes once, with the HELP and TYPE of the first worker that
+/// has it, followed by the samples of every worker labelled `shard="I"`.
+/// `fix_shard_up{shard}` says which workers answered.
+pub fn merge_metrics(replies: &[ShardReply]) -> String {
+    // Families by name, in the order they first appear
+    let mut families: Vec<(String, Vec<String>, Vec<String>)> = Vec::new();
+    for reply in replies {
+        let Some(body) = reply.body() else {
+            continue;
+        };
+        let mut current: Option<usize> = None;
+        for line in body.lines().filter(|x| !x.trim().is_empty()) {
+            let name = match line.strip_prefix("# ") {
+                Some(comment) => match comment.split_whitespace().collect::<Vec<_>>()[..] {
+                    ["HELP" | "TYPE", name, ..] => name,
+                    _ => continue,
+                },
+                None if current.is_some() => "",
+                None => line.split(['{', ' ']).next().unwrap_or_default(),
+            };
+            if !name.is_empty() {
+                let index = match families.iter().position(|(x, _, _)| x == name) {
+                    Some(index) => index,
+                    None => {
+                        families.push((name.to_string(), Vec::new(), Vec::new()));
+                        families.len() - 1
+                    }
+                };
+                current = Some(index);
+            }
+            let Some(index) = current else {
+                continue;
+            };
+            let (_, headers, samples) = &mut families[index];
+            if line.starts_with('#') {
+                let kind = &line[..6];
+                if !headers.iter().any(|x| x.starts_with(kind)) {
+                    headers.push(line.to_string());
+                }
+            } else {
+                samples.push(labelled(line, reply.shard));
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for (_, headers, samples) in &families {
+        for line in headers.iter().chain(samples) {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out.push_str("# HELP fix_shard_up Whether the worker answered the coordinator\n");
+    out.push_str("# TYPE fix_shard_up gauge\n");
+    for reply in replies {
+        let up = u8::from(reply.body().is_some());
+        let _ = writeln!(out, "fix_shard_up{{shard=\"{}\"}} {up}", reply.shard);
+    }
+    out
+}
@@ -0,0 +1,276 @@
+// =============================================================================
+// SMTP Mail Sink
+// =============================================================================
+// Sends reports and notices by mail through a relay, e.g. the trade
+// confirmations the sell_side venue produces at end of day.
+//
+// Only the plain SMTP dialogue is implemented, over std TCP:
+//
+//   220 greeting <- EHLO -> MAIL FROM -> RCPT TO (each) -> DATA -> QUIT
+//
+// with a multipart/mixed MIME body (text part + base64 attachments). There is
+// no STARTTLS and no AUTH: point it at a local relay (postfix, an smtp4dev or
+// MailHog container) that is trusted to forward the mail.
+//
+// A MailNotifier mails alerts (trading::alerts), one mail each, from a thread
+// of its own so that the caller never waits for the relay.
+// =============================================================================
+
+use std::{
+    error::Error,
+    fmt::{self, Write as _},
+    io::{self, BufRead, BufReader, Write},
+    net::TcpStream,
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    alerts::{Alert, Notifier},
+    gateway::websocket::base64,
+    time,
+};
+
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Base64 lines in attachments, RFC 2045 limit
+const BASE64_LINE: usize = 76;
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+pub enum SmtpError {
+    Io(io::Error),
+
+    /// The server answered a command with an unexpected code
+    Rejected { command: String, reply: String },
+}
+
+impl fmt::Display for SmtpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmtpError::Io(err) => write!(f, "smtp: {err}"),
+            SmtpError::Rejected { command, reply } => {
+                write!(f, "smtp: {command} rejected: {reply}")
+            }
+        }
+    }
+}
+
+impl Error for SmtpError {}
+
+impl From<io::Error> for SmtpError {
+    fn from(err: io::Error) -> Self {
+        SmtpError::Io(err)
+    }
+}
+
+// =============================================================================
+// Messages
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub file_name: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: Vec<String>,
+    pub subject: String,
+
+    /// Plain text part
+    pub body: String,
+    pub attachments: Vec<Attachment>,
+}
+
+// =============================================================================
+// Sink
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct SmtpSink {
+    /// Relay, `host:port`
+    pub server: String,
+
+    /// Envelope and header sender
+    pub from: String,
+
+    /// Name announced in EHLO
+    pub hello: String,
+}
+
+impl SmtpSink {
+    pub fn new(server: &str, from: &str) -> Self {
+        Self {
+            server: server.to_string(),
+            from: from.to_string(),
+            hello: "localhost".to_string(),
+        }
+    }
+
+    /// Deliver one mail to the relay; blocks for the whole dialogue
+    pub fn send(&self, email: &Email) -> Result<(), SmtpError> {
+        let stream = TcpStream::connect(&self.server)?;
+        stream.set_read_timeout(Some(SOCKET_TIMEOUT))?;
+        stream.set_write_timeout(Some(SOCKET_TIMEOUT))?;
+        let mut session = Session {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+
+        session.expect("greeting", 220)?;
+        session.command(&format!("EHLO {}", self.hello), 250)?;
+        session.command(&format!("MAIL FROM:<{}>", self.from), 250)?;
+        for to in &email.to {
+            session.command(&format!("RCPT TO:<{to}>"), 250)?;
+        }
+        session.command("DATA", 354)?;
+        session.writer.write_all(self.render(email).as_bytes())?;
+        session.command(".", 250)?;
+        // The mail is accepted at this point, a failed QUIT does not matter
+        let _ = session.command("QUIT", 221);
+        Ok(())
+    }
+
+    /// Headers and MIME body, dot-stuffed, CRLF line endings
+    fn render(&self, email: &Email) -> String {
+        let boundary = format!("=_trading_{}", time::unix_now());
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "From: {}\r\n\
+             To: {}\r\n\
+             Subject: {}\r\n\
+             Date: {}\r\n\
+             MIME-Version: 1.0\r\n\
+             Content-Type: multipart/mixed; boundary=\"{boundary}\"\r\n\
+             \r\n",
+            self.from,
+            email.to.join(", "),
+            email.subject,
+            time::rfc5322(time::unix_now()),
+        );
+
+        let _ = write!(
+            out,
+            "--{boundary}\r\n\
+             Content-Type: text/plain; charset=utf-8\r\n\
+             \r\n"
+        );
+        for line in email.body.lines() {
+            // A line starting with '.' would be read as the end of DATA
+            if line.starts_with('.') {
+                out.push('.');
+            }
+            out.push_str(line);
+            out.push_str("\r\n");
+        }
+
+        for attachment in &email.attachments {
+            let _ = write!(
+                out,
+                "--{boundary}\r\n\
+                 Content-Type: {}; name=\"{}\"\r\n\
+                 Content-Disposition: attachment; filename=\"{}\"\r\n\
+                 Content-Transfer-Encoding: base64\r\n\
+                 \r\n",
+                attachment.content_type, attachment.file_name, attachment.file_name
+            );
+            let encoded = base64(&attachment.data);
+            for chunk in encoded.as_bytes().chunks(BASE64_LINE) {
+                // base64 is ASCII
+                out.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+                out.push_str("\r\n");
+            }
+        }
+
+        let _ = write!(out, "--{boundary}--\r\n");
+        out
+    }
+}
+
+// =============================================================================
+// Alerts by Mail
+// =============================================================================
+
+/// Mails each alert to the recipients, subject first line
+#[derive(Debug, Clone)]
+pub struct MailNotifier {
+    pub sink: SmtpSink,
+    pub to: Vec<String>,
+}
+
+impl MailNotifier {
+    pub fn new(sink: SmtpSink, to: Vec<String>) -> Self {
+        Self { sink, to }
+    }
+}
+
+impl Notifier for MailNotifier {
+    fn alert(&self, alert: &Alert) {
+        let email = Email {
+            to: self.to.clone(),
+            subject: format!("[{}] {} {}", alert.kind, alert.subject, alert.summary),
+            body: format!("{alert}\n\nat {}\n", time::rfc5322(alert.time)),
+            attachments: Vec::new(),
+        };
+        let sink = self.sink.clone();
+        let spawned = thread::Builder::new()
+            .name("alert-mail".to_string())
+            .spawn(move || {
+                if let Err(err) = sink.send(&email) {
+                    eprintln!("alert mail to {}: {err}", email.to.join(", "));
+                }
+            });
+        if let Err(err) = spawned {
+            eprintln!("alert mail: {err}");
+        }
+    }
+}
+
+// =============================================================================
+// SMTP Dialogue
+// =============================================================================
+
+struct Session {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Session {
+    fn command(&mut self, command: &str, code: u16) -> Result<(), SmtpError> {
+        self.writer.write_all(format!("{command}\r\n").as_bytes())?;
+        self.expect(command, code)
+    }
+
+    /// Read a reply, multi-line ones included (`250-...` up to `250 ...`)
+    fn expect(&mut self, command: &str, code: u16) -> Result<(), SmtpError> {
+        let mut reply = String::new();
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(SmtpError::Io(io::ErrorKind::UnexpectedEof.into()));
+            }
+            reply.push_str(&line);
+            if line.as_bytes().get(3) != Some(&b'-') {
+                break;
+            }
+        }
+
+        // 251 (user not local, will forward) is as good as 250
+        let received: u16 = reply.get(..3).and_then(|x| x.parse().ok()).unwrap_or(0);
+        if received == code || (code == 250 && received == 251) {
+            Ok(())
+        } else {
+            Err(SmtpError::Rejected {
+                command: command.to_string(),
+                reply: reply.trim_end().to_string(),
+            })
+        }
+    }
+}
@@ -0,0 +1,601 @@
+// =============================================================================
+// Outbound Webhooks
+// =============================================================================
+// POSTs a small JSON document to external endpoints when something they care
+// about happens, for systems that want a push without running Kafka:
+//
+//   order_filled    an order is completely filled
+//   session_down    a FIX session logged out
+//   limit_breach    pre-trade risk refused an order
+//   alert           an alert condition met or resolved (trading::alerts)
+//
+// Each endpoint picks its events and may give a template per event, where
+// {{name}} is replaced by the JSON-escaped value of a field (see `notify`), so
+//
+//   {"text":"{{symbol}} {{side}} {{quantity}}@{{price}} filled"}
+//
+// fits a chat webhook. Without a template the body is every field as a flat
+// JSON object of strings, plus "event", "id" and "timestamp". An alert's
+// fields are those of Alert::fields, so a Slack-style incoming webhook takes
+//
+//   {"text":"{{text}}"}
+//
+// With a secret, every request carries
+//
+//   X-Webhook-Timestamp: <unix seconds>
+//   X-Webhook-Signature: sha256=<hex HMAC-SHA256 of "<timestamp>.<body>">
+//
+// so receivers can check the origin and reject replays.
+//
+// Delivery happens on one background thread, over plain HTTP/1.1 on std TCP
+// (put a TLS-terminating proxy in front of https endpoints). A failed request
+// (connection error, timeout, 5xx, 408 or 429) is retried with exponential
+// backoff; other 4xx are final. Outcomes are counted in the metrics registry
+// as fix_webhook_deliveries_total{event,outcome}.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Write as _},
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    str::FromStr,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    alerts::{Alert, Notifier},
+    gateway::metrics::Metrics,
+    json::json_escape,
+};
+
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Delay before the first retry, doubled for each following one
+const FIRST_BACKOFF: Duration = Duration::from_millis(500);
+
+// =============================================================================
+// Events
+// =============================================================================
+
+/// What a webhook can be fired on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WebhookEvent {
+    OrderFilled,
+    SessionDown,
+    LimitBreach,
+    Alert,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 4] = [
+        WebhookEvent::OrderFilled,
+        WebhookEvent::SessionDown,
+        WebhookEvent::LimitBreach,
+        WebhookEvent::Alert,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEvent::OrderFilled => "order_filled",
+            WebhookEvent::SessionDown => "session_down",
+            WebhookEvent::LimitBreach => "limit_breach",
+            WebhookEvent::Alert => "alert",
+        }
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = WebhookError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|x| x.as_str() == source)
+            .ok_or_else(|| WebhookError::UnknownEvent(source.to_string()))
+    }
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+pub enum WebhookError {
+    /// Not `http://host[:port][/path]`
+    InvalidUrl(String),
+
+    /// Not one of the WebhookEvent names
+    UnknownEvent(String),
+
+    /// The delivery thread could not be started
+    Io(io::Error),
+}
+
+impl fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookError::InvalidUrl(url) => {
+                write!(f, "invalid webhook URL (expected http://host[:port]/path): {url}")
+            }
+            WebhookError::UnknownEvent(name) => write!(
+                f,
+                "unknown webhook event {name} \
+                 (expected order_filled, session_down, limit_breach or alert)"
+            ),
+            WebhookError::Io(err) => write!(f, "webhook thread: {err}"),
+        }
+    }
+}
+
+impl Error for WebhookError {}
+
+impl From<io::Error> for WebhookError {
+    fn from(err: io::Error) -> Self {
+        WebhookError::Io(err)
+    }
+}
+
+// =============================================================================
+// Configuration
+// =============================================================================
+
+/// One endpoint and what it receives
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// `http://host[:port][/path]`
+    pub url: String,
+
+    /// Events sent to this endpoint; empty for all
+    pub events: Vec<WebhookEvent>,
+
+    /// Body per event, with {{field}} placeholders; the default body otherwise
+    pub templates: HashMap<WebhookEvent, String>,
+
+    /// HMAC-SHA256 key; requests are not signed without one
+    pub secret: Option<String>,
+
+    /// Attempts after the first one
+    pub max_retries: u32,
+}
+
+impl WebhookConfig {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            events: Vec::new(),
+            templates: HashMap::new(),
+            secret: None,
+            max_retries: 3,
+        }
+    }
+
+    /// Restrict to a comma separated list of events: `"order_filled,session_down"`
+    pub fn with_events(mut self, events: &str) -> Result<Self, WebhookError> {
+        self.events = events
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(self)
+    }
+
+    pub fn with_template(mut self, event: WebhookEvent, template: &str) -> Self {
+        self.templates.insert(event, template.to_string());
+        self
+    }
+
+    pub fn with_secret(mut self, secret: &str) -> Self {
+        self.secret = Some(secret.to_string());
+        self
+    }
+
+    fn wants(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+/// Host, port and path of an http:// URL
+#[derive(Debug, Clone)]
+struct Endpoint {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> Result<Self, WebhookError> {
+        let invalid = || WebhookError::InvalidUrl(url.to_string());
+        let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+// =============================================================================
+// Notifier
+// =============================================================================
+
+struct Delivery {
+    endpoint: usize,
+    event: WebhookEvent,
+    id: String,
+    body: String,
+}
+
+pub struct WebhookNotifier {
+    configs: Vec<WebhookConfig>,
+
+    deliveries: Sender<Delivery>,
+    next_id: Mutex<u64>,
+
+    /// Start time in seconds, so delivery ids do not repeat across runs
+    run_id: u64,
+}
+
+impl WebhookNotifier {
+    /// Check the URLs, then start the delivery thread
+    pub fn start(
+        configs: Vec<WebhookConfig>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, WebhookError> {
+        let endpoints = configs
+            .iter()
+            .map(|x| Endpoint::parse(&x.url))
+            .collect::<Result<Vec<_>, _>>()?;
+        let secrets = configs.iter().map(|x| x.secret.clone()).collect();
+        let retries = configs.iter().map(|x| x.max_retries).collect();
+
+        let (sender, receiver) = mpsc::channel();
+        let worker = Worker {
+            endpoints,
+            secrets,
+            retries,
+            metrics,
+        };
+        thread::Builder::new()
+            .name("webhooks".to_string())
+            .spawn(move || worker.run(receiver))?;
+
+        Ok(Self {
+            configs,
+            deliveries: sender,
+            next_id: Mutex::new(1),
+            run_id: unix_seconds(),
+        })
+    }
+
+    /// Queue `event` for every endpoint that wants it; never blocks
+    ///
+    /// `fields` are the values templates can refer to, by name.
+    pub fn notify(&self, event: WebhookEvent, fields: &[(&str, String)]) {
+        let timestamp = unix_seconds();
+        for (endpoint, config) in self.configs.iter().enumerate() {
+            if !config.wants(event) {
+                continue;
+            }
+
+            let id = {
+                let mut next_id = self.next_id.lock().expect("webhook lock poisoned");
+                *next_id += 1;
+                format!("WH-{}-{}", self.run_id, *next_id - 1)
+            };
+            let mut values: Vec<(&str, String)> = vec![
+                ("event", event.as_str().to_string()),
+                ("id", id.clone()),
+                ("timestamp", timestamp.to_string()),
+            ];
+            values.extend(fields.iter().cloned());
+
+            let body = match config.templates.get(&event) {
+                Some(template) => render_template(template, &values),
+                None => default_body(&values),
+            };
+            let _ = self.deliveries.send(Delivery {
+                endpoint,
+                event,
+                id,
+                body,
+            });
+        }
+    }
+}
+
+/// Alerts go out as the `alert` event, to the endpoints that want it
+impl Notifier for WebhookNotifier {
+    fn alert(&self, alert: &Alert) {
+        self.notify(WebhookEvent::Alert, &alert.fields());
+    }
+}
+
+/// Replace each {{name}} by the JSON-escaped value of `name`; unknown names
+/// are left as they are
+fn render_template(template: &str, values: &[(&str, String)]) -> String {
+    let mut out = template.to_string();
+    for (name, value) in values {
+        out = out.replace(&format!("{{{{{name}}}}}"), &json_escape(value));
+    }
+    out
+}
+
+fn default_body(values: &[(&str, String)]) -> String {
+    let mut out = String::from("{");
+    for (index, (name, value)) in values.iter().enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+        let _ = write!(out, "\"{}\":\"{}\"", json_escape(name), json_escape(value));
+    }
+    out.push('}');
+    out
+}
+
+fn unix_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs())
+}
+
+// =============================================================================
+// Worker (delivery thread)
+// =============================================================================
+
+/// Why one attempt failed, and whether another one may succeed
+struct Failure {
+    reason: String,
+    retry: bool,
+}
+
+struct Worker {
+    endpoints: Vec<Endpoint>,
+    secrets: Vec<Option<String>>,
+    retries: Vec<u32>,
+    metrics: Arc<Metrics>,
+}
+
+impl Worker {
+    /// Deliver in order; a retrying delivery holds back the ones behind it
+    fn run(self, receiver: Receiver<Delivery>) {
+        while let Ok(delivery) = receiver.recv() {
+            let event = delivery.event.as_str();
+            let max_retries = self.retries[delivery.endpoint];
+            let mut backoff = FIRST_BACKOFF;
+            let mut attempt = 0;
+
+            loop {
+                match self.post(&delivery) {
+                    Ok(()) => {
+                        self.metrics.on_webhook(event, "delivered");
+                        break;
+                    }
+                    Err(failure) if failure.retry && attempt < max_retries => {
+                        self.metrics.on_webhook(event, "retried");
+                        attempt += 1;
+                        thread::sleep(backoff);
+                        backoff *= 2;
+                    }
+                    Err(failure) => {
+                        self.metrics.on_webhook(event, "failed");
+                        eprintln!(
+                            "webhook: dropped {} {} to {}: {}",
+                            event,
+                            delivery.id,
+                            self.endpoints[delivery.endpoint].host,
+                            failure.reason
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// One HTTP attempt
+    fn post(&self, delivery: &Delivery) -> Result<(), Failure> {
+        let endpoint = &self.endpoints[delivery.endpoint];
+        let io_failure = |err: io::Error| Failure {
+            reason: err.to_string(),
+            retry: true,
+        };
+
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\n\
+             Host: {}\r\n\
+             User-Agent: trading-webhook\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\
+             X-Webhook-Event: {}\r\n\
+             X-Webhook-Id: {}\r\n",
+            endpoint.path,
+            endpoint.host,
+            delivery.body.len(),
+            delivery.event.as_str(),
+            delivery.id
+        );
+        if let Some(secret) = &self.secrets[delivery.endpoint] {
+            let timestamp = unix_seconds();
+            let signed = format!("{timestamp}.{}", delivery.body);
+            let signature = hmac_sha256(secret.as_bytes(), signed.as_bytes());
+            let _ = write!(
+                request,
+                "X-Webhook-Timestamp: {timestamp}\r\nX-Webhook-Signature: sha256={}\r\n",
+                hex(&signature)
+            );
+        }
+        request.push_str("\r\n");
+        request.push_str(&delivery.body);
+
+        let address = (endpoint.host.as_str(), endpoint.port)
+            .to_socket_addrs()
+            .map_err(io_failure)?
+            .next()
+            .ok_or_else(|| io_failure(io::Error::new(io::ErrorKind::NotFound, "no address")))?;
+        let mut stream =
+            TcpStream::connect_timeout(&address, SOCKET_TIMEOUT).map_err(io_failure)?;
+        stream
+            .set_read_timeout(Some(SOCKET_TIMEOUT))
+            .and_then(|()| stream.set_write_timeout(Some(SOCKET_TIMEOUT)))
+            .and_then(|()| stream.write_all(request.as_bytes()))
+            .map_err(io_failure)?;
+
+        // Only the status line matters
+        let mut response = [0u8; 64];
+        let read = stream.read(&mut response).map_err(io_failure)?;
+        let status: u16 = std::str::from_utf8(&response[..read])
+            .ok()
+            .and_then(|x| x.split_whitespace().nth(1))
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(|| Failure {
+                reason: "malformed HTTP response".to_string(),
+                retry: true,
+            })?;
+
+        match status {
+            200..=299 => Ok(()),
+            408 | 429 | 500..=599 => Err(Failure {
+                reason: format!("HTTP {status}"),
+                retry: true,
+            }),
+            _ => Err(Failure {
+                reason: format!("HTTP {status}"),
+                retry: false,
+            }),
+        }
+    }
+}
+
+// =============================================================================
+// HMAC-SHA256
+// =============================================================================
+
+/// HMAC-SHA256 (RFC 2104) of `message` under `key`, the signature of a
+/// webhook request: receivers recompute it over "<timestamp>.<body>"
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK: usize = 64;
+
+    let mut block_key = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block_key[..32].copy_from_slice(&sha256(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner: Vec<u8> = block_key.iter().map(|x| x ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = block_key.iter().map(|x| x ^ 0x5c).collect();
+    outer.extend_from_slice(&sha256(&inner));
+    sha256(&outer)
+}
+
+/// SHA-256 digest (FIPS 180-4), also used for delivery checksums (delivery.rs)
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1,
+        0x923f_82a4, 0xab1c_5ed5, 0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3,
+        0x72be_5d74, 0x80de_b1fe, 0x9bdc_06a7, 0xc19b_f174, 0xe49b_69c1, 0xefbe_4786,
+        0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f, 0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da,
+        0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7, 0xc6e0_0bf3, 0xd5a7_9147,
+        0x06ca_6351, 0x1429_2967, 0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc, 0x5338_0d13,
+        0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85, 0xa2bf_e8a1, 0xa81a_664b,
+        0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070,
+        0x19a4_c116, 0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a,
+        0x5b9c_ca4f, 0x682e_6ff3, 0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208,
+        0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7, 0xc671_78f2,
+    ];
+
+    let mut h: [u32; 8] = [
+        0x6a09_e667,
+        0xbb67_ae85,
+        0x3c6e_f372,
+        0xa54f_f53a,
+        0x510e_527f,
+        0x9b05_688c,
+        0x1f83_d9ab,
+        0x5be0_cd19,
+    ];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for (k, word) in K.iter().zip(w) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let temp1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(*k)
+                .wrapping_add(word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (chunk, word) in digest.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Lower-case hex
+pub fn hex(data: &[u8]) -> String {
+    data.iter().fold(String::with_capacity(data.len() * 2), |mut out, x| {
+        let _ = write!(out, "{x:02x}");
+        out
+    })
+}
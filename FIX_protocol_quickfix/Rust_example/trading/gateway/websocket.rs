@@ -0,0 +1,457 @@
+// =============================================================================
+// WebSocket Streaming Bridge
+// =============================================================================
+// Broadcasts events as JSON text frames to every connected WebSocket client,
+// for live dashboards on top of the examples:
+//
+//   {"type":"fix","session":"FIX.4.4:A->B","direction":"in","msg_type":"D",
+//    "time_ms":1700000000000,"fields":[[8,"FIX.4.4"],[35,"D"],...]}
+//   {"type":"order", ...}     OMS updates (buy_side)
+//   {"type":"position", ...}  position updates (buy_side)
+//   {"type":"news", ...}      headlines from a news feed (buy_side --news)
+//   {"type":"trade", ...}     fills for the trade blotter (buy_side)
+//
+// Clients pick what they receive with query parameters on the upgrade URL:
+//
+//   ws://host:port/?session=CLIENT&types=D,8,order
+//   ws://host:port/?types=trade&account=ACC1&side=buy&min_qty=100
+//
+//   session  substring of the session label (e.g. a CompID), comma separated
+//   types    MsgTypes for FIX events, or "order" / "position" / "news" /
+//            "trade"
+//   account, symbol, side, min_qty
+//            trade blotter filter (oms::blotter), applied to trades only; a
+//            value the filter does not take is ignored
+//
+// Frames are written by a dedicated thread fed through a channel, so a slow
+// client never blocks a QuickFIX callback. Client frames (ping, close) are
+// not read: a client that goes away is dropped on the next failed write.
+// Only std is used (SHA-1 and base64 for the handshake are implemented below).
+// =============================================================================
+
+use std::{
+    fmt::{self, Write as _},
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use quickfix::{FieldMap, Message, SessionId};
+
+use crate::{
+    gateway::http::json_escape,
+    oms::blotter::{BlotterFilter, Trade},
+    session::{session_label, Direction},
+};
+
+/// GUID appended to the client key in the opening handshake (RFC 6455)
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// A write taking longer than this drops the client
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// =============================================================================
+// Events and Filters
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    /// Session label, None for events not tied to a session
+    pub session: Option<String>,
+
+    /// MsgType for FIX events, "order" / "position" otherwise
+    pub topic: String,
+
+    /// Complete JSON document sent to clients
+    pub json: String,
+}
+
+#[derive(Debug, Default)]
+struct Filter {
+    sessions: Vec<String>,
+    topics: Vec<String>,
+    trades: BlotterFilter,
+}
+
+impl Filter {
+    /// Build from the upgrade request path: `/?session=A,B&types=D,8`
+    fn from_path(path: &str) -> Self {
+        let mut filter = Self::default();
+        let query = path.split_once('?').map_or("", |(_, q)| q);
+        for (key, value) in query.split('&').filter_map(|x| x.split_once('=')) {
+            let values = value
+                .split(',')
+                .filter(|x| !x.is_empty())
+                .map(str::to_string);
+            match key {
+                "session" => filter.sessions.extend(values),
+                "types" => filter.topics.extend(values),
+                _ => {
+                    let _ = filter.trades.set(key, value);
+                }
+            }
+        }
+        filter
+    }
+
+    /// Empty lists match everything; the session filter ignores events
+    /// without a session
+    fn matches(&self, event: &Event, trade: Option<&Trade>) -> bool {
+        let session_ok = match &event.session {
+            Some(session) if !self.sessions.is_empty() => {
+                self.sessions.iter().any(|x| session.contains(x.as_str()))
+            }
+            _ => true,
+        };
+        let topic_ok = self.topics.is_empty() || self.topics.contains(&event.topic);
+        let trade_ok = trade.is_none_or(|x| self.trades.matches(x));
+        session_ok && topic_ok && trade_ok
+    }
+}
+
+struct Client {
+    stream: TcpStream,
+    filter: Filter,
+}
+
+enum Command {
+    Join(Client),
+    Publish(Event, Option<Trade>),
+}
+
+// =============================================================================
+// Bridge
+// =============================================================================
+
+pub struct Bridge {
+    commands: Sender<Command>,
+    clients: Arc<AtomicUsize>,
+}
+
+impl Default for Bridge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bridge {
+    /// Create the bridge and its writer thread
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let clients = Arc::new(AtomicUsize::new(0));
+
+        let counter = Arc::clone(&clients);
+        thread::Builder::new()
+            .name("ws-writer".to_string())
+            .spawn(move || writer_loop(receiver, &counter))
+            .expect("cannot spawn WebSocket writer thread");
+
+        Self {
+            commands: sender,
+            clients,
+        }
+    }
+
+    /// True when at least one client is connected
+    ///
+    /// Callers check this before building JSON so an idle bridge costs nothing.
+    pub fn has_clients(&self) -> bool {
+        self.clients.load(Ordering::Relaxed) > 0
+    }
+
+    pub fn publish(&self, event: Event) {
+        let _ = self
+            .commands
+            .send(Command::Publish(event, None));
+    }
+
+    /// Publish a fill of the trade blotter, to the clients whose filter it
+    /// matches
+    pub fn publish_trade(&self, trade: &Trade) {
+        if !self.has_clients() {
+            return;
+        }
+
+        let event = Event {
+            session: Some(trade.session.clone()),
+            topic: "trade".to_string(),
+            json: format!("{{\"type\":\"trade\",\"trade\":{}}}", trade.to_json()),
+        };
+        let _ = self
+            .commands
+            .send(Command::Publish(event, Some(trade.clone())));
+    }
+
+    /// Publish a decoded FIX message
+    pub fn publish_fix(&self, session: &SessionId, direction: Direction, msg: &Message) {
+        if !self.has_clients() {
+            return;
+        }
+
+        let label = session_label(session);
+        let msg_type = msg.with_header(|h| h.get_field(35)).unwrap_or_default();
+        let json = format!(
+            "{{\"type\":\"fix\",\"session\":\"{}\",\"direction\":\"{}\",\"msg_type\":\"{}\",\
+             \"time_ms\":{},\"fields\":{}}}",
+            json_escape(&label),
+            direction.as_label(),
+            json_escape(&msg_type),
+            now_millis(),
+            fields_json(msg),
+        );
+        self.publish(Event {
+            session: Some(label),
+            topic: msg_type,
+            json,
+        });
+    }
+
+    fn join(&self, client: Client) {
+        let _ = self
+            .commands
+            .send(Command::Join(client));
+    }
+}
+
+/// Decoded fields of a message as a JSON array of `[tag, "value"]` pairs
+///
+/// Pairs rather than an object: repeating groups reuse the same tags.
+pub fn fields_json(msg: &Message) -> String {
+    let text = msg.to_fix_string().unwrap_or_default();
+    pairs_json(text.split('\x01').filter_map(|x| x.split_once('=')))
+}
+
+/// Same JSON layout as fields_json, from already decoded `(tag, value)` pairs
+pub fn pairs_json<T: fmt::Display, V: AsRef<str>>(
+    pairs: impl IntoIterator<Item = (T, V)>,
+) -> String {
+    let mut out = String::from("[");
+    for (index, (tag, value)) in pairs.into_iter().enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+        let _ = write!(out, "[{tag},\"{}\"]", json_escape(value.as_ref()));
+    }
+    out.push(']');
+    out
+}
+
+/// Milliseconds since the Unix epoch, for event timestamps
+pub fn now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_millis())
+}
+
+fn writer_loop(receiver: Receiver<Command>, counter: &AtomicUsize) {
+    let mut clients: Vec<Client> = Vec::new();
+
+    for command in receiver {
+        match command {
+            Command::Join(client) => clients.push(client),
+            Command::Publish(event, trade) => {
+                let frame = text_frame(&event.json);
+                clients.retain_mut(|client| {
+                    !client.filter.matches(&event, trade.as_ref())
+                        || client.stream.write_all(&frame).is_ok()
+                });
+            }
+        }
+        counter.store(clients.len(), Ordering::Relaxed);
+    }
+}
+
+/// Unmasked, unfragmented text frame (server to client)
+fn text_frame(payload: &str) -> Vec<u8> {
+    let payload = payload.as_bytes();
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x81); // FIN + text opcode
+
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+// =============================================================================
+// Server
+// =============================================================================
+
+/// Accept WebSocket clients on `0.0.0.0:<port>` in a background thread
+pub fn spawn_server(port: u16, bridge: Arc<Bridge>) -> io::Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+
+    thread::Builder::new()
+        .name("ws-accept".to_string())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                match handshake(stream) {
+                    Ok(client) => bridge.join(client),
+                    Err(err) => eprintln!("ws-accept: {err}"),
+                }
+            }
+        })?;
+    println!(">> WebSocket stream available on ws://0.0.0.0:{port}/");
+    Ok(())
+}
+
+/// Read the HTTP upgrade request and answer with 101 Switching Protocols
+fn handshake(stream: TcpStream) -> io::Result<Client> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("/")
+        .to_string();
+
+    let mut key = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? <= 2 {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    let mut stream = stream;
+    let Some(key) = key else {
+        write!(
+            stream,
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        )?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing Sec-WebSocket-Key",
+        ));
+    };
+
+    let accept = accept_key(&key);
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {accept}\r\n\r\n"
+    )?;
+    stream.flush()?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+    Ok(Client {
+        stream,
+        filter: Filter::from_path(&path),
+    })
+}
+
+// =============================================================================
+// Handshake Helpers (SHA-1, base64)
+// =============================================================================
+
+/// Sec-WebSocket-Accept answering a Sec-WebSocket-Key (also checked by the
+/// news feed client)
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{WEBSOCKET_GUID}").as_bytes()))
+}
+
+/// SHA-1 digest (FIPS 180-4), for the handshake only
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (chunk, word) in digest.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Standard base64 with padding (also used for mail attachments)
+pub fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for (i, shift) in [18, 12, 6, 0].into_iter().enumerate() {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> shift & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,222 @@
+// =============================================================================
+// Gzip Files
+// =============================================================================
+// Archives that gunzip, zcat and zgrep read, written with std alone: one
+// DEFLATE block of the fixed Huffman codes (RFC 1951 3.2.6), its matches
+// found with a hash chain over the last 32 KiB, in a gzip member (RFC 1952).
+//
+// FIX messages repeat their tags, CompIDs and most of their values from one
+// message to the next: the fixed codes take a day of them to about a fifth of
+// their size, short of what zlib's dynamic codes reach but without the
+// dependency. Compression only: archives are read back with the usual tools.
+// =============================================================================
+
+/// Window matches are looked for in, the most DEFLATE allows
+const WINDOW: usize = 32 * 1024;
+
+/// Shortest and longest match DEFLATE encodes
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+/// Candidates tried at each position, speed against size
+const MAX_CHAIN: usize = 64;
+
+const HASH_BITS: u32 = 15;
+
+/// Base length of codes 257 to 285, and their extra bits
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distance of codes 0 to 29, and their extra bits
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// =============================================================================
+// Gzip Member
+// =============================================================================
+
+/// `data` as a gzip file
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    // Magic, deflate, no flags, no mtime (same input, same archive),
+    // no extra flags, unknown OS
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255];
+    out.extend(deflate(data));
+    out.extend(crc32(data).to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out
+}
+
+/// CRC-32 of gzip and zip (IEEE 802.3, reflected)
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// =============================================================================
+// DEFLATE
+// =============================================================================
+
+/// `data` as one final DEFLATE block of the fixed Huffman codes
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut bits = BitWriter::default();
+    bits.write(1, 1); // BFINAL
+    bits.write(1, 2); // BTYPE 01: fixed codes
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW];
+    let insert = |pos: usize, head: &mut [usize], prev: &mut [usize]| {
+        if pos + MIN_MATCH <= data.len() {
+            let hash = hash(&data[pos..pos + MIN_MATCH]);
+            prev[pos % WINDOW] = head[hash];
+            head[hash] = pos;
+        }
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let (length, distance) = longest_match(data, pos, &head, &prev);
+        if length >= MIN_MATCH {
+            write_match(&mut bits, length, distance);
+            for x in pos..pos + length {
+                insert(x, &mut head, &mut prev);
+            }
+            pos += length;
+        } else {
+            write_literal(&mut bits, u16::from(data[pos]));
+            insert(pos, &mut head, &mut prev);
+            pos += 1;
+        }
+    }
+    write_literal(&mut bits, 256); // end of block
+    bits.finish()
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+    (value.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+}
+
+/// Longest earlier occurrence of what starts at `pos`: length and distance
+fn longest_match(data: &[u8], pos: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+    if pos + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+    let limit = (data.len() - pos).min(MAX_MATCH);
+    let mut best = (0, 0);
+    let mut candidate = head[hash(&data[pos..pos + MIN_MATCH])];
+    for _ in 0..MAX_CHAIN {
+        if candidate == usize::MAX || pos - candidate > WINDOW {
+            break;
+        }
+        let length = data[candidate..]
+            .iter()
+            .zip(&data[pos..pos + limit])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if length > best.0 {
+            best = (length, pos - candidate);
+            if length == limit {
+                break;
+            }
+        }
+        let next = prev[candidate % WINDOW];
+        // An older position only; a newer one means the slot was reused
+        if next == usize::MAX || next >= candidate {
+            break;
+        }
+        candidate = next;
+    }
+    best
+}
+
+/// A literal byte or the end of block, in its fixed code
+fn write_literal(bits: &mut BitWriter, symbol: u16) {
+    let (code, length) = match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xc0 + symbol - 280, 8),
+    };
+    bits.write_code(code, length);
+}
+
+fn write_match(bits: &mut BitWriter, length: usize, distance: usize) {
+    let index = LENGTH_BASE
+        .iter()
+        .rposition(|&x| usize::from(x) <= length)
+        .unwrap_or(0);
+    write_literal(bits, 257 + index as u16);
+    bits.write(
+        (length - usize::from(LENGTH_BASE[index])) as u32,
+        LENGTH_EXTRA[index],
+    );
+
+    let index = DISTANCE_BASE
+        .iter()
+        .rposition(|&x| usize::from(x) <= distance)
+        .unwrap_or(0);
+    bits.write_code(index as u16, 5);
+    bits.write(
+        (distance - usize::from(DISTANCE_BASE[index])) as u32,
+        DISTANCE_EXTRA[index],
+    );
+}
+
+/// Bits packed from the least significant bit of each byte, as DEFLATE
+/// packs them
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u32,
+    count: u8,
+}
+
+impl BitWriter {
+    /// The low `count` bits of `value`, least significant first
+    fn write(&mut self, value: u32, count: u8) {
+        for bit in 0..count {
+            self.buffer |= ((value >> bit) & 1) << self.count;
+            self.count += 1;
+            if self.count == 8 {
+                self.out.push(self.buffer as u8);
+                self.buffer = 0;
+                self.count = 0;
+            }
+        }
+    }
+
+    /// A Huffman code, most significant bit first
+    fn write_code(&mut self, code: u16, length: u8) {
+        for bit in (0..length).rev() {
+            self.write(u32::from(code >> bit) & 1, 1);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.buffer as u8);
+        }
+        self.out
+    }
+}
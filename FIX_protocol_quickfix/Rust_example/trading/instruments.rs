@@ -0,0 +1,504 @@
+// =============================================================================
+// Instrument Reference Data (35=c / 35=d, 35=x / 35=y)
+// =============================================================================
+// What the venue says about the symbols it trades, asked for once logged on
+// and kept for the risk checks and the order builders:
+//
+//   35=c  SecurityReqID (320), SecurityRequestType (321) = 0 specifications
+//         of the symbol, Symbol
+//   35=d  SecurityReqID, SecurityResponseID (322), SecurityResponseType
+//         (323) 1 accepted / 5 rejected, Symbol, Text, and the fields below
+//   35=x  SecurityReqID, SecurityListRequestType (559) 0 one symbol / 4 all
+//         securities, Symbol
+//   35=y  SecurityReqID, SecurityResponseID, SecurityRequestResult (560)
+//         0 valid / 2 no instrument found, TotNoRelatedSym (393)
+//         NoRelatedSym (146)  -> Symbol and the fields below, per instrument
+//
+//   SecurityType (167), Currency (15), MinPriceIncrement (969),
+//   ContractMultiplier (231), RoundLot (561)
+//
+// MinPriceIncrement is a FIX 5.0 field: a FIX.4.4 DataDictionary has to
+// list it in both messages for QuickFIX to let it through.
+//
+// An InstrumentStore keeps the last definition of each symbol. The risk
+// checks count a contract's notional with its multiplier and refuse odd
+// lots (RiskChecker::check_instrument); limit prices are rounded to the tick,
+// away from the market (Instrument::round_price). A symbol the venue did not
+// define is traded without either.
+//
+// With a state store every definition is written through under
+// "instruments", so they are known before the venue answers again.
+// =============================================================================
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+use quickfix::{FieldMap, Group, Message, QuickFixError};
+
+use crate::{
+    json::{json_escape, JsonValue},
+    oms::Side,
+    session::{
+        events::{group_field, FixMessage},
+        Direction,
+    },
+    store::{StateStore, StoreError},
+    time::unix_now,
+};
+
+/// Store namespace of the definitions, keyed by symbol
+pub const INSTRUMENTS_NAMESPACE: &str = "instruments";
+
+// =============================================================================
+// Instrument
+// =============================================================================
+
+/// What the venue says about a symbol
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instrument {
+    pub symbol: String,
+
+    /// SecurityType (167): CS, FUT, FOR...; empty if not sent
+    pub security_type: String,
+
+    /// Currency (15) of the prices; empty if not sent
+    pub currency: String,
+
+    /// MinPriceIncrement (969)
+    pub tick_size: Option<f64>,
+
+    /// ContractMultiplier (231): what one unit is worth per point of price
+    pub multiplier: f64,
+
+    /// RoundLot (561): quantities are a multiple of it
+    pub lot_size: Option<f64>,
+
+    /// When the definition was received, Unix milliseconds
+    pub updated: i64,
+}
+
+impl Instrument {
+    /// A symbol with a multiplier of 1, and nothing else known
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            security_type: String::new(),
+            currency: String::new(),
+            tick_size: None,
+            multiplier: 1.0,
+            lot_size: None,
+            updated: unix_now() * 1_000,
+        }
+    }
+
+    pub fn with_security_type(mut self, security_type: &str) -> Self {
+        self.security_type = security_type.to_string();
+        self
+    }
+
+    pub fn with_currency(mut self, currency: &str) -> Self {
+        self.currency = currency.to_string();
+        self
+    }
+
+    pub fn with_tick_size(mut self, tick_size: f64) -> Self {
+        self.tick_size = Some(tick_size);
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn with_lot_size(mut self, lot_size: f64) -> Self {
+        self.lot_size = Some(lot_size);
+        self
+    }
+
+    /// A limit price on the tick grid, rounded away from the market: down
+    /// for a buy, up for a sell. Unchanged without a tick size.
+    pub fn round_price(&self, side: Side, price: f64) -> f64 {
+        let Some(tick) = self.tick_size.filter(|x| *x > 0.0) else {
+            return price;
+        };
+        // Tolerance for prices already on the grid, 100.1 / 0.01 = 10009.99...
+        let ticks = price / tick;
+        let ticks = match side {
+            Side::Buy => (ticks + 1e-6).floor(),
+            Side::Sell => (ticks - 1e-6).ceil(),
+        };
+        let scale = 10f64.powi(decimals(tick) as i32);
+        (ticks * tick * scale).round() / scale
+    }
+
+    /// Whether a price is on the tick grid; any price is without a tick size
+    pub fn is_on_tick(&self, price: f64) -> bool {
+        self.tick_size.filter(|x| *x > 0.0).is_none_or(|tick| {
+            let ticks = price / tick;
+            (ticks - ticks.round()).abs() < 1e-6
+        })
+    }
+
+    /// Whether a quantity is a whole number of lots; any is without a lot size
+    pub fn is_round_lot(&self, quantity: f64) -> bool {
+        self.lot_size.filter(|x| *x > 0.0).is_none_or(|lot| {
+            let lots = quantity / lot;
+            (lots - lots.round()).abs() < 1e-9
+        })
+    }
+
+    /// What an order of this quantity is worth at this price
+    pub fn notional(&self, quantity: f64, price: f64) -> f64 {
+        quantity * price * self.multiplier
+    }
+
+    /// Read the definition fields, of a message or of one of its groups;
+    /// None without Symbol
+    fn from_fields<'a>(field: impl Fn(i32) -> Option<&'a str>) -> Option<Self> {
+        let number = |tag| field(tag).and_then(|x| x.parse::<f64>().ok());
+        Some(Self {
+            symbol: field(55)?.to_string(),
+            security_type: field(167).unwrap_or_default().to_string(),
+            currency: field(15).unwrap_or_default().to_string(),
+            tick_size: number(969),
+            multiplier: number(231).unwrap_or(1.0),
+            lot_size: number(561),
+            updated: unix_now() * 1_000,
+        })
+    }
+
+    /// Write the definition fields, to a message or one of its groups
+    fn write_fields(&self, fields: &mut impl FieldMap) -> Result<(), QuickFixError> {
+        fields.set_field(55, self.symbol.as_str())?;
+        if !self.security_type.is_empty() {
+            fields.set_field(167, self.security_type.as_str())?; // SecurityType
+        }
+        if !self.currency.is_empty() {
+            fields.set_field(15, self.currency.as_str())?; // Currency
+        }
+        if let Some(tick_size) = self.tick_size {
+            fields.set_field(969, tick_size.to_string().as_str())?; // MinPriceIncrement
+        }
+        fields.set_field(231, self.multiplier.to_string().as_str())?; // ContractMultiplier
+        if let Some(lot_size) = self.lot_size {
+            fields.set_field(561, lot_size.to_string().as_str())?; // RoundLot
+        }
+        Ok(())
+    }
+
+    /// JSON object, as stored; unknown sizes are null
+    pub fn to_json(&self) -> String {
+        let number = |x: Option<f64>| x.map_or("null".to_string(), |x| x.to_string());
+        format!(
+            "{{\"symbol\":\"{}\",\"security_type\":\"{}\",\"currency\":\"{}\",\
+             \"tick_size\":{},\"multiplier\":{},\"lot_size\":{},\"updated\":{}}}",
+            json_escape(&self.symbol),
+            json_escape(&self.security_type),
+            json_escape(&self.currency),
+            number(self.tick_size),
+            self.multiplier,
+            number(self.lot_size),
+            self.updated
+        )
+    }
+
+    /// Parse what to_json wrote; None if it is not an instrument
+    pub fn from_json(text: &str) -> Option<Self> {
+        let value = JsonValue::parse(text)?;
+        let text = |key: &str| Some(value.get(key)?.as_str()?.to_string());
+        let number = |key: &str| value.get(key).and_then(JsonValue::as_f64);
+        Some(Self {
+            symbol: text("symbol")?,
+            security_type: text("security_type")?,
+            currency: text("currency")?,
+            tick_size: number("tick_size"),
+            multiplier: number("multiplier")?,
+            lot_size: number("lot_size"),
+            updated: number("updated")? as i64,
+        })
+    }
+}
+
+/// Digits after the decimal point of a tick size, 0.25 -> 2
+fn decimals(tick: f64) -> usize {
+    let text = tick.to_string();
+    text.split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len())
+}
+
+// =============================================================================
+// Requests
+// =============================================================================
+
+/// SecurityDefinitionRequest (35=c), for the specifications of one symbol
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityDefinitionRequest {
+    pub request_id: String,
+    pub symbol: String,
+}
+
+impl SecurityDefinitionRequest {
+    pub fn to_message(&self) -> Result<Message, QuickFixError> {
+        let mut msg = Message::new();
+        msg.with_header_mut(|h| h.set_field(35, "c"))?;
+        msg.set_field(320, self.request_id.as_str())?; // SecurityReqID
+        msg.set_field(321, "0")?; // SecurityRequestType: specifications
+        msg.set_field(55, self.symbol.as_str())?;
+        Ok(msg)
+    }
+
+    /// Decode a received 35=c; None without SecurityReqID or Symbol
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        Some(Self {
+            request_id: msg.get_field(320)?,
+            symbol: msg.get_field(55)?,
+        })
+    }
+}
+
+/// SecurityListRequest (35=x), for one symbol or every one the venue trades
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityListRequest {
+    pub request_id: String,
+
+    /// Every security if None
+    pub symbol: Option<String>,
+}
+
+impl SecurityListRequest {
+    pub fn to_message(&self) -> Result<Message, QuickFixError> {
+        let mut msg = Message::new();
+        msg.with_header_mut(|h| h.set_field(35, "x"))?;
+        msg.set_field(320, self.request_id.as_str())?; // SecurityReqID
+        match &self.symbol {
+            Some(symbol) => {
+                msg.set_field(559, "0")?; // SecurityListRequestType: symbol
+                msg.set_field(55, symbol.as_str())?;
+            }
+            None => msg.set_field(559, "4")?, // SecurityListRequestType: all
+        }
+        Ok(msg)
+    }
+
+    /// Decode a received 35=x; None without SecurityReqID
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        Some(Self {
+            request_id: msg.get_field(320)?,
+            symbol: msg.get_field(55),
+        })
+    }
+}
+
+// =============================================================================
+// Responses
+// =============================================================================
+
+/// SecurityDefinition (35=d) answering a request: the instrument, or the
+/// reason there is none
+pub fn build_security_definition(
+    request: &SecurityDefinitionRequest,
+    result: Result<&Instrument, &str>,
+) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "d"))?;
+    msg.set_field(320, request.request_id.as_str())?; // SecurityReqID
+    let response_id = format!("R-{}", request.request_id);
+    msg.set_field(322, response_id.as_str())?; // SecurityResponseID
+    match result {
+        Ok(instrument) => {
+            msg.set_field(323, "1")?; // SecurityResponseType: accept as is
+            instrument.write_fields(&mut msg)?;
+        }
+        Err(text) => {
+            msg.set_field(323, "5")?; // SecurityResponseType: reject
+            msg.set_field(55, request.symbol.as_str())?;
+            msg.set_field(58, text)?;
+        }
+    }
+    Ok(msg)
+}
+
+/// SecurityList (35=y) answering a request, one NoRelatedSym entry per
+/// instrument; no instrument found when empty
+pub fn build_security_list(
+    request: &SecurityListRequest,
+    instruments: &[Instrument],
+) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "y"))?;
+    msg.set_field(320, request.request_id.as_str())?; // SecurityReqID
+    let response_id = format!("R-{}", request.request_id);
+    msg.set_field(322, response_id.as_str())?; // SecurityResponseID
+    if instruments.is_empty() {
+        msg.set_field(560, "2")?; // SecurityRequestResult: none found
+        return Ok(msg);
+    }
+    msg.set_field(560, "0")?; // SecurityRequestResult: valid request
+    msg.set_field(393, instruments.len().to_string().as_str())?; // TotNoRelatedSym
+    for instrument in instruments {
+        let mut group = Group::try_new(146, 55)?;
+        instrument.write_fields(&mut group)?;
+        msg.add_group(&group)?;
+    }
+    Ok(msg)
+}
+
+// =============================================================================
+// InstrumentStore
+// =============================================================================
+
+/// What a message changed in the store
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstrumentUpdate {
+    /// A 35=d accepted or a valid 35=y, with the instruments kept
+    Defined {
+        request_id: String,
+        instruments: Vec<Instrument>,
+    },
+
+    /// A 35=d rejected, or a 35=y without instruments
+    Rejected { request_id: String, reason: String },
+}
+
+/// The last definition received of each symbol
+pub struct InstrumentStore {
+    id_prefix: String,
+    instruments: Mutex<BTreeMap<String, Instrument>>,
+    next_id: AtomicU64,
+
+    /// Where every definition is written through, if set
+    store: Option<Arc<dyn StateStore>>,
+}
+
+impl InstrumentStore {
+    /// SecurityReqIDs are `PREFIX-1`, `PREFIX-2`...
+    pub fn new(id_prefix: &str) -> Self {
+        Self {
+            id_prefix: id_prefix.to_string(),
+            instruments: Mutex::new(BTreeMap::new()),
+            next_id: AtomicU64::new(1),
+            store: None,
+        }
+    }
+
+    /// Restore the definitions kept in a store, and write every new one to it
+    pub fn with_store(mut self, store: Arc<dyn StateStore>) -> Result<Self, StoreError> {
+        let instruments = self
+            .instruments
+            .get_mut()
+            .expect("instrument lock poisoned");
+        for (key, value) in store.scan(INSTRUMENTS_NAMESPACE)? {
+            let instrument = Instrument::from_json(&value).ok_or_else(|| StoreError::Corrupt {
+                namespace: INSTRUMENTS_NAMESPACE.to_string(),
+                key: key.clone(),
+            })?;
+            instruments.insert(instrument.symbol.clone(), instrument);
+        }
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    /// A request for the definition of a symbol, under a new SecurityReqID
+    pub fn definition_request(&self, symbol: &str) -> SecurityDefinitionRequest {
+        SecurityDefinitionRequest {
+            request_id: self.next_request_id(),
+            symbol: symbol.to_string(),
+        }
+    }
+
+    /// A request for the list of securities (all of them if None), under a
+    /// new SecurityReqID
+    pub fn list_request(&self, symbol: Option<&str>) -> SecurityListRequest {
+        SecurityListRequest {
+            request_id: self.next_request_id(),
+            symbol: symbol.map(str::to_string),
+        }
+    }
+
+    fn next_request_id(&self) -> String {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        format!("{}-{id}", self.id_prefix)
+    }
+
+    /// Apply a received 35=d or 35=y
+    ///
+    /// # Returns
+    /// What changed; None for other messages, outbound ones and those
+    /// without SecurityReqID
+    pub fn on_message(&self, msg: &FixMessage) -> Option<InstrumentUpdate> {
+        if msg.direction != Direction::Inbound {
+            return None;
+        }
+        let request_id = msg.get(320)?.to_string();
+        let reason = |default: String| msg.get(58).map_or(default, str::to_string);
+        let instruments: Vec<_> = match msg.msg_type() {
+            "d" => match msg.get(323) {
+                Some("1" | "2" | "3") => Instrument::from_fields(|tag| msg.get(tag))
+                    .into_iter()
+                    .collect(),
+                response => {
+                    let response = response.unwrap_or("none");
+                    return Some(InstrumentUpdate::Rejected {
+                        request_id,
+                        reason: reason(format!("SecurityResponseType {response}")),
+                    });
+                }
+            },
+            "y" => match msg.get(560) {
+                Some("0") | None => msg
+                    .groups(55)
+                    .into_iter()
+                    .filter_map(|group| Instrument::from_fields(|tag| group_field(group, tag)))
+                    .collect(),
+                Some(result) => {
+                    return Some(InstrumentUpdate::Rejected {
+                        request_id,
+                        reason: reason(format!("SecurityRequestResult {result}")),
+                    });
+                }
+            },
+            _ => return None,
+        };
+
+        for instrument in &instruments {
+            self.insert(instrument.clone());
+        }
+        Some(InstrumentUpdate::Defined {
+            request_id,
+            instruments,
+        })
+    }
+
+    /// Keep a definition, replacing the symbol's previous one
+    ///
+    /// A failed write is logged, not returned: the definition is already in
+    /// memory, and asking the venue again writes it again.
+    pub fn insert(&self, instrument: Instrument) {
+        if let Some(store) = &self.store {
+            let symbol = &instrument.symbol;
+            if let Err(err) = store.put(INSTRUMENTS_NAMESPACE, symbol, &instrument.to_json()) {
+                eprintln!("Instruments: cannot store {symbol}: {err}");
+            }
+        }
+        self.lock().insert(instrument.symbol.clone(), instrument);
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<Instrument> {
+        self.lock().get(symbol).cloned()
+    }
+
+    /// Every instrument known, by symbol
+    pub fn instruments(&self) -> Vec<Instrument> {
+        self.lock().values().cloned().collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, Instrument>> {
+        self.instruments.lock().expect("instrument lock poisoned")
+    }
+}
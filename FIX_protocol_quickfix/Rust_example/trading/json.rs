@@ -0,0 +1,254 @@
+// =============================================================================
+// JSON Helpers
+// =============================================================================
+// The OMS and position keeper render themselves as JSON for the REST API and
+// the WebSocket bridge. The one helper they need lives in the core so they
+// build without the gateway feature.
+//
+// JsonValue reads whole documents, for inputs whose shape is not ours to
+// choose (news feeds, see news.rs). Flat request bodies of the HTTP gateway
+// keep the simpler http::parse_json_object.
+// =============================================================================
+
+use std::fmt::Write as _;
+
+/// Escape a string for inclusion inside a JSON string literal
+pub fn json_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// =============================================================================
+// JsonValue
+// =============================================================================
+
+/// Nesting deeper than this is refused rather than recursed into
+const MAX_DEPTH: usize = 64;
+
+/// A parsed JSON document
+///
+/// Object members keep their document order; `get` returns the first of
+/// duplicated keys.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Parse a complete document; None if it is not valid JSON
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parser = Parser {
+            bytes: text.as_bytes(),
+            position: 0,
+        };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        (parser.position == parser.bytes.len()).then_some(value)
+    }
+
+    /// Member of an object
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members.iter().find(|(x, _)| x == key).map(|(_, x)| x),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.bytes.get(self.position) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.position).copied()
+    }
+
+    fn eat(&mut self, literal: &str) -> Option<()> {
+        let end = self.position + literal.len();
+        (self.bytes.get(self.position..end)? == literal.as_bytes()).then(|| {
+            self.position = end;
+        })
+    }
+
+    fn value(&mut self, depth: usize) -> Option<JsonValue> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        match self.peek()? {
+            b'n' => self.eat("null").map(|()| JsonValue::Null),
+            b't' => self.eat("true").map(|()| JsonValue::Bool(true)),
+            b'f' => self.eat("false").map(|()| JsonValue::Bool(false)),
+            b'"' => self.string().map(JsonValue::String),
+            b'[' => {
+                self.position += 1;
+                let mut items = Vec::new();
+                if self.peek()? == b']' {
+                    self.position += 1;
+                    return Some(JsonValue::Array(items));
+                }
+                loop {
+                    items.push(self.value(depth + 1)?);
+                    match self.peek()? {
+                        b',' => self.position += 1,
+                        b']' => break,
+                        _ => return None,
+                    }
+                }
+                self.position += 1;
+                Some(JsonValue::Array(items))
+            }
+            b'{' => {
+                self.position += 1;
+                let mut members = Vec::new();
+                if self.peek()? == b'}' {
+                    self.position += 1;
+                    return Some(JsonValue::Object(members));
+                }
+                loop {
+                    if self.peek()? != b'"' {
+                        return None;
+                    }
+                    let key = self.string()?;
+                    if self.peek()? != b':' {
+                        return None;
+                    }
+                    self.position += 1;
+                    members.push((key, self.value(depth + 1)?));
+                    match self.peek()? {
+                        b',' => self.position += 1,
+                        b'}' => break,
+                        _ => return None,
+                    }
+                }
+                self.position += 1;
+                Some(JsonValue::Object(members))
+            }
+            _ => self.number().map(JsonValue::Number),
+        }
+    }
+
+    fn number(&mut self) -> Option<f64> {
+        let start = self.position;
+        while let Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') =
+            self.bytes.get(self.position)
+        {
+            self.position += 1;
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.position]).ok()?;
+        // Rust also reads "inf", "NaN" and "1."; JSON does not
+        if text.is_empty() || text.ends_with('.') || text.starts_with(['+', '.']) {
+            return None;
+        }
+        text.parse().ok()
+    }
+
+    /// String starting at the opening quote
+    fn string(&mut self) -> Option<String> {
+        self.position += 1;
+        let mut out = String::new();
+        loop {
+            let start = self.position;
+            while let Some(&byte) = self.bytes.get(self.position) {
+                if byte == b'"' || byte == b'\\' || byte < 0x20 {
+                    break;
+                }
+                self.position += 1;
+            }
+            out.push_str(std::str::from_utf8(&self.bytes[start..self.position]).ok()?);
+
+            match self.bytes.get(self.position)? {
+                b'"' => {
+                    self.position += 1;
+                    return Some(out);
+                }
+                b'\\' => {
+                    let escape = *self.bytes.get(self.position + 1)?;
+                    self.position += 2;
+                    match escape {
+                        b'"' => out.push('"'),
+                        b'\\' => out.push('\\'),
+                        b'/' => out.push('/'),
+                        b'b' => out.push('\u{8}'),
+                        b'f' => out.push('\u{c}'),
+                        b'n' => out.push('\n'),
+                        b'r' => out.push('\r'),
+                        b't' => out.push('\t'),
+                        b'u' => out.push(self.unicode_escape()?),
+                        _ => return None,
+                    }
+                }
+                // Raw control characters are not allowed in strings
+                _ => return None,
+            }
+        }
+    }
+
+    /// The XXXX of \uXXXX, and the low half of a surrogate pair after it
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high);
+        }
+        self.eat("\\u")?;
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return None;
+        }
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = self.bytes.get(self.position..self.position + 4)?;
+        let value = u32::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+        self.position += 4;
+        Some(value)
+    }
+}
@@ -0,0 +1,137 @@
+// =============================================================================
+// trading: Building Blocks of the QuickFIX Examples
+// =============================================================================
+// The examples of this directory used to share code by including files with
+// #[path]. This library is that code, with a stable surface, so downstream
+// projects can depend on it instead of copying example sources:
+//
+//   trading::alerts    conditions worth paging for (session down, reject
+//                      rate, risk breach, heartbeat latency), notifiers
+//   trading::audit     append-only log of operator commands
+//   trading::bus       decoded business events (fills, rejects, market
+//                      data, sessions) handed to every subscriber
+//   trading::bench     in-process acceptor / initiator throughput benchmark
+//   trading::clock     clock sync evidence against an SNTP server
+//   trading::conformance
+//                      scripted counterparty certification scenarios,
+//                      venue profiles checked before sending, the
+//                      standard self-check battery
+//   trading::session   session labels, decoded events, runtime provisioning,
+//                      tokio runtime helpers, version upgrade handover,
+//                      fast / batch event lanes, counterparty scorecards,
+//                      session schedules, config files with variables,
+//                      configuration checkup, file store maintenance,
+//                      Parquet message archive, typed message views,
+//                      structs mapped to messages (fix_message!),
+//                      outbound rate limits, a send queue for sessions
+//                      logged off, failover to backup gateways, warm
+//                      standby, lock-free message statistics, callback
+//                      log rings, a binary wire journal, counterparty
+//                      clock skew, end-of-day task scheduling, routing
+//                      between sessions, message transform rules
+//   trading::oms       order management, position keeping, local stops,
+//                      trade blotter, post-trade allocations, trade capture
+//                      reports, order mass status, duplicate orders,
+//                      ClOrdID generators, cancel on disconnect, daily
+//                      trading reports
+//   trading::instruments
+//                      security definitions and lists (35=c/d, 35=x/y):
+//                      tick size, multiplier, currency of each symbol
+//   trading::symbology internal symbols mapped to each venue's Symbol /
+//                      SecurityID and to ISIN, CUSIP, RIC, from CSV
+//   trading::expr      conditions on FIX messages as text, for alert, routing
+//                      and transform rules
+//   trading::risk      pre-trade risk checks, portfolio margin with offsets,
+//                      notionals in one currency with FX rates
+//   trading::md        market data subscriptions and snapshots (35=V/W)
+//   trading::quotes    quotes answering requests for quote (35=R/S)
+//   trading::news      headlines from news / sentiment feeds, normalized
+//   trading::sbe       SBE market data (CME MDP 3.0) decoded into books,
+//                      published as 35=W like a FIX market data session
+//   trading::synthetic baskets and indices priced from their constituents
+//   trading::sim       matching engine and venue profiles, paper trading
+//                      against live quotes
+//   trading::csv       CSV fields quoted for the blotter and report files
+//   trading::gzip      gzip archives written with std alone
+//   trading::parquet   Parquet files written with std alone
+//   trading::pcap      FIX messages from packet captures, TCP streams put
+//                      back in order
+//   trading::store     state kept across restarts: memory, file, SQLite,
+//                      Redis backends behind one trait, encrypted at rest
+//   trading::time      UTC calendar dates for trade dates and file names
+//   trading::gateway   HTTP, gRPC, Prometheus, WebSocket, webhook, SMTP, news
+//                      feed, SBE market data feed and Kafka gateways, EOD file
+//                      delivery, session shards behind a coordinator
+//   trading::testing   acceptor / initiator pairs for integration tests,
+//                      latency budgets, concurrent callbacks
+//
+// Features
+// --------
+// session, oms, risk, instruments, symbology, expr, news, sbe, synthetic, alerts, audit, bench,
+// bus, clock, conformance, csv, gzip, json, parquet, pcap, store and time are the core: they
+// need quickfix and std only. The rest is behind Cargo features, all enabled by default but
+// `testing`, `sqlite` and `redis`:
+//
+//   runtime   tokio: event channel and lanes, stdin lines, shutdown signal
+//   gateway   gateway::{delivery, grpc, http, http2, metrics, news, sbe, shards, smtp,
+//             webhook, websocket}
+//   kafka     gateway::kafka
+//   sim       sim, md and quotes
+//   testing   testing (with sim), for dev-dependencies
+//   sqlite    rusqlite (bundled SQLite): store::sqlite, file stores
+//             migrated to SQLite (session::file_store)
+//   redis     store::redis, over std TCP
+//   encryption  aes-gcm: store::encrypted
+//
+// A latency-sensitive build takes the core alone:
+//
+//   trading = { version = "0.2", default-features = false }
+//
+// The example binaries (buy_side, sell_side, fix_repl) are consumers of this
+// library like any other: what they need is public here, what they add on
+// top (strategy, shell, venue wiring) stays in the example.
+//
+// Versioning
+// ----------
+// The crate follows semantic versioning from 0.1.0 (see CHANGELOG.md). Until
+// 1.0, a minor version bump may break the public API and a patch bump may
+// not; every breaking change is listed in the changelog. Only the items
+// reachable through the modules above are covered: anything else,
+// including the example binaries, can change at any time.
+// =============================================================================
+
+pub mod alerts;
+pub mod audit;
+pub mod bench;
+pub mod bus;
+pub mod clock;
+pub mod conformance;
+pub mod csv;
+pub mod expr;
+#[cfg(any(feature = "gateway", feature = "kafka"))]
+pub mod gateway;
+pub mod gzip;
+pub mod instruments;
+pub mod json;
+#[cfg(feature = "sim")]
+pub mod md;
+pub mod news;
+pub mod oms;
+pub mod parquet;
+pub mod pcap;
+#[cfg(feature = "sim")]
+pub mod quotes;
+pub mod risk;
+pub mod sbe;
+pub mod session;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod store;
+pub mod symbology;
+pub mod synthetic;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod time;
+
+/// Version of the library, to log next to the QuickFIX version at startup
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,218 @@
+// =============================================================================
+// Market Data Publisher
+// =============================================================================
+// Tracks MarketDataRequest (35=V) subscriptions and publishes a top-of-book
+// MarketDataSnapshotFullRefresh (35=W) to every subscriber each time a book
+// changes:
+//
+//   269=0 bid   269=1 offer   269=2 last trade
+//
+// Subscriptions are keyed by symbol; each entry remembers the subscriber's
+// CompID and the MDReqID (262) to echo back. With a state store they are
+// written through under "subscriptions", so a subscriber that reconnects
+// after a restart of the venue keeps receiving snapshots without asking
+// again. A logout still drops them.
+// =============================================================================
+
+use std::{collections::HashMap, sync::Arc};
+
+use quickfix::{FieldMap, Group, Message, QuickFixError};
+
+use crate::{
+    json::{json_escape, JsonValue},
+    sim::{matching::OrderBook, venue::VenueProfile},
+    store::{StateStore, StoreError},
+};
+
+/// Store namespace of the subscribers, keyed by symbol
+pub const SUBSCRIPTIONS_NAMESPACE: &str = "subscriptions";
+
+#[derive(Debug, Clone)]
+struct Subscription {
+    comp_id: String,
+    md_req_id: String,
+}
+
+impl Subscription {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"comp_id\":\"{}\",\"md_req_id\":\"{}\"}}",
+            json_escape(&self.comp_id),
+            json_escape(&self.md_req_id)
+        )
+    }
+
+    fn from_value(value: &JsonValue) -> Option<Self> {
+        Some(Self {
+            comp_id: value.get("comp_id")?.as_str()?.to_string(),
+            md_req_id: value.get("md_req_id")?.as_str()?.to_string(),
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct MarketDataPublisher {
+    subscriptions: HashMap<String, Vec<Subscription>>,
+
+    /// Where the subscribers of each symbol are written through, if set
+    store: Option<Arc<dyn StateStore>>,
+}
+
+impl MarketDataPublisher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restore the subscriptions kept in a store, then write every change
+    /// through to it
+    pub fn with_store(mut self, store: Arc<dyn StateStore>) -> Result<Self, StoreError> {
+        for (symbol, value) in store.scan(SUBSCRIPTIONS_NAMESPACE)? {
+            let subscribers = JsonValue::parse(&value)
+                .as_ref()
+                .and_then(JsonValue::as_array)
+                .and_then(|x| {
+                    x.iter()
+                        .map(Subscription::from_value)
+                        .collect::<Option<_>>()
+                })
+                .ok_or_else(|| StoreError::Corrupt {
+                    namespace: SUBSCRIPTIONS_NAMESPACE.to_string(),
+                    key: symbol.clone(),
+                })?;
+            self.subscriptions.insert(symbol, subscribers);
+        }
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    /// Write the subscribers of a symbol through to the store, if any
+    fn persist(&self, symbol: &str) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let result = match self.subscriptions.get(symbol).filter(|x| !x.is_empty()) {
+            Some(subscribers) => {
+                let subscribers: Vec<_> = subscribers.iter().map(Subscription::to_json).collect();
+                let value = format!("[{}]", subscribers.join(","));
+                store.put(SUBSCRIPTIONS_NAMESPACE, symbol, &value)
+            }
+            None => store.delete(SUBSCRIPTIONS_NAMESPACE, symbol),
+        };
+        if let Err(err) = result {
+            eprintln!("Market data: cannot store subscriptions of {symbol}: {err}");
+        }
+    }
+
+    /// Apply a MarketDataRequest
+    ///
+    /// # Returns
+    /// The symbols newly subscribed (an initial snapshot should be sent)
+    pub fn on_request(&mut self, msg: &Message, comp_id: &str) -> Vec<String> {
+        let md_req_id = msg.get_field(262).unwrap_or_default();
+        let unsubscribe = msg.get_field(263).as_deref() == Some("2");
+
+        let count: i32 = msg.get_field(146).and_then(|x| x.parse().ok()).unwrap_or(0);
+        let mut symbols: Vec<String> = (1..=count)
+            .filter_map(|index| msg.clone_group(index, 146))
+            .filter_map(|group| group.get_field(55))
+            .collect();
+        // Some clients put a single Symbol outside of the group
+        symbols.extend(msg.get_field(55));
+
+        for symbol in &symbols {
+            let subscribers = self.subscriptions.entry(symbol.clone()).or_default();
+            subscribers.retain(|x| x.comp_id != comp_id || x.md_req_id != md_req_id);
+            if !unsubscribe {
+                subscribers.push(Subscription {
+                    comp_id: comp_id.to_string(),
+                    md_req_id: md_req_id.clone(),
+                });
+            }
+            self.persist(symbol);
+        }
+
+        if unsubscribe {
+            Vec::new()
+        } else {
+            symbols
+        }
+    }
+
+    /// Drop every subscription of a counterparty (on logout)
+    pub fn remove_session(&mut self, comp_id: &str) {
+        let mut changed = Vec::new();
+        for (symbol, subscribers) in &mut self.subscriptions {
+            let count = subscribers.len();
+            subscribers.retain(|x| x.comp_id != comp_id);
+            if subscribers.len() != count {
+                changed.push(symbol.clone());
+            }
+        }
+        for symbol in changed {
+            self.persist(&symbol);
+        }
+    }
+
+    /// Build the snapshots to send for a symbol, one per subscriber CompID
+    pub fn snapshots(
+        &self,
+        symbol: &str,
+        book: Option<&OrderBook>,
+        profile: &VenueProfile,
+    ) -> Vec<(Message, String)> {
+        let Some(subscribers) = self.subscriptions.get(symbol) else {
+            return Vec::new();
+        };
+
+        subscribers
+            .iter()
+            .filter_map(|sub| {
+                build_snapshot(&sub.md_req_id, symbol, book, profile)
+                    .ok()
+                    .map(|msg| (msg, sub.comp_id.clone()))
+            })
+            .collect()
+    }
+
+    /// Number of (symbol, subscriber) pairs, for the admin API
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.values().map(Vec::len).sum()
+    }
+}
+
+fn build_snapshot(
+    md_req_id: &str,
+    symbol: &str,
+    book: Option<&OrderBook>,
+    profile: &VenueProfile,
+) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "W"))?;
+    msg.set_field(262, md_req_id)?;
+    msg.set_field(55, symbol)?;
+
+    let mut entries = Vec::new();
+    if let Some(book) = book {
+        if let Some((px, qty)) = book.best_bid() {
+            entries.push(("0", px, Some(qty)));
+        }
+        if let Some((px, qty)) = book.best_ask() {
+            entries.push(("1", px, Some(qty)));
+        }
+        if let Some(px) = book.last_trade() {
+            entries.push(("2", px, None));
+        }
+    }
+
+    for (entry_type, price_ticks, size) in entries {
+        let mut group = Group::try_new(268, 269)?;
+        group.set_field(269, entry_type)?;
+        group.set_field(270, profile.to_price(price_ticks).to_string().as_str())?;
+        if let Some(size) = size {
+            group.set_field(271, size.to_string().as_str())?;
+        }
+        msg.add_group(&group)?;
+    }
+
+    Ok(msg)
+}
@@ -0,0 +1,318 @@
+// =============================================================================
+// News and Sentiment Events
+// =============================================================================
+// Headlines from external feeds, normalized so strategies see one shape
+// whatever the provider:
+//
+//   {"id":"n1","title":"Apple beats estimates","tickers":["AAPL"],
+//    "sentiment":0.8,"published_at":"2024-01-02T13:30:00Z","source":"wire"}
+//   -> NewsEvent { id: "n1", headline: "Apple beats estimates",
+//                  symbols: ["AAPL"], sentiment: Some(0.8), ... }
+//
+// A document is one article, an array of articles, or an object holding the
+// array (`articles`, `data`, `items`, `news`, `results`). Field names of the
+// usual providers are accepted (see `normalize`); anything without a
+// headline, like a feed's own heartbeat, is skipped.
+//
+// Symbols come from the article's own tags, plus the tickers and company
+// names a SymbolTagger finds in the headline and summary. Sentiment is a
+// score in -1.0 (bearish) ..= 1.0 (bullish), from a number or a label.
+//
+// The events travel on the same bus as FIX events (FixEvent::News), fed by
+// gateway::news for WebSocket and REST feeds.
+// =============================================================================
+
+use std::{
+    fmt::Write as _,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    json::{json_escape, JsonValue},
+    time::{parse_iso8601, parse_utc_timestamp},
+};
+
+/// Members holding the articles of a wrapped document
+const ARTICLE_LISTS: [&str; 5] = ["articles", "data", "items", "news", "results"];
+
+/// One headline, normalized
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewsEvent {
+    /// The provider's id, or one derived from the content; stable across
+    /// repeated deliveries, for deduplication
+    pub id: String,
+
+    /// Provider, or the feed name when the article does not say
+    pub source: String,
+    pub headline: String,
+    pub summary: Option<String>,
+
+    /// Tagged symbols, uppercase, without duplicates
+    pub symbols: Vec<String>,
+
+    /// -1.0 (bearish) ..= 1.0 (bullish)
+    pub sentiment: Option<f64>,
+
+    /// Unix milliseconds: publication (receipt when the article has no
+    /// time) and receipt
+    pub published_ms: i64,
+    pub received_ms: i64,
+}
+
+impl NewsEvent {
+    pub fn is_about(&self, symbol: &str) -> bool {
+        self.symbols.iter().any(|x| x == symbol)
+    }
+
+    /// `{"id":..,"source":..,"headline":..,"summary":..,"symbols":[..],
+    /// "sentiment":..,"published_ms":..,"received_ms":..}`
+    pub fn to_json(&self) -> String {
+        let summary = self
+            .summary
+            .as_ref()
+            .map_or("null".to_string(), |x| format!("\"{}\"", json_escape(x)));
+        let mut symbols = String::new();
+        for (index, symbol) in self.symbols.iter().enumerate() {
+            let separator = if index == 0 { "" } else { "," };
+            let _ = write!(symbols, "{separator}\"{}\"", json_escape(symbol));
+        }
+        format!(
+            "{{\"id\":\"{}\",\"source\":\"{}\",\"headline\":\"{}\",\"summary\":{},\
+             \"symbols\":[{symbols}],\"sentiment\":{},\"published_ms\":{},\"received_ms\":{}}}",
+            json_escape(&self.id),
+            json_escape(&self.source),
+            json_escape(&self.headline),
+            summary,
+            self.sentiment.map_or("null".to_string(), |x| x.to_string()),
+            self.published_ms,
+            self.received_ms
+        )
+    }
+}
+
+// =============================================================================
+// Symbol Tagging
+// =============================================================================
+
+/// Finds instruments mentioned in free text
+///
+/// Tickers match as whole words, case-sensitive, with or without a `$`
+/// (`AAPL`, `$AAPL`). Aliases, such as company names, match as whole words
+/// in any case.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTagger {
+    symbols: Vec<String>,
+
+    /// Lowercase alias -> symbol
+    aliases: Vec<(String, String)>,
+}
+
+impl SymbolTagger {
+    pub fn new<I, S>(symbols: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            symbols: symbols.into_iter().map(Into::into).collect(),
+            aliases: Vec::new(),
+        }
+    }
+
+    /// Tag `symbol` when `alias` (e.g. "Apple") appears
+    pub fn with_alias(mut self, alias: &str, symbol: &str) -> Self {
+        self.aliases.push((alias.to_lowercase(), symbol.to_string()));
+        self
+    }
+
+    /// Symbols mentioned in `text`, in tagger order
+    pub fn tag(&self, text: &str) -> Vec<String> {
+        let words: Vec<&str> = text
+            .split(|c: char| !(c.is_alphanumeric() || c == '.' || c == '$'))
+            .map(|x| x.trim_start_matches('$').trim_end_matches('.'))
+            .filter(|x| !x.is_empty())
+            .collect();
+        let lowercase = text.to_lowercase();
+
+        let mut found: Vec<String> = self
+            .symbols
+            .iter()
+            .filter(|symbol| words.contains(&symbol.as_str()))
+            .cloned()
+            .collect();
+        for (alias, symbol) in &self.aliases {
+            if !found.contains(symbol) && contains_word(&lowercase, alias) {
+                found.push(symbol.clone());
+            }
+        }
+        found
+    }
+}
+
+/// `needle` in `haystack`, not inside a longer word
+fn contains_word(haystack: &str, needle: &str) -> bool {
+    if needle.is_empty() {
+        return false;
+    }
+    haystack.match_indices(needle).any(|(index, _)| {
+        let before = haystack[..index].chars().next_back();
+        let after = haystack[index + needle.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+// =============================================================================
+// Normalization
+// =============================================================================
+
+/// Unix milliseconds, now
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_millis() as i64)
+}
+
+/// Turn one feed document into events; None if it is not JSON
+///
+/// Accepted fields, first present wins:
+///
+///   headline    headline, title
+///   summary     summary, description, body
+///   id          id, uuid, guid, url
+///   source      source (or source.name), provider, publisher
+///   symbols     symbols, tickers, related (array or comma separated)
+///   sentiment   sentiment, sentiment_score, score: a number, a label
+///               (positive / bullish, neutral, negative / bearish) or an
+///               object with score / polarity / label
+///   time        published_at, publishedAt, datetime, timestamp, time:
+///               ISO 8601, FIX UTCTimestamp, or Unix seconds / milliseconds
+///
+/// # Arguments
+/// * `document` - One message of a WebSocket feed, one REST response body
+/// * `feed` - Source name when the article has none
+/// * `tagger` - Finds more symbols in the headline and summary
+pub fn normalize(document: &str, feed: &str, tagger: &SymbolTagger) -> Option<Vec<NewsEvent>> {
+    let document = JsonValue::parse(document)?;
+    let received_ms = now_ms();
+
+    let articles: &[JsonValue] = match &document {
+        JsonValue::Array(items) => items,
+        JsonValue::Object(_) => ARTICLE_LISTS
+            .iter()
+            .find_map(|key| document.get(key)?.as_array())
+            .unwrap_or(std::slice::from_ref(&document)),
+        _ => &[],
+    };
+    Some(
+        articles
+            .iter()
+            .filter_map(|article| article_event(article, feed, tagger, received_ms))
+            .collect(),
+    )
+}
+
+fn article_event(
+    article: &JsonValue,
+    feed: &str,
+    tagger: &SymbolTagger,
+    received_ms: i64,
+) -> Option<NewsEvent> {
+    let first = |keys: &[&str]| keys.iter().find_map(|key| article.get(key));
+    let text = |keys: &[&str]| {
+        first(keys)
+            .and_then(JsonValue::as_str)
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(str::to_string)
+    };
+
+    let headline = text(&["headline", "title"])?;
+    let summary = text(&["summary", "description", "body"]);
+    let source = first(&["source", "provider", "publisher"])
+        .and_then(|x| x.as_str().or_else(|| x.get("name")?.as_str()))
+        .unwrap_or(feed)
+        .to_string();
+    let published_ms = first(&["published_at", "publishedAt", "datetime", "timestamp", "time"])
+        .and_then(parse_time)
+        .unwrap_or(received_ms);
+    let sentiment = first(&["sentiment", "sentiment_score", "score"]).and_then(parse_sentiment);
+
+    let mut symbols: Vec<String> = Vec::new();
+    let declared = first(&["symbols", "tickers", "related"]);
+    let declared: Vec<&str> = match declared {
+        Some(JsonValue::Array(items)) => items.iter().filter_map(JsonValue::as_str).collect(),
+        Some(JsonValue::String(list)) => list.split(',').collect(),
+        _ => Vec::new(),
+    };
+    let tagged = tagger.tag(&format!("{headline}\n{}", summary.as_deref().unwrap_or_default()));
+    for symbol in declared.into_iter().map(str::trim).chain(tagged.iter().map(String::as_str)) {
+        let symbol = symbol.trim_start_matches('$').to_uppercase();
+        if !symbol.is_empty() && !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+
+    let id = match first(&["id", "uuid", "guid", "url"]) {
+        Some(JsonValue::String(id)) if !id.is_empty() => id.clone(),
+        Some(JsonValue::Number(id)) => id.to_string(),
+        _ => format!("{source}-{published_ms}-{:016x}", fnv1a(&headline)),
+    };
+
+    Some(NewsEvent {
+        id,
+        source,
+        headline,
+        summary,
+        symbols,
+        sentiment,
+        published_ms,
+        received_ms,
+    })
+}
+
+/// Unix milliseconds of a time field
+fn parse_time(value: &JsonValue) -> Option<i64> {
+    let from_number = |x: f64| {
+        // Seconds until the year 5138, milliseconds after
+        let ms = if x.abs() < 1e11 { x * 1_000.0 } else { x };
+        ms.is_finite().then_some(ms as i64)
+    };
+    match value {
+        JsonValue::Number(x) => from_number(*x),
+        JsonValue::String(x) => parse_iso8601(x)
+            .or_else(|| parse_utc_timestamp(x))
+            .map(|nanos| nanos / 1_000_000)
+            .or_else(|| x.parse().ok().and_then(from_number)),
+        _ => None,
+    }
+}
+
+/// -1.0 ..= 1.0 from a score, a label or an object holding either
+fn parse_sentiment(value: &JsonValue) -> Option<f64> {
+    match value {
+        JsonValue::Number(x) if x.is_finite() => Some(x.clamp(-1.0, 1.0)),
+        JsonValue::String(label) => match label.to_lowercase().as_str() {
+            "positive" | "bullish" => Some(1.0),
+            "somewhat-bullish" | "somewhat_bullish" => Some(0.5),
+            "neutral" => Some(0.0),
+            "somewhat-bearish" | "somewhat_bearish" => Some(-0.5),
+            "negative" | "bearish" => Some(-1.0),
+            other => other
+                .parse::<f64>()
+                .ok()
+                .filter(|x| x.is_finite())
+                .map(|x| x.clamp(-1.0, 1.0)),
+        },
+        JsonValue::Object(_) => ["score", "polarity", "label"]
+            .iter()
+            .find_map(|key| parse_sentiment(value.get(key)?)),
+        _ => None,
+    }
+}
+
+/// FNV-1a, for ids derived from the headline
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,850 @@
+// =============================================================================
+// Order Management System (OMS)
+// =============================================================================
+// Keeps the book of orders we sent, keyed by ClOrdID (tag 11), and applies
+// ExecutionReports (35=8) received from the venue to move each order through
+// its life cycle:
+//
+//   PendingNew -> New -> PartiallyFilled -> Filled
+//                    \-> Canceled / Rejected
+//
+// Fills are remembered by ExecID (17) so the venue can take them back: a
+// trade cancel (ExecType 150=H) busts the fill its ExecRefID (19) points at,
+// a trade correct (150=G) replaces its quantity and price. Both come out as
+// a Correction, kept in order so the chain of amendments of any fill can be
+// audited. FIX 4.2 venues say the same with ExecTransType (20) = 1 / 2.
+//
+// The OMS is the single source of truth for "what is working in the market".
+// The risk checks, the position keeper and the strategy all read from it.
+//
+// With a state store (store::StateStore) every order is written through
+// under "orders", and the ClOrdID sequence under "counters", so a restarted
+// process knows its working orders and never reuses a ClOrdID.
+//
+// Orders are plain limit or market orders, what every venue accepts; stops
+// and take-profits are held locally until they trigger (stops.rs).
+//
+// Every fill, whoever's order it is, can also be kept in a trade blotter
+// (blotter.rs) to be filtered, paged through and exported.
+//
+// Once filled, the fills of a block are split between accounts with an
+// AllocationInstruction, whose status is tracked (allocations.rs).
+//
+// At the end of the day the venue's own record of the trades is asked for
+// with a TradeCaptureReportRequest and kept, to reconcile against the fills
+// (captures.rs). The orders still open are checked the same way, against an
+// OrderMassStatusRequest's reports (mass_status.rs).
+//
+// Order messages typed by hand or scripted, with ClOrdIDs of their own, go
+// through a DuplicateGuard before they are sent: a ClOrdID reused or the
+// same order twice in a short window is refused, and one left out is
+// generated (duplicates.rs).
+//
+// ClOrdIDs come from a generator: PREFIX-N by default, dated, UUIDs, or a
+// venue's length limit (ids.rs). Its sequence resumes after the ClOrdIDs
+// restored from the state store and those found in the message store.
+//
+// The orders open on a session that drops are canceled when it logs on
+// again, or left for review (disconnect.rs).
+//
+// A day's orders, fills and rejects add up to a trading report per session
+// and symbol: cancel ratio, notional, slippage against the arrival price,
+// as text, HTML or JSON (report.rs).
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use quickfix::{FieldMap, Message, QuickFixError};
+
+use crate::{
+    json::{json_escape, JsonValue},
+    session::{
+        events::FixMessage,
+        mapping::{FieldSink, FieldValue},
+        view::{MessageView, ViewError},
+    },
+    store::{StateStore, StoreError},
+    time::{transact_time, unix_now},
+};
+
+use self::ids::{ClOrdIdGenerator, SequenceIds};
+
+pub mod allocations;
+pub mod blotter;
+pub mod captures;
+pub mod disconnect;
+pub mod duplicates;
+pub mod ids;
+pub mod mass_status;
+pub mod positions;
+pub mod report;
+pub mod stops;
+
+/// Store namespace of the orders, keyed by ClOrdID
+pub const ORDERS_NAMESPACE: &str = "orders";
+
+/// Store namespace of the ClOrdID sequences, keyed by prefix
+pub const COUNTERS_NAMESPACE: &str = "counters";
+
+// =============================================================================
+// Order Model
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    /// Lower-case name used in JSON
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }
+    }
+
+    /// FIX Side (tag 54) value
+    pub fn as_fix(self) -> &'static str {
+        match self {
+            Side::Buy => "1",
+            Side::Sell => "2",
+        }
+    }
+
+    /// Inverse of `as_fix`; other sides (sell short, ...) are not ours
+    pub fn from_fix(value: &str) -> Option<Self> {
+        match value {
+            "1" => Some(Side::Buy),
+            "2" => Some(Side::Sell),
+            _ => None,
+        }
+    }
+
+    /// Inverse of `as_str`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "buy" => Some(Side::Buy),
+            "sell" => Some(Side::Sell),
+            _ => None,
+        }
+    }
+}
+
+impl FieldValue for Side {
+    fn read(view: &MessageView<'_>, tag: i32) -> Result<Self, ViewError> {
+        view.get_with(tag, Side::from_fix)
+    }
+
+    fn write<S: FieldSink>(&self, sink: &mut S, tag: i32) -> Result<(), QuickFixError> {
+        sink.set_field(tag, self.as_fix())
+    }
+}
+
+/// OrdType (tag 40) of the orders we send
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrdType {
+    Market,
+    Limit,
+}
+
+impl OrdType {
+    /// Lower-case name used in JSON
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OrdType::Market => "market",
+            OrdType::Limit => "limit",
+        }
+    }
+
+    /// FIX OrdType (tag 40) value
+    pub fn as_fix(self) -> &'static str {
+        match self {
+            OrdType::Market => "1",
+            OrdType::Limit => "2",
+        }
+    }
+
+    /// Inverse of `as_fix`
+    pub fn from_fix(value: &str) -> Option<Self> {
+        match value {
+            "1" => Some(OrdType::Market),
+            "2" => Some(OrdType::Limit),
+            _ => None,
+        }
+    }
+
+    /// Inverse of `as_str`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "market" => Some(OrdType::Market),
+            "limit" => Some(OrdType::Limit),
+            _ => None,
+        }
+    }
+}
+
+impl FieldValue for OrdType {
+    fn read(view: &MessageView<'_>, tag: i32) -> Result<Self, ViewError> {
+        view.get_with(tag, OrdType::from_fix)
+    }
+
+    fn write<S: FieldSink>(&self, sink: &mut S, tag: i32) -> Result<(), QuickFixError> {
+        sink.set_field(tag, self.as_fix())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStatus {
+    /// Sent, not yet acknowledged by the venue
+    PendingNew,
+    New,
+    PartiallyFilled,
+    Filled,
+    /// Cancel sent, not yet confirmed
+    PendingCancel,
+    Canceled,
+    Rejected,
+}
+
+impl OrderStatus {
+    /// Map FIX OrdStatus (tag 39) to our status
+    pub fn from_fix(value: &str) -> Option<Self> {
+        match value {
+            "0" => Some(OrderStatus::New),
+            "1" => Some(OrderStatus::PartiallyFilled),
+            "2" => Some(OrderStatus::Filled),
+            "4" => Some(OrderStatus::Canceled),
+            "6" => Some(OrderStatus::PendingCancel),
+            "8" => Some(OrderStatus::Rejected),
+            "A" => Some(OrderStatus::PendingNew),
+            _ => None,
+        }
+    }
+
+    /// Status as written in JSON (`PendingNew`, `Filled`...)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "PendingNew" => Some(OrderStatus::PendingNew),
+            "New" => Some(OrderStatus::New),
+            "PartiallyFilled" => Some(OrderStatus::PartiallyFilled),
+            "Filled" => Some(OrderStatus::Filled),
+            "PendingCancel" => Some(OrderStatus::PendingCancel),
+            "Canceled" => Some(OrderStatus::Canceled),
+            "Rejected" => Some(OrderStatus::Rejected),
+            _ => None,
+        }
+    }
+
+    /// An order is working while it can still trade
+    pub fn is_working(self) -> bool {
+        matches!(
+            self,
+            OrderStatus::PendingNew
+                | OrderStatus::New
+                | OrderStatus::PartiallyFilled
+                | OrderStatus::PendingCancel
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Order {
+    pub cl_ord_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+
+    /// Limit price; for a market order, the price it was risk-checked at
+    pub price: f64,
+    pub ord_type: OrdType,
+    pub cum_qty: f64,
+    pub avg_px: f64,
+    pub status: OrderStatus,
+}
+
+impl Order {
+    pub fn leaves_qty(&self) -> f64 {
+        if self.status.is_working() {
+            self.quantity - self.cum_qty
+        } else {
+            0.0
+        }
+    }
+
+    /// Build the NewOrderSingle (35=D) for this order
+    pub fn to_new_order_single(&self) -> Result<Message, QuickFixError> {
+        let mut msg = Message::new();
+        msg.with_header_mut(|h| h.set_field(35, "D"))?;
+        msg.set_field(11, self.cl_ord_id.as_str())?; // ClOrdID
+        msg.set_field(21, "1")?; // HandlInst: automated, no intervention
+        msg.set_field(55, self.symbol.as_str())?; // Symbol
+        msg.set_field(54, self.side.as_fix())?; // Side
+        msg.set_field(38, self.quantity.to_string().as_str())?; // OrderQty
+        msg.set_field(40, self.ord_type.as_fix())?; // OrdType
+        if self.ord_type == OrdType::Limit {
+            msg.set_field(44, self.price.to_string().as_str())?; // Price
+        }
+        msg.set_field(59, "0")?; // TimeInForce: Day
+        msg.set_field(60, transact_time().as_str())?; // TransactTime
+        Ok(msg)
+    }
+
+    /// Build the OrderCancelRequest (35=F) for this order
+    pub fn to_cancel_request(&self, cancel_cl_ord_id: &str) -> Result<Message, QuickFixError> {
+        let mut msg = Message::new();
+        msg.with_header_mut(|h| h.set_field(35, "F"))?;
+        msg.set_field(11, cancel_cl_ord_id)?; // ClOrdID of the cancel itself
+        msg.set_field(41, self.cl_ord_id.as_str())?; // OrigClOrdID
+        msg.set_field(55, self.symbol.as_str())?;
+        msg.set_field(54, self.side.as_fix())?;
+        msg.set_field(38, self.quantity.to_string().as_str())?;
+        msg.set_field(60, transact_time().as_str())?; // TransactTime
+        Ok(msg)
+    }
+
+    /// JSON object for the REST gateway and the WebSocket stream
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"cl_ord_id\":\"{}\",\"symbol\":\"{}\",\"side\":\"{}\",\"quantity\":{},\
+             \"price\":{},\"ord_type\":\"{}\",\"cum_qty\":{},\"avg_px\":{},\"leaves_qty\":{},\
+             \"status\":\"{:?}\"}}",
+            json_escape(&self.cl_ord_id),
+            json_escape(&self.symbol),
+            self.side.as_str(),
+            self.quantity,
+            self.price,
+            self.ord_type.as_str(),
+            self.cum_qty,
+            self.avg_px,
+            self.leaves_qty(),
+            self.status
+        )
+    }
+
+    /// Read back `to_json`; None if it is not an order
+    pub fn from_json(json: &str) -> Option<Self> {
+        Self::from_value(&JsonValue::parse(json)?)
+    }
+
+    /// Read back `to_json`, already parsed
+    ///
+    /// Orders written before OrdType was kept are limit orders.
+    pub fn from_value(value: &JsonValue) -> Option<Self> {
+        let text = |key| value.get(key).and_then(JsonValue::as_str);
+        let number = |key| value.get(key).and_then(JsonValue::as_f64);
+        Some(Self {
+            cl_ord_id: text("cl_ord_id")?.to_string(),
+            symbol: text("symbol")?.to_string(),
+            side: Side::from_name(text("side")?)?,
+            quantity: number("quantity")?,
+            price: number("price")?,
+            ord_type: match text("ord_type") {
+                Some(name) => OrdType::from_name(name)?,
+                None => OrdType::Limit,
+            },
+            cum_qty: number("cum_qty")?,
+            avg_px: number("avg_px")?,
+            status: OrderStatus::from_name(text("status")?)?,
+        })
+    }
+}
+
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let price = match self.ord_type {
+            OrdType::Market => "MKT".to_string(),
+            OrdType::Limit => self.price.to_string(),
+        };
+        write!(
+            f,
+            "{} {:?} {} {}@{} filled={}@{} status={:?}",
+            self.cl_ord_id,
+            self.side,
+            self.symbol,
+            self.quantity,
+            price,
+            self.cum_qty,
+            self.avg_px,
+            self.status
+        )
+    }
+}
+
+/// A single execution extracted from an ExecutionReport
+#[derive(Debug, Clone)]
+pub struct Fill {
+    /// ExecID (17) of the report; empty if the venue sent none
+    pub exec_id: String,
+    pub cl_ord_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    pub price: f64,
+}
+
+impl Fill {
+    /// JSON object for the REST gateway
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"exec_id\":\"{}\",\"cl_ord_id\":\"{}\",\"symbol\":\"{}\",\"side\":\"{}\",\
+             \"quantity\":{},\"price\":{}}}",
+            json_escape(&self.exec_id),
+            json_escape(&self.cl_ord_id),
+            json_escape(&self.symbol),
+            self.side.as_str(),
+            self.quantity,
+            self.price
+        )
+    }
+
+    /// Read back `to_json`; None if it is not a fill
+    pub fn from_value(value: &JsonValue) -> Option<Self> {
+        let text = |key| value.get(key).and_then(JsonValue::as_str);
+        let number = |key| value.get(key).and_then(JsonValue::as_f64);
+        Some(Self {
+            exec_id: text("exec_id")?.to_string(),
+            cl_ord_id: text("cl_ord_id")?.to_string(),
+            symbol: text("symbol")?.to_string(),
+            side: Side::from_name(text("side")?)?,
+            quantity: number("quantity")?,
+            price: number("price")?,
+        })
+    }
+}
+
+/// A trade bust (ExecType H) or trade correct (ExecType G) of an earlier fill
+#[derive(Debug, Clone)]
+pub struct Correction {
+    /// ExecID of the bust / correct report
+    pub exec_id: String,
+
+    /// ExecRefID (19): ExecID of the fill it amends
+    pub exec_ref_id: String,
+
+    /// The fill as it stood before
+    pub original: Fill,
+
+    /// The fill as corrected, under the correct's ExecID; None for a bust
+    pub corrected: Option<Fill>,
+
+    /// Unix seconds when received
+    pub time: i64,
+}
+
+impl Correction {
+    pub fn is_bust(&self) -> bool {
+        self.corrected.is_none()
+    }
+
+    /// JSON object for the REST gateway and the WebSocket stream
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"type\":\"{}\",\"exec_id\":\"{}\",\"exec_ref_id\":\"{}\",\"original\":{},\
+             \"corrected\":{},\"time\":{}}}",
+            if self.is_bust() { "bust" } else { "correct" },
+            json_escape(&self.exec_id),
+            json_escape(&self.exec_ref_id),
+            self.original.to_json(),
+            self.corrected
+                .as_ref()
+                .map_or("null".to_string(), Fill::to_json),
+            self.time
+        )
+    }
+}
+
+/// Result of applying an ExecutionReport
+#[derive(Debug, Clone)]
+pub struct OrderUpdate {
+    /// Order state after the report
+    pub order: Order,
+
+    /// Status before the report, to detect state transitions
+    pub previous_status: OrderStatus,
+
+    /// The fill carried by the report, if any (LastQty > 0)
+    pub fill: Option<Fill>,
+
+    /// Set when the report busts or corrects a fill we know of
+    pub correction: Option<Correction>,
+}
+
+/// Whether a report amends an earlier fill
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Execution {
+    /// New, fill, cancel, reject...: anything that is not an amendment
+    Ordinary,
+    TradeCancel,
+    TradeCorrect,
+}
+
+impl Execution {
+    fn of(msg: &FixMessage) -> Self {
+        match (msg.get(150), msg.get(20)) {
+            (Some("H"), _) | (_, Some("1")) => Execution::TradeCancel,
+            (Some("G"), _) | (_, Some("2")) => Execution::TradeCorrect,
+            _ => Execution::Ordinary,
+        }
+    }
+}
+
+/// Fills still standing, by ExecID, and every correction received
+#[derive(Default)]
+struct FillHistory {
+    fills: HashMap<String, Fill>,
+    corrections: Vec<Correction>,
+}
+
+// =============================================================================
+// OrderManager
+// =============================================================================
+// Interior mutability (Mutex) because QuickFIX callbacks only give us &self.
+// =============================================================================
+
+pub struct OrderManager {
+    orders: Mutex<HashMap<String, Order>>,
+    history: Mutex<FillHistory>,
+    ids: Box<dyn ClOrdIdGenerator>,
+    id_prefix: String,
+
+    /// Where orders and the ClOrdID sequence are written through, if set
+    store: Option<Arc<dyn StateStore>>,
+}
+
+impl OrderManager {
+    /// Create a new OMS
+    ///
+    /// # Arguments
+    /// * `id_prefix` - Prefix of generated ClOrdIDs (`PREFIX-N`), must be
+    ///   unique per run unless a state store keeps the sequence
+    ///   (`with_store`)
+    pub fn new(id_prefix: &str) -> Self {
+        Self {
+            orders: Mutex::new(HashMap::new()),
+            history: Mutex::new(FillHistory::default()),
+            ids: Box::new(SequenceIds::new(id_prefix)),
+            id_prefix: id_prefix.to_string(),
+            store: None,
+        }
+    }
+
+    /// Take the ClOrdIDs from this generator instead, before `with_store`
+    ///
+    /// `id_prefix` still names the sequence in the store and the handover.
+    pub fn with_id_generator(mut self, ids: Box<dyn ClOrdIdGenerator>) -> Self {
+        ids.advance(self.ids.sequence());
+        self.ids = ids;
+        self
+    }
+
+    /// Restore the orders and ClOrdID sequence kept in a store, then write
+    /// every change through to it
+    ///
+    /// Orders of other prefixes sharing the store are restored too; the
+    /// sequence resumes after the highest ClOrdID of ours, whether or not
+    /// its counter was stored.
+    pub fn with_store(mut self, store: Arc<dyn StateStore>) -> Result<Self, StoreError> {
+        if let Some(value) = store.get(COUNTERS_NAMESPACE, &self.id_prefix)? {
+            let next_id = value.trim().parse().map_err(|_| StoreError::Corrupt {
+                namespace: COUNTERS_NAMESPACE.to_string(),
+                key: self.id_prefix.clone(),
+            })?;
+            self.ids.advance(next_id);
+        }
+
+        let orders = self.orders.get_mut().expect("OMS lock poisoned");
+        for (key, value) in store.scan(ORDERS_NAMESPACE)? {
+            let order = Order::from_json(&value).ok_or_else(|| StoreError::Corrupt {
+                namespace: ORDERS_NAMESPACE.to_string(),
+                key: key.clone(),
+            })?;
+            if let Some(next_id) = self.ids.sequence_after(&order.cl_ord_id) {
+                self.ids.advance(next_id);
+            }
+            orders.insert(order.cl_ord_id.clone(), order);
+        }
+
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    /// Allocate a new unique ClOrdID
+    pub fn next_cl_ord_id(&self) -> String {
+        let id = self.ids.next_id();
+        let next = self.ids.sequence();
+        if let Some(store) = self.store.as_ref().filter(|_| next > 0) {
+            if let Err(err) = store.put(COUNTERS_NAMESPACE, &self.id_prefix, &next.to_string()) {
+                eprintln!(
+                    "OMS: cannot store ClOrdID sequence {}: {err}",
+                    self.id_prefix
+                );
+            }
+        }
+        id
+    }
+
+    /// Resume the sequence after ClOrdIDs sent before, e.g. those of the
+    /// message store (ids::sent_cl_ord_ids)
+    ///
+    /// # Returns
+    /// How many of them the generator could have made
+    pub fn resume_after<'a, I: IntoIterator<Item = &'a str>>(&self, ids: I) -> usize {
+        let mut ours = 0;
+        for id in ids {
+            if let Some(next_id) = self.ids.sequence_after(id) {
+                self.ids.advance(next_id);
+                ours += 1;
+            }
+        }
+        ours
+    }
+
+    /// Prefix of the ClOrdIDs this OMS allocates
+    pub fn id_prefix(&self) -> &str {
+        &self.id_prefix
+    }
+
+    /// Number of the next ClOrdID to allocate, 0 for a generator without
+    /// a sequence
+    pub fn sequence(&self) -> u64 {
+        self.ids.sequence()
+    }
+
+    /// Take over the orders and ClOrdID sequence of another process (a
+    /// version upgrade's handover)
+    ///
+    /// Orders replace those of the same ClOrdID; the sequence only moves
+    /// forward.
+    pub fn restore(&self, orders: Vec<Order>, sequence: u64) {
+        let mut book = self.orders.lock().expect("OMS lock poisoned");
+        for order in orders {
+            self.persist(&order);
+            book.insert(order.cl_ord_id.clone(), order);
+        }
+        self.ids.advance(sequence);
+    }
+
+    /// Write an order through to the store, if any
+    ///
+    /// A failed write is logged, not returned: the order is already in the
+    /// market, and the next change of it writes it again.
+    fn persist(&self, order: &Order) {
+        if let Some(store) = &self.store {
+            if let Err(err) = store.put(ORDERS_NAMESPACE, &order.cl_ord_id, &order.to_json()) {
+                eprintln!("OMS: cannot store order {}: {err}", order.cl_ord_id);
+            }
+        }
+    }
+
+    /// Register a new limit order in PendingNew state and return it
+    pub fn create_order(&self, symbol: &str, side: Side, quantity: f64, price: f64) -> Order {
+        self.create_order_of_type(symbol, side, quantity, price, OrdType::Limit)
+    }
+
+    /// Register a new market order in PendingNew state and return it
+    ///
+    /// `price` is not sent: it is what the order is expected to trade at,
+    /// for the risk checks and the displays.
+    pub fn create_market_order(
+        &self,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+        price: f64,
+    ) -> Order {
+        self.create_order_of_type(symbol, side, quantity, price, OrdType::Market)
+    }
+
+    fn create_order_of_type(
+        &self,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+        price: f64,
+        ord_type: OrdType,
+    ) -> Order {
+        let order = Order {
+            cl_ord_id: self.next_cl_ord_id(),
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            price,
+            ord_type,
+            cum_qty: 0.0,
+            avg_px: 0.0,
+            status: OrderStatus::PendingNew,
+        };
+        self.orders
+            .lock()
+            .expect("OMS lock poisoned")
+            .insert(order.cl_ord_id.clone(), order.clone());
+        self.persist(&order);
+        order
+    }
+
+    /// Mark an order as rejected locally (e.g. the send failed)
+    pub fn reject_locally(&self, cl_ord_id: &str) -> Option<Order> {
+        self.set_status(cl_ord_id, OrderStatus::Rejected)
+    }
+
+    /// Mark an order as pending cancel
+    pub fn mark_pending_cancel(&self, cl_ord_id: &str) -> Option<Order> {
+        self.set_status(cl_ord_id, OrderStatus::PendingCancel)
+    }
+
+    fn set_status(&self, cl_ord_id: &str, status: OrderStatus) -> Option<Order> {
+        let mut orders = self.orders.lock().expect("OMS lock poisoned");
+        let order = orders.get_mut(cl_ord_id)?;
+        order.status = status;
+        self.persist(order);
+        Some(order.clone())
+    }
+
+    /// Apply an ExecutionReport to the order book
+    ///
+    /// A bust or correct of a fill that is not known (sent before a restart,
+    /// or already busted) still updates the order, without a Correction.
+    ///
+    /// # Returns
+    /// The updated order and its fill, or None if the order is not ours
+    pub fn on_execution_report(&self, msg: &FixMessage) -> Option<OrderUpdate> {
+        // Cancels are reported with the cancel's ClOrdID and OrigClOrdID (41)
+        // pointing at the original order
+        let cl_ord_id = msg.get(41).or_else(|| msg.get(11))?;
+
+        let mut orders = self.orders.lock().expect("OMS lock poisoned");
+        let order = orders.get_mut(cl_ord_id)?;
+        let previous_status = order.status;
+
+        if let Some(status) = msg.get(39).and_then(OrderStatus::from_fix) {
+            order.status = status;
+        }
+        if let Some(cum_qty) = msg.get(14).and_then(|x| x.parse().ok()) {
+            order.cum_qty = cum_qty;
+        }
+        if let Some(avg_px) = msg.get(6).and_then(|x| x.parse().ok()) {
+            order.avg_px = avg_px;
+        }
+        self.persist(order);
+
+        let last_qty: f64 = msg.get(32).and_then(|x| x.parse().ok()).unwrap_or(0.0);
+        let last_px: f64 = msg.get(31).and_then(|x| x.parse().ok()).unwrap_or(0.0);
+        let exec_id = msg.get(17).unwrap_or_default();
+        let fill = (last_qty > 0.0).then(|| Fill {
+            exec_id: exec_id.to_string(),
+            cl_ord_id: order.cl_ord_id.clone(),
+            symbol: order.symbol.clone(),
+            side: order.side,
+            quantity: last_qty,
+            price: last_px,
+        });
+
+        let mut history = self.history.lock().expect("OMS lock poisoned");
+        let (fill, correction) = match Execution::of(msg) {
+            Execution::Ordinary => {
+                if let Some(fill) = fill.as_ref().filter(|x| !x.exec_id.is_empty()) {
+                    history.fills.insert(fill.exec_id.clone(), fill.clone());
+                }
+                (fill, None)
+            }
+            kind => {
+                let correction = msg.get(19).and_then(|exec_ref_id| {
+                    let original = history.fills.remove(exec_ref_id)?;
+                    let corrected = fill.filter(|_| kind == Execution::TradeCorrect);
+                    if let Some(corrected) = &corrected {
+                        history.fills.insert(exec_id.to_string(), corrected.clone());
+                    }
+                    Some(Correction {
+                        exec_id: exec_id.to_string(),
+                        exec_ref_id: exec_ref_id.to_string(),
+                        original,
+                        corrected,
+                        time: unix_now(),
+                    })
+                });
+                if let Some(correction) = &correction {
+                    history.corrections.push(correction.clone());
+                }
+                (None, correction)
+            }
+        };
+
+        Some(OrderUpdate {
+            order: order.clone(),
+            previous_status,
+            fill,
+            correction,
+        })
+    }
+
+    /// Every bust and correct received, oldest first
+    pub fn corrections(&self) -> Vec<Correction> {
+        self.history
+            .lock()
+            .expect("OMS lock poisoned")
+            .corrections
+            .clone()
+    }
+
+    /// The amendments of the fill `exec_id` belongs to, oldest first
+    ///
+    /// `exec_id` may be the original fill or any correction of it.
+    pub fn correction_chain(&self, exec_id: &str) -> Vec<Correction> {
+        let history = self.history.lock().expect("OMS lock poisoned");
+        let corrections = &history.corrections;
+
+        // Back to the original fill, then forward through its amendments.
+        // Bounded: a venue reusing ExecIDs could otherwise loop us forever.
+        let mut current = exec_id;
+        for _ in 0..corrections.len() {
+            match corrections.iter().find(|x| x.exec_id == current) {
+                Some(x) => current = &x.exec_ref_id,
+                None => break,
+            }
+        }
+        let mut chain = Vec::new();
+        for _ in 0..corrections.len() {
+            match corrections.iter().find(|x| x.exec_ref_id == current) {
+                Some(x) => {
+                    chain.push(x.clone());
+                    current = &x.exec_id;
+                }
+                None => break,
+            }
+        }
+        chain
+    }
+
+    /// Snapshot of all orders
+    pub fn orders(&self) -> Vec<Order> {
+        self.orders
+            .lock()
+            .expect("OMS lock poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Snapshot of orders that can still trade
+    pub fn working_orders(&self) -> Vec<Order> {
+        self.orders()
+            .into_iter()
+            .filter(|x| x.status.is_working())
+            .collect()
+    }
+
+    /// Total working quantity for a symbol and side
+    pub fn working_qty(&self, symbol: &str, side: Side) -> f64 {
+        self.working_orders()
+            .iter()
+            .filter(|x| x.symbol == symbol && x.side == side)
+            .map(Order::leaves_qty)
+            .sum()
+    }
+}
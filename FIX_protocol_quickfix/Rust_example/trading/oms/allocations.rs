@@ -0,0 +1,650 @@
+// =============================================================================
+// Post-Trade Allocations (35=J / 35=P / 35=AS)
+// =============================================================================
+// A block order is traded for several accounts (funds, sub-accounts) at
+// once; once it is filled, the broker is told how to split it with an
+// AllocationInstruction:
+//
+//   35=J  AllocID (70), AllocTransType (71) = 0 new, AllocType (626) = 2
+//         preliminary, Side, Symbol, Quantity (53), AvgPx (6), TradeDate,
+//         TransactTime
+//         NoOrders (73)  -> ClOrdID (11) of each order of the block
+//         NoExecs (124)  -> LastQty (32), ExecID (17), LastPx (31) per fill
+//         NoAllocs (78)  -> AllocAccount (79), AllocQty (80) per account
+//
+// The fills of an instruction share a symbol and a side, and the account
+// quantities add up to what they filled. A fill goes into one allocation at
+// a time: it is free again once that allocation is rejected.
+//
+// The broker answers with an AllocationInstructionAck (35=P) or, in FIX 4.4
+// and later, AllocationReports (35=AS); both carry AllocStatus (87), and
+// AllocRejCode (88) with Text (58) when rejected. The book keeps the last
+// status of each allocation, and so of each fill in it.
+//
+// The receiving side decodes an instruction (`from_message`), checks that it
+// adds up (`check`) and answers with `build_allocation_ack`.
+// =============================================================================
+
+use std::{
+    error::Error,
+    fmt,
+    sync::{Mutex, MutexGuard},
+};
+
+use quickfix::{FieldMap, Group, Message, QuickFixError};
+
+use crate::{
+    json::json_escape,
+    oms::{Fill, Side},
+    session::events::FixMessage,
+    time::{transact_time, Date},
+};
+
+/// Quantities and prices closer than this are equal
+const TOLERANCE: f64 = 1e-6;
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum AllocationError {
+    /// An instruction needs at least one fill and one account
+    Empty,
+
+    /// Fills of different symbols or sides
+    MixedFills,
+
+    /// An account without a name
+    MissingAccount,
+
+    /// An account quantity that is not a positive number
+    InvalidQuantity(String),
+
+    /// The account quantities do not add up to the quantity filled
+    Unbalanced { allocated: f64, filled: f64 },
+
+    /// AvgPx is not the average price of the fills
+    AveragePrice { stated: f64, fills: f64 },
+
+    /// The fill is in an allocation not rejected yet
+    AlreadyAllocated(String),
+
+    /// `ACCOUNT=QTY,...` that does not parse
+    InvalidShares(String),
+
+    /// No allocation with this AllocID
+    UnknownAllocation(String),
+}
+
+impl AllocationError {
+    /// AllocRejCode (88) telling the sender what is wrong
+    pub fn rej_code(&self) -> &'static str {
+        match self {
+            AllocationError::MissingAccount => "0", // Unknown account(s)
+            AllocationError::InvalidQuantity(_) | AllocationError::Unbalanced { .. } => "1",
+            AllocationError::AveragePrice { .. } => "2",
+            _ => "7", // Other
+        }
+    }
+}
+
+impl fmt::Display for AllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllocationError::Empty => write!(f, "no fills or no accounts"),
+            AllocationError::MixedFills => write!(f, "fills of different symbols or sides"),
+            AllocationError::MissingAccount => write!(f, "account without a name"),
+            AllocationError::InvalidQuantity(account) => {
+                write!(f, "invalid quantity for account {account}")
+            }
+            AllocationError::Unbalanced { allocated, filled } => {
+                write!(f, "{allocated} allocated, {filled} filled")
+            }
+            AllocationError::AveragePrice { stated, fills } => {
+                write!(f, "AvgPx {stated}, fills average {fills}")
+            }
+            AllocationError::AlreadyAllocated(exec_id) => {
+                write!(f, "fill {exec_id} is already allocated")
+            }
+            AllocationError::InvalidShares(text) => {
+                write!(f, "expected ACCOUNT=QTY,...: {text}")
+            }
+            AllocationError::UnknownAllocation(id) => write!(f, "unknown allocation {id}"),
+        }
+    }
+}
+
+impl Error for AllocationError {}
+
+// =============================================================================
+// Instruction
+// =============================================================================
+
+/// Quantity given to one account
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocShare {
+    pub account: String,
+    pub quantity: f64,
+}
+
+/// Parse `ACCOUNT=QTY,ACCOUNT=QTY...`
+pub fn parse_shares(text: &str) -> Result<Vec<AllocShare>, AllocationError> {
+    text.split(',')
+        .map(|share| {
+            let (account, quantity) = share
+                .split_once('=')
+                .ok_or_else(|| AllocationError::InvalidShares(share.to_string()))?;
+            let quantity = quantity
+                .trim()
+                .parse()
+                .map_err(|_| AllocationError::InvalidShares(share.to_string()))?;
+            Ok(AllocShare {
+                account: account.trim().to_string(),
+                quantity,
+            })
+        })
+        .collect()
+}
+
+/// One fill of the block, as listed in NoExecs
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocExec {
+    pub exec_id: String,
+    pub quantity: f64,
+    pub price: f64,
+}
+
+/// AllocationInstruction (35=J)
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocationInstruction {
+    pub alloc_id: String,
+    pub symbol: String,
+    pub side: Side,
+
+    /// Quantity filled, split between the accounts
+    pub quantity: f64,
+    pub avg_px: f64,
+
+    /// TradeDate (75), YYYYMMDD
+    pub trade_date: String,
+
+    /// ClOrdIDs of the orders of the block
+    pub orders: Vec<String>,
+    pub execs: Vec<AllocExec>,
+    pub allocs: Vec<AllocShare>,
+}
+
+impl AllocationInstruction {
+    /// Split `fills` between the accounts of `allocs`, traded today
+    pub fn from_fills(
+        alloc_id: &str,
+        fills: &[Fill],
+        allocs: Vec<AllocShare>,
+    ) -> Result<Self, AllocationError> {
+        let first = fills.first().ok_or(AllocationError::Empty)?;
+        if fills
+            .iter()
+            .any(|x| x.symbol != first.symbol || x.side != first.side)
+        {
+            return Err(AllocationError::MixedFills);
+        }
+        let quantity: f64 = fills.iter().map(|x| x.quantity).sum();
+        let notional: f64 = fills.iter().map(|x| x.quantity * x.price).sum();
+        let mut orders: Vec<String> = Vec::new();
+        for fill in fills {
+            if !orders.contains(&fill.cl_ord_id) {
+                orders.push(fill.cl_ord_id.clone());
+            }
+        }
+
+        let instruction = Self {
+            alloc_id: alloc_id.to_string(),
+            symbol: first.symbol.clone(),
+            side: first.side,
+            quantity,
+            avg_px: notional / quantity,
+            trade_date: Date::today().to_fix(),
+            orders,
+            execs: fills
+                .iter()
+                .map(|x| AllocExec {
+                    exec_id: x.exec_id.clone(),
+                    quantity: x.quantity,
+                    price: x.price,
+                })
+                .collect(),
+            allocs,
+        };
+        instruction.check()?;
+        Ok(instruction)
+    }
+
+    /// Whether the instruction adds up: accounts named with a positive
+    /// quantity, account quantities summing to Quantity, and when fills are
+    /// listed, their quantities too and AvgPx their average price
+    pub fn check(&self) -> Result<(), AllocationError> {
+        if self.allocs.is_empty() {
+            return Err(AllocationError::Empty);
+        }
+        for share in &self.allocs {
+            if share.account.is_empty() {
+                return Err(AllocationError::MissingAccount);
+            }
+            if !share.quantity.is_finite() || share.quantity <= 0.0 {
+                return Err(AllocationError::InvalidQuantity(share.account.clone()));
+            }
+        }
+        let allocated: f64 = self.allocs.iter().map(|x| x.quantity).sum();
+        if (allocated - self.quantity).abs() > TOLERANCE {
+            return Err(AllocationError::Unbalanced {
+                allocated,
+                filled: self.quantity,
+            });
+        }
+
+        if self.execs.is_empty() {
+            return Ok(());
+        }
+        let filled: f64 = self.execs.iter().map(|x| x.quantity).sum();
+        if (filled - self.quantity).abs() > TOLERANCE {
+            return Err(AllocationError::Unbalanced {
+                allocated: self.quantity,
+                filled,
+            });
+        }
+        let average = self.execs.iter().map(|x| x.quantity * x.price).sum::<f64>() / filled;
+        if (average - self.avg_px).abs() > TOLERANCE {
+            return Err(AllocationError::AveragePrice {
+                stated: self.avg_px,
+                fills: average,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn to_message(&self) -> Result<Message, QuickFixError> {
+        let mut msg = Message::new();
+        msg.with_header_mut(|h| h.set_field(35, "J"))?;
+        msg.set_field(70, self.alloc_id.as_str())?; // AllocID
+        msg.set_field(71, "0")?; // AllocTransType: new
+        msg.set_field(626, "2")?; // AllocType: preliminary
+        msg.set_field(54, self.side.as_fix())?;
+        msg.set_field(55, self.symbol.as_str())?;
+        msg.set_field(53, self.quantity.to_string().as_str())?; // Quantity
+        msg.set_field(6, self.avg_px.to_string().as_str())?; // AvgPx
+        msg.set_field(75, self.trade_date.as_str())?; // TradeDate
+        msg.set_field(60, transact_time().as_str())?; // TransactTime
+
+        for cl_ord_id in &self.orders {
+            let mut group = Group::try_new(73, 11)?;
+            group.set_field(11, cl_ord_id.as_str())?;
+            msg.add_group(&group)?;
+        }
+        for exec in &self.execs {
+            let mut group = Group::try_new(124, 32)?;
+            group.set_field(32, exec.quantity.to_string().as_str())?; // LastQty
+            group.set_field(17, exec.exec_id.as_str())?;
+            group.set_field(31, exec.price.to_string().as_str())?; // LastPx
+            msg.add_group(&group)?;
+        }
+        for share in &self.allocs {
+            let mut group = Group::try_new(78, 79)?;
+            group.set_field(79, share.account.as_str())?; // AllocAccount
+            group.set_field(80, share.quantity.to_string().as_str())?; // AllocQty
+            msg.add_group(&group)?;
+        }
+        Ok(msg)
+    }
+
+    /// Decode a received 35=J; None without AllocID, Side, Symbol or
+    /// Quantity
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        let number = |x: Option<String>| x.and_then(|x| x.parse::<f64>().ok());
+        let groups = |tag| (1..).map_while(move |index| msg.clone_group(index, tag));
+        let side = match msg.get_field(54)?.as_str() {
+            "1" => Side::Buy,
+            "2" => Side::Sell,
+            _ => return None,
+        };
+        let trade_date = msg.get_field(75).unwrap_or_else(|| Date::today().to_fix());
+
+        Some(Self {
+            alloc_id: msg.get_field(70)?,
+            symbol: msg.get_field(55)?,
+            side,
+            quantity: number(msg.get_field(53))?,
+            avg_px: number(msg.get_field(6)).unwrap_or(0.0),
+            trade_date,
+            orders: groups(73).filter_map(|x| x.get_field(11)).collect(),
+            execs: groups(124)
+                .map(|x| AllocExec {
+                    exec_id: x.get_field(17).unwrap_or_default(),
+                    quantity: number(x.get_field(32)).unwrap_or(0.0),
+                    price: number(x.get_field(31)).unwrap_or(0.0),
+                })
+                .collect(),
+            allocs: groups(78)
+                .map(|x| AllocShare {
+                    account: x.get_field(79).unwrap_or_default(),
+                    quantity: number(x.get_field(80)).unwrap_or(0.0),
+                })
+                .collect(),
+        })
+    }
+}
+
+impl fmt::Display for AllocationInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let allocs: Vec<_> = self
+            .allocs
+            .iter()
+            .map(|x| format!("{}={}", x.account, x.quantity))
+            .collect();
+        write!(
+            f,
+            "{} {:?} {} {}@{} ({} fill(s)) -> {}",
+            self.alloc_id,
+            self.side,
+            self.symbol,
+            self.quantity,
+            self.avg_px,
+            self.execs.len(),
+            allocs.join(",")
+        )
+    }
+}
+
+// =============================================================================
+// Acknowledgements
+// =============================================================================
+
+/// AllocStatus (87), plus the state of an instruction nobody answered yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocStatus {
+    /// Sent, no answer yet
+    Sent,
+    Accepted,
+    BlockRejected,
+    AccountRejected,
+    Received,
+    Incomplete,
+    RejectedByIntermediary,
+    Pending,
+    Reversed,
+}
+
+impl AllocStatus {
+    pub fn from_fix(value: &str) -> Option<Self> {
+        match value {
+            "0" => Some(AllocStatus::Accepted),
+            "1" => Some(AllocStatus::BlockRejected),
+            "2" => Some(AllocStatus::AccountRejected),
+            "3" => Some(AllocStatus::Received),
+            "4" => Some(AllocStatus::Incomplete),
+            "5" => Some(AllocStatus::RejectedByIntermediary),
+            "6" => Some(AllocStatus::Pending),
+            "7" => Some(AllocStatus::Reversed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AllocStatus::Sent => "sent",
+            AllocStatus::Accepted => "accepted",
+            AllocStatus::BlockRejected => "block_rejected",
+            AllocStatus::AccountRejected => "account_rejected",
+            AllocStatus::Received => "received",
+            AllocStatus::Incomplete => "incomplete",
+            AllocStatus::RejectedByIntermediary => "rejected_by_intermediary",
+            AllocStatus::Pending => "pending",
+            AllocStatus::Reversed => "reversed",
+        }
+    }
+
+    /// Whether the fills are free to be allocated again
+    pub fn releases_fills(self) -> bool {
+        matches!(
+            self,
+            AllocStatus::BlockRejected
+                | AllocStatus::AccountRejected
+                | AllocStatus::RejectedByIntermediary
+                | AllocStatus::Reversed
+        )
+    }
+}
+
+/// AllocationInstructionAck (35=P) or AllocationReport (35=AS) received
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocationAck {
+    pub alloc_id: String,
+    pub status: AllocStatus,
+
+    /// AllocRejCode (88)
+    pub rej_code: Option<String>,
+    pub text: Option<String>,
+}
+
+impl AllocationAck {
+    /// None if the message is neither, or lacks AllocID or AllocStatus
+    pub fn from_fix(msg: &FixMessage) -> Option<Self> {
+        if !matches!(msg.msg_type(), "P" | "AS") {
+            return None;
+        }
+        Some(Self {
+            alloc_id: msg.get(70)?.to_string(),
+            status: AllocStatus::from_fix(msg.get(87)?)?,
+            rej_code: msg.get(88).map(str::to_string),
+            text: msg.get(58).map(str::to_string),
+        })
+    }
+}
+
+/// AllocationInstructionAck (35=P) answering an instruction: accepted, or
+/// rejected as a block with the reason
+pub fn build_allocation_ack(
+    alloc_id: &str,
+    result: &Result<(), AllocationError>,
+) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "P"))?;
+    msg.set_field(70, alloc_id)?; // AllocID
+    msg.set_field(60, transact_time().as_str())?; // TransactTime
+    match result {
+        Ok(()) => msg.set_field(87, "0")?, // AllocStatus: accepted
+        Err(err) => {
+            msg.set_field(87, "1")?; // AllocStatus: block level reject
+            msg.set_field(88, err.rej_code())?; // AllocRejCode
+            msg.set_field(58, err.to_string().as_str())?;
+        }
+    }
+    Ok(msg)
+}
+
+// =============================================================================
+// AllocationBook
+// =============================================================================
+
+/// An instruction sent, with the last status received for it
+#[derive(Debug, Clone)]
+pub struct Allocation {
+    pub instruction: AllocationInstruction,
+    pub status: AllocStatus,
+    pub rej_code: Option<String>,
+    pub text: Option<String>,
+}
+
+impl Allocation {
+    /// JSON object for the REST gateway
+    pub fn to_json(&self) -> String {
+        let instruction = &self.instruction;
+        let optional = |x: &Option<String>| {
+            x.as_ref()
+                .map_or("null".to_string(), |x| format!("\"{}\"", json_escape(x)))
+        };
+        let orders: Vec<_> = instruction
+            .orders
+            .iter()
+            .map(|x| format!("\"{}\"", json_escape(x)))
+            .collect();
+        let execs: Vec<_> = instruction
+            .execs
+            .iter()
+            .map(|x| {
+                format!(
+                    "{{\"exec_id\":\"{}\",\"quantity\":{},\"price\":{}}}",
+                    json_escape(&x.exec_id),
+                    x.quantity,
+                    x.price
+                )
+            })
+            .collect();
+        let allocs: Vec<_> = instruction
+            .allocs
+            .iter()
+            .map(|x| {
+                format!(
+                    "{{\"account\":\"{}\",\"quantity\":{}}}",
+                    json_escape(&x.account),
+                    x.quantity
+                )
+            })
+            .collect();
+        format!(
+            "{{\"alloc_id\":\"{}\",\"symbol\":\"{}\",\"side\":\"{}\",\"quantity\":{},\
+             \"avg_px\":{},\"trade_date\":\"{}\",\"orders\":[{}],\"execs\":[{}],\
+             \"allocs\":[{}],\"status\":\"{}\",\"rej_code\":{},\"text\":{}}}",
+            json_escape(&instruction.alloc_id),
+            json_escape(&instruction.symbol),
+            instruction.side.as_str(),
+            instruction.quantity,
+            instruction.avg_px,
+            json_escape(&instruction.trade_date),
+            orders.join(","),
+            execs.join(","),
+            allocs.join(","),
+            self.status.as_str(),
+            optional(&self.rej_code),
+            optional(&self.text)
+        )
+    }
+}
+
+impl fmt::Display for Allocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.instruction, self.status.as_str())?;
+        if let Some(text) = &self.text {
+            write!(f, " ({text})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Allocations sent, and the status of each
+pub struct AllocationBook {
+    id_prefix: String,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    allocations: Vec<Allocation>,
+    next_id: u64,
+}
+
+impl AllocationBook {
+    /// AllocIDs are `PREFIX-1`, `PREFIX-2`...
+    pub fn new(id_prefix: &str) -> Self {
+        Self {
+            id_prefix: id_prefix.to_string(),
+            inner: Mutex::new(Inner {
+                allocations: Vec::new(),
+                next_id: 1,
+            }),
+        }
+    }
+
+    /// The instruction splitting `fills` between the accounts, under a new
+    /// AllocID; not tracked until `track`
+    pub fn prepare(
+        &self,
+        fills: &[Fill],
+        allocs: Vec<AllocShare>,
+    ) -> Result<AllocationInstruction, AllocationError> {
+        let mut inner = self.lock();
+        for fill in fills {
+            let taken = inner.allocations.iter().any(|x| {
+                !x.status.releases_fills()
+                    && x.instruction
+                        .execs
+                        .iter()
+                        .any(|e| e.exec_id == fill.exec_id)
+            });
+            if taken {
+                return Err(AllocationError::AlreadyAllocated(fill.exec_id.clone()));
+            }
+        }
+        let alloc_id = format!("{}-{}", self.id_prefix, inner.next_id);
+        let instruction = AllocationInstruction::from_fills(&alloc_id, fills, allocs)?;
+        inner.next_id += 1;
+        Ok(instruction)
+    }
+
+    /// Track an instruction once sent
+    pub fn track(&self, instruction: AllocationInstruction) {
+        self.lock().allocations.push(Allocation {
+            instruction,
+            status: AllocStatus::Sent,
+            rej_code: None,
+            text: None,
+        });
+    }
+
+    /// Apply a 35=P or 35=AS
+    ///
+    /// # Returns
+    /// The allocation as updated; None if the message is neither, or about
+    /// an allocation we did not send
+    pub fn on_message(&self, msg: &FixMessage) -> Option<Allocation> {
+        let ack = AllocationAck::from_fix(msg)?;
+        let mut inner = self.lock();
+        let allocation = inner
+            .allocations
+            .iter_mut()
+            .find(|x| x.instruction.alloc_id == ack.alloc_id)?;
+        allocation.status = ack.status;
+        allocation.rej_code = ack.rej_code;
+        allocation.text = ack.text;
+        Some(allocation.clone())
+    }
+
+    /// Every allocation sent, oldest first
+    pub fn allocations(&self) -> Vec<Allocation> {
+        self.lock().allocations.clone()
+    }
+
+    pub fn get(&self, alloc_id: &str) -> Result<Allocation, AllocationError> {
+        self.lock()
+            .allocations
+            .iter()
+            .find(|x| x.instruction.alloc_id == alloc_id)
+            .cloned()
+            .ok_or_else(|| AllocationError::UnknownAllocation(alloc_id.to_string()))
+    }
+
+    /// Status of the latest allocation a fill is in; None if never
+    /// allocated
+    pub fn status_of(&self, exec_id: &str) -> Option<AllocStatus> {
+        self.lock()
+            .allocations
+            .iter()
+            .rev()
+            .find(|x| x.instruction.execs.iter().any(|e| e.exec_id == exec_id))
+            .map(|x| x.status)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().expect("allocation book lock poisoned")
+    }
+}
@@ -0,0 +1,414 @@
+// =============================================================================
+// Trade Blotter
+// =============================================================================
+// Every fill received, in one list that can be filtered, paged through and
+// exported, which is what an operator asks of the stack first: "what did we
+// trade, for whom, at what price".
+//
+// A trade is an ExecutionReport (35=8) with a LastQty (32) above zero. Its
+// account is the Account (1) of the report, empty when the venue sends
+// none; its time the TransactTime (60), else the time it was received. A
+// trade bust (ExecType 150=H) takes the fill its ExecRefID (19) points at
+// off the blotter, a trade correct (150=G) replaces its quantity and price,
+// as the OMS does with positions.
+//
+// A BlotterFilter selects by account, symbol, side and minimum quantity; all
+// of them must match, and a filter without any matches every trade. The same
+// filter serves a page of history (newest first), a CSV export (oldest
+// first) and live streams, which test each new trade with `matches`:
+//
+//   account=ACC1  symbol=AAPL  side=buy|sell  min_qty=100
+//
+// Trades are kept in memory, DEFAULT_CAPACITY of them by default, the oldest
+// dropped first; they do not survive a restart.
+// =============================================================================
+
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt::{self, Write as _},
+    sync::{Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    csv::csv_field,
+    json::json_escape,
+    oms::{Execution, Side},
+    session::events::FixMessage,
+    time::parse_utc_timestamp,
+};
+
+/// Trades kept when no capacity is given
+pub const DEFAULT_CAPACITY: usize = 100_000;
+
+/// Trades in a page when no limit is given
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// First line of `to_csv`
+pub const CSV_HEADER: &str = "time_ms,session,exec_id,cl_ord_id,account,symbol,side,quantity,price";
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum BlotterError {
+    /// Not account, symbol, side or min_qty
+    UnknownFilter(String),
+
+    /// A side other than buy or sell
+    InvalidSide(String),
+
+    /// A minimum quantity that is not a number
+    InvalidQuantity(String),
+}
+
+impl fmt::Display for BlotterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlotterError::UnknownFilter(name) => write!(f, "unknown filter {name}"),
+            BlotterError::InvalidSide(value) => write!(f, "invalid side {value}"),
+            BlotterError::InvalidQuantity(value) => write!(f, "invalid quantity {value}"),
+        }
+    }
+}
+
+impl Error for BlotterError {}
+
+// =============================================================================
+// Trades
+// =============================================================================
+
+/// One fill, as the blotter shows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    /// Unix milliseconds
+    pub time: i64,
+    pub session: String,
+
+    /// ExecID (17); empty if the venue sent none
+    pub exec_id: String,
+    pub cl_ord_id: String,
+
+    /// Account (1); empty if the venue sent none
+    pub account: String,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    pub price: f64,
+}
+
+impl Trade {
+    /// The fill an ExecutionReport carries, busts and corrects included;
+    /// None for a report without one
+    pub fn from_execution_report(msg: &FixMessage) -> Option<Self> {
+        if msg.msg_type() != "8" {
+            return None;
+        }
+        let number = |tag| msg.get(tag).and_then(|x| x.parse::<f64>().ok());
+        let quantity = number(32).filter(|x| *x > 0.0)?;
+        let side = match msg.get(54)? {
+            "1" | "3" => Side::Buy,
+            "2" | "4" | "5" | "6" => Side::Sell,
+            _ => return None,
+        };
+        let field = |tag| msg.get(tag).unwrap_or_default().to_string();
+        let time = msg
+            .get(60)
+            .and_then(parse_utc_timestamp)
+            .map_or_else(unix_millis, |x| x / 1_000_000);
+
+        Some(Self {
+            time,
+            session: msg.session.clone(),
+            exec_id: field(17),
+            cl_ord_id: field(11),
+            account: field(1),
+            symbol: field(55),
+            side,
+            quantity,
+            price: number(31).unwrap_or(0.0),
+        })
+    }
+
+    /// JSON object for the REST gateway and the WebSocket stream
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"time_ms\":{},\"session\":\"{}\",\"exec_id\":\"{}\",\"cl_ord_id\":\"{}\",\
+             \"account\":\"{}\",\"symbol\":\"{}\",\"side\":\"{}\",\"quantity\":{},\"price\":{}}}",
+            self.time,
+            json_escape(&self.session),
+            json_escape(&self.exec_id),
+            json_escape(&self.cl_ord_id),
+            json_escape(&self.account),
+            json_escape(&self.symbol),
+            self.side.as_str(),
+            self.quantity,
+            self.price
+        )
+    }
+}
+
+impl fmt::Display for Trade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}@{} {}",
+            self.side.as_str(),
+            self.symbol,
+            self.cl_ord_id,
+            self.quantity,
+            self.price,
+            self.exec_id
+        )?;
+        if !self.account.is_empty() {
+            write!(f, " account {}", self.account)?;
+        }
+        Ok(())
+    }
+}
+
+/// Trades as CSV, header first, in the order given
+pub fn to_csv(trades: &[Trade]) -> String {
+    let mut out = format!("{CSV_HEADER}\n");
+    for trade in trades {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{}",
+            trade.time,
+            csv_field(&trade.session),
+            csv_field(&trade.exec_id),
+            csv_field(&trade.cl_ord_id),
+            csv_field(&trade.account),
+            csv_field(&trade.symbol),
+            trade.side.as_str(),
+            trade.quantity,
+            trade.price
+        );
+    }
+    out
+}
+
+/// Milliseconds since the Unix epoch, now
+fn unix_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_millis() as i64)
+}
+
+// =============================================================================
+// Filters
+// =============================================================================
+
+/// Which trades to show; None matches anything
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlotterFilter {
+    pub account: Option<String>,
+    pub symbol: Option<String>,
+    pub side: Option<Side>,
+    pub min_quantity: Option<f64>,
+}
+
+impl BlotterFilter {
+    /// Set one criterion by name, as given in a query string or on a
+    /// command line: `account`, `symbol`, `side` or `min_qty`
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), BlotterError> {
+        match name {
+            "account" => self.account = Some(value.to_string()),
+            "symbol" => self.symbol = Some(value.to_string()),
+            "side" => {
+                let side = Side::from_name(&value.to_ascii_lowercase())
+                    .ok_or_else(|| BlotterError::InvalidSide(value.to_string()))?;
+                self.side = Some(side);
+            }
+            "min_qty" => {
+                let quantity = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|x| x.is_finite() && *x >= 0.0)
+                    .ok_or_else(|| BlotterError::InvalidQuantity(value.to_string()))?;
+                self.min_quantity = Some(quantity);
+            }
+            _ => return Err(BlotterError::UnknownFilter(name.to_string())),
+        }
+        Ok(())
+    }
+
+    pub fn with_account(mut self, account: &str) -> Self {
+        self.account = Some(account.to_string());
+        self
+    }
+
+    pub fn with_symbol(mut self, symbol: &str) -> Self {
+        self.symbol = Some(symbol.to_string());
+        self
+    }
+
+    pub fn with_side(mut self, side: Side) -> Self {
+        self.side = Some(side);
+        self
+    }
+
+    pub fn with_min_quantity(mut self, quantity: f64) -> Self {
+        self.min_quantity = Some(quantity);
+        self
+    }
+
+    pub fn matches(&self, trade: &Trade) -> bool {
+        self.account.as_ref().is_none_or(|x| *x == trade.account)
+            && self.symbol.as_ref().is_none_or(|x| *x == trade.symbol)
+            && self.side.is_none_or(|x| x == trade.side)
+            && self.min_quantity.is_none_or(|x| trade.quantity >= x)
+    }
+}
+
+impl fmt::Display for BlotterFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut criteria = Vec::new();
+        if let Some(account) = &self.account {
+            criteria.push(format!("account={account}"));
+        }
+        if let Some(symbol) = &self.symbol {
+            criteria.push(format!("symbol={symbol}"));
+        }
+        if let Some(side) = self.side {
+            criteria.push(format!("side={}", side.as_str()));
+        }
+        if let Some(quantity) = self.min_quantity {
+            criteria.push(format!("min_qty={quantity}"));
+        }
+        if criteria.is_empty() {
+            write!(f, "all trades")
+        } else {
+            write!(f, "{}", criteria.join(" "))
+        }
+    }
+}
+
+/// One page of matching trades, newest first
+#[derive(Debug, Clone)]
+pub struct BlotterPage {
+    pub trades: Vec<Trade>,
+
+    /// Matching trades skipped before this page
+    pub offset: usize,
+
+    /// Matching trades in all
+    pub total: usize,
+}
+
+impl BlotterPage {
+    pub fn to_json(&self) -> String {
+        let trades: Vec<_> = self.trades.iter().map(Trade::to_json).collect();
+        format!(
+            "{{\"offset\":{},\"total\":{},\"trades\":[{}]}}",
+            self.offset,
+            self.total,
+            trades.join(",")
+        )
+    }
+}
+
+// =============================================================================
+// TradeBlotter
+// =============================================================================
+
+/// The trades received, oldest first
+pub struct TradeBlotter {
+    capacity: usize,
+    trades: Mutex<VecDeque<Trade>>,
+}
+
+impl Default for TradeBlotter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TradeBlotter {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Keep at most `capacity` trades
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            trades: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Record the fill of an ExecutionReport, or apply its bust or correct
+    ///
+    /// # Returns
+    /// The trade added or corrected, to be streamed; None for a report
+    /// without a fill and for a bust
+    pub fn on_execution_report(&self, msg: &FixMessage) -> Option<Trade> {
+        let mut trades = self.lock();
+        match Execution::of(msg) {
+            Execution::Ordinary => {
+                let trade = Trade::from_execution_report(msg)?;
+                if trades.len() == self.capacity {
+                    trades.pop_front();
+                }
+                trades.push_back(trade.clone());
+                Some(trade)
+            }
+            Execution::TradeCancel => {
+                let exec_ref_id = msg.get(19)?;
+                trades.retain(|x| x.session != msg.session || x.exec_id != exec_ref_id);
+                None
+            }
+            Execution::TradeCorrect => {
+                let corrected = Trade::from_execution_report(msg)?;
+                let exec_ref_id = msg.get(19)?;
+                let trade = trades
+                    .iter_mut()
+                    .find(|x| x.session == msg.session && x.exec_id == exec_ref_id)?;
+                trade.exec_id = corrected.exec_id;
+                trade.quantity = corrected.quantity;
+                trade.price = corrected.price;
+                Some(trade.clone())
+            }
+        }
+    }
+
+    /// Matching trades, newest first, skipping `offset` of them
+    pub fn page(&self, filter: &BlotterFilter, offset: usize, limit: usize) -> BlotterPage {
+        let trades = self.lock();
+        let matching: Vec<_> = trades.iter().rev().filter(|x| filter.matches(x)).collect();
+        BlotterPage {
+            trades: matching
+                .iter()
+                .skip(offset)
+                .take(limit)
+                .map(|x| (*x).clone())
+                .collect(),
+            offset,
+            total: matching.len(),
+        }
+    }
+
+    /// Every matching trade, oldest first (for an export)
+    pub fn matching(&self, filter: &BlotterFilter) -> Vec<Trade> {
+        self.lock()
+            .iter()
+            .filter(|x| filter.matches(x))
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<Trade>> {
+        self.trades.lock().expect("blotter lock poisoned")
+    }
+}
@@ -0,0 +1,766 @@
+// =============================================================================
+// Trade Capture Reports (35=AD / 35=AQ / 35=AE)
+// =============================================================================
+// The venue's own record of what we traded, asked for at the end of the day
+// to reconcile it against the fills we received:
+//
+//   35=AD  TradeRequestID (568), TradeRequestType (569) = 0 all trades,
+//          SubscriptionRequestType (263) = 0 snapshot, Symbol
+//          NoDates (580)  -> TradeDate (75) of the day asked for
+//   35=AQ  TradeRequestID, TradeRequestResult (749), TradeRequestStatus
+//          (750) 0 accepted / 1 completed / 2 rejected, TotNumTradeReports
+//          (748), Text
+//   35=AE  TradeReportID (571), TradeReportTransType (487) 0 new / 1 cancel
+//          / 2 replace, TradeReportRefID (572), TradeRequestID,
+//          LastRptRequested (912), PreviouslyReported (570), ExecID (17),
+//          TrdMatchID (880), Symbol, LastQty, LastPx, TradeDate, TransactTime
+//          NoSides (552)  -> Side, OrderID, ClOrdID, Account of our side
+//
+// A TradeCaptureBook keeps every report received, by TradeReportID. A cancel
+// marks the report it points at (TradeReportRefID, else its own ID)
+// canceled; a replace marks it replaced and takes its place. Reports pushed
+// by the venue without a request are kept as well.
+//
+// With a state store every report is written through under
+// "trade_captures", so the table of a day survives a restart of the process
+// reconciling it. Requests and their acks are kept in memory only.
+//
+// The venue side decodes a request (`from_message`) and answers with
+// `build_capture_ack`, then a `to_message` per report.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Write as _},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use quickfix::{FieldMap, Group, Message, QuickFixError};
+
+use crate::{
+    csv::csv_field,
+    json::{json_escape, JsonValue},
+    oms::Side,
+    session::{
+        events::{group_field, FixMessage},
+        Direction,
+    },
+    store::{StateStore, StoreError},
+    time::{parse_utc_timestamp, time_of_day, unix_now, Date},
+};
+
+/// Store namespace of the reports, keyed by TradeReportID
+pub const TRADE_CAPTURES_NAMESPACE: &str = "trade_captures";
+
+/// First line of `to_csv`
+pub const CSV_HEADER: &str = "trade_date,time_ms,session,trade_report_id,status,exec_id,\
+                              match_id,order_id,cl_ord_id,account,symbol,side,quantity,price";
+
+// =============================================================================
+// Request
+// =============================================================================
+
+/// TradeCaptureReportRequest (35=AD), for a snapshot of the trades
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeCaptureRequest {
+    pub request_id: String,
+
+    /// Every symbol if None
+    pub symbol: Option<String>,
+
+    /// Every day the venue keeps if None
+    pub trade_date: Option<Date>,
+}
+
+impl TradeCaptureRequest {
+    pub fn to_message(&self) -> Result<Message, QuickFixError> {
+        let mut msg = Message::new();
+        msg.with_header_mut(|h| h.set_field(35, "AD"))?;
+        msg.set_field(568, self.request_id.as_str())?; // TradeRequestID
+        msg.set_field(569, "0")?; // TradeRequestType: all trades
+        msg.set_field(263, "0")?; // SubscriptionRequestType: snapshot
+        if let Some(symbol) = &self.symbol {
+            msg.set_field(55, symbol.as_str())?;
+        }
+        if let Some(date) = self.trade_date {
+            let mut group = Group::try_new(580, 75)?;
+            group.set_field(75, date.to_fix().as_str())?; // TradeDate
+            msg.add_group(&group)?;
+        }
+        Ok(msg)
+    }
+
+    /// Decode a received 35=AD; None without TradeRequestID, or with a
+    /// TradeDate that is not YYYYMMDD
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        let trade_date = match msg
+            .clone_group(1, 580)
+            .and_then(|x| x.get_field(75))
+            .or_else(|| msg.get_field(75))
+        {
+            Some(date) => Some(Date::from_fix(&date)?),
+            None => None,
+        };
+        Some(Self {
+            request_id: msg.get_field(568)?,
+            symbol: msg.get_field(55),
+            trade_date,
+        })
+    }
+}
+
+impl fmt::Display for TradeCaptureRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.request_id)?;
+        if let Some(symbol) = &self.symbol {
+            write!(f, " {symbol}")?;
+        }
+        match self.trade_date {
+            Some(date) => write!(f, " {}", date.to_iso()),
+            None => write!(f, " all days"),
+        }
+    }
+}
+
+// =============================================================================
+// Request Ack
+// =============================================================================
+
+/// Where a request stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestStatus {
+    /// No ack yet
+    Sent,
+    Accepted,
+    Completed,
+    Rejected,
+}
+
+impl RequestStatus {
+    /// TradeRequestStatus (750)
+    pub fn from_fix(value: &str) -> Option<Self> {
+        match value {
+            "0" => Some(RequestStatus::Accepted),
+            "1" => Some(RequestStatus::Completed),
+            "2" => Some(RequestStatus::Rejected),
+            _ => None,
+        }
+    }
+
+    /// Lower-case name used in JSON
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RequestStatus::Sent => "sent",
+            RequestStatus::Accepted => "accepted",
+            RequestStatus::Completed => "completed",
+            RequestStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// TradeCaptureReportRequestAck (35=AQ) received
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeCaptureAck {
+    pub request_id: String,
+    pub status: RequestStatus,
+
+    /// TradeRequestResult (749), 0 when successful
+    pub result: String,
+
+    /// TotNumTradeReports (748)
+    pub total: Option<u64>,
+    pub text: Option<String>,
+}
+
+impl TradeCaptureAck {
+    /// None if the message is not one, or lacks TradeRequestID or
+    /// TradeRequestStatus
+    pub fn from_fix(msg: &FixMessage) -> Option<Self> {
+        if msg.msg_type() != "AQ" {
+            return None;
+        }
+        Some(Self {
+            request_id: msg.get(568)?.to_string(),
+            status: RequestStatus::from_fix(msg.get(750)?)?,
+            result: msg.get(749).unwrap_or("0").to_string(),
+            total: msg.get(748).and_then(|x| x.parse().ok()),
+            text: msg.get(58).map(str::to_string),
+        })
+    }
+}
+
+/// TradeCaptureReportRequestAck (35=AQ) answering a request: accepted with
+/// the number of reports that follow, or rejected with the reason
+pub fn build_capture_ack(
+    request: &TradeCaptureRequest,
+    result: Result<usize, &str>,
+) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "AQ"))?;
+    msg.set_field(568, request.request_id.as_str())?; // TradeRequestID
+    msg.set_field(569, "0")?; // TradeRequestType: all trades
+    if let Some(symbol) = &request.symbol {
+        msg.set_field(55, symbol.as_str())?;
+    }
+    match result {
+        Ok(total) => {
+            msg.set_field(748, total.to_string().as_str())?; // TotNumTradeReports
+            msg.set_field(749, "0")?; // TradeRequestResult: successful
+            msg.set_field(750, "0")?; // TradeRequestStatus: accepted
+        }
+        Err(text) => {
+            msg.set_field(749, "99")?; // TradeRequestResult: other
+            msg.set_field(750, "2")?; // TradeRequestStatus: rejected
+            msg.set_field(58, text)?;
+        }
+    }
+    Ok(msg)
+}
+
+// =============================================================================
+// Report
+// =============================================================================
+
+/// TradeReportTransType (487)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportTransType {
+    New,
+    Cancel,
+    Replace,
+}
+
+impl ReportTransType {
+    pub fn from_fix(value: &str) -> Option<Self> {
+        match value {
+            "0" => Some(ReportTransType::New),
+            "1" => Some(ReportTransType::Cancel),
+            "2" => Some(ReportTransType::Replace),
+            _ => None,
+        }
+    }
+
+    pub fn as_fix(self) -> &'static str {
+        match self {
+            ReportTransType::New => "0",
+            ReportTransType::Cancel => "1",
+            ReportTransType::Replace => "2",
+        }
+    }
+}
+
+/// TradeCaptureReport (35=AE), with our side of the trade
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeCaptureReport {
+    pub trade_report_id: String,
+    pub trans_type: ReportTransType,
+
+    /// TradeReportRefID (572) of a cancel or replace; empty if none
+    pub ref_id: String,
+
+    /// TradeRequestID (568) answered; empty if pushed unasked
+    pub request_id: String,
+
+    /// LastRptRequested (912): the last report of the request
+    pub last_requested: bool,
+
+    /// ExecID (17) of the fill, as in our ExecutionReports; empty if none
+    pub exec_id: String,
+
+    /// TrdMatchID (880); empty if none
+    pub match_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    pub price: f64,
+    pub trade_date: Date,
+
+    /// TransactTime (60), Unix milliseconds
+    pub time: i64,
+
+    /// OrderID, ClOrdID and Account of our side; empty if not sent
+    pub order_id: String,
+    pub cl_ord_id: String,
+    pub account: String,
+}
+
+impl TradeCaptureReport {
+    /// None if the message is not one, or lacks TradeReportID, Symbol,
+    /// LastQty, LastPx or the side
+    pub fn from_fix(msg: &FixMessage) -> Option<Self> {
+        if msg.msg_type() != "AE" {
+            return None;
+        }
+        let field = |tag| msg.get(tag).unwrap_or_default().to_string();
+        let number = |tag| msg.get(tag).and_then(|x| x.parse::<f64>().ok());
+        let sides = msg.groups(54);
+        let side = sides.first()?;
+        let side_field = |tag| group_field(side, tag).unwrap_or_default().to_string();
+        let time = msg
+            .get(60)
+            .and_then(parse_utc_timestamp)
+            .map_or(unix_now() * 1_000, |x| x / 1_000_000);
+
+        Some(Self {
+            trade_report_id: msg.get(571)?.to_string(),
+            trans_type: msg
+                .get(487)
+                .map_or(Some(ReportTransType::New), ReportTransType::from_fix)?,
+            ref_id: field(572),
+            request_id: field(568),
+            last_requested: msg.get(912) == Some("Y"),
+            exec_id: field(17),
+            match_id: field(880),
+            symbol: msg.get(55)?.to_string(),
+            side: match group_field(side, 54)? {
+                "1" => Side::Buy,
+                "2" => Side::Sell,
+                _ => return None,
+            },
+            quantity: number(32)?,
+            price: number(31)?,
+            trade_date: msg
+                .get(75)
+                .and_then(Date::from_fix)
+                .unwrap_or_else(|| Date::from_unix(time.div_euclid(1_000))),
+            time,
+            order_id: side_field(37),
+            cl_ord_id: side_field(11),
+            account: side_field(1),
+        })
+    }
+
+    pub fn to_message(&self) -> Result<Message, QuickFixError> {
+        let mut msg = Message::new();
+        msg.with_header_mut(|h| h.set_field(35, "AE"))?;
+        msg.set_field(571, self.trade_report_id.as_str())?; // TradeReportID
+        msg.set_field(487, self.trans_type.as_fix())?; // TradeReportTransType
+        for (tag, value) in [
+            (572, &self.ref_id),     // TradeReportRefID
+            (568, &self.request_id), // TradeRequestID
+            (17, &self.exec_id),
+            (880, &self.match_id), // TrdMatchID
+        ] {
+            if !value.is_empty() {
+                msg.set_field(tag, value.as_str())?;
+            }
+        }
+        if !self.request_id.is_empty() {
+            // LastRptRequested
+            msg.set_field(912, if self.last_requested { "Y" } else { "N" })?;
+        }
+        msg.set_field(570, "N")?; // PreviouslyReported
+        msg.set_field(55, self.symbol.as_str())?;
+        msg.set_field(32, self.quantity.to_string().as_str())?; // LastQty
+        msg.set_field(31, self.price.to_string().as_str())?; // LastPx
+        msg.set_field(75, self.trade_date.to_fix().as_str())?; // TradeDate
+        let seconds = self.time.div_euclid(1_000);
+        let transact_time = format!(
+            "{}-{}.{:03}",
+            Date::from_unix(seconds).to_fix(),
+            time_of_day(seconds),
+            self.time.rem_euclid(1_000)
+        );
+        msg.set_field(60, transact_time.as_str())?; // TransactTime
+
+        let mut group = Group::try_new(552, 54)?;
+        group.set_field(54, self.side.as_fix())?;
+        group.set_field(37, self.order_id.as_str())?; // OrderID
+        if !self.cl_ord_id.is_empty() {
+            group.set_field(11, self.cl_ord_id.as_str())?;
+        }
+        if !self.account.is_empty() {
+            group.set_field(1, self.account.as_str())?;
+        }
+        msg.add_group(&group)?;
+        Ok(msg)
+    }
+
+    /// The report a cancel or replace points at
+    fn target(&self) -> &str {
+        if self.ref_id.is_empty() {
+            &self.trade_report_id
+        } else {
+            &self.ref_id
+        }
+    }
+}
+
+// =============================================================================
+// TradeCaptureBook
+// =============================================================================
+
+/// What became of a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureStatus {
+    Active,
+    Canceled,
+    Replaced,
+}
+
+impl CaptureStatus {
+    /// Lower-case name used in JSON and CSV
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CaptureStatus::Active => "active",
+            CaptureStatus::Canceled => "canceled",
+            CaptureStatus::Replaced => "replaced",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "active" => Some(CaptureStatus::Active),
+            "canceled" => Some(CaptureStatus::Canceled),
+            "replaced" => Some(CaptureStatus::Replaced),
+            _ => None,
+        }
+    }
+}
+
+/// A report received on a session, with what became of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedTrade {
+    pub session: String,
+    pub report: TradeCaptureReport,
+    pub status: CaptureStatus,
+}
+
+impl CapturedTrade {
+    /// JSON object, as stored
+    pub fn to_json(&self) -> String {
+        let report = &self.report;
+        format!(
+            "{{\"session\":\"{}\",\"trade_report_id\":\"{}\",\"status\":\"{}\",\
+             \"ref_id\":\"{}\",\"request_id\":\"{}\",\"exec_id\":\"{}\",\"match_id\":\"{}\",\
+             \"symbol\":\"{}\",\"side\":\"{}\",\"quantity\":{},\"price\":{},\
+             \"trade_date\":\"{}\",\"time\":{},\"order_id\":\"{}\",\"cl_ord_id\":\"{}\",\
+             \"account\":\"{}\"}}",
+            json_escape(&self.session),
+            json_escape(&report.trade_report_id),
+            self.status.as_str(),
+            json_escape(&report.ref_id),
+            json_escape(&report.request_id),
+            json_escape(&report.exec_id),
+            json_escape(&report.match_id),
+            json_escape(&report.symbol),
+            report.side.as_str(),
+            report.quantity,
+            report.price,
+            report.trade_date.to_fix(),
+            report.time,
+            json_escape(&report.order_id),
+            json_escape(&report.cl_ord_id),
+            json_escape(&report.account)
+        )
+    }
+
+    /// Parse what to_json wrote; None if it is not a captured trade
+    pub fn from_json(text: &str) -> Option<Self> {
+        let value = JsonValue::parse(text)?;
+        let text = |key: &str| Some(value.get(key)?.as_str()?.to_string());
+        let number = |key: &str| value.get(key)?.as_f64();
+        Some(Self {
+            session: text("session")?,
+            status: CaptureStatus::from_name(value.get("status")?.as_str()?)?,
+            report: TradeCaptureReport {
+                trade_report_id: text("trade_report_id")?,
+                trans_type: ReportTransType::New,
+                ref_id: text("ref_id")?,
+                request_id: text("request_id")?,
+                last_requested: false,
+                exec_id: text("exec_id")?,
+                match_id: text("match_id")?,
+                symbol: text("symbol")?,
+                side: Side::from_name(value.get("side")?.as_str()?)?,
+                quantity: number("quantity")?,
+                price: number("price")?,
+                trade_date: Date::from_fix(value.get("trade_date")?.as_str()?)?,
+                time: number("time")? as i64,
+                order_id: text("order_id")?,
+                cl_ord_id: text("cl_ord_id")?,
+                account: text("account")?,
+            },
+        })
+    }
+}
+
+/// A request sent, and how far its answer got
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureRequest {
+    pub session: String,
+    pub request: TradeCaptureRequest,
+    pub status: RequestStatus,
+
+    /// TotNumTradeReports of the ack
+    pub expected: Option<u64>,
+
+    /// Reports received so far
+    pub received: u64,
+    pub text: Option<String>,
+}
+
+impl fmt::Display for CaptureRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.request, self.status.as_str())?;
+        match self.expected {
+            Some(expected) => write!(f, ", {} of {expected} reports", self.received)?,
+            None => write!(f, ", {} reports", self.received)?,
+        }
+        if let Some(text) = &self.text {
+            write!(f, " ({text})")?;
+        }
+        Ok(())
+    }
+}
+
+/// What a message changed in the book
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureUpdate {
+    /// A 35=AQ about one of our requests
+    Ack(CaptureRequest),
+
+    /// A 35=AE, as kept
+    Report(CapturedTrade),
+}
+
+/// Which reports to list; None matches anything
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CaptureFilter {
+    pub session: Option<String>,
+    pub symbol: Option<String>,
+    pub trade_date: Option<Date>,
+}
+
+impl CaptureFilter {
+    pub fn matches(&self, trade: &CapturedTrade) -> bool {
+        self.session.as_ref().is_none_or(|x| *x == trade.session)
+            && self
+                .symbol
+                .as_ref()
+                .is_none_or(|x| *x == trade.report.symbol)
+            && self.trade_date.is_none_or(|x| x == trade.report.trade_date)
+    }
+}
+
+/// Trade capture reports received, and the requests asking for them
+pub struct TradeCaptureBook {
+    id_prefix: String,
+    inner: Mutex<Inner>,
+
+    /// Where every report is written through, if set
+    store: Option<Arc<dyn StateStore>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// In the order received
+    trades: Vec<CapturedTrade>,
+
+    /// Index in `trades` by TradeReportID
+    index: HashMap<String, usize>,
+    requests: Vec<CaptureRequest>,
+    next_id: u64,
+}
+
+impl Inner {
+    fn insert(&mut self, trade: CapturedTrade) {
+        match self.index.get(&trade.report.trade_report_id) {
+            Some(&position) => self.trades[position] = trade,
+            None => {
+                let id = trade.report.trade_report_id.clone();
+                self.index.insert(id, self.trades.len());
+                self.trades.push(trade);
+            }
+        }
+    }
+}
+
+impl TradeCaptureBook {
+    /// TradeRequestIDs are `PREFIX-1`, `PREFIX-2`...
+    pub fn new(id_prefix: &str) -> Self {
+        Self {
+            id_prefix: id_prefix.to_string(),
+            inner: Mutex::new(Inner {
+                next_id: 1,
+                ..Inner::default()
+            }),
+            store: None,
+        }
+    }
+
+    /// Restore the reports kept in a store, and write every new one to it
+    pub fn with_store(mut self, store: Arc<dyn StateStore>) -> Result<Self, StoreError> {
+        let mut trades = Vec::new();
+        for (key, value) in store.scan(TRADE_CAPTURES_NAMESPACE)? {
+            let trade = CapturedTrade::from_json(&value).ok_or_else(|| StoreError::Corrupt {
+                namespace: TRADE_CAPTURES_NAMESPACE.to_string(),
+                key: key.clone(),
+            })?;
+            trades.push(trade);
+        }
+        trades.sort_by(|a, b| {
+            let (a, b) = (&a.report, &b.report);
+            a.time
+                .cmp(&b.time)
+                .then_with(|| a.trade_report_id.cmp(&b.trade_report_id))
+        });
+
+        let inner = self.inner.get_mut().expect("trade capture lock poisoned");
+        for trade in trades {
+            inner.insert(trade);
+        }
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    /// A request for the trades of a day (all of them if None), under a
+    /// new TradeRequestID; not tracked until `track`
+    pub fn prepare(&self, symbol: Option<&str>, trade_date: Option<Date>) -> TradeCaptureRequest {
+        let mut inner = self.lock();
+        let request_id = format!("{}-{}", self.id_prefix, inner.next_id);
+        inner.next_id += 1;
+        TradeCaptureRequest {
+            request_id,
+            symbol: symbol.map(str::to_string),
+            trade_date,
+        }
+    }
+
+    /// Track a request once sent on a session
+    pub fn track(&self, session: &str, request: TradeCaptureRequest) {
+        self.lock().requests.push(CaptureRequest {
+            session: session.to_string(),
+            request,
+            status: RequestStatus::Sent,
+            expected: None,
+            received: 0,
+            text: None,
+        });
+    }
+
+    /// Apply a received 35=AQ or 35=AE
+    ///
+    /// # Returns
+    /// What changed; None for other messages, outbound ones, malformed
+    /// reports and acks of requests we did not send
+    pub fn on_message(&self, msg: &FixMessage) -> Option<CaptureUpdate> {
+        if msg.direction != Direction::Inbound {
+            return None;
+        }
+        if let Some(ack) = TradeCaptureAck::from_fix(msg) {
+            let mut inner = self.lock();
+            let request = inner
+                .requests
+                .iter_mut()
+                .find(|x| x.request.request_id == ack.request_id)?;
+            request.status = ack.status;
+            request.expected = ack.total.or(request.expected);
+            request.text = ack.text;
+            return Some(CaptureUpdate::Ack(request.clone()));
+        }
+
+        let report = TradeCaptureReport::from_fix(msg)?;
+        let mut changed = Vec::new();
+        {
+            let mut inner = self.lock();
+            if let Some(request) = inner
+                .requests
+                .iter_mut()
+                .find(|x| x.request.request_id == report.request_id)
+            {
+                request.received += 1;
+                if report.last_requested {
+                    request.status = RequestStatus::Completed;
+                }
+            }
+
+            // A cancel or replace takes the report it points at off
+            if report.trans_type != ReportTransType::New {
+                let status = match report.trans_type {
+                    ReportTransType::Cancel => CaptureStatus::Canceled,
+                    _ => CaptureStatus::Replaced,
+                };
+                if let Some(&position) = inner.index.get(report.target()) {
+                    let target = &mut inner.trades[position];
+                    target.status = status;
+                    changed.push(target.clone());
+                }
+            }
+            if report.trans_type != ReportTransType::Cancel {
+                let trade = CapturedTrade {
+                    session: msg.session.clone(),
+                    report,
+                    status: CaptureStatus::Active,
+                };
+                inner.insert(trade.clone());
+                changed.push(trade);
+            }
+        }
+
+        for trade in &changed {
+            self.persist(trade);
+        }
+        changed.pop().map(CaptureUpdate::Report)
+    }
+
+    /// Write a report through to the store, if any
+    ///
+    /// A failed write is logged, not returned: the report is already in
+    /// memory, and asking the venue again writes it again.
+    fn persist(&self, trade: &CapturedTrade) {
+        if let Some(store) = &self.store {
+            let id = &trade.report.trade_report_id;
+            if let Err(err) = store.put(TRADE_CAPTURES_NAMESPACE, id, &trade.to_json()) {
+                eprintln!("Trade captures: cannot store report {id}: {err}");
+            }
+        }
+    }
+
+    /// Reports matching the filter, in the order received
+    pub fn trades(&self, filter: &CaptureFilter) -> Vec<CapturedTrade> {
+        self.lock()
+            .trades
+            .iter()
+            .filter(|x| filter.matches(x))
+            .cloned()
+            .collect()
+    }
+
+    /// Every request sent, oldest first
+    pub fn requests(&self) -> Vec<CaptureRequest> {
+        self.lock().requests.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().expect("trade capture lock poisoned")
+    }
+}
+
+/// CSV with a header line, one report per line, canceled and replaced ones
+/// included (see the status column)
+pub fn to_csv(trades: &[CapturedTrade]) -> String {
+    let mut out = format!("{CSV_HEADER}\n");
+    for trade in trades {
+        let report = &trade.report;
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            report.trade_date.to_iso(),
+            report.time,
+            csv_field(&trade.session),
+            csv_field(&report.trade_report_id),
+            trade.status.as_str(),
+            csv_field(&report.exec_id),
+            csv_field(&report.match_id),
+            csv_field(&report.order_id),
+            csv_field(&report.cl_ord_id),
+            csv_field(&report.account),
+            csv_field(&report.symbol),
+            report.side.as_str(),
+            report.quantity,
+            report.price
+        );
+    }
+    out
+}
+
@@ -0,0 +1,308 @@
+// =============================================================================
+// Cancel on Disconnect
+// =============================================================================
+// A session that drops leaves our orders working at the venue with nobody
+// watching them: the fills keep coming while we cannot see them, let alone
+// cancel. Some venues cancel the orders of a dropped session themselves; for
+// the others, a DisconnectGuard remembers the orders open when the session
+// went down (QuickFIX calls on_logout for a Logout, a lost connection and a
+// heartbeat timeout alike) and decides their fate when it logs on again:
+//
+//   cancel   a cancel for each of them, sent first thing after the logon,
+//            before the strategy or the operator sends anything new
+//   review   nothing sent: the orders wait on a review list until the
+//            operator has looked at them
+//
+// Either way the guard keeps a DisconnectReport of what it did, one per
+// outage. The orders come from whichever book the caller keeps
+// (mass_status::LocalOrder), and so do the cancels: the guard decides, the
+// caller sends.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::{Mutex, MutexGuard},
+};
+
+use super::{mass_status::LocalOrder, Side};
+
+/// What to do with the orders of a session that dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectPolicy {
+    /// Cancel them once the session is back
+    Cancel,
+
+    /// Put them on the review list
+    Review,
+}
+
+impl DisconnectPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DisconnectPolicy::Cancel => "cancel",
+            DisconnectPolicy::Review => "review",
+        }
+    }
+}
+
+/// `cancel` or `review`
+impl FromStr for DisconnectPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cancel" => Ok(DisconnectPolicy::Cancel),
+            "review" => Ok(DisconnectPolicy::Review),
+            _ => Err(format!("unknown disconnect policy {s} (cancel or review)")),
+        }
+    }
+}
+
+// =============================================================================
+// Report
+// =============================================================================
+
+/// What was done with one order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Cancel sent, with this ClOrdID
+    Canceled(String),
+
+    /// The cancel could not be sent: the order is left for review
+    CancelFailed(String),
+
+    /// Left for review
+    Review,
+}
+
+/// An order open when its session dropped, and what was done with it
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderAction {
+    pub order: LocalOrder,
+    pub action: Action,
+}
+
+impl fmt::Display for OrderAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let order = &self.order;
+        write!(
+            f,
+            "{} {} {} {}: ",
+            order.cl_ord_id,
+            order.side.map_or("?", Side::as_str),
+            order.quantity,
+            order.symbol
+        )?;
+        match &self.action {
+            Action::Canceled(cancel_id) => write!(f, "cancel {cancel_id} sent"),
+            Action::CancelFailed(err) => write!(f, "cancel failed ({err}), left for review"),
+            Action::Review => write!(f, "left for review"),
+        }
+    }
+}
+
+/// One outage of a session: when, and what was done with its orders
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisconnectReport {
+    /// Session label
+    pub session: String,
+
+    /// When it went down and came back, Unix seconds
+    pub down_at: i64,
+    pub up_at: i64,
+    pub policy: DisconnectPolicy,
+
+    /// The orders open when it went down, in the order they were given
+    pub actions: Vec<OrderAction>,
+}
+
+impl DisconnectReport {
+    /// Orders a cancel was sent for
+    pub fn canceled(&self) -> usize {
+        self.actions
+            .iter()
+            .filter(|x| matches!(x.action, Action::Canceled(_)))
+            .count()
+    }
+
+    /// Orders left for review, failed cancels included
+    pub fn left_for_review(&self) -> usize {
+        self.actions.len() - self.canceled()
+    }
+}
+
+impl fmt::Display for DisconnectReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} down {}s ({}): {} open, {} canceled, {} for review",
+            self.session,
+            self.up_at - self.down_at,
+            self.policy.as_str(),
+            self.actions.len(),
+            self.canceled(),
+            self.left_for_review()
+        )
+    }
+}
+
+/// An order waiting for the operator
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewItem {
+    pub session: String,
+    pub order: LocalOrder,
+
+    /// When its session went down, Unix seconds
+    pub down_at: i64,
+}
+
+// =============================================================================
+// Guard
+// =============================================================================
+
+#[derive(Debug)]
+struct Outage {
+    down_at: i64,
+    orders: Vec<LocalOrder>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Sessions down, by label
+    down: HashMap<String, Outage>,
+    reports: Vec<DisconnectReport>,
+    review: Vec<ReviewItem>,
+}
+
+/// The orders of the sessions that dropped, until they log on again
+#[derive(Debug)]
+pub struct DisconnectGuard {
+    policy: DisconnectPolicy,
+    state: Mutex<State>,
+}
+
+impl DisconnectGuard {
+    pub fn new(policy: DisconnectPolicy) -> Self {
+        Self {
+            policy,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn policy(&self) -> DisconnectPolicy {
+        self.policy
+    }
+
+    /// A session went down with these orders open
+    ///
+    /// A session already down keeps the time it first went down; the
+    /// orders are those of the last call.
+    pub fn on_logout(&self, session: &str, open: Vec<LocalOrder>, now: i64) {
+        let mut state = self.lock();
+        let down_at = state.down.get(session).map_or(now, |x| x.down_at);
+        state.down.insert(
+            session.to_string(),
+            Outage {
+                down_at,
+                orders: open,
+            },
+        );
+    }
+
+    /// A session logged on: apply the policy to the orders it had open
+    ///
+    /// With the cancel policy, `cancel` sends the cancel of an order and
+    /// returns its ClOrdID; an order whose cancel fails is left for review.
+    ///
+    /// # Returns
+    /// The report of the outage, None if the session was not known to be
+    /// down or had no order open
+    pub fn on_logon<F, E>(&self, session: &str, now: i64, mut cancel: F) -> Option<DisconnectReport>
+    where
+        F: FnMut(&LocalOrder) -> Result<String, E>,
+        E: fmt::Display,
+    {
+        let outage = self.lock().down.remove(session)?;
+        if outage.orders.is_empty() {
+            return None;
+        }
+
+        // Sent without the lock: the caller's send may wait on rate limits
+        let actions: Vec<_> = outage
+            .orders
+            .into_iter()
+            .map(|order| {
+                let action = match self.policy {
+                    DisconnectPolicy::Cancel => match cancel(&order) {
+                        Ok(cancel_id) => Action::Canceled(cancel_id),
+                        Err(err) => Action::CancelFailed(err.to_string()),
+                    },
+                    DisconnectPolicy::Review => Action::Review,
+                };
+                OrderAction { order, action }
+            })
+            .collect();
+        let report = DisconnectReport {
+            session: session.to_string(),
+            down_at: outage.down_at,
+            up_at: now,
+            policy: self.policy,
+            actions,
+        };
+
+        let mut state = self.lock();
+        for action in &report.actions {
+            if !matches!(action.action, Action::Canceled(_)) {
+                state.review.push(ReviewItem {
+                    session: report.session.clone(),
+                    order: action.order.clone(),
+                    down_at: report.down_at,
+                });
+            }
+        }
+        state.reports.push(report.clone());
+        Some(report)
+    }
+
+    /// Sessions down, with the number of orders they had open, sorted by
+    /// label
+    pub fn down(&self) -> Vec<(String, usize)> {
+        let state = self.lock();
+        let mut down: Vec<_> = state
+            .down
+            .iter()
+            .map(|(session, outage)| (session.clone(), outage.orders.len()))
+            .collect();
+        down.sort();
+        down
+    }
+
+    /// Every report, oldest first
+    pub fn reports(&self) -> Vec<DisconnectReport> {
+        self.lock().reports.clone()
+    }
+
+    /// Orders waiting for the operator, oldest outage first
+    pub fn review(&self) -> Vec<ReviewItem> {
+        self.lock().review.clone()
+    }
+
+    /// Take orders off the review list: one ClOrdID, or all of them
+    ///
+    /// # Returns
+    /// How many were taken off
+    pub fn resolve(&self, cl_ord_id: Option<&str>) -> usize {
+        let mut state = self.lock();
+        let before = state.review.len();
+        state
+            .review
+            .retain(|x| cl_ord_id.is_some_and(|id| id != x.order.cl_ord_id));
+        before - state.review.len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("disconnect guard lock poisoned")
+    }
+}
@@ -0,0 +1,304 @@
+// =============================================================================
+// Duplicate Order Protection
+// =============================================================================
+// A ClOrdID (11) names one request for the whole trading day: the venue
+// rejects a second order under the same one, or worse, takes a retyped
+// order for a second order. A DuplicateGuard sits in the send path, before
+// the order messages (35=D, F, G, AB) go out, and checks three things:
+//
+//   no ClOrdID         one is generated (PREFIX-N, or any generator of
+//                      ids.rs), never seen by the guard
+//   ClOrdID reused     one the guard has already let through
+//   same order again   a NewOrderSingle with the symbol, side, quantity,
+//                      price, OrdType and account of one sent within the
+//                      window, under another ClOrdID (a double click, a
+//                      script run twice)
+//
+// The policy decides what a duplicate does: rejected, not sent, or let
+// through with a warning. A window of zero turns the second check off.
+//
+// A ClOrdID is remembered once checked, whether the send succeeds or not:
+// a message QuickFIX failed to send may still have reached the venue. The
+// guard lives in memory; the OMS's store keeps its own ClOrdIDs unique
+// across restarts.
+// =============================================================================
+
+use std::{
+    collections::{HashSet, VecDeque},
+    error::Error,
+    fmt,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use quickfix::{FieldMap, Message};
+
+use super::ids::{ClOrdIdGenerator, SequenceIds};
+
+/// How long an order stays in memory to be compared with the next ones
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(2);
+
+/// Message types carrying a new ClOrdID: orders, cancels, replaces and
+/// multileg orders
+pub(super) const ORDER_TYPES: [&str; 4] = ["D", "F", "G", "AB"];
+
+// =============================================================================
+// Policy and Findings
+// =============================================================================
+
+/// What to do with a duplicate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Refuse it: the message is not sent
+    #[default]
+    Reject,
+
+    /// Send it, with a warning
+    Warn,
+}
+
+/// `reject` or `warn`
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(DuplicatePolicy::Reject),
+            "warn" => Ok(DuplicatePolicy::Warn),
+            _ => Err(format!("unknown duplicate policy {s} (reject or warn)")),
+        }
+    }
+}
+
+/// A message the guard takes for a duplicate
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Duplicate {
+    /// This ClOrdID already went out
+    ClOrdIdReused(String),
+
+    /// A NewOrderSingle with the same parameters went out `age` ago
+    SameOrder {
+        cl_ord_id: String,
+        previous: String,
+        age: Duration,
+    },
+}
+
+impl fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Duplicate::ClOrdIdReused(id) => write!(f, "ClOrdID {id} already used"),
+            Duplicate::SameOrder {
+                cl_ord_id,
+                previous,
+                age,
+            } => write!(
+                f,
+                "order {cl_ord_id} repeats {previous}, sent {}ms ago",
+                age.as_millis()
+            ),
+        }
+    }
+}
+
+impl Error for Duplicate {}
+
+/// What the guard did with a message it let through
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checked {
+    /// The ClOrdID it generated, the message had none
+    pub assigned: Option<String>,
+
+    /// Duplicates let through by the warn policy
+    pub warnings: Vec<Duplicate>,
+}
+
+// =============================================================================
+// Guard
+// =============================================================================
+
+/// The fields that make two NewOrderSingles the same order, quantity and
+/// price as numbers so 100 and 100.0 compare equal
+#[derive(Debug, Clone, PartialEq)]
+struct OrderKey {
+    symbol: Option<String>,
+    side: Option<String>,
+    quantity: Option<f64>,
+    price: Option<f64>,
+    ord_type: Option<String>,
+    account: Option<String>,
+}
+
+impl OrderKey {
+    fn of(msg: &Message) -> Self {
+        let number = |tag| msg.get_field(tag).and_then(|x| x.parse::<f64>().ok());
+        Self {
+            symbol: msg.get_field(55),
+            side: msg.get_field(54),
+            quantity: number(38),
+            price: number(44),
+            ord_type: msg.get_field(40),
+            account: msg.get_field(1),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Recent {
+    key: OrderKey,
+    cl_ord_id: String,
+    at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    used: HashSet<String>,
+    recent: VecDeque<Recent>,
+}
+
+/// ClOrdIDs generated and checked for reuse, orders checked for repeats
+#[derive(Debug)]
+pub struct DuplicateGuard {
+    ids: Box<dyn ClOrdIdGenerator>,
+    policy: DuplicatePolicy,
+    window: Duration,
+    state: Mutex<State>,
+}
+
+impl DuplicateGuard {
+    /// Generate the missing ClOrdIDs as `prefix`-N
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            ids: Box::new(SequenceIds::new(prefix)),
+            policy: DuplicatePolicy::default(),
+            window: DEFAULT_WINDOW,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Generate the missing ClOrdIDs with this generator instead
+    pub fn with_ids(mut self, ids: Box<dyn ClOrdIdGenerator>) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Compare new orders with those sent this long before; zero compares
+    /// nothing
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn policy(&self) -> DuplicatePolicy {
+        self.policy
+    }
+
+    /// Check a message about to be sent, and give it a ClOrdID if it has
+    /// none; other message types pass untouched
+    ///
+    /// # Returns
+    /// What was done to it, or the duplicate refused by the reject policy
+    pub fn check(&self, msg: &mut Message, now: Instant) -> Result<Checked, Duplicate> {
+        let msg_type = msg.with_header(|h| h.get_field(35)).unwrap_or_default();
+        if !ORDER_TYPES.contains(&msg_type.as_str()) {
+            return Ok(Checked::default());
+        }
+
+        let mut state = self.state.lock().expect("duplicate guard lock poisoned");
+        let window = self.window;
+        while let Some(recent) = state.recent.front() {
+            if now.saturating_duration_since(recent.at) <= window {
+                break;
+            }
+            state.recent.pop_front();
+        }
+
+        let mut checked = Checked::default();
+        let cl_ord_id = match msg.get_field(11) {
+            Some(id) => id,
+            None => {
+                let id = loop {
+                    let id = self.ids.next_id();
+                    if !state.used.contains(&id) {
+                        break id;
+                    }
+                };
+                // A message the engine cannot set a field on is refused
+                // by it at send time anyway
+                let _ = msg.set_field(11, id.as_str());
+                checked.assigned = Some(id.clone());
+                id
+            }
+        };
+
+        let mut found = Vec::new();
+        if state.used.contains(&cl_ord_id) {
+            found.push(Duplicate::ClOrdIdReused(cl_ord_id.clone()));
+        }
+        let key = (msg_type == "D" && !window.is_zero()).then(|| OrderKey::of(msg));
+        if let Some(key) = &key {
+            if let Some(recent) = state
+                .recent
+                .iter()
+                .rev()
+                .find(|x| x.key == *key && x.cl_ord_id != cl_ord_id)
+            {
+                found.push(Duplicate::SameOrder {
+                    cl_ord_id: cl_ord_id.clone(),
+                    previous: recent.cl_ord_id.clone(),
+                    age: now.saturating_duration_since(recent.at),
+                });
+            }
+        }
+        if self.policy == DuplicatePolicy::Reject {
+            if let Some(duplicate) = found.into_iter().next() {
+                return Err(duplicate);
+            }
+        } else {
+            checked.warnings = found;
+        }
+
+        state.used.insert(cl_ord_id.clone());
+        if let Some(key) = key {
+            state.recent.push_back(Recent {
+                key,
+                cl_ord_id,
+                at: now,
+            });
+        }
+        Ok(checked)
+    }
+
+    /// Remember ClOrdIDs used elsewhere, e.g. by an OMS restored from its
+    /// store, so the guard neither generates nor lets them through again
+    pub fn mark_used<I: IntoIterator<Item = String>>(&self, ids: I) {
+        let mut state = self.state.lock().expect("duplicate guard lock poisoned");
+        state.used.extend(ids);
+    }
+
+    /// Forget the ClOrdIDs and orders seen, and start the generator over:
+    /// at the end of a trading day, for the next one
+    pub fn reset(&self) {
+        let mut state = self.state.lock().expect("duplicate guard lock poisoned");
+        state.used.clear();
+        state.recent.clear();
+        self.ids.reset();
+    }
+
+    /// ClOrdIDs seen so far
+    pub fn len(&self) -> usize {
+        let state = self.state.lock().expect("duplicate guard lock poisoned");
+        state.used.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,317 @@
+// =============================================================================
+// ClOrdID Generators
+// =============================================================================
+// Every order, cancel and replace needs a ClOrdID (11) the venue has never
+// seen from us. How it looks is up to the venue and the firm, so the OMS
+// takes its ClOrdIDs from a generator:
+//
+//   sequence     PREFIX-N, the sequence kept in the state store
+//   dated        PREFIX-YYYYMMDD-N, today's date in front of the sequence
+//   uuid         a random UUID (version 4), nothing to keep
+//   venue:LEN    PREFIX then the sequence in base 36, upper case, never
+//                longer than LEN characters (venues capping ClOrdID at 20)
+//
+// A generator with a sequence never goes back: the OMS resumes it after the
+// number it stored, after the highest ClOrdID of its orders, and after those
+// found in the QuickFIX message store (sent_cl_ord_ids), so a process
+// restarted without its state store still does not send a ClOrdID twice.
+// =============================================================================
+
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+    session::file_store::{FileStore, FileStoreError},
+    time::Date,
+};
+
+use super::duplicates::ORDER_TYPES;
+
+/// Base 36 digits left to the sequence of a venue format, at least
+const MIN_VENUE_DIGITS: usize = 6;
+
+/// Where an OMS takes its ClOrdIDs from
+pub trait ClOrdIdGenerator: fmt::Debug + Send + Sync {
+    /// A ClOrdID this generator never returned before
+    fn next_id(&self) -> String;
+
+    /// Number of the next ClOrdID, what is stored to resume after a
+    /// restart; 0 for a generator without a sequence
+    fn sequence(&self) -> u64;
+
+    /// Move the sequence forward to `sequence`, never back
+    fn advance(&self, sequence: u64);
+
+    /// The sequence after `id`, if this generator could have made it
+    fn sequence_after(&self, id: &str) -> Option<u64>;
+
+    /// Start the sequence over at 1, for a new trading day: ClOrdIDs only
+    /// need to be unique within one. Nothing for a generator without a
+    /// sequence
+    fn reset(&self) {}
+}
+
+// =============================================================================
+// Formats
+// =============================================================================
+
+/// The generators, by name, for command-line flags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdFormat {
+    #[default]
+    Sequence,
+    Dated,
+    Uuid,
+
+    /// At most this many characters
+    Venue {
+        max_len: usize,
+    },
+}
+
+/// `sequence`, `dated`, `uuid` or `venue:LEN`
+impl FromStr for IdFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "sequence" => Ok(IdFormat::Sequence),
+            None if s == "dated" => Ok(IdFormat::Dated),
+            None if s == "uuid" => Ok(IdFormat::Uuid),
+            Some(("venue", len)) => match len.parse() {
+                Ok(max_len) => Ok(IdFormat::Venue { max_len }),
+                Err(_) => Err(format!("not a length: {len}")),
+            },
+            _ => Err(format!(
+                "unknown ClOrdID format {s} (sequence, dated, uuid or venue:LEN)"
+            )),
+        }
+    }
+}
+
+impl IdFormat {
+    /// The generator of this format for a prefix
+    pub fn generator(self, prefix: &str) -> Result<Box<dyn ClOrdIdGenerator>, String> {
+        Ok(match self {
+            IdFormat::Sequence => Box::new(SequenceIds::new(prefix)),
+            IdFormat::Dated => Box::new(DatedIds::new(prefix)),
+            IdFormat::Uuid => Box::new(UuidIds),
+            IdFormat::Venue { max_len } => Box::new(VenueIds::new(prefix, max_len)?),
+        })
+    }
+}
+
+// =============================================================================
+// Generators
+// =============================================================================
+
+/// `PREFIX-N`
+#[derive(Debug)]
+pub struct SequenceIds {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl SequenceIds {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: format!("{prefix}-"),
+            next: AtomicU64::new(1),
+        }
+    }
+}
+
+impl ClOrdIdGenerator for SequenceIds {
+    fn next_id(&self) -> String {
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        format!("{}{id}", self.prefix)
+    }
+
+    fn sequence(&self) -> u64 {
+        self.next.load(Ordering::Relaxed)
+    }
+
+    fn advance(&self, sequence: u64) {
+        self.next.fetch_max(sequence, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        self.next.store(1, Ordering::Relaxed);
+    }
+
+    fn sequence_after(&self, id: &str) -> Option<u64> {
+        let id: u64 = id.strip_prefix(&self.prefix)?.parse().ok()?;
+        Some(id + 1)
+    }
+}
+
+/// `PREFIX-YYYYMMDD-N`, the sequence running on from one day to the next
+#[derive(Debug)]
+pub struct DatedIds {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl DatedIds {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: format!("{prefix}-"),
+            next: AtomicU64::new(1),
+        }
+    }
+}
+
+impl ClOrdIdGenerator for DatedIds {
+    fn next_id(&self) -> String {
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        format!("{}{}-{id}", self.prefix, Date::today().to_fix())
+    }
+
+    fn sequence(&self) -> u64 {
+        self.next.load(Ordering::Relaxed)
+    }
+
+    fn advance(&self, sequence: u64) {
+        self.next.fetch_max(sequence, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        self.next.store(1, Ordering::Relaxed);
+    }
+
+    fn sequence_after(&self, id: &str) -> Option<u64> {
+        let (date, id) = id.strip_prefix(&self.prefix)?.split_once('-')?;
+        Date::from_fix(date)?;
+        Some(id.parse::<u64>().ok()? + 1)
+    }
+}
+
+/// Random version 4 UUIDs, `xxxxxxxx-xxxx-4xxx-yxxx-xxxxxxxxxxxx`
+///
+/// Drawn from std's randomly seeded hasher: unique, not unpredictable.
+#[derive(Debug)]
+pub struct UuidIds;
+
+impl ClOrdIdGenerator for UuidIds {
+    fn next_id(&self) -> String {
+        let mut bytes = [0u8; 16];
+        for chunk in bytes.chunks_mut(8) {
+            let value = RandomState::new().build_hasher().finish().to_be_bytes();
+            chunk.copy_from_slice(&value);
+        }
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let hex: String = bytes.iter().map(|x| format!("{x:02x}")).collect();
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    }
+
+    fn sequence(&self) -> u64 {
+        0
+    }
+
+    fn advance(&self, _sequence: u64) {}
+
+    fn sequence_after(&self, _id: &str) -> Option<u64> {
+        None
+    }
+}
+
+/// `PREFIX` then the sequence in base 36, within a length limit
+///
+/// The prefix must be letters and digits, short enough to leave six digits
+/// to the sequence: two billion ClOrdIDs.
+#[derive(Debug)]
+pub struct VenueIds {
+    prefix: String,
+    max_len: usize,
+    next: AtomicU64,
+}
+
+impl VenueIds {
+    pub fn new(prefix: &str, max_len: usize) -> Result<Self, String> {
+        if !prefix.bytes().all(|x| x.is_ascii_alphanumeric()) {
+            return Err(format!("ClOrdID prefix {prefix} is not letters and digits"));
+        }
+        if prefix.len() + MIN_VENUE_DIGITS > max_len {
+            return Err(format!(
+                "ClOrdID prefix {prefix} leaves less than {MIN_VENUE_DIGITS} of {max_len} \
+                 characters"
+            ));
+        }
+        Ok(Self {
+            prefix: prefix.to_ascii_uppercase(),
+            max_len,
+            next: AtomicU64::new(1),
+        })
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+}
+
+impl ClOrdIdGenerator for VenueIds {
+    fn next_id(&self) -> String {
+        let mut id = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut digits = Vec::new();
+        while id > 0 {
+            digits.push(char::from_digit((id % 36) as u32, 36).unwrap_or('0'));
+            id /= 36;
+        }
+        let digits: String = digits.iter().rev().collect();
+        format!("{}{}", self.prefix, digits.to_ascii_uppercase())
+    }
+
+    fn sequence(&self) -> u64 {
+        self.next.load(Ordering::Relaxed)
+    }
+
+    fn advance(&self, sequence: u64) {
+        self.next.fetch_max(sequence, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        self.next.store(1, Ordering::Relaxed);
+    }
+
+    fn sequence_after(&self, id: &str) -> Option<u64> {
+        let digits = id.strip_prefix(&self.prefix)?;
+        if digits.is_empty() || id.len() > self.max_len {
+            return None;
+        }
+        Some(u64::from_str_radix(digits, 36).ok()? + 1)
+    }
+}
+
+// =============================================================================
+// Message Store
+// =============================================================================
+
+/// ClOrdIDs of the orders, cancels and replaces a session's file store
+/// holds, in the order they were sent
+///
+/// Read with the engine stopped, or at least before it sends: the body
+/// grows while it runs.
+pub fn sent_cl_ord_ids(store: &FileStore) -> Result<Vec<String>, FileStoreError> {
+    Ok(store
+        .verify()?
+        .messages
+        .iter()
+        .filter(|x| x.field(35).is_some_and(|x| ORDER_TYPES.contains(&x)))
+        .filter_map(|x| x.field(11))
+        .map(str::to_string)
+        .collect())
+}
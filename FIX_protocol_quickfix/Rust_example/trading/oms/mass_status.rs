@@ -0,0 +1,475 @@
+// =============================================================================
+// Order Mass Status (35=AF)
+// =============================================================================
+// The venue's view of our orders, asked for after a reconnect or whenever
+// the local book is in doubt, to find the orders one side knows and the
+// other does not:
+//
+//   35=AF  MassStatusReqID (584), MassStatusReqType (585) = 7 all orders,
+//          or 1 with Symbol (55) for the orders of one security
+//   35=8   one per order, ExecType (150) = I (order status), with the
+//          MassStatusReqID, TotNumReports (911) and LastRptRequested (912)
+//          on the last; ClOrdID, OrderID, OrdStatus, Symbol, Side,
+//          OrderQty, Price, LeavesQty, CumQty, AvgPx
+//
+// A venue without an order to report sends a single 35=8 with
+// TotNumReports 0, OrdStatus 8 and OrdRejReason (103) 5, unknown order: it
+// completes the request and is not a report.
+//
+// A MassStatusBook tracks the requests sent and keeps the reports of each.
+// Once a request is complete, `reconcile` sets its reports against the
+// orders we hold open on that session (LocalOrder, from whichever book the
+// caller keeps) and returns the discrepancies:
+//
+//   unknown   the venue has an open order we do not know
+//   missing   we hold an order open that the venue did not report
+//   differs   both know the order, but not with the same symbol, side,
+//             quantity, or the venue has it done
+//
+// The venue side decodes a request (`from_message`) and answers with a
+// `to_message` per report, or `build_no_orders`.
+// =============================================================================
+
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{Mutex, MutexGuard},
+};
+
+use quickfix::{FieldMap, Message, QuickFixError};
+
+use crate::{
+    oms::Side,
+    session::{events::FixMessage, Direction},
+};
+
+// =============================================================================
+// Request
+// =============================================================================
+
+/// OrderMassStatusRequest (35=AF)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MassStatusRequest {
+    pub request_id: String,
+
+    /// Every order if None
+    pub symbol: Option<String>,
+}
+
+impl MassStatusRequest {
+    pub fn to_message(&self) -> Result<Message, QuickFixError> {
+        let mut msg = Message::new();
+        msg.with_header_mut(|h| h.set_field(35, "AF"))?;
+        msg.set_field(584, self.request_id.as_str())?; // MassStatusReqID
+        match &self.symbol {
+            Some(symbol) => {
+                msg.set_field(585, "1")?; // MassStatusReqType: security
+                msg.set_field(55, symbol.as_str())?;
+            }
+            None => msg.set_field(585, "7")?, // MassStatusReqType: all orders
+        }
+        Ok(msg)
+    }
+
+    /// Decode a received 35=AF; None without MassStatusReqID
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        Some(Self {
+            request_id: msg.get_field(584)?,
+            symbol: msg.get_field(55),
+        })
+    }
+}
+
+impl fmt::Display for MassStatusRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.symbol {
+            Some(symbol) => write!(f, "{} {symbol}", self.request_id),
+            None => write!(f, "{} all orders", self.request_id),
+        }
+    }
+}
+
+// =============================================================================
+// Report
+// =============================================================================
+
+/// ExecutionReport (35=8) with ExecType I, answering a mass status request
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderStatusReport {
+    /// MassStatusReqID (584) answered
+    pub request_id: String,
+
+    /// TotNumReports (911), if sent
+    pub total: Option<u64>,
+
+    /// LastRptRequested (912): the last report of the request
+    pub last_requested: bool,
+    pub cl_ord_id: String,
+    pub order_id: String,
+
+    /// OrdStatus (39), as sent
+    pub ord_status: String,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+
+    /// Limit price (44); None for a market order
+    pub price: Option<f64>,
+    pub leaves_qty: f64,
+    pub cum_qty: f64,
+    pub avg_px: f64,
+}
+
+impl OrderStatusReport {
+    /// None if the message is not an ExecutionReport answering a mass
+    /// status request, or lacks ClOrdID, Symbol or Side
+    pub fn from_fix(msg: &FixMessage) -> Option<Self> {
+        if msg.msg_type() != "8" {
+            return None;
+        }
+        let number = |tag| msg.get(tag).and_then(|x| x.parse::<f64>().ok());
+        Some(Self {
+            request_id: msg.get(584)?.to_string(),
+            total: msg.get(911).and_then(|x| x.parse().ok()),
+            last_requested: msg.get(912) == Some("Y"),
+            cl_ord_id: msg.get(11)?.to_string(),
+            order_id: msg.get(37).unwrap_or_default().to_string(),
+            ord_status: msg.get(39).unwrap_or_default().to_string(),
+            symbol: msg.get(55)?.to_string(),
+            side: match msg.get(54)? {
+                "1" => Side::Buy,
+                "2" => Side::Sell,
+                _ => return None,
+            },
+            quantity: number(38).unwrap_or_default(),
+            price: number(44),
+            leaves_qty: number(151).unwrap_or_default(),
+            cum_qty: number(14).unwrap_or_default(),
+            avg_px: number(6).unwrap_or_default(),
+        })
+    }
+
+    pub fn to_message(&self, exec_id: &str) -> Result<Message, QuickFixError> {
+        let mut msg = Message::new();
+        msg.with_header_mut(|h| h.set_field(35, "8"))?;
+        msg.set_field(584, self.request_id.as_str())?; // MassStatusReqID
+        if let Some(total) = self.total {
+            msg.set_field(911, total.to_string().as_str())?; // TotNumReports
+        }
+        // LastRptRequested
+        msg.set_field(912, if self.last_requested { "Y" } else { "N" })?;
+        msg.set_field(37, self.order_id.as_str())?; // OrderID
+        msg.set_field(11, self.cl_ord_id.as_str())?; // ClOrdID
+        msg.set_field(17, exec_id)?; // ExecID
+        msg.set_field(150, "I")?; // ExecType: order status
+        msg.set_field(39, self.ord_status.as_str())?; // OrdStatus
+        msg.set_field(55, self.symbol.as_str())?;
+        msg.set_field(54, self.side.as_fix())?;
+        msg.set_field(38, self.quantity.to_string().as_str())?; // OrderQty
+        if let Some(price) = self.price {
+            msg.set_field(44, price.to_string().as_str())?; // Price
+        }
+        msg.set_field(151, self.leaves_qty.to_string().as_str())?; // LeavesQty
+        msg.set_field(14, self.cum_qty.to_string().as_str())?; // CumQty
+        msg.set_field(6, self.avg_px.to_string().as_str())?; // AvgPx
+        Ok(msg)
+    }
+
+    /// Whether the venue has the order still working: anything but
+    /// filled, done for day, canceled, rejected or expired
+    pub fn is_working(&self) -> bool {
+        !matches!(self.ord_status.as_str(), "2" | "3" | "4" | "8" | "C")
+    }
+}
+
+impl fmt::Display for OrderStatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (order {}) {} {} {}",
+            self.cl_ord_id,
+            self.order_id,
+            self.side.as_str(),
+            self.quantity,
+            self.symbol
+        )?;
+        if let Some(price) = self.price {
+            write!(f, " @ {price}")?;
+        }
+        write!(
+            f,
+            " 39={} leaves {} cum {}",
+            self.ord_status, self.leaves_qty, self.cum_qty
+        )
+    }
+}
+
+/// The single ExecutionReport answering a request when there is no order
+/// to report: TotNumReports 0, rejected as unknown order
+pub fn build_no_orders(
+    request: &MassStatusRequest,
+    exec_id: &str,
+) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "8"))?;
+    msg.set_field(584, request.request_id.as_str())?; // MassStatusReqID
+    msg.set_field(911, "0")?; // TotNumReports
+    msg.set_field(912, "Y")?; // LastRptRequested
+    msg.set_field(37, "NONE")?; // OrderID
+    msg.set_field(11, "NONE")?; // ClOrdID
+    msg.set_field(17, exec_id)?; // ExecID
+    msg.set_field(150, "I")?; // ExecType: order status
+    msg.set_field(39, "8")?; // OrdStatus: rejected
+    msg.set_field(103, "5")?; // OrdRejReason: unknown order
+    msg.set_field(55, request.symbol.as_deref().unwrap_or("NA"))?;
+    msg.set_field(54, "1")?; // Side, required
+    msg.set_field(151, "0")?; // LeavesQty
+    msg.set_field(14, "0")?; // CumQty
+    msg.set_field(6, "0")?; // AvgPx
+    msg.set_field(58, "no open orders")?;
+    Ok(msg)
+}
+
+// =============================================================================
+// Reconciliation
+// =============================================================================
+
+/// An order we hold open on the session asked about
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalOrder {
+    pub cl_ord_id: String,
+    pub symbol: String,
+
+    /// None if unknown locally
+    pub side: Option<Side>,
+    pub quantity: f64,
+}
+
+/// One side's order the other does not agree with
+#[derive(Debug, Clone, PartialEq)]
+pub enum Discrepancy {
+    /// The venue has an open order we do not know
+    Unknown(OrderStatusReport),
+
+    /// We hold an order open that the venue did not report
+    Missing(LocalOrder),
+
+    /// Both know the order, with the fields that differ (`symbol`, `side`,
+    /// `quantity`, `status` when the venue has it done)
+    Differs {
+        local: LocalOrder,
+        report: OrderStatusReport,
+        fields: Vec<&'static str>,
+    },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::Unknown(report) => write!(f, "unknown to us: {report}"),
+            Discrepancy::Missing(order) => write!(
+                f,
+                "missing at the venue: {} {} {} {}",
+                order.cl_ord_id,
+                order.side.map_or("?", Side::as_str),
+                order.quantity,
+                order.symbol
+            ),
+            Discrepancy::Differs { report, fields, .. } => {
+                write!(f, "differs in {}: {report}", fields.join(", "))
+            }
+        }
+    }
+}
+
+/// Set the venue's reports against our open orders
+///
+/// A done order the venue reports is only a discrepancy if we still hold it
+/// open.
+pub fn reconcile(reports: &[OrderStatusReport], local: &[LocalOrder]) -> Vec<Discrepancy> {
+    let mut discrepancies = Vec::new();
+    let mut reported = HashSet::new();
+    for report in reports {
+        reported.insert(report.cl_ord_id.as_str());
+        let Some(order) = local.iter().find(|x| x.cl_ord_id == report.cl_ord_id) else {
+            if report.is_working() {
+                discrepancies.push(Discrepancy::Unknown(report.clone()));
+            }
+            continue;
+        };
+
+        let mut fields = Vec::new();
+        if order.symbol != report.symbol {
+            fields.push("symbol");
+        }
+        if order.side.is_some_and(|x| x != report.side) {
+            fields.push("side");
+        }
+        if order.quantity != report.quantity {
+            fields.push("quantity");
+        }
+        if !report.is_working() {
+            fields.push("status");
+        }
+        if !fields.is_empty() {
+            discrepancies.push(Discrepancy::Differs {
+                local: order.clone(),
+                report: report.clone(),
+                fields,
+            });
+        }
+    }
+
+    for order in local {
+        if !reported.contains(order.cl_ord_id.as_str()) {
+            discrepancies.push(Discrepancy::Missing(order.clone()));
+        }
+    }
+    discrepancies
+}
+
+// =============================================================================
+// MassStatusBook
+// =============================================================================
+
+/// A request sent, and the reports received so far
+#[derive(Debug, Clone, PartialEq)]
+pub struct MassStatusRun {
+    pub session: String,
+    pub request: MassStatusRequest,
+
+    /// TotNumReports of the first report
+    pub expected: Option<u64>,
+    pub reports: Vec<OrderStatusReport>,
+
+    /// The last report came in, or the venue had none
+    pub complete: bool,
+}
+
+impl MassStatusRun {
+    /// Set the reports against our open orders on the session, those of
+    /// the symbol asked for if any
+    pub fn reconcile(&self, local: &[LocalOrder]) -> Vec<Discrepancy> {
+        let local: Vec<LocalOrder> = local
+            .iter()
+            .filter(|x| {
+                self.request
+                    .symbol
+                    .as_ref()
+                    .is_none_or(|symbol| x.symbol == *symbol)
+            })
+            .cloned()
+            .collect();
+        reconcile(&self.reports, &local)
+    }
+}
+
+impl fmt::Display for MassStatusRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.complete { "complete" } else { "pending" };
+        write!(f, "{} {state}", self.request)?;
+        match self.expected {
+            Some(expected) => write!(f, ", {} of {expected} reports", self.reports.len()),
+            None => write!(f, ", {} reports", self.reports.len()),
+        }
+    }
+}
+
+/// What a message changed in the book
+#[derive(Debug, Clone, PartialEq)]
+pub enum MassStatusUpdate {
+    /// A report of a request still running
+    Report(OrderStatusReport),
+
+    /// The request is complete, with every report
+    Completed(MassStatusRun),
+}
+
+/// Mass status requests sent, and the reports answering them
+pub struct MassStatusBook {
+    id_prefix: String,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    runs: Vec<MassStatusRun>,
+    next_id: u64,
+}
+
+impl MassStatusBook {
+    /// MassStatusReqIDs are `PREFIX-1`, `PREFIX-2`...
+    pub fn new(id_prefix: &str) -> Self {
+        Self {
+            id_prefix: id_prefix.to_string(),
+            inner: Mutex::new(Inner {
+                runs: Vec::new(),
+                next_id: 1,
+            }),
+        }
+    }
+
+    /// A request for the orders of a symbol (all of them if None), under a
+    /// new MassStatusReqID; not tracked until `track`
+    pub fn prepare(&self, symbol: Option<&str>) -> MassStatusRequest {
+        let mut inner = self.lock();
+        let request_id = format!("{}-{}", self.id_prefix, inner.next_id);
+        inner.next_id += 1;
+        MassStatusRequest {
+            request_id,
+            symbol: symbol.map(str::to_string),
+        }
+    }
+
+    /// Track a request once sent on a session
+    pub fn track(&self, session: &str, request: MassStatusRequest) {
+        self.lock().runs.push(MassStatusRun {
+            session: session.to_string(),
+            request,
+            expected: None,
+            reports: Vec::new(),
+            complete: false,
+        });
+    }
+
+    /// Apply a received ExecutionReport
+    ///
+    /// # Returns
+    /// What changed; None for other messages, outbound ones and reports of
+    /// requests we did not send
+    pub fn on_message(&self, msg: &FixMessage) -> Option<MassStatusUpdate> {
+        if msg.direction != Direction::Inbound || msg.msg_type() != "8" {
+            return None;
+        }
+        let request_id = msg.get(584)?;
+        let mut inner = self.lock();
+        let run = inner
+            .runs
+            .iter_mut()
+            .find(|x| x.request.request_id == request_id)?;
+
+        let total = msg.get(911).and_then(|x| x.parse::<u64>().ok());
+        run.expected = run.expected.or(total);
+        if total == Some(0) {
+            run.complete = true;
+            return Some(MassStatusUpdate::Completed(run.clone()));
+        }
+
+        let report = OrderStatusReport::from_fix(msg)?;
+        run.reports.push(report.clone());
+        let received = run.reports.len() as u64;
+        if report.last_requested || run.expected.is_some_and(|x| received >= x) {
+            run.complete = true;
+            return Some(MassStatusUpdate::Completed(run.clone()));
+        }
+        Some(MassStatusUpdate::Report(report))
+    }
+
+    /// Every request sent, oldest first
+    pub fn runs(&self) -> Vec<MassStatusRun> {
+        self.lock().runs.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().expect("mass status lock poisoned")
+    }
+}
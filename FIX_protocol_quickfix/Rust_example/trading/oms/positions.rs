@@ -0,0 +1,255 @@
+// =============================================================================
+// Position Keeper
+// =============================================================================
+// Aggregates fills into a signed position per symbol with average cost and
+// realized P&L. Fed by the OMS each time an ExecutionReport carries a fill.
+//
+// The fills of each symbol are kept in order. Average cost accounting does
+// not run backwards, so a bust or correct of a fill edits that list and the
+// position is rebuilt from it: realized P&L of every later trade moves too.
+//
+// The same list is what a state store keeps, under "positions" by symbol:
+// a restarted process rebuilds its positions, and with them the limits the
+// risk checks count against, from the fills.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    json::{json_escape, JsonValue},
+    oms::{Correction, Fill, Side},
+    store::{StateStore, StoreError},
+};
+
+/// Store namespace of the fills, keyed by symbol
+pub const POSITIONS_NAMESPACE: &str = "positions";
+
+#[derive(Debug, Clone, Default)]
+pub struct Position {
+    /// Signed quantity: positive = long, negative = short
+    pub quantity: f64,
+
+    /// Average cost of the open quantity
+    pub avg_cost: f64,
+
+    /// P&L locked in by closing trades
+    pub realized_pnl: f64,
+}
+
+impl Position {
+    /// Apply one fill using average-cost accounting
+    fn apply(&mut self, side: Side, quantity: f64, price: f64) {
+        let signed = match side {
+            Side::Buy => quantity,
+            Side::Sell => -quantity,
+        };
+
+        if self.quantity == 0.0 || self.quantity.signum() == signed.signum() {
+            // Opening or increasing: blend the average cost
+            let total = self.quantity + signed;
+            self.avg_cost = (self.avg_cost * self.quantity.abs() + price * quantity) / total.abs();
+            self.quantity = total;
+            return;
+        }
+
+        // Reducing, closing or flipping
+        let closed = quantity.min(self.quantity.abs());
+        self.realized_pnl += closed * (price - self.avg_cost) * self.quantity.signum();
+        self.quantity += signed;
+
+        if self.quantity == 0.0 {
+            self.avg_cost = 0.0;
+        } else if self.quantity.signum() == signed.signum() {
+            // Flipped: the remainder was opened at this fill's price
+            self.avg_cost = price;
+        }
+    }
+
+    /// JSON object for the REST gateway and the WebSocket stream
+    pub fn to_json(&self, symbol: &str) -> String {
+        format!(
+            "{{\"symbol\":\"{}\",\"quantity\":{},\"avg_cost\":{},\"realized_pnl\":{}}}",
+            json_escape(symbol),
+            self.quantity,
+            self.avg_cost,
+            self.realized_pnl
+        )
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "qty={} avg_cost={:.4} realized={:.2}",
+            self.quantity, self.avg_cost, self.realized_pnl
+        )
+    }
+}
+
+// =============================================================================
+// PositionBook
+// =============================================================================
+
+/// A symbol's position and the fills it was built from
+#[derive(Default)]
+struct Entry {
+    position: Position,
+    fills: Vec<Fill>,
+}
+
+impl Entry {
+    fn rebuild(&mut self) {
+        self.position = Position::default();
+        for fill in &self.fills {
+            self.position.apply(fill.side, fill.quantity, fill.price);
+        }
+    }
+
+    fn fills_json(&self) -> String {
+        let fills: Vec<_> = self.fills.iter().map(Fill::to_json).collect();
+        format!("[{}]", fills.join(","))
+    }
+}
+
+#[derive(Default)]
+pub struct PositionBook {
+    positions: Mutex<HashMap<String, Entry>>,
+
+    /// Where the fills of each symbol are written through, if set
+    store: Option<Arc<dyn StateStore>>,
+}
+
+impl PositionBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuild the positions from the fills kept in a store, then write
+    /// every change through to it
+    pub fn with_store(mut self, store: Arc<dyn StateStore>) -> Result<Self, StoreError> {
+        let positions = self.positions.get_mut().expect("position lock poisoned");
+        for (symbol, value) in store.scan(POSITIONS_NAMESPACE)? {
+            let corrupt = || StoreError::Corrupt {
+                namespace: POSITIONS_NAMESPACE.to_string(),
+                key: symbol.clone(),
+            };
+            let value = JsonValue::parse(&value).ok_or_else(corrupt)?;
+            let fills = value
+                .as_array()
+                .ok_or_else(corrupt)?
+                .iter()
+                .map(Fill::from_value)
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(corrupt)?;
+            let mut entry = Entry {
+                position: Position::default(),
+                fills,
+            };
+            entry.rebuild();
+            positions.insert(symbol, entry);
+        }
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    /// Write the fills of a symbol through to the store, if any
+    fn persist(&self, symbol: &str, entry: &Entry) {
+        if let Some(store) = &self.store {
+            if let Err(err) = store.put(POSITIONS_NAMESPACE, symbol, &entry.fills_json()) {
+                eprintln!("Positions: cannot store {symbol}: {err}");
+            }
+        }
+    }
+
+    /// Apply a fill and return the updated position
+    pub fn apply_fill(&self, fill: &Fill) -> Position {
+        let mut positions = self.positions.lock().expect("position lock poisoned");
+        let entry = positions.entry(fill.symbol.clone()).or_default();
+        entry.position.apply(fill.side, fill.quantity, fill.price);
+        entry.fills.push(fill.clone());
+        self.persist(&fill.symbol, entry);
+        entry.position.clone()
+    }
+
+    /// Bust or correct a fill applied earlier and return the updated position
+    ///
+    /// A fill this book never saw leaves the position unchanged.
+    pub fn apply_correction(&self, correction: &Correction) -> Position {
+        let mut positions = self.positions.lock().expect("position lock poisoned");
+        let entry = positions
+            .entry(correction.original.symbol.clone())
+            .or_default();
+        let Some(index) = entry
+            .fills
+            .iter()
+            .position(|x| x.exec_id == correction.original.exec_id)
+        else {
+            return entry.position.clone();
+        };
+
+        match &correction.corrected {
+            Some(fill) => entry.fills[index] = fill.clone(),
+            None => {
+                entry.fills.remove(index);
+            }
+        }
+        entry.rebuild();
+        self.persist(&correction.original.symbol, entry);
+        entry.position.clone()
+    }
+
+    /// Every fill standing, by symbol then in order
+    pub fn fills(&self) -> Vec<Fill> {
+        let positions = self.positions.lock().expect("position lock poisoned");
+        let mut symbols: Vec<_> = positions.keys().collect();
+        symbols.sort();
+        symbols
+            .into_iter()
+            .flat_map(|x| positions[x].fills.iter().cloned())
+            .collect()
+    }
+
+    /// Take over the fills of another process (a version upgrade's
+    /// handover): the positions of their symbols are rebuilt from them
+    /// alone, so restoring the same fills twice counts them once
+    pub fn restore(&self, fills: Vec<Fill>) {
+        let mut by_symbol: HashMap<String, Vec<Fill>> = HashMap::new();
+        for fill in fills {
+            by_symbol.entry(fill.symbol.clone()).or_default().push(fill);
+        }
+        let mut positions = self.positions.lock().expect("position lock poisoned");
+        for (symbol, fills) in by_symbol {
+            let entry = positions.entry(symbol.clone()).or_default();
+            entry.fills = fills;
+            entry.rebuild();
+            self.persist(&symbol, entry);
+        }
+    }
+
+    /// Signed quantity held in a symbol (0 when flat or unknown)
+    pub fn quantity(&self, symbol: &str) -> f64 {
+        self.positions
+            .lock()
+            .expect("position lock poisoned")
+            .get(symbol)
+            .map_or(0.0, |x| x.position.quantity)
+    }
+
+    /// Snapshot of all positions, sorted by symbol
+    pub fn snapshot(&self) -> Vec<(String, Position)> {
+        let mut positions: Vec<_> = self
+            .positions
+            .lock()
+            .expect("position lock poisoned")
+            .iter()
+            .map(|(symbol, entry)| (symbol.clone(), entry.position.clone()))
+            .collect();
+        positions.sort_by(|a, b| a.0.cmp(&b.0));
+        positions
+    }
+}
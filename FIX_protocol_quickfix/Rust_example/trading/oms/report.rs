@@ -0,0 +1,502 @@
+// =============================================================================
+// Daily Trading Report
+// =============================================================================
+// What a day of trading came to, per session and symbol, for the desk and
+// for whoever reviews it the next morning:
+//
+//   orders      NewOrderSingles sent, and how many the venue rejected
+//   cancels     cancel requests sent; the cancel ratio is cancels / orders
+//   rejects     Rejects (35=3) and BusinessMessageRejects (35=j) received
+//   fills       bought, sold and their notional (quantity x price)
+//   slippage    fill price against the arrival price of its order, in basis
+//               points weighted by quantity: positive is a cost (bought
+//               above, sold below it)
+//
+// The arrival price is the market when the order was sent, the mid of the
+// book if there was one; the application that sends orders records it. Fills
+// of orders without one are left out of the slippage, not of the rest.
+//
+// A report covers a period, a UTC day or the day up to a session's close,
+// and is written as text (a table per section), HTML (one page, as the
+// confirmations of sell_side) or JSON (one object, numbers unrounded).
+// =============================================================================
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    str::FromStr,
+};
+
+use crate::{
+    json::json_escape,
+    oms::{blotter::Trade, Side},
+    session::rejects::Reject,
+    time::{time_of_day, Date},
+};
+
+// =============================================================================
+// Formats
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    #[default]
+    Text,
+    Html,
+    Json,
+}
+
+impl ReportFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReportFormat::Text => "text",
+            ReportFormat::Html => "html",
+            ReportFormat::Json => "json",
+        }
+    }
+
+    /// File extension of a report in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Text => "txt",
+            ReportFormat::Html => "html",
+            ReportFormat::Json => "json",
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" | "txt" => Ok(ReportFormat::Text),
+            "html" => Ok(ReportFormat::Html),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(format!(
+                "unknown report format {value}, not text, html or json"
+            )),
+        }
+    }
+}
+
+// =============================================================================
+// Inputs
+// =============================================================================
+
+/// An order as the report needs it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportOrder {
+    pub session: String,
+    pub cl_ord_id: String,
+    pub symbol: String,
+    pub side: Side,
+
+    /// Unix milliseconds
+    pub sent: i64,
+
+    /// Market price when it was sent, if known
+    pub arrival: Option<f64>,
+
+    /// Cancel requests sent for it
+    pub cancels: u32,
+    pub rejected: bool,
+}
+
+// =============================================================================
+// Report
+// =============================================================================
+
+/// One session and symbol, or the total of a session
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReportRow {
+    pub orders: u64,
+    pub rejected: u64,
+    pub cancels: u64,
+    pub fills: u64,
+    pub bought: f64,
+    pub sold: f64,
+    pub buy_notional: f64,
+    pub sell_notional: f64,
+
+    /// Slippage in basis points times quantity, and that quantity
+    slippage: f64,
+    slipped: f64,
+}
+
+impl ReportRow {
+    /// Cancel requests per order sent
+    pub fn cancel_ratio(&self) -> Option<f64> {
+        (self.orders > 0).then(|| self.cancels as f64 / self.orders as f64)
+    }
+
+    pub fn notional(&self) -> f64 {
+        self.buy_notional + self.sell_notional
+    }
+
+    /// Basis points, weighted by quantity; None without an arrival price
+    pub fn slippage_bps(&self) -> Option<f64> {
+        (self.slipped > 0.0).then(|| self.slippage / self.slipped)
+    }
+
+    fn add(&mut self, other: &ReportRow) {
+        self.orders += other.orders;
+        self.rejected += other.rejected;
+        self.cancels += other.cancels;
+        self.fills += other.fills;
+        self.bought += other.bought;
+        self.sold += other.sold;
+        self.buy_notional += other.buy_notional;
+        self.sell_notional += other.sell_notional;
+        self.slippage += other.slippage;
+        self.slipped += other.slipped;
+    }
+}
+
+/// A session's rows, its total and the rejects it received
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionReport {
+    pub total: ReportRow,
+
+    /// By symbol
+    pub symbols: BTreeMap<String, ReportRow>,
+
+    /// Rejects (35=3, 35=j) received
+    pub rejects: u64,
+}
+
+/// What the sessions did over a period
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyReport {
+    /// Unix seconds, `to` excluded
+    pub from: i64,
+    pub to: i64,
+
+    /// By session label
+    pub sessions: BTreeMap<String, SessionReport>,
+    pub total: ReportRow,
+}
+
+impl DailyReport {
+    /// Aggregate the orders sent, fills and rejects received over a period
+    ///
+    /// # Arguments
+    /// * `from`, `to` - Unix seconds, `to` excluded
+    /// * `session` - Only this session label, else every one
+    pub fn build(
+        from: i64,
+        to: i64,
+        session: Option<&str>,
+        orders: &[ReportOrder],
+        fills: &[Trade],
+        rejects: &[Reject],
+    ) -> Self {
+        let wanted = |x: &str| session.is_none_or(|session| session == x);
+        let in_period = |ms: i64| ms >= from * 1_000 && ms < to * 1_000;
+        let mut sessions: BTreeMap<String, SessionReport> = BTreeMap::new();
+
+        let mut arrivals = BTreeMap::new();
+        for order in orders.iter().filter(|x| wanted(&x.session)) {
+            arrivals.insert(
+                (order.session.as_str(), order.cl_ord_id.as_str()),
+                order.arrival,
+            );
+            if !in_period(order.sent) {
+                continue;
+            }
+            let row = row(&mut sessions, &order.session, &order.symbol);
+            row.orders += 1;
+            row.cancels += u64::from(order.cancels);
+            row.rejected += u64::from(order.rejected);
+        }
+
+        for fill in fills {
+            if !wanted(&fill.session) || !in_period(fill.time) {
+                continue;
+            }
+            let row = row(&mut sessions, &fill.session, &fill.symbol);
+            let notional = fill.quantity * fill.price;
+            let sign = match fill.side {
+                Side::Buy => {
+                    row.bought += fill.quantity;
+                    row.buy_notional += notional;
+                    1.0
+                }
+                Side::Sell => {
+                    row.sold += fill.quantity;
+                    row.sell_notional += notional;
+                    -1.0
+                }
+            };
+            row.fills += 1;
+            let key = (fill.session.as_str(), fill.cl_ord_id.as_str());
+            if let Some(Some(arrival)) = arrivals.get(&key) {
+                if *arrival > 0.0 {
+                    let bps = sign * (fill.price - arrival) / arrival * 10_000.0;
+                    row.slippage += bps * fill.quantity;
+                    row.slipped += fill.quantity;
+                }
+            }
+        }
+
+        for reject in rejects {
+            if wanted(&reject.session) && (from..to).contains(&reject.time) {
+                sessions.entry(reject.session.clone()).or_default().rejects += 1;
+            }
+        }
+
+        let mut total = ReportRow::default();
+        for report in sessions.values_mut() {
+            for row in report.symbols.values() {
+                report.total.add(row);
+            }
+            total.add(&report.total);
+        }
+        Self {
+            from,
+            to,
+            sessions,
+            total,
+        }
+    }
+
+    /// The report of a UTC day
+    pub fn for_date(
+        date: Date,
+        session: Option<&str>,
+        orders: &[ReportOrder],
+        fills: &[Trade],
+        rejects: &[Reject],
+    ) -> Self {
+        let from = date.to_unix();
+        Self::build(from, from + 86_400, session, orders, fills, rejects)
+    }
+
+    pub fn rejects(&self) -> u64 {
+        self.sessions.values().map(|x| x.rejects).sum()
+    }
+
+    /// `2026-10-16 00:00:00 - 2026-10-17 00:00:00 UTC`
+    pub fn period(&self) -> String {
+        let at = |x: i64| format!("{} {}", Date::from_unix(x).to_iso(), time_of_day(x));
+        format!("{} - {} UTC", at(self.from), at(self.to))
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Text => self.to_text(),
+            ReportFormat::Html => self.to_html(),
+            ReportFormat::Json => self.to_json(),
+        }
+    }
+
+    /// Every row of the report: (session, symbol), session totals with
+    /// symbol "*", in order
+    fn rows(&self) -> Vec<(&str, &str, &ReportRow)> {
+        let mut rows = Vec::new();
+        for (session, report) in &self.sessions {
+            for (symbol, row) in &report.symbols {
+                rows.push((session.as_str(), symbol.as_str(), row));
+            }
+            rows.push((session.as_str(), "*", &report.total));
+        }
+        rows
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = format!("Trading report {}\n", self.period());
+        let _ = writeln!(
+            out,
+            "orders {}, rejected {}, cancels {} (ratio {}), fills {}, notional {:.2}, \
+             rejects received {}, slippage {}",
+            self.total.orders,
+            self.total.rejected,
+            self.total.cancels,
+            ratio(self.total.cancel_ratio()),
+            self.total.fills,
+            self.total.notional(),
+            self.rejects(),
+            bps(self.total.slippage_bps())
+        );
+        if self.sessions.is_empty() {
+            return out;
+        }
+        let _ = writeln!(
+            out,
+            "\n{:<28} {:<10} {:>6} {:>5} {:>6} {:>6} {:>6} {:>10} {:>10} {:>14} {:>9}",
+            "session",
+            "symbol",
+            "orders",
+            "rej",
+            "cxl",
+            "ratio",
+            "fills",
+            "bought",
+            "sold",
+            "notional",
+            "slip bps"
+        );
+        for (session, symbol, row) in self.rows() {
+            let _ = writeln!(
+                out,
+                "{:<28} {:<10} {:>6} {:>5} {:>6} {:>6} {:>6} {:>10} {:>10} {:>14.2} {:>9}",
+                session,
+                symbol,
+                row.orders,
+                row.rejected,
+                row.cancels,
+                ratio(row.cancel_ratio()),
+                row.fills,
+                row.bought,
+                row.sold,
+                row.notional(),
+                bps(row.slippage_bps())
+            );
+        }
+        for (session, report) in &self.sessions {
+            if report.rejects > 0 {
+                let _ = writeln!(out, "{session}: {} reject(s) received", report.rejects);
+            }
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let title = format!("Trading report - {}", html(&self.period()));
+        let mut out = format!(
+            "<!DOCTYPE html>\n\
+             <html><head><meta charset=\"utf-8\"><title>{title}</title>\n\
+             <style>body{{font-family:sans-serif}}table{{border-collapse:collapse}}\
+             td,th{{border:1px solid #999;padding:2px 8px}}td.n{{text-align:right}}\
+             tr.t{{font-weight:bold}}</style>\n\
+             </head><body>\n\
+             <h1>{title}</h1>\n\
+             <p>Orders {}, rejected {}, cancels {} (ratio {}), fills {}, notional {:.2}, \
+             rejects received {}, slippage {} bps.</p>\n\
+             <table><tr><th>Session</th><th>Symbol</th><th>Orders</th><th>Rejected</th>\
+             <th>Cancels</th><th>Cancel ratio</th><th>Fills</th><th>Bought</th><th>Sold</th>\
+             <th>Buy notional</th><th>Sell notional</th><th>Slippage (bps)</th></tr>\n",
+            self.total.orders,
+            self.total.rejected,
+            self.total.cancels,
+            ratio(self.total.cancel_ratio()),
+            self.total.fills,
+            self.total.notional(),
+            self.rejects(),
+            bps(self.total.slippage_bps())
+        );
+        for (session, symbol, row) in self.rows() {
+            let class = if symbol == "*" { " class=\"t\"" } else { "" };
+            let _ = writeln!(
+                out,
+                "<tr{class}><td>{}</td><td>{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td>\
+                 <td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td>\
+                 <td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{:.2}</td>\
+                 <td class=\"n\">{:.2}</td><td class=\"n\">{}</td></tr>",
+                html(session),
+                html(symbol),
+                row.orders,
+                row.rejected,
+                row.cancels,
+                ratio(row.cancel_ratio()),
+                row.fills,
+                row.bought,
+                row.sold,
+                row.buy_notional,
+                row.sell_notional,
+                bps(row.slippage_bps())
+            );
+        }
+        out.push_str("</table>\n</body></html>\n");
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let sessions: Vec<_> = self
+            .sessions
+            .iter()
+            .map(|(session, report)| {
+                let symbols: Vec<_> = report
+                    .symbols
+                    .iter()
+                    .map(|(symbol, row)| {
+                        format!(
+                            "{{\"symbol\":\"{}\",{}}}",
+                            json_escape(symbol),
+                            row_json(row)
+                        )
+                    })
+                    .collect();
+                format!(
+                    "{{\"session\":\"{}\",\"rejects\":{},{},\"symbols\":[{}]}}",
+                    json_escape(session),
+                    report.rejects,
+                    row_json(&report.total),
+                    symbols.join(",")
+                )
+            })
+            .collect();
+        format!(
+            "{{\"from\":{},\"to\":{},\"rejects\":{},{},\"sessions\":[{}]}}",
+            self.from,
+            self.to,
+            self.rejects(),
+            row_json(&self.total),
+            sessions.join(",")
+        )
+    }
+}
+
+fn row<'a>(
+    sessions: &'a mut BTreeMap<String, SessionReport>,
+    session: &str,
+    symbol: &str,
+) -> &'a mut ReportRow {
+    sessions
+        .entry(session.to_string())
+        .or_default()
+        .symbols
+        .entry(symbol.to_string())
+        .or_default()
+}
+
+fn row_json(row: &ReportRow) -> String {
+    let number = |x: Option<f64>| x.map_or("null".to_string(), |x| x.to_string());
+    format!(
+        "\"orders\":{},\"rejected\":{},\"cancels\":{},\"cancel_ratio\":{},\"fills\":{},\
+         \"bought\":{},\"sold\":{},\"buy_notional\":{},\"sell_notional\":{},\
+         \"slippage_bps\":{}",
+        row.orders,
+        row.rejected,
+        row.cancels,
+        number(row.cancel_ratio()),
+        row.fills,
+        row.bought,
+        row.sold,
+        row.buy_notional,
+        row.sell_notional,
+        number(row.slippage_bps())
+    )
+}
+
+fn ratio(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |x| format!("{x:.2}"))
+}
+
+fn bps(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |x| format!("{x:+.2}"))
+}
+
+/// Escape text for HTML content
+fn html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
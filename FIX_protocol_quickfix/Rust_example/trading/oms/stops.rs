@@ -0,0 +1,420 @@
+// =============================================================================
+// Stop-Loss / Take-Profit Orders
+// =============================================================================
+// Many counterparties only take plain market and limit orders (40=1 / 40=2).
+// Stops and take-profits are then held here, on our side, and a plain child
+// order goes out once the market reaches the trigger:
+//
+//   stop          sell: price <= trigger    buy: price >= trigger
+//   take profit   sell: price >= trigger    buy: price <= trigger
+//
+// The price watched is either the touch the child would trade against, the
+// bid for a sell and the ask for a buy (on_quote), or the last trade price
+// (on_trade: our own fills, or trades reported by market data), as each
+// instruction says. The child is a market order, or a limit order at a
+// price of its own (a stop-limit).
+//
+// An OCO (one-cancels-other) pair, typically the stop and the take-profit
+// of one position, fires at most one child: when one leg triggers, the
+// other is dropped, and cancelling either leg cancels both. What fires
+// leaves the book and is handed back to the caller, which sends the child
+// through risk and the OMS like any other order.
+//
+// Nothing is held at the venue: the instructions live in this process only,
+// and a market gapping through the trigger fills the child wherever it is.
+// =============================================================================
+
+use std::{
+    error::Error,
+    fmt,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{
+    json::json_escape,
+    oms::{OrdType, Side},
+};
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum StopError {
+    /// A quantity that is not a positive number
+    InvalidQuantity(f64),
+
+    /// A trigger or limit price that is not a positive number
+    InvalidPrice(f64),
+
+    /// No instruction with this id in the book
+    UnknownStop(String),
+}
+
+impl fmt::Display for StopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopError::InvalidQuantity(x) => write!(f, "invalid quantity {x}"),
+            StopError::InvalidPrice(x) => write!(f, "invalid price {x}"),
+            StopError::UnknownStop(id) => write!(f, "unknown stop {id}"),
+        }
+    }
+}
+
+impl Error for StopError {}
+
+// =============================================================================
+// Instructions
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerKind {
+    /// Fires when the price moves against the position
+    Stop,
+
+    /// Fires when the price reaches the target
+    TakeProfit,
+}
+
+impl TriggerKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TriggerKind::Stop => "stop",
+            TriggerKind::TakeProfit => "take_profit",
+        }
+    }
+}
+
+/// Which price is compared with the trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceSource {
+    /// Bid for a sell, ask for a buy
+    Quote,
+
+    /// Last trade price
+    Trade,
+}
+
+impl PriceSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PriceSource::Quote => "quote",
+            PriceSource::Trade => "trade",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "quote" => Some(PriceSource::Quote),
+            "trade" => Some(PriceSource::Trade),
+            _ => None,
+        }
+    }
+}
+
+/// A child order to send once a price is reached
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopInstruction {
+    pub symbol: String,
+
+    /// Side of the child order
+    pub side: Side,
+    pub quantity: f64,
+    pub kind: TriggerKind,
+    pub trigger: f64,
+
+    /// Limit price of the child; None for a market order
+    pub limit: Option<f64>,
+    pub source: PriceSource,
+}
+
+impl StopInstruction {
+    /// A stop sending a market order, watching the quotes
+    pub fn stop(symbol: &str, side: Side, quantity: f64, trigger: f64) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            kind: TriggerKind::Stop,
+            trigger,
+            limit: None,
+            source: PriceSource::Quote,
+        }
+    }
+
+    /// A take-profit sending a limit order at the target, watching the
+    /// quotes
+    pub fn take_profit(symbol: &str, side: Side, quantity: f64, target: f64) -> Self {
+        Self {
+            kind: TriggerKind::TakeProfit,
+            limit: Some(target),
+            ..Self::stop(symbol, side, quantity, target)
+        }
+    }
+
+    /// Send a limit order at this price instead (a stop-limit)
+    pub fn with_limit(mut self, limit: Option<f64>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn with_source(mut self, source: PriceSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn ord_type(&self) -> OrdType {
+        match self.limit {
+            Some(_) => OrdType::Limit,
+            None => OrdType::Market,
+        }
+    }
+
+    /// Whether a price reaches the trigger
+    pub fn is_triggered(&self, price: f64) -> bool {
+        match (self.kind, self.side) {
+            (TriggerKind::Stop, Side::Sell) | (TriggerKind::TakeProfit, Side::Buy) => {
+                price <= self.trigger
+            }
+            (TriggerKind::Stop, Side::Buy) | (TriggerKind::TakeProfit, Side::Sell) => {
+                price >= self.trigger
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), StopError> {
+        let valid = |x: f64| x.is_finite() && x > 0.0;
+        if !valid(self.quantity) {
+            return Err(StopError::InvalidQuantity(self.quantity));
+        }
+        match self.limit {
+            Some(limit) if !valid(limit) => Err(StopError::InvalidPrice(limit)),
+            _ if !valid(self.trigger) => Err(StopError::InvalidPrice(self.trigger)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// An instruction in the book
+#[derive(Debug, Clone)]
+pub struct StopEntry {
+    pub id: String,
+
+    /// The other leg of its OCO pair
+    pub oco: Option<String>,
+    pub instruction: StopInstruction,
+}
+
+impl StopEntry {
+    /// JSON object for the REST gateway
+    pub fn to_json(&self) -> String {
+        let instruction = &self.instruction;
+        let optional = |x: Option<String>| x.unwrap_or_else(|| "null".to_string());
+        format!(
+            "{{\"id\":\"{}\",\"oco\":{},\"symbol\":\"{}\",\"side\":\"{}\",\"quantity\":{},\
+             \"kind\":\"{}\",\"trigger\":{},\"limit\":{},\"source\":\"{}\"}}",
+            json_escape(&self.id),
+            optional(self.oco.as_ref().map(|x| format!("\"{}\"", json_escape(x)))),
+            json_escape(&instruction.symbol),
+            instruction.side.as_str(),
+            instruction.quantity,
+            instruction.kind.as_str(),
+            instruction.trigger,
+            optional(instruction.limit.map(|x| x.to_string())),
+            instruction.source.as_str()
+        )
+    }
+}
+
+impl fmt::Display for StopEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let instruction = &self.instruction;
+        let limit = instruction
+            .limit
+            .map_or("MKT".to_string(), |x| x.to_string());
+        write!(
+            f,
+            "{} {} {:?} {} {} {} at {} -> {}",
+            self.id,
+            instruction.kind.as_str(),
+            instruction.side,
+            instruction.symbol,
+            instruction.quantity,
+            instruction.source.as_str(),
+            instruction.trigger,
+            limit
+        )?;
+        if let Some(oco) = &self.oco {
+            write!(f, " (oco {oco})")?;
+        }
+        Ok(())
+    }
+}
+
+/// An instruction whose trigger was reached: its child is to be sent
+#[derive(Debug, Clone)]
+pub struct Triggered {
+    pub entry: StopEntry,
+
+    /// The price that reached the trigger
+    pub price: f64,
+}
+
+// =============================================================================
+// StopBook
+// =============================================================================
+
+/// Stops and take-profits waiting for their trigger
+pub struct StopBook {
+    id_prefix: String,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    entries: Vec<StopEntry>,
+    next_id: u64,
+}
+
+impl StopBook {
+    /// Ids are `PREFIX-1`, `PREFIX-2`...
+    pub fn new(id_prefix: &str) -> Self {
+        Self {
+            id_prefix: id_prefix.to_string(),
+            inner: Mutex::new(Inner {
+                entries: Vec::new(),
+                next_id: 1,
+            }),
+        }
+    }
+
+    /// Hold an instruction until its trigger
+    ///
+    /// # Returns
+    /// Its id
+    pub fn add(&self, instruction: StopInstruction) -> Result<String, StopError> {
+        instruction.validate()?;
+        let mut inner = self.lock();
+        let id = self.next_id(&mut inner);
+        inner.entries.push(StopEntry {
+            id: id.clone(),
+            oco: None,
+            instruction,
+        });
+        Ok(id)
+    }
+
+    /// Hold two instructions of which only the first to trigger fires
+    ///
+    /// # Returns
+    /// Their ids
+    pub fn add_oco(
+        &self,
+        first: StopInstruction,
+        second: StopInstruction,
+    ) -> Result<(String, String), StopError> {
+        first.validate()?;
+        second.validate()?;
+        let mut inner = self.lock();
+        let first_id = self.next_id(&mut inner);
+        let second_id = self.next_id(&mut inner);
+        inner.entries.push(StopEntry {
+            id: first_id.clone(),
+            oco: Some(second_id.clone()),
+            instruction: first,
+        });
+        inner.entries.push(StopEntry {
+            id: second_id.clone(),
+            oco: Some(first_id.clone()),
+            instruction: second,
+        });
+        Ok((first_id, second_id))
+    }
+
+    /// Drop an instruction, and the other leg of its OCO pair
+    ///
+    /// # Returns
+    /// What was dropped
+    pub fn cancel(&self, id: &str) -> Result<Vec<StopEntry>, StopError> {
+        let mut inner = self.lock();
+        let oco = inner
+            .entries
+            .iter()
+            .find(|x| x.id == id)
+            .ok_or_else(|| StopError::UnknownStop(id.to_string()))?
+            .oco
+            .clone();
+        let (cancelled, kept) = std::mem::take(&mut inner.entries)
+            .into_iter()
+            .partition(|x| x.id == id || Some(&x.id) == oco.as_ref());
+        inner.entries = kept;
+        Ok(cancelled)
+    }
+
+    /// Every instruction waiting, oldest first
+    pub fn entries(&self) -> Vec<StopEntry> {
+        self.lock().entries.clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().entries.is_empty()
+    }
+
+    /// A new top of book: fire the quote-watching instructions it triggers
+    pub fn on_quote(&self, symbol: &str, bid: f64, ask: f64) -> Vec<Triggered> {
+        self.fire(symbol, PriceSource::Quote, |side| match side {
+            Side::Buy => ask,
+            Side::Sell => bid,
+        })
+    }
+
+    /// A trade: fire the trade-watching instructions it triggers
+    pub fn on_trade(&self, symbol: &str, price: f64) -> Vec<Triggered> {
+        self.fire(symbol, PriceSource::Trade, |_| price)
+    }
+
+    fn fire(
+        &self,
+        symbol: &str,
+        source: PriceSource,
+        price: impl Fn(Side) -> f64,
+    ) -> Vec<Triggered> {
+        let mut inner = self.lock();
+        let mut triggered: Vec<Triggered> = Vec::new();
+        for entry in &inner.entries {
+            let instruction = &entry.instruction;
+            if instruction.symbol != symbol || instruction.source != source {
+                continue;
+            }
+            let price = price(instruction.side);
+            // The other leg of a pair that fired already is cancelled
+            let sibling_fired = entry
+                .oco
+                .as_ref()
+                .is_some_and(|oco| triggered.iter().any(|x| x.entry.id == *oco));
+            if instruction.is_triggered(price) && !sibling_fired {
+                triggered.push(Triggered {
+                    entry: entry.clone(),
+                    price,
+                });
+            }
+        }
+
+        inner.entries.retain(|entry| {
+            !triggered
+                .iter()
+                .any(|x| x.entry.id == entry.id || x.entry.oco.as_ref() == Some(&entry.id))
+        });
+        triggered
+    }
+
+    fn next_id(&self, inner: &mut Inner) -> String {
+        let id = format!("{}-{}", self.id_prefix, inner.next_id);
+        inner.next_id += 1;
+        id
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().expect("stop book lock poisoned")
+    }
+}
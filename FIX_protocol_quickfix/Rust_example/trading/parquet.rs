@@ -0,0 +1,449 @@
+// =============================================================================
+// Parquet Files
+// =============================================================================
+// Tables written as Parquet files that pandas, DuckDB, Spark and pyarrow
+// read, with std alone (and gzip.rs):
+//
+//   PAR1 | column chunk ... | FileMetaData | metadata length | PAR1
+//
+// One row group, one data page per column, every column nullable
+// (OPTIONAL), values PLAIN encoded after their definition levels. Pages are
+// gzip compressed (codec GZIP) or left as they are. The metadata is Thrift
+// in its compact protocol, of which only what the format needs is written.
+//
+// Sized for files of a few hundred thousand rows at most: the table is
+// built in memory, then written at once. Larger sets are several files, as
+// partitioned datasets are anyway.
+// =============================================================================
+
+use std::{error::Error, fmt};
+
+use crate::gzip::gzip;
+
+const MAGIC: &[u8] = b"PAR1";
+
+// Parquet enums (parquet.thrift)
+const TYPE_BOOLEAN: i32 = 0;
+const TYPE_INT64: i32 = 2;
+const TYPE_DOUBLE: i32 = 5;
+const TYPE_BYTE_ARRAY: i32 = 6;
+const CONVERTED_UTF8: i32 = 0;
+const CONVERTED_TIMESTAMP_MICROS: i32 = 10;
+const REPETITION_OPTIONAL: i32 = 1;
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+const PAGE_DATA: i32 = 0;
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParquetError {
+    /// A row with more or fewer values than columns: values, columns
+    Width(usize, usize),
+
+    /// A value of another type than its column: column name
+    Type(String),
+}
+
+impl fmt::Display for ParquetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParquetError::Width(values, columns) => {
+                write!(f, "{values} value(s) for {columns} column(s)")
+            }
+            ParquetError::Type(name) => write!(f, "value of the wrong type for column {name}"),
+        }
+    }
+}
+
+impl Error for ParquetError {}
+
+// =============================================================================
+// Schema and Values
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Boolean,
+    Int64,
+
+    /// Microseconds since the Unix epoch, UTC, read as a timestamp
+    Timestamp,
+    Double,
+
+    /// UTF-8 text
+    Text,
+}
+
+impl ColumnType {
+    fn physical(self) -> i32 {
+        match self {
+            ColumnType::Boolean => TYPE_BOOLEAN,
+            ColumnType::Int64 | ColumnType::Timestamp => TYPE_INT64,
+            ColumnType::Double => TYPE_DOUBLE,
+            ColumnType::Text => TYPE_BYTE_ARRAY,
+        }
+    }
+
+    fn converted(self) -> Option<i32> {
+        match self {
+            ColumnType::Timestamp => Some(CONVERTED_TIMESTAMP_MICROS),
+            ColumnType::Text => Some(CONVERTED_UTF8),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub name: String,
+    pub kind: ColumnType,
+}
+
+impl Column {
+    pub fn new(name: &str, kind: ColumnType) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+
+    /// An Int64 or a Timestamp
+    Int64(i64),
+    Double(f64),
+    Text(String),
+}
+
+impl Value {
+    fn fits(&self, kind: ColumnType) -> bool {
+        matches!(
+            (self, kind),
+            (Value::Null, _)
+                | (Value::Boolean(_), ColumnType::Boolean)
+                | (Value::Int64(_), ColumnType::Int64 | ColumnType::Timestamp)
+                | (Value::Double(_), ColumnType::Double)
+                | (Value::Text(_), ColumnType::Text)
+        )
+    }
+}
+
+/// Gzip pages or not
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    Uncompressed,
+    #[default]
+    Gzip,
+}
+
+impl Codec {
+    /// CompressionCodec of parquet.thrift
+    fn id(self) -> i32 {
+        match self {
+            Codec::Uncompressed => 0,
+            Codec::Gzip => 2,
+        }
+    }
+}
+
+// =============================================================================
+// Table
+// =============================================================================
+
+/// Rows kept by column, written out as one Parquet file
+#[derive(Debug, Clone)]
+pub struct Table {
+    columns: Vec<Column>,
+    values: Vec<Vec<Value>>,
+    rows: usize,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            values: vec![Vec::new(); columns.len()],
+            columns,
+            rows: 0,
+        }
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+
+    /// Add a row, one value per column in column order
+    pub fn push(&mut self, row: Vec<Value>) -> Result<(), ParquetError> {
+        if row.len() != self.columns.len() {
+            return Err(ParquetError::Width(row.len(), self.columns.len()));
+        }
+        if let Some(column) = self.columns.iter().zip(&row).find(|(c, v)| !v.fits(c.kind)) {
+            return Err(ParquetError::Type(column.0.name.clone()));
+        }
+        for (values, value) in self.values.iter_mut().zip(row) {
+            values.push(value);
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// The table as a Parquet file
+    pub fn to_parquet(&self, codec: Codec) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        let mut chunks = Vec::new();
+        for (column, values) in self.columns.iter().zip(&self.values) {
+            let offset = out.len();
+            let page = encode_page(column.kind, values);
+            let data = match codec {
+                Codec::Uncompressed => page.clone(),
+                Codec::Gzip => gzip(&page),
+            };
+
+            let mut header = Thrift::default();
+            header.i32(1, PAGE_DATA);
+            header.i32(2, page.len() as i32);
+            header.i32(3, data.len() as i32);
+            header.begin_struct(5);
+            header.i32(1, values.len() as i32);
+            header.i32(2, ENCODING_PLAIN);
+            header.i32(3, ENCODING_RLE);
+            header.i32(4, ENCODING_RLE);
+            header.end_struct();
+            header.stop();
+
+            out.extend_from_slice(&header.out);
+            out.extend_from_slice(&data);
+            chunks.push(Chunk {
+                offset: offset as i64,
+                uncompressed: (header.out.len() + page.len()) as i64,
+                compressed: (header.out.len() + data.len()) as i64,
+            });
+        }
+
+        let metadata = self.metadata(&chunks, codec);
+        out.extend_from_slice(&metadata);
+        out.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+        out.extend_from_slice(MAGIC);
+        out
+    }
+
+    /// FileMetaData: the schema, and where each column chunk is
+    fn metadata(&self, chunks: &[Chunk], codec: Codec) -> Vec<u8> {
+        let mut meta = Thrift::default();
+        meta.i32(1, 1); // version
+        meta.list(2, TYPE_STRUCT, self.columns.len() + 1);
+        meta.begin_element();
+        meta.binary(4, b"schema");
+        meta.i32(5, self.columns.len() as i32);
+        meta.end_struct();
+        for column in &self.columns {
+            meta.begin_element();
+            meta.i32(1, column.kind.physical());
+            meta.i32(3, REPETITION_OPTIONAL);
+            meta.binary(4, column.name.as_bytes());
+            if let Some(converted) = column.kind.converted() {
+                meta.i32(6, converted);
+            }
+            meta.end_struct();
+        }
+        meta.i64(3, self.rows as i64);
+
+        meta.list(4, TYPE_STRUCT, 1);
+        meta.begin_element();
+        meta.list(1, TYPE_STRUCT, self.columns.len());
+        for ((column, chunk), values) in self.columns.iter().zip(chunks).zip(&self.values) {
+            meta.begin_element();
+            meta.i64(2, chunk.offset);
+            meta.begin_struct(3);
+            meta.i32(1, column.kind.physical());
+            meta.list(2, TYPE_I32, 2);
+            meta.element_i32(ENCODING_PLAIN);
+            meta.element_i32(ENCODING_RLE);
+            meta.list(3, TYPE_BINARY, 1);
+            meta.element_binary(column.name.as_bytes());
+            meta.i32(4, codec.id());
+            meta.i64(5, values.len() as i64);
+            meta.i64(6, chunk.uncompressed);
+            meta.i64(7, chunk.compressed);
+            meta.i64(9, chunk.offset);
+            meta.end_struct();
+            meta.end_struct();
+        }
+        meta.i64(2, chunks.iter().map(|x| x.uncompressed).sum());
+        meta.i64(3, self.rows as i64);
+        meta.end_struct();
+
+        meta.binary(6, b"trading");
+        meta.stop();
+        meta.out
+    }
+}
+
+/// Where a column chunk was written
+struct Chunk {
+    offset: i64,
+    uncompressed: i64,
+    compressed: i64,
+}
+
+/// Definition levels (RLE / bit-packed hybrid, bit-packed runs only), then
+/// the non-null values, PLAIN
+fn encode_page(kind: ColumnType, values: &[Value]) -> Vec<u8> {
+    let defined: Vec<bool> = values.iter().map(|x| *x != Value::Null).collect();
+    let mut levels = Vec::new();
+    write_varint(&mut levels, ((defined.len().div_ceil(8) as u64) << 1) | 1);
+    levels.extend(pack_bits(&defined));
+
+    let mut page = (levels.len() as u32).to_le_bytes().to_vec();
+    page.extend(levels);
+    if kind == ColumnType::Boolean {
+        let bits: Vec<bool> = values
+            .iter()
+            .filter_map(|x| match x {
+                Value::Boolean(x) => Some(*x),
+                _ => None,
+            })
+            .collect();
+        page.extend(pack_bits(&bits));
+        return page;
+    }
+    for value in values {
+        match value {
+            Value::Int64(x) => page.extend(x.to_le_bytes()),
+            Value::Double(x) => page.extend(x.to_le_bytes()),
+            Value::Text(x) => {
+                page.extend((x.len() as u32).to_le_bytes());
+                page.extend(x.as_bytes());
+            }
+            Value::Null | Value::Boolean(_) => {}
+        }
+    }
+    page
+}
+
+/// Least significant bit first, the last byte padded with zeros
+fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (i, &bit)| byte | (u8::from(bit) << i))
+        })
+        .collect()
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+// =============================================================================
+// Thrift Compact Protocol
+// =============================================================================
+
+const TYPE_I32: u8 = 5;
+const TYPE_I64: u8 = 6;
+const TYPE_BINARY: u8 = 8;
+const TYPE_LIST: u8 = 9;
+const TYPE_STRUCT: u8 = 12;
+
+/// Writer of Thrift structs, fields given in increasing id order
+#[derive(Default)]
+struct Thrift {
+    out: Vec<u8>,
+
+    /// Last field id of the struct being written, and of the enclosing ones
+    last: i16,
+    enclosing: Vec<i16>,
+}
+
+impl Thrift {
+    fn field(&mut self, id: i16, kind: u8) {
+        let delta = id - self.last;
+        if (1..=15).contains(&delta) {
+            self.out.push((delta as u8) << 4 | kind);
+        } else {
+            self.out.push(kind);
+            write_varint(&mut self.out, zigzag(i64::from(id)));
+        }
+        self.last = id;
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, TYPE_I32);
+        write_varint(&mut self.out, zigzag(i64::from(value)));
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, TYPE_I64);
+        write_varint(&mut self.out, zigzag(value));
+    }
+
+    fn binary(&mut self, id: i16, value: &[u8]) {
+        self.field(id, TYPE_BINARY);
+        self.element_binary(value);
+    }
+
+    fn begin_struct(&mut self, id: i16) {
+        self.field(id, TYPE_STRUCT);
+        self.begin_element();
+    }
+
+    /// A struct in a list
+    fn begin_element(&mut self) {
+        self.enclosing.push(self.last);
+        self.last = 0;
+    }
+
+    fn end_struct(&mut self) {
+        self.stop();
+        self.last = self.enclosing.pop().unwrap_or_default();
+    }
+
+    /// End of the outermost struct
+    fn stop(&mut self) {
+        self.out.push(0);
+    }
+
+    fn list(&mut self, id: i16, kind: u8, size: usize) {
+        self.field(id, TYPE_LIST);
+        if size < 15 {
+            self.out.push((size as u8) << 4 | kind);
+        } else {
+            self.out.push(0xf0 | kind);
+            write_varint(&mut self.out, size as u64);
+        }
+    }
+
+    fn element_i32(&mut self, value: i32) {
+        write_varint(&mut self.out, zigzag(i64::from(value)));
+    }
+
+    fn element_binary(&mut self, value: &[u8]) {
+        write_varint(&mut self.out, value.len() as u64);
+        self.out.extend_from_slice(value);
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
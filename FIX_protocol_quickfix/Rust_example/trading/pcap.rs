@@ -0,0 +1,654 @@
+// =============================================================================
+// Packet Captures
+// =============================================================================
+// FIX messages taken from a packet capture: what actually went over the
+// wire, to set against what the engine logged. Std only, so a capture is
+// read from a file or, live, from tcpdump on a pipe:
+//
+//   tcpdump -i eth0 -w - 'tcp port 5001' | fixtail --pcap -
+//
+// Three layers, each usable on its own:
+//
+//   PcapReader   frames of a pcap (microsecond or nanosecond, either byte
+//                order) or pcapng capture
+//   tcp_segment  the TCP segment of a frame: Ethernet (VLAN tags skipped),
+//                Linux cooked (SLL, SLL2), loopback or raw IP, IPv4 or IPv6
+//   FixStreams   each direction of each connection put back in order, and
+//                the FIX messages cut out of it by BodyLength (9) and
+//                CheckSum (10)
+//
+// Segments seen twice (retransmissions) are trimmed to what is new; those
+// ahead of a missing one wait for it, up to MAX_PENDING, after which the
+// stream jumps the hole and picks up again at the next BeginString. The
+// capture may start in the middle of a connection: its stream starts at the
+// first segment seen. IP fragments are not reassembled.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    io::{self, Read},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+/// Segments held ahead of a hole in a stream before giving up on it
+pub const MAX_PENDING: usize = 1_024;
+
+/// Largest frame accepted, against a corrupt length
+const MAX_FRAME: usize = 256 * 1024;
+
+/// A BodyLength beyond this is taken for garbage
+const MAX_BODY: usize = 1024 * 1024;
+
+/// pcapng block types
+const PCAPNG_SECTION: u32 = 0x0A0D_0D0A;
+const PCAPNG_INTERFACE: u32 = 1;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PcapError {
+    Io(io::Error),
+
+    /// Neither a pcap nor a pcapng file
+    NotPcap,
+
+    /// The capture ends within a header or a frame
+    Truncated,
+
+    /// A record or block length that cannot be right
+    Corrupt(String),
+}
+
+impl fmt::Display for PcapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PcapError::Io(err) => write!(f, "{err}"),
+            PcapError::NotPcap => write!(f, "not a pcap or pcapng capture"),
+            PcapError::Truncated => write!(f, "capture truncated"),
+            PcapError::Corrupt(what) => write!(f, "corrupt capture: {what}"),
+        }
+    }
+}
+
+impl Error for PcapError {}
+
+impl From<io::Error> for PcapError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => PcapError::Truncated,
+            _ => PcapError::Io(err),
+        }
+    }
+}
+
+// =============================================================================
+// Capture Files
+// =============================================================================
+
+/// One captured frame
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Unix nanoseconds
+    pub time: i64,
+
+    /// LINKTYPE_ of the interface (1 Ethernet, 113 Linux cooked, ...)
+    pub link_type: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+enum Format {
+    Pcap {
+        big_endian: bool,
+        nanos: bool,
+        link_type: u32,
+    },
+    Pcapng {
+        big_endian: bool,
+
+        /// Link type and timestamp units per second of each interface
+        interfaces: Vec<(u32, u64)>,
+    },
+}
+
+/// Reads the frames of a capture, in file order
+pub struct PcapReader<R> {
+    reader: R,
+    format: Format,
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Read the file header
+    pub fn new(mut reader: R) -> Result<Self, PcapError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        let format = match u32::from_le_bytes(magic) {
+            0xA1B2_C3D4 | 0xD4C3_B2A1 | 0xA1B2_3C4D | 0x4D3C_B2A1 => {
+                let little = u32::from_le_bytes(magic);
+                let big_endian = matches!(little, 0xD4C3_B2A1 | 0x4D3C_B2A1);
+                let nanos = matches!(little, 0xA1B2_3C4D | 0x4D3C_B2A1);
+                // version (2 x u16), thiszone, sigfigs, snaplen, then the link
+                // type
+                let mut header = [0u8; 20];
+                reader.read_exact(&mut header)?;
+                Format::Pcap {
+                    big_endian,
+                    nanos,
+                    link_type: u32_at(&header, 16, big_endian),
+                }
+            }
+            PCAPNG_SECTION => {
+                let big_endian = Self::section_header(&mut reader)?;
+                Format::Pcapng {
+                    big_endian,
+                    interfaces: Vec::new(),
+                }
+            }
+            _ => return Err(PcapError::NotPcap),
+        };
+        Ok(Self { reader, format })
+    }
+
+    /// The rest of a pcapng Section Header Block, its type read
+    ///
+    /// # Returns
+    /// Whether the section is big-endian
+    fn section_header(reader: &mut R) -> Result<bool, PcapError> {
+        let mut head = [0u8; 8];
+        reader.read_exact(&mut head)?;
+        let big_endian = match u32::from_le_bytes([head[4], head[5], head[6], head[7]]) {
+            0x1A2B_3C4D => false,
+            0x4D3C_2B1A => true,
+            _ => return Err(PcapError::NotPcap),
+        };
+        let length = u32_at(&head, 0, big_endian) as usize;
+        if !(28..=MAX_FRAME).contains(&length) {
+            return Err(PcapError::Corrupt(format!("section of {length} bytes")));
+        }
+        let mut rest = vec![0u8; length - 12];
+        reader.read_exact(&mut rest)?;
+        Ok(big_endian)
+    }
+
+    /// The next frame; None at the end of the capture
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, PcapError> {
+        match self.format {
+            Format::Pcap {
+                big_endian,
+                nanos,
+                link_type,
+            } => {
+                let mut header = [0u8; 16];
+                if !read_or_end(&mut self.reader, &mut header)? {
+                    return Ok(None);
+                }
+                let seconds = i64::from(u32_at(&header, 0, big_endian));
+                let fraction = i64::from(u32_at(&header, 4, big_endian));
+                let length = u32_at(&header, 8, big_endian) as usize;
+                if length > MAX_FRAME {
+                    return Err(PcapError::Corrupt(format!("frame of {length} bytes")));
+                }
+                let mut data = vec![0u8; length];
+                self.reader.read_exact(&mut data)?;
+                let fraction = if nanos { fraction } else { fraction * 1_000 };
+                Ok(Some(Frame {
+                    time: seconds * 1_000_000_000 + fraction,
+                    link_type,
+                    data,
+                }))
+            }
+            Format::Pcapng { .. } => self.next_block(),
+        }
+    }
+
+    /// Blocks up to the next packet, interfaces and sections taken in
+    fn next_block(&mut self) -> Result<Option<Frame>, PcapError> {
+        loop {
+            let Format::Pcapng { big_endian, .. } = self.format else {
+                unreachable!("pcapng blocks in a pcap file");
+            };
+            let mut head = [0u8; 4];
+            if !read_or_end(&mut self.reader, &mut head)? {
+                return Ok(None);
+            }
+            if u32::from_le_bytes(head) == PCAPNG_SECTION {
+                let big_endian = Self::section_header(&mut self.reader)?;
+                self.format = Format::Pcapng {
+                    big_endian,
+                    interfaces: Vec::new(),
+                };
+                continue;
+            }
+
+            let kind = u32_at(&head, 0, big_endian);
+            let mut length = [0u8; 4];
+            self.reader.read_exact(&mut length)?;
+            let length = u32_at(&length, 0, big_endian) as usize;
+            if !(12..=MAX_FRAME).contains(&length) || !length.is_multiple_of(4) {
+                return Err(PcapError::Corrupt(format!("block of {length} bytes")));
+            }
+            let mut body = vec![0u8; length - 8];
+            self.reader.read_exact(&mut body)?;
+            body.truncate(length - 12);
+
+            let Format::Pcapng { interfaces, .. } = &mut self.format else {
+                unreachable!("pcapng blocks in a pcap file");
+            };
+            match kind {
+                PCAPNG_INTERFACE if body.len() >= 8 => {
+                    let link_type = u32::from(u16_at(&body, 0, big_endian));
+                    interfaces.push((link_type, units_per_second(&body[8..], big_endian)));
+                }
+                PCAPNG_ENHANCED_PACKET if body.len() >= 20 => {
+                    let interface = u32_at(&body, 0, big_endian) as usize;
+                    let &(link_type, units) = interfaces.get(interface).ok_or_else(|| {
+                        PcapError::Corrupt(format!("packet of unknown interface {interface}"))
+                    })?;
+                    let stamp = u64::from(u32_at(&body, 4, big_endian)) << 32
+                        | u64::from(u32_at(&body, 8, big_endian));
+                    let captured = (u32_at(&body, 12, big_endian) as usize).min(body.len() - 20);
+                    let nanos = u128::from(stamp) * 1_000_000_000 / u128::from(units);
+                    return Ok(Some(Frame {
+                        time: nanos as i64,
+                        link_type,
+                        data: body[20..20 + captured].to_vec(),
+                    }));
+                }
+                PCAPNG_SIMPLE_PACKET if body.len() >= 4 => {
+                    let link_type = interfaces.first().map_or(1, |x| x.0);
+                    let captured = (u32_at(&body, 0, big_endian) as usize).min(body.len() - 4);
+                    return Ok(Some(Frame {
+                        time: 0,
+                        link_type,
+                        data: body[4..4 + captured].to_vec(),
+                    }));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Fill `buffer`, or report a clean end of file before its first byte
+fn read_or_end(reader: &mut impl Read, buffer: &mut [u8]) -> Result<bool, PcapError> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(PcapError::Truncated),
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(true)
+}
+
+/// if_tsresol (9) of an Interface Description Block's options; microseconds
+/// without it
+fn units_per_second(mut options: &[u8], big_endian: bool) -> u64 {
+    while options.len() >= 4 {
+        let code = u16_at(options, 0, big_endian);
+        let length = usize::from(u16_at(options, 2, big_endian));
+        let value = options.get(4..4 + length).unwrap_or_default();
+        match (code, value.first()) {
+            (0, _) => break,
+            (9, Some(&resolution)) if resolution & 0x80 == 0 => {
+                return 10u64
+                    .checked_pow(u32::from(resolution))
+                    .unwrap_or(1_000_000);
+            }
+            (9, Some(&resolution)) => {
+                return 1u64
+                    .checked_shl(u32::from(resolution & 0x7F))
+                    .unwrap_or(1_000_000);
+            }
+            _ => {}
+        }
+        options = options
+            .get(4 + length.next_multiple_of(4)..)
+            .unwrap_or_default();
+    }
+    1_000_000
+}
+
+fn u16_at(data: &[u8], at: usize, big_endian: bool) -> u16 {
+    let bytes = [data[at], data[at + 1]];
+    if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    }
+}
+
+fn u32_at(data: &[u8], at: usize, big_endian: bool) -> u32 {
+    let bytes = [data[at], data[at + 1], data[at + 2], data[at + 3]];
+    if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    }
+}
+
+// =============================================================================
+// TCP Segments
+// =============================================================================
+
+/// The TCP part of a frame
+#[derive(Debug, Clone)]
+pub struct Segment {
+    /// Unix nanoseconds
+    pub time: i64,
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub seq: u32,
+    pub syn: bool,
+    pub fin: bool,
+    pub rst: bool,
+    pub payload: Vec<u8>,
+}
+
+/// The TCP segment a frame carries; None for anything else (ARP, UDP, IP
+/// fragments, unknown link types)
+pub fn tcp_segment(frame: &Frame) -> Option<Segment> {
+    let data = frame.data.as_slice();
+    let ip = match frame.link_type {
+        // Ethernet, 802.1Q / 802.1ad tags skipped
+        1 => {
+            let mut at = 12;
+            while matches!(be16(data, at)?, 0x8100 | 0x88A8) {
+                at += 4;
+            }
+            data.get(at + 2..)?
+        }
+        // Linux cooked capture v1, v2
+        113 => data.get(16..)?,
+        276 => data.get(20..)?,
+        // BSD loopback: the address family, in host or network order
+        0 | 108 => data.get(4..)?,
+        // Raw IP
+        12 | 14 | 101 => data,
+        _ => return None,
+    };
+
+    let (src, dst, tcp) = match ip.first()? >> 4 {
+        4 => {
+            let header = usize::from(ip[0] & 0x0F) * 4;
+            let total = usize::from(be16(ip, 2)?);
+            let fragment = be16(ip, 6)? & 0x1FFF;
+            if ip.get(9)? != &6 || fragment != 0 {
+                return None;
+            }
+            let src = Ipv4Addr::from(<[u8; 4]>::try_from(ip.get(12..16)?).ok()?);
+            let dst = Ipv4Addr::from(<[u8; 4]>::try_from(ip.get(16..20)?).ok()?);
+            let tcp = ip.get(header..total.min(ip.len()))?;
+            (IpAddr::V4(src), IpAddr::V4(dst), tcp)
+        }
+        6 => {
+            let payload = usize::from(be16(ip, 4)?);
+            if ip.get(6)? != &6 {
+                return None;
+            }
+            let src = Ipv6Addr::from(<[u8; 16]>::try_from(ip.get(8..24)?).ok()?);
+            let dst = Ipv6Addr::from(<[u8; 16]>::try_from(ip.get(24..40)?).ok()?);
+            let tcp = ip.get(40..(40 + payload).min(ip.len()))?;
+            (IpAddr::V6(src), IpAddr::V6(dst), tcp)
+        }
+        _ => return None,
+    };
+
+    let header = usize::from(tcp.get(12)? >> 4) * 4;
+    let flags = *tcp.get(13)?;
+    Some(Segment {
+        time: frame.time,
+        src: SocketAddr::new(src, be16(tcp, 0)?),
+        dst: SocketAddr::new(dst, be16(tcp, 2)?),
+        seq: u32::from_be_bytes(tcp.get(4..8)?.try_into().ok()?),
+        syn: flags & 0x02 != 0,
+        fin: flags & 0x01 != 0,
+        rst: flags & 0x04 != 0,
+        payload: tcp.get(header..)?.to_vec(),
+    })
+}
+
+fn be16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*data.get(at)?, *data.get(at + 1)?]))
+}
+
+// =============================================================================
+// Streams
+// =============================================================================
+
+/// A FIX message as sent on the wire
+#[derive(Debug, Clone)]
+pub struct WireMessage {
+    /// Unix nanoseconds of the segment that completed it
+    pub time: i64,
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+
+    /// From BeginString (8) to CheckSum (10), SOH included
+    pub raw: Vec<u8>,
+}
+
+/// One direction of a connection
+#[derive(Debug, Default)]
+struct Stream {
+    /// Sequence number of the next byte
+    next_seq: u32,
+
+    /// Segments ahead of a hole
+    pending: Vec<(u32, Vec<u8>)>,
+
+    /// Bytes in order, not yet a whole message
+    buffer: Vec<u8>,
+}
+
+impl Stream {
+    /// Take the new part of a segment in, then what was waiting for it
+    ///
+    /// # Returns
+    /// Whether a hole was jumped
+    fn push(&mut self, seq: u32, payload: &[u8]) -> bool {
+        self.pending.push((seq, payload.to_vec()));
+        let mut jumped = false;
+        loop {
+            // Segments at or before the next byte: their new part goes on
+            let ready = self
+                .pending
+                .iter()
+                .position(|(seq, _)| (seq.wrapping_sub(self.next_seq) as i32) <= 0);
+            if let Some(index) = ready {
+                let (seq, payload) = self.pending.swap_remove(index);
+                let seen = self.next_seq.wrapping_sub(seq) as usize;
+                if let Some(new) = payload.get(seen..) {
+                    self.buffer.extend_from_slice(new);
+                    self.next_seq = self.next_seq.wrapping_add(new.len() as u32);
+                }
+                continue;
+            }
+            if self.pending.len() <= MAX_PENDING {
+                return jumped;
+            }
+
+            // Too long a wait: resume at the first segment after the hole,
+            // the partial message before it is lost
+            let first = self
+                .pending
+                .iter()
+                .map(|(seq, _)| *seq)
+                .min_by_key(|seq| seq.wrapping_sub(self.next_seq))
+                .unwrap_or(self.next_seq);
+            self.next_seq = first;
+            self.buffer.clear();
+            jumped = true;
+        }
+    }
+
+    /// Cut the whole messages out of the buffer
+    fn messages(&mut self) -> (Vec<Vec<u8>>, u64) {
+        let mut messages = Vec::new();
+        let mut garbled = 0;
+        loop {
+            let Some(start) = find(&self.buffer, b"8=FIX") else {
+                // Keep what could be the start of a BeginString
+                let keep = self.buffer.len().min(4);
+                self.buffer.drain(..self.buffer.len() - keep);
+                break;
+            };
+            self.buffer.drain(..start);
+            match frame_length(&self.buffer) {
+                Framing::Complete(length) => {
+                    messages.push(self.buffer.drain(..length).collect());
+                }
+                Framing::Incomplete => break,
+                Framing::Garbled => {
+                    // Look for the next BeginString
+                    self.buffer.drain(..1);
+                    garbled += 1;
+                }
+            }
+        }
+        (messages, garbled)
+    }
+}
+
+/// What the start of a buffer holds
+enum Framing {
+    Complete(usize),
+    Incomplete,
+    Garbled,
+}
+
+/// Length of the message at the start of `data`, from its BodyLength
+fn frame_length(data: &[u8]) -> Framing {
+    let Some(first) = data.iter().position(|x| *x == 0x01) else {
+        return if data.len() > 32 {
+            Framing::Garbled
+        } else {
+            Framing::Incomplete
+        };
+    };
+    let rest = &data[first + 1..];
+    if rest.len() < 2 {
+        return Framing::Incomplete;
+    }
+    if !rest.starts_with(b"9=") {
+        return Framing::Garbled;
+    }
+    let Some(second) = rest.iter().position(|x| *x == 0x01) else {
+        return if rest.len() > 12 {
+            Framing::Garbled
+        } else {
+            Framing::Incomplete
+        };
+    };
+    let body = std::str::from_utf8(&rest[2..second])
+        .ok()
+        .and_then(|x| x.parse::<usize>().ok())
+        .filter(|x| *x <= MAX_BODY);
+    let Some(body) = body else {
+        return Framing::Garbled;
+    };
+
+    // CheckSum: 10=NNN<SOH>
+    let trailer = first + 1 + second + 1 + body;
+    let length = trailer + 7;
+    match data.get(trailer..length) {
+        None => Framing::Incomplete,
+        Some(x) if x.starts_with(b"10=") && x[6] == 0x01 => Framing::Complete(length),
+        Some(_) => Framing::Garbled,
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|x| x == needle)
+}
+
+/// The FIX messages of the TCP connections of a capture
+#[derive(Debug, Default)]
+pub struct FixStreams {
+    /// Connections with one end on these ports; every one when empty
+    ports: Vec<u16>,
+    streams: HashMap<(SocketAddr, SocketAddr), Stream>,
+    gaps: u64,
+    garbled: u64,
+}
+
+impl FixStreams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only the connections with one end on these ports (the FIX ports of
+    /// the sessions)
+    pub fn with_ports(mut self, ports: &[u16]) -> Self {
+        self.ports = ports.to_vec();
+        self
+    }
+
+    /// Holes jumped so far, in any stream
+    pub fn gaps(&self) -> u64 {
+        self.gaps
+    }
+
+    /// Bytes skipped looking for a BeginString after something that was not
+    /// a whole message
+    pub fn garbled(&self) -> u64 {
+        self.garbled
+    }
+
+    /// Take a segment in
+    ///
+    /// # Returns
+    /// The messages it completed, in stream order
+    pub fn on_segment(&mut self, segment: &Segment) -> Vec<WireMessage> {
+        let ports = [segment.src.port(), segment.dst.port()];
+        if !self.ports.is_empty() && !ports.iter().any(|x| self.ports.contains(x)) {
+            return Vec::new();
+        }
+        let key = (segment.src, segment.dst);
+        if segment.rst {
+            self.streams.remove(&key);
+            return Vec::new();
+        }
+        if segment.syn {
+            let stream = Stream {
+                next_seq: segment.seq.wrapping_add(1),
+                ..Stream::default()
+            };
+            self.streams.insert(key, stream);
+            return Vec::new();
+        }
+
+        let stream = self.streams.entry(key).or_insert_with(|| Stream {
+            next_seq: segment.seq,
+            ..Stream::default()
+        });
+        if !segment.payload.is_empty() && stream.push(segment.seq, &segment.payload) {
+            self.gaps += 1;
+        }
+        let (messages, garbled) = stream.messages();
+        self.garbled += garbled;
+        if segment.fin {
+            self.streams.remove(&key);
+        }
+
+        messages
+            .into_iter()
+            .map(|raw| WireMessage {
+                time: segment.time,
+                src: segment.src,
+                dst: segment.dst,
+                raw,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,555 @@
+// =============================================================================
+// Quote Engine (35=R / 35=S)
+// =============================================================================
+// Request-for-quote market making: a counterparty asks for a price with a
+// QuoteRequest (35=R) and gets a two-way Quote (35=S) back, built around a
+// reference price with a spread in basis points:
+//
+//   bid   = reference * (1 - spread / 2)    rounded down to the tick
+//   offer = reference * (1 + spread / 2)    rounded up to the tick
+//
+// The reference is the caller's (the sell_side takes the mid of its book,
+// else the last trade), or one set per symbol here. With neither, the
+// request gets a QuoteRequestReject (35=AG). A request to buy (54=1) gets
+// an offer only, one to sell a bid only; the size is the OrderQty (38)
+// asked, or the default size.
+//
+// A quote is live until its ValidUntilTime (62), until the next quote to
+// the same counterparty for the same symbol replaces it, or until a
+// QuoteCancel (35=Z) from that counterparty withdraws it:
+//
+//   298=1  its quotes for the Symbol (55)
+//   298=4  all its quotes
+//   298=5  the quote with this QuoteID (117)
+//
+// Cancels are confirmed, and QuoteStatusRequests (35=a) answered, with one
+// QuoteStatusReport (35=AI) per quote, QuoteStatus (297) saying what became
+// of it. Quotes are indicative: nothing here trades on them.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    time::Duration,
+};
+
+use quickfix::{FieldMap, Group, Message, QuickFixError};
+
+use crate::{
+    json::json_escape,
+    sim::{matching::Side, venue::VenueProfile},
+    time::{unix_millis, utc_timestamp},
+};
+
+// =============================================================================
+// Settings
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct QuoteSettings {
+    /// Offer minus bid, in basis points of the reference
+    pub spread_bps: f64,
+
+    /// Size quoted when the request has no OrderQty
+    pub default_size: u64,
+
+    /// How long a quote stays live
+    pub valid_for: Duration,
+
+    /// Prices are rounded to this increment
+    pub tick_size: f64,
+}
+
+impl Default for QuoteSettings {
+    fn default() -> Self {
+        Self {
+            spread_bps: 10.0,
+            default_size: 100,
+            valid_for: Duration::from_secs(30),
+            tick_size: 0.01,
+        }
+    }
+}
+
+impl QuoteSettings {
+    /// Default settings on a venue's tick, a lot as the default size
+    pub fn for_venue(profile: &VenueProfile) -> Self {
+        Self {
+            tick_size: profile.tick_size,
+            default_size: profile.lot_size.max(Self::default().default_size),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_spread_bps(mut self, spread_bps: f64) -> Self {
+        self.spread_bps = spread_bps;
+        self
+    }
+
+    pub fn with_default_size(mut self, size: u64) -> Self {
+        self.default_size = size;
+        self
+    }
+
+    pub fn with_valid_for(mut self, valid_for: Duration) -> Self {
+        self.valid_for = valid_for;
+        self
+    }
+}
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum QuoteError {
+    /// No reference price for the symbol
+    NoReference(String),
+
+    /// A size of zero
+    InvalidQuantity(u64),
+}
+
+impl QuoteError {
+    /// QuoteRequestRejectReason (658)
+    pub fn reject_reason(&self) -> &'static str {
+        match self {
+            QuoteError::NoReference(_) => "1",      // unknown symbol
+            QuoteError::InvalidQuantity(_) => "99", // other
+        }
+    }
+}
+
+impl fmt::Display for QuoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuoteError::NoReference(symbol) => write!(f, "no reference price for {symbol}"),
+            QuoteError::InvalidQuantity(x) => write!(f, "invalid quantity {x}"),
+        }
+    }
+}
+
+impl Error for QuoteError {}
+
+// =============================================================================
+// Requests
+// =============================================================================
+
+/// A QuoteRequest (35=R), first instrument of its NoRelatedSym (146) group
+#[derive(Debug, Clone)]
+pub struct QuoteRequest {
+    pub quote_req_id: String,
+    pub symbol: String,
+
+    /// None for a two-way quote
+    pub side: Option<Side>,
+    pub quantity: Option<u64>,
+}
+
+impl QuoteRequest {
+    /// None without QuoteReqID (131) or Symbol (55)
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        // Fields are looked up in the group, then at the top level, where
+        // some clients put them
+        let group = msg.clone_group(1, 146);
+        let field = |tag| {
+            group
+                .as_ref()
+                .and_then(|x| x.get_field(tag))
+                .or_else(|| msg.get_field(tag))
+        };
+        Some(Self {
+            quote_req_id: msg.get_field(131)?,
+            symbol: field(55)?,
+            side: field(54).as_deref().and_then(Side::from_fix),
+            quantity: field(38).and_then(|x| x.parse().ok()),
+        })
+    }
+}
+
+/// What a QuoteCancel (35=Z) withdraws, from QuoteCancelType (298)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuoteCancelScope {
+    Symbol(String),
+    All,
+    QuoteId(String),
+}
+
+impl QuoteCancelScope {
+    /// None for a cancel type or a message this engine does not handle
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        let symbol = msg
+            .clone_group(1, 295)
+            .and_then(|x| x.get_field(55))
+            .or_else(|| msg.get_field(55));
+        match msg.get_field(298)?.as_str() {
+            "1" => symbol.map(QuoteCancelScope::Symbol),
+            "4" => Some(QuoteCancelScope::All),
+            "5" => msg.get_field(117).map(QuoteCancelScope::QuoteId),
+            _ => None,
+        }
+    }
+}
+
+/// A QuoteStatusRequest (35=a): by QuoteID, by Symbol, or every quote
+#[derive(Debug, Clone, Default)]
+pub struct QuoteStatusQuery {
+    /// QuoteStatusReqID (649), echoed in the reports
+    pub status_req_id: Option<String>,
+    pub quote_id: Option<String>,
+    pub symbol: Option<String>,
+}
+
+impl QuoteStatusQuery {
+    pub fn from_message(msg: &Message) -> Self {
+        Self {
+            status_req_id: msg.get_field(649),
+            quote_id: msg.get_field(117),
+            symbol: msg.get_field(55),
+        }
+    }
+}
+
+// =============================================================================
+// Quotes
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveQuote {
+    pub quote_id: String,
+    pub quote_req_id: String,
+
+    /// CompID of the counterparty it was given to
+    pub owner: String,
+    pub symbol: String,
+
+    /// (price, size); None on a one-sided quote
+    pub bid: Option<(f64, u64)>,
+    pub offer: Option<(f64, u64)>,
+
+    /// Unix milliseconds
+    pub valid_until: i64,
+}
+
+impl LiveQuote {
+    pub fn is_expired(&self) -> bool {
+        unix_millis() >= self.valid_until
+    }
+
+    /// JSON object for the admin API
+    pub fn to_json(&self) -> String {
+        let side = |x: Option<(f64, u64)>| match x {
+            Some((px, qty)) => format!("{{\"px\":{px},\"qty\":{qty}}}"),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"quote_id\":\"{}\",\"quote_req_id\":\"{}\",\"owner\":\"{}\",\
+             \"symbol\":\"{}\",\"bid\":{},\"offer\":{},\"valid_until\":\"{}\"}}",
+            json_escape(&self.quote_id),
+            json_escape(&self.quote_req_id),
+            json_escape(&self.owner),
+            json_escape(&self.symbol),
+            side(self.bid),
+            side(self.offer),
+            utc_timestamp(self.valid_until)
+        )
+    }
+}
+
+impl fmt::Display for LiveQuote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.quote_id, self.symbol)?;
+        if let Some((price, size)) = self.bid {
+            write!(f, " bid {size}@{price}")?;
+        }
+        if let Some((price, size)) = self.offer {
+            write!(f, " offer {size}@{price}")?;
+        }
+        write!(f, " for {}", self.owner)
+    }
+}
+
+/// QuoteStatus (297) reported for a quote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteStatus {
+    Accepted,
+    CanceledForSymbol,
+    CanceledAll,
+    RemovedFromMarket,
+    Expired,
+    NotFound,
+}
+
+impl QuoteStatus {
+    pub fn as_fix(self) -> &'static str {
+        match self {
+            QuoteStatus::Accepted => "0",
+            QuoteStatus::CanceledForSymbol => "1",
+            QuoteStatus::CanceledAll => "4",
+            QuoteStatus::RemovedFromMarket => "6",
+            QuoteStatus::Expired => "7",
+            QuoteStatus::NotFound => "9",
+        }
+    }
+}
+
+/// One QuoteStatusReport (35=AI) to send
+#[derive(Debug, Clone)]
+pub struct QuoteStatusReport {
+    pub status: QuoteStatus,
+    pub quote_id: String,
+    pub symbol: Option<String>,
+
+    /// The quote as it was; None when not found
+    pub quote: Option<LiveQuote>,
+    pub status_req_id: Option<String>,
+}
+
+impl QuoteStatusReport {
+    fn of(quote: LiveQuote, status: QuoteStatus) -> Self {
+        Self {
+            status,
+            quote_id: quote.quote_id.clone(),
+            symbol: Some(quote.symbol.clone()),
+            quote: Some(quote),
+            status_req_id: None,
+        }
+    }
+}
+
+// =============================================================================
+// QuoteEngine
+// =============================================================================
+
+pub struct QuoteEngine {
+    settings: QuoteSettings,
+
+    /// Reference prices used when the caller has none, by symbol
+    references: HashMap<String, f64>,
+    quotes: Vec<LiveQuote>,
+    next_id: u64,
+}
+
+impl QuoteEngine {
+    pub fn new(settings: QuoteSettings) -> Self {
+        Self {
+            settings,
+            references: HashMap::new(),
+            quotes: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Quote a symbol around this price when the caller has no reference
+    pub fn with_reference(mut self, symbol: &str, price: f64) -> Self {
+        self.references.insert(symbol.to_string(), price);
+        self
+    }
+
+    pub fn settings(&self) -> &QuoteSettings {
+        &self.settings
+    }
+
+    /// Price a request; the quote replaces the owner's live quote for the
+    /// symbol, if any
+    ///
+    /// # Arguments
+    /// * `owner` - CompID of the counterparty asking
+    /// * `reference` - Reference price of the caller, if it has one
+    pub fn quote(
+        &mut self,
+        request: &QuoteRequest,
+        owner: &str,
+        reference: Option<f64>,
+    ) -> Result<LiveQuote, QuoteError> {
+        let reference = reference
+            .or_else(|| self.references.get(&request.symbol).copied())
+            .filter(|x| x.is_finite() && *x > 0.0)
+            .ok_or_else(|| QuoteError::NoReference(request.symbol.clone()))?;
+        let size = request.quantity.unwrap_or(self.settings.default_size);
+        if size == 0 {
+            return Err(QuoteError::InvalidQuantity(size));
+        }
+
+        let tick = self.settings.tick_size;
+        let half_spread = reference * self.settings.spread_bps / 20_000.0;
+        // At least a tick apart, whatever the spread
+        let bid = ((reference - half_spread) / tick).floor() * tick;
+        let offer = (((reference + half_spread) / tick).ceil() * tick).max(bid + tick);
+        let quote = LiveQuote {
+            quote_id: format!("Q{}", self.next_id),
+            quote_req_id: request.quote_req_id.clone(),
+            owner: owner.to_string(),
+            symbol: request.symbol.clone(),
+            bid: (request.side != Some(Side::Buy)).then_some((round(bid, tick), size)),
+            offer: (request.side != Some(Side::Sell)).then_some((round(offer, tick), size)),
+            valid_until: unix_millis() + self.settings.valid_for.as_millis() as i64,
+        };
+        self.next_id += 1;
+
+        self.quotes
+            .retain(|x| x.owner != owner || x.symbol != request.symbol);
+        self.quotes.push(quote.clone());
+        Ok(quote)
+    }
+
+    /// Withdraw an owner's quotes
+    ///
+    /// # Returns
+    /// A report per quote withdrawn; NotFound for an unknown QuoteID
+    pub fn cancel(&mut self, owner: &str, scope: &QuoteCancelScope) -> Vec<QuoteStatusReport> {
+        self.expire();
+        let selected = |x: &LiveQuote| {
+            x.owner == owner
+                && match scope {
+                    QuoteCancelScope::Symbol(symbol) => x.symbol == *symbol,
+                    QuoteCancelScope::All => true,
+                    QuoteCancelScope::QuoteId(id) => x.quote_id == *id,
+                }
+        };
+        let (cancelled, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.quotes)
+            .into_iter()
+            .partition(selected);
+        self.quotes = kept;
+
+        let status = match scope {
+            QuoteCancelScope::Symbol(_) => QuoteStatus::CanceledForSymbol,
+            QuoteCancelScope::All => QuoteStatus::CanceledAll,
+            QuoteCancelScope::QuoteId(_) => QuoteStatus::RemovedFromMarket,
+        };
+        match scope {
+            QuoteCancelScope::QuoteId(id) if cancelled.is_empty() => vec![not_found(id, None)],
+            _ => cancelled
+                .into_iter()
+                .map(|x| QuoteStatusReport::of(x, status))
+                .collect(),
+        }
+    }
+
+    /// Status of an owner's quotes: the one with the QuoteID asked, those
+    /// for the symbol asked, or all of them
+    pub fn status(&mut self, owner: &str, query: &QuoteStatusQuery) -> Vec<QuoteStatusReport> {
+        let expired = self.expire();
+        let mut reports: Vec<_> = self
+            .quotes
+            .iter()
+            .map(|x| (x, QuoteStatus::Accepted))
+            .chain(expired.iter().map(|x| (x, QuoteStatus::Expired)))
+            .filter(|(x, _)| x.owner == owner)
+            .filter(|(x, _)| query.quote_id.as_ref().is_none_or(|id| x.quote_id == *id))
+            .filter(|(x, _)| query.symbol.as_ref().is_none_or(|s| x.symbol == *s))
+            .map(|(x, status)| QuoteStatusReport::of(x.clone(), status))
+            .collect();
+        if let (Some(id), true) = (&query.quote_id, reports.is_empty()) {
+            reports.push(not_found(id, query.symbol.clone()));
+        }
+        for report in &mut reports {
+            report.status_req_id = query.status_req_id.clone();
+        }
+        reports
+    }
+
+    /// Drop every quote of a counterparty (on logout)
+    pub fn remove_session(&mut self, owner: &str) {
+        self.quotes.retain(|x| x.owner != owner);
+    }
+
+    /// Quotes live now, oldest first
+    pub fn live_quotes(&mut self) -> Vec<LiveQuote> {
+        self.expire();
+        self.quotes.clone()
+    }
+
+    /// Drop the quotes past their ValidUntilTime and return them
+    fn expire(&mut self) -> Vec<LiveQuote> {
+        let (expired, live) = std::mem::take(&mut self.quotes)
+            .into_iter()
+            .partition(LiveQuote::is_expired);
+        self.quotes = live;
+        expired
+    }
+}
+
+fn not_found(quote_id: &str, symbol: Option<String>) -> QuoteStatusReport {
+    QuoteStatusReport {
+        status: QuoteStatus::NotFound,
+        quote_id: quote_id.to_string(),
+        symbol,
+        quote: None,
+        status_req_id: None,
+    }
+}
+
+/// A price on the tick grid without the binary noise of the division
+fn round(price: f64, tick: f64) -> f64 {
+    let decimals = (-tick.log10()).ceil().max(0.0) as i32;
+    let scale = 10f64.powi(decimals);
+    (price * scale).round() / scale
+}
+
+// =============================================================================
+// Message Builders
+// =============================================================================
+
+/// Quote (35=S) answering a request
+pub fn build_quote(quote: &LiveQuote) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "S"))?;
+    msg.set_field(131, quote.quote_req_id.as_str())?; // QuoteReqID
+    msg.set_field(117, quote.quote_id.as_str())?; // QuoteID
+    msg.set_field(55, quote.symbol.as_str())?;
+    set_sides(&mut msg, quote)?;
+    msg.set_field(62, utc_timestamp(quote.valid_until).as_str())?; // ValidUntilTime
+    Ok(msg)
+}
+
+/// QuoteRequestReject (35=AG)
+pub fn build_quote_request_reject(
+    request: &QuoteRequest,
+    error: &QuoteError,
+) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "AG"))?;
+    msg.set_field(131, request.quote_req_id.as_str())?;
+    msg.set_field(658, error.reject_reason())?; // QuoteRequestRejectReason
+    msg.set_field(58, error.to_string().as_str())?;
+    let mut group = Group::try_new(146, 55)?;
+    group.set_field(55, request.symbol.as_str())?;
+    msg.add_group(&group)?;
+    Ok(msg)
+}
+
+/// QuoteStatusReport (35=AI)
+pub fn build_quote_status_report(report: &QuoteStatusReport) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, "AI"))?;
+    if let Some(status_req_id) = &report.status_req_id {
+        msg.set_field(649, status_req_id.as_str())?; // QuoteStatusReqID
+    }
+    msg.set_field(117, report.quote_id.as_str())?;
+    msg.set_field(297, report.status.as_fix())?; // QuoteStatus
+    if let Some(symbol) = &report.symbol {
+        msg.set_field(55, symbol.as_str())?;
+    }
+    if let Some(quote) = &report.quote {
+        msg.set_field(131, quote.quote_req_id.as_str())?;
+        set_sides(&mut msg, quote)?;
+        msg.set_field(62, utc_timestamp(quote.valid_until).as_str())?;
+    }
+    Ok(msg)
+}
+
+/// BidPx (132), OfferPx (133), BidSize (134), OfferSize (135)
+fn set_sides(msg: &mut Message, quote: &LiveQuote) -> Result<(), QuickFixError> {
+    if let Some((price, size)) = quote.bid {
+        msg.set_field(132, price.to_string().as_str())?;
+        msg.set_field(134, size.to_string().as_str())?;
+    }
+    if let Some((price, size)) = quote.offer {
+        msg.set_field(133, price.to_string().as_str())?;
+        msg.set_field(135, size.to_string().as_str())?;
+    }
+    Ok(())
+}
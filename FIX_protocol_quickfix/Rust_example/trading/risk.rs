@@ -0,0 +1,369 @@
+// =============================================================================
+// Pre-Trade Risk Checks
+// =============================================================================
+// Every order produced by the strategy goes through these checks before it
+// reaches the OMS and the wire. They are deliberately simple, but they are
+// the checks every real buy-side gateway has in some form:
+//
+// - Fat finger: quantity and notional limits per order
+// - Instrument: round lots, and the notional of contracts with their
+//   multiplier, as the venue defined them (trading::instruments)
+// - Position limit: worst-case position if all working orders fill
+// - Margin: what the whole portfolio requires once the order fills, hedged
+//   pairs offset, against the account's equity (margin.rs)
+// - Currency: the order in its instrument's currency, and its notional
+//   converted to the base currency of the limits (currency.rs)
+// =============================================================================
+
+use std::{error::Error, fmt};
+
+use crate::{instruments::Instrument, oms::Side};
+
+pub mod currency;
+pub mod margin;
+
+use currency::{CurrencyIssue, FxRates};
+use margin::{MarginModel, MarginReport};
+
+#[derive(Debug, Clone)]
+pub struct RiskLimits {
+    /// Maximum quantity of a single order
+    pub max_order_qty: f64,
+
+    /// Maximum notional (qty * price) of a single order, in the base
+    /// currency when FX rates are set
+    pub max_order_notional: f64,
+
+    /// Maximum absolute position per symbol, including working orders
+    pub max_position: f64,
+}
+
+impl Default for RiskLimits {
+    fn default() -> Self {
+        Self {
+            max_order_qty: 1_000.0,
+            max_order_notional: 100_000.0,
+            max_position: 5_000.0,
+        }
+    }
+}
+
+impl RiskLimits {
+    /// Names accepted by `by_profile`, tightest first
+    pub const PROFILES: [&'static str; 3] = ["conservative", "standard", "aggressive"];
+
+    /// Look a named set of limits up (command-line friendly)
+    ///
+    /// `standard` is the default limits.
+    pub fn by_profile(name: &str) -> Option<Self> {
+        match name {
+            "conservative" => Some(Self {
+                max_order_qty: 100.0,
+                max_order_notional: 10_000.0,
+                max_position: 500.0,
+            }),
+            "standard" => Some(Self::default()),
+            "aggressive" => Some(Self {
+                max_order_qty: 10_000.0,
+                max_order_notional: 1_000_000.0,
+                max_position: 50_000.0,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The limits as session settings, to keep them with a session's
+    /// configuration (QuickFIX ignores keys it does not know)
+    pub fn to_settings(&self) -> [(&'static str, String); 3] {
+        [
+            ("RiskMaxOrderQty", self.max_order_qty.to_string()),
+            ("RiskMaxOrderNotional", self.max_order_notional.to_string()),
+            ("RiskMaxPosition", self.max_position.to_string()),
+        ]
+    }
+}
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RiskViolation {
+    /// Order quantity is zero, negative, not a number or above the limit
+    OrderQty { quantity: f64, limit: f64 },
+
+    /// Order price is zero, negative or not a number
+    OrderPrice { price: f64 },
+
+    /// Order notional above the limit
+    OrderNotional { notional: f64, limit: f64 },
+
+    /// Position would exceed the limit if everything fills
+    Position { projected: f64, limit: f64 },
+
+    /// The portfolio would require more margin than the equity
+    Margin { required: f64, equity: f64 },
+
+    /// Order quantity is not a multiple of the instrument's round lot
+    OddLot { quantity: f64, lot_size: f64 },
+
+    /// Order currency missing, other than the instrument's, or without a
+    /// rate to the base currency
+    Currency(CurrencyIssue),
+}
+
+impl fmt::Display for RiskViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskViolation::OrderQty { quantity, limit } => {
+                write!(f, "order quantity {quantity} outside (0, {limit}]")
+            }
+            RiskViolation::OrderPrice { price } => {
+                write!(f, "order price {price} not a positive number")
+            }
+            RiskViolation::OrderNotional { notional, limit } => {
+                write!(f, "order notional {notional} above limit {limit}")
+            }
+            RiskViolation::Position { projected, limit } => {
+                write!(f, "projected position {projected} above limit {limit}")
+            }
+            RiskViolation::Margin { required, equity } => {
+                write!(f, "margin requirement {required:.2} above equity {equity}")
+            }
+            RiskViolation::OddLot { quantity, lot_size } => {
+                write!(f, "order quantity {quantity} not in lots of {lot_size}")
+            }
+            RiskViolation::Currency(issue) => write!(f, "{issue}"),
+        }
+    }
+}
+
+impl Error for RiskViolation {}
+
+// =============================================================================
+// RiskChecker
+// =============================================================================
+
+pub struct RiskChecker {
+    limits: RiskLimits,
+
+    /// Offset matrix and account equity, when margin is checked
+    margin: Option<(MarginModel, f64)>,
+
+    /// Rates to the base currency, when notionals are normalized
+    fx: Option<FxRates>,
+}
+
+impl RiskChecker {
+    pub fn new(limits: RiskLimits) -> Self {
+        Self {
+            limits,
+            margin: None,
+            fx: None,
+        }
+    }
+
+    /// Also check the portfolio margin against this equity (check_margin)
+    pub fn with_margin(mut self, model: MarginModel, equity: f64) -> Self {
+        self.margin = Some((model, equity));
+        self
+    }
+
+    /// Check notionals in the base currency of these rates (check_currency)
+    /// rather than in the currency of each order
+    pub fn with_fx(mut self, fx: FxRates) -> Self {
+        self.fx = Some(fx);
+        self
+    }
+
+    pub fn fx(&self) -> Option<&FxRates> {
+        self.fx.as_ref()
+    }
+
+    /// Margin of a portfolio under the model of with_margin
+    ///
+    /// # Arguments
+    /// * `positions` - Signed quantity by symbol
+    /// * `price` - Price of a symbol
+    ///
+    /// # Returns
+    /// The report and the equity it is checked against; None without
+    /// with_margin
+    pub fn margin_report(
+        &self,
+        positions: &[(String, f64)],
+        price: &dyn Fn(&str) -> Option<f64>,
+    ) -> Option<(MarginReport, f64)> {
+        let (model, equity) = self.margin.as_ref()?;
+        Some((model.margin(positions, price), *equity))
+    }
+
+    /// Validate a prospective order
+    ///
+    /// # Arguments
+    /// * `side`, `quantity`, `price` - The order to validate; a market order
+    ///   at the price it is expected to trade at
+    /// * `position` - Current signed position in the symbol
+    /// * `working_qty` - Quantity already working on the same side
+    pub fn check(
+        &self,
+        side: Side,
+        quantity: f64,
+        price: f64,
+        position: f64,
+        working_qty: f64,
+    ) -> Result<(), RiskViolation> {
+        // Written so that NaN, for which every comparison is false, fails
+        if !(quantity.is_finite() && quantity > 0.0) || quantity > self.limits.max_order_qty {
+            return Err(RiskViolation::OrderQty {
+                quantity,
+                limit: self.limits.max_order_qty,
+            });
+        }
+        if !(price.is_finite() && price > 0.0) {
+            return Err(RiskViolation::OrderPrice { price });
+        }
+
+        let notional = quantity * price;
+        if self.fx.is_none() && notional > self.limits.max_order_notional {
+            return Err(RiskViolation::OrderNotional {
+                notional,
+                limit: self.limits.max_order_notional,
+            });
+        }
+
+        let projected = match side {
+            Side::Buy => position + working_qty + quantity,
+            Side::Sell => position - working_qty - quantity,
+        };
+        if projected.abs() > self.limits.max_position {
+            return Err(RiskViolation::Position {
+                projected,
+                limit: self.limits.max_position,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Validate a prospective order against the venue's definition of its
+    /// instrument: a whole number of lots, and a notional within the limit
+    /// once multiplied by the contract multiplier
+    pub fn check_instrument(
+        &self,
+        instrument: &Instrument,
+        quantity: f64,
+        price: f64,
+    ) -> Result<(), RiskViolation> {
+        if let Some(lot_size) = instrument.lot_size {
+            if !instrument.is_round_lot(quantity) {
+                return Err(RiskViolation::OddLot { quantity, lot_size });
+            }
+        }
+
+        let notional = instrument.notional(quantity, price);
+        if self.fx.is_none() && notional > self.limits.max_order_notional {
+            return Err(RiskViolation::OrderNotional {
+                notional,
+                limit: self.limits.max_order_notional,
+            });
+        }
+        Ok(())
+    }
+
+    /// Validate the currency of a prospective order and its notional in the
+    /// base currency, when FX rates are set (a no-op otherwise)
+    ///
+    /// The instrument's currency is that of the venue's definition, else
+    /// the one the rates list for the symbol. An order with no currency of
+    /// its own and none expected is taken to be in the base currency.
+    ///
+    /// # Arguments
+    /// * `symbol`, `instrument` - What the order trades, if defined
+    /// * `currency` - Currency (15) the order is sent in
+    /// * `quantity`, `price` - The order to validate
+    pub fn check_currency(
+        &self,
+        symbol: &str,
+        instrument: Option<&Instrument>,
+        currency: Option<&str>,
+        quantity: f64,
+        price: f64,
+    ) -> Result<(), RiskViolation> {
+        let Some(fx) = &self.fx else {
+            return Ok(());
+        };
+        let expected = instrument
+            .map(|x| x.currency.as_str())
+            .filter(|x| !x.is_empty())
+            .or_else(|| fx.symbol_currency(symbol));
+        currency::check_currency(currency, expected).map_err(RiskViolation::Currency)?;
+
+        let notional = match instrument {
+            Some(instrument) => instrument.notional(quantity, price),
+            None => quantity * price,
+        };
+        let notional = fx
+            .to_base(notional, currency.or(expected).unwrap_or(fx.base()))
+            .map_err(RiskViolation::Currency)?;
+        if notional > self.limits.max_order_notional {
+            return Err(RiskViolation::OrderNotional {
+                notional,
+                limit: self.limits.max_order_notional,
+            });
+        }
+        Ok(())
+    }
+
+    /// Validate the portfolio margin of a prospective order, when enabled
+    ///
+    /// The order is counted as filled at its price. An order that does not
+    /// raise the requirement passes even above the equity: it is what
+    /// brings the account back.
+    ///
+    /// # Arguments
+    /// * `positions` - Signed quantity by symbol
+    /// * `price` - Price of a symbol, for the other positions
+    /// * `symbol`, `side`, `quantity`, `order_price` - The order to validate
+    pub fn check_margin(
+        &self,
+        positions: &[(String, f64)],
+        price: &dyn Fn(&str) -> Option<f64>,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+        order_price: f64,
+    ) -> Result<(), RiskViolation> {
+        let Some((model, equity)) = &self.margin else {
+            return Ok(());
+        };
+        let price = |x: &str| {
+            if x == symbol {
+                Some(order_price)
+            } else {
+                price(x)
+            }
+        };
+        let before = model.margin(positions, &price).net;
+
+        let signed = match side {
+            Side::Buy => quantity,
+            Side::Sell => -quantity,
+        };
+        let mut after: Vec<_> = positions.to_vec();
+        match after.iter_mut().find(|(x, _)| x == symbol) {
+            Some((_, held)) => *held += signed,
+            None => after.push((symbol.to_string(), signed)),
+        }
+        let required = model.margin(&after, &price).net;
+
+        if required > *equity && required > before {
+            return Err(RiskViolation::Margin {
+                required,
+                equity: *equity,
+            });
+        }
+        Ok(())
+    }
+}
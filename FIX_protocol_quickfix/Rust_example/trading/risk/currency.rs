@@ -0,0 +1,336 @@
+// =============================================================================
+// Currencies and FX Rates
+// =============================================================================
+// Orders of one account trade in several currencies, and a notional limit of
+// 100,000 means little until it says which. Here the Currency (15) of each
+// order is kept, and notionals are converted to one base currency, the
+// currency of the limits and of the P&L, with the rates of a file:
+//
+//   # Currency the limits and the P&L are expressed in
+//   base = "USD"
+//
+//   # Value of one unit of each currency in the base
+//   [rates]
+//   EUR = 1.0850
+//   GBP = 1.2700
+//   JPY = 0.0067
+//
+//   # Currency the desk trades a symbol in, for the orders it sends
+//   [symbols]
+//   VOD = "GBP"
+//   SAP = "EUR"
+//
+// An order is flagged when it has no currency while its instrument trades in
+// one, or a currency other than the instrument's (RiskChecker::
+// check_currency, with the currency of the venue's security definition); an
+// execution report when its currency differs from its order's
+// (CurrencyTracker). A currency without a rate cannot be converted: what is
+// in it is flagged as well rather than counted at 1.
+//
+// Only this subset of TOML is read, as for the margin matrix: tables,
+// `key = value` lines and `#` comments. Currency codes are upper-cased.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::session::events::FixMessage;
+
+/// Base currency of FxRates::default()
+pub const DEFAULT_BASE: &str = "USD";
+
+// =============================================================================
+// Error Types
+// =============================================================================
+
+/// A rates file that could not be loaded
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FxError {
+    Io(PathBuf, io::Error),
+
+    /// A line of a rates file could not be read
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for FxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FxError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            FxError::Syntax {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+        }
+    }
+}
+
+impl Error for FxError {}
+
+/// What is wrong with the currency of an order or a fill
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum CurrencyIssue {
+    /// No Currency (15), the instrument trades in `expected`
+    Missing { expected: String },
+
+    /// Currency (15) other than the instrument's, or the order's
+    Mismatch { sent: String, expected: String },
+
+    /// No rate to the base currency
+    NoRate(String),
+}
+
+impl fmt::Display for CurrencyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CurrencyIssue::Missing { expected } => {
+                write!(f, "no currency, instrument trades in {expected}")
+            }
+            CurrencyIssue::Mismatch { sent, expected } => {
+                write!(f, "currency {sent}, expected {expected}")
+            }
+            CurrencyIssue::NoRate(currency) => write!(f, "no FX rate for {currency}"),
+        }
+    }
+}
+
+impl Error for CurrencyIssue {}
+
+/// Compare the currency of an order with the one expected of it; nothing
+/// to compare with passes
+pub fn check_currency(sent: Option<&str>, expected: Option<&str>) -> Result<(), CurrencyIssue> {
+    match (sent, expected) {
+        (_, None) => Ok(()),
+        (None, Some(expected)) => Err(CurrencyIssue::Missing {
+            expected: expected.to_string(),
+        }),
+        (Some(sent), Some(expected)) if !sent.eq_ignore_ascii_case(expected) => {
+            Err(CurrencyIssue::Mismatch {
+                sent: sent.to_string(),
+                expected: expected.to_string(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+// =============================================================================
+// FX Rates
+// =============================================================================
+
+/// Rates to one base currency, and the currency of the symbols listed
+#[derive(Debug, Clone)]
+pub struct FxRates {
+    base: String,
+
+    /// Value of one unit of a currency in the base
+    rates: HashMap<String, f64>,
+    symbols: HashMap<String, String>,
+}
+
+impl Default for FxRates {
+    fn default() -> Self {
+        Self::new(DEFAULT_BASE)
+    }
+}
+
+impl FxRates {
+    /// No rates but the base's own
+    pub fn new(base: &str) -> Self {
+        Self {
+            base: base.to_ascii_uppercase(),
+            rates: HashMap::new(),
+            symbols: HashMap::new(),
+        }
+    }
+
+    /// The rates of a file, in USD unless it sets `base`
+    pub fn load(path: &Path) -> Result<Self, FxError> {
+        let source =
+            fs::read_to_string(path).map_err(|err| FxError::Io(path.to_path_buf(), err))?;
+        Self::default().merge(path, &source)
+    }
+
+    /// Replace what the source of a rates file lists (`path` is only for
+    /// errors)
+    pub fn merge(mut self, path: &Path, source: &str) -> Result<Self, FxError> {
+        let mut table = None;
+        for (index, line) in source.lines().enumerate() {
+            let syntax = |message: String| FxError::Syntax {
+                path: path.to_path_buf(),
+                line: index + 1,
+                message,
+            };
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                table = match header.strip_suffix(']').map(str::trim) {
+                    Some(name @ ("rates" | "symbols")) => Some(name),
+                    _ => return Err(syntax("expected [rates] or [symbols]".to_string())),
+                };
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| syntax("expected key = value".to_string()))?;
+            let (key, value) = (key.trim().trim_matches('"'), value.trim().trim_matches('"'));
+            let currency = |code: &str| {
+                let valid = code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic());
+                valid
+                    .then(|| code.to_ascii_uppercase())
+                    .ok_or_else(|| syntax(format!("expected a currency code: {code}")))
+            };
+
+            match table {
+                None if key == "base" => self.base = currency(value)?,
+                None => return Err(syntax(format!("unknown key {key} outside of a table"))),
+                Some("rates") => {
+                    let rate = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|x| *x > 0.0)
+                        .ok_or_else(|| syntax(format!("{key} must be above 0: {value}")))?;
+                    self.rates.insert(currency(key)?, rate);
+                }
+                Some(_) => {
+                    self.symbols.insert(key.to_string(), currency(value)?);
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Set the value of one unit of `currency` in the base
+    pub fn with_rate(mut self, currency: &str, rate: f64) -> Self {
+        self.rates.insert(currency.to_ascii_uppercase(), rate);
+        self
+    }
+
+    /// Set the currency a symbol is traded in
+    pub fn with_symbol(mut self, symbol: &str, currency: &str) -> Self {
+        self.symbols
+            .insert(symbol.to_string(), currency.to_ascii_uppercase());
+        self
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// Value of one unit of `currency` in the base; 1 for the base itself
+    pub fn rate(&self, currency: &str) -> Option<f64> {
+        if currency.eq_ignore_ascii_case(&self.base) {
+            return Some(1.0);
+        }
+        self.rates.get(&currency.to_ascii_uppercase()).copied()
+    }
+
+    /// An amount in `currency` converted to the base
+    pub fn to_base(&self, amount: f64, currency: &str) -> Result<f64, CurrencyIssue> {
+        self.rate(currency)
+            .map(|rate| amount * rate)
+            .ok_or_else(|| CurrencyIssue::NoRate(currency.to_string()))
+    }
+
+    /// Amounts in several currencies, summed in the base
+    pub fn sum_to_base<'a>(
+        &self,
+        amounts: impl IntoIterator<Item = (f64, &'a str)>,
+    ) -> Result<f64, CurrencyIssue> {
+        amounts
+            .into_iter()
+            .map(|(amount, currency)| self.to_base(amount, currency))
+            .sum()
+    }
+
+    /// Currency a symbol is traded in, if listed under [symbols]
+    pub fn symbol_currency(&self, symbol: &str) -> Option<&str> {
+        self.symbols.get(symbol).map(String::as_str)
+    }
+}
+
+// =============================================================================
+// Currency Tracker
+// =============================================================================
+
+/// Currency (15) of each order by ClOrdID, to check the fills against
+#[derive(Debug, Default)]
+pub struct CurrencyTracker {
+    orders: HashMap<String, Option<String>>,
+}
+
+impl CurrencyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the currency an order was sent in
+    pub fn on_order(&mut self, cl_ord_id: &str, currency: Option<&str>) {
+        self.orders
+            .insert(cl_ord_id.to_string(), currency.map(str::to_string));
+    }
+
+    /// Remember the currency of a NewOrderSingle or a cancel / replace
+    /// request (35=D/G), check that of an ExecutionReport (35=8)
+    ///
+    /// # Returns
+    /// The issue of a report in another currency than its order; a report
+    /// without Currency, or of an order not seen, passes
+    pub fn on_message(&mut self, msg: &FixMessage) -> Option<CurrencyIssue> {
+        let cl_ord_id = msg.get(11)?;
+        match msg.msg_type() {
+            "D" | "G" => {
+                self.on_order(cl_ord_id, msg.get(15));
+                None
+            }
+            "8" => {
+                let sent = msg.get(15)?;
+                let expected = self
+                    .currency(cl_ord_id)
+                    .or_else(|| self.currency(msg.get(41)?))?;
+                check_currency(Some(sent), Some(expected)).err()
+            }
+            _ => None,
+        }
+    }
+
+    /// Currency of an order; None if it had none or was not seen
+    pub fn currency(&self, cl_ord_id: &str) -> Option<&str> {
+        self.orders.get(cl_ord_id)?.as_deref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+}
+
+/// A `#` outside of a string starts a comment
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
@@ -0,0 +1,580 @@
+// =============================================================================
+// Portfolio Margin with Offsets
+// =============================================================================
+// What an account must hold against its positions. Summing each position's
+// own requirement overstates it: a call written against stock held, or a
+// future bought against a later one sold, risks far less than either leg
+// alone. Here each position is charged a rate of its value, then pairs of
+// positions that hedge each other give some of it back, as an offset matrix
+// says:
+//
+//   # Requirement, a fraction of the value of a position; long- / short-
+//   # set one direction of a class. A class not listed is charged in full.
+//   [rates]
+//   stock = 0.25
+//   short-stock = 0.30
+//   call = 0.20
+//   put = 0.20
+//   long-call = 0                 # bought options are paid in full
+//   long-put = 0
+//   future = 0.10
+//
+//   # Hedged pairs on the same underlying: the matched quantity of both
+//   # legs is charged (1 - credit) of its requirement. Applied in order,
+//   # each unit of a position in one pair at most.
+//   [offsets]
+//   long-stock/short-call = 0.80     # covered call
+//   long-stock/long-put = 0.50       # protective put
+//   short-stock/long-call = 0.50
+//   long-future/short-future = 0.75  # calendar spread
+//
+//   # Anything but a stock: CLASS UNDERLYING MULTIPLIER
+//   [instruments]
+//   AAPL240621C190 = "call AAPL 100"
+//   ESZ4 = "future ES 50"
+//   ESH5 = "future ES 50"
+//
+// A symbol not listed under [instruments] is a stock, its own underlying.
+// Quantities are matched in units of the underlying (quantity times
+// multiplier): one call of multiplier 100 covers 100 shares. An option is
+// valued on its underlying, the exposure its requirement is about; a
+// future on its own price. Without a price for the underlying, an option
+// falls back to its own.
+//
+// MarginModel::standard() is the matrix above; a file replaces what it
+// lists. Only this subset of TOML is read: tables, `key = value` lines and
+// `#` comments.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+/// An offset matrix that could not be loaded
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MarginError {
+    Io(PathBuf, io::Error),
+
+    /// A line of a matrix file could not be read
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for MarginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarginError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            MarginError::Syntax {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+        }
+    }
+}
+
+impl Error for MarginError {}
+
+// =============================================================================
+// Instruments
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetClass {
+    Stock,
+    Call,
+    Put,
+    Future,
+}
+
+impl AssetClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssetClass::Stock => "stock",
+            AssetClass::Call => "call",
+            AssetClass::Put => "put",
+            AssetClass::Future => "future",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "stock" => Some(AssetClass::Stock),
+            "call" => Some(AssetClass::Call),
+            "put" => Some(AssetClass::Put),
+            "future" => Some(AssetClass::Future),
+            _ => None,
+        }
+    }
+
+    /// Valued on the underlying rather than on its own price
+    fn is_option(&self) -> bool {
+        matches!(self, AssetClass::Call | AssetClass::Put)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instrument {
+    pub class: AssetClass,
+
+    /// Symbol of the underlying; a stock's own
+    pub underlying: String,
+
+    /// Units of the underlying per unit of quantity
+    pub multiplier: f64,
+}
+
+impl Instrument {
+    pub fn stock(symbol: &str) -> Self {
+        Self {
+            class: AssetClass::Stock,
+            underlying: symbol.to_string(),
+            multiplier: 1.0,
+        }
+    }
+
+    /// `CLASS UNDERLYING MULTIPLIER`, as in the [instruments] table
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut words = text.split_whitespace();
+        let class = AssetClass::from_name(words.next()?)?;
+        let underlying = words.next()?.to_string();
+        let multiplier = match words.next() {
+            Some(x) => x.parse().ok().filter(|x: &f64| *x > 0.0)?,
+            None => 1.0,
+        };
+        words.next().is_none().then_some(Self {
+            class,
+            underlying,
+            multiplier,
+        })
+    }
+}
+
+// =============================================================================
+// Offset Matrix
+// =============================================================================
+
+/// One side of a hedged pair: `long-stock`, `short-call`...
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LegPattern {
+    pub long: bool,
+    pub class: AssetClass,
+}
+
+impl LegPattern {
+    pub fn parse(text: &str) -> Option<Self> {
+        let (direction, class) = text.split_once('-')?;
+        let long = match direction {
+            "long" => true,
+            "short" => false,
+            _ => return None,
+        };
+        Some(Self {
+            long,
+            class: AssetClass::from_name(class)?,
+        })
+    }
+
+    fn matches(&self, leg: &Leg) -> bool {
+        self.long == (leg.quantity > 0.0) && self.class == leg.instrument.class
+    }
+}
+
+impl fmt::Display for LegPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = if self.long { "long" } else { "short" };
+        write!(f, "{direction}-{}", self.class.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffsetRule {
+    pub first: LegPattern,
+    pub second: LegPattern,
+
+    /// Fraction of the matched requirement given back, in [0, 1]
+    pub credit: f64,
+}
+
+impl fmt::Display for OffsetRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.first, self.second)
+    }
+}
+
+/// Rates, offsets and instruments margin is computed with
+#[derive(Debug, Clone, Default)]
+pub struct MarginModel {
+    /// By `class` or `long-class` / `short-class`
+    rates: HashMap<String, f64>,
+    offsets: Vec<OffsetRule>,
+    instruments: HashMap<String, Instrument>,
+}
+
+/// Rates of MarginModel::standard()
+const STANDARD_RATES: &[(&str, f64)] = &[
+    ("stock", 0.25),
+    ("short-stock", 0.30),
+    ("call", 0.20),
+    ("put", 0.20),
+    ("long-call", 0.0),
+    ("long-put", 0.0),
+    ("future", 0.10),
+];
+
+/// Offsets of MarginModel::standard(), in the order they apply
+const STANDARD_OFFSETS: &[(&str, &str, f64)] = &[
+    ("long-stock", "short-call", 0.80),
+    ("long-stock", "long-put", 0.50),
+    ("short-stock", "long-call", 0.50),
+    ("long-future", "short-future", 0.75),
+];
+
+impl MarginModel {
+    /// The matrix of the module documentation, without instruments
+    pub fn standard() -> Self {
+        let mut model = Self::default();
+        for (key, rate) in STANDARD_RATES {
+            model.rates.insert(key.to_string(), *rate);
+        }
+        for (first, second, credit) in STANDARD_OFFSETS {
+            model.offsets.push(OffsetRule {
+                first: LegPattern::parse(first).expect("standard offset"),
+                second: LegPattern::parse(second).expect("standard offset"),
+                credit: *credit,
+            });
+        }
+        model
+    }
+
+    /// The standard matrix, with what a file lists replaced
+    pub fn load(path: &Path) -> Result<Self, MarginError> {
+        let source =
+            fs::read_to_string(path).map_err(|err| MarginError::Io(path.to_path_buf(), err))?;
+        Self::standard().merge(path, &source)
+    }
+
+    /// Replace what the source of a matrix file lists (`path` is only for
+    /// errors)
+    pub fn merge(mut self, path: &Path, source: &str) -> Result<Self, MarginError> {
+        let mut table = None;
+        for (index, line) in source.lines().enumerate() {
+            let syntax = |message: String| MarginError::Syntax {
+                path: path.to_path_buf(),
+                line: index + 1,
+                message,
+            };
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                table = match header.strip_suffix(']').map(str::trim) {
+                    Some(name @ ("rates" | "offsets" | "instruments")) => Some(name),
+                    _ => {
+                        return Err(syntax(
+                            "expected [rates], [offsets] or [instruments]".to_string(),
+                        ))
+                    }
+                };
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| syntax("expected key = value".to_string()))?;
+            let (key, value) = (key.trim().trim_matches('"'), value.trim().trim_matches('"'));
+            let fraction = || {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|x| (0.0..=1.0).contains(x))
+                    .ok_or_else(|| syntax(format!("{key} must be between 0 and 1: {value}")))
+            };
+
+            match table {
+                Some("rates") => {
+                    let class = key.strip_prefix("long-").or(key.strip_prefix("short-"));
+                    if AssetClass::from_name(class.unwrap_or(key)).is_none() {
+                        return Err(syntax(format!("unknown class {key}")));
+                    }
+                    self.rates.insert(key.to_string(), fraction()?);
+                }
+                Some("offsets") => {
+                    let (first, second) = key
+                        .split_once('/')
+                        .and_then(|(a, b)| Some((LegPattern::parse(a)?, LegPattern::parse(b)?)))
+                        .ok_or_else(|| {
+                            syntax(format!("expected long-CLASS/short-CLASS, not {key}"))
+                        })?;
+                    let rule = OffsetRule {
+                        first,
+                        second,
+                        credit: fraction()?,
+                    };
+                    let known = self
+                        .offsets
+                        .iter_mut()
+                        .find(|x| x.first == first && x.second == second);
+                    match known {
+                        Some(known) => known.credit = rule.credit,
+                        None => self.offsets.push(rule),
+                    }
+                }
+                Some(_) => {
+                    let instrument = Instrument::parse(value).ok_or_else(|| {
+                        syntax(format!("expected CLASS UNDERLYING MULTIPLIER: {value}"))
+                    })?;
+                    self.instruments.insert(key.to_string(), instrument);
+                }
+                None => return Err(syntax("key outside of a table".to_string())),
+            }
+        }
+        Ok(self)
+    }
+
+    /// Set the rate of `class`, `long-class` or `short-class`
+    pub fn with_rate(mut self, key: &str, rate: f64) -> Self {
+        self.rates.insert(key.to_string(), rate);
+        self
+    }
+
+    /// Add an offset, applied after those already there
+    pub fn with_offset(mut self, rule: OffsetRule) -> Self {
+        self.offsets.push(rule);
+        self
+    }
+
+    pub fn with_instrument(mut self, symbol: &str, instrument: Instrument) -> Self {
+        self.instruments.insert(symbol.to_string(), instrument);
+        self
+    }
+
+    /// What a symbol is; a stock unless listed
+    pub fn instrument(&self, symbol: &str) -> Instrument {
+        self.instruments
+            .get(symbol)
+            .cloned()
+            .unwrap_or_else(|| Instrument::stock(symbol))
+    }
+
+    pub fn offsets(&self) -> &[OffsetRule] {
+        &self.offsets
+    }
+
+    /// Rate of one direction of a class; in full when not set
+    pub fn rate(&self, long: bool, class: AssetClass) -> f64 {
+        let direction = if long { "long" } else { "short" };
+        self.rates
+            .get(&format!("{direction}-{}", class.as_str()))
+            .or_else(|| self.rates.get(class.as_str()))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// Margin of a portfolio
+    ///
+    /// # Arguments
+    /// * `positions` - Signed quantity by symbol
+    /// * `price` - Price of a symbol; positions it has none for are listed
+    ///   in the report and charged nothing
+    pub fn margin(
+        &self,
+        positions: &[(String, f64)],
+        price: &dyn Fn(&str) -> Option<f64>,
+    ) -> MarginReport {
+        let mut report = MarginReport::default();
+        let mut legs = Vec::new();
+        for (symbol, quantity) in positions.iter().filter(|(_, x)| *x != 0.0) {
+            let instrument = self.instrument(symbol);
+            let unit_price = if instrument.class.is_option() {
+                price(&instrument.underlying).or_else(|| price(symbol))
+            } else {
+                price(symbol)
+            };
+            let Some(unit_price) = unit_price else {
+                report.unpriced.push(symbol.clone());
+                continue;
+            };
+            let units = quantity.abs() * instrument.multiplier;
+            let unit_margin = unit_price * self.rate(*quantity > 0.0, instrument.class);
+            report.legs.push(LegMargin {
+                symbol: symbol.clone(),
+                class: instrument.class,
+                quantity: *quantity,
+                value: units * unit_price,
+                requirement: units * unit_margin,
+            });
+            legs.push(Leg {
+                symbol,
+                quantity: *quantity,
+                instrument,
+                unit_margin,
+                units_left: units,
+            });
+        }
+        report.gross = report.legs.iter().map(|x| x.requirement).sum();
+
+        for rule in &self.offsets {
+            for i in 0..legs.len() {
+                for j in 0..legs.len() {
+                    let (first, second) = (&legs[i], &legs[j]);
+                    if i == j
+                        || first.instrument.underlying != second.instrument.underlying
+                        || !rule.first.matches(first)
+                        || !rule.second.matches(second)
+                    {
+                        continue;
+                    }
+                    let units = first.units_left.min(second.units_left);
+                    if units <= 0.0 {
+                        continue;
+                    }
+                    let credit = units * (first.unit_margin + second.unit_margin) * rule.credit;
+                    report.offsets.push(AppliedOffset {
+                        rule: rule.to_string(),
+                        first: first.symbol.to_string(),
+                        second: second.symbol.to_string(),
+                        units,
+                        credit,
+                    });
+                    legs[i].units_left -= units;
+                    legs[j].units_left -= units;
+                }
+            }
+        }
+        report.net = report.gross - report.offsets.iter().map(|x| x.credit).sum::<f64>();
+        report
+    }
+}
+
+/// A position being matched against the offsets
+struct Leg<'a> {
+    symbol: &'a str,
+    quantity: f64,
+    instrument: Instrument,
+
+    /// Requirement of one unit of the underlying
+    unit_margin: f64,
+
+    /// Units not yet in a pair
+    units_left: f64,
+}
+
+// =============================================================================
+// MarginReport
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct LegMargin {
+    pub symbol: String,
+    pub class: AssetClass,
+    pub quantity: f64,
+
+    /// Value the rate applies to
+    pub value: f64,
+
+    /// Before offsets
+    pub requirement: f64,
+}
+
+/// A hedged pair found in the portfolio
+#[derive(Debug, Clone)]
+pub struct AppliedOffset {
+    /// `long-stock/short-call`
+    pub rule: String,
+    pub first: String,
+    pub second: String,
+
+    /// Units of the underlying matched
+    pub units: f64,
+
+    /// Requirement given back
+    pub credit: f64,
+}
+
+impl fmt::Display for AppliedOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} / {}: {} units, -{:.2}",
+            self.rule, self.first, self.second, self.units, self.credit
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MarginReport {
+    pub legs: Vec<LegMargin>,
+    pub offsets: Vec<AppliedOffset>,
+
+    /// Symbols without a price, left out
+    pub unpriced: Vec<String>,
+
+    /// Sum of the requirements of every position on its own
+    pub gross: f64,
+
+    /// Gross less the offsets: what the account must hold
+    pub net: f64,
+}
+
+impl MarginReport {
+    /// What an account with this equity can still commit
+    pub fn buying_power(&self, equity: f64) -> f64 {
+        equity - self.net
+    }
+}
+
+/// The positions, the offsets and the totals, one per line
+impl fmt::Display for MarginReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} {:<7} {:>12} {:>14} {:>12}",
+            "SYMBOL", "CLASS", "QTY", "VALUE", "MARGIN"
+        )?;
+        for leg in &self.legs {
+            writeln!(
+                f,
+                "{:<16} {:<7} {:>12} {:>14.2} {:>12.2}",
+                leg.symbol,
+                leg.class.as_str(),
+                leg.quantity,
+                leg.value,
+                leg.requirement
+            )?;
+        }
+        for offset in &self.offsets {
+            writeln!(f, "  offset {offset}")?;
+        }
+        if !self.unpriced.is_empty() {
+            writeln!(f, "  no price: {}", self.unpriced.join(", "))?;
+        }
+        write!(f, "gross {:.2}  net {:.2}", self.gross, self.net)
+    }
+}
+
+/// A `#` outside of a string starts a comment
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
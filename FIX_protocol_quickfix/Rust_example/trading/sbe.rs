@@ -0,0 +1,541 @@
+// =============================================================================
+// SBE Market Data
+// =============================================================================
+// Binary market data feeds (Simple Binary Encoding, CME MDP 3.0 layout)
+// decoded and kept as books, which come out as the FIX messages the market
+// data clients already consume: a MarketDataSnapshotFullRefresh (35=W) of
+// each instrument an update touched, top levels and last trade,
+//
+//   35=W 55=ESZ6 48=1234 268=3 269=0 270=5999.75 271=12 ... 269=2 270=6000 ...
+//
+// so a strategy, a book view or a blotter reads the FIX session and the
+// binary feed alike (FixEvent::Message, direction inbound, session label
+// given by the feed). The transport is gateway::sbe.
+//
+// A packet is a sequence number and a sending time, then SBE messages, each
+// with its size and the standard header (block length, template, schema,
+// version), little-endian:
+//
+//   46  MDIncrementalRefreshBook       price levels: new, change, delete,
+//                                      delete thru, delete from, overlay
+//   48  MDIncrementalRefreshTradeSummary   trades: the last one is kept
+//
+// Other templates and other schemas are skipped by their size. Fields are
+// read at their offsets in the root block and in each group entry, and
+// entries are stepped by the block length they declare, so later schema
+// versions that append fields decode as well.
+//
+// Books are kept by price level, DEFAULT_DEPTH deep, implied prices left
+// out. Packets already seen (the same packet from the A and the B feed)
+// are dropped; a jump in the sequence numbers is counted, and entries of
+// an instrument at or below its last RptSeq (83) are skipped. Recovery from
+// a gap (snapshot channel, TCP replay) is out of scope.
+// =============================================================================
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use crate::{
+    session::{events::FixMessage, Direction},
+    time::{time_of_day, Date},
+};
+
+/// Schema of CME MDP 3.0
+pub const MDP3_SCHEMA_ID: u16 = 1;
+
+/// Levels kept per side when no depth is given
+pub const DEFAULT_DEPTH: usize = 10;
+
+/// Session label of the messages when none is given
+pub const DEFAULT_SESSION: &str = "SBE";
+
+const TEMPLATE_BOOK: u16 = 46;
+const TEMPLATE_TRADE_SUMMARY: u16 = 48;
+
+/// Binary packet header: MsgSeqNum (u32) and SendingTime (u64)
+const PACKET_HEADER: usize = 12;
+
+/// Message size (u16), then the SBE message header (4 x u16)
+const MESSAGE_HEADER: usize = 10;
+
+/// groupSize: blockLength (u16) and numInGroup (u8)
+const GROUP_HEADER: usize = 3;
+
+/// Null value of PRICENULL9 and of the nullable Int32 fields
+const NULL_PRICE: i64 = i64::MAX;
+const NULL_INT32: i32 = i32::MAX;
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SbeError {
+    /// The packet ends before the header, message or entry at this offset
+    Truncated(usize),
+
+    /// A message size smaller than its header, at this offset
+    InvalidSize(usize),
+}
+
+impl fmt::Display for SbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SbeError::Truncated(at) => write!(f, "packet truncated at byte {at}"),
+            SbeError::InvalidSize(at) => write!(f, "invalid message size at byte {at}"),
+        }
+    }
+}
+
+impl Error for SbeError {}
+
+// =============================================================================
+// Decoded Messages
+// =============================================================================
+
+/// MDUpdateAction (279) of a book entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateAction {
+    New,
+    Change,
+    Delete,
+
+    /// Every level of the side
+    DeleteThru,
+
+    /// The level and every level below it
+    DeleteFrom,
+    Overlay,
+}
+
+impl UpdateAction {
+    fn from_sbe(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::New),
+            1 => Some(Self::Change),
+            2 => Some(Self::Delete),
+            3 => Some(Self::DeleteThru),
+            4 => Some(Self::DeleteFrom),
+            5 => Some(Self::Overlay),
+            _ => None,
+        }
+    }
+}
+
+/// One price level update (template 46)
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookEntry {
+    pub security_id: i32,
+    pub rpt_seq: u32,
+
+    /// None for a delete that does not carry it
+    pub price: Option<f64>,
+    pub size: Option<i64>,
+    pub orders: Option<i64>,
+
+    /// 1 for the top of the book
+    pub level: u8,
+    pub action: UpdateAction,
+
+    /// MDEntryType (269): `0` bid, `1` offer, `E` / `F` implied
+    pub entry_type: char,
+}
+
+/// One trade (template 48)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeEntry {
+    pub security_id: i32,
+    pub rpt_seq: u32,
+    pub price: f64,
+    pub size: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SbeMessage {
+    Book {
+        /// Unix nanoseconds
+        transact_time: u64,
+        entries: Vec<BookEntry>,
+    },
+    Trades {
+        /// Unix nanoseconds
+        transact_time: u64,
+        entries: Vec<TradeEntry>,
+    },
+
+    /// A template or schema not decoded here
+    Other { schema_id: u16, template_id: u16 },
+}
+
+/// One datagram of the feed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+    pub seq_num: u32,
+
+    /// Unix nanoseconds
+    pub sending_time: u64,
+    pub messages: Vec<SbeMessage>,
+}
+
+// =============================================================================
+// Decoding
+// =============================================================================
+
+/// Little-endian reads at an offset, None past the end
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&self, at: usize) -> Option<[u8; N]> {
+        self.0.get(at..at + N)?.try_into().ok()
+    }
+
+    fn u8(&self, at: usize) -> Option<u8> {
+        self.0.get(at).copied()
+    }
+
+    fn u16(&self, at: usize) -> Option<u16> {
+        self.bytes(at).map(u16::from_le_bytes)
+    }
+
+    fn u32(&self, at: usize) -> Option<u32> {
+        self.bytes(at).map(u32::from_le_bytes)
+    }
+
+    fn i32(&self, at: usize) -> Option<i32> {
+        self.bytes(at).map(i32::from_le_bytes)
+    }
+
+    fn u64(&self, at: usize) -> Option<u64> {
+        self.bytes(at).map(u64::from_le_bytes)
+    }
+
+    fn i64(&self, at: usize) -> Option<i64> {
+        self.bytes(at).map(i64::from_le_bytes)
+    }
+}
+
+/// Decode one packet of the feed
+pub fn decode_packet(data: &[u8]) -> Result<Packet, SbeError> {
+    let reader = Reader(data);
+    let (Some(seq_num), Some(sending_time)) = (reader.u32(0), reader.u64(4)) else {
+        return Err(SbeError::Truncated(0));
+    };
+
+    let mut messages = Vec::new();
+    let mut at = PACKET_HEADER;
+    while at < data.len() {
+        let size = usize::from(reader.u16(at).ok_or(SbeError::Truncated(at))?);
+        if size < MESSAGE_HEADER {
+            return Err(SbeError::InvalidSize(at));
+        }
+        let message = data.get(at..at + size).ok_or(SbeError::Truncated(at))?;
+        messages.push(decode_message(message).ok_or(SbeError::Truncated(at))?);
+        at += size;
+    }
+    Ok(Packet {
+        seq_num,
+        sending_time,
+        messages,
+    })
+}
+
+/// One message, size field included; None if it ends too early
+fn decode_message(data: &[u8]) -> Option<SbeMessage> {
+    let reader = Reader(data);
+    let block_length = usize::from(reader.u16(2)?);
+    let template_id = reader.u16(4)?;
+    let schema_id = reader.u16(6)?;
+    let other = SbeMessage::Other {
+        schema_id,
+        template_id,
+    };
+    // Before the root block is read: other templates may have no group
+    if schema_id != MDP3_SCHEMA_ID || !matches!(template_id, TEMPLATE_BOOK | TEMPLATE_TRADE_SUMMARY)
+    {
+        return Some(other);
+    }
+
+    let transact_time = reader.u64(MESSAGE_HEADER)?;
+    let group = MESSAGE_HEADER + block_length;
+    let entry_length = usize::from(reader.u16(group)?);
+    let count = usize::from(reader.u8(group + 2)?);
+    let entry = |index: usize| group + GROUP_HEADER + index * entry_length;
+    data.get(..entry(count))?;
+
+    let price = |at| {
+        reader
+            .i64(at)
+            .filter(|x| *x != NULL_PRICE)
+            .map(|x| x as f64 / 1e9)
+    };
+    let int = |at| reader.i32(at).filter(|x| *x != NULL_INT32).map(i64::from);
+    match template_id {
+        TEMPLATE_BOOK => {
+            let entries = (0..count)
+                .filter_map(|index| {
+                    let at = entry(index);
+                    Some(BookEntry {
+                        security_id: reader.i32(at + 12)?,
+                        rpt_seq: reader.u32(at + 16)?,
+                        price: price(at),
+                        size: int(at + 8),
+                        orders: int(at + 20),
+                        level: reader.u8(at + 24)?,
+                        action: UpdateAction::from_sbe(reader.u8(at + 25)?)?,
+                        entry_type: char::from(reader.u8(at + 26)?),
+                    })
+                })
+                .collect();
+            Some(SbeMessage::Book {
+                transact_time,
+                entries,
+            })
+        }
+        TEMPLATE_TRADE_SUMMARY => {
+            let entries = (0..count)
+                .filter_map(|index| {
+                    let at = entry(index);
+                    Some(TradeEntry {
+                        security_id: reader.i32(at + 12)?,
+                        rpt_seq: reader.u32(at + 16)?,
+                        price: price(at)?,
+                        size: int(at + 8)?,
+                    })
+                })
+                .collect();
+            Some(SbeMessage::Trades {
+                transact_time,
+                entries,
+            })
+        }
+        _ => Some(other),
+    }
+}
+
+// =============================================================================
+// Books
+// =============================================================================
+
+/// A price level: price, size, number of orders
+type Level = (f64, i64, Option<i64>);
+
+#[derive(Debug, Default)]
+struct Instrument {
+    bids: Vec<Level>,
+    offers: Vec<Level>,
+
+    /// Price and size
+    last_trade: Option<(f64, i64)>,
+    rpt_seq: u32,
+}
+
+impl Instrument {
+    /// Take an entry in, unless already seen
+    fn accept(&mut self, rpt_seq: u32) -> bool {
+        if rpt_seq != 0 && rpt_seq <= self.rpt_seq {
+            return false;
+        }
+        self.rpt_seq = rpt_seq;
+        true
+    }
+
+    fn apply(&mut self, entry: &BookEntry, depth: usize) {
+        let side = match entry.entry_type {
+            '0' => &mut self.bids,
+            '1' => &mut self.offers,
+            _ => return,
+        };
+        let index = usize::from(entry.level.max(1)) - 1;
+        let level = || Some((entry.price?, entry.size.unwrap_or_default(), entry.orders));
+        match entry.action {
+            UpdateAction::New => {
+                if let Some(level) = level() {
+                    side.insert(index.min(side.len()), level);
+                }
+            }
+            UpdateAction::Change | UpdateAction::Overlay => match (side.get_mut(index), level()) {
+                (Some(current), Some(level)) => *current = level,
+                (None, Some(level)) => side.push(level),
+                _ => {}
+            },
+            UpdateAction::Delete => {
+                if index < side.len() {
+                    side.remove(index);
+                }
+            }
+            UpdateAction::DeleteThru => side.clear(),
+            UpdateAction::DeleteFrom => side.truncate(index),
+        }
+        side.truncate(depth);
+    }
+}
+
+/// The books of a feed, built from its packets
+pub struct SbeBook {
+    session: String,
+    depth: usize,
+    symbols: HashMap<i32, String>,
+    instruments: HashMap<i32, Instrument>,
+
+    /// Last packet taken in
+    seq_num: Option<u32>,
+    gaps: u64,
+}
+
+impl Default for SbeBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SbeBook {
+    pub fn new() -> Self {
+        Self {
+            session: DEFAULT_SESSION.to_string(),
+            depth: DEFAULT_DEPTH,
+            symbols: HashMap::new(),
+            instruments: HashMap::new(),
+            seq_num: None,
+            gaps: 0,
+        }
+    }
+
+    /// Session label of the messages, e.g. `SBE:CME-310`
+    pub fn with_session(mut self, label: &str) -> Self {
+        self.session = label.to_string();
+        self
+    }
+
+    /// Levels kept and published per side
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+
+    /// Symbol (55) of a SecurityID (48), as the security definitions give
+    /// it; without one, the messages carry the SecurityID as the symbol
+    pub fn with_symbol(mut self, security_id: i32, symbol: &str) -> Self {
+        self.symbols.insert(security_id, symbol.to_string());
+        self
+    }
+
+    /// Jumps in the packet sequence numbers so far
+    pub fn gaps(&self) -> u64 {
+        self.gaps
+    }
+
+    /// Apply a packet
+    ///
+    /// # Returns
+    /// A 35=W of each instrument the packet changed, in the order first
+    /// touched; nothing for a packet already seen
+    pub fn on_packet(&mut self, packet: &Packet) -> Vec<FixMessage> {
+        if let Some(last) = self.seq_num {
+            if packet.seq_num <= last {
+                return Vec::new();
+            }
+            if packet.seq_num > last + 1 {
+                self.gaps += 1;
+            }
+        }
+        self.seq_num = Some(packet.seq_num);
+
+        let mut touched: Vec<(i32, u64)> = Vec::new();
+        let mut touch = |security_id, time| match touched.iter_mut().find(|x| x.0 == security_id) {
+            Some(entry) => entry.1 = time,
+            None => touched.push((security_id, time)),
+        };
+        for message in &packet.messages {
+            match message {
+                SbeMessage::Book {
+                    transact_time,
+                    entries,
+                } => {
+                    for entry in entries {
+                        let instrument = self.instruments.entry(entry.security_id).or_default();
+                        if instrument.accept(entry.rpt_seq) {
+                            instrument.apply(entry, self.depth);
+                            touch(entry.security_id, *transact_time);
+                        }
+                    }
+                }
+                SbeMessage::Trades {
+                    transact_time,
+                    entries,
+                } => {
+                    for entry in entries {
+                        let instrument = self.instruments.entry(entry.security_id).or_default();
+                        if instrument.accept(entry.rpt_seq) {
+                            instrument.last_trade = Some((entry.price, entry.size));
+                            touch(entry.security_id, *transact_time);
+                        }
+                    }
+                }
+                SbeMessage::Other { .. } => {}
+            }
+        }
+
+        touched
+            .into_iter()
+            .map(|(security_id, transact_time)| self.snapshot(security_id, packet, transact_time))
+            .collect()
+    }
+
+    /// The 35=W of one instrument
+    fn snapshot(&self, security_id: i32, packet: &Packet, transact_time: u64) -> FixMessage {
+        let instrument = &self.instruments[&security_id];
+        let symbol = self
+            .symbols
+            .get(&security_id)
+            .cloned()
+            .unwrap_or_else(|| security_id.to_string());
+        let entries = instrument.bids.len()
+            + instrument.offers.len()
+            + usize::from(instrument.last_trade.is_some());
+
+        let mut fields = vec![
+            (35, "W".to_string()),
+            (34, packet.seq_num.to_string()),
+            (52, utc_timestamp(packet.sending_time)),
+            (55, symbol),
+            (48, security_id.to_string()),
+            (22, "8".to_string()),
+            (60, utc_timestamp(transact_time)),
+            (268, entries.to_string()),
+        ];
+        for (entry_type, side) in [("0", &instrument.bids), ("1", &instrument.offers)] {
+            for (index, (price, size, orders)) in side.iter().enumerate() {
+                fields.push((269, entry_type.to_string()));
+                fields.push((270, price.to_string()));
+                fields.push((271, size.to_string()));
+                if let Some(orders) = orders {
+                    fields.push((346, orders.to_string()));
+                }
+                fields.push((1023, (index + 1).to_string()));
+            }
+        }
+        if let Some((price, size)) = instrument.last_trade {
+            fields.push((269, "2".to_string()));
+            fields.push((270, price.to_string()));
+            fields.push((271, size.to_string()));
+        }
+
+        FixMessage {
+            session: self.session.clone(),
+            direction: Direction::Inbound,
+            admin: false,
+            fields,
+        }
+    }
+}
+
+/// `YYYYMMDD-HH:MM:SS.nnnnnnnnn` of Unix nanoseconds
+fn utc_timestamp(nanos: u64) -> String {
+    let seconds = (nanos / 1_000_000_000) as i64;
+    format!(
+        "{}-{}.{:09}",
+        Date::from_unix(seconds).to_fix(),
+        time_of_day(seconds),
+        nanos % 1_000_000_000
+    )
+}
@@ -0,0 +1,153 @@
+// =============================================================================
+// FIX Session Layer
+// =============================================================================
+// What every application built on QuickFIX needs around its sessions,
+// whatever it trades:
+//
+// - archive: every message batched into Parquet files by day and session
+// - callback_log: callbacks copied into a ring per session and written by a
+//   thread of their own, dropped and counted under overload
+// - doctor: a configuration checked before the engine starts (ports,
+//   dictionaries, directories, hours, duplicate SessionIDs)
+// - dry_run: outbound orders dropped before the send and acknowledged
+//   locally, globally or per session
+// - dictionary: names, types and values of the fields, with venue-defined tags
+//   merged from TOML files
+// - eod: end-of-day tasks (flatten, fills export, store archive, ClOrdID
+//   reset, summary) due at times relative to each session's close
+// - events: callbacks decoded into owned FixEvents, handed to async tasks
+// - failover: initiators switched to a backup gateway after sustained
+//   connect failures, and back
+// - journal: raw messages appended byte for byte, with nanosecond
+//   timestamps, to a binary journal, and read back
+// - file_store: the QuickFIX file store verified, compacted into gzip
+//   archives, migrated to SQLite
+// - faults: outbound messages dropped, corrupted, delayed or renumbered on
+//   purpose, to test the counterparty's recovery
+// - garbled: messages the engine discarded for a bad frame (BodyLength,
+//   CheckSum, header order), taken apart byte by byte
+// - handover: sequence numbers, orders and fills passed from a process to
+//   the new version replacing it, without a sequence reset
+// - lanes: latency-critical MsgTypes on a fast lane, the rest batched
+//   (`runtime` feature)
+// - provisioning: sessions added to the settings at runtime
+// - rate_limit: token buckets in front of send_to_target, per session and
+//   global, queueing or rejecting what goes over
+// - send_queue: application messages held while their session is logged
+//   off, sent in order at the next logon or dropped after a TTL
+// - rejects: 35=3 and 35=j decoded and matched with the message they reject
+// - routing: application messages passed from session to session by rules,
+//   ClOrdIDs rewritten on the way and put back on the replies (a FIX hub)
+// - transform: fields added, stripped, renamed and values remapped by the
+//   rules of a file, per counterparty, on the way in or out
+// - stats: messages counted by session, MsgType and direction from every
+//   callback thread at once, without a global lock
+// - skew: the counterparty's clock skew, from the SendingTime of its
+//   messages, alerted on past a threshold
+// - standby: a warm standby following the primary's heartbeat and message
+//   store, taking its sessions over on command or when it goes silent
+// - settings: configuration files with ${VAR} values, filled from the
+//   environment or a SecretsProvider, built into SessionSettings
+// - scorecard: uptime, rejects, ack latency, resends and fill quality of
+//   each counterparty session, day by day
+// - runtime: stdin lines and the shutdown signal for tokio main loops
+//   (`runtime` feature)
+// - schedule: session windows from StartTime / EndTime / StartDay / EndDay,
+//   the handler started and stopped around them, weekly sequence resets
+// - mapping: structs declared with their tags (fix_message!, fix_group!),
+//   converted to and from messages
+// - version: FIX 4.0 to 5.0SP2, BeginString and ApplVerID
+// - view: typed getters over the fields of a message and its groups
+//
+// plus the two identifiers shared by every other module, the session label
+// and the message direction, and the socket server kind a connection handler
+// may use with the application it is given.
+// =============================================================================
+
+use quickfix::{ApplicationCallback, FixSocketServerKind, QuickFixError, SessionId};
+
+pub mod archive;
+pub mod callback_log;
+pub mod dictionary;
+pub mod doctor;
+pub mod dry_run;
+pub mod eod;
+pub mod events;
+pub mod failover;
+pub mod faults;
+pub mod file_store;
+pub mod garbled;
+pub mod handover;
+pub mod journal;
+#[cfg(feature = "runtime")]
+pub mod lanes;
+pub mod mapping;
+pub mod provisioning;
+pub mod rate_limit;
+pub mod rejects;
+pub mod routing;
+#[cfg(feature = "runtime")]
+pub mod runtime;
+pub mod schedule;
+pub mod scorecard;
+pub mod send_queue;
+pub mod settings;
+pub mod skew;
+pub mod standby;
+pub mod stats;
+pub mod transform;
+pub mod version;
+pub mod view;
+
+/// Message direction, from our point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+impl Direction {
+    pub fn as_label(self) -> &'static str {
+        match self {
+            Direction::Inbound => "in",
+            Direction::Outbound => "out",
+        }
+    }
+}
+
+/// Stable, human readable label for a session: `FIX.4.4:SENDER->TARGET`
+pub fn session_label(session: &SessionId) -> String {
+    format!(
+        "{}:{}->{}",
+        session.get_begin_string().unwrap_or_default(),
+        session.get_sender_comp_id().unwrap_or_default(),
+        session.get_target_comp_id().unwrap_or_default(),
+    )
+}
+
+/// SessionId back from a label made by [`session_label`]
+///
+/// `None` when the text is not a label; the qualifier is not part of labels
+/// and comes back empty.
+pub fn parse_session_label(label: &str) -> Option<Result<SessionId, QuickFixError>> {
+    let (begin_string, comp_ids) = label.split_once(':')?;
+    let (sender, target) = comp_ids.split_once("->")?;
+    Some(SessionId::try_new(begin_string, sender, target, ""))
+}
+
+/// Socket server kind for a connection handler built on `callbacks`
+///
+/// MultiThreaded gives each session a thread of its own, and QuickFIX calls
+/// the application from all of them at once: what it keeps between
+/// callbacks must be behind locks or atomics. Asking for it here instead of
+/// naming the variant checks that the callbacks are Sync.
+pub fn socket_server_kind<C>(_callbacks: &C, multi_threaded: bool) -> FixSocketServerKind
+where
+    C: ApplicationCallback + Sync,
+{
+    if multi_threaded {
+        FixSocketServerKind::MultiThreaded
+    } else {
+        FixSocketServerKind::SingleThreaded
+    }
+}
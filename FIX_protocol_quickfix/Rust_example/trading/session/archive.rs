@@ -0,0 +1,306 @@
+// =============================================================================
+// Message Archive
+// =============================================================================
+// Every message sent and received, kept as Parquet files for analytics over
+// the history of the traffic (pandas, DuckDB, Spark), partitioned by day
+// and session the way these tools read a dataset:
+//
+//   DIR/date=2026-10-15/session_id=FIX.4.4-CLIENT-EXCHANGE/part-1792072987250-1.parquet
+//
+//   SELECT symbol, count(*), sum(last_qty)
+//   FROM read_parquet('DIR/*/*/*.parquet', hive_partitioning = true)
+//   WHERE msg_type = '8' AND exec_type IN ('1', '2') GROUP BY symbol
+//
+// One row per message, with the message as text (`|` between fields) and
+// the fields most queries need in columns of their own:
+//
+//   time          when it went through the callbacks, UTC (microseconds)
+//   direction     in / out
+//   admin         session-level message (Logon, Heartbeat, ...)
+//   msg_type, seq_num, sending_time
+//   cl_ord_id, orig_cl_ord_id, order_id, exec_id, symbol, side
+//   price, qty    Price (44) and OrderQty (38)
+//   last_px, last_qty, exec_type, ord_status
+//
+// A field a message does not have is null. The callbacks only add rows to
+// the current batch of their partition; flush_due writes the batches that
+// reached the row count or the age given, flush writes them all (at
+// shutdown). Each batch is a new file, written to a temporary name then
+// renamed, so a reader never sees half a file. A batch that cannot be
+// written is kept for the next flush.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    parquet::{Codec, Column, ColumnType, Table, Value},
+    session::{events::FixMessage, Direction},
+    time::{parse_utc_timestamp, Date},
+};
+
+/// Rows of a batch before it is written, when not given
+pub const DEFAULT_BATCH_ROWS: usize = 10_000;
+
+/// Age of a batch before it is written, when not given
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// Columns of the archive, in file order
+const COLUMNS: [(&str, ColumnType); 20] = [
+    ("time", ColumnType::Timestamp),
+    ("session", ColumnType::Text),
+    ("direction", ColumnType::Text),
+    ("admin", ColumnType::Boolean),
+    ("msg_type", ColumnType::Text),
+    ("seq_num", ColumnType::Int64),
+    ("sending_time", ColumnType::Timestamp),
+    ("cl_ord_id", ColumnType::Text),
+    ("orig_cl_ord_id", ColumnType::Text),
+    ("order_id", ColumnType::Text),
+    ("exec_id", ColumnType::Text),
+    ("symbol", ColumnType::Text),
+    ("side", ColumnType::Text),
+    ("price", ColumnType::Double),
+    ("qty", ColumnType::Double),
+    ("last_px", ColumnType::Double),
+    ("last_qty", ColumnType::Double),
+    ("exec_type", ColumnType::Text),
+    ("ord_status", ColumnType::Text),
+    ("message", ColumnType::Text),
+];
+
+/// Text columns taken from a tag as they are, after sending_time
+const TEXT_FIELDS: [i32; 6] = [11, 41, 37, 17, 55, 54];
+
+/// Number columns after the text ones
+const NUMBER_FIELDS: [i32; 4] = [44, 38, 31, 32];
+
+// =============================================================================
+// Archive
+// =============================================================================
+
+/// A day of a session: where its rows go
+type Partition = (Date, String);
+
+/// Rows waiting to be written, and since when
+struct Batch {
+    rows: Vec<Vec<Value>>,
+    since: Instant,
+}
+
+/// Batches messages into Parquet files by day and session
+pub struct MessageArchive {
+    dir: PathBuf,
+    batch_rows: usize,
+    max_age: Duration,
+    codec: Codec,
+    batches: Mutex<HashMap<Partition, Batch>>,
+
+    /// Files written so far, for unique names within a millisecond
+    written: Mutex<u64>,
+}
+
+impl MessageArchive {
+    /// Archive in `dir`, created at the first write
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            batch_rows: DEFAULT_BATCH_ROWS,
+            max_age: DEFAULT_MAX_AGE,
+            codec: Codec::Gzip,
+            batches: Mutex::default(),
+            written: Mutex::default(),
+        }
+    }
+
+    /// Write a batch once it has this many rows
+    pub fn with_batch_rows(mut self, rows: usize) -> Self {
+        self.batch_rows = rows.max(1);
+        self
+    }
+
+    /// Write a batch once its first row is this old, however small
+    pub fn with_max_age(mut self, age: Duration) -> Self {
+        self.max_age = age;
+        self
+    }
+
+    /// Compress the pages or not (gzip by default)
+    pub fn with_codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Rows not written yet
+    pub fn pending(&self) -> usize {
+        self.lock().values().map(|x| x.rows.len()).sum()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Partition, Batch>> {
+        self.batches.lock().expect("archive lock poisoned")
+    }
+
+    /// Add a message to the batch of its day and session
+    pub fn on_message(&self, msg: &FixMessage) {
+        let micros = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_micros() as i64);
+        let partition = (
+            Date::from_unix(micros.div_euclid(1_000_000)),
+            msg.session.clone(),
+        );
+        let row = decode(micros, msg);
+        self.lock()
+            .entry(partition)
+            .or_insert_with(|| Batch {
+                rows: Vec::new(),
+                since: Instant::now(),
+            })
+            .rows
+            .push(row);
+    }
+
+    /// Write the batches that are full or old enough
+    ///
+    /// # Returns
+    /// The files written
+    pub fn flush_due(&self) -> io::Result<Vec<PathBuf>> {
+        self.write(|batch| {
+            batch.rows.len() >= self.batch_rows || batch.since.elapsed() >= self.max_age
+        })
+    }
+
+    /// Write every batch, however small
+    pub fn flush(&self) -> io::Result<Vec<PathBuf>> {
+        self.write(|_| true)
+    }
+
+    fn write<F: Fn(&Batch) -> bool>(&self, due: F) -> io::Result<Vec<PathBuf>> {
+        // Taken out of the lock: the callbacks do not wait for the disk
+        let taken: Vec<_> = {
+            let mut batches = self.lock();
+            let keys: Vec<_> = batches
+                .iter()
+                .filter(|(_, batch)| due(batch))
+                .map(|(key, _)| key.clone())
+                .collect();
+            keys.into_iter()
+                .filter_map(|key| batches.remove_entry(&key))
+                .collect()
+        };
+
+        let mut written = Vec::new();
+        let mut failure = None;
+        for (partition, batch) in taken {
+            if failure.is_some() {
+                self.restore(partition, batch);
+                continue;
+            }
+            match self.write_batch(&partition, &batch.rows) {
+                Ok(path) => written.push(path),
+                Err(err) => {
+                    self.restore(partition, batch);
+                    failure = Some(err);
+                }
+            }
+        }
+        match failure {
+            Some(err) => Err(err),
+            None => Ok(written),
+        }
+    }
+
+    /// Put a batch back, ahead of what came in meanwhile
+    fn restore(&self, partition: Partition, mut batch: Batch) {
+        let mut batches = self.lock();
+        if let Some(newer) = batches.remove(&partition) {
+            batch.rows.extend(newer.rows);
+        }
+        batches.insert(partition, batch);
+    }
+
+    fn write_batch(&self, partition: &Partition, rows: &[Vec<Value>]) -> io::Result<PathBuf> {
+        let columns = COLUMNS.iter().map(|&(name, kind)| Column::new(name, kind));
+        let mut table = Table::new(columns.collect());
+        for row in rows {
+            table
+                .push(row.clone())
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        }
+
+        let (date, session) = partition;
+        let dir = self
+            .dir
+            .join(format!("date={}", date.to_iso()))
+            .join(format!("session_id={}", path_safe(session)));
+        fs::create_dir_all(&dir)?;
+        let sequence = {
+            let mut written = self.written.lock().expect("archive lock poisoned");
+            *written += 1;
+            *written
+        };
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_millis());
+        let path = dir.join(format!("part-{millis}-{sequence}.parquet"));
+
+        // Not .parquet until complete, so that globs skip it
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, table.to_parquet(self.codec))?;
+        fs::rename(&temp, &path)?;
+        Ok(path)
+    }
+}
+
+/// The row of a message, in the order of COLUMNS
+fn decode(micros: i64, msg: &FixMessage) -> Vec<Value> {
+    let text = |value: Option<&str>| value.map_or(Value::Null, |x| Value::Text(x.to_string()));
+    let direction = match msg.direction {
+        Direction::Inbound => "in",
+        Direction::Outbound => "out",
+    };
+    let mut row = vec![
+        Value::Int64(micros),
+        Value::Text(msg.session.clone()),
+        Value::Text(direction.to_string()),
+        Value::Boolean(msg.admin),
+        Value::Text(msg.msg_type().to_string()),
+        msg.get(34)
+            .and_then(|x| x.parse().ok())
+            .map_or(Value::Null, Value::Int64),
+        msg.get(52)
+            .and_then(parse_utc_timestamp)
+            .map_or(Value::Null, |nanos| Value::Int64(nanos / 1_000)),
+    ];
+    row.extend(TEXT_FIELDS.iter().map(|&tag| text(msg.get(tag))));
+    row.extend(NUMBER_FIELDS.iter().map(|&tag| {
+        msg.get(tag)
+            .and_then(|x| x.parse().ok())
+            .map_or(Value::Null, Value::Double)
+    }));
+    row.push(text(msg.get(150)));
+    row.push(text(msg.get(39)));
+    row.push(Value::Text(msg.to_text()));
+    row
+}
+
+/// `FIX.4.4:CLIENT->EXCHANGE` as a directory name: `FIX.4.4-CLIENT-EXCHANGE`
+fn path_safe(session: &str) -> String {
+    session
+        .replace("->", "-")
+        .chars()
+        .map(|x| match x {
+            ':' | '/' | '\\' | '=' | ' ' => '-',
+            x => x,
+        })
+        .collect()
+}
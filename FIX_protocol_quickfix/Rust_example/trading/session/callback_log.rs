@@ -0,0 +1,475 @@
+// =============================================================================
+// Callback Log
+// =============================================================================
+// Printing from a callback formats and writes on the engine's thread, under
+// the stdout lock, for every message: a burst waits on the terminal. Here a
+// callback only copies a fixed-size record (time, callback, MsgType,
+// MsgSeqNum, a few key fields) into a ring, and a writer thread of its own
+// formats and writes them:
+//
+//   callback --push--> Producer ==ring==> "callback-log" thread --> Write
+//
+// - one ring per producer, single producer single consumer: the producer
+//   publishes a slot with one atomic store, the writer frees it with another.
+//   Slots are atomic words allocated once, so a push neither allocates nor
+//   locks (reading the fields from the Message does allocate: the crate
+//   returns owned Strings)
+// - a full ring drops the record and counts it; the writer reports the drops
+//   of each producer as it sees them, and stop() returns the total. A
+//   callback never waits for the terminal
+// - a producer is one session's: QuickFIX calls a session's callbacks from
+//   one thread at a time, so the Mutex an application wraps it in is never
+//   contended
+//
+// stop() drains every ring before the writer exits, so the last records of a
+// session logged out on shutdown are written.
+// =============================================================================
+
+use std::{
+    fmt,
+    io::{self, BufWriter, Write},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use quickfix::{FieldMap, Message};
+
+use crate::time::time_of_day;
+
+/// Records per ring, when the application does not say
+pub const DEFAULT_CAPACITY: usize = 4_096;
+
+/// Bytes of key fields kept in a record, the rest cut off
+const TEXT_LEN: usize = 64;
+
+/// Words of an encoded record: time, MsgSeqNum, callback / MsgType / length,
+/// then the text
+const WORDS: usize = 3 + TEXT_LEN / 8;
+
+/// How long the writer sleeps when every ring is empty
+const IDLE: Duration = Duration::from_millis(1);
+
+/// Fields kept in the text of a message record
+const KEY_FIELDS: [i32; 4] = [11, 55, 39, 58];
+
+// =============================================================================
+// Records
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Callback {
+    Create,
+    Logon,
+    Logout,
+    ToAdmin,
+    ToApp,
+    FromAdmin,
+    FromApp,
+}
+
+impl Callback {
+    const ALL: [Callback; 7] = [
+        Callback::Create,
+        Callback::Logon,
+        Callback::Logout,
+        Callback::ToAdmin,
+        Callback::ToApp,
+        Callback::FromAdmin,
+        Callback::FromApp,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Callback::Create => "on_create",
+            Callback::Logon => "logon",
+            Callback::Logout => "logout",
+            Callback::ToAdmin => "to_admin",
+            Callback::ToApp => "to_app",
+            Callback::FromAdmin => "from_admin",
+            Callback::FromApp => "from_app",
+        }
+    }
+}
+
+/// One callback, pre-encoded: fixed size, copied into the ring as is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record {
+    /// Unix time, nanoseconds
+    pub at: u64,
+    pub callback: Callback,
+
+    /// MsgSeqNum (34), 0 for none
+    pub seq_num: u64,
+
+    msg_type: [u8; 4],
+    text: [u8; TEXT_LEN],
+    len: u8,
+}
+
+impl Record {
+    /// A record of `callback`, timed now
+    pub fn new(callback: Callback) -> Self {
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_nanos() as u64);
+        Self {
+            at,
+            callback,
+            seq_num: 0,
+            msg_type: [0; 4],
+            text: [0; TEXT_LEN],
+            len: 0,
+        }
+    }
+
+    /// A record of a message: MsgType, MsgSeqNum and the key fields it has
+    /// (ClOrdID, Symbol, OrdStatus, Text)
+    pub fn from_message(callback: Callback, msg: &Message) -> Self {
+        let mut record = Self::new(callback);
+        if let Some(msg_type) = msg.with_header(|h| h.get_field(35)) {
+            record = record.with_msg_type(&msg_type);
+        }
+        if let Some(seq_num) = msg.with_header(|h| h.get_field(34)) {
+            record.seq_num = seq_num.parse().unwrap_or(0);
+        }
+        for tag in KEY_FIELDS {
+            if let Some(value) = msg.get_field(tag) {
+                record.append_field(tag, &value);
+            }
+        }
+        record
+    }
+
+    pub fn with_seq_num(mut self, seq_num: u64) -> Self {
+        self.seq_num = seq_num;
+        self
+    }
+
+    /// MsgType, cut to 4 bytes
+    pub fn with_msg_type(mut self, msg_type: &str) -> Self {
+        let len = floor_char_boundary(msg_type, self.msg_type.len());
+        self.msg_type = [0; 4];
+        self.msg_type[..len].copy_from_slice(&msg_type.as_bytes()[..len]);
+        self
+    }
+
+    /// Free text, cut to 64 bytes
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.len = 0;
+        self.append(text);
+        self
+    }
+
+    pub fn msg_type(&self) -> &str {
+        let len = self.msg_type.iter().position(|x| *x == 0).unwrap_or(4);
+        std::str::from_utf8(&self.msg_type[..len]).unwrap_or_default()
+    }
+
+    pub fn text(&self) -> &str {
+        std::str::from_utf8(&self.text[..usize::from(self.len)]).unwrap_or_default()
+    }
+
+    /// ` TAG=VALUE`, without formatting
+    fn append_field(&mut self, tag: i32, value: &str) {
+        let mut digits = [0; 10];
+        let mut n = tag.unsigned_abs();
+        let mut start = digits.len();
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (n % 10) as u8;
+            n /= 10;
+            if n == 0 {
+                break;
+            }
+        }
+        if self.len > 0 {
+            self.append(" ");
+        }
+        self.append(std::str::from_utf8(&digits[start..]).unwrap_or_default());
+        self.append("=");
+        self.append(value);
+    }
+
+    fn append(&mut self, text: &str) {
+        let at = usize::from(self.len);
+        let len = floor_char_boundary(text, TEXT_LEN - at);
+        self.text[at..at + len].copy_from_slice(&text.as_bytes()[..len]);
+        self.len += len as u8;
+    }
+
+    fn encode(&self, words: &[AtomicU64; WORDS]) {
+        let mut packed = [0; 8];
+        packed[0] = Callback::ALL
+            .iter()
+            .position(|x| *x == self.callback)
+            .unwrap_or(0) as u8;
+        packed[1] = self.len;
+        packed[2..6].copy_from_slice(&self.msg_type);
+
+        words[0].store(self.at, Ordering::Relaxed);
+        words[1].store(self.seq_num, Ordering::Relaxed);
+        words[2].store(u64::from_le_bytes(packed), Ordering::Relaxed);
+        for (word, bytes) in words[3..].iter().zip(self.text.chunks_exact(8)) {
+            let bytes = bytes.try_into().expect("8 bytes");
+            word.store(u64::from_le_bytes(bytes), Ordering::Relaxed);
+        }
+    }
+
+    fn decode(words: &[AtomicU64; WORDS]) -> Self {
+        let packed = words[2].load(Ordering::Relaxed).to_le_bytes();
+        let mut record = Self {
+            at: words[0].load(Ordering::Relaxed),
+            callback: Callback::ALL[usize::from(packed[0]) % Callback::ALL.len()],
+            seq_num: words[1].load(Ordering::Relaxed),
+            msg_type: [packed[2], packed[3], packed[4], packed[5]],
+            text: [0; TEXT_LEN],
+            len: packed[1].min(TEXT_LEN as u8),
+        };
+        for (bytes, word) in record.text.chunks_exact_mut(8).zip(&words[3..]) {
+            bytes.copy_from_slice(&word.load(Ordering::Relaxed).to_le_bytes());
+        }
+        record
+    }
+}
+
+/// `HH:MM:SS.mmm callback 35=D 34=12 11=ORD1 55=AAPL`
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = (self.at / 1_000_000_000) as i64;
+        let millis = self.at / 1_000_000 % 1_000;
+        write!(
+            f,
+            "{}.{millis:03} {}",
+            time_of_day(seconds),
+            self.callback.as_str()
+        )?;
+        if !self.msg_type().is_empty() {
+            write!(f, " 35={}", self.msg_type())?;
+        }
+        if self.seq_num > 0 {
+            write!(f, " 34={}", self.seq_num)?;
+        }
+        if self.len > 0 {
+            write!(f, " {}", self.text())?;
+        }
+        Ok(())
+    }
+}
+
+/// The longest prefix of `text` within `max` bytes ending on a character
+fn floor_char_boundary(text: &str, max: usize) -> usize {
+    if text.len() <= max {
+        return text.len();
+    }
+    (0..=max)
+        .rev()
+        .find(|x| text.is_char_boundary(*x))
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Ring
+// =============================================================================
+
+struct Ring {
+    /// Whose records, for the lines written
+    name: String,
+    slots: Box<[[AtomicU64; WORDS]]>,
+
+    /// Records read, and written, since the start; tail - head are waiting
+    head: AtomicUsize,
+    tail: AtomicUsize,
+
+    dropped: AtomicU64,
+}
+
+impl Ring {
+    fn new(name: &str, capacity: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            slots: (0..capacity.max(1))
+                .map(|_| std::array::from_fn(|_| AtomicU64::new(0)))
+                .collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// The next record waiting, if any; consumer side only
+    fn pop(&self) -> Option<Record> {
+        let head = self.head.load(Ordering::Relaxed);
+        if head == self.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        let record = Record::decode(&self.slots[head % self.slots.len()]);
+        // The slot is the producer's again once head moves past it
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(record)
+    }
+}
+
+/// The writing end of one ring
+///
+/// `push` takes `&mut self`: one producer per ring. Callbacks shared by
+/// sessions keep one per session, behind a Mutex of their own.
+pub struct Producer {
+    ring: Arc<Ring>,
+}
+
+impl Producer {
+    /// Copy a record into the ring; false if the ring was full and the
+    /// record dropped
+    pub fn push(&mut self, record: &Record) -> bool {
+        let ring = &self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        if tail.wrapping_sub(ring.head.load(Ordering::Acquire)) >= ring.slots.len() {
+            ring.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        record.encode(&ring.slots[tail % ring.slots.len()]);
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// Records dropped so far, the ring being full
+    pub fn dropped(&self) -> u64 {
+        self.ring.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for Producer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Producer")
+            .field("name", &self.ring.name)
+            .field("capacity", &self.ring.slots.len())
+            .finish()
+    }
+}
+
+// =============================================================================
+// Writer
+// =============================================================================
+
+/// The rings of every producer and the thread writing them out
+pub struct CallbackLog {
+    rings: Arc<Mutex<Vec<Arc<Ring>>>>,
+    capacity: usize,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl CallbackLog {
+    /// Start the writer thread, rings of DEFAULT_CAPACITY records
+    pub fn spawn<W: Write + Send + 'static>(out: W) -> io::Result<Self> {
+        Self::with_capacity(out, DEFAULT_CAPACITY)
+    }
+
+    /// Start the writer thread, rings of `capacity` records
+    pub fn with_capacity<W: Write + Send + 'static>(out: W, capacity: usize) -> io::Result<Self> {
+        let rings: Arc<Mutex<Vec<Arc<Ring>>>> = Arc::default();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let rings = Arc::clone(&rings);
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name("callback-log".to_string())
+                .spawn(move || write_loop(&rings, &stop, BufWriter::new(out)))?
+        };
+        Ok(Self {
+            rings,
+            capacity,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// A ring of its own for `name`, its records written as `[name] ...`
+    pub fn producer(&self, name: &str) -> Producer {
+        let ring = Arc::new(Ring::new(name, self.capacity));
+        self.rings
+            .lock()
+            .expect("callback log lock poisoned")
+            .push(Arc::clone(&ring));
+        Producer { ring }
+    }
+
+    /// Records dropped so far, every ring together
+    pub fn dropped(&self) -> u64 {
+        self.rings
+            .lock()
+            .expect("callback log lock poisoned")
+            .iter()
+            .map(|x| x.dropped.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Write what is left in the rings and stop the thread
+    ///
+    /// # Returns
+    /// The records dropped, every ring together
+    pub fn stop(mut self) -> io::Result<u64> {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("callback log thread panicked")))?;
+        }
+        Ok(self.dropped())
+    }
+}
+
+impl fmt::Debug for CallbackLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallbackLog")
+            .field("capacity", &self.capacity)
+            .field("dropped", &self.dropped())
+            .finish()
+    }
+}
+
+/// Drain the rings until stopped and drained
+fn write_loop<W: Write>(
+    rings: &Mutex<Vec<Arc<Ring>>>,
+    stop: &AtomicBool,
+    mut out: BufWriter<W>,
+) -> io::Result<()> {
+    // Drops reported so far, by ring
+    let mut reported: Vec<u64> = Vec::new();
+    loop {
+        // Read before draining: a stop seen here leaves nothing behind
+        let stopping = stop.load(Ordering::Acquire);
+        let rings: Vec<_> = rings.lock().expect("callback log lock poisoned").clone();
+        reported.resize(rings.len(), 0);
+
+        let mut written = 0;
+        for (ring, reported) in rings.iter().zip(&mut reported) {
+            while let Some(record) = ring.pop() {
+                writeln!(out, "[{}] {record}", ring.name)?;
+                written += 1;
+            }
+            let dropped = ring.dropped.load(Ordering::Relaxed);
+            if dropped > *reported {
+                writeln!(
+                    out,
+                    "[{}] {} record(s) dropped, the ring being full",
+                    ring.name,
+                    dropped - *reported
+                )?;
+                *reported = dropped;
+            }
+        }
+
+        if written == 0 {
+            out.flush()?;
+            if stopping {
+                return Ok(());
+            }
+            thread::sleep(IDLE);
+        }
+    }
+}
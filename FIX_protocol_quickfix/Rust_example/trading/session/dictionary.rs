@@ -0,0 +1,785 @@
+// =============================================================================
+// Tag Dictionary
+// =============================================================================
+// Names and types of the FIX fields, so tools can print `OrdType=2` rather
+// than `40=2`, check a value before it goes out, and take a field by name.
+// The standard part is built in: the header and trailer and the common
+// order, execution, market data and reject fields, with their types, and
+// the values of the enumerated ones among them (MsgType, Side, OrdType,
+// TimeInForce, OrdStatus, ExecType...). A value can be given by its name,
+// `Side=Buy` for `54=1`: case, spaces and punctuation do not count.
+//
+// Venues add their own fields (user-defined tags, 5000 and up) and their
+// own values for standard ones. Those come from TOML files, merged over the
+// standard dictionary in the order given, one table per tag:
+//
+//   [5001]
+//   name = "VenueOrderClass"
+//   type = "CHAR"
+//
+//   [5001.values]
+//   A = "Agency"
+//   P = "Principal"
+//
+//   # Pegged orders, a value the standard table does not list
+//   [40.values]
+//   P = "Pegged"
+//
+// A table for a standard tag keeps its name and type unless it sets them.
+// A venue field with values only accepts those; values given for a standard
+// field only name them, and its format alone is checked. Only this subset
+// of TOML is read: tables, `key = value` lines and `#` comments.
+// =============================================================================
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+/// A dictionary file that could not be loaded
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DictionaryError {
+    Io(PathBuf, io::Error),
+
+    /// A line of a dictionary file could not be read
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+
+    /// Two tags with the same name: names must find one tag
+    DuplicateName {
+        name: String,
+        tag: i32,
+        other: i32,
+    },
+}
+
+impl fmt::Display for DictionaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DictionaryError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            DictionaryError::Syntax {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+            DictionaryError::DuplicateName { name, tag, other } => {
+                write!(f, "{name} names both tag {other} and tag {tag}")
+            }
+        }
+    }
+}
+
+impl Error for DictionaryError {}
+
+/// A value the dictionary does not accept for its field
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FieldError {
+    /// Not in the format of the field's type
+    BadFormat {
+        tag: i32,
+        value: String,
+        field_type: FieldType,
+    },
+
+    /// Not one of the field's values
+    UnknownValue { tag: i32, value: String },
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldError::BadFormat {
+                tag,
+                value,
+                field_type,
+            } => write!(f, "{tag}={value} is not a {}", field_type.as_str()),
+            FieldError::UnknownValue { tag, value } => {
+                write!(f, "{tag}={value} is not one of the values of tag {tag}")
+            }
+        }
+    }
+}
+
+impl Error for FieldError {}
+
+// =============================================================================
+// Fields
+// =============================================================================
+
+/// Value formats, with the FIX types each one covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    /// INT, LENGTH, SEQNUM, NUMINGROUP, TAGNUM, DAYOFMONTH
+    Int,
+
+    /// FLOAT, QTY, PRICE, PRICEOFFSET, AMT, PERCENTAGE
+    Float,
+
+    /// CHAR
+    Char,
+
+    /// BOOLEAN: Y or N
+    Boolean,
+
+    /// STRING, CURRENCY, EXCHANGE, COUNTRY, DATA, ...: anything
+    String,
+
+    /// MULTIPLEVALUESTRING, MULTIPLECHARVALUE: values separated by spaces
+    MultipleValue,
+
+    /// UTCTIMESTAMP: YYYYMMDD-HH:MM:SS[.sss]
+    UtcTimestamp,
+
+    /// UTCDATEONLY, LOCALMKTDATE: YYYYMMDD
+    Date,
+}
+
+impl FieldType {
+    /// Type from its FIX name, case insensitive
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_uppercase().as_str() {
+            "INT" | "LENGTH" | "SEQNUM" | "NUMINGROUP" | "TAGNUM" | "DAYOFMONTH" => Self::Int,
+            "FLOAT" | "QTY" | "PRICE" | "PRICEOFFSET" | "AMT" | "PERCENTAGE" => Self::Float,
+            "CHAR" => Self::Char,
+            "BOOLEAN" => Self::Boolean,
+            "STRING" | "CURRENCY" | "EXCHANGE" | "COUNTRY" | "DATA" | "MONTHYEAR"
+            | "UTCTIMEONLY" | "LANGUAGE" => Self::String,
+            "MULTIPLEVALUESTRING" | "MULTIPLECHARVALUE" | "MULTIPLESTRINGVALUE" => {
+                Self::MultipleValue
+            }
+            "UTCTIMESTAMP" => Self::UtcTimestamp,
+            "UTCDATEONLY" | "UTCDATE" | "LOCALMKTDATE" => Self::Date,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FieldType::Int => "INT",
+            FieldType::Float => "FLOAT",
+            FieldType::Char => "CHAR",
+            FieldType::Boolean => "BOOLEAN",
+            FieldType::String => "STRING",
+            FieldType::MultipleValue => "MULTIPLEVALUESTRING",
+            FieldType::UtcTimestamp => "UTCTIMESTAMP",
+            FieldType::Date => "LOCALMKTDATE",
+        }
+    }
+
+    /// Whether a value is in this format
+    pub fn accepts(self, value: &str) -> bool {
+        let digits = |x: &str| !x.is_empty() && x.bytes().all(|b| b.is_ascii_digit());
+        match self {
+            FieldType::Int => digits(value.strip_prefix('-').unwrap_or(value)),
+            FieldType::Float => {
+                let unsigned = value.strip_prefix('-').unwrap_or(value);
+                let (int, frac) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+                !unsigned.is_empty()
+                    && unsigned != "."
+                    && int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+            }
+            FieldType::Char => value.chars().count() == 1,
+            FieldType::Boolean => matches!(value, "Y" | "N"),
+            FieldType::String => !value.is_empty(),
+            FieldType::MultipleValue => !value.trim().is_empty(),
+            FieldType::UtcTimestamp => {
+                let Some((date, time)) = value.split_once('-') else {
+                    return false;
+                };
+                let (hms, millis) = time.split_once('.').unwrap_or((time, "0"));
+                let parts: Vec<_> = hms.split(':').collect();
+                FieldType::Date.accepts(date)
+                    && parts.len() == 3
+                    && parts.iter().all(|x| x.len() == 2 && digits(x))
+                    && digits(millis)
+            }
+            FieldType::Date => value.len() == 8 && digits(value),
+        }
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One field: its name, type and, for enumerated fields, its values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDef {
+    pub tag: i32,
+    pub name: String,
+    pub field_type: FieldType,
+
+    /// Value and description, in file order
+    pub values: Vec<(String, String)>,
+
+    /// Only the values listed are accepted (venue fields that list some)
+    pub closed: bool,
+
+    /// Defined or changed by a dictionary file
+    pub custom: bool,
+}
+
+impl FieldDef {
+    /// Description of a value, if the field lists it
+    pub fn value_name(&self, value: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(x, _)| x == value)
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// The listed value a text stands for: the value itself, or the one it
+    /// is the description of (`Buy`, `good_till_cancel`)
+    pub fn value_of(&self, value_or_name: &str) -> Option<&str> {
+        let key = name_key(value_or_name);
+        self.values
+            .iter()
+            .find(|(x, _)| x == value_or_name)
+            .or_else(|| self.values.iter().find(|(_, name)| name_key(name) == key))
+            .map(|(value, _)| value.as_str())
+    }
+
+    /// Check a value against the type and the values of the field
+    pub fn validate(&self, value: &str) -> Result<(), FieldError> {
+        if !self.field_type.accepts(value) {
+            return Err(FieldError::BadFormat {
+                tag: self.tag,
+                value: value.to_string(),
+                field_type: self.field_type,
+            });
+        }
+
+        let listed = |x: &str| self.values.iter().any(|(v, _)| v == x);
+        let known = match self.field_type {
+            _ if !self.closed => true,
+            FieldType::MultipleValue => value.split_whitespace().all(listed),
+            _ => listed(value),
+        };
+        if !known {
+            return Err(FieldError::UnknownValue {
+                tag: self.tag,
+                value: value.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Standard Fields
+// =============================================================================
+
+/// The built-in fields: tag, name and type
+const STANDARD_FIELDS: &[(i32, &str, FieldType)] = &[
+    (1, "Account", FieldType::String),
+    (6, "AvgPx", FieldType::Float),
+    (7, "BeginSeqNo", FieldType::Int),
+    (8, "BeginString", FieldType::String),
+    (9, "BodyLength", FieldType::Int),
+    (10, "CheckSum", FieldType::String),
+    (11, "ClOrdID", FieldType::String),
+    (14, "CumQty", FieldType::Float),
+    (15, "Currency", FieldType::String),
+    (16, "EndSeqNo", FieldType::Int),
+    (17, "ExecID", FieldType::String),
+    (20, "ExecTransType", FieldType::Char),
+    (21, "HandlInst", FieldType::Char),
+    (31, "LastPx", FieldType::Float),
+    (32, "LastQty", FieldType::Float),
+    (34, "MsgSeqNum", FieldType::Int),
+    (35, "MsgType", FieldType::String),
+    (36, "NewSeqNo", FieldType::Int),
+    (37, "OrderID", FieldType::String),
+    (38, "OrderQty", FieldType::Float),
+    (39, "OrdStatus", FieldType::Char),
+    (40, "OrdType", FieldType::Char),
+    (41, "OrigClOrdID", FieldType::String),
+    (43, "PossDupFlag", FieldType::Boolean),
+    (44, "Price", FieldType::Float),
+    (45, "RefSeqNum", FieldType::Int),
+    (49, "SenderCompID", FieldType::String),
+    (52, "SendingTime", FieldType::UtcTimestamp),
+    (54, "Side", FieldType::Char),
+    (55, "Symbol", FieldType::String),
+    (56, "TargetCompID", FieldType::String),
+    (58, "Text", FieldType::String),
+    (59, "TimeInForce", FieldType::Char),
+    (60, "TransactTime", FieldType::UtcTimestamp),
+    (97, "PossResend", FieldType::Boolean),
+    (98, "EncryptMethod", FieldType::Int),
+    (99, "StopPx", FieldType::Float),
+    (108, "HeartBtInt", FieldType::Int),
+    (112, "TestReqID", FieldType::String),
+    (122, "OrigSendingTime", FieldType::UtcTimestamp),
+    (123, "GapFillFlag", FieldType::Boolean),
+    (141, "ResetSeqNumFlag", FieldType::Boolean),
+    (150, "ExecType", FieldType::Char),
+    (151, "LeavesQty", FieldType::Float),
+    (262, "MDReqID", FieldType::String),
+    (263, "SubscriptionRequestType", FieldType::Char),
+    (264, "MarketDepth", FieldType::Int),
+    (268, "NoMDEntries", FieldType::Int),
+    (269, "MDEntryType", FieldType::Char),
+    (270, "MDEntryPx", FieldType::Float),
+    (271, "MDEntrySize", FieldType::Float),
+    (279, "MDUpdateAction", FieldType::Char),
+    (371, "RefTagID", FieldType::Int),
+    (372, "RefMsgType", FieldType::String),
+    (373, "SessionRejectReason", FieldType::Int),
+    (379, "BusinessRejectRefID", FieldType::String),
+    (380, "BusinessRejectReason", FieldType::Int),
+    (553, "Username", FieldType::String),
+    (554, "Password", FieldType::String),
+    (1128, "ApplVerID", FieldType::String),
+    (1137, "DefaultApplVerID", FieldType::String),
+];
+
+/// Values of the built-in enumerated fields, with their descriptions
+///
+/// Not every value FIX defines, the ones these examples send or receive.
+const STANDARD_VALUES: &[(i32, &[(&str, &str)])] = &[
+    (
+        20,
+        &[
+            ("0", "New"),
+            ("1", "Cancel"),
+            ("2", "Correct"),
+            ("3", "Status"),
+        ],
+    ),
+    (
+        21,
+        &[
+            ("1", "Automated, private"),
+            ("2", "Automated, public"),
+            ("3", "Manual"),
+        ],
+    ),
+    (
+        35,
+        &[
+            ("0", "Heartbeat"),
+            ("1", "Test request"),
+            ("2", "Resend request"),
+            ("3", "Reject"),
+            ("4", "Sequence reset"),
+            ("5", "Logout"),
+            ("8", "Execution report"),
+            ("9", "Order cancel reject"),
+            ("A", "Logon"),
+            ("D", "New order single"),
+            ("F", "Order cancel request"),
+            ("G", "Order cancel replace request"),
+            ("H", "Order status request"),
+            ("V", "Market data request"),
+            ("W", "Market data snapshot full refresh"),
+            ("X", "Market data incremental refresh"),
+            ("Y", "Market data request reject"),
+            ("j", "Business message reject"),
+            ("q", "Order mass cancel request"),
+            ("r", "Order mass cancel report"),
+        ],
+    ),
+    (
+        39,
+        &[
+            ("0", "New"),
+            ("1", "Partially filled"),
+            ("2", "Filled"),
+            ("3", "Done for day"),
+            ("4", "Canceled"),
+            ("5", "Replaced"),
+            ("6", "Pending cancel"),
+            ("7", "Stopped"),
+            ("8", "Rejected"),
+            ("A", "Pending new"),
+            ("C", "Expired"),
+            ("E", "Pending replace"),
+        ],
+    ),
+    (
+        40,
+        &[
+            ("1", "Market"),
+            ("2", "Limit"),
+            ("3", "Stop"),
+            ("4", "Stop limit"),
+        ],
+    ),
+    (
+        54,
+        &[
+            ("1", "Buy"),
+            ("2", "Sell"),
+            ("3", "Buy minus"),
+            ("4", "Sell plus"),
+            ("5", "Sell short"),
+            ("6", "Sell short exempt"),
+        ],
+    ),
+    (
+        59,
+        &[
+            ("0", "Day"),
+            ("1", "Good till cancel"),
+            ("2", "At the opening"),
+            ("3", "Immediate or cancel"),
+            ("4", "Fill or kill"),
+            ("6", "Good till date"),
+        ],
+    ),
+    (
+        150,
+        &[
+            ("0", "New"),
+            ("3", "Done for day"),
+            ("4", "Canceled"),
+            ("5", "Replaced"),
+            ("6", "Pending cancel"),
+            ("7", "Stopped"),
+            ("8", "Rejected"),
+            ("A", "Pending new"),
+            ("C", "Expired"),
+            ("D", "Restated"),
+            ("E", "Pending replace"),
+            ("F", "Trade"),
+            ("G", "Trade correct"),
+            ("H", "Trade cancel"),
+            ("I", "Order status"),
+        ],
+    ),
+    (
+        263,
+        &[
+            ("0", "Snapshot"),
+            ("1", "Snapshot plus updates"),
+            ("2", "Disable previous snapshot plus update request"),
+        ],
+    ),
+    (269, &[("0", "Bid"), ("1", "Offer"), ("2", "Trade")]),
+    (279, &[("0", "New"), ("1", "Change"), ("2", "Delete")]),
+];
+
+/// What a value name is compared on: letters and digits, lower case
+fn name_key(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|x| x.to_ascii_lowercase())
+        .collect()
+}
+
+// =============================================================================
+// Dictionary
+// =============================================================================
+
+#[derive(Debug, Clone, Default)]
+pub struct Dictionary {
+    fields: BTreeMap<i32, FieldDef>,
+
+    /// Tag by lower-case name
+    names: HashMap<String, i32>,
+}
+
+impl Dictionary {
+    /// The built-in fields
+    pub fn standard() -> Self {
+        let mut dictionary = Self::default();
+        for &(tag, name, field_type) in STANDARD_FIELDS {
+            let values = STANDARD_VALUES
+                .iter()
+                .find(|(x, _)| *x == tag)
+                .map(|(_, values)| {
+                    values
+                        .iter()
+                        .map(|&(value, name)| (value.to_string(), name.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default();
+            dictionary.names.insert(name.to_ascii_lowercase(), tag);
+            dictionary.fields.insert(
+                tag,
+                FieldDef {
+                    tag,
+                    name: name.to_string(),
+                    field_type,
+                    values,
+                    closed: false,
+                    custom: false,
+                },
+            );
+        }
+        dictionary
+    }
+
+    /// The built-in fields, merged with dictionary files in order
+    pub fn with_files(paths: &[PathBuf]) -> Result<Self, DictionaryError> {
+        let mut dictionary = Self::standard();
+        for path in paths {
+            dictionary.load(path)?;
+        }
+        Ok(dictionary)
+    }
+
+    /// Merge a dictionary file: its fields replace or extend ours
+    pub fn load(&mut self, path: &Path) -> Result<(), DictionaryError> {
+        let source =
+            fs::read_to_string(path).map_err(|err| DictionaryError::Io(path.to_path_buf(), err))?;
+        self.merge(path, &source)
+    }
+
+    /// Merge the source of a dictionary file (`path` is only for errors)
+    pub fn merge(&mut self, path: &Path, source: &str) -> Result<(), DictionaryError> {
+        for table in parse(path, source)? {
+            let standard = self.fields.get(&table.tag);
+            let name = table
+                .name
+                .or_else(|| standard.map(|x| x.name.clone()))
+                .ok_or_else(|| DictionaryError::Syntax {
+                    path: path.to_path_buf(),
+                    line: table.line,
+                    message: format!("tag {} needs a name", table.tag),
+                })?;
+            let field_type = table
+                .field_type
+                .or_else(|| standard.map(|x| x.field_type))
+                .unwrap_or(FieldType::String);
+            let closed = standard.map_or(!table.values.is_empty(), |x| x.closed);
+            let mut values = standard.map(|x| x.values.clone()).unwrap_or_default();
+            for (value, description) in table.values {
+                match values.iter_mut().find(|(x, _)| *x == value) {
+                    Some(known) => known.1 = description,
+                    None => values.push((value, description)),
+                }
+            }
+
+            let key = name.to_ascii_lowercase();
+            match self.names.get(&key) {
+                Some(&other) if other != table.tag => {
+                    return Err(DictionaryError::DuplicateName {
+                        name,
+                        tag: table.tag,
+                        other,
+                    })
+                }
+                _ => {}
+            }
+            if let Some(old) = self.fields.get(&table.tag) {
+                self.names.remove(&old.name.to_ascii_lowercase());
+            }
+            self.names.insert(key, table.tag);
+            self.fields.insert(
+                table.tag,
+                FieldDef {
+                    tag: table.tag,
+                    name,
+                    field_type,
+                    values,
+                    closed,
+                    custom: true,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    pub fn field(&self, tag: i32) -> Option<&FieldDef> {
+        self.fields.get(&tag)
+    }
+
+    pub fn name(&self, tag: i32) -> Option<&str> {
+        self.field(tag).map(|x| x.name.as_str())
+    }
+
+    /// Tag of a field name, case insensitive
+    pub fn tag(&self, name: &str) -> Option<i32> {
+        self.names.get(&name.to_ascii_lowercase()).copied()
+    }
+
+    /// A tag number, or the name of a field
+    pub fn resolve(&self, tag_or_name: &str) -> Option<i32> {
+        match tag_or_name.parse::<i32>() {
+            Ok(tag) => Some(tag).filter(|x| *x > 0),
+            Err(_) => self.tag(tag_or_name),
+        }
+    }
+
+    /// Description of a value of a field, if the dictionary lists it
+    pub fn value_name(&self, tag: i32, value: &str) -> Option<&str> {
+        self.field(tag)?.value_name(value)
+    }
+
+    /// A value of a field, or the name of one; anything else as given
+    pub fn resolve_value<'a>(&'a self, tag: i32, value_or_name: &'a str) -> &'a str {
+        self.field(tag)
+            .and_then(|x| x.value_of(value_or_name))
+            .unwrap_or(value_or_name)
+    }
+
+    /// Check a value; tags the dictionary does not know take anything
+    pub fn validate(&self, tag: i32, value: &str) -> Result<(), FieldError> {
+        match self.field(tag) {
+            Some(field) => field.validate(value),
+            None => Ok(()),
+        }
+    }
+
+    /// Every field a dictionary file defined or changed, by tag
+    pub fn custom_fields(&self) -> impl Iterator<Item = &FieldDef> {
+        self.fields.values().filter(|x| x.custom)
+    }
+
+    /// Number of fields known
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+// =============================================================================
+// File Parser
+// =============================================================================
+
+/// One `[tag]` table with its `[tag.values]`
+#[derive(Debug)]
+struct Table {
+    tag: i32,
+
+    /// Line of the `[tag]` header
+    line: usize,
+    name: Option<String>,
+    field_type: Option<FieldType>,
+    values: Vec<(String, String)>,
+}
+
+fn parse(path: &Path, source: &str) -> Result<Vec<Table>, DictionaryError> {
+    let syntax = |line, message: &str| DictionaryError::Syntax {
+        path: path.to_path_buf(),
+        line,
+        message: message.to_string(),
+    };
+
+    let mut tables: Vec<Table> = Vec::new();
+    // Inside a [tag.values] table
+    let mut in_values = false;
+    for (index, line) in source.lines().enumerate() {
+        let line_no = index + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header
+                .strip_suffix(']')
+                .map(str::trim)
+                .ok_or_else(|| syntax(line_no, "expected [tag] or [tag.values]"))?;
+            let (tag, values) = match header.strip_suffix(".values") {
+                Some(tag) => (tag, true),
+                None => (header, false),
+            };
+            let tag: i32 = tag
+                .parse()
+                .ok()
+                .filter(|x| *x > 0)
+                .ok_or_else(|| syntax(line_no, "table name must be a tag number"))?;
+
+            in_values = values;
+            match tables.iter().position(|x| x.tag == tag) {
+                // [tag.values] after its [tag]
+                Some(position) if values && position + 1 == tables.len() => {}
+                Some(_) => return Err(syntax(line_no, "tag defined twice")),
+                None => tables.push(Table {
+                    tag,
+                    line: line_no,
+                    name: None,
+                    field_type: None,
+                    values: Vec::new(),
+                }),
+            }
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| syntax(line_no, "expected key = value"))?;
+        let key = parse_value(key.trim())
+            .filter(|x| !x.is_empty())
+            .ok_or_else(|| syntax(line_no, "invalid key"))?;
+        let value = parse_value(value.trim())
+            .ok_or_else(|| syntax(line_no, "invalid string (unterminated or trailing text)"))?;
+        let table = tables
+            .last_mut()
+            .ok_or_else(|| syntax(line_no, "key outside of a [tag] table"))?;
+
+        if in_values {
+            if table.values.iter().any(|(x, _)| *x == key) {
+                return Err(syntax(line_no, "value listed twice"));
+            }
+            table.values.push((key, value));
+            continue;
+        }
+        match key.as_str() {
+            "name" if !value.is_empty() && !value.contains(['=', '|', ' ']) => {
+                table.name = Some(value)
+            }
+            "name" => return Err(syntax(line_no, "name must be a word")),
+            "type" => {
+                let field_type = FieldType::from_name(&value)
+                    .ok_or_else(|| syntax(line_no, "unknown type (INT, PRICE, CHAR, STRING...)"))?;
+                table.field_type = Some(field_type);
+            }
+            _ => return Err(syntax(line_no, "key must be name or type")),
+        }
+    }
+    Ok(tables)
+}
+
+/// A `#` outside of a string starts a comment
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// A quoted string (with \" and \\ escapes) or a bare value
+fn parse_value(raw: &str) -> Option<String> {
+    let Some(quoted) = raw.strip_prefix('"') else {
+        return Some(raw.to_string());
+    };
+
+    let mut out = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return chars.as_str().trim().is_empty().then_some(out),
+            '\\' => out.push(chars.next()?),
+            c => out.push(c),
+        }
+    }
+    None
+}
@@ -0,0 +1,720 @@
+// =============================================================================
+// Configuration Doctor
+// =============================================================================
+// QuickFIX finds most configuration mistakes when the connection handler is
+// created or started, one at a time, with a terse ConfigError. Diagnosis
+// goes through a whole file beforehand and reports everything at once, each
+// finding with what to do about it:
+//
+//   sessions       BeginString / SenderCompID / TargetCompID present and
+//                  valid, no SessionID twice, ConnectionType known
+//   ports          each SocketAcceptPort free to listen on; each initiator
+//                  with a SocketConnectHost that resolves and a port
+//   dictionaries   DataDictionary (FIX.4.x) or TransportDataDictionary and
+//                  AppDataDictionary (FIXT.1.1) present, well-formed XML with
+//                  the sections QuickFIX reads, of the session's version
+//   directories    FileStorePath and FileLogPath writable, or creatable
+//   hours          StartTime / EndTime / StartDay / EndDay readable (see
+//                  schedule), HeartBtInt set for initiators
+//
+// Nothing is changed: directories are probed with a file removed at once and
+// ports with a listener closed at once. Relative paths are taken from the
+// current directory, as QuickFIX takes them: run it from where the engine
+// runs. A port in use may be the engine itself, already running.
+// =============================================================================
+
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    io::ErrorKind,
+    net::{TcpListener, ToSocketAddrs},
+    path::Path,
+};
+
+use crate::session::{
+    schedule::{ScheduleError, SessionSchedule},
+    session_label,
+    settings::{ConfigFile, SessionBlock},
+    version::{is_begin_string, FIXT_1_1},
+};
+
+/// Sections of a data dictionary QuickFIX needs
+const DICTIONARY_SECTIONS: [&str; 4] = ["header", "trailer", "messages", "fields"];
+
+// =============================================================================
+// Findings
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// QuickFIX will refuse the file, or the session cannot work
+    Error,
+
+    /// Works, but probably not as meant
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// One problem of a configuration, and what to do about it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+
+    /// Label of the session, none for the file as a whole
+    pub session: Option<String>,
+
+    /// Setting at fault, if one is
+    pub key: Option<&'static str>,
+
+    pub problem: String,
+
+    /// What to do about it
+    pub fix: String,
+}
+
+impl fmt::Display for Finding {
+    /// `error FIX.4.4:A->B SocketAcceptPort: port 5001 is in use`, then the
+    /// fix on its own line
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<7}", self.severity.as_str())?;
+        if let Some(session) = &self.session {
+            write!(f, " {session}")?;
+        }
+        if let Some(key) = self.key {
+            write!(f, " {key}:")?;
+        }
+        write!(f, " {}\n        -> {}", self.problem, self.fix)
+    }
+}
+
+/// Every finding on a configuration
+#[derive(Debug, Clone, Default)]
+pub struct Diagnosis {
+    pub findings: Vec<Finding>,
+
+    /// Sessions looked at
+    pub sessions: usize,
+}
+
+impl Diagnosis {
+    pub fn errors(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|x| x.severity == severity)
+            .count()
+    }
+
+    /// Whether QuickFIX should start with this configuration
+    pub fn is_ok(&self) -> bool {
+        self.errors() == 0
+    }
+
+    fn push(
+        &mut self,
+        severity: Severity,
+        session: Option<&str>,
+        key: Option<&'static str>,
+        problem: String,
+        fix: &str,
+    ) {
+        self.findings.push(Finding {
+            severity,
+            session: session.map(str::to_string),
+            key,
+            problem,
+            fix: fix.to_string(),
+        });
+    }
+}
+
+// =============================================================================
+// Doctor
+// =============================================================================
+
+/// Which checks run; all of them by default
+#[derive(Debug, Clone, Copy)]
+pub struct Doctor {
+    /// Bind each acceptor port
+    ports: bool,
+
+    /// Resolve each SocketConnectHost
+    hosts: bool,
+}
+
+impl Default for Doctor {
+    fn default() -> Self {
+        Self {
+            ports: true,
+            hosts: true,
+        }
+    }
+}
+
+impl Doctor {
+    /// Skip binding the acceptor ports, e.g. next to the running engine
+    pub fn with_ports(mut self, ports: bool) -> Self {
+        self.ports = ports;
+        self
+    }
+
+    /// Skip resolving the initiator hosts, e.g. on a machine without DNS
+    pub fn with_hosts(mut self, hosts: bool) -> Self {
+        self.hosts = hosts;
+        self
+    }
+
+    /// Check a configuration file, its variables already replaced
+    pub fn diagnose(&self, config: &ConfigFile) -> Diagnosis {
+        let mut diagnosis = Diagnosis::default();
+        let sessions = config.sessions();
+        diagnosis.sessions = sessions.len();
+        if sessions.is_empty() {
+            diagnosis.push(
+                Severity::Error,
+                None,
+                None,
+                "no [SESSION] block".to_string(),
+                "add one per counterparty, with BeginString, SenderCompID and TargetCompID",
+            );
+        }
+
+        // A port, path or dictionary shared by several sessions is checked
+        // once, under the first of them
+        let mut seen_ids = HashSet::new();
+        let mut checked = HashSet::new();
+        for block in &sessions {
+            let label = match check_identity(block, &mut diagnosis) {
+                Some(label) => label,
+                None => continue,
+            };
+            let qualifier = block.get("SessionQualifier").unwrap_or_default();
+            if !seen_ids.insert((label.clone(), qualifier.to_string())) {
+                diagnosis.push(
+                    Severity::Error,
+                    Some(&label),
+                    None,
+                    "the same SessionID is configured twice".to_string(),
+                    "remove one block, or tell them apart with a SessionQualifier",
+                );
+                continue;
+            }
+            self.connection(block, &label, &mut checked, &mut diagnosis);
+            check_dictionaries(block, &label, &mut checked, &mut diagnosis);
+            for key in ["FileStorePath", "FileLogPath"] {
+                if let Some(dir) = block.get(key) {
+                    if checked.insert((key, dir.to_string())) {
+                        check_directory(key, Path::new(dir), &label, &mut diagnosis);
+                    }
+                }
+            }
+            check_hours(block, &label, &mut diagnosis);
+        }
+        diagnosis
+    }
+
+    /// ConnectionType, and the port or host it needs
+    fn connection(
+        &self,
+        block: &SessionBlock,
+        label: &str,
+        checked: &mut HashSet<(&'static str, String)>,
+        diagnosis: &mut Diagnosis,
+    ) {
+        match block.get("ConnectionType") {
+            Some("acceptor") => {
+                let Some(port) = parse_port(block, "SocketAcceptPort", label, diagnosis) else {
+                    return;
+                };
+                if self.ports && checked.insert(("SocketAcceptPort", port.to_string())) {
+                    if let Err(err) = TcpListener::bind(("0.0.0.0", port)) {
+                        let fix = if err.kind() == ErrorKind::AddrInUse {
+                            "stop what listens on it (the engine already running?) or pick \
+                             another port"
+                        } else {
+                            "pick a port this user may listen on (1024 and above)"
+                        };
+                        diagnosis.push(
+                            Severity::Error,
+                            Some(label),
+                            Some("SocketAcceptPort"),
+                            format!("cannot listen on port {port}: {err}"),
+                            fix,
+                        );
+                    }
+                }
+            }
+            Some("initiator") => {
+                let port = parse_port(block, "SocketConnectPort", label, diagnosis);
+                let Some(host) = block.get("SocketConnectHost") else {
+                    diagnosis.push(
+                        Severity::Error,
+                        Some(label),
+                        Some("SocketConnectHost"),
+                        "missing".to_string(),
+                        "set the counterparty's host name or address",
+                    );
+                    return;
+                };
+                if let (true, Some(port)) = (self.hosts, port) {
+                    if checked.insert(("SocketConnectHost", host.to_string())) {
+                        if let Err(err) = (host, port).to_socket_addrs() {
+                            diagnosis.push(
+                                Severity::Warning,
+                                Some(label),
+                                Some("SocketConnectHost"),
+                                format!("{host} does not resolve: {err}"),
+                                "check the name, or the DNS of this machine",
+                            );
+                        }
+                    }
+                }
+                match block.get("HeartBtInt").map(str::parse::<u32>) {
+                    Some(Ok(seconds)) if seconds > 0 => {}
+                    Some(_) => diagnosis.push(
+                        Severity::Error,
+                        Some(label),
+                        Some("HeartBtInt"),
+                        format!(
+                            "not a positive number of seconds: {}",
+                            block.get("HeartBtInt").unwrap_or_default()
+                        ),
+                        "set the interval agreed with the counterparty, e.g. 30",
+                    ),
+                    None => diagnosis.push(
+                        Severity::Error,
+                        Some(label),
+                        Some("HeartBtInt"),
+                        "missing, initiators send it in the Logon".to_string(),
+                        "set the interval agreed with the counterparty, e.g. 30",
+                    ),
+                }
+            }
+            Some(other) => diagnosis.push(
+                Severity::Error,
+                Some(label),
+                Some("ConnectionType"),
+                format!("unknown type {other}"),
+                "use acceptor or initiator",
+            ),
+            None => diagnosis.push(
+                Severity::Error,
+                Some(label),
+                Some("ConnectionType"),
+                "missing".to_string(),
+                "set ConnectionType=acceptor or initiator in [DEFAULT]",
+            ),
+        }
+    }
+}
+
+/// Label of a block, or none (and a finding) if it has no valid SessionID
+fn check_identity(block: &SessionBlock, diagnosis: &mut Diagnosis) -> Option<String> {
+    let mut complete = true;
+    for key in ["BeginString", "SenderCompID", "TargetCompID"] {
+        if block.get(key).is_none_or(str::is_empty) {
+            diagnosis.push(
+                Severity::Error,
+                None,
+                Some(key),
+                format!(
+                    "missing from the [SESSION] block {}->{}",
+                    block.get("SenderCompID").unwrap_or("?"),
+                    block.get("TargetCompID").unwrap_or("?")
+                ),
+                "set it in the block, or in [DEFAULT] for every session",
+            );
+            complete = false;
+        }
+    }
+    if !complete {
+        return None;
+    }
+    let label = match block.session_id() {
+        Ok(session_id) => session_label(&session_id),
+        Err(err) => {
+            diagnosis.push(
+                Severity::Error,
+                None,
+                None,
+                format!("invalid SessionID: {err:?}"),
+                "check BeginString, SenderCompID and TargetCompID",
+            );
+            return None;
+        }
+    };
+    let begin_string = block.get("BeginString").unwrap_or_default();
+    if !is_begin_string(begin_string) {
+        diagnosis.push(
+            Severity::Error,
+            Some(&label),
+            Some("BeginString"),
+            format!("unknown version {begin_string}"),
+            "use FIX.4.0 to FIX.4.4, or FIXT.1.1 with a DefaultApplVerID",
+        );
+    }
+    Some(label)
+}
+
+/// A TCP port setting, or none (and a finding) if missing or invalid
+fn parse_port(
+    block: &SessionBlock,
+    key: &'static str,
+    label: &str,
+    diagnosis: &mut Diagnosis,
+) -> Option<u16> {
+    let Some(value) = block.get(key) else {
+        diagnosis.push(
+            Severity::Error,
+            Some(label),
+            Some(key),
+            "missing".to_string(),
+            "set the TCP port of the session",
+        );
+        return None;
+    };
+    match value.parse::<u16>() {
+        Ok(port) if port > 0 => Some(port),
+        _ => {
+            diagnosis.push(
+                Severity::Error,
+                Some(label),
+                Some(key),
+                format!("not a TCP port: {value}"),
+                "use a number from 1 to 65535",
+            );
+            None
+        }
+    }
+}
+
+// =============================================================================
+// Data Dictionaries
+// =============================================================================
+
+/// The dictionaries a session loads, when UseDataDictionary is on
+fn check_dictionaries(
+    block: &SessionBlock,
+    label: &str,
+    checked: &mut HashSet<(&'static str, String)>,
+    diagnosis: &mut Diagnosis,
+) {
+    if block.get("UseDataDictionary") == Some("N") {
+        return;
+    }
+    let begin_string = block.get("BeginString").unwrap_or_default();
+    let wanted: &[(&'static str, Option<&str>)] = if begin_string == FIXT_1_1 {
+        &[
+            ("TransportDataDictionary", Some(FIXT_1_1)),
+            ("AppDataDictionary", None),
+        ]
+    } else {
+        &[("DataDictionary", Some(begin_string))]
+    };
+    for &(key, version) in wanted {
+        // AppDataDictionary falls back on DataDictionary, as in QuickFIX
+        let path = block.get(key).or_else(|| {
+            (key == "AppDataDictionary")
+                .then(|| block.get("DataDictionary"))
+                .flatten()
+        });
+        let Some(path) = path else {
+            diagnosis.push(
+                Severity::Error,
+                Some(label),
+                Some(key),
+                "missing while UseDataDictionary is on".to_string(),
+                "point it at the spec file (e.g. spec/FIX44.xml), or set UseDataDictionary=N",
+            );
+            continue;
+        };
+        if !checked.insert((key, format!("{path} {begin_string}"))) {
+            continue;
+        }
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                diagnosis.push(
+                    Severity::Error,
+                    Some(label),
+                    Some(key),
+                    format!("cannot read {path}: {err}"),
+                    "check the path, relative to where the engine runs",
+                );
+                continue;
+            }
+        };
+        let root = match parse_xml(&text) {
+            Ok(root) => root,
+            Err(err) => {
+                diagnosis.push(
+                    Severity::Error,
+                    Some(label),
+                    Some(key),
+                    format!("{path} is not well-formed XML: {err}"),
+                    "fix the file, or take it again from the QuickFIX spec directory",
+                );
+                continue;
+            }
+        };
+        let missing: Vec<_> = DICTIONARY_SECTIONS
+            .iter()
+            .filter(|x| !root.children.iter().any(|child| child == *x))
+            .collect();
+        if root.name != "fix" || !missing.is_empty() {
+            diagnosis.push(
+                Severity::Error,
+                Some(label),
+                Some(key),
+                format!(
+                    "{path} is not a QuickFIX data dictionary (root <{}>, missing {:?})",
+                    root.name, missing
+                ),
+                "use a spec file of the QuickFIX distribution as a starting point",
+            );
+            continue;
+        }
+        if let Some(version) = version {
+            let found = format!(
+                "{}.{}.{}",
+                root.attribute("type").unwrap_or("FIX"),
+                root.attribute("major").unwrap_or("?"),
+                root.attribute("minor").unwrap_or("?")
+            );
+            if found != version {
+                diagnosis.push(
+                    Severity::Warning,
+                    Some(label),
+                    Some(key),
+                    format!("{path} describes {found}, the session is {version}"),
+                    "load the dictionary of the session's version",
+                );
+            }
+        }
+    }
+}
+
+/// Root element of an XML document: its name, attributes and the names of
+/// its children
+struct XmlRoot {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<String>,
+}
+
+impl XmlRoot {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Check that every element is closed in order, and return the root
+///
+/// Enough to catch a truncated or hand-broken spec file; entities, DTDs and
+/// namespaces are not looked at.
+fn parse_xml(text: &str) -> Result<XmlRoot, String> {
+    let line = |offset: usize| text[..offset].matches('\n').count() + 1;
+    let mut stack: Vec<&str> = Vec::new();
+    let mut root: Option<XmlRoot> = None;
+    let mut rest = 0;
+    while let Some(found) = text[rest..].find('<') {
+        let start = rest + found;
+        let tail = &text[start..];
+        let (skip_to, end_marker) = if tail.starts_with("<?") {
+            (2, "?>")
+        } else if tail.starts_with("<!--") {
+            (4, "-->")
+        } else if tail.starts_with("<!") {
+            (2, ">")
+        } else {
+            (1, "")
+        };
+        if !end_marker.is_empty() {
+            let end = tail[skip_to..].find(end_marker).ok_or_else(|| {
+                format!("line {}: unterminated {}", line(start), &tail[..skip_to])
+            })?;
+            rest = start + skip_to + end + end_marker.len();
+            continue;
+        }
+
+        // The tag runs to the first > outside quotes
+        let mut quote = None;
+        let end = tail
+            .char_indices()
+            .find(|&(_, c)| match quote {
+                Some(q) if c == q => {
+                    quote = None;
+                    false
+                }
+                Some(_) => false,
+                None if c == '"' || c == '\'' => {
+                    quote = Some(c);
+                    false
+                }
+                None => c == '>',
+            })
+            .map(|(index, _)| index)
+            .ok_or_else(|| format!("line {}: unterminated tag", line(start)))?;
+        let tag = &tail[1..end];
+        rest = start + end + 1;
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            match stack.pop() {
+                Some(open) if open == name => {}
+                Some(open) => {
+                    return Err(format!("line {}: </{name}> closes <{open}>", line(start)))
+                }
+                None => return Err(format!("line {}: </{name}> without <{name}>", line(start))),
+            }
+            continue;
+        }
+
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        if name.is_empty() {
+            return Err(format!("line {}: empty tag", line(start)));
+        }
+        match (&mut root, stack.len()) {
+            (None, 0) => {
+                root = Some(XmlRoot {
+                    name: name.to_string(),
+                    attributes: parse_attributes(&tag[name.len()..]),
+                    children: Vec::new(),
+                })
+            }
+            (Some(_), 0) => {
+                return Err(format!(
+                    "line {}: a second root element <{name}>",
+                    line(start)
+                ))
+            }
+            (Some(root), 1) => root.children.push(name.to_string()),
+            _ => {}
+        }
+        if !self_closing {
+            stack.push(name);
+        }
+    }
+
+    if let Some(open) = stack.last() {
+        return Err(format!("<{open}> is never closed"));
+    }
+    root.ok_or_else(|| "no element".to_string())
+}
+
+/// `key="value"` pairs of a tag
+fn parse_attributes(text: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = text;
+    while let Some((key, after)) = rest.split_once('=') {
+        let after = after.trim_start();
+        let Some(quote) = after.chars().next().filter(|x| *x == '"' || *x == '\'') else {
+            break;
+        };
+        let Some(end) = after[1..].find(quote) else {
+            break;
+        };
+        attributes.push((key.trim().to_string(), after[1..1 + end].to_string()));
+        rest = &after[end + 2..];
+    }
+    attributes
+}
+
+// =============================================================================
+// Directories and Hours
+// =============================================================================
+
+/// A directory QuickFIX writes to: writable if it exists, creatable if not
+fn check_directory(key: &'static str, dir: &Path, label: &str, diagnosis: &mut Diagnosis) {
+    if dir.exists() {
+        if !dir.is_dir() {
+            diagnosis.push(
+                Severity::Error,
+                Some(label),
+                Some(key),
+                format!("{} is a file, not a directory", dir.display()),
+                "point it at a directory",
+            );
+            return;
+        }
+        let probe = dir.join(".fix_doctor_probe");
+        match fs::write(&probe, b"") {
+            Ok(()) => {
+                let _ = fs::remove_file(&probe);
+            }
+            Err(err) => diagnosis.push(
+                Severity::Error,
+                Some(label),
+                Some(key),
+                format!("cannot write to {}: {err}", dir.display()),
+                "give the engine's user write access, or choose another directory",
+            ),
+        }
+        return;
+    }
+
+    // QuickFIX creates it: the closest existing parent must be writable
+    let parent = dir
+        .ancestors()
+        .skip(1)
+        .find(|x| x.as_os_str().is_empty() || x.exists())
+        .map(|x| {
+            if x.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                x
+            }
+        });
+    let writable = parent
+        .is_some_and(|x| fs::metadata(x).is_ok_and(|x| x.is_dir() && !x.permissions().readonly()));
+    if !writable {
+        diagnosis.push(
+            Severity::Error,
+            Some(label),
+            Some(key),
+            format!("{} does not exist and cannot be created", dir.display()),
+            "create it for the engine's user, or choose another directory",
+        );
+    }
+}
+
+/// The session hours, read as the scheduler reads them
+fn check_hours(block: &SessionBlock, label: &str, diagnosis: &mut Diagnosis) {
+    match SessionSchedule::from_settings(|key| block.get(key).map(str::to_string)) {
+        Ok(_) => {}
+        Err(ScheduleError::LocalTime) => diagnosis.push(
+            Severity::Warning,
+            Some(label),
+            None,
+            "session hours in local time".to_string(),
+            "fine for QuickFIX, but fix_repl --schedule only reads UTC hours",
+        ),
+        Err(err) => diagnosis.push(
+            Severity::Error,
+            Some(label),
+            None,
+            format!("session hours: {err}"),
+            "StartTime and EndTime as HH:MM:SS (UTC), StartDay and EndDay both or neither",
+        ),
+    }
+}
@@ -0,0 +1,149 @@
+// =============================================================================
+// Dry Run
+// =============================================================================
+// A new strategy or configuration can be tried against production sessions
+// without a single order reaching the counterparty: everything up to the
+// send runs as usual (strategy, risk, OMS, metrics, downstream feeds), then
+// the message is dropped and the counterparty's answer is made up locally.
+//
+// Dry run is switched on for every session (global) or for some of them
+// (by label), and can be switched on and off while running.
+//
+// The made-up answers are ExecutionReports (35=8) that the OMS applies like
+// real ones, flagged so nobody mistakes them for real ones:
+//
+//   35=D  ->  150=0 39=0   New
+//   35=F  ->  150=4 39=4   Canceled
+//   35=G  ->  150=5 39=0   Replaced
+//
+// with ExecID (17) and OrderID (37) prefixed DRY- and Text (58) = DRY RUN.
+// Nothing is filled: a dry-run order stays New until canceled. Other message
+// types are dropped without an answer.
+// =============================================================================
+
+use std::{
+    collections::BTreeSet,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use quickfix::{FieldMap, Message};
+
+use crate::session::{events::FixMessage, Direction};
+
+/// Text (58) of every made-up ExecutionReport
+pub const DRY_RUN_TEXT: &str = "DRY RUN";
+
+/// Whether a message was made up by a DryRun rather than received
+pub fn is_dry_run(msg: &FixMessage) -> bool {
+    msg.get(58) == Some(DRY_RUN_TEXT)
+}
+
+#[derive(Debug, Default)]
+pub struct DryRun {
+    /// Every session
+    global: AtomicBool,
+
+    /// Session labels, on top of global
+    sessions: Mutex<BTreeSet<String>>,
+
+    /// For ExecIDs and OrderIDs
+    next_id: AtomicU64,
+}
+
+impl DryRun {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_global(&self, enabled: bool) {
+        self.global.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_global(&self) -> bool {
+        self.global.load(Ordering::Relaxed)
+    }
+
+    /// Switch dry run on or off for one session (a label); global dry run
+    /// still applies to it
+    pub fn set_session(&self, label: &str, enabled: bool) {
+        let mut sessions = self.sessions.lock().expect("dry run lock poisoned");
+        if enabled {
+            sessions.insert(label.to_string());
+        } else {
+            sessions.remove(label);
+        }
+    }
+
+    /// Sessions in dry run by label, sorted
+    pub fn sessions(&self) -> Vec<String> {
+        let sessions = self.sessions.lock().expect("dry run lock poisoned");
+        sessions.iter().cloned().collect()
+    }
+
+    /// Whether messages to this session must not be sent
+    pub fn is_active(&self, label: &str) -> bool {
+        self.is_global()
+            || self
+                .sessions
+                .lock()
+                .expect("dry run lock poisoned")
+                .contains(label)
+    }
+
+    /// The answer the counterparty would have sent to `msg`
+    ///
+    /// # Arguments
+    /// * `label` - Session the message was meant for
+    ///
+    /// # Returns
+    /// An inbound ExecutionReport, flagged; None for message types other
+    /// than D, F and G
+    pub fn acknowledge(&self, label: &str, msg: &Message) -> Option<FixMessage> {
+        let msg_type = msg.with_header(|h| h.get_field(35)).unwrap_or_default();
+        let (exec_type, ord_status) = match msg_type.as_str() {
+            "D" => ("0", "0"),
+            "F" => ("4", "4"),
+            "G" => ("5", "0"),
+            _ => return None,
+        };
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cl_ord_id = msg.get_field(11).unwrap_or_default();
+        let orig_cl_ord_id = msg.get_field(41);
+        let quantity = msg.get_field(38).unwrap_or_default();
+        let leaves_qty = if exec_type == "4" { "0" } else { &quantity };
+
+        // The OrderID is made up from the ClOrdID of the original order
+        let order_id = format!("DRY-{}", orig_cl_ord_id.as_deref().unwrap_or(&cl_ord_id));
+        let mut fields = vec![
+            (35, "8".to_string()),
+            (37, order_id),
+            (11, cl_ord_id),
+        ];
+        if let Some(orig_cl_ord_id) = orig_cl_ord_id {
+            fields.push((41, orig_cl_ord_id));
+        }
+        fields.extend([
+            (17, format!("DRY-{id}")),
+            (150, exec_type.to_string()),
+            (39, ord_status.to_string()),
+            (55, msg.get_field(55).unwrap_or_default()),
+            (54, msg.get_field(54).unwrap_or_default()),
+            (38, quantity.clone()),
+            (151, leaves_qty.to_string()),
+            (14, "0".to_string()),
+            (6, "0".to_string()),
+            (58, DRY_RUN_TEXT.to_string()),
+        ]);
+
+        Some(FixMessage {
+            session: label.to_string(),
+            direction: Direction::Inbound,
+            admin: false,
+            fields,
+        })
+    }
+}
@@ -0,0 +1,368 @@
+// =============================================================================
+// End-of-Day Tasks
+// =============================================================================
+// What an operator would otherwise script around the close, run by the
+// process itself at a time relative to each session's close. Set in the
+// [SESSION] block (or [DEFAULT]) with keys QuickFIX ignores, one per task,
+// the offset from the close as [+-]HH:MM:SS (before the close when
+// negative):
+//
+//   EndOfDayFlatten=-00:15:00        flatten: cancel the working orders and
+//                                    close the net position of each symbol
+//   EndOfDayExportFills=00:01:00     export_fills: the day's fills to CSV
+//   EndOfDayArchiveStore=00:05:00    archive_store: the file store compacted
+//                                    into a gzip archive
+//   EndOfDayResetOrderIds=00:05:00   reset_order_ids: ClOrdIDs started over
+//   EndOfDaySummary=00:10:00         summary: the day's trading report
+//                                    (oms::report)
+//
+//   EndOfDayTime=HH:MM:SS            the close, every day, for a session
+//                                    that never closes (NonStopSession) or
+//                                    in place of its EndTime
+//   EndOfDayDir=DIR                  where exports, archives and summaries
+//                                    go (./eod by default)
+//   EndOfDayReportFormat=FORMAT      text (default), html or json
+//
+// The close is the end of the session window (schedule.rs), UTC like it.
+// The scheduler only says what is due; carrying it out is up to the
+// application, which holds the orders, fills and stores. A task whose time
+// passed while the process was down is not caught up.
+// =============================================================================
+
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use quickfix::SessionId;
+
+use super::{
+    handover::store_prefix,
+    schedule::{parse_time, ScheduleError, SessionSchedule},
+    session_label,
+    settings::ConfigFile,
+};
+use crate::{oms::report::ReportFormat, time::Date};
+
+const DAY: i64 = 86_400;
+
+/// Where the tasks write without EndOfDayDir
+pub const DEFAULT_DIR: &str = "eod";
+
+// =============================================================================
+// Actions
+// =============================================================================
+
+/// One end-of-day task, in the order they run when due at the same time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EodAction {
+    Flatten,
+    ExportFills,
+    ArchiveStore,
+    ResetOrderIds,
+    Summary,
+}
+
+impl EodAction {
+    pub const ALL: [EodAction; 5] = [
+        EodAction::Flatten,
+        EodAction::ExportFills,
+        EodAction::ArchiveStore,
+        EodAction::ResetOrderIds,
+        EodAction::Summary,
+    ];
+
+    /// Name in commands and logs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EodAction::Flatten => "flatten",
+            EodAction::ExportFills => "export_fills",
+            EodAction::ArchiveStore => "archive_store",
+            EodAction::ResetOrderIds => "reset_order_ids",
+            EodAction::Summary => "summary",
+        }
+    }
+
+    /// Setting giving its offset from the close
+    pub fn key(self) -> &'static str {
+        match self {
+            EodAction::Flatten => "EndOfDayFlatten",
+            EodAction::ExportFills => "EndOfDayExportFills",
+            EodAction::ArchiveStore => "EndOfDayArchiveStore",
+            EodAction::ResetOrderIds => "EndOfDayResetOrderIds",
+            EodAction::Summary => "EndOfDaySummary",
+        }
+    }
+}
+
+impl fmt::Display for EodAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EodAction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        EodAction::ALL
+            .into_iter()
+            .find(|x| x.as_str() == value)
+            .ok_or_else(|| {
+                let names: Vec<_> = EodAction::ALL.iter().map(|x| x.as_str()).collect();
+                format!("unknown end-of-day task {value}, not {}", names.join(", "))
+            })
+    }
+}
+
+// =============================================================================
+// Plans
+// =============================================================================
+
+/// A task and when it runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EodTask {
+    pub action: EodAction,
+
+    /// Seconds from the close, negative before it
+    pub offset: i64,
+}
+
+/// `flatten -00:15:00`
+impl fmt::Display for EodTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.offset < 0 { '-' } else { '+' };
+        let offset = self.offset.unsigned_abs();
+        write!(
+            f,
+            "{} {sign}{:02}:{:02}:{:02}",
+            self.action,
+            offset / 3_600,
+            offset / 60 % 60,
+            offset % 60
+        )
+    }
+}
+
+/// The end-of-day tasks of one session
+#[derive(Debug, Clone)]
+pub struct EodPlan {
+    /// Session label
+    pub session: String,
+
+    /// Windows the close is taken from; non-stop with EndOfDayTime
+    pub schedule: SessionSchedule,
+
+    /// EndOfDayTime, seconds from midnight UTC
+    pub day_end: Option<i64>,
+
+    /// By offset, then in the order of EodAction
+    pub tasks: Vec<EodTask>,
+
+    /// EndOfDayDir
+    pub dir: PathBuf,
+
+    /// EndOfDayReportFormat, of the summary
+    pub report_format: ReportFormat,
+
+    /// FileStorePath and the name of the session's files in it, for
+    /// archive_store
+    pub store_dir: Option<PathBuf>,
+    pub store_prefix: String,
+}
+
+impl EodPlan {
+    /// Read the tasks from the settings of a session
+    ///
+    /// # Arguments
+    /// * `get` - Value of a setting, [DEFAULT] included
+    pub fn from_settings<F>(session_id: &SessionId, get: F) -> Result<Self, ScheduleError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut tasks = Vec::new();
+        for action in EodAction::ALL {
+            if let Some(value) = get(action.key()) {
+                let offset =
+                    parse_offset(&value).ok_or(ScheduleError::Invalid(action.key(), value))?;
+                tasks.push(EodTask { action, offset });
+            }
+        }
+        tasks.sort_by_key(|x| (x.offset, x.action));
+
+        let day_end = get("EndOfDayTime")
+            .map(|x| parse_time(&x).ok_or(ScheduleError::Invalid("EndOfDayTime", x)))
+            .transpose()?;
+        let schedule = match day_end {
+            Some(_) => SessionSchedule::non_stop(),
+            None => SessionSchedule::from_settings(&get)?,
+        };
+        if !tasks.is_empty() && day_end.is_none() && schedule.is_non_stop() {
+            return Err(ScheduleError::Missing("EndOfDayTime"));
+        }
+        let report_format = get("EndOfDayReportFormat")
+            .map(|x| {
+                x.parse()
+                    .map_err(|_| ScheduleError::Invalid("EndOfDayReportFormat", x))
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Self {
+            session: session_label(session_id),
+            schedule,
+            day_end,
+            tasks,
+            dir: PathBuf::from(get("EndOfDayDir").unwrap_or_else(|| DEFAULT_DIR.to_string())),
+            report_format,
+            store_dir: get("FileStorePath").map(PathBuf::from),
+            store_prefix: store_prefix(session_id),
+        })
+    }
+
+    /// The first close after `now`
+    pub fn next_close(&self, now: i64) -> Option<i64> {
+        match self.day_end {
+            Some(day_end) => {
+                let close = now - now.rem_euclid(DAY) + day_end;
+                Some(if close > now { close } else { close + DAY })
+            }
+            None => self.schedule.next_close(now),
+        }
+    }
+
+    /// The last close at or before `now`, looked for over the past week
+    pub fn last_close(&self, now: i64) -> Option<i64> {
+        (1..=7).find_map(|days| self.next_close(now - days * DAY).filter(|x| *x <= now))
+    }
+
+    /// When a task runs next, and the close it belongs to
+    pub fn next_run(&self, task: &EodTask, now: i64) -> Option<(i64, i64)> {
+        let close = self.next_close(now - task.offset)?;
+        Some((close + task.offset, close))
+    }
+
+    /// Where a task writes the file of a day: `DIR/NAME-SESSION-YYYYMMDD.EXT`,
+    /// the session label made fit for a file name
+    pub fn path(&self, name: &str, close: i64, extension: &str) -> PathBuf {
+        let session: String = self
+            .session
+            .chars()
+            .map(|x| {
+                if x.is_ascii_alphanumeric() || x == '.' {
+                    x
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let date = Date::from_unix(close).to_fix();
+        self.dir
+            .join(format!("{name}-{session}-{date}.{extension}"))
+    }
+
+    /// Directory of the store archives
+    pub fn archive_dir(&self) -> PathBuf {
+        self.dir.join("store")
+    }
+}
+
+/// The end-of-day tasks of every [SESSION] block that has some
+pub fn read_eod_plans(config: &ConfigFile) -> Result<Vec<EodPlan>, ScheduleError> {
+    let mut plans = Vec::new();
+    for block in config.sessions() {
+        // The hours of a session without tasks are none of our business
+        if EodAction::ALL.iter().all(|x| block.get(x.key()).is_none()) {
+            continue;
+        }
+        let session_id = block
+            .session_id()
+            .map_err(|err| ScheduleError::Invalid("[SESSION]", format!("{err:?}")))?;
+        let plan = EodPlan::from_settings(&session_id, |key| block.get(key).map(str::to_string))
+            .map_err(|err| ScheduleError::Session(session_label(&session_id), Box::new(err)))?;
+        plans.push(plan);
+    }
+    Ok(plans)
+}
+
+/// `[+-]HH:MM:SS` in seconds, hours past 23 allowed
+fn parse_offset(value: &str) -> Option<i64> {
+    let (sign, time) = match value.strip_prefix('-') {
+        Some(time) => (-1, time),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let parts: Vec<i64> = time
+        .split(':')
+        .map(|x| x.parse().ok())
+        .collect::<Option<_>>()?;
+    match parts.as_slice() {
+        [h, m, s] if *h >= 0 && (0..60).contains(m) && (0..60).contains(s) => {
+            Some(sign * (h * 3_600 + m * 60 + s))
+        }
+        _ => None,
+    }
+}
+
+// =============================================================================
+// Scheduler
+// =============================================================================
+
+/// A task due, for the close of one day
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EodRun {
+    pub session: String,
+    pub action: EodAction,
+
+    /// The close it belongs to, Unix time
+    pub close: i64,
+}
+
+/// Turns the plans into the tasks due at each tick
+#[derive(Debug, Clone)]
+pub struct EodScheduler {
+    plans: Vec<EodPlan>,
+
+    /// Unix time of the last tick
+    last_tick: Option<i64>,
+}
+
+impl EodScheduler {
+    pub fn new(plans: Vec<EodPlan>) -> Self {
+        Self {
+            plans,
+            last_tick: None,
+        }
+    }
+
+    pub fn plans(&self) -> &[EodPlan] {
+        &self.plans
+    }
+
+    pub fn plan(&self, session: &str) -> Option<&EodPlan> {
+        self.plans.iter().find(|x| x.session == session)
+    }
+
+    /// Tasks whose time fell since the last tick, in order; the first tick
+    /// reports none
+    pub fn tick(&mut self, now: i64) -> Vec<EodRun> {
+        let Some(last) = self.last_tick.replace(now) else {
+            return Vec::new();
+        };
+        let mut due: Vec<_> = self
+            .plans
+            .iter()
+            .flat_map(|plan| {
+                plan.tasks.iter().filter_map(move |task| {
+                    let (at, close) = plan.next_run(task, last)?;
+                    (at <= now).then(|| {
+                        let run = EodRun {
+                            session: plan.session.clone(),
+                            action: task.action,
+                            close,
+                        };
+                        (at, run)
+                    })
+                })
+            })
+            .collect();
+        due.sort_by_key(|x| (x.0, x.1.action));
+        due.into_iter().map(|(_, run)| run).collect()
+    }
+}
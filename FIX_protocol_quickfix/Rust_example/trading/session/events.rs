@@ -0,0 +1,171 @@
+// =============================================================================
+// Decoded FIX Events
+// =============================================================================
+// QuickFIX callbacks run on engine threads and only lend us Message and
+// SessionId, which wrap C++ handles that cannot cross threads. Callbacks
+// therefore stay thin: they decode what they received into an owned FixEvent
+// and push it into a tokio mpsc channel, and business logic consumes the
+// channel from an async task:
+//
+//   engine thread --FixEvent--> mpsc channel --> async task (REPL, OMS, ...)
+//
+// The channel is unbounded on purpose: a callback must never block the engine
+// thread. Consumers are expected to keep up; if they cannot, the fix is in the
+// consumer, not in back-pressure on the FIX session.
+//
+// Other sources can share the channel: news feeds push FixEvent::News, so a
+// consumer sees headlines and market data in arrival order.
+//
+// When some MsgTypes must not wait behind the others, lanes.rs replaces the
+// channel with two: a fast lane for those, a batch lane for the rest.
+//
+// The event types are plain data and always available; the tokio channel
+// needs the `runtime` feature.
+// =============================================================================
+
+use quickfix::{Message, SessionId};
+#[cfg(feature = "runtime")]
+use tokio::sync::mpsc;
+
+use crate::{
+    news::NewsEvent,
+    session::{session_label, Direction},
+};
+
+#[cfg(feature = "runtime")]
+pub type EventSender = mpsc::UnboundedSender<FixEvent>;
+#[cfg(feature = "runtime")]
+pub type EventReceiver = mpsc::UnboundedReceiver<FixEvent>;
+
+/// Channel between the QuickFIX callbacks and the async consumer
+#[cfg(feature = "runtime")]
+pub fn channel() -> (EventSender, EventReceiver) {
+    mpsc::unbounded_channel()
+}
+
+/// Something that happened in the FIX engine, or next to it
+///
+/// Sessions are identified by their label (`FIX.4.4:SENDER->TARGET`).
+#[derive(Debug, Clone)]
+pub enum FixEvent {
+    Created { session: String },
+    Logon { session: String },
+    Logout { session: String },
+    Message(FixMessage),
+
+    /// A headline from a news feed (gateway::news), delivered in order with
+    /// the market data it may explain
+    News(NewsEvent),
+}
+
+impl FixEvent {
+    pub fn created(session: &SessionId) -> Self {
+        FixEvent::Created {
+            session: session_label(session),
+        }
+    }
+
+    pub fn logon(session: &SessionId) -> Self {
+        FixEvent::Logon {
+            session: session_label(session),
+        }
+    }
+
+    pub fn logout(session: &SessionId) -> Self {
+        FixEvent::Logout {
+            session: session_label(session),
+        }
+    }
+}
+
+/// Owned copy of a FIX message, decoded from its wire form
+#[derive(Debug, Clone)]
+pub struct FixMessage {
+    pub session: String,
+    pub direction: Direction,
+
+    /// Session-level message (Logon, Heartbeat, ...) rather than business
+    pub admin: bool,
+
+    /// Header, body and trailer fields in wire order; repeating groups keep
+    /// their instances in sequence
+    pub fields: Vec<(i32, String)>,
+}
+
+impl FixMessage {
+    pub fn decode(session: &SessionId, direction: Direction, admin: bool, msg: &Message) -> Self {
+        let text = msg.to_fix_string().unwrap_or_default();
+        let fields = text
+            .split('\x01')
+            .filter_map(|x| x.split_once('='))
+            .filter_map(|(tag, value)| Some((tag.parse().ok()?, value.to_string())))
+            .collect();
+
+        Self {
+            session: session_label(session),
+            direction,
+            admin,
+            fields,
+        }
+    }
+
+    /// First value of a tag, wherever it is (header, body or trailer)
+    pub fn get(&self, tag: i32) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(x, _)| *x == tag)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// MsgType (tag 35), empty if missing
+    pub fn msg_type(&self) -> &str {
+        self.get(35).unwrap_or_default()
+    }
+
+    /// MsgSeqNum (tag 34), empty if missing
+    pub fn seq_num(&self) -> &str {
+        self.get(34).unwrap_or_default()
+    }
+
+    /// Instances of a repeating group, each starting at its delimiter tag
+    ///
+    /// An instance runs until the next delimiter or the CheckSum (10), so
+    /// this fits groups that close the body, like the MDEntries of 35=W/X.
+    pub fn groups(&self, delimiter: i32) -> Vec<&[(i32, String)]> {
+        let mut starts: Vec<_> = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, (tag, _))| *tag == delimiter)
+            .map(|(index, _)| index)
+            .collect();
+        let end = self
+            .fields
+            .iter()
+            .rposition(|(tag, _)| *tag == 10)
+            .unwrap_or(self.fields.len());
+        starts.push(end);
+
+        starts
+            .windows(2)
+            .map(|x| &self.fields[x[0]..x[1].max(x[0])])
+            .collect()
+    }
+
+    /// Fields as `8=FIX.4.4|35=D|...`, for log lines
+    pub fn to_text(&self) -> String {
+        self.fields
+            .iter()
+            .map(|(tag, value)| format!("{tag}={value}"))
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+}
+
+/// First value of a tag within one repeating group instance
+pub fn group_field(group: &[(i32, String)], tag: i32) -> Option<&str> {
+    group
+        .iter()
+        .find(|(x, _)| *x == tag)
+        .map(|(_, value)| value.as_str())
+}
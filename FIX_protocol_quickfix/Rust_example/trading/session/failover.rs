@@ -0,0 +1,492 @@
+// =============================================================================
+// Gateway Failover
+// =============================================================================
+// Venues run a backup gateway next to the primary one, on another host or
+// in another site, for the day the primary goes dark. An initiator is told
+// about it in the [SESSION] block, with keys QuickFIX ignores:
+//
+//   FailoverConnectHost    host of the backup gateway
+//   FailoverConnectPort    its port, SocketConnectPort by default
+//   FailoverAfter          connect attempts in a row without a logon before
+//                          the switch, 3 by default
+//   FailoverResetSeqNum=Y  the backup keeps sequence numbers of its own: the
+//                          session's file store is emptied at each switch
+//
+// QuickFIX has failover hosts of its own (SocketConnectHost1, 2, ...), but
+// it moves to the next one at every failed attempt, so one dropped packet
+// sends a session from gateway to gateway. A Failover switches on a
+// sustained failure only, and stays on the gateway it switched to until that
+// one fails the same way.
+//
+// A refused connection reaches no callback: the attempts are counted from
+// the time a session has been down while the handler runs, one per
+// ReconnectInterval (30 seconds by default). A session down for FailoverAfter
+// of them is switched to its other gateway, backup then primary again.
+//
+// A switch keeps the SessionID, so the file store keeps the sequence
+// numbers: the Logon to the backup carries on from the last message to the
+// primary, and either side asks for what it missed, as after any reconnect.
+// With FailoverResetSeqNum both sides start again from MsgSeqNum 1.
+//
+// QuickFIX reads SocketConnectHost once, when the initiator is created: the
+// caller stops the handler, empties the stores the switches say to
+// (schedule::reset_store), and rebuilds it from Failover::settings.
+// =============================================================================
+
+use std::{collections::BTreeMap, error::Error, fmt, path::PathBuf};
+
+use quickfix::SessionSettings;
+
+use crate::session::{
+    handover::store_prefix,
+    session_label,
+    settings::{ConfigFile, SettingsError},
+};
+
+/// Connect attempts without a logon before the switch, without FailoverAfter
+pub const DEFAULT_FAILOVER_AFTER: u32 = 3;
+
+/// QuickFIX's ReconnectInterval default, in seconds
+const DEFAULT_RECONNECT_INTERVAL: i64 = 30;
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FailoverError {
+    /// The failover settings of a session are wrong: its label and why
+    Invalid(String, String),
+
+    /// No session of that label has a backup gateway
+    UnknownSession(String),
+
+    /// The settings could not be rebuilt with the gateways switched
+    Settings(SettingsError),
+}
+
+impl fmt::Display for FailoverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailoverError::Invalid(session, why) => write!(f, "{session}: {why}"),
+            FailoverError::UnknownSession(session) => {
+                write!(f, "{session} has no backup gateway (FailoverConnectHost)")
+            }
+            FailoverError::Settings(err) => write!(f, "settings: {err}"),
+        }
+    }
+}
+
+impl Error for FailoverError {}
+
+impl From<SettingsError> for FailoverError {
+    fn from(err: SettingsError) -> Self {
+        FailoverError::Settings(err)
+    }
+}
+
+// =============================================================================
+// Routes
+// =============================================================================
+
+/// Where an initiator connects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+/// One of the two gateways of a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gateway {
+    Primary,
+    Backup,
+}
+
+impl Gateway {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Gateway::Primary => "primary",
+            Gateway::Backup => "backup",
+        }
+    }
+
+    /// The gateway a switch goes to
+    pub fn other(self) -> Self {
+        match self {
+            Gateway::Primary => Gateway::Backup,
+            Gateway::Backup => Gateway::Primary,
+        }
+    }
+}
+
+/// A session with a backup gateway, as configured
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailoverRoute {
+    /// Session label
+    pub session: String,
+
+    /// SocketConnectHost / SocketConnectPort
+    pub primary: Endpoint,
+
+    /// FailoverConnectHost / FailoverConnectPort
+    pub backup: Endpoint,
+
+    /// Failed connect attempts in a row before the switch
+    pub after: u32,
+
+    /// Seconds between two connect attempts (ReconnectInterval)
+    pub reconnect_interval: i64,
+
+    /// FailoverResetSeqNum: the file store is emptied at each switch
+    pub reset_seq_num: bool,
+
+    /// FileStorePath, required with FailoverResetSeqNum
+    pub store_dir: Option<PathBuf>,
+
+    /// Name of the session's files in the store
+    pub store_prefix: String,
+}
+
+impl FailoverRoute {
+    pub fn endpoint(&self, gateway: Gateway) -> &Endpoint {
+        match gateway {
+            Gateway::Primary => &self.primary,
+            Gateway::Backup => &self.backup,
+        }
+    }
+}
+
+/// The routes of every [SESSION] block of a configuration file that has a
+/// backup gateway
+pub fn read_routes(config: &ConfigFile) -> Result<Vec<FailoverRoute>, FailoverError> {
+    let mut routes = Vec::new();
+    for block in config.sessions() {
+        if block.get("FailoverConnectHost").is_none() && block.get("FailoverConnectPort").is_none()
+        {
+            continue;
+        }
+        let session_id = block
+            .session_id()
+            .map_err(|err| FailoverError::Invalid("[SESSION]".into(), format!("{err:?}")))?;
+        let session = session_label(&session_id);
+        let invalid = |why: String| FailoverError::Invalid(session.clone(), why);
+
+        let port = |key: &str| match block.get(key) {
+            Some(port) => port
+                .parse::<u16>()
+                .map(Some)
+                .map_err(|_| invalid(format!("invalid {key}: {port:?}"))),
+            None => Ok(None),
+        };
+        let (Some(host), Some(primary_port)) =
+            (block.get("SocketConnectHost"), port("SocketConnectPort")?)
+        else {
+            return Err(invalid(
+                "SocketConnectHost and SocketConnectPort missing".into(),
+            ));
+        };
+        let primary = Endpoint {
+            host: host.to_string(),
+            port: primary_port,
+        };
+        let backup = Endpoint {
+            host: block.get("FailoverConnectHost").unwrap_or(host).to_string(),
+            port: port("FailoverConnectPort")?.unwrap_or(primary_port),
+        };
+        if backup == primary {
+            return Err(invalid(format!("backup gateway {backup} is the primary")));
+        }
+
+        let after = match block.get("FailoverAfter") {
+            Some(value) => value
+                .parse::<u32>()
+                .ok()
+                .filter(|x| *x > 0)
+                .ok_or_else(|| invalid(format!("invalid FailoverAfter: {value:?}")))?,
+            None => DEFAULT_FAILOVER_AFTER,
+        };
+        let reconnect_interval = match block.get("ReconnectInterval") {
+            Some(value) => value
+                .parse::<i64>()
+                .ok()
+                .filter(|x| *x > 0)
+                .ok_or_else(|| invalid(format!("invalid ReconnectInterval: {value:?}")))?,
+            None => DEFAULT_RECONNECT_INTERVAL,
+        };
+        let reset_seq_num = block.get("FailoverResetSeqNum") == Some("Y");
+        let store_dir = block.get("FileStorePath").map(PathBuf::from);
+        if reset_seq_num && store_dir.is_none() {
+            return Err(invalid(
+                "FailoverResetSeqNum=Y without a FileStorePath".into(),
+            ));
+        }
+
+        routes.push(FailoverRoute {
+            session,
+            primary,
+            backup,
+            after,
+            reconnect_interval,
+            reset_seq_num,
+            store_dir,
+            store_prefix: store_prefix(&session_id),
+        });
+    }
+    Ok(routes)
+}
+
+// =============================================================================
+// Switches
+// =============================================================================
+
+/// Why a session was switched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchReason {
+    /// This many connect attempts in a row without a logon
+    ConnectFailures(u32),
+
+    /// Asked for by the operator
+    Forced,
+}
+
+impl fmt::Display for SwitchReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SwitchReason::ConnectFailures(attempts) => {
+                write!(f, "{attempts} connect attempts without a logon")
+            }
+            SwitchReason::Forced => write!(f, "forced"),
+        }
+    }
+}
+
+/// A session moved to its other gateway
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Switch {
+    /// Session label
+    pub session: String,
+
+    /// The gateway it now connects to, and where that is
+    pub to: Gateway,
+    pub endpoint: Endpoint,
+    pub reason: SwitchReason,
+
+    /// Unix seconds
+    pub at: i64,
+
+    /// The store to empty before the handler is rebuilt, with
+    /// FailoverResetSeqNum
+    pub reset_store: Option<PathBuf>,
+
+    /// Name of the session's files in the store
+    pub store_prefix: String,
+}
+
+impl fmt::Display for Switch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} switched to the {} gateway {} ({})",
+            self.session,
+            self.to.as_str(),
+            self.endpoint,
+            self.reason
+        )?;
+        if self.reset_store.is_some() {
+            write!(f, ", sequence numbers reset")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a session with a backup gateway stands
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteStatus {
+    pub session: String,
+
+    /// The gateway it connects to, and where that is
+    pub active: Gateway,
+    pub endpoint: Endpoint,
+
+    /// Failed connect attempts so far; None while logged on or with the
+    /// handler stopped
+    pub attempts: Option<u32>,
+
+    /// Attempts that make a switch
+    pub after: u32,
+
+    /// Switches since the start
+    pub switches: u32,
+}
+
+// =============================================================================
+// Failover
+// =============================================================================
+
+#[derive(Debug)]
+struct Track {
+    route: FailoverRoute,
+    active: Gateway,
+
+    /// Since when the session has been down with the handler running
+    down_since: Option<i64>,
+    switches: u32,
+}
+
+impl Track {
+    fn attempts(&self, now: i64) -> Option<u32> {
+        let down_since = self.down_since?;
+        let attempts = (now - down_since).max(0) / self.route.reconnect_interval;
+        Some(u32::try_from(attempts).unwrap_or(u32::MAX))
+    }
+
+    fn switch(&mut self, reason: SwitchReason, now: i64) -> Switch {
+        self.active = self.active.other();
+        self.down_since = None;
+        self.switches += 1;
+        let route = &self.route;
+        Switch {
+            session: route.session.clone(),
+            to: self.active,
+            endpoint: route.endpoint(self.active).clone(),
+            reason,
+            at: now,
+            reset_store: route.store_dir.clone().filter(|_| route.reset_seq_num),
+            store_prefix: route.store_prefix.clone(),
+        }
+    }
+}
+
+/// The gateway each session with a backup connects to, switched when it
+/// fails
+#[derive(Debug)]
+pub struct Failover {
+    /// The configuration the settings are rebuilt from
+    config: ConfigFile,
+
+    /// By session label
+    tracks: BTreeMap<String, Track>,
+
+    /// Every switch, oldest first
+    history: Vec<Switch>,
+}
+
+impl Failover {
+    /// Read the routes of a configuration file; every session starts on
+    /// its primary gateway
+    pub fn new(config: &ConfigFile) -> Result<Self, FailoverError> {
+        let tracks = read_routes(config)?
+            .into_iter()
+            .map(|route| {
+                let track = Track {
+                    route,
+                    active: Gateway::Primary,
+                    down_since: None,
+                    switches: 0,
+                };
+                (track.route.session.clone(), track)
+            })
+            .collect();
+        Ok(Self {
+            config: config.clone(),
+            tracks,
+            history: Vec::new(),
+        })
+    }
+
+    /// No session has a backup gateway
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// Count the attempts of the sessions down, and switch those that
+    /// failed long enough
+    ///
+    /// # Arguments
+    /// * `running` - Whether the connection handler runs: a stopped handler
+    ///   makes no attempt
+    /// * `logged_on` - Whether a session, by label, is logged on
+    ///
+    /// # Returns
+    /// The switches made: the caller rebuilds the handler
+    pub fn tick<F>(&mut self, now: i64, running: bool, logged_on: F) -> Vec<Switch>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let mut switches = Vec::new();
+        for track in self.tracks.values_mut() {
+            if !running || logged_on(&track.route.session) {
+                track.down_since = None;
+                continue;
+            }
+            track.down_since.get_or_insert(now);
+            let attempts = track.attempts(now).unwrap_or_default();
+            if attempts >= track.route.after {
+                switches.push(track.switch(SwitchReason::ConnectFailures(attempts), now));
+            }
+        }
+        self.history.extend(switches.iter().cloned());
+        switches
+    }
+
+    /// Switch a session, by label, or every one, whatever their state
+    pub fn force(&mut self, session: Option<&str>, now: i64) -> Result<Vec<Switch>, FailoverError> {
+        let switches = match session {
+            Some(session) => {
+                let track = self
+                    .tracks
+                    .get_mut(session)
+                    .ok_or_else(|| FailoverError::UnknownSession(session.to_string()))?;
+                vec![track.switch(SwitchReason::Forced, now)]
+            }
+            None => self
+                .tracks
+                .values_mut()
+                .map(|track| track.switch(SwitchReason::Forced, now))
+                .collect(),
+        };
+        self.history.extend(switches.iter().cloned());
+        Ok(switches)
+    }
+
+    /// Every session with a backup, sorted by label
+    pub fn routes(&self, now: i64) -> Vec<RouteStatus> {
+        self.tracks
+            .values()
+            .map(|track| RouteStatus {
+                session: track.route.session.clone(),
+                active: track.active,
+                endpoint: track.route.endpoint(track.active).clone(),
+                attempts: track.attempts(now),
+                after: track.route.after,
+                switches: track.switches,
+            })
+            .collect()
+    }
+
+    /// Every switch, oldest first
+    pub fn history(&self) -> &[Switch] {
+        &self.history
+    }
+
+    /// The settings of the configuration file, each session pointed at its
+    /// active gateway
+    pub fn settings(&self) -> Result<SessionSettings, FailoverError> {
+        let mut config = self.config.clone();
+        for track in self.tracks.values() {
+            let endpoint = track.route.endpoint(track.active);
+            let session = &track.route.session;
+            config.set(session, "SocketConnectHost", &endpoint.host);
+            config.set(session, "SocketConnectPort", &endpoint.port.to_string());
+        }
+        Ok(config.to_settings()?)
+    }
+}
@@ -0,0 +1,379 @@
+// =============================================================================
+// Fault Injection
+// =============================================================================
+// Resend and recovery are the parts of a counterparty's FIX engine that
+// never run on a good day. A FaultInjector breaks our side of the session on
+// purpose, from the outbound callbacks (to_app / to_admin), so they can be
+// seen at work:
+//
+//   drop N        every Nth application message is not sent
+//   corrupt N     every Nth application message goes out malformed
+//   jitter MS     every application message waits 0..=MS milliseconds
+//   skip-seq N    every Nth message goes out one sequence number further
+//   heartbeat S   every Heartbeat waits S seconds
+//
+// A value of 0 switches a fault off; faults can be combined and apply to
+// every session. What the counterparty should do about each:
+//
+// - drop: QuickFIX gives a message it does not send no sequence number, so
+//   there is no gap to detect: the order just never gets an answer. Use
+//   skip-seq for gaps.
+// - corrupt: QuickFIX computes BodyLength (9) and CheckSum (10) after the
+//   callback, from the fields, so a bad CheckSum cannot be put on the wire
+//   from here. The message gets a SendingTime (52) that is not a timestamp
+//   instead: it must be rejected (35=3, 373=6) and its number consumed.
+// - skip-seq: a gap, then a ResendRequest (35=2), which our engine answers
+//   with a SequenceReset-GapFill (35=4, 123=Y) for the skipped number. The
+//   numbers skipped stay skipped for the life of the process, even once the
+//   fault is off, since the counterparty now expects them: only a sequence
+//   reset (a Logon with 141=Y) brings the two sides back in step. After a
+//   restart in between, reset the sequence numbers.
+// - jitter and heartbeat hold the engine thread that sends: with a heartbeat
+//   hold longer than the heartbeat interval, TestRequests (35=1) go
+//   unanswered too, and the counterparty should end the session.
+// =============================================================================
+
+use std::{
+    collections::{hash_map::RandomState, BTreeMap, HashMap},
+    error::Error,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use quickfix::{FieldMap, Message};
+
+/// SendingTime (52) of corrupted messages
+pub const CORRUPT_SENDING_TIME: &str = "NOT-A-TIMESTAMP";
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FaultError {
+    /// Not one of drop, corrupt, jitter, skip-seq, heartbeat
+    UnknownFault(String),
+}
+
+impl fmt::Display for FaultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultError::UnknownFault(x) => write!(
+                f,
+                "unknown fault {x}: expected drop, corrupt, jitter, skip-seq or heartbeat"
+            ),
+        }
+    }
+}
+
+impl Error for FaultError {}
+
+// =============================================================================
+// Faults
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Fault {
+    /// Every Nth application message
+    Drop,
+
+    /// Every Nth application message
+    Corrupt,
+
+    /// Milliseconds, at most
+    Jitter,
+
+    /// Every Nth message, admin or application
+    SkipSeq,
+
+    /// Seconds
+    Heartbeat,
+}
+
+impl Fault {
+    /// Name, as typed in the shell
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Fault::Drop => "drop",
+            Fault::Corrupt => "corrupt",
+            Fault::Jitter => "jitter",
+            Fault::SkipSeq => "skip-seq",
+            Fault::Heartbeat => "heartbeat",
+        }
+    }
+
+    /// What its value is, for status lines
+    pub fn describe(self, value: u64) -> String {
+        match self {
+            Fault::Drop => format!("drop every {value}th application message"),
+            Fault::Corrupt => format!("corrupt every {value}th application message"),
+            Fault::Jitter => format!("delay application messages up to {value} ms"),
+            Fault::SkipSeq => format!("skip a sequence number every {value}th message"),
+            Fault::Heartbeat => format!("hold heartbeats {value} s"),
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Fault {
+    type Err = FaultError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop" => Ok(Fault::Drop),
+            "corrupt" => Ok(Fault::Corrupt),
+            "jitter" => Ok(Fault::Jitter),
+            "skip-seq" => Ok(Fault::SkipSeq),
+            "heartbeat" => Ok(Fault::Heartbeat),
+            _ => Err(FaultError::UnknownFault(s.to_string())),
+        }
+    }
+}
+
+// =============================================================================
+// Injector
+// =============================================================================
+
+/// Per session, by label
+#[derive(Debug, Default)]
+struct SessionFaults {
+    /// Messages sent, for skip-seq
+    sent: u64,
+
+    /// Application messages offered, for drop and corrupt
+    app_sent: u64,
+
+    /// Sequence numbers skipped since the last reset: added to every
+    /// outbound MsgSeqNum (34)
+    seq_offset: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Faults switched on, with their value
+    settings: BTreeMap<Fault, u64>,
+
+    sessions: HashMap<String, SessionFaults>,
+
+    /// Faults injected so far
+    injected: BTreeMap<Fault, u64>,
+}
+
+impl State {
+    fn is_armed(&self) -> bool {
+        !self.settings.is_empty() || self.sessions.values().any(|x| x.seq_offset > 0)
+    }
+
+    /// Whether the `count`th message is hit by a fault set to every Nth
+    fn hits(&self, fault: Fault, count: u64) -> bool {
+        self.settings.get(&fault).is_some_and(|every| count.is_multiple_of(*every))
+    }
+
+    fn record(&mut self, fault: Fault) {
+        *self.injected.entry(fault).or_default() += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    /// Whether there is anything to do; spares the callbacks the lock
+    /// otherwise
+    armed: AtomicBool,
+
+    state: Mutex<State>,
+}
+
+impl FaultInjector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Switch a fault on with its value (see the module header), or off
+    /// with 0
+    pub fn set(&self, fault: Fault, value: u64) {
+        let mut state = self.state.lock().expect("fault lock poisoned");
+        if value == 0 {
+            state.settings.remove(&fault);
+        } else {
+            state.settings.insert(fault, value);
+        }
+        self.armed.store(state.is_armed(), Ordering::Relaxed);
+    }
+
+    /// Switch every fault off; skipped sequence numbers stay skipped
+    pub fn clear(&self) {
+        let mut state = self.state.lock().expect("fault lock poisoned");
+        state.settings.clear();
+        self.armed.store(state.is_armed(), Ordering::Relaxed);
+    }
+
+    /// Whether any fault is on, or any session has sequence numbers skipped
+    pub fn is_armed(&self) -> bool {
+        self.armed.load(Ordering::Relaxed)
+    }
+
+    /// Faults switched on, with their value
+    pub fn settings(&self) -> Vec<(Fault, u64)> {
+        let state = self.state.lock().expect("fault lock poisoned");
+        state.settings.iter().map(|(k, v)| (*k, *v)).collect()
+    }
+
+    /// How many times each fault was injected
+    pub fn injected(&self) -> Vec<(Fault, u64)> {
+        let state = self.state.lock().expect("fault lock poisoned");
+        state.injected.iter().map(|(k, v)| (*k, *v)).collect()
+    }
+
+    /// Sequence numbers skipped per session since its last reset, sorted by
+    /// label; sessions with none are left out
+    pub fn seq_offsets(&self) -> Vec<(String, u64)> {
+        let state = self.state.lock().expect("fault lock poisoned");
+        let mut offsets: Vec<_> = state
+            .sessions
+            .iter()
+            .filter(|(_, x)| x.seq_offset > 0)
+            .map(|(k, x)| (k.clone(), x.seq_offset))
+            .collect();
+        offsets.sort();
+        offsets
+    }
+
+    /// Apply the faults to an outbound application message, from to_app
+    ///
+    /// May sleep (jitter).
+    ///
+    /// # Arguments
+    /// * `label` - Session the message goes to
+    ///
+    /// # Returns
+    /// The faults injected; when it holds Drop, the message must not be
+    /// sent
+    pub fn on_app(&self, label: &str, msg: &mut Message) -> Vec<Fault> {
+        if !self.is_armed() || is_poss_dup(msg) {
+            return Vec::new();
+        }
+
+        let mut injected = Vec::new();
+        let jitter = {
+            let mut state = self.state.lock().expect("fault lock poisoned");
+            let session = state.sessions.entry(label.to_string()).or_default();
+            session.app_sent += 1;
+            let count = session.app_sent;
+
+            if state.hits(Fault::Drop, count) {
+                state.record(Fault::Drop);
+                return vec![Fault::Drop];
+            }
+            if let Some(fault) = Self::renumber(&mut state, label, msg) {
+                injected.push(fault);
+            }
+            if state.hits(Fault::Corrupt, count) {
+                let _ = msg.with_header_mut(|h| h.set_field(52, CORRUPT_SENDING_TIME));
+                state.record(Fault::Corrupt);
+                injected.push(Fault::Corrupt);
+            }
+            state.settings.get(&Fault::Jitter).copied()
+        };
+
+        // Outside the lock: other sessions keep going
+        if let Some(max) = jitter {
+            let delay = RandomState::new().build_hasher().finish() % (max + 1);
+            if delay > 0 {
+                thread::sleep(Duration::from_millis(delay));
+                self.state.lock().expect("fault lock poisoned").record(Fault::Jitter);
+                injected.push(Fault::Jitter);
+            }
+        }
+        injected
+    }
+
+    /// Apply the faults to an outbound admin message, from to_admin
+    ///
+    /// Sleeps on Heartbeats (heartbeat).
+    ///
+    /// # Returns
+    /// The faults injected
+    pub fn on_admin(&self, label: &str, msg: &mut Message) -> Vec<Fault> {
+        let msg_type = msg.with_header(|h| h.get_field(35)).unwrap_or_default();
+
+        if !self.is_armed() {
+            return Vec::new();
+        }
+        // A sequence reset on both sides puts them back in step
+        if msg_type == "A" && msg.get_field(141).as_deref() == Some("Y") {
+            let mut state = self.state.lock().expect("fault lock poisoned");
+            state.sessions.remove(label);
+            self.armed.store(state.is_armed(), Ordering::Relaxed);
+        }
+        if !self.is_armed() || is_poss_dup(msg) {
+            return Vec::new();
+        }
+
+        let mut injected = Vec::new();
+        let hold = {
+            let mut state = self.state.lock().expect("fault lock poisoned");
+            if let Some(fault) = Self::renumber(&mut state, label, msg) {
+                injected.push(fault);
+            }
+            state.settings.get(&Fault::Heartbeat).copied()
+        };
+
+        if let Some(seconds) = hold.filter(|_| msg_type == "0") {
+            thread::sleep(Duration::from_secs(seconds));
+            self.state.lock().expect("fault lock poisoned").record(Fault::Heartbeat);
+            injected.push(Fault::Heartbeat);
+        }
+        injected
+    }
+
+    /// Count the message for skip-seq, skip a number when its turn comes,
+    /// and shift its MsgSeqNum (34) by the numbers skipped so far
+    ///
+    /// QuickFIX stores the message under the MsgSeqNum it goes out with,
+    /// so a resend finds it under the shifted number.
+    fn renumber(state: &mut State, label: &str, msg: &mut Message) -> Option<Fault> {
+        let skip = state.settings.get(&Fault::SkipSeq).copied();
+        let session = state.sessions.entry(label.to_string()).or_default();
+        session.sent += 1;
+        let skipped = skip.is_some_and(|every| session.sent.is_multiple_of(every));
+        if skipped {
+            session.seq_offset += 1;
+        }
+
+        let offset = session.seq_offset;
+        if offset > 0 {
+            let seq_num = msg
+                .with_header(|h| h.get_field(34))
+                .and_then(|x| x.parse::<u64>().ok());
+            if let Some(seq_num) = seq_num {
+                let seq_num = (seq_num + offset).to_string();
+                let _ = msg.with_header_mut(|h| h.set_field(34, seq_num.as_str()));
+            }
+        }
+
+        if skipped {
+            state.record(Fault::SkipSeq);
+            return Some(Fault::SkipSeq);
+        }
+        None
+    }
+}
+
+/// Resent messages (PossDupFlag 43=Y) and gap fills already carry the
+/// number they were stored under: they are left alone
+fn is_poss_dup(msg: &Message) -> bool {
+    msg.with_header(|h| h.get_field(43)).as_deref() == Some("Y")
+}
@@ -0,0 +1,568 @@
+// =============================================================================
+// File Store Maintenance
+// =============================================================================
+// The QuickFIX file store (FileStorePath) keeps four files per session:
+//
+//   PREFIX.seqnums   `NEXT_SENDER : NEXT_TARGET`
+//   PREFIX.body      every message sent, one after the other, for resends
+//   PREFIX.header    `SEQNUM,OFFSET,SIZE ` of each message in the body
+//   PREFIX.session   creation time of the store
+//
+// and never shrinks: a session that does not reset every day grows its body
+// by the day's traffic. With the engine stopped, FileStore can
+//
+//   verify    check the files against each other: every header entry within
+//             the body and at the message of its MsgSeqNum (34), no stored
+//             MsgSeqNum at or past the next one of the seqnums file
+//   compact   move the oldest messages, up to the first one sent after a
+//             cutoff, to a gzip archive, and rewrite body and header
+//             without them
+//   migrate   copy the store to the sessions and messages tables of the
+//             QuickFIX SQL stores, in a SQLite database (`sqlite` feature)
+//
+// A compacted store answers a ResendRequest for an archived message with a
+// gap fill, as QuickFIX does for any message it no longer has: compact past
+// what a counterparty may still ask for. Compaction and migration refuse a
+// store verify finds anything wrong with. The engine keeps the offsets of
+// the body in memory: run them with it stopped.
+// =============================================================================
+
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use quickfix::SessionId;
+
+use crate::{gzip::gzip, session::handover::store_prefix, time::parse_utc_timestamp};
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FileStoreError {
+    Io(io::Error),
+
+    /// Verification found problems: how many
+    Inconsistent(usize),
+
+    /// The archive to write is already there
+    ArchiveExists(PathBuf),
+
+    /// The database refused the migration, or this build has no SQLite
+    Backend(String),
+}
+
+impl fmt::Display for FileStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileStoreError::Io(err) => write!(f, "{err}"),
+            FileStoreError::Inconsistent(count) => {
+                write!(f, "{count} problem(s) in the store, left as it is")
+            }
+            FileStoreError::ArchiveExists(path) => {
+                write!(f, "{} exists, left as it is", path.display())
+            }
+            FileStoreError::Backend(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for FileStoreError {}
+
+impl From<io::Error> for FileStoreError {
+    fn from(err: io::Error) -> Self {
+        FileStoreError::Io(err)
+    }
+}
+
+// =============================================================================
+// Stored Messages
+// =============================================================================
+
+/// A message of the body, as sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredMessage {
+    /// MsgSeqNum of its header entry
+    pub seq_num: u64,
+
+    /// The message, SOH delimited
+    pub raw: Vec<u8>,
+}
+
+impl StoredMessage {
+    /// Value of the first occurrence of a tag
+    pub fn field(&self, tag: u32) -> Option<&str> {
+        self.raw
+            .split(|&b| b == 1)
+            .filter_map(|x| std::str::from_utf8(x).ok())
+            .filter_map(|x| x.split_once('='))
+            .find(|(t, _)| t.parse() == Ok(tag))
+            .map(|(_, value)| value)
+    }
+
+    /// SendingTime (52), seconds since the Unix epoch
+    pub fn sending_time(&self) -> Option<i64> {
+        parse_utc_timestamp(self.field(52)?).map(|nanos| nanos / 1_000_000_000)
+    }
+}
+
+/// Something wrong with a store
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StoreIssue {
+    /// The seqnums file is not `SENDER : TARGET`
+    Seqnums(String),
+
+    /// A header entry is not `SEQNUM,OFFSET,SIZE`
+    Header(String),
+
+    /// A header entry past the end of the body
+    OutsideBody { seq_num: u64, end: u64, body: u64 },
+
+    /// A MsgSeqNum with two header entries; QuickFIX resends the last one
+    Duplicate(u64),
+
+    /// The message at a header entry has another MsgSeqNum (34), or none
+    WrongMessage { seq_num: u64, found: Option<String> },
+
+    /// The highest stored MsgSeqNum, at or past the next one to send: the
+    /// next messages would reuse it
+    AheadOfSeqnums { seq_num: u64, next_sender: u64 },
+
+    /// Body bytes past the last entry, from a write the header never got
+    Unindexed(u64),
+}
+
+impl fmt::Display for StoreIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreIssue::Seqnums(text) => write!(f, "seqnums file reads {text:?}"),
+            StoreIssue::Header(entry) => write!(f, "header entry {entry:?} is not SEQ,OFFSET,SIZE"),
+            StoreIssue::OutsideBody { seq_num, end, body } => write!(
+                f,
+                "message {seq_num} ends at byte {end}, the body has {body}"
+            ),
+            StoreIssue::Duplicate(seq_num) => write!(f, "message {seq_num} is stored twice"),
+            StoreIssue::WrongMessage { seq_num, found } => match found {
+                Some(found) => write!(f, "message {seq_num} is stored with 34={found}"),
+                None => write!(f, "message {seq_num} is stored without a MsgSeqNum"),
+            },
+            StoreIssue::AheadOfSeqnums {
+                seq_num,
+                next_sender,
+            } => write!(
+                f,
+                "message {seq_num} is stored but the next to send is {next_sender}"
+            ),
+            StoreIssue::Unindexed(bytes) => {
+                write!(
+                    f,
+                    "{bytes} byte(s) at the end of the body are in no header entry"
+                )
+            }
+        }
+    }
+}
+
+/// What a store holds, and what is wrong with it
+#[derive(Debug, Clone, Default)]
+pub struct Verification {
+    pub next_sender: u64,
+    pub next_target: u64,
+
+    /// Creation time, as the session file has it
+    pub creation_time: Option<String>,
+
+    /// Messages of the header entries that could be read, in header order
+    pub messages: Vec<StoredMessage>,
+
+    pub issues: Vec<StoreIssue>,
+}
+
+impl Verification {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// What a compaction did
+#[derive(Debug, Clone, Default)]
+pub struct Compaction {
+    pub archived: usize,
+    pub kept: usize,
+
+    /// Archive written, none when nothing was old enough
+    pub archive: Option<PathBuf>,
+
+    /// Size of the body before and after
+    pub body_before: u64,
+    pub body_after: u64,
+}
+
+// =============================================================================
+// File Store
+// =============================================================================
+
+/// The files of one session in a file store directory
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    dir: PathBuf,
+    prefix: String,
+}
+
+impl FileStore {
+    /// # Arguments
+    /// * `dir` - FileStorePath
+    /// * `prefix` - Name of the session's files, without extension
+    pub fn new(dir: &Path, prefix: &str) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            prefix: prefix.to_string(),
+        }
+    }
+
+    pub fn for_session(dir: &Path, session: &SessionId) -> Self {
+        Self::new(dir, &store_prefix(session))
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn path(&self, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}.{extension}", self.prefix))
+    }
+
+    /// Whether the session has written anything to the store
+    pub fn exists(&self) -> bool {
+        self.path("seqnums").exists() || self.path("body").exists()
+    }
+
+    /// Read the store and check its files against each other
+    pub fn verify(&self) -> Result<Verification, FileStoreError> {
+        let mut verification = Verification {
+            next_sender: 1,
+            next_target: 1,
+            ..Default::default()
+        };
+        if let Some(text) = read_optional(&self.path("seqnums"))? {
+            let text = String::from_utf8_lossy(&text);
+            match parse_seqnums(&text) {
+                Some((sender, target)) => {
+                    verification.next_sender = sender;
+                    verification.next_target = target;
+                }
+                None => verification
+                    .issues
+                    .push(StoreIssue::Seqnums(text.to_string())),
+            }
+        }
+        verification.creation_time = read_optional(&self.path("session"))?
+            .map(|x| String::from_utf8_lossy(&x).trim().to_string());
+
+        let body = read_optional(&self.path("body"))?.unwrap_or_default();
+        let header = read_optional(&self.path("header"))?.unwrap_or_default();
+        let mut seen = HashSet::new();
+        let mut indexed = 0;
+        for entry in String::from_utf8_lossy(&header).split_whitespace() {
+            let Some((seq_num, offset, size)) = parse_entry(entry) else {
+                verification
+                    .issues
+                    .push(StoreIssue::Header(entry.to_string()));
+                continue;
+            };
+            let end = offset + size;
+            if end > body.len() as u64 {
+                verification.issues.push(StoreIssue::OutsideBody {
+                    seq_num,
+                    end,
+                    body: body.len() as u64,
+                });
+                continue;
+            }
+            indexed = indexed.max(end);
+            if !seen.insert(seq_num) {
+                verification.issues.push(StoreIssue::Duplicate(seq_num));
+            }
+            let message = StoredMessage {
+                seq_num,
+                raw: body[offset as usize..end as usize].to_vec(),
+            };
+            let found = message.field(34);
+            if found.and_then(|x| x.parse().ok()) != Some(seq_num) {
+                verification.issues.push(StoreIssue::WrongMessage {
+                    seq_num,
+                    found: found.map(str::to_string),
+                });
+            }
+            verification.messages.push(message);
+        }
+        // The last one only: every message after it is ahead too
+        let last = verification.messages.iter().map(|x| x.seq_num).max();
+        if let Some(seq_num) = last.filter(|&x| x >= verification.next_sender) {
+            verification.issues.push(StoreIssue::AheadOfSeqnums {
+                seq_num,
+                next_sender: verification.next_sender,
+            });
+        }
+        if indexed < body.len() as u64 {
+            verification
+                .issues
+                .push(StoreIssue::Unindexed(body.len() as u64 - indexed));
+        }
+        Ok(verification)
+    }
+
+    /// Verify the store, refusing it on any problem
+    fn read_consistent(&self) -> Result<Verification, FileStoreError> {
+        let verification = self.verify()?;
+        if !verification.is_ok() {
+            return Err(FileStoreError::Inconsistent(verification.issues.len()));
+        }
+        Ok(verification)
+    }
+
+    /// Move the messages sent before `before` to a gzip archive
+    ///
+    /// Messages go from the oldest up to the first one sent at or after
+    /// `before` (or without a readable SendingTime), so that the store
+    /// keeps the most recent run of MsgSeqNums. The archive,
+    /// `PREFIX.FIRST-LAST.gz` in `archive_dir`, is a message per line as in
+    /// the QuickFIX message logs; it is written and synced before body and
+    /// header are replaced.
+    ///
+    /// # Arguments
+    /// * `before` - Cutoff, seconds since the Unix epoch
+    pub fn compact(&self, before: i64, archive_dir: &Path) -> Result<Compaction, FileStoreError> {
+        let verification = self.read_consistent()?;
+        let body_before = verification
+            .messages
+            .iter()
+            .map(|x| x.raw.len() as u64)
+            .sum();
+        let archived = verification
+            .messages
+            .iter()
+            .take_while(|x| x.sending_time().is_some_and(|time| time < before))
+            .count();
+        let (old, kept) = verification.messages.split_at(archived);
+        let (Some(first), Some(last)) = (old.first(), old.last()) else {
+            return Ok(Compaction {
+                kept: kept.len(),
+                body_before,
+                body_after: body_before,
+                ..Default::default()
+            });
+        };
+
+        let archive = archive_dir.join(format!(
+            "{}.{}-{}.gz",
+            self.prefix, first.seq_num, last.seq_num
+        ));
+        if archive.exists() {
+            return Err(FileStoreError::ArchiveExists(archive));
+        }
+        let mut lines = Vec::new();
+        for message in old {
+            lines.extend_from_slice(&message.raw);
+            lines.push(b'\n');
+        }
+        fs::create_dir_all(archive_dir)?;
+        write_atomically(&archive, &gzip(&lines))?;
+
+        let mut body = Vec::new();
+        let mut header = String::new();
+        for message in kept {
+            header.push_str(&format!(
+                "{},{},{} ",
+                message.seq_num,
+                body.len(),
+                message.raw.len()
+            ));
+            body.extend_from_slice(&message.raw);
+        }
+        // Body first: until the header follows, its entries are past the
+        // end of the new body and verify says so
+        write_atomically(&self.path("body"), &body)?;
+        write_atomically(&self.path("header"), header.as_bytes())?;
+
+        Ok(Compaction {
+            archived,
+            kept: kept.len(),
+            archive: Some(archive),
+            body_before,
+            body_after: body.len() as u64,
+        })
+    }
+
+    /// Copy the store to the tables of the QuickFIX SQL stores in a SQLite
+    /// database, replacing what the database had of the session
+    ///
+    /// `sessions` and `messages` are created if needed, with the columns of
+    /// the scripts QuickFIX ships for MySQL and PostgreSQL (an OdbcStore or
+    /// a QuickFIX/J JdbcStore over SQLite reads them).
+    ///
+    /// # Returns
+    /// How many messages were copied
+    pub fn migrate_to_sqlite(
+        &self,
+        session: &SessionId,
+        database: &Path,
+    ) -> Result<usize, FileStoreError> {
+        let verification = self.read_consistent()?;
+        #[cfg(feature = "sqlite")]
+        return sqlite::migrate(&verification, session, database)
+            .map_err(|err| FileStoreError::Backend(format!("sqlite: {err}")));
+        #[cfg(not(feature = "sqlite"))]
+        {
+            let _ = (verification, session, database);
+            Err(FileStoreError::Backend(
+                "built without the sqlite feature".to_string(),
+            ))
+        }
+    }
+}
+
+/// `0000000012 : 0000000009`
+fn parse_seqnums(text: &str) -> Option<(u64, u64)> {
+    let (sender, target) = text.split_once(':')?;
+    Some((sender.trim().parse().ok()?, target.trim().parse().ok()?))
+}
+
+/// `12,3456,230`
+fn parse_entry(entry: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = entry.split(',').map(|x| x.parse().ok());
+    let entry = (parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(entry)
+}
+
+fn read_optional(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Write to a temporary file, sync it, then rename it over `path`
+fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    let mut file = File::create(&temp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&temp, path)
+}
+
+// =============================================================================
+// SQLite
+// =============================================================================
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::path::Path;
+
+    use quickfix::SessionId;
+    use rusqlite::{params, Connection};
+
+    use super::Verification;
+    use crate::time::{parse_utc_timestamp, time_of_day, unix_now, Date};
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS sessions (
+            beginstring       CHAR(8)     NOT NULL,
+            sendercompid      VARCHAR(64) NOT NULL,
+            targetcompid      VARCHAR(64) NOT NULL,
+            session_qualifier VARCHAR(64) NOT NULL,
+            creation_time     DATETIME    NOT NULL,
+            incoming_seqnum   INT         NOT NULL,
+            outgoing_seqnum   INT         NOT NULL,
+            PRIMARY KEY (beginstring, sendercompid, targetcompid, session_qualifier)
+        );
+        CREATE TABLE IF NOT EXISTS messages (
+            beginstring       CHAR(8)     NOT NULL,
+            sendercompid      VARCHAR(64) NOT NULL,
+            targetcompid      VARCHAR(64) NOT NULL,
+            session_qualifier VARCHAR(64) NOT NULL,
+            msgseqnum         INT         NOT NULL,
+            message           TEXT        NOT NULL,
+            PRIMARY KEY (beginstring, sendercompid, targetcompid, session_qualifier, msgseqnum)
+        );
+    ";
+
+    pub(super) fn migrate(
+        verification: &Verification,
+        session: &SessionId,
+        database: &Path,
+    ) -> rusqlite::Result<usize> {
+        let key = [
+            session.get_begin_string().unwrap_or_default(),
+            session.get_sender_comp_id().unwrap_or_default(),
+            session.get_target_comp_id().unwrap_or_default(),
+            session.get_session_qualifier().unwrap_or_default(),
+        ];
+        let mut connection = Connection::open(database)?;
+        connection.execute_batch(SCHEMA)?;
+
+        // One transaction: the session is there whole or not at all
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "DELETE FROM messages WHERE beginstring = ?1 AND sendercompid = ?2 \
+             AND targetcompid = ?3 AND session_qualifier = ?4",
+            params![key[0], key[1], key[2], key[3]],
+        )?;
+        transaction.execute(
+            "INSERT OR REPLACE INTO sessions (beginstring, sendercompid, targetcompid, \
+             session_qualifier, creation_time, incoming_seqnum, outgoing_seqnum) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                key[0],
+                key[1],
+                key[2],
+                key[3],
+                sql_datetime(verification.creation_time.as_deref()),
+                verification.next_target as i64,
+                verification.next_sender as i64
+            ],
+        )?;
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO messages (beginstring, sendercompid, targetcompid, \
+                 session_qualifier, msgseqnum, message) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for message in &verification.messages {
+                insert.execute(params![
+                    key[0],
+                    key[1],
+                    key[2],
+                    key[3],
+                    message.seq_num as i64,
+                    String::from_utf8_lossy(&message.raw)
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(verification.messages.len())
+    }
+
+    /// Creation time of the session file, `YYYYMMDD-HH:MM:SS`, as a SQL
+    /// DATETIME; now if there is none
+    fn sql_datetime(creation_time: Option<&str>) -> String {
+        let seconds = creation_time
+            .and_then(parse_utc_timestamp)
+            .map_or_else(unix_now, |nanos| nanos.div_euclid(1_000_000_000));
+        format!(
+            "{} {}",
+            Date::from_unix(seconds).to_iso(),
+            time_of_day(seconds)
+        )
+    }
+}
@@ -0,0 +1,435 @@
+// =============================================================================
+// Garbled Messages
+// =============================================================================
+// QuickFIX throws a received message away when its frame is wrong: a
+// BodyLength (9) that does not match the body, a CheckSum (10) that does not
+// match the bytes, BeginString / BodyLength / MsgType out of place. All that
+// is left of it is one engine event ("Expected CheckSum=123, Received
+// CheckSum=124"), next to a raw log line nobody reads.
+//
+// The GarbledMonitor sits on the engine's log callbacks: it remembers the
+// last message received per session and, when the event that follows says
+// the engine discarded it, keeps a report with an annotated breakdown of its
+// bytes:
+//
+//   offset  field
+//        0  8=FIX.4.4
+//       10  9=65            <- BodyLength 65, the body is 63 bytes (15 to 78)
+//       15  35=D
+//       ...
+//       78  10=124          <- CheckSum 124, the bytes before it sum to 123
+//
+// Bytes that are not printable ASCII show as \xNN.
+//
+// The engine only logs what it framed: bytes before the next 8=, or a
+// BodyLength that is not a number, are dropped by its parser without a log
+// line, and cannot be reported here.
+// =============================================================================
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use crate::time::{time_of_day, unix_now};
+
+/// Reports kept for display
+pub const REPORT_CAPACITY: usize = 100;
+
+/// Field delimiter
+const SOH: char = '\x01';
+
+/// Beginnings of the engine events that mean the last message received was
+/// discarded
+const DISCARD_EVENTS: [&str; 5] = [
+    "Expected BodyLength",
+    "Expected CheckSum",
+    "Header fields out of order",
+    "Invalid message",
+    "Garbled",
+];
+
+/// Whether an engine event says the last message received was discarded
+pub fn is_discard_event(text: &str) -> bool {
+    DISCARD_EVENTS.iter().any(|x| text.starts_with(x))
+}
+
+// =============================================================================
+// Diagnosis
+// =============================================================================
+
+/// What is wrong with the frame of a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// No SOH at all: not FIX, or the delimiters were replaced on the way
+    NoDelimiter,
+
+    /// The first field is not BeginString (8)
+    BeginString,
+
+    /// The second field is not BodyLength (9)
+    BodyLengthMissing,
+
+    /// BodyLength is not a number
+    BodyLengthFormat,
+
+    /// BodyLength does not match the bytes between it and the CheckSum
+    BodyLength { declared: usize, actual: usize },
+
+    /// The third field is not MsgType (35)
+    MsgTypeMissing,
+
+    /// A field that is not TAG=VALUE with a numeric tag and a value
+    BadField { offset: usize },
+
+    /// No CheckSum (10)
+    CheckSumMissing,
+
+    /// CheckSum is not three digits
+    CheckSumFormat,
+
+    /// CheckSum does not match the bytes before it
+    CheckSum { declared: u32, actual: u32 },
+
+    /// Bytes after the CheckSum
+    Trailing { offset: usize },
+
+    /// No SOH after the CheckSum
+    Unterminated,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::NoDelimiter => write!(f, "no SOH delimiter"),
+            Problem::BeginString => write!(f, "first field is not BeginString (8)"),
+            Problem::BodyLengthMissing => write!(f, "second field is not BodyLength (9)"),
+            Problem::BodyLengthFormat => write!(f, "BodyLength is not a number"),
+            Problem::BodyLength { declared, actual } => {
+                write!(f, "BodyLength {declared}, the body is {actual} bytes")
+            }
+            Problem::MsgTypeMissing => write!(f, "third field is not MsgType (35)"),
+            Problem::BadField { offset } => write!(f, "malformed field at offset {offset}"),
+            Problem::CheckSumMissing => write!(f, "no CheckSum (10)"),
+            Problem::CheckSumFormat => write!(f, "CheckSum is not three digits"),
+            Problem::CheckSum { declared, actual } => {
+                write!(f, "CheckSum {declared:03}, the bytes sum to {actual:03}")
+            }
+            Problem::Trailing { offset } => write!(f, "bytes after the CheckSum, at {offset}"),
+            Problem::Unterminated => write!(f, "no SOH after the CheckSum"),
+        }
+    }
+}
+
+/// One field of a message, where it starts and what is wrong with it
+#[derive(Debug, Clone)]
+pub struct Field {
+    /// Byte offset in the message
+    pub offset: usize,
+
+    /// TAG=VALUE as received, without the SOH
+    pub text: String,
+
+    /// Tag, if it is a number
+    pub tag: Option<i32>,
+
+    /// What is wrong with it, for the breakdown
+    pub note: Option<String>,
+}
+
+impl Field {
+    /// What follows the first '='
+    pub fn value(&self) -> Option<&str> {
+        self.text.split_once('=').map(|(_, value)| value)
+    }
+}
+
+/// A message taken apart field by field
+#[derive(Debug, Clone)]
+pub struct Diagnosis {
+    pub fields: Vec<Field>,
+    pub problems: Vec<Problem>,
+}
+
+impl Diagnosis {
+    /// Take a raw message apart and check its frame
+    pub fn new(raw: &str) -> Self {
+        let mut fields = Vec::new();
+        let mut offset = 0;
+        for text in raw.split_terminator(SOH) {
+            let tag = text.split_once('=').and_then(|(tag, _)| tag.parse().ok());
+            fields.push(Field {
+                offset,
+                text: text.to_string(),
+                tag,
+                note: None,
+            });
+            offset += text.len() + 1;
+        }
+
+        let mut diagnosis = Self {
+            fields,
+            problems: Vec::new(),
+        };
+        if !raw.contains(SOH) {
+            diagnosis.problems.push(Problem::NoDelimiter);
+            return diagnosis;
+        }
+        diagnosis.check_header();
+        diagnosis.check_fields();
+        diagnosis.check_trailer(raw);
+        diagnosis
+    }
+
+    /// Whether the frame is wrong
+    pub fn is_garbled(&self) -> bool {
+        !self.problems.is_empty()
+    }
+
+    fn note(&mut self, index: usize, problem: Problem, note: impl Into<String>) {
+        if let Some(field) = self.fields.get_mut(index) {
+            field.note.get_or_insert(note.into());
+        }
+        self.problems.push(problem);
+    }
+
+    /// BeginString, BodyLength and MsgType, in this order
+    fn check_header(&mut self) {
+        let tags: Vec<_> = (0..3)
+            .map(|index| self.fields.get(index).and_then(|x| x.tag))
+            .collect();
+        let tag = |index: usize| tags[index];
+        if tag(0) != Some(8) {
+            self.note(0, Problem::BeginString, "expected BeginString (8=)");
+        }
+        if tag(1) != Some(9) {
+            self.note(1, Problem::BodyLengthMissing, "expected BodyLength (9=)");
+        }
+        if tag(2) != Some(35) {
+            self.note(2, Problem::MsgTypeMissing, "expected MsgType (35=)");
+        }
+    }
+
+    fn check_fields(&mut self) {
+        for index in 0..self.fields.len() {
+            let field = &self.fields[index];
+            let valid = field.tag.is_some() && field.value().is_some_and(|x| !x.is_empty());
+            if !valid {
+                let offset = field.offset;
+                self.note(index, Problem::BadField { offset }, "not TAG=VALUE");
+            }
+        }
+    }
+
+    /// BodyLength and CheckSum against the bytes, then what follows the
+    /// CheckSum
+    fn check_trailer(&mut self, raw: &str) {
+        let Some(checksum) = self.fields.iter().position(|x| x.tag == Some(10)) else {
+            let last = self.fields.len().saturating_sub(1);
+            self.note(last, Problem::CheckSumMissing, "expected CheckSum (10=)");
+            return;
+        };
+        let trailer_start = self.fields[checksum].offset;
+
+        // From the SOH after BodyLength to the SOH before the CheckSum
+        let body_length = self.fields.iter().position(|x| x.tag == Some(9));
+        if let Some(index) = body_length.filter(|x| *x < checksum) {
+            let field = &self.fields[index];
+            let body_start = field.offset + field.text.len() + 1;
+            let actual = trailer_start - body_start;
+            match field.value().unwrap_or_default().parse::<usize>() {
+                Ok(declared) if declared == actual => {}
+                Ok(declared) => {
+                    let note = format!(
+                        "BodyLength {declared}, the body is {actual} bytes \
+                         ({body_start} to {trailer_start})"
+                    );
+                    self.note(index, Problem::BodyLength { declared, actual }, note);
+                }
+                Err(_) => {
+                    let note = format!("not a number; the body is {actual} bytes");
+                    self.note(index, Problem::BodyLengthFormat, note);
+                }
+            }
+        }
+
+        // Every byte up to the SOH before the CheckSum, modulo 256
+        let actual = raw.as_bytes()[..trailer_start]
+            .iter()
+            .fold(0u32, |sum, x| (sum + u32::from(*x)) % 256);
+        let value = self.fields[checksum].value().unwrap_or_default();
+        let parsed = value.parse::<u32>().ok().filter(|_| value.len() == 3);
+        match parsed {
+            Some(declared) if declared == actual => {}
+            Some(declared) => {
+                let note =
+                    format!("CheckSum {declared:03}, the bytes before it sum to {actual:03}");
+                self.note(checksum, Problem::CheckSum { declared, actual }, note);
+            }
+            None => {
+                let note = format!("not three digits; the bytes before it sum to {actual:03}");
+                self.note(checksum, Problem::CheckSumFormat, note);
+            }
+        }
+
+        if let Some(next) = self.fields.get(checksum + 1) {
+            let offset = next.offset;
+            self.note(
+                checksum + 1,
+                Problem::Trailing { offset },
+                "after the CheckSum",
+            );
+        } else if !raw.ends_with(SOH) {
+            self.note(checksum, Problem::Unterminated, "no SOH after it");
+        }
+    }
+
+    /// One line per field: offset, field as received, what is wrong
+    pub fn breakdown(&self) -> String {
+        let mut text = "  offset  field\n".to_string();
+        for field in &self.fields {
+            let shown = printable(&field.text);
+            match &field.note {
+                Some(note) => text += &format!("  {:>6}  {shown:<20} <- {note}\n", field.offset),
+                None => text += &format!("  {:>6}  {shown}\n", field.offset),
+            }
+        }
+        text
+    }
+}
+
+/// Bytes that are not printable ASCII as \xNN
+pub fn printable(text: &str) -> String {
+    let mut shown = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_graphic() || byte == b' ' {
+            shown.push(char::from(byte));
+        } else {
+            shown += &format!("\\x{byte:02X}");
+        }
+    }
+    shown
+}
+
+// =============================================================================
+// Garbled Monitor
+// =============================================================================
+
+/// A message the engine discarded
+#[derive(Debug, Clone)]
+pub struct GarbledReport {
+    pub session: String,
+
+    /// Unix seconds, when it was discarded
+    pub time: i64,
+
+    /// The engine event that said so
+    pub reason: String,
+
+    /// The message as received
+    pub raw: String,
+
+    pub diagnosis: Diagnosis,
+}
+
+impl GarbledReport {
+    /// Time, session and reason, the problems found, then the breakdown
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{} {} discarded, {} bytes: {}\n",
+            time_of_day(self.time),
+            self.session,
+            self.raw.len(),
+            self.reason,
+        );
+        for problem in &self.diagnosis.problems {
+            text += &format!("  - {problem}\n");
+        }
+        text + &self.diagnosis.breakdown()
+    }
+}
+
+impl fmt::Display for GarbledReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_text())
+    }
+}
+
+/// Discarded messages of every session, fed by the engine's log callbacks
+#[derive(Debug, Default)]
+pub struct GarbledMonitor {
+    enabled: AtomicBool,
+
+    /// Last message received, by session label
+    last: Mutex<HashMap<String, String>>,
+
+    reports: Mutex<VecDeque<GarbledReport>>,
+}
+
+impl GarbledMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Switch the monitor on or off; off, it keeps nothing
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.last.lock().expect("garbled lock poisoned").clear();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// A message received, before the engine parses it
+    pub fn on_incoming(&self, label: &str, raw: &str) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut last = self.last.lock().expect("garbled lock poisoned");
+        last.insert(label.to_string(), raw.to_string());
+    }
+
+    /// An engine event
+    ///
+    /// # Returns
+    /// The report, when the event says the last message received was
+    /// discarded
+    pub fn on_event(&self, label: &str, text: &str) -> Option<GarbledReport> {
+        if !self.is_enabled() || !is_discard_event(text) {
+            return None;
+        }
+        let raw = self
+            .last
+            .lock()
+            .expect("garbled lock poisoned")
+            .remove(label)?;
+
+        let report = GarbledReport {
+            session: label.to_string(),
+            time: unix_now(),
+            reason: text.to_string(),
+            diagnosis: Diagnosis::new(&raw),
+            raw,
+        };
+        let mut reports = self.reports.lock().expect("garbled lock poisoned");
+        if reports.len() == REPORT_CAPACITY {
+            reports.pop_front();
+        }
+        reports.push_back(report.clone());
+        Some(report)
+    }
+
+    /// The last `limit` reports, oldest first
+    pub fn recent(&self, limit: usize) -> Vec<GarbledReport> {
+        let reports = self.reports.lock().expect("garbled lock poisoned");
+        let skip = reports.len().saturating_sub(limit);
+        reports.iter().skip(skip).cloned().collect()
+    }
+}
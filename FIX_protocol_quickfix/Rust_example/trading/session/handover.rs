@@ -0,0 +1,351 @@
+// =============================================================================
+// Version Upgrade Handover
+// =============================================================================
+// Replacing a running binary with a new version without the counterparty
+// seeing a sequence reset. The old process:
+//
+//   1. stops taking new business (new orders are refused)
+//   2. drains: waits for the acknowledgement of what it already sent
+//   3. logs out and closes its sockets (stops the connection handler), so
+//      the message store is flushed and nobody is logged on as us
+//   4. writes the handover file: the next sequence numbers of each session,
+//      the OMS and its ClOrdID sequence, the fills behind the positions
+//   5. exits, leaving its working orders in the market
+//
+// and the new one, started with the same file name, waits for the file,
+// puts the sequence numbers in its own message store if they are not there
+// already, takes over the orders and fills, and logs on where the old one
+// left off. The file is written to a temporary name and renamed: its
+// appearance is the signal that the old sockets are closed. Once read it is
+// renamed to FILE.done, so a later start does not take it over again.
+//
+// Sequence numbers live in the QuickFIX file store (FileStorePath), one
+// `BEGINSTRING-SENDER-TARGET.seqnums` file per session holding
+// `NEXT_SENDER : NEXT_TARGET`. A venue that answers the Logon with a reset
+// (141=Y), or that resets every day, still resets: the handover only avoids
+// the ones we would cause. Sessions with a SessionQualifier are not handled.
+// =============================================================================
+
+use std::{
+    error::Error,
+    fmt,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use quickfix::SessionId;
+
+use crate::{
+    json::{json_escape, JsonValue},
+    oms::{Fill, Order},
+    session::session_label,
+    time::unix_now,
+};
+
+/// Format of the handover file this version writes, and the newest it reads
+pub const HANDOVER_FORMAT: u32 = 1;
+
+/// Handover file when none is given
+pub const DEFAULT_HANDOVER_FILE: &str = "handover.json";
+
+/// How often the new process looks for the handover file
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum HandoverError {
+    Io(io::Error),
+
+    /// The file is not a handover, or a sequence file is not one
+    Invalid(String),
+
+    /// Written by a newer version, in a format this one does not read
+    NewerFormat(u32),
+
+    /// The old process did not hand over in time
+    Timeout(PathBuf),
+}
+
+impl fmt::Display for HandoverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandoverError::Io(err) => write!(f, "{err}"),
+            HandoverError::Invalid(what) => write!(f, "invalid {what}"),
+            HandoverError::NewerFormat(format) => write!(
+                f,
+                "handover format {format} is newer than this version reads ({HANDOVER_FORMAT})"
+            ),
+            HandoverError::Timeout(path) => {
+                write!(f, "no handover in {} before the timeout", path.display())
+            }
+        }
+    }
+}
+
+impl Error for HandoverError {}
+
+impl From<io::Error> for HandoverError {
+    fn from(err: io::Error) -> Self {
+        HandoverError::Io(err)
+    }
+}
+
+// =============================================================================
+// Sequence Numbers
+// =============================================================================
+
+/// Next sequence numbers of a session, as its message store keeps them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionSequences {
+    /// Session label, for logs
+    pub session: String,
+
+    /// Name of its files in the message store, without extension
+    pub store_prefix: String,
+
+    /// MsgSeqNum of the next message we send
+    pub next_sender: u64,
+
+    /// MsgSeqNum expected of the next message we receive
+    pub next_target: u64,
+}
+
+impl SessionSequences {
+    /// Read the sequence numbers of a session from a file store directory
+    ///
+    /// A session the store has never seen starts at 1 both ways.
+    pub fn read(store_dir: &Path, session: &SessionId) -> Result<Self, HandoverError> {
+        let store_prefix = store_prefix(session);
+        let path = seqnums_path(store_dir, &store_prefix);
+        let (next_sender, next_target) = match fs::read_to_string(&path) {
+            Ok(text) => parse_seqnums(&text)
+                .ok_or_else(|| HandoverError::Invalid(path.display().to_string()))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (1, 1),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            session: session_label(session),
+            store_prefix,
+            next_sender,
+            next_target,
+        })
+    }
+
+    /// Put these sequence numbers in a file store directory, unless it has
+    /// them already
+    ///
+    /// # Returns
+    /// Whether the store was changed
+    pub fn apply(&self, store_dir: &Path) -> Result<bool, HandoverError> {
+        let path = seqnums_path(store_dir, &self.store_prefix);
+        let current = match fs::read_to_string(&path) {
+            Ok(text) => parse_seqnums(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        if current == Some((self.next_sender, self.next_target)) {
+            return Ok(false);
+        }
+        fs::create_dir_all(store_dir)?;
+        // The width QuickFIX writes; it reads any
+        let text = format!("{:010} : {:010}", self.next_sender, self.next_target);
+        write_atomically(&path, &text)?;
+        Ok(true)
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"session\":\"{}\",\"store_prefix\":\"{}\",\"next_sender\":{},\
+             \"next_target\":{}}}",
+            json_escape(&self.session),
+            json_escape(&self.store_prefix),
+            self.next_sender,
+            self.next_target
+        )
+    }
+
+    fn from_value(value: &JsonValue) -> Option<Self> {
+        let text = |key| value.get(key).and_then(JsonValue::as_str);
+        let number = |key| value.get(key).and_then(JsonValue::as_f64);
+        Some(Self {
+            session: text("session")?.to_string(),
+            store_prefix: text("store_prefix")?.to_string(),
+            next_sender: number("next_sender")? as u64,
+            next_target: number("next_target")? as u64,
+        })
+    }
+}
+
+/// Name of a session's files in the QuickFIX file store:
+/// `BEGINSTRING-SENDER-TARGET`
+pub fn store_prefix(session: &SessionId) -> String {
+    format!(
+        "{}-{}-{}",
+        session.get_begin_string().unwrap_or_default(),
+        session.get_sender_comp_id().unwrap_or_default(),
+        session.get_target_comp_id().unwrap_or_default()
+    )
+}
+
+fn seqnums_path(store_dir: &Path, store_prefix: &str) -> PathBuf {
+    store_dir.join(format!("{store_prefix}.seqnums"))
+}
+
+/// `0000000012 : 0000000009`
+fn parse_seqnums(text: &str) -> Option<(u64, u64)> {
+    let (sender, target) = text.split_once(':')?;
+    Some((sender.trim().parse().ok()?, target.trim().parse().ok()?))
+}
+
+// =============================================================================
+// Handover File
+// =============================================================================
+
+/// What the old process leaves to the new one
+#[derive(Debug, Clone)]
+pub struct Handover {
+    /// HANDOVER_FORMAT of the writer
+    pub format: u32,
+
+    /// Library version of the writer
+    pub version: String,
+
+    /// Process id of the writer
+    pub pid: u32,
+
+    /// Unix seconds, when written
+    pub time: i64,
+
+    pub sessions: Vec<SessionSequences>,
+
+    /// Every order of the OMS, working or not
+    pub orders: Vec<Order>,
+
+    /// ClOrdID prefix of the OMS and the number of its next ClOrdID
+    pub id_prefix: String,
+    pub next_cl_ord_id: u64,
+
+    /// Fills the positions are built from
+    pub fills: Vec<Fill>,
+}
+
+impl Handover {
+    /// A handover from this process, to fill in
+    pub fn new(id_prefix: &str) -> Self {
+        Self {
+            format: HANDOVER_FORMAT,
+            version: crate::VERSION.to_string(),
+            pid: std::process::id(),
+            time: unix_now(),
+            sessions: Vec::new(),
+            orders: Vec::new(),
+            id_prefix: id_prefix.to_string(),
+            next_cl_ord_id: 1,
+            fills: Vec::new(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let array = |items: Vec<String>| format!("[{}]", items.join(","));
+        format!(
+            "{{\"format\":{},\"version\":\"{}\",\"pid\":{},\"time\":{},\"sessions\":{},\
+             \"orders\":{},\"id_prefix\":\"{}\",\"next_cl_ord_id\":{},\"fills\":{}}}",
+            self.format,
+            json_escape(&self.version),
+            self.pid,
+            self.time,
+            array(
+                self.sessions
+                    .iter()
+                    .map(SessionSequences::to_json)
+                    .collect()
+            ),
+            array(self.orders.iter().map(Order::to_json).collect()),
+            json_escape(&self.id_prefix),
+            self.next_cl_ord_id,
+            array(self.fills.iter().map(Fill::to_json).collect()),
+        )
+    }
+
+    /// Read back `to_json`
+    pub fn from_json(json: &str) -> Result<Self, HandoverError> {
+        let invalid = || HandoverError::Invalid("handover file".to_string());
+        let value = JsonValue::parse(json).ok_or_else(invalid)?;
+        let format = value
+            .get("format")
+            .and_then(JsonValue::as_f64)
+            .ok_or_else(invalid)? as u32;
+        if format > HANDOVER_FORMAT {
+            return Err(HandoverError::NewerFormat(format));
+        }
+
+        let text = |key| value.get(key).and_then(JsonValue::as_str);
+        let number = |key| value.get(key).and_then(JsonValue::as_f64);
+        let items = |key| value.get(key).and_then(JsonValue::as_array);
+        Ok(Self {
+            format,
+            version: text("version").ok_or_else(invalid)?.to_string(),
+            pid: number("pid").ok_or_else(invalid)? as u32,
+            time: number("time").ok_or_else(invalid)? as i64,
+            sessions: items("sessions")
+                .and_then(|x| x.iter().map(SessionSequences::from_value).collect())
+                .ok_or_else(invalid)?,
+            orders: items("orders")
+                .and_then(|x| x.iter().map(Order::from_value).collect())
+                .ok_or_else(invalid)?,
+            id_prefix: text("id_prefix").ok_or_else(invalid)?.to_string(),
+            next_cl_ord_id: number("next_cl_ord_id").ok_or_else(invalid)? as u64,
+            fills: items("fills")
+                .and_then(|x| x.iter().map(Fill::from_value).collect())
+                .ok_or_else(invalid)?,
+        })
+    }
+
+    /// Write the file; the new process may start reading it at once
+    pub fn write(&self, path: &Path) -> Result<(), HandoverError> {
+        write_atomically(path, &self.to_json())?;
+        Ok(())
+    }
+
+    /// Wait for the old process to write the file, then read it
+    pub fn wait(path: &Path, timeout: Duration) -> Result<Self, HandoverError> {
+        let started = Instant::now();
+        loop {
+            match fs::read_to_string(path) {
+                Ok(json) => return Self::from_json(&json),
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                Err(_) if started.elapsed() >= timeout => {
+                    return Err(HandoverError::Timeout(path.to_path_buf()))
+                }
+                Err(_) => thread::sleep(POLL_INTERVAL),
+            }
+        }
+    }
+
+    /// Mark the file as taken over (FILE.done), so it is read only once
+    pub fn consume(path: &Path) -> Result<PathBuf, HandoverError> {
+        let mut done = path.as_os_str().to_owned();
+        done.push(".done");
+        let done = PathBuf::from(done);
+        fs::rename(path, &done)?;
+        Ok(done)
+    }
+}
+
+/// Write to a temporary file, sync it, then rename it over `path`
+fn write_atomically(path: &Path, text: &str) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    let mut file = File::create(&temp)?;
+    file.write_all(text.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp, path)
+}
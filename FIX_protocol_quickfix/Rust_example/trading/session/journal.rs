@@ -0,0 +1,524 @@
+// =============================================================================
+// Wire Journal
+// =============================================================================
+// Every FIX message as it crossed the wire, byte for byte, with the time it
+// was received or sent to the nanosecond, appended to a binary file of its
+// own: replayed exactly, or set against SendingTime (52) for latency,
+// whatever the engine's own log keeps or drops.
+//
+// The file is a header (MAGIC, 8 bytes) followed by records, all integers
+// little endian:
+//
+//   length     u32   bytes of the record after this field
+//   at         u64   Unix nanoseconds
+//   direction  u8    0 inbound, 1 outbound
+//   label      u16   length, then the session label (UTF-8)
+//   payload          the rest: the message, SOH delimited
+//
+// Journal appends (one lock per record, buffered, flushed on flush() and on
+// drop); it is a LogCallback, so the engine's LogFactory can feed it the raw
+// traffic before anything is parsed. JournalReader reads the records back in
+// order; a record cut short at the end of the file (a writer killed in the
+// middle of one) is an error carrying its offset, every record before it
+// having been returned. Opened again for appending, the journal loses that
+// torn record.
+//
+// With the `encryption` feature, Journal::open_encrypted keeps the messages
+// encrypted at rest (AES-256-GCM, the key of the state store: a key file or
+// TRADING_STORE_KEY, store::encrypted). Such a journal starts with
+// MAGIC_ENCRYPTED, and the payload of each record is
+//
+//   nonce (12 bytes), ciphertext, tag (16 bytes)
+//
+// with the rest of the record (time, direction and session, which stay in
+// clear) as associated data: a payload moved to another record does not
+// decrypt. JournalReader::with_key reads it back decrypted, and a journal
+// in clear as it is; JournalReader::new refuses an encrypted one.
+// =============================================================================
+
+use std::{
+    error::Error,
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use quickfix::{LogCallback, SessionId};
+
+use super::{session_label, Direction};
+#[cfg(feature = "encryption")]
+use crate::store::encrypted::{Cipher, EncryptionKey, NONCE_LEN};
+use crate::time::parse_utc_timestamp;
+
+/// First bytes of every journal
+pub const MAGIC: &[u8; 8] = b"FIXJRNL1";
+
+/// First bytes of an encrypted journal
+pub const MAGIC_ENCRYPTED: &[u8; 8] = b"FIXJRNE1";
+
+/// Fixed part of a record after its length: at, direction, label length
+const FIXED: usize = 8 + 1 + 2;
+
+/// A record longer than this is taken for corruption
+const MAX_RECORD: usize = 16 * 1024 * 1024;
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum JournalError {
+    Io(io::Error),
+
+    /// The file does not start with MAGIC
+    NotJournal,
+
+    /// The file ends within the record starting at this offset
+    Truncated(u64),
+
+    /// A record that cannot be right, at this offset
+    Corrupt(u64, String),
+
+    /// An encrypted journal, opened without a key
+    Encrypted,
+
+    /// A journal in clear, opened to append encrypted records to
+    NotEncrypted,
+
+    /// The payload of the record at this offset does not decrypt: another
+    /// key, or a record tampered with
+    Decrypt(u64),
+}
+
+impl fmt::Display for JournalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JournalError::Io(err) => write!(f, "{err}"),
+            JournalError::NotJournal => write!(f, "not a FIX journal"),
+            JournalError::Truncated(offset) => {
+                write!(f, "journal truncated in the record at byte {offset}")
+            }
+            JournalError::Corrupt(offset, what) => {
+                write!(f, "corrupt record at byte {offset}: {what}")
+            }
+            JournalError::Encrypted => write!(f, "journal encrypted, no key given"),
+            JournalError::NotEncrypted => {
+                write!(f, "journal in clear, cannot append encrypted records")
+            }
+            JournalError::Decrypt(offset) => write!(
+                f,
+                "record at byte {offset} does not decrypt: wrong key, or tampered with"
+            ),
+        }
+    }
+}
+
+impl Error for JournalError {}
+
+impl From<io::Error> for JournalError {
+    fn from(err: io::Error) -> Self {
+        JournalError::Io(err)
+    }
+}
+
+// =============================================================================
+// Records
+// =============================================================================
+
+/// One message of a journal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalRecord {
+    /// Received or sent, Unix nanoseconds
+    pub at: i64,
+    pub direction: Direction,
+
+    /// Session label
+    pub session: String,
+
+    /// The message, exactly as on the wire
+    pub payload: Vec<u8>,
+}
+
+impl JournalRecord {
+    /// The payload as text, SOH kept
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.payload).into_owned()
+    }
+
+    /// Value of a tag in the payload, first occurrence
+    pub fn field(&self, tag: u32) -> Option<&str> {
+        let tag = tag.to_string();
+        self.payload
+            .split(|&b| b == 0x01)
+            .filter_map(|field| std::str::from_utf8(field).ok())
+            .filter_map(|field| field.split_once('='))
+            .find(|(t, _)| *t == tag)
+            .map(|(_, value)| value)
+    }
+
+    /// Time from SendingTime (52) to the record's own timestamp, in
+    /// nanoseconds: for an inbound message, the counterparty's send to our
+    /// receive (clock skew included); negative when 52 is ahead
+    pub fn sending_lag(&self) -> Option<i64> {
+        let sending_time = parse_utc_timestamp(self.field(52)?)?;
+        Some(self.at - sending_time)
+    }
+}
+
+// =============================================================================
+// Writer
+// =============================================================================
+
+/// An append-only journal, shared by the threads that record into it
+#[derive(Debug)]
+pub struct Journal {
+    out: Mutex<BufWriter<File>>,
+
+    /// Of an encrypted journal
+    #[cfg(feature = "encryption")]
+    cipher: Option<Cipher>,
+}
+
+impl Journal {
+    /// Open a journal to append to, created with its header if missing
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, JournalError> {
+        Ok(Self {
+            out: Mutex::new(BufWriter::new(append_to(path.as_ref(), MAGIC)?)),
+            #[cfg(feature = "encryption")]
+            cipher: None,
+        })
+    }
+
+    /// Open an encrypted journal to append to, created if missing
+    #[cfg(feature = "encryption")]
+    pub fn open_encrypted(
+        path: impl AsRef<Path>,
+        key: &EncryptionKey,
+    ) -> Result<Self, JournalError> {
+        Ok(Self {
+            out: Mutex::new(BufWriter::new(append_to(path.as_ref(), MAGIC_ENCRYPTED)?)),
+            cipher: Some(Cipher::new(key)),
+        })
+    }
+
+    /// Append a message with the current time
+    pub fn record(&self, session: &str, direction: Direction, payload: &[u8]) -> io::Result<()> {
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_nanos() as i64);
+        self.record_at(at, session, direction, payload)
+    }
+
+    /// Append a message with its own timestamp, Unix nanoseconds
+    pub fn record_at(
+        &self,
+        at: i64,
+        session: &str,
+        direction: Direction,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        let label = &session.as_bytes()[..session.len().min(u16::MAX as usize)];
+
+        // Time, direction and session: the associated data of an encrypted
+        // payload
+        let mut head = Vec::with_capacity(FIXED + label.len());
+        head.extend_from_slice(&at.to_le_bytes());
+        head.push(match direction {
+            Direction::Inbound => 0,
+            Direction::Outbound => 1,
+        });
+        head.extend_from_slice(&(label.len() as u16).to_le_bytes());
+        head.extend_from_slice(label);
+
+        #[cfg(feature = "encryption")]
+        let sealed = match &self.cipher {
+            Some(cipher) => {
+                let nonce = Cipher::generate_nonce();
+                let mut sealed = nonce.to_vec();
+                let data = cipher
+                    .seal(&nonce, &head, payload)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+                sealed.extend_from_slice(&data);
+                Some(sealed)
+            }
+            None => None,
+        };
+        #[cfg(feature = "encryption")]
+        let payload = sealed.as_deref().unwrap_or(payload);
+
+        let length = head.len() + payload.len();
+        if length > MAX_RECORD {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("message of {} bytes", payload.len()),
+            ));
+        }
+
+        let mut record = Vec::with_capacity(4 + length);
+        record.extend_from_slice(&(length as u32).to_le_bytes());
+        record.extend_from_slice(&head);
+        record.extend_from_slice(payload);
+
+        // One write per record: a record is never interleaved with another
+        self.out
+            .lock()
+            .expect("journal lock poisoned")
+            .write_all(&record)
+    }
+
+    /// Write out what is buffered
+    pub fn flush(&self) -> io::Result<()> {
+        self.out.lock().expect("journal lock poisoned").flush()
+    }
+
+    fn log(&self, session_id: Option<&SessionId>, direction: Direction, msg: &str) {
+        let session = session_id.map(session_label).unwrap_or_default();
+        if let Err(err) = self.record(&session, direction, msg.as_bytes()) {
+            eprintln!("Journal: cannot record a message: {err}");
+        }
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        if let Ok(out) = self.out.get_mut() {
+            let _ = out.flush();
+        }
+    }
+}
+
+/// Raw traffic from the engine's LogFactory; events are not journaled
+impl LogCallback for Journal {
+    fn on_incoming(&self, session_id: Option<&SessionId>, msg: &str) {
+        self.log(session_id, Direction::Inbound, msg);
+    }
+
+    fn on_outgoing(&self, session_id: Option<&SessionId>, msg: &str) {
+        self.log(session_id, Direction::Outbound, msg);
+    }
+
+    fn on_event(&self, _session_id: Option<&SessionId>, _msg: &str) {}
+}
+
+// =============================================================================
+// Reader
+// =============================================================================
+
+/// The records of a journal, in the order written
+pub struct JournalReader<R> {
+    input: R,
+
+    /// Byte offset of the next record
+    offset: u64,
+    done: bool,
+
+    /// Whether the journal starts with MAGIC_ENCRYPTED
+    encrypted: bool,
+
+    /// Decrypting the payloads of an encrypted journal; without it, they are
+    /// returned as stored
+    #[cfg(feature = "encryption")]
+    cipher: Option<Cipher>,
+}
+
+impl JournalReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, JournalError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+
+    /// Open a journal, encrypted with `key` or in clear
+    #[cfg(feature = "encryption")]
+    pub fn open_with_key(
+        path: impl AsRef<Path>,
+        key: &EncryptionKey,
+    ) -> Result<Self, JournalError> {
+        Self::with_key(BufReader::new(File::open(path)?), key)
+    }
+}
+
+impl<R: Read> JournalReader<R> {
+    /// Check the header and stand at the first record
+    ///
+    /// An encrypted journal is refused (JournalError::Encrypted): with_key
+    /// reads it.
+    pub fn new(input: R) -> Result<Self, JournalError> {
+        let reader = Self::framing(input)?;
+        if reader.encrypted {
+            return Err(JournalError::Encrypted);
+        }
+        Ok(reader)
+    }
+
+    /// Check the header and stand at the first record, the payloads of an
+    /// encrypted journal to be decrypted with `key`
+    #[cfg(feature = "encryption")]
+    pub fn with_key(input: R, key: &EncryptionKey) -> Result<Self, JournalError> {
+        let mut reader = Self::framing(input)?;
+        if reader.encrypted {
+            reader.cipher = Some(Cipher::new(key));
+        }
+        Ok(reader)
+    }
+
+    /// A journal of either kind, its payloads as stored
+    fn framing(mut input: R) -> Result<Self, JournalError> {
+        let mut magic = [0; MAGIC.len()];
+        input
+            .read_exact(&mut magic)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => JournalError::NotJournal,
+                _ => JournalError::Io(err),
+            })?;
+        if &magic != MAGIC && &magic != MAGIC_ENCRYPTED {
+            return Err(JournalError::NotJournal);
+        }
+        Ok(Self {
+            input,
+            offset: MAGIC.len() as u64,
+            done: false,
+            encrypted: &magic == MAGIC_ENCRYPTED,
+            #[cfg(feature = "encryption")]
+            cipher: None,
+        })
+    }
+
+    /// Byte offset of the next record
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    fn read_record(&mut self) -> Result<Option<JournalRecord>, JournalError> {
+        let offset = self.offset;
+
+        // A clean end falls between two records
+        let mut length = [0; 4];
+        let read = read_full(&mut self.input, &mut length)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if read < length.len() {
+            return Err(JournalError::Truncated(offset));
+        }
+        let length = u32::from_le_bytes(length) as usize;
+        if !(FIXED..=MAX_RECORD).contains(&length) {
+            return Err(JournalError::Corrupt(offset, format!("length {length}")));
+        }
+
+        let mut record = vec![0; length];
+        if read_full(&mut self.input, &mut record)? < length {
+            return Err(JournalError::Truncated(offset));
+        }
+
+        let at = i64::from_le_bytes(record[..8].try_into().expect("8 bytes"));
+        let direction = match record[8] {
+            0 => Direction::Inbound,
+            1 => Direction::Outbound,
+            other => {
+                let what = format!("direction {other}");
+                return Err(JournalError::Corrupt(offset, what));
+            }
+        };
+        let label_length = u16::from_le_bytes([record[9], record[10]]) as usize;
+        if FIXED + label_length > length {
+            let what = format!("label of {label_length} bytes");
+            return Err(JournalError::Corrupt(offset, what));
+        }
+        let session = String::from_utf8_lossy(&record[FIXED..FIXED + label_length]).into_owned();
+        let payload = record.split_off(FIXED + label_length);
+        #[cfg(feature = "encryption")]
+        let payload = match &self.cipher {
+            Some(cipher) => {
+                open_payload(cipher, &record, &payload).ok_or(JournalError::Decrypt(offset))?
+            }
+            None => payload,
+        };
+
+        self.offset += 4 + length as u64;
+        Ok(Some(JournalRecord {
+            at,
+            direction,
+            session,
+            payload,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for JournalReader<R> {
+    type Item = Result<JournalRecord, JournalError>;
+
+    /// Stops after the first error: what follows it cannot be framed
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_record() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Open a journal to append to, created with `magic` if missing, a record
+/// torn at its end cut off
+fn append_to(path: &Path, magic: &[u8; 8]) -> Result<File, JournalError> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+    if file.metadata()?.len() == 0 {
+        file.write_all(magic)?;
+        return Ok(file);
+    }
+
+    file.seek(SeekFrom::Start(0))?;
+    let reader = JournalReader::framing(BufReader::new(&file))?;
+    match (reader.encrypted, magic == MAGIC_ENCRYPTED) {
+        (true, false) => return Err(JournalError::Encrypted),
+        (false, true) => return Err(JournalError::NotEncrypted),
+        _ => {}
+    }
+    // A record torn by a writer killed in its middle is cut off: appended
+    // after it, the next ones could not be framed
+    for record in reader {
+        match record {
+            Ok(_) => {}
+            Err(JournalError::Truncated(offset)) => file.set_len(offset)?,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(file)
+}
+
+/// The payload of an encrypted record, `head` its time, direction and
+/// session
+#[cfg(feature = "encryption")]
+fn open_payload(cipher: &Cipher, head: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+    let (nonce, data) = sealed.split_at_checked(NONCE_LEN)?;
+    cipher.open(nonce.try_into().ok()?, head, data)
+}
+
+/// Read until `buffer` is full or the input ends; the bytes read
+fn read_full(input: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match input.read(&mut buffer[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read)
+}
@@ -0,0 +1,329 @@
+// =============================================================================
+// Fast Lane / Batch Lane Event Queues
+// =============================================================================
+// A single event channel (events.rs) delivers in arrival order: an
+// ExecutionReport queued behind a burst of news or a security list waits
+// for all of it to be processed. A LaneSplit declares which MsgTypes are
+// latency-critical; those take a fast lane, the rest a batch lane:
+//
+//   callbacks --8, X--------> fast lane  --+
+//             --news, y, ...> batch lane --+--> LaneReceiver::recv()
+//
+// recv() always looks at the fast lane first, and again before each batch
+// event it hands out, so a critical event waits for at most one other
+// event. Batch events are gathered until --batch-size of them are waiting
+// or the first has waited the batch delay, then handed out together.
+//
+// Session events (created, logon, logout) always take the fast lane, and
+// news always takes the batch lane. With no critical MsgType declared,
+// everything takes the fast lane: one queue in arrival order, as before.
+//
+// Order is kept within a lane, not across lanes: MsgTypes whose relative
+// order matters (an ExecutionReport and the market data it traded on, say)
+// belong on the same lane.
+//
+// The receiver reports each event handed out, with its lane and the time it
+// queued, and each batch to a LaneObserver (gateway::metrics::Metrics).
+// =============================================================================
+
+use std::{
+    collections::{HashSet, VecDeque},
+    error::Error,
+    fmt,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::mpsc::{self, error::SendError};
+
+use crate::session::events::FixEvent;
+
+/// Batch events handed out together, at most
+pub const DEFAULT_BATCH_SIZE: usize = 64;
+
+/// How long the first event of a batch waits for the others
+pub const DEFAULT_BATCH_DELAY: Duration = Duration::from_millis(5);
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LaneError {
+    /// Not a MsgType: empty, or not letters and digits
+    InvalidMsgType(String),
+}
+
+impl fmt::Display for LaneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LaneError::InvalidMsgType(x) => write!(f, "invalid MsgType '{x}'"),
+        }
+    }
+}
+
+impl Error for LaneError {}
+
+// =============================================================================
+// Split
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lane {
+    Fast,
+    Batch,
+}
+
+impl Lane {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lane::Fast => "fast",
+            Lane::Batch => "batch",
+        }
+    }
+}
+
+/// Which events take the fast lane, and how the others are batched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaneSplit {
+    critical: HashSet<String>,
+    batch_size: usize,
+    batch_delay: Duration,
+}
+
+impl Default for LaneSplit {
+    fn default() -> Self {
+        Self {
+            critical: HashSet::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            batch_delay: DEFAULT_BATCH_DELAY,
+        }
+    }
+}
+
+impl LaneSplit {
+    /// No critical MsgType: one lane, arrival order
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add MsgTypes to the fast lane
+    pub fn with_critical(mut self, msg_types: &[&str]) -> Self {
+        self.critical
+            .extend(msg_types.iter().map(|x| x.to_string()));
+        self
+    }
+
+    /// Hand out batch events by `size` at most (1 at least), the first
+    /// waiting `delay` for the others
+    pub fn with_batch(mut self, size: usize, delay: Duration) -> Self {
+        self.batch_size = size.max(1);
+        self.batch_delay = delay;
+        self
+    }
+
+    /// Whether any MsgType was declared critical
+    pub fn is_split(&self) -> bool {
+        !self.critical.is_empty()
+    }
+
+    /// Critical MsgTypes, sorted
+    pub fn critical(&self) -> Vec<&str> {
+        let mut msg_types: Vec<_> = self.critical.iter().map(String::as_str).collect();
+        msg_types.sort_unstable();
+        msg_types
+    }
+
+    /// The lane an event takes
+    pub fn lane(&self, event: &FixEvent) -> Lane {
+        if !self.is_split() {
+            return Lane::Fast;
+        }
+        match event {
+            FixEvent::Created { .. } | FixEvent::Logon { .. } | FixEvent::Logout { .. } => {
+                Lane::Fast
+            }
+            FixEvent::Message(msg) if self.critical.contains(msg.msg_type()) => Lane::Fast,
+            FixEvent::Message(_) | FixEvent::News(_) => Lane::Batch,
+        }
+    }
+}
+
+/// Comma-separated critical MsgTypes: `8,X`
+impl FromStr for LaneSplit {
+    type Err = LaneError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let msg_types = text
+            .split(',')
+            .map(str::trim)
+            .map(|x| {
+                if !x.is_empty() && x.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    Ok(x)
+                } else {
+                    Err(LaneError::InvalidMsgType(x.to_string()))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new().with_critical(&msg_types))
+    }
+}
+
+// =============================================================================
+// Observer
+// =============================================================================
+
+/// Told about what the receiver hands out
+pub trait LaneObserver: Send + Sync {
+    /// An event handed out, after `waited` in its lane
+    fn on_lane_event(&self, lane: Lane, waited: Duration);
+
+    /// A batch released, of `len` events
+    fn on_lane_batch(&self, len: usize);
+}
+
+// =============================================================================
+// Channel
+// =============================================================================
+
+struct Queued {
+    event: FixEvent,
+    sent: Instant,
+}
+
+/// Sending half: routes each event to its lane; cheap to clone
+#[derive(Clone)]
+pub struct LaneSender {
+    split: Arc<LaneSplit>,
+    fast: mpsc::UnboundedSender<Queued>,
+    batch: mpsc::UnboundedSender<Queued>,
+}
+
+impl LaneSender {
+    /// Never blocks (unbounded lanes); fails only once the receiver is gone,
+    /// handing the event back boxed (a FixEvent is large)
+    pub fn send(&self, event: FixEvent) -> Result<(), Box<SendError<FixEvent>>> {
+        let lane = match self.split.lane(&event) {
+            Lane::Fast => &self.fast,
+            Lane::Batch => &self.batch,
+        };
+        lane.send(Queued {
+            event,
+            sent: Instant::now(),
+        })
+        .map_err(|err| Box::new(SendError(err.0.event)))
+    }
+}
+
+/// Receiving half, for the consuming task
+pub struct LaneReceiver {
+    split: Arc<LaneSplit>,
+    fast: mpsc::UnboundedReceiver<Queued>,
+    batch: mpsc::UnboundedReceiver<Queued>,
+    fast_open: bool,
+    batch_open: bool,
+
+    /// Batch events gathered so far, and when they are released at the
+    /// latest
+    gathering: Vec<Queued>,
+    deadline: Option<Instant>,
+
+    /// Released batch events, not handed out yet
+    released: VecDeque<Queued>,
+    observer: Option<Arc<dyn LaneObserver>>,
+}
+
+/// Two lanes, split as `split` says
+pub fn channel(split: LaneSplit) -> (LaneSender, LaneReceiver) {
+    let split = Arc::new(split);
+    let (fast_sender, fast_receiver) = mpsc::unbounded_channel();
+    let (batch_sender, batch_receiver) = mpsc::unbounded_channel();
+    let sender = LaneSender {
+        split: Arc::clone(&split),
+        fast: fast_sender,
+        batch: batch_sender,
+    };
+    let receiver = LaneReceiver {
+        split,
+        fast: fast_receiver,
+        batch: batch_receiver,
+        fast_open: true,
+        batch_open: true,
+        gathering: Vec::new(),
+        deadline: None,
+        released: VecDeque::new(),
+        observer: None,
+    };
+    (sender, receiver)
+}
+
+impl LaneReceiver {
+    pub fn with_observer(mut self, observer: Arc<dyn LaneObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Next event: from the fast lane if one is waiting there
+    ///
+    /// None once every sender is dropped and both lanes are drained.
+    pub async fn recv(&mut self) -> Option<FixEvent> {
+        loop {
+            if let Ok(queued) = self.fast.try_recv() {
+                return Some(self.hand_out(Lane::Fast, queued));
+            }
+            if let Some(queued) = self.released.pop_front() {
+                return Some(self.hand_out(Lane::Batch, queued));
+            }
+            if !self.fast_open && !self.batch_open {
+                if self.gathering.is_empty() {
+                    return None;
+                }
+                self.release();
+                continue;
+            }
+
+            let deadline = self.deadline.unwrap_or_else(Instant::now);
+            tokio::select! {
+                biased;
+                queued = self.fast.recv(), if self.fast_open => match queued {
+                    Some(queued) => return Some(self.hand_out(Lane::Fast, queued)),
+                    None => self.fast_open = false,
+                },
+                queued = self.batch.recv(), if self.batch_open => match queued {
+                    Some(queued) => self.gather(queued),
+                    None => self.batch_open = false,
+                },
+                () = tokio::time::sleep_until(deadline.into()), if self.deadline.is_some() => {
+                    self.release();
+                }
+            }
+        }
+    }
+
+    fn gather(&mut self, queued: Queued) {
+        if self.gathering.is_empty() {
+            self.deadline = Some(Instant::now() + self.split.batch_delay);
+        }
+        self.gathering.push(queued);
+        if self.gathering.len() >= self.split.batch_size {
+            self.release();
+        }
+    }
+
+    fn release(&mut self) {
+        if let Some(observer) = &self.observer {
+            observer.on_lane_batch(self.gathering.len());
+        }
+        self.released.extend(self.gathering.drain(..));
+        self.deadline = None;
+    }
+
+    fn hand_out(&self, lane: Lane, queued: Queued) -> FixEvent {
+        if let Some(observer) = &self.observer {
+            observer.on_lane_event(lane, queued.sent.elapsed());
+        }
+        queued.event
+    }
+}
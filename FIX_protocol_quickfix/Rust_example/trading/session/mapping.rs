@@ -0,0 +1,317 @@
+// =============================================================================
+// Struct Mappings
+// =============================================================================
+// A struct declared with fix_message! knows its MsgType and the tag of each
+// field, and converts to and from a QuickFIX Message; fix_group! does the
+// same for the entries of a repeating group, which a message (or another
+// group) holds as a Vec:
+//
+//   trading::fix_group! {
+//       #[derive(Debug, Clone)]
+//       pub struct Leg {
+//           pub symbol: String = 600,          // LegSymbol, the delimiter
+//           pub ratio: Option<f64> = 623,      // LegRatioQty
+//       }
+//   }
+//
+//   trading::fix_message! {
+//       #[derive(Debug, Clone)]
+//       pub struct NewOrder: "D" {
+//           pub cl_ord_id: String = 11,
+//           pub symbol: String = 55,
+//           pub side: Side = 54,
+//           pub quantity: f64 = 38,
+//           pub price: Option<f64> = 44,
+//           pub legs: Vec<Leg> = 555,          // NoLegs
+//       }
+//   }
+//
+//   let msg = order.to_message()?;             // MessageMapping
+//   let order = NewOrder::from_message(&msg)?; // a ViewError if malformed
+//
+// The first field of a group is its delimiter. A field is read and written
+// through its type's FieldValue: the strings, numbers, char, bool and Date
+// of FIX, Option for an optional field (absent when None), Vec for a
+// group (empty when its count is missing). Enumerations implement it
+// with their FIX values, as oms::Side and oms::OrdType do. Reading goes
+// through a MessageView (view.rs), so a mapped struct is rejected for the
+// same reasons, with the same MsgFromAppError, as the getters.
+// =============================================================================
+
+use quickfix::{FieldMap, Group, Message};
+
+use crate::{
+    session::view::{MessageView, OptionalField, ViewError},
+    time::Date,
+};
+
+#[doc(hidden)]
+pub use quickfix::QuickFixError;
+
+// =============================================================================
+// Traits
+// =============================================================================
+
+/// Where fields and groups are written: a Message or a Group
+pub trait FieldSink: FieldMap {
+    fn add_group(&mut self, group: &Group) -> Result<(), QuickFixError>;
+}
+
+impl FieldSink for Message {
+    fn add_group(&mut self, group: &Group) -> Result<(), QuickFixError> {
+        Message::add_group(self, group)
+    }
+}
+
+impl FieldSink for Group {
+    fn add_group(&mut self, group: &Group) -> Result<(), QuickFixError> {
+        Group::add_group(self, group)
+    }
+}
+
+/// A type a mapped field can have
+pub trait FieldValue: Sized {
+    fn read(view: &MessageView<'_>, tag: i32) -> Result<Self, ViewError>;
+
+    fn write<S: FieldSink>(&self, sink: &mut S, tag: i32) -> Result<(), QuickFixError>;
+}
+
+/// The fields of a mapped struct, implemented by the macros
+pub trait FixFields: Sized {
+    fn from_view(view: &MessageView<'_>) -> Result<Self, ViewError>;
+
+    fn write_fields<S: FieldSink>(&self, sink: &mut S) -> Result<(), QuickFixError>;
+}
+
+/// A struct declared with fix_message!
+pub trait MessageMapping: FixFields {
+    /// MsgType (35)
+    const MSG_TYPE: &'static str;
+
+    /// The message, header MsgType included; the engine fills the rest of
+    /// the header
+    fn to_message(&self) -> Result<Message, QuickFixError> {
+        let mut msg = Message::new();
+        msg.with_header_mut(|h| h.set_field(35, Self::MSG_TYPE))?;
+        self.write_fields(&mut msg)?;
+        Ok(msg)
+    }
+
+    /// The struct of a message of its MsgType
+    fn from_message(msg: &Message) -> Result<Self, ViewError> {
+        let view = MessageView::new(msg);
+        let msg_type = view.msg_type()?;
+        if msg_type != Self::MSG_TYPE {
+            return Err(ViewError::OutOfRange {
+                tag: 35,
+                value: msg_type,
+            });
+        }
+        Self::from_view(&view)
+    }
+}
+
+/// A struct declared with fix_group!
+pub trait GroupMapping: FixFields {
+    /// Tag of the first field of an entry
+    const DELIMITER: i32;
+}
+
+// =============================================================================
+// Field Types
+// =============================================================================
+
+impl FieldValue for String {
+    fn read(view: &MessageView<'_>, tag: i32) -> Result<Self, ViewError> {
+        view.get_str(tag)
+    }
+
+    fn write<S: FieldSink>(&self, sink: &mut S, tag: i32) -> Result<(), QuickFixError> {
+        sink.set_field(tag, self.as_str())
+    }
+}
+
+impl FieldValue for char {
+    fn read(view: &MessageView<'_>, tag: i32) -> Result<Self, ViewError> {
+        view.get_char(tag)
+    }
+
+    fn write<S: FieldSink>(&self, sink: &mut S, tag: i32) -> Result<(), QuickFixError> {
+        sink.set_field(tag, self.to_string().as_str())
+    }
+}
+
+impl FieldValue for bool {
+    fn read(view: &MessageView<'_>, tag: i32) -> Result<Self, ViewError> {
+        view.get_bool(tag)
+    }
+
+    fn write<S: FieldSink>(&self, sink: &mut S, tag: i32) -> Result<(), QuickFixError> {
+        sink.set_field(tag, if *self { "Y" } else { "N" })
+    }
+}
+
+impl FieldValue for i64 {
+    fn read(view: &MessageView<'_>, tag: i32) -> Result<Self, ViewError> {
+        view.get_int(tag)
+    }
+
+    fn write<S: FieldSink>(&self, sink: &mut S, tag: i32) -> Result<(), QuickFixError> {
+        sink.set_field(tag, self.to_string().as_str())
+    }
+}
+
+impl FieldValue for u64 {
+    fn read(view: &MessageView<'_>, tag: i32) -> Result<Self, ViewError> {
+        let value = view.get_int(tag)?;
+        u64::try_from(value).map_err(|_| ViewError::Invalid {
+            tag,
+            value: value.to_string(),
+            expected: "positive int",
+        })
+    }
+
+    fn write<S: FieldSink>(&self, sink: &mut S, tag: i32) -> Result<(), QuickFixError> {
+        sink.set_field(tag, self.to_string().as_str())
+    }
+}
+
+/// Prices and quantities; a quantity is not checked for sign here, unlike
+/// MessageView::get_qty
+impl FieldValue for f64 {
+    fn read(view: &MessageView<'_>, tag: i32) -> Result<Self, ViewError> {
+        view.get_price(tag)
+    }
+
+    fn write<S: FieldSink>(&self, sink: &mut S, tag: i32) -> Result<(), QuickFixError> {
+        sink.set_field(tag, self.to_string().as_str())
+    }
+}
+
+impl FieldValue for Date {
+    fn read(view: &MessageView<'_>, tag: i32) -> Result<Self, ViewError> {
+        view.get_date(tag)
+    }
+
+    fn write<S: FieldSink>(&self, sink: &mut S, tag: i32) -> Result<(), QuickFixError> {
+        sink.set_field(tag, self.to_fix().as_str())
+    }
+}
+
+impl<T: FieldValue> FieldValue for Option<T> {
+    fn read(view: &MessageView<'_>, tag: i32) -> Result<Self, ViewError> {
+        T::read(view, tag).optional()
+    }
+
+    fn write<S: FieldSink>(&self, sink: &mut S, tag: i32) -> Result<(), QuickFixError> {
+        match self {
+            Some(value) => value.write(sink, tag),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A repeating group, by its NumInGroup tag; QuickFIX sets the count
+impl<G: GroupMapping> FieldValue for Vec<G> {
+    fn read(view: &MessageView<'_>, tag: i32) -> Result<Self, ViewError> {
+        view.groups(tag).map(|entry| G::from_view(&entry)).collect()
+    }
+
+    fn write<S: FieldSink>(&self, sink: &mut S, tag: i32) -> Result<(), QuickFixError> {
+        for entry in self {
+            let mut group = Group::try_new(tag, G::DELIMITER)?;
+            entry.write_fields(&mut group)?;
+            sink.add_group(&group)?;
+        }
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Macros
+// =============================================================================
+
+/// Declare a struct mapped to a FIX message: `struct Name: "MsgType" {
+/// field: Type = tag, ... }` (see trading::session::mapping)
+#[macro_export]
+macro_rules! fix_message {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident : $msg_type:literal {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident : $ty:ty = $tag:literal),+
+            $(,)?
+        }
+    ) => {
+        $crate::fix_fields! {
+            $(#[$meta])*
+            $vis struct $name {
+                $($(#[$field_meta])* $field_vis $field : $ty = $tag),+
+            }
+        }
+
+        impl $crate::session::mapping::MessageMapping for $name {
+            const MSG_TYPE: &'static str = $msg_type;
+        }
+    };
+}
+
+/// Declare a struct mapped to a repeating group entry, its first field the
+/// delimiter: `struct Name { field: Type = tag, ... }`
+#[macro_export]
+macro_rules! fix_group {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(#[$first_meta:meta])* $first_vis:vis $first:ident : $first_ty:ty = $first_tag:literal
+            $(, $(#[$field_meta:meta])* $field_vis:vis $field:ident : $ty:ty = $tag:literal)*
+            $(,)?
+        }
+    ) => {
+        $crate::fix_fields! {
+            $(#[$meta])*
+            $vis struct $name {
+                $(#[$first_meta])* $first_vis $first : $first_ty = $first_tag
+                $(, $(#[$field_meta])* $field_vis $field : $ty = $tag)*
+            }
+        }
+
+        impl $crate::session::mapping::GroupMapping for $name {
+            const DELIMITER: i32 = $first_tag;
+        }
+    };
+}
+
+/// The struct and its FixFields, for fix_message! and fix_group!
+#[doc(hidden)]
+#[macro_export]
+macro_rules! fix_fields {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident : $ty:ty = $tag:literal),+
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty),+
+        }
+
+        impl $crate::session::mapping::FixFields for $name {
+            fn from_view(
+                view: &$crate::session::view::MessageView<'_>,
+            ) -> Result<Self, $crate::session::view::ViewError> {
+                Ok(Self {
+                    $($field: $crate::session::mapping::FieldValue::read(view, $tag)?),+
+                })
+            }
+
+            fn write_fields<S: $crate::session::mapping::FieldSink>(
+                &self,
+                sink: &mut S,
+            ) -> Result<(), $crate::session::mapping::QuickFixError> {
+                $($crate::session::mapping::FieldValue::write(&self.$field, sink, $tag)?;)+
+                Ok(())
+            }
+        }
+    };
+}
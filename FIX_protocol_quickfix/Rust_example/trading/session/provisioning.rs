@@ -0,0 +1,186 @@
+// =============================================================================
+// Dynamic Session Provisioning
+// =============================================================================
+// Adds sessions to a running application without restarting the process,
+// e.g. from the fix_repl shell:
+//
+//   FIX> add_session FIX.4.4 EXCHANGE CLIENT2 port=5002 HeartBtInt=30
+//
+// 1. SessionSpec::to_dictionary builds the session's Dictionary; a FIXT.1.1
+//    session must have a DefaultApplVerID, here or in [DEFAULT], since
+//    QuickFIX sends it in the Logon
+// 2. add_session registers it with the SessionSettings ([DEFAULT] values
+//    from the config file still apply, as for a [SESSION] block)
+// 3. The caller rebuilds the connection handler (and the file store
+//    factory) with the updated settings
+//
+// Step 3 exists because QuickFIX cannot attach a session to a running
+// SocketAcceptor / SocketInitiator: both read the session list once, when
+// they are created. The handler is stopped and recreated, so sessions that
+// were already connected log out and back in; their sequence numbers are
+// kept by the file message store.
+// =============================================================================
+
+use std::{error::Error, fmt};
+
+use quickfix::{Dictionary, QuickFixError, SessionId, SessionSettings};
+
+use crate::session::version::{is_begin_string, FixVersion, FIXT_1_1};
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+pub enum ProvisioningError {
+    /// The settings have no ConnectionType, so `port=` cannot be mapped
+    UnknownConnectionType,
+
+    /// `port=` is not a valid TCP port
+    InvalidPort(String),
+
+    /// Not FIX.4.0 to FIX.4.4 or FIXT.1.1
+    InvalidBeginString(String),
+
+    /// A FIXT.1.1 session without DefaultApplVerID
+    MissingDefaultApplVerId,
+
+    /// DefaultApplVerID is neither a version name nor an ApplVerID value
+    InvalidDefaultApplVerId(String),
+
+    /// QuickFIX refused the dictionary (duplicate session, bad value, ...)
+    QuickFix(QuickFixError),
+}
+
+impl fmt::Display for ProvisioningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProvisioningError::UnknownConnectionType => {
+                write!(f, "ConnectionType missing from the [DEFAULT] settings")
+            }
+            ProvisioningError::InvalidPort(port) => write!(f, "invalid port: {port}"),
+            ProvisioningError::InvalidBeginString(begin_string) => {
+                write!(f, "invalid BeginString: {begin_string}")
+            }
+            ProvisioningError::MissingDefaultApplVerId => {
+                write!(f, "FIXT.1.1 sessions need DefaultApplVerID (e.g. FIX.5.0SP2)")
+            }
+            ProvisioningError::InvalidDefaultApplVerId(value) => {
+                write!(f, "invalid DefaultApplVerID: {value}")
+            }
+            ProvisioningError::QuickFix(err) => write!(f, "quickfix: {err:?}"),
+        }
+    }
+}
+
+impl Error for ProvisioningError {}
+
+impl From<QuickFixError> for ProvisioningError {
+    fn from(err: QuickFixError) -> Self {
+        ProvisioningError::QuickFix(err)
+    }
+}
+
+// =============================================================================
+// SessionSpec
+// =============================================================================
+
+/// A session to add: its identifier plus settings as `key=value` pairs
+///
+/// `port` is a shorthand for SocketAcceptPort or SocketConnectPort, depending
+/// on the connection type; any other key is a QuickFIX setting, passed as is.
+#[derive(Debug, Clone)]
+pub struct SessionSpec {
+    pub begin_string: String,
+    pub sender_comp_id: String,
+    pub target_comp_id: String,
+    pub params: Vec<(String, String)>,
+}
+
+impl SessionSpec {
+    pub fn session_id(&self) -> Result<SessionId, QuickFixError> {
+        SessionId::try_new(
+            &self.begin_string,
+            &self.sender_comp_id,
+            &self.target_comp_id,
+            "",
+        )
+    }
+
+    /// Build the session's Dictionary for an acceptor or an initiator
+    ///
+    /// # Arguments
+    /// * `connection_type` - `acceptor` or `initiator`, as in ConnectionType
+    pub fn to_dictionary(&self, connection_type: &str) -> Result<Dictionary, ProvisioningError> {
+        let port_key = match connection_type {
+            "acceptor" => "SocketAcceptPort",
+            "initiator" => "SocketConnectPort",
+            _ => return Err(ProvisioningError::UnknownConnectionType),
+        };
+
+        let mut dictionary = Dictionary::new();
+        for (key, value) in &self.params {
+            if key == "port" {
+                let port: u16 = value
+                    .parse()
+                    .map_err(|_| ProvisioningError::InvalidPort(value.clone()))?;
+                dictionary.set(port_key, i32::from(port))?;
+            } else {
+                dictionary.set(key, value.as_str())?;
+            }
+        }
+        Ok(dictionary)
+    }
+}
+
+impl fmt::Display for SessionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}->{}",
+            self.begin_string, self.sender_comp_id, self.target_comp_id
+        )
+    }
+}
+
+// =============================================================================
+// Registration
+// =============================================================================
+
+/// Register a new session with the settings
+///
+/// The running connection handler does not see it until it is rebuilt from
+/// these settings (see the module comment).
+pub fn add_session(
+    settings: &mut SessionSettings,
+    spec: &SessionSpec,
+) -> Result<SessionId, ProvisioningError> {
+    let connection_type = settings
+        .with_dictionary(None, |x| x.get::<String>("ConnectionType").ok())
+        .flatten()
+        .ok_or(ProvisioningError::UnknownConnectionType)?;
+
+    if !is_begin_string(&spec.begin_string) {
+        return Err(ProvisioningError::InvalidBeginString(spec.begin_string.clone()));
+    }
+    if spec.begin_string == FIXT_1_1 {
+        let appl_ver_id = spec
+            .params
+            .iter()
+            .find(|(key, _)| key == "DefaultApplVerID")
+            .map(|(_, value)| value.clone())
+            .or_else(|| {
+                settings
+                    .with_dictionary(None, |x| x.get::<String>("DefaultApplVerID").ok())
+                    .flatten()
+            })
+            .ok_or(ProvisioningError::MissingDefaultApplVerId)?;
+        if FixVersion::from_appl_ver_id(&appl_ver_id).is_none() {
+            return Err(ProvisioningError::InvalidDefaultApplVerId(appl_ver_id));
+        }
+    }
+
+    let session_id = spec.session_id()?;
+    settings.set(Some(&session_id), spec.to_dictionary(&connection_type)?)?;
+    Ok(session_id)
+}
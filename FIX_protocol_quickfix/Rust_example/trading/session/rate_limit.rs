@@ -0,0 +1,433 @@
+// =============================================================================
+// Outbound Rate Limits
+// =============================================================================
+// Venues cap how many messages a client sends, per session and sometimes
+// across every session of the firm, and reject or log out whoever goes over.
+// A RateLimiter sits in front of send_to_target with a token bucket per
+// session and one shared by all of them:
+//
+//   rate    tokens added per second: the messages per second sustained
+//   burst   tokens a bucket holds: the messages sent at once after a pause
+//
+// A message takes a token from the bucket of its session and from the
+// global one. When either is empty, the policy decides:
+//
+//   queue    wait for the token on the calling thread, up to max_wait;
+//            each message reserves its slot, so they keep their order
+//   reject   fail at once with RateLimited, saying when to try again
+//
+// A message that would wait longer than max_wait is rejected too. A message
+// rejected takes no token. Without limits, send is send_to_target.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    str::FromStr,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use quickfix::{send_to_target, Message, QuickFixError, SessionId};
+
+use crate::session::session_label;
+
+/// How long a queued message waits at most, for `queue` without a wait
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(1);
+
+// =============================================================================
+// Limits
+// =============================================================================
+
+/// A token bucket's refill rate and size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    /// Messages per second
+    pub per_second: u32,
+
+    /// Messages sent at once after a pause
+    pub burst: u32,
+}
+
+impl Rate {
+    /// A burst of one second's worth
+    pub fn per_second(per_second: u32) -> Self {
+        Self {
+            per_second,
+            burst: per_second.max(1),
+        }
+    }
+
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+}
+
+/// `RATE` or `RATE/BURST`, in messages per second
+impl FromStr for Rate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rate, burst) = match s.split_once('/') {
+            Some((rate, burst)) => (rate, Some(burst)),
+            None => (s, None),
+        };
+        let number = |x: &str| x.trim().parse::<u32>().ok().filter(|x| *x > 0);
+        let rate = number(rate).ok_or_else(|| format!("not a rate in msgs/sec: {s}"))?;
+        match burst {
+            Some(burst) => {
+                let burst = number(burst).ok_or_else(|| format!("not a burst size: {s}"))?;
+                Ok(Rate::per_second(rate).with_burst(burst))
+            }
+            None => Ok(Rate::per_second(rate)),
+        }
+    }
+}
+
+/// What to do with a message over the limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RatePolicy {
+    /// Wait for a token, at most this long
+    Queue { max_wait: Duration },
+
+    /// Fail at once
+    Reject,
+}
+
+impl Default for RatePolicy {
+    fn default() -> Self {
+        RatePolicy::Queue {
+            max_wait: DEFAULT_MAX_WAIT,
+        }
+    }
+}
+
+/// `queue`, `queue:MS` (the longest wait, in milliseconds) or `reject`
+impl FromStr for RatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "queue" => Ok(RatePolicy::default()),
+            None if s == "reject" => Ok(RatePolicy::Reject),
+            Some(("queue", ms)) => match ms.parse() {
+                Ok(ms) => Ok(RatePolicy::Queue {
+                    max_wait: Duration::from_millis(ms),
+                }),
+                Err(_) => Err(format!("not a wait in milliseconds: {ms}")),
+            },
+            _ => Err(format!("unknown policy {s} (queue, queue:MS or reject)")),
+        }
+    }
+}
+
+/// The caps of a RateLimiter
+#[derive(Debug, Clone, Default)]
+pub struct RateLimits {
+    /// Each session, unless it has its own
+    pub session: Option<Rate>,
+
+    /// Sessions by label, instead of `session`
+    pub sessions: HashMap<String, Rate>,
+
+    /// Every session together
+    pub global: Option<Rate>,
+    pub policy: RatePolicy,
+}
+
+impl RateLimits {
+    /// No limit at all
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cap every session at this rate
+    pub fn with_session(mut self, rate: Rate) -> Self {
+        self.session = Some(rate);
+        self
+    }
+
+    /// Cap one session (a label) at its own rate
+    pub fn with_session_rate(mut self, label: &str, rate: Rate) -> Self {
+        self.sessions.insert(label.to_string(), rate);
+        self
+    }
+
+    /// Cap all the sessions together
+    pub fn with_global(mut self, rate: Rate) -> Self {
+        self.global = Some(rate);
+        self
+    }
+
+    pub fn with_policy(mut self, policy: RatePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.session.is_some() || !self.sessions.is_empty() || self.global.is_some()
+    }
+
+    /// The cap of a session, if any
+    pub fn session_rate(&self, label: &str) -> Option<Rate> {
+        self.sessions.get(label).copied().or(self.session)
+    }
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+/// Which bucket ran out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateScope {
+    /// The session of this label
+    Session(String),
+    Global,
+}
+
+/// A message over the limits, not sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimited {
+    pub scope: RateScope,
+
+    /// When a token is free again
+    pub retry_after: Duration,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.scope {
+            RateScope::Session(label) => write!(f, "rate limit of {label} reached")?,
+            RateScope::Global => write!(f, "global rate limit reached")?,
+        }
+        write!(f, ", retry in {:?}", self.retry_after)
+    }
+}
+
+impl Error for RateLimited {}
+
+/// Why RateLimiter::send did not send
+#[derive(Debug)]
+pub enum SendError {
+    /// Over the limits: not handed to QuickFIX
+    Limited(RateLimited),
+
+    /// QuickFIX refused the message
+    Send(QuickFixError),
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Limited(err) => write!(f, "{err}"),
+            SendError::Send(err) => write!(f, "send failed: {err:?}"),
+        }
+    }
+}
+
+impl Error for SendError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SendError::Limited(err) => Some(err),
+            SendError::Send(_) => None,
+        }
+    }
+}
+
+impl From<RateLimited> for SendError {
+    fn from(err: RateLimited) -> Self {
+        SendError::Limited(err)
+    }
+}
+
+impl From<QuickFixError> for SendError {
+    fn from(err: QuickFixError) -> Self {
+        SendError::Send(err)
+    }
+}
+
+// =============================================================================
+// Limiter
+// =============================================================================
+
+#[derive(Debug)]
+struct Bucket {
+    rate: Rate,
+
+    /// Below zero when messages wait for the tokens they reserved
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(rate: Rate, now: Instant) -> Self {
+        Self {
+            rate,
+            tokens: f64::from(rate.burst),
+            updated: now,
+        }
+    }
+
+    /// How long until the next token, once refilled up to `now`
+    fn wait(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let rate = f64::from(self.rate.per_second);
+        self.tokens = (self.tokens + elapsed * rate).min(f64::from(self.rate.burst));
+        self.updated = self.updated.max(now);
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else if rate > 0.0 {
+            Duration::from_secs_f64((1.0 - self.tokens) / rate)
+        } else {
+            Duration::MAX
+        }
+    }
+}
+
+/// Messages counted against the limits, for one session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateStats {
+    /// Sent without waiting
+    pub immediate: u64,
+
+    /// Sent after waiting for a token
+    pub queued: u64,
+
+    /// Not sent
+    pub rejected: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    global: Option<Bucket>,
+    sessions: HashMap<String, Bucket>,
+    stats: HashMap<String, RateStats>,
+}
+
+/// Token buckets in front of send_to_target
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: RateLimits,
+    state: Mutex<State>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// No limit at all
+    pub fn unlimited() -> Self {
+        Self::new(RateLimits::new())
+    }
+
+    pub fn limits(&self) -> &RateLimits {
+        &self.limits
+    }
+
+    /// Take a token for one message of a session, or reserve the next one
+    ///
+    /// # Returns
+    /// How long to wait before sending, zero if the message can go now
+    pub fn acquire(&self, label: &str, now: Instant) -> Result<Duration, RateLimited> {
+        if !self.limits.is_limited() {
+            return Ok(Duration::ZERO);
+        }
+
+        let mut state = self.state.lock().expect("rate limiter lock poisoned");
+        let State {
+            global,
+            sessions,
+            stats,
+        } = &mut *state;
+        let session = self.limits.session_rate(label).map(|rate| {
+            sessions
+                .entry(label.to_string())
+                .or_insert_with(|| Bucket::new(rate, now))
+        });
+        let global = self
+            .limits
+            .global
+            .map(|rate| global.get_or_insert_with(|| Bucket::new(rate, now)));
+
+        let mut wait = Duration::ZERO;
+        let mut scope = RateScope::Global;
+        let mut buckets = Vec::with_capacity(2);
+        if let Some(bucket) = session {
+            let bucket_wait = bucket.wait(now);
+            if bucket_wait > wait {
+                wait = bucket_wait;
+                scope = RateScope::Session(label.to_string());
+            }
+            buckets.push(bucket);
+        }
+        if let Some(bucket) = global {
+            let bucket_wait = bucket.wait(now);
+            if bucket_wait > wait {
+                wait = bucket_wait;
+                scope = RateScope::Global;
+            }
+            buckets.push(bucket);
+        }
+
+        let stats = stats.entry(label.to_string()).or_default();
+        let allowed = match self.limits.policy {
+            RatePolicy::Queue { max_wait } => wait <= max_wait,
+            RatePolicy::Reject => wait.is_zero(),
+        };
+        if !allowed {
+            stats.rejected += 1;
+            return Err(RateLimited {
+                scope,
+                retry_after: wait,
+            });
+        }
+
+        for bucket in buckets {
+            bucket.tokens -= 1.0;
+        }
+        if wait.is_zero() {
+            stats.immediate += 1;
+        } else {
+            stats.queued += 1;
+        }
+        Ok(wait)
+    }
+
+    /// send_to_target once the limits allow, waiting on this thread with
+    /// the queue policy
+    pub fn send(&self, msg: Message, session: &SessionId) -> Result<(), SendError> {
+        if self.limits.is_limited() {
+            let wait = self.acquire(&session_label(session), Instant::now())?;
+            if !wait.is_zero() {
+                thread::sleep(wait);
+            }
+        }
+        Ok(send_to_target(msg, session)?)
+    }
+
+    /// Messages of every session counted so far, sorted by label
+    pub fn stats(&self) -> Vec<(String, RateStats)> {
+        let state = self.state.lock().expect("rate limiter lock poisoned");
+        let mut stats: Vec<_> = state
+            .stats
+            .iter()
+            .map(|(label, x)| (label.clone(), *x))
+            .collect();
+        stats.sort_by(|a, b| a.0.cmp(&b.0));
+        stats
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
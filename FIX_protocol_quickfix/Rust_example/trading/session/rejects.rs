@@ -0,0 +1,300 @@
+// =============================================================================
+// Rejects
+// =============================================================================
+// A Reject (35=3) says a message broke the session rules (a missing tag, a
+// bad format, a SendingTime out of range); a BusinessMessageReject (35=j)
+// says an application message could not be handled (unknown security, not
+// authorized, unsupported type). Both point back at the message they reject
+// by its MsgSeqNum (RefSeqNum, 45), which only means something next to the
+// message itself.
+//
+// QuickFIX's message store is not reachable through the bindings, so the
+// RejectLog journals the outbound messages itself, as the callbacks see
+// them: under the MsgSeqNum they go out with, the last JOURNAL_CAPACITY of
+// them. Each reject is decoded with what it refers to:
+//
+//   35=3  RefSeqNum (45) RefTagID (371) RefMsgType (372)
+//         SessionRejectReason (373) Text (58)
+//   35=j  RefSeqNum (45) RefMsgType (372) BusinessRejectRefID (379)
+//         BusinessRejectReason (380) Text (58)
+//
+// and the last REJECT_CAPACITY rejects are kept for display.
+// =============================================================================
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::Mutex,
+};
+
+use crate::{
+    session::{events::FixMessage, Direction},
+    time::{time_of_day, unix_now},
+};
+
+/// Outbound messages kept for correlation
+pub const JOURNAL_CAPACITY: usize = 10_000;
+
+/// Rejects kept for display
+pub const REJECT_CAPACITY: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectKind {
+    /// Reject (35=3)
+    Session,
+
+    /// BusinessMessageReject (35=j)
+    Business,
+}
+
+impl RejectKind {
+    /// MsgType of the reject
+    pub fn msg_type(self) -> &'static str {
+        match self {
+            RejectKind::Session => "3",
+            RejectKind::Business => "j",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Reject {
+    pub session: String,
+    pub kind: RejectKind,
+
+    /// Unix seconds, when it was received
+    pub time: i64,
+
+    /// RefSeqNum (45)
+    pub ref_seq_num: Option<u64>,
+
+    /// RefMsgType (372)
+    pub ref_msg_type: Option<String>,
+
+    /// RefTagID (371), session rejects only
+    pub ref_tag_id: Option<String>,
+
+    /// BusinessRejectRefID (379), business rejects only: the ClOrdID,
+    /// MDReqID, ... of the rejected message
+    pub ref_id: Option<String>,
+
+    /// SessionRejectReason (373) or BusinessRejectReason (380)
+    pub reason: Option<String>,
+
+    /// Text (58)
+    pub text: Option<String>,
+
+    /// The rejected message, if it is still in the journal
+    pub original: Option<FixMessage>,
+}
+
+impl Reject {
+    /// Decode a received 35=3 or 35=j; None for anything else
+    pub fn decode(msg: &FixMessage) -> Option<Self> {
+        if msg.direction != Direction::Inbound {
+            return None;
+        }
+        let kind = match msg.msg_type() {
+            "3" => RejectKind::Session,
+            "j" => RejectKind::Business,
+            _ => return None,
+        };
+        let field = |tag| msg.get(tag).map(str::to_string);
+
+        Some(Self {
+            session: msg.session.clone(),
+            kind,
+            time: unix_now(),
+            ref_seq_num: msg.get(45).and_then(|x| x.parse().ok()),
+            ref_msg_type: field(372),
+            ref_tag_id: field(371),
+            ref_id: field(379),
+            reason: match kind {
+                RejectKind::Session => field(373),
+                RejectKind::Business => field(380),
+            },
+            text: field(58),
+            original: None,
+        })
+    }
+
+    /// Name of the reason code, from the FIX specification
+    pub fn reason_name(&self) -> Option<&'static str> {
+        let code = self.reason.as_deref()?;
+        let name = match self.kind {
+            RejectKind::Session => match code {
+                "0" => "Invalid tag number",
+                "1" => "Required tag missing",
+                "2" => "Tag not defined for this message type",
+                "3" => "Undefined tag",
+                "4" => "Tag specified without a value",
+                "5" => "Value is incorrect (out of range) for this tag",
+                "6" => "Incorrect data format for value",
+                "7" => "Decryption problem",
+                "8" => "Signature problem",
+                "9" => "CompID problem",
+                "10" => "SendingTime accuracy problem",
+                "11" => "Invalid MsgType",
+                "12" => "XML validation error",
+                "13" => "Tag appears more than once",
+                "14" => "Tag specified out of required order",
+                "15" => "Repeating group fields out of order",
+                "16" => "Incorrect NumInGroup count for repeating group",
+                "17" => "Non \"data\" value includes field delimiter",
+                "18" => "Invalid / unsupported application version",
+                "99" => "Other",
+                _ => return None,
+            },
+            RejectKind::Business => match code {
+                "0" => "Other",
+                "1" => "Unknown ID",
+                "2" => "Unknown security",
+                "3" => "Unsupported message type",
+                "4" => "Application not available",
+                "5" => "Conditionally required field missing",
+                "6" => "Not authorized",
+                "7" => "DeliverTo firm not available at this time",
+                "8" => "Throttle limit exceeded",
+                "9" => "Throttle limit exceeded, session will be disconnected",
+                "10" => "Throttled messages rejected on request",
+                "18" => "Invalid price increment",
+                _ => return None,
+            },
+        };
+        Some(name)
+    }
+
+    /// MsgType of the rejected message
+    pub fn rejected_msg_type(&self) -> Option<&str> {
+        self.ref_msg_type
+            .as_deref()
+            .or_else(|| self.original.as_ref().map(FixMessage::msg_type))
+    }
+
+    /// ClOrdID (11) of the rejected message, or the BusinessRejectRefID
+    /// when it is not in the journal
+    pub fn cl_ord_id(&self) -> Option<&str> {
+        self.original
+            .as_ref()
+            .and_then(|x| x.get(11))
+            .or(self.ref_id.as_deref())
+    }
+
+    /// One line: time, session, what was rejected and why
+    pub fn to_text(&self) -> String {
+        let mut line = format!(
+            "{} {} 35={} ref={}",
+            time_of_day(self.time),
+            self.session,
+            self.kind.msg_type(),
+            self.ref_seq_num.map_or("?".to_string(), |x| x.to_string()),
+        );
+        if let Some(msg_type) = self.rejected_msg_type() {
+            line += &format!(" 35={msg_type}");
+        }
+        if let Some(cl_ord_id) = self.cl_ord_id() {
+            line += &format!(" 11={cl_ord_id}");
+        }
+        if let Some(tag) = &self.ref_tag_id {
+            line += &format!(" tag={tag}");
+        }
+        if let Some(reason) = &self.reason {
+            line += &format!(" reason={reason}");
+            if let Some(name) = self.reason_name() {
+                line += &format!(" ({name})");
+            }
+        }
+        if let Some(text) = &self.text {
+            line += &format!(" \"{text}\"");
+        }
+        line
+    }
+}
+
+impl fmt::Display for Reject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_text())
+    }
+}
+
+// =============================================================================
+// Reject Log
+// =============================================================================
+
+#[derive(Debug, Default)]
+struct Journal {
+    /// By session label and MsgSeqNum
+    messages: HashMap<(String, u64), FixMessage>,
+
+    /// Insertion order, oldest first, to evict
+    order: VecDeque<(String, u64)>,
+}
+
+#[derive(Debug, Default)]
+pub struct RejectLog {
+    journal: Mutex<Journal>,
+    rejects: Mutex<VecDeque<Reject>>,
+}
+
+impl RejectLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Journal an outbound message, or decode and keep a reject
+    ///
+    /// # Returns
+    /// The reject, with the message it refers to when the journal still
+    /// has it
+    pub fn on_message(&self, msg: &FixMessage) -> Option<Reject> {
+        if msg.direction == Direction::Outbound {
+            self.journal(msg);
+            return None;
+        }
+
+        let mut reject = Reject::decode(msg)?;
+        if let Some(seq_num) = reject.ref_seq_num {
+            let journal = self.journal.lock().expect("reject log lock poisoned");
+            reject.original = journal
+                .messages
+                .get(&(reject.session.clone(), seq_num))
+                .cloned();
+        }
+
+        let mut rejects = self.rejects.lock().expect("reject log lock poisoned");
+        if rejects.len() == REJECT_CAPACITY {
+            rejects.pop_front();
+        }
+        rejects.push_back(reject.clone());
+        Some(reject)
+    }
+
+    fn journal(&self, msg: &FixMessage) {
+        // Heartbeats are not worth the room
+        if msg.msg_type() == "0" {
+            return;
+        }
+        let Ok(seq_num) = msg.seq_num().parse::<u64>() else {
+            return;
+        };
+
+        let key = (msg.session.clone(), seq_num);
+        let mut journal = self.journal.lock().expect("reject log lock poisoned");
+        // A sequence reset reuses numbers: the newest message wins
+        if journal.messages.insert(key.clone(), msg.clone()).is_none() {
+            journal.order.push_back(key);
+        }
+        while journal.order.len() > JOURNAL_CAPACITY {
+            if let Some(oldest) = journal.order.pop_front() {
+                journal.messages.remove(&oldest);
+            }
+        }
+    }
+
+    /// The last `limit` rejects, oldest first
+    pub fn recent(&self, limit: usize) -> Vec<Reject> {
+        let rejects = self.rejects.lock().expect("reject log lock poisoned");
+        let skip = rejects.len().saturating_sub(limit);
+        rejects.iter().skip(skip).cloned().collect()
+    }
+}
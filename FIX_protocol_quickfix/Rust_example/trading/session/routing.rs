@@ -0,0 +1,369 @@
+// =============================================================================
+// FIX-to-FIX Routing
+// =============================================================================
+// What a hub needs to pass application messages between the sessions it
+// accepts and those it initiates, as a minimal FIX router:
+//
+//   client --35=D 11=A1--> hub --35=D 11=HUB-20240115-1 115=CLIENT--> venue
+//   client <--35=8 11=A1--  hub <--35=8 11=HUB-20240115-1-------------- venue
+//
+// A routes file says where messages go, one rule per line, the first that
+// matches wins (`#` for comments):
+//
+//   NAME = EXPRESSION => TARGET SESSION LABEL
+//
+//   big_aapl  = symbol == "AAPL" && qty >= 10000 => FIX.4.4:HUB->VENUE_B
+//   client_a  = msg.49 == "CLIENT_A" => FIX.4.4:HUB->VENUE_A
+//   fallback  = msg.35 in ["D", "F", "G"] => FIX.4.4:HUB->VENUE_A
+//
+// The expressions are those of trading::expr: by SenderCompID (msg.49, or
+// the session label), symbol, or any tag.
+//
+// Going out, the ClOrdID (11) of a message is replaced by one of the hub's
+// own, so that two clients using the same ClOrdIDs never collide at the
+// venue, and the OrigClOrdID (41) of a cancel or replace by the hub's
+// ClOrdID of the order it refers to. OnBehalfOfCompID (115) names the
+// client. CompIDs of the header are those of the target session: QuickFIX
+// fills them in.
+//
+// Coming back, a message whose ClOrdID is one of the hub's, from the session
+// it was sent to, goes to the session the order came from with the client's
+// ClOrdIDs put back, OnBehalfOfCompID naming the venue and without the
+// venue's DeliverToCompID (128). Other messages are routed by the rules like
+// any (market data from a venue to clients, say).
+//
+// The ClOrdIDs are mapped in memory: after a restart, replies to orders
+// routed before it have nowhere to go.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use quickfix::{FieldMap, Message, QuickFixError};
+
+use crate::{
+    expr::{ExprError, Rule},
+    oms::ids::{ClOrdIdGenerator, DatedIds},
+    session::{dictionary::Dictionary, events::FixMessage},
+};
+
+/// Prefix of the hub's ClOrdIDs when none is given
+pub const DEFAULT_ID_PREFIX: &str = "HUB";
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RoutingError {
+    Io(PathBuf, io::Error),
+
+    /// A line without `=> TARGET`, or with a target that is not a session
+    /// label: line number and why
+    Syntax(usize, String),
+
+    /// The condition of a line: line number and error
+    Expr(usize, ExprError),
+
+    /// Two routes of a file with the same name
+    DuplicateRoute(String),
+}
+
+impl fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoutingError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            RoutingError::Syntax(line, message) => write!(f, "line {line}: {message}"),
+            RoutingError::Expr(line, err) => write!(f, "line {line}: {err}"),
+            RoutingError::DuplicateRoute(name) => write!(f, "route {name} defined twice"),
+        }
+    }
+}
+
+impl Error for RoutingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RoutingError::Io(_, err) => Some(err),
+            RoutingError::Expr(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// =============================================================================
+// Routes
+// =============================================================================
+
+/// Where the messages a condition matches go
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub rule: Rule,
+
+    /// Label of the target session, `FIX.4.4:HUB->VENUE`
+    pub target: String,
+}
+
+impl Route {
+    /// Parse `NAME = EXPRESSION => TARGET`
+    pub fn parse_with(line: &str, dictionary: &Dictionary) -> Result<Self, RoutingError> {
+        let (rule, target) = line.rsplit_once("=>").ok_or_else(|| {
+            RoutingError::Syntax(0, "expected NAME = EXPRESSION => TARGET".into())
+        })?;
+        let target = target.trim();
+        if !is_session_label(target) {
+            return Err(RoutingError::Syntax(
+                0,
+                format!("{target} is not a session label (BEGINSTRING:SENDER->TARGET)"),
+            ));
+        }
+        Ok(Self {
+            rule: Rule::parse_with(rule, dictionary).map_err(|err| RoutingError::Expr(0, err))?,
+            target: target.to_string(),
+        })
+    }
+
+    pub fn parse(line: &str) -> Result<Self, RoutingError> {
+        Self::parse_with(line, &Dictionary::standard())
+    }
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} => {}", self.rule, self.target)
+    }
+}
+
+fn is_session_label(text: &str) -> bool {
+    text.split_once(':')
+        .and_then(|(begin_string, comp_ids)| {
+            let (sender, target) = comp_ids.split_once("->")?;
+            Some(!begin_string.is_empty() && !sender.is_empty() && !target.is_empty())
+        })
+        .unwrap_or(false)
+}
+
+/// Parse the routes of a file's text: one per line, blank lines and lines
+/// starting with `#` skipped
+pub fn parse_routes(text: &str, dictionary: &Dictionary) -> Result<Vec<Route>, RoutingError> {
+    let mut routes: Vec<Route> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let route = Route::parse_with(line, dictionary).map_err(|err| match err {
+            RoutingError::Syntax(_, message) => RoutingError::Syntax(index + 1, message),
+            RoutingError::Expr(_, err) => RoutingError::Expr(index + 1, err),
+            err => err,
+        })?;
+        if routes.iter().any(|x| x.rule.name == route.rule.name) {
+            return Err(RoutingError::DuplicateRoute(route.rule.name));
+        }
+        routes.push(route);
+    }
+    Ok(routes)
+}
+
+/// Read and parse a routes file
+pub fn load_routes(path: &Path, dictionary: &Dictionary) -> Result<Vec<Route>, RoutingError> {
+    let text = fs::read_to_string(path).map_err(|err| RoutingError::Io(path.to_path_buf(), err))?;
+    parse_routes(&text, dictionary)
+}
+
+// =============================================================================
+// Router
+// =============================================================================
+
+/// What becomes of an inbound application message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    /// Send it on to `target`, rewritten
+    Forward(Forward),
+
+    /// No route matches it
+    Unroutable,
+}
+
+/// A message on its way to another session
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forward {
+    /// Label of the session to send it to
+    pub target: String,
+
+    /// Name of the route that matched; None for a reply going back to the
+    /// session its order came from
+    pub route: Option<String>,
+
+    /// Fields set on the way, header fields included
+    pub set: Vec<(i32, String)>,
+
+    /// Header fields removed on the way
+    pub removed: Vec<i32>,
+}
+
+/// Header fields a Forward may set or remove
+const HEADER_TAGS: [i32; 2] = [115, 128];
+
+impl Forward {
+    /// A copy of `msg` rewritten, to hand to send_to_target with the target
+    /// session
+    pub fn apply(&self, msg: &Message) -> Result<Message, QuickFixError> {
+        let mut forwarded = Message::try_from_text(&msg.to_fix_string()?)?;
+        for tag in &self.removed {
+            forwarded.with_header_mut(|h| h.remove_field(*tag))?;
+        }
+        for (tag, value) in &self.set {
+            if HEADER_TAGS.contains(tag) {
+                forwarded.with_header_mut(|h| h.set_field(*tag, value.as_str()))?;
+            } else {
+                forwarded.set_field(*tag, value.as_str())?;
+            }
+        }
+        Ok(forwarded)
+    }
+}
+
+/// An order routed, by the hub's ClOrdID
+#[derive(Debug, Clone)]
+struct Routed {
+    /// Label of the session it came from, and its ClOrdID there
+    origin: String,
+    cl_ord_id: String,
+
+    /// Label of the session it went to
+    target: String,
+}
+
+#[derive(Default)]
+struct Maps {
+    /// Hub ClOrdID -> the order routed
+    routed: HashMap<String, Routed>,
+
+    /// (origin session, client ClOrdID) -> hub ClOrdID
+    hub_ids: HashMap<(String, String), String>,
+}
+
+/// Routes messages between sessions, rewriting their ClOrdIDs
+pub struct Router {
+    routes: Vec<Route>,
+    ids: Box<dyn ClOrdIdGenerator>,
+    maps: Mutex<Maps>,
+}
+
+impl Router {
+    /// ClOrdIDs are dated (`HUB-YYYYMMDD-N`) so that a hub restarted the
+    /// same day does not reuse those it sent before it
+    pub fn new(routes: Vec<Route>) -> Self {
+        Self::with_id_generator(routes, Box::new(DatedIds::new(DEFAULT_ID_PREFIX)))
+    }
+
+    pub fn with_id_generator(routes: Vec<Route>, ids: Box<dyn ClOrdIdGenerator>) -> Self {
+        Self {
+            routes,
+            ids,
+            maps: Mutex::new(Maps::default()),
+        }
+    }
+
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// The sessions routes send to, each once, in the order of the routes
+    pub fn targets(&self) -> Vec<&str> {
+        let mut targets: Vec<&str> = Vec::new();
+        for route in &self.routes {
+            if !targets.contains(&route.target.as_str()) {
+                targets.push(&route.target);
+            }
+        }
+        targets
+    }
+
+    /// Orders routed whose replies are still expected
+    pub fn routed_count(&self) -> usize {
+        self.lock().routed.len()
+    }
+
+    /// Where an inbound application message goes, and how it is rewritten
+    ///
+    /// A reply to an order routed goes back where the order came from;
+    /// anything else goes where the first matching route says, the routes
+    /// not sending a message back to the session it came from.
+    pub fn decide(&self, msg: &FixMessage) -> Decision {
+        if let Some(forward) = self.reply(msg) {
+            return Decision::Forward(forward);
+        }
+        let Some(route) = self
+            .routes
+            .iter()
+            .find(|x| x.target != msg.session && x.rule.expr.matches(msg))
+        else {
+            return Decision::Unroutable;
+        };
+
+        let mut maps = self.lock();
+        let mut set = Vec::new();
+        if let Some(cl_ord_id) = msg.get(11) {
+            let hub_id = self.ids.next_id();
+            maps.routed.insert(
+                hub_id.clone(),
+                Routed {
+                    origin: msg.session.clone(),
+                    cl_ord_id: cl_ord_id.to_string(),
+                    target: route.target.clone(),
+                },
+            );
+            maps.hub_ids
+                .insert((msg.session.clone(), cl_ord_id.to_string()), hub_id.clone());
+            set.push((11, hub_id));
+        }
+        if let Some(orig) = msg.get(41) {
+            if let Some(hub_id) = maps.hub_ids.get(&(msg.session.clone(), orig.to_string())) {
+                set.push((41, hub_id.clone()));
+            }
+        }
+        if let Some(sender) = msg.get(49) {
+            set.push((115, sender.to_string()));
+        }
+        Decision::Forward(Forward {
+            target: route.target.clone(),
+            route: Some(route.rule.name.clone()),
+            set,
+            removed: Vec::new(),
+        })
+    }
+
+    /// A message about an order the hub routed, from where it was routed,
+    /// on its way back
+    fn reply(&self, msg: &FixMessage) -> Option<Forward> {
+        let maps = self.lock();
+        let routed = maps
+            .routed
+            .get(msg.get(11)?)
+            .filter(|x| x.target == msg.session)?;
+        let mut set = vec![(11, routed.cl_ord_id.clone())];
+        if let Some(orig) = msg.get(41).and_then(|x| maps.routed.get(x)) {
+            set.push((41, orig.cl_ord_id.clone()));
+        }
+        if let Some(sender) = msg.get(49) {
+            set.push((115, sender.to_string()));
+        }
+        Some(Forward {
+            target: routed.origin.clone(),
+            route: None,
+            set,
+            removed: vec![128],
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Maps> {
+        self.maps.lock().expect("router lock poisoned")
+    }
+}
@@ -0,0 +1,75 @@
+// =============================================================================
+// Async Runtime Helpers (tokio)
+// =============================================================================
+// The examples run on a tokio runtime: QuickFIX keeps its own engine threads,
+// everything else (shell, business logic) runs as async tasks. This module
+// holds the two pieces every main loop needs:
+//
+// - stdin as a stream of lines, so a prompt can wait on input and on other
+//   events at the same time (tokio::select!)
+// - the shutdown signal (CTRL-C, or SIGTERM on Unix as sent by `docker stop`
+//   and systemd), which triggers the same graceful shutdown as typing 'q'
+// =============================================================================
+
+use std::{
+    future,
+    io::{stdin, BufRead},
+    thread,
+};
+
+use tokio::sync::mpsc;
+
+/// Lines typed on stdin, without the trailing newline
+///
+/// Read by a dedicated thread rather than tokio::io::stdin: a pending tokio
+/// stdin read keeps the runtime from shutting down until Enter is pressed,
+/// while a plain thread does not keep the process alive. The channel closes
+/// on EOF (CTRL-D).
+pub fn stdin_lines() -> mpsc::UnboundedReceiver<String> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    thread::Builder::new()
+        .name("stdin".to_string())
+        .spawn(move || {
+            for line in stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        })
+        .expect("cannot spawn stdin thread");
+    receiver
+}
+
+/// Resolve when the process is asked to stop (CTRL-C, or SIGTERM on Unix)
+///
+/// Create it once and pin it outside the main loop: the handlers are
+/// installed on first poll and a signal received meanwhile is not lost.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            // No handler available: only the other triggers can stop us
+            future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
@@ -0,0 +1,507 @@
+// =============================================================================
+// Session Schedule
+// =============================================================================
+// When each session is meant to be connected, read from the same settings as
+// QuickFIX reads them, so that a process can start its connection handler
+// when a window opens and stop it when the last one closes, rather than
+// leaving QuickFIX to knock on a closed door all weekend:
+//
+//   StartTime / EndTime   daily window, HH:MM:SS; an EndTime before the
+//                         StartTime runs over midnight, an EndTime equal to
+//                         it means open around the clock
+//   StartDay / EndDay     one weekly window instead, from StartDay StartTime
+//                         to EndDay EndTime (Sunday, Sun or Su)
+//   Weekdays              the daily window only on these days (Mon,Tue,...),
+//                         as QuickFIX/J reads it
+//   NonStopSession=Y      never closes
+//
+// plus two keys QuickFIX ignores, for venues that keep their sequence numbers
+// through the daily breaks and reset them once a week:
+//
+//   WeeklyResetDay / WeeklyResetTime   the weekly reset point (the time
+//                                      defaults to StartTime)
+//
+// A weekly session resets at its StartDay StartTime unless WeeklyResetDay
+// says otherwise; a daily one only with WeeklyResetDay (QuickFIX resets it
+// every day by itself). A reset empties the session's file store while the
+// handler is stopped: both sides start again from MsgSeqNum 1.
+//
+// Times are UTC: UseLocalTime and TimeZone are refused rather than misread.
+// QuickFIX does not list the sessions it found in the file: read_schedules
+// takes them from the [SESSION] blocks of a settings::ConfigFile.
+// =============================================================================
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    session::{handover::store_prefix, session_label, settings::ConfigFile},
+    time::{time_of_day, Date},
+};
+
+const DAY: i64 = 86_400;
+const WEEK: i64 = 7 * DAY;
+
+/// Weekday names from Sunday, as the settings and the status spell them
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// Extensions of the QuickFIX file store, emptied by a reset
+const STORE_FILES: [&str; 4] = ["seqnums", "body", "header", "session"];
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ScheduleError {
+    /// A setting the schedule needs is not there
+    Missing(&'static str),
+
+    /// A setting that cannot be read: key and value
+    Invalid(&'static str, String),
+
+    /// UseLocalTime or TimeZone: only UTC schedules are read
+    LocalTime,
+
+    /// The schedule of a session is wrong: its label and why
+    Session(String, Box<ScheduleError>),
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::Missing(key) => write!(f, "{key} missing"),
+            ScheduleError::Invalid(key, value) => write!(f, "invalid {key}: {value:?}"),
+            ScheduleError::LocalTime => {
+                write!(
+                    f,
+                    "UseLocalTime and TimeZone are not supported, times are UTC"
+                )
+            }
+            ScheduleError::Session(session, err) => write!(f, "{session}: {err}"),
+        }
+    }
+}
+
+impl Error for ScheduleError {}
+
+// =============================================================================
+// Schedule
+// =============================================================================
+
+/// Open windows and weekly reset point of one session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionSchedule {
+    /// Start and length of each window, in seconds from Sunday 00:00 UTC;
+    /// none when the session never closes
+    windows: Vec<(i64, i64)>,
+
+    /// Weekly reset point, in seconds from Sunday 00:00 UTC
+    reset: Option<i64>,
+}
+
+impl SessionSchedule {
+    /// Open around the clock, never reset
+    pub fn non_stop() -> Self {
+        Self {
+            windows: Vec::new(),
+            reset: None,
+        }
+    }
+
+    /// Read a schedule from the settings of a session
+    ///
+    /// # Arguments
+    /// * `get` - Value of a setting, [DEFAULT] included
+    pub fn from_settings<F>(get: F) -> Result<Self, ScheduleError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if get("UseLocalTime").is_some_and(|x| x == "Y") || get("TimeZone").is_some() {
+            return Err(ScheduleError::LocalTime);
+        }
+        let time = |key: &'static str| {
+            get(key)
+                .map(|x| parse_time(&x).ok_or(ScheduleError::Invalid(key, x)))
+                .transpose()
+        };
+        let day = |key: &'static str| {
+            get(key)
+                .map(|x| parse_weekday(&x).ok_or(ScheduleError::Invalid(key, x)))
+                .transpose()
+        };
+
+        let non_stop = get("NonStopSession").is_some_and(|x| x == "Y");
+        let start = time("StartTime")?;
+        let mut schedule = if non_stop {
+            Self::non_stop()
+        } else {
+            let start = start.ok_or(ScheduleError::Missing("StartTime"))?;
+            let end = time("EndTime")?.ok_or(ScheduleError::Missing("EndTime"))?;
+            match (day("StartDay")?, day("EndDay")?, get("Weekdays")) {
+                (Some(_), None, _) => return Err(ScheduleError::Missing("EndDay")),
+                (None, Some(_), _) => return Err(ScheduleError::Missing("StartDay")),
+                (Some(_), Some(_), Some(_)) => {
+                    return Err(ScheduleError::Invalid(
+                        "Weekdays",
+                        "with StartDay".to_string(),
+                    ))
+                }
+                (Some(start_day), Some(end_day), None) => {
+                    Self::weekly(start_day * DAY + start, end_day * DAY + end)
+                }
+                (None, None, Some(weekdays)) => {
+                    let days = weekdays
+                        .split(',')
+                        .map(|x| parse_weekday(x.trim()))
+                        .collect::<Option<Vec<_>>>()
+                        .filter(|x| !x.is_empty())
+                        .ok_or(ScheduleError::Invalid("Weekdays", weekdays))?;
+                    Self::daily(start, end, &days)
+                }
+                (None, None, None) => Self::daily(start, end, &[0, 1, 2, 3, 4, 5, 6]),
+            }
+        };
+
+        if let Some(reset_day) = day("WeeklyResetDay")? {
+            let reset_time = time("WeeklyResetTime")?.or(start).unwrap_or(0);
+            schedule.reset = Some(reset_day * DAY + reset_time);
+        }
+        Ok(schedule)
+    }
+
+    /// From `start` on `days` (0 = Sunday) to `end`, the next day if earlier
+    fn daily(start: i64, end: i64, days: &[i64]) -> Self {
+        let length = (end - start).rem_euclid(DAY);
+        if length == 0 && days.len() == 7 {
+            return Self::non_stop();
+        }
+        let length = if length == 0 { DAY } else { length };
+        let mut windows: Vec<_> = days.iter().map(|day| (day * DAY + start, length)).collect();
+        windows.sort_unstable();
+        windows.dedup();
+        Self {
+            windows,
+            reset: None,
+        }
+    }
+
+    /// From `start` to `end`, both in seconds from Sunday 00:00, reset at
+    /// the start
+    fn weekly(start: i64, end: i64) -> Self {
+        let length = (end - start).rem_euclid(WEEK);
+        Self {
+            windows: if length == 0 {
+                Vec::new()
+            } else {
+                vec![(start, length)]
+            },
+            reset: Some(start),
+        }
+    }
+
+    /// Whether the session never closes
+    pub fn is_non_stop(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// Whether the session is in one of its windows at a Unix time
+    pub fn is_open(&self, now: i64) -> bool {
+        self.is_non_stop()
+            || self
+                .windows
+                .iter()
+                .any(|&(start, length)| (second_of_week(now) - start).rem_euclid(WEEK) < length)
+    }
+
+    /// When the session opens next: `now` if open already, none if it
+    /// never closes
+    pub fn next_open(&self, now: i64) -> Option<i64> {
+        if self.is_non_stop() {
+            return None;
+        }
+        if self.is_open(now) {
+            return Some(now);
+        }
+        let sow = second_of_week(now);
+        self.windows
+            .iter()
+            .map(|&(start, _)| now + (start - sow).rem_euclid(WEEK))
+            .min()
+    }
+
+    /// When the session closes next, from the window it is in or the next
+    /// one; none if it never closes
+    pub fn next_close(&self, now: i64) -> Option<i64> {
+        let open = self.next_open(now)?;
+        let sow = second_of_week(open);
+        self.windows
+            .iter()
+            .map(|&(start, length)| (start, length, (sow - start).rem_euclid(WEEK)))
+            .filter(|&(_, length, elapsed)| elapsed < length)
+            .map(|(_, length, elapsed)| open + length - elapsed)
+            .max()
+    }
+
+    /// The first reset point after `now`, if the session has one
+    pub fn next_reset(&self, now: i64) -> Option<i64> {
+        let reset = self.reset?;
+        let ahead = (reset - second_of_week(now)).rem_euclid(WEEK);
+        Some(now + if ahead == 0 { WEEK } else { ahead })
+    }
+
+    /// Whether a reset point falls in `(from, to]`
+    pub fn resets_between(&self, from: i64, to: i64) -> bool {
+        self.next_reset(from).is_some_and(|x| x <= to)
+    }
+
+    /// Open or closed at `now`, with the next opening, closing and reset
+    pub fn status(&self, now: i64) -> ScheduleStatus {
+        let open = self.is_open(now);
+        ScheduleStatus {
+            open,
+            next_open: if open { None } else { self.next_open(now) },
+            next_close: self.next_close(now),
+            next_reset: self.next_reset(now),
+        }
+    }
+}
+
+/// Where a session stands in its schedule, for a status line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleStatus {
+    pub open: bool,
+
+    /// Unix time of the next opening, if closed
+    pub next_open: Option<i64>,
+
+    /// Unix time of the next closing, none if it never closes
+    pub next_close: Option<i64>,
+
+    /// Unix time of the next sequence reset
+    pub next_reset: Option<i64>,
+}
+
+impl fmt::Display for ScheduleStatus {
+    /// `open, closes Fri 2026-10-16 21:00:00 UTC, resets Sun 2026-10-18 ...`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.open, self.next_open, self.next_close) {
+            (_, _, None) => write!(f, "open around the clock")?,
+            (true, _, Some(close)) => write!(f, "open, closes {}", format_instant(close))?,
+            (false, Some(open), Some(close)) => write!(
+                f,
+                "closed, opens {}, closes {}",
+                format_instant(open),
+                format_instant(close)
+            )?,
+            (false, None, Some(_)) => write!(f, "closed")?,
+        }
+        if let Some(reset) = self.next_reset {
+            write!(f, ", resets {}", format_instant(reset))?;
+        }
+        Ok(())
+    }
+}
+
+/// `Sun 2026-10-18 22:00:00 UTC`
+fn format_instant(unix: i64) -> String {
+    let weekday = (unix + 4 * DAY).div_euclid(DAY).rem_euclid(7) as usize;
+    format!(
+        "{} {} {} UTC",
+        &WEEKDAYS[weekday][..3],
+        Date::from_unix(unix).to_iso(),
+        time_of_day(unix)
+    )
+}
+
+/// Seconds from Sunday 00:00 UTC; the epoch was a Thursday
+fn second_of_week(unix: i64) -> i64 {
+    (unix + 4 * DAY).rem_euclid(WEEK)
+}
+
+/// `HH:MM:SS` in seconds from midnight
+pub(super) fn parse_time(value: &str) -> Option<i64> {
+    let parts: Vec<i64> = value
+        .split(':')
+        .map(|x| x.parse().ok())
+        .collect::<Option<_>>()?;
+    match parts.as_slice() {
+        [h, m, s] if value.len() == 8 && *h < 24 && *m < 60 && *s < 60 => {
+            Some(h * 3_600 + m * 60 + s)
+        }
+        _ => None,
+    }
+}
+
+/// Day from Sunday (0), by its name or its first two letters or more
+fn parse_weekday(value: &str) -> Option<i64> {
+    let value = value.to_ascii_lowercase();
+    if value.len() < 2 {
+        return None;
+    }
+    WEEKDAYS
+        .iter()
+        .position(|x| x.to_ascii_lowercase().starts_with(&value))
+        .map(|x| x as i64)
+}
+
+// =============================================================================
+// Sessions of a Configuration File
+// =============================================================================
+
+/// A session of the configuration file and its schedule
+#[derive(Debug, Clone)]
+pub struct ScheduledSession {
+    /// Session label, for logs and the status
+    pub session: String,
+
+    pub schedule: SessionSchedule,
+
+    /// FileStorePath, where a reset empties the store; none for a session
+    /// without a file store
+    pub store_dir: Option<PathBuf>,
+
+    /// Name of its files in the store
+    pub store_prefix: String,
+}
+
+/// The schedule of every [SESSION] block of a configuration file
+pub fn read_schedules(config: &ConfigFile) -> Result<Vec<ScheduledSession>, ScheduleError> {
+    config
+        .sessions()
+        .into_iter()
+        .map(|block| {
+            let session_id = block
+                .session_id()
+                .map_err(|err| ScheduleError::Invalid("[SESSION]", format!("{err:?}")))?;
+            let session = session_label(&session_id);
+            let schedule = SessionSchedule::from_settings(|key| block.get(key).map(str::to_string))
+                .map_err(|err| ScheduleError::Session(session.clone(), Box::new(err)))?;
+            Ok(ScheduledSession {
+                session,
+                schedule,
+                store_dir: block.get("FileStorePath").map(PathBuf::from),
+                store_prefix: store_prefix(&session_id),
+            })
+        })
+        .collect()
+}
+
+/// Empty a session's file store, so that it starts again from MsgSeqNum 1
+/// both ways
+///
+/// Only with the connection handler stopped: a running session keeps its
+/// sequence numbers in memory and writes them back.
+pub fn reset_store(store_dir: &Path, store_prefix: &str) -> io::Result<()> {
+    for extension in STORE_FILES {
+        match fs::remove_file(store_dir.join(format!("{store_prefix}.{extension}"))) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// =============================================================================
+// Scheduler
+// =============================================================================
+
+/// What the connection handler should do, from a tick of the scheduler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleAction {
+    /// A window opened while every other session was closed
+    Start,
+
+    /// The last open window closed
+    Stop,
+
+    /// A session passed its reset point: stop the handler, reset_store,
+    /// then start it again if it was running
+    Reset {
+        session: String,
+        store_dir: PathBuf,
+        store_prefix: String,
+    },
+}
+
+/// Turns the schedules of the sessions into actions on their handler
+///
+/// A single handler serves every session, so it runs while any of them is
+/// open. Only changes are reported: a handler started or stopped by hand
+/// stays so until the next window opens or closes.
+#[derive(Debug, Clone)]
+pub struct SessionScheduler {
+    sessions: Vec<ScheduledSession>,
+
+    /// Whether any session was open at the last tick
+    open: Option<bool>,
+
+    /// Unix time of the last tick
+    last_tick: Option<i64>,
+}
+
+impl SessionScheduler {
+    pub fn new(sessions: Vec<ScheduledSession>) -> Self {
+        Self {
+            sessions,
+            open: None,
+            last_tick: None,
+        }
+    }
+
+    pub fn sessions(&self) -> &[ScheduledSession] {
+        &self.sessions
+    }
+
+    /// Whether any session is open at a Unix time (also with no session)
+    pub fn is_open(&self, now: i64) -> bool {
+        self.sessions.is_empty() || self.sessions.iter().any(|x| x.schedule.is_open(now))
+    }
+
+    /// Actions due at `now`: the resets passed since the last tick, then
+    /// Start or Stop if the handler should change state
+    ///
+    /// The first tick reports the state of the moment and no reset:
+    /// QuickFIX resets a stale store at logon by itself.
+    pub fn tick(&mut self, now: i64) -> Vec<ScheduleAction> {
+        let mut actions = Vec::new();
+        if let Some(last) = self.last_tick {
+            for session in &self.sessions {
+                if let (Some(store_dir), true) = (
+                    &session.store_dir,
+                    session.schedule.resets_between(last, now),
+                ) {
+                    actions.push(ScheduleAction::Reset {
+                        session: session.session.clone(),
+                        store_dir: store_dir.clone(),
+                        store_prefix: session.store_prefix.clone(),
+                    });
+                }
+            }
+        }
+        self.last_tick = Some(now);
+
+        let open = self.is_open(now);
+        if self.open != Some(open) {
+            actions.push(if open {
+                ScheduleAction::Start
+            } else {
+                ScheduleAction::Stop
+            });
+        }
+        self.open = Some(open);
+        actions
+    }
+}
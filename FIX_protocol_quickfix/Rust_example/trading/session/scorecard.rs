@@ -0,0 +1,635 @@
+// =============================================================================
+// Counterparty Scorecards
+// =============================================================================
+// How did each counterparty session behave, day after day? Connectivity and
+// broker review meetings ask the same questions every time:
+//
+//   uptime        time logged on, out of the time the scorecard was running
+//   rejects       orders (D, F, G) rejected: ExecType 8, 35=9 and 35=j;
+//                 session-level Rejects (35=3) apart
+//   ack latency   order sent -> first ExecutionReport or OrderCancelReject
+//                 with its ClOrdID
+//   resends       ResendRequests each way, gap fills and PossDup messages
+//                 received
+//   fill quality  share of the quantity ordered that filled, and the price
+//                 improvement of limit orders, in bps of the limit
+//
+// A Scorecard is fed the events of every session (FixEvent), from the
+// callbacks so that times are taken on the engine threads, and keeps a
+// DayScore per session and UTC day. At midnight the day is closed and a new
+// one starts with the sessions known.
+//
+// With a state store the days are kept in the `scorecards` namespace, key
+// `YYYYMMDD/<session label>`. They are flushed (every minute in fix_repl,
+// and on the way out) rather than written through, since every message
+// changes them. The stored scores of today are picked up again on start.
+//
+// `history` returns the days of a window, today included; `trends` merges
+// them per session and sets the last day against the days before it.
+// =============================================================================
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    json::{json_escape, JsonValue},
+    session::{
+        events::{FixEvent, FixMessage},
+        Direction,
+    },
+    store::{StateStore, StoreError},
+    time::{unix_now, Date},
+};
+
+/// Namespace of the days kept in a state store
+pub const SCORECARDS_NAMESPACE: &str = "scorecards";
+
+/// Orders waiting for their answer, at most; past it, the oldest are
+/// forgotten
+const MAX_PENDING: usize = 10_000;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+// =============================================================================
+// Scores of a Day
+// =============================================================================
+
+/// One session over one UTC day, or several days merged
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayScore {
+    pub session: String,
+
+    /// The day; the last of them once merged
+    pub date: Date,
+
+    /// Seconds the scorecard was running, and the session logged on
+    pub observed_secs: u64,
+    pub uptime_secs: u64,
+    pub logons: u64,
+    pub logouts: u64,
+
+    /// D, F and G sent, and the quantity of the D
+    pub orders: u64,
+    pub ordered_qty: f64,
+
+    /// Orders answered, and their answer times in microseconds
+    pub acks: u64,
+    pub ack_total_us: u64,
+    pub ack_max_us: u64,
+
+    pub order_rejects: u64,
+    pub session_rejects: u64,
+
+    /// ResendRequests received (the counterparty missed ours) and sent
+    pub resends_in: u64,
+    pub resends_out: u64,
+
+    /// SequenceResets with GapFillFlag (123=Y) received
+    pub gap_fills: u64,
+
+    /// Messages received with PossDupFlag (43=Y)
+    pub poss_dups: u64,
+
+    pub fills: u64,
+    pub filled_qty: f64,
+
+    /// Quantity filled on limit orders, and its improvement on the limit,
+    /// in bps times quantity
+    pub limit_qty: f64,
+    pub improvement_bps_qty: f64,
+}
+
+impl DayScore {
+    pub fn new(session: &str, date: Date) -> Self {
+        Self {
+            session: session.to_string(),
+            date,
+            observed_secs: 0,
+            uptime_secs: 0,
+            logons: 0,
+            logouts: 0,
+            orders: 0,
+            ordered_qty: 0.0,
+            acks: 0,
+            ack_total_us: 0,
+            ack_max_us: 0,
+            order_rejects: 0,
+            session_rejects: 0,
+            resends_in: 0,
+            resends_out: 0,
+            gap_fills: 0,
+            poss_dups: 0,
+            fills: 0,
+            filled_qty: 0.0,
+            limit_qty: 0.0,
+            improvement_bps_qty: 0.0,
+        }
+    }
+
+    /// Share of the observed time logged on, 0 to 1
+    pub fn uptime(&self) -> Option<f64> {
+        (self.observed_secs > 0).then(|| self.uptime_secs as f64 / self.observed_secs as f64)
+    }
+
+    /// Share of the orders sent rejected, 0 to 1
+    pub fn reject_rate(&self) -> Option<f64> {
+        (self.orders > 0).then(|| self.order_rejects as f64 / self.orders as f64)
+    }
+
+    pub fn mean_ack(&self) -> Option<Duration> {
+        (self.acks > 0).then(|| Duration::from_micros(self.ack_total_us / self.acks))
+    }
+
+    pub fn max_ack(&self) -> Option<Duration> {
+        (self.acks > 0).then(|| Duration::from_micros(self.ack_max_us))
+    }
+
+    /// ResendRequests, both ways, per hour logged on
+    pub fn resends_per_hour(&self) -> Option<f64> {
+        (self.uptime_secs > 0).then(|| {
+            (self.resends_in + self.resends_out) as f64 * 3_600.0 / self.uptime_secs as f64
+        })
+    }
+
+    /// Share of the quantity ordered that filled, 0 to 1
+    pub fn fill_rate(&self) -> Option<f64> {
+        (self.ordered_qty > 0.0).then(|| self.filled_qty / self.ordered_qty)
+    }
+
+    /// Mean price improvement of limit order fills on their limit, in bps;
+    /// negative for fills through it
+    pub fn improvement_bps(&self) -> Option<f64> {
+        (self.limit_qty > 0.0).then(|| self.improvement_bps_qty / self.limit_qty)
+    }
+
+    /// Add the counts of another day; the date becomes the later of both
+    pub fn merge(&mut self, other: &DayScore) {
+        self.date = self.date.max(other.date);
+        self.observed_secs += other.observed_secs;
+        self.uptime_secs += other.uptime_secs;
+        self.logons += other.logons;
+        self.logouts += other.logouts;
+        self.orders += other.orders;
+        self.ordered_qty += other.ordered_qty;
+        self.acks += other.acks;
+        self.ack_total_us += other.ack_total_us;
+        self.ack_max_us = self.ack_max_us.max(other.ack_max_us);
+        self.order_rejects += other.order_rejects;
+        self.session_rejects += other.session_rejects;
+        self.resends_in += other.resends_in;
+        self.resends_out += other.resends_out;
+        self.gap_fills += other.gap_fills;
+        self.poss_dups += other.poss_dups;
+        self.fills += other.fills;
+        self.filled_qty += other.filled_qty;
+        self.limit_qty += other.limit_qty;
+        self.improvement_bps_qty += other.improvement_bps_qty;
+    }
+
+    /// JSON object, as stored
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"session\":\"{}\",\"date\":\"{}\",\"observed_secs\":{},\"uptime_secs\":{},\
+             \"logons\":{},\"logouts\":{},\"orders\":{},\"ordered_qty\":{},\"acks\":{},\
+             \"ack_total_us\":{},\"ack_max_us\":{},\"order_rejects\":{},\"session_rejects\":{},\
+             \"resends_in\":{},\"resends_out\":{},\"gap_fills\":{},\"poss_dups\":{},\
+             \"fills\":{},\"filled_qty\":{},\"limit_qty\":{},\"improvement_bps_qty\":{}}}",
+            json_escape(&self.session),
+            self.date.to_fix(),
+            self.observed_secs,
+            self.uptime_secs,
+            self.logons,
+            self.logouts,
+            self.orders,
+            self.ordered_qty,
+            self.acks,
+            self.ack_total_us,
+            self.ack_max_us,
+            self.order_rejects,
+            self.session_rejects,
+            self.resends_in,
+            self.resends_out,
+            self.gap_fills,
+            self.poss_dups,
+            self.fills,
+            self.filled_qty,
+            self.limit_qty,
+            self.improvement_bps_qty
+        )
+    }
+
+    /// Parse what to_json wrote; None if it is not a day score
+    pub fn from_json(text: &str) -> Option<Self> {
+        let value = JsonValue::parse(text)?;
+        let number = |key: &str| value.get(key)?.as_f64();
+        let count = |key: &str| number(key).map(|x| x as u64);
+        Some(Self {
+            session: value.get("session")?.as_str()?.to_string(),
+            date: Date::from_fix(value.get("date")?.as_str()?)?,
+            observed_secs: count("observed_secs")?,
+            uptime_secs: count("uptime_secs")?,
+            logons: count("logons")?,
+            logouts: count("logouts")?,
+            orders: count("orders")?,
+            ordered_qty: number("ordered_qty")?,
+            acks: count("acks")?,
+            ack_total_us: count("ack_total_us")?,
+            ack_max_us: count("ack_max_us")?,
+            order_rejects: count("order_rejects")?,
+            session_rejects: count("session_rejects")?,
+            resends_in: count("resends_in")?,
+            resends_out: count("resends_out")?,
+            gap_fills: count("gap_fills")?,
+            poss_dups: count("poss_dups")?,
+            fills: count("fills")?,
+            filled_qty: number("filled_qty")?,
+            limit_qty: number("limit_qty")?,
+            improvement_bps_qty: number("improvement_bps_qty")?,
+        })
+    }
+}
+
+// =============================================================================
+// Trends
+// =============================================================================
+
+/// A session over a window of days
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreTrend {
+    /// Every day of the window merged
+    pub window: DayScore,
+
+    /// The last day of the window
+    pub last: DayScore,
+
+    /// The days before it merged, if any
+    pub before: Option<DayScore>,
+}
+
+/// Merge days per session, sorted by session
+pub fn trends(scores: &[DayScore]) -> Vec<ScoreTrend> {
+    let mut by_session: BTreeMap<&str, Vec<&DayScore>> = BTreeMap::new();
+    for score in scores {
+        by_session.entry(&score.session).or_default().push(score);
+    }
+
+    by_session
+        .into_values()
+        .filter_map(|mut days| {
+            days.sort_by_key(|x| x.date);
+            let (last, before) = days.split_last()?;
+            let before = before.iter().fold(None, |merged: Option<DayScore>, x| {
+                let mut merged = merged.unwrap_or_else(|| DayScore::new(&x.session, x.date));
+                merged.merge(x);
+                Some(merged)
+            });
+            let mut window = before
+                .clone()
+                .unwrap_or_else(|| DayScore::new(&last.session, last.date));
+            window.merge(last);
+            Some(ScoreTrend {
+                window,
+                last: (*last).clone(),
+                before,
+            })
+        })
+        .collect()
+}
+
+// =============================================================================
+// Scorecard
+// =============================================================================
+
+/// An order waiting for its answer, and what its fills are compared with
+struct Pending {
+    sent: Instant,
+    buy: bool,
+    limit: Option<f64>,
+    acked: bool,
+}
+
+struct State {
+    date: Date,
+    scores: BTreeMap<String, DayScore>,
+    logged_on: HashSet<String>,
+
+    /// Unix seconds up to which observed and uptime seconds are counted
+    counted_to: i64,
+
+    /// By session and ClOrdID
+    pending: HashMap<(String, String), Pending>,
+}
+
+impl State {
+    fn score(&mut self, session: &str) -> &mut DayScore {
+        let date = self.date;
+        self.scores
+            .entry(session.to_string())
+            .or_insert_with(|| DayScore::new(session, date))
+    }
+}
+
+/// Scores of every session today, fed with the events of the callbacks
+pub struct Scorecard {
+    state: Mutex<State>,
+
+    /// Where the days are flushed, if set
+    store: Option<Arc<dyn StateStore>>,
+}
+
+impl Default for Scorecard {
+    fn default() -> Self {
+        let now = unix_now();
+        Self {
+            state: Mutex::new(State {
+                date: Date::from_unix(now),
+                scores: BTreeMap::new(),
+                logged_on: HashSet::new(),
+                counted_to: now,
+                pending: HashMap::new(),
+            }),
+            store: None,
+        }
+    }
+}
+
+impl Scorecard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Carry on from today's scores kept in a store, then flush the days
+    /// to it
+    pub fn with_store(mut self, store: Arc<dyn StateStore>) -> Result<Self, StoreError> {
+        let state = self.state.get_mut().expect("scorecard lock poisoned");
+        let prefix = format!("{}/", state.date.to_fix());
+        for (key, value) in store.scan(SCORECARDS_NAMESPACE)? {
+            let Some(session) = key.strip_prefix(&prefix) else {
+                continue;
+            };
+            let score = DayScore::from_json(&value).ok_or_else(|| StoreError::Corrupt {
+                namespace: SCORECARDS_NAMESPACE.to_string(),
+                key: key.clone(),
+            })?;
+            state.scores.insert(session.to_string(), score);
+        }
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("scorecard lock poisoned")
+    }
+
+    /// Count an event of a session
+    pub fn on_event(&self, event: &FixEvent) {
+        let mut state = self.lock();
+        self.advance(&mut state, unix_now());
+        match event {
+            FixEvent::Created { session } => {
+                state.score(session);
+            }
+            FixEvent::Logon { session } => {
+                state.score(session).logons += 1;
+                state.logged_on.insert(session.clone());
+            }
+            // A logout also ends logons that failed: only count real ones
+            FixEvent::Logout { session } => {
+                if state.logged_on.remove(session) {
+                    state.score(session).logouts += 1;
+                }
+            }
+            FixEvent::Message(msg) => on_message(&mut state, msg),
+            FixEvent::News(_) => {}
+        }
+    }
+
+    /// Count the seconds elapsed up to `now`, closing the days it ends
+    fn advance(&self, state: &mut State, now: i64) {
+        loop {
+            let day_end = state.date.to_unix() + SECONDS_PER_DAY;
+            let until = now.min(day_end);
+            let elapsed = (until - state.counted_to).max(0) as u64;
+            for score in state.scores.values_mut() {
+                score.observed_secs += elapsed;
+                if state.logged_on.contains(&score.session) {
+                    score.uptime_secs += elapsed;
+                }
+            }
+            state.counted_to = state.counted_to.max(until);
+            if now < day_end {
+                return;
+            }
+
+            // The day is over: keep it, start the next with the same sessions
+            if let Err(err) = self.store_day(state) {
+                eprintln!(
+                    "cannot keep the scorecards of {}: {err}",
+                    state.date.to_iso()
+                );
+            }
+            state.date = Date::from_unix(day_end);
+            let date = state.date;
+            for score in state.scores.values_mut() {
+                *score = DayScore::new(&score.session, date);
+            }
+        }
+    }
+
+    fn store_day(&self, state: &State) -> Result<(), StoreError> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        for (session, score) in &state.scores {
+            let key = format!("{}/{session}", state.date.to_fix());
+            store.put(SCORECARDS_NAMESPACE, &key, &score.to_json())?;
+        }
+        Ok(())
+    }
+
+    /// Write today's scores to the store, counted up to now
+    pub fn flush(&self) -> Result<(), StoreError> {
+        let mut state = self.lock();
+        self.advance(&mut state, unix_now());
+        self.store_day(&state)
+    }
+
+    /// Today's scores, counted up to now, sorted by session
+    pub fn today(&self) -> Vec<DayScore> {
+        let mut state = self.lock();
+        self.advance(&mut state, unix_now());
+        state.scores.values().cloned().collect()
+    }
+
+    /// Scores of the last `days` days (today included, 1 at least), from
+    /// the store for the days before today, by session then date
+    pub fn history(&self, days: u32) -> Result<Vec<DayScore>, StoreError> {
+        let (today, mut scores) = {
+            let mut state = self.lock();
+            self.advance(&mut state, unix_now());
+            (
+                state.date,
+                state.scores.values().cloned().collect::<Vec<_>>(),
+            )
+        };
+        scores.extend(self.stored(today, days)?);
+        scores.sort_by(|a, b| a.session.cmp(&b.session).then(a.date.cmp(&b.date)));
+        Ok(scores)
+    }
+
+    /// Stored days of the window ending with `today`, today excluded
+    fn stored(&self, today: Date, days: u32) -> Result<Vec<DayScore>, StoreError> {
+        let Some(store) = &self.store else {
+            return Ok(Vec::new());
+        };
+        let first = Date::from_unix(today.to_unix() - i64::from(days.max(1) - 1) * SECONDS_PER_DAY);
+        let mut scores = Vec::new();
+        for (key, value) in store.scan(SCORECARDS_NAMESPACE)? {
+            let date = key
+                .split_once('/')
+                .and_then(|(date, _)| Date::from_fix(date));
+            if !date.is_some_and(|x| x >= first && x < today) {
+                continue;
+            }
+            let score = DayScore::from_json(&value).ok_or_else(|| StoreError::Corrupt {
+                namespace: SCORECARDS_NAMESPACE.to_string(),
+                key: key.clone(),
+            })?;
+            scores.push(score);
+        }
+        Ok(scores)
+    }
+}
+
+// =============================================================================
+// Messages
+// =============================================================================
+
+/// Order statuses after which an order gets no more answers
+const TERMINAL_STATUSES: [&str; 5] = ["2", "3", "4", "8", "C"];
+
+fn on_message(state: &mut State, msg: &FixMessage) {
+    let session = msg.session.as_str();
+    let number = |tag: i32| msg.get(tag).and_then(|x| x.parse::<f64>().ok());
+    if msg.direction == Direction::Inbound && msg.get(43) == Some("Y") {
+        state.score(session).poss_dups += 1;
+    }
+
+    match (msg.direction, msg.msg_type()) {
+        (Direction::Outbound, msg_type @ ("D" | "F" | "G")) => {
+            let score = state.score(session);
+            score.orders += 1;
+            if msg_type == "D" {
+                score.ordered_qty += number(38).unwrap_or(0.0);
+            }
+            let Some(cl_ord_id) = msg.get(11) else {
+                return;
+            };
+            if state.pending.len() >= MAX_PENDING {
+                forget_oldest(&mut state.pending);
+            }
+            state.pending.insert(
+                (session.to_string(), cl_ord_id.to_string()),
+                Pending {
+                    sent: Instant::now(),
+                    buy: msg.get(54) == Some("1"),
+                    limit: number(44).filter(|x| *x > 0.0),
+                    acked: false,
+                },
+            );
+        }
+        (Direction::Outbound, "2") => state.score(session).resends_out += 1,
+        (Direction::Inbound, "2") => state.score(session).resends_in += 1,
+        (Direction::Inbound, "4") if msg.get(123) == Some("Y") => {
+            state.score(session).gap_fills += 1;
+        }
+        (Direction::Inbound, "3") => state.score(session).session_rejects += 1,
+        (Direction::Inbound, "j") => state.score(session).order_rejects += 1,
+        (Direction::Inbound, "9") => {
+            acknowledge(state, msg);
+            state.score(session).order_rejects += 1;
+            if let Some(cl_ord_id) = msg.get(11) {
+                state
+                    .pending
+                    .remove(&(session.to_string(), cl_ord_id.to_string()));
+            }
+        }
+        (Direction::Inbound, "8") => on_execution_report(state, msg),
+        _ => {}
+    }
+}
+
+fn on_execution_report(state: &mut State, msg: &FixMessage) {
+    let session = msg.session.as_str();
+    acknowledge(state, msg);
+    let key = |tag: i32| msg.get(tag).map(|x| (session.to_string(), x.to_string()));
+
+    match msg.get(150) {
+        Some("8") => state.score(session).order_rejects += 1,
+        // F is a trade from FIX 4.3 on, 1 and 2 (partial) fills before
+        Some("F" | "1" | "2") => {
+            let quantity = msg
+                .get(32)
+                .and_then(|x| x.parse::<f64>().ok())
+                .unwrap_or(0.0);
+            let price = msg.get(31).and_then(|x| x.parse::<f64>().ok());
+            let improvement = key(11)
+                .and_then(|key| state.pending.get(&key))
+                .and_then(|x| Some((x.buy, x.limit?)))
+                .zip(price)
+                .map(|((buy, limit), price)| {
+                    let better = if buy { limit - price } else { price - limit };
+                    better / limit * 10_000.0
+                });
+            let score = state.score(session);
+            score.fills += 1;
+            score.filled_qty += quantity;
+            if let Some(bps) = improvement {
+                score.limit_qty += quantity;
+                score.improvement_bps_qty += bps * quantity;
+            }
+        }
+        _ => {}
+    }
+
+    // The order is done, or replaced by the one of this report
+    let done = msg.get(39).is_some_and(|x| TERMINAL_STATUSES.contains(&x));
+    if done {
+        if let Some(key) = key(11) {
+            state.pending.remove(&key);
+        }
+    }
+    if done || msg.get(150) == Some("5") {
+        if let Some(key) = key(41) {
+            state.pending.remove(&key);
+        }
+    }
+}
+
+/// Time the first answer to the order of the ClOrdID of `msg`
+fn acknowledge(state: &mut State, msg: &FixMessage) {
+    let Some(cl_ord_id) = msg.get(11) else {
+        return;
+    };
+    let key = (msg.session.clone(), cl_ord_id.to_string());
+    let Some(pending) = state.pending.get_mut(&key).filter(|x| !x.acked) else {
+        return;
+    };
+    pending.acked = true;
+    let micros = pending.sent.elapsed().as_micros() as u64;
+    let score = state.score(&msg.session);
+    score.acks += 1;
+    score.ack_total_us += micros;
+    score.ack_max_us = score.ack_max_us.max(micros);
+}
+
+/// Make room: drop the older half of the orders waiting
+fn forget_oldest(pending: &mut HashMap<(String, String), Pending>) {
+    let mut sent: Vec<_> = pending.values().map(|x| x.sent).collect();
+    sent.sort_unstable();
+    let cutoff = sent[sent.len() / 2];
+    pending.retain(|_, x| x.sent >= cutoff);
+}
@@ -0,0 +1,250 @@
+// =============================================================================
+// Send Queue
+// =============================================================================
+// send_to_target fails while a session is logged off, so an order typed a
+// second before a reconnect is lost. A SendQueue holds the application
+// messages of a session that is not logged on, and sends them in order at
+// the next logon:
+//
+//   logged on, nothing queued   sent at once (through the rate limits)
+//   logged off, or a backlog    queued behind the others
+//   on_logon                    the queue flushed, oldest first, before
+//                               anything sent after it
+//   older than the TTL          dropped at the flush, counted as expired
+//
+// The sessions are logged off until on_logon is called for them: whoever
+// owns the queue feeds it the Logon and Logout events. A TTL of zero turns
+// queueing off, messages to a logged off session then fail as they did.
+//
+// Messages are kept as text and parsed again when sent, so the queue can be
+// shared between threads. Session-level messages (TestRequest, ResendRequest)
+// mean nothing after a reconnect: send them through limiter() instead.
+// =============================================================================
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use quickfix::{Message, SessionId};
+
+use crate::session::{
+    rate_limit::{RateLimiter, SendError},
+    session_label,
+};
+
+/// How long a message waits for a logon before it is dropped
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30);
+
+/// What SendQueue::send did with a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sent {
+    /// Handed to QuickFIX
+    Now,
+
+    /// Held until the session logs on, behind `waiting - 1` others
+    Queued { waiting: usize },
+}
+
+/// What a logon did with the queue of its session
+#[derive(Debug, Default)]
+pub struct Flush {
+    /// Messages sent, in the order they were queued
+    pub sent: usize,
+
+    /// Messages dropped for being older than the TTL
+    pub expired: usize,
+
+    /// The send that stopped the flush; that message and those after it
+    /// stay queued
+    pub failed: Option<SendError>,
+}
+
+/// The queue of one session, for display
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueStats {
+    pub session: String,
+    pub logged_on: bool,
+
+    /// Messages waiting
+    pub queued: usize,
+
+    /// Age of the oldest one
+    pub oldest: Option<Duration>,
+
+    /// Messages sent by the flushes so far
+    pub flushed: u64,
+
+    /// Messages dropped for their age so far
+    pub expired: u64,
+}
+
+#[derive(Debug)]
+struct Queued {
+    text: String,
+    at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    flushed: u64,
+    expired: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    logged_on: HashSet<String>,
+    queues: HashMap<String, VecDeque<Queued>>,
+    counts: HashMap<String, Counts>,
+}
+
+/// Application messages held while their session is logged off
+#[derive(Debug)]
+pub struct SendQueue {
+    limiter: RateLimiter,
+    ttl: Duration,
+    state: Mutex<State>,
+}
+
+impl SendQueue {
+    /// Messages go out through this limiter, now or at the flush
+    pub fn new(limiter: RateLimiter) -> Self {
+        Self {
+            limiter,
+            ttl: DEFAULT_TTL,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Drop the messages queued for longer; zero queues nothing
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// The rate limits messages go through, for those never queued
+    pub fn limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+
+    /// Send a message now if its session is logged on with nothing queued,
+    /// else queue it
+    pub fn send(&self, msg: Message, session: &SessionId) -> Result<Sent, SendError> {
+        let label = session_label(session);
+        let mut state = self.state.lock().expect("send queue lock poisoned");
+        let backlog = state.queues.get(&label).map_or(0, VecDeque::len);
+        if self.ttl.is_zero() || (backlog == 0 && state.logged_on.contains(&label)) {
+            drop(state);
+            return self.limiter.send(msg, session).map(|()| Sent::Now);
+        }
+
+        let text = msg.to_fix_string()?;
+        let queue = state.queues.entry(label).or_default();
+        queue.push_back(Queued {
+            text,
+            at: Instant::now(),
+        });
+        Ok(Sent::Queued {
+            waiting: queue.len(),
+        })
+    }
+
+    /// The session logged on: send what it queued, oldest first, dropping
+    /// what is older than the TTL
+    pub fn on_logon(&self, session: &SessionId) -> Flush {
+        let label = session_label(session);
+        let mut state = self.state.lock().expect("send queue lock poisoned");
+        state.logged_on.insert(label.clone());
+        let State { queues, counts, .. } = &mut *state;
+        let Some(queue) = queues.get_mut(&label) else {
+            return Flush::default();
+        };
+
+        // Sent under the lock: what the shell sends meanwhile goes behind
+        let counts = counts.entry(label).or_default();
+        let mut flush = Flush::default();
+        while let Some(queued) = queue.front() {
+            if queued.at.elapsed() > self.ttl {
+                queue.pop_front();
+                flush.expired += 1;
+                continue;
+            }
+            let result = Message::try_from_text(&queued.text)
+                .map_err(SendError::from)
+                .and_then(|msg| self.limiter.send(msg, session));
+            if let Err(err) = result {
+                flush.failed = Some(err);
+                break;
+            }
+            queue.pop_front();
+            flush.sent += 1;
+        }
+        counts.flushed += flush.sent as u64;
+        counts.expired += flush.expired as u64;
+        flush
+    }
+
+    /// The session logged off: queue what it is sent from now on
+    pub fn on_logout(&self, session: &SessionId) {
+        let mut state = self.state.lock().expect("send queue lock poisoned");
+        state.logged_on.remove(&session_label(session));
+    }
+
+    /// Drop the messages queued for a session, or for all of them
+    ///
+    /// # Returns
+    /// How many were dropped
+    pub fn clear(&self, label: Option<&str>) -> usize {
+        let mut state = self.state.lock().expect("send queue lock poisoned");
+        state
+            .queues
+            .iter_mut()
+            .filter(|(session, _)| label.is_none_or(|x| x == *session))
+            .map(|(_, queue)| queue.drain(..).count())
+            .sum()
+    }
+
+    /// Every session queued for or logged on, sorted by label
+    pub fn stats(&self) -> Vec<QueueStats> {
+        let state = self.state.lock().expect("send queue lock poisoned");
+        let mut labels: Vec<_> = state
+            .logged_on
+            .iter()
+            .chain(state.queues.keys())
+            .chain(state.counts.keys())
+            .collect();
+        labels.sort();
+        labels.dedup();
+
+        labels
+            .into_iter()
+            .map(|label| {
+                let queue = state.queues.get(label);
+                let counts = state.counts.get(label);
+                QueueStats {
+                    session: label.clone(),
+                    logged_on: state.logged_on.contains(label),
+                    queued: queue.map_or(0, VecDeque::len),
+                    oldest: queue.and_then(|x| x.front()).map(|x| x.at.elapsed()),
+                    flushed: counts.map_or(0, |x| x.flushed),
+                    expired: counts.map_or(0, |x| x.expired),
+                }
+            })
+            .collect()
+    }
+
+    /// Messages waiting, all sessions together
+    pub fn len(&self) -> usize {
+        let state = self.state.lock().expect("send queue lock poisoned");
+        state.queues.values().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
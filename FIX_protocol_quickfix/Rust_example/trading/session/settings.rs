@@ -0,0 +1,394 @@
+// =============================================================================
+// Configuration Files With Variables
+// =============================================================================
+// SessionSettings::try_from_path takes the file as it is, so a password or a
+// production host ends up committed with it. SettingsLoader reads the same
+// file, replaces the variables of each value, then builds the
+// SessionSettings in memory:
+//
+//   [SESSION]
+//   SocketConnectHost=${FIX_HOST}
+//   SocketConnectPort=${FIX_PORT:-5001}
+//   Password=${file:broker_password}
+//
+//   ${NAME}            the first provider that has NAME, the environment
+//                      unless others were added
+//   ${scheme:NAME}     only the provider of that scheme (env, file, ...)
+//   ${NAME:-default}   the default when no provider has it
+//   $$                 a literal $
+//
+// A variable nobody has stops the load, naming the line and the variable but
+// never a value. Secrets come from SecretsProvider implementations: the
+// environment (EnvSecrets), a directory of one file per secret as Docker and
+// Kubernetes mount them (FileSecrets), or a vault client of one's own.
+//
+// Only values are replaced, once the file is split into keys: a value with
+// a newline or a `[SESSION]` in it stays a value. The [SESSION] blocks read
+// on the way are kept (ConfigFile), since the quickfix crate does not list
+// the sessions of the settings it builds.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use quickfix::{Dictionary, QuickFixError, SessionId, SessionSettings};
+
+use crate::session::session_label;
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SettingsError {
+    Io(io::Error),
+
+    /// No provider has the variable: line number and variable
+    Unresolved(usize, String),
+
+    /// `${` without its `}`, or an empty name: line number
+    Syntax(usize),
+
+    /// `${scheme:NAME}` with no provider of that scheme: line number and
+    /// scheme
+    UnknownScheme(usize, String),
+
+    /// A provider failed: its scheme and why
+    Provider(String, String),
+
+    /// QuickFIX refused a session or a value
+    QuickFix(QuickFixError),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(err) => write!(f, "{err}"),
+            SettingsError::Unresolved(line, name) => {
+                write!(f, "line {line}: ${{{name}}} is not set")
+            }
+            SettingsError::Syntax(line) => write!(f, "line {line}: unterminated or empty ${{}}"),
+            SettingsError::UnknownScheme(line, scheme) => {
+                write!(f, "line {line}: no secrets provider for {scheme}:")
+            }
+            SettingsError::Provider(scheme, err) => write!(f, "{scheme} secrets: {err}"),
+            SettingsError::QuickFix(err) => write!(f, "quickfix: {err:?}"),
+        }
+    }
+}
+
+impl Error for SettingsError {}
+
+impl From<io::Error> for SettingsError {
+    fn from(err: io::Error) -> Self {
+        SettingsError::Io(err)
+    }
+}
+
+impl From<QuickFixError> for SettingsError {
+    fn from(err: QuickFixError) -> Self {
+        SettingsError::QuickFix(err)
+    }
+}
+
+// =============================================================================
+// Secrets Providers
+// =============================================================================
+
+/// Where the values of `${...}` come from
+pub trait SecretsProvider: Send + Sync {
+    /// Scheme naming the provider in `${scheme:NAME}`
+    fn scheme(&self) -> &str;
+
+    /// Value of a variable, none if this provider does not have it
+    fn lookup(&self, name: &str) -> Result<Option<String>, SettingsError>;
+}
+
+/// Environment variables (`env`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvSecrets;
+
+impl SecretsProvider for EnvSecrets {
+    fn scheme(&self) -> &str {
+        "env"
+    }
+
+    fn lookup(&self, name: &str) -> Result<Option<String>, SettingsError> {
+        Ok(env::var(name).ok())
+    }
+}
+
+/// One file per secret in a directory, its content the value, trailing
+/// newline dropped (`file`), as /run/secrets is mounted
+#[derive(Debug, Clone)]
+pub struct FileSecrets {
+    dir: PathBuf,
+}
+
+impl FileSecrets {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+}
+
+impl SecretsProvider for FileSecrets {
+    fn scheme(&self) -> &str {
+        "file"
+    }
+
+    fn lookup(&self, name: &str) -> Result<Option<String>, SettingsError> {
+        // A name is a file of the directory, not a path out of it
+        if name.contains(['/', '\\']) || name.starts_with('.') {
+            return Ok(None);
+        }
+        match fs::read_to_string(self.dir.join(name)) {
+            Ok(value) => Ok(Some(value.trim_end_matches(['\r', '\n']).to_string())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(SettingsError::Provider(
+                self.scheme().to_string(),
+                format!("{name}: {err}"),
+            )),
+        }
+    }
+}
+
+// =============================================================================
+// Configuration File
+// =============================================================================
+
+/// One [SESSION] block, [DEFAULT] values included
+#[derive(Debug, Clone)]
+pub struct SessionBlock {
+    values: HashMap<String, String>,
+}
+
+impl SessionBlock {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Identifier of the session this block configures
+    pub fn session_id(&self) -> Result<SessionId, QuickFixError> {
+        SessionId::try_new(
+            self.get("BeginString").unwrap_or_default(),
+            self.get("SenderCompID").unwrap_or_default(),
+            self.get("TargetCompID").unwrap_or_default(),
+            self.get("SessionQualifier").unwrap_or_default(),
+        )
+    }
+}
+
+/// A QuickFIX configuration file, variables replaced
+#[derive(Debug, Clone, Default)]
+pub struct ConfigFile {
+    /// [DEFAULT] values, in file order
+    defaults: Vec<(String, String)>,
+
+    /// Values of each [SESSION] block, in file order
+    sessions: Vec<Vec<(String, String)>>,
+}
+
+impl ConfigFile {
+    /// Read the sections of a configuration file as they are
+    pub fn parse(text: &str) -> Self {
+        Self::parse_with(text, |_, value| Ok(value.to_string())).unwrap_or_default()
+    }
+
+    /// Read the sections of a configuration file, each value through
+    /// `value` with its line number
+    fn parse_with<F>(text: &str, mut value: F) -> Result<Self, SettingsError>
+    where
+        F: FnMut(usize, &str) -> Result<String, SettingsError>,
+    {
+        let mut config = Self::default();
+        let mut in_session = false;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || is_comment(line) {
+                continue;
+            }
+            if line.starts_with('[') {
+                in_session = line.eq_ignore_ascii_case("[SESSION]");
+                if in_session {
+                    config.sessions.push(Vec::new());
+                }
+                continue;
+            }
+            let Some((key, raw)) = line.split_once('=') else {
+                continue;
+            };
+            let pair = (key.trim().to_string(), value(index + 1, raw.trim())?);
+            match config.sessions.last_mut() {
+                Some(session) if in_session => session.push(pair),
+                _ => config.defaults.push(pair),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Every [SESSION] block, [DEFAULT] values included
+    pub fn sessions(&self) -> Vec<SessionBlock> {
+        self.sessions
+            .iter()
+            .map(|session| SessionBlock {
+                values: self.defaults.iter().chain(session).cloned().collect(),
+            })
+            .collect()
+    }
+
+    /// Set a value of the [SESSION] block of a session, by label, in place
+    /// of the one of the file or [DEFAULT]
+    ///
+    /// # Returns
+    /// false when no block configures that session
+    pub fn set(&mut self, session: &str, key: &str, value: &str) -> bool {
+        let position = self.sessions().iter().position(|block| {
+            block
+                .session_id()
+                .is_ok_and(|x| session_label(&x) == session)
+        });
+        let Some(values) = position.map(|index| &mut self.sessions[index]) else {
+            return false;
+        };
+        match values.iter_mut().find(|(name, _)| name == key) {
+            Some(pair) => pair.1 = value.to_string(),
+            None => values.push((key.to_string(), value.to_string())),
+        }
+        true
+    }
+
+    /// The settings QuickFIX would have read from the file
+    pub fn to_settings(&self) -> Result<SessionSettings, SettingsError> {
+        let mut settings = SessionSettings::new();
+        // Defaults first: QuickFIX merges them into each session as it is set
+        settings.set(None, dictionary(&self.defaults)?)?;
+        for (session, block) in self.sessions.iter().zip(self.sessions()) {
+            settings.set(Some(&block.session_id()?), dictionary(session)?)?;
+        }
+        Ok(settings)
+    }
+}
+
+fn dictionary(values: &[(String, String)]) -> Result<Dictionary, QuickFixError> {
+    let mut dictionary = Dictionary::new();
+    for (key, value) in values {
+        dictionary.set(key, value.as_str())?;
+    }
+    Ok(dictionary)
+}
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with('#') || line.starts_with(';')
+}
+
+// =============================================================================
+// Loader
+// =============================================================================
+
+/// Reads configuration files, variables replaced by the providers
+pub struct SettingsLoader {
+    providers: Vec<Box<dyn SecretsProvider>>,
+}
+
+impl Default for SettingsLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SettingsLoader {
+    /// Variables from the environment
+    pub fn new() -> Self {
+        Self {
+            providers: vec![Box::new(EnvSecrets)],
+        }
+    }
+
+    /// Also look variables up in this provider, after the ones before it
+    pub fn with_provider<P: SecretsProvider + 'static>(mut self, provider: P) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    /// Schemes of the providers, in lookup order
+    pub fn schemes(&self) -> Vec<&str> {
+        self.providers.iter().map(|x| x.scheme()).collect()
+    }
+
+    /// Replace the variables of a value
+    ///
+    /// # Arguments
+    /// * `number` - Line of the value, for errors
+    pub fn interpolate(&self, number: usize, value: &str) -> Result<String, SettingsError> {
+        let mut out = String::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(dollar) = rest.find('$') {
+            out.push_str(&rest[..dollar]);
+            rest = &rest[dollar..];
+            if let Some(after) = rest.strip_prefix("$$") {
+                out.push('$');
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("${") {
+                let end = after.find('}').ok_or(SettingsError::Syntax(number))?;
+                out.push_str(&self.resolve(number, &after[..end])?);
+                rest = &after[end + 1..];
+            } else {
+                out.push('$');
+                rest = &rest[1..];
+            }
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// Value of `scheme:NAME:-default`, scheme and default optional
+    fn resolve(&self, number: usize, variable: &str) -> Result<String, SettingsError> {
+        let (reference, default) = match variable.split_once(":-") {
+            Some((reference, default)) => (reference, Some(default)),
+            None => (variable, None),
+        };
+        let (scheme, name) = match reference.split_once(':') {
+            Some((scheme, name)) => (Some(scheme), name),
+            None => (None, reference),
+        };
+        if name.is_empty() {
+            return Err(SettingsError::Syntax(number));
+        }
+
+        let providers: Vec<_> = self
+            .providers
+            .iter()
+            .filter(|x| scheme.is_none_or(|scheme| x.scheme() == scheme))
+            .collect();
+        if let (Some(scheme), true) = (scheme, providers.is_empty()) {
+            return Err(SettingsError::UnknownScheme(number, scheme.to_string()));
+        }
+        for provider in providers {
+            if let Some(value) = provider.lookup(name)? {
+                return Ok(value);
+            }
+        }
+        default
+            .map(str::to_string)
+            .ok_or_else(|| SettingsError::Unresolved(number, reference.to_string()))
+    }
+
+    /// Read a configuration file and replace its variables
+    pub fn read(&self, path: &Path) -> Result<ConfigFile, SettingsError> {
+        let text = fs::read_to_string(path)?;
+        ConfigFile::parse_with(&text, |number, value| self.interpolate(number, value))
+    }
+
+    /// SessionSettings of a configuration file, its variables replaced
+    pub fn load(&self, path: &Path) -> Result<SessionSettings, SettingsError> {
+        self.read(path)?.to_settings()
+    }
+}
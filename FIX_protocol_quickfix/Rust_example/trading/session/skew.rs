@@ -0,0 +1,186 @@
+// =============================================================================
+// Clock Skew
+// =============================================================================
+// How far the counterparty's clock is from ours, from the SendingTime (52) of
+// what it sends. Each inbound message gives
+//
+//   offset = receive time - SendingTime        skew + wire + its send path
+//
+// so a single offset overstates the skew by the transit time. Over the last
+// WINDOW messages, the least delayed one comes closest: the smallest offset
+// is taken for the skew (positive: the counterparty's clock is behind ours,
+// negative: ahead). SendingTime in milliseconds truncates up to one more.
+//
+// A skew beyond the threshold is worth an alert before it is a disconnect:
+// session-time validation (CheckLatency, MaxLatency) rejects messages whose
+// SendingTime is too far from the receiver's clock, on both sides. Messages
+// resent (PossDupFlag 43=Y) may have waited in the engine for a gap to fill,
+// and are left out.
+// =============================================================================
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use quickfix::{FieldMap, Message, SessionId};
+
+use super::session_label;
+use crate::time::parse_utc_timestamp;
+
+/// Offsets the skew is estimated from, per session
+pub const WINDOW: usize = 32;
+
+/// Skew alerted on by default: well under QuickFIX's MaxLatency (120 s), past
+/// what NTP leaves on a host that runs it
+pub const DEFAULT_MAX_SKEW: Duration = Duration::from_secs(1);
+
+// =============================================================================
+// Readings
+// =============================================================================
+
+/// One session's skew, as read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClockSkew {
+    pub session: String,
+
+    /// Estimate over the window, nanoseconds; positive: counterparty behind
+    pub skew: i64,
+
+    /// Offset of the last message, nanoseconds
+    pub last: i64,
+
+    /// Messages measured since the start
+    pub samples: u64,
+
+    /// Beyond the threshold
+    pub exceeded: bool,
+}
+
+/// A skew crossing the threshold, either way
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkewAlert {
+    Exceeded { session: String, skew: i64 },
+    Recovered { session: String, skew: i64 },
+}
+
+// =============================================================================
+// Monitor
+// =============================================================================
+
+#[derive(Debug, Default)]
+struct SessionSkew {
+    offsets: VecDeque<i64>,
+    samples: u64,
+    exceeded: bool,
+}
+
+impl SessionSkew {
+    fn skew(&self) -> i64 {
+        self.offsets.iter().copied().min().unwrap_or_default()
+    }
+}
+
+/// Skew of every session, fed by the inbound callbacks
+#[derive(Debug)]
+pub struct SkewMonitor {
+    sessions: Mutex<HashMap<String, SessionSkew>>,
+    max_skew: Duration,
+}
+
+impl Default for SkewMonitor {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_SKEW)
+    }
+}
+
+impl SkewMonitor {
+    /// Alert beyond `max_skew`, either way
+    pub fn new(max_skew: Duration) -> Self {
+        Self {
+            sessions: Mutex::default(),
+            max_skew,
+        }
+    }
+
+    pub fn max_skew(&self) -> Duration {
+        self.max_skew
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, SessionSkew>> {
+        self.sessions.lock().expect("skew lock poisoned")
+    }
+
+    /// Measure one inbound message; call from the from_admin / from_app
+    /// callbacks, as early as possible
+    pub fn on_inbound(&self, session: &SessionId, msg: &Message) -> Option<SkewAlert> {
+        let received = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_nanos() as i64);
+        let (sending_time, poss_dup) = msg.with_header(|h| (h.get_field(52), h.get_field(43)));
+        if poss_dup.as_deref() == Some("Y") {
+            return None;
+        }
+        let sending_time = parse_utc_timestamp(&sending_time?)?;
+        self.record(&session_label(session), received - sending_time)
+    }
+
+    /// Add the offset of one message, nanoseconds; an alert when the skew
+    /// crosses the threshold
+    pub fn record(&self, session: &str, offset: i64) -> Option<SkewAlert> {
+        let mut sessions = self.lock();
+        let state = sessions.entry(session.to_string()).or_default();
+        if state.offsets.len() == WINDOW {
+            state.offsets.pop_front();
+        }
+        state.offsets.push_back(offset);
+        state.samples += 1;
+
+        let skew = state.skew();
+        let exceeded = skew.unsigned_abs() > self.max_skew.as_nanos() as u64;
+        if exceeded == state.exceeded {
+            return None;
+        }
+        state.exceeded = exceeded;
+        let session = session.to_string();
+        if exceeded {
+            Some(SkewAlert::Exceeded { session, skew })
+        } else {
+            Some(SkewAlert::Recovered { session, skew })
+        }
+    }
+
+    /// Start the window over, e.g. at a logon: the route may have changed
+    pub fn reset(&self, session: &SessionId) {
+        if let Some(state) = self.lock().get_mut(&session_label(session)) {
+            state.offsets.clear();
+        }
+    }
+
+    /// One session, if it has sent a SendingTime
+    pub fn session(&self, session: &str) -> Option<ClockSkew> {
+        self.lock().get(session).and_then(|x| read(session, x))
+    }
+
+    /// Every session measured, sorted by label
+    pub fn snapshot(&self) -> Vec<ClockSkew> {
+        let mut skews: Vec<_> = self
+            .lock()
+            .iter()
+            .filter_map(|(label, x)| read(label, x))
+            .collect();
+        skews.sort_by(|a, b| a.session.cmp(&b.session));
+        skews
+    }
+}
+
+fn read(session: &str, state: &SessionSkew) -> Option<ClockSkew> {
+    Some(ClockSkew {
+        session: session.to_string(),
+        skew: state.skew(),
+        last: *state.offsets.back()?,
+        samples: state.samples,
+        exceeded: state.exceeded,
+    })
+}
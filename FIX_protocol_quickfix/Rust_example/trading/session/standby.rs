@@ -0,0 +1,271 @@
+// =============================================================================
+// Warm Standby
+// =============================================================================
+// A second process, started with the same sessions but not logged on, ready
+// to carry on when the primary goes away. Both see the same volume:
+//
+//   primary   writes a heartbeat file every second (HeartbeatWriter): what
+//             it would hand over to a new version (handover.rs), the orders,
+//             the ClOrdID sequence and the fills, with its pid and the time
+//   standby   reads it (Standby::poll), and the sequence numbers from the
+//             primary's message store (its FileStorePath): those move with
+//             every message, the heartbeat once a second
+//
+// The standby takes the sessions over in one of two ways:
+//
+//   on command   it claims them (FILE.claim). The primary sees the claim at
+//                its next heartbeat and stands down as for an upgrade:
+//                drains, logs out, writes the handover file the standby
+//                waits for. Nothing is lost.
+//   stale        the heartbeat is older than stale_after: the primary died.
+//                The standby carries on from the last heartbeat and the
+//                sequence numbers of the store; orders sent or filled since
+//                that heartbeat are unknown until the venue's reports bring
+//                them back (a mass status, 35=AF).
+//
+// Either way the sequence numbers go into the standby's own store before its
+// sessions are created, and it logs on where the primary left off. A primary
+// that quits normally removes its heartbeat file: the standby keeps waiting.
+// A standby cannot tell a dead primary from a cut link to the volume; a
+// stale_after long against the heartbeat interval, and a venue refusing a
+// second logon with the same CompIDs, keep two processes from trading.
+// =============================================================================
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use quickfix::SessionId;
+
+use super::handover::{Handover, HandoverError, SessionSequences};
+
+/// How often the primary writes its heartbeat
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Age of the heartbeat after which the standby takes over
+pub const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(5);
+
+/// The file a standby writes to claim the sessions: `FILE.claim`
+pub fn claim_path(heartbeat: &Path) -> PathBuf {
+    let mut claim = heartbeat.as_os_str().to_owned();
+    claim.push(".claim");
+    PathBuf::from(claim)
+}
+
+// =============================================================================
+// Primary
+// =============================================================================
+
+/// The primary's heartbeat, written by a thread of its own
+#[derive(Debug)]
+pub struct HeartbeatWriter {
+    path: PathBuf,
+    claimed: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HeartbeatWriter {
+    /// Write the snapshot to `path` now, then every `interval`
+    ///
+    /// Its sessions may be left empty: the standby reads the sequence
+    /// numbers from the message store.
+    ///
+    /// A claim left over from an earlier run is removed first. The thread
+    /// stops writing once the sessions are claimed: the next thing the
+    /// standby reads from the primary is the handover file.
+    pub fn spawn<F>(path: &Path, interval: Duration, snapshot: F) -> Result<Self, HandoverError>
+    where
+        F: Fn() -> Handover + Send + 'static,
+    {
+        remove_if_exists(&claim_path(path))?;
+        snapshot().write(path)?;
+
+        let claimed = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let path = path.to_path_buf();
+            let claimed = Arc::clone(&claimed);
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name("heartbeat".to_string())
+                .spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        thread::sleep(interval);
+                        if claim_path(&path).exists() {
+                            claimed.store(true, Ordering::Relaxed);
+                            break;
+                        }
+                        if let Err(err) = snapshot().write(&path) {
+                            eprintln!("Cannot write the heartbeat {}: {err}", path.display());
+                        }
+                    }
+                })?
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            claimed,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Whether a standby claimed the sessions
+    pub fn claimed(&self) -> bool {
+        self.claimed.load(Ordering::Relaxed)
+    }
+
+    /// Stop writing and remove the heartbeat (and the claim): a standby
+    /// does not take over from a primary that quit
+    pub fn stop(mut self) -> Result<(), HandoverError> {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        remove_if_exists(&claim_path(&self.path))?;
+        remove_if_exists(&self.path)?;
+        Ok(())
+    }
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+// =============================================================================
+// Standby
+// =============================================================================
+
+/// What the standby knows of the primary
+#[derive(Debug, Clone)]
+pub struct StandbyStatus {
+    /// Pid and library version of the primary, if it has a heartbeat
+    pub primary: Option<(u32, String)>,
+
+    /// Seconds since its last heartbeat
+    pub age: Option<i64>,
+
+    /// Orders working at its last heartbeat
+    pub open_orders: usize,
+
+    /// Next sequence numbers of each session, from the primary's store
+    pub sessions: Vec<SessionSequences>,
+
+    /// The heartbeat is too old: the primary is gone
+    pub stale: bool,
+}
+
+impl fmt::Display for StandbyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.primary, self.age) {
+            (Some((pid, version)), Some(age)) => write!(
+                f,
+                "primary pid {pid} ({version}), heartbeat {age}s ago{}, {} open orders",
+                if self.stale { " STALE" } else { "" },
+                self.open_orders
+            )?,
+            _ => write!(f, "no primary")?,
+        }
+        for x in &self.sessions {
+            write!(
+                f,
+                "; {} out {} in {}",
+                x.session, x.next_sender, x.next_target
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A standby following the primary through its heartbeat and message store
+#[derive(Debug)]
+pub struct Standby {
+    heartbeat: PathBuf,
+    primary_store: PathBuf,
+    sessions: Vec<SessionId>,
+    stale_after: i64,
+    last: Option<Handover>,
+}
+
+impl Standby {
+    pub fn new(heartbeat: &Path, primary_store: &Path, sessions: Vec<SessionId>) -> Self {
+        Self {
+            heartbeat: heartbeat.to_path_buf(),
+            primary_store: primary_store.to_path_buf(),
+            sessions,
+            stale_after: DEFAULT_STALE_AFTER.as_secs() as i64,
+            last: None,
+        }
+    }
+
+    pub fn with_stale_after(mut self, stale_after: Duration) -> Self {
+        self.stale_after = stale_after.as_secs() as i64;
+        self
+    }
+
+    /// Read the heartbeat and the sequence numbers again
+    ///
+    /// A missing heartbeat is a primary not started, or one that quit: never
+    /// stale. One being rewritten keeps the last read.
+    pub fn poll(&mut self, now: i64) -> Result<StandbyStatus, HandoverError> {
+        match fs::read_to_string(&self.heartbeat) {
+            Ok(json) => {
+                if let Ok(handover) = Handover::from_json(&json) {
+                    self.last = Some(handover);
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => self.last = None,
+            Err(err) => return Err(err.into()),
+        }
+
+        let age = self.last.as_ref().map(|x| now - x.time);
+        Ok(StandbyStatus {
+            primary: self.last.as_ref().map(|x| (x.pid, x.version.clone())),
+            age,
+            open_orders: self.last.as_ref().map_or(0, |x| {
+                x.orders.iter().filter(|x| x.status.is_working()).count()
+            }),
+            sessions: self.sequences()?,
+            stale: age.is_some_and(|age| age > self.stale_after),
+        })
+    }
+
+    /// Ask the primary to stand down
+    ///
+    /// It writes the handover file once its sessions are closed.
+    pub fn claim(&self) -> Result<(), HandoverError> {
+        fs::write(claim_path(&self.heartbeat), std::process::id().to_string())?;
+        Ok(())
+    }
+
+    /// Carry on from the last heartbeat, the primary gone: the sequence
+    /// numbers of its store go into ours
+    pub fn take_over(self, store_dir: &Path) -> Result<Handover, HandoverError> {
+        let sessions = self.sequences()?;
+        let mut handover = self
+            .last
+            .ok_or_else(|| HandoverError::Invalid("standby: no heartbeat".to_string()))?;
+        for sequences in &sessions {
+            sequences.apply(store_dir)?;
+        }
+        handover.sessions = sessions;
+        Ok(handover)
+    }
+
+    fn sequences(&self) -> Result<Vec<SessionSequences>, HandoverError> {
+        self.sessions
+            .iter()
+            .map(|x| SessionSequences::read(&self.primary_store, x))
+            .collect()
+    }
+}
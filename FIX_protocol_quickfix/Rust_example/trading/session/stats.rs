@@ -0,0 +1,267 @@
+// =============================================================================
+// Message Statistics
+// =============================================================================
+// Every message sent and received, counted by session, MsgType and
+// direction, from the callbacks of all the sessions at once (MultiThreaded
+// sockets) without a lock they would all queue on:
+//
+//   - sessions are spread over SHARDS shards by a hash of their label, each
+//     a map behind its own RwLock
+//   - a message of a MsgType its session has seen before takes two read
+//     locks (its shard, its session), shared by every thread, and bumps
+//     atomics; a write lock is taken only the first time a session shows
+//     up, or a MsgType on a session
+//   - per session, the time of the last message each way and the largest
+//     gap between two inbound messages of one logon, atomics too
+//
+// Readers (the REPL `stats` command, the metrics exporter) take a snapshot:
+// every counter in it is exact, the set of them is not one instant.
+// =============================================================================
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use super::Direction;
+
+/// Shards of the session map; more than the cores of a busy gateway
+const SHARDS: usize = 16;
+
+// =============================================================================
+// Counters
+// =============================================================================
+
+/// Messages of one MsgType on one session, each way
+#[derive(Debug, Default)]
+struct TypeCounters {
+    inbound: AtomicU64,
+    outbound: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct SessionCounters {
+    by_type: RwLock<HashMap<String, Arc<TypeCounters>>>,
+    inbound: AtomicU64,
+    outbound: AtomicU64,
+
+    /// Nanoseconds from the registry's start, plus one: 0 is never
+    last_inbound: AtomicU64,
+    last_outbound: AtomicU64,
+
+    /// last_inbound as of this logon, where the next inbound gap is
+    /// measured from: 0 until the first message after a logon
+    gap_from: AtomicU64,
+
+    /// Nanoseconds
+    max_inbound_gap: AtomicU64,
+}
+
+impl SessionCounters {
+    fn record(&self, direction: Direction, msg_type: &str, now: u64) {
+        let by_type = self.by_type.read().expect("stats lock poisoned");
+        match by_type.get(msg_type) {
+            Some(counters) => counters.add(direction),
+            None => {
+                drop(by_type);
+                let mut by_type = self.by_type.write().expect("stats lock poisoned");
+                by_type
+                    .entry(msg_type.to_string())
+                    .or_default()
+                    .add(direction);
+            }
+        }
+
+        match direction {
+            Direction::Inbound => {
+                self.inbound.fetch_add(1, Ordering::Relaxed);
+                self.last_inbound.store(now, Ordering::Relaxed);
+                let from = self.gap_from.swap(now, Ordering::Relaxed);
+                if from != 0 {
+                    let gap = now.saturating_sub(from);
+                    self.max_inbound_gap.fetch_max(gap, Ordering::Relaxed);
+                }
+            }
+            Direction::Outbound => {
+                self.outbound.fetch_add(1, Ordering::Relaxed);
+                self.last_outbound.store(now, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl TypeCounters {
+    fn add(&self, direction: Direction) {
+        match direction {
+            Direction::Inbound => self.inbound.fetch_add(1, Ordering::Relaxed),
+            Direction::Outbound => self.outbound.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+}
+
+// =============================================================================
+// Snapshots
+// =============================================================================
+
+/// Messages of one MsgType on a session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeCount {
+    pub msg_type: String,
+    pub inbound: u64,
+    pub outbound: u64,
+}
+
+/// One session's counters, as read
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionStats {
+    /// Session label
+    pub session: String,
+    pub inbound: u64,
+    pub outbound: u64,
+
+    /// How long ago the last message came in, and went out
+    pub since_inbound: Option<Duration>,
+    pub since_outbound: Option<Duration>,
+
+    /// Largest gap between two inbound messages, reconnections excepted
+    pub max_inbound_gap: Duration,
+
+    /// By MsgType, sorted
+    pub by_type: Vec<TypeCount>,
+}
+
+impl SessionStats {
+    /// Messages of a MsgType each way, (0, 0) if none
+    pub fn count(&self, msg_type: &str) -> (u64, u64) {
+        self.by_type
+            .iter()
+            .find(|x| x.msg_type == msg_type)
+            .map_or((0, 0), |x| (x.inbound, x.outbound))
+    }
+}
+
+// =============================================================================
+// Registry
+// =============================================================================
+
+/// Message counters of every session, shared by the callbacks and the
+/// readers
+#[derive(Debug)]
+pub struct MessageStats {
+    started: Instant,
+    shards: Vec<RwLock<HashMap<String, Arc<SessionCounters>>>>,
+}
+
+impl Default for MessageStats {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            shards: (0..SHARDS).map(|_| RwLock::default()).collect(),
+        }
+    }
+}
+
+impl MessageStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one message; call from the to_/from_ admin/app callbacks
+    pub fn record(&self, session: &str, direction: Direction, msg_type: &str) {
+        let now = self.now();
+        let shard = self.shard(session).read().expect("stats lock poisoned");
+        match shard.get(session) {
+            Some(counters) => counters.record(direction, msg_type, now),
+            None => {
+                drop(shard);
+                let counters = Arc::clone(
+                    self.shard(session)
+                        .write()
+                        .expect("stats lock poisoned")
+                        .entry(session.to_string())
+                        .or_default(),
+                );
+                counters.record(direction, msg_type, now);
+            }
+        }
+    }
+
+    /// A session logged on: the silence before is not an inbound gap; the
+    /// time of the last message, and since_inbound, stay as they were
+    pub fn on_logon(&self, session: &str) {
+        let shard = self.shard(session).read().expect("stats lock poisoned");
+        if let Some(counters) = shard.get(session) {
+            counters.gap_from.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Every session, sorted by label
+    pub fn snapshot(&self) -> Vec<SessionStats> {
+        let mut sessions: Vec<_> = self
+            .shards
+            .iter()
+            .flat_map(|shard| {
+                let shard = shard.read().expect("stats lock poisoned");
+                shard
+                    .iter()
+                    .map(|(label, counters)| self.read(label, counters))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        sessions.sort_by(|a, b| a.session.cmp(&b.session));
+        sessions
+    }
+
+    /// One session, if it has a message
+    pub fn session(&self, session: &str) -> Option<SessionStats> {
+        let shard = self.shard(session).read().expect("stats lock poisoned");
+        shard
+            .get(session)
+            .map(|counters| self.read(session, counters))
+    }
+
+    fn read(&self, label: &str, counters: &SessionCounters) -> SessionStats {
+        let now = self.now();
+        let since = |last: &AtomicU64| match last.load(Ordering::Relaxed) {
+            0 => None,
+            last => Some(Duration::from_nanos(now.saturating_sub(last))),
+        };
+        let mut by_type: Vec<_> = counters
+            .by_type
+            .read()
+            .expect("stats lock poisoned")
+            .iter()
+            .map(|(msg_type, x)| TypeCount {
+                msg_type: msg_type.clone(),
+                inbound: x.inbound.load(Ordering::Relaxed),
+                outbound: x.outbound.load(Ordering::Relaxed),
+            })
+            .collect();
+        by_type.sort_by(|a, b| a.msg_type.cmp(&b.msg_type));
+        SessionStats {
+            session: label.to_string(),
+            inbound: counters.inbound.load(Ordering::Relaxed),
+            outbound: counters.outbound.load(Ordering::Relaxed),
+            since_inbound: since(&counters.last_inbound),
+            since_outbound: since(&counters.last_outbound),
+            max_inbound_gap: Duration::from_nanos(counters.max_inbound_gap.load(Ordering::Relaxed)),
+            by_type,
+        }
+    }
+
+    fn shard(&self, session: &str) -> &RwLock<HashMap<String, Arc<SessionCounters>>> {
+        let mut hasher = DefaultHasher::new();
+        session.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    /// Nanoseconds since the start, plus one
+    fn now(&self) -> u64 {
+        self.started.elapsed().as_nanos() as u64 + 1
+    }
+}
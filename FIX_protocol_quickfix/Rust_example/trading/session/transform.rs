@@ -0,0 +1,399 @@
+// =============================================================================
+// Message Transforms
+// =============================================================================
+// Venue quirks handled in a rules file rather than in code: fields added,
+// stripped or renamed and values remapped, per counterparty, on the way out
+// (on_msg_to_app) or in (on_msg_from_app). One rule per line, `#` for
+// comments:
+//
+//   NAME = CONDITION => ACTION; ACTION; ...
+//
+//   account  = direction == "out" && msg.35 == "D" => set Account ACCT-7
+//   venue_b  = session == "FIX.4.4:HUB->VENUE_B" => map TimeInForce Day=GoodTillCancel; strip 58
+//   venue_px = direction == "in" && msg.5044 != null => rename 5044 LastPx
+//
+// Conditions are those of trading::expr, `session` naming the counterparty.
+// Actions, tags by number or dictionary name and values by value or name:
+//
+//   set TAG VALUE         add the field, or replace its value
+//   strip TAG             remove it
+//   rename FROM TO        move its value to another tag
+//   map TAG A=B, C=D      replace value A with B, C with D, others kept
+//
+// Every rule that matches applies, in the order of the file, each seeing
+// the message as the rules before it left it. The fields the engine manages
+// (BeginString, BodyLength, MsgType, CompIDs, MsgSeqNum, SendingTime,
+// CheckSum) cannot be touched, and only the top-level fields of a message
+// are reached, not those inside repeating groups.
+// =============================================================================
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use quickfix::{FieldMap, Message, QuickFixError, SessionId};
+
+use crate::{
+    expr::{ExprError, Rule},
+    session::{dictionary::Dictionary, events::FixMessage, Direction},
+};
+
+/// Tags the engine fills in or checks, out of reach of the actions
+const ENGINE_TAGS: [i32; 8] = [8, 9, 10, 34, 35, 49, 52, 56];
+
+/// Tags the actions find in the header rather than the body
+const HEADER_TAGS: [i32; 8] = [43, 50, 57, 97, 115, 116, 122, 128];
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TransformError {
+    Io(PathBuf, io::Error),
+
+    /// A line without `=> ACTION`, or with an action that cannot be read:
+    /// line number and why
+    Syntax(usize, String),
+
+    /// The condition of a line: line number and error
+    Expr(usize, ExprError),
+
+    /// Two rules of a file with the same name
+    DuplicateRule(String),
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransformError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            TransformError::Syntax(line, message) => write!(f, "line {line}: {message}"),
+            TransformError::Expr(line, err) => write!(f, "line {line}: {err}"),
+            TransformError::DuplicateRule(name) => write!(f, "transform {name} defined twice"),
+        }
+    }
+}
+
+impl Error for TransformError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TransformError::Io(_, err) => Some(err),
+            TransformError::Expr(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// =============================================================================
+// Actions
+// =============================================================================
+
+/// What a rule does to the messages it matches
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Set(i32, String),
+    Strip(i32),
+    Rename(i32, i32),
+
+    /// Values replaced, the others kept
+    Map(i32, Vec<(String, String)>),
+}
+
+impl Action {
+    /// Parse `set TAG VALUE`, `strip TAG`, `rename FROM TO` or
+    /// `map TAG A=B, C=D`
+    pub fn parse_with(text: &str, dictionary: &Dictionary) -> Result<Self, TransformError> {
+        let syntax = |message: String| TransformError::Syntax(0, message);
+        let text = text.trim();
+        let (verb, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let (field, rest) = rest
+            .trim()
+            .split_once(char::is_whitespace)
+            .unwrap_or((rest.trim(), ""));
+        let rest = rest.trim();
+        let tag = |name: &str| -> Result<i32, TransformError> {
+            let tag = dictionary
+                .resolve(name)
+                .ok_or_else(|| syntax(format!("unknown field {name:?}")))?;
+            if ENGINE_TAGS.contains(&tag) {
+                return Err(syntax(format!("tag {tag} is managed by the engine")));
+            }
+            Ok(tag)
+        };
+
+        let action = match verb {
+            "set" if !rest.is_empty() => {
+                let tag = tag(field)?;
+                Action::Set(tag, dictionary.resolve_value(tag, rest).to_string())
+            }
+            "strip" if rest.is_empty() => Action::Strip(tag(field)?),
+            "rename" if !rest.is_empty() && !rest.contains(char::is_whitespace) => {
+                Action::Rename(tag(field)?, tag(rest)?)
+            }
+            "map" if !rest.is_empty() => {
+                let tag = tag(field)?;
+                let pairs = rest
+                    .split(',')
+                    .map(|pair| -> Result<_, TransformError> {
+                        let (from, to) = pair
+                            .split_once('=')
+                            .map(|(from, to)| (from.trim(), to.trim()))
+                            .filter(|(from, to)| !from.is_empty() && !to.is_empty())
+                            .ok_or_else(|| syntax(format!("expected FROM=TO, not {pair:?}")))?;
+                        Ok((
+                            dictionary.resolve_value(tag, from).to_string(),
+                            dictionary.resolve_value(tag, to).to_string(),
+                        ))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Action::Map(tag, pairs)
+            }
+            _ => {
+                return Err(syntax(format!(
+                    "expected set TAG VALUE, strip TAG, rename FROM TO or map TAG A=B, not {text:?}"
+                )))
+            }
+        };
+        Ok(action)
+    }
+
+    /// Apply to a message; whether it changed
+    fn apply(&self, msg: &mut Message) -> Result<bool, QuickFixError> {
+        match self {
+            Action::Set(tag, value) => {
+                if get(msg, *tag).as_ref() == Some(value) {
+                    return Ok(false);
+                }
+                set(msg, *tag, value)?;
+            }
+            Action::Strip(tag) => {
+                if get(msg, *tag).is_none() {
+                    return Ok(false);
+                }
+                remove(msg, *tag)?;
+            }
+            Action::Rename(from, to) => {
+                let Some(value) = get(msg, *from) else {
+                    return Ok(false);
+                };
+                remove(msg, *from)?;
+                set(msg, *to, &value)?;
+            }
+            Action::Map(tag, pairs) => {
+                let Some((_, to)) =
+                    get(msg, *tag).and_then(|value| pairs.iter().find(|(from, _)| *from == value))
+                else {
+                    return Ok(false);
+                };
+                set(msg, *tag, to)?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Set(tag, value) => write!(f, "set {tag} {value}"),
+            Action::Strip(tag) => write!(f, "strip {tag}"),
+            Action::Rename(from, to) => write!(f, "rename {from} {to}"),
+            Action::Map(tag, pairs) => {
+                let pairs: Vec<_> = pairs
+                    .iter()
+                    .map(|(from, to)| format!("{from}={to}"))
+                    .collect();
+                write!(f, "map {tag} {}", pairs.join(", "))
+            }
+        }
+    }
+}
+
+fn get(msg: &Message, tag: i32) -> Option<String> {
+    if HEADER_TAGS.contains(&tag) {
+        msg.with_header(|h| h.get_field(tag))
+    } else {
+        msg.get_field(tag)
+    }
+}
+
+fn set(msg: &mut Message, tag: i32, value: &str) -> Result<(), QuickFixError> {
+    if HEADER_TAGS.contains(&tag) {
+        msg.with_header_mut(|h| h.set_field(tag, value))
+    } else {
+        msg.set_field(tag, value)
+    }
+}
+
+fn remove(msg: &mut Message, tag: i32) -> Result<(), QuickFixError> {
+    if HEADER_TAGS.contains(&tag) {
+        msg.with_header_mut(|h| h.remove_field(tag))
+    } else {
+        msg.remove_field(tag)
+    }
+}
+
+// =============================================================================
+// Rules
+// =============================================================================
+
+/// A condition and what to do to the messages it matches
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformRule {
+    pub rule: Rule,
+    pub actions: Vec<Action>,
+}
+
+impl TransformRule {
+    /// Parse `NAME = CONDITION => ACTION; ACTION; ...`
+    pub fn parse_with(line: &str, dictionary: &Dictionary) -> Result<Self, TransformError> {
+        let (rule, actions) = line.split_once("=>").ok_or_else(|| {
+            TransformError::Syntax(0, "expected NAME = CONDITION => ACTION".into())
+        })?;
+        let actions = actions
+            .split(';')
+            .filter(|x| !x.trim().is_empty())
+            .map(|x| Action::parse_with(x, dictionary))
+            .collect::<Result<Vec<_>, _>>()?;
+        if actions.is_empty() {
+            return Err(TransformError::Syntax(0, "no action after =>".into()));
+        }
+        Ok(Self {
+            rule: Rule::parse_with(rule, dictionary).map_err(|err| TransformError::Expr(0, err))?,
+            actions,
+        })
+    }
+
+    pub fn parse(line: &str) -> Result<Self, TransformError> {
+        Self::parse_with(line, &Dictionary::standard())
+    }
+}
+
+impl fmt::Display for TransformRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let actions: Vec<_> = self.actions.iter().map(Action::to_string).collect();
+        write!(f, "{} => {}", self.rule, actions.join("; "))
+    }
+}
+
+/// Parse the rules of a file's text: one per line, blank lines and lines
+/// starting with `#` skipped
+pub fn parse_transforms(
+    text: &str,
+    dictionary: &Dictionary,
+) -> Result<Vec<TransformRule>, TransformError> {
+    let mut rules: Vec<TransformRule> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let rule = TransformRule::parse_with(line, dictionary).map_err(|err| match err {
+            TransformError::Syntax(_, message) => TransformError::Syntax(index + 1, message),
+            TransformError::Expr(_, err) => TransformError::Expr(index + 1, err),
+            err => err,
+        })?;
+        if rules.iter().any(|x| x.rule.name == rule.rule.name) {
+            return Err(TransformError::DuplicateRule(rule.rule.name));
+        }
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// Read and parse a rules file
+pub fn load_transforms(
+    path: &Path,
+    dictionary: &Dictionary,
+) -> Result<Vec<TransformRule>, TransformError> {
+    let text =
+        fs::read_to_string(path).map_err(|err| TransformError::Io(path.to_path_buf(), err))?;
+    parse_transforms(&text, dictionary)
+}
+
+// =============================================================================
+// Transformer
+// =============================================================================
+
+/// The rules of a file, applied to the messages of the callbacks
+#[derive(Debug, Clone, Default)]
+pub struct Transformer {
+    rules: Vec<TransformRule>,
+}
+
+impl Transformer {
+    pub fn new(rules: Vec<TransformRule>) -> Self {
+        Self { rules }
+    }
+
+    pub fn rules(&self) -> &[TransformRule] {
+        &self.rules
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Transform a message about to be sent, in place (on_msg_to_app); the
+    /// names of the rules that changed it
+    pub fn outbound(
+        &self,
+        msg: &mut Message,
+        session: &SessionId,
+    ) -> Result<Vec<&str>, QuickFixError> {
+        self.apply(msg, session, Direction::Outbound, 0)
+    }
+
+    /// A transformed copy of a message received (on_msg_from_app), None
+    /// when no rule matches it
+    pub fn inbound(
+        &self,
+        msg: &Message,
+        session: &SessionId,
+    ) -> Result<Option<Message>, QuickFixError> {
+        if self.rules.is_empty() {
+            return Ok(None);
+        }
+        let decoded = FixMessage::decode(session, Direction::Inbound, false, msg);
+        let Some(first) = self
+            .rules
+            .iter()
+            .position(|x| x.rule.expr.matches(&decoded))
+        else {
+            return Ok(None);
+        };
+        let mut copy = Message::try_from_text(&msg.to_fix_string()?)?;
+        self.apply(&mut copy, session, Direction::Inbound, first)?;
+        Ok(Some(copy))
+    }
+
+    /// Run the rules from `first` on, each on the message as the ones
+    /// before it left it
+    fn apply(
+        &self,
+        msg: &mut Message,
+        session: &SessionId,
+        direction: Direction,
+        first: usize,
+    ) -> Result<Vec<&str>, QuickFixError> {
+        let mut applied = Vec::new();
+        let mut decoded = FixMessage::decode(session, direction, false, msg);
+        for transform in &self.rules[first..] {
+            if !transform.rule.expr.matches(&decoded) {
+                continue;
+            }
+            let mut changed = false;
+            for action in &transform.actions {
+                changed |= action.apply(msg)?;
+            }
+            if changed {
+                applied.push(transform.rule.name.as_str());
+                decoded = FixMessage::decode(session, direction, false, msg);
+            }
+        }
+        Ok(applied)
+    }
+}
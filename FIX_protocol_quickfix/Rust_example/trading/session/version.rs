@@ -0,0 +1,142 @@
+// =============================================================================
+// FIX Protocol Versions
+// =============================================================================
+// Up to FIX 4.4 the BeginString (8) names the version of the whole message.
+// From FIX 5.0 on, the session layer is FIXT.1.1 and the application version
+// travels separately:
+//
+//   FIX.4.4                     8=FIX.4.4
+//   FIX.5.0SP2                  8=FIXT.1.1, ApplVerID (1128) = 9 in the header
+//
+// A FIXT session agrees on a default application version at logon: each side
+// sends its DefaultApplVerID (1137) in the Logon, and QuickFIX takes ours from
+// the DefaultApplVerID setting, which is mandatory for FIXT.1.1 sessions.
+// Messages without ApplVerID are in the counterparty's default version.
+// =============================================================================
+
+use std::{fmt, str::FromStr};
+
+/// BeginString of every FIX 5.0+ session
+pub const FIXT_1_1: &str = "FIXT.1.1";
+
+/// A FIX application version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FixVersion {
+    Fix40,
+    Fix41,
+    Fix42,
+    Fix43,
+    Fix44,
+    Fix50,
+    Fix50Sp1,
+    Fix50Sp2,
+}
+
+impl FixVersion {
+    pub const ALL: [FixVersion; 8] = [
+        FixVersion::Fix40,
+        FixVersion::Fix41,
+        FixVersion::Fix42,
+        FixVersion::Fix43,
+        FixVersion::Fix44,
+        FixVersion::Fix50,
+        FixVersion::Fix50Sp1,
+        FixVersion::Fix50Sp2,
+    ];
+
+    /// Name as written in configs and BeginStrings: `FIX.4.4`, `FIX.5.0SP2`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FixVersion::Fix40 => "FIX.4.0",
+            FixVersion::Fix41 => "FIX.4.1",
+            FixVersion::Fix42 => "FIX.4.2",
+            FixVersion::Fix43 => "FIX.4.3",
+            FixVersion::Fix44 => "FIX.4.4",
+            FixVersion::Fix50 => "FIX.5.0",
+            FixVersion::Fix50Sp1 => "FIX.5.0SP1",
+            FixVersion::Fix50Sp2 => "FIX.5.0SP2",
+        }
+    }
+
+    /// ApplVerID (1128) / DefaultApplVerID (1137) value
+    pub fn appl_ver_id(self) -> &'static str {
+        match self {
+            FixVersion::Fix40 => "2",
+            FixVersion::Fix41 => "3",
+            FixVersion::Fix42 => "4",
+            FixVersion::Fix43 => "5",
+            FixVersion::Fix44 => "6",
+            FixVersion::Fix50 => "7",
+            FixVersion::Fix50Sp1 => "8",
+            FixVersion::Fix50Sp2 => "9",
+        }
+    }
+
+    /// Version from an ApplVerID value, or from a name (QuickFIX accepts both
+    /// in DefaultApplVerID)
+    pub fn from_appl_ver_id(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|x| x.appl_ver_id() == value)
+            .or_else(|| value.parse().ok())
+    }
+
+    /// Only carried over FIXT.1.1
+    pub fn needs_fixt(self) -> bool {
+        self >= FixVersion::Fix50
+    }
+
+    /// BeginString of a session carrying this version
+    pub fn begin_string(self) -> &'static str {
+        if self.needs_fixt() {
+            FIXT_1_1
+        } else {
+            self.as_str()
+        }
+    }
+
+    /// Can a message of this version go over a session with `begin_string`?
+    ///
+    /// FIXT.1.1 carries any version (ApplVerID says which); a FIX.4.x
+    /// session only its own.
+    pub fn fits(self, begin_string: &str) -> bool {
+        begin_string == FIXT_1_1 || begin_string == self.as_str()
+    }
+}
+
+impl fmt::Display for FixVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Not one of the names of [`FixVersion::as_str`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownVersion(pub String);
+
+impl fmt::Display for UnknownVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown FIX version: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownVersion {}
+
+impl FromStr for FixVersion {
+    type Err = UnknownVersion;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|x| x.as_str() == source)
+            .ok_or_else(|| UnknownVersion(source.to_string()))
+    }
+}
+
+/// Is `begin_string` one QuickFIX can run a session with?
+pub fn is_begin_string(begin_string: &str) -> bool {
+    begin_string == FIXT_1_1
+        || FixVersion::ALL
+            .into_iter()
+            .any(|x| !x.needs_fixt() && x.as_str() == begin_string)
+}
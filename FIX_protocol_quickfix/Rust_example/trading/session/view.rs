@@ -0,0 +1,246 @@
+// =============================================================================
+// Message Views
+// =============================================================================
+// get_field hands back every value as an Option<String>; each callback then
+// parses it, picks an error for a missing tag and another for a bad value,
+// and does it again for the next field. A MessageView does that once, by
+// FIX data type:
+//
+//   let view = MessageView::new(msg);
+//   let price = view.get_price(44)?;                  // Price
+//   let quantity = view.get_qty(38)?;                 // Qty
+//   let side = view.get_with(54, Side::from_fix)?;    // an enumeration
+//   let transact_time = view.get_utc_timestamp(60).optional()?;
+//   for leg in view.groups(555) { ... }               // NoLegs
+//
+// A getter fails with ViewError::Missing, Invalid or OutOfRange, which
+// convert into the MsgFromAppError of the callback (FieldNotFound,
+// IncorrectDataFormat, IncorrectTagValue), so `?` rejects the message the
+// way QuickFIX would.
+// `.optional()` turns a missing field into None, a malformed one staying
+// an error. Header fields are read with get_header; a group entry is a
+// MessageView too, its own groups included.
+// =============================================================================
+
+use std::{error::Error, fmt};
+
+use quickfix::{FieldMap, Group, Message, MsgFromAppError};
+
+use crate::time::{parse_utc_timestamp, Date};
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ViewError {
+    /// The tag is not in the message
+    Missing(i32),
+
+    /// The value is not of the type asked for
+    Invalid {
+        tag: i32,
+        value: String,
+
+        /// The FIX data type, or what the value should be
+        expected: &'static str,
+    },
+
+    /// Not one of the values accepted, for get_with
+    OutOfRange { tag: i32, value: String },
+}
+
+impl ViewError {
+    /// The tag the error is about
+    pub fn tag(&self) -> i32 {
+        match self {
+            ViewError::Missing(tag)
+            | ViewError::Invalid { tag, .. }
+            | ViewError::OutOfRange { tag, .. } => *tag,
+        }
+    }
+}
+
+impl fmt::Display for ViewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViewError::Missing(tag) => write!(f, "tag {tag} missing"),
+            ViewError::Invalid {
+                tag,
+                value,
+                expected,
+            } => write!(f, "tag {tag}: {value:?} is not a valid {expected}"),
+            ViewError::OutOfRange { tag, value } => {
+                write!(f, "tag {tag}: {value:?} is not a value accepted")
+            }
+        }
+    }
+}
+
+impl Error for ViewError {}
+
+impl From<ViewError> for MsgFromAppError {
+    fn from(err: ViewError) -> Self {
+        match err {
+            ViewError::Missing(_) => MsgFromAppError::FieldNotFound,
+            ViewError::Invalid { .. } => MsgFromAppError::IncorrectDataFormat,
+            ViewError::OutOfRange { .. } => MsgFromAppError::IncorrectTagValue,
+        }
+    }
+}
+
+/// `.optional()` on the result of a getter
+pub trait OptionalField<T> {
+    /// None if the field is missing; a malformed value is still an error
+    fn optional(self) -> Result<Option<T>, ViewError>;
+}
+
+impl<T> OptionalField<T> for Result<T, ViewError> {
+    fn optional(self) -> Result<Option<T>, ViewError> {
+        match self {
+            Ok(value) => Ok(Some(value)),
+            Err(ViewError::Missing(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+// =============================================================================
+// View
+// =============================================================================
+
+enum Fields<'a> {
+    Message(&'a Message),
+    Group(Group),
+}
+
+/// Typed getters over the fields of a message, or of one group entry
+pub struct MessageView<'a> {
+    fields: Fields<'a>,
+}
+
+impl<'a> MessageView<'a> {
+    pub fn new(msg: &'a Message) -> Self {
+        Self {
+            fields: Fields::Message(msg),
+        }
+    }
+
+    /// The value as sent, if present
+    pub fn get_raw(&self, tag: i32) -> Option<String> {
+        match &self.fields {
+            Fields::Message(msg) => msg.get_field(tag),
+            Fields::Group(group) => group.get_field(tag),
+        }
+    }
+
+    /// A header field of the message (None in a group entry)
+    pub fn get_header(&self, tag: i32) -> Result<String, ViewError> {
+        let value = match &self.fields {
+            Fields::Message(msg) => msg.with_header(|h| h.get_field(tag)),
+            Fields::Group(_) => None,
+        };
+        value.ok_or(ViewError::Missing(tag))
+    }
+
+    /// MsgType (35)
+    pub fn msg_type(&self) -> Result<String, ViewError> {
+        self.get_header(35)
+    }
+
+    /// String, char sequences such as ClOrdID (11) or Symbol (55)
+    pub fn get_str(&self, tag: i32) -> Result<String, ViewError> {
+        self.get_raw(tag).ok_or(ViewError::Missing(tag))
+    }
+
+    /// A value converted by `parse`, typically an enumeration's from_fix
+    pub fn get_with<T>(
+        &self,
+        tag: i32,
+        parse: impl FnOnce(&str) -> Option<T>,
+    ) -> Result<T, ViewError> {
+        let value = self.get_str(tag)?;
+        parse(&value).ok_or(ViewError::OutOfRange { tag, value })
+    }
+
+    /// Price, PriceOffset and Amt fields: a finite decimal
+    pub fn get_price(&self, tag: i32) -> Result<f64, ViewError> {
+        self.parse(tag, "price", |x| {
+            x.parse().ok().filter(|x: &f64| x.is_finite())
+        })
+    }
+
+    /// Qty fields: a finite decimal, not negative
+    pub fn get_qty(&self, tag: i32) -> Result<f64, ViewError> {
+        self.parse(tag, "quantity", |x| {
+            x.parse().ok().filter(|x: &f64| x.is_finite() && *x >= 0.0)
+        })
+    }
+
+    /// int, SeqNum, NumInGroup and Length fields
+    pub fn get_int(&self, tag: i32) -> Result<i64, ViewError> {
+        self.parse(tag, "int", |x| x.parse().ok())
+    }
+
+    /// char fields, such as Side (54) or OrdType (40)
+    pub fn get_char(&self, tag: i32) -> Result<char, ViewError> {
+        self.parse(tag, "char", |x| {
+            let mut chars = x.chars();
+            chars.next().filter(|_| chars.next().is_none())
+        })
+    }
+
+    /// Boolean fields: `Y` or `N`
+    pub fn get_bool(&self, tag: i32) -> Result<bool, ViewError> {
+        self.parse(tag, "Boolean", |x| match x {
+            "Y" => Some(true),
+            "N" => Some(false),
+            _ => None,
+        })
+    }
+
+    /// UTCTimestamp fields, in nanoseconds since the Unix epoch
+    pub fn get_utc_timestamp(&self, tag: i32) -> Result<i64, ViewError> {
+        self.parse(tag, "UTCTimestamp", parse_utc_timestamp)
+    }
+
+    /// LocalMktDate and UTCDateOnly fields (`YYYYMMDD`)
+    pub fn get_date(&self, tag: i32) -> Result<Date, ViewError> {
+        self.parse(tag, "date", Date::from_fix)
+    }
+
+    /// Entries of a repeating group, by its NumInGroup tag
+    ///
+    /// As many entries as the count says; none when the count is missing
+    /// or not a number.
+    pub fn groups(&self, count_tag: i32) -> impl Iterator<Item = MessageView<'a>> + '_ {
+        let count = self
+            .get_int(count_tag)
+            .unwrap_or(0)
+            .clamp(0, i64::from(i32::MAX)) as i32;
+        (1..=count).filter_map(move |index| {
+            let group = match &self.fields {
+                Fields::Message(msg) => msg.clone_group(index, count_tag),
+                Fields::Group(group) => group.clone_group(index, count_tag),
+            };
+            group.map(|group| MessageView {
+                fields: Fields::Group(group),
+            })
+        })
+    }
+
+    fn parse<T>(
+        &self,
+        tag: i32,
+        expected: &'static str,
+        parse: impl FnOnce(&str) -> Option<T>,
+    ) -> Result<T, ViewError> {
+        let value = self.get_str(tag)?;
+        parse(&value).ok_or(ViewError::Invalid {
+            tag,
+            value,
+            expected,
+        })
+    }
+}
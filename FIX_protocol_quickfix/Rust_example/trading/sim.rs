@@ -0,0 +1,15 @@
+// =============================================================================
+// Venue Simulator
+// =============================================================================
+// The matching side of an exchange, without FIX: price-time priority books
+// parameterized by venue trading rules. The sell_side example wraps it in an
+// acceptor; tests can drive it directly. The inbound throttle protects the
+// acceptor from clients flooding it, with limits from the venue profile.
+// The paper engine fills a client's own orders against live quotes instead,
+// for paper trading.
+// =============================================================================
+
+pub mod matching;
+pub mod paper;
+pub mod throttle;
+pub mod venue;
@@ -0,0 +1,439 @@
+// =============================================================================
+// Matching Engine
+// =============================================================================
+// Price-time priority limit order books, one per symbol, in the spirit of the
+// C++ and Java ordermatch examples of this repository:
+//
+//   bids: BTreeMap<price, FIFO queue>  best = highest price
+//   asks: BTreeMap<price, FIFO queue>  best = lowest price
+//
+// Prices are kept as integer ticks (see VenueProfile) so that levels compare
+// exactly. The engine knows nothing about FIX: it returns ExecEvents which the
+// application layer turns into ExecutionReports.
+//
+// Both sides of a trade carry the same match id. When a trade is busted or
+// corrected after the fact, `amend_fill` fixes up the order if it still
+// rests: the busted quantity is not put back in the market, so OrderQty moves
+// with CumQty and LeavesQty stays the same.
+// =============================================================================
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use crate::sim::venue::VenueProfile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn from_fix(value: &str) -> Option<Self> {
+        match value {
+            "1" => Some(Side::Buy),
+            "2" => Some(Side::Sell),
+            _ => None,
+        }
+    }
+
+    pub fn as_fix(self) -> &'static str {
+        match self {
+            Side::Buy => "1",
+            Side::Sell => "2",
+        }
+    }
+}
+
+/// Order as held by the engine
+#[derive(Debug, Clone)]
+pub struct BookOrder {
+    pub order_id: String,
+    pub cl_ord_id: String,
+
+    /// CompID of the counterparty that sent the order
+    pub owner: String,
+    pub symbol: String,
+    pub side: Side,
+    pub price_ticks: i64,
+    pub quantity: u64,
+    pub cum_qty: u64,
+
+    /// Sum of qty * price of all fills, for AvgPx
+    pub filled_notional: f64,
+}
+
+impl BookOrder {
+    pub fn leaves_qty(&self) -> u64 {
+        self.quantity - self.cum_qty
+    }
+
+    pub fn avg_px(&self) -> f64 {
+        if self.cum_qty == 0 {
+            0.0
+        } else {
+            self.filled_notional / self.cum_qty as f64
+        }
+    }
+}
+
+/// What happened to an order, from the owner's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecKind {
+    New,
+    Trade,
+    Canceled,
+    Rejected,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecEvent {
+    pub kind: ExecKind,
+    pub order: BookOrder,
+    pub last_qty: u64,
+    pub last_px: f64,
+
+    /// Set on cancels: ClOrdID of the cancel request
+    pub cancel_cl_ord_id: Option<String>,
+
+    /// Set on trades: owner of the other side (used by surveillance)
+    pub counterparty: Option<String>,
+
+    /// Set on trades: shared by the two sides of one match
+    pub match_id: Option<String>,
+
+    pub text: Option<String>,
+}
+
+impl ExecEvent {
+    fn new(kind: ExecKind, order: &BookOrder) -> Self {
+        Self {
+            kind,
+            order: order.clone(),
+            last_qty: 0,
+            last_px: 0.0,
+            cancel_cl_ord_id: None,
+            counterparty: None,
+            match_id: None,
+            text: None,
+        }
+    }
+
+    fn rejected(order: &BookOrder, reason: &str) -> Self {
+        Self {
+            text: Some(reason.to_string()),
+            ..Self::new(ExecKind::Rejected, order)
+        }
+    }
+}
+
+/// Incoming order request, already decoded from FIX
+#[derive(Debug, Clone)]
+pub struct NewOrder {
+    pub cl_ord_id: String,
+    pub owner: String,
+    pub symbol: String,
+    pub side: Side,
+    pub price: f64,
+    pub quantity: f64,
+}
+
+// =============================================================================
+// Order Book (one symbol)
+// =============================================================================
+
+#[derive(Default)]
+pub struct OrderBook {
+    bids: BTreeMap<i64, VecDeque<BookOrder>>,
+    asks: BTreeMap<i64, VecDeque<BookOrder>>,
+    last_trade: Option<i64>,
+}
+
+impl OrderBook {
+    pub fn best_bid(&self) -> Option<(i64, u64)> {
+        self.bids
+            .iter()
+            .next_back()
+            .map(|(px, level)| (*px, level.iter().map(BookOrder::leaves_qty).sum()))
+    }
+
+    pub fn best_ask(&self) -> Option<(i64, u64)> {
+        self.asks
+            .iter()
+            .next()
+            .map(|(px, level)| (*px, level.iter().map(BookOrder::leaves_qty).sum()))
+    }
+
+    pub fn last_trade(&self) -> Option<i64> {
+        self.last_trade
+    }
+
+    /// Resting orders, bids then asks, in priority order
+    pub fn resting(&self) -> impl Iterator<Item = &BookOrder> {
+        self.bids.values().rev().chain(self.asks.values()).flatten()
+    }
+
+    /// Match an incoming order, then rest the remainder
+    ///
+    /// # Arguments
+    /// * `next_match_id` - Engine-wide counter of match ids
+    fn execute(
+        &mut self,
+        mut order: BookOrder,
+        profile: &VenueProfile,
+        next_match_id: &mut u64,
+    ) -> Vec<ExecEvent> {
+        let mut events = vec![ExecEvent::new(ExecKind::New, &order)];
+
+        loop {
+            if order.leaves_qty() == 0 {
+                break;
+            }
+
+            // Best opposite level, if it crosses
+            let opposite = match order.side {
+                Side::Buy => self
+                    .asks
+                    .first_entry()
+                    .filter(|x| *x.key() <= order.price_ticks),
+                Side::Sell => self
+                    .bids
+                    .last_entry()
+                    .filter(|x| *x.key() >= order.price_ticks),
+            };
+            let Some(mut level) = opposite else {
+                break;
+            };
+
+            let price_ticks = *level.key();
+            let price = profile.to_price(price_ticks);
+            let resting = level.get_mut().front_mut().expect("empty price level");
+
+            let quantity = order.leaves_qty().min(resting.leaves_qty());
+            for side in [&mut order, &mut *resting] {
+                side.cum_qty += quantity;
+                side.filled_notional += quantity as f64 * price;
+            }
+
+            let match_id = format!("M{next_match_id}");
+            *next_match_id += 1;
+
+            let mut aggressor_event = ExecEvent::new(ExecKind::Trade, &order);
+            aggressor_event.last_qty = quantity;
+            aggressor_event.last_px = price;
+            aggressor_event.counterparty = Some(resting.owner.clone());
+            aggressor_event.match_id = Some(match_id.clone());
+
+            let mut resting_event = ExecEvent::new(ExecKind::Trade, resting);
+            resting_event.last_qty = quantity;
+            resting_event.last_px = price;
+            resting_event.counterparty = Some(order.owner.clone());
+            resting_event.match_id = Some(match_id);
+
+            events.push(aggressor_event);
+            events.push(resting_event);
+            self.last_trade = Some(price_ticks);
+
+            if resting.leaves_qty() == 0 {
+                level.get_mut().pop_front();
+                if level.get().is_empty() {
+                    level.remove();
+                }
+            }
+        }
+
+        if order.leaves_qty() > 0 {
+            let book = match order.side {
+                Side::Buy => &mut self.bids,
+                Side::Sell => &mut self.asks,
+            };
+            book.entry(order.price_ticks).or_default().push_back(order);
+        }
+
+        events
+    }
+
+    fn resting_mut(&mut self, order_id: &str) -> Option<&mut BookOrder> {
+        self.bids
+            .values_mut()
+            .chain(self.asks.values_mut())
+            .flatten()
+            .find(|x| x.order_id == order_id)
+    }
+
+    /// Remove a resting order by ClOrdID and owner
+    fn cancel(&mut self, cl_ord_id: &str, owner: &str) -> Option<BookOrder> {
+        for book in [&mut self.bids, &mut self.asks] {
+            let mut found = None;
+            for (price, level) in book.iter_mut() {
+                if let Some(index) = level
+                    .iter()
+                    .position(|x| x.cl_ord_id == cl_ord_id && x.owner == owner)
+                {
+                    found = Some((*price, level.remove(index).expect("index in range")));
+                    break;
+                }
+            }
+            if let Some((price, order)) = found {
+                if book.get(&price).is_some_and(VecDeque::is_empty) {
+                    book.remove(&price);
+                }
+                return Some(order);
+            }
+        }
+        None
+    }
+}
+
+// =============================================================================
+// Matching Engine (all symbols)
+// =============================================================================
+
+pub struct MatchingEngine {
+    profile: VenueProfile,
+    books: HashMap<String, OrderBook>,
+    halted: HashSet<String>,
+    next_order_id: u64,
+    next_match_id: u64,
+}
+
+impl MatchingEngine {
+    pub fn new(profile: VenueProfile) -> Self {
+        Self {
+            profile,
+            books: HashMap::new(),
+            halted: HashSet::new(),
+            next_order_id: 1,
+            next_match_id: 1,
+        }
+    }
+
+    pub fn profile(&self) -> &VenueProfile {
+        &self.profile
+    }
+
+    pub fn book(&self, symbol: &str) -> Option<&OrderBook> {
+        self.books.get(symbol)
+    }
+
+    pub fn symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<_> = self.books.keys().cloned().collect();
+        symbols.sort();
+        symbols
+    }
+
+    /// Resting orders of a counterparty, on one symbol or on every symbol
+    /// in alphabetical order
+    pub fn open_orders(&self, owner: &str, symbol: Option<&str>) -> Vec<BookOrder> {
+        let symbols = match symbol {
+            Some(symbol) => vec![symbol.to_string()],
+            None => self.symbols(),
+        };
+        symbols
+            .iter()
+            .filter_map(|x| self.books.get(x))
+            .flat_map(OrderBook::resting)
+            .filter(|x| x.owner == owner)
+            .cloned()
+            .collect()
+    }
+
+    pub fn halt(&mut self, symbol: &str) {
+        self.halted.insert(symbol.to_string());
+    }
+
+    pub fn resume(&mut self, symbol: &str) {
+        self.halted.remove(symbol);
+    }
+
+    pub fn is_halted(&self, symbol: &str) -> bool {
+        self.halted.contains(symbol)
+    }
+
+    /// Validate against the venue profile, then match
+    pub fn submit(&mut self, request: NewOrder) -> Vec<ExecEvent> {
+        let order_id = format!("O{}", self.next_order_id);
+        self.next_order_id += 1;
+
+        let mut order = BookOrder {
+            order_id,
+            cl_ord_id: request.cl_ord_id,
+            owner: request.owner,
+            symbol: request.symbol,
+            side: request.side,
+            price_ticks: 0,
+            quantity: request.quantity.max(0.0) as u64,
+            cum_qty: 0,
+            filled_notional: 0.0,
+        };
+
+        if self.is_halted(&order.symbol) {
+            return vec![ExecEvent::rejected(&order, "symbol halted")];
+        }
+        if request.quantity.fract() != 0.0 || order.quantity == 0 {
+            return vec![ExecEvent::rejected(&order, "invalid quantity")];
+        }
+        if order.quantity.checked_rem(self.profile.lot_size) != Some(0) {
+            return vec![ExecEvent::rejected(
+                &order,
+                "quantity not a multiple of lot size",
+            )];
+        }
+        if order.quantity > self.profile.max_order_qty {
+            return vec![ExecEvent::rejected(&order, "quantity above venue maximum")];
+        }
+        let Some(price_ticks) = self.profile.to_ticks(request.price).filter(|x| *x > 0) else {
+            return vec![ExecEvent::rejected(&order, "price not on tick grid")];
+        };
+        order.price_ticks = price_ticks;
+
+        let book = self.books.entry(order.symbol.clone()).or_default();
+        if let Some(last) = book.last_trade {
+            let deviation = (price_ticks - last).abs() as f64 / last as f64;
+            if deviation > self.profile.price_band {
+                return vec![ExecEvent::rejected(&order, "price outside band")];
+            }
+        }
+
+        book.execute(order, &self.profile, &mut self.next_match_id)
+    }
+
+    /// Take back (`new` None) or change an earlier fill of an order
+    ///
+    /// # Arguments
+    /// * `old` - Quantity and price of the fill as it was
+    /// * `new` - Quantity and price it is corrected to
+    ///
+    /// # Returns
+    /// The order after the change, or None if it no longer rests in the book
+    pub fn amend_fill(
+        &mut self,
+        symbol: &str,
+        order_id: &str,
+        old: (u64, f64),
+        new: Option<(u64, f64)>,
+    ) -> Option<BookOrder> {
+        let order = self.books.get_mut(symbol)?.resting_mut(order_id)?;
+        let (new_qty, new_px) = new.unwrap_or((0, 0.0));
+        order.quantity = order.quantity - old.0 + new_qty;
+        order.cum_qty = order.cum_qty - old.0 + new_qty;
+        order.filled_notional += new_qty as f64 * new_px - old.0 as f64 * old.1;
+        Some(order.clone())
+    }
+
+    /// Cancel a resting order
+    ///
+    /// # Returns
+    /// The Canceled event, or None if the order is unknown / already done
+    pub fn cancel(
+        &mut self,
+        symbol: &str,
+        orig_cl_ord_id: &str,
+        cancel_cl_ord_id: &str,
+        owner: &str,
+    ) -> Option<ExecEvent> {
+        let order = self.books.get_mut(symbol)?.cancel(orig_cl_ord_id, owner)?;
+        let mut event = ExecEvent::new(ExecKind::Canceled, &order);
+        event.cancel_cl_ord_id = Some(cancel_cl_ord_id.to_string());
+        Some(event)
+    }
+}
@@ -0,0 +1,488 @@
+// =============================================================================
+// Paper Trading
+// =============================================================================
+// A strategy can trade live market data without a single order reaching the
+// venue: the orders are kept here instead of being sent, and filled against
+// the quotes the market data session streams, the way a venue would:
+//
+//   35=D    ->  150=0 39=0  New, then filled at once if the quote crosses
+//   35=F    ->  150=4 39=4  Canceled; 35=9 if filled already or unknown
+//   35=G    ->  150=5       Replaced, then filled if the new price crosses
+//   35=W/X  ->  150=F 39=1/2 for each resting order the new quote crosses
+//
+// A buy fills at the offer, a sell at the bid: a market order as soon as
+// there is one, a limit order once the touch reaches its price. A fill is at
+// most the size quoted, which is then used up until the next quote for the
+// symbol; nobody is ahead in the queue, so passive fills are optimistic.
+//
+// The reports are inbound FixMessages that the OMS applies like real ones,
+// with ExecID (17) and OrderID (37) prefixed PAPER- and Text (58) starting
+// with PAPER. Other message types get no answer.
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use quickfix::{FieldMap, Message};
+
+use crate::{
+    session::{
+        events::{group_field, FixMessage},
+        Direction,
+    },
+    sim::matching::Side,
+};
+
+/// Text (58) of every report made up, or its prefix when it says why
+pub const PAPER_TEXT: &str = "PAPER";
+
+/// Whether a message was made up by a PaperEngine rather than received
+pub fn is_paper(msg: &FixMessage) -> bool {
+    msg.get(58).is_some_and(|x| x.starts_with(PAPER_TEXT))
+}
+
+/// One side of the touch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Level {
+    pub price: f64,
+
+    /// MDEntrySize (271), what is left of it; None if not quoted
+    pub size: Option<f64>,
+}
+
+/// Best bid and offer of a symbol
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Touch {
+    pub bid: Option<Level>,
+    pub ask: Option<Level>,
+}
+
+/// An order as the engine holds it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaperOrder {
+    /// Label of the session the order was meant for
+    pub session: String,
+    pub order_id: String,
+
+    /// ClOrdID of the order, or of its last replace
+    pub cl_ord_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+
+    /// None for a market order
+    pub price: Option<f64>,
+    pub cum_qty: f64,
+
+    /// Sum of qty * price of the fills, for AvgPx
+    pub filled_notional: f64,
+    pub canceled: bool,
+}
+
+impl PaperOrder {
+    pub fn leaves_qty(&self) -> f64 {
+        if self.canceled {
+            0.0
+        } else {
+            (self.quantity - self.cum_qty).max(0.0)
+        }
+    }
+
+    pub fn avg_px(&self) -> f64 {
+        if self.cum_qty > 0.0 {
+            self.filled_notional / self.cum_qty
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_working(&self) -> bool {
+        self.leaves_qty() > 0.0
+    }
+
+    /// OrdStatus (39)
+    pub fn ord_status(&self) -> &'static str {
+        if self.canceled {
+            "4"
+        } else if self.leaves_qty() == 0.0 {
+            "2"
+        } else if self.cum_qty > 0.0 {
+            "1"
+        } else {
+            "0"
+        }
+    }
+
+    /// Fill what the touch crosses, using up its size
+    ///
+    /// # Returns
+    /// LastQty and LastPx, or None if the touch does not cross
+    fn fill(&mut self, touch: &mut Touch) -> Option<(f64, f64)> {
+        let level = match self.side {
+            Side::Buy => touch.ask.as_mut()?,
+            Side::Sell => touch.bid.as_mut()?,
+        };
+        let crosses = match (self.side, self.price) {
+            (_, None) => true,
+            (Side::Buy, Some(limit)) => level.price <= limit,
+            (Side::Sell, Some(limit)) => level.price >= limit,
+        };
+        let leaves_qty = self.leaves_qty();
+        let quantity = level.size.map_or(leaves_qty, |size| size.min(leaves_qty));
+        if !crosses || quantity <= 0.0 {
+            return None;
+        }
+
+        if let Some(size) = &mut level.size {
+            *size -= quantity;
+        }
+        self.cum_qty += quantity;
+        self.filled_notional += quantity * level.price;
+        Some((quantity, level.price))
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    touches: HashMap<String, Touch>,
+
+    /// Every order, oldest first: the first to fill when a quote crosses
+    orders: Vec<PaperOrder>,
+
+    /// For ExecIDs
+    next_id: u64,
+}
+
+/// Orders kept instead of sent, filled against the live quotes
+#[derive(Debug, Default)]
+pub struct PaperEngine {
+    state: Mutex<State>,
+}
+
+impl PaperEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The last touch of a symbol, what fills used of it deducted
+    pub fn touch(&self, symbol: &str) -> Option<Touch> {
+        self.lock().touches.get(symbol).copied()
+    }
+
+    /// Orders still working, oldest first
+    pub fn working_orders(&self) -> Vec<PaperOrder> {
+        let state = self.lock();
+        state
+            .orders
+            .iter()
+            .filter(|x| x.is_working())
+            .cloned()
+            .collect()
+    }
+
+    /// Take an order, cancel or replace instead of the venue
+    ///
+    /// # Arguments
+    /// * `label` - Session the message was meant for
+    ///
+    /// # Returns
+    /// The inbound reports the venue would have sent, in order: the
+    /// acknowledgement, then any fill
+    pub fn on_order_message(&self, label: &str, msg: &Message) -> Vec<FixMessage> {
+        let msg_type = msg.with_header(|h| h.get_field(35)).unwrap_or_default();
+        let mut state = self.lock();
+        match msg_type.as_str() {
+            "D" => state.new_order(label, msg),
+            "F" => state.cancel(label, msg),
+            "G" => state.replace(label, msg),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Take the touch of a snapshot (35=W) or incremental refresh (35=X),
+    /// then fill the orders it crosses
+    ///
+    /// # Returns
+    /// The fills, as inbound ExecutionReports
+    pub fn on_market_data(&self, msg: &FixMessage) -> Vec<FixMessage> {
+        let snapshot = match msg.msg_type() {
+            "W" => true,
+            "X" => false,
+            _ => return Vec::new(),
+        };
+
+        let mut state = self.lock();
+        let mut updated: Vec<String> = Vec::new();
+        for group in msg.groups(269) {
+            let Some(symbol) = group_field(group, 55).or_else(|| msg.get(55)) else {
+                continue;
+            };
+            let first = !updated.iter().any(|x| x == symbol);
+            if first {
+                updated.push(symbol.to_string());
+            }
+            let touch = state.touches.entry(symbol.to_string()).or_default();
+            if snapshot && first {
+                *touch = Touch::default();
+            }
+
+            let side = match group_field(group, 269) {
+                Some("0") => &mut touch.bid,
+                Some("1") => &mut touch.ask,
+                _ => continue,
+            };
+            let number = |tag| group_field(group, tag).and_then(|x| x.parse::<f64>().ok());
+            // MDUpdateAction (279) 2: delete
+            if group_field(group, 279) == Some("2") {
+                *side = None;
+            } else if let Some(price) = number(270) {
+                *side = Some(Level {
+                    price,
+                    size: number(271),
+                });
+            }
+        }
+
+        let mut reports = Vec::new();
+        for symbol in updated {
+            reports.extend(state.match_symbol(&symbol));
+        }
+        reports
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("paper engine lock poisoned")
+    }
+}
+
+impl State {
+    fn new_order(&mut self, label: &str, msg: &Message) -> Vec<FixMessage> {
+        let cl_ord_id = msg.get_field(11).unwrap_or_default();
+        let number = |tag| msg.get_field(tag).and_then(|x| x.parse::<f64>().ok());
+        let market = msg.get_field(40).as_deref() == Some("1");
+        let side = msg.get_field(54).as_deref().and_then(Side::from_fix);
+        let quantity = number(38).filter(|x| *x > 0.0);
+        let price = if market { None } else { number(44) };
+        let problem = match (side, quantity) {
+            (None, _) => Some("unsupported side"),
+            (_, None) => Some("no quantity"),
+            _ if !market && price.is_none() => Some("no limit price"),
+            _ => None,
+        };
+
+        let mut order = PaperOrder {
+            session: label.to_string(),
+            order_id: format!("PAPER-{cl_ord_id}"),
+            cl_ord_id,
+            symbol: msg.get_field(55).unwrap_or_default(),
+            side: side.unwrap_or(Side::Buy),
+            quantity: quantity.unwrap_or(0.0),
+            price,
+            cum_qty: 0.0,
+            filled_notional: 0.0,
+            canceled: false,
+        };
+        if let Some(problem) = problem {
+            // Rejected: no quantity left working
+            order.canceled = true;
+            let text = format!("{PAPER_TEXT}: {problem}");
+            return vec![self.report(&order, "8", "8", None, None, &text)];
+        }
+
+        let mut reports = vec![self.report(&order, "0", order.ord_status(), None, None, "")];
+        let fill = self
+            .touches
+            .get_mut(&order.symbol)
+            .and_then(|touch| order.fill(touch));
+        if let Some(last) = fill {
+            reports.push(self.report(&order, "F", order.ord_status(), None, Some(last), ""));
+        }
+        self.orders.push(order);
+        reports
+    }
+
+    fn cancel(&mut self, label: &str, msg: &Message) -> Vec<FixMessage> {
+        let cl_ord_id = msg.get_field(11).unwrap_or_default();
+        let orig_cl_ord_id = msg.get_field(41).unwrap_or_default();
+        let Some(index) = self.find(&orig_cl_ord_id) else {
+            return vec![cancel_reject(label, "1", &cl_ord_id, &orig_cl_ord_id, None)];
+        };
+        if !self.orders[index].is_working() {
+            let order = &self.orders[index];
+            return vec![cancel_reject(
+                label,
+                "1",
+                &cl_ord_id,
+                &orig_cl_ord_id,
+                Some(order),
+            )];
+        }
+
+        self.orders[index].canceled = true;
+        let order = self.orders[index].clone();
+        vec![self.report(&order, "4", "4", Some(&cl_ord_id), None, "")]
+    }
+
+    fn replace(&mut self, label: &str, msg: &Message) -> Vec<FixMessage> {
+        let cl_ord_id = msg.get_field(11).unwrap_or_default();
+        let orig_cl_ord_id = msg.get_field(41).unwrap_or_default();
+        let Some(index) = self
+            .find(&orig_cl_ord_id)
+            .filter(|x| self.orders[*x].is_working())
+        else {
+            let order = self.find(&orig_cl_ord_id).map(|x| &self.orders[x]);
+            return vec![cancel_reject(
+                label,
+                "2",
+                &cl_ord_id,
+                &orig_cl_ord_id,
+                order,
+            )];
+        };
+
+        let number = |tag| msg.get_field(tag).and_then(|x| x.parse::<f64>().ok());
+        let mut order = self.orders[index].clone();
+        order.cl_ord_id = cl_ord_id.clone();
+        if let Some(quantity) = number(38) {
+            order.quantity = quantity.max(order.cum_qty);
+        }
+        if order.price.is_some() {
+            order.price = number(44).or(order.price);
+        }
+
+        let mut reports =
+            vec![self.report(&order, "5", order.ord_status(), Some(&cl_ord_id), None, "")];
+        // The replace report names the old ClOrdID in 41, the fills only the
+        // new one
+        let fill = self
+            .touches
+            .get_mut(&order.symbol)
+            .and_then(|touch| order.fill(touch));
+        if let Some(last) = fill {
+            reports.push(self.report(&order, "F", order.ord_status(), None, Some(last), ""));
+        }
+        self.orders[index] = order;
+        reports
+    }
+
+    /// Fill the working orders of a symbol, oldest first, against its touch
+    fn match_symbol(&mut self, symbol: &str) -> Vec<FixMessage> {
+        let Some(mut touch) = self.touches.get(symbol).copied() else {
+            return Vec::new();
+        };
+        let mut filled = Vec::new();
+        for order in &mut self.orders {
+            if order.symbol != symbol || !order.is_working() {
+                continue;
+            }
+            if let Some(last) = order.fill(&mut touch) {
+                filled.push((order.clone(), last));
+            }
+        }
+        self.touches.insert(symbol.to_string(), touch);
+
+        filled
+            .into_iter()
+            .map(|(order, last)| self.report(&order, "F", order.ord_status(), None, Some(last), ""))
+            .collect()
+    }
+
+    /// Index of the order whose current ClOrdID this is
+    fn find(&self, cl_ord_id: &str) -> Option<usize> {
+        self.orders.iter().position(|x| x.cl_ord_id == cl_ord_id)
+    }
+
+    /// An ExecutionReport (35=8) on an order
+    ///
+    /// # Arguments
+    /// * `request` - ClOrdID of the cancel or replace, the order's going in
+    ///   OrigClOrdID (41)
+    /// * `last` - LastQty and LastPx of a fill
+    /// * `text` - Text (58), PAPER when empty
+    fn report(
+        &mut self,
+        order: &PaperOrder,
+        exec_type: &str,
+        ord_status: &str,
+        request: Option<&str>,
+        last: Option<(f64, f64)>,
+        text: &str,
+    ) -> FixMessage {
+        self.next_id += 1;
+        let cl_ord_id = request.unwrap_or(&order.cl_ord_id);
+        let mut fields = vec![
+            (35, "8".to_string()),
+            (37, order.order_id.clone()),
+            (11, cl_ord_id.to_string()),
+        ];
+        if request.is_some() {
+            fields.push((41, order.cl_ord_id.clone()));
+        }
+        fields.extend([
+            (17, format!("PAPER-{}", self.next_id)),
+            (150, exec_type.to_string()),
+            (39, ord_status.to_string()),
+            (55, order.symbol.clone()),
+            (54, order.side.as_fix().to_string()),
+            (38, order.quantity.to_string()),
+        ]);
+        if let Some(price) = order.price {
+            fields.push((44, price.to_string()));
+        }
+        if let Some((last_qty, last_px)) = last {
+            fields.push((32, last_qty.to_string()));
+            fields.push((31, last_px.to_string()));
+        }
+        fields.extend([
+            (151, order.leaves_qty().to_string()),
+            (14, order.cum_qty.to_string()),
+            (6, order.avg_px().to_string()),
+            (
+                58,
+                if text.is_empty() { PAPER_TEXT } else { text }.to_string(),
+            ),
+        ]);
+        inbound(&order.session, fields)
+    }
+}
+
+/// An OrderCancelReject (35=9): the order is done, or was never taken
+///
+/// # Arguments
+/// * `response_to` - CxlRejResponseTo (434): `1` cancel, `2` replace
+fn cancel_reject(
+    label: &str,
+    response_to: &str,
+    cl_ord_id: &str,
+    orig_cl_ord_id: &str,
+    order: Option<&PaperOrder>,
+) -> FixMessage {
+    let (order_id, ord_status, reason, text) = match order {
+        Some(order) => (order.order_id.clone(), order.ord_status(), "0", "too late"),
+        None => (format!("PAPER-{orig_cl_ord_id}"), "8", "1", "unknown order"),
+    };
+    inbound(
+        label,
+        vec![
+            (35, "9".to_string()),
+            (37, order_id),
+            (11, cl_ord_id.to_string()),
+            (41, orig_cl_ord_id.to_string()),
+            (39, ord_status.to_string()),
+            (434, response_to.to_string()),
+            (102, reason.to_string()), // CxlRejReason: too late, unknown order
+            (58, format!("{PAPER_TEXT}: {text}")),
+        ],
+    )
+}
+
+fn inbound(session: &str, fields: Vec<(i32, String)>) -> FixMessage {
+    FixMessage {
+        session: session.to_string(),
+        direction: Direction::Inbound,
+        admin: false,
+        fields,
+    }
+}
@@ -0,0 +1,179 @@
+// =============================================================================
+// Inbound Throttle
+// =============================================================================
+// A venue cannot let one misbehaving client (a stuck loop, a replay gone
+// wrong) flood it at the expense of everyone else. Every inbound message is
+// counted per session over a fixed window, and the count escalates through
+// levels set by the venue profile:
+//
+//   Normal       processed
+//   Warn         processed, logged once per window
+//   Throttle     processed after a delay, which pushes back on the client
+//   Disconnect   dropped; the session is logged out
+//
+// A window starts afresh at Normal, except after Disconnect, which lasts
+// until the session is reset (on logout).
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Escalation level, in increasing order of severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ThrottleLevel {
+    Normal,
+    Warn,
+    Throttle,
+    Disconnect,
+}
+
+impl ThrottleLevel {
+    /// Lower-case name used in metrics and JSON
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ThrottleLevel::Normal => "normal",
+            ThrottleLevel::Warn => "warn",
+            ThrottleLevel::Throttle => "throttle",
+            ThrottleLevel::Disconnect => "disconnect",
+        }
+    }
+}
+
+/// Message counts per window at which each level starts
+#[derive(Debug, Clone)]
+pub struct ThrottlePolicy {
+    pub window: Duration,
+    pub warn_at: u32,
+    pub throttle_at: u32,
+    pub disconnect_at: u32,
+
+    /// How long each message over `throttle_at` waits
+    pub delay: Duration,
+}
+
+impl ThrottlePolicy {
+    /// Per second: warn, throttle and disconnect thresholds
+    pub fn per_second(warn_at: u32, throttle_at: u32, disconnect_at: u32) -> Self {
+        Self {
+            window: Duration::from_secs(1),
+            warn_at,
+            throttle_at,
+            disconnect_at,
+            delay: Duration::from_millis(10),
+        }
+    }
+
+    /// No limit at all
+    pub fn unlimited() -> Self {
+        Self::per_second(u32::MAX, u32::MAX, u32::MAX)
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Level of the `count`th message of a window
+    pub fn level(&self, count: u32) -> ThrottleLevel {
+        if count >= self.disconnect_at {
+            ThrottleLevel::Disconnect
+        } else if count >= self.throttle_at {
+            ThrottleLevel::Throttle
+        } else if count >= self.warn_at {
+            ThrottleLevel::Warn
+        } else {
+            ThrottleLevel::Normal
+        }
+    }
+}
+
+/// What to do with one inbound message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThrottleVerdict {
+    pub level: ThrottleLevel,
+
+    /// The level was reached with this message: log it, act on it once
+    pub escalated: bool,
+
+    /// Messages so far in the window, this one included
+    pub count: u32,
+}
+
+#[derive(Debug)]
+struct Window {
+    started: Instant,
+    count: u32,
+    level: ThrottleLevel,
+}
+
+/// Inbound message rates of every session, by label
+#[derive(Debug)]
+pub struct InboundThrottle {
+    policy: ThrottlePolicy,
+    sessions: Mutex<HashMap<String, Window>>,
+}
+
+impl InboundThrottle {
+    pub fn new(policy: ThrottlePolicy) -> Self {
+        Self {
+            policy,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn policy(&self) -> &ThrottlePolicy {
+        &self.policy
+    }
+
+    /// Count one inbound message of a session
+    pub fn on_message(&self, label: &str, now: Instant) -> ThrottleVerdict {
+        let mut sessions = self.sessions.lock().expect("throttle lock poisoned");
+        let window = sessions.entry(label.to_string()).or_insert(Window {
+            started: now,
+            count: 0,
+            level: ThrottleLevel::Normal,
+        });
+
+        if now.duration_since(window.started) >= self.policy.window
+            && window.level != ThrottleLevel::Disconnect
+        {
+            window.started = now;
+            window.count = 0;
+            window.level = ThrottleLevel::Normal;
+        }
+
+        window.count = window.count.saturating_add(1);
+        let level = self.policy.level(window.count).max(window.level);
+        let escalated = level > window.level;
+        window.level = level;
+
+        ThrottleVerdict {
+            level,
+            escalated,
+            count: window.count,
+        }
+    }
+
+    /// Forget a session (logout): it starts again at Normal
+    pub fn reset(&self, label: &str) {
+        self.sessions
+            .lock()
+            .expect("throttle lock poisoned")
+            .remove(label);
+    }
+
+    /// Messages in the last window and level of every session, sorted by
+    /// label
+    pub fn snapshot(&self) -> Vec<(String, u32, ThrottleLevel)> {
+        let sessions = self.sessions.lock().expect("throttle lock poisoned");
+        let mut snapshot: Vec<_> = sessions
+            .iter()
+            .map(|(label, x)| (label.clone(), x.count, x.level))
+            .collect();
+        snapshot.sort_by(|a, b| a.0.cmp(&b.0));
+        snapshot
+    }
+}
@@ -0,0 +1,125 @@
+// =============================================================================
+// Venue Profiles
+// =============================================================================
+// Every venue publishes trading rules that inbound orders must respect. The
+// matching engine is parameterized by one of these profiles so the same code
+// can simulate an equity exchange, a futures exchange or an FX ECN.
+//
+// The profile also sets how many messages per second a session may send
+// before the venue warns, throttles and disconnects it (throttle.rs).
+//
+// What a counterparty asks for with a SecurityDefinitionRequest comes from
+// it as well: every symbol of a venue shares its tick size, round lot,
+// contract multiplier and currency (`instrument`).
+// =============================================================================
+
+use std::time::Duration;
+
+use crate::{instruments::Instrument, sim::throttle::ThrottlePolicy};
+
+#[derive(Debug, Clone)]
+pub struct VenueProfile {
+    pub name: &'static str,
+
+    /// Minimum price increment
+    pub tick_size: f64,
+
+    /// Quantities must be a multiple of this
+    pub lot_size: u64,
+
+    /// Largest quantity accepted on a single order
+    pub max_order_qty: u64,
+
+    /// What one unit is worth per point of price (ContractMultiplier)
+    pub multiplier: f64,
+
+    /// Currency of the prices
+    pub currency: &'static str,
+
+    /// SecurityType (167) of the symbols traded
+    pub security_type: &'static str,
+
+    /// Limit orders further than this fraction away from the last trade are
+    /// rejected (0.1 = 10%). Disabled until the first trade prints.
+    pub price_band: f64,
+
+    /// Inbound message rates per session
+    pub throttle: ThrottlePolicy,
+}
+
+impl VenueProfile {
+    pub fn equities() -> Self {
+        Self {
+            name: "equities",
+            tick_size: 0.01,
+            lot_size: 1,
+            max_order_qty: 100_000,
+            multiplier: 1.0,
+            currency: "USD",
+            security_type: "CS",
+            price_band: 0.10,
+            throttle: ThrottlePolicy::per_second(100, 200, 500),
+        }
+    }
+
+    pub fn futures() -> Self {
+        Self {
+            name: "futures",
+            tick_size: 0.25,
+            lot_size: 1,
+            max_order_qty: 5_000,
+            multiplier: 50.0,
+            currency: "USD",
+            security_type: "FUT",
+            price_band: 0.05,
+            throttle: ThrottlePolicy::per_second(200, 400, 1_000),
+        }
+    }
+
+    pub fn fx() -> Self {
+        Self {
+            name: "fx",
+            tick_size: 0.000_01,
+            lot_size: 1_000,
+            max_order_qty: 50_000_000,
+            multiplier: 1.0,
+            currency: "USD",
+            security_type: "FOR",
+            price_band: 0.02,
+            // Streaming quote updates and fast replaces are the norm
+            throttle: ThrottlePolicy::per_second(500, 1_000, 2_500)
+                .with_delay(Duration::from_millis(5)),
+        }
+    }
+
+    /// Look a profile up by name (command-line friendly)
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "equities" => Some(Self::equities()),
+            "futures" => Some(Self::futures()),
+            "fx" => Some(Self::fx()),
+            _ => None,
+        }
+    }
+
+    /// Convert a price to integer ticks, None if it is off the tick grid
+    pub fn to_ticks(&self, price: f64) -> Option<i64> {
+        let ticks = price / self.tick_size;
+        let rounded = ticks.round();
+        ((ticks - rounded).abs() < 1e-6).then_some(rounded as i64)
+    }
+
+    pub fn to_price(&self, ticks: i64) -> f64 {
+        ticks as f64 * self.tick_size
+    }
+
+    /// The definition of a symbol traded under this profile
+    pub fn instrument(&self, symbol: &str) -> Instrument {
+        Instrument::new(symbol)
+            .with_security_type(self.security_type)
+            .with_currency(self.currency)
+            .with_tick_size(self.tick_size)
+            .with_multiplier(self.multiplier)
+            .with_lot_size(self.lot_size as f64)
+    }
+}
@@ -0,0 +1,303 @@
+// =============================================================================
+// State Store
+// =============================================================================
+// Where the business state of an application survives a restart: its
+// orders, its ClOrdID sequence, its positions, its market data subscribers,
+// its message templates. Each component keeps its own namespace of keys,
+// and every backend keeps them the same way, so a deployment picks one
+// persistence layer for all of them with a URL:
+//
+//   memory                         nothing survives the process (default)
+//   file:DIR, or a plain path      one directory per namespace, one file
+//                                  per key, replaced atomically
+//   sqlite:PATH                    one table (`sqlite` feature)
+//   redis://HOST[:PORT][/PREFIX]   one hash per namespace, PREFIX:namespace
+//                                  (`redis` feature)
+//
+// The namespaces in use:
+//
+//   orders          OMS orders by ClOrdID (oms)
+//   counters        ClOrdID sequences by prefix (oms)
+//   positions       fills of each symbol, the positions the risk checks
+//                   count on are rebuilt from them (oms::positions)
+//   subscriptions   market data subscribers of each symbol (md)
+//   templates       fix_repl message template files
+//   trade_captures  trade capture reports received, by TradeReportID
+//                   (oms::captures)
+//   instruments     security definitions received, by symbol (instruments)
+//   scorecards      counterparty statistics by day and session, flushed
+//                   periodically rather than written through
+//                   (session::scorecard)
+//
+// Values are text, JSON for all but the templates. Components write through:
+// each change is stored when it is made, one key at a time; there is no
+// transaction across keys.
+//
+// Processes sharing one backend keep apart with a ScopedStore, which puts a
+// scope in front of every namespace (`shard-1-subscriptions`). Any backend
+// keeps its values encrypted behind an EncryptedStore (store::encrypted,
+// `encryption` feature), with the key of a key file or TRADING_STORE_KEY.
+// =============================================================================
+
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt, io,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "encryption")]
+pub mod encrypted;
+pub mod file;
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+/// Longest key accepted, in bytes
+pub const MAX_KEY_LEN: usize = 200;
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum StoreError {
+    Io(io::Error),
+
+    /// Not a store URL, or a backend this build does not have
+    Url(String),
+
+    /// A namespace other than lower-case letters, digits, `_` and `-`, or
+    /// an empty, too long or control character key
+    InvalidKey(String),
+
+    /// A stored value its component cannot read back
+    Corrupt {
+        namespace: String,
+        key: String,
+    },
+
+    /// The backend refused a request (SQLite, Redis)
+    Backend(String),
+
+    /// An encryption key that is not 32 bytes
+    Key(String),
+
+    /// An encrypted value that does not decrypt: another key, a value
+    /// tampered with, or one in clear
+    Decrypt {
+        namespace: String,
+        key: String,
+    },
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(err) => write!(f, "{err}"),
+            StoreError::Url(url) => write!(
+                f,
+                "unknown state store {url}: expected memory, file:DIR, sqlite:PATH \
+                 or redis://HOST[:PORT][/PREFIX]"
+            ),
+            StoreError::InvalidKey(key) => write!(f, "invalid namespace or key: {key:?}"),
+            StoreError::Corrupt { namespace, key } => {
+                write!(f, "stored value {namespace}/{key} cannot be read")
+            }
+            StoreError::Backend(err) => write!(f, "{err}"),
+            StoreError::Key(err) => write!(f, "invalid store encryption key: {err}"),
+            StoreError::Decrypt { namespace, key } => write!(
+                f,
+                "stored value {namespace}/{key} does not decrypt: wrong key, or not encrypted"
+            ),
+        }
+    }
+}
+
+impl Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(err: io::Error) -> Self {
+        StoreError::Io(err)
+    }
+}
+
+// =============================================================================
+// The Trait
+// =============================================================================
+
+/// Text values by namespace and key
+///
+/// Implementations are shared between threads (QuickFIX callbacks, tasks,
+/// gateways) and serialize their own access.
+pub trait StateStore: Send + Sync {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, StoreError>;
+
+    /// Set a key, replacing its value
+    fn put(&self, namespace: &str, key: &str, value: &str) -> Result<(), StoreError>;
+
+    /// Remove a key; removing a missing one is not an error
+    fn delete(&self, namespace: &str, key: &str) -> Result<(), StoreError>;
+
+    /// Every key of a namespace with its value, sorted by key
+    fn scan(&self, namespace: &str) -> Result<Vec<(String, String)>, StoreError>;
+
+    /// The URL it was opened from, for logs
+    fn url(&self) -> String;
+}
+
+/// Refuse namespaces some backend could not keep as given
+pub fn check_namespace(namespace: &str) -> Result<(), StoreError> {
+    let valid = !namespace.is_empty()
+        && namespace
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_' || b == b'-');
+    if !valid {
+        return Err(StoreError::InvalidKey(namespace.to_string()));
+    }
+    Ok(())
+}
+
+/// Refuse namespaces and keys some backend could not keep as given
+pub fn check_key(namespace: &str, key: &str) -> Result<(), StoreError> {
+    check_namespace(namespace)?;
+    if key.is_empty() || key.len() > MAX_KEY_LEN || key.chars().any(char::is_control) {
+        return Err(StoreError::InvalidKey(key.to_string()));
+    }
+    Ok(())
+}
+
+/// Open the store a URL names
+pub fn open(url: &str) -> Result<Arc<dyn StateStore>, StoreError> {
+    if url == "memory" {
+        return Ok(Arc::new(MemoryStore::new()));
+    }
+    if let Some(path) = url.strip_prefix("sqlite:") {
+        #[cfg(feature = "sqlite")]
+        return Ok(Arc::new(sqlite::SqliteStore::open(Path::new(path))?));
+        #[cfg(not(feature = "sqlite"))]
+        {
+            let _ = path;
+            return Err(StoreError::Url(url.to_string()));
+        }
+    }
+    if url.starts_with("redis://") {
+        #[cfg(feature = "redis")]
+        return Ok(Arc::new(redis::RedisStore::open(url)?));
+        #[cfg(not(feature = "redis"))]
+        return Err(StoreError::Url(url.to_string()));
+    }
+
+    let dir = url.strip_prefix("file:").unwrap_or(url);
+    if dir.is_empty() || dir.contains("://") {
+        return Err(StoreError::Url(url.to_string()));
+    }
+    Ok(Arc::new(file::FileStore::open(Path::new(dir))?))
+}
+
+// =============================================================================
+// MemoryStore
+// =============================================================================
+
+/// Kept in the process only: the store of a run that persists nothing
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    values: Mutex<BTreeMap<(String, String), String>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StateStore for MemoryStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, StoreError> {
+        check_key(namespace, key)?;
+        let values = self.values.lock().expect("store lock poisoned");
+        Ok(values
+            .get(&(namespace.to_string(), key.to_string()))
+            .cloned())
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: &str) -> Result<(), StoreError> {
+        check_key(namespace, key)?;
+        let mut values = self.values.lock().expect("store lock poisoned");
+        values.insert((namespace.to_string(), key.to_string()), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<(), StoreError> {
+        check_key(namespace, key)?;
+        let mut values = self.values.lock().expect("store lock poisoned");
+        values.remove(&(namespace.to_string(), key.to_string()));
+        Ok(())
+    }
+
+    fn scan(&self, namespace: &str) -> Result<Vec<(String, String)>, StoreError> {
+        check_namespace(namespace)?;
+        let values = self.values.lock().expect("store lock poisoned");
+        Ok(values
+            .iter()
+            .filter(|((x, _), _)| x == namespace)
+            .map(|((_, key), value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn url(&self) -> String {
+        "memory".to_string()
+    }
+}
+
+// =============================================================================
+// ScopedStore
+// =============================================================================
+
+/// Another store under a scope: namespace `subscriptions` is kept there as
+/// `<scope>-subscriptions`, so that processes sharing one backend (the
+/// shards of a coordinator) do not read each other's keys
+pub struct ScopedStore {
+    inner: Arc<dyn StateStore>,
+    scope: String,
+}
+
+impl ScopedStore {
+    /// The scope takes the characters of a namespace: lower-case letters,
+    /// digits, `_` and `-`
+    pub fn new(inner: Arc<dyn StateStore>, scope: &str) -> Result<Self, StoreError> {
+        check_namespace(scope)?;
+        Ok(Self {
+            inner,
+            scope: scope.to_string(),
+        })
+    }
+
+    fn namespace(&self, namespace: &str) -> String {
+        format!("{}-{namespace}", self.scope)
+    }
+}
+
+impl StateStore for ScopedStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, StoreError> {
+        self.inner.get(&self.namespace(namespace), key)
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: &str) -> Result<(), StoreError> {
+        self.inner.put(&self.namespace(namespace), key, value)
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<(), StoreError> {
+        self.inner.delete(&self.namespace(namespace), key)
+    }
+
+    fn scan(&self, namespace: &str) -> Result<Vec<(String, String)>, StoreError> {
+        self.inner.scan(&self.namespace(namespace))
+    }
+
+    fn url(&self) -> String {
+        format!("{} ({} only)", self.inner.url(), self.scope)
+    }
+}
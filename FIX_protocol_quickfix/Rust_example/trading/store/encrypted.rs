@@ -0,0 +1,318 @@
+// =============================================================================
+// Encrypted State Store
+// =============================================================================
+// Another store with its values encrypted, so that the orders, fills and
+// trade captures it keeps are unreadable on disk, in a backup or in Redis
+// without the key. Every value is stored as text:
+//
+//   aes256gcm:<hex of nonce (12 bytes), ciphertext, tag (16 bytes)>
+//
+// AES-256-GCM (the RustCrypto aes-gcm crate, `encryption` feature), with a
+// fresh random nonce per write (from the OS, through getrandom) and the
+// namespace and key as associated data, each preceded by its length: a
+// value copied under another key does not decrypt.
+// Namespaces and keys stay in clear, they are file names and hash fields:
+// ClOrdIDs and symbols can be read, prices, quantities and accounts cannot.
+// The AES is AES-NI where the CPU has it, constant-time software otherwise.
+//
+// The key is 32 bytes, in a key file (the bytes themselves, or 64 hex
+// digits) or as 64 hex digits in TRADING_STORE_KEY:
+//
+//   head -c 32 /dev/urandom > state.key && chmod 600 state.key
+//
+// Reads decrypt, so the components restoring their state from the store
+// see what they wrote. A wrong key, or a value tampered with, is an error
+// (StoreError::Decrypt); so is a value in clear, from before the store was
+// encrypted, unless with_plaintext_reads: encrypt_existing rewrites those.
+//
+// The messages themselves are kept encrypted by the wire journal
+// (session::journal, Journal::open_encrypted), which fixtail reads back
+// with the same key. The QuickFIX message store (FileStorePath), which holds
+// the messages for resends, is written by the engine itself, past any hook
+// of ours: it is for the file system to protect.
+// =============================================================================
+
+use std::{fmt, fs, io, path::Path, sync::Arc};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+
+use super::{StateStore, StoreError};
+
+/// Environment variable holding the key as 64 hex digits
+pub const KEY_ENV: &str = "TRADING_STORE_KEY";
+
+/// Start of every encrypted value
+const PREFIX: &str = "aes256gcm:";
+
+/// Bytes of the nonce in front of every value
+pub const NONCE_LEN: usize = 12;
+
+/// Bytes of the tag after every value
+pub const TAG_LEN: usize = 16;
+
+// =============================================================================
+// Key
+// =============================================================================
+
+/// An AES-256 key; its Debug output does not show it
+#[derive(Clone)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// 64 hex digits
+    pub fn from_hex(value: &str) -> Result<Self, StoreError> {
+        let bytes = from_hex(value.trim())
+            .and_then(|x| <[u8; 32]>::try_from(x).ok())
+            .ok_or_else(|| StoreError::Key("expected 64 hex digits".to_string()))?;
+        Ok(Self(bytes))
+    }
+
+    /// A key file: the 32 bytes of the key, or 64 hex digits
+    pub fn from_file(path: &Path) -> Result<Self, StoreError> {
+        let bytes = fs::read(path)?;
+        if let Ok(key) = <[u8; 32]>::try_from(bytes.as_slice()) {
+            return Ok(Self(key));
+        }
+        std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|x| Self::from_hex(x).ok())
+            .ok_or_else(|| {
+                StoreError::Key(format!(
+                    "{}: expected 32 bytes or 64 hex digits",
+                    path.display()
+                ))
+            })
+    }
+
+    /// The key file if given, otherwise TRADING_STORE_KEY if set
+    ///
+    /// # Returns
+    /// None when there is neither: the store is not encrypted
+    pub fn load(file: Option<&Path>) -> Result<Option<Self>, StoreError> {
+        if let Some(file) = file {
+            return Self::from_file(file).map(Some);
+        }
+        match std::env::var(KEY_ENV) {
+            Ok(value) => Self::from_hex(&value)
+                .map(Some)
+                .map_err(|_| StoreError::Key(format!("{KEY_ENV}: expected 64 hex digits"))),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+// =============================================================================
+// Cipher
+// =============================================================================
+
+/// AES-256-GCM under one key, 96-bit nonce and 128-bit tag
+pub struct Cipher(Aes256Gcm);
+
+impl Cipher {
+    pub fn new(key: &EncryptionKey) -> Self {
+        Self(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.0)))
+    }
+
+    /// A fresh random nonce, from the OS: never two values under one nonce
+    pub fn generate_nonce() -> [u8; NONCE_LEN] {
+        Aes256Gcm::generate_nonce(&mut OsRng).into()
+    }
+
+    /// Encrypt `plaintext`
+    ///
+    /// # Returns
+    /// The ciphertext followed by the tag
+    pub fn seal(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, StoreError> {
+        let payload = Payload {
+            msg: plaintext,
+            aad,
+        };
+        self.0
+            .encrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| {
+                StoreError::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "value too long to encrypt",
+                ))
+            })
+    }
+
+    /// Decrypt the ciphertext and tag of `seal`
+    ///
+    /// # Returns
+    /// None when the tag does not match: another key, nonce or associated
+    /// data, or a ciphertext tampered with
+    pub fn open(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+        let payload = Payload { msg: sealed, aad };
+        self.0.decrypt(Nonce::from_slice(nonce), payload).ok()
+    }
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Cipher(..)")
+    }
+}
+
+// =============================================================================
+// EncryptedStore
+// =============================================================================
+
+pub struct EncryptedStore {
+    inner: Arc<dyn StateStore>,
+    cipher: Cipher,
+
+    /// Values in clear are returned as they are rather than refused
+    plaintext_reads: bool,
+}
+
+impl EncryptedStore {
+    pub fn new(inner: Arc<dyn StateStore>, key: &EncryptionKey) -> Self {
+        Self {
+            inner,
+            cipher: Cipher::new(key),
+            plaintext_reads: false,
+        }
+    }
+
+    /// Read the values written before the store was encrypted as they are,
+    /// until they are written again or encrypt_existing encrypts them
+    pub fn with_plaintext_reads(mut self) -> Self {
+        self.plaintext_reads = true;
+        self
+    }
+
+    /// `inner` encrypted with `key`, or as it is without one
+    pub fn wrap(inner: Arc<dyn StateStore>, key: Option<&EncryptionKey>) -> Arc<dyn StateStore> {
+        match key {
+            Some(key) => Arc::new(Self::new(inner, key)),
+            None => inner,
+        }
+    }
+
+    /// Encrypt the values of a namespace still in clear
+    ///
+    /// # Returns
+    /// How many were encrypted
+    pub fn encrypt_existing(&self, namespace: &str) -> Result<usize, StoreError> {
+        let mut count = 0;
+        for (key, value) in self.inner.scan(namespace)? {
+            if !value.starts_with(PREFIX) {
+                self.put(namespace, &key, &value)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    fn seal(&self, namespace: &str, key: &str, value: &str) -> Result<String, StoreError> {
+        let nonce = Cipher::generate_nonce();
+        let data = self
+            .cipher
+            .seal(&nonce, &aad(namespace, key), value.as_bytes())?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&data);
+        Ok(format!("{PREFIX}{}", to_hex(&sealed)))
+    }
+
+    fn open(&self, namespace: &str, key: &str, stored: String) -> Result<String, StoreError> {
+        let failed = || StoreError::Decrypt {
+            namespace: namespace.to_string(),
+            key: key.to_string(),
+        };
+        let Some(sealed) = stored.strip_prefix(PREFIX) else {
+            return match self.plaintext_reads {
+                true => Ok(stored),
+                false => Err(failed()),
+            };
+        };
+        let sealed = from_hex(sealed)
+            .filter(|x| x.len() >= NONCE_LEN + TAG_LEN)
+            .ok_or_else(failed)?;
+
+        let (nonce, data) = sealed.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().map_err(|_| failed())?;
+        let data = self
+            .cipher
+            .open(&nonce, &aad(namespace, key), data)
+            .ok_or_else(failed)?;
+        String::from_utf8(data).map_err(|_| failed())
+    }
+}
+
+impl StateStore for EncryptedStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, StoreError> {
+        match self.inner.get(namespace, key)? {
+            Some(stored) => self.open(namespace, key, stored).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: &str) -> Result<(), StoreError> {
+        let sealed = self.seal(namespace, key, value)?;
+        self.inner.put(namespace, key, &sealed)
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<(), StoreError> {
+        self.inner.delete(namespace, key)
+    }
+
+    fn scan(&self, namespace: &str) -> Result<Vec<(String, String)>, StoreError> {
+        self.inner
+            .scan(namespace)?
+            .into_iter()
+            .map(|(key, stored)| {
+                let value = self.open(namespace, &key, stored)?;
+                Ok((key, value))
+            })
+            .collect()
+    }
+
+    fn url(&self) -> String {
+        format!("{} (encrypted)", self.inner.url())
+    }
+}
+
+/// Associated data of a value: namespace and key, each after its length
+/// (u32, big endian), so that no other pair gives the same bytes
+fn aad(namespace: &str, key: &str) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(8 + namespace.len() + key.len());
+    for part in [namespace, key] {
+        aad.extend_from_slice(&(part.len() as u32).to_be_bytes());
+        aad.extend_from_slice(part.as_bytes());
+    }
+    aad
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+fn from_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) || !value.is_ascii() {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok())
+        .collect()
+}
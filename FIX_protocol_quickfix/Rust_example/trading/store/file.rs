@@ -0,0 +1,145 @@
+// =============================================================================
+// File State Store
+// =============================================================================
+// One directory per namespace under the root, one file per key:
+//
+//   state/orders/BUY-1
+//   state/counters/BUY
+//   state/positions/AAPL
+//
+// Bytes of a key other than letters, digits, `-` and `_` are written as
+// `%XX`, so any key is a portable file name. A value is written to a
+// temporary file, synced, then renamed over the key's file: a crash leaves
+// the old value or the new one, never half of either. Temporary files start
+// with `.` and are not keys.
+// =============================================================================
+
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use super::{check_key, check_namespace, StateStore, StoreError};
+
+#[derive(Debug)]
+pub struct FileStore {
+    root: PathBuf,
+
+    /// Serializes writers of this process; other processes must not share
+    /// the directory
+    lock: Mutex<()>,
+}
+
+impl FileStore {
+    /// Open a store directory, created if needed
+    pub fn open(root: &Path) -> Result<Self, StoreError> {
+        fs::create_dir_all(root)?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            lock: Mutex::new(()),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, namespace: &str, key: &str) -> PathBuf {
+        self.root.join(namespace).join(encode(key))
+    }
+}
+
+impl StateStore for FileStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, StoreError> {
+        check_key(namespace, key)?;
+        match fs::read_to_string(self.path(namespace, key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: &str) -> Result<(), StoreError> {
+        check_key(namespace, key)?;
+        let _guard = self.lock.lock().expect("store lock poisoned");
+        let dir = self.root.join(namespace);
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join(encode(key));
+        let temp = dir.join(format!(".{}.tmp", encode(key)));
+        let mut file = File::create(&temp)?;
+        file.write_all(value.as_bytes())?;
+        file.sync_data()?;
+        fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<(), StoreError> {
+        check_key(namespace, key)?;
+        let _guard = self.lock.lock().expect("store lock poisoned");
+        match fs::remove_file(self.path(namespace, key)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn scan(&self, namespace: &str) -> Result<Vec<(String, String)>, StoreError> {
+        check_namespace(namespace)?;
+        let entries = match fs::read_dir(self.root.join(namespace)) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut values = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(key) = name
+                .to_str()
+                .filter(|x| !x.starts_with('.'))
+                .and_then(decode)
+            else {
+                continue;
+            };
+            values.push((key, fs::read_to_string(entry.path())?));
+        }
+        values.sort();
+        Ok(values)
+    }
+
+    fn url(&self) -> String {
+        format!("file:{}", self.root.display())
+    }
+}
+
+/// File name of a key
+fn encode(key: &str) -> String {
+    let mut name = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' => name.push(byte as char),
+            _ => name.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    name
+}
+
+/// Key of a file name; None for names `encode` does not make
+fn decode(name: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
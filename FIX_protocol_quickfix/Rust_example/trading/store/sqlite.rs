@@ -0,0 +1,107 @@
+// =============================================================================
+// SQLite State Store
+// =============================================================================
+// One table in one database file, keyed by namespace and key:
+//
+//   CREATE TABLE state (namespace TEXT, key TEXT, value TEXT,
+//                       PRIMARY KEY (namespace, key))
+//
+// in WAL mode, so reading it (sqlite3 state.db "SELECT ...") does not block
+// the application. Every put is its own transaction. Uses rusqlite, which
+// the `sqlite` feature pulls in with SQLite itself (bundled).
+// =============================================================================
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{check_key, check_namespace, StateStore, StoreError};
+
+const SCHEMA: &str = "
+    PRAGMA journal_mode = WAL;
+    CREATE TABLE IF NOT EXISTS state (
+        namespace TEXT NOT NULL,
+        key       TEXT NOT NULL,
+        value     TEXT NOT NULL,
+        PRIMARY KEY (namespace, key)
+    );
+";
+
+#[derive(Debug)]
+pub struct SqliteStore {
+    path: PathBuf,
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Open a database file, created with its table if needed
+    pub fn open(path: &Path) -> Result<Self, StoreError> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection.lock().expect("sqlite lock poisoned")
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(err: rusqlite::Error) -> Self {
+        StoreError::Backend(format!("sqlite: {err}"))
+    }
+}
+
+impl StateStore for SqliteStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, StoreError> {
+        check_key(namespace, key)?;
+        let value = self
+            .connection()
+            .query_row(
+                "SELECT value FROM state WHERE namespace = ?1 AND key = ?2",
+                params![namespace, key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: &str) -> Result<(), StoreError> {
+        check_key(namespace, key)?;
+        self.connection().execute(
+            "INSERT INTO state (namespace, key, value) VALUES (?1, ?2, ?3) \
+             ON CONFLICT (namespace, key) DO UPDATE SET value = excluded.value",
+            params![namespace, key, value],
+        )?;
+        Ok(())
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<(), StoreError> {
+        check_key(namespace, key)?;
+        self.connection().execute(
+            "DELETE FROM state WHERE namespace = ?1 AND key = ?2",
+            params![namespace, key],
+        )?;
+        Ok(())
+    }
+
+    fn scan(&self, namespace: &str) -> Result<Vec<(String, String)>, StoreError> {
+        check_namespace(namespace)?;
+        let connection = self.connection();
+        let mut statement =
+            connection.prepare("SELECT key, value FROM state WHERE namespace = ?1 ORDER BY key")?;
+        let rows = statement.query_map(params![namespace], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let values = rows.collect::<Result<Vec<_>, _>>()?;
+        Ok(values)
+    }
+
+    fn url(&self) -> String {
+        format!("sqlite:{}", self.path.display())
+    }
+}
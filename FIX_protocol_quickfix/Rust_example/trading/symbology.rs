@@ -0,0 +1,525 @@
+// =============================================================================
+// Symbology
+// =============================================================================
+// The application trades internal symbols; each venue wants its own: a
+// ticker of its own (AAPL.O), or an identifier in SecurityID (48) with its
+// SecurityIDSource (22). A Symbology maps between them per session, from a
+// CSV file with a header row, columns in any order:
+//
+//   internal,session,symbol,security_id,security_id_source,isin,cusip,ric
+//   AAPL,,,,,US0378331005,037833100,AAPL.OQ
+//   AAPL,FIX.4.4:BUYSIDE_ORD->SIMULATOR,AAPL.O,,,,,
+//   VOD,FIX.4.4:HUB->VENUE_B,VOD.L,,isin,GB00BH4HKS39,,VOD.L
+//
+//   internal              the application's symbol (required)
+//   session               the session label the row is for; every session
+//                         when empty, a row for the session winning
+//   symbol                Symbol (55) at the venue; the internal one when
+//                         empty
+//   security_id, _source  SecurityID (48) and SecurityIDSource (22) at the
+//                         venue. The source is a 22 value or isin (4), cusip
+//                         (1), ric (5): without a security_id, the
+//                         instrument's identifier of that kind is sent
+//   isin, cusip, ric      identifiers of the instrument, on any of its rows
+//
+// A row without a session or venue columns only gives identifiers.
+//
+// Outbound, the Symbol of a message is replaced with the venue's and the
+// SecurityID / SecurityIDSource added; inbound, a message whose SecurityID
+// (else Symbol) the session's venue uses gets the internal Symbol back.
+// Symbols nobody mapped go through as they are. Only the top-level fields
+// of a message are rewritten, not those inside repeating groups (the
+// instruments of a 35=V or the entries of a 35=X).
+// =============================================================================
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use quickfix::{FieldMap, Message, QuickFixError, SessionId};
+
+use crate::session::session_label;
+
+/// Columns of a symbology file; `internal` is required
+pub const COLUMNS: [&str; 8] = [
+    "internal",
+    "session",
+    "symbol",
+    "security_id",
+    "security_id_source",
+    "isin",
+    "cusip",
+    "ric",
+];
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SymbologyError {
+    Io(PathBuf, io::Error),
+
+    /// A line that cannot be read: line number and why
+    Syntax(usize, String),
+
+    /// A column of the header row that is not one of COLUMNS
+    UnknownColumn(String),
+
+    /// A required column missing from the header row
+    MissingColumn(&'static str),
+
+    /// Two rows for the same symbol and session, or two internal symbols
+    /// with the same venue symbol: line number and what
+    Conflict(usize, String),
+}
+
+impl fmt::Display for SymbologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbologyError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            SymbologyError::Syntax(line, message) => write!(f, "line {line}: {message}"),
+            SymbologyError::UnknownColumn(name) => {
+                write!(f, "unknown column {name} (expected {})", COLUMNS.join(", "))
+            }
+            SymbologyError::MissingColumn(name) => write!(f, "missing column {name}"),
+            SymbologyError::Conflict(line, message) => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl Error for SymbologyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SymbologyError::Io(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// =============================================================================
+// Identifiers
+// =============================================================================
+
+/// Kinds of instrument identifiers, with their SecurityIDSource (22)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdSource {
+    Cusip,
+    Isin,
+    Ric,
+}
+
+impl IdSource {
+    /// SecurityIDSource (22) value
+    pub fn code(self) -> &'static str {
+        match self {
+            IdSource::Cusip => "1",
+            IdSource::Isin => "4",
+            IdSource::Ric => "5",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IdSource::Cusip => "cusip",
+            IdSource::Isin => "isin",
+            IdSource::Ric => "ric",
+        }
+    }
+
+    /// From a name or a SecurityIDSource value
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.to_ascii_lowercase().as_str() {
+            "cusip" | "1" => Some(IdSource::Cusip),
+            "isin" | "4" => Some(IdSource::Isin),
+            "ric" | "5" => Some(IdSource::Ric),
+            _ => None,
+        }
+    }
+}
+
+/// What identifies an instrument outside of any venue
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Identifiers {
+    pub isin: Option<String>,
+    pub cusip: Option<String>,
+    pub ric: Option<String>,
+}
+
+impl Identifiers {
+    pub fn get(&self, source: IdSource) -> Option<&str> {
+        match source {
+            IdSource::Cusip => self.cusip.as_deref(),
+            IdSource::Isin => self.isin.as_deref(),
+            IdSource::Ric => self.ric.as_deref(),
+        }
+    }
+
+    fn slot(&mut self, source: IdSource) -> &mut Option<String> {
+        match source {
+            IdSource::Cusip => &mut self.cusip,
+            IdSource::Isin => &mut self.isin,
+            IdSource::Ric => &mut self.ric,
+        }
+    }
+}
+
+/// How a venue names an instrument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VenueSymbol {
+    /// Symbol (55)
+    pub symbol: String,
+
+    /// SecurityID (48) and SecurityIDSource (22), if the venue wants them
+    pub security_id: Option<(String, String)>,
+}
+
+/// What a venue's message names an instrument by
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum VenueKey {
+    Symbol(String),
+    SecurityId(String, String),
+}
+
+// =============================================================================
+// Symbology
+// =============================================================================
+
+/// Internal symbols, their identifiers and what each session's venue calls
+/// them
+#[derive(Debug, Clone, Default)]
+pub struct Symbology {
+    identifiers: HashMap<String, Identifiers>,
+
+    /// (session label, empty for every session; internal symbol)
+    venue: HashMap<(String, String), VenueSymbol>,
+
+    /// (session label or empty; venue key) -> internal symbol
+    internal: HashMap<(String, VenueKey), String>,
+
+    /// Internal symbol by (kind, identifier)
+    by_identifier: HashMap<(IdSource, String), String>,
+}
+
+impl Symbology {
+    /// Read a symbology file's text: a header row, then one row per line;
+    /// blank lines and lines starting with `#` skipped
+    pub fn from_csv(text: &str) -> Result<Self, SymbologyError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        let Some((number, header)) = lines.next() else {
+            return Err(SymbologyError::MissingColumn("internal"));
+        };
+        let columns = split_csv(header).map_err(|err| SymbologyError::Syntax(number, err))?;
+        for column in &columns {
+            if !COLUMNS.contains(&column.as_str()) {
+                return Err(SymbologyError::UnknownColumn(column.clone()));
+            }
+        }
+        if !columns.iter().any(|x| x == "internal") {
+            return Err(SymbologyError::MissingColumn("internal"));
+        }
+
+        // Venue rows asking for an identifier wait until every row gave
+        // its identifiers
+        let mut symbology = Self::default();
+        let mut wanted = Vec::new();
+        for (number, line) in lines {
+            let values = split_csv(line).map_err(|err| SymbologyError::Syntax(number, err))?;
+            if values.len() > columns.len() {
+                return Err(SymbologyError::Syntax(
+                    number,
+                    format!("{} values for {} columns", values.len(), columns.len()),
+                ));
+            }
+            let get = |name: &str| {
+                columns
+                    .iter()
+                    .position(|x| x == name)
+                    .and_then(|x| values.get(x))
+                    .map(|x| x.trim())
+                    .filter(|x| !x.is_empty())
+            };
+            let Some(internal) = get("internal") else {
+                return Err(SymbologyError::Syntax(number, "no internal symbol".into()));
+            };
+
+            for source in [IdSource::Isin, IdSource::Cusip, IdSource::Ric] {
+                if let Some(id) = get(source.as_str()) {
+                    symbology.add_identifier(number, internal, source, id)?;
+                }
+            }
+
+            let session = get("session").unwrap_or_default();
+            let symbol = get("symbol");
+            let source = get("security_id_source");
+            if session.is_empty() && symbol.is_none() && source.is_none() {
+                continue;
+            }
+            let security_id = match (get("security_id"), source) {
+                (Some(id), Some(source)) => {
+                    let code = IdSource::parse(source).map_or(source, |x| x.code());
+                    Some((id.to_string(), code.to_string()))
+                }
+                (None, Some(source)) => {
+                    let kind = IdSource::parse(source).ok_or_else(|| {
+                        SymbologyError::Syntax(
+                            number,
+                            format!("security_id_source {source} needs a security_id"),
+                        )
+                    })?;
+                    wanted.push((number, session.to_string(), internal.to_string(), kind));
+                    None
+                }
+                (Some(_), None) => {
+                    return Err(SymbologyError::Syntax(
+                        number,
+                        "security_id without a security_id_source".into(),
+                    ))
+                }
+                (None, None) => None,
+            };
+            let venue = VenueSymbol {
+                symbol: symbol.unwrap_or(internal).to_string(),
+                security_id,
+            };
+            symbology.add_venue(number, session, internal, venue)?;
+        }
+
+        for (number, session, internal, kind) in wanted {
+            let id = symbology
+                .identifiers(&internal)
+                .and_then(|x| x.get(kind))
+                .ok_or_else(|| {
+                    SymbologyError::Syntax(number, format!("{internal} has no {}", kind.as_str()))
+                })?
+                .to_string();
+            let key = (session.clone(), internal.clone());
+            let mut venue = symbology.venue[&key].clone();
+            venue.security_id = Some((id, kind.code().to_string()));
+            symbology.add_venue(number, &session, &internal, venue)?;
+        }
+        Ok(symbology)
+    }
+
+    /// Read a symbology file
+    pub fn load(path: &Path) -> Result<Self, SymbologyError> {
+        let text =
+            fs::read_to_string(path).map_err(|err| SymbologyError::Io(path.to_path_buf(), err))?;
+        Self::from_csv(&text)
+    }
+
+    fn add_identifier(
+        &mut self,
+        number: usize,
+        internal: &str,
+        source: IdSource,
+        id: &str,
+    ) -> Result<(), SymbologyError> {
+        let key = (source, id.to_string());
+        if let Some(other) = self.by_identifier.get(&key).filter(|x| *x != internal) {
+            return Err(SymbologyError::Conflict(
+                number,
+                format!("{} {id} is {other}'s already", source.as_str()),
+            ));
+        }
+        let slot = self
+            .identifiers
+            .entry(internal.to_string())
+            .or_default()
+            .slot(source);
+        if slot.as_deref().is_some_and(|x| x != id) {
+            return Err(SymbologyError::Conflict(
+                number,
+                format!("{internal} has another {}", source.as_str()),
+            ));
+        }
+        *slot = Some(id.to_string());
+        self.by_identifier.insert(key, internal.to_string());
+        Ok(())
+    }
+
+    /// Add or replace (a row asking for an identifier, completed) what a
+    /// session's venue calls a symbol
+    fn add_venue(
+        &mut self,
+        number: usize,
+        session: &str,
+        internal: &str,
+        venue: VenueSymbol,
+    ) -> Result<(), SymbologyError> {
+        let key = (session.to_string(), internal.to_string());
+        let replaced = self.venue.get(&key).cloned();
+        match &replaced {
+            Some(old) if old.symbol != venue.symbol || old.security_id.is_some() => {
+                return Err(SymbologyError::Conflict(
+                    number,
+                    format!("{internal} mapped twice for {}", session_or_all(session)),
+                ))
+            }
+            _ => {}
+        }
+
+        let mut keys = vec![VenueKey::Symbol(venue.symbol.clone())];
+        if let Some((id, source)) = &venue.security_id {
+            keys.push(VenueKey::SecurityId(id.clone(), source.clone()));
+        }
+        for venue_key in keys {
+            let key = (session.to_string(), venue_key);
+            if let Some(other) = self.internal.get(&key).filter(|x| *x != internal) {
+                return Err(SymbologyError::Conflict(
+                    number,
+                    format!(
+                        "{internal} and {other} have the same venue symbol for {}",
+                        session_or_all(session)
+                    ),
+                ));
+            }
+            self.internal.insert(key, internal.to_string());
+        }
+        self.venue.insert(key, venue);
+        Ok(())
+    }
+
+    /// Internal symbols known
+    pub fn len(&self) -> usize {
+        let mut symbols: Vec<_> = self.identifiers.keys().collect();
+        symbols.extend(self.venue.keys().map(|(_, x)| x));
+        symbols.sort();
+        symbols.dedup();
+        symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.identifiers.is_empty() && self.venue.is_empty()
+    }
+
+    pub fn identifiers(&self, internal: &str) -> Option<&Identifiers> {
+        self.identifiers.get(internal)
+    }
+
+    /// The internal symbol of an ISIN, CUSIP or RIC
+    pub fn by_identifier(&self, source: IdSource, id: &str) -> Option<&str> {
+        self.by_identifier
+            .get(&(source, id.to_string()))
+            .map(String::as_str)
+    }
+
+    /// What the venue of a session calls an internal symbol
+    pub fn to_venue(&self, session: &str, internal: &str) -> Option<&VenueSymbol> {
+        [session, ""]
+            .into_iter()
+            .find_map(|x| self.venue.get(&(x.to_string(), internal.to_string())))
+    }
+
+    /// The internal symbol of what the venue of a session sent: by
+    /// SecurityID and SecurityIDSource, else by Symbol
+    pub fn to_internal(
+        &self,
+        session: &str,
+        symbol: Option<&str>,
+        security_id: Option<(&str, &str)>,
+    ) -> Option<&str> {
+        let mut keys = Vec::new();
+        if let Some((id, source)) = security_id {
+            keys.push(VenueKey::SecurityId(id.to_string(), source.to_string()));
+        }
+        if let Some(symbol) = symbol {
+            keys.push(VenueKey::Symbol(symbol.to_string()));
+        }
+        [session, ""]
+            .into_iter()
+            .flat_map(|session| {
+                keys.iter()
+                    .map(move |key| (session.to_string(), key.clone()))
+            })
+            .find_map(|key| self.internal.get(&key))
+            .map(String::as_str)
+    }
+
+    /// Give a message about to be sent the venue's names (on_msg_to_app);
+    /// whether it changed
+    pub fn outbound(&self, msg: &mut Message, session: &SessionId) -> Result<bool, QuickFixError> {
+        let Some(symbol) = msg.get_field(55) else {
+            return Ok(false);
+        };
+        let Some(venue) = self.to_venue(&session_label(session), &symbol) else {
+            return Ok(false);
+        };
+        if venue.symbol == symbol && venue.security_id.is_none() {
+            return Ok(false);
+        }
+        msg.set_field(55, venue.symbol.as_str())?;
+        if let Some((id, source)) = &venue.security_id {
+            msg.set_field(48, id.as_str())?;
+            msg.set_field(22, source.as_str())?;
+        }
+        Ok(true)
+    }
+
+    /// A copy of a message received with the internal Symbol
+    /// (on_msg_from_app), None when it needs none
+    pub fn inbound(
+        &self,
+        msg: &Message,
+        session: &SessionId,
+    ) -> Result<Option<Message>, QuickFixError> {
+        let symbol = msg.get_field(55);
+        let security_id = msg.get_field(48).zip(msg.get_field(22));
+        let internal = self.to_internal(
+            &session_label(session),
+            symbol.as_deref(),
+            security_id
+                .as_ref()
+                .map(|(id, source)| (id.as_str(), source.as_str())),
+        );
+        match internal {
+            Some(internal) if symbol.as_deref() != Some(internal) => {
+                let mut copy = Message::try_from_text(&msg.to_fix_string()?)?;
+                copy.set_field(55, internal)?;
+                Ok(Some(copy))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+fn session_or_all(session: &str) -> &str {
+    if session.is_empty() {
+        "every session"
+    } else {
+        session
+    }
+}
+
+/// The values of a CSV line: commas outside double quotes separate them,
+/// `""` inside is a quote
+fn split_csv(line: &str) -> Result<Vec<String>, String> {
+    let mut values = Vec::new();
+    let mut value = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                value.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => values.push(std::mem::take(&mut value)),
+            c => value.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".into());
+    }
+    values.push(value);
+    Ok(values)
+}
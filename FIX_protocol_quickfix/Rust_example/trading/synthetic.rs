@@ -0,0 +1,345 @@
+// =============================================================================
+// Synthetic Instruments (Baskets and Indices)
+// =============================================================================
+// A synthetic instrument is a weighted basket of real ones, traded and priced
+// as if it were listed:
+//
+//   TECH=AAPL:0.5,MSFT:0.3,NVDA:0.2      long-only basket
+//   PAIR=AAPL:1,MSFT:-1.8                spread (negative weight = short leg)
+//   IDX=AAPL:2,MSFT:1/3                  index with divisor 3
+//
+// Pricing: the SyntheticBook keeps the top of book of every constituent and
+// reprices the synthetics containing a symbol when its quote changes. The
+// synthetic bid is what selling one unit would fetch (bids of the long legs,
+// asks of the short ones), the ask what buying one would cost:
+//
+//   bid = (sum w * bid_i for w > 0 + sum w * ask_i for w < 0) / divisor
+//   ask = (sum w * ask_i for w > 0 + sum w * bid_i for w < 0) / divisor
+//
+// Routing: an order on a synthetic is never sent as such. `decompose` turns
+// it into one child limit order per leg, quantity * |weight| rounded to whole
+// units, the side flipped for short legs, priced at the leg's far touch (so
+// the basket trades together). Each child then goes through risk and the OMS
+// like any other order.
+// =============================================================================
+
+use std::{collections::HashMap, error::Error, fmt, str::FromStr};
+
+use crate::oms::Side;
+
+// =============================================================================
+// Error Type
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum SyntheticError {
+    /// Not `NAME=SYMBOL:WEIGHT,...[/DIVISOR]`
+    InvalidDefinition(String),
+
+    /// A weight that is zero, a divisor that is not positive, or not a
+    /// finite number
+    InvalidWeight(String),
+
+    /// The same symbol twice in a basket, or a basket containing itself
+    DuplicateSymbol(String),
+
+    /// No synthetic instrument with this symbol
+    UnknownInstrument(String),
+
+    /// A leg has no quote yet, so the order cannot be priced
+    NoPrice(String),
+
+    /// Every leg rounds to a quantity of zero
+    QuantityTooSmall,
+}
+
+impl fmt::Display for SyntheticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntheticError::InvalidDefinition(x) => {
+                write!(f, "expected NAME=SYMBOL:WEIGHT,...[/DIVISOR]: {x}")
+            }
+            SyntheticError::InvalidWeight(x) => write!(f, "invalid weight or divisor: {x}"),
+            SyntheticError::DuplicateSymbol(x) => write!(f, "{x} appears twice in the basket"),
+            SyntheticError::UnknownInstrument(x) => write!(f, "no synthetic instrument {x}"),
+            SyntheticError::NoPrice(x) => write!(f, "no quote for leg {x}"),
+            SyntheticError::QuantityTooSmall => write!(f, "quantity rounds to zero on every leg"),
+        }
+    }
+}
+
+impl Error for SyntheticError {}
+
+// =============================================================================
+// Definitions
+// =============================================================================
+
+/// One constituent of a basket
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leg {
+    pub symbol: String,
+
+    /// Units of the constituent per unit of the synthetic; negative for a
+    /// short leg
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntheticInstrument {
+    pub symbol: String,
+    pub legs: Vec<Leg>,
+
+    /// The basket value is divided by this (1 unless given); positive
+    pub divisor: f64,
+}
+
+impl SyntheticInstrument {
+    /// # Arguments
+    /// * `symbol` - Name the synthetic is quoted and traded under
+    /// * `legs` - `(symbol, weight)` of each constituent
+    pub fn new<S>(symbol: &str, legs: Vec<(S, f64)>) -> Result<Self, SyntheticError>
+    where
+        S: Into<String>,
+    {
+        let legs: Vec<Leg> = legs
+            .into_iter()
+            .map(|(symbol, weight)| Leg {
+                symbol: symbol.into(),
+                weight,
+            })
+            .collect();
+        if symbol.is_empty() || legs.is_empty() {
+            return Err(SyntheticError::InvalidDefinition(symbol.to_string()));
+        }
+        for (index, leg) in legs.iter().enumerate() {
+            if leg.symbol.is_empty() {
+                return Err(SyntheticError::InvalidDefinition(symbol.to_string()));
+            }
+            if !leg.weight.is_finite() || leg.weight == 0.0 {
+                let leg = format!("{}:{}", leg.symbol, leg.weight);
+                return Err(SyntheticError::InvalidWeight(leg));
+            }
+            if leg.symbol == symbol || legs[..index].iter().any(|x| x.symbol == leg.symbol) {
+                return Err(SyntheticError::DuplicateSymbol(leg.symbol.clone()));
+            }
+        }
+        Ok(Self {
+            symbol: symbol.to_string(),
+            legs,
+            divisor: 1.0,
+        })
+    }
+
+    pub fn with_divisor(mut self, divisor: f64) -> Result<Self, SyntheticError> {
+        if !divisor.is_finite() || divisor <= 0.0 {
+            return Err(SyntheticError::InvalidWeight(divisor.to_string()));
+        }
+        self.divisor = divisor;
+        Ok(self)
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.legs.iter().any(|x| x.symbol == symbol)
+    }
+
+    /// Bid and ask of the synthetic; None until every leg has a quote
+    ///
+    /// # Arguments
+    /// * `top` - Bid and ask of a constituent
+    pub fn price(&self, top: impl Fn(&str) -> Option<(f64, f64)>) -> Option<(f64, f64)> {
+        let (mut bid, mut ask) = (0.0, 0.0);
+        for leg in &self.legs {
+            let (leg_bid, leg_ask) = top(&leg.symbol)?;
+            if leg.weight > 0.0 {
+                bid += leg.weight * leg_bid;
+                ask += leg.weight * leg_ask;
+            } else {
+                bid += leg.weight * leg_ask;
+                ask += leg.weight * leg_bid;
+            }
+        }
+        Some((bid / self.divisor, ask / self.divisor))
+    }
+
+    /// Units of the synthetic held through its constituents: the number of
+    /// complete baskets, signed; 0 if the legs do not line up
+    ///
+    /// # Arguments
+    /// * `position` - Signed position in a constituent
+    pub fn position(&self, position: impl Fn(&str) -> f64) -> f64 {
+        let units: Vec<f64> = self
+            .legs
+            .iter()
+            .map(|leg| position(&leg.symbol) / leg.weight)
+            .collect();
+        if units.iter().all(|x| *x > 0.0) {
+            units.into_iter().fold(f64::INFINITY, f64::min)
+        } else if units.iter().all(|x| *x < 0.0) {
+            units.into_iter().fold(f64::NEG_INFINITY, f64::max)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// `NAME=SYMBOL:WEIGHT,SYMBOL:WEIGHT[/DIVISOR]`; a missing weight is 1
+impl FromStr for SyntheticInstrument {
+    type Err = SyntheticError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SyntheticError::InvalidDefinition(s.to_string());
+        let (symbol, basket) = s.split_once('=').ok_or_else(invalid)?;
+        let (basket, divisor) = match basket.rsplit_once('/') {
+            Some((basket, divisor)) => (basket, Some(divisor.trim())),
+            None => (basket, None),
+        };
+
+        let mut legs = Vec::new();
+        for leg in basket.split(',') {
+            let (leg_symbol, weight) = match leg.split_once(':') {
+                Some((leg_symbol, weight)) => (leg_symbol, weight.trim()),
+                None => (leg, "1"),
+            };
+            let weight = weight
+                .parse::<f64>()
+                .map_err(|_| SyntheticError::InvalidWeight(leg.trim().to_string()))?;
+            legs.push((leg_symbol.trim(), weight));
+        }
+
+        let instrument = Self::new(symbol.trim(), legs)?;
+        match divisor {
+            Some(divisor) => {
+                let divisor = divisor
+                    .parse::<f64>()
+                    .map_err(|_| SyntheticError::InvalidWeight(divisor.to_string()))?;
+                instrument.with_divisor(divisor)
+            }
+            None => Ok(instrument),
+        }
+    }
+}
+
+// =============================================================================
+// SyntheticBook
+// =============================================================================
+
+/// A constituent order produced by `decompose`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChildOrder {
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    pub price: f64,
+}
+
+/// Tops of book of the constituents, and the synthetics priced from them
+#[derive(Debug, Clone, Default)]
+pub struct SyntheticBook {
+    instruments: Vec<SyntheticInstrument>,
+
+    /// Symbol -> (bid, ask)
+    tops: HashMap<String, (f64, f64)>,
+}
+
+impl SyntheticBook {
+    pub fn new(instruments: Vec<SyntheticInstrument>) -> Self {
+        Self {
+            instruments,
+            tops: HashMap::new(),
+        }
+    }
+
+    pub fn instruments(&self) -> &[SyntheticInstrument] {
+        &self.instruments
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&SyntheticInstrument> {
+        self.instruments.iter().find(|x| x.symbol == symbol)
+    }
+
+    pub fn is_synthetic(&self, symbol: &str) -> bool {
+        self.get(symbol).is_some()
+    }
+
+    /// Every constituent, once, in definition order (what to subscribe to)
+    pub fn constituents(&self) -> Vec<String> {
+        let mut out: Vec<String> = Vec::new();
+        for leg in self.instruments.iter().flat_map(|x| &x.legs) {
+            if !out.contains(&leg.symbol) {
+                out.push(leg.symbol.clone());
+            }
+        }
+        out
+    }
+
+    /// Record a constituent's top of book
+    ///
+    /// # Returns
+    /// `(synthetic, bid, ask)` of every synthetic containing `symbol` that
+    /// can now be priced
+    pub fn on_quote(&mut self, symbol: &str, bid: f64, ask: f64) -> Vec<(String, f64, f64)> {
+        self.tops.insert(symbol.to_string(), (bid, ask));
+        self.instruments
+            .iter()
+            .filter(|x| x.contains(symbol))
+            .filter_map(|x| {
+                let (bid, ask) = x.price(|leg| self.tops.get(leg).copied())?;
+                Some((x.symbol.clone(), bid, ask))
+            })
+            .collect()
+    }
+
+    /// Current bid and ask of a synthetic
+    pub fn price(&self, symbol: &str) -> Option<(f64, f64)> {
+        self.get(symbol)?.price(|leg| self.tops.get(leg).copied())
+    }
+
+    /// Split an order on a synthetic into child orders on its legs
+    ///
+    /// # Arguments
+    /// * `symbol` - The synthetic
+    /// * `side` - Side of the synthetic order; short legs trade the other side
+    /// * `quantity` - Units of the synthetic
+    pub fn decompose(
+        &self,
+        symbol: &str,
+        side: Side,
+        quantity: f64,
+    ) -> Result<Vec<ChildOrder>, SyntheticError> {
+        let instrument = self
+            .get(symbol)
+            .ok_or_else(|| SyntheticError::UnknownInstrument(symbol.to_string()))?;
+
+        let mut children = Vec::with_capacity(instrument.legs.len());
+        for leg in &instrument.legs {
+            let (bid, ask) = self
+                .tops
+                .get(&leg.symbol)
+                .copied()
+                .ok_or_else(|| SyntheticError::NoPrice(leg.symbol.clone()))?;
+            let child_quantity = (quantity * leg.weight).abs().round();
+            if child_quantity == 0.0 {
+                continue;
+            }
+
+            let buys = (side == Side::Buy) == (leg.weight > 0.0);
+            let (side, price) = if buys {
+                (Side::Buy, ask)
+            } else {
+                (Side::Sell, bid)
+            };
+            children.push(ChildOrder {
+                symbol: leg.symbol.clone(),
+                side,
+                quantity: child_quantity,
+                price,
+            });
+        }
+
+        if children.is_empty() {
+            return Err(SyntheticError::QuantityTooSmall);
+        }
+        Ok(children)
+    }
+}
@@ -0,0 +1,718 @@
+// =============================================================================
+// Integration Test Harness
+// =============================================================================
+// Session logic verified by `cargo test` instead of two terminals: run_pair
+// starts an acceptor ("venue") and an initiator ("client") in-process, on an
+// ephemeral port with scratch message stores, waits until both are logged
+// on, then runs a script against them:
+//
+//   #[test]
+//   fn venue_acknowledges_orders() -> Result<(), HarnessError> {
+//       let config = PairConfig::default();
+//       run_pair(&config, MyVenue::default(), NoApp, |pair| {
+//           pair.send_from_client(message("D", &[(11, "ORD-1"), (55, "AAPL")])?)?;
+//           let report = pair.client().expect_app("8", DEFAULT_TIMEOUT)?;
+//           assert_eq!(report.get(11), Some("ORD-1"));
+//           Ok(())
+//       })?
+//   }
+//
+// Each end's application is wrapped in a Recording, which passes every
+// callback on and keeps a decoded copy (kind, session, FixMessage). Scripts
+// assert on it in order: `expect` waits for the next recorded callback
+// matching a predicate, after the one the previous `expect` returned, so a
+// script reads like the exchange it checks.
+//
+// Pairs do not share anything (port, CompIDs, store directory), so tests can
+// run in parallel. Messages are not validated against a data dictionary
+// unless one is configured.
+//
+// budget: latency and throughput limits (order acks through a pair like
+// these, the matching engine alone) that fail a test when they are broken
+//
+// hammer: the callbacks of an application called from many sessions at
+// once, as FixSocketServerKind::MultiThreaded does, to find what it shares
+// without a lock
+// =============================================================================
+
+use std::{
+    env,
+    error::Error,
+    fmt, fs, io,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Condvar, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use quickfix::{dictionary_item::*, *};
+
+use crate::{
+    bench::StoreKind,
+    session::{events::FixMessage, session_label, Direction},
+};
+
+pub mod budget;
+pub mod hammer;
+
+/// How long `expect` waits when a script has no better idea
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the pair may take to log on
+const LOGON_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Numbers the scratch directories of the pairs of this process
+static PAIRS: AtomicUsize = AtomicUsize::new(0);
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum HarnessError {
+    /// No free port, or the store directory cannot be created
+    Io(io::Error),
+
+    /// The pair could not be built, or a message not built or sent
+    QuickFix(QuickFixError),
+
+    /// Both ends were not logged on within LOGON_TIMEOUT
+    LogonTimeout,
+
+    /// Nothing matched an `expect` in time
+    Timeout {
+        /// What the script waited for
+        expected: String,
+
+        /// Every callback recorded after the previous match, as text
+        seen: Vec<String>,
+    },
+
+    /// Something matched an `expect_none`
+    Unexpected(String),
+}
+
+impl fmt::Display for HarnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HarnessError::Io(err) => write!(f, "I/O: {err}"),
+            HarnessError::QuickFix(err) => write!(f, "quickfix: {err:?}"),
+            HarnessError::LogonTimeout => {
+                write!(f, "no logon within {}s", LOGON_TIMEOUT.as_secs())
+            }
+            HarnessError::Timeout { expected, seen } => {
+                write!(f, "timed out waiting for {expected}; recorded meanwhile:")?;
+                if seen.is_empty() {
+                    write!(f, " nothing")?;
+                }
+                for line in seen {
+                    write!(f, "\n  {line}")?;
+                }
+                Ok(())
+            }
+            HarnessError::Unexpected(event) => write!(f, "unexpected callback: {event}"),
+        }
+    }
+}
+
+impl Error for HarnessError {}
+
+impl From<io::Error> for HarnessError {
+    fn from(err: io::Error) -> Self {
+        HarnessError::Io(err)
+    }
+}
+
+impl From<QuickFixError> for HarnessError {
+    fn from(err: QuickFixError) -> Self {
+        HarnessError::QuickFix(err)
+    }
+}
+
+// =============================================================================
+// Configuration
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct PairConfig {
+    pub begin_string: String,
+    pub venue_comp_id: String,
+    pub client_comp_id: String,
+    pub store: StoreKind,
+    pub heart_bt_int: u32,
+
+    /// Validate messages against this dictionary (both ends)
+    pub data_dictionary: Option<PathBuf>,
+
+    /// More `[DEFAULT]` settings for both ends, e.g. ResetOnLogon
+    pub settings: Vec<(String, String)>,
+
+    /// Run both ends with FixSocketServerKind::MultiThreaded
+    ///
+    /// One session a side: each application is still called from one thread
+    /// at a time. hammer is the test for concurrent callbacks.
+    pub multi_threaded: bool,
+}
+
+impl Default for PairConfig {
+    fn default() -> Self {
+        Self {
+            begin_string: "FIX.4.4".to_string(),
+            venue_comp_id: "VENUE".to_string(),
+            client_comp_id: "CLIENT".to_string(),
+            store: StoreKind::File,
+            heart_bt_int: 30,
+            data_dictionary: None,
+            settings: Vec::new(),
+            multi_threaded: false,
+        }
+    }
+}
+
+impl PairConfig {
+    pub fn with_comp_ids(mut self, venue: &str, client: &str) -> Self {
+        self.venue_comp_id = venue.to_string();
+        self.client_comp_id = client.to_string();
+        self
+    }
+
+    pub fn with_begin_string(mut self, begin_string: &str) -> Self {
+        self.begin_string = begin_string.to_string();
+        self
+    }
+
+    pub fn with_store(mut self, store: StoreKind) -> Self {
+        self.store = store;
+        self
+    }
+
+    pub fn with_heart_bt_int(mut self, seconds: u32) -> Self {
+        self.heart_bt_int = seconds;
+        self
+    }
+
+    pub fn with_data_dictionary(mut self, path: impl Into<PathBuf>) -> Self {
+        self.data_dictionary = Some(path.into());
+        self
+    }
+
+    pub fn with_setting(mut self, key: &str, value: &str) -> Self {
+        self.settings.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_multi_threaded(mut self) -> Self {
+        self.multi_threaded = true;
+        self
+    }
+}
+
+// =============================================================================
+// Recording
+// =============================================================================
+
+/// Which ApplicationCallback method ran
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallbackKind {
+    Create,
+    Logon,
+    Logout,
+    ToAdmin,
+    ToApp,
+    FromAdmin,
+    FromApp,
+}
+
+/// One callback, as recorded
+#[derive(Debug, Clone)]
+pub struct Recorded {
+    pub kind: CallbackKind,
+
+    /// Session label (`FIX.4.4:VENUE->CLIENT`)
+    pub session: String,
+
+    /// The message, for the to_* / from_* callbacks
+    pub message: Option<FixMessage>,
+}
+
+impl Recorded {
+    /// MsgType (tag 35) of the message, if any
+    pub fn msg_type(&self) -> Option<&str> {
+        self.message.as_ref().map(FixMessage::msg_type)
+    }
+
+    /// A tag of the message, if any
+    pub fn get(&self, tag: i32) -> Option<&str> {
+        self.message.as_ref()?.get(tag)
+    }
+
+    /// `FromApp FIX.4.4:CLIENT->VENUE 8=FIX.4.4|35=8|...`
+    pub fn to_text(&self) -> String {
+        match &self.message {
+            Some(message) => format!("{:?} {} {}", self.kind, self.session, message.to_text()),
+            None => format!("{:?} {}", self.kind, self.session),
+        }
+    }
+}
+
+/// An application under test, with every callback it receives recorded
+pub struct Recording<A> {
+    inner: A,
+    log: Mutex<Vec<Recorded>>,
+    changed: Condvar,
+
+    /// Index after the last `expect` match
+    cursor: AtomicUsize,
+}
+
+impl<A> Recording<A> {
+    pub fn new(inner: A) -> Self {
+        Self {
+            inner,
+            log: Mutex::new(Vec::new()),
+            changed: Condvar::new(),
+            cursor: AtomicUsize::new(0),
+        }
+    }
+
+    /// The wrapped application, to check its own state
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Recorded>> {
+        self.log.lock().expect("recording lock poisoned")
+    }
+
+    fn record(&self, kind: CallbackKind, session: &SessionId, message: Option<FixMessage>) {
+        self.lock().push(Recorded {
+            kind,
+            session: session_label(session),
+            message,
+        });
+        self.changed.notify_all();
+    }
+
+    fn record_message(&self, kind: CallbackKind, session: &SessionId, msg: &Message) {
+        let (direction, admin) = match kind {
+            CallbackKind::ToAdmin => (Direction::Outbound, true),
+            CallbackKind::ToApp => (Direction::Outbound, false),
+            CallbackKind::FromAdmin => (Direction::Inbound, true),
+            _ => (Direction::Inbound, false),
+        };
+        let message = FixMessage::decode(session, direction, admin, msg);
+        self.record(kind, session, Some(message));
+    }
+
+    /// Everything recorded so far
+    pub fn events(&self) -> Vec<Recorded> {
+        self.lock().clone()
+    }
+
+    /// Wait for a callback matching `predicate`, from index `from` on
+    fn wait<F>(&self, from: usize, timeout: Duration, predicate: F) -> Result<usize, Vec<String>>
+    where
+        F: Fn(&Recorded) -> bool,
+    {
+        let deadline = Instant::now() + timeout;
+        let mut log = self.lock();
+        loop {
+            let start = from.min(log.len());
+            if let Some(index) = log[start..].iter().position(&predicate) {
+                return Ok(start + index);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(log[start..].iter().map(Recorded::to_text).collect());
+            }
+            log = self
+                .changed
+                .wait_timeout(log, deadline - now)
+                .expect("recording lock poisoned")
+                .0;
+        }
+    }
+
+    /// Wait for the next callback matching `predicate`, after the previous
+    /// match
+    ///
+    /// # Arguments
+    /// * `expected` - What is waited for, for the error message
+    pub fn expect<F>(
+        &self,
+        expected: &str,
+        timeout: Duration,
+        predicate: F,
+    ) -> Result<Recorded, HarnessError>
+    where
+        F: Fn(&Recorded) -> bool,
+    {
+        let from = self.cursor.load(Ordering::Acquire);
+        match self.wait(from, timeout, predicate) {
+            Ok(index) => {
+                self.cursor.store(index + 1, Ordering::Release);
+                Ok(self.lock()[index].clone())
+            }
+            Err(seen) => Err(HarnessError::Timeout {
+                expected: expected.to_string(),
+                seen,
+            }),
+        }
+    }
+
+    /// Wait for the next application message received, of this MsgType
+    pub fn expect_app(&self, msg_type: &str, timeout: Duration) -> Result<Recorded, HarnessError> {
+        self.expect(&format!("application message 35={msg_type}"), timeout, |x| {
+            x.kind == CallbackKind::FromApp && x.msg_type() == Some(msg_type)
+        })
+    }
+
+    /// Wait for the next admin message received, of this MsgType
+    pub fn expect_admin(
+        &self,
+        msg_type: &str,
+        timeout: Duration,
+    ) -> Result<Recorded, HarnessError> {
+        self.expect(&format!("admin message 35={msg_type}"), timeout, |x| {
+            x.kind == CallbackKind::FromAdmin && x.msg_type() == Some(msg_type)
+        })
+    }
+
+    /// Fail if a callback matching `predicate` is recorded within `period`,
+    /// after the previous match (which stays where it is)
+    pub fn expect_none<F>(&self, period: Duration, predicate: F) -> Result<(), HarnessError>
+    where
+        F: Fn(&Recorded) -> bool,
+    {
+        let from = self.cursor.load(Ordering::Acquire);
+        match self.wait(from, period, predicate) {
+            Ok(index) => Err(HarnessError::Unexpected(self.lock()[index].to_text())),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl<A: ApplicationCallback> ApplicationCallback for Recording<A> {
+    fn on_create(&self, session: &SessionId) {
+        self.record(CallbackKind::Create, session, None);
+        self.inner.on_create(session);
+    }
+
+    fn on_logon(&self, session: &SessionId) {
+        self.record(CallbackKind::Logon, session, None);
+        self.inner.on_logon(session);
+    }
+
+    fn on_logout(&self, session: &SessionId) {
+        self.record(CallbackKind::Logout, session, None);
+        self.inner.on_logout(session);
+    }
+
+    fn on_msg_to_admin(&self, msg: &mut Message, session: &SessionId) {
+        self.inner.on_msg_to_admin(msg, session);
+        self.record_message(CallbackKind::ToAdmin, session, msg);
+    }
+
+    fn on_msg_to_app(&self, msg: &mut Message, session: &SessionId) -> Result<(), MsgToAppError> {
+        let result = self.inner.on_msg_to_app(msg, session);
+        self.record_message(CallbackKind::ToApp, session, msg);
+        result
+    }
+
+    fn on_msg_from_admin(
+        &self,
+        msg: &Message,
+        session: &SessionId,
+    ) -> Result<(), MsgFromAdminError> {
+        self.record_message(CallbackKind::FromAdmin, session, msg);
+        self.inner.on_msg_from_admin(msg, session)
+    }
+
+    fn on_msg_from_app(&self, msg: &Message, session: &SessionId) -> Result<(), MsgFromAppError> {
+        self.record_message(CallbackKind::FromApp, session, msg);
+        self.inner.on_msg_from_app(msg, session)
+    }
+}
+
+/// An end with no application logic of its own, only recorded
+#[derive(Debug, Default)]
+pub struct NoApp;
+
+impl ApplicationCallback for NoApp {}
+
+// =============================================================================
+// Pair
+// =============================================================================
+
+/// The running pair, as seen by a script
+pub struct Pair<'a, V, C> {
+    venue: &'a Recording<V>,
+    client: &'a Recording<C>,
+    venue_session: SessionId,
+    client_session: SessionId,
+}
+
+impl<V, C> Pair<'_, V, C> {
+    pub fn venue(&self) -> &Recording<V> {
+        self.venue
+    }
+
+    pub fn client(&self) -> &Recording<C> {
+        self.client
+    }
+
+    /// The acceptor's session (VENUE->CLIENT)
+    pub fn venue_session(&self) -> &SessionId {
+        &self.venue_session
+    }
+
+    /// The initiator's session (CLIENT->VENUE)
+    pub fn client_session(&self) -> &SessionId {
+        &self.client_session
+    }
+
+    pub fn send_from_client(&self, msg: Message) -> Result<(), HarnessError> {
+        send_to_target(msg, &self.client_session)?;
+        Ok(())
+    }
+
+    pub fn send_from_venue(&self, msg: Message) -> Result<(), HarnessError> {
+        send_to_target(msg, &self.venue_session)?;
+        Ok(())
+    }
+}
+
+/// Build a message: MsgType in the header, then the fields in order
+pub fn message(msg_type: &str, fields: &[(i32, &str)]) -> Result<Message, QuickFixError> {
+    let mut msg = Message::new();
+    msg.with_header_mut(|h| h.set_field(35, msg_type))?;
+    for (tag, value) in fields {
+        msg.set_field(*tag, *value)?;
+    }
+    Ok(msg)
+}
+
+// =============================================================================
+// Run
+// =============================================================================
+
+/// Start a pair, wait for both logons, run `script`, stop the pair
+///
+/// The store directory is removed afterwards, whatever the outcome.
+///
+/// # Arguments
+/// * `config` - CompIDs, store and settings of both ends
+/// * `venue` - The acceptor's application
+/// * `client` - The initiator's application
+/// * `script` - Sends and expects; its result is returned as is
+pub fn run_pair<V, C, T, F>(
+    config: &PairConfig,
+    venue: V,
+    client: C,
+    script: F,
+) -> Result<T, HarnessError>
+where
+    V: ApplicationCallback,
+    C: ApplicationCallback,
+    F: FnOnce(&Pair<'_, V, C>) -> T,
+{
+    let store_path = env::temp_dir().join(format!(
+        "trading_pair_{}_{}",
+        process::id(),
+        PAIRS.fetch_add(1, Ordering::Relaxed)
+    ));
+    if config.store == StoreKind::File {
+        fs::create_dir_all(&store_path)?;
+    }
+    let result = start_pair(config, venue, client, &store_path, script);
+    if config.store == StoreKind::File {
+        let _ = fs::remove_dir_all(&store_path);
+    }
+    result
+}
+
+fn start_pair<V, C, T, F>(
+    config: &PairConfig,
+    venue: V,
+    client: C,
+    store_path: &Path,
+    script: F,
+) -> Result<T, HarnessError>
+where
+    V: ApplicationCallback,
+    C: ApplicationCallback,
+    F: FnOnce(&Pair<'_, V, C>) -> T,
+{
+    let port = ephemeral_port()?;
+    let venue_session = SessionId::try_new(
+        &config.begin_string,
+        &config.venue_comp_id,
+        &config.client_comp_id,
+        "",
+    )?;
+    let client_session = SessionId::try_new(
+        &config.begin_string,
+        &config.client_comp_id,
+        &config.venue_comp_id,
+        "",
+    )?;
+
+    let venue_settings = build_settings(
+        config,
+        &venue_session,
+        &[&ConnectionType::Acceptor, &SocketAcceptPort(port)],
+        &store_path.join("venue"),
+    )?;
+    let client_settings = build_settings(
+        config,
+        &client_session,
+        &[
+            &ConnectionType::Initiator,
+            &ReconnectInterval(1),
+            &SocketConnectHost("127.0.0.1"),
+            &SocketConnectPort(port),
+        ],
+        &store_path.join("client"),
+    )?;
+
+    let venue = Recording::new(venue);
+    let venue_app = Application::try_new(&venue)?;
+    let venue_log = LogFactory::try_new(&NullLogger)?;
+    let client = Recording::new(client);
+    let client_app = Application::try_new(&client)?;
+    let client_log = LogFactory::try_new(&NullLogger)?;
+
+    let pair = Pair {
+        venue: &venue,
+        client: &client,
+        venue_session,
+        client_session,
+    };
+
+    // The factories differ in type, hence the two branches
+    match config.store {
+        StoreKind::Memory => {
+            let venue_store = MemoryMessageStoreFactory::new();
+            let client_store = MemoryMessageStoreFactory::new();
+            let mut acceptor = Acceptor::try_new(
+                &venue_settings,
+                &venue_app,
+                &venue_store,
+                &venue_log,
+                server_kind(config),
+            )?;
+            let mut initiator = Initiator::try_new(
+                &client_settings,
+                &client_app,
+                &client_store,
+                &client_log,
+                server_kind(config),
+            )?;
+            drive(&pair, &mut acceptor, &mut initiator, script)
+        }
+        StoreKind::File => {
+            let venue_store = FileMessageStoreFactory::try_new(&venue_settings)?;
+            let client_store = FileMessageStoreFactory::try_new(&client_settings)?;
+            let mut acceptor = Acceptor::try_new(
+                &venue_settings,
+                &venue_app,
+                &venue_store,
+                &venue_log,
+                server_kind(config),
+            )?;
+            let mut initiator = Initiator::try_new(
+                &client_settings,
+                &client_app,
+                &client_store,
+                &client_log,
+                server_kind(config),
+            )?;
+            drive(&pair, &mut acceptor, &mut initiator, script)
+        }
+    }
+}
+
+/// Start both ends, wait for the logons, run the script, stop both ends
+fn drive<V, C, T, F, A, I>(
+    pair: &Pair<'_, V, C>,
+    acceptor: &mut A,
+    initiator: &mut I,
+    script: F,
+) -> Result<T, HarnessError>
+where
+    F: FnOnce(&Pair<'_, V, C>) -> T,
+    A: ConnectionHandler,
+    I: ConnectionHandler,
+{
+    acceptor.start()?;
+    initiator.start()?;
+
+    let is_logon = |x: &Recorded| x.kind == CallbackKind::Logon;
+    let logged_on = pair.client.wait(0, LOGON_TIMEOUT, is_logon).is_ok()
+        && pair.venue.wait(0, LOGON_TIMEOUT, is_logon).is_ok();
+    let outcome = if logged_on {
+        Ok(script(pair))
+    } else {
+        Err(HarnessError::LogonTimeout)
+    };
+
+    // Harmless if the pair never logged on
+    let _ = initiator.stop();
+    let _ = acceptor.stop();
+    outcome
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Ask the OS for a free TCP port
+fn ephemeral_port() -> io::Result<u16> {
+    // The listener is dropped right away; the acceptor binds the port next
+    Ok(TcpListener::bind(("127.0.0.1", 0))?.local_addr()?.port())
+}
+
+/// Settings for one end: `[DEFAULT]` from `items` and the config, plus the
+/// one session
+fn build_settings(
+    config: &PairConfig,
+    session: &SessionId,
+    items: &[&dyn DictionaryItem],
+    store_path: &Path,
+) -> Result<SessionSettings, QuickFixError> {
+    let mut defaults = Dictionary::try_from_items(items)?;
+    defaults.set("StartTime", "00:00:00")?;
+    defaults.set("EndTime", "00:00:00")?;
+    defaults.set("HeartBtInt", config.heart_bt_int.to_string().as_str())?;
+    match &config.data_dictionary {
+        Some(path) => {
+            defaults.set("UseDataDictionary", "Y")?;
+            defaults.set("DataDictionary", path.to_string_lossy().as_ref())?;
+        }
+        None => defaults.set("UseDataDictionary", "N")?,
+    }
+    if config.store == StoreKind::File {
+        defaults.set("FileStorePath", store_path.to_string_lossy().as_ref())?;
+    }
+    for (key, value) in &config.settings {
+        defaults.set(key, value.as_str())?;
+    }
+
+    let mut settings = SessionSettings::new();
+    settings.set(None, defaults)?;
+    settings.set(Some(session), Dictionary::new())?;
+    Ok(settings)
+}
+
+fn server_kind(config: &PairConfig) -> FixSocketServerKind {
+    if config.multi_threaded {
+        FixSocketServerKind::MultiThreaded
+    } else {
+        FixSocketServerKind::SingleThreaded
+    }
+}
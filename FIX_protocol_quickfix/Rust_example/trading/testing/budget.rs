@@ -0,0 +1,534 @@
+// =============================================================================
+// Latency Budgets
+// =============================================================================
+// What the engine must sustain, asserted by `cargo test` so that a slower
+// build fails CI instead of showing up in production. Two measurements:
+//
+//   order ack   NewOrderSingle -> ExecutionReport round trips through an
+//               in-process acceptor / initiator pair (trading::bench), with
+//               limits on p50, p99, max and round trips per second
+//   matching    orders per second through the matching engine alone
+//               (trading::sim), no FIX involved
+//
+//   #[test]
+//   fn latency_within_budget() -> Result<(), BudgetError> {
+//       let budget = LatencyBudget::load(Path::new("latency_budget.toml"))?;
+//       let report = check_budget(&budget.scaled_by_env())?;
+//       report.print();
+//       report.assert_within()
+//   }
+//
+// Budgets come from a file, so CI and a developer's machine share them, or
+// from the `with_*` methods. Only this subset of TOML is read: `key = value`
+// lines and `#` comments. Durations take ns, us, ms or s; unset limits are
+// not checked:
+//
+//   round_trips = 20000          # measured, after the warmup
+//   warmup = 1000
+//   in_flight = 1                # 1: latency alone, no queueing
+//   store = "memory"             # or "file"
+//   ack_p50 = "250us"
+//   ack_p99 = "2ms"
+//   ack_max = "50ms"
+//   min_round_trips_per_sec = 2000
+//   matching_orders = 200000     # 0: no matching measurement
+//   min_matching_orders_per_sec = 100000
+//
+// Time is the real clock (Instant): a budget is about wall time. Shared CI
+// runners and debug builds are slower than the machine the budget was set
+// on; LATENCY_BUDGET_SCALE=3 (scaled_by_env) multiplies every latency limit
+// by 3 and divides every rate by 3 rather than editing the file. Run the
+// budget tests on their own (`cargo test --release latency -- --test-threads=1`):
+// other tests running alongside share the cores being measured.
+// =============================================================================
+
+use std::{
+    env,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bench::{run_throughput, BenchError, Load, StoreKind, ThroughputOptions, ThroughputReport},
+    sim::{
+        matching::{ExecKind, MatchingEngine, NewOrder, Side},
+        venue::VenueProfile,
+    },
+};
+
+/// Factor applied by `scaled_by_env`
+pub const SCALE_ENV: &str = "LATENCY_BUDGET_SCALE";
+
+/// Round trips measured when the budget does not say
+pub const DEFAULT_ROUND_TRIPS: u64 = 20_000;
+
+/// Round trips left out of the figures when the budget does not say
+pub const DEFAULT_WARMUP: u64 = 1_000;
+
+/// Orders through the matching engine when the budget does not say
+pub const DEFAULT_MATCHING_ORDERS: u64 = 200_000;
+
+/// Price the matching orders are spread around
+const MATCHING_MID: f64 = 100.0;
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BudgetError {
+    /// The budget file cannot be read
+    Io(PathBuf, io::Error),
+
+    /// A line of the budget file is not understood
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+
+    /// The in-process pair could not run
+    Bench(BenchError),
+
+    /// Measured, and over budget: the checks that failed
+    Breached(Vec<BudgetCheck>),
+}
+
+impl fmt::Display for BudgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            BudgetError::Syntax {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+            BudgetError::Bench(err) => write!(f, "benchmark: {err}"),
+            BudgetError::Breached(checks) => {
+                write!(f, "over budget:")?;
+                for check in checks {
+                    write!(f, "\n  {check}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for BudgetError {}
+
+impl From<BenchError> for BudgetError {
+    fn from(err: BenchError) -> Self {
+        BudgetError::Bench(err)
+    }
+}
+
+// =============================================================================
+// Budget
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct LatencyBudget {
+    /// Load of the order ack measurement
+    pub throughput: ThroughputOptions,
+
+    /// Order ack round trip limits
+    pub ack_p50: Option<Duration>,
+    pub ack_p99: Option<Duration>,
+    pub ack_max: Option<Duration>,
+    pub min_round_trips_per_sec: Option<f64>,
+
+    /// Orders through the matching engine; 0 skips the measurement
+    pub matching_orders: u64,
+    pub min_matching_orders_per_sec: Option<f64>,
+}
+
+impl Default for LatencyBudget {
+    /// The loads, without any limit
+    fn default() -> Self {
+        Self {
+            throughput: ThroughputOptions {
+                load: Load::RoundTrips(DEFAULT_ROUND_TRIPS),
+                warmup: DEFAULT_WARMUP,
+                max_in_flight: 1,
+                ..ThroughputOptions::default()
+            },
+            ack_p50: None,
+            ack_p99: None,
+            ack_max: None,
+            min_round_trips_per_sec: None,
+            matching_orders: DEFAULT_MATCHING_ORDERS,
+            min_matching_orders_per_sec: None,
+        }
+    }
+}
+
+impl LatencyBudget {
+    /// Read a budget file
+    pub fn load(path: &Path) -> Result<Self, BudgetError> {
+        let source =
+            fs::read_to_string(path).map_err(|err| BudgetError::Io(path.to_path_buf(), err))?;
+        Self::parse(path, &source)
+    }
+
+    /// Read the source of a budget file (`path` is only for errors)
+    pub fn parse(path: &Path, source: &str) -> Result<Self, BudgetError> {
+        let mut budget = Self::default();
+        for (index, line) in source.lines().enumerate() {
+            let syntax = |message: String| BudgetError::Syntax {
+                path: path.to_path_buf(),
+                line: index + 1,
+                message,
+            };
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| syntax("expected key = value".to_string()))?;
+            let (key, value) = (key.trim(), value.trim().trim_matches('"'));
+            let count = || {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| syntax(format!("{key} must be a whole number: {value}")))
+            };
+            let duration = || {
+                parse_duration(value).ok_or_else(|| {
+                    syntax(format!("{key} must be a duration (250us, 2ms): {value}"))
+                })
+            };
+            let rate = || {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|x| *x > 0.0)
+                    .ok_or_else(|| syntax(format!("{key} must be a positive number: {value}")))
+            };
+
+            match key {
+                "round_trips" => budget.throughput.load = Load::RoundTrips(count()?),
+                "duration" => budget.throughput.load = Load::Duration(duration()?),
+                "warmup" => budget.throughput.warmup = count()?,
+                "in_flight" => budget.throughput.max_in_flight = count()?.max(1),
+                "store" => {
+                    budget.throughput.store = StoreKind::from_name(value)
+                        .ok_or_else(|| syntax(format!("store must be memory or file: {value}")))?;
+                }
+                "multi_threaded" => {
+                    budget.throughput.multi_threaded = match value {
+                        "true" => true,
+                        "false" => false,
+                        _ => return Err(syntax(format!("{key} must be true or false"))),
+                    };
+                }
+                "ack_p50" => budget.ack_p50 = Some(duration()?),
+                "ack_p99" => budget.ack_p99 = Some(duration()?),
+                "ack_max" => budget.ack_max = Some(duration()?),
+                "min_round_trips_per_sec" => budget.min_round_trips_per_sec = Some(rate()?),
+                "matching_orders" => budget.matching_orders = count()?,
+                "min_matching_orders_per_sec" => {
+                    budget.min_matching_orders_per_sec = Some(rate()?);
+                }
+                _ => return Err(syntax(format!("unknown key {key}"))),
+            }
+        }
+        Ok(budget)
+    }
+
+    pub fn with_load(mut self, load: Load) -> Self {
+        self.throughput.load = load;
+        self
+    }
+
+    pub fn with_store(mut self, store: StoreKind) -> Self {
+        self.throughput.store = store;
+        self
+    }
+
+    pub fn with_ack_p50(mut self, limit: Duration) -> Self {
+        self.ack_p50 = Some(limit);
+        self
+    }
+
+    pub fn with_ack_p99(mut self, limit: Duration) -> Self {
+        self.ack_p99 = Some(limit);
+        self
+    }
+
+    pub fn with_ack_max(mut self, limit: Duration) -> Self {
+        self.ack_max = Some(limit);
+        self
+    }
+
+    pub fn with_min_round_trips_per_sec(mut self, rate: f64) -> Self {
+        self.min_round_trips_per_sec = Some(rate);
+        self
+    }
+
+    /// Orders through the matching engine and the rate they must reach
+    pub fn with_matching(mut self, orders: u64, min_per_sec: f64) -> Self {
+        self.matching_orders = orders;
+        self.min_matching_orders_per_sec = Some(min_per_sec);
+        self
+    }
+
+    /// Latency limits times `factor`, rates divided by it
+    pub fn scaled(mut self, factor: f64) -> Self {
+        let factor = factor.max(f64::EPSILON);
+        for limit in [&mut self.ack_p50, &mut self.ack_p99, &mut self.ack_max] {
+            *limit = limit.map(|x| x.mul_f64(factor));
+        }
+        for rate in [
+            &mut self.min_round_trips_per_sec,
+            &mut self.min_matching_orders_per_sec,
+        ] {
+            *rate = rate.map(|x| x / factor);
+        }
+        self
+    }
+
+    /// Scaled by LATENCY_BUDGET_SCALE, if set to a positive number
+    pub fn scaled_by_env(self) -> Self {
+        let factor = env::var(SCALE_ENV)
+            .ok()
+            .and_then(|x| x.trim().parse::<f64>().ok())
+            .filter(|x| *x > 0.0);
+        match factor {
+            Some(factor) => self.scaled(factor),
+            None => self,
+        }
+    }
+}
+
+// =============================================================================
+// Matching Engine Measurement
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct MatchingReport {
+    pub orders: u64,
+
+    /// Matches; each fills two orders
+    pub trades: u64,
+    pub elapsed: Duration,
+}
+
+impl MatchingReport {
+    pub fn orders_per_sec(&self) -> f64 {
+        self.orders as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Time `orders` limit orders through a fresh equities matching engine
+///
+/// Buys and sells alternate on a few symbols, at prices a few ticks around
+/// the same mid, so about half of them trade and the rest build up books a
+/// few levels deep.
+pub fn measure_matching(orders: u64) -> MatchingReport {
+    const SYMBOLS: [&str; 4] = ["AAA", "BBB", "CCC", "DDD"];
+    let profile = VenueProfile::equities();
+    let tick = profile.tick_size;
+    let mut engine = MatchingEngine::new(profile);
+
+    // Built first, so the measurement is the engine's alone
+    let requests: Vec<NewOrder> = (0..orders)
+        .map(|i| {
+            let side = if i % 2 == 0 { Side::Buy } else { Side::Sell };
+            let offset = (i % 7) as f64 - 3.0;
+            NewOrder {
+                cl_ord_id: format!("B{i}"),
+                owner: if side == Side::Buy { "BUYER" } else { "SELLER" }.to_string(),
+                symbol: SYMBOLS[(i / 2) as usize % SYMBOLS.len()].to_string(),
+                side,
+                price: MATCHING_MID + offset * tick,
+                quantity: 100.0 * (1 + i % 3) as f64,
+            }
+        })
+        .collect();
+
+    let started = Instant::now();
+    let mut fills = 0;
+    for request in requests {
+        fills += engine
+            .submit(request)
+            .iter()
+            .filter(|x| x.kind == ExecKind::Trade)
+            .count() as u64;
+    }
+    MatchingReport {
+        orders,
+        trades: fills / 2,
+        elapsed: started.elapsed(),
+    }
+}
+
+// =============================================================================
+// Report
+// =============================================================================
+
+/// One limit, against what was measured
+#[derive(Debug, Clone)]
+pub struct BudgetCheck {
+    pub name: &'static str,
+    pub limit: String,
+    pub measured: String,
+    pub passed: bool,
+}
+
+impl fmt::Display for BudgetCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.passed { "ok" } else { "OVER BUDGET" };
+        write!(
+            f,
+            "{:<28} {:>14} {:>14}   {verdict}",
+            self.name, self.measured, self.limit
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct BudgetReport {
+    pub throughput: ThroughputReport,
+
+    /// None when the budget has no matching orders
+    pub matching: Option<MatchingReport>,
+    pub checks: Vec<BudgetCheck>,
+}
+
+impl BudgetReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|x| x.passed)
+    }
+
+    pub fn breaches(&self) -> Vec<&BudgetCheck> {
+        self.checks.iter().filter(|x| !x.passed).collect()
+    }
+
+    /// For tests: Err with every check over budget
+    pub fn assert_within(&self) -> Result<(), BudgetError> {
+        if self.passed() {
+            return Ok(());
+        }
+        Err(BudgetError::Breached(
+            self.breaches().into_iter().cloned().collect(),
+        ))
+    }
+
+    pub fn print(&self) {
+        self.throughput.print();
+        if let Some(matching) = &self.matching {
+            println!(
+                "matching: {} orders, {} trades in {:?}, {:.0} orders/s",
+                matching.orders,
+                matching.trades,
+                matching.elapsed,
+                matching.orders_per_sec()
+            );
+        }
+        println!("{:<28} {:>14} {:>14}", "budget", "measured", "limit");
+        for check in &self.checks {
+            println!("{check}");
+        }
+    }
+}
+
+// =============================================================================
+// Run
+// =============================================================================
+
+/// Measure the order acks, then the matching engine, and check them against
+/// the budget; blocks the calling thread throughout
+///
+/// Being over budget is not an error here: see `BudgetReport::assert_within`.
+pub fn check_budget(budget: &LatencyBudget) -> Result<BudgetReport, BudgetError> {
+    let throughput = run_throughput(&budget.throughput)?;
+    let matching = (budget.matching_orders > 0).then(|| measure_matching(budget.matching_orders));
+
+    let mut checks = vec![BudgetCheck {
+        name: "order acks answered",
+        limit: throughput.sent.to_string(),
+        measured: throughput.received.to_string(),
+        passed: throughput.received == throughput.sent,
+    }];
+    let latencies = [
+        ("order ack p50", budget.ack_p50, 0.5),
+        ("order ack p99", budget.ack_p99, 0.99),
+        ("order ack max", budget.ack_max, 1.0),
+    ];
+    for (name, limit, quantile) in latencies {
+        if let Some(limit) = limit {
+            let measured = throughput.percentile(quantile);
+            checks.push(BudgetCheck {
+                name,
+                limit: format!("{limit:?}"),
+                measured: format!("{measured:?}"),
+                passed: measured <= limit,
+            });
+        }
+    }
+    if let Some(limit) = budget.min_round_trips_per_sec {
+        checks.push(rate_check(
+            "order round trips/s",
+            limit,
+            throughput.round_trips_per_sec(),
+        ));
+    }
+    if let (Some(limit), Some(matching)) = (budget.min_matching_orders_per_sec, &matching) {
+        checks.push(rate_check(
+            "matching orders/s",
+            limit,
+            matching.orders_per_sec(),
+        ));
+    }
+
+    Ok(BudgetReport {
+        throughput,
+        matching,
+        checks,
+    })
+}
+
+fn rate_check(name: &'static str, limit: f64, measured: f64) -> BudgetCheck {
+    BudgetCheck {
+        name,
+        limit: format!(">= {limit:.0}"),
+        measured: format!("{measured:.0}"),
+        passed: measured >= limit,
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// `250us`, `2ms`, `1.5s`, `800ns`
+fn parse_duration(value: &str) -> Option<Duration> {
+    let split = value.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, unit) = value.split_at(split);
+    let number = number.trim().parse::<f64>().ok().filter(|x| *x >= 0.0)?;
+    let seconds = match unit {
+        "ns" => number / 1e9,
+        "us" => number / 1e6,
+        "ms" => number / 1e3,
+        "s" => number,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// The line up to a `#` outside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
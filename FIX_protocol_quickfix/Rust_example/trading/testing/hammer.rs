@@ -0,0 +1,196 @@
+// =============================================================================
+// Concurrent Callbacks
+// =============================================================================
+// With FixSocketServerKind::MultiThreaded each session has a thread of its
+// own and QuickFIX calls the application from all of them at once. A pair
+// (run_pair) rarely shows a race: two sessions, a message at a time. hammer
+// calls the callbacks directly, from one thread per session, as many
+// sessions and messages as asked, every thread released at the same time:
+//
+//   #[test]
+//   fn fills_from_every_session_are_counted() -> Result<(), HarnessError> {
+//       let app = MyApp::default();
+//       let options = HammerOptions::default().with_sessions(16).with_messages(5_000);
+//       let report = hammer(&app, &options, |session, n| {
+//           let id = format!("S{session}-{n}");
+//           message("8", &[(17, &id), (150, "F"), (55, "AAPL"), (32, "1")])
+//       })?;
+//       assert_eq!(report.rejected, 0);
+//       assert_eq!(app.filled_qty(), report.accepted as f64);
+//       Ok(())
+//   }
+//
+// Each thread creates its session (CLIENTn -> VENUE), logs it on, feeds its
+// messages to on_msg_from_app in order, then logs it out: the order within
+// a session is the engine's, between sessions there is none. What the test
+// then asserts (nothing lost, nothing counted twice) is up to it; a panic in
+// a callback fails the test like one in the test itself.
+// =============================================================================
+
+use std::{
+    fmt, panic,
+    sync::Barrier,
+    thread,
+    time::{Duration, Instant},
+};
+
+use quickfix::{ApplicationCallback, Message, QuickFixError, SessionId};
+
+use super::HarnessError;
+
+#[derive(Debug, Clone)]
+pub struct HammerOptions {
+    /// Sessions, a thread each
+    pub sessions: usize,
+
+    /// Messages per session
+    pub messages: usize,
+
+    pub begin_string: String,
+
+    /// TargetCompID of every session; the SenderCompIDs are CLIENT1..N
+    pub target_comp_id: String,
+}
+
+impl Default for HammerOptions {
+    fn default() -> Self {
+        Self {
+            sessions: 8,
+            messages: 1_000,
+            begin_string: "FIX.4.4".to_string(),
+            target_comp_id: "VENUE".to_string(),
+        }
+    }
+}
+
+impl HammerOptions {
+    pub fn with_sessions(mut self, sessions: usize) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    pub fn with_messages(mut self, messages: usize) -> Self {
+        self.messages = messages;
+        self
+    }
+
+    pub fn with_begin_string(mut self, begin_string: &str) -> Self {
+        self.begin_string = begin_string.to_string();
+        self
+    }
+
+    pub fn with_target_comp_id(mut self, target_comp_id: &str) -> Self {
+        self.target_comp_id = target_comp_id.to_string();
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HammerReport {
+    pub sessions: usize,
+
+    /// Messages on_msg_from_app took, and refused
+    pub accepted: usize,
+    pub rejected: usize,
+
+    /// From the release of the threads to the last logout
+    pub elapsed: Duration,
+}
+
+impl HammerReport {
+    pub fn messages_per_sec(&self) -> f64 {
+        (self.accepted + self.rejected) as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for HammerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} sessions: {} accepted, {} rejected in {:.3}s ({:.0} msgs/sec)",
+            self.sessions,
+            self.accepted,
+            self.rejected,
+            self.elapsed.as_secs_f64(),
+            self.messages_per_sec()
+        )
+    }
+}
+
+/// Call the callbacks of `app` from a thread per session at once
+///
+/// `make(session, n)` builds the n-th message of a session, both from 0,
+/// on the session's thread.
+pub fn hammer<A, F>(app: &A, options: &HammerOptions, make: F) -> Result<HammerReport, HarnessError>
+where
+    A: ApplicationCallback + Sync,
+    F: Fn(usize, usize) -> Result<Message, QuickFixError> + Sync,
+{
+    // The threads and this one, which starts the clock
+    let start = Barrier::new(options.sessions + 1);
+    let (counts, elapsed) = thread::scope(|scope| {
+        let threads: Vec<_> = (0..options.sessions)
+            .map(|index| {
+                let (start, make) = (&start, &make);
+                scope.spawn(move || {
+                    // Waited for even on error: the others are waiting too
+                    let session = SessionId::try_new(
+                        &options.begin_string,
+                        &format!("CLIENT{}", index + 1),
+                        &options.target_comp_id,
+                        "",
+                    );
+                    start.wait();
+                    let session = session?;
+                    run_session(app, &session, index, options.messages, make)
+                })
+            })
+            .collect();
+        start.wait();
+        let started = Instant::now();
+        let counts: Vec<_> = threads
+            .into_iter()
+            .map(|x| x.join().unwrap_or_else(|panic| panic::resume_unwind(panic)))
+            .collect();
+        (counts, started.elapsed())
+    });
+
+    let mut report = HammerReport {
+        sessions: options.sessions,
+        accepted: 0,
+        rejected: 0,
+        elapsed,
+    };
+    for count in counts {
+        let (accepted, rejected) = count?;
+        report.accepted += accepted;
+        report.rejected += rejected;
+    }
+    Ok(report)
+}
+
+/// One session's callbacks, in the order the engine makes them
+fn run_session<A, F>(
+    app: &A,
+    session: &SessionId,
+    index: usize,
+    messages: usize,
+    make: &F,
+) -> Result<(usize, usize), HarnessError>
+where
+    A: ApplicationCallback,
+    F: Fn(usize, usize) -> Result<Message, QuickFixError>,
+{
+    app.on_create(session);
+    app.on_logon(session);
+    let (mut accepted, mut rejected) = (0, 0);
+    for n in 0..messages {
+        let msg = make(index, n)?;
+        match app.on_msg_from_app(&msg, session) {
+            Ok(()) => accepted += 1,
+            Err(_) => rejected += 1,
+        }
+    }
+    app.on_logout(session);
+    Ok((accepted, rejected))
+}
@@ -0,0 +1,221 @@
+// =============================================================================
+// UTC Calendar Helpers
+// =============================================================================
+// Trade dates, file names and mail headers need calendar dates, and FIX
+// timestamps are UTC. std only gives seconds since the epoch, so the
+// conversion to a civil date is done here (proleptic Gregorian calendar, after
+// Howard Hinnant's days_from_civil / civil_from_days).
+// =============================================================================
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since the Unix epoch, now
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs() as i64)
+}
+
+/// Milliseconds since the Unix epoch, now
+pub fn unix_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_millis() as i64)
+}
+
+/// A UTC calendar date
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Date of a Unix timestamp, in UTC
+    pub fn from_unix(seconds: i64) -> Self {
+        let days = seconds.div_euclid(86_400);
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as i32;
+        Self { year, month, day }
+    }
+
+    /// Unix timestamp of the date's midnight, in UTC
+    pub fn to_unix(self) -> i64 {
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let month = i64::from(self.month);
+        let mp = if month > 2 { month - 3 } else { month + 9 };
+        let doy = (153 * mp + 2) / 5 + i64::from(self.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        (era * 146_097 + doe - 719_468) * 86_400
+    }
+
+    pub fn today() -> Self {
+        Self::from_unix(unix_now())
+    }
+
+    /// `YYYYMMDD`, as in FIX dates (TradeDate, 75)
+    pub fn to_fix(self) -> String {
+        format!("{:04}{:02}{:02}", self.year, self.month, self.day)
+    }
+
+    /// Parse `YYYYMMDD`; None if malformed or not a calendar date
+    pub fn from_fix(value: &str) -> Option<Self> {
+        if value.len() != 8 || !value.bytes().all(|x| x.is_ascii_digit()) {
+            return None;
+        }
+        let date = Self {
+            year: value[..4].parse().ok()?,
+            month: value[4..6].parse().ok()?,
+            day: value[6..].parse().ok()?,
+        };
+        // Out of range days and months roll over into another date
+        (Self::from_unix(date.to_unix()) == date).then_some(date)
+    }
+
+    /// `YYYY-MM-DD`
+    pub fn to_iso(self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Nanoseconds since the Unix epoch of a FIX UTCTimestamp
+///
+/// `YYYYMMDD-HH:MM:SS`, with an optional fraction of 1 to 9 digits
+/// (milliseconds, microseconds, nanoseconds), as in SendingTime (52) and
+/// TransactTime (60). None if malformed.
+pub fn parse_utc_timestamp(value: &str) -> Option<i64> {
+    if !value.is_ascii() {
+        return None;
+    }
+    let (date, time) = value.split_once('-')?;
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let number = |x: &str| {
+        x.bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| x.parse::<u32>().ok())
+            .flatten()
+    };
+
+    if date.len() != 8 || time.len() != 8 || fraction.len() > 9 {
+        return None;
+    }
+    let date = Date {
+        year: number(&date[..4])? as i32,
+        month: number(&date[4..6])?,
+        day: number(&date[6..])?,
+    };
+    let (hours, minutes, seconds) = match time.split(':').collect::<Vec<_>>()[..] {
+        [h, m, s] => (number(h)?, number(m)?, number(s)?),
+        _ => return None,
+    };
+    // Seconds up to 60: leap seconds are valid UTCTimestamps
+    if !(1..=12).contains(&date.month)
+        || !(1..=31).contains(&date.day)
+        || hours > 23
+        || minutes > 59
+        || seconds > 60
+    {
+        return None;
+    }
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        number(fraction)? * 10u32.pow(9 - fraction.len() as u32)
+    };
+
+    let seconds =
+        date.to_unix() + i64::from(hours) * 3_600 + i64::from(minutes) * 60 + i64::from(seconds);
+    Some(seconds * 1_000_000_000 + i64::from(nanos))
+}
+
+/// Nanoseconds since the Unix epoch of an ISO 8601 / RFC 3339 timestamp
+///
+/// `YYYY-MM-DDTHH:MM:SS`, with `T` or a space, an optional fraction and an
+/// optional `Z` or `+HH:MM` offset (none means UTC). None if malformed.
+pub fn parse_iso8601(value: &str) -> Option<i64> {
+    let (date, rest) = (value.get(..10)?, value.get(10..)?);
+    let rest = rest.strip_prefix(['T', 't', ' '])?;
+    let (time, offset_seconds) = match rest.find(['Z', 'z', '+', '-']) {
+        None => (rest, 0),
+        Some(index) => {
+            let (time, offset) = rest.split_at(index);
+            let seconds = match offset {
+                "Z" | "z" => 0,
+                _ => {
+                    let sign = if offset.starts_with('-') { -1 } else { 1 };
+                    let digits = offset[1..].replace(':', "");
+                    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                        return None;
+                    }
+                    let hours: i64 = digits[..2].parse().ok()?;
+                    let minutes: i64 = digits[2..].parse().ok()?;
+                    sign * (hours * 3_600 + minutes * 60)
+                }
+            };
+            (time, seconds)
+        }
+    };
+    if date.as_bytes()[4] != b'-' || date.as_bytes()[7] != b'-' {
+        return None;
+    }
+
+    let fix = format!("{}{}{}-{time}", &date[..4], &date[5..7], &date[8..]);
+    let nanos = parse_utc_timestamp(&fix)?;
+    Some(nanos - offset_seconds * 1_000_000_000)
+}
+
+/// `HH:MM:SS` of a Unix timestamp, in UTC
+pub fn time_of_day(seconds: i64) -> String {
+    let seconds = seconds.rem_euclid(86_400);
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3_600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// FIX UTCTimestamp with milliseconds (`YYYYMMDD-HH:MM:SS.sss`) of Unix
+/// milliseconds
+pub fn utc_timestamp(millis: i64) -> String {
+    let seconds = millis.div_euclid(1_000);
+    format!(
+        "{}-{}.{:03}",
+        Date::from_unix(seconds).to_fix(),
+        time_of_day(seconds),
+        millis.rem_euclid(1_000)
+    )
+}
+
+/// TransactTime (60) of a message built now
+pub fn transact_time() -> String {
+    utc_timestamp(unix_millis())
+}
+
+/// RFC 5322 date, as in mail headers: `Thu, 01 Jan 1970 00:00:00 +0000`
+pub fn rfc5322(seconds: i64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let date = Date::from_unix(seconds);
+    format!(
+        "{}, {:02} {} {:04} {} +0000",
+        WEEKDAYS[seconds.div_euclid(86_400).rem_euclid(7) as usize],
+        date.day,
+        MONTHS[date.month as usize - 1],
+        date.year,
+        time_of_day(seconds)
+    )
+}